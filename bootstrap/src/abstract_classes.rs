@@ -0,0 +1,213 @@
+//! Abstract classes.
+//!
+//! `abstract Shape { ... }` can't be instantiated. It may declare fields,
+//! ordinary methods, and `abstract` methods, which are signatures only. A
+//! class implementing it (`Circle : Shape { ... }`) inherits the fields it
+//! doesn't declare itself and the methods it doesn't override with the same
+//! name and parameter count, and must implement every abstract method unless
+//! it is abstract too.
+//!
+//! This pass copies the inherited members into each implementing class, so
+//! the rest of the compiler sees ordinary classes. Semantic analysis runs it
+//! for the abstract classes of the program at hand; a multi-module build runs
+//! it across modules first (see `ModuleResolver::inherit_abstract_members`).
+//! Running it twice changes nothing.
+
+use crate::ast::*;
+use crate::error::{CompilerError, SemanticErrorInfo};
+use crate::error_codes::*;
+use crate::Result;
+use std::collections::{HashMap, HashSet};
+
+/// The abstract classes declared in `programs`, by name
+pub fn collect<'a>(programs: impl IntoIterator<Item = &'a Program>) -> HashMap<String, ClassDecl> {
+    programs
+        .into_iter()
+        .flat_map(|program| &program.items)
+        .filter_map(|item| match item {
+            TopLevel::Class(class) if class.is_abstract => {
+                Some((class.name.clone(), class.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Copy what the abstract classes in `bases` pass on into the classes of
+/// `program` implementing them
+pub fn inherit(program: &mut Program, bases: &HashMap<String, ClassDecl>) -> Result<()> {
+    for item in &mut program.items {
+        let TopLevel::Class(class) = item else {
+            continue;
+        };
+        check_declaration(class)?;
+
+        let mut inherited = Vec::new();
+        let mut seen = HashSet::new();
+        for base in &class.implements {
+            inherited_members(base, bases, &mut seen, &mut inherited);
+        }
+
+        let mut fields = Vec::new();
+        for member in inherited {
+            match member {
+                Member::Field(field) => {
+                    let declared = class.members.iter().chain(&fields).any(
+                        |other| matches!(other, Member::Field(other) if other.name == field.name),
+                    );
+                    if !declared {
+                        fields.push(Member::Field(field));
+                    }
+                }
+                Member::Method(method) => {
+                    if !declares_method(&class.members, &method) {
+                        class.members.push(Member::Method(method));
+                    }
+                }
+            }
+        }
+        // Fields come first in a class body
+        class.members.splice(0..0, fields);
+
+        if !class.is_abstract {
+            let missing: Vec<&str> = class
+                .members
+                .iter()
+                .filter_map(|member| match member {
+                    Member::Method(method) if method.is_abstract => Some(method.name.as_str()),
+                    _ => None,
+                })
+                .collect();
+            if !missing.is_empty() {
+                return Err(missing_methods(class, &missing));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The abstract classes `name` stands for, itself first and then the ones it
+/// implements, in the order their members are inherited
+pub fn ancestors<'a>(name: &str, bases: &'a HashMap<String, ClassDecl>) -> Vec<&'a ClassDecl> {
+    let mut found: Vec<&ClassDecl> = Vec::new();
+    let mut pending = vec![name];
+    while let Some(name) = pending.pop() {
+        if let Some(base) = bases.get(name) {
+            if !found.iter().any(|other| other.name == base.name) {
+                found.push(base);
+                pending.extend(base.implements.iter().rev().map(String::as_str));
+            }
+        }
+    }
+    found
+}
+
+/// Members `base` passes on: its own, then what it inherits without
+/// overriding. `seen` guards against classes implementing each other.
+fn inherited_members(
+    base: &str,
+    bases: &HashMap<String, ClassDecl>,
+    seen: &mut HashSet<String>,
+    members: &mut Vec<Member>,
+) {
+    let Some(class) = bases.get(base) else {
+        return;
+    };
+    if !seen.insert(base.to_string()) {
+        return;
+    }
+    let mut own: Vec<Member> = class
+        .members
+        .iter()
+        .filter(|member| !matches!(member, Member::Method(method) if method.name == "constructor"))
+        .cloned()
+        .collect();
+    for grand_base in &class.implements {
+        let mut further = Vec::new();
+        inherited_members(grand_base, bases, seen, &mut further);
+        for member in further {
+            let overridden = match &member {
+                Member::Field(field) => own
+                    .iter()
+                    .any(|other| matches!(other, Member::Field(other) if other.name == field.name)),
+                Member::Method(method) => declares_method(&own, method),
+            };
+            if !overridden {
+                own.push(member);
+            }
+        }
+    }
+    members.extend(own);
+}
+
+fn declares_method(members: &[Member], method: &MethodDecl) -> bool {
+    members.iter().any(|other| {
+        matches!(other, Member::Method(other)
+            if other.name == method.name && other.params.len() == method.params.len())
+    })
+}
+
+/// `abstract` methods only belong in abstract classes, which have no
+/// constructor since they are never instantiated
+fn check_declaration(class: &ClassDecl) -> Result<()> {
+    for member in &class.members {
+        let Member::Method(method) = member else {
+            continue;
+        };
+        if method.is_abstract && !class.is_abstract {
+            return Err(misplaced_abstract(
+                &format!(
+                    "Method '{}' is abstract, but class '{}' isn't",
+                    method.name, class.name
+                ),
+                &format!(
+                    "Declare the class as 'abstract {}', or give the method a body",
+                    class.name
+                ),
+            ));
+        }
+        if class.is_abstract && method.name == "constructor" {
+            return Err(misplaced_abstract(
+                &format!(
+                    "Abstract class '{}' can't have a constructor, since it is never instantiated",
+                    class.name
+                ),
+                "Move the constructor to the classes implementing it",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn missing_methods(class: &ClassDecl, missing: &[&str]) -> CompilerError {
+    CompilerError::SemanticError(Box::new(
+        SemanticErrorInfo::new(
+            E0016_MISSING_ABSTRACT_METHOD,
+            "Abstract method not implemented",
+            &format!(
+                "Class '{}' doesn't implement abstract method{} {} of {}",
+                class.name,
+                if missing.len() == 1 { "" } else { "s" },
+                missing
+                    .iter()
+                    .map(|name| format!("'{}'", name))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                class.implements.join(", ")
+            ),
+        )
+        .with_help(&format!(
+            "Implement {} in '{}', or declare it as 'abstract {}'",
+            if missing.len() == 1 { "it" } else { "them" },
+            class.name,
+            class.name
+        )),
+    ))
+}
+
+fn misplaced_abstract(message: &str, help: &str) -> CompilerError {
+    CompilerError::SemanticError(Box::new(
+        SemanticErrorInfo::new(E0017_MISPLACED_ABSTRACT, "Misplaced abstract", message)
+            .with_help(help),
+    ))
+}
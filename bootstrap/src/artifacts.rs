@@ -0,0 +1,360 @@
+//! Structured description of what a compilation produced.
+//!
+//! Build tools embedding livac read [`ArtifactMetadata`] from
+//! `CompilationResult::metadata` instead of listing the output directory or
+//! re-parsing the generated `Cargo.toml`. All structs here are
+//! `#[non_exhaustive]`, so new fields can be added in minor releases.
+
+use crate::ast::{Program, TopLevel};
+use crate::debuginfo::LineMap;
+use crate::package::Manifest;
+use crate::BuildProfile;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Package name of a crate generated from a string rather than a file
+pub const DEFAULT_CRATE_NAME: &str = "liva_project";
+
+/// Names cargo rejects for a package or a binary target
+const RESERVED_CRATE_NAMES: &[&str] = &[
+    "alloc",
+    "build",
+    "core",
+    "deps",
+    "examples",
+    "incremental",
+    "proc_macro",
+    "self",
+    "std",
+    "super",
+    "test",
+];
+
+/// Everything a compilation generated, and where it came from
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct ArtifactMetadata {
+    /// Package name in the generated `Cargo.toml`, also the binary's name
+    pub crate_name: String,
+    /// Where `cargo build` puts the binary, relative to the crate root;
+    /// `None` when the entry module has no `main()`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary: Option<PathBuf>,
+    /// Files of the generated crate, relative to its root
+    pub files: Vec<GeneratedFile>,
+    /// Crates under `[dependencies]` in the generated `Cargo.toml`
+    pub dependencies: Vec<CrateDependency>,
+    /// Runnable entry points of the entry module
+    pub entry_points: Vec<EntryPoint>,
+    /// Liva source behind each generated Rust file
+    pub source_map: Vec<SourceMapping>,
+    /// Statement-level Liva ↔ Rust line table; only filled for debug builds,
+    /// where codegen emits the markers it is built from, and with
+    /// `CompilerOptions::source_map`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_map: Option<LineMap>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum FileKind {
+    RustSource,
+    CargoManifest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub kind: FileKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct CrateDependency {
+    pub name: String,
+    /// `None` for path/git dependencies without a version requirement
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum EntryPointKind {
+    /// `main()`, the binary's entry point
+    Main,
+    /// A `test` declaration, run by `livac test`
+    Test,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct EntryPoint {
+    pub name: String,
+    pub kind: EntryPointKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct SourceMapping {
+    pub liva_file: PathBuf,
+    pub rust_file: PathBuf,
+}
+
+impl ArtifactMetadata {
+    /// Describe a generated crate. `generated` holds each Rust file (relative
+    /// path and contents) with the Liva file it was generated from.
+    pub(crate) fn collect(
+        entry: &Program,
+        generated: &[(PathBuf, PathBuf, &str)],
+        cargo_toml: &str,
+        debug: bool,
+        profile: BuildProfile,
+    ) -> Self {
+        let mut metadata = Self {
+            crate_name: manifest_package_name(cargo_toml),
+            ..Self::default()
+        };
+
+        let mut generated: Vec<&(PathBuf, PathBuf, &str)> = generated.iter().collect();
+        generated.sort_by(|a, b| a.1.cmp(&b.1));
+        for (liva_file, rust_file, _) in &generated {
+            metadata.files.push(GeneratedFile {
+                path: rust_file.clone(),
+                kind: FileKind::RustSource,
+            });
+            metadata.source_map.push(SourceMapping {
+                liva_file: liva_file.clone(),
+                rust_file: rust_file.clone(),
+            });
+        }
+        metadata.files.push(GeneratedFile {
+            path: PathBuf::from("Cargo.toml"),
+            kind: FileKind::CargoManifest,
+        });
+
+        metadata.dependencies = manifest_dependencies(cargo_toml);
+
+        for item in &entry.items {
+            match item {
+                TopLevel::Function(func) if func.name == "main" => {
+                    metadata.entry_points.push(EntryPoint {
+                        name: func.name.clone(),
+                        kind: EntryPointKind::Main,
+                    });
+                    metadata.binary = Some(
+                        Path::new("target")
+                            .join(profile.target_dir())
+                            .join(&metadata.crate_name),
+                    );
+                }
+                TopLevel::Test(test) => metadata.entry_points.push(EntryPoint {
+                    name: test.name.clone(),
+                    kind: EntryPointKind::Test,
+                }),
+                _ => {}
+            }
+        }
+
+        if debug {
+            let mut line_map = LineMap::new();
+            for (_, rust_file, code) in &generated {
+                line_map.add_generated(&rust_file.display().to_string(), code);
+            }
+            metadata.line_map = Some(line_map);
+        }
+
+        metadata
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Cargo package name for the program whose entry file is `input`: the
+/// `liva.toml` package name when `input` is that project's `package.entry`,
+/// otherwise the file stem. Either is lowercased and made a valid crate name,
+/// so programs compiled side by side get binaries that don't collide.
+pub fn crate_name(input: &Path) -> String {
+    let from_manifest = input
+        .parent()
+        .and_then(Manifest::find)
+        .and_then(|path| {
+            let manifest = Manifest::load(&path).ok()?;
+            let entry = path.parent()?.join(manifest.package.entry.as_ref()?);
+            let is_entry = entry.canonicalize().ok()? == input.canonicalize().ok()?;
+            is_entry.then_some(manifest.package.name)
+        })
+        .filter(|name| !name.is_empty());
+    let raw = from_manifest.unwrap_or_else(|| {
+        input
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    sanitize_crate_name(&raw)
+}
+
+/// Lowercase `raw`, replace what cargo doesn't allow with `_`, and prefix
+/// names that start with a digit or that cargo reserves with `liva_`
+fn sanitize_crate_name(raw: &str) -> String {
+    let name: String = raw
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.trim_matches('_').is_empty() {
+        return DEFAULT_CRATE_NAME.to_string();
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit())
+        || RESERVED_CRATE_NAMES.contains(&name.as_str())
+    {
+        return format!("liva_{}", name);
+    }
+    name
+}
+
+/// `package.name` of a generated `Cargo.toml`
+fn manifest_package_name(cargo_toml: &str) -> String {
+    cargo_toml
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|manifest| {
+            manifest
+                .get("package")?
+                .get("name")?
+                .as_str()
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| DEFAULT_CRATE_NAME.to_string())
+}
+
+/// The `[dependencies]` table of a generated `Cargo.toml`, sorted by name
+fn manifest_dependencies(cargo_toml: &str) -> Vec<CrateDependency> {
+    let Ok(manifest) = cargo_toml.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let Some(deps) = manifest.get("dependencies").and_then(|d| d.as_table()) else {
+        return Vec::new();
+    };
+
+    deps.iter()
+        .map(|(name, spec)| {
+            let (version, features) = match spec {
+                toml::Value::String(version) => (Some(version.clone()), Vec::new()),
+                toml::Value::Table(table) => (
+                    table
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string),
+                    table
+                        .get("features")
+                        .and_then(|f| f.as_array())
+                        .map(|f| {
+                            f.iter()
+                                .filter_map(|v| v.as_str().map(str::to_string))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                ),
+                _ => (None, Vec::new()),
+            };
+            CrateDependency {
+                name: name.clone(),
+                version,
+                features,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_collect_describes_generated_crate() {
+        let source = "main() {\n    print(1)\n}\n\ntest \"adds\" {\n    print(2)\n}\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let cargo_toml = "[package]\nname = \"liva_project\"\n\n[dependencies]\ntokio = { version = \"1\", features = [\"full\"] }\nserde_json = \"1.0\"\n";
+        let generated = vec![(
+            PathBuf::from("main.liva"),
+            PathBuf::from("src/main.rs"),
+            "fn main() {}",
+        )];
+
+        let metadata =
+            ArtifactMetadata::collect(&program, &generated, cargo_toml, false, BuildProfile::Dev);
+
+        assert_eq!(metadata.files.len(), 2);
+        assert_eq!(metadata.files[1].kind, FileKind::CargoManifest);
+        assert_eq!(metadata.source_map[0].liva_file, PathBuf::from("main.liva"));
+        let names: Vec<&str> = metadata
+            .dependencies
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["serde_json", "tokio"]);
+        assert_eq!(metadata.dependencies[1].features, vec!["full"]);
+        assert_eq!(
+            metadata.entry_points,
+            vec![
+                EntryPoint {
+                    name: "main".to_string(),
+                    kind: EntryPointKind::Main
+                },
+                EntryPoint {
+                    name: "adds".to_string(),
+                    kind: EntryPointKind::Test
+                },
+            ]
+        );
+        assert!(metadata.line_map.is_none());
+        assert_eq!(metadata.crate_name, "liva_project");
+        assert_eq!(
+            metadata.binary,
+            Some(PathBuf::from("target/debug/liva_project"))
+        );
+    }
+
+    #[test]
+    fn test_crate_name_from_file_stem() {
+        assert_eq!(crate_name(Path::new("tools/Word-Count.liva")), "word_count");
+        assert_eq!(crate_name(Path::new("2048.liva")), "liva_2048");
+        assert_eq!(crate_name(Path::new("test.liva")), "liva_test");
+        assert_eq!(crate_name(Path::new("---.liva")), DEFAULT_CRATE_NAME);
+    }
+
+    #[test]
+    fn test_crate_name_from_manifest_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("liva.toml"),
+            "[package]\nname = \"todo-app\"\nentry = \"src/main.liva\"\n",
+        )
+        .unwrap();
+        let entry = dir.path().join("src/main.liva");
+        let other = dir.path().join("src/seed.liva");
+        std::fs::write(&entry, "main() {}\n").unwrap();
+        std::fs::write(&other, "main() {}\n").unwrap();
+
+        assert_eq!(crate_name(&entry), "todo_app");
+        assert_eq!(crate_name(&other), "seed");
+    }
+}
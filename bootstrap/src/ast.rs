@@ -4,6 +4,42 @@ use std::fmt;
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Program {
     pub items: Vec<TopLevel>,
+    /// Types semantic analysis worked out for unannotated bindings and lambda
+    /// parameters, for editor hints; empty before analysis
+    #[serde(skip)]
+    pub inferred_types: Vec<InferredType>,
+    /// Who calls whom among the functions and class methods analysis could
+    /// resolve, for editor call hierarchies; empty before analysis
+    #[serde(skip)]
+    pub call_graph: Vec<CallEdge>,
+    /// Parameter lists of the functions and class methods declared here, for
+    /// editor signature help; empty before analysis
+    #[serde(skip)]
+    pub signatures: Vec<crate::signatures::Signature>,
+    /// Fields and methods of the classes declared here, for lowering and
+    /// codegen; empty before analysis
+    #[serde(skip)]
+    pub symbols: crate::symbols::SymbolTable,
+    /// Every comment in the source, in order, for formatters; doc comments
+    /// are also attached to the declarations they precede
+    #[serde(skip)]
+    pub comments: Vec<crate::lexer::Comment>,
+}
+
+/// Type of a binding or parameter that the source leaves unannotated
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredType {
+    /// Span of the binding or parameter name
+    pub span: crate::span::Span,
+    pub type_ref: TypeRef,
+}
+
+/// A function or method that calls another at least once. Functions are
+/// named as declared, methods as `Class.method`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
 }
 
 /// Type parameter with optional constraints
@@ -60,6 +96,13 @@ pub enum TopLevel {
     /// `extend ClassName { method+ }` — adds methods to an existing class.
     /// See docs/language-reference/class-extensions.md.
     ClassExtension(ClassExtensionDecl),
+    /// `runtime { threads: 8 }` — program-level thread-pool configuration.
+    Runtime(RuntimeDecl),
+    /// `init { ... }` — module initialization, run before `main`.
+    /// See docs/language-reference/modules.md.
+    Init(InitDecl),
+    /// `before { ... }` / `after { ... }` — run around every `test` of the file.
+    TestHook(TestHookDecl),
 }
 
 /// `extend ClassName { method+ }`
@@ -71,12 +114,102 @@ pub struct ClassExtensionDecl {
     pub span: Option<crate::span::Span>,
 }
 
+/// `runtime { threads: 8, asyncThreads: 4, parThreads: 8 }`
+///
+/// `threads` sizes both the tokio worker pool and the rayon pool; the
+/// specific keys override it for one pool. `LIVA_THREADS` overrides all of
+/// them when the program runs.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
+pub struct RuntimeDecl {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "asyncThreads")]
+    pub async_threads: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "parThreads")]
+    pub par_threads: Option<i64>,
+}
+
+impl RuntimeDecl {
+    /// Worker count for the tokio runtime
+    pub fn async_worker_threads(&self) -> Option<i64> {
+        self.async_threads.or(self.threads)
+    }
+
+    /// Pool size for the rayon global thread pool
+    pub fn par_worker_threads(&self) -> Option<i64> {
+        self.par_threads.or(self.threads)
+    }
+}
+
+/// `init { ... }`
+///
+/// At most one per module. The generated `main` runs every module's block
+/// before the program's own body, imported modules before their importers.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InitDecl {
+    pub body: BlockStmt,
+    /// From the `init` keyword to the closing brace
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
+}
+
+/// `before { ... }` or `after { ... }`
+///
+/// At most one of each per file. They are the file's `beforeEach` and
+/// `afterEach`: every test of the file, describes included, runs the `before`
+/// block first and the `after` block last.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TestHookDecl {
+    pub kind: TestHookKind,
+    pub body: BlockStmt,
+    /// From the `before`/`after` keyword to the closing brace
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TestHookKind {
+    Before,
+    After,
+}
+
+impl TestHookKind {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            TestHookKind::Before => "before",
+            TestHookKind::After => "after",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ImportDecl {
     pub imports: Vec<String>,  // List of imported symbols: ["add", "multiply"]
     pub source: String,        // Path to file: "./math.liva"
     pub is_wildcard: bool,     // true for `import *`
     pub alias: Option<String>, // For wildcard: `import * as name`
+    /// Per-symbol aliases: `import { parse as parseCfg }` maps `parse` -> `parseCfg`
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub renames: std::collections::HashMap<String, String>,
+    /// `export { a } from "./m"` / `export * from "./m"` — the imported
+    /// symbols become part of this module's public API
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_reexport: bool,
+}
+
+impl ImportDecl {
+    /// Name a symbol is bound to in the importing module
+    pub fn local_name<'a>(&'a self, symbol: &'a str) -> &'a str {
+        self.renames
+            .get(symbol)
+            .map(String::as_str)
+            .unwrap_or(symbol)
+    }
+
+    /// Local names introduced by a named import, in declaration order
+    pub fn local_names(&self) -> Vec<&str> {
+        self.imports.iter().map(|s| self.local_name(s)).collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -96,6 +229,13 @@ pub struct TypeDecl {
     pub name: String,
     pub type_params: Vec<TypeParameter>, // Generic type parameters
     pub members: Vec<Member>,
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// From the first token of the declaration to its closing brace (or the
+    /// end of its expression body)
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
 }
 
 /// Type alias declaration: type Point = (int, int)
@@ -104,6 +244,9 @@ pub struct TypeAliasDecl {
     pub name: String,
     pub type_params: Vec<TypeParameter>, // Generic type parameters
     pub target_type: TypeRef,            // The type being aliased
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
     #[serde(skip)]
     pub span: Option<crate::span::Span>,
 }
@@ -118,6 +261,30 @@ pub struct ClassDecl {
     pub needs_serde: bool, // Phase 2: true if used with JSON.parse
     #[serde(default)]
     pub is_data: bool, // deprecated: auto-detected in codegen (fields + no constructor = data class)
+    /// `abstract Name { ... }`: can't be instantiated, and may declare
+    /// abstract methods for the classes implementing it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_abstract: bool,
+    /// `delegate field` lines, whose methods the class forwards
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delegates: Vec<DelegateDecl>,
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// From the first token of the declaration to its closing brace (or the
+    /// end of its expression body)
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
+}
+
+/// `delegate engine` or `delegate engine { start, stop }` in a class: the
+/// class gets methods forwarding to those of the field
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DelegateDecl {
+    pub field: String,
+    /// Methods to forward; empty forwards every public one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods: Vec<String>,
 }
 
 /// Enum declaration: enum Color { Red, Green, Blue }
@@ -127,6 +294,9 @@ pub struct EnumDecl {
     pub name: String,
     pub type_params: Vec<TypeParameter>,
     pub variants: Vec<EnumVariant>,
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
 }
 
 /// A single variant of an enum
@@ -158,6 +328,9 @@ pub struct FieldDecl {
     pub init: Option<Expr>,
     #[serde(default)]
     pub is_optional: bool, // true if field?: Type syntax
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -171,6 +344,27 @@ pub struct MethodDecl {
     pub expr_body: Option<Expr>,
     pub is_async_inferred: bool,
     pub contains_fail: bool,
+    /// `abstract name(...)`: a signature every concrete class implementing
+    /// the abstract class must provide
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_abstract: bool,
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// From the first token of the declaration to its closing brace (or the
+    /// end of its expression body)
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
+}
+
+/// Explicit inlining annotation on a function
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InlineHint {
+    /// `@inline`
+    Always,
+    /// `@noinline`
+    Never,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -183,6 +377,43 @@ pub struct FunctionDecl {
     pub expr_body: Option<Expr>,
     pub is_async_inferred: bool,
     pub contains_fail: bool,
+    /// `@inline` / `@noinline` annotation, overriding the inlining pass
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline: Option<InlineHint>,
+    /// `requires` clauses, checked when the function is called
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<Contract>,
+    /// `ensures` clauses, checked on the value it returns (`result`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ensures: Vec<Contract>,
+    /// `@checked`: keep the contracts in release builds
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub checked: bool,
+    /// `unsafe rust fn`: `expr_body` is a `RustBlock` holding the raw Rust
+    /// body, and only the declared signature is checked
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unsafe_rust: bool,
+    /// Locals of the enclosing function that a nested function uses, set by
+    /// the semantic analyzer. Always empty for top-level functions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub captures: Vec<String>,
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// From the first token of the declaration to its closing brace (or the
+    /// end of its expression body)
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
+}
+
+/// `requires cond` or `ensures cond` on a function
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Contract {
+    pub condition: Expr,
+    /// The condition as written, for the message when it fails
+    pub source: String,
+    /// 1-based line of the clause
+    pub line: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -211,6 +442,9 @@ impl Param {
 pub struct TestDecl {
     pub name: String,
     pub body: BlockStmt,
+    /// From the `test` keyword to the closing brace
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
 }
 
 #[derive(Debug, Clone, PartialEq, Copy, serde::Serialize, serde::Deserialize)]
@@ -249,7 +483,35 @@ pub enum TypeRef {
     Fn(Vec<TypeRef>, Box<TypeRef>),
 }
 
+/// Names `typeOf()` gives values of built-in types; class instances give
+/// their class name
+pub const TYPE_OF_TAGS: &[&str] = &[
+    "int", "float", "string", "bool", "array", "map", "set", "object", "null",
+];
+
 impl TypeRef {
+    /// Name `typeOf()` gives a value of this type: `int`, `float`, `string`,
+    /// `bool`, `array`, `map` or `set` for built-in types, the class name for
+    /// instances
+    pub fn type_of_name(&self) -> String {
+        match self {
+            TypeRef::Simple(name) => match name.as_str() {
+                "number" | "int" | "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16"
+                | "u32" | "u64" | "usize" => "int".to_string(),
+                "float" | "f32" | "f64" => "float".to_string(),
+                "string" | "char" => "string".to_string(),
+                "bytes" => "array".to_string(),
+                _ => name.clone(),
+            },
+            TypeRef::Generic { base, .. } => base.clone(),
+            TypeRef::Array(_) => "array".to_string(),
+            TypeRef::Map(_, _) => "map".to_string(),
+            TypeRef::Set(_) => "set".to_string(),
+            TypeRef::Optional(inner) | TypeRef::Fallible(inner) => inner.type_of_name(),
+            other => other.to_string(),
+        }
+    }
+
     pub fn to_rust_type(&self) -> String {
         match self {
             TypeRef::Simple(name) => match name.as_str() {
@@ -334,15 +596,39 @@ pub enum Stmt {
     Fail(FailStmt),
     Return(ReturnStmt),
     Defer(DeferStmt),
+    /// `scope { ... }`: tasks spawned inside are done when it exits
+    Scope(ScopeStmt),
+    /// `mock HTTP.get returns value` in a test
+    Mock(MockStmt),
     Break,
     Continue,
     Expr(ExprStmt),
     Block(BlockStmt),
+    /// Function declared inside a function body
+    Function(FunctionDecl),
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct BlockStmt {
     pub stmts: Vec<Stmt>,
+    /// 1-based source line of each statement in `stmts` (empty when unknown).
+    /// Used by `--coverage` instrumentation.
+    #[serde(skip)]
+    pub stmt_lines: Vec<u32>,
+    /// From `{` to `}`; `None` for one-liner bodies (`=> stmt`)
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
+}
+
+/// Result combinator called on a fallible initializer
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ResultCombinator {
+    /// `.orElse(value)`: same as `or value`
+    OrElse,
+    /// `.expect("msg")`: panics with the message instead of propagating
+    Expect,
+    /// `.mapErr(fn)`: rewrites the error bound by `let value, err = ...`
+    MapErr(Box<Expr>),
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -363,6 +649,14 @@ pub struct VarDecl {
     /// Source line number (1-based) for error trace (set when or_fail_msg is present)
     #[serde(default)]
     pub or_fail_line: u32,
+    /// Set when the `or` clause was written as a result combinator:
+    /// `.orElse(v)` fills `or_value`, `.expect("msg")` fills `or_fail_msg`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub combinator: Option<ResultCombinator>,
+    /// From `let` to the end of the initializer (and its `or` clause).
+    /// Lowerings that rewrite the declaration keep it.
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -402,6 +696,24 @@ impl BindingPattern {
     pub fn is_simple(&self) -> bool {
         matches!(self, BindingPattern::Identifier(_))
     }
+
+    /// The names the pattern binds, in source order
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            BindingPattern::Identifier(name) => vec![name.clone()],
+            BindingPattern::Object(object) => {
+                object.fields.iter().map(|f| f.binding.clone()).collect()
+            }
+            BindingPattern::Array(array) => array
+                .elements
+                .iter()
+                .flatten()
+                .chain(array.rest.iter())
+                .cloned()
+                .collect(),
+            BindingPattern::Tuple(tuple) => tuple.elements.clone(),
+        }
+    }
 }
 
 /// Object destructuring pattern: {name, age: userAge}
@@ -434,6 +746,13 @@ pub struct ConstDecl {
     pub name: String,
     pub type_ref: Option<TypeRef>,
     pub init: Expr,
+    /// `lazy NAME = expr`: evaluated once, on first use, instead of at
+    /// compile time
+    #[serde(default)]
+    pub is_lazy: bool,
+    /// Text of the `///` comment above the declaration, one line per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
     #[serde(skip)]
     pub span: Option<crate::span::Span>,
 }
@@ -445,21 +764,80 @@ pub struct AssignStmt {
     /// If this was a compound assignment (`+=`, `-=`, etc.), stores the operator.
     /// The `value` field already contains the desugared expression (`target op rhs`).
     pub op: Option<BinOp>,
+    /// From the target to the end of the value, as written
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
 }
 
+/// `if a { } else if b { } else { }`
+///
+/// An `else if` chain stays flat: every `else if` lands in `else_ifs`, in
+/// order, rather than as an `if` nested in the `else` branch, so walking a
+/// long chain doesn't recurse once per link.
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct IfStmt {
     pub condition: Expr,
     pub then_branch: IfBody,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub else_ifs: Vec<ElseIf>,
     pub else_branch: Option<IfBody>,
 }
 
+/// One `else if condition { body }` link of an [`IfStmt`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ElseIf {
+    pub condition: Expr,
+    pub body: IfBody,
+}
+
+impl IfStmt {
+    /// The conditions and their bodies, in the order they're tested
+    pub fn branches(&self) -> impl Iterator<Item = (&Expr, &IfBody)> {
+        std::iter::once((&self.condition, &self.then_branch)).chain(
+            self.else_ifs
+                .iter()
+                .map(|link| (&link.condition, &link.body)),
+        )
+    }
+
+    /// Every body of the chain, the final `else` included
+    pub fn bodies(&self) -> impl Iterator<Item = &IfBody> {
+        self.branches()
+            .map(|(_, body)| body)
+            .chain(self.else_branch.as_ref())
+    }
+
+    /// Mutable [`IfStmt::branches`]
+    pub fn branches_mut(&mut self) -> impl Iterator<Item = (&mut Expr, &mut IfBody)> {
+        std::iter::once((&mut self.condition, &mut self.then_branch)).chain(
+            self.else_ifs
+                .iter_mut()
+                .map(|link| (&mut link.condition, &mut link.body)),
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum IfBody {
     Block(BlockStmt),
     Stmt(Box<Stmt>),
 }
 
+impl IfBody {
+    /// Whether the body always leaves the enclosing block: it ends in
+    /// `return`, `fail`, `throw`, `break` or `continue`
+    pub fn always_exits(&self) -> bool {
+        let last = match self {
+            IfBody::Block(block) => block.stmts.last(),
+            IfBody::Stmt(stmt) => Some(stmt.as_ref()),
+        };
+        matches!(
+            last,
+            Some(Stmt::Return(_) | Stmt::Fail(_) | Stmt::Throw(_) | Stmt::Break | Stmt::Continue)
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct WhileStmt {
     pub condition: Expr,
@@ -584,6 +962,15 @@ pub struct SwitchExpr {
     pub arms: Vec<SwitchArm>,
 }
 
+/// `if` expression; unlike the statement form, `else` is required
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct IfExpr {
+    pub condition: Box<Expr>,
+    pub then_branch: SwitchBody,
+    /// `else if` chains nest another `Expr::If` as `SwitchBody::Expr`
+    pub else_branch: SwitchBody,
+}
+
 /// A single arm in a switch expression
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct SwitchArm {
@@ -619,6 +1006,20 @@ pub enum Pattern {
     },
 }
 
+impl Pattern {
+    /// The names a match of the pattern binds
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Pattern::Binding(name) | Pattern::Typed { name, .. } => vec![name.clone()],
+            Pattern::Tuple(items) | Pattern::Array(items) | Pattern::Or(items) => {
+                items.iter().flat_map(Pattern::names).collect()
+            }
+            Pattern::EnumVariant { bindings, .. } => bindings.clone(),
+            Pattern::Literal(_) | Pattern::Wildcard | Pattern::Range(_) => Vec::new(),
+        }
+    }
+}
+
 /// Range pattern for numeric matching
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct RangePattern {
@@ -627,7 +1028,7 @@ pub struct RangePattern {
     pub inclusive: bool, // true for ..=, false for ..
 }
 
-/// Body of a switch arm (can be expression or block)
+/// Body of a switch arm or `if` expression branch (can be expression or block)
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum SwitchBody {
     /// Single expression: => expr
@@ -661,6 +1062,28 @@ pub struct DeferStmt {
     pub body: Box<Stmt>,
 }
 
+/// `scope { ... }` — structured concurrency block
+///
+/// Every `async`/`par` task started directly in the body belongs to the
+/// scope. Leaving the body normally waits for the tasks still running;
+/// leaving it early (`return`, `break`, `fail`, an error) cancels them.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ScopeStmt {
+    pub body: BlockStmt,
+}
+
+/// `mock Builtin.name returns value` — stub a builtin for the rest of a test
+///
+/// Later calls of the builtin in the test give `value` instead of doing any
+/// IO: the response body for `HTTP.*`, the line for `readLine` and `prompt`,
+/// the date for `Date.now`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MockStmt {
+    /// The builtin's name as listed in [`crate::builtins::BUILTINS`]
+    pub target: String,
+    pub value: Expr,
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ReturnStmt {
     pub expr: Option<Expr>,
@@ -716,6 +1139,15 @@ pub enum Expr {
     Fail(Box<Expr>),
     MethodCall(MethodCallExpr),
     Switch(SwitchExpr), // Enhanced pattern matching (v0.9.5)
+    /// `if` used as a value: `let grade = if score > 90 { "A" } else { "B" }`
+    If(IfExpr),
+    /// Explicit conversion: `value as int`, `value as float`, `value as string`
+    /// A numeric cast of a string parses it, so it needs error binding like
+    /// `parseInt`
+    Cast {
+        expr: Box<Expr>,
+        target: TypeRef,
+    },
     /// Method/function reference with `::` syntax (v1.1.0 Phase 11.4)
     /// Examples: `Utils::validate`, `logger::log`, `User::new`
     MethodRef {
@@ -744,6 +1176,19 @@ pub enum Expr {
     },
 }
 
+impl Expr {
+    /// `a..b` or `a..=b`
+    pub fn is_range(&self) -> bool {
+        matches!(
+            self,
+            Expr::Binary {
+                op: BinOp::Range | BinOp::RangeInclusive,
+                ..
+            }
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum StringTemplatePart {
     Text(String),
@@ -797,6 +1242,9 @@ pub struct MethodCallExpr {
     /// Options for the adapter (threads, chunk, simdWidth, etc.)
     #[serde(default)]
     pub adapter_options: AdapterOptions,
+    /// Set when the parser built this call from a comprehension
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comprehension: Option<Comprehension>,
 }
 
 impl MethodCallExpr {
@@ -807,6 +1255,17 @@ impl MethodCallExpr {
             args,
             adapter: ArrayAdapter::Seq,
             adapter_options: AdapterOptions::default(),
+            comprehension: None,
+        }
+    }
+
+    /// `Array.fill(n, value)`, `Array.from(range)` or `range.toArray()`,
+    /// which build an array without spelling out its elements
+    pub fn is_array_constructor(&self) -> bool {
+        match (self.object.as_ref(), self.method.as_str()) {
+            (Expr::Identifier(name), "fill" | "from") => name == "Array",
+            (object, "toArray") => object.is_range() && self.args.is_empty(),
+            _ => false,
         }
     }
 }
@@ -831,6 +1290,19 @@ impl Default for ArrayAdapter {
     }
 }
 
+/// `[x * 2 for x in items if x > 0]`, which the parser rewrites to
+/// `items.filter(x => x > 0).map(x => x * 2)`. Kept on the outermost call
+/// (`map`, or `filter` when the element is the loop variable itself) so the
+/// formatter and `--trace-desugar` can show the comprehension as written.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Comprehension {
+    /// Whether it has an `if`, which became a `filter` call
+    pub filtered: bool,
+    /// From `[` to `]`
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
+}
+
 /// Options for array adapters
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
 pub struct AdapterOptions {
@@ -880,6 +1352,8 @@ pub struct LambdaExpr {
 pub struct LambdaParam {
     pub pattern: BindingPattern,
     pub type_ref: Option<TypeRef>,
+    #[serde(skip)]
+    pub span: Option<crate::span::Span>,
 }
 
 impl LambdaParam {
@@ -927,6 +1401,12 @@ pub enum BinOp {
     Mul,
     Div,
     Mod,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
     Lt,
     Le,
     Gt,
@@ -948,6 +1428,12 @@ impl fmt::Display for BinOp {
             BinOp::Mul => write!(f, "*"),
             BinOp::Div => write!(f, "/"),
             BinOp::Mod => write!(f, "%"),
+            BinOp::Pow => write!(f, "**"),
+            BinOp::BitAnd => write!(f, "&"),
+            BinOp::BitOr => write!(f, "|"),
+            BinOp::BitXor => write!(f, "^"),
+            BinOp::Shl => write!(f, "<<"),
+            BinOp::Shr => write!(f, ">>"),
             BinOp::Lt => write!(f, "<"),
             BinOp::Le => write!(f, "<="),
             BinOp::Gt => write!(f, ">"),
@@ -967,6 +1453,7 @@ impl fmt::Display for BinOp {
 pub enum UnOp {
     Neg,
     Not,
+    BitNot,
     Await,
 }
 
@@ -975,24 +1462,60 @@ impl fmt::Display for UnOp {
         match self {
             UnOp::Neg => write!(f, "-"),
             UnOp::Not => write!(f, "!"),
+            UnOp::BitNot => write!(f, "~"),
             UnOp::Await => write!(f, "await"),
         }
     }
 }
 
+/// Liva source syntax: `[number]`, `string?`, `Map<string, number>`
+impl fmt::Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |types: &[TypeRef], sep: &str| {
+            types
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(sep)
+        };
+        match self {
+            TypeRef::Simple(name) => write!(f, "{}", name),
+            TypeRef::Generic { base, args } => write!(f, "{}<{}>", base, join(args, ", ")),
+            TypeRef::Array(inner) => write!(f, "[{}]", inner),
+            TypeRef::Map(key, value) => write!(f, "Map<{}, {}>", key, value),
+            TypeRef::Set(inner) => write!(f, "Set<{}>", inner),
+            TypeRef::Optional(inner) => write!(f, "{}?", inner),
+            TypeRef::Fallible(inner) => write!(f, "{}!", inner),
+            TypeRef::Tuple(types) => write!(f, "({})", join(types, ", ")),
+            TypeRef::Union(types) => write!(f, "{}", join(types, " | ")),
+            TypeRef::Fn(args, ret) => write!(f, "({}) => {}", join(args, ", "), ret),
+        }
+    }
+}
+
 impl fmt::Display for ImportDecl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keyword = if self.is_reexport { "export" } else { "import" };
         if self.is_wildcard {
             if let Some(alias) = &self.alias {
-                write!(f, "import * as {} from \"{}\"", alias, self.source)
+                write!(f, "{} * as {} from \"{}\"", keyword, alias, self.source)
             } else {
-                write!(f, "import * from \"{}\"", self.source)
+                write!(f, "{} * from \"{}\"", keyword, self.source)
             }
         } else {
+            let symbols: Vec<String> = self
+                .imports
+                .iter()
+                .map(|s| match self.renames.get(s) {
+                    Some(local) => format!("{} as {}", s, local),
+                    None => s.clone(),
+                })
+                .collect();
             write!(
                 f,
-                "import {{ {} }} from \"{}\"",
-                self.imports.join(", "),
+                "{} {{ {} }} from \"{}\"",
+                keyword,
+                symbols.join(", "),
                 self.source
             )
         }
@@ -1066,6 +1589,618 @@ impl fmt::Display for RangePattern {
     }
 }
 
+// ===== Visitors =====
+
+/// A pass that reads the declarations, statements and expressions of a
+/// program. Each `visit_*` method defaults to the `walk_*` function of the
+/// same node, which visits its children in source order; a pass overrides
+/// the nodes it looks at and calls the `walk_*` function to go on into them.
+pub trait Visitor<'ast>: Sized {
+    fn visit_program(&mut self, program: &'ast Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_item(&mut self, item: &'ast TopLevel) {
+        walk_item(self, item)
+    }
+
+    fn visit_function(&mut self, func: &'ast FunctionDecl) {
+        walk_function(self, func)
+    }
+
+    fn visit_method(&mut self, method: &'ast MethodDecl) {
+        walk_method(self, method)
+    }
+
+    fn visit_block(&mut self, block: &'ast BlockStmt) {
+        walk_block(self, block)
+    }
+
+    /// A run of statements with a scope of its own: a block's, a switch
+    /// case's or arm's, or the one statement of a braceless `if` body
+    fn visit_stmts(&mut self, stmts: &'ast [Stmt]) {
+        walk_stmts(self, stmts)
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr)
+    }
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast>>(v: &mut V, program: &'ast Program) {
+    for item in &program.items {
+        v.visit_item(item);
+    }
+}
+
+pub fn walk_item<'ast, V: Visitor<'ast>>(v: &mut V, item: &'ast TopLevel) {
+    match item {
+        TopLevel::Function(func) => v.visit_function(func),
+        TopLevel::Class(ClassDecl { members, .. }) | TopLevel::Type(TypeDecl { members, .. }) => {
+            for member in members {
+                match member {
+                    Member::Field(field) => {
+                        if let Some(init) = &field.init {
+                            v.visit_expr(init);
+                        }
+                    }
+                    Member::Method(method) => v.visit_method(method),
+                }
+            }
+        }
+        TopLevel::ClassExtension(extension) => {
+            for method in &extension.methods {
+                v.visit_method(method);
+            }
+        }
+        TopLevel::Test(test) => v.visit_block(&test.body),
+        TopLevel::Init(init) => v.visit_block(&init.body),
+        TopLevel::TestHook(hook) => v.visit_block(&hook.body),
+        TopLevel::ConstDecl(decl) => v.visit_expr(&decl.init),
+        TopLevel::ExprStmt(expr) => v.visit_expr(expr),
+        TopLevel::Import(_)
+        | TopLevel::UseRust(_)
+        | TopLevel::TypeAlias(_)
+        | TopLevel::Enum(_)
+        | TopLevel::Runtime(_) => {}
+    }
+}
+
+/// The parameter defaults, the body and the `requires`/`ensures` conditions
+pub fn walk_function<'ast, V: Visitor<'ast>>(v: &mut V, func: &'ast FunctionDecl) {
+    for default in func.params.iter().filter_map(|p| p.default.as_ref()) {
+        v.visit_expr(default);
+    }
+    if let Some(body) = &func.body {
+        v.visit_block(body);
+    }
+    if let Some(expr) = &func.expr_body {
+        v.visit_expr(expr);
+    }
+    for contract in func.requires.iter().chain(&func.ensures) {
+        v.visit_expr(&contract.condition);
+    }
+}
+
+pub fn walk_method<'ast, V: Visitor<'ast>>(v: &mut V, method: &'ast MethodDecl) {
+    for default in method.params.iter().filter_map(|p| p.default.as_ref()) {
+        v.visit_expr(default);
+    }
+    if let Some(body) = &method.body {
+        v.visit_block(body);
+    }
+    if let Some(expr) = &method.expr_body {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn walk_block<'ast, V: Visitor<'ast>>(v: &mut V, block: &'ast BlockStmt) {
+    v.visit_stmts(&block.stmts);
+}
+
+pub fn walk_stmts<'ast, V: Visitor<'ast>>(v: &mut V, stmts: &'ast [Stmt]) {
+    for stmt in stmts {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'ast, V: Visitor<'ast>>(v: &mut V, stmt: &'ast Stmt) {
+    match stmt {
+        Stmt::VarDecl(decl) => {
+            v.visit_expr(&decl.init);
+            if let Some(msg) = &decl.or_fail_msg {
+                v.visit_expr(msg);
+            }
+            if let Some(value) = &decl.or_value {
+                v.visit_expr(value);
+            }
+            if let Some(ResultCombinator::MapErr(map)) = &decl.combinator {
+                v.visit_expr(map);
+            }
+        }
+        Stmt::ConstDecl(decl) => v.visit_expr(&decl.init),
+        Stmt::Assign(assign) => {
+            v.visit_expr(&assign.target);
+            v.visit_expr(&assign.value);
+        }
+        Stmt::If(if_stmt) => {
+            for (condition, body) in if_stmt.branches() {
+                v.visit_expr(condition);
+                walk_if_body(v, body);
+            }
+            if let Some(else_branch) = &if_stmt.else_branch {
+                walk_if_body(v, else_branch);
+            }
+        }
+        Stmt::While(while_stmt) => {
+            v.visit_expr(&while_stmt.condition);
+            v.visit_block(&while_stmt.body);
+        }
+        Stmt::For(for_stmt) => {
+            v.visit_expr(&for_stmt.iterable);
+            v.visit_block(&for_stmt.body);
+        }
+        Stmt::Switch(switch) => {
+            v.visit_expr(&switch.discriminant);
+            for case in &switch.cases {
+                v.visit_expr(&case.value);
+                v.visit_stmts(&case.body);
+            }
+            if let Some(default) = &switch.default {
+                v.visit_stmts(default);
+            }
+        }
+        Stmt::TryCatch(try_catch) => {
+            v.visit_block(&try_catch.try_block);
+            v.visit_block(&try_catch.catch_block);
+        }
+        Stmt::Throw(throw) => v.visit_expr(&throw.expr),
+        Stmt::Fail(fail) => v.visit_expr(&fail.expr),
+        Stmt::Return(ret) => {
+            if let Some(expr) = &ret.expr {
+                v.visit_expr(expr);
+            }
+        }
+        Stmt::Defer(defer) => v.visit_stmt(&defer.body),
+        Stmt::Scope(scope) => v.visit_block(&scope.body),
+        Stmt::Mock(mock) => v.visit_expr(&mock.value),
+        Stmt::Expr(expr_stmt) => v.visit_expr(&expr_stmt.expr),
+        Stmt::Block(block) => v.visit_block(block),
+        Stmt::Function(func) => v.visit_function(func),
+        Stmt::Break | Stmt::Continue => {}
+    }
+}
+
+pub fn walk_if_body<'ast, V: Visitor<'ast>>(v: &mut V, body: &'ast IfBody) {
+    match body {
+        IfBody::Block(block) => v.visit_block(block),
+        IfBody::Stmt(stmt) => v.visit_stmts(std::slice::from_ref(stmt.as_ref())),
+    }
+}
+
+pub fn walk_switch_body<'ast, V: Visitor<'ast>>(v: &mut V, body: &'ast SwitchBody) {
+    match body {
+        SwitchBody::Expr(expr) => v.visit_expr(expr),
+        SwitchBody::Block(stmts) => v.visit_stmts(stmts),
+    }
+}
+
+/// The bounds of the range patterns in `pattern`
+pub fn walk_pattern<'ast, V: Visitor<'ast>>(v: &mut V, pattern: &'ast Pattern) {
+    match pattern {
+        Pattern::Range(range) => {
+            for bound in range.start.iter().chain(&range.end) {
+                v.visit_expr(bound);
+            }
+        }
+        Pattern::Tuple(items) | Pattern::Array(items) | Pattern::Or(items) => {
+            for item in items {
+                walk_pattern(v, item);
+            }
+        }
+        Pattern::Literal(_)
+        | Pattern::Wildcard
+        | Pattern::Binding(_)
+        | Pattern::Typed { .. }
+        | Pattern::EnumVariant { .. } => {}
+    }
+}
+
+pub fn walk_expr<'ast, V: Visitor<'ast>>(v: &mut V, expr: &'ast Expr) {
+    match expr {
+        Expr::Binary { left, right, .. } => {
+            v.visit_expr(left);
+            v.visit_expr(right);
+        }
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            v.visit_expr(condition);
+            v.visit_expr(then_expr);
+            v.visit_expr(else_expr);
+        }
+        Expr::Call(call) => {
+            v.visit_expr(&call.callee);
+            for arg in &call.args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::MethodCall(call) => {
+            v.visit_expr(&call.object);
+            for arg in &call.args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::Index { object, index } => {
+            v.visit_expr(object);
+            v.visit_expr(index);
+        }
+        Expr::ObjectLiteral(fields) | Expr::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                v.visit_expr(value);
+            }
+        }
+        Expr::ArrayLiteral(items) | Expr::SetLiteral(items) | Expr::Tuple(items) => {
+            for item in items {
+                v.visit_expr(item);
+            }
+        }
+        Expr::MapLiteral(entries) => {
+            for (key, value) in entries {
+                v.visit_expr(key);
+                v.visit_expr(value);
+            }
+        }
+        Expr::Lambda(lambda) => match &lambda.body {
+            LambdaBody::Expr(body) => v.visit_expr(body),
+            LambdaBody::Block(block) => v.visit_block(block),
+        },
+        Expr::StringTemplate { parts } => {
+            for part in parts {
+                if let StringTemplatePart::Expr(expr) = part {
+                    v.visit_expr(expr);
+                }
+            }
+        }
+        Expr::Switch(switch) => {
+            v.visit_expr(&switch.discriminant);
+            for arm in &switch.arms {
+                walk_pattern(v, &arm.pattern);
+                if let Some(guard) = &arm.guard {
+                    v.visit_expr(guard);
+                }
+                walk_switch_body(v, &arm.body);
+            }
+        }
+        Expr::If(if_expr) => {
+            v.visit_expr(&if_expr.condition);
+            walk_switch_body(v, &if_expr.then_branch);
+            walk_switch_body(v, &if_expr.else_branch);
+        }
+        Expr::Unary { operand: inner, .. }
+        | Expr::Member { object: inner, .. }
+        | Expr::OptionalChain { object: inner, .. }
+        | Expr::Fail(inner)
+        | Expr::Unwrap(inner)
+        | Expr::Try(inner)
+        | Expr::Cast { expr: inner, .. } => v.visit_expr(inner),
+        Expr::Literal(_)
+        | Expr::Identifier(_)
+        | Expr::MethodRef { .. }
+        | Expr::RustBlock { .. } => {}
+    }
+}
+
+/// [`Visitor`] for a pass that rewrites the nodes it visits; its `walk_*`
+/// functions end in `_mut`
+pub trait VisitorMut: Sized {
+    fn visit_program(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+
+    fn visit_item(&mut self, item: &mut TopLevel) {
+        walk_item_mut(self, item)
+    }
+
+    fn visit_function(&mut self, func: &mut FunctionDecl) {
+        walk_function_mut(self, func)
+    }
+
+    fn visit_method(&mut self, method: &mut MethodDecl) {
+        walk_method_mut(self, method)
+    }
+
+    fn visit_block(&mut self, block: &mut BlockStmt) {
+        walk_block_mut(self, block)
+    }
+
+    /// A run of statements with a scope of its own: a block's, a switch
+    /// case's or arm's, or the one statement of a braceless `if` body
+    fn visit_stmts(&mut self, stmts: &mut [Stmt]) {
+        walk_stmts_mut(self, stmts)
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut>(v: &mut V, program: &mut Program) {
+    for item in &mut program.items {
+        v.visit_item(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut>(v: &mut V, item: &mut TopLevel) {
+    match item {
+        TopLevel::Function(func) => v.visit_function(func),
+        TopLevel::Class(ClassDecl { members, .. }) | TopLevel::Type(TypeDecl { members, .. }) => {
+            for member in members {
+                match member {
+                    Member::Field(field) => {
+                        if let Some(init) = &mut field.init {
+                            v.visit_expr(init);
+                        }
+                    }
+                    Member::Method(method) => v.visit_method(method),
+                }
+            }
+        }
+        TopLevel::ClassExtension(extension) => {
+            for method in &mut extension.methods {
+                v.visit_method(method);
+            }
+        }
+        TopLevel::Test(test) => v.visit_block(&mut test.body),
+        TopLevel::Init(init) => v.visit_block(&mut init.body),
+        TopLevel::TestHook(hook) => v.visit_block(&mut hook.body),
+        TopLevel::ConstDecl(decl) => v.visit_expr(&mut decl.init),
+        TopLevel::ExprStmt(expr) => v.visit_expr(expr),
+        TopLevel::Import(_)
+        | TopLevel::UseRust(_)
+        | TopLevel::TypeAlias(_)
+        | TopLevel::Enum(_)
+        | TopLevel::Runtime(_) => {}
+    }
+}
+
+/// The parameter defaults, the body and the `requires`/`ensures` conditions
+pub fn walk_function_mut<V: VisitorMut>(v: &mut V, func: &mut FunctionDecl) {
+    for default in func.params.iter_mut().filter_map(|p| p.default.as_mut()) {
+        v.visit_expr(default);
+    }
+    if let Some(body) = &mut func.body {
+        v.visit_block(body);
+    }
+    if let Some(expr) = &mut func.expr_body {
+        v.visit_expr(expr);
+    }
+    for contract in func.requires.iter_mut().chain(&mut func.ensures) {
+        v.visit_expr(&mut contract.condition);
+    }
+}
+
+pub fn walk_method_mut<V: VisitorMut>(v: &mut V, method: &mut MethodDecl) {
+    for default in method.params.iter_mut().filter_map(|p| p.default.as_mut()) {
+        v.visit_expr(default);
+    }
+    if let Some(body) = &mut method.body {
+        v.visit_block(body);
+    }
+    if let Some(expr) = &mut method.expr_body {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut>(v: &mut V, block: &mut BlockStmt) {
+    v.visit_stmts(&mut block.stmts);
+}
+
+pub fn walk_stmts_mut<V: VisitorMut>(v: &mut V, stmts: &mut [Stmt]) {
+    for stmt in stmts {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut>(v: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::VarDecl(decl) => {
+            v.visit_expr(&mut decl.init);
+            if let Some(msg) = &mut decl.or_fail_msg {
+                v.visit_expr(msg);
+            }
+            if let Some(value) = &mut decl.or_value {
+                v.visit_expr(value);
+            }
+            if let Some(ResultCombinator::MapErr(map)) = &mut decl.combinator {
+                v.visit_expr(map);
+            }
+        }
+        Stmt::ConstDecl(decl) => v.visit_expr(&mut decl.init),
+        Stmt::Assign(assign) => {
+            v.visit_expr(&mut assign.target);
+            v.visit_expr(&mut assign.value);
+        }
+        Stmt::If(if_stmt) => {
+            for (condition, body) in if_stmt.branches_mut() {
+                v.visit_expr(condition);
+                walk_if_body_mut(v, body);
+            }
+            if let Some(else_branch) = &mut if_stmt.else_branch {
+                walk_if_body_mut(v, else_branch);
+            }
+        }
+        Stmt::While(while_stmt) => {
+            v.visit_expr(&mut while_stmt.condition);
+            v.visit_block(&mut while_stmt.body);
+        }
+        Stmt::For(for_stmt) => {
+            v.visit_expr(&mut for_stmt.iterable);
+            v.visit_block(&mut for_stmt.body);
+        }
+        Stmt::Switch(switch) => {
+            v.visit_expr(&mut switch.discriminant);
+            for case in &mut switch.cases {
+                v.visit_expr(&mut case.value);
+                v.visit_stmts(&mut case.body);
+            }
+            if let Some(default) = &mut switch.default {
+                v.visit_stmts(default);
+            }
+        }
+        Stmt::TryCatch(try_catch) => {
+            v.visit_block(&mut try_catch.try_block);
+            v.visit_block(&mut try_catch.catch_block);
+        }
+        Stmt::Throw(throw) => v.visit_expr(&mut throw.expr),
+        Stmt::Fail(fail) => v.visit_expr(&mut fail.expr),
+        Stmt::Return(ret) => {
+            if let Some(expr) = &mut ret.expr {
+                v.visit_expr(expr);
+            }
+        }
+        Stmt::Defer(defer) => v.visit_stmt(&mut defer.body),
+        Stmt::Scope(scope) => v.visit_block(&mut scope.body),
+        Stmt::Mock(mock) => v.visit_expr(&mut mock.value),
+        Stmt::Expr(expr_stmt) => v.visit_expr(&mut expr_stmt.expr),
+        Stmt::Block(block) => v.visit_block(block),
+        Stmt::Function(func) => v.visit_function(func),
+        Stmt::Break | Stmt::Continue => {}
+    }
+}
+
+pub fn walk_if_body_mut<V: VisitorMut>(v: &mut V, body: &mut IfBody) {
+    match body {
+        IfBody::Block(block) => v.visit_block(block),
+        IfBody::Stmt(stmt) => v.visit_stmts(std::slice::from_mut(stmt.as_mut())),
+    }
+}
+
+pub fn walk_switch_body_mut<V: VisitorMut>(v: &mut V, body: &mut SwitchBody) {
+    match body {
+        SwitchBody::Expr(expr) => v.visit_expr(expr),
+        SwitchBody::Block(stmts) => v.visit_stmts(stmts),
+    }
+}
+
+/// The bounds of the range patterns in `pattern`
+pub fn walk_pattern_mut<V: VisitorMut>(v: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Range(range) => {
+            for bound in range.start.iter_mut().chain(&mut range.end) {
+                v.visit_expr(bound);
+            }
+        }
+        Pattern::Tuple(items) | Pattern::Array(items) | Pattern::Or(items) => {
+            for item in items {
+                walk_pattern_mut(v, item);
+            }
+        }
+        Pattern::Literal(_)
+        | Pattern::Wildcard
+        | Pattern::Binding(_)
+        | Pattern::Typed { .. }
+        | Pattern::EnumVariant { .. } => {}
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(v: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Binary { left, right, .. } => {
+            v.visit_expr(left);
+            v.visit_expr(right);
+        }
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            v.visit_expr(condition);
+            v.visit_expr(then_expr);
+            v.visit_expr(else_expr);
+        }
+        Expr::Call(call) => {
+            v.visit_expr(&mut call.callee);
+            for arg in &mut call.args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::MethodCall(call) => {
+            v.visit_expr(&mut call.object);
+            for arg in &mut call.args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::Index { object, index } => {
+            v.visit_expr(object);
+            v.visit_expr(index);
+        }
+        Expr::ObjectLiteral(fields) | Expr::StructLiteral { fields, .. } => {
+            for (_, value) in fields {
+                v.visit_expr(value);
+            }
+        }
+        Expr::ArrayLiteral(items) | Expr::SetLiteral(items) | Expr::Tuple(items) => {
+            for item in items {
+                v.visit_expr(item);
+            }
+        }
+        Expr::MapLiteral(entries) => {
+            for (key, value) in entries {
+                v.visit_expr(key);
+                v.visit_expr(value);
+            }
+        }
+        Expr::Lambda(lambda) => match &mut lambda.body {
+            LambdaBody::Expr(body) => v.visit_expr(body),
+            LambdaBody::Block(block) => v.visit_block(block),
+        },
+        Expr::StringTemplate { parts } => {
+            for part in parts {
+                if let StringTemplatePart::Expr(expr) = part {
+                    v.visit_expr(expr);
+                }
+            }
+        }
+        Expr::Switch(switch) => {
+            v.visit_expr(&mut switch.discriminant);
+            for arm in &mut switch.arms {
+                walk_pattern_mut(v, &mut arm.pattern);
+                if let Some(guard) = &mut arm.guard {
+                    v.visit_expr(guard);
+                }
+                walk_switch_body_mut(v, &mut arm.body);
+            }
+        }
+        Expr::If(if_expr) => {
+            v.visit_expr(&mut if_expr.condition);
+            walk_switch_body_mut(v, &mut if_expr.then_branch);
+            walk_switch_body_mut(v, &mut if_expr.else_branch);
+        }
+        Expr::Unary { operand: inner, .. }
+        | Expr::Member { object: inner, .. }
+        | Expr::OptionalChain { object: inner, .. }
+        | Expr::Fail(inner)
+        | Expr::Unwrap(inner)
+        | Expr::Try(inner)
+        | Expr::Cast { expr: inner, .. } => v.visit_expr(inner),
+        Expr::Literal(_)
+        | Expr::Identifier(_)
+        | Expr::MethodRef { .. }
+        | Expr::RustBlock { .. } => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1096,4 +2231,65 @@ mod tests {
         let optional = TypeRef::Optional(Box::new(TypeRef::Simple("float".into())));
         assert_eq!(optional.to_rust_type(), "Option<f64>");
     }
+
+    /// Every identifier a [`Visitor`] reaches, in visiting order
+    #[derive(Default)]
+    struct Identifiers(Vec<String>);
+
+    impl Visitor<'_> for Identifiers {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::Identifier(name) = expr {
+                self.0.push(name.clone());
+            }
+            walk_expr(self, expr);
+        }
+    }
+
+    /// Suffixes every identifier it reaches
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_expr(&mut self, expr: &mut Expr) {
+            if let Expr::Identifier(name) = expr {
+                name.push('_');
+            }
+            walk_expr_mut(self, expr);
+        }
+    }
+
+    #[test]
+    fn test_visitors_reach_every_expression() {
+        let source = r#"
+grade(score: int, low: int): string
+    requires score >= low
+{
+    defer print(low)
+    let label = switch score {
+        low..=100 if score > 0 => $"{score}",
+        _ => { return "F" }
+    }
+    let pick = (n) => n + low
+    return label
+}
+"#;
+        let mut program =
+            crate::parser::parse(crate::lexer::tokenize(source).unwrap(), source).unwrap();
+        let mut identifiers = Identifiers::default();
+        identifiers.visit_program(&program);
+        let mut found = identifiers.0;
+        found.sort();
+        // `low` in the contract, the deferred call, the range and the lambda
+        assert_eq!(
+            found,
+            [
+                "label", "low", "low", "low", "low", "n", "print", "score", "score", "score",
+                "score"
+            ]
+        );
+
+        Rename.visit_program(&mut program);
+        let mut identifiers = Identifiers::default();
+        identifiers.visit_program(&program);
+        assert!(identifiers.0.iter().all(|name| name.ends_with('_')));
+    }
 }
@@ -0,0 +1,383 @@
+//! Structural diff of two programs, for `livac dev ast-diff`.
+//!
+//! Declarations are matched by kind and name, class members by kind and
+//! name within their class, so moving a function or reformatting a file
+//! isn't a change. A declaration that differs lists the members and body
+//! statements that were added or removed; statements are matched in order
+//! (longest common subsequence), and a statement edited in place shows as
+//! the old one removed and the new one added. Nodes are compared as the
+//! trees `pretty` prints, without source positions; comments are ignored.
+//!
+//! [`diff`] returns plain data that serializes to stable JSON, so codemods
+//! and review tools can build on it; [`render`] is the text form.
+
+use crate::ast::*;
+use crate::pretty::{pretty_item, pretty_member, pretty_stmt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl ChangeKind {
+    fn sign(self) -> char {
+        match self {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Changed => '~',
+        }
+    }
+}
+
+/// A declaration or class member that was added, removed or changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclChange {
+    pub change: ChangeKind,
+    /// `function`, `class`, `interface`, `method`, `field`, `test`, ...
+    pub kind: &'static str,
+    pub name: String,
+    /// Members of a changed class, interface or extension that differ
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<DeclChange>,
+    /// Statements of a changed body that were added or removed; empty when
+    /// only the signature changed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub statements: Vec<StmtChange>,
+}
+
+/// A statement added to or removed from a body
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StmtChange {
+    pub change: ChangeKind,
+    /// Position in the old body for a removed statement, in the new one for
+    /// an added statement
+    pub index: usize,
+    /// The statement as a `pretty` tree
+    pub tree: String,
+}
+
+/// What changed from `old` to `new`: removed declarations first, in their
+/// old order, then added and changed ones in their new order
+pub fn diff(old: &Program, new: &Program) -> Vec<DeclChange> {
+    let old_decls = keyed(old.items.iter().map(decl_key));
+    let new_decls = keyed(new.items.iter().map(decl_key));
+    let old_index: HashMap<_, _> = old_decls
+        .iter()
+        .map(|(k, item)| (k.clone(), *item))
+        .collect();
+    let new_keys: HashMap<_, _> = new_decls.iter().map(|(k, _)| (k.clone(), ())).collect();
+
+    let mut changes: Vec<DeclChange> = old_decls
+        .iter()
+        .filter(|(key, _)| !new_keys.contains_key(key))
+        .map(|(key, _)| DeclChange::new(ChangeKind::Removed, key))
+        .collect();
+    for (key, item) in &new_decls {
+        match old_index.get(key) {
+            None => changes.push(DeclChange::new(ChangeKind::Added, key)),
+            Some(old_item) if pretty_item(old_item) != pretty_item(item) => {
+                let mut change = DeclChange::new(ChangeKind::Changed, key);
+                change.members = member_changes(old_item, item);
+                change.statements = diff_statements(&item_body(old_item), &item_body(item));
+                changes.push(change);
+            }
+            Some(_) => {}
+        }
+    }
+    changes
+}
+
+/// `changes` as text: one line per declaration, `+` added, `-` removed,
+/// `~` changed, with the members and statements of a change under it
+pub fn render(changes: &[DeclChange]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        render_decl(&mut out, change, 0);
+    }
+    out
+}
+
+fn render_decl(out: &mut String, change: &DeclChange, depth: usize) {
+    let indent = "    ".repeat(depth);
+    let _ = writeln!(
+        out,
+        "{}{} {} {}",
+        indent,
+        change.change.sign(),
+        change.kind,
+        change.name
+    );
+    for member in &change.members {
+        render_decl(out, member, depth + 1);
+    }
+    for stmt in &change.statements {
+        let mut lines = stmt.tree.lines();
+        let first = lines.next().unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "{}    {} [{}] {}",
+            indent,
+            stmt.change.sign(),
+            stmt.index,
+            first
+        );
+        for line in lines {
+            let _ = writeln!(out, "{}          {}", indent, line);
+        }
+    }
+}
+
+/// Kind, name and occurrence of a declaration or member, so overloads and
+/// repeated top-level expressions stay apart
+type Key = (&'static str, String, usize);
+
+impl DeclChange {
+    fn new(change: ChangeKind, key: &Key) -> Self {
+        Self {
+            change,
+            kind: key.0,
+            name: key.1.clone(),
+            members: Vec::new(),
+            statements: Vec::new(),
+        }
+    }
+}
+
+fn keyed<'a, T>(entries: impl Iterator<Item = (&'static str, String, &'a T)>) -> Vec<(Key, &'a T)> {
+    let mut seen: HashMap<(&'static str, String), usize> = HashMap::new();
+    entries
+        .map(|(kind, name, node)| {
+            let nth = seen.entry((kind, name.clone())).or_default();
+            *nth += 1;
+            ((kind, name, *nth), node)
+        })
+        .collect()
+}
+
+fn decl_key(item: &TopLevel) -> (&'static str, String, &TopLevel) {
+    let (kind, name) = match item {
+        TopLevel::Import(import) => ("import", import.source.clone()),
+        TopLevel::UseRust(decl) => ("use rust", decl.crate_name.clone()),
+        TopLevel::Type(decl) => ("interface", decl.name.clone()),
+        TopLevel::TypeAlias(decl) => ("type", decl.name.clone()),
+        TopLevel::Class(decl) => ("class", decl.name.clone()),
+        TopLevel::Enum(decl) => ("enum", decl.name.clone()),
+        TopLevel::Function(func) => ("function", func.name.clone()),
+        TopLevel::Test(test) => ("test", format!("{:?}", test.name)),
+        TopLevel::Init(_) => ("init", String::new()),
+        TopLevel::TestHook(hook) => (hook.kind.keyword(), String::new()),
+        TopLevel::ConstDecl(decl) => ("const", decl.name.clone()),
+        TopLevel::ExprStmt(_) => ("expression", String::new()),
+        TopLevel::ClassExtension(decl) => ("extension", decl.name.clone()),
+        TopLevel::Runtime(_) => ("runtime", String::new()),
+    };
+    (kind, name, item)
+}
+
+/// Fields and methods of a class or interface, and the methods of a class
+/// extension
+fn members(item: &TopLevel) -> Vec<Member> {
+    match item {
+        TopLevel::Class(ClassDecl { members, .. }) | TopLevel::Type(TypeDecl { members, .. }) => {
+            members.clone()
+        }
+        TopLevel::ClassExtension(decl) => {
+            decl.methods.iter().cloned().map(Member::Method).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn member_key(member: &Member) -> (&'static str, String, &Member) {
+    match member {
+        Member::Field(field) => ("field", field.name.clone(), member),
+        Member::Method(method) => ("method", method.name.clone(), member),
+    }
+}
+
+/// Added, removed and changed members of a declaration
+fn member_changes(old: &TopLevel, new: &TopLevel) -> Vec<DeclChange> {
+    let (old, new) = (members(old), members(new));
+    let old_members = keyed(old.iter().map(member_key));
+    let new_members = keyed(new.iter().map(member_key));
+    let old_index: HashMap<_, _> = old_members.iter().map(|(k, m)| (k.clone(), *m)).collect();
+    let new_keys: HashMap<_, _> = new_members.iter().map(|(k, _)| (k.clone(), ())).collect();
+
+    let mut changes: Vec<DeclChange> = old_members
+        .iter()
+        .filter(|(key, _)| !new_keys.contains_key(key))
+        .map(|(key, _)| DeclChange::new(ChangeKind::Removed, key))
+        .collect();
+    for (key, member) in &new_members {
+        match old_index.get(key) {
+            None => changes.push(DeclChange::new(ChangeKind::Added, key)),
+            Some(old_member) if pretty_member(old_member) != pretty_member(member) => {
+                let mut change = DeclChange::new(ChangeKind::Changed, key);
+                change.statements = diff_statements(&member_body(old_member), &member_body(member));
+                changes.push(change);
+            }
+            Some(_) => {}
+        }
+    }
+    changes
+}
+
+/// Body statements of a function, method, test, init block or test hook, as
+/// trees
+fn item_body(item: &TopLevel) -> Vec<String> {
+    match item {
+        TopLevel::Function(func) => body(&func.body, &func.expr_body),
+        TopLevel::Test(test) => test.body.stmts.iter().map(pretty_stmt).collect(),
+        TopLevel::Init(init) => init.body.stmts.iter().map(pretty_stmt).collect(),
+        TopLevel::TestHook(hook) => hook.body.stmts.iter().map(pretty_stmt).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn member_body(member: &Member) -> Vec<String> {
+    match member {
+        Member::Method(method) => body(&method.body, &method.expr_body),
+        Member::Field(_) => Vec::new(),
+    }
+}
+
+fn body(block: &Option<BlockStmt>, expr: &Option<Expr>) -> Vec<String> {
+    let mut trees: Vec<String> = block
+        .iter()
+        .flat_map(|block| block.stmts.iter().map(pretty_stmt))
+        .collect();
+    // `=> expr` is a body that returns `expr`
+    trees.extend(expr.iter().map(|expr| {
+        pretty_stmt(&Stmt::Return(ReturnStmt {
+            expr: Some(expr.clone()),
+        }))
+    }));
+    trees
+}
+
+/// Statements only in `old` (removed) and only in `new` (added), outside
+/// their longest common subsequence
+fn diff_statements(old: &[String], new: &[String]) -> Vec<StmtChange> {
+    // lcs[i][j]: length of the longest common subsequence of old[i..], new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            changes.push(StmtChange {
+                change: ChangeKind::Added,
+                index: j,
+                tree: new[j].clone(),
+            });
+            j += 1;
+        } else {
+            changes.push(StmtChange {
+                change: ChangeKind::Removed,
+                index: i,
+                tree: old[i].clone(),
+            });
+            i += 1;
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::tokenize, parser::parse};
+
+    fn program(source: &str) -> Program {
+        parse(tokenize(source).unwrap(), source).unwrap()
+    }
+
+    #[test]
+    fn test_moved_and_reformatted_code_is_unchanged() {
+        let old = program("a() {\n    print(1)\n}\n\nb() {\n    print(2)\n}\n");
+        let new = program("// b first\nb() { print(2) }\n\na() {\n\n    print(1)\n}\n");
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_reports_declarations_and_statements() {
+        let old = program(
+            r#"
+Point {
+    x: number
+    constructor(x: number) { this.x = x }
+    norm(): number { return this.x }
+}
+
+helper() { print("old") }
+
+main() {
+    let p = Point(1)
+    print(p.norm())
+}
+"#,
+        );
+        let new = program(
+            r#"
+Point {
+    x: number
+    y: number
+    constructor(x: number) { this.x = x }
+    norm(): number { return this.x }
+}
+
+main() {
+    let p = Point(1)
+    print("norm")
+    print(p.norm())
+}
+
+extra() { }
+"#,
+        );
+        let changes = diff(&old, &new);
+        let summary: Vec<(ChangeKind, &str, &str)> = changes
+            .iter()
+            .map(|c| (c.change, c.kind, c.name.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeKind::Removed, "function", "helper"),
+                (ChangeKind::Changed, "class", "Point"),
+                (ChangeKind::Changed, "function", "main"),
+                (ChangeKind::Added, "function", "extra"),
+            ]
+        );
+        assert_eq!(changes[1].members.len(), 1);
+        assert_eq!(changes[1].members[0].change, ChangeKind::Added);
+        assert_eq!(changes[1].members[0].name, "y");
+        assert_eq!(changes[2].statements.len(), 1);
+        assert_eq!(changes[2].statements[0].change, ChangeKind::Added);
+        assert_eq!(changes[2].statements[0].index, 1);
+
+        let text = render(&changes);
+        assert!(text.starts_with("- function helper\n~ class Point\n    + field y\n"));
+        assert!(text.contains("    + [1] "), "{}", text);
+    }
+}
@@ -0,0 +1,131 @@
+//! Code generation backends.
+//!
+//! Everything before code generation is target independent: a backend
+//! receives the analyzed, desugared program (or every module of a project in
+//! compilation order) together with the [`DesugarContext`] the earlier passes
+//! filled in, and returns the files of a buildable project. That input is the
+//! contract between the front end and a backend, so a new target only needs
+//! a [`Backend`] implementation and a [`BackendKind`] variant; parsing,
+//! semantic analysis and desugaring stay untouched.
+//!
+//! [`RustBackend`] is the only backend so far. It emits a Cargo project.
+
+use crate::ast::Program;
+use crate::desugaring::DesugarContext;
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::module::Module;
+use crate::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Output of a backend
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedProject {
+    /// Generated sources, by path relative to the output directory
+    pub files: HashMap<PathBuf, String>,
+    /// Build manifest of the project (`Cargo.toml` for Rust)
+    pub manifest: String,
+    /// Warnings reported while generating
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A code generation target
+pub trait Backend {
+    /// Name used to select the backend, as in [`BackendKind`]'s `FromStr`
+    fn name(&self) -> &'static str;
+
+    /// File the entry module is written to
+    fn entry_file(&self) -> PathBuf;
+
+    /// File an imported module is written to
+    fn module_file(&self, module: &Module) -> PathBuf;
+
+    /// Generate a project from a single file without imports
+    fn generate_program(&self, program: &Program, ctx: DesugarContext) -> Result<GeneratedProject>;
+
+    /// Generate a project from `modules`, in compilation order; `entry` is the
+    /// module holding `main`
+    fn generate_modules(
+        &self,
+        modules: &[&Module],
+        entry: &Module,
+        ctx: DesugarContext,
+    ) -> Result<GeneratedProject>;
+}
+
+/// Emits Rust source and a `Cargo.toml`; every Liva module becomes a Rust
+/// module of one binary crate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RustBackend;
+
+impl Backend for RustBackend {
+    fn name(&self) -> &'static str {
+        "rust"
+    }
+
+    fn entry_file(&self) -> PathBuf {
+        PathBuf::from("src/main.rs")
+    }
+
+    fn module_file(&self, module: &Module) -> PathBuf {
+        PathBuf::from("src").join(format!("{}.rs", module.rust_module_name()))
+    }
+
+    fn generate_program(&self, program: &Program, ctx: DesugarContext) -> Result<GeneratedProject> {
+        let mut diagnostics = DiagnosticSink::new();
+        let (rust_code, cargo_toml) =
+            crate::codegen::generate_with_diagnostics(program, ctx, &mut diagnostics)?;
+        Ok(GeneratedProject {
+            files: HashMap::from([(self.entry_file(), rust_code)]),
+            manifest: cargo_toml,
+            diagnostics: diagnostics.into_vec(),
+        })
+    }
+
+    fn generate_modules(
+        &self,
+        modules: &[&Module],
+        entry: &Module,
+        ctx: DesugarContext,
+    ) -> Result<GeneratedProject> {
+        let manifest = crate::codegen::generate_cargo_toml(&ctx)?;
+        let mut diagnostics = DiagnosticSink::new();
+        let files =
+            crate::codegen::generate_multifile_project(modules, entry, ctx, &mut diagnostics)?;
+        Ok(GeneratedProject {
+            files,
+            manifest,
+            diagnostics: diagnostics.into_vec(),
+        })
+    }
+}
+
+/// Backend selected in [`CompilerOptions`](crate::CompilerOptions)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Rust,
+}
+
+impl BackendKind {
+    /// Every backend, for tests that must hold for all of them
+    pub const ALL: &'static [BackendKind] = &[BackendKind::Rust];
+
+    pub fn backend(self) -> Box<dyn Backend> {
+        match self {
+            BackendKind::Rust => Box::new(RustBackend),
+        }
+    }
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "rust" => Ok(BackendKind::Rust),
+            other => Err(format!("unknown backend '{}' (expected rust)", other)),
+        }
+    }
+}
@@ -0,0 +1,572 @@
+//! The functions the compiler provides, in one table.
+//!
+//! Each entry records what a call looks like (`parseInt(str: string)`),
+//! whether it returns a value together with an error and so needs an error
+//! binding (`let n, err = parseInt(s)`), whether it runs asynchronously, and
+//! which crates it adds to the generated `Cargo.toml`. Codegen asks this
+//! table which calls are fallible or async, signature help reads its
+//! parameter lists, and `livac builtins --json` prints the whole of it for
+//! editor extensions and documentation.
+//!
+//! Array and string methods are not listed: they are methods on values, not
+//! functions, and are resolved by the type of the receiver.
+
+use serde_json::{json, Value};
+
+use crate::signatures::{Signature, SignatureParam};
+
+/// A compiler-provided function: `print` or `Math.pow`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Builtin {
+    /// `name` for global functions, `Namespace.name` for the rest
+    pub name: &'static str,
+    /// `(name, type)` in Liva syntax
+    pub params: &'static [(&'static str, &'static str)],
+    pub returns: &'static str,
+    /// Returns a value and an error, to bind as `let value, err = ...`
+    pub fallible: bool,
+    /// Runs as a task: `async HTTP.get(url)`
+    pub is_async: bool,
+    /// Crates the generated project depends on when the function is used,
+    /// beyond the ones every project gets
+    pub crates: &'static [&'static str],
+    /// Tests can stub it: `mock HTTP.get returns "{}"`
+    pub mockable: bool,
+}
+
+impl Builtin {
+    const fn new(
+        name: &'static str,
+        params: &'static [(&'static str, &'static str)],
+        returns: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            returns,
+            fallible: false,
+            is_async: false,
+            crates: &[],
+            mockable: false,
+        }
+    }
+
+    const fn fallible(self) -> Self {
+        Self {
+            fallible: true,
+            ..self
+        }
+    }
+
+    const fn asynchronous(self) -> Self {
+        Self {
+            is_async: true,
+            ..self
+        }
+    }
+
+    const fn crates(self, crates: &'static [&'static str]) -> Self {
+        Self { crates, ..self }
+    }
+
+    const fn mockable(self) -> Self {
+        Self {
+            mockable: true,
+            ..self
+        }
+    }
+
+    /// Namespace of a `Namespace.name` builtin
+    pub fn namespace(&self) -> Option<&'static str> {
+        self.name.split_once('.').map(|(namespace, _)| namespace)
+    }
+
+    /// Signature as shown by signature help; fallible builtins return
+    /// `(value, error)`
+    pub fn signature(&self) -> Signature {
+        Signature {
+            name: self.name.to_string(),
+            params: self
+                .params
+                .iter()
+                .map(|(param, type_ref)| SignatureParam {
+                    name: param.to_string(),
+                    type_ref: Some(type_ref.to_string()),
+                    default: None,
+                })
+                .collect(),
+            return_type: Some(if self.fallible {
+                format!("({}, string)", self.returns)
+            } else {
+                self.returns.to_string()
+            }),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let params: Vec<Value> = self
+            .params
+            .iter()
+            .map(|(name, type_ref)| json!({ "name": name, "type": type_ref }))
+            .collect();
+        json!({
+            "name": self.name,
+            "namespace": self.namespace(),
+            "params": params,
+            "returns": self.returns,
+            "fallible": self.fallible,
+            "async": self.is_async,
+            "crates": self.crates,
+            "mockable": self.mockable,
+        })
+    }
+}
+
+const CHRONO: &[&str] = &["chrono"];
+const CRYPTO: &[&str] = &["sha2", "md-5", "base64"];
+const RANDOM: &[&str] = &["rand", "uuid"];
+const RUSQLITE: &[&str] = &["rusqlite"];
+
+/// Every builtin function, grouped by namespace
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::new("print", &[("value", "any")], "void"),
+    Builtin::new("parseInt", &[("str", "string")], "int").fallible(),
+    Builtin::new("parseFloat", &[("str", "string")], "float").fallible(),
+    Builtin::new("toString", &[("value", "any")], "string"),
+    Builtin::new("zip", &[("a", "[T]"), ("b", "[U]")], "[(T, U)]"),
+    Builtin::new("clone", &[("value", "T")], "T"),
+    Builtin::new("freeze", &[("value", "T")], "T"),
+    Builtin::new("typeOf", &[("value", "any")], "string"),
+    Builtin::new("fields", &[("class", "class")], "[any]"),
+    Builtin::new("charCode", &[("c", "char")], "int"),
+    Builtin::new("fromCharCode", &[("code", "int")], "char"),
+    Builtin::new("readLine", &[], "string").mockable(),
+    Builtin::new("prompt", &[("message", "string")], "string").mockable(),
+    // Array
+    Builtin::new("Array.fill", &[("count", "int"), ("value", "T")], "[T]"),
+    Builtin::new("Array.from", &[("range", "range")], "[int]"),
+    // Math
+    Builtin::new("Math.sqrt", &[("n", "float")], "float"),
+    Builtin::new("Math.pow", &[("base", "float"), ("exp", "float")], "float"),
+    Builtin::new("Math.abs", &[("n", "float")], "float"),
+    Builtin::new("Math.log", &[("x", "float")], "float"),
+    Builtin::new("Math.floor", &[("n", "float")], "int"),
+    Builtin::new("Math.ceil", &[("n", "float")], "int"),
+    Builtin::new("Math.round", &[("n", "float")], "int"),
+    Builtin::new("Math.min", &[("a", "float"), ("b", "float")], "float"),
+    Builtin::new("Math.max", &[("a", "float"), ("b", "float")], "float"),
+    Builtin::new(
+        "Math.clamp",
+        &[("val", "number"), ("min", "number"), ("max", "number")],
+        "number",
+    ),
+    Builtin::new("Math.sign", &[("val", "number")], "int"),
+    Builtin::new(
+        "Math.floorDiv",
+        &[("a", "number"), ("b", "number")],
+        "number",
+    ),
+    Builtin::new(
+        "Math.floorMod",
+        &[("a", "number"), ("b", "number")],
+        "number",
+    ),
+    Builtin::new("Math.random", &[], "float").crates(RANDOM),
+    // Bits
+    Builtin::new("Bits.countOnes", &[("x", "int")], "int"),
+    Builtin::new("Bits.leadingZeros", &[("x", "int")], "int"),
+    Builtin::new("Bits.trailingZeros", &[("x", "int")], "int"),
+    Builtin::new("Bits.rotateLeft", &[("x", "int"), ("n", "int")], "int"),
+    Builtin::new("Bits.rotateRight", &[("x", "int"), ("n", "int")], "int"),
+    // console
+    Builtin::new("console.log", &[("values", "...any")], "void"),
+    Builtin::new("console.error", &[("values", "...any")], "void"),
+    Builtin::new("console.warn", &[("values", "...any")], "void"),
+    Builtin::new("console.success", &[("values", "...any")], "void"),
+    Builtin::new("console.input", &[("message", "string?")], "string"),
+    // JSON
+    Builtin::new("JSON.parse", &[("text", "string")], "any").fallible(),
+    Builtin::new("JSON.stringify", &[("value", "any")], "string").fallible(),
+    // File
+    Builtin::new("File.read", &[("path", "string")], "string").fallible(),
+    Builtin::new(
+        "File.write",
+        &[("path", "string"), ("content", "string")],
+        "bool",
+    )
+    .fallible(),
+    Builtin::new(
+        "File.append",
+        &[("path", "string"), ("content", "string")],
+        "bool",
+    )
+    .fallible(),
+    Builtin::new("File.exists", &[("path", "string")], "bool"),
+    Builtin::new("File.delete", &[("path", "string")], "bool").fallible(),
+    Builtin::new(
+        "File.copy",
+        &[("src", "string"), ("dest", "string")],
+        "bool",
+    )
+    .fallible(),
+    Builtin::new(
+        "File.move",
+        &[("src", "string"), ("dest", "string")],
+        "bool",
+    )
+    .fallible(),
+    Builtin::new("File.size", &[("path", "string")], "int").fallible(),
+    Builtin::new("File.extension", &[("path", "string")], "string"),
+    Builtin::new("File.readLines", &[("path", "string")], "[string]").fallible(),
+    Builtin::new(
+        "File.writeLines",
+        &[("path", "string"), ("lines", "[string]")],
+        "bool",
+    )
+    .fallible(),
+    // Dir
+    Builtin::new("Dir.list", &[("path", "string")], "[string]").fallible(),
+    Builtin::new("Dir.isDir", &[("path", "string")], "bool"),
+    Builtin::new("Dir.exists", &[("path", "string")], "bool"),
+    Builtin::new("Dir.create", &[("path", "string")], "bool").fallible(),
+    Builtin::new("Dir.delete", &[("path", "string")], "bool").fallible(),
+    Builtin::new("Dir.listRecursive", &[("path", "string")], "[string]").fallible(),
+    Builtin::new("Dir.walk", &[("path", "string")], "[string]").fallible(),
+    // HTTP
+    Builtin::new("HTTP.get", &[("url", "string")], "Response")
+        .fallible()
+        .asynchronous()
+        .mockable(),
+    Builtin::new(
+        "HTTP.post",
+        &[("url", "string"), ("body", "string")],
+        "Response",
+    )
+    .fallible()
+    .asynchronous()
+    .mockable(),
+    Builtin::new(
+        "HTTP.put",
+        &[("url", "string"), ("body", "string")],
+        "Response",
+    )
+    .fallible()
+    .asynchronous()
+    .mockable(),
+    Builtin::new("HTTP.delete", &[("url", "string")], "Response")
+        .fallible()
+        .asynchronous()
+        .mockable(),
+    // Sys
+    Builtin::new("Sys.args", &[], "[string]"),
+    Builtin::new("Sys.env", &[("name", "string")], "string"),
+    Builtin::new("Sys.exit", &[("code", "int")], "void"),
+    // Env
+    Builtin::new("Env.get", &[("name", "string")], "string?"),
+    Builtin::new("Env.has", &[("name", "string")], "bool"),
+    Builtin::new(
+        "Env.set",
+        &[("name", "string"), ("value", "string")],
+        "void",
+    ),
+    Builtin::new("Env.unset", &[("name", "string")], "void"),
+    Builtin::new("Env.all", &[], "Map<string, string>"),
+    // Path
+    Builtin::new(
+        "Path.join",
+        &[("base", "string"), ("part", "string")],
+        "string",
+    ),
+    Builtin::new("Path.parent", &[("path", "string")], "string"),
+    Builtin::new("Path.extension", &[("path", "string")], "string"),
+    Builtin::new("Path.basename", &[("path", "string")], "string"),
+    Builtin::new("Path.exists", &[("path", "string")], "bool"),
+    Builtin::new("Path.isAbsolute", &[("path", "string")], "bool"),
+    Builtin::new("Path.normalize", &[("path", "string")], "string"),
+    // Log
+    Builtin::new("Log.info", &[("values", "...any")], "void").crates(CHRONO),
+    Builtin::new("Log.warn", &[("values", "...any")], "void").crates(CHRONO),
+    Builtin::new("Log.error", &[("values", "...any")], "void").crates(CHRONO),
+    Builtin::new("Log.debug", &[("values", "...any")], "void").crates(CHRONO),
+    Builtin::new("Log.setLevel", &[("level", "string")], "void").crates(CHRONO),
+    // Config
+    Builtin::new("Config.load", &[("path", "string")], "Map<string, string>").fallible(),
+    Builtin::new(
+        "Config.get",
+        &[("config", "Map<string, string>"), ("key", "string")],
+        "string",
+    )
+    .fallible(),
+    Builtin::new(
+        "Config.getInt",
+        &[("config", "Map<string, string>"), ("key", "string")],
+        "int",
+    )
+    .fallible(),
+    Builtin::new(
+        "Config.getBool",
+        &[("config", "Map<string, string>"), ("key", "string")],
+        "bool",
+    )
+    .fallible(),
+    Builtin::new(
+        "Config.getAll",
+        &[("config", "Map<string, string>")],
+        "Map<string, string>",
+    ),
+    // Regex
+    Builtin::new(
+        "Regex.test",
+        &[("pattern", "string"), ("text", "string")],
+        "bool",
+    )
+    .crates(&["regex"]),
+    Builtin::new(
+        "Regex.match",
+        &[("pattern", "string"), ("text", "string")],
+        "string",
+    )
+    .fallible()
+    .crates(&["regex"]),
+    Builtin::new(
+        "Regex.findAll",
+        &[("pattern", "string"), ("text", "string")],
+        "[string]",
+    )
+    .crates(&["regex"]),
+    Builtin::new(
+        "Regex.replace",
+        &[
+            ("pattern", "string"),
+            ("text", "string"),
+            ("replacement", "string"),
+        ],
+        "string",
+    )
+    .crates(&["regex"]),
+    Builtin::new(
+        "Regex.split",
+        &[("pattern", "string"), ("text", "string")],
+        "[string]",
+    )
+    .crates(&["regex"]),
+    // Date
+    Builtin::new("Date.now", &[], "Date")
+        .crates(CHRONO)
+        .mockable(),
+    Builtin::new("Date.nowUtc", &[], "Date").crates(CHRONO),
+    Builtin::new(
+        "Date.new",
+        &[("year", "int"), ("month", "int"), ("day", "int")],
+        "Date",
+    )
+    .crates(CHRONO),
+    Builtin::new(
+        "Date.parse",
+        &[("text", "string"), ("pattern", "string")],
+        "Date",
+    )
+    .fallible()
+    .crates(CHRONO),
+    Builtin::new("Date.parseIso", &[("text", "string")], "Date")
+        .fallible()
+        .crates(CHRONO),
+    Builtin::new("Date.toIso", &[("date", "Date")], "string").crates(CHRONO),
+    Builtin::new("Date.timestamp", &[], "int").crates(CHRONO),
+    // CSV
+    Builtin::new("CSV.read", &[("path", "string")], "[[string]]").fallible(),
+    Builtin::new(
+        "CSV.write",
+        &[("path", "string"), ("rows", "[[string]]")],
+        "bool",
+    )
+    .fallible(),
+    Builtin::new(
+        "CSV.readTable",
+        &[("path", "string")],
+        "[Map<string, string>]",
+    )
+    .fallible(),
+    Builtin::new(
+        "CSV.writeTable",
+        &[("path", "string"), ("table", "[Map<string, string>]")],
+        "bool",
+    )
+    .fallible(),
+    Builtin::new("CSV.parse", &[("text", "string")], "[[string]]"),
+    Builtin::new("CSV.stringify", &[("rows", "[[string]]")], "string"),
+    Builtin::new(
+        "CSV.headers",
+        &[("table", "[Map<string, string>]")],
+        "[string]",
+    ),
+    Builtin::new(
+        "CSV.column",
+        &[("table", "[Map<string, string>]"), ("name", "string")],
+        "[string]",
+    ),
+    // Random
+    Builtin::new("Random.nextInt", &[("min", "int"), ("max", "int")], "int").crates(RANDOM),
+    Builtin::new(
+        "Random.nextFloat",
+        &[("min", "float"), ("max", "float")],
+        "float",
+    )
+    .crates(RANDOM),
+    Builtin::new("Random.choice", &[("items", "[T]")], "T").crates(RANDOM),
+    Builtin::new("Random.shuffle", &[("items", "[T]")], "[T]").crates(RANDOM),
+    Builtin::new("Random.uuid", &[], "string").crates(RANDOM),
+    // Crypto
+    Builtin::new("Crypto.sha256", &[("input", "string")], "string").crates(CRYPTO),
+    Builtin::new("Crypto.md5", &[("input", "string")], "string").crates(CRYPTO),
+    Builtin::new("Crypto.base64Encode", &[("input", "string")], "string").crates(CRYPTO),
+    Builtin::new("Crypto.base64Decode", &[("input", "string")], "string")
+        .fallible()
+        .crates(CRYPTO),
+    // Process
+    Builtin::new("Process.exec", &[("command", "string")], "string").fallible(),
+    Builtin::new("Process.spawn", &[("command", "string")], "int").fallible(),
+    Builtin::new("Process.args", &[], "[string]"),
+    Builtin::new("Process.pid", &[], "int"),
+    Builtin::new("Process.exit", &[("code", "int")], "void"),
+    // Server
+    Builtin::new("Server.create", &[], "App").crates(&["axum"]),
+    Builtin::new("Response.text", &[("body", "string")], "Response"),
+    Builtin::new("Response.json", &[("body", "string")], "Response"),
+    Builtin::new("Response.status", &[("code", "int")], "Response"),
+    // DB
+    Builtin::new("DB.open", &[("path", "string")], "Connection")
+        .fallible()
+        .crates(RUSQLITE),
+    Builtin::new("DB.exec", &[("db", "Connection"), ("sql", "string")], "int")
+        .fallible()
+        .crates(RUSQLITE),
+    Builtin::new(
+        "DB.query",
+        &[("db", "Connection"), ("sql", "string")],
+        "[Map<string, string>]",
+    )
+    .fallible()
+    .crates(RUSQLITE),
+    Builtin::new("DB.close", &[("db", "Connection")], "void").crates(RUSQLITE),
+];
+
+/// The builtin called `name`; `Http.get` finds `HTTP.get`
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    match name.split_once('.') {
+        Some((namespace, function)) => lookup_method(namespace, function),
+        None => BUILTINS.iter().find(|builtin| builtin.name == name),
+    }
+}
+
+/// The builtin `namespace.function`, as in `File.read(path)`
+pub fn lookup_method(namespace: &str, function: &str) -> Option<&'static Builtin> {
+    let namespace = if namespace == "Http" {
+        "HTTP"
+    } else {
+        namespace
+    };
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name.split_once('.') == Some((namespace, function)))
+}
+
+/// The whole table, as printed by `livac builtins --json`
+pub fn to_json() -> Value {
+    let builtins: Vec<Value> = BUILTINS.iter().map(Builtin::to_json).collect();
+    json!({
+        "version": crate::version(),
+        "builtins": builtins,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_lookup() {
+        let get = lookup("Http.get").unwrap();
+        assert_eq!(get.name, "HTTP.get");
+        assert!(get.fallible && get.is_async);
+        assert_eq!(
+            lookup_method("File", "read").unwrap().params,
+            &[("path", "string")]
+        );
+        assert!(lookup("parseInt").unwrap().fallible);
+        assert!(lookup("Math.nope").is_none());
+        assert!(lookup("File.readLine").is_none());
+    }
+
+    #[test]
+    fn test_mockable_builtins() {
+        let mockable: Vec<_> = BUILTINS
+            .iter()
+            .filter(|b| b.mockable)
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(
+            mockable,
+            vec![
+                "readLine",
+                "prompt",
+                "HTTP.get",
+                "HTTP.post",
+                "HTTP.put",
+                "HTTP.delete",
+                "Date.now"
+            ]
+        );
+        assert_eq!(to_json()["builtins"][0]["mockable"], false);
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let mut seen = HashSet::new();
+        for builtin in BUILTINS {
+            assert!(
+                seen.insert(builtin.name),
+                "{} is listed twice",
+                builtin.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_crates_match_generated_manifest() {
+        // Each namespace that pulls in crates must make codegen add them
+        for builtin in BUILTINS.iter().filter(|b| !b.crates.is_empty()) {
+            let source = format!("main() {{\n    {}()\n}}\n", builtin.name);
+            let tokens = crate::lexer::tokenize(&source).unwrap();
+            let program = crate::parser::parse(tokens, &source).unwrap();
+            let ctx = crate::desugaring::desugar(program).unwrap();
+            let manifest = crate::codegen::generate_cargo_toml(&ctx).unwrap();
+            for krate in builtin.crates {
+                assert!(
+                    manifest.contains(&format!("\n{} = ", krate)),
+                    "{} should add {}:\n{}",
+                    builtin.name,
+                    krate,
+                    manifest
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let json = to_json();
+        let entries = json["builtins"].as_array().unwrap();
+        assert_eq!(entries.len(), BUILTINS.len());
+        let decode = entries
+            .iter()
+            .find(|b| b["name"] == "Crypto.base64Decode")
+            .unwrap();
+        assert_eq!(decode["namespace"], "Crypto");
+        assert_eq!(decode["fallible"], true);
+        assert_eq!(decode["async"], false);
+        assert_eq!(decode["params"][0]["type"], "string");
+        assert_eq!(decode["crates"], json!(["sha2", "md-5", "base64"]));
+    }
+}
@@ -0,0 +1,106 @@
+//! Cooperative cancellation for library-driven compilation.
+//!
+//! Editors re-run analysis on every keystroke; once a newer run starts, the
+//! result of the previous one is useless. A [`CancellationToken`] is handed to
+//! [`compile_source_with`](crate::compile_source_with) or
+//! [`check_source_with`](crate::check_source_with), and the pipeline polls it
+//! between stages, between custom passes and — since that is where big files
+//! spend their time — before every top-level item and statement during
+//! semantic analysis. A cancelled run returns [`CompilerError::Cancelled`];
+//! one that outlives its deadline returns [`CompilerError::DeadlineExceeded`].
+
+use crate::error::{CompilerError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Shared flag (plus optional deadline) that aborts a compilation in progress
+///
+/// Clones share the flag, so the caller keeps one clone and passes another to
+/// the compiler, possibly on a different thread.
+///
+/// ```rust
+/// use livac::cancel::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let worker = token.clone();
+/// token.cancel();
+/// assert!(worker.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// A token that only stops the compilation when [`cancel`](Self::cancel) is called
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that also expires at `deadline`
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// A token that expires `timeout` from now
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Ask every compilation holding a clone of this token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// True once cancelled or past the deadline
+    pub fn is_cancelled(&self) -> bool {
+        self.check().is_err()
+    }
+
+    /// `Err` with the reason the compilation should stop, if it should
+    pub fn check(&self) -> Result<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(CompilerError::Cancelled);
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(CompilerError::DeadlineExceeded),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_is_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+        assert!(matches!(token.check(), Err(CompilerError::Cancelled)));
+    }
+
+    #[test]
+    fn test_deadline_expires() {
+        let expired = CancellationToken::with_deadline(Instant::now());
+        assert!(matches!(
+            expired.check(),
+            Err(CompilerError::DeadlineExceeded)
+        ));
+
+        let later = CancellationToken::with_timeout(Duration::from_secs(3600));
+        assert!(!later.is_cancelled());
+    }
+}
@@ -0,0 +1,244 @@
+//! Capture analysis for nested functions.
+//!
+//! A function declared inside a function body can use the locals of the
+//! enclosing body that are declared before it. Codegen lowers a nested
+//! function that uses none of them to a nested Rust `fn`, and one that does
+//! to a closure bound to the function's name, so this pass records on each
+//! nested `FunctionDecl` which enclosing locals it uses.
+//!
+//! The analysis goes by name: a capture is a name the nested function uses
+//! but does not declare itself, that is a local (parameter, `let`, loop or
+//! pattern binding, `this`) of an enclosing body at the point of the
+//! declaration. A nested function that captures is itself a local, so a
+//! sibling declared after it and calling it captures it too.
+
+use crate::ast::*;
+use std::collections::HashSet;
+
+/// Fill in `FunctionDecl::captures` for every nested function in `program`
+pub fn resolve(program: &mut Program) {
+    for item in &mut program.items {
+        Resolver::default().visit_item(item);
+    }
+}
+
+/// Whether `func` calls itself by name
+pub fn is_recursive(func: &FunctionDecl) -> bool {
+    let mut resolver = Resolver::default();
+    resolver.visit_function(&mut func.clone());
+    resolver.used.contains(&func.name)
+}
+
+/// Every name `expr` reads
+pub fn used_names(expr: &Expr) -> HashSet<String> {
+    let mut resolver = Resolver::default();
+    resolver.visit_expr(&mut expr.clone());
+    resolver.used
+}
+
+#[derive(Default)]
+struct Resolver {
+    /// Locals visible at the current point, innermost scope last
+    scopes: Vec<HashSet<String>>,
+    /// Names used and declared in the function being walked, nested
+    /// functions included
+    used: HashSet<String>,
+    declared: HashSet<String>,
+}
+
+impl Resolver {
+    fn declare(&mut self, name: String) {
+        self.declared.insert(name.clone());
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name);
+        }
+    }
+
+    /// Walk `node` in a new scope holding `names`
+    fn scoped<T: ?Sized>(
+        &mut self,
+        names: Vec<String>,
+        node: &mut T,
+        walk: impl FnOnce(&mut Self, &mut T),
+    ) {
+        self.scopes.push(HashSet::new());
+        for name in names {
+            self.declare(name);
+        }
+        walk(self, node);
+        self.scopes.pop();
+    }
+
+    fn nested_function(&mut self, func: &mut FunctionDecl) {
+        let visible: HashSet<String> = self.scopes.iter().flatten().cloned().collect();
+        let outer_used = std::mem::take(&mut self.used);
+        let outer_declared = std::mem::take(&mut self.declared);
+
+        self.scoped(param_names(&func.params), func, walk_function_mut);
+
+        let free: HashSet<String> = self.used.difference(&self.declared).cloned().collect();
+        let mut captures: Vec<String> = free.intersection(&visible).cloned().collect();
+        captures.sort();
+        func.captures = captures;
+
+        self.used = outer_used;
+        self.used.extend(free);
+        self.declared = outer_declared;
+        if func.captures.is_empty() {
+            self.declared.insert(func.name.clone());
+        } else {
+            self.declare(func.name.clone());
+        }
+    }
+}
+
+impl VisitorMut for Resolver {
+    fn visit_function(&mut self, func: &mut FunctionDecl) {
+        // Only a nested function has an enclosing scope
+        if self.scopes.is_empty() {
+            self.scoped(param_names(&func.params), func, walk_function_mut);
+        } else {
+            self.nested_function(func);
+        }
+    }
+
+    fn visit_method(&mut self, method: &mut MethodDecl) {
+        let mut params = param_names(&method.params);
+        params.push("this".to_string());
+        self.scoped(params, method, walk_method_mut);
+    }
+
+    fn visit_stmts(&mut self, stmts: &mut [Stmt]) {
+        self.scoped(Vec::new(), stmts, walk_stmts_mut);
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                let names: Vec<String> = decl
+                    .bindings
+                    .iter()
+                    .flat_map(|binding| binding.pattern.names())
+                    .collect();
+                walk_stmt_mut(self, stmt);
+                for name in names {
+                    self.declare(name);
+                }
+            }
+            Stmt::ConstDecl(decl) => {
+                self.visit_expr(&mut decl.init);
+                self.declare(decl.name.clone());
+            }
+            Stmt::For(for_stmt) => {
+                self.visit_expr(&mut for_stmt.iterable);
+                let mut vars = vec![for_stmt.var.clone()];
+                vars.extend(for_stmt.var2.clone());
+                self.scoped(vars, &mut for_stmt.body, Self::visit_block);
+            }
+            Stmt::TryCatch(try_catch) => {
+                self.visit_block(&mut try_catch.try_block);
+                self.scoped(
+                    vec![try_catch.catch_var.clone()],
+                    &mut try_catch.catch_block,
+                    Self::visit_block,
+                );
+            }
+            _ => walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Identifier(name) => {
+                self.used.insert(name.clone());
+            }
+            Expr::MethodRef { object, .. } => {
+                self.used.insert(object.clone());
+            }
+            Expr::Lambda(lambda) => {
+                let params = lambda
+                    .params
+                    .iter()
+                    .flat_map(|param| param.pattern.names())
+                    .collect();
+                self.scoped(params, expr, walk_expr_mut);
+            }
+            Expr::Switch(switch) => {
+                self.visit_expr(&mut switch.discriminant);
+                for arm in &mut switch.arms {
+                    self.scoped(arm.pattern.names(), arm, |this, arm| {
+                        walk_pattern_mut(this, &mut arm.pattern);
+                        if let Some(guard) = &mut arm.guard {
+                            this.visit_expr(guard);
+                        }
+                        walk_switch_body_mut(this, &mut arm.body);
+                    });
+                }
+            }
+            _ => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+fn param_names(params: &[Param]) -> Vec<String> {
+    params.iter().flat_map(|p| p.pattern.names()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    /// Captures of each nested function, in declaration order
+    fn captures(source: &str) -> Vec<(String, Vec<String>)> {
+        let mut program = parse(tokenize(source).unwrap(), source).unwrap();
+        resolve(&mut program);
+        let mut found = Vec::new();
+        for item in &program.items {
+            if let TopLevel::Function(func) = item {
+                collect(func.body.as_ref().unwrap(), &mut found);
+            }
+        }
+        found
+    }
+
+    fn collect(block: &BlockStmt, found: &mut Vec<(String, Vec<String>)>) {
+        for stmt in &block.stmts {
+            if let Stmt::Function(func) = stmt {
+                found.push((func.name.clone(), func.captures.clone()));
+                if let Some(body) = &func.body {
+                    collect(body, found);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_captures_enclosing_locals() {
+        let source = "total(items: [int], rate: float): float {\n    let bonus = 2\n    square(n: int): int => n * n\n    scaled(n: int): float {\n        let base = square(n) + bonus\n        return base * rate\n    }\n    let unused = 1\n    return scaled(items[0])\n}\n";
+        assert_eq!(
+            captures(source),
+            vec![
+                ("square".to_string(), vec![]),
+                (
+                    "scaled".to_string(),
+                    vec!["bonus".to_string(), "rate".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_captures_through_nesting_and_siblings() {
+        let source = "main() {\n    let prefix = \"> \"\n    show(text: string) => print(prefix + text)\n    outer() {\n        inner() {\n            show(\"hi\")\n        }\n        inner()\n    }\n    outer()\n}\n";
+        assert_eq!(
+            captures(source),
+            vec![
+                ("show".to_string(), vec!["prefix".to_string()]),
+                ("outer".to_string(), vec!["show".to_string()]),
+                ("inner".to_string(), vec!["show".to_string()]),
+            ]
+        );
+    }
+}
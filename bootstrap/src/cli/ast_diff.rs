@@ -0,0 +1,36 @@
+//! `livac dev ast-diff`: what changed between two versions of a program,
+//! by declaration and statement rather than by line. See `livac::ast_diff`.
+
+use colored::*;
+use std::path::Path;
+
+use livac::ast::Program;
+use livac::ast_diff;
+
+/// Print the differences from `old` to `new`, as text or as JSON; true when
+/// there are none
+pub fn run(old: &Path, new: &Path, json: bool) -> Result<bool, Box<dyn std::error::Error>> {
+    let changes = ast_diff::diff(&parse(old)?, &parse(new)?);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+    } else {
+        for line in ast_diff::render(&changes).lines() {
+            let colored = match line.trim_start().chars().next() {
+                Some('+') => line.green(),
+                Some('-') => line.red(),
+                Some('~') => line.yellow(),
+                _ => line.normal(),
+            };
+            println!("{}", colored);
+        }
+    }
+    Ok(changes.is_empty())
+}
+
+fn parse(path: &Path) -> Result<Program, Box<dyn std::error::Error>> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let tokens =
+        livac::lexer::tokenize(&source).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(livac::parser::parse(tokens, &source).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
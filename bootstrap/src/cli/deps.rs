@@ -0,0 +1,112 @@
+//! `livac add` and `livac install`: manage `[dependencies]` in `liva.toml`.
+
+use colored::*;
+use livac::package::{self, DependencyDetail, DependencySpec, Manifest, Resolution};
+use std::path::PathBuf;
+
+/// Source flags of `livac add`
+#[derive(Debug, Default, Clone)]
+pub struct AddSource {
+    pub path: Option<String>,
+    pub git: Option<String>,
+    pub rev: Option<String>,
+    pub tag: Option<String>,
+    pub branch: Option<String>,
+}
+
+/// Record `name` in the nearest `liva.toml`, then fetch it and update `liva.lock`
+pub fn add(name: &str, source: AddSource) -> Result<(), Box<dyn std::error::Error>> {
+    crate::cli::scaffold::validate_name(name)?;
+    if source.path.is_none() && source.git.is_none() {
+        return Err(format!(
+            "No source given for '{}'. A package registry is not available yet.\nUse `livac add {} --path ../{}` or `livac add {} --git <url>`",
+            name, name, name, name
+        )
+        .into());
+    }
+
+    let manifest_path = find_manifest()?;
+    let text = std::fs::read_to_string(&manifest_path)?;
+    let spec = DependencySpec::Detailed(DependencyDetail {
+        path: source.path,
+        git: source.git,
+        rev: source.rev,
+        tag: source.tag,
+        branch: source.branch,
+        version: None,
+    });
+    spec.source(name)?;
+
+    let updated = package::add_dependency(&text, name, &spec);
+    Manifest::parse(&updated)?;
+    std::fs::write(&manifest_path, &updated)?;
+
+    // Roll the manifest back if the new dependency can't be resolved
+    let resolution = match package::resolve_project(&manifest_path) {
+        Ok(resolution) => resolution,
+        Err(e) => {
+            std::fs::write(&manifest_path, text)?;
+            return Err(e.into());
+        }
+    };
+    println!(
+        "{} Added {} = {}",
+        "✓".green().bold(),
+        name.bold(),
+        spec.to_toml()
+    );
+    print_packages(&resolution);
+    Ok(())
+}
+
+/// Fetch every dependency of the nearest `liva.toml` and write `liva.lock`
+pub fn install() -> Result<(), Box<dyn std::error::Error>> {
+    let manifest_path = find_manifest()?;
+    let resolution = package::resolve_project(&manifest_path)?;
+    if resolution.is_empty() {
+        println!(
+            "{} No dependencies in {}",
+            "✓".green().bold(),
+            manifest_path.display()
+        );
+        return Ok(());
+    }
+    println!(
+        "{} Installed {} package{}",
+        "✓".green().bold(),
+        resolution.packages.len(),
+        if resolution.packages.len() == 1 {
+            ""
+        } else {
+            "s"
+        }
+    );
+    print_packages(&resolution);
+    Ok(())
+}
+
+fn find_manifest() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Manifest::find(&std::env::current_dir()?).ok_or_else(|| {
+        format!(
+            "No {} found in this directory or its parents.\nRun `livac init` to create one.",
+            package::MANIFEST_FILE
+        )
+        .into()
+    })
+}
+
+fn print_packages(resolution: &Resolution) {
+    for package in resolution.packages.values() {
+        let version = if package.version.is_empty() {
+            String::new()
+        } else {
+            format!(" v{}", package.version)
+        };
+        println!(
+            "    {}{} {}",
+            package.name,
+            version,
+            format!("({})", package.source).dimmed()
+        );
+    }
+}
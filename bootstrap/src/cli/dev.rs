@@ -0,0 +1,287 @@
+//! `livac dev`: rebuild and restart a program whenever its sources change.
+//!
+//! Watches every `.liva` file under the entry file's directory by polling
+//! modification times, so it needs no platform file-watching API. On a
+//! change the program is rebuilt into `.liva_build/`; only generated files
+//! whose contents changed are rewritten, so cargo recompiles just what the
+//! edit touched. When the build succeeds the running process gets SIGTERM,
+//! which makes `app.listen` stop accepting connections and finish the
+//! requests in flight, and the new binary starts. A failed build leaves the
+//! old process serving.
+
+use colored::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+
+use super::events::Events;
+use crate::CompileArgs;
+use livac::BuildProfile;
+
+/// How often sources are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Quiet time after a change before rebuilding, so saving several files
+/// at once triggers one rebuild
+const SETTLE_TIME: Duration = Duration::from_millis(150);
+
+/// Options of `livac dev`
+#[derive(Debug, Clone)]
+pub struct DevOptions {
+    pub input: PathBuf,
+    pub profile: BuildProfile,
+    /// How long a stopping process may take to finish its requests
+    pub drain_timeout: Duration,
+    pub program_args: Vec<String>,
+}
+
+/// Modification time of every watched source file
+type Snapshot = HashMap<PathBuf, SystemTime>;
+
+/// Build, run, and rebuild on change until interrupted
+pub fn run(options: DevOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let root = options
+        .input
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    let output_dir = root.join(".liva_build");
+    let binary = output_dir
+        .join("target")
+        .join(options.profile.target_dir())
+        .join(livac::artifacts::crate_name(&options.input));
+
+    println!(
+        "{} {} {}",
+        "👀 Watching".cyan().bold(),
+        root.display(),
+        "(Ctrl-C to stop)".dimmed()
+    );
+
+    let mut sources = snapshot(&root);
+    let mut child = if build(&options, &output_dir) {
+        Some(start(&binary, &options.program_args)?)
+    } else {
+        None
+    };
+    let mut reported_exit = false;
+
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+
+        if let Some(process) = child.as_mut() {
+            if !reported_exit {
+                if let Some(status) = process.try_wait()? {
+                    println!(
+                        "{} {} {}",
+                        "■ Program exited".yellow(),
+                        status,
+                        "- waiting for changes".dimmed()
+                    );
+                    reported_exit = true;
+                }
+            }
+        }
+
+        let mut current = snapshot(&root);
+        if changed_files(&sources, &current).is_empty() {
+            continue;
+        }
+        std::thread::sleep(SETTLE_TIME);
+        current = snapshot(&root);
+        for path in changed_files(&sources, &current) {
+            let shown = path.strip_prefix(&root).unwrap_or(&path);
+            println!("{} {}", "↻ Changed".cyan(), shown.display());
+        }
+        sources = current;
+
+        if !build(&options, &output_dir) {
+            if child.is_some() && !reported_exit {
+                println!(
+                    "{}",
+                    "  Build failed; the previous version keeps running".yellow()
+                );
+            }
+            continue;
+        }
+        if let Some(process) = child.take() {
+            stop(process, options.drain_timeout)?;
+        }
+        child = Some(start(&binary, &options.program_args)?);
+        reported_exit = false;
+    }
+}
+
+/// Compile into `output_dir`, reporting errors; true when a binary was built
+fn build(options: &DevOptions, output_dir: &Path) -> bool {
+    let args = CompileArgs {
+        output: Some(output_dir.to_path_buf()),
+        run: false,
+        verbose: false,
+        check: false,
+        json: false,
+        profile: options.profile,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        program_args: Vec::new(),
+        quiet: false,
+        events: Events::default(),
+        per_target: false,
+        sandbox: None,
+        self_check: false,
+        // Each rebuild reparses only the items the edit touched
+        incremental: true,
+        explain_codegen: false,
+        format_output: false,
+        max_errors: 1,
+        report: None,
+    };
+    match crate::compile(&args, &options.input) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            false
+        }
+    }
+}
+
+fn start(binary: &Path, program_args: &[String]) -> std::io::Result<Child> {
+    println!("{}", "▶ Starting".green().bold());
+    Command::new(binary).args(program_args).spawn()
+}
+
+/// Ask the process to shut down, then kill it if it outlives `drain_timeout`
+fn stop(mut process: Child, drain_timeout: Duration) -> std::io::Result<()> {
+    if process.try_wait()?.is_some() {
+        return Ok(());
+    }
+    println!("{}", "■ Stopping (draining connections)".yellow());
+    if terminate(&process) {
+        let deadline = Instant::now() + drain_timeout;
+        while Instant::now() < deadline {
+            if process.try_wait()?.is_some() {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        println!(
+            "{}",
+            format!(
+                "  Still running after {}s; killing it",
+                drain_timeout.as_secs()
+            )
+            .yellow()
+        );
+    }
+    process.kill()?;
+    process.wait()?;
+    Ok(())
+}
+
+/// Send SIGTERM; false where there is no such signal
+#[cfg(unix)]
+fn terminate(process: &Child) -> bool {
+    Command::new("kill")
+        .arg("-TERM")
+        .arg(process.id().to_string())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn terminate(_process: &Child) -> bool {
+    false
+}
+
+/// The `.liva` files under `root`, skipping build output and hidden dirs
+fn snapshot(root: &Path) -> Snapshot {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || name == "target")
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "liva"))
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((entry.into_path(), modified))
+        })
+        .collect()
+}
+
+/// Files added, removed or modified between two snapshots, sorted
+fn changed_files(before: &Snapshot, after: &Snapshot) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = after
+        .iter()
+        .filter(|(path, modified)| before.get(*path) != Some(modified))
+        .map(|(path, _)| path.clone())
+        .chain(
+            before
+                .keys()
+                .filter(|path| !after.contains_key(*path))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_skips_build_output() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.liva"), "main() {}\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("handlers")).unwrap();
+        std::fs::write(dir.path().join("handlers/users.liva"), "").unwrap();
+        std::fs::create_dir_all(dir.path().join(".liva_build/src")).unwrap();
+        std::fs::write(dir.path().join(".liva_build/src/copy.liva"), "").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "").unwrap();
+
+        let mut files: Vec<PathBuf> = snapshot(dir.path()).into_keys().collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("handlers/users.liva"),
+                dir.path().join("main.liva"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let t1 = t0 + Duration::from_secs(1);
+        let before: Snapshot = [
+            (PathBuf::from("a.liva"), t0),
+            (PathBuf::from("b.liva"), t0),
+            (PathBuf::from("c.liva"), t0),
+        ]
+        .into();
+        let after: Snapshot = [
+            (PathBuf::from("a.liva"), t0),
+            (PathBuf::from("b.liva"), t1),
+            (PathBuf::from("d.liva"), t0),
+        ]
+        .into();
+        assert_eq!(
+            changed_files(&before, &after),
+            vec![
+                PathBuf::from("b.liva"),
+                PathBuf::from("c.liva"),
+                PathBuf::from("d.liva"),
+            ]
+        );
+        assert!(changed_files(&after, &after).is_empty());
+    }
+}
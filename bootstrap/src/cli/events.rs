@@ -0,0 +1,157 @@
+//! `--message-format json`: build progress as newline-delimited JSON on
+//! stdout, one object per line, for build tools and editor tasks.
+//!
+//! Every event has an `"event"` field:
+//!
+//! - `phase-started` / `phase-finished`: `"phase"` is `compile`,
+//!   `cargo-build` or `run`; `phase-finished` adds `"success"`
+//! - `diagnostic`: `"level"` (`error`, `warning` or `hint`) and
+//!   `"diagnostic"`, the same object `--json` prints for it
+//! - `artifact`: `"kind"` (`rust-crate`, `line-map`, `source-map` or `binary`)
+//!   and `"path"`
+//! - `cargo`: a message of `cargo build --message-format=json` under
+//!   `"message"`, or a line cargo wrote to stderr under `"line"`
+//! - `finished`: the last event, with `"success"` and `"exit_code"`
+
+use livac::{CompilerError, Diagnostic};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// How the CLI reports progress and diagnostics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Colored text for people
+    #[default]
+    Human,
+    /// Newline-delimited JSON events
+    Json,
+}
+
+/// Emits events when the message format is JSON, and does nothing otherwise
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Events {
+    enabled: bool,
+}
+
+impl Events {
+    pub fn new(format: MessageFormat) -> Self {
+        Events {
+            enabled: format == MessageFormat::Json,
+        }
+    }
+
+    pub fn enabled(self) -> bool {
+        self.enabled
+    }
+
+    /// Run `phase` between its started and finished events
+    pub fn phase<T>(
+        self,
+        phase: &str,
+        run: impl FnOnce() -> Result<T, CompilerError>,
+    ) -> Result<T, CompilerError> {
+        self.emit(json!({ "event": "phase-started", "phase": phase }));
+        let result = run();
+        self.emit(json!({
+            "event": "phase-finished",
+            "phase": phase,
+            "success": result.is_ok(),
+        }));
+        result
+    }
+
+    /// A warning or hint, at the level of its severity
+    pub fn report(self, diagnostic: &Diagnostic) {
+        let json = diagnostic
+            .to_json()
+            .ok()
+            .and_then(|json_str| serde_json::from_str(&json_str).ok())
+            .unwrap_or_else(|| json!({ "message": diagnostic.info.message }));
+        self.diagnostic(diagnostic.severity.name(), json);
+    }
+
+    /// One `diagnostic` event per error, so a `Multiple` gives several
+    pub fn error(self, error: &CompilerError) {
+        for error in error.errors() {
+            let diagnostic = error
+                .to_json()
+                .and_then(|json_str| serde_json::from_str(&json_str).ok())
+                .unwrap_or_else(|| json!({ "message": error.to_string() }));
+            self.diagnostic("error", diagnostic);
+        }
+    }
+
+    fn diagnostic(self, level: &str, diagnostic: Value) {
+        self.emit(json!({ "event": "diagnostic", "level": level, "diagnostic": diagnostic }));
+    }
+
+    pub fn artifact(self, kind: &str, path: &Path) {
+        self.emit(json!({ "event": "artifact", "kind": kind, "path": path.display().to_string() }));
+    }
+
+    /// Pass on the output of `cargo build --message-format=json`
+    pub fn cargo_output(self, stdout: &str, stderr: &str) {
+        for event in cargo_events(stdout, stderr) {
+            self.emit(event);
+        }
+    }
+
+    pub fn finished(self, exit_code: i32) {
+        self.emit(json!({
+            "event": "finished",
+            "success": exit_code == 0,
+            "exit_code": exit_code,
+        }));
+    }
+
+    fn emit(self, event: Value) {
+        if self.enabled {
+            println!("{}", event);
+        }
+    }
+}
+
+/// One event per message cargo printed on stdout, then one per stderr line
+fn cargo_events(stdout: &str, stderr: &str) -> Vec<Value> {
+    fn non_empty(text: &str) -> impl Iterator<Item = &str> {
+        text.lines().filter(|line| !line.trim().is_empty())
+    }
+    let mut events: Vec<Value> = non_empty(stdout)
+        .map(|line| match serde_json::from_str::<Value>(line) {
+            Ok(message) => json!({ "event": "cargo", "message": message }),
+            Err(_) => json!({ "event": "cargo", "line": line }),
+        })
+        .collect();
+    events.extend(non_empty(stderr).map(|line| json!({ "event": "cargo", "line": line })));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo_output_becomes_events() {
+        let stdout = "{\"reason\":\"build-finished\",\"success\":true}\nnot json\n";
+        let stderr = "   Compiling liva_project v0.1.0\n\n";
+        let message = json!({ "reason": "build-finished", "success": true });
+        assert_eq!(
+            cargo_events(stdout, stderr),
+            [
+                json!({ "event": "cargo", "message": message }),
+                json!({ "event": "cargo", "line": "not json" }),
+                json!({ "event": "cargo", "line": "   Compiling liva_project v0.1.0" }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_phase_reports_failure() {
+        let events = Events::new(MessageFormat::Json);
+        assert!(events.enabled());
+        let failed: Result<(), CompilerError> =
+            events.phase("compile", || Err(CompilerError::IoError("missing".into())));
+        assert!(failed.is_err());
+        assert!(!Events::new(MessageFormat::Human).enabled());
+    }
+}
@@ -0,0 +1,303 @@
+//! `livac dev run-examples`: the example programs as a regression suite.
+//!
+//! Every `.liva` file under the examples directory that declares `main` is a
+//! sample, and must compile. A sample with a `<name>.stdout` file next to it
+//! is also built and run, and what it prints must match that file exactly;
+//! with `--bless` the file is rewritten from the actual output instead, so an
+//! empty `<name>.stdout` is how a new sample joins the suite. Test files
+//! (`*.test.liva`), hidden directories and `target/` are skipped.
+
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+use livac::ast::TopLevel;
+use livac::{CompilerOptions, RunOptions};
+
+/// Options of `livac dev run-examples`
+#[derive(Debug, Clone)]
+pub struct ExamplesOptions {
+    pub dir: PathBuf,
+    /// Build and run samples that have expected output; otherwise only compile
+    pub run: bool,
+    /// Write the actual output to the `.stdout` files instead of comparing
+    pub bless: bool,
+    /// Wall-clock limit of each program
+    pub timeout: Duration,
+    /// Only samples whose path contains this
+    pub filter: Option<String>,
+}
+
+/// What happened to one sample
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Compiled,
+    Matched,
+    Blessed,
+    Failed(String),
+}
+
+/// Check every sample; true when none failed
+pub fn run(options: &ExamplesOptions) -> Result<bool, Box<dyn std::error::Error>> {
+    if !options.dir.is_dir() {
+        return Err(format!("{} is not a directory", options.dir.display()).into());
+    }
+    let samples: Vec<PathBuf> = samples(&options.dir)
+        .into_iter()
+        .filter(|path| match &options.filter {
+            Some(filter) => path.to_string_lossy().contains(filter.as_str()),
+            None => true,
+        })
+        .collect();
+
+    println!(
+        "{} {} ({} sample{})",
+        "🧪 Running examples in".cyan().bold(),
+        options.dir.display(),
+        samples.len(),
+        if samples.len() == 1 { "" } else { "s" }
+    );
+    println!();
+
+    let start = Instant::now();
+    let mut failed = 0;
+    for sample in &samples {
+        let shown = sample.strip_prefix(&options.dir).unwrap_or(sample);
+        let sample_start = Instant::now();
+        let outcome = check(sample, options);
+        let elapsed = sample_start.elapsed();
+        match &outcome {
+            Outcome::Compiled => println!(
+                " {} {} (compiled, {:.0?})",
+                "PASS".white().on_green().bold(),
+                shown.display(),
+                elapsed
+            ),
+            Outcome::Matched => println!(
+                " {} {} (output matches, {:.0?})",
+                "PASS".white().on_green().bold(),
+                shown.display(),
+                elapsed
+            ),
+            Outcome::Blessed => println!(
+                " {} {} (expected output written, {:.0?})",
+                "BLESS".white().on_blue().bold(),
+                shown.display(),
+                elapsed
+            ),
+            Outcome::Failed(reason) => {
+                failed += 1;
+                println!(
+                    " {} {} ({:.0?})",
+                    "FAIL".white().on_red().bold(),
+                    shown.display(),
+                    elapsed
+                );
+                for line in reason.lines() {
+                    println!("       {}", line.red());
+                }
+            }
+        }
+    }
+
+    println!();
+    println!("{}", "─".repeat(50));
+    let summary = format!(
+        "Examples:  {} passed, {} failed, {} total",
+        samples.len() - failed,
+        failed,
+        samples.len()
+    );
+    if failed == 0 {
+        println!("{}", summary.green().bold());
+    } else {
+        println!("{}", summary.red().bold());
+    }
+    println!("Time:      {:.2?}", start.elapsed());
+    Ok(failed == 0)
+}
+
+/// The samples under `dir`, sorted
+fn samples(dir: &Path) -> Vec<PathBuf> {
+    let mut samples: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || name == "target")
+        })
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "liva")
+                && !path.to_string_lossy().ends_with(".test.liva")
+                && declares_main(path)
+        })
+        .collect();
+    samples.sort();
+    samples
+}
+
+/// True unless the file parses and has no `main`: a file that doesn't parse
+/// counts as a sample, so the suite reports it instead of skipping it
+fn declares_main(path: &Path) -> bool {
+    let Ok(source) = std::fs::read_to_string(path) else {
+        return true;
+    };
+    match livac::lexer::tokenize(&source).and_then(|tokens| livac::parser::parse(tokens, &source)) {
+        Ok(program) => program
+            .items
+            .iter()
+            .any(|item| matches!(item, TopLevel::Function(func) if func.name == "main")),
+        Err(_) => true,
+    }
+}
+
+/// Where the expected output of `sample` is kept
+fn expected_path(sample: &Path) -> PathBuf {
+    sample.with_extension("stdout")
+}
+
+fn check(sample: &Path, options: &ExamplesOptions) -> Outcome {
+    let expected_file = expected_path(sample);
+    if !options.run || !expected_file.exists() {
+        let compile_options = CompilerOptions {
+            input: sample.to_path_buf(),
+            ..CompilerOptions::default()
+        };
+        return match livac::compile_file(&compile_options) {
+            Ok(_) => Outcome::Compiled,
+            Err(e) => Outcome::Failed(e.to_string()),
+        };
+    }
+
+    let run_options = RunOptions {
+        timeout: options.timeout,
+        memory_limit: None,
+        sandbox: None,
+        ..RunOptions::default()
+    };
+    let result = match livac::run_file(sample, &run_options) {
+        Ok(result) => result,
+        Err(e) => return Outcome::Failed(e.to_string()),
+    };
+    if result.timed_out {
+        return Outcome::Failed(format!(
+            "still running after {}s",
+            options.timeout.as_secs()
+        ));
+    }
+    if !result.success() {
+        let status = result
+            .exit_code
+            .map_or_else(|| "a signal".to_string(), |code| code.to_string());
+        return Outcome::Failed(format!(
+            "exited with {}\n{}",
+            status,
+            result.stderr.trim_end()
+        ));
+    }
+
+    if options.bless {
+        return match std::fs::write(&expected_file, &result.stdout) {
+            Ok(()) => Outcome::Blessed,
+            Err(e) => Outcome::Failed(format!("cannot write {}: {}", expected_file.display(), e)),
+        };
+    }
+    let expected = std::fs::read_to_string(&expected_file).unwrap_or_default();
+    match first_difference(&expected, &result.stdout) {
+        None => Outcome::Matched,
+        Some(difference) => Outcome::Failed(difference),
+    }
+}
+
+/// The first line where `actual` output differs from `expected`, if any
+fn first_difference(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(want), Some(got)) if want == got => line += 1,
+            (None, None) => return Some("output differs in its final newline".to_string()),
+            (want, got) => {
+                let show = |text: Option<&str>| {
+                    text.map_or_else(|| "<end of output>".to_string(), |t| format!("{:?}", t))
+                };
+                return Some(format!(
+                    "output differs at line {}\n  expected: {}\n  actual:   {}",
+                    line,
+                    show(want),
+                    show(got)
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_need_main() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hello.liva"), "main() {\n    print(1)\n}\n").unwrap();
+        std::fs::write(dir.path().join("lib.liva"), "add(a, b) => a + b\n").unwrap();
+        std::fs::write(dir.path().join("math.test.liva"), "main() {}\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("app/target")).unwrap();
+        std::fs::write(dir.path().join("app/main.liva"), "main() {}\n").unwrap();
+        std::fs::write(dir.path().join("app/target/copy.liva"), "main() {}\n").unwrap();
+        std::fs::write(dir.path().join("broken.liva"), "main( {\n").unwrap();
+
+        assert_eq!(
+            samples(dir.path()),
+            vec![
+                dir.path().join("app/main.liva"),
+                dir.path().join("broken.liva"),
+                dir.path().join("hello.liva"),
+            ]
+        );
+        assert_eq!(
+            expected_path(&dir.path().join("hello.liva")),
+            dir.path().join("hello.stdout")
+        );
+    }
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference("a\nb\n", "a\nb\n"), None);
+        assert_eq!(
+            first_difference("a\nb\n", "a\nc\n").unwrap(),
+            "output differs at line 2\n  expected: \"b\"\n  actual:   \"c\""
+        );
+        assert!(first_difference("a\n", "a\nb\n")
+            .unwrap()
+            .contains("expected: <end of output>"));
+        assert_eq!(
+            first_difference("a\n", "a").unwrap(),
+            "output differs in its final newline"
+        );
+    }
+
+    #[test]
+    fn test_compile_only_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let sample = dir.path().join("hello.liva");
+        std::fs::write(&sample, "main() {\n    print(\"hi\")\n}\n").unwrap();
+        let options = ExamplesOptions {
+            dir: dir.path().to_path_buf(),
+            run: false,
+            bless: false,
+            timeout: Duration::from_secs(10),
+            filter: None,
+        };
+        assert_eq!(check(&sample, &options), Outcome::Compiled);
+
+        std::fs::write(&sample, "main() {\n    let x =\n}\n").unwrap();
+        assert!(matches!(check(&sample, &options), Outcome::Failed(_)));
+    }
+}
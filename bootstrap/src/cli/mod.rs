@@ -0,0 +1,9 @@
+//! Subcommand implementations that live outside `main.rs`.
+
+pub mod ast_diff;
+pub mod deps;
+pub mod dev;
+pub mod events;
+pub mod examples;
+pub mod reduce;
+pub mod scaffold;
@@ -0,0 +1,50 @@
+//! `livac dev reduce`: shrink a program that makes the compiler fail to the
+//! few lines that still make it fail the same way. See `livac::reduce`.
+
+use colored::*;
+use std::path::Path;
+
+use livac::reduce;
+
+/// Reduce `input`, writing the result to `output` or printing it
+pub fn run(
+    input: &Path,
+    output: Option<&Path>,
+    exact: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Panics of the compiler are what is being reduced; keep them quiet
+    reduce::install_panic_hook();
+    let source = std::fs::read_to_string(input)
+        .map_err(|e| format!("cannot read {}: {}", input.display(), e))?;
+    let original = reduce::failure(&source).ok_or_else(|| {
+        format!(
+            "{} compiles without errors; there is nothing to reduce",
+            input.display()
+        )
+    })?;
+    eprintln!(
+        "{} {} ({} lines): {}",
+        "Reducing".cyan().bold(),
+        input.display(),
+        source.lines().count(),
+        original
+    );
+
+    let reduction = reduce::reduce(&source, |candidate| {
+        reduce::failure(candidate).is_some_and(|failure| failure.matches(&original, exact))
+    });
+
+    match output {
+        Some(path) => std::fs::write(path, &reduction.source)
+            .map_err(|e| format!("cannot write {}: {}", path.display(), e))?,
+        None => print!("{}", reduction.source),
+    }
+    eprintln!(
+        "{} {} lines left, {} removed, after {} compiles",
+        "✓".green().bold(),
+        reduction.source.lines().count(),
+        reduction.removed_lines,
+        reduction.attempts
+    );
+    Ok(())
+}
@@ -0,0 +1,845 @@
+//! Project scaffolding for `livac new` and `livac init`.
+//!
+//! Every template produces the same layout: a `liva.toml` manifest, sources
+//! under `src/`, a `tests/` directory and a `.gitignore`. Code the tests
+//! exercise lives in its own module (`src/greeting.liva`, `src/lib.liva`, ...)
+//! because a test file cannot import from the module that defines `main()`.
+
+use colored::*;
+use std::path::{Path, PathBuf};
+
+/// Starter project layouts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Template {
+    /// Hello-world application
+    #[default]
+    App,
+    /// Command-line tool reading `Sys.args()`
+    #[value(alias = "cli")]
+    CliApp,
+    /// HTTP JSON service built on `Server`
+    #[value(alias = "http")]
+    HttpService,
+    /// Reusable library with no `main()`
+    #[value(alias = "lib")]
+    Library,
+    /// Multi-file tour of the language
+    Tour,
+}
+
+impl Template {
+    /// Entry file recorded in `liva.toml`
+    pub fn entry(self) -> &'static str {
+        match self {
+            Template::Library => "src/lib.liva",
+            _ => "src/main.liva",
+        }
+    }
+
+    /// Files to write, as (path relative to the project root, contents)
+    pub fn files(self, name: &str) -> Vec<(&'static str, String)> {
+        let mut files = vec![("liva.toml", manifest(name, self.entry()))];
+        match self {
+            Template::App => {
+                files.push(("src/main.liva", app_main(name)));
+                files.push(("src/greeting.liva", APP_GREETING.to_string()));
+                files.push(("tests/greeting.test.liva", APP_TEST.to_string()));
+            }
+            Template::CliApp => {
+                files.push(("src/main.liva", cli_main(name)));
+                files.push(("src/commands.liva", CLI_COMMANDS.to_string()));
+                files.push(("tests/commands.test.liva", CLI_TEST.to_string()));
+            }
+            Template::HttpService => {
+                files.push(("src/main.liva", http_main(name)));
+                files.push(("src/responses.liva", HTTP_RESPONSES.to_string()));
+                files.push(("tests/responses.test.liva", HTTP_TEST.to_string()));
+            }
+            Template::Library => {
+                files.push(("src/lib.liva", library_lib(name)));
+                files.push(("tests/lib.test.liva", LIBRARY_TEST.to_string()));
+            }
+            Template::Tour => {
+                files.push(("src/main.liva", tour_main(name)));
+                files.push(("src/math.liva", TOUR_MATH.to_string()));
+                files.push(("src/models.liva", TOUR_MODELS.to_string()));
+                files.push(("tests/main.test.liva", TOUR_TEST.to_string()));
+            }
+        }
+        files.push((".gitignore", GITIGNORE_TEMPLATE.to_string()));
+        files
+    }
+}
+
+/// `livac new <name>`: create the project in a new `<name>` directory
+pub fn new_project(name: &str, template: Template) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    validate_name(name)?;
+    let dir = PathBuf::from(name);
+    if dir.exists() {
+        return Err(format!("Directory '{}' already exists", name).into());
+    }
+    write_project(&dir, name, template)?;
+    print_summary(name, name, template);
+    Ok(dir)
+}
+
+/// `livac init [name]`: scaffold into the current directory for `"."`,
+/// otherwise behave like `livac new`
+pub fn init_project(name: &str, template: Template) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if name != "." {
+        return new_project(name, template);
+    }
+    let cwd = std::env::current_dir()?;
+    let display_name = cwd
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("my-project")
+        .to_string();
+    write_project(&cwd, &display_name, template)?;
+    print_summary(".", &display_name, template);
+    Ok(cwd)
+}
+
+/// Project names become directory names and the `liva.toml` package name
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Project name cannot be empty".into());
+    }
+    if name.contains(std::path::MAIN_SEPARATOR) || name.contains('/') || name.contains('\\') {
+        return Err("Project name cannot contain path separators".into());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(
+            "Project name can only contain letters, numbers, hyphens, and underscores".into(),
+        );
+    }
+    Ok(())
+}
+
+/// Write the template into `dir`, refusing to overwrite any existing file
+/// other than a `.gitignore`, which gets the entries it lacks appended
+pub fn write_project(
+    dir: &Path,
+    name: &str,
+    template: Template,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let files = template.files(name);
+    if let Some((path, _)) = files
+        .iter()
+        .find(|(path, _)| *path != ".gitignore" && dir.join(path).exists())
+    {
+        return Err(format!("{} already exists in {}", path, dir.display()).into());
+    }
+
+    println!("{} Creating project '{}'...", "→".blue(), name.bold());
+    for (path, contents) in &files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if path.ends_with(".gitignore") && path.exists() {
+            merge_gitignore(&path, contents)?;
+        } else {
+            std::fs::write(path, contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// Append the patterns of `template` that the `.gitignore` at `path` lacks,
+/// for `livac init` in a directory that is already a git checkout
+fn merge_gitignore(path: &Path, template: &str) -> std::io::Result<()> {
+    let existing = std::fs::read_to_string(path)?;
+    let present: std::collections::HashSet<&str> = existing.lines().map(str::trim).collect();
+    let missing: Vec<&str> = template
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !present.contains(line))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let mut merged = existing;
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+    merged.push_str("\n# Added by livac init\n");
+    for line in missing {
+        merged.push_str(line);
+        merged.push('\n');
+    }
+    std::fs::write(path, merged)
+}
+
+fn print_summary(name: &str, display_name: &str, template: Template) {
+    println!("{} Created project structure:", "✓".green().bold());
+    if name == "." {
+        println!("    ./ ({})", display_name);
+    } else {
+        println!("    {}/", display_name);
+    }
+    let mut paths: Vec<&str> = template
+        .files(display_name)
+        .iter()
+        .map(|(p, _)| *p)
+        .collect();
+    paths.sort_by_key(|p| (!p.contains('/'), *p));
+    for line in tree_lines(&paths) {
+        println!("    {}", line);
+    }
+
+    let cd = if name == "." {
+        String::new()
+    } else {
+        format!("cd {} && ", display_name)
+    };
+    println!();
+    println!("  Get started:");
+    if template != Template::Library {
+        println!("    {}{} {}", cd, "livac run".cyan(), template.entry());
+    }
+    println!("    {}{}", cd, "livac test".cyan());
+}
+
+/// Render sorted relative paths (one directory level deep) as a tree
+fn tree_lines(paths: &[&str]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut i = 0;
+    while i < paths.len() {
+        let last = |next: usize| next >= paths.len();
+        match paths[i].split_once('/') {
+            Some((dir, _)) => {
+                let end = paths[i..]
+                    .iter()
+                    .position(|p| !p.starts_with(&format!("{}/", dir)))
+                    .map_or(paths.len(), |n| i + n);
+                let (branch, indent) = if last(end) {
+                    ("└──", "    ")
+                } else {
+                    ("├──", "│   ")
+                };
+                lines.push(format!("{} {}/", branch, dir));
+                for (j, path) in paths[i..end].iter().enumerate() {
+                    let child = if i + j + 1 == end {
+                        "└──"
+                    } else {
+                        "├──"
+                    };
+                    lines.push(format!("{}{} {}", indent, child, &path[dir.len() + 1..]));
+                }
+                i = end;
+            }
+            None => {
+                let branch = if last(i + 1) {
+                    "└──"
+                } else {
+                    "├──"
+                };
+                lines.push(format!("{} {}", branch, paths[i]));
+                i += 1;
+            }
+        }
+    }
+    lines
+}
+
+// ── Templates ──────────────────────────────────────────────
+
+fn manifest(name: &str, entry: &str) -> String {
+    format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+entry = "{entry}"
+
+[dependencies]
+"#
+    )
+}
+
+const GITIGNORE_TEMPLATE: &str = "\
+# Liva build output
+target/
+
+# Environment files with secrets
+.env
+*.env.local
+
+# OS files
+.DS_Store
+Thumbs.db
+";
+
+fn app_main(name: &str) -> String {
+    format!(
+        r#"// {name}
+// Run:  livac run src/main.liva
+// Test: livac test
+
+import {{ greet }} from "./greeting.liva"
+
+main() {{
+    print(greet("World"))
+}}
+"#
+    )
+}
+
+const APP_GREETING: &str = r#"// greeting.liva - code shared by main.liva and the tests
+
+greet(name: string): string => $"Hello, {name}!"
+"#;
+
+const APP_TEST: &str = r#"import { describe, test, expect } from "liva/test"
+import { greet } from "../src/greeting.liva"
+
+describe("greet", () => {
+    test("greets by name", () => {
+        expect(greet("World")).toBe("Hello, World!")
+        expect(greet("Liva")).toBe("Hello, Liva!")
+    })
+})
+"#;
+
+fn cli_main(name: &str) -> String {
+    format!(
+        r#"// {name} - command-line tool
+// Run:  livac run src/main.liva -- Ada --shout
+// Test: livac test
+
+import {{ usage, greeting }} from "./commands.liva"
+
+main() {{
+    let args = Sys.args()
+    if args.length < 2 {{
+        print(usage("{name}"))
+        Sys.exit(1)
+    }}
+
+    let name = args[1]
+    let shout = args.length > 2 && args[2] == "--shout"
+    print(greeting(name, shout))
+}}
+"#
+    )
+}
+
+const CLI_COMMANDS: &str = r#"// commands.liva - argument handling, kept free of I/O so it can be tested
+
+usage(program: string): string => $"Usage: {program} <name> [--shout]"
+
+greeting(name: string, shout: bool): string {
+    let message = $"Hello, {name}!"
+    if shout {
+        return message.toUpperCase()
+    }
+    return message
+}
+"#;
+
+const CLI_TEST: &str = r#"import { describe, test, expect } from "liva/test"
+import { usage, greeting } from "../src/commands.liva"
+
+describe("commands", () => {
+    test("usage names the program", () => {
+        expect(usage("greeter")).toBe("Usage: greeter <name> [--shout]")
+    })
+
+    test("greeting honours --shout", () => {
+        expect(greeting("Ada", false)).toBe("Hello, Ada!")
+        expect(greeting("Ada", true)).toBe("HELLO, ADA!")
+    })
+})
+"#;
+
+fn http_main(name: &str) -> String {
+    format!(
+        r#"// {name} - HTTP service
+// Run:  livac run src/main.liva   (then: curl localhost:3000/hello/Ada)
+// Test: livac test
+
+import {{ healthBody, helloBody }} from "./responses.liva"
+
+main() {{
+    let app = Server.create()
+
+    app.get("/health", (req) => {{
+        Response.json(healthBody())
+    }})
+
+    app.get("/hello/:name", (req) => {{
+        let name = req.params.get("name")
+        Response.json(helloBody(name))
+    }})
+
+    print("Listening on http://localhost:3000")
+    app.listen(3000)
+}}
+"#
+    )
+}
+
+const HTTP_RESPONSES: &str = r#"// responses.liva - response bodies, kept separate from the routes so they can be tested
+
+healthBody(): string => "{ \"status\": \"ok\" }"
+
+helloBody(name: string): string => "{ \"message\": \"Hello, " + name + "!\" }"
+"#;
+
+const HTTP_TEST: &str = r#"import { describe, test, expect } from "liva/test"
+import { healthBody, helloBody } from "../src/responses.liva"
+
+describe("responses", () => {
+    test("health reports ok", () => {
+        expect(healthBody()).toBe("{ \"status\": \"ok\" }")
+    })
+
+    test("hello greets by name", () => {
+        expect(helloBody("Ada")).toBe("{ \"message\": \"Hello, Ada!\" }")
+    })
+})
+"#;
+
+fn library_lib(name: &str) -> String {
+    format!(
+        r#"// {name} - library
+// Import from another project: import {{ clamp, slugify }} from "path/to/{name}/src/lib.liva"
+// Test: livac test
+
+clamp(value: number, lo: number, hi: number): number {{
+    if value < lo {{ return lo }}
+    if value > hi {{ return hi }}
+    return value
+}}
+
+slugify(text: string): string => text.trim().toLowerCase().replace(" ", "-")
+"#
+    )
+}
+
+const LIBRARY_TEST: &str = r#"import { describe, test, expect } from "liva/test"
+import { clamp, slugify } from "../src/lib.liva"
+
+describe("clamp", () => {
+    test("keeps values inside the range", () => {
+        expect(clamp(5, 0, 10)).toBe(5)
+        expect(clamp(-3, 0, 10)).toBe(0)
+        expect(clamp(42, 0, 10)).toBe(10)
+    })
+})
+
+describe("slugify", () => {
+    test("lowercases and joins words", () => {
+        expect(slugify("  Hello World ")).toBe("hello-world")
+    })
+})
+"#;
+
+fn tour_main(name: &str) -> String {
+    format!(
+        r#"// {name} - Liva Language Tour
+// A multi-file showcase of the language
+// Run:  livac run src/main.liva
+// Test: livac test
+
+import {{ add, square, isEven, factorial, divide, describeScore, greet }} from "./math.liva"
+import {{ Point, Pet }} from "./models.liva"
+
+const VERSION = "0.1.0"
+
+// -- Enums --
+
+enum Color {{ Red, Green, Blue }}
+
+enum Shape {{
+    Circle(radius: float),
+    Rect(width: float, height: float),
+    Dot
+}}
+
+// -- Enum helpers (switch expressions) --
+
+shapeInfo(s: Shape): string {{
+    let info = switch s {{
+        Shape.Circle(r)  => $"Circle r={{r}}",
+        Shape.Rect(w, h) => $"Rect {{w}}x{{h}}",
+        Shape.Dot        => "Dot"
+    }}
+    return info
+}}
+
+colorName(c: Color): string {{
+    let result = switch c {{
+        Color.Red   => "Red",
+        Color.Green => "Green",
+        Color.Blue  => "Blue"
+    }}
+    return result
+}}
+
+// -- Main --
+
+main() {{
+    print($"Welcome to {name}! (v{{VERSION}})")
+    print("")
+
+    // -- Variables & Types --
+    print("-- Variables & Types --")
+    let count = 0
+    let pi: float = 3.14159
+    let words: [string] = ["Liva", "compiles", "to", "Rust"]
+
+    print($"pi = {{pi}}")
+    let motto = words.join(" ")
+    print($"Motto: {{motto}}")
+    print("")
+
+    // -- Functions (imported from math.liva) --
+    print("-- Functions --")
+    print($"add(2, 3)    = {{add(2, 3)}}")
+    print($"square(7)    = {{square(7)}}")
+    print($"factorial(6) = {{factorial(6)}}")
+    print(greet("World"))
+    print(greet("Liva"))
+    print("")
+
+    // -- Error Handling --
+    print("-- Error Handling --")
+
+    // Error binding: two-variable pattern
+    let result, e1 = divide(10.0, 3.0)
+    if e1 {{
+        print($"Error: {{e1}}")
+    }} else {{
+        print($"10 / 3 = {{result}}")
+    }}
+
+    // Catching a failure
+    let bad, e2 = divide(5.0, 0.0)
+    if e2 {{
+        print($"Caught: {{e2}}")
+    }}
+    print("")
+
+    // -- Control Flow --
+    print("-- Control Flow --")
+    for i in 1..=5 {{
+        count = count + 1
+    }}
+    print($"Counted to {{count}}")
+
+    for word in words {{
+        print($"  -> {{word}}")
+    }}
+    print("")
+
+    // -- Arrays (functional pipeline) --
+    print("-- Arrays --")
+    let numbers = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+    let evens = numbers.filter(n => isEven(n))
+    let doubled = evens.map(n => n * 2)
+    let total = doubled.reduce(0, (acc, n) => acc + n)
+
+    print($"Numbers: {{numbers}}")
+    print($"Evens:   {{evens}}")
+    print($"Doubled: {{doubled}}")
+    print($"Sum:     {{total}}")
+
+    let hasLarge = numbers.some(n => n > 8)
+    let allPos = numbers.every(n => n > 0)
+    print($"Has > 8: {{hasLarge}}")
+    print($"All > 0: {{allPos}}")
+    print("")
+
+    // -- Maps --
+    print("-- Maps --")
+    let scores = Map {{ "Alice": 95, "Bob": 82, "Carol": 91 }}
+    let aliceScore = scores.get("Alice") or 0
+    print($"Alice: {{aliceScore}}")
+    scores.set("Dave", 78)
+
+    let hasDave = scores.has("Dave")
+    print($"Has Dave: {{hasDave}}")
+
+    for student, score in scores {{
+        print($"  {{student}}: {{describeScore(score)}}")
+    }}
+    print("")
+
+    // -- Sets --
+    print("-- Sets --")
+    let tags = Set {{ "rust", "fast", "safe" }}
+    tags.add("compiled")
+    let hasRust = tags.has("rust")
+    let hasGo = tags.has("go")
+    print($"has rust: {{hasRust}}")
+    print($"has go:   {{hasGo}}")
+    print("")
+
+    // -- Data Classes (imported from models.liva) --
+    print("-- Data Classes --")
+    let p1 = Point(10, 20)
+    let p2 = Point(10, 20)
+    let p3 = Point(99, 1)
+    print($"p1:        {{p1}}")
+    print($"p1 == p2:  {{p1 == p2}}")
+    print($"p1 == p3:  {{p1 == p3}}")
+    print("")
+
+    // -- Enums & Pattern Matching --
+    print("-- Enums & Pattern Matching --")
+    print($"Color: {{colorName(Color.Red)}}")
+    print($"  {{shapeInfo(Shape.Circle(5.0))}}")
+    print($"  {{shapeInfo(Shape.Rect(4.0, 6.0))}}")
+    print($"  {{shapeInfo(Shape.Dot)}}")
+    print("")
+
+    // -- Classes & Interfaces (imported from models.liva) --
+    print("-- Classes & Interfaces --")
+    let pet = Pet("Luna", "cat")
+    print(pet.describe())
+    print("")
+
+    // -- Grade Report (switch with ranges) --
+    print("-- Grade Report --")
+    let testScores = [95, 82, 73, 45]
+    for score in testScores {{
+        print($"  Score {{score}}: {{describeScore(score)}}")
+    }}
+    print("")
+
+    // -- Math stdlib --
+    print("-- Math --")
+    print($"sqrt(16) = {{Math.sqrt(16.0)}}")
+    print($"pow(2,3) = {{Math.pow(2.0, 3.0)}}")
+    print($"PI       = {{Math.PI}}")
+    print($"abs(-5)  = {{Math.abs(-5.0)}}")
+    print("")
+
+    print("Tour complete!")
+}}
+"#,
+        name = name
+    )
+}
+
+const TOUR_MATH: &str = r#"// math.liva - Pure functions
+
+// -- Arrow functions (implicit return) --
+
+add(a: number, b: number): number => a + b
+square(n: number): number => n * n
+isEven(n: number): bool => n % 2 == 0
+greet(name: string): string => $"Hello, {name}!"
+
+// -- Block function (multi-line, recursion) --
+
+factorial(n: number): number {
+    if n <= 1 { return 1 }
+    return n * factorial(n - 1)
+}
+
+// -- Fallible function (fail keyword) --
+
+divide(a: float, b: float): float {
+    if b == 0.0 { fail "Division by zero" }
+    return a / b
+}
+
+// -- Pattern matching: switch with ranges --
+
+describeScore(score: number): string {
+    let label = switch score {
+        90..=100 => "A - Excellent",
+        80..=89  => "B - Good",
+        70..=79  => "C - Average",
+        _        => "F - Needs work"
+    }
+    return label
+}
+"#;
+
+const TOUR_MODELS: &str = r#"// models.liva - Data classes, classes, interfaces
+
+// -- Data Class (auto constructor, Display, PartialEq) --
+
+Point { x: number; y: number }
+
+// -- Interface --
+
+Describable { describe(): string }
+
+// -- Class implementing interface --
+
+Pet : Describable {
+    name: string
+    kind: string
+    constructor(name: string, kind: string) {
+        this.name = name
+        this.kind = kind
+    }
+    describe(): string => $"{this.name} the {this.kind}"
+}
+"#;
+
+const TOUR_TEST: &str = r#"// Tests for the project
+// Run: livac test
+
+import { describe, test, expect } from "liva/test"
+
+// -- Functions under test --
+
+add(a: number, b: number): number => a + b
+square(n: number): number => n * n
+isEven(n: number): bool => n % 2 == 0
+
+factorial(n: number): number {
+    if n <= 1 { return 1 }
+    return n * factorial(n - 1)
+}
+
+describeScore(score: number): string {
+    let label = switch score {
+        90..=100 => "A - Excellent",
+        80..=89  => "B - Good",
+        70..=79  => "C - Average",
+        _        => "F - Needs work"
+    }
+    return label
+}
+
+// -- Tests --
+
+describe("Math", () => {
+    test("add works", () => {
+        expect(add(2, 3)).toBe(5)
+        expect(add(-1, 1)).toBe(0)
+        expect(add(0, 0)).toBe(0)
+    })
+
+    test("square computes squares", () => {
+        expect(square(7)).toBe(49)
+        expect(square(0)).toBe(0)
+    })
+
+    test("isEven detects parity", () => {
+        expect(isEven(4)).toBeTruthy()
+        expect(isEven(3)).toBeFalsy()
+        expect(isEven(0)).toBeTruthy()
+    })
+
+    test("factorial recurses correctly", () => {
+        expect(factorial(0)).toBe(1)
+        expect(factorial(1)).toBe(1)
+        expect(factorial(6)).toBe(720)
+    })
+})
+
+describe("Score Grading", () => {
+    test("describeScore grades correctly", () => {
+        expect(describeScore(95)).toBe("A - Excellent")
+        expect(describeScore(82)).toBe("B - Good")
+        expect(describeScore(73)).toBe("C - Average")
+        expect(describeScore(50)).toBe("F - Needs work")
+    })
+})
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const TEMPLATES: [Template; 5] = [
+        Template::App,
+        Template::CliApp,
+        Template::HttpService,
+        Template::Library,
+        Template::Tour,
+    ];
+
+    #[test]
+    fn test_every_template_has_manifest_sources_tests_and_gitignore() {
+        for template in TEMPLATES {
+            let files = template.files("demo");
+            let paths: Vec<&str> = files.iter().map(|(p, _)| *p).collect();
+            assert!(paths.contains(&"liva.toml"), "{:?}", template);
+            assert!(paths.contains(&".gitignore"), "{:?}", template);
+            assert!(paths.contains(&template.entry()), "{:?}", template);
+            assert!(paths
+                .iter()
+                .any(|p| p.starts_with("tests/") && p.ends_with(".test.liva")));
+
+            let manifest = &files[0].1;
+            assert!(manifest.contains("name = \"demo\""));
+            assert!(manifest.contains(&format!("entry = \"{}\"", template.entry())));
+        }
+    }
+
+    #[test]
+    fn test_generated_sources_parse() {
+        for template in TEMPLATES {
+            for (path, contents) in template.files("demo") {
+                if path.ends_with(".liva") {
+                    let report = livac::check_source(&contents, path);
+                    assert!(
+                        report.diagnostics.is_empty(),
+                        "{:?} {}: {:?}",
+                        template,
+                        path,
+                        report.diagnostics
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_project_refuses_to_overwrite() {
+        let dir = tempdir().unwrap();
+        write_project(dir.path(), "demo", Template::App).unwrap();
+        assert!(dir.path().join("src/main.liva").exists());
+        assert!(dir.path().join("tests/greeting.test.liva").exists());
+
+        let err = write_project(dir.path(), "demo", Template::App).unwrap_err();
+        assert!(err.to_string().contains("liva.toml already exists"));
+    }
+
+    #[test]
+    fn test_init_in_a_git_checkout_extends_its_gitignore() {
+        let dir = tempdir().unwrap();
+        let gitignore = dir.path().join(".gitignore");
+        std::fs::write(&gitignore, "node_modules/\ntarget/").unwrap();
+        write_project(dir.path(), "demo", Template::App).unwrap();
+
+        let merged = std::fs::read_to_string(&gitignore).unwrap();
+        assert!(merged.starts_with("node_modules/\ntarget/\n"));
+        assert!(merged.contains("\n.env\n"));
+        assert_eq!(merged.matches("target/").count(), 1);
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("my-app_2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("a/b").is_err());
+        assert!(validate_name("bad name").is_err());
+    }
+
+    #[test]
+    fn test_tree_lines() {
+        let lines = tree_lines(&["src/main.liva", "tests/main.test.liva", "liva.toml"]);
+        assert_eq!(
+            lines,
+            vec![
+                "├── src/",
+                "│   └── main.liva",
+                "├── tests/",
+                "│   └── main.test.liva",
+                "└── liva.toml",
+            ]
+        );
+    }
+}
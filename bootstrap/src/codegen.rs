@@ -1,7 +1,14 @@
 use crate::ast::*;
+use crate::builtins;
+use crate::consteval::ConstValue;
 use crate::desugaring::DesugarContext;
+use crate::diagnostics::DiagnosticSink;
 use crate::error::{CompilerError, Result, SemanticErrorInfo};
+use crate::error_codes::E0606_UNUSED_TASK;
+use crate::parser::grow_stack;
+use crate::rust_ast;
 use crate::traits::TraitRegistry;
+use crate::IdentifierMapping;
 use std::collections::HashSet;
 use std::fmt::Write;
 
@@ -46,6 +53,71 @@ struct TestHookScope {
     depth: usize,
 }
 
+/// Whether a number is an integer or a float, for `**`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Numeric {
+    Int,
+    Float,
+}
+
+/// What `numeric_kind` found, as `--explain-codegen` words it
+fn numeric_name(kind: Option<Numeric>) -> &'static str {
+    match kind {
+        Some(Numeric::Int) => "an int",
+        Some(Numeric::Float) => "a float",
+        None => "a value of unknown type",
+    }
+}
+
+/// The declared type of each parameter of `func`. A type parameter of the
+/// function counts as undeclared, since the call site decides it.
+fn declared_param_types(func: &FunctionDecl) -> Vec<Option<TypeRef>> {
+    let is_type_param = |name: &String| func.type_params.iter().any(|tp| &tp.name == name);
+    func.params
+        .iter()
+        .map(|p| {
+            p.type_ref
+                .clone()
+                .filter(|ty| !matches!(ty, TypeRef::Simple(name) if is_type_param(name)))
+        })
+        .collect()
+}
+
+/// How the generated `fn main` turns the user's `main` into an exit status
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MainExit {
+    /// `main(): int` - the returned value is the exit code, or 255 when it
+    /// is outside 0-255
+    Code,
+    /// `main` can fail - exit 1 after printing the error, else 0
+    Fallible,
+    /// `main(): int` that can fail
+    FallibleCode,
+}
+
+impl MainExit {
+    /// From the Rust return type of `main`; None when it needs no wrapper
+    fn from_return_type(return_type: &str) -> Option<Self> {
+        let ty = return_type.trim_start_matches(" -> ");
+        let is_code = |ty: &str| {
+            let numeric = [
+                "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64",
+            ];
+            numeric.contains(&ty) || matches!(ty, "usize" | "f32" | "f64")
+        };
+        let fallible = ty
+            .strip_prefix("Result<")
+            .and_then(|rest| rest.strip_suffix(", liva_rt::Error>"));
+        match fallible {
+            Some("()") => Some(MainExit::Fallible),
+            Some(inner) if is_code(inner) => Some(MainExit::FallibleCode),
+            Some(_) => None,
+            None if is_code(ty) => Some(MainExit::Code),
+            None => None,
+        }
+    }
+}
+
 pub struct CodeGenerator {
     pub(crate) output: String,
     indent_level: usize,
@@ -62,6 +134,10 @@ pub struct CodeGenerator {
     /// not clone the slot first (which would silently lose `&mut self`
     /// mutations like `Particle::step`). Single-shot.
     suppress_index_elem_clone: bool,
+    /// Set by the method-call path when its method is an explicit JSON
+    /// conversion (`item.price.asFloat()`), so the receiver skips the
+    /// name-based conversion it would otherwise get. Single-shot.
+    explicit_json_conversion: bool,
     in_fallible_function: bool,
     in_optional_function: bool, // BUG-006: Track if inside function returning T?
     in_test_block: bool,
@@ -73,6 +149,10 @@ pub struct CodeGenerator {
     array_vars: std::collections::HashSet<String>, // Track which variables are arrays
     map_vars: std::collections::HashSet<String>,   // Track which variables are Map<K,V>
     set_vars: std::collections::HashSet<String>,   // Track which variables are Set<T>
+    frozen_vars: std::collections::HashSet<String>, // Track which variables are liva_rt::Frozen<T>
+    lazy_values: std::collections::HashMap<String, bool>, // `lazy` value -> whether its initializer can fail
+    const_values: std::collections::HashMap<String, ConstValue>, // `const` -> its compile-time value
+    init_calls: Vec<String>, // `init` functions `main` calls first, dependencies before importers
     json_value_vars: std::collections::HashSet<String>, // Track which variables are JsonValue
     string_vars: std::collections::HashSet<String>, // Track which variables are strings
     float_vars: std::collections::HashSet<String>, // Track which variables are floats (B32)
@@ -106,6 +186,8 @@ pub struct CodeGenerator {
     type_aliases: std::collections::HashMap<String, (Vec<TypeParameter>, TypeRef)>,
     // --- Union types (for enum generation)
     union_types: std::collections::HashSet<Vec<String>>, // Track all union types used: [(i32, String), ...]
+    union_vars: std::collections::HashMap<String, Vec<TypeRef>>, // Union-typed variables -> member types
+    guard_narrowed_vars: Vec<String>, // Option vars unwrapped by `if x == null { return }` in the open blocks
     // --- Phase 2: Lazy await/join tracking
     pending_tasks: std::collections::HashMap<String, TaskInfo>, // Variables that hold unawaited Tasks
     // --- Phase 3: Error binding variables (Option<String> type)
@@ -132,10 +214,15 @@ pub struct CodeGenerator {
     // --- Phase 6: Interface method signatures (for type inference)
     interface_methods:
         std::collections::HashMap<String, std::collections::HashMap<String, TypeRef>>, // interface_name -> (method_name -> return_type)
+    // --- Abstract classes, with the path that reaches them from the module
+    // being generated (`crate::shapes::`, empty for its own)
+    abstract_classes: std::collections::HashMap<String, ClassDecl>,
+    abstract_class_paths: std::collections::HashMap<String, String>,
     // --- Module aliases for wildcard imports (alias -> actual_module_name)
     module_aliases: std::collections::HashMap<String, String>,
     // --- Current function return type (for casting division results)
     current_return_type: Option<String>,
+    current_return_union: Option<Vec<TypeRef>>, // Members when the current function returns a union
     // --- Enum metadata (enum_name -> variant_names with field info)
     enum_names: std::collections::HashSet<String>,
     enum_variants:
@@ -157,10 +244,15 @@ pub struct CodeGenerator {
     // --- Error trace context
     current_function_name: String, // Current function/method name for error traces
     source_filename: String,       // Source filename for error traces
+    /// Warnings found while generating, handed to the caller at the end
+    diagnostics: DiagnosticSink,
     /// Hoisted `use` statements extracted from `rust { }` blocks (emitted at top of file)
     rust_block_uses: Vec<String>,
     /// Counter for generating unique defer guard variable names
     defer_counter: usize,
+    /// `liva_rt::TaskScope` variables of the enclosing `scope { }` blocks,
+    /// innermost last; tasks started while one is open belong to it
+    task_scopes: Vec<String>,
     /// SH-002: When true, we're inside a constructor body — `this.field` maps to local vars
     in_constructor: bool,
     /// B148: Set of field names that have been assigned in the constructor so far,
@@ -171,8 +263,15 @@ pub struct CodeGenerator {
     /// GAP-007: Track function param types to wrap Lambda args in Box::new when the
     /// expected type is a function type (Box<dyn Fn(...)>).
     function_param_types: std::collections::HashMap<String, Vec<Option<TypeRef>>>,
+    /// Declared return types of functions, so calls returning a union stay union values
+    function_return_types: std::collections::HashMap<String, TypeRef>,
     /// B109: Track used test function names to avoid collisions
     used_test_names: std::collections::HashMap<String, usize>,
+    /// Liva file named by `--coverage` probes and `--debug` line markers
+    /// (the module's own path in multi-file builds)
+    line_hook_file: String,
+    /// Module whose inline decisions apply; empty for a single-file build
+    module_path: std::path::PathBuf,
 }
 
 impl CodeGenerator {
@@ -189,6 +288,7 @@ impl CodeGenerator {
             mut_self_methods: HashSet::new(),
             in_assignment_target: false,
             suppress_index_elem_clone: false,
+            explicit_json_conversion: false,
             in_fallible_function: false,
             in_optional_function: false,
             in_test_block: false,
@@ -198,6 +298,10 @@ impl CodeGenerator {
             array_vars: std::collections::HashSet::new(),
             map_vars: std::collections::HashSet::new(),
             set_vars: std::collections::HashSet::new(),
+            frozen_vars: std::collections::HashSet::new(),
+            lazy_values: std::collections::HashMap::new(),
+            init_calls: Vec::new(),
+            const_values: std::collections::HashMap::new(),
             json_value_vars: std::collections::HashSet::new(),
             string_vars: std::collections::HashSet::new(),
             float_vars: std::collections::HashSet::new(),
@@ -226,6 +330,8 @@ impl CodeGenerator {
             suppress_map_get_unwrap: false,
             type_aliases: std::collections::HashMap::new(),
             union_types: std::collections::HashSet::new(),
+            union_vars: std::collections::HashMap::new(),
+            guard_narrowed_vars: Vec::new(),
             pending_tasks: std::collections::HashMap::new(),
             error_binding_vars: std::collections::HashSet::new(),
             narrowed_error_binding_vars: std::collections::HashSet::new(),
@@ -243,8 +349,11 @@ impl CodeGenerator {
             trait_registry: TraitRegistry::new(),
             async_functions: async_funcs,
             interface_methods: std::collections::HashMap::new(),
+            abstract_classes: std::collections::HashMap::new(),
+            abstract_class_paths: std::collections::HashMap::new(),
             module_aliases: std::collections::HashMap::new(),
             current_return_type: None,
+            current_return_union: None,
             test_hooks_stack: Vec::new(),
             enum_names: std::collections::HashSet::new(),
             enum_variants: std::collections::HashMap::new(),
@@ -253,13 +362,18 @@ impl CodeGenerator {
             serde_classes: std::collections::HashSet::new(),
             float_literal_suffix: "f64".to_string(),
             current_function_name: String::new(),
+            line_hook_file: source_filename.clone(),
+            module_path: std::path::PathBuf::new(),
             source_filename,
+            diagnostics: DiagnosticSink::new(),
             rust_block_uses: Vec::new(),
             defer_counter: 0,
+            task_scopes: Vec::new(),
             in_constructor: false,
             constructor_assigned_fields: std::collections::HashSet::new(),
             function_defaults: std::collections::HashMap::new(),
             function_param_types: std::collections::HashMap::new(),
+            function_return_types: std::collections::HashMap::new(),
             used_test_names: std::collections::HashMap::new(),
         }
     }
@@ -404,75 +518,40 @@ impl CodeGenerator {
             Expr::Call(call) => {
                 // Check if callee is HTTP method call (async HTTP.get, etc.)
                 if let Expr::MethodCall(mc) = call.callee.as_ref() {
-                    if let Expr::Identifier(obj) = mc.object.as_ref() {
-                        return (obj == "HTTP" || obj == "Http")
-                            && matches!(mc.method.as_str(), "get" | "post" | "put" | "delete");
-                    }
-                }
-                false
-            }
-            Expr::MethodCall(mc) => {
-                if let Expr::Identifier(obj) = mc.object.as_ref() {
-                    return (obj == "HTTP" || obj == "Http")
-                        && matches!(mc.method.as_str(), "get" | "post" | "put" | "delete");
+                    return Self::is_async_builtin(mc);
                 }
                 false
             }
+            Expr::MethodCall(mc) => Self::is_async_builtin(mc),
             _ => false,
         }
     }
 
-    /// Check if expression is a File call (read/write/append/delete) or Dir.list call
+    /// Check if expression is a fallible namespace call (`File.read`,
+    /// `Dir.list`, `DB.query`, ...) that returns `(Option<T>, String)`.
+    /// JSON and HTTP calls are fallible too but lowered separately, and
+    /// `Date.parseIso` has no default value to return on error.
     fn is_file_call(&self, expr: &Expr) -> bool {
         match expr {
-            Expr::MethodCall(mc) => {
-                if let Expr::Identifier(obj) = mc.object.as_ref() {
-                    if obj == "File" {
-                        return matches!(
-                            mc.method.as_str(),
-                            "read"
-                                | "write"
-                                | "append"
-                                | "delete"
-                                | "copy"
-                                | "move"
-                                | "size"
-                                | "readLines"
-                                | "writeLines"
-                        );
-                    }
-                    if obj == "Dir" {
-                        return matches!(
-                            mc.method.as_str(),
-                            "list" | "create" | "delete" | "listRecursive" | "walk"
-                        );
-                    }
-                    if obj == "Config" {
-                        return matches!(mc.method.as_str(), "get" | "getInt" | "getBool" | "load");
-                    }
-                    if obj == "Regex" {
-                        return matches!(mc.method.as_str(), "match");
-                    }
-                    if obj == "Date" {
-                        return matches!(mc.method.as_str(), "parse");
-                    }
-                    if obj == "CSV" {
-                        return matches!(
-                            mc.method.as_str(),
-                            "read" | "write" | "readTable" | "writeTable"
-                        );
-                    }
-                    if obj == "Process" {
-                        return matches!(mc.method.as_str(), "exec" | "spawn");
-                    }
-                    if obj == "Crypto" {
-                        return matches!(mc.method.as_str(), "base64Decode");
-                    }
-                    if obj == "DB" {
-                        return matches!(mc.method.as_str(), "open" | "exec" | "query");
-                    }
+            Expr::MethodCall(mc) => match mc.object.as_ref() {
+                Expr::Identifier(obj) => {
+                    let excluded = matches!(obj.as_str(), "JSON" | "HTTP" | "Http")
+                        || (obj == "Date" && mc.method == "parseIso");
+                    !excluded
+                        && builtins::lookup_method(obj, &mc.method)
+                            .is_some_and(|builtin| builtin.fallible)
                 }
-                false
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// `HTTP.get(url)` and the other builtins that run as tasks
+    fn is_async_builtin(mc: &MethodCallExpr) -> bool {
+        match mc.object.as_ref() {
+            Expr::Identifier(obj) => {
+                builtins::lookup_method(obj, &mc.method).is_some_and(|builtin| builtin.is_async)
             }
             _ => false,
         }
@@ -505,10 +584,7 @@ impl CodeGenerator {
             Expr::Call(call) => {
                 // Check if callee is HTTP method call
                 if let Expr::MethodCall(mc) = call.callee.as_ref() {
-                    if let Expr::Identifier(obj) = mc.object.as_ref() {
-                        return (obj == "HTTP" || obj == "Http")
-                            && matches!(mc.method.as_str(), "get" | "post" | "put" | "delete");
-                    }
+                    return Self::is_async_builtin(mc);
                 }
                 false
             }
@@ -667,25 +743,14 @@ impl CodeGenerator {
                 }
                 false
             }
-            Stmt::If(if_stmt) => {
-                let then_returns = match &if_stmt.then_branch {
-                    IfBody::Block(b) => {
-                        self.block_returns_self_field_of_type(b, type_param, generic_fields)
-                    }
-                    IfBody::Stmt(s) => {
-                        self.stmt_returns_self_field_of_type(s, type_param, generic_fields)
-                    }
-                };
-                let else_returns = if_stmt.else_branch.as_ref().map_or(false, |eb| match eb {
-                    IfBody::Block(b) => {
-                        self.block_returns_self_field_of_type(b, type_param, generic_fields)
-                    }
-                    IfBody::Stmt(s) => {
-                        self.stmt_returns_self_field_of_type(s, type_param, generic_fields)
-                    }
-                });
-                then_returns || else_returns
-            }
+            Stmt::If(if_stmt) => if_stmt.bodies().any(|body| match body {
+                IfBody::Block(b) => {
+                    self.block_returns_self_field_of_type(b, type_param, generic_fields)
+                }
+                IfBody::Stmt(s) => {
+                    self.stmt_returns_self_field_of_type(s, type_param, generic_fields)
+                }
+            }),
             _ => false,
         }
     }
@@ -766,25 +831,17 @@ impl CodeGenerator {
                 self.expr_uses_type_in_template(&var_decl.init, type_param, generic_fields)
             }
             Stmt::If(if_stmt) => {
-                let cond =
-                    self.expr_uses_type_in_template(&if_stmt.condition, type_param, generic_fields);
-                let then_branch = match &if_stmt.then_branch {
-                    IfBody::Block(b) => {
-                        self.block_uses_type_in_template(b, type_param, generic_fields)
-                    }
-                    IfBody::Stmt(s) => {
-                        self.stmt_uses_type_in_template(s, type_param, generic_fields)
-                    }
-                };
-                let else_branch = if_stmt.else_branch.as_ref().map_or(false, |eb| match eb {
+                let cond = if_stmt.branches().any(|(condition, _)| {
+                    self.expr_uses_type_in_template(condition, type_param, generic_fields)
+                });
+                cond || if_stmt.bodies().any(|body| match body {
                     IfBody::Block(b) => {
                         self.block_uses_type_in_template(b, type_param, generic_fields)
                     }
                     IfBody::Stmt(s) => {
                         self.stmt_uses_type_in_template(s, type_param, generic_fields)
                     }
-                });
-                cond || then_branch || else_branch
+                })
             }
             Stmt::While(while_stmt) => {
                 self.expr_uses_type_in_template(&while_stmt.condition, type_param, generic_fields)
@@ -977,19 +1034,11 @@ impl CodeGenerator {
                 self.scan_expr_for_stringify(&assign.value, var_class, class_names);
             }
             Stmt::If(if_stmt) => {
-                self.scan_expr_for_stringify(&if_stmt.condition, var_class, class_names);
-                match &if_stmt.then_branch {
-                    IfBody::Block(block) => {
-                        for s in &block.stmts {
-                            self.scan_expr_for_stringify_in_stmt(s, var_class, class_names);
-                        }
-                    }
-                    IfBody::Stmt(s) => {
-                        self.scan_expr_for_stringify_in_stmt(s, var_class, class_names);
-                    }
+                for (condition, _) in if_stmt.branches() {
+                    self.scan_expr_for_stringify(condition, var_class, class_names);
                 }
-                if let Some(else_branch) = &if_stmt.else_branch {
-                    match else_branch {
+                for body in if_stmt.bodies() {
+                    match body {
                         IfBody::Block(block) => {
                             for s in &block.stmts {
                                 self.scan_expr_for_stringify_in_stmt(s, var_class, class_names);
@@ -1123,16 +1172,14 @@ impl CodeGenerator {
             Stmt::VarDecl(var) => self.expr_calls_mut_self_method(&var.init),
             Stmt::Assign(assign) => self.expr_calls_mut_self_method(&assign.value),
             Stmt::If(if_stmt) => {
-                let cond_calls = self.expr_calls_mut_self_method(&if_stmt.condition);
-                let then_calls = match &if_stmt.then_branch {
-                    IfBody::Block(b) => self.block_calls_mut_self_method(b),
-                    IfBody::Stmt(s) => self.stmt_calls_mut_self_method(s),
-                };
-                let else_calls = if_stmt.else_branch.as_ref().map_or(false, |eb| match eb {
-                    IfBody::Block(b) => self.block_calls_mut_self_method(b),
-                    IfBody::Stmt(s) => self.stmt_calls_mut_self_method(s),
-                });
-                cond_calls || then_calls || else_calls
+                let cond_calls = if_stmt
+                    .branches()
+                    .any(|(condition, _)| self.expr_calls_mut_self_method(condition));
+                cond_calls
+                    || if_stmt.bodies().any(|body| match body {
+                        IfBody::Block(b) => self.block_calls_mut_self_method(b),
+                        IfBody::Stmt(s) => self.stmt_calls_mut_self_method(s),
+                    })
             }
             Stmt::While(w) => {
                 self.expr_calls_mut_self_method(&w.condition)
@@ -1153,6 +1200,7 @@ impl CodeGenerator {
                     })
             }
             Stmt::Defer(defer_stmt) => self.stmt_calls_mut_self_method(&defer_stmt.body),
+            Stmt::Scope(scope) => self.block_calls_mut_self_method(&scope.body),
             _ => false,
         }
     }
@@ -1200,6 +1248,17 @@ impl CodeGenerator {
                         SwitchBody::Expr(e) => self.expr_calls_mut_self_method(&*e),
                     })
             }
+            Expr::If(if_expr) => {
+                self.expr_calls_mut_self_method(&if_expr.condition)
+                    || [&if_expr.then_branch, &if_expr.else_branch]
+                        .into_iter()
+                        .any(|branch| match branch {
+                            SwitchBody::Block(b) => {
+                                b.iter().any(|s| self.stmt_calls_mut_self_method(s))
+                            }
+                            SwitchBody::Expr(e) => self.expr_calls_mut_self_method(e),
+                        })
+            }
             Expr::ArrayLiteral(elems) => elems.iter().any(|e| self.expr_calls_mut_self_method(e)),
             Expr::StringTemplate { parts } => parts.iter().any(|p| {
                 if let StringTemplatePart::Expr(e) = p {
@@ -1240,16 +1299,14 @@ impl CodeGenerator {
                 self.expr_modifies_self(&assign_stmt.value)
             }
             Stmt::If(if_stmt) => {
-                let cond_modifies = self.expr_modifies_self(&if_stmt.condition);
-                let then_modifies = match &if_stmt.then_branch {
-                    IfBody::Block(b) => self.block_modifies_self(b),
-                    IfBody::Stmt(s) => self.stmt_modifies_self(s),
-                };
-                let else_modifies = if_stmt.else_branch.as_ref().map_or(false, |eb| match eb {
-                    IfBody::Block(b) => self.block_modifies_self(b),
-                    IfBody::Stmt(s) => self.stmt_modifies_self(s),
-                });
-                cond_modifies || then_modifies || else_modifies
+                let cond_modifies = if_stmt
+                    .branches()
+                    .any(|(condition, _)| self.expr_modifies_self(condition));
+                cond_modifies
+                    || if_stmt.bodies().any(|body| match body {
+                        IfBody::Block(b) => self.block_modifies_self(b),
+                        IfBody::Stmt(s) => self.stmt_modifies_self(s),
+                    })
             }
             Stmt::While(while_stmt) => {
                 self.expr_modifies_self(&while_stmt.condition)
@@ -1287,6 +1344,7 @@ impl CodeGenerator {
                 self.block_modifies_self(&tc.try_block) || self.block_modifies_self(&tc.catch_block)
             }
             Stmt::Block(block) => self.block_modifies_self(block),
+            Stmt::Scope(scope) => self.block_modifies_self(&scope.body),
             _ => false,
         }
     }
@@ -1302,6 +1360,7 @@ impl CodeGenerator {
                     "push"
                         | "pop"
                         | "remove"
+                        | "removeAt"
                         | "clear"
                         | "insert"
                         | "sort"
@@ -1365,6 +1424,17 @@ impl CodeGenerator {
                 }
                 false
             }
+            Expr::If(if_expr) => {
+                self.expr_modifies_self(&if_expr.condition)
+                    || [&if_expr.then_branch, &if_expr.else_branch]
+                        .into_iter()
+                        .any(|branch| match branch {
+                            SwitchBody::Expr(e) => self.expr_modifies_self(e),
+                            SwitchBody::Block(stmts) => {
+                                stmts.iter().any(|s| self.stmt_modifies_self(s))
+                            }
+                        })
+            }
             _ => false,
         }
     }
@@ -1482,12 +1552,8 @@ impl CodeGenerator {
             }
             Stmt::If(if_stmt) => {
                 // Recurse into branches
-                match &if_stmt.then_branch {
-                    IfBody::Block(b) => self.collect_mutated_vars_in_block(b, mutated),
-                    IfBody::Stmt(s) => self.collect_mutated_vars_in_stmt(s, mutated),
-                }
-                if let Some(else_branch) = &if_stmt.else_branch {
-                    match else_branch {
+                for body in if_stmt.bodies() {
+                    match body {
                         IfBody::Block(b) => self.collect_mutated_vars_in_block(b, mutated),
                         IfBody::Stmt(s) => self.collect_mutated_vars_in_stmt(s, mutated),
                     }
@@ -1518,6 +1584,15 @@ impl CodeGenerator {
             Stmt::Defer(defer_stmt) => {
                 self.collect_mutated_vars_in_stmt(&defer_stmt.body, mutated);
             }
+            Stmt::Scope(scope) => {
+                self.collect_mutated_vars_in_block(&scope.body, mutated);
+            }
+            // A closure assigning to a captured local needs that local `mut`
+            Stmt::Function(func) if !func.captures.is_empty() => {
+                if let Some(body) = &func.body {
+                    self.collect_mutated_vars_in_block(body, mutated);
+                }
+            }
             Stmt::Expr(expr_stmt) => {
                 self.collect_mutated_vars_in_expr(&expr_stmt.expr, mutated);
             }
@@ -1547,6 +1622,7 @@ impl CodeGenerator {
                     "push"
                         | "pop"
                         | "remove"
+                        | "removeAt"
                         | "clear"
                         | "insert"
                         | "sort"
@@ -1627,7 +1703,11 @@ impl CodeGenerator {
                     || mc.method == "take"
                     || mc.method == "drop"
                     || mc.method == "chunks"
+                    || mc.method == "windows"
+                    || mc.method == "enumerate"
                     || mc.method == "sortBy"
+                    || mc.method == "minBy"
+                    || mc.method == "maxBy"
                     || mc.method == "groupBy"
                     || mc.method == "reversed"
                     || mc.method == "sum"
@@ -1688,6 +1768,20 @@ impl CodeGenerator {
                     }
                 }
             }
+            Expr::If(if_expr) => {
+                for branch in [&if_expr.then_branch, &if_expr.else_branch] {
+                    match branch {
+                        SwitchBody::Block(stmts) => {
+                            for s in stmts {
+                                self.collect_mutated_vars_in_stmt(s, mutated);
+                            }
+                        }
+                        SwitchBody::Expr(e) => {
+                            self.collect_mutated_vars_in_expr(e, mutated);
+                        }
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -1719,10 +1813,12 @@ impl CodeGenerator {
             if let Some(arg) = method_call.args.first() {
                 self.generate_expr(arg)?;
             } else {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3001",
-                    "JSON.parse requires a string argument",
-                    "JSON.parse must be called with a JSON string",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3001",
+                        "JSON.parse requires a string argument",
+                        "JSON.parse must be called with a JSON string",
+                    ),
                 )));
             }
         }
@@ -1755,7 +1851,52 @@ impl CodeGenerator {
         self.output.push('\n');
     }
 
+    /// `///` lines carrying a declaration's doc comment over to Rust
+    fn write_doc_comment(&mut self, doc: Option<&str>) {
+        for line in doc.into_iter().flat_map(str::lines) {
+            self.write_indent();
+            if line.is_empty() {
+                self.output.push_str("///\n");
+            } else {
+                writeln!(self.output, "/// {}", line).unwrap();
+            }
+        }
+    }
+
+    /// `//!` lines for the file's `//!` comments; must come before any item
+    fn write_module_doc(&mut self, program: &Program) {
+        let lines: Vec<String> = program
+            .comments
+            .iter()
+            .filter(|comment| comment.kind == crate::lexer::CommentKind::InnerDoc)
+            .flat_map(|comment| comment.lines())
+            .collect();
+        for line in lines {
+            if line.is_empty() {
+                self.output.push_str("//!\n");
+            } else {
+                writeln!(self.output, "//! {}", line).unwrap();
+            }
+        }
+    }
+
+    /// Crate-level `#![allow]` for warnings the generated code would raise
+    fn write_crate_allows(&mut self) {
+        match self.ctx.identifier_mapping {
+            IdentifierMapping::Snake => self.writeln("#![allow(unused_parens, unused_mut)]"),
+            // camelCase functions and variables are what was asked for
+            IdentifierMapping::Preserve => {
+                self.writeln("#![allow(unused_parens, unused_mut, non_snake_case)]")
+            }
+        }
+    }
+
     fn generate_program(&mut self, program: &Program) -> Result<()> {
+        // Union enums are only known after the items are generated; they go here,
+        // after the crate doc and `#![allow]` attributes already written
+        let items_start = self.output.len();
+        let file_hooks = self.scan_file_for_hooks(&program.items);
+        self.test_hooks_stack.extend(file_hooks);
         // Generate use statements for Rust crates
         // Rust identifiers cannot contain hyphens, so convert them to underscores
         for dep in &self.ctx.rust_crates {
@@ -1771,7 +1912,7 @@ impl CodeGenerator {
             self.output.push('\n');
         }
 
-        // Build class metadata maps
+        // Build class metadata maps from the symbol table
         // Note: class_fields/class_optional_fields/class_array_field_types are NOT
         // fully cleared here because they may contain pre-populated data from imported modules
         // (generate_entry_point/generate_module_code) — BUG-003 fix
@@ -1781,86 +1922,15 @@ impl CodeGenerator {
         // pre-populated data from imported modules (generate_entry_point/generate_module_code)
         for item in &program.items {
             if let TopLevel::Class(cls) = item {
-                let mut fields = std::collections::HashSet::new();
-                let mut optional_fields = std::collections::HashSet::new();
-                let mut array_field_types = std::collections::HashMap::new();
-                let mut map_value_types: std::collections::HashMap<String, String> =
-                    std::collections::HashMap::new();
-                let mut constructor_optionals: Vec<bool> = Vec::new();
-                for m in &cls.members {
-                    if let Member::Field(f) = m {
-                        fields.insert(f.name.clone());
-                        let is_opt =
-                            f.is_optional || matches!(&f.type_ref, Some(TypeRef::Optional(_)));
-                        if is_opt {
-                            optional_fields.insert(f.name.clone());
-                        }
-                        constructor_optionals.push(is_opt);
-                        // Track array field types: fieldName -> elementType
-                        if let Some(TypeRef::Array(element_type)) = &f.type_ref {
-                            if let TypeRef::Simple(type_name) = element_type.as_ref() {
-                                array_field_types.insert(f.name.clone(), type_name.clone());
-                            }
-                        }
-                        // B125: Track Map field value types: fieldName -> valueType
-                        // Encoding: "TypeName" for Simple, "[T]" for Array<T>, "{}" for Map (B134).
-                        if let Some(TypeRef::Map(_, value_type)) = &f.type_ref {
-                            match value_type.as_ref() {
-                                TypeRef::Simple(type_name) => {
-                                    map_value_types.insert(f.name.clone(), type_name.clone());
-                                }
-                                TypeRef::Array(inner) => {
-                                    let inner_name = if let TypeRef::Simple(n) = inner.as_ref() {
-                                        n.clone()
-                                    } else {
-                                        "_".to_string()
-                                    };
-                                    map_value_types
-                                        .insert(f.name.clone(), format!("[{}]", inner_name));
-                                }
-                                TypeRef::Map(_, _) => {
-                                    map_value_types.insert(f.name.clone(), "{}".to_string());
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                }
-                self.class_fields.insert(cls.name.clone(), fields);
-                self.class_optional_fields
-                    .insert(cls.name.clone(), optional_fields);
-                self.class_constructor_optionals
-                    .insert(cls.name.clone(), constructor_optionals);
-                if !array_field_types.is_empty() {
-                    self.class_array_field_types
-                        .insert(cls.name.clone(), array_field_types);
-                }
-                if !map_value_types.is_empty() {
-                    self.class_map_value_types
-                        .insert(cls.name.clone(), map_value_types);
-                }
-
-                // B100 fix: Scan class methods for return types (string, [T])
-                for m in &cls.members {
-                    if let Member::Method(method) = m {
-                        if let Some(ret_type) = &method.return_type {
-                            if matches!(ret_type, TypeRef::Simple(name) if name == "string") {
-                                self.string_returning_methods.insert(method.name.clone());
-                            }
-                            if let TypeRef::Array(elem) = ret_type {
-                                let elem_type = match elem.as_ref() {
-                                    TypeRef::Simple(name) => name.clone(),
-                                    _ => String::new(),
-                                };
-                                self.array_returning_methods
-                                    .insert(method.name.clone(), elem_type);
-                            }
-                        }
-                    }
+                if let Some(class) = self.register_class_symbols(&cls.name) {
+                    self.class_constructor_optionals
+                        .insert(cls.name.clone(), class.constructor_optionals());
                 }
             }
         }
 
+        self.register_abstract_classes(&program.items, "");
+
         // Build interface method signatures map (for type inference in implementing classes)
         // Note: Due to parser design, interfaces may be parsed as Class without constructor
         self.interface_methods.clear();
@@ -1890,8 +1960,9 @@ impl CodeGenerator {
                 let has_method_bodies = class.members.iter().any(|m| {
                     matches!(m, Member::Method(method) if method.body.is_some() || method.expr_body.is_some())
                 });
-                // If no constructor and no method bodies, it's an interface
-                if !has_constructor && !has_method_bodies {
+                // If no constructor and no method bodies, it's an interface. An
+                // abstract class declares return types for its implementations too.
+                if class.is_abstract || (!has_constructor && !has_method_bodies) {
                     let mut methods: std::collections::HashMap<String, TypeRef> =
                         std::collections::HashMap::new();
                     for member in &class.members {
@@ -1970,7 +2041,36 @@ impl CodeGenerator {
         if std::env::var("LIVA_DEBUG").is_ok() {
             println!("DEBUG: Including liva_rt module");
         }
-        self.output.push_str(include_str!("liva_rt_template.rs.in"));
+        let runtime = include_str!("liva_rt_template.rs.in");
+        // The helpers only some programs call go inside `mod liva_rt` when used
+        let helpers = [
+            (
+                self.ctx.has_task_scope,
+                include_str!("liva_rt_task_scope.rs.in"),
+            ),
+            (self.ctx.has_power, include_str!("liva_rt_power.rs.in")),
+            (
+                self.ctx.has_floor_division,
+                include_str!("liva_rt_floor_division.rs.in"),
+            ),
+            (self.ctx.has_type_of, include_str!("liva_rt_type_of.rs.in")),
+            (
+                self.ctx.has_char_code,
+                include_str!("liva_rt_char_code.rs.in"),
+            ),
+            (self.ctx.has_frozen, include_str!("liva_rt_frozen.rs.in")),
+            (self.ctx.has_mocks, include_str!("liva_rt_mocks.rs.in")),
+            (
+                self.ctx.has_key_sort,
+                include_str!("liva_rt_sort_key.rs.in"),
+            ),
+        ];
+        let mod_end = runtime.rfind('}').unwrap_or(runtime.len());
+        self.output.push_str(&runtime[..mod_end]);
+        for (_, helper) in helpers.iter().filter(|(used, _)| *used) {
+            self.output.push_str(helper);
+        }
+        self.output.push_str(&runtime[mod_end..]);
         self.writeln("");
 
         // Add rayon imports if parallel execution is used (at top level, after liva_rt module)
@@ -1980,6 +2080,130 @@ impl CodeGenerator {
             self.writeln("");
         }
 
+        // Thread-count resolution for `runtime { ... }` (LIVA_THREADS wins at run time),
+        // only when the `main` wrapper sizes a rayon or tokio pool with it
+        if self.main_sizes_thread_pools(program) {
+            self.writeln("// Runtime configuration helper (`runtime { ... }` block)");
+            self.writeln("fn liva_thread_count(configured: Option<usize>) -> Option<usize> {");
+            self.writeln("    std::env::var(\"LIVA_THREADS\")");
+            self.writeln("        .ok()");
+            self.writeln("        .and_then(|v| v.trim().parse::<usize>().ok())");
+            self.writeln("        .filter(|n| *n > 0)");
+            self.writeln("        .or(configured)");
+            self.writeln("}");
+            self.writeln("");
+        }
+
+        // Tests running beforeEach/afterEach (or `before`/`after`) hooks hold
+        // this lock, so one test's fixtures don't change under another's
+        if self.ctx.has_test_hooks {
+            self.writeln("#[cfg(test)]");
+            self.writeln(
+                "static LIVA_TEST_HOOKS: std::sync::Mutex<()> = std::sync::Mutex::new(());",
+            );
+            self.writeln("");
+        }
+
+        // Deterministic mode: every random builtin draws from one seeded generator
+        if self.ctx.deterministic && self.ctx.has_random {
+            self.writeln("// Seeded RNG for deterministic runs (LIVA_SEED, default 0)");
+            self.writeln(
+                "static LIVA_RNG: std::sync::Mutex<Option<rand::rngs::StdRng>> = std::sync::Mutex::new(None);",
+            );
+            self.writeln("");
+            self.writeln("fn liva_rng() -> rand::rngs::StdRng {");
+            self.writeln("    use rand::{Rng, SeedableRng};");
+            self.writeln("    let mut rng = LIVA_RNG.lock().unwrap_or_else(|e| e.into_inner());");
+            self.writeln("    let next = rng");
+            self.writeln("        .get_or_insert_with(|| {");
+            self.writeln("            let seed = std::env::var(\"LIVA_SEED\")");
+            self.writeln("                .ok()");
+            self.writeln("                .and_then(|s| s.trim().parse::<u64>().ok())");
+            self.writeln("                .unwrap_or(0);");
+            self.writeln("            rand::rngs::StdRng::seed_from_u64(seed)");
+            self.writeln("        })");
+            self.writeln("        .gen::<u64>();");
+            self.writeln("    rand::rngs::StdRng::seed_from_u64(next)");
+            self.writeln("}");
+            self.writeln("");
+            self.writeln("// Each test starts the sequence over, so its values don't depend on");
+            self.writeln("// which tests ran before it");
+            self.writeln("#[cfg(test)]");
+            self.writeln("fn liva_reseed() {");
+            self.writeln("    *LIVA_RNG.lock().unwrap_or_else(|e| e.into_inner()) = None;");
+            self.writeln("}");
+            self.writeln("");
+        }
+
+        // Coverage: hit counters flushed to LIVA_COVERAGE_FILE when a guard drops
+        if self.ctx.coverage {
+            self.writeln("// Statement coverage counters (`livac test --coverage`)");
+            self.writeln("fn liva_cov_counts(");
+            self.writeln(") -> &'static std::sync::Mutex<std::collections::BTreeMap<(&'static str, u32), u64>> {");
+            self.writeln("    static COUNTS: std::sync::OnceLock<");
+            self.writeln(
+                "        std::sync::Mutex<std::collections::BTreeMap<(&'static str, u32), u64>>,",
+            );
+            self.writeln("    > = std::sync::OnceLock::new();");
+            self.writeln("    COUNTS.get_or_init(Default::default)");
+            self.writeln("}");
+            self.writeln("");
+            self.writeln("#[allow(dead_code)]");
+            self.writeln("fn liva_cov_hit(file: &'static str, line: u32) {");
+            self.writeln(
+                "    let mut counts = liva_cov_counts().lock().unwrap_or_else(|e| e.into_inner());",
+            );
+            self.writeln("    *counts.entry((file, line)).or_insert(0) += 1;");
+            self.writeln("}");
+            self.writeln("");
+            self.writeln("struct LivaCoverageGuard;");
+            self.writeln("");
+            self.writeln("impl Drop for LivaCoverageGuard {");
+            self.writeln("    fn drop(&mut self) {");
+            self.writeln(
+                "        let Ok(path) = std::env::var(\"LIVA_COVERAGE_FILE\") else { return };",
+            );
+            self.writeln(
+                "        let counts = liva_cov_counts().lock().unwrap_or_else(|e| e.into_inner());",
+            );
+            self.writeln("        let mut out = String::new();");
+            self.writeln("        for ((file, line), hits) in counts.iter() {");
+            self.writeln(
+                "            out.push_str(&format!(\"{}\\t{}\\t{}\\n\", file, line, hits));",
+            );
+            self.writeln("        }");
+            self.writeln("        let _ = std::fs::write(path, out);");
+            self.writeln("    }");
+            self.writeln("}");
+            self.writeln("");
+        }
+
+        // Server: app.listen stops accepting on Ctrl-C or SIGTERM and lets
+        // in-flight requests finish, so `livac dev` can restart it cleanly
+        if self.ctx.has_server {
+            self.writeln("// Graceful shutdown for app.listen (Ctrl-C or SIGTERM)");
+            self.writeln("async fn liva_shutdown_signal() {");
+            self.writeln("    let ctrl_c = async {");
+            self.writeln("        let _ = tokio::signal::ctrl_c().await;");
+            self.writeln("    };");
+            self.writeln("    #[cfg(unix)]");
+            self.writeln("    let terminate = async {");
+            self.writeln("        use tokio::signal::unix::{signal, SignalKind};");
+            self.writeln("        match signal(SignalKind::terminate()) {");
+            self.writeln("            Ok(mut term) => { term.recv().await; }");
+            self.writeln("            Err(_) => std::future::pending::<()>().await,");
+            self.writeln("        }");
+            self.writeln("    };");
+            self.writeln("    #[cfg(not(unix))]");
+            self.writeln("    let terminate = std::future::pending::<()>();");
+            self.writeln("    tokio::select! {");
+            self.writeln("        _ = ctrl_c => {}");
+            self.writeln("        _ = terminate => {}");
+            self.writeln("    }");
+            self.writeln("}");
+            self.writeln("");
+        }
+
         // Add logging runtime helpers if Log.* is used
         if self.ctx.has_logging {
             self.writeln("// Logging runtime helpers");
@@ -2265,7 +2489,7 @@ impl CodeGenerator {
         // After first pass, generate union type enum definitions
         let unions_to_generate: Vec<Vec<String>> = self.union_types.iter().cloned().collect();
         if !unions_to_generate.is_empty() {
-            // Insert union enums before the generated code
+            // Insert union enums before the generated items
             let mut union_defs = String::new();
             union_defs.push_str("\n// Union type definitions\n");
 
@@ -2302,11 +2526,7 @@ impl CodeGenerator {
                 union_defs.push_str("}\n\n");
             }
 
-            // Find where to insert (after liva_rt module, before first function)
-            // For now, prepend to output (will fix positioning later)
-            let temp = self.output.clone();
-            self.output = union_defs;
-            self.output.push_str(&temp);
+            self.output.insert_str(items_start, &union_defs);
         }
 
         Ok(())
@@ -2393,14 +2613,24 @@ impl CodeGenerator {
                 self.generate_class(class)
             }
             TopLevel::Enum(enum_decl) => self.generate_enum(enum_decl),
-            TopLevel::Function(func) => self.generate_function(func),
+            TopLevel::Function(func) => self.generate_function(func, false),
             TopLevel::Test(test) => self.generate_test(test),
             TopLevel::ExprStmt(expr) => {
                 self.generate_expr(expr)?;
                 Ok(())
             }
+            TopLevel::ConstDecl(const_decl) if const_decl.is_lazy => {
+                self.generate_lazy_value(const_decl)
+            }
             TopLevel::ConstDecl(const_decl) => {
+                self.write_doc_comment(const_decl.doc.as_deref());
                 write!(self.output, "const {}: ", const_decl.name.to_uppercase()).unwrap();
+                // Semantic analysis made the constants it can't evaluate
+                // `lazy`, so everything left here folds to a literal
+                let folded = match &const_decl.init {
+                    Expr::Literal(_) => None,
+                    init => crate::consteval::evaluate(init, &self.const_values),
+                };
                 let type_str = if let Some(type_ref) = &const_decl.type_ref {
                     let rust_type = type_ref.to_rust_type();
                     // B31: const string can't use String (heap-allocated), must use &str
@@ -2409,21 +2639,30 @@ impl CodeGenerator {
                     } else {
                         rust_type
                     }
+                } else if let Some(value) = &folded {
+                    self.infer_const_type(&value.to_literal())
                 } else {
                     self.infer_const_type(&const_decl.init)
                 };
+                let folded = match folded {
+                    Some(ConstValue::Int(n)) if type_str == "f64" => {
+                        Some(ConstValue::Float(n as f64).to_literal())
+                    }
+                    value => value.map(|value| value.to_literal()),
+                };
+                let init = folded.as_ref().unwrap_or(&const_decl.init);
                 self.output.push_str(&type_str);
                 self.output.push_str(" = ");
                 // B31: For const string, don't add .to_string()
                 let is_const_str = type_str == "&str";
                 if is_const_str {
-                    if let Expr::Literal(Literal::String(s)) = &const_decl.init {
+                    if let Expr::Literal(Literal::String(s)) = init {
                         write!(self.output, "\"{}\"", s).unwrap();
                     } else {
-                        self.generate_expr(&const_decl.init)?;
+                        self.generate_expr(init)?;
                     }
                 } else {
-                    self.generate_expr(&const_decl.init)?;
+                    self.generate_expr(init)?;
                 }
                 self.output.push_str(";\n");
                 Ok(())
@@ -2434,50 +2673,332 @@ impl CodeGenerator {
                 // If one survives to here, ignore safely.
                 Ok(())
             }
+            TopLevel::Runtime(_) => {
+                // Consumed by desugaring; shapes the generated `main` instead.
+                Ok(())
+            }
+            TopLevel::Init(init) => self.generate_init(init),
+            TopLevel::TestHook(hook) => self.generate_test_hook(hook),
         }
     }
 
-    fn generate_type_decl(&mut self, type_decl: &TypeDecl) -> Result<()> {
-        // Interfaces in Liva are compile-time only contracts.
-        // They are validated by the semantic analyzer but do NOT generate any Rust code.
-        // Classes implementing interfaces just need to have the required methods.
-        // Interface method signatures are collected in generate_program() for type inference.
-
-        // Only generate a comment for documentation purposes
+    /// `lazy NAME = init` becomes an accessor holding the value in a
+    /// `OnceLock`, so the first read initializes it, once, from any thread.
+    /// A fallible initializer's error is stored and handed to every reader.
+    fn generate_lazy_value(&mut self, decl: &ConstDecl) -> Result<()> {
+        let value_type = match &decl.type_ref {
+            Some(type_ref) => self.expand_type_alias(type_ref),
+            None => match self.infer_const_type(&decl.init).as_str() {
+                "&str" => "String".to_string(),
+                other => other.to_string(),
+            },
+        };
+        let value_type = if self.lazy_values.get(&decl.name) == Some(&true) {
+            format!("Result<{}, liva_rt::Error>", value_type)
+        } else {
+            value_type
+        };
+        self.write_doc_comment(decl.doc.as_deref());
+        self.writeln("#[allow(non_snake_case)]");
         self.writeln(&format!(
-            "// Interface: {} (compile-time validation only)",
-            type_decl.name
+            "fn {}() -> {} {{",
+            decl.name.to_uppercase(),
+            value_type
         ));
-
+        self.indent();
+        self.writeln(&format!(
+            "static VALUE: std::sync::OnceLock<{}> = std::sync::OnceLock::new();",
+            value_type
+        ));
+        self.write_indent();
+        self.output.push_str("VALUE.get_or_init(|| ");
+        self.generate_expr(&decl.init)?;
+        self.output.push_str(").clone()\n");
+        self.dedent();
+        self.writeln("}");
         Ok(())
     }
 
-    fn generate_type_alias(&mut self, alias: &TypeAliasDecl) -> Result<()> {
-        // Store type alias for expansion during type annotation generation
-        self.type_aliases.insert(
-            alias.name.clone(),
-            (alias.type_params.clone(), alias.target_type.clone()),
-        );
-        // Type aliases in Liva are expanded inline during type checking
-        // We don't generate Rust type aliases to keep codegen simple
-        Ok(())
+    /// Records the fields and methods of class `name` from the build's symbol
+    /// table: field names, optional fields, element types of `[T]` fields,
+    /// value types of Map fields, and methods returning strings or arrays
+    fn register_class_symbols(&mut self, name: &str) -> Option<crate::symbols::ClassSymbol> {
+        let class = self.ctx.symbols.class(name)?.clone();
+        let mut fields = HashSet::new();
+        let mut optional_fields = HashSet::new();
+        let mut array_field_types = std::collections::HashMap::new();
+        let mut map_value_types = std::collections::HashMap::new();
+        for field in &class.fields {
+            fields.insert(field.name.clone());
+            if field.optional {
+                optional_fields.insert(field.name.clone());
+            }
+            if let Some(element) = field.array_element() {
+                array_field_types.insert(field.name.clone(), element.to_string());
+            }
+            // B125: Map field value types. Encoding: "TypeName" for Simple,
+            // "[T]" for Array<T>, "{}" for Map (B134).
+            if let Some(TypeRef::Map(_, value_type)) = &field.type_ref {
+                let encoded = match value_type.as_ref() {
+                    TypeRef::Simple(type_name) => Some(type_name.clone()),
+                    TypeRef::Array(inner) => match inner.as_ref() {
+                        TypeRef::Simple(n) => Some(format!("[{}]", n)),
+                        _ => Some("[_]".to_string()),
+                    },
+                    TypeRef::Map(_, _) => Some("{}".to_string()),
+                    _ => None,
+                };
+                if let Some(encoded) = encoded {
+                    map_value_types.insert(field.name.clone(), encoded);
+                }
+            }
+        }
+        self.class_fields.insert(class.name.clone(), fields);
+        self.class_optional_fields
+            .insert(class.name.clone(), optional_fields);
+        if !array_field_types.is_empty() {
+            self.class_array_field_types
+                .insert(class.name.clone(), array_field_types);
+        }
+        if !map_value_types.is_empty() {
+            self.class_map_value_types
+                .insert(class.name.clone(), map_value_types);
+        }
+
+        // B100 fix: Methods returning string or [T], for VarDecl tracking
+        for method in &class.methods {
+            if method.returns_string() {
+                self.string_returning_methods.insert(method.name.clone());
+            }
+            if let Some(element) = method.array_element() {
+                self.array_returning_methods
+                    .insert(method.name.clone(), element);
+            }
+        }
+        Some(class)
     }
 
-    /// Expand type aliases in a TypeRef to get the final Rust type string
-    fn expand_type_alias(&mut self, type_ref: &TypeRef) -> String {
-        match type_ref {
-            TypeRef::Simple(name) => {
-                // Check if it's a type alias
-                if let Some((alias_params, target_type)) = self.type_aliases.get(name).cloned() {
-                    // If the alias has no type parameters, just expand the target
-                    if alias_params.is_empty() {
-                        return self.expand_type_alias(&target_type);
-                    }
-                    // If it has type parameters but no args, just expand (error should be caught in semantic)
-                    return self.expand_type_alias(&target_type);
+    /// Records the abstract classes of `items`, reached through `path` from
+    /// the module being generated
+    fn register_abstract_classes(&mut self, items: &[TopLevel], path: &str) {
+        for item in items {
+            if let TopLevel::Class(class) = item {
+                if class.is_abstract {
+                    self.abstract_classes
+                        .insert(class.name.clone(), class.clone());
+                    self.abstract_class_paths
+                        .insert(class.name.clone(), path.to_string());
                 }
-                // Not a type alias, use the normal to_rust_type conversion
-                type_ref.to_rust_type()
+            }
+        }
+    }
+
+    /// Records the `lazy` values of `items`, after the fallible functions
+    /// their initializers may call, and the values of the other constants
+    fn register_lazy_values(&mut self, items: &[TopLevel]) {
+        for item in items {
+            if let TopLevel::ConstDecl(decl) = item {
+                if decl.is_lazy {
+                    let fallible = self.is_fallible_expr(&decl.init);
+                    self.lazy_values.insert(decl.name.clone(), fallible);
+                } else if let Some(value) =
+                    crate::consteval::evaluate(&decl.init, &self.const_values)
+                {
+                    self.const_values.insert(decl.name.clone(), value);
+                }
+            }
+        }
+    }
+
+    /// RNG used by the random builtins: seeded `liva_rng()` in deterministic mode
+    fn rng_expr(&self) -> &'static str {
+        if self.ctx.deterministic {
+            "crate::liva_rng()"
+        } else {
+            "rand::thread_rng()"
+        }
+    }
+
+    /// Expression for a uniform `f64` in `0.0..1.0`
+    fn random_f64_expr(&self) -> &'static str {
+        if self.ctx.deterministic {
+            "{ use rand::Rng; crate::liva_rng().gen::<f64>() }"
+        } else {
+            "rand::random::<f64>()"
+        }
+    }
+
+    /// Whether `generate_main_wrapper` calls `liva_thread_count`: a `runtime`
+    /// block plus a `main` that uses rayon or is async, outside deterministic
+    /// mode
+    fn main_sizes_thread_pools(&self, program: &Program) -> bool {
+        if self.ctx.runtime.is_none() {
+            return false;
+        }
+        program.items.iter().any(|item| match item {
+            TopLevel::Function(func) if func.name == "main" => {
+                (self.ctx.has_parallel || func.is_async_inferred) && !self.ctx.deterministic
+            }
+            _ => false,
+        })
+    }
+
+    /// `fn main` that calls the user's `main`, emitted as `liva_main`. With a
+    /// `runtime { ... }` block it sizes the thread pools first; with `exit`
+    /// it returns an `ExitCode` from `liva_main`'s result, where a code
+    /// outside 0-255 exits with 255 rather than wrapping around.
+    fn generate_main_wrapper(&mut self, is_async: bool, return_type: &str, exit: Option<MainExit>) {
+        let runtime = self.ctx.runtime.clone();
+        let configured = |threads: Option<i64>| match threads {
+            Some(n) => format!("Some({})", n),
+            None => "None".to_string(),
+        };
+
+        // Without a runtime block, an async `main` keeps `#[tokio::main]`
+        if runtime.is_none() && is_async {
+            if self.ctx.deterministic {
+                self.writeln("#[tokio::main(flavor = \"current_thread\")]");
+            } else {
+                self.writeln("#[tokio::main]");
+            }
+        }
+        let outer_return = if exit.is_some() {
+            " -> std::process::ExitCode"
+        } else {
+            return_type
+        };
+        let async_kw = match runtime {
+            None if is_async => "async ",
+            _ => "",
+        };
+        writeln!(self.output, "{}fn main(){} {{", async_kw, outer_return).unwrap();
+        self.indent();
+
+        let mut call: Vec<String> = Vec::new();
+        if let Some(runtime) = &runtime {
+            // Deterministic mode ignores the configured sizes and LIVA_THREADS: one
+            // rayon worker and a current-thread tokio runtime keep output ordering
+            // reproducible.
+            if self.ctx.has_parallel && self.ctx.deterministic {
+                self.writeln(
+                    "let _ = rayon::ThreadPoolBuilder::new().num_threads(1).build_global();",
+                );
+            } else if self.ctx.has_parallel {
+                self.writeln(&format!(
+                    "if let Some(n) = liva_thread_count({}) {{",
+                    configured(runtime.par_worker_threads())
+                ));
+                self.writeln(
+                    "    let _ = rayon::ThreadPoolBuilder::new().num_threads(n).build_global();",
+                );
+                self.writeln("}");
+            }
+            if is_async && self.ctx.deterministic {
+                call.push("tokio::runtime::Builder::new_current_thread()".to_string());
+                call.push("    .enable_all()".to_string());
+                call.push("    .build()".to_string());
+                call.push("    .expect(\"failed to start the tokio runtime\")".to_string());
+                call.push("    .block_on(liva_main())".to_string());
+            } else if is_async {
+                self.writeln("let mut builder = tokio::runtime::Builder::new_multi_thread();");
+                self.writeln(&format!(
+                    "if let Some(n) = liva_thread_count({}) {{",
+                    configured(runtime.async_worker_threads())
+                ));
+                self.writeln("    builder.worker_threads(n);");
+                self.writeln("}");
+                call.push("builder".to_string());
+                call.push("    .enable_all()".to_string());
+                call.push("    .build()".to_string());
+                call.push("    .expect(\"failed to start the tokio runtime\")".to_string());
+                call.push("    .block_on(liva_main())".to_string());
+            } else {
+                call.push("liva_main()".to_string());
+            }
+        } else if is_async {
+            call.push("liva_main().await".to_string());
+        } else {
+            call.push("liva_main()".to_string());
+        }
+
+        match exit {
+            None => {
+                for line in &call {
+                    self.writeln(line);
+                }
+            }
+            Some(exit) => {
+                let last = call.len() - 1;
+                for (i, line) in call.iter().enumerate() {
+                    let prefix = if i == 0 { "let result = " } else { "" };
+                    let suffix = if i == last { ";" } else { "" };
+                    self.writeln(&format!("{}{}{}", prefix, line, suffix));
+                }
+                match exit {
+                    MainExit::Code => {
+                        self.writeln(
+                            "std::process::ExitCode::from(u8::try_from(result).unwrap_or(u8::MAX))",
+                        );
+                    }
+                    MainExit::Fallible | MainExit::FallibleCode => {
+                        self.writeln("match result {");
+                        self.writeln(if exit == MainExit::Fallible {
+                            "    Ok(()) => std::process::ExitCode::SUCCESS,"
+                        } else {
+                            "    Ok(code) => std::process::ExitCode::from(u8::try_from(code).unwrap_or(u8::MAX)),"
+                        });
+                        self.writeln("    Err(e) => liva_rt::main_failed(&e),");
+                        self.writeln("}");
+                    }
+                }
+            }
+        }
+        self.dedent();
+        self.writeln("}");
+        self.output.push('\n');
+    }
+
+    fn generate_type_decl(&mut self, type_decl: &TypeDecl) -> Result<()> {
+        // Interfaces in Liva are compile-time only contracts.
+        // They are validated by the semantic analyzer but do NOT generate any Rust code.
+        // Classes implementing interfaces just need to have the required methods.
+        // Interface method signatures are collected in generate_program() for type inference.
+
+        // Only generate a comment for documentation purposes
+        self.writeln(&format!(
+            "// Interface: {} (compile-time validation only)",
+            type_decl.name
+        ));
+
+        Ok(())
+    }
+
+    fn generate_type_alias(&mut self, alias: &TypeAliasDecl) -> Result<()> {
+        // Store type alias for expansion during type annotation generation
+        self.type_aliases.insert(
+            alias.name.clone(),
+            (alias.type_params.clone(), alias.target_type.clone()),
+        );
+        // Type aliases in Liva are expanded inline during type checking
+        // We don't generate Rust type aliases to keep codegen simple
+        Ok(())
+    }
+
+    /// Expand type aliases in a TypeRef to get the final Rust type string
+    fn expand_type_alias(&mut self, type_ref: &TypeRef) -> String {
+        match type_ref {
+            TypeRef::Simple(name) => {
+                // Check if it's a type alias
+                if let Some((alias_params, target_type)) = self.type_aliases.get(name).cloned() {
+                    // If the alias has no type parameters, just expand the target
+                    if alias_params.is_empty() {
+                        return self.expand_type_alias(&target_type);
+                    }
+                    // If it has type parameters but no args, just expand (error should be caught in semantic)
+                    return self.expand_type_alias(&target_type);
+                }
+                // Not a type alias, use the normal to_rust_type conversion
+                type_ref.to_rust_type()
             }
             TypeRef::Generic { base, args } => {
                 // Check if the base is a type alias
@@ -2629,6 +3150,10 @@ impl CodeGenerator {
     }
 
     fn generate_class(&mut self, class: &ClassDecl) -> Result<()> {
+        if class.is_abstract {
+            return self.generate_abstract_class(class);
+        }
+
         // Check if this is actually an interface (no constructor, methods without bodies)
         // Interfaces are compile-time only and don't generate Rust code
         let has_constructor = class
@@ -2753,6 +3278,7 @@ impl CodeGenerator {
                 class.implements.join(", ")
             ));
         }
+        self.write_doc_comment(class.doc.as_deref());
         self.writeln(derives);
         self.writeln(&format!("pub struct {}{} {{", class.name, type_params_str));
         self.indent();
@@ -2965,12 +3491,15 @@ impl CodeGenerator {
                             self.output.push_str("std::collections::HashMap::new()");
                         } else if (init_is_empty_object || init_is_empty_set) && field_is_set {
                             self.output.push_str("std::collections::HashSet::new()");
+                        } else if matches!(value_expr, Expr::Literal(Literal::String(_))) {
+                            // A type parameter of the class is for the caller to decide
+                            let declared = field_type.cloned().filter(|ty| {
+                                !matches!(ty, TypeRef::Simple(name)
+                                    if class.type_params.iter().any(|tp| &tp.name == name))
+                            });
+                            self.generate_string_literal(declared, "field", value_expr)?;
                         } else {
                             self.generate_expr(value_expr)?;
-                            // Promote string literal to String when needed.
-                            if matches!(value_expr, Expr::Literal(Literal::String(_))) {
-                                self.output.push_str(".to_string()");
-                            }
                         }
 
                         if is_opt_field {
@@ -3266,6 +3795,8 @@ impl CodeGenerator {
         self.dedent();
         self.writeln("}");
 
+        self.generate_abstract_impls(class)?;
+
         // BUG-004 fix: Auto-generate Display impl for ALL classes with fields,
         // not just data classes. Classes with explicit constructors also need Display.
         if has_fields {
@@ -3391,6 +3922,144 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// An abstract class becomes a trait declaring its abstract methods, and
+    /// a `{Name}Base` struct holding the fields it passes on; the trait's
+    /// `base()` hands those out from any implementation
+    fn generate_abstract_class(&mut self, class: &ClassDecl) -> Result<()> {
+        self.write_doc_comment(class.doc.as_deref());
+        self.writeln(&format!("pub trait {} {{", class.name));
+        self.indent();
+        self.writeln(&format!("fn base(&self) -> {}Base;", class.name));
+        for member in &class.members {
+            if let Member::Method(method) = member {
+                if method.is_abstract {
+                    let signature = self.abstract_method_signature(method, class)?;
+                    self.write_doc_comment(method.doc.as_deref());
+                    self.writeln(&format!("{};", signature));
+                }
+            }
+        }
+        self.dedent();
+        self.writeln("}");
+        self.output.push('\n');
+
+        self.writeln(&format!(
+            "/// Fields of every class implementing `{}`",
+            class.name
+        ));
+        self.writeln("#[derive(Debug, Clone, PartialEq)]");
+        self.writeln(&format!("pub struct {}Base {{", class.name));
+        self.indent();
+        for member in &class.members {
+            if let Member::Field(field) = member {
+                self.generate_field(field, false, Some(&class.name))?;
+            }
+        }
+        self.dedent();
+        self.writeln("}");
+        Ok(())
+    }
+
+    /// `fn name(&self, ...) -> T` of an abstract method, as its trait
+    /// declares it
+    fn abstract_method_signature(
+        &mut self,
+        method: &MethodDecl,
+        class: &ClassDecl,
+    ) -> Result<String> {
+        let params = self.generate_params(
+            &method.params,
+            true,
+            Some(class),
+            Some(&method.name),
+            Some(method),
+        )?;
+        let return_type = method
+            .return_type
+            .as_ref()
+            .map(|ret| format!(" -> {}", self.expand_type_alias(ret)))
+            .unwrap_or_default();
+        Ok(format!(
+            "fn {}({}){}",
+            self.sanitize_name(&method.name),
+            params,
+            return_type
+        ))
+    }
+
+    /// Implement the trait of every abstract class `class` implements,
+    /// directly or through another abstract class, by calling its own methods
+    fn generate_abstract_impls(&mut self, class: &ClassDecl) -> Result<()> {
+        let mut bases: Vec<ClassDecl> = Vec::new();
+        for name in &class.implements {
+            for base in crate::abstract_classes::ancestors(name, &self.abstract_classes) {
+                if !bases.iter().any(|other| other.name == base.name) {
+                    bases.push(base.clone());
+                }
+            }
+        }
+
+        for base in &bases {
+            let path = self
+                .abstract_class_paths
+                .get(&base.name)
+                .cloned()
+                .unwrap_or_default();
+            self.output.push('\n');
+            self.writeln(&format!("impl {}{} for {} {{", path, base.name, class.name));
+            self.indent();
+
+            self.writeln(&format!("fn base(&self) -> {}{}Base {{", path, base.name));
+            self.indent();
+            self.writeln(&format!("{}{}Base {{", path, base.name));
+            self.indent();
+            for member in &base.members {
+                if let Member::Field(field) = member {
+                    let field_name = self.sanitize_name(&field.name);
+                    self.writeln(&format!("{}: self.{}.clone(),", field_name, field_name));
+                }
+            }
+            self.dedent();
+            self.writeln("}");
+            self.dedent();
+            self.writeln("}");
+
+            for member in &base.members {
+                let Member::Method(method) = member else {
+                    continue;
+                };
+                if !method.is_abstract {
+                    continue;
+                }
+                let signature = self.abstract_method_signature(method, base)?;
+                let args: Vec<String> = std::iter::once("self".to_string())
+                    .chain(method.params.iter().enumerate().map(|(i, param)| {
+                        if param.is_destructuring() {
+                            format!("_param_{}", i)
+                        } else {
+                            self.sanitize_name(param.name().unwrap())
+                        }
+                    }))
+                    .collect();
+                self.output.push('\n');
+                self.writeln(&format!("{} {{", signature));
+                self.indent();
+                self.writeln(&format!(
+                    "{}::{}({})",
+                    class.name,
+                    self.sanitize_name(&method.name),
+                    args.join(", ")
+                ));
+                self.dedent();
+                self.writeln("}");
+            }
+
+            self.dedent();
+            self.writeln("}");
+        }
+        Ok(())
+    }
+
     fn generate_field_default_function(
         &mut self,
         class_name: &str,
@@ -3464,16 +4133,14 @@ impl CodeGenerator {
         };
 
         let field_name_rust = self.sanitize_name(&field.name);
+        self.write_doc_comment(field.doc.as_deref());
 
         // Add serde attributes for optional fields
         if needs_serde && field.is_optional {
             // If the field has a default value, use serde default function
-            if field.init.is_some() && class_name.is_some() {
-                let func_name = format!(
-                    "default_{}_{}",
-                    class_name.unwrap().to_lowercase(),
-                    field_name_rust
-                );
+            if let Some(class_name) = class_name.filter(|_| field.init.is_some()) {
+                let func_name =
+                    format!("default_{}_{}", class_name.to_lowercase(), field_name_rust);
                 self.writeln(&format!("#[serde(default = \"{}\")]", func_name));
             }
             self.writeln("#[serde(skip_serializing_if = \"Option::is_none\")]");
@@ -3512,9 +4179,9 @@ impl CodeGenerator {
             Expr::Member { object, property } => {
                 // Check if this is accessing a field of 'this'
                 if let Expr::Identifier(obj) = object.as_ref() {
-                    if obj == "this" && class.is_some() {
+                    if let Some(class) = class.filter(|_| obj == "this") {
                         // Find the field type
-                        for member in &class.unwrap().members {
+                        for member in &class.members {
                             if let Member::Field(field) = member {
                                 if field.name == *property {
                                     let rust_type = field
@@ -3577,11 +4244,33 @@ impl CodeGenerator {
                     | BinOp::And
                     | BinOp::Or => Some(" -> bool".to_string()),
                     // B18 fix: Arithmetic ops → infer from operands
-                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod => {
+                    BinOp::Add
+                    | BinOp::Sub
+                    | BinOp::Mul
+                    | BinOp::Div
+                    | BinOp::Mod
+                    | BinOp::BitAnd
+                    | BinOp::BitOr
+                    | BinOp::BitXor
+                    | BinOp::Shl
+                    | BinOp::Shr => {
                         // Try to infer from left operand first, then right
                         self.infer_expr_type(left, class)
                             .or_else(|| self.infer_expr_type(right, class))
                     }
+                    // A float on either side makes the power a float
+                    BinOp::Pow => {
+                        let (left, right) = (
+                            self.infer_expr_type(left, class),
+                            self.infer_expr_type(right, class),
+                        );
+                        let float = Some(" -> f64".to_string());
+                        if left == float || right == float {
+                            float
+                        } else {
+                            left
+                        }
+                    }
                     _ => None,
                 }
             }
@@ -3611,8 +4300,23 @@ impl CodeGenerator {
             } => self
                 .infer_expr_type(then_expr, class)
                 .or_else(|| self.infer_expr_type(else_expr, class)),
+            Expr::If(if_expr) => [&if_expr.then_branch, &if_expr.else_branch]
+                .into_iter()
+                .find_map(|branch| match branch {
+                    SwitchBody::Expr(e) => self.infer_expr_type(e, class),
+                    SwitchBody::Block(_) => None,
+                }),
             // B18 fix: Unary not → bool
             Expr::Unary { op, .. } if matches!(op, UnOp::Not) => Some(" -> bool".to_string()),
+            Expr::Cast {
+                target: TypeRef::Simple(target),
+                ..
+            } => match target.as_str() {
+                "int" => Some(" -> i32".to_string()),
+                "float" => Some(" -> f64".to_string()),
+                "string" => Some(" -> String".to_string()),
+                _ => None,
+            },
             _ => None,
         }
     }
@@ -3763,6 +4467,10 @@ impl CodeGenerator {
             }
         };
 
+        self.write_doc_comment(method.doc.as_deref());
+        if self.ctx.debug {
+            self.writeln("#[inline(never)]");
+        }
         self.write_indent();
         write!(
             self.output,
@@ -3808,13 +4516,13 @@ impl CodeGenerator {
             let needs_clone = returns_type_param && !self.current_method_is_mut;
             if method.contains_fail {
                 self.output.push_str("Ok(");
-                self.generate_expr(expr)?;
+                self.generate_expr_body(expr, &return_type)?;
                 if needs_clone {
                     self.output.push_str(".clone()");
                 }
                 self.output.push(')');
             } else {
-                self.generate_expr(expr)?;
+                self.generate_expr_body(expr, &return_type)?;
                 if needs_clone {
                     self.output.push_str(".clone()");
                 }
@@ -3875,6 +4583,7 @@ impl CodeGenerator {
                 .insert(enum_decl.name.clone(), boxed_fields_for_enum);
         }
 
+        self.write_doc_comment(enum_decl.doc.as_deref());
         // Generate Rust enum with derive macros
         // FIX-5: Unit enums (no data fields) get Copy to avoid move errors
         if all_unit && !enum_decl.variants.is_empty() {
@@ -3977,7 +4686,25 @@ impl CodeGenerator {
         Ok(())
     }
 
-    fn generate_function(&mut self, func: &FunctionDecl) -> Result<()> {
+    /// Emits a nested function in statement position: a nested `fn` when it
+    /// uses no enclosing locals, otherwise a closure bound to its name
+    fn generate_nested_function(&mut self, func: &FunctionDecl) -> Result<()> {
+        if func.contains_fail {
+            self.fallible_functions.insert(func.name.clone());
+        }
+        // The enclosing function's analysis must survive the nested one's
+        let mutated_vars = std::mem::take(&mut self.mutated_vars);
+        let pending_tasks = std::mem::take(&mut self.pending_tasks);
+        let task_scopes = std::mem::take(&mut self.task_scopes);
+        self.write_indent();
+        let result = self.generate_function(func, true);
+        self.mutated_vars = mutated_vars;
+        self.pending_tasks = pending_tasks;
+        self.task_scopes = task_scopes;
+        result
+    }
+
+    fn generate_function(&mut self, func: &FunctionDecl, nested: bool) -> Result<()> {
         // Track current function name for error traces
         let prev_function_name =
             std::mem::replace(&mut self.current_function_name, func.name.clone());
@@ -3995,8 +4722,7 @@ impl CodeGenerator {
 
         // GAP-007: Record param types so call-site can wrap Lambda args in Box::new
         // when the expected type is a function type.
-        let param_types: Vec<Option<TypeRef>> =
-            func.params.iter().map(|p| p.type_ref.clone()).collect();
+        let param_types = declared_param_types(func);
         if param_types.iter().any(|t| t.is_some()) {
             self.function_param_types
                 .insert(func.name.clone(), param_types);
@@ -4012,8 +4738,13 @@ impl CodeGenerator {
         }
 
         let (async_kw, tokio_attr) = if func.name == "main" && func.is_async_inferred {
-            // For main function with async, use tokio::main attribute with async keyword
-            ("async ", "#[tokio::main]\n")
+            // For main function with async, use tokio::main attribute with async keyword.
+            // Deterministic mode pins every task to the main thread.
+            if self.ctx.deterministic {
+                ("async ", "#[tokio::main(flavor = \"current_thread\")]\n")
+            } else {
+                ("async ", "#[tokio::main]\n")
+            }
         } else if func.is_async_inferred {
             ("async ", "")
         } else {
@@ -4075,6 +4806,9 @@ impl CodeGenerator {
         } else {
             if let Some(ret) = &func.return_type {
                 format!(" -> {}", self.expand_type_alias(ret))
+            } else if func.unsafe_rust {
+                // Only the declared signature counts; no type means no value
+                String::new()
             } else if let Some(expr) = &func.expr_body {
                 // For expression-bodied functions without explicit return type, infer from the expression
                 self.infer_expr_type(expr, None)
@@ -4097,29 +4831,98 @@ impl CodeGenerator {
             }
         };
 
-        write!(
-            self.output,
-            "{}{}fn {}{}({})",
-            tokio_attr,
-            async_kw,
-            self.sanitize_name(&func.name),
-            type_params,
-            params_str
-        )
-        .unwrap();
+        // With a `runtime { ... }` block, or when `main` can fail or returns an
+        // exit code, `main` becomes a wrapper that calls the user's body,
+        // emitted as `liva_main`.
+        let is_main = func.name == "main" && !nested;
+        let main_exit = if is_main && (func.contains_fail || func.return_type.is_some()) {
+            MainExit::from_return_type(&return_type)
+        } else {
+            None
+        };
+        let (fn_name, tokio_attr) =
+            if is_main && (self.ctx.runtime.is_some() || main_exit.is_some()) {
+                self.generate_main_wrapper(func.is_async_inferred, &return_type, main_exit);
+                ("liva_main".to_string(), "")
+            } else {
+                (self.sanitize_name(&func.name), tokio_attr)
+            };
+
+        if !nested {
+            self.write_doc_comment(func.doc.as_deref());
+        }
+
+        // Keep a real call frame per Liva function so breakpoints and stepping work
+        if nested {
+            // Inlining decisions only cover top-level functions
+        } else if self.ctx.debug && func.name != "main" {
+            self.output.push_str("#[inline(never)]\n");
+        } else if let Some(decision) = self
+            .ctx
+            .inline
+            .get(&self.module_path)
+            .and_then(|decisions| decisions.get(&func.name))
+        {
+            if func.name != "main" {
+                self.output.push_str(decision.rust_attribute());
+                self.output.push('\n');
+            }
+        }
+
+        let is_closure = !func.captures.is_empty();
+        if is_closure {
+            // Calling a closure that assigns to a captured local needs `let mut`
+            let mut assigned = std::collections::HashSet::new();
+            if let Some(body) = &func.body {
+                self.collect_mutated_vars_in_block(body, &mut assigned);
+            }
+            let mut_kw = if func.captures.iter().any(|name| assigned.contains(name)) {
+                "mut "
+            } else {
+                ""
+            };
+            write!(self.output, "let {}{} = |{}|", mut_kw, fn_name, params_str).unwrap();
+        } else {
+            write!(
+                self.output,
+                "{}{}fn {}{}({})",
+                tokio_attr, async_kw, fn_name, type_params, params_str
+            )
+            .unwrap();
+        }
+        let closing = if is_closure { "};" } else { "}" };
 
         if !return_type.is_empty() {
             write!(self.output, "{}", return_type).unwrap();
         }
 
-        if let Some(expr) = &func.expr_body {
+        if let (true, Some(Expr::RustBlock { code })) = (func.unsafe_rust, &func.expr_body) {
+            // `unsafe rust fn`: the body is the user's Rust, as written. A
+            // `T!` one may evaluate to any `Result<T, E: Display>`.
+            if func.contains_fail {
+                self.output.push_str(" {\n    liva_rt::from_rust_result(");
+                self.generate_rust_block(code)?;
+                self.output.push_str(")\n}\n");
+            } else {
+                self.output.push(' ');
+                self.generate_rust_block(code)?;
+                self.output.push('\n');
+            }
+        } else if let Some(expr) = &func.expr_body {
             self.output.push_str(" {\n");
             self.indent();
 
             // Generate destructuring code for parameters
             self.generate_param_destructuring(&func.params)?;
+            if is_main {
+                self.generate_init_calls();
+            }
+            self.generate_contract_entry(func)?;
 
             self.write_indent();
+            if !func.ensures.is_empty() {
+                self.output.push_str("let __liva_result = ");
+            }
             let was_fallible = self.in_fallible_function;
             self.in_fallible_function = func.contains_fail;
             let was_optional = self.in_optional_function;
@@ -4130,6 +4933,11 @@ impl CodeGenerator {
             if let Some(ret) = &func.return_type {
                 self.current_return_type = Some(ret.to_rust_type());
             }
+            let union = func
+                .return_type
+                .as_ref()
+                .and_then(|ret| self.union_type_members(ret));
+            let prev_return_union = std::mem::replace(&mut self.current_return_union, union);
 
             if func.contains_fail {
                 // Check if the expression already returns a Result (like a fallible ternary)
@@ -4139,38 +4947,59 @@ impl CodeGenerator {
                 if !expr_returns_result {
                     self.output.push_str("Ok(");
                 }
-                self.generate_expr(expr)?;
+                self.generate_expr_body(expr, &return_type)?;
                 if !expr_returns_result {
                     self.output.push(')');
                 }
             } else if self.in_optional_function {
                 // BUG-006: Wrap expression body in Some() for optional return
                 self.output.push_str("Some(");
-                self.generate_expr(expr)?;
+                self.generate_expr_body(expr, &return_type)?;
                 self.output.push(')');
             } else {
-                self.generate_expr(expr)?;
+                self.generate_expr_body(expr, &return_type)?;
             }
             self.in_fallible_function = was_fallible;
             self.in_optional_function = was_optional;
             self.current_return_type = prev_return_type;
-            self.output.push('\n');
+            self.current_return_union = prev_return_union;
+            if func.ensures.is_empty() {
+                self.output.push('\n');
+            } else {
+                self.output.push_str(";\n");
+                self.generate_ensures_check(func, &return_type)?;
+            }
 
             // Phase 4.2: Check for dead tasks
             self.check_dead_tasks();
             self.pending_tasks.clear();
 
             self.dedent();
-            self.writeln("}");
+            self.writeln(closing);
         } else if let Some(body) = &func.body {
             self.output.push_str(" {\n");
             self.indent();
 
             // Generate destructuring code for parameters
             self.generate_param_destructuring(&func.params)?;
+            if is_main {
+                self.generate_init_calls();
+            }
+            self.generate_contract_entry(func)?;
 
-            let was_fallible = self.in_fallible_function;
-            self.in_fallible_function = func.contains_fail;
+            // With `ensures`, the body runs first so its value can be checked;
+            // a `return` in it leaves the closure (or async block), not the function
+            if !func.ensures.is_empty() {
+                if func.is_async_inferred {
+                    self.writeln("let __liva_result = async {");
+                } else {
+                    self.writeln(&format!("let __liva_result = (||{} {{", return_type));
+                }
+                self.indent();
+            }
+
+            let was_fallible = self.in_fallible_function;
+            self.in_fallible_function = func.contains_fail;
             let was_optional = self.in_optional_function;
             self.in_optional_function = matches!(&func.return_type, Some(TypeRef::Optional(_)));
 
@@ -4179,6 +5008,11 @@ impl CodeGenerator {
             if let Some(ret) = &func.return_type {
                 self.current_return_type = Some(ret.to_rust_type());
             }
+            let union = func
+                .return_type
+                .as_ref()
+                .and_then(|ret| self.union_type_members(ret));
+            let prev_return_union = std::mem::replace(&mut self.current_return_union, union);
 
             self.generate_block_inner(body)?;
             // If function is fallible and doesn't end with explicit return, add Ok(())
@@ -4189,6 +5023,17 @@ impl CodeGenerator {
             self.in_fallible_function = was_fallible;
             self.in_optional_function = was_optional;
             self.current_return_type = prev_return_type;
+            self.current_return_union = prev_return_union;
+
+            if !func.ensures.is_empty() {
+                self.dedent();
+                self.writeln(if func.is_async_inferred {
+                    "}.await;"
+                } else {
+                    "})();"
+                });
+                self.generate_ensures_check(func, &return_type)?;
+            }
 
             // Phase 4.2: Check for dead tasks (tasks that were never awaited)
             self.check_dead_tasks();
@@ -4197,7 +5042,7 @@ impl CodeGenerator {
             self.pending_tasks.clear();
 
             self.dedent();
-            self.writeln("}");
+            self.writeln(closing);
         }
 
         // Restore previous function name
@@ -4206,6 +5051,132 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// `requires` clauses as asserts at the top of the body, plus a copy of
+    /// each parameter an `ensures` clause reads, taken before the body runs.
+    /// Without `@checked` they only exist in debug builds.
+    fn generate_contract_entry(&mut self, func: &FunctionDecl) -> Result<()> {
+        let assert_macro = if func.checked {
+            "assert!"
+        } else {
+            "debug_assert!"
+        };
+        for contract in &func.requires {
+            self.generate_contract_assert(assert_macro, func, "requires", contract)?;
+        }
+        for name in self.ensures_params(func) {
+            if !func.checked {
+                self.writeln("#[cfg(debug_assertions)]");
+            }
+            self.writeln(&format!("let __liva_old_{} = {}.clone();", name, name));
+        }
+        Ok(())
+    }
+
+    /// Check the `ensures` clauses against `__liva_result`, the value of the
+    /// body, then return it. A failed call (`Err`) is not checked.
+    fn generate_ensures_check(&mut self, func: &FunctionDecl, return_type: &str) -> Result<()> {
+        if !func.checked {
+            self.writeln("#[cfg(debug_assertions)]");
+        }
+        self.writeln("{");
+        self.indent();
+        for name in self.ensures_params(func) {
+            self.writeln(&format!("let {} = __liva_old_{};", name, name));
+        }
+        let uses_result = !return_type.is_empty()
+            && func
+                .ensures
+                .iter()
+                .any(|contract| self.expr_uses_var(&contract.condition, "result"));
+        let guarded = func.contains_fail;
+        if guarded && uses_result {
+            self.writeln("if let Some(result) = __liva_result.as_ref().ok().cloned() {");
+        } else if guarded {
+            self.writeln("if __liva_result.is_ok() {");
+        } else if uses_result {
+            self.writeln("let result = __liva_result.clone();");
+        }
+        if guarded {
+            self.indent();
+        }
+        for contract in &func.ensures {
+            self.generate_contract_assert("assert!", func, "ensures", contract)?;
+        }
+        if guarded {
+            self.dedent();
+            self.writeln("}");
+        }
+        self.dedent();
+        self.writeln("}");
+        self.writeln("__liva_result");
+        Ok(())
+    }
+
+    /// `assert!(cond, "f requires cond (file:line)")`
+    fn generate_contract_assert(
+        &mut self,
+        assert_macro: &str,
+        func: &FunctionDecl,
+        keyword: &str,
+        contract: &Contract,
+    ) -> Result<()> {
+        let condition = {
+            let saved = std::mem::take(&mut self.output);
+            self.generate_expr(&contract.condition)?;
+            std::mem::replace(&mut self.output, saved)
+        };
+        let location = if self.source_filename.is_empty() {
+            format!("line {}", contract.line)
+        } else {
+            format!("{}:{}", self.source_filename, contract.line)
+        };
+        let message = format!(
+            "{} {} {} ({})",
+            func.name, keyword, contract.source, location
+        )
+        .replace('{', "{{")
+        .replace('}', "}}");
+        self.writeln(&format!("{}({}, {:?});", assert_macro, condition, message));
+        Ok(())
+    }
+
+    /// Parameters read by an `ensures` clause, as Rust names
+    fn ensures_params(&self, func: &FunctionDecl) -> Vec<String> {
+        func.params
+            .iter()
+            .filter_map(|param| param.name())
+            .map(|name| self.sanitize_name(name))
+            .filter(|name| {
+                func.ensures
+                    .iter()
+                    .any(|contract| self.expr_uses_var(&contract.condition, name))
+            })
+            .collect()
+    }
+
+    /// `init { ... }` becomes `fn liva_init()`, called from `main`
+    fn generate_init(&mut self, init: &InitDecl) -> Result<()> {
+        self.writeln("fn liva_init() {");
+        self.indent();
+        let saved_mutated = std::mem::take(&mut self.mutated_vars);
+        let mut temp_mutated = std::collections::HashSet::new();
+        self.collect_mutated_vars_in_block(&init.body, &mut temp_mutated);
+        self.mutated_vars = temp_mutated;
+
+        self.generate_block_inner(&init.body)?;
+        self.mutated_vars = saved_mutated;
+        self.dedent();
+        self.writeln("}");
+        Ok(())
+    }
+
+    /// Run every module's `init` block before the body of `main`
+    fn generate_init_calls(&mut self) {
+        for call in self.init_calls.clone() {
+            self.writeln(&format!("{};", call));
+        }
+    }
+
     fn generate_test(&mut self, test: &TestDecl) -> Result<()> {
         self.writeln("#[test]");
         self.writeln(&format!(
@@ -4223,7 +5194,9 @@ impl CodeGenerator {
         self.collect_mutated_vars_in_block(&test.body, &mut temp_mutated);
         self.mutated_vars = temp_mutated;
 
+        self.generate_test_prologue(false);
         self.generate_block_inner(&test.body)?;
+        self.generate_test_epilogue(false);
         self.in_test_block = was_in_test;
         self.mutated_vars = saved_mutated;
         self.dedent();
@@ -4231,6 +5204,21 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// `before { ... }` and `after { ... }` are the file's beforeEach and
+    /// afterEach hooks, run by every test of the file and of its describes
+    fn generate_test_hook(&mut self, hook: &TestHookDecl) -> Result<()> {
+        let fn_name = match hook.kind {
+            TestHookKind::Before => "before_each",
+            TestHookKind::After => "after_each",
+        };
+        self.writeln("#[cfg(test)]");
+        let was_in_test = self.in_test_block;
+        self.in_test_block = true;
+        self.generate_hook_fn(fn_name, &LambdaBody::Block(hook.body.clone()))?;
+        self.in_test_block = was_in_test;
+        Ok(())
+    }
+
     // ─── liva/test virtual library codegen ───────────────────────────
 
     /// Scan a describe block's statements to detect which lifecycle hooks are present.
@@ -4271,6 +5259,31 @@ impl CodeGenerator {
         scope
     }
 
+    /// The file-level hooks, from its `before`/`after` blocks. `None` when
+    /// the file has neither.
+    fn scan_file_for_hooks(&self, items: &[TopLevel]) -> Option<TestHookScope> {
+        let mut scope = TestHookScope {
+            depth: self.test_hooks_stack.len(),
+            ..Default::default()
+        };
+        for item in items {
+            if let TopLevel::TestHook(hook) = item {
+                let is_async = hook.body.stmts.iter().any(ast_stmt_has_async);
+                match hook.kind {
+                    TestHookKind::Before => {
+                        scope.has_before_each = true;
+                        scope.before_each_is_async = is_async;
+                    }
+                    TestHookKind::After => {
+                        scope.has_after_each = true;
+                        scope.after_each_is_async = is_async;
+                    }
+                }
+            }
+        }
+        (scope.has_before_each || scope.has_after_each).then_some(scope)
+    }
+
     /// Collect all hook function names that should be called for each test,
     /// traversing the entire hooks stack (parent describes + current).
     /// Returns (fn_name, is_async) pairs.
@@ -4309,10 +5322,12 @@ impl CodeGenerator {
     fn generate_test_describe(&mut self, call: &CallExpr) -> Result<()> {
         // describe("name", () => { ... })
         if call.args.len() < 2 {
-            return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                "E3000",
-                "describe() requires 2 arguments",
-                "describe(name: string, callback: () => void)",
+            return Err(CompilerError::CodegenError(Box::new(
+                SemanticErrorInfo::new(
+                    "E3000",
+                    "describe() requires 2 arguments",
+                    "describe(name: string, callback: () => void)",
+                ),
             )));
         }
 
@@ -4326,10 +5341,12 @@ impl CodeGenerator {
         let lambda_body = match &call.args[1] {
             Expr::Lambda(lambda) => &lambda.body,
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    "describe() second argument must be a function",
-                    "describe(\"name\", () => { ... })",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        "describe() second argument must be a function",
+                        "describe(\"name\", () => { ... })",
+                    ),
                 )));
             }
         };
@@ -4379,10 +5396,12 @@ impl CodeGenerator {
     fn generate_test_case(&mut self, call: &CallExpr) -> Result<()> {
         // test("name", () => { ... })
         if call.args.len() < 2 {
-            return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                "E3000",
-                "test() requires 2 arguments",
-                "test(name: string, callback: () => void)",
+            return Err(CompilerError::CodegenError(Box::new(
+                SemanticErrorInfo::new(
+                    "E3000",
+                    "test() requires 2 arguments",
+                    "test(name: string, callback: () => void)",
+                ),
             )));
         }
 
@@ -4405,10 +5424,12 @@ impl CodeGenerator {
         let lambda_body = match &call.args[1] {
             Expr::Lambda(lambda) => &lambda.body,
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    "test() second argument must be a function",
-                    "test(\"name\", () => { ... })",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        "test() second argument must be a function",
+                        "test(\"name\", () => { ... })",
+                    ),
                 )));
             }
         };
@@ -4436,15 +5457,7 @@ impl CodeGenerator {
             self.mutated_vars = temp_mutated;
         }
 
-        // Auto-invoke beforeEach hooks (from all parent describe scopes + current)
-        let before_hooks = self.collect_before_each_hooks();
-        for (hook_fn, hook_is_async) in &before_hooks {
-            if is_async && *hook_is_async {
-                self.writeln(&format!("{}().await;", hook_fn));
-            } else {
-                self.writeln(&format!("{}();", hook_fn));
-            }
-        }
+        self.generate_test_prologue(is_async);
 
         match lambda_body {
             LambdaBody::Block(block) => {
@@ -4457,7 +5470,48 @@ impl CodeGenerator {
             }
         }
 
-        // Auto-invoke afterEach hooks (innermost first, then parent scopes)
+        self.generate_test_epilogue(is_async);
+
+        self.in_test_block = was_in_test;
+        self.mutated_vars = saved_mutated;
+        self.dedent();
+        self.writeln("}");
+        Ok(())
+    }
+
+    /// The start of every test body: coverage guard, stub and RNG reset, the
+    /// hooks lock and the beforeEach hooks (from all parent describe scopes + current)
+    fn generate_test_prologue(&mut self, is_async: bool) {
+        if self.ctx.coverage {
+            self.writeln("let _liva_cov = crate::LivaCoverageGuard;");
+        }
+        // Before beforeEach, which may set stubs of its own
+        if self.ctx.has_mocks {
+            self.writeln("liva_rt::clear_mocks();");
+        }
+        if self.ctx.deterministic && self.ctx.has_random {
+            self.writeln("crate::liva_reseed();");
+        }
+
+        let before_hooks = self.collect_before_each_hooks();
+        // Hooks share fixtures between tests, which cargo runs in parallel
+        if !before_hooks.is_empty() || !self.collect_after_each_hooks().is_empty() {
+            self.writeln(
+                "let _liva_hooks = crate::LIVA_TEST_HOOKS.lock().unwrap_or_else(|e| e.into_inner());",
+            );
+        }
+        for (hook_fn, hook_is_async) in &before_hooks {
+            if is_async && *hook_is_async {
+                self.writeln(&format!("{}().await;", hook_fn));
+            } else {
+                self.writeln(&format!("{}();", hook_fn));
+            }
+        }
+    }
+
+    /// The end of every test body: the afterEach hooks (innermost first,
+    /// then parent scopes)
+    fn generate_test_epilogue(&mut self, is_async: bool) {
         let after_hooks = self.collect_after_each_hooks();
         for (hook_fn, hook_is_async) in &after_hooks {
             if is_async && *hook_is_async {
@@ -4466,12 +5520,6 @@ impl CodeGenerator {
                 self.writeln(&format!("{}();", hook_fn));
             }
         }
-
-        self.in_test_block = was_in_test;
-        self.mutated_vars = saved_mutated;
-        self.dedent();
-        self.writeln("}");
-        Ok(())
     }
 
     /// Generate lifecycle hooks (beforeEach, afterEach, beforeAll, afterAll)
@@ -4498,8 +5546,11 @@ impl CodeGenerator {
             Expr::Lambda(lambda) => &lambda.body,
             _ => return Ok(()),
         };
+        self.generate_hook_fn(&fn_name, lambda_body)
+    }
 
-        // Detect if the hook body contains async calls
+    /// A hook's helper function, `async` if the body awaits anything
+    fn generate_hook_fn(&mut self, fn_name: &str, lambda_body: &LambdaBody) -> Result<()> {
         let is_async = ast_lambda_body_has_async(lambda_body);
 
         if is_async {
@@ -4588,10 +5639,12 @@ impl CodeGenerator {
         // Helper to generate expected arg
         let gen_expected = |this: &mut Self| -> Result<String> {
             if method_call.args.is_empty() {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    "Matcher requires 1 argument",
-                    "expect(actual).toBe(expected)",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        "Matcher requires 1 argument",
+                        "expect(actual).toBe(expected)",
+                    ),
                 )));
             }
             let saved = std::mem::take(&mut this.output);
@@ -4755,11 +5808,11 @@ impl CodeGenerator {
                 }
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown matcher: {}", matcher),
                     "Available: toBe, toEqual, toBeTruthy, toBeFalsy, toBeGreaterThan, toBeLessThan, toContain, toBeNull, toThrow",
-                )));
+                ))));
             }
         };
 
@@ -4834,6 +5887,9 @@ impl CodeGenerator {
                             self.var_types.insert(param_name.clone(), tname.clone());
                         }
                     }
+                    if let Some(members) = self.union_type_members(type_ref) {
+                        self.union_vars.insert(param_name.clone(), members);
+                    }
                     // Track array-typed parameters in typed_array_vars and array_vars
                     // so that forEach/map/filter generate correct lambda patterns
                     // e.g., segments: [string] → typed_array_vars["segments"] = "string"
@@ -4911,12 +5967,151 @@ impl CodeGenerator {
     }
 
     fn generate_block_inner(&mut self, block: &BlockStmt) -> Result<()> {
-        for stmt in &block.stmts {
+        let has_lines = block.stmt_lines.len() == block.stmts.len();
+        let guards = self.guard_narrowed_vars.len();
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            if has_lines {
+                self.write_line_hooks(block.stmt_lines[i]);
+            }
+            self.generate_stmt(stmt)?;
+        }
+        // Variables unwrapped by a guard in this block are optional again outside it
+        for var_name in self.guard_narrowed_vars.split_off(guards) {
+            self.option_value_vars.insert(var_name);
+        }
+        Ok(())
+    }
+
+    /// Per-statement instrumentation for the statement that starts on Liva `line`:
+    /// a `--coverage` hit counter and/or a line marker for `--debug` or the
+    /// source map. The marker is written last so the statement's code begins
+    /// on the line after it.
+    fn write_line_hooks(&mut self, line: u32) {
+        if self.ctx.coverage {
+            self.writeln(&format!(
+                "crate::liva_cov_hit({:?}, {});",
+                self.line_hook_file, line
+            ));
+        }
+        if self.ctx.debug || self.ctx.source_map {
+            self.writeln(&crate::debuginfo::line_marker(&self.line_hook_file, line));
+        }
+    }
+
+    /// Block body of a lambda. `return` anywhere exits the lambda, so the
+    /// enclosing function's `Ok(..)`/`Some(..)` return wrapping is switched off
+    /// inside it. The last statement is the lambda's value: a final `return x`
+    /// or an `if`/`else` whose branches all end in `return x` becomes the tail
+    /// expression.
+    fn generate_lambda_block(
+        &mut self,
+        lambda: &LambdaExpr,
+        block: &BlockStmt,
+        is_json_value: bool,
+        element_type: Option<&str>,
+    ) -> Result<()> {
+        if block.stmts.is_empty()
+            || matches!(
+                block.stmts.as_slice(),
+                [Stmt::Return(ReturnStmt { expr: None })]
+            )
+        {
+            self.output.push_str("()");
+            return Ok(());
+        }
+
+        self.output.push('{');
+        self.indent();
+        self.output.push('\n');
+
+        // Generate destructuring for lambda params (if any)
+        for (idx, param) in lambda.params.iter().enumerate() {
+            if param.is_destructuring() {
+                let temp_name = format!("_param_{}", idx);
+                self.write_indent();
+                self.generate_lambda_param_destructuring(
+                    &param.pattern,
+                    &temp_name,
+                    is_json_value,
+                    element_type,
+                )?;
+                self.output.push('\n');
+            }
+        }
+
+        let was_fallible = std::mem::replace(&mut self.in_fallible_function, false);
+        let was_optional = std::mem::replace(&mut self.in_optional_function, false);
+        let prev_return_type = self.current_return_type.take();
+        let result = self.generate_lambda_block_stmts(&block.stmts);
+        self.in_fallible_function = was_fallible;
+        self.in_optional_function = was_optional;
+        self.current_return_type = prev_return_type;
+        result?;
+
+        self.dedent();
+        self.write_indent();
+        self.output.push('}');
+        Ok(())
+    }
+
+    fn generate_lambda_block_stmts(&mut self, stmts: &[Stmt]) -> Result<()> {
+        let Some((last, init)) = stmts.split_last() else {
+            return Ok(());
+        };
+        for stmt in init {
             self.generate_stmt(stmt)?;
         }
+        match last {
+            Stmt::Return(ReturnStmt { expr: Some(expr) }) => {
+                self.write_indent();
+                self.generate_expr(expr)?;
+                // Early returns go through `generate_return_expr`, which makes
+                // string literals owned; the tail has to match
+                if matches!(expr, Expr::Literal(Literal::String(_))) && stmts_have_return(init) {
+                    self.output.push_str(".to_string()");
+                }
+                self.output.push('\n');
+            }
+            // `if c { return a } else { return b }` → `if c { a } else { b }`.
+            // Null checks keep the statement form, which narrows the Option.
+            Stmt::If(if_stmt)
+                if if_yields_value(if_stmt)
+                    && self.extract_option_null_check(&if_stmt.condition).is_none() =>
+            {
+                self.write_indent();
+                self.generate_stmt_as_tail_expr(last)?;
+                self.output.push('\n');
+            }
+            _ => self.generate_stmt(last)?,
+        }
         Ok(())
     }
 
+    /// Name the element is bound to for a `sortBy`/`minBy`/`maxBy`/`groupBy` key
+    fn key_lambda_param(&self, lambda: &LambdaExpr) -> String {
+        lambda
+            .params
+            .first()
+            .and_then(|p| p.name())
+            .map(|n| self.sanitize_name(n))
+            .unwrap_or_else(|| "__x".to_string())
+    }
+
+    /// Key of a `sortBy`/`minBy`/`maxBy`/`groupBy` callback, with the element
+    /// already bound to the parameter name. A block body runs as a closure
+    /// called in place, so its `return` yields the key.
+    fn generate_key_lambda_body(&mut self, lambda: &LambdaExpr) -> Result<()> {
+        match &lambda.body {
+            LambdaBody::Expr(expr) => self.generate_expr(expr),
+            LambdaBody::Block(block) => {
+                self.output.push_str("(|| ");
+                self.generate_lambda_block(lambda, block, false, None)?;
+                self.output.push_str(")()");
+                Ok(())
+            }
+        }
+    }
+
     /// B135: Generate a statement as a tail-expression (no trailing semicolon),
     /// recognising `Stmt::If` and `Stmt::Return(Some(_))` as expression-producing
     /// constructs. Used in switch-arm Block bodies so the arm yields a value.
@@ -4947,6 +6142,16 @@ impl CodeGenerator {
                 self.generate_if_body_as_tail(&if_stmt.then_branch)?;
                 self.dedent();
                 self.output.push('\n');
+                for link in &if_stmt.else_ifs {
+                    self.write_indent();
+                    self.output.push_str("} else if ");
+                    self.generate_condition_expr(&link.condition)?;
+                    self.output.push_str(" {\n");
+                    self.indent();
+                    self.generate_if_body_as_tail(&link.body)?;
+                    self.dedent();
+                    self.output.push('\n');
+                }
                 self.write_indent();
                 self.output.push_str("} else {\n");
                 self.indent();
@@ -4972,7 +6177,11 @@ impl CodeGenerator {
                 if block.stmts.is_empty() {
                     return Ok(());
                 }
+                let has_lines = block.stmt_lines.len() == block.stmts.len();
                 for (i, stmt) in block.stmts.iter().enumerate() {
+                    if has_lines {
+                        self.write_line_hooks(block.stmt_lines[i]);
+                    }
                     if i == block.stmts.len() - 1 {
                         self.write_indent();
                         self.generate_stmt_as_tail_expr(stmt)?;
@@ -4989,12 +6198,95 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// `if condition { body }` of an `if` or `else if`, from the `if` keyword
+    /// to the closing brace
+    fn generate_if_branch(&mut self, condition: &Expr, body: &IfBody) -> Result<()> {
+        // BUG-007: Detect `if x != null { ... }` where x is Option<T>
+        // Transform to `if let Some(x) = x { ... }` for type narrowing
+        let option_null_var = self.extract_option_null_check(condition);
+
+        // B130 (truthy form): `if err { ... }` where `err` is an error binding
+        // (Option<liva_rt::Error>) — still emit `if cond` (which already lowers
+        // to `err.is_some()` via condition rewrite), but record the narrowing
+        // so `err.message` resolves to a `String`-typed field access.
+        let mut truthy_error_var: Option<String> = None;
+        if option_null_var.is_none() {
+            if let Expr::Identifier(name) = condition {
+                let sanitized = self.sanitize_name(name);
+                if self.error_binding_vars.contains(&sanitized) {
+                    truthy_error_var = Some(sanitized);
+                }
+            }
+        }
+
+        if let Some(ref var_name) = option_null_var {
+            // Generate: if let Some(var) = var { ... }
+            writeln!(self.output, "if let Some({}) = {} {{", var_name, var_name).unwrap();
+        } else {
+            self.output.push_str("if ");
+            self.generate_condition_expr(condition)?;
+            self.output.push_str(" {\n");
+        }
+        self.indent();
+        // BUG-007: Inside the narrowed block, the variable is no longer Option
+        let mut narrowed_error_var: Option<String> = None;
+        if let Some(ref var_name) = option_null_var {
+            self.option_value_vars.remove(var_name);
+            // B130: error binding vars (Option<liva_rt::Error>) get the same narrowing.
+            // We don't remove from error_binding_vars (other code depends on it),
+            // but we record the narrowing for the .message access site.
+            if self.error_binding_vars.contains(var_name) {
+                self.narrowed_error_binding_vars.insert(var_name.clone());
+                narrowed_error_var = Some(var_name.clone());
+            }
+        }
+        if let Some(ref var_name) = truthy_error_var {
+            // Same narrowing for `if err { ... }` truthy form.
+            self.narrowed_error_binding_vars.insert(var_name.clone());
+            self.truthy_narrowed_error_binding_vars
+                .insert(var_name.clone());
+        }
+        self.generate_if_body(body)?;
+        // BUG-007: Restore Option tracking after the block
+        if let Some(ref var_name) = option_null_var {
+            self.option_value_vars.insert(var_name.clone());
+        }
+        if let Some(name) = narrowed_error_var {
+            self.narrowed_error_binding_vars.remove(&name);
+        }
+        if let Some(name) = truthy_error_var {
+            self.narrowed_error_binding_vars.remove(&name);
+            self.truthy_narrowed_error_binding_vars.remove(&name);
+        }
+        self.dedent();
+        self.write_indent();
+        self.output.push('}');
+        Ok(())
+    }
+
+    /// ` else { body }` after the closing brace of an `if`
+    fn generate_else_body(&mut self, body: &IfBody) -> Result<()> {
+        self.output.push_str(" else {\n");
+        self.indent();
+        self.generate_if_body(body)?;
+        self.dedent();
+        self.write_indent();
+        self.output.push('}');
+        Ok(())
+    }
+
+    /// Whether [`Self::generate_narrowing_if`] handles an `if` on `condition`
+    fn is_narrowing_check(&mut self, condition: &Expr) -> bool {
+        self.extract_union_type_check(condition).is_some()
+            || self
+                .extract_option_is_null_check(condition)
+                .is_some_and(|name| self.option_value_vars.contains(&name))
+    }
+
     fn generate_if_body(&mut self, body: &IfBody) -> Result<()> {
         match body {
             IfBody::Block(block) => {
-                for stmt in &block.stmts {
-                    self.generate_stmt(stmt)?;
-                }
+                self.generate_block_inner(block)?;
             }
             IfBody::Stmt(stmt) => {
                 self.generate_stmt(stmt)?;
@@ -5004,6 +6296,14 @@ impl CodeGenerator {
     }
 
     fn generate_stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        grow_stack(|| self.generate_stmt_kind(stmt))
+    }
+
+    fn generate_stmt_kind(&mut self, stmt: &Stmt) -> Result<()> {
+        if let Some(lowered) = lower_parse_cast(stmt) {
+            return self.generate_stmt(&lowered);
+        }
+
         // Phase 4: Check if this statement uses multiple pending tasks (join combining optimization)
         let used_tasks = self.stmt_uses_pending_tasks(stmt);
 
@@ -5026,12 +6326,21 @@ impl CodeGenerator {
 
                 // Handle `or fail "message"` — error propagation shorthand (v1.1.0)
                 if let Some(fail_msg) = &var.or_fail_msg {
+                    // `.expect("msg")` panics with the error instead of returning it
+                    let is_expect = var.combinator == Some(ResultCombinator::Expect);
+                    let fail_with = if is_expect {
+                        "panic!(\"{}\", "
+                    } else {
+                        "return Err("
+                    };
+
                     // Check if this is bare `or fail` (no message) — Bug #95 fix
                     // Bare `or fail` propagates the original error unchanged.
-                    let is_bare_or_fail = matches!(
-                        fail_msg.as_ref(),
-                        Expr::Literal(crate::ast::Literal::String(s)) if s.is_empty()
-                    );
+                    let is_bare_or_fail = !is_expect
+                        && matches!(
+                            fail_msg.as_ref(),
+                            Expr::Literal(crate::ast::Literal::String(s)) if s.is_empty()
+                        );
 
                     // let x = fallible_expr or fail "message"
                     // Generates: let x = match fallible_expr { Ok(v) => v, Err(e) => return Err(liva_rt::Error::chain("message", fn, loc, e)) };
@@ -5058,7 +6367,8 @@ impl CodeGenerator {
                         self.generate_expr(&var.init)?;
                         write!(
                             self.output,
-                            ".await; if !err_str.is_empty() {{ return Err(liva_rt::Error::new(",
+                            ".await; if !err_str.is_empty() {{ {}liva_rt::Error::new(",
+                            fail_with
                         )
                         .unwrap();
                         if is_bare_or_fail {
@@ -5081,7 +6391,8 @@ impl CodeGenerator {
                         self.generate_expr(&var.init)?;
                         write!(
                             self.output,
-                            "; if !err_str.is_empty() {{ return Err(liva_rt::Error::new(",
+                            "; if !err_str.is_empty() {{ {}liva_rt::Error::new(",
+                            fail_with
                         )
                         .unwrap();
                         if is_bare_or_fail {
@@ -5101,7 +6412,8 @@ impl CodeGenerator {
                         self.generate_expr(&var.init)?;
                         write!(
                             self.output,
-                            "; if !err_str.is_empty() {{ return Err(liva_rt::Error::new(",
+                            "; if !err_str.is_empty() {{ {}liva_rt::Error::new(",
+                            fail_with
                         )
                         .unwrap();
                         if is_bare_or_fail {
@@ -5138,9 +6450,12 @@ impl CodeGenerator {
                                 .push_str(" { Ok(v) => v, Err(e) => return Err(e) };\n");
                         } else {
                             // Chain: Err(e) => return Err(Error::chain("msg", fn, loc, e))
-                            self.output.push_str(
-                                " { Ok(v) => v, Err(e) => return Err(liva_rt::Error::chain(",
-                            );
+                            write!(
+                                self.output,
+                                " {{ Ok(v) => v, Err(e) => {}liva_rt::Error::chain(",
+                                fail_with
+                            )
+                            .unwrap();
                             self.generate_expr(fail_msg)?;
                             write!(
                                 self.output,
@@ -5165,10 +6480,16 @@ impl CodeGenerator {
                         .unwrap();
                         self.generate_expr(&var.init)?;
                         self.suppress_option_unwrap = false;
-                        self.output
-                            .push_str(" { Some(v) => v, None => panic!(\"or fail: {}\", ");
+                        if is_expect {
+                            self.output
+                                .push_str(" { Some(v) => v, None => panic!(\"{}\", ");
+                        } else {
+                            self.output
+                                .push_str(" { Some(v) => v, None => panic!(\"or fail: {}\", ");
+                        }
                         self.generate_expr(fail_msg)?;
                         self.output.push_str(") };\n");
+                        self.track_min_max_by_result(&var_name, &var.init);
                     } else if self.is_map_get_call(&var.init) {
                         // B132: Map.get(k) or fail "msg" — propagate the failure when the key is absent.
                         // Generates: match map.get(&k).cloned() { Some(v) => v, None => return Err(...) };
@@ -5186,8 +6507,12 @@ impl CodeGenerator {
                         .unwrap();
                         self.generate_expr(&var.init)?;
                         self.suppress_map_get_unwrap = false;
-                        self.output
-                            .push_str(" { Some(v) => v, None => return Err(liva_rt::Error::new(");
+                        write!(
+                            self.output,
+                            " {{ Some(v) => v, None => {}liva_rt::Error::new(",
+                            fail_with
+                        )
+                        .unwrap();
                         if is_bare_or_fail {
                             self.output.push_str("\"map: missing key\".to_string()");
                         } else {
@@ -5214,7 +6539,12 @@ impl CodeGenerator {
                             )
                             .unwrap();
                             self.generate_expr(&call.args[0])?;
-                            write!(self.output, ".parse::<{}>() {{ Ok(v) => v, Err(e) => return Err(liva_rt::Error::", parse_t).unwrap();
+                            write!(
+                                self.output,
+                                ".parse::<{}>() {{ Ok(v) => v, Err(e) => {}liva_rt::Error::",
+                                parse_t, fail_with
+                            )
+                            .unwrap();
                             if is_bare_or_fail {
                                 self.output.push_str("from(e.to_string()))");
                             } else {
@@ -5259,7 +6589,12 @@ impl CodeGenerator {
                             )
                             .unwrap();
                             self.generate_expr(&mc.object)?;
-                            write!(self.output, ".parse::<{}>() {{ Ok(v) => v, Err(e) => return Err(liva_rt::Error::", parse_t).unwrap();
+                            write!(
+                                self.output,
+                                ".parse::<{}>() {{ Ok(v) => v, Err(e) => {}liva_rt::Error::",
+                                parse_t, fail_with
+                            )
+                            .unwrap();
                             if is_bare_or_fail {
                                 self.output.push_str("from(e.to_string()))");
                             } else {
@@ -5437,6 +6772,7 @@ impl CodeGenerator {
                         self.output.push_str(".unwrap_or(");
                         self.generate_expr(default_val)?;
                         self.output.push_str(");\n");
+                        self.track_min_max_by_result(&var_name, &var.init);
                     } else if let Expr::Call(call) = &var.init {
                         // B16 fix: parseInt/parseFloat with or default
                         // Generate: let var = match arg.parse::<T>() { Ok(v) => v, Err(_) => default };
@@ -6019,17 +7355,21 @@ impl CodeGenerator {
                             }
                         }
                     }
+
+                    if let Some(ResultCombinator::MapErr(map)) = &var.combinator {
+                        self.generate_map_err(&binding_names[1], map)?;
+                    }
                 } else {
                     // Normal binding: let a = expr (only one binding expected)
                     if var.bindings.len() != 1 {
-                        return Err(CompilerError::CodegenError(
+                        return Err(CompilerError::CodegenError(Box::new(
                             SemanticErrorInfo::new(
                                 "E3000",
                                 "Invalid binding pattern",
                                 "Let statement should have exactly one binding when not using fallible pattern"
                             )
                             .with_help("Use fallible binding pattern 'let result, err = ...' or single binding 'let result = ...'")
-                        ));
+                        )));
                     }
                     let binding = &var.bindings[0];
 
@@ -6109,6 +7449,13 @@ impl CodeGenerator {
                             return Ok(());
                         }
 
+                        // Copies of a frozen binding share its value
+                        if self.is_frozen_expr(&var.init) {
+                            if let Some(name) = binding.name() {
+                                self.frozen_vars.insert(self.sanitize_name(name));
+                            }
+                        }
+
                         // Check if initializing with a string literal or string expression - mark variable as string
                         if let Expr::Literal(Literal::String(_)) = &var.init {
                             if let Some(name) = binding.name() {
@@ -6132,6 +7479,25 @@ impl CodeGenerator {
                                 }
                             }
                         }
+                        // `x as float` and `x as string` have a known type
+                        if let Expr::Cast {
+                            target: TypeRef::Simple(target),
+                            ..
+                        } = &var.init
+                        {
+                            if let Some(name) = binding.name() {
+                                let name = self.sanitize_name(name);
+                                match target.as_str() {
+                                    "float" => {
+                                        self.float_vars.insert(name);
+                                    }
+                                    "string" => {
+                                        self.string_vars.insert(name);
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
                         // B32: Track float variables for mixed-type arithmetic detection
                         if let Expr::Literal(Literal::Float(_)) = &var.init {
                             if let Some(name) = binding.name() {
@@ -6277,6 +7643,7 @@ impl CodeGenerator {
                                     | "drop"
                                     | "slice"
                                     | "chunks"
+                                    | "windows"
                                     | "flatMap"
                             ) {
                                 if let Some(name) = binding.name() {
@@ -6321,6 +7688,14 @@ impl CodeGenerator {
                                     self.map_vars.insert(name.to_string());
                                 }
                             }
+                            // minBy/maxBy return Option<T> of the array's element type
+                            else if matches!(method_call.method.as_str(), "minBy" | "maxBy") {
+                                if let Some(name) = binding.name() {
+                                    let san_name = self.sanitize_name(name);
+                                    self.option_value_vars.insert(san_name.clone());
+                                    self.track_min_max_by_result(&san_name, &var.init);
+                                }
+                            }
                             // B110 fix: union/intersection/difference on Set return HashSet — track as set
                             else if matches!(
                                 method_call.method.as_str(),
@@ -6330,10 +7705,10 @@ impl CodeGenerator {
                                     self.set_vars.insert(self.sanitize_name(name));
                                 }
                             }
-                            // Sys.args() returns Vec<String> - need direct indexing
+                            // Sys.args()/Process.args() return Vec<String> - need direct indexing
                             else if method_call.method.as_str() == "args" {
                                 if let Expr::Identifier(obj_name) = method_call.object.as_ref() {
-                                    if obj_name == "Sys" {
+                                    if obj_name == "Sys" || obj_name == "Process" {
                                         if let Some(name) = binding.name() {
                                             self.native_vec_string_vars.insert(name.to_string());
                                             self.array_vars.insert(name.to_string());
@@ -6357,6 +7732,12 @@ impl CodeGenerator {
                                     self.array_vars.insert(name.to_string());
                                 }
                             }
+                            // Array.fill / Array.from / range.toArray() build a Vec
+                            else if method_call.is_array_constructor() {
+                                if let Some(name) = binding.name() {
+                                    self.array_vars.insert(name.to_string());
+                                }
+                            }
                             // Date.now() / Date.new() returns chrono::NaiveDateTime
                             if let Expr::Identifier(obj_name) = method_call.object.as_ref() {
                                 // FIX-4: Track variables assigned from enum variant construction
@@ -6425,8 +7806,12 @@ impl CodeGenerator {
                             if let Expr::Identifier(class_name) = &*call.callee {
                                 if let Some(name) = binding.name() {
                                     let san_name = self.sanitize_name(name);
+                                    if class_name == "clone" || class_name == "freeze" {
+                                        self.track_wrapped_var(&san_name, class_name, &call.args);
+                                    }
                                     // BUG-007: Track variables assigned from optional-returning functions
-                                    if self.optional_returning_functions.contains(class_name) {
+                                    else if self.optional_returning_functions.contains(class_name)
+                                    {
                                         self.option_value_vars.insert(san_name.clone());
                                     }
                                     // Check if this is an array-returning function
@@ -6584,8 +7969,22 @@ impl CodeGenerator {
                         // Check if we need to wrap in a union variant
                         let (needs_union_close, mut needs_to_string) =
                             if let Some(type_ref) = &binding.type_ref {
-                                self.maybe_wrap_in_union(type_ref, &var.init)
+                                match self.union_type_members(type_ref) {
+                                    Some(members) => {
+                                        self.union_vars.insert(var_name.clone(), members.clone());
+                                        if self.union_members_of_expr(&var.init).is_some() {
+                                            (false, false)
+                                        } else {
+                                            let union = TypeRef::Union(members);
+                                            self.maybe_wrap_in_union(&union, &var.init)
+                                        }
+                                    }
+                                    None => (false, false),
+                                }
                             } else {
+                                if let Some(members) = self.union_members_of_expr(&var.init) {
+                                    self.union_vars.insert(var_name.clone(), members);
+                                }
                                 (false, false)
                             };
 
@@ -6688,9 +8087,7 @@ impl CodeGenerator {
                 }
             }
             Stmt::ConstDecl(const_decl) => {
-                self.write_indent();
-                write!(self.output, "const {}: ", const_decl.name.to_uppercase()).unwrap();
-                let type_str = if let Some(type_ref) = &const_decl.type_ref {
+                let ty = if let Some(type_ref) = &const_decl.type_ref {
                     let rust_type = type_ref.to_rust_type();
                     // B31: const string can't use String (heap-allocated), must use &str
                     if rust_type == "String" {
@@ -6701,21 +8098,15 @@ impl CodeGenerator {
                 } else {
                     self.infer_const_type(&const_decl.init)
                 };
-                self.output.push_str(&type_str);
-                self.output.push_str(" = ");
-                // B31: For const string, don't add .to_string() — just use the literal directly
-                let is_const_str = type_str == "&str";
-                if is_const_str {
-                    // Generate string literal without .to_string() conversion
-                    if let Expr::Literal(Literal::String(s)) = &const_decl.init {
-                        write!(self.output, "\"{}\"", s).unwrap();
-                    } else {
-                        self.generate_expr(&const_decl.init)?;
-                    }
-                } else {
-                    self.generate_expr(&const_decl.init)?;
-                }
-                self.output.push_str(";\n");
+                // B31: a const string is the bare literal, without .to_string()
+                let item = rust_ast::Item::Const {
+                    name: const_decl.name.to_uppercase(),
+                    ty,
+                    init: self.expr_node(&const_decl.init)?,
+                };
+                self.write_indent();
+                self.output.push_str(&item.print(self.indent_level));
+                self.output.push('\n');
             }
             Stmt::Assign(assign) => {
                 // ── push_str optimization: x = x + expr → x.push_str(...) ──
@@ -6814,10 +8205,18 @@ impl CodeGenerator {
                     self.output.push_str("Some(");
                 }
 
-                // If assigning a string literal to what might be a String field, add .to_string()
+                // A string literal assigned to a field of a known class converts
+                // like an argument; anything else might be a String, so add .to_string()
                 if let Expr::Literal(Literal::String(_)) = &assign.value {
-                    self.generate_expr(&assign.value)?;
-                    self.output.push_str(".to_string()");
+                    match self.assigned_field_type(&assign.target) {
+                        Some(declared) => {
+                            self.generate_string_literal(declared, "field", &assign.value)?
+                        }
+                        None => {
+                            self.generate_expr(&assign.value)?;
+                            self.output.push_str(".to_string()");
+                        }
+                    }
                 } else {
                     self.generate_expr(&assign.value)?;
                 }
@@ -6829,75 +8228,25 @@ impl CodeGenerator {
                 self.output.push_str(";\n");
             }
             Stmt::If(if_stmt) => {
-                // BUG-007: Detect `if x != null { ... }` where x is Option<T>
-                // Transform to `if let Some(x) = x { ... }` for type narrowing
-                let option_null_var = self.extract_option_null_check(&if_stmt.condition);
-
-                // B130 (truthy form): `if err { ... }` where `err` is an error binding
-                // (Option<liva_rt::Error>) — still emit `if cond` (which already lowers
-                // to `err.is_some()` via condition rewrite), but record the narrowing
-                // so `err.message` resolves to a `String`-typed field access.
-                let mut truthy_error_var: Option<String> = None;
-                if option_null_var.is_none() {
-                    if let Expr::Identifier(name) = &if_stmt.condition {
-                        let sanitized = self.sanitize_name(name);
-                        if self.error_binding_vars.contains(&sanitized) {
-                            truthy_error_var = Some(sanitized);
-                        }
-                    }
-                }
-
-                self.write_indent();
-                if let Some(ref var_name) = option_null_var {
-                    // Generate: if let Some(var) = var { ... }
-                    write!(self.output, "if let Some({}) = {} {{\n", var_name, var_name).unwrap();
-                } else {
-                    self.output.push_str("if ");
-                    self.generate_condition_expr(&if_stmt.condition)?;
-                    self.output.push_str(" {\n");
-                }
-                self.indent();
-                // BUG-007: Inside the narrowed block, the variable is no longer Option
-                let mut narrowed_error_var: Option<String> = None;
-                if let Some(ref var_name) = option_null_var {
-                    self.option_value_vars.remove(var_name);
-                    // B130: error binding vars (Option<liva_rt::Error>) get the same narrowing.
-                    // We don't remove from error_binding_vars (other code depends on it),
-                    // but we record the narrowing for the .message access site.
-                    if self.error_binding_vars.contains(var_name) {
-                        self.narrowed_error_binding_vars.insert(var_name.clone());
-                        narrowed_error_var = Some(var_name.clone());
-                    }
-                }
-                if let Some(ref var_name) = truthy_error_var {
-                    // Same narrowing for `if err { ... }` truthy form.
-                    self.narrowed_error_binding_vars.insert(var_name.clone());
-                    self.truthy_narrowed_error_binding_vars
-                        .insert(var_name.clone());
-                }
-                self.generate_if_body(&if_stmt.then_branch)?;
-                // BUG-007: Restore Option tracking after the block
-                if let Some(ref var_name) = option_null_var {
-                    self.option_value_vars.insert(var_name.clone());
-                }
-                if let Some(name) = narrowed_error_var {
-                    self.narrowed_error_binding_vars.remove(&name);
-                }
-                if let Some(name) = truthy_error_var {
-                    self.narrowed_error_binding_vars.remove(&name);
-                    self.truthy_narrowed_error_binding_vars.remove(&name);
+                if self.generate_narrowing_if(if_stmt)? {
+                    return Ok(());
                 }
-                self.dedent();
                 self.write_indent();
-                self.output.push('}');
-
-                if let Some(else_branch) = &if_stmt.else_branch {
-                    self.output.push_str(" else {\n");
-                    self.indent();
-                    self.generate_if_body(else_branch)?;
-                    self.dedent();
-                    self.write_indent();
-                    self.output.push('}');
+                self.generate_if_branch(&if_stmt.condition, &if_stmt.then_branch)?;
+
+                // `else if` stays flat up to a link whose check narrows a
+                // variable; that one needs an `if` of its own, nested in `else`
+                let nested_from = (0..if_stmt.else_ifs.len())
+                    .find(|&i| self.is_narrowing_check(&if_stmt.else_ifs[i].condition));
+                let flat = nested_from.unwrap_or(if_stmt.else_ifs.len());
+                for link in &if_stmt.else_ifs[..flat] {
+                    self.output.push_str(" else ");
+                    self.generate_if_branch(&link.condition, &link.body)?;
+                }
+                if let Some(from) = nested_from {
+                    self.generate_else_body(&else_if_chain(if_stmt, from))?;
+                } else if let Some(else_branch) = &if_stmt.else_branch {
+                    self.generate_else_body(else_branch)?;
                 }
                 self.output.push('\n');
             }
@@ -6917,6 +8266,9 @@ impl CodeGenerator {
                     let var1_name = self.sanitize_name(&for_stmt.var);
                     let var2_name_san = self.sanitize_name(var2_name);
 
+                    // `enumerate()` / `zip()` already yield (a, b) pairs
+                    let pair_elements = self.pair_iterable_element_types(&for_stmt.iterable);
+
                     // Detect if iterable is a Map (key-value iteration) or Array (enumerate)
                     let is_map_iteration = match &for_stmt.iterable {
                         Expr::Identifier(name) => {
@@ -6976,7 +8328,16 @@ impl CodeGenerator {
                         }
                     }
 
-                    if is_map_iteration {
+                    if let Some((first_type, second_type)) = pair_elements {
+                        // Pair iteration: for i, item in items.enumerate() { ... }
+                        self.write_indent();
+                        write!(self.output, "for ({}, {}) in ", var1_name, var2_name_san).unwrap();
+                        self.generate_expr(&for_stmt.iterable)?;
+                        self.output.push_str(" {\n");
+                        self.indent();
+                        self.track_loop_element(&var1_name, first_type.as_deref());
+                        self.track_loop_element(&var2_name_san, second_type.as_deref());
+                    } else if is_map_iteration {
                         // Map iteration: for key, value in map { ... }
                         // B134: figure out the value type to track var2 in the right set
                         // (string_vars / array_vars / map_vars / class_instance_vars).
@@ -7134,6 +8495,23 @@ impl CodeGenerator {
                             self.map_vars.insert(var_name.clone());
                         }
                     }
+                    // chars() hands out one-character strings
+                    if matches!(&for_stmt.iterable, Expr::MethodCall(mc) if mc.method == "chars") {
+                        self.string_vars.insert(var_name.clone());
+                    }
+                    // windows(n) / chunks(n) hand out sub-arrays of the array's elements
+                    if let Expr::MethodCall(mc) = &for_stmt.iterable {
+                        if matches!(mc.method.as_str(), "windows" | "chunks") {
+                            let element = self.get_base_var_name(&mc.object).and_then(|name| {
+                                self.typed_array_vars
+                                    .get(&self.sanitize_name(&name))
+                                    .or_else(|| self.typed_array_vars.get(&name))
+                                    .cloned()
+                            });
+                            let sub_array = format!("[{}]", element.unwrap_or_default());
+                            self.track_loop_element(&var_name, Some(&sub_array));
+                        }
+                    }
                     // Also handle `for item in obj.field` where obj is a class instance
                     if let Expr::Member { object, property } = &for_stmt.iterable {
                         if let Expr::Identifier(obj_name) = object.as_ref() {
@@ -7528,53 +8906,42 @@ impl CodeGenerator {
                 self.writeln("}");
             }
             Stmt::Throw(throw_stmt) => {
-                self.write_indent();
-                self.output.push_str("panic!(\"{}\", ");
-                self.generate_expr(&throw_stmt.expr)?;
-                self.output.push_str(");\n");
+                let format = rust_ast::Expr::Lit(rust_ast::Lit::Str("{}".to_string()));
+                let message = self.expr_node(&throw_stmt.expr)?;
+                let panic = rust_ast::Expr::Macro("panic".to_string(), vec![format, message]);
+                self.emit_stmt(&rust_ast::Stmt::Expr(panic));
             }
             Stmt::Return(ret) => {
-                self.write_indent();
-                // B128: `return fail X` would otherwise produce
-                // `return Ok(return Err(...));)` (nested return + stray paren).
-                // Treat it as a direct Err return.
-                if let Some(Expr::Fail(inner)) = ret.expr.as_ref() {
-                    self.output.push_str("return Err(liva_rt::Error::from(");
-                    self.generate_expr(inner)?;
-                    self.output.push_str("));\n");
-                    return Ok(());
-                }
-                self.output.push_str("return");
-                if let Some(expr) = &ret.expr {
-                    self.output.push(' ');
-                    if self.in_fallible_function {
-                        self.output.push_str("Ok(");
-                        self.generate_return_expr(expr)?;
-                        self.output.push(')');
-                    } else if self.in_optional_function {
-                        // BUG-006: return value → return Some(value), return null → return None
-                        let is_null = matches!(expr, Expr::Literal(Literal::Null));
-                        if is_null {
-                            self.output.push_str("None");
+                let value = match &ret.expr {
+                    // B128: `return fail X` would otherwise produce
+                    // `return Ok(return Err(...));)` (nested return + stray paren).
+                    // Treat it as a direct Err return.
+                    Some(Expr::Fail(inner)) => Some(self.fail_node(inner)?),
+                    // BUG-006: return value → return Some(value), return null → return None
+                    Some(Expr::Literal(Literal::Null))
+                        if self.in_optional_function && !self.in_fallible_function =>
+                    {
+                        Some(rust_ast::Expr::path("None"))
+                    }
+                    Some(expr) => {
+                        let value = self.generated_node(rust_ast::Prec::Lowest, |this| {
+                            this.generate_return_expr(expr)
+                        })?;
+                        Some(if self.in_fallible_function {
+                            rust_ast::Expr::call("Ok", vec![value])
+                        } else if self.in_optional_function {
+                            rust_ast::Expr::call("Some", vec![value])
                         } else {
-                            self.output.push_str("Some(");
-                            self.generate_return_expr(expr)?;
-                            self.output.push(')');
-                        }
-                    } else {
-                        self.generate_return_expr(expr)?;
+                            value
+                        })
                     }
-                }
-                self.output.push_str(";\n");
-            }
-            Stmt::Break => {
-                self.write_indent();
-                self.output.push_str("break;\n");
-            }
-            Stmt::Continue => {
-                self.write_indent();
-                self.output.push_str("continue;\n");
+                    None => None,
+                };
+                let ret = rust_ast::Expr::Return(value.map(Box::new));
+                self.emit_stmt(&rust_ast::Stmt::Expr(ret));
             }
+            Stmt::Break => self.emit_stmt(&rust_ast::Stmt::Expr(rust_ast::Expr::Break)),
+            Stmt::Continue => self.emit_stmt(&rust_ast::Stmt::Expr(rust_ast::Expr::Continue)),
             Stmt::Expr(expr_stmt) => {
                 // Modern arrow-form switch in statement position:
                 // `switch x { Pat => stmt, _ => {} }` parses to
@@ -7637,6 +9004,53 @@ impl CodeGenerator {
                 self.dedent();
                 self.writeln("}");
             }
+            Stmt::Function(func) => {
+                self.generate_nested_function(func)?;
+            }
+            Stmt::Mock(mock) => {
+                self.write_indent();
+                self.output
+                    .push_str(&format!("liva_rt::mock({:?}, ", mock.target));
+                match mock.target.as_str() {
+                    "Date.now" => self.generate_expr(&mock.value)?,
+                    target if target.starts_with("HTTP.") => {
+                        self.output.push_str("liva_rt::mock_http_response((");
+                        self.generate_expr(&mock.value)?;
+                        self.output.push_str(").to_string())");
+                    }
+                    _ => {
+                        self.output.push('(');
+                        self.generate_expr(&mock.value)?;
+                        self.output.push_str(").to_string()");
+                    }
+                }
+                self.output.push_str(");\n");
+            }
+            Stmt::Scope(scope) => {
+                // Dropping the TaskScope (leaving early) aborts its tasks;
+                // falling off the end joins them
+                let name = format!("__scope_{}", self.task_scopes.len());
+                self.writeln("{");
+                self.indent();
+                self.writeln(&format!("let mut {} = liva_rt::TaskScope::new();", name));
+                self.task_scopes.push(name.clone());
+                let result = self.generate_block_inner(&scope.body);
+                self.task_scopes.pop();
+                result?;
+                if self.in_fallible_function {
+                    self.writeln(&format!(
+                        "if let Err(e) = {}.join().await {{ return Err(e); }}",
+                        name
+                    ));
+                } else {
+                    self.writeln(&format!(
+                        "if let Err(e) = {}.join().await {{ panic!(\"{{}}\", e); }}",
+                        name
+                    ));
+                }
+                self.dedent();
+                self.writeln("}");
+            }
             Stmt::Defer(defer_stmt) => {
                 // Generate a Rust scope guard using Drop trait.
                 // `defer expr` → creates a guard variable that executes expr when dropped.
@@ -7808,6 +9222,69 @@ impl CodeGenerator {
 
     /// Returns true if the expression is a simple Identifier that is a known class instance.
     /// Used to auto-clone on assignment: `let copy = original` → `let copy = original.clone()`
+    fn is_frozen_expr(&self, expr: &Expr) -> bool {
+        matches!(
+            expr,
+            Expr::Identifier(name) if self.frozen_vars.contains(&self.sanitize_name(name))
+        )
+    }
+
+    /// Tracks `name` bound to `clone(value)` or `freeze(value)` the way `value`
+    /// itself is tracked; the `Frozen` wrapper derefs to it
+    fn track_wrapped_var(&mut self, name: &str, wrapper: &str, args: &[Expr]) {
+        if wrapper == "freeze" {
+            self.frozen_vars.insert(name.to_string());
+        }
+        match args.first() {
+            Some(Expr::ArrayLiteral(_)) => {
+                self.array_vars.insert(name.to_string());
+            }
+            Some(Expr::MapLiteral(_)) => {
+                self.map_vars.insert(name.to_string());
+            }
+            Some(Expr::SetLiteral(_)) => {
+                self.set_vars.insert(name.to_string());
+            }
+            Some(Expr::StructLiteral { type_name, .. }) => {
+                self.class_instance_vars.insert(name.to_string());
+                self.var_types.insert(name.to_string(), type_name.clone());
+            }
+            Some(Expr::Call(call)) => {
+                if let Expr::Identifier(class_name) = call.callee.as_ref() {
+                    if class_name.starts_with(|c: char| c.is_uppercase()) {
+                        self.class_instance_vars.insert(name.to_string());
+                        self.var_types.insert(name.to_string(), class_name.clone());
+                    }
+                }
+            }
+            Some(Expr::Identifier(source)) => {
+                let source = self.sanitize_name(source);
+                if self.array_vars.contains(&source) {
+                    self.array_vars.insert(name.to_string());
+                }
+                if let Some(element) = self.typed_array_vars.get(&source).cloned() {
+                    self.typed_array_vars.insert(name.to_string(), element);
+                }
+                if self.map_vars.contains(&source) {
+                    self.map_vars.insert(name.to_string());
+                }
+                if self.set_vars.contains(&source) {
+                    self.set_vars.insert(name.to_string());
+                }
+                if self.string_vars.contains(&source) {
+                    self.string_vars.insert(name.to_string());
+                }
+                if self.class_instance_vars.contains(&source) {
+                    self.class_instance_vars.insert(name.to_string());
+                }
+                if let Some(class_name) = self.var_types.get(&source).cloned() {
+                    self.var_types.insert(name.to_string(), class_name);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn expr_is_class_instance(&self, expr: &Expr) -> bool {
         if let Expr::Identifier(name) = expr {
             let sanitized = self.sanitize_name(name);
@@ -7843,10 +9320,28 @@ impl CodeGenerator {
         false
     }
 
+    /// Expression body of a function or method. A bare string literal is a
+    /// `&str` in Rust, so it needs `.to_string()` when the signature returns `String`.
+    fn generate_expr_body(&mut self, expr: &Expr, return_type: &str) -> Result<()> {
+        if let Some(members) = self.current_return_union.clone() {
+            return self.generate_union_value(&members, expr);
+        }
+        self.generate_expr(expr)?;
+        if matches!(expr, Expr::Literal(Literal::String(_))) && return_type.contains("String") {
+            self.output.push_str(".to_string()");
+        }
+        Ok(())
+    }
+
     /// Generate return expression with auto-clone for non-Copy types
     /// Detects when returning a field from self and automatically adds .clone()
     /// Bug #52: Also handles casting integer division to float when return type is f64
     fn generate_return_expr(&mut self, expr: &Expr) -> Result<()> {
+        // Union return types take the variant of the returned member
+        if let Some(members) = self.current_return_union.clone() {
+            return self.generate_union_value(&members, expr);
+        }
+
         // Check if this is a string literal - needs .to_string() for String return type
         if let Expr::Literal(Literal::String(_)) = expr {
             self.generate_expr(expr)?;
@@ -7916,9 +9411,13 @@ impl CodeGenerator {
             Expr::Binary { op, left, right } => {
                 matches!(
                     op,
-                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod
+                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::Pow
                 ) && self.expr_is_integer_expr(left)
                     && self.expr_is_integer_expr(right)
+                    || matches!(
+                        op,
+                        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr
+                    )
             }
             // Method calls - could return anything, assume not integer for safety
             _ => false,
@@ -7932,7 +9431,59 @@ impl CodeGenerator {
         false
     }
 
+    /// With `--explain-codegen`, a `/* livac: ... */` comment in front of the
+    /// code emitted since `start`, saying which guess produced it. The comment
+    /// goes in front so checks on how the output ends keep working.
+    fn explain(&mut self, start: usize, why: std::fmt::Arguments) {
+        if self.ctx.explain_codegen {
+            self.output
+                .insert_str(start, &format!("/* livac: {} */ ", why));
+        }
+    }
+
+    /// Conversion of a JSON field that is used as a number, following the
+    /// `--numeric-coercion` policy
+    fn write_json_number_coercion(&mut self, start: usize, field: &str) -> Result<()> {
+        self.explain(
+            start,
+            format_args!(
+                "`{}` is named like a number, so the JSON value is converted",
+                field
+            ),
+        );
+        match self.ctx.numeric_coercion {
+            crate::NumericCoercion::Coerce => self.output.push_str(".as_f64().unwrap_or(0.0)"),
+            crate::NumericCoercion::Checked => write!(
+                self.output,
+                ".as_f64().expect({:?})",
+                format!("JSON field '{}' is missing or not a number", field)
+            )
+            .unwrap(),
+            crate::NumericCoercion::Forbid => {
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3003",
+                        "Implicit numeric coercion",
+                        &format!(
+                            "JSON field '{}' is used as a number without an explicit conversion, which numeric coercion policy 'forbid' rejects",
+                            field
+                        ),
+                    )
+                    .with_hint(&format!("Convert it explicitly: .{}.asFloat()", field)),
+                )));
+            }
+        }
+        Ok(())
+    }
+
     fn generate_expr(&mut self, expr: &Expr) -> Result<()> {
+        grow_stack(|| self.generate_expr_kind(expr))
+    }
+
+    fn generate_expr_kind(&mut self, expr: &Expr) -> Result<()> {
+        // Taken before any sub-expression is generated, so only the receiver
+        // of the conversion call sees it
+        let explicit_conversion = std::mem::take(&mut self.explicit_json_conversion);
         match expr {
             Expr::Literal(lit) => self.generate_literal(lit)?,
             Expr::Identifier(name) => {
@@ -7943,8 +9494,10 @@ impl CodeGenerator {
                     name
                 };
 
-                // Check if this is a constant (uppercase identifier)
-                if actual_name.chars().all(|c| c.is_uppercase() || c == '_') {
+                if self.lazy_values.contains_key(actual_name) {
+                    write!(self.output, "{}()", actual_name.to_uppercase()).unwrap();
+                } else if actual_name.chars().all(|c| c.is_uppercase() || c == '_') {
+                    // Constants (uppercase identifiers) keep their name
                     write!(self.output, "{}", actual_name).unwrap();
                 } else {
                     write!(self.output, "{}", self.sanitize_name(actual_name)).unwrap();
@@ -7989,6 +9542,10 @@ impl CodeGenerator {
                 } else if matches!(op, BinOp::Add)
                     && (self.expr_is_stringy(left) || self.expr_is_stringy(right))
                 {
+                    self.explain(
+                        self.output.len(),
+                        format_args!("an operand of `+` looks like a string, so it concatenates"),
+                    );
                     self.output.push_str("format!(\"{}{}\", ");
                     self.generate_expr_for_string_concat(left)?;
                     self.output.push_str(", ");
@@ -7998,10 +9555,10 @@ impl CodeGenerator {
                     self.generate_binary_operation(op, left, right)?;
                 }
             }
-            Expr::Unary { op, operand } => match op {
-                crate::ast::UnOp::Await => {
+            Expr::Unary { op, operand } => {
+                match (op, operand.as_ref()) {
                     // Check if we're awaiting a pending task variable
-                    if let Expr::Identifier(name) = operand.as_ref() {
+                    (UnOp::Await, Expr::Identifier(name)) => {
                         let sanitized = self.sanitize_name(name);
                         if self.pending_tasks.contains_key(&sanitized) {
                             // Generate task_name_task.await.unwrap() instead of task_name.await
@@ -8014,14 +9571,17 @@ impl CodeGenerator {
                             return Ok(());
                         }
                     }
-                    self.generate_expr(operand)?;
-                    self.output.push_str(".await");
-                }
-                crate::ast::UnOp::Not => {
                     // Special handling for !error_var -> error_var.is_none()
-                    if let Expr::Identifier(name) = operand.as_ref() {
+                    (UnOp::Not, Expr::Identifier(name)) => {
                         let sanitized = self.sanitize_name(name);
                         if self.error_binding_vars.contains(&sanitized) {
+                            self.explain(
+                                self.output.len(),
+                                format_args!(
+                                    "`{}` is an error binding, so `!` tests for no error",
+                                    name
+                                ),
+                            );
                             write!(self.output, "{}.is_none()", sanitized).unwrap();
                             return Ok(());
                         }
@@ -8031,59 +9591,40 @@ impl CodeGenerator {
                             return Ok(());
                         }
                     }
-                    write!(self.output, "{}", op).unwrap();
-                    self.generate_expr(operand)?;
-                }
-                _ => {
-                    write!(self.output, "{}", op).unwrap();
-                    self.generate_expr(operand)?;
+                    _ => {}
                 }
-            },
+                let operand = Box::new(self.expr_node(operand)?);
+                let node = match op {
+                    UnOp::Await => rust_ast::Expr::Await(operand),
+                    UnOp::Neg => rust_ast::Expr::Unary(rust_ast::UnOp::Neg, operand),
+                    // Rust spells bitwise not `!`
+                    UnOp::Not | UnOp::BitNot => rust_ast::Expr::Unary(rust_ast::UnOp::Not, operand),
+                };
+                self.emit_node(&node);
+            }
             Expr::Ternary {
                 condition,
                 then_expr,
                 else_expr,
             } => {
-                // Check if this ternary contains a fail - if so, generate as Result
+                // Check if this ternary contains a fail - if so, generate as Result:
+                // if cond { Ok(then) or return Err(...) } else { Err(...) or Ok(else) }
                 let has_fail =
                     self.expr_contains_fail(then_expr) || self.expr_contains_fail(else_expr);
-
-                if has_fail {
-                    // Generate as Result: if cond { Ok(then) or Err(...) } else { Err(...) or Ok(else) }
-                    self.output.push_str("if ");
-                    self.generate_expr(condition)?;
-                    self.output.push_str(" { ");
-                    // For the then branch, check if it's a fail
-                    if let Expr::Fail(expr) = then_expr.as_ref() {
-                        self.output.push_str("return Err(liva_rt::Error::from(");
-                        self.generate_expr(expr)?;
-                        self.output.push_str("))");
-                    } else {
-                        self.output.push_str("Ok(");
-                        self.generate_expr(then_expr)?;
-                        self.output.push_str(")");
-                    }
-                    self.output.push_str(" } else { ");
-                    // For the else branch, check if it's a fail
-                    if let Expr::Fail(expr) = else_expr.as_ref() {
-                        self.output.push_str("Err(liva_rt::Error::from(");
-                        self.generate_expr(expr)?;
-                        self.output.push_str("))");
-                    } else {
-                        self.output.push_str("Ok(");
-                        self.generate_expr(else_expr)?;
-                        self.output.push_str(")");
-                    }
-                    self.output.push_str(" }");
+                let condition = self.expr_node(condition)?;
+                let (then_value, else_value) = if has_fail {
+                    (
+                        self.ternary_branch(then_expr, true)?,
+                        self.ternary_branch(else_expr, false)?,
+                    )
                 } else {
-                    self.output.push_str("if ");
-                    self.generate_expr(condition)?;
-                    self.output.push_str(" { ");
-                    self.generate_expr(then_expr)?;
-                    self.output.push_str(" } else { ");
-                    self.generate_expr(else_expr)?;
-                    self.output.push_str(" }");
-                }
+                    (self.expr_node(then_expr)?, self.expr_node(else_expr)?)
+                };
+                self.emit_node(&rust_ast::Expr::If(
+                    Box::new(condition),
+                    rust_ast::Block::of(then_value),
+                    Some(rust_ast::Block::of(else_value)),
+                ));
             }
             Expr::Call(call) => {
                 // Check if this is a .count() call on a sequence
@@ -8311,9 +9852,11 @@ impl CodeGenerator {
                     match object.as_ref() {
                         Expr::Identifier(var_name) => {
                             let sanitized = self.sanitize_name(var_name);
-                            // Check if this is a known JsonValue variable
-                            let is_json_value = self.json_value_vars.contains(&sanitized)
-                                || self.json_value_vars.contains(var_name);
+                            // Check if this is a known JsonValue variable, not a
+                            // Vec of them (`let rows = [{ id: 1 }]`)
+                            let is_json_value = (self.json_value_vars.contains(&sanitized)
+                                || self.json_value_vars.contains(var_name))
+                                && !self.array_vars.contains(var_name);
 
                             if is_json_value {
                                 // JsonValue uses .length() (already returns i32)
@@ -8335,6 +9878,7 @@ impl CodeGenerator {
                     return Ok(());
                 }
 
+                let start = self.output.len();
                 self.generate_expr(object)?;
 
                 // Use bracket notation for JSON objects, dot notation for structs
@@ -8359,6 +9903,13 @@ impl CodeGenerator {
                                 property
                             )
                             .unwrap();
+                            self.explain(
+                                start,
+                                format_args!(
+                                    "`{}` is not a known class instance, Rust struct or array, so `.{}` reads a JSON field",
+                                    var_name, property
+                                ),
+                            );
                             return Ok(());
                         }
 
@@ -8383,6 +9934,22 @@ impl CodeGenerator {
                                     || property.ends_with("data"))
                             {
                                 self.output.push_str(".clone()");
+                                self.explain(
+                                    start,
+                                    format_args!(
+                                        "`.{}` is named like an owned string field, so it is cloned",
+                                        property
+                                    ),
+                                );
+                            }
+                            if !is_rust_struct && !self.is_class_instance(var_name) {
+                                self.explain(
+                                    start,
+                                    format_args!(
+                                        "`{}` is named like a class instance, so `.{}` is a struct field",
+                                        var_name, property
+                                    ),
+                                );
                             }
                             return Ok(());
                         }
@@ -8391,14 +9958,14 @@ impl CodeGenerator {
                         write!(self.output, "[\"{}\"]", property).unwrap();
 
                         // Convert numeric properties automatically (but not in string templates - format! handles it)
-                        if !self.in_string_template {
+                        if !self.in_string_template && !explicit_conversion {
                             if property == "price"
                                 || property == "age"
                                 || property.contains("count")
                                 || property.contains("total")
                                 || property.contains("sum")
                             {
-                                self.output.push_str(".as_f64().unwrap_or(0.0)");
+                                self.write_json_number_coercion(start, property)?;
                             } else if property == "name"
                                 || property.contains("text")
                                 || property.contains("data")
@@ -8479,23 +10046,36 @@ impl CodeGenerator {
                             // This replaces the old Bug #51 hardcoded field name list.
                             if !self.in_assignment_target {
                                 self.output.push_str(".clone()");
+                                self.explain(
+                                    start,
+                                    format_args!(
+                                        "the array holds class instances, so `.{}` is cloned out of the element",
+                                        property
+                                    ),
+                                );
                             }
                         } else {
                             // Indexed access like array[index] - the result is typically a JSON object
                             // So property access should use bracket notation
                             write!(self.output, "[\"{}\"]", property).unwrap();
+                            self.explain(
+                                start,
+                                format_args!(
+                                    "the array's elements are not known to be class instances, so `.{}` uses JSON bracket notation",
+                                    property
+                                ),
+                            );
 
                             // For numeric fields in JSON objects, convert to appropriate type (but not in string templates)
-                            if !self.in_string_template {
+                            if !self.in_string_template && !explicit_conversion {
                                 // Always convert price to f64 since it's commonly used in arithmetic
-                                if property == "price" {
-                                    self.output.push_str(".as_f64().unwrap_or(0.0)");
-                                } else if property == "age"
+                                if property == "price"
+                                    || property == "age"
                                     || property.contains("count")
                                     || property.contains("total")
                                     || property.contains("sum")
                                 {
-                                    self.output.push_str(".as_f64().unwrap_or(0.0)");
+                                    self.write_json_number_coercion(start, property)?;
                                 } else if property == "name"
                                     || property.contains("text")
                                     || property.contains("data")
@@ -8512,6 +10092,7 @@ impl CodeGenerator {
                 }
             }
             Expr::Index { object, index } => {
+                let start = self.output.len();
                 // Special handling for JsonValue (both Option<JsonValue> and JsonValue)
                 // BUT: Skip this if we're in a string template (handled separately there)
                 if !self.in_string_template {
@@ -8789,23 +10370,32 @@ impl CodeGenerator {
                 // (would clone the slot and drop &mut self mutations).
                 if needs_clone && !self.in_assignment_target && !self.suppress_index_elem_clone {
                     self.output.push_str(".clone()");
+                    self.explain(
+                        start,
+                        format_args!(
+                            "indexing yields a reference to a non-Copy element, so it is cloned"
+                        ),
+                    );
                 }
                 // B157: consume single-shot flag so it does not leak into the
                 // index sub-expression nor into argument lists below.
                 self.suppress_index_elem_clone = false;
 
                 // Convert numeric properties automatically
-                if let Expr::Literal(Literal::String(prop)) = index.as_ref() {
-                    if prop == "price"
-                        || prop == "age"
-                        || prop.contains("count")
-                        || prop.contains("total")
-                        || prop.contains("sum")
-                    {
-                        self.output.push_str(".as_f64().unwrap_or(0.0)");
-                    } else if prop == "name" || prop.contains("text") || prop.contains("data") {
-                        self.output.push_str(".as_string().unwrap_or_default()");
+                match index.as_ref() {
+                    Expr::Literal(Literal::String(prop)) if !explicit_conversion => {
+                        if prop == "price"
+                            || prop == "age"
+                            || prop.contains("count")
+                            || prop.contains("total")
+                            || prop.contains("sum")
+                        {
+                            self.write_json_number_coercion(start, prop)?;
+                        } else if prop == "name" || prop.contains("text") || prop.contains("data") {
+                            self.output.push_str(".as_string().unwrap_or_default()");
+                        }
                     }
+                    _ => {}
                 }
             }
             Expr::ObjectLiteral(fields) => {
@@ -8850,102 +10440,58 @@ impl CodeGenerator {
                 self.output.push_str(" }");
             }
             Expr::ArrayLiteral(elements) => {
-                self.output.push_str("vec![");
-                for (i, elem) in elements.iter().enumerate() {
-                    if i > 0 {
-                        self.output.push_str(", ");
-                    }
-                    // String literals in array literals need .to_string() to produce Vec<String>
-                    // instead of Vec<&str>, which is incompatible with Liva's string type
-                    if matches!(elem, Expr::Literal(Literal::String(_))) {
-                        self.generate_expr(elem)?;
-                        self.output.push_str(".to_string()");
-                    } else if let Expr::Identifier(name) = elem {
-                        // B133: cloning Identifier elements avoids partial-move when the var
-                        // is referenced again later (e.g. `let q = [start]; map.set(start, ...)`)
-                        let san = self.sanitize_name(name);
-                        if self.string_vars.contains(&san)
-                            || self.array_vars.contains(&san)
-                            || self.map_vars.contains(&san)
-                            || self.class_instance_vars.contains(&san)
-                        {
-                            self.generate_expr(elem)?;
-                            self.output.push_str(".clone()");
-                        } else {
-                            self.generate_expr(elem)?;
+                let mut nodes = Vec::new();
+                for elem in elements {
+                    let node = self.owned_node(elem)?;
+                    // B133: cloning Identifier elements avoids partial-move when the var
+                    // is referenced again later (e.g. `let q = [start]; map.set(start, ...)`)
+                    let is_heap_var = match elem {
+                        Expr::Identifier(name) => {
+                            let san = self.sanitize_name(name);
+                            self.string_vars.contains(&san)
+                                || self.array_vars.contains(&san)
+                                || self.map_vars.contains(&san)
+                                || self.class_instance_vars.contains(&san)
                         }
+                        _ => false,
+                    };
+                    nodes.push(if is_heap_var {
+                        node.method("clone", vec![])
                     } else {
-                        self.generate_expr(elem)?;
-                    }
+                        node
+                    });
                 }
-                self.output.push(']');
+                self.emit_node(&rust_ast::Expr::Vec(nodes));
             }
             Expr::MapLiteral(entries) => {
                 if entries.is_empty() {
                     self.output.push_str("std::collections::HashMap::new()");
                 } else {
-                    self.output.push_str("std::collections::HashMap::from([");
-                    for (i, (key, value)) in entries.iter().enumerate() {
-                        if i > 0 {
-                            self.output.push_str(", ");
-                        }
-                        self.output.push('(');
-                        if matches!(key, Expr::Literal(Literal::String(_))) {
-                            self.generate_expr(key)?;
-                            self.output.push_str(".to_string()");
-                        } else {
-                            self.generate_expr(key)?;
-                        }
-                        self.output.push_str(", ");
-                        if matches!(value, Expr::Literal(Literal::String(_))) {
-                            self.generate_expr(value)?;
-                            self.output.push_str(".to_string()");
-                        } else {
-                            self.generate_expr(value)?;
-                        }
-                        self.output.push(')');
-                    }
-                    self.output.push_str("])");
+                    let mut pairs = Vec::new();
+                    for (key, value) in entries {
+                        let pair = vec![self.owned_node(key)?, self.owned_node(value)?];
+                        pairs.push(rust_ast::Expr::Tuple(pair));
+                    }
+                    self.emit_node(&rust_ast::Expr::call(
+                        "std::collections::HashMap::from",
+                        vec![rust_ast::Expr::Array(pairs)],
+                    ));
                 }
             }
             Expr::SetLiteral(elements) => {
                 if elements.is_empty() {
                     self.output.push_str("std::collections::HashSet::new()");
                 } else {
-                    self.output.push_str("std::collections::HashSet::from([");
-                    for (i, elem) in elements.iter().enumerate() {
-                        if i > 0 {
-                            self.output.push_str(", ");
-                        }
-                        if matches!(elem, Expr::Literal(Literal::String(_))) {
-                            self.generate_expr(elem)?;
-                            self.output.push_str(".to_string()");
-                        } else {
-                            self.generate_expr(elem)?;
-                        }
-                    }
-                    self.output.push_str("])");
+                    let nodes = self.owned_nodes(elements)?;
+                    self.emit_node(&rust_ast::Expr::call(
+                        "std::collections::HashSet::from",
+                        vec![rust_ast::Expr::Array(nodes)],
+                    ));
                 }
             }
             Expr::Tuple(elements) => {
-                self.output.push('(');
-                for (i, elem) in elements.iter().enumerate() {
-                    if i > 0 {
-                        self.output.push_str(", ");
-                    }
-                    // Convert string literals to String for tuple compatibility
-                    if matches!(elem, Expr::Literal(Literal::String(_))) {
-                        self.generate_expr(elem)?;
-                        self.output.push_str(".to_string()");
-                    } else {
-                        self.generate_expr(elem)?;
-                    }
-                }
-                // Rust requires trailing comma for single-element tuples
-                if elements.len() == 1 {
-                    self.output.push(',');
-                }
-                self.output.push(')');
+                let nodes = self.owned_nodes(elements)?;
+                self.emit_node(&rust_ast::Expr::Tuple(nodes));
             }
             Expr::StringTemplate { parts } => {
                 self.output.push_str("format!(\"");
@@ -8966,54 +10512,15 @@ impl CodeGenerator {
                                 }
                             }
                         }
-                        StringTemplatePart::Expr(expr) => match expr.as_ref() {
-                            // Literals always use Display
-                            Expr::Literal(_) => {
-                                self.output.push_str("{}");
-                            }
-                            // Simple identifiers: check if they're arrays or option values
-                            Expr::Identifier(name) => {
-                                if self.array_vars.contains(name) {
-                                    self.output.push_str("{:?}");
-                                } else if self.option_value_vars.contains(name)
-                                    || self.error_binding_vars.contains(name)
-                                {
-                                    // Option values need special handling - will be unwrapped
-                                    self.output.push_str("{}");
-                                } else {
-                                    self.output.push_str("{}");
-                                }
-                            }
-                            // Member access uses Display
-                            Expr::Member { .. } => {
-                                self.output.push_str("{}");
-                            }
-                            // Index access uses Display
-                            Expr::Index { .. } => {
-                                self.output.push_str("{}");
-                            }
-                            // Binary operations (including comparisons) use Display
-                            Expr::Binary { .. } => {
-                                self.output.push_str("{}");
-                            }
-                            // Ternary/If expressions with string results use Display
-                            Expr::Ternary { .. } => {
-                                self.output.push_str("{}");
-                            }
-                            // Function calls use Display
-                            Expr::Call { .. } | Expr::MethodCall { .. } => {
-                                self.output.push_str("{}");
-                            }
-                            // Arrays and objects use Debug
-                            Expr::ArrayLiteral(_) | Expr::ObjectLiteral(_) | Expr::Tuple(_) => {
+                        // Display for strings, numbers and class values; Debug
+                        // only for aggregates, which have no Display impl
+                        StringTemplatePart::Expr(expr) => {
+                            if self.template_expr_is_aggregate(expr) {
                                 self.output.push_str("{:?}");
-                            }
-                            // B29 fix: Default to Display for all other expression types
-                            // (Unary, Cast, Lambda, etc.) — {:?} adds unwanted quotes on strings
-                            _ => {
+                            } else {
                                 self.output.push_str("{}");
                             }
-                        },
+                        }
                     }
                 }
 
@@ -9129,6 +10636,8 @@ impl CodeGenerator {
                 // Check if we need to generate destructuring code
                 let has_destructuring = lambda.params.iter().any(|p| p.is_destructuring());
 
+                // Tasks started in a lambda aren't tied to the scope it's written in
+                let task_scopes = std::mem::take(&mut self.task_scopes);
                 match &lambda.body {
                     LambdaBody::Expr(expr) => {
                         if has_destructuring {
@@ -9165,107 +10674,18 @@ impl CodeGenerator {
                         }
                     }
                     LambdaBody::Block(block) => {
-                        // For lambdas, we need to generate a block that returns a value
-                        // Check if the last statement is a return statement
-                        if let Some(last_stmt) = block.stmts.last() {
-                            if let Stmt::Return(return_stmt) = last_stmt {
-                                if let Some(expr) = &return_stmt.expr {
-                                    // Generate the block with statements except the last return
-                                    self.output.push('{');
-                                    self.indent();
-                                    self.output.push('\n');
-
-                                    // Capture element type before mutable borrows
-                                    let element_type_for_destr =
-                                        self.current_lambda_element_type.clone();
-
-                                    // Generate destructuring for lambda params (if any)
-                                    if has_destructuring {
-                                        for (idx, param) in lambda.params.iter().enumerate() {
-                                            if param.is_destructuring() {
-                                                let temp_name = format!("_param_{}", idx);
-                                                self.write_indent();
-                                                self.generate_lambda_param_destructuring(
-                                                    &param.pattern,
-                                                    &temp_name,
-                                                    false,
-                                                    element_type_for_destr.as_deref(),
-                                                )?;
-                                                self.output.push('\n');
-                                            }
-                                        }
-                                    }
-
-                                    self.write_indent();
-
-                                    // Generate all statements except the last return
-                                    for stmt in &block.stmts[..block.stmts.len() - 1] {
-                                        self.generate_stmt(stmt)?;
-                                        self.output.push('\n');
-                                        self.write_indent();
-                                    }
-
-                                    // Generate the return expression without the return keyword
-                                    self.generate_expr(expr)?;
-
-                                    self.dedent();
-                                    self.output.push('\n');
-                                    self.write_indent();
-                                    self.output.push('}');
-                                } else {
-                                    // Empty return, generate unit type
-                                    self.output.push_str("()");
-                                }
-                            } else {
-                                // No return statement, generate block as-is
-                                self.output.push('{');
-                                self.indent();
-                                self.output.push('\n');
-
-                                // Capture element type before mutable borrows
-                                let element_type_for_destr =
-                                    self.current_lambda_element_type.clone();
-
-                                // Generate destructuring for lambda params (if any)
-                                if has_destructuring {
-                                    for (idx, param) in lambda.params.iter().enumerate() {
-                                        if param.is_destructuring() {
-                                            let temp_name = format!("_param_{}", idx);
-                                            self.write_indent();
-                                            self.generate_lambda_param_destructuring(
-                                                &param.pattern,
-                                                &temp_name,
-                                                false,
-                                                element_type_for_destr.as_deref(),
-                                            )?;
-                                            self.output.push('\n');
-                                        }
-                                    }
-                                }
-
-                                self.write_indent();
-                                for stmt in &block.stmts {
-                                    self.generate_stmt(stmt)?;
-                                    self.output.push('\n');
-                                    self.write_indent();
-                                }
-                                self.dedent();
-                                self.output.push('}');
-                            }
-                        } else {
-                            // Empty block
-                            self.output.push_str("()");
-                        }
+                        let element_type = self.current_lambda_element_type.clone();
+                        self.generate_lambda_block(lambda, block, false, element_type.as_deref())?;
                     }
                 }
+                self.task_scopes = task_scopes;
             }
             Expr::Fail(expr) => {
                 // B138: emit as a bare expression (no indent/`;`) so it works in
                 // both statement context (Stmt::Expr adds `;`) and expression
                 // context (switch arm, ternary).
-                self.output.push_str("return Err(liva_rt::Error::from(");
-                self.generate_expr(expr)?;
-                self.output.push_str("))");
+                let err = self.fail_node(expr)?;
+                self.emit_node(&rust_ast::Expr::ret(err));
             }
             Expr::MethodCall(method_call) => {
                 // TODO: Implement method call code generation (stdlib Phase 2)
@@ -9276,6 +10696,13 @@ impl CodeGenerator {
                 let saved_suppress = self.suppress_index_elem_clone;
                 self.suppress_index_elem_clone =
                     matches!(method_call.object.as_ref(), Expr::Index { .. });
+                self.explicit_json_conversion = matches!(
+                    method_call.method.as_str(),
+                    "asFloat" | "asInt" | "asString" | "asBool"
+                ) && matches!(
+                    method_call.object.as_ref(),
+                    Expr::Member { .. } | Expr::Index { .. }
+                );
                 let res = self.generate_method_call_expr(method_call);
                 self.suppress_index_elem_clone = saved_suppress;
                 res?;
@@ -9283,6 +10710,12 @@ impl CodeGenerator {
             Expr::Switch(switch_expr) => {
                 self.generate_switch_expr(switch_expr)?;
             }
+            Expr::If(if_expr) => {
+                self.generate_if_expr(if_expr)?;
+            }
+            Expr::Cast { expr, target } => {
+                self.generate_cast(expr, target)?;
+            }
             Expr::MethodRef { object, method } => {
                 // Phase 11.4: Generate closure wrapper for method references
                 // Utils::validate → |_x| Utils::validate(_x)
@@ -9316,16 +10749,16 @@ impl CodeGenerator {
             }
             Expr::Unwrap(inner) => {
                 // Postfix unwrap: expr! → expr.unwrap()
-                self.generate_expr(inner)?;
-                self.output.push_str(".unwrap()");
+                let node = self.expr_node(inner)?;
+                self.emit_node(&node.method("unwrap", vec![]));
             }
             Expr::Try(inner) => {
                 // Postfix try: expr? → expr? in Rust (error propagation).
                 // Both Liva and Rust use `?` so the translation is direct;
                 // the surrounding fallible function emits a Result return type
                 // already, so the `?` operator type-checks.
-                self.generate_expr(inner)?;
-                self.output.push_str("?");
+                let node = self.expr_node(inner)?;
+                self.emit_node(&rust_ast::Expr::Try(Box::new(node)));
             }
             Expr::OptionalChain { object, property } => {
                 // Optional chaining: expr?.field → expr.as_ref().map(|__v| __v.field.clone())
@@ -9397,15 +10830,20 @@ impl CodeGenerator {
             });
         let needs_ref = needs_ref_b97
             || (is_enum_data_switch && matches!(&*switch_expr.discriminant, Expr::Identifier(_)));
+        let type_switch = self.type_of_switch_subject(switch_expr);
 
         self.write_indent();
         self.output.push_str("match ");
-        if needs_ref {
-            self.output.push('&');
-        }
-        self.generate_expr(&switch_expr.discriminant)?;
-        if is_string_switch {
-            self.output.push_str(".as_str()");
+        if let Some((var_name, _, _)) = &type_switch {
+            write!(self.output, "{}.clone()", var_name).unwrap();
+        } else {
+            if needs_ref {
+                self.output.push('&');
+            }
+            self.generate_expr(&switch_expr.discriminant)?;
+            if is_string_switch {
+                self.output.push_str(".as_str()");
+            }
         }
         self.output.push_str(" {");
         self.indent();
@@ -9413,7 +10851,10 @@ impl CodeGenerator {
         for arm in &switch_expr.arms {
             self.output.push('\n');
             self.write_indent();
-            if let Some(ref union_name) = union_type_name {
+            let mut narrowed_member = None;
+            if let Some(subject) = &type_switch {
+                narrowed_member = self.generate_type_switch_pattern(&arm.pattern, subject);
+            } else if let Some(ref union_name) = union_type_name {
                 self.generate_union_pattern(&arm.pattern, union_name)?;
             } else {
                 self.generate_pattern(&arm.pattern)?;
@@ -9431,6 +10872,12 @@ impl CodeGenerator {
                 Vec::new()
             };
             let registered_bindings = self.register_pattern_bindings(&arm.pattern);
+            let narrowed = match (&type_switch, &narrowed_member) {
+                (Some((var_name, _, _)), Some(member)) => {
+                    Some((var_name.clone(), self.narrow_union_var(var_name, member)))
+                }
+                _ => None,
+            };
 
             // Emit each arm body as a `()`-typed block so the match arms unify.
             self.output.push('{');
@@ -9467,6 +10914,9 @@ impl CodeGenerator {
             self.write_indent();
             self.output.push('}');
 
+            if let Some((var_name, narrowed)) = narrowed {
+                self.widen_union_var(&var_name, narrowed);
+            }
             self.unregister_pattern_bindings(&registered_bindings);
             self.output.push(',');
         }
@@ -9477,7 +10927,17 @@ impl CodeGenerator {
             .arms
             .iter()
             .any(|arm| matches!(arm.pattern, Pattern::Wildcard));
-        if !has_wildcard {
+        // A `switch typeOf(x)` with a case per member already is exhaustive
+        let covers_union = type_switch.as_ref().is_some_and(|(_, _, members)| {
+            members.iter().all(|member| {
+                let tag = member.type_of_name();
+                switch_expr
+                    .arms
+                    .iter()
+                    .any(|arm| arm.guard.is_none() && Self::pattern_names_type(&arm.pattern, &tag))
+            })
+        });
+        if !has_wildcard && !covers_union {
             self.output.push('\n');
             self.write_indent();
             self.output.push_str("_ => {},");
@@ -9517,14 +10977,21 @@ impl CodeGenerator {
             || (is_enum_data_switch_expr
                 && matches!(&*switch_expr.discriminant, Expr::Identifier(_)));
 
+        // `switch typeOf(x)` on a union matches its variants directly
+        let type_switch = self.type_of_switch_subject(switch_expr);
+
         // Generate Rust match expression
         self.output.push_str("match ");
-        if needs_ref {
-            self.output.push('&');
-        }
-        self.generate_expr(&switch_expr.discriminant)?;
-        if is_string_switch {
-            self.output.push_str(".as_str()");
+        if let Some((var_name, _, _)) = &type_switch {
+            write!(self.output, "{}.clone()", var_name).unwrap();
+        } else {
+            if needs_ref {
+                self.output.push('&');
+            }
+            self.generate_expr(&switch_expr.discriminant)?;
+            if is_string_switch {
+                self.output.push_str(".as_str()");
+            }
         }
         self.output.push_str(" {");
         self.indent();
@@ -9534,7 +11001,10 @@ impl CodeGenerator {
             self.write_indent();
 
             // Generate pattern (with union context if applicable)
-            if let Some(ref union_name) = union_type_name {
+            let mut narrowed_member = None;
+            if let Some(subject) = &type_switch {
+                narrowed_member = self.generate_type_switch_pattern(&arm.pattern, subject);
+            } else if let Some(ref union_name) = union_type_name {
                 self.generate_union_pattern(&arm.pattern, union_name)?;
             } else {
                 self.generate_pattern(&arm.pattern)?;
@@ -9560,6 +11030,12 @@ impl CodeGenerator {
 
             // GAP-007 fix: Register pattern bindings as class instances for member access
             let registered_bindings = self.register_pattern_bindings(&arm.pattern);
+            let narrowed = match (&type_switch, &narrowed_member) {
+                (Some((var_name, _, _)), Some(member)) => {
+                    Some((var_name.clone(), self.narrow_union_var(var_name, member)))
+                }
+                _ => None,
+            };
 
             if !boxed_bindings.is_empty() || !ref_clone_bindings.is_empty() {
                 // Wrap body in a block with auto-dereference/clone let statements
@@ -9646,6 +11122,9 @@ impl CodeGenerator {
                 }
             }
 
+            if let Some((var_name, narrowed)) = narrowed {
+                self.widen_union_var(&var_name, narrowed);
+            }
             // GAP-007 fix: Unregister pattern bindings after arm body
             self.unregister_pattern_bindings(&registered_bindings);
 
@@ -9660,6 +11139,95 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// `value as int|float|string` → Rust `as` cast or Display formatting.
+    /// Float to int truncates toward zero and saturates, NaN becomes 0.
+    fn generate_cast(&mut self, expr: &Expr, target: &TypeRef) -> Result<()> {
+        let rust_type = match target {
+            TypeRef::Simple(name) if name == "string" => {
+                self.output.push_str("format!(\"{}\", ");
+                self.generate_expr(expr)?;
+                self.output.push(')');
+                return Ok(());
+            }
+            TypeRef::Simple(name) if name == "int" => "i32",
+            TypeRef::Simple(name) if name == "float" => "f64",
+            _ => {
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E5005",
+                        "Unsupported conversion",
+                        &format!("Cannot convert a value with `as {}`", target),
+                    ),
+                )))
+            }
+        };
+        // Parenthesized so a following `<` isn't read as generic arguments
+        self.output.push_str("((");
+        self.generate_expr(expr)?;
+        write!(self.output, ") as {})", rust_type).unwrap();
+        Ok(())
+    }
+
+    /// `if` in value position → Rust `if cond { a } else { b }` expression
+    fn generate_if_expr(&mut self, if_expr: &IfExpr) -> Result<()> {
+        // `if c { 1 } else { 2.5 }` unifies to float; write the int as `1.0`
+        let has_float = [&if_expr.then_branch, &if_expr.else_branch]
+            .into_iter()
+            .any(|branch| match branch {
+                SwitchBody::Expr(e) => matches!(e.as_ref(), Expr::Literal(Literal::Float(_))),
+                SwitchBody::Block(_) => false,
+            });
+
+        self.output.push_str("if ");
+        self.generate_condition_expr(&if_expr.condition)?;
+        self.output.push_str(" {\n");
+        self.generate_if_expr_branch(&if_expr.then_branch, has_float)?;
+        self.output.push_str(" else ");
+        match &if_expr.else_branch {
+            SwitchBody::Expr(e) if matches!(e.as_ref(), Expr::If(_)) => self.generate_expr(e),
+            branch => {
+                self.output.push_str("{\n");
+                self.generate_if_expr_branch(branch, has_float)
+            }
+        }
+    }
+
+    /// Body and closing `}` of an `if` expression branch; the last statement
+    /// of a block is its value
+    fn generate_if_expr_branch(&mut self, branch: &SwitchBody, as_float: bool) -> Result<()> {
+        self.indent();
+        match branch {
+            SwitchBody::Expr(expr) => {
+                self.write_indent();
+                match expr.as_ref() {
+                    Expr::Literal(Literal::Int(n)) if as_float => {
+                        write!(self.output, "{}.0", n).unwrap();
+                    }
+                    Expr::Literal(Literal::String(_)) => {
+                        self.generate_expr(expr)?;
+                        self.output.push_str(".to_string()");
+                    }
+                    _ => self.generate_expr(expr)?,
+                }
+            }
+            SwitchBody::Block(stmts) => {
+                for (i, stmt) in stmts.iter().enumerate() {
+                    if i == stmts.len() - 1 {
+                        self.write_indent();
+                        self.generate_stmt_as_tail_expr(stmt)?;
+                    } else {
+                        self.generate_stmt(stmt)?;
+                    }
+                }
+            }
+        }
+        self.dedent();
+        self.output.push('\n');
+        self.write_indent();
+        self.output.push('}');
+        Ok(())
+    }
+
     /// Get the list of binding names in a pattern that correspond to boxed (recursive) enum fields.
     /// These bindings need auto-dereference (`let binding = *binding;`) in the match arm body.
     fn get_boxed_pattern_bindings(&self, pattern: &Pattern) -> Vec<String> {
@@ -10026,10 +11594,12 @@ impl CodeGenerator {
             // Handle parseInt(str) -> (i32, Option<Error>)
             if name == "parseInt" {
                 if call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "parseInt requires 1 argument",
-                        "parseInt(str) takes exactly one string argument",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "parseInt requires 1 argument",
+                            "parseInt(str) takes exactly one string argument",
+                        ),
                     )));
                 }
                 self.output.push_str("match ");
@@ -10041,10 +11611,12 @@ impl CodeGenerator {
             // Handle parseFloat(str) -> (f64, Option<Error>)
             if name == "parseFloat" {
                 if call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "parseFloat requires 1 argument",
-                        "parseFloat(str) takes exactly one string argument",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "parseFloat requires 1 argument",
+                            "parseFloat(str) takes exactly one string argument",
+                        ),
                     )));
                 }
                 self.output.push_str("match ");
@@ -10053,13 +11625,115 @@ impl CodeGenerator {
                 return Ok(());
             }
 
+            // Handle zip(a, b) -> [(T, U)], stopping at the shorter array
+            if name == "zip" && call.args.len() == 2 {
+                self.generate_expr(&call.args[0])?;
+                self.output.push_str(".iter().zip(");
+                self.generate_expr(&call.args[1])?;
+                self.output
+                    .push_str(".iter()).map(|(a, b)| (a.clone(), b.clone())).collect::<Vec<_>>()");
+                return Ok(());
+            }
+
+            // Handle clone(value) -> deep copy. Classes derive Clone, and arrays,
+            // maps and JSON values own their elements, so Clone copies all the
+            // way down
+            if name == "clone" && call.args.len() == 1 {
+                // Cloning a frozen value thaws it into a mutable copy
+                if self.is_frozen_expr(&call.args[0]) {
+                    self.generate_expr(&call.args[0])?;
+                    self.output.push_str(".thaw()");
+                    return Ok(());
+                }
+                let needs_parens = matches!(&call.args[0], Expr::Binary { .. });
+                if needs_parens {
+                    self.output.push('(');
+                }
+                self.generate_expr(&call.args[0])?;
+                if needs_parens {
+                    self.output.push(')');
+                }
+                // Indexing class arrays already yields a clone
+                if !self.output.ends_with(".clone()") {
+                    self.output.push_str(".clone()");
+                }
+                return Ok(());
+            }
+
+            // Handle typeOf(value) -> the value's type tag as a String: the member
+            // a union holds, the class of an instance, liva_rt::type_of otherwise
+            if name == "typeOf" && call.args.len() == 1 {
+                let arg = &call.args[0];
+                if let Some((var_name, members)) = self.union_var_of(arg) {
+                    let union_name = self.expand_type_alias(&TypeRef::Union(members.clone()));
+                    write!(self.output, "(match &{} {{ ", var_name).unwrap();
+                    for member in &members {
+                        let rust_type = self.expand_type_alias(member);
+                        write!(
+                            self.output,
+                            "{}::{}(_) => \"{}\", ",
+                            union_name,
+                            self.type_to_variant_name(&rust_type),
+                            member.type_of_name()
+                        )
+                        .unwrap();
+                    }
+                    self.output.push_str("}).to_string()");
+                    return Ok(());
+                }
+                if let Some(class_name) = self.class_of_expr(arg) {
+                    write!(self.output, "String::from(\"{}\")", class_name).unwrap();
+                    return Ok(());
+                }
+                self.output.push_str("liva_rt::type_of(&");
+                self.generate_expr(arg)?;
+                self.output.push(')');
+                return Ok(());
+            }
+
+            // Handle charCode(c) -> the code point as i32, of a char or of a
+            // one-character string; fromCharCode(n) -> the char with that code
+            if name == "charCode" && call.args.len() == 1 {
+                self.output.push_str("liva_rt::char_code(&");
+                self.generate_expr(&call.args[0])?;
+                self.output.push(')');
+                return Ok(());
+            }
+            if name == "fromCharCode" && call.args.len() == 1 {
+                self.output.push_str("liva_rt::from_char_code(");
+                self.generate_expr(&call.args[0])?;
+                self.output.push(')');
+                return Ok(());
+            }
+
+            // Handle freeze(value) -> liva_rt::Frozen<T>, shared and read-only
+            if name == "freeze" && call.args.len() == 1 {
+                let arg = &call.args[0];
+                if self.is_frozen_expr(arg) {
+                    self.generate_expr(arg)?;
+                    self.output.push_str(".clone()");
+                    return Ok(());
+                }
+                self.output.push_str("liva_rt::freeze(");
+                self.generate_expr(arg)?;
+                if let Expr::Identifier(var_name) = arg {
+                    if !self.is_copy_var(&self.sanitize_name(var_name)) {
+                        self.output.push_str(".clone()");
+                    }
+                }
+                self.output.push(')');
+                return Ok(());
+            }
+
             // Handle toString(value) -> String
             if name == "toString" {
                 if call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "toString requires 1 argument",
-                        "toString(value) takes exactly one argument",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "toString requires 1 argument",
+                            "toString(value) takes exactly one argument",
+                        ),
                     )));
                 }
                 self.output.push_str("format!(\"{}\", ");
@@ -10070,22 +11744,38 @@ impl CodeGenerator {
 
             // Handle readLine() -> String (read from stdin)
             if name == "readLine" {
+                if self.ctx.has_mocks {
+                    self.output
+                        .push_str("liva_rt::mocked::<String>(\"readLine\").unwrap_or_else(|| ");
+                }
                 self.output.push_str("{ let mut input = String::new(); std::io::stdin().read_line(&mut input).expect(\"Failed to read line\"); input.trim().to_string() }");
+                if self.ctx.has_mocks {
+                    self.output.push(')');
+                }
                 return Ok(());
             }
 
             // Handle prompt(message) -> String (display message and read input)
             if name == "prompt" {
                 if call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "prompt requires 1 argument",
-                        "prompt(message) takes exactly one string argument",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "prompt requires 1 argument",
+                            "prompt(message) takes exactly one string argument",
+                        ),
                     )));
                 }
+                if self.ctx.has_mocks {
+                    self.output
+                        .push_str("liva_rt::mocked::<String>(\"prompt\").unwrap_or_else(|| ");
+                }
                 self.output.push_str("{ print!(\"{}\", ");
                 self.generate_expr(&call.args[0])?;
                 self.output.push_str("); std::io::stdout().flush().expect(\"Failed to flush stdout\"); let mut input = String::new(); std::io::stdin().read_line(&mut input).expect(\"Failed to read line\"); input.trim().to_string() }");
+                if self.ctx.has_mocks {
+                    self.output.push(')');
+                }
                 return Ok(());
             }
 
@@ -10158,10 +11848,8 @@ impl CodeGenerator {
                         self.output.push_str("Some(");
                     }
 
-                    // Add .to_string() for string literals (hack for constructor parameters)
                     if let Expr::Literal(Literal::String(_)) = arg {
-                        self.generate_expr(arg)?;
-                        self.output.push_str(".to_string()");
+                        self.generate_string_arg(&call.callee, i, arg)?;
                     } else if let Expr::Identifier(var_name) = arg {
                         // B17 fix: Clone non-Copy variables when passing to constructors
                         let sanitized = self.sanitize_name(var_name);
@@ -10170,7 +11858,15 @@ impl CodeGenerator {
                         let is_map = self.map_vars.contains(&sanitized);
                         let is_array = self.array_vars.contains(&sanitized);
                         let is_json = self.json_value_vars.contains(&sanitized);
-                        if is_string_var || is_class_instance || is_map || is_array || is_json {
+                        if self.frozen_vars.contains(&sanitized) {
+                            self.generate_expr(arg)?;
+                            self.output.push_str(".thaw()");
+                        } else if is_string_var
+                            || is_class_instance
+                            || is_map
+                            || is_array
+                            || is_json
+                        {
                             self.generate_expr(arg)?;
                             self.output.push_str(".clone()");
                         } else {
@@ -10252,10 +11948,24 @@ impl CodeGenerator {
                 self.output.push(')');
                 continue;
             }
-            // Convert string literals to String automatically
+            // Union parameters take the variant of the member passed
+            let union_param = match call.callee.as_ref() {
+                Expr::Identifier(fname) => self
+                    .function_param_types
+                    .get(fname)
+                    .and_then(|types| types.get(i).cloned())
+                    .flatten()
+                    .and_then(|t| self.union_type_members(&t)),
+                _ => None,
+            };
+            let union_variant =
+                union_param.and_then(|members| self.union_variant_for(&members, arg));
+            if let Some(variant) = &union_variant {
+                write!(self.output, "{}(", variant).unwrap();
+            }
+            let start = self.output.len();
             if let Expr::Literal(Literal::String(_)) = arg {
-                self.generate_expr(arg)?;
-                self.output.push_str(".to_string()");
+                self.generate_string_arg(&call.callee, i, arg)?;
             } else if let Expr::Identifier(name) = arg {
                 // B17 fix: Clone non-Copy variables when passing to functions
                 // to avoid ownership issues (Rust moves String/struct/Vec/HashMap by default)
@@ -10264,7 +11974,11 @@ impl CodeGenerator {
                 // enum types, cross-module classes, and any other non-Copy types.
                 let sanitized = self.sanitize_name(name);
                 let is_known_copy = self.is_copy_var(&sanitized);
-                if !is_known_copy
+                if self.frozen_vars.contains(&sanitized) {
+                    // Parameters take the plain value; the callee gets its own copy
+                    self.generate_expr(arg)?;
+                    self.output.push_str(".thaw()");
+                } else if !is_known_copy
                     && (self.class_instance_vars.contains(&sanitized)
                         || self.string_vars.contains(&sanitized)
                         || self.map_vars.contains(&sanitized)
@@ -10276,11 +11990,25 @@ impl CodeGenerator {
                 {
                     self.generate_expr(arg)?;
                     self.output.push_str(".clone()");
+                    self.explain(
+                        start,
+                        format_args!(
+                            "`{}` is passed by value and not known to be Copy, so it is cloned",
+                            name
+                        ),
+                    );
                 } else if !is_known_copy && self.looks_like_non_copy_var(&sanitized) {
                     // FIX-4: Catch-all for variables not tracked in any set
                     // but that are likely non-Copy (not a known primitive identifier)
                     self.generate_expr(arg)?;
                     self.output.push_str(".clone()");
+                    self.explain(
+                        start,
+                        format_args!(
+                            "`{}` has no known type and is not named like a number, so it is cloned",
+                            name
+                        ),
+                    );
                 } else {
                     self.generate_expr(arg)?;
                 }
@@ -10307,9 +12035,16 @@ impl CodeGenerator {
                 // In &self methods, self.field can't be moved — needs .clone()
                 self.generate_expr(arg)?;
                 self.output.push_str(".clone()");
+                self.explain(
+                    start,
+                    format_args!("a field can't be moved out of `&self`, so it is cloned"),
+                );
             } else {
                 self.generate_expr(arg)?;
             }
+            if union_variant.is_some() {
+                self.output.push(')');
+            }
         }
 
         // Inject default parameter values for missing arguments
@@ -10431,6 +12166,14 @@ impl CodeGenerator {
                     || self.expr_uses_var(then_expr, var_name)
                     || self.expr_uses_var(else_expr, var_name)
             }
+            Expr::If(if_expr) => {
+                self.expr_uses_var(&if_expr.condition, var_name)
+                    || [&if_expr.then_branch, &if_expr.else_branch]
+                        .into_iter()
+                        .any(|branch| {
+                            matches!(branch, SwitchBody::Expr(e) if self.expr_uses_var(e, var_name))
+                        })
+            }
             _ => false,
         }
     }
@@ -10453,9 +12196,11 @@ impl CodeGenerator {
                 Stmt::Expr(expr_stmt) => check_vars
                     .iter()
                     .any(|v| self.expr_uses_var(&expr_stmt.expr, v)),
-                Stmt::If(if_stmt) => check_vars
-                    .iter()
-                    .any(|v| self.expr_uses_var(&if_stmt.condition, v)),
+                Stmt::If(if_stmt) => check_vars.iter().any(|v| {
+                    if_stmt
+                        .branches()
+                        .any(|(condition, _)| self.expr_uses_var(condition, v))
+                }),
                 Stmt::Return(ret_stmt) => ret_stmt.expr.as_ref().map_or(false, |e| {
                     check_vars.iter().any(|v| self.expr_uses_var(e, v))
                 }),
@@ -10504,9 +12249,11 @@ impl CodeGenerator {
                 Stmt::Expr(expr_stmt) => check_vars
                     .iter()
                     .any(|v| self.expr_uses_var(&expr_stmt.expr, v)),
-                Stmt::If(if_stmt) => check_vars
-                    .iter()
-                    .any(|v| self.expr_uses_var(&if_stmt.condition, v)),
+                Stmt::If(if_stmt) => check_vars.iter().any(|v| {
+                    if_stmt
+                        .branches()
+                        .any(|(condition, _)| self.expr_uses_var(condition, v))
+                }),
                 Stmt::Return(ret_stmt) => ret_stmt.expr.as_ref().map_or(false, |e| {
                     check_vars.iter().any(|v| self.expr_uses_var(e, v))
                 }),
@@ -10536,21 +12283,31 @@ impl CodeGenerator {
         used_tasks
     }
 
-    /// Phase 4.2: Check for dead tasks (never awaited) and emit warnings
-    fn check_dead_tasks(&self) {
-        for (var_name, task_info) in &self.pending_tasks {
-            if !task_info.awaited {
-                eprintln!(
-                    "⚠️  Warning: Task '{}' was created but never used",
-                    var_name
-                );
-                eprintln!("   → Consider removing the task creation or using the variable");
-                eprintln!("   → This creates an async/parallel task that does nothing");
-            }
-        }
-    }
-
-    /// Phase 4: Generate tokio::join! for multiple pending tasks (optimization)
+    /// Phase 4.2: Report dead tasks (never awaited) as warnings (E0606)
+    fn check_dead_tasks(&mut self) {
+        let mut dead: Vec<&String> = self
+            .pending_tasks
+            .iter()
+            .filter(|(_, task_info)| !task_info.awaited)
+            .map(|(var_name, _)| var_name)
+            .collect();
+        dead.sort();
+        for var_name in dead {
+            self.diagnostics.warning(
+                SemanticErrorInfo::new(
+                    E0606_UNUSED_TASK,
+                    "Task never used",
+                    &format!(
+                        "Task '{}' in '{}' is created but never awaited or used",
+                        var_name, self.current_function_name
+                    ),
+                )
+                .with_help("Await the task or remove it; as it is, it runs work nobody reads"),
+            );
+        }
+    }
+
+    /// Phase 4: Generate tokio::join! for multiple pending tasks (optimization)
     fn generate_tasks_join(&mut self, task_vars: &[String]) -> Result<()> {
         if task_vars.is_empty() {
             return Ok(());
@@ -10657,6 +12414,44 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// `.mapErr(fn)`: shadow the error binding with the rewritten error.
+    /// Tuple builtins bind the message as a `String` that is empty on success;
+    /// everything else binds `Option<liva_rt::Error>`, and the original error
+    /// stays on as the cause.
+    fn generate_map_err(&mut self, err_name: &str, map: &Expr) -> Result<()> {
+        let binding = if self.mutated_vars.contains(err_name) {
+            format!("mut {}", err_name)
+        } else {
+            err_name.to_string()
+        };
+        self.write_indent();
+        if self.string_error_vars.contains(err_name) {
+            write!(
+                self.output,
+                "let {} = if {1}.is_empty() {{ {1} }} else {{ (",
+                binding, err_name
+            )
+            .unwrap();
+            self.generate_expr(map)?;
+            writeln!(self.output, ")({}) }};", err_name).unwrap();
+        } else {
+            write!(
+                self.output,
+                "let {} = {}.map(|e| liva_rt::Error::chain((",
+                binding, err_name
+            )
+            .unwrap();
+            self.generate_expr(map)?;
+            writeln!(
+                self.output,
+                ")(e.message.clone()), \"{}\", \"{}\", e));",
+                self.current_function_name, self.source_filename
+            )
+            .unwrap();
+        }
+        Ok(())
+    }
+
     /// Generate the await code for a pending task (Phase 2: Lazy await)
     fn generate_task_await(&mut self, var_name: &str) -> Result<()> {
         let task_info = self.pending_tasks.get(var_name).cloned();
@@ -10771,12 +12566,21 @@ impl CodeGenerator {
             return Ok(());
         }
 
+        if method_call.is_array_constructor() {
+            return self.generate_array_constructor_call(method_call);
+        }
+
         // Check if this is a Math function call (Math.sqrt, Math.pow, etc.)
         if let Expr::Identifier(name) = method_call.object.as_ref() {
             if name == "Math" {
                 return self.generate_math_function_call(method_call);
             }
 
+            // Check if this is a Bits function call (Bits.countOnes, etc.)
+            if name == "Bits" {
+                return self.generate_bits_function_call(method_call);
+            }
+
             // Check if this is a console function call (console.log, console.error, etc.)
             if name == "console" {
                 return self.generate_console_function_call(method_call);
@@ -11296,12 +13100,7 @@ impl CodeGenerator {
             && !object_is_class_instance
         {
             if let Expr::Lambda(lambda) = &method_call.args[0] {
-                let param_name = lambda
-                    .params
-                    .first()
-                    .and_then(|p| p.name())
-                    .map(|n| self.sanitize_name(n))
-                    .unwrap_or_else(|| "__x".to_string());
+                let param_name = self.key_lambda_param(lambda);
                 self.output.push_str("{ let mut __v = ");
                 self.generate_expr(&method_call.object)?;
                 write!(
@@ -11310,23 +13109,51 @@ impl CodeGenerator {
                     param_name
                 )
                 .unwrap();
-                match &lambda.body {
-                    LambdaBody::Expr(expr) => self.generate_expr(expr)?,
-                    LambdaBody::Block(block) => self.generate_block_inner(block)?,
-                }
+                self.generate_key_lambda_body(lambda)?;
                 write!(
                     self.output,
                     "; let {} = (*__b).clone(); let __kb = ",
                     param_name
                 )
                 .unwrap();
-                match &lambda.body {
-                    LambdaBody::Expr(expr) => self.generate_expr(expr)?,
-                    LambdaBody::Block(block) => self.generate_block_inner(block)?,
-                }
-                self.output.push_str(
-                    "; __ka.partial_cmp(&__kb).unwrap_or(std::cmp::Ordering::Equal) }); __v }",
-                );
+                self.generate_key_lambda_body(lambda)?;
+                self.output
+                    .push_str("; liva_rt::key_cmp(&__ka, &__kb) }); __v }");
+                return Ok(());
+            }
+        }
+
+        // Handle arr.minBy(fn) / arr.maxBy(fn) — element with the smallest/largest key,
+        // always left as Option<T> (None for an empty array). Ties keep the first
+        // element, so maxBy scans in reverse.
+        if matches!(method_call.method.as_str(), "minBy" | "maxBy")
+            && !method_call.args.is_empty()
+            && !object_is_class_instance
+        {
+            if let Expr::Lambda(lambda) = &method_call.args[0] {
+                let param_name = self.key_lambda_param(lambda);
+                let (reverse, fold) = if method_call.method == "minBy" {
+                    ("", "min_by")
+                } else {
+                    (".rev()", "max_by")
+                };
+                self.generate_expr(&method_call.object)?;
+                write!(
+                    self.output,
+                    ".iter(){}.{}(|__a, __b| {{ let {} = (*__a).clone(); let __ka = ",
+                    reverse, fold, param_name
+                )
+                .unwrap();
+                self.generate_key_lambda_body(lambda)?;
+                write!(
+                    self.output,
+                    "; let {} = (*__b).clone(); let __kb = ",
+                    param_name
+                )
+                .unwrap();
+                self.generate_key_lambda_body(lambda)?;
+                self.output
+                    .push_str("; liva_rt::key_cmp(&__ka, &__kb) }).cloned()");
                 return Ok(());
             }
         }
@@ -11337,12 +13164,7 @@ impl CodeGenerator {
             && !object_is_class_instance
         {
             if let Expr::Lambda(lambda) = &method_call.args[0] {
-                let param_name = lambda
-                    .params
-                    .first()
-                    .and_then(|p| p.name())
-                    .map(|n| self.sanitize_name(n))
-                    .unwrap_or_else(|| "__x".to_string());
+                let param_name = self.key_lambda_param(lambda);
                 self.output.push_str("{ let mut __m: std::collections::HashMap<_, Vec<_>> = std::collections::HashMap::new(); for __item in ");
                 self.generate_expr(&method_call.object)?;
                 write!(
@@ -11351,10 +13173,7 @@ impl CodeGenerator {
                     param_name
                 )
                 .unwrap();
-                match &lambda.body {
-                    LambdaBody::Expr(expr) => self.generate_expr(expr)?,
-                    LambdaBody::Block(block) => self.generate_block_inner(block)?,
-                }
+                self.generate_key_lambda_body(lambda)?;
                 self.output.push_str(
                     "; __m.entry(__key).or_insert_with(Vec::new).push((*__item).clone()); } __m }",
                 );
@@ -11382,6 +13201,31 @@ impl CodeGenerator {
             return Ok(());
         }
 
+        // Handle arr.windows(size) — overlapping sub-arrays of given size
+        if method_call.method == "windows"
+            && !method_call.args.is_empty()
+            && !object_is_class_instance
+        {
+            self.generate_expr(&method_call.object)?;
+            self.output.push_str(".windows((");
+            self.generate_expr(&method_call.args[0])?;
+            self.output
+                .push_str(") as usize).map(|w| w.to_vec()).collect::<Vec<Vec<_>>>()");
+            return Ok(());
+        }
+
+        // Handle arr.enumerate() — (index, value) pairs with an int index
+        if method_call.method == "enumerate"
+            && method_call.args.is_empty()
+            && !object_is_class_instance
+        {
+            self.generate_expr(&method_call.object)?;
+            self.output.push_str(
+                ".iter().cloned().enumerate().map(|(i, x)| (i as i32, x)).collect::<Vec<_>>()",
+            );
+            return Ok(());
+        }
+
         // Handle arr.chunks(size) — splits into sub-arrays of given size
         if method_call.method == "chunks"
             && !method_call.args.is_empty()
@@ -11705,6 +13549,7 @@ impl CodeGenerator {
             "findIndex" => "position".to_string(), // Liva: findIndex, Rust: position
             "flatMap" => "flat_map".to_string(), // Liva: flatMap, Rust: flat_map
             "count" => "filter".to_string(), // Liva: count(fn), Rust: filter(fn).count()
+            "removeAt" if !object_is_class_instance => "remove".to_string(), // Liva: removeAt, Rust: remove
             method_name => self.sanitize_name(method_name), // Sanitize custom method names (e.g., isAdult -> is_adult)
         };
 
@@ -11722,6 +13567,7 @@ impl CodeGenerator {
         // Note: Liva reduce syntax is .reduce(initial, lambda) - same order as Rust's .fold()
         // No reordering needed
         let args_to_generate: Vec<&Expr> = method_call.args.iter().collect();
+        let method_params = self.class_method_params(&method_call.object, &method_call.method);
 
         for (i, arg) in args_to_generate.iter().enumerate() {
             if i > 0 {
@@ -11762,6 +13608,17 @@ impl CodeGenerator {
                 }
             }
 
+            // insert(i, x) / removeAt(i): Vec positions are usize
+            if i == 0
+                && matches!(method_call.method.as_str(), "insert" | "removeAt")
+                && !object_is_class_instance
+            {
+                self.output.push('(');
+                self.generate_expr(arg)?;
+                self.output.push_str(") as usize");
+                continue;
+            }
+
             // Special handling for includes/indexOf: wrap value in closure
             if method_call.method == "includes" || method_call.method == "indexOf" {
                 self.output.push_str("|x| *x == ");
@@ -11769,6 +13626,13 @@ impl CodeGenerator {
                 continue;
             }
 
+            // Methods of a known class convert like functions do
+            if let (Expr::Literal(Literal::String(_)), Some(params)) = (arg, &method_params) {
+                let param_type = params.get(i).cloned().flatten();
+                self.generate_string_literal(param_type, "parameter", arg)?;
+                continue;
+            }
+
             // Convert string literals to String for methods/functions
             // This avoids "expected String, found &str" errors
             if matches!(arg, Expr::Literal(Literal::String(_))) {
@@ -12239,46 +14103,12 @@ impl CodeGenerator {
                             }
                         }
                         LambdaBody::Block(block) => {
-                            self.output.push('{');
-                            self.indent();
-                            self.output.push('\n');
-
-                            // Generate destructuring for lambda params (if any)
-                            if has_destructuring {
-                                for (idx, param) in lambda.params.iter().enumerate() {
-                                    if param.is_destructuring() {
-                                        let temp_name = format!("_param_{}", idx);
-                                        self.write_indent();
-                                        self.generate_lambda_param_destructuring(
-                                            &param.pattern,
-                                            &temp_name,
-                                            is_json_value,
-                                            element_type.as_deref(),
-                                        )?;
-                                        self.output.push('\n');
-                                    }
-                                }
-                            }
-
-                            self.write_indent();
-                            for stmt in &block.stmts[..block.stmts.len().saturating_sub(1)] {
-                                self.generate_stmt(stmt)?;
-                                self.output.push('\n');
-                                self.write_indent();
-                            }
-                            if let Some(last_stmt) = block.stmts.last() {
-                                if let Stmt::Return(return_stmt) = last_stmt {
-                                    if let Some(expr) = &return_stmt.expr {
-                                        self.generate_expr(expr)?;
-                                    }
-                                } else {
-                                    self.generate_stmt(last_stmt)?;
-                                }
-                            }
-                            self.dedent();
-                            self.output.push('\n');
-                            self.write_indent();
-                            self.output.push('}');
+                            self.generate_lambda_block(
+                                lambda,
+                                block,
+                                is_json_value,
+                                element_type.as_deref(),
+                            )?;
                         }
                     }
                     // Clear ref_lambda_params after lambda body is generated
@@ -13294,6 +15124,44 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// `Array.fill(n, value)` becomes `vec![value; n]` and a range turned
+    /// into an array is collected, so large arrays need no literal for
+    /// rustc to chew through
+    fn generate_array_constructor_call(&mut self, method_call: &MethodCallExpr) -> Result<()> {
+        let range = match (method_call.method.as_str(), method_call.args.as_slice()) {
+            ("fill", [count, value]) => {
+                self.output.push_str("vec![");
+                self.generate_expr(value)?;
+                match value {
+                    Expr::Literal(Literal::String(_)) => self.output.push_str(".to_string()"),
+                    // The variable stays usable after the call
+                    Expr::Identifier(_) => self.output.push_str(".clone()"),
+                    _ => {}
+                }
+                // A negative count makes an empty array
+                self.output.push_str("; std::cmp::max(");
+                self.generate_expr(count)?;
+                self.output.push_str(", 0) as usize]");
+                return Ok(());
+            }
+            ("from", [range]) => range,
+            ("toArray", []) => method_call.object.as_ref(),
+            _ => {
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Invalid Array.{} call", method_call.method),
+                        "Use Array.fill(count, value) or Array.from(start..end)",
+                    ),
+                )));
+            }
+        };
+        self.output.push('(');
+        self.generate_expr(range)?;
+        self.output.push_str(").collect::<Vec<_>>()");
+        Ok(())
+    }
+
     fn generate_math_function_call(
         &mut self,
         method_call: &crate::ast::MethodCallExpr,
@@ -13303,10 +15171,12 @@ impl CodeGenerator {
             "sqrt" | "abs" => {
                 // sqrt(x) -> x.sqrt() or abs(x) -> x.abs()
                 if method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        &format!("Math.{} requires 1 argument", method_call.method),
-                        &format!("Math.{} takes exactly one argument", method_call.method),
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            &format!("Math.{} requires 1 argument", method_call.method),
+                            &format!("Math.{} takes exactly one argument", method_call.method),
+                        ),
                     )));
                 }
 
@@ -13328,10 +15198,12 @@ impl CodeGenerator {
             "pow" => {
                 // pow(base, exp) -> base.powf(exp)
                 if method_call.args.len() < 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Math.pow requires 2 arguments",
-                        "Math.pow(base, exponent) takes exactly two arguments",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Math.pow requires 2 arguments",
+                            "Math.pow(base, exponent) takes exactly two arguments",
+                        ),
                     )));
                 }
                 let needs_parens = matches!(&method_call.args[0], Expr::Unary { .. });
@@ -13349,10 +15221,12 @@ impl CodeGenerator {
             "floor" | "ceil" | "round" => {
                 // floor(x) -> x.floor() as i32
                 if method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        &format!("Math.{} requires 1 argument", method_call.method),
-                        &format!("Math.{} takes exactly one argument", method_call.method),
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            &format!("Math.{} requires 1 argument", method_call.method),
+                            &format!("Math.{} takes exactly one argument", method_call.method),
+                        ),
                     )));
                 }
                 let needs_parens = matches!(&method_call.args[0], Expr::Unary { .. });
@@ -13370,10 +15244,12 @@ impl CodeGenerator {
             "min" | "max" => {
                 // min(a, b) -> a.min(b) or max(a, b) -> a.max(b)
                 if method_call.args.len() < 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        &format!("Math.{} requires 2 arguments", method_call.method),
-                        &format!("Math.{} takes exactly two arguments", method_call.method),
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            &format!("Math.{} requires 2 arguments", method_call.method),
+                            &format!("Math.{} takes exactly two arguments", method_call.method),
+                        ),
                     )));
                 }
                 let needs_parens = matches!(&method_call.args[0], Expr::Unary { .. });
@@ -13393,15 +15269,18 @@ impl CodeGenerator {
             "random" => {
                 // random() -> rand::random::<f64>()
                 // Note: requires use rand::Rng in the generated code
-                self.output.push_str("rand::random::<f64>()");
+                let random_f64 = self.random_f64_expr();
+                self.output.push_str(random_f64);
             }
             "clamp" => {
                 // clamp(val, min, max) -> val.max(min).min(max)
                 if method_call.args.len() < 3 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Math.clamp requires 3 arguments",
-                        "Math.clamp(value, min, max) takes exactly three arguments",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Math.clamp requires 3 arguments",
+                            "Math.clamp(value, min, max) takes exactly three arguments",
+                        ),
                     )));
                 }
                 let needs_parens = matches!(&method_call.args[0], Expr::Unary { .. });
@@ -13421,10 +15300,12 @@ impl CodeGenerator {
             "sign" => {
                 // sign(val) -> if val > 0 { 1 } else if val < 0 { -1 } else { 0 }
                 if method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Math.sign requires 1 argument",
-                        "Math.sign takes exactly one argument",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Math.sign requires 1 argument",
+                            "Math.sign takes exactly one argument",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __v: f64 = ");
@@ -13432,13 +15313,38 @@ impl CodeGenerator {
                 self.output
                     .push_str("; if __v > 0.0 { 1 } else if __v < 0.0 { -1 } else { 0 } }");
             }
+            "floorDiv" | "floorMod" => {
+                // floorDiv(a, b) -> liva_rt::floor_div(a, b), rounding toward
+                // negative infinity where `/` and `%` truncate toward zero
+                if method_call.args.len() < 2 {
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            &format!("Math.{} requires 2 arguments", method_call.method),
+                            &format!("Math.{} takes exactly two arguments", method_call.method),
+                        ),
+                    )));
+                }
+                let helper = if method_call.method == "floorDiv" {
+                    "floor_div"
+                } else {
+                    "floor_mod"
+                };
+                write!(self.output, "liva_rt::{}(", helper).unwrap();
+                self.generate_expr(&method_call.args[0])?;
+                self.output.push_str(", ");
+                self.generate_expr(&method_call.args[1])?;
+                self.output.push(')');
+            }
             "log" => {
                 // log(x) -> (x as f64).ln()
                 if method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Math.log requires 1 argument",
-                        "Math.log takes exactly one argument",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Math.log requires 1 argument",
+                            "Math.log takes exactly one argument",
+                        ),
                     )));
                 }
                 self.output.push('(');
@@ -13446,13 +15352,13 @@ impl CodeGenerator {
                 self.output.push_str(" as f64).ln()");
             }
             _ => {
-                return Err(CompilerError::CodegenError(
+                return Err(CompilerError::CodegenError(Box::new(
                     SemanticErrorInfo::new(
                         "E3000",
                         &format!("Unknown Math function: {}", method_call.method),
-                        "Available Math functions: sqrt, pow, abs, floor, ceil, round, min, max, random, clamp, sign, log"
+                        "Available Math functions: sqrt, pow, abs, floor, ceil, round, min, max, random, clamp, sign, log, floorDiv, floorMod"
                     )
-                ));
+                )));
             }
         }
 
@@ -13606,10 +15512,12 @@ impl CodeGenerator {
                 self.output.push_str("}");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown console function: {}", method_call.method),
-                    "Available console functions: log, error, warn, success, input",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown console function: {}", method_call.method),
+                        "Available console functions: log, error, warn, success, input",
+                    ),
                 )));
             }
         }
@@ -13627,10 +15535,12 @@ impl CodeGenerator {
                 // JSON.parse(json_str) returns (Option<JsonValue>, String)
                 // Generates: match serde_json::from_str(...) { Ok(v) => (Some(JsonValue(v)), String::new()), Err(e) => (None, format!("...")) }
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "JSON.parse requires exactly 1 argument",
-                        "Usage: JSON.parse(json_string)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "JSON.parse requires exactly 1 argument",
+                            "Usage: JSON.parse(json_string)",
+                        ),
                     )));
                 }
 
@@ -13643,10 +15553,12 @@ impl CodeGenerator {
                 // JSON.stringify(value) returns (Option<String>, String)
                 // Generates: match serde_json::to_string(...) { Ok(s) => (Some(s), String::new()), Err(e) => (None, format!("...")) }
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "JSON.stringify requires exactly 1 argument",
-                        "Usage: JSON.stringify(value)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "JSON.stringify requires exactly 1 argument",
+                            "Usage: JSON.stringify(value)",
+                        ),
                     )));
                 }
 
@@ -13655,10 +15567,12 @@ impl CodeGenerator {
                 self.output.push_str(") { Ok(s) => (Some(s), String::new()), Err(e) => (None, format!(\"JSON stringify error: {}\", e)) }");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown JSON function: {}", method_call.method),
-                    "Available JSON functions: parse, stringify",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown JSON function: {}", method_call.method),
+                        "Available JSON functions: parse, stringify",
+                    ),
                 )));
             }
         }
@@ -13676,10 +15590,12 @@ impl CodeGenerator {
                 // File.read(path) returns (Option<String>, String)
                 // Error is "" on success, error message on failure
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.read requires exactly 1 argument",
-                        "Usage: File.read(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.read requires exactly 1 argument",
+                            "Usage: File.read(path)",
+                        ),
                     )));
                 }
 
@@ -13691,10 +15607,12 @@ impl CodeGenerator {
                 // File.write(path, content) returns (Option<bool>, String)
                 // Error is "" on success, error message on failure
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.write requires exactly 2 arguments",
-                        "Usage: File.write(path, content)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.write requires exactly 2 arguments",
+                            "Usage: File.write(path, content)",
+                        ),
                     )));
                 }
 
@@ -13708,10 +15626,12 @@ impl CodeGenerator {
                 // File.append(path, content) returns (Option<bool>, String)
                 // Error is "" on success, error message on failure
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.append requires exactly 2 arguments",
-                        "Usage: File.append(path, content)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.append requires exactly 2 arguments",
+                            "Usage: File.append(path, content)",
+                        ),
                     )));
                 }
 
@@ -13726,10 +15646,12 @@ impl CodeGenerator {
             "exists" => {
                 // File.exists(path) returns bool (no error binding)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.exists requires exactly 1 argument",
-                        "Usage: File.exists(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.exists requires exactly 1 argument",
+                            "Usage: File.exists(path)",
+                        ),
                     )));
                 }
 
@@ -13742,10 +15664,12 @@ impl CodeGenerator {
                 // File.delete(path) returns (Option<bool>, String)
                 // Error is "" on success, error message on failure
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.delete requires exactly 1 argument",
-                        "Usage: File.delete(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.delete requires exactly 1 argument",
+                            "Usage: File.delete(path)",
+                        ),
                     )));
                 }
 
@@ -13756,10 +15680,12 @@ impl CodeGenerator {
             "copy" => {
                 // File.copy(src, dest) returns (Option<bool>, String)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.copy requires exactly 2 arguments",
-                        "Usage: File.copy(src, dest)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.copy requires exactly 2 arguments",
+                            "Usage: File.copy(src, dest)",
+                        ),
                     )));
                 }
 
@@ -13772,10 +15698,12 @@ impl CodeGenerator {
             "move" => {
                 // File.move(src, dest) returns (Option<bool>, String)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.move requires exactly 2 arguments",
-                        "Usage: File.move(src, dest)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.move requires exactly 2 arguments",
+                            "Usage: File.move(src, dest)",
+                        ),
                     )));
                 }
 
@@ -13788,10 +15716,12 @@ impl CodeGenerator {
             "size" => {
                 // File.size(path) returns (Option<i64>, String)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.size requires exactly 1 argument",
-                        "Usage: File.size(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.size requires exactly 1 argument",
+                            "Usage: File.size(path)",
+                        ),
                     )));
                 }
 
@@ -13802,10 +15732,12 @@ impl CodeGenerator {
             "extension" => {
                 // File.extension(path) returns string (no error binding)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.extension requires exactly 1 argument",
-                        "Usage: File.extension(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.extension requires exactly 1 argument",
+                            "Usage: File.extension(path)",
+                        ),
                     )));
                 }
 
@@ -13816,10 +15748,12 @@ impl CodeGenerator {
             "readLines" => {
                 // File.readLines(path) returns (Option<Vec<String>>, String)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.readLines requires exactly 1 argument",
-                        "Usage: File.readLines(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.readLines requires exactly 1 argument",
+                            "Usage: File.readLines(path)",
+                        ),
                     )));
                 }
 
@@ -13830,10 +15764,12 @@ impl CodeGenerator {
             "writeLines" => {
                 // File.writeLines(path, lines) returns (Option<bool>, String)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "File.writeLines requires exactly 2 arguments",
-                        "Usage: File.writeLines(path, lines)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "File.writeLines requires exactly 2 arguments",
+                            "Usage: File.writeLines(path, lines)",
+                        ),
                     )));
                 }
 
@@ -13844,11 +15780,11 @@ impl CodeGenerator {
                 self.output.push_str(".join(\"\\n\")) { Ok(_) => (Some(true), String::new()), Err(e) => (Some(false), format!(\"File writeLines error: {}\", e)) }");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown File function: {}", method_call.method),
                     "Available File functions: read, write, append, exists, delete, copy, move, size, extension, readLines, writeLines",
-                )));
+                ))));
             }
         }
 
@@ -13865,10 +15801,12 @@ impl CodeGenerator {
                 // Dir.list(path) returns ([string], String) - error binding
                 // Returns list of file/directory names in the given path
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Dir.list requires exactly 1 argument",
-                        "Usage: Dir.list(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Dir.list requires exactly 1 argument",
+                            "Usage: Dir.list(path)",
+                        ),
                     )));
                 }
 
@@ -13879,10 +15817,12 @@ impl CodeGenerator {
             "isDir" => {
                 // Dir.isDir(path) returns bool (no error binding, like File.exists)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Dir.isDir requires exactly 1 argument",
-                        "Usage: Dir.isDir(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Dir.isDir requires exactly 1 argument",
+                            "Usage: Dir.isDir(path)",
+                        ),
                     )));
                 }
 
@@ -13894,10 +15834,12 @@ impl CodeGenerator {
             "exists" => {
                 // Dir.exists(path) returns bool (no error binding)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Dir.exists requires exactly 1 argument",
-                        "Usage: Dir.exists(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Dir.exists requires exactly 1 argument",
+                            "Usage: Dir.exists(path)",
+                        ),
                     )));
                 }
 
@@ -13908,10 +15850,12 @@ impl CodeGenerator {
             "create" => {
                 // Dir.create(path) returns (Option<bool>, String)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Dir.create requires exactly 1 argument",
-                        "Usage: Dir.create(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Dir.create requires exactly 1 argument",
+                            "Usage: Dir.create(path)",
+                        ),
                     )));
                 }
 
@@ -13922,10 +15866,12 @@ impl CodeGenerator {
             "delete" => {
                 // Dir.delete(path) returns (Option<bool>, String)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Dir.delete requires exactly 1 argument",
-                        "Usage: Dir.delete(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Dir.delete requires exactly 1 argument",
+                            "Usage: Dir.delete(path)",
+                        ),
                     )));
                 }
 
@@ -13937,10 +15883,12 @@ impl CodeGenerator {
                 // Dir.listRecursive(path) / Dir.walk(path) returns ([string], String) - error binding
                 // Returns all files recursively
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        &format!("Dir.{} requires exactly 1 argument", method_call.method),
-                        &format!("Usage: Dir.{}(path)", method_call.method),
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            &format!("Dir.{} requires exactly 1 argument", method_call.method),
+                            &format!("Usage: Dir.{}(path)", method_call.method),
+                        ),
                     )));
                 }
 
@@ -13951,11 +15899,11 @@ impl CodeGenerator {
                 self.output.push_str(" error: {}\", e)) } }");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown Dir function: {}", method_call.method),
                     "Available Dir functions: list, isDir, exists, create, delete, listRecursive, walk",
-                )));
+                ))));
             }
         }
 
@@ -13966,16 +15914,32 @@ impl CodeGenerator {
         &mut self,
         method_call: &crate::ast::MethodCallExpr,
     ) -> Result<()> {
+        // A test may stub the request with `mock HTTP.get returns ...`
+        if self.ctx.has_mocks {
+            self.output.push_str(&format!(
+                "liva_rt::mock_or(\"HTTP.{}\", ",
+                method_call.method
+            ));
+            self.generate_http_request(method_call)?;
+            self.output.push(')');
+            return Ok(());
+        }
+        self.generate_http_request(method_call)
+    }
+
+    fn generate_http_request(&mut self, method_call: &crate::ast::MethodCallExpr) -> Result<()> {
         // HTTP functions: get, post, put, delete
         // All return (Option<LivaHttpResponse>, Option<String>)
         match method_call.method.as_str() {
             "get" => {
                 // HTTP.get(url) returns (Option<LivaHttpResponse>, Option<String>)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "HTTP.get requires exactly 1 argument",
-                        "Usage: HTTP.get(url)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "HTTP.get requires exactly 1 argument",
+                            "Usage: HTTP.get(url)",
+                        ),
                     )));
                 }
 
@@ -13986,10 +15950,12 @@ impl CodeGenerator {
             "post" => {
                 // HTTP.post(url, body) returns (Option<LivaHttpResponse>, Option<String>)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "HTTP.post requires exactly 2 arguments",
-                        "Usage: HTTP.post(url, body)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "HTTP.post requires exactly 2 arguments",
+                            "Usage: HTTP.post(url, body)",
+                        ),
                     )));
                 }
 
@@ -14002,10 +15968,12 @@ impl CodeGenerator {
             "put" => {
                 // HTTP.put(url, body) returns (Option<LivaHttpResponse>, Option<String>)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "HTTP.put requires exactly 2 arguments",
-                        "Usage: HTTP.put(url, body)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "HTTP.put requires exactly 2 arguments",
+                            "Usage: HTTP.put(url, body)",
+                        ),
                     )));
                 }
 
@@ -14018,10 +15986,12 @@ impl CodeGenerator {
             "delete" => {
                 // HTTP.delete(url) returns (Option<LivaHttpResponse>, Option<String>)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "HTTP.delete requires exactly 1 argument",
-                        "Usage: HTTP.delete(url)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "HTTP.delete requires exactly 1 argument",
+                            "Usage: HTTP.delete(url)",
+                        ),
                     )));
                 }
 
@@ -14030,10 +16000,12 @@ impl CodeGenerator {
                 self.output.push_str(".to_string())");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown HTTP function: {}", method_call.method),
-                    "Available HTTP functions: get, post, put, delete",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown HTTP function: {}", method_call.method),
+                        "Available HTTP functions: get, post, put, delete",
+                    ),
                 )));
             }
         }
@@ -14057,10 +16029,12 @@ impl CodeGenerator {
                 // Sys.env(key) returns string - environment variable value
                 // Returns empty string if not found
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Sys.env requires exactly 1 argument",
-                        "Usage: Sys.env(\"VAR_NAME\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Sys.env requires exactly 1 argument",
+                            "Usage: Sys.env(\"VAR_NAME\")",
+                        ),
                     )));
                 }
 
@@ -14071,10 +16045,12 @@ impl CodeGenerator {
             "exit" => {
                 // Sys.exit(code) - exit program with code
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Sys.exit requires exactly 1 argument",
-                        "Usage: Sys.exit(0)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Sys.exit requires exactly 1 argument",
+                            "Usage: Sys.exit(0)",
+                        ),
                     )));
                 }
 
@@ -14083,10 +16059,12 @@ impl CodeGenerator {
                 self.output.push_str(" as i32)");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Sys function: {}", method_call.method),
-                    "Available Sys functions: args, env, exit",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Sys function: {}", method_call.method),
+                        "Available Sys functions: args, env, exit",
+                    ),
                 )));
             }
         }
@@ -14107,10 +16085,12 @@ impl CodeGenerator {
         match method_call.method.as_str() {
             "get" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Env.get requires exactly 1 argument",
-                        "Usage: Env.get(\"VAR_NAME\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Env.get requires exactly 1 argument",
+                            "Usage: Env.get(\"VAR_NAME\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::env::var(");
@@ -14119,10 +16099,12 @@ impl CodeGenerator {
             }
             "has" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Env.has requires exactly 1 argument",
-                        "Usage: Env.has(\"VAR_NAME\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Env.has requires exactly 1 argument",
+                            "Usage: Env.has(\"VAR_NAME\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::env::var(");
@@ -14131,10 +16113,12 @@ impl CodeGenerator {
             }
             "set" => {
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Env.set requires exactly 2 arguments",
-                        "Usage: Env.set(\"KEY\", \"VALUE\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Env.set requires exactly 2 arguments",
+                            "Usage: Env.set(\"KEY\", \"VALUE\")",
+                        ),
                     )));
                 }
                 self.output.push_str("{ unsafe { std::env::set_var(");
@@ -14145,10 +16129,12 @@ impl CodeGenerator {
             }
             "unset" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Env.unset requires exactly 1 argument",
-                        "Usage: Env.unset(\"KEY\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Env.unset requires exactly 1 argument",
+                            "Usage: Env.unset(\"KEY\")",
+                        ),
                     )));
                 }
                 self.output.push_str("{ unsafe { std::env::remove_var(");
@@ -14157,10 +16143,12 @@ impl CodeGenerator {
             }
             "all" => {
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Env.all takes no arguments",
-                        "Usage: Env.all()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Env.all takes no arguments",
+                            "Usage: Env.all()",
+                        ),
                     )));
                 }
                 self.output.push_str(
@@ -14168,10 +16156,12 @@ impl CodeGenerator {
                 );
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Env function: {}", method_call.method),
-                    "Available Env functions: get, has, set, unset, all",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Env function: {}", method_call.method),
+                        "Available Env functions: get, has, set, unset, all",
+                    ),
                 )));
             }
         }
@@ -14195,10 +16185,12 @@ impl CodeGenerator {
         match method_call.method.as_str() {
             "join" => {
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Path.join requires exactly 2 arguments",
-                        "Usage: Path.join(\"a/b\", \"c\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Path.join requires exactly 2 arguments",
+                            "Usage: Path.join(\"a/b\", \"c\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::path::Path::new(&");
@@ -14210,10 +16202,12 @@ impl CodeGenerator {
             }
             "parent" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Path.parent requires exactly 1 argument",
-                        "Usage: Path.parent(\"/a/b/c\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Path.parent requires exactly 1 argument",
+                            "Usage: Path.parent(\"/a/b/c\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::path::Path::new(&");
@@ -14224,10 +16218,12 @@ impl CodeGenerator {
             }
             "extension" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Path.extension requires exactly 1 argument",
-                        "Usage: Path.extension(\"file.txt\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Path.extension requires exactly 1 argument",
+                            "Usage: Path.extension(\"file.txt\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::path::Path::new(&");
@@ -14238,10 +16234,12 @@ impl CodeGenerator {
             }
             "basename" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Path.basename requires exactly 1 argument",
-                        "Usage: Path.basename(\"/a/b/c.txt\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Path.basename requires exactly 1 argument",
+                            "Usage: Path.basename(\"/a/b/c.txt\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::path::Path::new(&");
@@ -14252,10 +16250,12 @@ impl CodeGenerator {
             }
             "exists" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Path.exists requires exactly 1 argument",
-                        "Usage: Path.exists(\"/a/b\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Path.exists requires exactly 1 argument",
+                            "Usage: Path.exists(\"/a/b\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::path::Path::new(&");
@@ -14264,10 +16264,12 @@ impl CodeGenerator {
             }
             "isAbsolute" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Path.isAbsolute requires exactly 1 argument",
-                        "Usage: Path.isAbsolute(\"/a/b\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Path.isAbsolute requires exactly 1 argument",
+                            "Usage: Path.isAbsolute(\"/a/b\")",
+                        ),
                     )));
                 }
                 self.output.push_str("std::path::Path::new(&");
@@ -14276,10 +16278,12 @@ impl CodeGenerator {
             }
             "normalize" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Path.normalize requires exactly 1 argument",
-                        "Usage: Path.normalize(\"/a/./b/../c\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Path.normalize requires exactly 1 argument",
+                            "Usage: Path.normalize(\"/a/./b/../c\")",
+                        ),
                     )));
                 }
                 // Pure-lexical normalization: collapses "." and ".." without
@@ -14291,11 +16295,11 @@ impl CodeGenerator {
                 );
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown Path function: {}", method_call.method),
                     "Available Path functions: join, parent, extension, basename, exists, isAbsolute, normalize",
-                )));
+                ))));
             }
         }
 
@@ -14319,12 +16323,14 @@ impl CodeGenerator {
                 let label = method_call.method.to_uppercase();
 
                 if method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        &format!("Log.{} requires at least 1 argument", method_call.method),
-                        &format!(
-                            "Usage: Log.{}(\"message\", arg1, arg2, ...)",
-                            method_call.method
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            &format!("Log.{} requires at least 1 argument", method_call.method),
+                            &format!(
+                                "Usage: Log.{}(\"message\", arg1, arg2, ...)",
+                                method_call.method
+                            ),
                         ),
                     )));
                 }
@@ -14537,10 +16543,12 @@ impl CodeGenerator {
             }
             "setLevel" => {
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Log.setLevel requires exactly 1 argument",
-                        "Usage: Log.setLevel(\"info\") — levels: debug, info, warn, error",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Log.setLevel requires exactly 1 argument",
+                            "Usage: Log.setLevel(\"info\") — levels: debug, info, warn, error",
+                        ),
                     )));
                 }
 
@@ -14549,10 +16557,12 @@ impl CodeGenerator {
                 self.output.push_str("))");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Log function: {}", method_call.method),
-                    "Available Log functions: info, warn, error, debug, setLevel",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Log function: {}", method_call.method),
+                        "Available Log functions: info, warn, error, debug, setLevel",
+                    ),
                 )));
             }
         }
@@ -14568,10 +16578,12 @@ impl CodeGenerator {
             "load" => {
                 // Config.load(path) returns (Option<HashMap<String, String>>, String)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Config.load requires exactly 1 argument",
-                        "Usage: Config.load(\"path/to/.env\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Config.load requires exactly 1 argument",
+                            "Usage: Config.load(\"path/to/.env\")",
+                        ),
                     )));
                 }
                 self.output.push_str("liva_config_load(&");
@@ -14581,10 +16593,12 @@ impl CodeGenerator {
             "get" => {
                 // Config.get(map, key) returns (Option<String>, String)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Config.get requires exactly 2 arguments",
-                        "Usage: Config.get(config, \"KEY\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Config.get requires exactly 2 arguments",
+                            "Usage: Config.get(config, \"KEY\")",
+                        ),
                     )));
                 }
                 self.output.push_str("liva_config_get(&");
@@ -14596,10 +16610,12 @@ impl CodeGenerator {
             "getInt" => {
                 // Config.getInt(map, key) returns (Option<i32>, String)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Config.getInt requires exactly 2 arguments",
-                        "Usage: Config.getInt(config, \"PORT\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Config.getInt requires exactly 2 arguments",
+                            "Usage: Config.getInt(config, \"PORT\")",
+                        ),
                     )));
                 }
                 self.output.push_str("liva_config_get_int(&");
@@ -14611,10 +16627,12 @@ impl CodeGenerator {
             "getBool" => {
                 // Config.getBool(map, key) returns (Option<bool>, String)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Config.getBool requires exactly 2 arguments",
-                        "Usage: Config.getBool(config, \"VERBOSE\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Config.getBool requires exactly 2 arguments",
+                            "Usage: Config.getBool(config, \"VERBOSE\")",
+                        ),
                     )));
                 }
                 self.output.push_str("liva_config_get_bool(&");
@@ -14626,10 +16644,12 @@ impl CodeGenerator {
             "getAll" => {
                 // Config.getAll(map) returns BTreeMap<String, String> as Map<string, string>
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Config.getAll requires exactly 1 argument",
-                        "Usage: Config.getAll(config)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Config.getAll requires exactly 1 argument",
+                            "Usage: Config.getAll(config)",
+                        ),
                     )));
                 }
                 self.output.push_str("liva_config_get_all(&");
@@ -14637,10 +16657,12 @@ impl CodeGenerator {
                 self.output.push_str(")");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Config function: {}", method_call.method),
-                    "Available Config functions: load, get, getInt, getBool, getAll",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Config function: {}", method_call.method),
+                        "Available Config functions: load, get, getInt, getBool, getAll",
+                    ),
                 )));
             }
         }
@@ -14658,10 +16680,12 @@ impl CodeGenerator {
             "test" => {
                 // Regex.test(pattern, text) → bool
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Regex.test requires exactly 2 arguments",
-                        "Usage: Regex.test(pattern, text)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Regex.test requires exactly 2 arguments",
+                            "Usage: Regex.test(pattern, text)",
+                        ),
                     )));
                 }
 
@@ -14675,10 +16699,12 @@ impl CodeGenerator {
                 // Regex.match(pattern, text) → (Option<String>, String)
                 // Returns first match or error
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Regex.match requires exactly 2 arguments",
-                        "Usage: Regex.match(pattern, text)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Regex.match requires exactly 2 arguments",
+                            "Usage: Regex.match(pattern, text)",
+                        ),
                     )));
                 }
 
@@ -14692,10 +16718,12 @@ impl CodeGenerator {
                 // Regex.findAll(pattern, text) → [string]
                 // Returns all matches (empty array if no matches or invalid pattern)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Regex.findAll requires exactly 2 arguments",
-                        "Usage: Regex.findAll(pattern, text)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Regex.findAll requires exactly 2 arguments",
+                            "Usage: Regex.findAll(pattern, text)",
+                        ),
                     )));
                 }
 
@@ -14708,10 +16736,12 @@ impl CodeGenerator {
             "replace" => {
                 // Regex.replace(pattern, text, replacement) → string
                 if method_call.args.len() != 3 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Regex.replace requires exactly 3 arguments",
-                        "Usage: Regex.replace(pattern, text, replacement)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Regex.replace requires exactly 3 arguments",
+                            "Usage: Regex.replace(pattern, text, replacement)",
+                        ),
                     )));
                 }
 
@@ -14729,10 +16759,12 @@ impl CodeGenerator {
             "split" => {
                 // Regex.split(pattern, text) → [string]
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Regex.split requires exactly 2 arguments",
-                        "Usage: Regex.split(pattern, text)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Regex.split requires exactly 2 arguments",
+                            "Usage: Regex.split(pattern, text)",
+                        ),
                     )));
                 }
 
@@ -14747,10 +16779,12 @@ impl CodeGenerator {
                 self.output.push_str(".to_string()])");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Regex function: {}", method_call.method),
-                    "Available Regex functions: test, match, findAll, replace, split",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Regex function: {}", method_call.method),
+                        "Available Regex functions: test, match, findAll, replace, split",
+                    ),
                 )));
             }
         }
@@ -14766,23 +16800,29 @@ impl CodeGenerator {
             "now" => {
                 // Date.now() → chrono::Local::now().naive_local()
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.now takes no arguments",
-                        "Usage: Date.now()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.now takes no arguments",
+                            "Usage: Date.now()",
+                        ),
                     )));
                 }
-                self.output.push_str("chrono::Local::now().naive_local()");
+                if self.ctx.has_mocks {
+                    self.output.push_str("liva_rt::mocked::<chrono::NaiveDateTime>(\"Date.now\").unwrap_or_else(|| chrono::Local::now().naive_local())");
+                } else {
+                    self.output.push_str("chrono::Local::now().naive_local()");
+                }
             }
             "new" => {
                 // Date.new(year, month, day) → NaiveDate::from_ymd_opt(y,m,d).unwrap().and_hms_opt(0,0,0).unwrap()
                 // Date.new(year, month, day, hour, minute, second) → full datetime
                 if method_call.args.len() != 3 && method_call.args.len() != 6 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                    return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                         "E3000",
                         "Date.new requires 3 or 6 arguments",
                         "Usage: Date.new(year, month, day) or Date.new(year, month, day, hour, minute, second)",
-                    )));
+                    ))));
                 }
                 self.output.push_str("chrono::NaiveDate::from_ymd_opt(");
                 self.generate_expr(&method_call.args[0])?;
@@ -14806,10 +16846,12 @@ impl CodeGenerator {
             "parse" => {
                 // Date.parse(str, pattern) → (Option<NaiveDateTime>, String)
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.parse requires exactly 2 arguments",
-                        "Usage: Date.parse(\"2026-03-11\", \"%Y-%m-%d\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.parse requires exactly 2 arguments",
+                            "Usage: Date.parse(\"2026-03-11\", \"%Y-%m-%d\")",
+                        ),
                     )));
                 }
                 // Convert Liva-style patterns to chrono strftime patterns
@@ -14826,10 +16868,12 @@ impl CodeGenerator {
             "timestamp" => {
                 // Date.timestamp() → chrono::Local::now().timestamp_millis() as i32
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.timestamp takes no arguments",
-                        "Usage: Date.timestamp()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.timestamp takes no arguments",
+                            "Usage: Date.timestamp()",
+                        ),
                     )));
                 }
                 self.output
@@ -14838,10 +16882,12 @@ impl CodeGenerator {
             "nowUtc" => {
                 // D.5: UTC equivalent of Date.now() — naive UTC datetime.
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.nowUtc takes no arguments",
-                        "Usage: Date.nowUtc()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.nowUtc takes no arguments",
+                            "Usage: Date.nowUtc()",
+                        ),
                     )));
                 }
                 self.output.push_str("chrono::Utc::now().naive_utc()");
@@ -14849,10 +16895,12 @@ impl CodeGenerator {
             "toIso" => {
                 // D.5: ISO 8601 formatting (NaiveDateTime -> string).
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.toIso requires exactly 1 argument",
-                        "Usage: Date.toIso(date)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.toIso requires exactly 1 argument",
+                            "Usage: Date.toIso(date)",
+                        ),
                     )));
                 }
                 self.output.push_str("(");
@@ -14864,10 +16912,12 @@ impl CodeGenerator {
                 // D.5: parse ISO 8601 -> (Option<NaiveDateTime>, String).
                 // Accepts both "T" and " " separators between date and time.
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.parseIso requires exactly 1 argument",
-                        "Usage: Date.parseIso(\"2026-05-06T12:34:56\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.parseIso requires exactly 1 argument",
+                            "Usage: Date.parseIso(\"2026-05-06T12:34:56\")",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __s = ");
@@ -14875,11 +16925,11 @@ impl CodeGenerator {
                 self.output.push_str(".to_string(); match chrono::NaiveDateTime::parse_from_str(&__s, \"%Y-%m-%dT%H:%M:%S\").or_else(|_| chrono::NaiveDateTime::parse_from_str(&__s, \"%Y-%m-%d %H:%M:%S\")) { Ok(dt) => (dt, String::new()), Err(e) => (chrono::NaiveDateTime::default(), format!(\"Date.parseIso error: {}\", e)) } }");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown Date function: {}", method_call.method),
                     "Available: Date.now(), Date.nowUtc(), Date.new(y,m,d), Date.parse(str, pattern), Date.parseIso(str), Date.toIso(date), Date.timestamp()",
-                )));
+                ))));
             }
         }
 
@@ -14895,10 +16945,12 @@ impl CodeGenerator {
                 // d.format(pattern) → d.format(chrono_pattern).to_string()
                 // Convert Liva-style patterns (YYYY, MM, DD, HH, mm, ss) to chrono strftime (%Y, %m, %d, %H, %M, %S)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.format requires exactly 1 argument",
-                        "Usage: d.format(\"DD/MM/YYYY\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.format requires exactly 1 argument",
+                            "Usage: d.format(\"DD/MM/YYYY\")",
+                        ),
                     )));
                 }
                 self.generate_expr(&method_call.object)?;
@@ -14910,10 +16962,12 @@ impl CodeGenerator {
                 // d.add(n, unit) → d + chrono::Duration::xxx(n)
                 // units: "days", "hours", "minutes", "seconds", "weeks"
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.add requires exactly 2 arguments",
-                        "Usage: d.add(7, \"days\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.add requires exactly 2 arguments",
+                            "Usage: d.add(7, \"days\")",
+                        ),
                     )));
                 }
                 // Generate: d + chrono::Duration::days(n as i64) (etc.)
@@ -14941,10 +16995,12 @@ impl CodeGenerator {
             "diff" => {
                 // d.diff(other, unit) → difference in specified unit
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.diff requires exactly 2 arguments",
-                        "Usage: d.diff(other, \"days\")",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.diff requires exactly 2 arguments",
+                            "Usage: d.diff(other, \"days\")",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __liva_diff = ");
@@ -14974,10 +17030,12 @@ impl CodeGenerator {
             "toString" => {
                 // d.toString() → d.format("%Y-%m-%dT%H:%M:%S").to_string()
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Date.toString takes no arguments",
-                        "Usage: d.toString()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Date.toString takes no arguments",
+                            "Usage: d.toString()",
+                        ),
                     )));
                 }
                 self.generate_expr(&method_call.object)?;
@@ -14985,10 +17043,12 @@ impl CodeGenerator {
                     .push_str(".format(\"%Y-%m-%dT%H:%M:%S\").to_string()");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Date method: {}", method_call.method),
-                    "Available: format(pattern), add(n, unit), diff(other, unit), toString()",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Date method: {}", method_call.method),
+                        "Available: format(pattern), add(n, unit), diff(other, unit), toString()",
+                    ),
                 )));
             }
         }
@@ -15005,10 +17065,12 @@ impl CodeGenerator {
                 // CSV.read(path) or CSV.read(path, separator: "\t")
                 // Returns (Option<Vec<Vec<String>>>, String) — fallible
                 if method_call.args.is_empty() || method_call.args.len() > 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.read requires 1-2 arguments",
-                        "Usage: CSV.read(path) or CSV.read(path, separator)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.read requires 1-2 arguments",
+                            "Usage: CSV.read(path) or CSV.read(path, separator)",
+                        ),
                     )));
                 }
                 // Determine separator — default ','
@@ -15060,10 +17122,12 @@ impl CodeGenerator {
                 // CSV.write(path, data) — data is Vec<Vec<String>>
                 // Returns (Option<bool>, String) — fallible
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.write requires exactly 2 arguments",
-                        "Usage: CSV.write(path, data)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.write requires exactly 2 arguments",
+                            "Usage: CSV.write(path, data)",
+                        ),
                     )));
                 }
                 self.output.push_str("{\n");
@@ -15089,10 +17153,12 @@ impl CodeGenerator {
                 // CSV.readTable(path) — reads CSV with first row as headers
                 // Returns (Option<Vec<std::collections::HashMap<String,String>>>, String) — fallible
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.readTable requires exactly 1 argument",
-                        "Usage: CSV.readTable(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.readTable requires exactly 1 argument",
+                            "Usage: CSV.readTable(path)",
+                        ),
                     )));
                 }
                 self.output.push_str("{\n");
@@ -15147,10 +17213,12 @@ impl CodeGenerator {
                 // CSV.writeTable(path, table) — table is Vec<HashMap<String,String>>
                 // Returns (Option<bool>, String) — fallible
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.writeTable requires exactly 2 arguments",
-                        "Usage: CSV.writeTable(path, table)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.writeTable requires exactly 2 arguments",
+                            "Usage: CSV.writeTable(path, table)",
+                        ),
                     )));
                 }
                 self.output.push_str("{\n");
@@ -15192,10 +17260,12 @@ impl CodeGenerator {
             "parse" => {
                 // CSV.parse(text) → Vec<Vec<String>> — pure, no error binding
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.parse requires exactly 1 argument",
-                        "Usage: CSV.parse(text)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.parse requires exactly 1 argument",
+                            "Usage: CSV.parse(text)",
+                        ),
                     )));
                 }
                 self.output.push_str("{\n");
@@ -15226,10 +17296,12 @@ impl CodeGenerator {
             "stringify" => {
                 // CSV.stringify(rows) → String — pure, no error binding
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.stringify requires exactly 1 argument",
-                        "Usage: CSV.stringify(rows)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.stringify requires exactly 1 argument",
+                            "Usage: CSV.stringify(rows)",
+                        ),
                     )));
                 }
                 self.generate_expr(&method_call.args[0])?;
@@ -15247,10 +17319,12 @@ impl CodeGenerator {
             "headers" => {
                 // CSV.headers(table) → Vec<String> — gets sorted keys from first row
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.headers requires exactly 1 argument",
-                        "Usage: CSV.headers(table)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.headers requires exactly 1 argument",
+                            "Usage: CSV.headers(table)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __t = &");
@@ -15260,10 +17334,12 @@ impl CodeGenerator {
             "column" => {
                 // CSV.column(table, colName) → Vec<String> — extract a column from table
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "CSV.column requires exactly 2 arguments",
-                        "Usage: CSV.column(table, columnName)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "CSV.column requires exactly 2 arguments",
+                            "Usage: CSV.column(table, columnName)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __col_name = &");
@@ -15273,11 +17349,11 @@ impl CodeGenerator {
                 self.output.push_str(".iter().map(|row| row.get(__col_name.as_str()).cloned().unwrap_or_default()).collect::<Vec<String>>() }");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown CSV function: {}", method_call.method),
                     "Available: read, write, readTable, writeTable, parse, stringify, headers, column",
-                )));
+                ))));
             }
         }
 
@@ -15292,19 +17368,23 @@ impl CodeGenerator {
             "create" => {
                 // Server.create() → axum::Router::new()
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Server.create takes no arguments",
-                        "Usage: let app = Server.create()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Server.create takes no arguments",
+                            "Usage: let app = Server.create()",
+                        ),
                     )));
                 }
                 self.output.push_str("axum::Router::new()");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Server function: {}", method_call.method),
-                    "Available: create()",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Server function: {}", method_call.method),
+                        "Available: create()",
+                    ),
                 )));
             }
         }
@@ -15320,10 +17400,12 @@ impl CodeGenerator {
             "get" | "post" | "put" | "delete" => {
                 // app.get("/path", handler) → app = app.route("/path", axum::routing::get(handler))
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        &format!("Server.{} requires exactly 2 arguments", method_call.method),
-                        &format!("Usage: app.{}(path, handler)", method_call.method),
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            &format!("Server.{} requires exactly 2 arguments", method_call.method),
+                            &format!("Usage: app.{}(path, handler)", method_call.method),
+                        ),
                     )));
                 }
 
@@ -15419,20 +17501,24 @@ impl CodeGenerator {
                         self.output.push_str(" }");
                     }
                 } else {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Server route handler must be a lambda/function",
-                        &format!("Usage: app.{}(path, (req) => {{ ... }})", http_method),
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Server route handler must be a lambda/function",
+                            &format!("Usage: app.{}(path, (req) => {{ ... }})", http_method),
+                        ),
                     )));
                 }
             }
             "listen" => {
                 // app.listen(port) → start axum server
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Server.listen requires exactly 1 argument",
-                        "Usage: app.listen(port)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Server.listen requires exactly 1 argument",
+                            "Usage: app.listen(port)",
+                        ),
                     )));
                 }
 
@@ -15444,14 +17530,14 @@ impl CodeGenerator {
 
                 write!(self.output, "{{ let __addr = format!(\"0.0.0.0:{{}}\", ",).unwrap();
                 self.generate_expr(&method_call.args[0])?;
-                write!(self.output, "); let __listener = tokio::net::TcpListener::bind(&__addr).await.unwrap(); axum::serve(__listener, {}).await.unwrap(); }}", var_name).unwrap();
+                write!(self.output, "); let __listener = tokio::net::TcpListener::bind(&__addr).await.unwrap(); axum::serve(__listener, {}).with_graceful_shutdown(liva_shutdown_signal()).await.unwrap(); }}", var_name).unwrap();
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown server method: {}", method_call.method),
                     "Available: get(path, handler), post(path, handler), put(path, handler), delete(path, handler), listen(port)",
-                )));
+                ))));
             }
         }
 
@@ -15512,10 +17598,12 @@ impl CodeGenerator {
                 // Express-like: accepts Map literal, Map var, [Map] var, or string
                 // → (StatusCode::OK, axum::Json(...))
                 if method_call.args.is_empty() || method_call.args.len() > 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Response.json requires 1-2 arguments",
-                        "Usage: Response.json(data) or Response.json(data, statusCode)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Response.json requires 1-2 arguments",
+                            "Usage: Response.json(data) or Response.json(data, statusCode)",
+                        ),
                     )));
                 }
 
@@ -15611,10 +17699,12 @@ impl CodeGenerator {
                 // Response.text(text) or Response.text(text, status)
                 // → (StatusCode::OK, text.to_string())
                 if method_call.args.is_empty() || method_call.args.len() > 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Response.text requires 1-2 arguments",
-                        "Usage: Response.text(text) or Response.text(text, statusCode)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Response.text requires 1-2 arguments",
+                            "Usage: Response.text(text) or Response.text(text, statusCode)",
+                        ),
                     )));
                 }
 
@@ -15640,10 +17730,12 @@ impl CodeGenerator {
             "status" => {
                 // Response.status(code) → StatusCode only (for empty responses)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Response.status requires exactly 1 argument",
-                        "Usage: Response.status(code)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Response.status requires exactly 1 argument",
+                            "Usage: Response.status(code)",
+                        ),
                     )));
                 }
                 self.output.push_str("axum::http::StatusCode::from_u16(");
@@ -15652,10 +17744,12 @@ impl CodeGenerator {
                     .push_str(" as u16).unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR)");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Response function: {}", method_call.method),
-                    "Available: json(data[, status]), text(msg[, status]), status(code)",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Response function: {}", method_call.method),
+                        "Available: json(data[, status]), text(msg[, status]), status(code)",
+                    ),
                 )));
             }
         }
@@ -15671,10 +17765,12 @@ impl CodeGenerator {
             "open" => {
                 // DB.open(path) → (Option<Connection>, String) — fallible
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "DB.open requires exactly 1 argument",
-                        "Usage: let db, err = DB.open(path)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "DB.open requires exactly 1 argument",
+                            "Usage: let db, err = DB.open(path)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __path = ");
@@ -15684,10 +17780,12 @@ impl CodeGenerator {
             "exec" => {
                 // DB.exec(db, sql) or DB.exec(db, sql, params) → (Option<String>, String) — fallible
                 if method_call.args.len() < 2 || method_call.args.len() > 3 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "DB.exec requires 2-3 arguments",
-                        "Usage: DB.exec(db, sql) or DB.exec(db, sql, params)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "DB.exec requires 2-3 arguments",
+                            "Usage: DB.exec(db, sql) or DB.exec(db, sql, params)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __sql = ");
@@ -15710,10 +17808,12 @@ impl CodeGenerator {
             "query" => {
                 // DB.query(db, sql) or DB.query(db, sql, params) → (Option<Vec<HashMap>>, String) — fallible
                 if method_call.args.len() < 2 || method_call.args.len() > 3 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "DB.query requires 2-3 arguments",
-                        "Usage: DB.query(db, sql) or DB.query(db, sql, params)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "DB.query requires 2-3 arguments",
+                            "Usage: DB.query(db, sql) or DB.query(db, sql, params)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __sql = ");
@@ -15737,10 +17837,12 @@ impl CodeGenerator {
             "close" => {
                 // DB.close(db) → drop the connection (no-op, Rust drops automatically)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "DB.close requires exactly 1 argument",
-                        "Usage: DB.close(db)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "DB.close requires exactly 1 argument",
+                            "Usage: DB.close(db)",
+                        ),
                     )));
                 }
                 self.output.push_str("drop(");
@@ -15748,11 +17850,11 @@ impl CodeGenerator {
                 self.output.push(')');
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown DB function: {}", method_call.method),
                     "Available: open(path), exec(db, sql[, params]), query(db, sql[, params]), close(db)",
-                )));
+                ))));
             }
         }
 
@@ -15789,14 +17891,20 @@ impl CodeGenerator {
             "nextInt" => {
                 // Random.nextInt(min, max) → i64
                 if method_call.args.len() != 2 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Random.nextInt requires exactly 2 arguments",
-                        "Usage: Random.nextInt(min, max)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Random.nextInt requires exactly 2 arguments",
+                            "Usage: Random.nextInt(min, max)",
+                        ),
                     )));
                 }
-                self.output
-                    .push_str("{ use rand::Rng; rand::thread_rng().gen_range(");
+                write!(
+                    self.output,
+                    "{{ use rand::Rng; {}.gen_range(",
+                    self.rng_expr()
+                )
+                .unwrap();
                 self.generate_expr(&method_call.args[0])?;
                 self.output.push_str("..=");
                 self.generate_expr(&method_call.args[1])?;
@@ -15805,67 +17913,90 @@ impl CodeGenerator {
             "nextFloat" => {
                 // Random.nextFloat(min, max) → f64, or Random.nextFloat() → 0.0..1.0
                 if method_call.args.is_empty() {
-                    self.output.push_str("rand::random::<f64>()");
+                    let random_f64 = self.random_f64_expr();
+                    self.output.push_str(random_f64);
                 } else if method_call.args.len() == 2 {
                     self.output.push_str("{ use rand::Rng; let __min: f64 = ");
                     self.generate_expr(&method_call.args[0])?;
                     self.output.push_str(" as f64; let __max: f64 = ");
                     self.generate_expr(&method_call.args[1])?;
-                    self.output
-                        .push_str(" as f64; rand::thread_rng().gen_range(__min..__max) }");
+                    write!(
+                        self.output,
+                        " as f64; {}.gen_range(__min..__max) }}",
+                        self.rng_expr()
+                    )
+                    .unwrap();
                 } else {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Random.nextFloat requires 0 or 2 arguments",
-                        "Usage: Random.nextFloat() or Random.nextFloat(min, max)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Random.nextFloat requires 0 or 2 arguments",
+                            "Usage: Random.nextFloat() or Random.nextFloat(min, max)",
+                        ),
                     )));
                 }
             }
             "choice" => {
                 // Random.choice(array) → Option<T> element
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Random.choice requires exactly 1 argument",
-                        "Usage: Random.choice(array)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Random.choice requires exactly 1 argument",
+                            "Usage: Random.choice(array)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ use rand::Rng; let __arr = &");
                 self.generate_expr(&method_call.args[0])?;
-                self.output.push_str("; if __arr.is_empty() { panic!(\"Random.choice: empty array\") } else { __arr[rand::thread_rng().gen_range(0..__arr.len())].clone() } }");
+                write!(self.output, "; if __arr.is_empty() {{ panic!(\"Random.choice: empty array\") }} else {{ __arr[{}.gen_range(0..__arr.len())].clone() }} }}", self.rng_expr()).unwrap();
             }
             "shuffle" => {
                 // Random.shuffle(array) → new shuffled array
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Random.shuffle requires exactly 1 argument",
-                        "Usage: Random.shuffle(array)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Random.shuffle requires exactly 1 argument",
+                            "Usage: Random.shuffle(array)",
+                        ),
                     )));
                 }
                 self.output
                     .push_str("{ use rand::seq::SliceRandom; let mut __v = ");
                 self.generate_expr(&method_call.args[0])?;
-                self.output
-                    .push_str(".clone(); __v.shuffle(&mut rand::thread_rng()); __v }");
+                write!(
+                    self.output,
+                    ".clone(); __v.shuffle(&mut {}); __v }}",
+                    self.rng_expr()
+                )
+                .unwrap();
             }
             "uuid" => {
                 // Random.uuid() → String (UUID v4)
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Random.uuid takes no arguments",
-                        "Usage: Random.uuid()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Random.uuid takes no arguments",
+                            "Usage: Random.uuid()",
+                        ),
                     )));
                 }
-                self.output.push_str("uuid::Uuid::new_v4().to_string()");
+                if self.ctx.deterministic {
+                    self.output.push_str(
+                        "uuid::Builder::from_random_bytes(rand::Rng::gen(&mut crate::liva_rng())).into_uuid().to_string()",
+                    );
+                } else {
+                    self.output.push_str("uuid::Uuid::new_v4().to_string()");
+                }
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown Random function: {}", method_call.method),
                     "Available: nextInt(min, max), nextFloat([min, max]), choice(arr), shuffle(arr), uuid()",
-                )));
+                ))));
             }
         }
 
@@ -15880,10 +18011,12 @@ impl CodeGenerator {
             "sha256" => {
                 // Crypto.sha256(input) → String (hex)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Crypto.sha256 requires exactly 1 argument",
-                        "Usage: Crypto.sha256(input)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Crypto.sha256 requires exactly 1 argument",
+                            "Usage: Crypto.sha256(input)",
+                        ),
                     )));
                 }
                 self.output.push_str(
@@ -15896,10 +18029,12 @@ impl CodeGenerator {
             "md5" => {
                 // Crypto.md5(input) → String (hex)
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Crypto.md5 requires exactly 1 argument",
-                        "Usage: Crypto.md5(input)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Crypto.md5 requires exactly 1 argument",
+                            "Usage: Crypto.md5(input)",
+                        ),
                     )));
                 }
                 self.output.push_str(
@@ -15912,10 +18047,12 @@ impl CodeGenerator {
             "base64Encode" => {
                 // Crypto.base64Encode(input) → String
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Crypto.base64Encode requires exactly 1 argument",
-                        "Usage: Crypto.base64Encode(input)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Crypto.base64Encode requires exactly 1 argument",
+                            "Usage: Crypto.base64Encode(input)",
+                        ),
                     )));
                 }
                 self.output.push_str(
@@ -15927,10 +18064,12 @@ impl CodeGenerator {
             "base64Decode" => {
                 // Crypto.base64Decode(input) → (Option<String>, String) — fallible
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Crypto.base64Decode requires exactly 1 argument",
-                        "Usage: Crypto.base64Decode(input)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Crypto.base64Decode requires exactly 1 argument",
+                            "Usage: Crypto.base64Decode(input)",
+                        ),
                     )));
                 }
                 self.output.push_str(
@@ -15940,14 +18079,62 @@ impl CodeGenerator {
                 self.output.push_str(".as_bytes()) { Ok(bytes) => match String::from_utf8(bytes) { Ok(s) => (Some(s), String::new()), Err(e) => (None, format!(\"Crypto.base64Decode UTF-8 error: {}\", e)) }, Err(e) => (None, format!(\"Crypto.base64Decode error: {}\", e)) } }");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E3000",
                     &format!("Unknown Crypto function: {}", method_call.method),
                     "Available: sha256(input), md5(input), base64Encode(input), base64Decode(input)",
-                )));
+                ))));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `Bits.countOnes(x)` → `i32::count_ones(x) as i32`; the counts come
+    /// back as `u32` in Rust and the rotations take a `u32` amount
+    fn generate_bits_function_call(
+        &mut self,
+        method_call: &crate::ast::MethodCallExpr,
+    ) -> Result<()> {
+        let (function, arity) = match method_call.method.as_str() {
+            "countOnes" => ("count_ones", 1),
+            "leadingZeros" => ("leading_zeros", 1),
+            "trailingZeros" => ("trailing_zeros", 1),
+            "rotateLeft" => ("rotate_left", 2),
+            "rotateRight" => ("rotate_right", 2),
+            _ => {
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
+                    "E3000",
+                    &format!("Unknown Bits function: {}", method_call.method),
+                    "Available: countOnes(x), leadingZeros(x), trailingZeros(x), rotateLeft(x, n), rotateRight(x, n)",
+                ))));
             }
+        };
+        if method_call.args.len() != arity {
+            let usage = if arity == 1 { "x" } else { "x, n" };
+            return Err(CompilerError::CodegenError(Box::new(
+                SemanticErrorInfo::new(
+                    "E3000",
+                    &format!(
+                        "Bits.{} requires exactly {} argument{}",
+                        method_call.method,
+                        arity,
+                        if arity == 1 { "" } else { "s" }
+                    ),
+                    &format!("Usage: Bits.{}({})", method_call.method, usage),
+                ),
+            )));
         }
 
+        write!(self.output, "i32::{}(", function).unwrap();
+        self.generate_expr(&method_call.args[0])?;
+        if arity == 1 {
+            self.output.push_str(") as i32");
+        } else {
+            self.output.push_str(", (");
+            self.generate_expr(&method_call.args[1])?;
+            self.output.push_str(") as u32)");
+        }
         Ok(())
     }
 
@@ -15960,10 +18147,12 @@ impl CodeGenerator {
                 // Process.exec(cmd) → (Option<String>, String) — fallible
                 // Runs command via shell and captures output
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Process.exec requires exactly 1 argument",
-                        "Usage: Process.exec(command)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Process.exec requires exactly 1 argument",
+                            "Usage: Process.exec(command)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __cmd = &");
@@ -15973,23 +18162,42 @@ impl CodeGenerator {
             "spawn" => {
                 // Process.spawn(cmd) → (Option<i64>, String) — fallible, returns PID
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Process.spawn requires exactly 1 argument",
-                        "Usage: Process.spawn(command)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Process.spawn requires exactly 1 argument",
+                            "Usage: Process.spawn(command)",
+                        ),
                     )));
                 }
                 self.output.push_str("{ let __cmd = &");
                 self.generate_expr(&method_call.args[0])?;
                 self.output.push_str("; match std::process::Command::new(\"sh\").arg(\"-c\").arg(__cmd).spawn() { Ok(child) => (Some(child.id() as i64), String::new()), Err(e) => (None, format!(\"Process.spawn error: {}\", e)) } }");
             }
+            "args" => {
+                // Process.args() → [string]: what followed `--` in `livac run`,
+                // without the program name Sys.args() starts with
+                if !method_call.args.is_empty() {
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Process.args takes no arguments",
+                            "Usage: Process.args()",
+                        ),
+                    )));
+                }
+                self.output
+                    .push_str("std::env::args().skip(1).collect::<Vec<String>>()");
+            }
             "pid" => {
                 // Process.pid() → i64 (current process PID)
                 if !method_call.args.is_empty() {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Process.pid takes no arguments",
-                        "Usage: Process.pid()",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Process.pid takes no arguments",
+                            "Usage: Process.pid()",
+                        ),
                     )));
                 }
                 self.output.push_str("(std::process::id() as i64)");
@@ -15997,10 +18205,12 @@ impl CodeGenerator {
             "exit" => {
                 // Process.exit(code) — terminates process
                 if method_call.args.len() != 1 {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                        "E3000",
-                        "Process.exit requires exactly 1 argument",
-                        "Usage: Process.exit(code)",
+                    return Err(CompilerError::CodegenError(Box::new(
+                        SemanticErrorInfo::new(
+                            "E3000",
+                            "Process.exit requires exactly 1 argument",
+                            "Usage: Process.exit(code)",
+                        ),
                     )));
                 }
                 self.output.push_str("std::process::exit(");
@@ -16008,10 +18218,12 @@ impl CodeGenerator {
                 self.output.push_str(" as i32)");
             }
             _ => {
-                return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                    "E3000",
-                    &format!("Unknown Process function: {}", method_call.method),
-                    "Available: exec(cmd), spawn(cmd), pid(), exit(code)",
+                return Err(CompilerError::CodegenError(Box::new(
+                    SemanticErrorInfo::new(
+                        "E3000",
+                        &format!("Unknown Process function: {}", method_call.method),
+                        "Available: exec(cmd), spawn(cmd), args(), pid(), exit(code)",
+                    ),
                 )));
             }
         }
@@ -16026,8 +18238,8 @@ impl CodeGenerator {
         module_name: &str,
         method_call: &crate::ast::MethodCallExpr,
     ) -> Result<()> {
-        // Convert method name to snake_case for Rust
-        let rust_method = to_snake_case(&method_call.method);
+        // Convert method name to the Rust spelling of function names
+        let rust_method = map_identifier_case(&method_call.method, self.ctx.identifier_mapping);
 
         // Generate module::function(args)
         self.output.push_str(module_name);
@@ -16066,7 +18278,8 @@ impl CodeGenerator {
     fn generate_async_call(&mut self, call: &CallExpr) -> Result<()> {
         // Phase 2: NO await here - just create the Task
         // The await will be inserted at first use of the variable
-        self.output.push_str("liva_rt::spawn_async(async move { ");
+        let spawn = self.task_spawner("spawn_async");
+        write!(self.output, "{}(async move {{ ", spawn).unwrap();
 
         // Check if callee is a MethodCall (e.g., HTTP.get())
         if let Expr::MethodCall(_) = &*call.callee {
@@ -16081,10 +18294,8 @@ impl CodeGenerator {
                 if i > 0 {
                     self.output.push_str(", ");
                 }
-                // Convert string literals to String automatically
                 if let Expr::Literal(Literal::String(_)) = arg {
-                    self.generate_expr(arg)?;
-                    self.output.push_str(".to_string()");
+                    self.generate_string_arg(&call.callee, i, arg)?;
                 } else {
                     self.generate_expr(arg)?;
                 }
@@ -16107,17 +18318,16 @@ impl CodeGenerator {
     fn generate_parallel_call(&mut self, call: &CallExpr) -> Result<()> {
         // Phase 2: NO await here - just create the Task
         // The await will be inserted at first use of the variable
-        self.output.push_str("liva_rt::spawn_parallel(move || ");
+        let spawn = self.task_spawner("spawn_parallel");
+        write!(self.output, "{}(move || ", spawn).unwrap();
         self.generate_expr(&call.callee)?;
         self.output.push('(');
         for (i, arg) in call.args.iter().enumerate() {
             if i > 0 {
                 self.output.push_str(", ");
             }
-            // Convert string literals to String automatically
             if let Expr::Literal(Literal::String(_)) = arg {
-                self.generate_expr(arg)?;
-                self.output.push_str(".to_string()");
+                self.generate_string_arg(&call.callee, i, arg)?;
             } else {
                 self.generate_expr(arg)?;
             }
@@ -16132,9 +18342,9 @@ impl CodeGenerator {
         let callee_name = match call.callee.as_ref() {
             Expr::Identifier(name) => name.clone(),
             _ => {
-                return Err(CompilerError::CodegenError(
+                return Err(CompilerError::CodegenError(Box::new(
                     "Task calls currently only support simple function names".into(),
-                ));
+                )));
             }
         };
 
@@ -16142,16 +18352,14 @@ impl CodeGenerator {
 
         match mode {
             ConcurrencyMode::Async => {
-                self.output.push_str("liva_rt::spawn_async(async move { ");
-                write!(self.output, "{}(", rust_name).unwrap();
+                let spawn = self.task_spawner("spawn_async");
+                write!(self.output, "{}(async move {{ {}(", spawn, rust_name).unwrap();
                 for (i, arg) in call.args.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    // Convert string literals to String automatically
                     if let Expr::Literal(Literal::String(_)) = arg {
-                        self.generate_expr(arg)?;
-                        self.output.push_str(".to_string()");
+                        self.generate_string_arg(&call.callee, i, arg)?;
                     } else {
                         self.generate_expr(arg)?;
                     }
@@ -16159,16 +18367,14 @@ impl CodeGenerator {
                 self.output.push_str(") })");
             }
             ConcurrencyMode::Parallel => {
-                self.output.push_str("liva_rt::spawn_parallel(move || { ");
-                write!(self.output, "{}(", rust_name).unwrap();
+                let spawn = self.task_spawner("spawn_parallel");
+                write!(self.output, "{}(move || {{ {}(", spawn, rust_name).unwrap();
                 for (i, arg) in call.args.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    // Convert string literals to String automatically
                     if let Expr::Literal(Literal::String(_)) = arg {
-                        self.generate_expr(arg)?;
-                        self.output.push_str(".to_string()");
+                        self.generate_string_arg(&call.callee, i, arg)?;
                     } else {
                         self.generate_expr(arg)?;
                     }
@@ -16183,55 +18389,72 @@ impl CodeGenerator {
         let callee_name = match call.callee.as_ref() {
             Expr::Identifier(name) => name.clone(),
             _ => {
-                return Err(CompilerError::CodegenError(
+                return Err(CompilerError::CodegenError(Box::new(
                     "Fire calls currently only support simple function names".into(),
-                ));
+                )));
             }
         };
 
         let rust_name = self.sanitize_name(&callee_name);
 
+        // Inside a scope a fallible task's error is handed to the scope,
+        // which fails with it; elsewhere the result is dropped
+        let scoped = !self.task_scopes.is_empty();
+        let discard = if scoped && self.fallible_functions.contains(&callee_name) {
+            ""
+        } else {
+            ";"
+        };
+        let awaited = if scoped && self.async_functions.contains(&callee_name) {
+            ".await"
+        } else {
+            ""
+        };
+
         match mode {
             ConcurrencyMode::Async => {
-                self.output.push_str("liva_rt::fire_async(async move {");
-                self.output.push(' ');
-                write!(self.output, "{}(", rust_name).unwrap();
+                let fire = self.task_spawner("fire_async");
+                write!(self.output, "{}(async move {{ {}(", fire, rust_name).unwrap();
                 for (i, arg) in call.args.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    // Convert string literals to String automatically
                     if let Expr::Literal(Literal::String(_)) = arg {
-                        self.generate_expr(arg)?;
-                        self.output.push_str(".to_string()");
+                        self.generate_string_arg(&call.callee, i, arg)?;
                     } else {
                         self.generate_expr(arg)?;
                     }
                 }
-                self.output.push_str("); })");
+                write!(self.output, "){}{} }})", awaited, discard).unwrap();
             }
             ConcurrencyMode::Parallel => {
-                self.output.push_str("liva_rt::fire_parallel(move || {");
-                self.output.push(' ');
-                write!(self.output, "{}(", rust_name).unwrap();
+                let fire = self.task_spawner("fire_parallel");
+                write!(self.output, "{}(move || {{ {}(", fire, rust_name).unwrap();
                 for (i, arg) in call.args.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    // Convert string literals to String automatically
                     if let Expr::Literal(Literal::String(_)) = arg {
-                        self.generate_expr(arg)?;
-                        self.output.push_str(".to_string()");
+                        self.generate_string_arg(&call.callee, i, arg)?;
                     } else {
                         self.generate_expr(arg)?;
                     }
                 }
-                self.output.push_str("); })");
+                write!(self.output, "){} }})", discard).unwrap();
             }
         }
         Ok(())
     }
 
+    /// `liva_rt::<function>`, or the method of the innermost open scope
+    /// that starts the task inside it
+    fn task_spawner(&self, function: &str) -> String {
+        match self.task_scopes.last() {
+            Some(scope) => format!("{}.{}", scope, function),
+            None => format!("liva_rt::{}", function),
+        }
+    }
+
     fn generate_condition_expr(&mut self, expr: &Expr) -> Result<()> {
         // Special handling for error variables in conditions
         // if error_var -> error_var.is_some()
@@ -16338,6 +18561,42 @@ impl CodeGenerator {
             return Ok(());
         }
 
+        // A char literal compared with a string (a `charAt` result, a `chars()`
+        // item) compares as a one-character string: ch.as_str() == "a"
+        if matches!(
+            op,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge
+        ) {
+            let text_and_char = match (left, right) {
+                (text, Expr::Literal(Literal::Char(c))) => Some((text, *c, false)),
+                (Expr::Literal(Literal::Char(c)), text) => Some((text, *c, true)),
+                _ => None,
+            };
+            if let Some((text, c, char_first)) =
+                text_and_char.filter(|(text, ..)| self.expr_is_stringy(text))
+            {
+                let literal = format!("\"{}\"", c.escape_default());
+                if char_first {
+                    write!(self.output, "{} {} ", literal, op).unwrap();
+                }
+                let needs_parens = matches!(text, Expr::Binary { .. });
+                if needs_parens {
+                    self.output.push('(');
+                }
+                self.generate_expr(text)?;
+                if needs_parens {
+                    self.output.push(')');
+                }
+                if !matches!(text, Expr::Literal(Literal::String(_))) {
+                    self.output.push_str(".as_str()");
+                }
+                if !char_first {
+                    write!(self.output, " {} {}", op, literal).unwrap();
+                }
+                return Ok(());
+            }
+        }
+
         // Phase 3: Special handling for error binding variable comparisons with ""
         // Transform: err != "" to err.is_some()
         // Transform: err == "" to err.is_none()
@@ -16548,56 +18807,187 @@ impl CodeGenerator {
             }
         }
 
-        // Original logic for other binary operations
-        // Only add parentheses when necessary for precedence
-        let left_needs_parens = self.expr_needs_parens_for_binop(left, op);
-        let right_needs_parens = self.expr_needs_parens_for_binop(right, op);
-
-        if left_needs_parens {
-            self.output.push('(');
+        if matches!(op, BinOp::Pow) {
+            return self.generate_power(left, right);
         }
-        self.generate_expr(left)?;
-        if left_needs_parens {
+
+        // Every other operator is Rust's own; the printer adds the parentheses
+        let left = self.expr_node(left)?;
+        let right = self.expr_node(right)?;
+        let node = rust_ast::Expr::binary(left, rust_binop(op), right);
+        self.emit_node(&node);
+        Ok(())
+    }
+
+    /// `base ** exponent` becomes `powi` for a float raised to an integer,
+    /// `powf` once the exponent is a float and `i32::pow` for an integer
+    /// raised to a non-negative integer literal. When an operand's type
+    /// isn't known here, `liva_rt::pow` leaves the choice to rustc.
+    fn generate_power(&mut self, base: &Expr, exponent: &Expr) -> Result<()> {
+        let base_kind = self.numeric_kind(base);
+        let unsigned_exponent =
+            matches!(exponent, Expr::Literal(Literal::Int(n)) if u32::try_from(*n).is_ok());
+        let method = match (base_kind, self.numeric_kind(exponent)) {
+            (Some(Numeric::Float), Some(Numeric::Int)) => "powi",
+            (Some(_), Some(Numeric::Float)) => "powf",
+            (Some(Numeric::Int), Some(Numeric::Int)) if unsigned_exponent => "i32::pow",
+            _ => "liva_rt::pow",
+        };
+        self.explain(
+            self.output.len(),
+            format_args!(
+                "`**` on {} and {} uses {}",
+                numeric_name(base_kind),
+                numeric_name(self.numeric_kind(exponent)),
+                method
+            ),
+        );
+
+        if method.contains("::") {
+            write!(self.output, "{}(", method).unwrap();
+            self.generate_expr(base)?;
+            self.output.push_str(", ");
+            self.generate_expr(exponent)?;
             self.output.push(')');
+            return Ok(());
         }
 
-        write!(self.output, " {} ", op).unwrap();
-
-        if right_needs_parens {
+        // An integer base is converted so the float method applies
+        let int_base = base_kind == Some(Numeric::Int);
+        let needs_parens = !matches!(
+            base,
+            Expr::Literal(_)
+                | Expr::Identifier(_)
+                | Expr::Member { .. }
+                | Expr::Index { .. }
+                | Expr::Call(_)
+                | Expr::MethodCall(_)
+                | Expr::Cast { .. }
+        );
+        if int_base {
+            self.output.push('(');
+        }
+        if needs_parens {
             self.output.push('(');
         }
-        self.generate_expr(right)?;
-        if right_needs_parens {
+        self.generate_expr(base)?;
+        if needs_parens {
             self.output.push(')');
         }
-
+        if int_base {
+            self.output.push_str(" as f64)");
+        }
+        write!(self.output, ".{}(", method).unwrap();
+        self.generate_expr(exponent)?;
+        self.output.push(')');
         Ok(())
     }
 
-    fn expr_needs_parens_for_binop(&self, expr: &Expr, parent_op: &BinOp) -> bool {
+    /// Whether `expr` is known to be an integer or a float without help from
+    /// rustc: literals, float variables, casts and arithmetic on those
+    fn numeric_kind(&self, expr: &Expr) -> Option<Numeric> {
         match expr {
-            Expr::Literal(_) | Expr::Identifier(_) => false,
-            // Member access, calls, method calls, and indexing bind tighter than any binop
-            Expr::Member { .. } | Expr::Call(_) | Expr::MethodCall(_) | Expr::Index { .. } => false,
-            Expr::Binary { op, .. } => {
-                // Parentheses needed if this expression has lower precedence than parent
-                self.binop_precedence(op) < self.binop_precedence(parent_op)
+            Expr::Literal(Literal::Int(_)) => Some(Numeric::Int),
+            Expr::Literal(Literal::Float(_)) => Some(Numeric::Float),
+            Expr::Identifier(name) if self.float_vars.contains(&self.sanitize_name(name)) => {
+                Some(Numeric::Float)
             }
-            _ => true, // Default to needing parentheses for complex expressions
+            Expr::Cast {
+                target: TypeRef::Simple(target),
+                ..
+            } => match target.as_str() {
+                "float" => Some(Numeric::Float),
+                "int" => Some(Numeric::Int),
+                _ => None,
+            },
+            Expr::Unary {
+                op: UnOp::Neg,
+                operand,
+            } => self.numeric_kind(operand),
+            Expr::Unary {
+                op: UnOp::BitNot, ..
+            }
+            | Expr::Binary {
+                op: BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr,
+                ..
+            } => Some(Numeric::Int),
+            Expr::Binary {
+                op: BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::Pow,
+                left,
+                right,
+            } => match (self.numeric_kind(left), self.numeric_kind(right)) {
+                (Some(Numeric::Float), _) | (_, Some(Numeric::Float)) => Some(Numeric::Float),
+                (Some(Numeric::Int), Some(Numeric::Int)) => Some(Numeric::Int),
+                _ => None,
+            },
+            _ => None,
         }
     }
 
-    fn binop_precedence(&self, op: &BinOp) -> i32 {
-        match op {
-            BinOp::Mul | BinOp::Div | BinOp::Mod => 100,
-            BinOp::Add | BinOp::Sub => 90,
-            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 80,
-            BinOp::Eq | BinOp::Ne => 70,
-            BinOp::And => 60,
-            BinOp::Or => 50,
-            BinOp::Coalesce => 45,
-            BinOp::Range | BinOp::RangeInclusive => 40,
-        }
+    /// `expr` generated the string way, as an operand of a `rust_ast` node
+    fn expr_node(&mut self, expr: &Expr) -> Result<rust_ast::Expr> {
+        self.generated_node(generated_prec(expr), |this| this.generate_expr(expr))
+    }
+
+    /// What `generate` appends to the output, as a node binding as tightly
+    /// as `prec`
+    fn generated_node(
+        &mut self,
+        prec: rust_ast::Prec,
+        generate: impl FnOnce(&mut Self) -> Result<()>,
+    ) -> Result<rust_ast::Expr> {
+        let saved = std::mem::take(&mut self.output);
+        let generated = generate(self);
+        let code = std::mem::replace(&mut self.output, saved);
+        generated?;
+        Ok(rust_ast::Expr::Raw(code, prec))
+    }
+
+    /// `expr` as an owned value: string literals become `String`s, which is
+    /// what collections of Liva strings hold
+    fn owned_node(&mut self, expr: &Expr) -> Result<rust_ast::Expr> {
+        let node = self.expr_node(expr)?;
+        Ok(match expr {
+            Expr::Literal(Literal::String(_)) => node.method("to_string", vec![]),
+            _ => node,
+        })
+    }
+
+    fn owned_nodes(&mut self, exprs: &[Expr]) -> Result<Vec<rust_ast::Expr>> {
+        exprs.iter().map(|expr| self.owned_node(expr)).collect()
+    }
+
+    /// `Err(liva_rt::Error::from(message))`, the error `fail message` returns
+    fn fail_node(&mut self, message: &Expr) -> Result<rust_ast::Expr> {
+        let error = rust_ast::Expr::call("liva_rt::Error::from", vec![self.expr_node(message)?]);
+        Ok(rust_ast::Expr::call("Err", vec![error]))
+    }
+
+    fn emit_node(&mut self, node: &rust_ast::Expr) {
+        let code = node.print(self.indent_level);
+        self.output.push_str(&code);
+    }
+
+    /// `stmt` on a line of its own at the current indentation
+    fn emit_stmt(&mut self, stmt: &rust_ast::Stmt) {
+        self.write_indent();
+        let code = stmt.print(self.indent_level);
+        self.output.push_str(&code);
+        self.output.push('\n');
+    }
+
+    /// One branch of a ternary that can fail: `Ok(value)`, or the error of a
+    /// `fail` branch, returned early when it is the then-branch
+    fn ternary_branch(&mut self, branch: &Expr, then_branch: bool) -> Result<rust_ast::Expr> {
+        let Expr::Fail(message) = branch else {
+            return Ok(rust_ast::Expr::call("Ok", vec![self.expr_node(branch)?]));
+        };
+        let err = self.fail_node(message)?;
+        Ok(if then_branch {
+            rust_ast::Expr::ret(err)
+        } else {
+            err
+        })
     }
 
     /// FIX-4: Check if a variable is known to be a Copy type (doesn't need cloning)
@@ -16667,27 +19057,460 @@ impl CodeGenerator {
             .unwrap_or(false)
     }
 
-    fn is_map_get_call(&self, expr: &Expr) -> bool {
-        if let Expr::MethodCall(mc) = expr {
-            if mc.method == "get" {
-                if let Expr::Identifier(obj_name) = &*mc.object {
-                    let sanitized = self.sanitize_name(obj_name);
-                    return self.map_vars.contains(&sanitized);
-                }
-                // Bug #76 fix: Also check this._field for Map-typed class fields
-                // B125: Generalize to any `obj.field` where `field` is a known
-                // Map-typed name in `map_vars` (which tracks both vars and fields).
-                if let Expr::Member { property, .. } = &*mc.object {
-                    return self.map_vars.contains(&self.sanitize_name(property));
-                }
+    fn is_map_get_call(&self, expr: &Expr) -> bool {
+        if let Expr::MethodCall(mc) = expr {
+            if mc.method == "get" {
+                if let Expr::Identifier(obj_name) = &*mc.object {
+                    let sanitized = self.sanitize_name(obj_name);
+                    return self.map_vars.contains(&sanitized);
+                }
+                // Bug #76 fix: Also check this._field for Map-typed class fields
+                // B125: Generalize to any `obj.field` where `field` is a known
+                // Map-typed name in `map_vars` (which tracks both vars and fields).
+                if let Expr::Member { property, .. } = &*mc.object {
+                    return self.map_vars.contains(&self.sanitize_name(property));
+                }
+            }
+        }
+        false
+    }
+
+    /// Check if expression is a method call that returns Option<T> (find, first, last, min, max)
+    /// BUG-007: Detect `x != null` where x is an Option variable.
+    /// Returns the sanitized variable name if this is a null-check on an Option var.
+    /// `if` statements whose check narrows a variable, generated so Rust
+    /// sees the narrowed type too. Returns false when `if_stmt` isn't one.
+    ///
+    /// - `if typeOf(x) == "string" { .. }` → `if let Union_..::Str(x) = x.clone() { .. }`
+    /// - `if x == null { return }` → `let Some(x) = x else { return };`, which
+    ///   leaves `x` unwrapped for the rest of the block
+    /// - `if x == null { a } else { b }` → `if let Some(x) = x { b } else { a }`
+    fn generate_narrowing_if(&mut self, if_stmt: &IfStmt) -> Result<bool> {
+        if let Some((var_name, union_name, variant, member)) =
+            self.extract_union_type_check(&if_stmt.condition)
+        {
+            self.write_indent();
+            writeln!(
+                self.output,
+                "if let {}::{}({}) = {}.clone() {{",
+                union_name, variant, var_name, var_name
+            )
+            .unwrap();
+            self.indent();
+            let narrowed = self.narrow_union_var(&var_name, &member);
+            self.generate_if_body(&if_stmt.then_branch)?;
+            self.widen_union_var(&var_name, narrowed);
+            self.dedent();
+            self.write_indent();
+            self.output.push('}');
+            if let Some(else_branch) = else_of(if_stmt) {
+                self.generate_else_body(&else_branch)?;
+            }
+            self.output.push('\n');
+            return Ok(true);
+        }
+
+        let Some(var_name) = self.extract_option_is_null_check(&if_stmt.condition) else {
+            return Ok(false);
+        };
+        if !self.option_value_vars.contains(&var_name) {
+            return Ok(false);
+        }
+        match &else_of(if_stmt) {
+            None if if_stmt.then_branch.always_exits() => {
+                let binding = if self.mutated_vars.contains(&var_name) {
+                    format!("mut {}", var_name)
+                } else {
+                    var_name.clone()
+                };
+                self.write_indent();
+                writeln!(self.output, "let Some({}) = {} else {{", binding, var_name).unwrap();
+                self.indent();
+                self.generate_if_body(&if_stmt.then_branch)?;
+                self.dedent();
+                self.writeln("};");
+                self.option_value_vars.remove(&var_name);
+                self.guard_narrowed_vars.push(var_name);
+            }
+            Some(else_branch) => {
+                self.write_indent();
+                writeln!(self.output, "if let Some({}) = {} {{", var_name, var_name).unwrap();
+                self.indent();
+                self.option_value_vars.remove(&var_name);
+                self.generate_if_body(else_branch)?;
+                self.option_value_vars.insert(var_name);
+                self.dedent();
+                self.writeln("} else {");
+                self.indent();
+                self.generate_if_body(&if_stmt.then_branch)?;
+                self.dedent();
+                self.writeln("}");
+            }
+            None => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Member types of a union type, directly or through a type alias
+    fn union_type_members(&self, type_ref: &TypeRef) -> Option<Vec<TypeRef>> {
+        match type_ref {
+            TypeRef::Union(members) => Some(members.clone()),
+            TypeRef::Simple(alias) => match self.type_aliases.get(alias) {
+                Some((_, TypeRef::Union(members))) => Some(members.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The sanitized name and member types of a union-typed variable
+    fn union_var_of(&self, expr: &Expr) -> Option<(String, Vec<TypeRef>)> {
+        let Expr::Identifier(name) = expr else {
+            return None;
+        };
+        let sanitized = self.sanitize_name(name);
+        let members = self.union_vars.get(&sanitized)?.clone();
+        Some((sanitized, members))
+    }
+
+    /// Treats union variable `var_name` as its `member` while narrowed code
+    /// is generated; `widen_union_var` undoes it
+    fn narrow_union_var(
+        &mut self,
+        var_name: &str,
+        member: &TypeRef,
+    ) -> (Option<Vec<TypeRef>>, bool) {
+        let members = self.union_vars.remove(var_name);
+        let added_string = matches!(member, TypeRef::Simple(name) if name == "string")
+            && self.string_vars.insert(var_name.to_string());
+        (members, added_string)
+    }
+
+    fn widen_union_var(&mut self, var_name: &str, narrowed: (Option<Vec<TypeRef>>, bool)) {
+        let (members, added_string) = narrowed;
+        if added_string {
+            self.string_vars.remove(var_name);
+        }
+        if let Some(members) = members {
+            self.union_vars.insert(var_name.to_string(), members);
+        }
+    }
+
+    /// `switch typeOf(x)` on a union variable with type names for cases:
+    /// the variable, the union's enum and its members
+    fn type_of_switch_subject(
+        &mut self,
+        switch_expr: &SwitchExpr,
+    ) -> Option<(String, String, Vec<TypeRef>)> {
+        let Expr::Call(call) = switch_expr.discriminant.as_ref() else {
+            return None;
+        };
+        if !matches!(call.callee.as_ref(), Expr::Identifier(callee) if callee == "typeOf") {
+            return None;
+        }
+        let (var_name, members) = self.union_var_of(call.args.first()?)?;
+        let is_type_name =
+            |p: &Pattern| matches!(p, Pattern::Literal(Literal::String(_)) | Pattern::Wildcard);
+        let by_type_name = switch_expr.arms.iter().all(|arm| match &arm.pattern {
+            Pattern::Or(patterns) => patterns.iter().all(is_type_name),
+            pattern => is_type_name(pattern),
+        });
+        if !by_type_name {
+            return None;
+        }
+        let union_name = self.expand_type_alias(&TypeRef::Union(members.clone()));
+        Some((var_name, union_name, members))
+    }
+
+    /// Case of a `switch typeOf(x)`: `"int"` matches the union's `Int`
+    /// variant and rebinds `x` to its value. Returns the member `x` holds
+    /// in the arm, if it's a single one.
+    fn generate_type_switch_pattern(
+        &mut self,
+        pattern: &Pattern,
+        subject: &(String, String, Vec<TypeRef>),
+    ) -> Option<TypeRef> {
+        let (var_name, union_name, members) = subject;
+        let tags: Vec<&str> = match pattern {
+            Pattern::Literal(Literal::String(tag)) => vec![tag.as_str()],
+            Pattern::Or(patterns) => patterns
+                .iter()
+                .filter_map(|p| match p {
+                    Pattern::Literal(Literal::String(tag)) => Some(tag.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let matched: Vec<TypeRef> = members
+            .iter()
+            .filter(|member| tags.contains(&member.type_of_name().as_str()))
+            .cloned()
+            .collect();
+        if matched.is_empty() {
+            self.output.push('_');
+            return None;
+        }
+        let binding = if matched.len() == 1 {
+            var_name.as_str()
+        } else {
+            "_"
+        };
+        let variants: Vec<String> = matched
+            .iter()
+            .map(|member| {
+                let rust_type = self.expand_type_alias(member);
+                let variant = self.type_to_variant_name(&rust_type);
+                format!("{}::{}({})", union_name, variant, binding)
+            })
+            .collect();
+        self.output.push_str(&variants.join(" | "));
+        (matched.len() == 1).then(|| matched[0].clone())
+    }
+
+    /// Whether a `switch typeOf(x)` case matches the type name `tag`
+    fn pattern_names_type(pattern: &Pattern, tag: &str) -> bool {
+        match pattern {
+            Pattern::Literal(Literal::String(name)) => name == tag,
+            Pattern::Or(patterns) => patterns.iter().any(|p| Self::pattern_names_type(p, tag)),
+            _ => false,
+        }
+    }
+
+    /// Class of a class instance expression: `this` or a variable holding one
+    fn class_of_expr(&self, expr: &Expr) -> Option<String> {
+        let class_name = match expr {
+            Expr::Identifier(name) if name == "this" => self.current_class_name.clone()?,
+            Expr::Identifier(name) => self.var_types.get(&self.sanitize_name(name))?.clone(),
+            _ => return None,
+        };
+        self.class_fields
+            .contains_key(&class_name)
+            .then_some(class_name)
+    }
+
+    /// Member types of an expression that already is a union value: a
+    /// union-typed variable or a call of a function returning a union
+    fn union_members_of_expr(&self, expr: &Expr) -> Option<Vec<TypeRef>> {
+        match expr {
+            Expr::Identifier(_) => self.union_var_of(expr).map(|(_, members)| members),
+            Expr::Call(call) => match call.callee.as_ref() {
+                Expr::Identifier(name) => {
+                    self.union_type_members(self.function_return_types.get(name)?)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// `Union_..::Variant` to wrap `expr` in where a union of `members` is
+    /// expected, or `None` when it already is a union value or its type
+    /// isn't one of the members
+    fn union_variant_for(&mut self, members: &[TypeRef], expr: &Expr) -> Option<String> {
+        if self.union_members_of_expr(expr).is_some() {
+            return None;
+        }
+        let expr_type = match self.infer_expr_type(expr, None) {
+            Some(inferred) => inferred.trim_start_matches(" -> ").to_string(),
+            None => self.tracked_var_type(expr)?,
+        };
+        let expr_type = expr_type.as_str();
+        let member_types: Vec<String> = members.iter().map(|m| self.expand_type_alias(m)).collect();
+        if !member_types.iter().any(|member| member == expr_type) {
+            return None;
+        }
+        let union_name = self.expand_type_alias(&TypeRef::Union(members.to_vec()));
+        Some(format!(
+            "{}::{}",
+            union_name,
+            self.type_to_variant_name(expr_type)
+        ))
+    }
+
+    /// Rust type of a variable from the string/float/class tracking sets
+    fn tracked_var_type(&self, expr: &Expr) -> Option<String> {
+        let Expr::Identifier(name) = expr else {
+            return None;
+        };
+        let name = self.sanitize_name(name);
+        if self.string_vars.contains(&name) {
+            Some("String".to_string())
+        } else if self.float_vars.contains(&name) {
+            Some("f64".to_string())
+        } else {
+            self.var_types.get(&name).cloned()
+        }
+    }
+
+    /// Generates `expr` where a union of `members` is expected, wrapped in
+    /// the variant of its member type
+    fn generate_union_value(&mut self, members: &[TypeRef], expr: &Expr) -> Result<()> {
+        let Some(variant) = self.union_variant_for(members, expr) else {
+            return self.generate_expr(expr);
+        };
+        write!(self.output, "{}(", variant).unwrap();
+        self.generate_expr(expr)?;
+        if matches!(expr, Expr::Literal(Literal::String(_))) {
+            self.output.push_str(".to_string()");
+        }
+        self.output.push(')');
+        Ok(())
+    }
+
+    /// A string literal passed as argument `index` of `callee`, a function or
+    /// a class constructor
+    fn generate_string_arg(&mut self, callee: &Expr, index: usize, arg: &Expr) -> Result<()> {
+        let param_type = match callee {
+            Expr::Identifier(name) => match self.function_param_types.get(name) {
+                Some(types) => types.get(index).cloned().flatten(),
+                None => self
+                    .ctx
+                    .symbols
+                    .class(name)
+                    .and_then(|class| class.constructor_params().get(index).cloned())
+                    .flatten(),
+            },
+            _ => None,
+        };
+        self.generate_string_literal(param_type, "parameter", arg)
+    }
+
+    /// A string literal going where `declared` is expected, `target` saying
+    /// what that is: a `String` when it takes a string, the bare `&str` when
+    /// it is declared with another type. Without a declared type it keeps
+    /// the `.to_string()`; semantic analysis warns about those (E0024).
+    fn generate_string_literal(
+        &mut self,
+        declared: Option<TypeRef>,
+        target: &str,
+        literal: &Expr,
+    ) -> Result<()> {
+        let start = self.output.len();
+        self.generate_expr(literal)?;
+        match declared {
+            Some(ty) if self.type_takes_string(&ty) => self.output.push_str(".to_string()"),
+            Some(ty) => self.explain(
+                start,
+                format_args!(
+                    "the {} is declared `{}`, so the literal stays a &str",
+                    target, ty
+                ),
+            ),
+            None => {
+                self.output.push_str(".to_string()");
+                self.explain(
+                    start,
+                    format_args!(
+                        "the {} has no declared type, so the literal is passed as a String",
+                        target
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Declared parameter types of `method` on `object`, when `object` is an
+    /// instance of a known class that has it
+    fn class_method_params(&self, object: &Expr, method: &str) -> Option<Vec<Option<TypeRef>>> {
+        let class = self.class_of_expr(object)?;
+        let method = self.ctx.symbols.class(&class)?.method(method)?;
+        Some(method.params.clone())
+    }
+
+    /// Declared type of the field an assignment writes, when `target` is a
+    /// field of a known class; the inner `None` is a field without a type
+    fn assigned_field_type(&self, target: &Expr) -> Option<Option<TypeRef>> {
+        let Expr::Member { object, property } = target else {
+            return None;
+        };
+        let class = self.ctx.symbols.class(&self.class_of_expr(object)?)?;
+        class.field(property)?;
+        Some(class.field_type(property))
+    }
+
+    /// Whether a parameter of type `ty` takes a `String`: `string` itself, an
+    /// alias of it, or an optional or union with a string in it
+    fn type_takes_string(&self, ty: &TypeRef) -> bool {
+        match ty {
+            TypeRef::Simple(name) if name == "string" || name == "String" => true,
+            TypeRef::Simple(alias) => match self.type_aliases.get(alias) {
+                Some((_, aliased)) => self.type_takes_string(aliased),
+                None => false,
+            },
+            TypeRef::Optional(inner) => self.type_takes_string(inner),
+            TypeRef::Union(members) => members.iter().any(|m| self.type_takes_string(m)),
+            _ => false,
+        }
+    }
+
+    /// Records a function's declared parameter and return types before any
+    /// body is generated, so calls ahead of the definition see them too
+    fn register_function_types(&mut self, func: &FunctionDecl) {
+        let param_types = declared_param_types(func);
+        if param_types.iter().any(|t| t.is_some()) {
+            self.function_param_types
+                .insert(func.name.clone(), param_types);
+        }
+        if let Some(ret) = &func.return_type {
+            self.function_return_types
+                .insert(func.name.clone(), ret.clone());
+        }
+    }
+
+    /// `typeOf(x) == "name"` on a union variable: the variable, the union's
+    /// enum and the variant holding `name`, with its member type
+    fn extract_union_type_check(
+        &mut self,
+        condition: &Expr,
+    ) -> Option<(String, String, String, TypeRef)> {
+        let Expr::Binary {
+            op: BinOp::Eq,
+            left,
+            right,
+        } = condition
+        else {
+            return None;
+        };
+        let (call, type_name) = match (left.as_ref(), right.as_ref()) {
+            (Expr::Call(call), Expr::Literal(Literal::String(name)))
+            | (Expr::Literal(Literal::String(name)), Expr::Call(call)) => (call, name),
+            _ => return None,
+        };
+        if !matches!(call.callee.as_ref(), Expr::Identifier(callee) if callee == "typeOf") {
+            return None;
+        }
+        let (var_name, members) = self.union_var_of(call.args.first()?)?;
+        let member = members
+            .iter()
+            .find(|member| member.type_of_name() == *type_name)?
+            .clone();
+        let union_name = self.expand_type_alias(&TypeRef::Union(members));
+        let rust_type = self.expand_type_alias(&member);
+        let variant = self.type_to_variant_name(&rust_type);
+        Some((var_name, union_name, variant, member))
+    }
+
+    /// `x == null` (or `null == x`) on a variable
+    fn extract_option_is_null_check(&self, condition: &Expr) -> Option<String> {
+        let Expr::Binary {
+            op: BinOp::Eq,
+            left,
+            right,
+        } = condition
+        else {
+            return None;
+        };
+        match (left.as_ref(), right.as_ref()) {
+            (Expr::Identifier(name), Expr::Literal(Literal::Null))
+            | (Expr::Literal(Literal::Null), Expr::Identifier(name)) => {
+                Some(self.sanitize_name(name))
             }
+            _ => None,
         }
-        false
     }
 
-    /// Check if expression is a method call that returns Option<T> (find, first, last, min, max)
-    /// BUG-007: Detect `x != null` where x is an Option variable.
-    /// Returns the sanitized variable name if this is a null-check on an Option var.
     fn extract_option_null_check(&self, condition: &Expr) -> Option<String> {
         if let Expr::Binary {
             op: BinOp::Ne,
@@ -16718,7 +19541,7 @@ impl CodeGenerator {
         if let Expr::MethodCall(mc) = expr {
             matches!(
                 mc.method.as_str(),
-                "find" | "first" | "last" | "min" | "max"
+                "find" | "first" | "last" | "min" | "max" | "minBy" | "maxBy"
             )
         } else {
             false
@@ -16761,7 +19584,7 @@ impl CodeGenerator {
             Expr::MethodCall(mc) => {
                 matches!(
                     mc.method.as_str(),
-                    "find" | "first" | "last" | "min" | "max"
+                    "find" | "first" | "last" | "min" | "max" | "minBy" | "maxBy"
                 )
             }
             // Optional chaining already produces Option<T>
@@ -16782,6 +19605,8 @@ impl CodeGenerator {
             }
             // B19 fix: Method calls can also be fallible
             Expr::MethodCall(mc) => self.fallible_methods.contains(&mc.method),
+            // A `lazy` value holds its initializer's Result
+            Expr::Identifier(name) => self.lazy_values.get(name) == Some(&true),
             Expr::Ternary {
                 condition: _,
                 then_expr,
@@ -16790,6 +19615,7 @@ impl CodeGenerator {
                 // A ternary is fallible if either branch contains a fail or calls a fallible function
                 self.expr_contains_fail(then_expr) || self.expr_contains_fail(else_expr)
             }
+            Expr::If(_) => self.expr_contains_fail(expr),
             _ => false,
         }
     }
@@ -16865,10 +19691,163 @@ impl CodeGenerator {
                 else_expr,
                 ..
             } => self.expr_contains_fail(then_expr) || self.expr_contains_fail(else_expr),
+            Expr::If(if_expr) => [&if_expr.then_branch, &if_expr.else_branch]
+                .into_iter()
+                .any(|branch| matches!(branch, SwitchBody::Expr(e) if self.expr_contains_fail(e))),
+            _ => false,
+        }
+    }
+
+    /// Whether a template placeholder's value is an array, map, set, tuple or
+    /// object literal, which format with `{:?}` instead of `{}`
+    fn template_expr_is_aggregate(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::ArrayLiteral(_) | Expr::ObjectLiteral(_) | Expr::Tuple(_) => true,
+            Expr::Identifier(name) => {
+                let sanitized = self.sanitize_name(name);
+                self.array_vars.contains(&sanitized)
+                    || self.typed_array_vars.contains_key(&sanitized)
+                    || self.map_vars.contains(&sanitized)
+                    || self.set_vars.contains(&sanitized)
+                    || self.map_array_vars.contains(&sanitized)
+                    || self.native_vec_string_vars.contains(&sanitized)
+            }
+            // `user.tags` / `this.tags`: array and Map fields of a known class
+            Expr::Member { object, property } => {
+                let class_name = match object.as_ref() {
+                    Expr::Identifier(obj) if obj == "this" => self.current_class_name.clone(),
+                    Expr::Identifier(obj) => self.var_types.get(&self.sanitize_name(obj)).cloned(),
+                    _ => None,
+                };
+                class_name.is_some_and(|class| {
+                    self.class_array_field_types
+                        .get(&class)
+                        .is_some_and(|fields| fields.contains_key(property))
+                        || self
+                            .class_map_value_types
+                            .get(&class)
+                            .is_some_and(|fields| fields.contains_key(property))
+                })
+            }
+            // Rows of `DB.query` / `CSV.readTable` are maps
+            Expr::Index { object, .. } => match object.as_ref() {
+                Expr::Identifier(name) => self.map_array_vars.contains(&self.sanitize_name(name)),
+                _ => false,
+            },
+            Expr::Call(call) => match (call.callee.as_ref(), call.args.as_slice()) {
+                (Expr::Identifier(name), [value]) if name == "clone" || name == "freeze" => {
+                    self.template_expr_is_aggregate(value)
+                }
+                (Expr::Identifier(name), _) => {
+                    name == "zip" || self.array_returning_functions.contains_key(name)
+                }
+                _ => false,
+            },
+            Expr::MethodCall(mc) => {
+                self.array_returning_methods.contains_key(&mc.method)
+                    || matches!(
+                        mc.method.as_str(),
+                        "split" | "enumerate" | "windows" | "chunks" | "zip"
+                    )
+                    || (matches!(
+                        mc.method.as_str(),
+                        "map"
+                            | "filter"
+                            | "slice"
+                            | "concat"
+                            | "reverse"
+                            | "sort"
+                            | "keys"
+                            | "values"
+                    ) && self.template_expr_is_aggregate(&mc.object))
+            }
+            Expr::Ternary {
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                self.template_expr_is_aggregate(then_expr)
+                    || self.template_expr_is_aggregate(else_expr)
+            }
+            Expr::If(if_expr) => [&if_expr.then_branch, &if_expr.else_branch]
+                .into_iter()
+                .any(|branch| match branch {
+                    SwitchBody::Expr(e) => self.template_expr_is_aggregate(e),
+                    SwitchBody::Block(_) => false,
+                }),
             _ => false,
         }
     }
 
+    /// Tracked element type of the array variable `array` is rooted at
+    fn array_element_type(&self, array: &Expr) -> Option<String> {
+        self.get_base_var_name(array).and_then(|name| {
+            self.typed_array_vars
+                .get(&self.sanitize_name(&name))
+                .or_else(|| self.typed_array_vars.get(&name))
+                .cloned()
+        })
+    }
+
+    /// Registers `var_name`, bound to the element a `minBy`/`maxBy` call picks,
+    /// with the element type of the array it scans
+    fn track_min_max_by_result(&mut self, var_name: &str, init: &Expr) {
+        if let Expr::MethodCall(mc) = init {
+            if matches!(mc.method.as_str(), "minBy" | "maxBy") {
+                let element_type = self.array_element_type(&mc.object);
+                self.track_loop_element(var_name, element_type.as_deref());
+            }
+        }
+    }
+
+    /// Element types of the pairs `items.enumerate()`, `a.zip(b)` and
+    /// `zip(a, b)` yield, each `None` when unknown; `None` for other iterables
+    fn pair_iterable_element_types(&self, expr: &Expr) -> Option<(Option<String>, Option<String>)> {
+        let element_type = |array: &Expr| self.array_element_type(array);
+        match expr {
+            Expr::MethodCall(mc) if mc.method == "enumerate" && mc.args.is_empty() => {
+                Some((Some("int".to_string()), element_type(&mc.object)))
+            }
+            Expr::MethodCall(mc) if mc.method == "zip" && mc.args.len() == 1 => {
+                Some((element_type(&mc.object), element_type(&mc.args[0])))
+            }
+            Expr::Call(call) if call.args.len() == 2 => match call.callee.as_ref() {
+                Expr::Identifier(name) if name == "zip" => {
+                    Some((element_type(&call.args[0]), element_type(&call.args[1])))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Registers a variable holding an array element of `element_type` (a loop
+    /// variable, or the element `minBy`/`maxBy` picks)
+    fn track_loop_element(&mut self, var_name: &str, element_type: Option<&str>) {
+        match element_type {
+            Some("string") => {
+                self.string_vars.insert(var_name.to_string());
+            }
+            Some("float") | Some("f64") => {
+                self.float_vars.insert(var_name.to_string());
+            }
+            Some(elem) if elem.starts_with('[') && elem.ends_with(']') => {
+                self.array_vars.insert(var_name.to_string());
+                let inner = elem.trim_start_matches('[').trim_end_matches(']');
+                if !inner.is_empty() {
+                    self.typed_array_vars
+                        .insert(var_name.to_string(), inner.to_string());
+                }
+            }
+            Some(elem) if elem.chars().next().is_some_and(|c| c.is_uppercase()) => {
+                self.class_instance_vars.insert(var_name.to_string());
+                self.var_types
+                    .insert(var_name.to_string(), elem.to_string());
+            }
+            _ => {}
+        }
+    }
+
     /// Check if a binary Add expression involves arrays (for array concatenation)
     fn expr_is_array(&self, left: &Expr, right: &Expr) -> bool {
         let is_array_expr = |expr: &Expr| -> bool {
@@ -16889,6 +19868,10 @@ impl CodeGenerator {
         match expr {
             Expr::Literal(Literal::String(_)) => true,
             Expr::StringTemplate { .. } => true,
+            Expr::Cast {
+                target: TypeRef::Simple(target),
+                ..
+            } => target == "string",
             Expr::Binary {
                 op: BinOp::Add,
                 left,
@@ -16912,6 +19895,7 @@ impl CodeGenerator {
                         | "replace"
                         | "substring"
                         | "join"
+                        | "charAt"
                 )
             }
             // Detect string-returning function calls like toString(x) or user-defined string functions
@@ -17354,32 +20338,11 @@ impl CodeGenerator {
     }
 
     fn sanitize_name(&self, name: &str) -> String {
-        // Convert to snake_case, preserving leading underscore for private fields
-        let has_leading_underscore = name.starts_with('_');
-        let name_without_prefix = name.trim_start_matches('_');
-        let snake = self.to_snake_case(name_without_prefix);
-
-        let result = if has_leading_underscore {
-            format!("_{}", snake)
-        } else {
-            snake
-        };
-
-        // B37: Escape Rust reserved keywords with r# prefix
-        escape_rust_keyword(&result)
+        rust_identifier_as(name, self.ctx.identifier_mapping)
     }
 
     fn sanitize_test_name(&self, name: &str) -> String {
-        name.chars()
-            .map(|c| {
-                if c.is_alphanumeric() || c == '_' {
-                    c
-                } else {
-                    '_'
-                }
-            })
-            .collect::<String>()
-            .to_lowercase()
+        sanitize_test_name(name)
     }
 
     fn to_snake_case(&self, s: &str) -> String {
@@ -17411,6 +20374,14 @@ impl CodeGenerator {
     }
 }
 
+/// `name` in the case `mapping` asks for, without escaping
+fn map_identifier_case(name: &str, mapping: IdentifierMapping) -> String {
+    match mapping {
+        IdentifierMapping::Snake => to_snake_case(name),
+        IdentifierMapping::Preserve => name.to_string(),
+    }
+}
+
 fn to_snake_case(s: &str) -> String {
     let mut result = String::new();
     let mut prev_lowercase = false;
@@ -17435,15 +20406,44 @@ fn to_snake_case(s: &str) -> String {
     }
 }
 
+/// The Rust name of a Liva identifier. Semantic analysis uses it to reject
+/// distinct names that would end up the same, such as `myValue` and `my_value`.
+pub(crate) fn rust_identifier(name: &str) -> String {
+    rust_identifier_as(name, IdentifierMapping::Snake)
+}
+
+/// [`rust_identifier`] under `--identifier-mapping`
+fn rust_identifier_as(name: &str, mapping: IdentifierMapping) -> String {
+    // Convert the case, preserving leading underscore for private fields
+    let has_leading_underscore = name.starts_with('_');
+    let name_without_prefix = name.trim_start_matches('_');
+    let mapped = if name_without_prefix.is_empty() {
+        String::new()
+    } else {
+        map_identifier_case(name_without_prefix, mapping)
+    };
+
+    let result = if has_leading_underscore {
+        format!("_{}", mapped)
+    } else {
+        mapped
+    };
+
+    // B37: Escape Rust reserved keywords with r# prefix
+    escape_rust_keyword(&escape_runtime_name(result))
+}
+
 /// B37: Escape Rust reserved keywords with r# prefix.
 /// This allows Liva identifiers like `type`, `match`, `mod` to compile as valid Rust.
+/// `crate` and `super` can't be raw identifiers, so they get a `_` suffix instead.
 fn escape_rust_keyword(name: &str) -> String {
     match name {
+        "crate" | "super" => format!("{}_", name),
         // Strict keywords (cannot be used as identifiers without r#)
-        "as" | "break" | "const" | "continue" | "crate" | "else" | "enum" | "extern"
+        "as" | "break" | "const" | "continue" | "else" | "enum" | "extern"
         | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop" | "match"
         | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "static" | "struct"
-        | "super" | "trait" | "true" | "type" | "unsafe" | "use" | "where" | "while"
+        | "trait" | "true" | "type" | "unsafe" | "use" | "where" | "while"
         | "async" | "await" | "dyn"
         // Reserved keywords (reserved for potential future use)
         | "abstract" | "become" | "box" | "do" | "final" | "macro" | "override"
@@ -17454,9 +20454,175 @@ fn escape_rust_keyword(name: &str) -> String {
     }
 }
 
+/// Names starting with `liva_` belong to the generated code (`liva_rt`,
+/// `liva_main`, `liva_log`, ...), so a user name gets a `_` suffix. Names
+/// that already end in `_` are left alone, which keeps this idempotent.
+fn escape_runtime_name(name: String) -> String {
+    if name.starts_with("liva_") && !name.ends_with('_') {
+        name + "_"
+    } else {
+        name
+    }
+}
+
 // ===== AST-level async detection (for test framework) =====
 
+/// The `else if` chain of `if_stmt` from link `from` on, as an `if` of its own
+fn else_if_chain(if_stmt: &IfStmt, from: usize) -> IfBody {
+    let link = &if_stmt.else_ifs[from];
+    IfBody::Stmt(Box::new(Stmt::If(IfStmt {
+        condition: link.condition.clone(),
+        then_branch: link.body.clone(),
+        else_ifs: if_stmt.else_ifs[from + 1..].to_vec(),
+        else_branch: if_stmt.else_branch.clone(),
+    })))
+}
+
+/// What runs when the first condition of `if_stmt` is false: its `else`, or
+/// the rest of its `else if` chain
+fn else_of(if_stmt: &IfStmt) -> Option<IfBody> {
+    if if_stmt.else_ifs.is_empty() {
+        if_stmt.else_branch.clone()
+    } else {
+        Some(else_if_chain(if_stmt, 0))
+    }
+}
+
+/// Whether every branch of an `if`/`else` ends in `return <value>`, following
+/// `else if` chains
+fn if_yields_value(if_stmt: &IfStmt) -> bool {
+    let body_yields = |body: &IfBody| {
+        let last = match body {
+            IfBody::Block(block) => block.stmts.last(),
+            IfBody::Stmt(stmt) => Some(stmt.as_ref()),
+        };
+        match last {
+            Some(Stmt::Return(ret)) => ret.expr.is_some(),
+            Some(Stmt::If(nested)) => if_yields_value(nested),
+            _ => false,
+        }
+    };
+    if_stmt.else_branch.is_some() && if_stmt.bodies().all(body_yields)
+}
+
+/// The Rust operator for a Liva one that is emitted as an operator
+fn rust_binop(op: &BinOp) -> rust_ast::BinOp {
+    match op {
+        BinOp::Add => rust_ast::BinOp::Add,
+        BinOp::Sub => rust_ast::BinOp::Sub,
+        BinOp::Mul => rust_ast::BinOp::Mul,
+        BinOp::Div => rust_ast::BinOp::Div,
+        BinOp::Mod => rust_ast::BinOp::Rem,
+        BinOp::BitAnd => rust_ast::BinOp::BitAnd,
+        BinOp::BitOr => rust_ast::BinOp::BitOr,
+        BinOp::BitXor => rust_ast::BinOp::BitXor,
+        BinOp::Shl => rust_ast::BinOp::Shl,
+        BinOp::Shr => rust_ast::BinOp::Shr,
+        BinOp::Lt => rust_ast::BinOp::Lt,
+        BinOp::Le => rust_ast::BinOp::Le,
+        BinOp::Gt => rust_ast::BinOp::Gt,
+        BinOp::Ge => rust_ast::BinOp::Ge,
+        BinOp::Eq => rust_ast::BinOp::Eq,
+        BinOp::Ne => rust_ast::BinOp::Ne,
+        BinOp::And => rust_ast::BinOp::And,
+        BinOp::Or => rust_ast::BinOp::Or,
+        BinOp::Range => rust_ast::BinOp::Range,
+        BinOp::RangeInclusive => rust_ast::BinOp::RangeInclusive,
+        // Lowered to method calls by `generate_binary_operation`
+        BinOp::Pow | BinOp::Coalesce => unreachable!(),
+    }
+}
+
+/// How tightly the Rust that `generate_expr` emits for `expr` binds. Only
+/// forms that always come out as a path, literal, macro or call are known
+/// not to need parentheses; anything unlisted gets them.
+fn generated_prec(expr: &Expr) -> rust_ast::Prec {
+    match expr {
+        Expr::Literal(_) | Expr::Identifier(_) | Expr::StringTemplate { .. } => {
+            rust_ast::Prec::Atom
+        }
+        Expr::Member { .. }
+        | Expr::Call(_)
+        | Expr::MethodCall(_)
+        | Expr::Index { .. }
+        | Expr::Unwrap(_)
+        | Expr::Try(_)
+        | Expr::Unary {
+            op: UnOp::Await, ..
+        } => rust_ast::Prec::Postfix,
+        Expr::Unary { .. } => rust_ast::Prec::Prefix,
+        // `a.pow(b)` and `(a).unwrap_or_else(|| b)`
+        Expr::Binary {
+            op: BinOp::Pow | BinOp::Coalesce,
+            ..
+        } => rust_ast::Prec::Postfix,
+        Expr::Binary { op, .. } => rust_binop(op).prec(),
+        _ => rust_ast::Prec::Lowest,
+    }
+}
+
+/// Whether any statement, at any depth short of a nested lambda, is a `return`
+fn stmts_have_return(stmts: &[Stmt]) -> bool {
+    let body_has_return = |body: &IfBody| match body {
+        IfBody::Block(block) => stmts_have_return(&block.stmts),
+        IfBody::Stmt(stmt) => stmts_have_return(std::slice::from_ref(stmt.as_ref())),
+    };
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::Return(_) => true,
+        Stmt::If(if_stmt) => if_stmt.bodies().any(body_has_return),
+        Stmt::While(while_stmt) => stmts_have_return(&while_stmt.body.stmts),
+        Stmt::For(for_stmt) => stmts_have_return(&for_stmt.body.stmts),
+        Stmt::Block(block) => stmts_have_return(&block.stmts),
+        Stmt::Scope(scope) => stmts_have_return(&scope.body.stmts),
+        Stmt::Switch(switch_stmt) => {
+            switch_stmt
+                .cases
+                .iter()
+                .any(|case| stmts_have_return(&case.body))
+                || switch_stmt
+                    .default
+                    .as_deref()
+                    .is_some_and(stmts_have_return)
+        }
+        Stmt::TryCatch(try_catch) => {
+            stmts_have_return(&try_catch.try_block.stmts)
+                || stmts_have_return(&try_catch.catch_block.stmts)
+        }
+        _ => false,
+    })
+}
+
 /// Check if an AST LambdaBody contains any async calls or await expressions
+/// `let n, err = text as int` → `let n, err = parseInt(text)`, so parsing a
+/// string shares the `parseInt`/`parseFloat` code paths, `or` forms included
+fn lower_parse_cast(stmt: &Stmt) -> Option<Stmt> {
+    let Stmt::VarDecl(var) = stmt else {
+        return None;
+    };
+    let Expr::Cast {
+        expr,
+        target: TypeRef::Simple(target),
+    } = &var.init
+    else {
+        return None;
+    };
+    let parser = match target.as_str() {
+        "int" => "parseInt",
+        "float" => "parseFloat",
+        _ => return None,
+    };
+    if !var.is_fallible {
+        return None;
+    }
+    Some(Stmt::VarDecl(VarDecl {
+        init: Expr::Call(CallExpr::new(
+            Expr::Identifier(parser.to_string()),
+            vec![(**expr).clone()],
+        )),
+        ..var.clone()
+    }))
+}
+
 fn ast_lambda_body_has_async(body: &LambdaBody) -> bool {
     match body {
         LambdaBody::Block(block) => block.stmts.iter().any(ast_stmt_has_async),
@@ -17477,13 +20643,10 @@ fn ast_stmt_has_async(stmt: &Stmt) -> bool {
         Stmt::Fail(f) => ast_expr_has_async(&f.expr),
         Stmt::Expr(e) => ast_expr_has_async(&e.expr),
         Stmt::If(if_stmt) => {
-            ast_expr_has_async(&if_stmt.condition)
-                || ast_if_body_has_async(&if_stmt.then_branch)
-                || if_stmt
-                    .else_branch
-                    .as_ref()
-                    .map(ast_if_body_has_async)
-                    .unwrap_or(false)
+            if_stmt
+                .branches()
+                .any(|(condition, _)| ast_expr_has_async(condition))
+                || if_stmt.bodies().any(ast_if_body_has_async)
         }
         Stmt::While(w) => {
             ast_expr_has_async(&w.condition) || w.body.stmts.iter().any(ast_stmt_has_async)
@@ -17493,6 +20656,9 @@ fn ast_stmt_has_async(stmt: &Stmt) -> bool {
         }
         Stmt::Block(block) => block.stmts.iter().any(ast_stmt_has_async),
         Stmt::Defer(defer_stmt) => ast_stmt_has_async(&defer_stmt.body),
+        Stmt::Scope(_) => true,
+        Stmt::Mock(mock) => ast_expr_has_async(&mock.value),
+        Stmt::Function(_) => false,
         Stmt::TryCatch(tc) => {
             tc.try_block.stmts.iter().any(ast_stmt_has_async)
                 || tc.catch_block.stmts.iter().any(ast_stmt_has_async)
@@ -17574,12 +20740,21 @@ fn ast_expr_has_async(expr: &Expr) -> bool {
                         }
                 })
         }
+        Expr::If(if_expr) => {
+            ast_expr_has_async(&if_expr.condition)
+                || [&if_expr.then_branch, &if_expr.else_branch]
+                    .into_iter()
+                    .any(|branch| match branch {
+                        SwitchBody::Expr(e) => ast_expr_has_async(e),
+                        SwitchBody::Block(stmts) => stmts.iter().any(ast_stmt_has_async),
+                    })
+        }
         Expr::MapLiteral(entries) => entries
             .iter()
             .any(|(k, v)| ast_expr_has_async(k) || ast_expr_has_async(v)),
         Expr::SetLiteral(elements) => elements.iter().any(ast_expr_has_async),
         Expr::Literal(_) | Expr::Identifier(_) | Expr::MethodRef { .. } => false,
-        Expr::Unwrap(inner) => ast_expr_has_async(inner),
+        Expr::Unwrap(inner) | Expr::Cast { expr: inner, .. } => ast_expr_has_async(inner),
         Expr::Try(inner) => ast_expr_has_async(inner),
         Expr::OptionalChain { object, .. } => ast_expr_has_async(object),
         // B24 fix: check rust { } blocks for .await
@@ -17592,6 +20767,7 @@ pub fn generate_multifile_project(
     modules: &[&crate::module::Module],
     entry_module: &crate::module::Module,
     ctx: DesugarContext,
+    diagnostics: &mut DiagnosticSink,
 ) -> Result<std::collections::HashMap<std::path::PathBuf, String>> {
     use std::collections::HashMap;
     use std::path::PathBuf;
@@ -17601,11 +20777,7 @@ pub fn generate_multifile_project(
 
     // Generate code for each module
     for module in modules {
-        let module_name = module
-            .path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("module");
+        let module_name = module.rust_module_name();
 
         // Skip main/entry module - it will be handled separately
         if module.path == entry_module.path {
@@ -17613,7 +20785,7 @@ pub fn generate_multifile_project(
         }
 
         // Generate Rust code for this module
-        let rust_code = generate_module_code(module, &ctx, modules)?;
+        let rust_code = generate_module_code(module, &ctx, modules, diagnostics)?;
 
         // Determine output path: src/module_name.rs
         let output_path = PathBuf::from("src").join(format!("{}.rs", module_name));
@@ -17624,7 +20796,8 @@ pub fn generate_multifile_project(
     }
 
     // Generate main.rs (entry point)
-    let main_code = generate_entry_point(entry_module, &mod_declarations, &ctx, modules)?;
+    let main_code =
+        generate_entry_point(entry_module, &mod_declarations, &ctx, modules, diagnostics)?;
     files.insert(PathBuf::from("src/main.rs"), main_code);
 
     Ok(files)
@@ -17635,8 +20808,13 @@ fn generate_module_code(
     module: &crate::module::Module,
     ctx: &DesugarContext,
     all_modules: &[&crate::module::Module],
+    diagnostics: &mut DiagnosticSink,
 ) -> Result<String> {
     let mut codegen = CodeGenerator::new(ctx.clone());
+    codegen.line_hook_file = module.path.display().to_string();
+    codegen.module_path = module.path.clone();
+    let file_hooks = codegen.scan_file_for_hooks(&module.ast.items);
+    codegen.test_hooks_stack.extend(file_hooks);
 
     // B06 fix: Pre-populate enum metadata so enum variants are recognized as
     // expressions (e.g., Priority.Alta) instead of falling through to get_field()
@@ -17701,6 +20879,7 @@ fn generate_module_code(
             }
             // B98 fix: Also pre-populate fallible functions/methods from imported modules
             if let TopLevel::Function(func) = item {
+                codegen.register_function_types(func);
                 if func.contains_fail {
                     codegen.fallible_functions.insert(func.name.clone());
                 }
@@ -17727,73 +20906,48 @@ fn generate_module_code(
                 }
             }
             if let TopLevel::Class(class) = item {
-                // BUG-003 fix: Pre-populate class_fields from all modules so
+                // BUG-003 fix: Register classes from all modules so
                 // register_pattern_bindings recognizes imported class types
-                let mut fields = std::collections::HashSet::new();
-                let mut optional_fields = std::collections::HashSet::new();
-                let mut array_field_types = std::collections::HashMap::new();
-                for m2 in &class.members {
-                    if let crate::ast::Member::Field(f) = m2 {
-                        fields.insert(f.name.clone());
-                        if f.is_optional || matches!(&f.type_ref, Some(TypeRef::Optional(_))) {
-                            optional_fields.insert(f.name.clone());
-                        }
-                        // BUG-003 fix: Track array field element types for for-loop var typing
-                        if let Some(TypeRef::Array(element_type)) = &f.type_ref {
-                            if let TypeRef::Simple(type_name) = element_type.as_ref() {
-                                array_field_types.insert(f.name.clone(), type_name.clone());
+                if let Some(class) = codegen.register_class_symbols(&class.name) {
+                    for field in &class.fields {
+                        // SH: Map/Set-typed fields, for deep member access
+                        match &field.type_ref {
+                            Some(TypeRef::Map(_, _)) => {
+                                codegen.map_vars.insert(codegen.sanitize_name(&field.name));
                             }
-                        }
-                        // SH: Track Map-typed fields from imported classes for deep member access
-                        if matches!(&f.type_ref, Some(TypeRef::Map(_, _))) {
-                            codegen.map_vars.insert(codegen.sanitize_name(&f.name));
-                        }
-                        // SH: Track Set-typed fields from imported classes
-                        if matches!(&f.type_ref, Some(TypeRef::Set(_))) {
-                            codegen.set_vars.insert(codegen.sanitize_name(&f.name));
+                            Some(TypeRef::Set(_)) => {
+                                codegen.set_vars.insert(codegen.sanitize_name(&field.name));
+                            }
+                            _ => {}
                         }
                     }
-                }
-                codegen.class_fields.insert(class.name.clone(), fields);
-                codegen
-                    .class_optional_fields
-                    .insert(class.name.clone(), optional_fields);
-                if !array_field_types.is_empty() {
-                    codegen
-                        .class_array_field_types
-                        .insert(class.name.clone(), array_field_types);
-                }
-
-                for member in &class.members {
-                    if let crate::ast::Member::Method(method) = member {
-                        if method.contains_fail {
+                    for method in &class.methods {
+                        if method.fallible {
                             codegen.fallible_methods.insert(method.name.clone());
                         }
-                        // B100 fix: Pre-populate string/array returning methods from imported classes
-                        if let Some(ret_type) = &method.return_type {
-                            if matches!(ret_type, TypeRef::Simple(name) if name == "string") {
-                                codegen.string_returning_methods.insert(method.name.clone());
-                            }
-                            if let TypeRef::Array(elem) = ret_type {
-                                let elem_type = match elem.as_ref() {
-                                    TypeRef::Simple(name) => name.clone(),
-                                    _ => String::new(),
-                                };
-                                codegen
-                                    .array_returning_methods
-                                    .insert(method.name.clone(), elem_type);
-                            }
-                        }
                     }
                 }
             }
         }
     }
 
+    for m in std::iter::once(module).chain(all_modules.iter().copied()) {
+        codegen.register_lazy_values(&m.ast.items);
+    }
+    for m in all_modules {
+        let path = if m.path == module.path {
+            String::new()
+        } else {
+            format!("crate::{}::", m.rust_module_name())
+        };
+        codegen.register_abstract_classes(&m.ast.items, &path);
+    }
+    register_import_renames(&mut codegen, &module.imports);
+
     // First, collect use statements from imports
     let mut use_statements = String::new();
     for import_decl in &module.imports {
-        let use_stmt = generate_use_statement(import_decl, &module.path)?;
+        let use_stmt = generate_use_statement(import_decl, &module.path, ctx.identifier_mapping)?;
         use_statements.push_str(&use_stmt);
         use_statements.push('\n');
     }
@@ -17812,13 +20966,15 @@ fn generate_module_code(
 
                 // Reset codegen output for this item
                 codegen.output.clear();
-                codegen.generate_function(func)?;
+                codegen.generate_function(func, false)?;
                 let func_code = codegen.output.clone();
+                let (doc, func_code) = split_doc_comment(&func_code);
 
+                module_body.push_str(doc);
                 if is_public {
                     module_body.push_str("pub ");
                 }
-                module_body.push_str(&func_code);
+                module_body.push_str(func_code);
                 module_body.push('\n');
             }
             TopLevel::Class(class) => {
@@ -17865,11 +21021,17 @@ fn generate_module_code(
                 codegen.output.clear();
                 codegen.generate_top_level(item)?;
                 let code = codegen.output.clone();
+                let (doc, code) = split_doc_comment(&code);
 
-                if is_public {
+                module_body.push_str(doc);
+                if is_public && const_decl.is_lazy {
+                    module_body.push_str(&code.replacen("fn ", "pub fn ", 1));
+                } else if is_public {
                     module_body.push_str("pub ");
+                    module_body.push_str(code);
+                } else {
+                    module_body.push_str(code);
                 }
-                module_body.push_str(&code);
                 module_body.push('\n');
             }
             TopLevel::Enum(enum_decl) => {
@@ -17878,17 +21040,22 @@ fn generate_module_code(
                 codegen.output.clear();
                 codegen.generate_top_level(item)?;
                 let code = codegen.output.clone();
+                let (doc, code) = split_doc_comment(&code);
 
+                module_body.push_str(doc);
                 if is_public {
                     // Add pub to enum definition and its Display impl
                     let code = code.replacen("enum ", "pub enum ", 1);
                     module_body.push_str(&code);
                 } else {
-                    module_body.push_str(&code);
+                    module_body.push_str(code);
                 }
                 module_body.push('\n');
             }
-            TopLevel::UseRust(_) | TopLevel::Test(_) | TopLevel::ExprStmt(_) => {
+            TopLevel::UseRust(_)
+            | TopLevel::Test(_)
+            | TopLevel::TestHook(_)
+            | TopLevel::ExprStmt(_) => {
                 // Reset codegen output for this item
                 codegen.output.clear();
                 codegen.generate_top_level(item)?;
@@ -17903,11 +21070,24 @@ fn generate_module_code(
                 // If one survives to here it's a compiler bug; ignore safely.
                 continue;
             }
+            TopLevel::Runtime(_) => {
+                // Only the entry module's runtime block configures `main`.
+                continue;
+            }
+            TopLevel::Init(_) => {
+                codegen.output.clear();
+                codegen.generate_top_level(item)?;
+                module_body.push_str("pub ");
+                module_body.push_str(&codegen.output);
+                module_body.push('\n');
+            }
         }
     }
 
     // Now build the final output, only adding liva_rt import if needed
-    let mut output = String::new();
+    codegen.output.clear();
+    codegen.write_module_doc(&module.ast);
+    let mut output = std::mem::take(&mut codegen.output);
 
     // Only add liva_rt import if the module actually uses it
     if module_body.contains("liva_rt::") || use_statements.contains("liva_rt::") {
@@ -17917,7 +21097,7 @@ fn generate_module_code(
     // Add use statements (with allow(unused_imports) to suppress warnings for pass-through types)
     if !use_statements.is_empty() {
         for line in use_statements.lines() {
-            if line.starts_with("use ") {
+            if line.starts_with("use ") || line.starts_with("pub use ") {
                 output.push_str("#[allow(unused_imports)]\n");
             }
             output.push_str(line);
@@ -17929,16 +21109,31 @@ fn generate_module_code(
     // Add module body
     output.push_str(&module_body);
 
+    diagnostics.extend(codegen.diagnostics.take());
     Ok(output)
 }
 
+/// Splits generated item code into its leading `///` and `#[...]` lines and
+/// the rest, so `pub` can be added in front of the item itself
+fn split_doc_comment(code: &str) -> (&str, &str) {
+    let doc_len: usize = code
+        .split_inclusive('\n')
+        .take_while(|line| line.starts_with("///") || line.starts_with("#["))
+        .map(str::len)
+        .sum();
+    code.split_at(doc_len)
+}
+
 /// Convert a Liva import to a Rust use statement
 /// Examples:
 /// - `import { add } from "./math.liva"` → `use crate::math::add;`
 /// - `import * as math from "./math.liva"` → `use crate::math;`
+/// - `import { parse as parseCfg } from "./config"` → `use crate::config::parse as parse_cfg;`
+/// - `export * from "./math.liva"` → `pub use crate::math::*;`
 fn generate_use_statement(
     import_decl: &ImportDecl,
     _current_module_path: &std::path::Path,
+    mapping: IdentifierMapping,
 ) -> Result<String> {
     use std::path::Path;
 
@@ -17950,16 +21145,22 @@ fn generate_use_statement(
     // Parse the source path and resolve relative to current module
     let source_path = Path::new(&import_decl.source);
 
-    // Remove .liva extension if present
-    let module_name = source_path
-        .file_stem()
-        .and_then(|s| s.to_str())
+    // Remove .liva extension if present; package names may contain '-'.
+    // Embedded std modules are generated as `std_<name>`
+    let module_name = crate::module::std_rust_module_name(&import_decl.source)
+        .filter(|_| crate::module::is_std_module(&import_decl.source))
+        .or_else(|| {
+            source_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| s.replace('-', "_"))
+        })
         .ok_or_else(|| {
-            crate::CompilerError::CodegenError(crate::error::SemanticErrorInfo::new(
+            crate::CompilerError::CodegenError(Box::new(crate::error::SemanticErrorInfo::new(
                 "E9001",
                 &format!("Invalid module path: {}", import_decl.source),
                 "",
-            ))
+            )))
         })?;
 
     // Convert relative path to Rust module path
@@ -17975,50 +21176,96 @@ fn generate_use_statement(
         format!("crate::{}", module_name)
     };
 
+    // Re-exports become part of this module's public API
+    let keyword = if import_decl.is_reexport {
+        "pub use"
+    } else {
+        "use"
+    };
+
     if import_decl.is_wildcard {
         // Wildcard import: import * as alias from "..."
         if let Some(alias) = &import_decl.alias {
             // Only use 'as' if alias is different from module name
-            if alias != module_name {
-                Ok(format!("use {} as {};", rust_module_path, alias))
+            if *alias != module_name {
+                Ok(format!("{} {} as {};", keyword, rust_module_path, alias))
             } else {
                 // If alias == module_name, just import the module itself
-                Ok(format!("use {};", rust_module_path))
+                Ok(format!("{} {};", keyword, rust_module_path))
             }
         } else {
-            Ok(format!("use {}::*;", rust_module_path))
-        }
-    } else if import_decl.imports.len() == 1 {
-        // Single import
-        let symbol = &import_decl.imports[0];
-        // Don't convert if it starts with uppercase (it's a type)
-        let rust_symbol = if symbol
-            .chars()
-            .next()
-            .map(|c| c.is_uppercase())
-            .unwrap_or(false)
-        {
-            symbol.clone()
-        } else {
-            to_snake_case(symbol)
-        };
-        Ok(format!("use {}::{};", rust_module_path, rust_symbol))
+            Ok(format!("{} {}::*;", keyword, rust_module_path))
+        }
     } else {
-        // Multiple imports: use crate::math::{add, subtract};
+        // Named imports, with `as` for aliased symbols:
+        // use crate::config::{parse as parse_cfg, Config};
         let rust_symbols: Vec<String> = import_decl
             .imports
             .iter()
             .map(|s| {
-                // Don't convert if it starts with uppercase (it's a type)
-                if s.chars().next().map(|c| c.is_uppercase()).unwrap_or(false) {
-                    s.clone()
-                } else {
-                    to_snake_case(s)
+                let rust_symbol = import_symbol_to_rust(s, mapping);
+                match import_decl.renames.get(s) {
+                    Some(local) => {
+                        format!(
+                            "{} as {}",
+                            rust_symbol,
+                            import_symbol_to_rust(local, mapping)
+                        )
+                    }
+                    None => rust_symbol,
                 }
             })
             .collect();
-        let symbols = rust_symbols.join(", ");
-        Ok(format!("use {}::{{{}}};", rust_module_path, symbols))
+        if rust_symbols.len() == 1 {
+            Ok(format!(
+                "{} {}::{};",
+                keyword, rust_module_path, rust_symbols[0]
+            ))
+        } else {
+            let symbols = rust_symbols.join(", ");
+            Ok(format!(
+                "{} {}::{{{}}};",
+                keyword, rust_module_path, symbols
+            ))
+        }
+    }
+}
+
+/// Rust name of an imported symbol: types (uppercase) keep their name,
+/// functions and constants are spelled as `mapping` says
+fn import_symbol_to_rust(symbol: &str, mapping: IdentifierMapping) -> String {
+    if symbol
+        .chars()
+        .next()
+        .map(|c| c.is_uppercase())
+        .unwrap_or(false)
+    {
+        symbol.to_string()
+    } else {
+        map_identifier_case(symbol, mapping)
+    }
+}
+
+/// Carry what codegen knows about imported functions (fallible, array/string/
+/// optional return) over to the local names they were aliased to
+fn register_import_renames(codegen: &mut CodeGenerator, imports: &[ImportDecl]) {
+    for import_decl in imports {
+        for (original, local) in &import_decl.renames {
+            if codegen.fallible_functions.contains(original) {
+                codegen.fallible_functions.insert(local.clone());
+            }
+            if let Some(elem_type) = codegen.array_returning_functions.get(original).cloned() {
+                codegen
+                    .array_returning_functions
+                    .insert(local.clone(), elem_type);
+            }
+            if codegen.string_returning_functions.contains(original) {
+                codegen.string_returning_functions.insert(local.clone());
+            }
+            if codegen.optional_returning_functions.contains(original) {
+                codegen.optional_returning_functions.insert(local.clone());
+            }
+        }
     }
 }
 
@@ -18028,8 +21275,10 @@ fn generate_entry_point(
     mod_declarations: &[String],
     ctx: &DesugarContext,
     all_modules: &[&crate::module::Module],
+    diagnostics: &mut DiagnosticSink,
 ) -> Result<String> {
     let mut codegen = CodeGenerator::new(ctx.clone());
+    codegen.module_path = entry_module.path.clone();
 
     // Pre-populate enum metadata from all imported modules so the entry module
     // can reference imported enums (e.g., Color.Red) correctly
@@ -18094,6 +21343,7 @@ fn generate_entry_point(
             // B23 fix: Pre-populate fallible functions from imported modules
             // Without this, cross-file error binding generates (fn(), None) instead of match { Ok/Err }
             if let TopLevel::Function(func) = item {
+                codegen.register_function_types(func);
                 if func.contains_fail {
                     codegen.fallible_functions.insert(func.name.clone());
                 }
@@ -18121,64 +21371,25 @@ fn generate_entry_point(
             }
             // B23 fix: Also pre-populate fallible methods from imported classes
             if let TopLevel::Class(class) = item {
-                // BUG-003 fix: Pre-populate class_fields from all modules so
+                // BUG-003 fix: Register classes from all modules so
                 // register_pattern_bindings recognizes imported class types
-                let mut fields = std::collections::HashSet::new();
-                let mut optional_fields = std::collections::HashSet::new();
-                let mut array_field_types = std::collections::HashMap::new();
-                for m2 in &class.members {
-                    if let crate::ast::Member::Field(f) = m2 {
-                        fields.insert(f.name.clone());
-                        if class.name == "TypeContext" {}
-                        if f.is_optional || matches!(&f.type_ref, Some(TypeRef::Optional(_))) {
-                            optional_fields.insert(f.name.clone());
-                        }
-                        // BUG-003 fix: Track array field element types for for-loop var typing
-                        if let Some(TypeRef::Array(element_type)) = &f.type_ref {
-                            if let TypeRef::Simple(type_name) = element_type.as_ref() {
-                                array_field_types.insert(f.name.clone(), type_name.clone());
+                if let Some(class) = codegen.register_class_symbols(&class.name) {
+                    for field in &class.fields {
+                        // SH: Map/Set-typed fields, for deep member access
+                        match &field.type_ref {
+                            Some(TypeRef::Map(_, _)) => {
+                                codegen.map_vars.insert(codegen.sanitize_name(&field.name));
                             }
-                        }
-                        // SH: Track Map-typed fields from imported classes for deep member access
-                        if matches!(&f.type_ref, Some(TypeRef::Map(_, _))) {
-                            codegen.map_vars.insert(codegen.sanitize_name(&f.name));
-                        }
-                        // SH: Track Set-typed fields from imported classes
-                        if matches!(&f.type_ref, Some(TypeRef::Set(_))) {
-                            codegen.set_vars.insert(codegen.sanitize_name(&f.name));
+                            Some(TypeRef::Set(_)) => {
+                                codegen.set_vars.insert(codegen.sanitize_name(&field.name));
+                            }
+                            _ => {}
                         }
                     }
-                }
-                codegen.class_fields.insert(class.name.clone(), fields);
-                codegen
-                    .class_optional_fields
-                    .insert(class.name.clone(), optional_fields);
-                if !array_field_types.is_empty() {
-                    codegen
-                        .class_array_field_types
-                        .insert(class.name.clone(), array_field_types);
-                }
-
-                for member in &class.members {
-                    if let crate::ast::Member::Method(method) = member {
-                        if method.contains_fail {
+                    for method in &class.methods {
+                        if method.fallible {
                             codegen.fallible_methods.insert(method.name.clone());
                         }
-                        // B100 fix: Pre-populate string/array returning methods from imported classes
-                        if let Some(ret_type) = &method.return_type {
-                            if matches!(ret_type, TypeRef::Simple(name) if name == "string") {
-                                codegen.string_returning_methods.insert(method.name.clone());
-                            }
-                            if let TypeRef::Array(elem) = ret_type {
-                                let elem_type = match elem.as_ref() {
-                                    TypeRef::Simple(name) => name.clone(),
-                                    _ => String::new(),
-                                };
-                                codegen
-                                    .array_returning_methods
-                                    .insert(method.name.clone(), elem_type);
-                            }
-                        }
                     }
                 }
             }
@@ -18186,7 +21397,8 @@ fn generate_entry_point(
     }
 
     // Suppress common codegen warnings (must be at top of main.rs)
-    codegen.writeln("#![allow(unused_parens, unused_mut)]");
+    codegen.write_module_doc(&entry_module.ast);
+    codegen.write_crate_allows();
 
     // Add mod declarations for all other modules
     for mod_decl in mod_declarations {
@@ -18197,24 +21409,57 @@ fn generate_entry_point(
         codegen.output.push('\n'); // Blank line after mod declarations
     }
 
+    for module in all_modules {
+        codegen.register_lazy_values(&module.ast.items);
+    }
+    for module in all_modules {
+        if module.path != entry_module.path {
+            let path = format!("crate::{}::", module.rust_module_name());
+            codegen.register_abstract_classes(&module.ast.items, &path);
+        }
+    }
+    register_import_renames(&mut codegen, &entry_module.imports);
+
+    // The compilation order lists importers before their imports
+    for module in all_modules.iter().rev() {
+        if !module
+            .ast
+            .items
+            .iter()
+            .any(|item| matches!(item, TopLevel::Init(_)))
+        {
+            continue;
+        }
+        if module.path == entry_module.path {
+            codegen.init_calls.push("liva_init()".to_string());
+        } else {
+            let name = module.rust_module_name();
+            codegen.init_calls.push(format!("{}::liva_init()", name));
+        }
+    }
+
     // Generate use statements from entry module's imports
     // Also register module aliases for wildcard imports
     for import_decl in &entry_module.imports {
-        if import_decl.is_wildcard && import_decl.alias.is_some() {
+        if let Some(alias) = import_decl
+            .alias
+            .as_ref()
+            .filter(|_| import_decl.is_wildcard)
+        {
             // Wildcard import with alias like `import * as utils from "./utils.liva"`
             // Register the alias -> module_name mapping for code generation
             let source_path = std::path::Path::new(&import_decl.source);
             if let Some(module_name) = source_path.file_stem().and_then(|s| s.to_str()) {
-                let alias = import_decl.alias.as_ref().unwrap();
                 codegen
                     .module_aliases
-                    .insert(alias.clone(), module_name.to_string());
+                    .insert(alias.clone(), module_name.replace('-', "_"));
             }
             // The module is already available via `mod math;`, skip the use statement
             continue;
         }
 
-        let use_stmt = generate_use_statement(import_decl, &entry_module.path)?;
+        let use_stmt =
+            generate_use_statement(import_decl, &entry_module.path, ctx.identifier_mapping)?;
         codegen.output.push_str("#[allow(unused_imports)]\n");
         codegen.output.push_str(&use_stmt);
         codegen.output.push('\n');
@@ -18227,18 +21472,50 @@ fn generate_entry_point(
     // Generate the entry module using generate_program logic
     codegen.generate_program(&entry_module.ast)?;
 
+    diagnostics.extend(codegen.diagnostics.take());
     Ok(codegen.output.clone())
 }
 
+fn sanitize_test_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Name of the Rust `#[test]` function generated for `test "name" { ... }`,
+/// which `livac test --filter` matches against
+pub fn test_function_name(name: &str) -> String {
+    format!("test_{}", sanitize_test_name(name))
+}
+
 pub fn generate_with_ast(program: &Program, ctx: DesugarContext) -> Result<(String, String)> {
+    generate_with_diagnostics(program, ctx, &mut DiagnosticSink::new())
+}
+
+/// [`generate_with_ast`], reporting warnings such as tasks that are never
+/// awaited into `diagnostics`
+pub fn generate_with_diagnostics(
+    program: &Program,
+    ctx: DesugarContext,
+    diagnostics: &mut DiagnosticSink,
+) -> Result<(String, String)> {
     let mut generator = CodeGenerator::new(ctx);
 
     // Suppress common codegen warnings (crate-level attribute for single-file projects)
-    generator.writeln("#![allow(unused_parens, unused_mut)]");
+    generator.write_module_doc(program);
+    generator.write_crate_allows();
 
     // First pass: collect fallible functions and array-returning functions
     for item in &program.items {
         if let TopLevel::Function(func) = item {
+            generator.register_function_types(func);
             if func.contains_fail {
                 generator.fallible_functions.insert(func.name.clone());
             }
@@ -18279,6 +21556,14 @@ pub fn generate_with_ast(program: &Program, ctx: DesugarContext) -> Result<(Stri
         }
     }
 
+    generator.register_lazy_values(&program.items);
+    if program
+        .items
+        .iter()
+        .any(|item| matches!(item, TopLevel::Init(_)))
+    {
+        generator.init_calls.push("liva_init()".to_string());
+    }
     generator.generate_program(program)?;
 
     // Insert hoisted `use` statements from `rust { }` blocks at the top of the file
@@ -18290,24 +21575,32 @@ pub fn generate_with_ast(program: &Program, ctx: DesugarContext) -> Result<(Stri
         }
         hoisted.push('\n');
         // Insert after the #![allow(...)] line
-        if let Some(pos) = generator.output.find('\n') {
-            generator.output.insert_str(pos + 1, &hoisted);
+        let allow_line = generator.output.find("#![allow").unwrap_or(0);
+        if let Some(pos) = generator.output[allow_line..].find('\n') {
+            generator.output.insert_str(allow_line + pos + 1, &hoisted);
         }
     }
 
     let cargo_toml = generate_cargo_toml(&generator.ctx)?;
 
+    diagnostics.extend(generator.diagnostics.take());
     Ok((generator.output, cargo_toml))
 }
 
 pub fn generate_cargo_toml(ctx: &DesugarContext) -> Result<String> {
-    let mut cargo_toml = String::from(
+    let crate_name = if ctx.crate_name.is_empty() {
+        crate::artifacts::DEFAULT_CRATE_NAME
+    } else {
+        ctx.crate_name.as_str()
+    };
+    let mut cargo_toml = format!(
         "[workspace]\n\n\
          [package]\n\
-         name = \"liva_project\"\n\
+         name = \"{}\"\n\
          version = \"0.1.0\"\n\
          edition = \"2021\"\n\n\
          [dependencies]\n",
+        crate_name
     );
 
     // Helper: collect extra features a user wants for an internal crate
@@ -18460,8 +21753,28 @@ mod tests {
             has_crypto: false,
             has_server: false,
             has_db: false,
+            has_key_sort: false,
+            has_mocks: false,
+            has_test_hooks: false,
+            has_task_scope: false,
+            has_power: false,
+            has_floor_division: false,
+            has_type_of: false,
+            has_char_code: false,
+            has_frozen: false,
             async_functions: std::collections::BTreeSet::new(),
+            runtime: None,
             source_filename: String::new(),
+            crate_name: String::new(),
+            deterministic: false,
+            coverage: false,
+            debug: false,
+            explain_codegen: false,
+            source_map: false,
+            inline: std::collections::BTreeMap::new(),
+            numeric_coercion: crate::NumericCoercion::Coerce,
+            identifier_mapping: crate::IdentifierMapping::Snake,
+            symbols: crate::symbols::SymbolTable::default(),
         });
 
         assert_eq!(gen.to_snake_case("CamelCase"), "camel_case");
@@ -0,0 +1,367 @@
+//! Compile-time evaluation of constant expressions.
+//!
+//! Folds expressions built only from literals, top-level `const`s and pure
+//! `Math` functions into a single value, following the runtime semantics of
+//! the generated Rust: integer arithmetic is checked and truncating, and an
+//! `int` mixed with a `float` is promoted. Anything that would overflow,
+//! divide by zero or depend on runtime state evaluates to `None`.
+//!
+//! Semantic analysis uses it to decide which `const`s become Rust `const`s
+//! (the rest are initialized on first read, like `lazy` values), codegen to
+//! emit their values, and editors to show the value of an expression on
+//! hover.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{
+    BinOp, Expr, Literal, Program, StringTemplatePart, SwitchBody, TopLevel, TypeRef, UnOp,
+};
+
+/// Value of a constant expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+/// Formats the value as a Liva literal, so it can replace the expression
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstValue::Int(n) => write!(f, "{}", n),
+            ConstValue::Float(n) => write!(f, "{:?}", n),
+            ConstValue::Bool(b) => write!(f, "{}", b),
+            ConstValue::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+impl ConstValue {
+    /// Liva type of the value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConstValue::Int(_) => "int",
+            ConstValue::Float(_) => "float",
+            ConstValue::Bool(_) => "bool",
+            ConstValue::String(_) => "string",
+        }
+    }
+
+    /// The value as a literal expression
+    pub fn to_literal(&self) -> Expr {
+        Expr::Literal(match self {
+            ConstValue::Int(n) => Literal::Int(*n),
+            ConstValue::Float(n) => Literal::Float(*n),
+            ConstValue::Bool(b) => Literal::Bool(*b),
+            ConstValue::String(s) => Literal::String(s.clone()),
+        })
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        match self {
+            ConstValue::Int(n) => Some(*n as f64),
+            ConstValue::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Values of the top-level `const`s of `program` that are constant
+/// expressions, each allowed to use the ones declared before it. `lazy`
+/// values are computed at run time and left out.
+pub fn program_constants(program: &Program) -> HashMap<String, ConstValue> {
+    let mut constants = HashMap::new();
+    for item in &program.items {
+        if let TopLevel::ConstDecl(decl) = item {
+            if decl.is_lazy {
+                continue;
+            }
+            if let Some(value) = evaluate(&decl.init, &constants) {
+                constants.insert(decl.name.clone(), value);
+            }
+        }
+    }
+    constants
+}
+
+/// Folds `expr` into a value, resolving identifiers through `constants`
+pub fn evaluate(expr: &Expr, constants: &HashMap<String, ConstValue>) -> Option<ConstValue> {
+    match expr {
+        Expr::Literal(Literal::Int(n)) => Some(ConstValue::Int(*n)),
+        Expr::Literal(Literal::Float(n)) => Some(ConstValue::Float(*n)),
+        Expr::Literal(Literal::Bool(b)) => Some(ConstValue::Bool(*b)),
+        Expr::Literal(Literal::String(s)) => Some(ConstValue::String(s.clone())),
+        Expr::Identifier(name) => constants.get(name).cloned(),
+        Expr::Unary { op, operand } => {
+            let value = evaluate(operand, constants)?;
+            match (op, value) {
+                (UnOp::Neg, ConstValue::Int(n)) => n.checked_neg().map(ConstValue::Int),
+                (UnOp::Neg, ConstValue::Float(n)) => Some(ConstValue::Float(-n)),
+                (UnOp::Not, ConstValue::Bool(b)) => Some(ConstValue::Bool(!b)),
+                (UnOp::BitNot, ConstValue::Int(n)) => Some(ConstValue::Int(!n)),
+                _ => None,
+            }
+        }
+        Expr::Binary { op, left, right } => {
+            let left = evaluate(left, constants)?;
+            // `&&` and `||` short-circuit, like at runtime
+            match (op, &left) {
+                (BinOp::And, ConstValue::Bool(false)) => return Some(left),
+                (BinOp::Or, ConstValue::Bool(true)) => return Some(left),
+                _ => {}
+            }
+            binary(*op, left, evaluate(right, constants)?)
+        }
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => match evaluate(condition, constants)? {
+            ConstValue::Bool(true) => evaluate(then_expr, constants),
+            ConstValue::Bool(false) => evaluate(else_expr, constants),
+            _ => None,
+        },
+        // Parsing a string with `as int` can fail, so it never folds
+        Expr::Cast { expr, target } => {
+            let TypeRef::Simple(target) = target else {
+                return None;
+            };
+            match (target.as_str(), evaluate(expr, constants)?) {
+                ("int", ConstValue::Int(n)) => Some(ConstValue::Int(n)),
+                ("int", ConstValue::Float(n)) if n.is_finite() => {
+                    Some(ConstValue::Int(n.trunc() as i64))
+                }
+                ("int", ConstValue::Bool(b)) => Some(ConstValue::Int(b as i64)),
+                ("float", value) => value.as_float().map(ConstValue::Float),
+                ("string", ConstValue::String(s)) => Some(ConstValue::String(s)),
+                ("string", ConstValue::Float(n)) => Some(ConstValue::String(n.to_string())),
+                ("string", value) => Some(ConstValue::String(value.to_string())),
+                _ => None,
+            }
+        }
+        Expr::If(if_expr) => {
+            let branch = match evaluate(&if_expr.condition, constants)? {
+                ConstValue::Bool(true) => &if_expr.then_branch,
+                ConstValue::Bool(false) => &if_expr.else_branch,
+                _ => return None,
+            };
+            match branch {
+                SwitchBody::Expr(expr) => evaluate(expr, constants),
+                SwitchBody::Block(_) => None,
+            }
+        }
+        Expr::StringTemplate { parts } => {
+            let mut text = String::new();
+            for part in parts {
+                match part {
+                    StringTemplatePart::Text(s) => text.push_str(s),
+                    StringTemplatePart::Expr(expr) => match evaluate(expr, constants)? {
+                        ConstValue::String(s) => text.push_str(&s),
+                        value => text.push_str(&value.to_string()),
+                    },
+                }
+            }
+            Some(ConstValue::String(text))
+        }
+        Expr::MethodCall(call) if matches!(call.object.as_ref(), Expr::Identifier(name) if name == "Math") =>
+        {
+            let args = call
+                .args
+                .iter()
+                .map(|arg| evaluate(arg, constants))
+                .collect::<Option<Vec<ConstValue>>>()?;
+            if let [ConstValue::Int(a), ConstValue::Int(b)] = args.as_slice() {
+                match call.method.as_str() {
+                    "floorDiv" => return floor_div(*a, *b).map(ConstValue::Int),
+                    "floorMod" => return floor_mod(*a, *b).map(ConstValue::Int),
+                    _ => {}
+                }
+            }
+            let args = args
+                .iter()
+                .map(ConstValue::as_float)
+                .collect::<Option<Vec<f64>>>()?;
+            math(&call.method, &args)
+        }
+        _ => None,
+    }
+}
+
+fn binary(op: BinOp, left: ConstValue, right: ConstValue) -> Option<ConstValue> {
+    use ConstValue::*;
+
+    match (op, left, right) {
+        (BinOp::Add, String(a), String(b)) => Some(String(a + &b)),
+        (BinOp::Eq, a, b) => Some(Bool(a == b)),
+        (BinOp::Ne, a, b) => Some(Bool(a != b)),
+        (BinOp::And, Bool(a), Bool(b)) => Some(Bool(a && b)),
+        (BinOp::Or, Bool(a), Bool(b)) => Some(Bool(a || b)),
+        (op, Int(a), Int(b)) => match op {
+            BinOp::Add => a.checked_add(b).map(Int),
+            BinOp::Sub => a.checked_sub(b).map(Int),
+            BinOp::Mul => a.checked_mul(b).map(Int),
+            BinOp::Div => a.checked_div(b).map(Int),
+            BinOp::Mod => a.checked_rem(b).map(Int),
+            BinOp::Pow => a.checked_pow(u32::try_from(b).ok()?).map(Int),
+            BinOp::BitAnd => Some(Int(a & b)),
+            BinOp::BitOr => Some(Int(a | b)),
+            BinOp::BitXor => Some(Int(a ^ b)),
+            BinOp::Shl => a.checked_shl(u32::try_from(b).ok()?).map(Int),
+            BinOp::Shr => a.checked_shr(u32::try_from(b).ok()?).map(Int),
+            _ => compare(op, a.cmp(&b)),
+        },
+        (op, a, b) => {
+            let (a, b) = (a.as_float()?, b.as_float()?);
+            let result = match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+                BinOp::Mod => a % b,
+                BinOp::Pow => a.powf(b),
+                _ => return compare(op, a.partial_cmp(&b)?),
+            };
+            // Infinity and NaN have no literal to show
+            result.is_finite().then_some(Float(result))
+        }
+    }
+}
+
+fn compare(op: BinOp, ordering: std::cmp::Ordering) -> Option<ConstValue> {
+    let result = match op {
+        BinOp::Lt => ordering.is_lt(),
+        BinOp::Le => ordering.is_le(),
+        BinOp::Gt => ordering.is_gt(),
+        BinOp::Ge => ordering.is_ge(),
+        _ => return None,
+    };
+    Some(ConstValue::Bool(result))
+}
+
+/// The pure `Math` functions; `Math.random()` is not one of them
+fn math(method: &str, args: &[f64]) -> Option<ConstValue> {
+    let float = |n: f64| n.is_finite().then_some(ConstValue::Float(n));
+    let int =
+        |n: f64| (n.is_finite() && n.abs() < i64::MAX as f64).then_some(ConstValue::Int(n as i64));
+
+    match (method, args) {
+        ("pow", [base, exp]) => float(base.powf(*exp)),
+        ("sqrt", [n]) => float(n.sqrt()),
+        ("abs", [n]) => float(n.abs()),
+        ("log", [n]) => float(n.ln()),
+        ("floor", [n]) => int(n.floor()),
+        ("ceil", [n]) => int(n.ceil()),
+        ("round", [n]) => int(n.round()),
+        ("min", [a, b]) => float(a.min(*b)),
+        ("max", [a, b]) => float(a.max(*b)),
+        ("floorDiv", [a, b]) => float((a / b).floor()),
+        ("floorMod", [a, b]) => float(a - b * (a / b).floor()),
+        _ => None,
+    }
+}
+
+/// Integer division rounding toward negative infinity; `/` truncates
+fn floor_div(a: i64, b: i64) -> Option<i64> {
+    let quotient = a.checked_div(b)?;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        quotient.checked_sub(1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// The remainder of [`floor_div`], with the sign of `b`; `%` takes the sign of `a`
+fn floor_mod(a: i64, b: i64) -> Option<i64> {
+    let remainder = a.checked_rem(b)?;
+    if remainder != 0 && (remainder < 0) != (b < 0) {
+        Some(remainder + b)
+    } else {
+        Some(remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    fn constants(source: &str) -> HashMap<String, ConstValue> {
+        program_constants(&parse(tokenize(source).unwrap(), source).unwrap())
+    }
+
+    #[test]
+    fn test_folds_arithmetic_and_constants() {
+        let values = constants(
+            "const KB = 1024\nconst SIZE = KB * 3 + 7 % 4\nconst HALF = SIZE / 2.0\nconst BIG = SIZE > 3000 && !false\nconst LABEL = \"size \" + \"is\"\nconst POW = Math.pow(2, 10)\nconst ROUND = Math.round(2.5)\nconst MODE = if BIG { \"big\" } else { \"small\" }\nconst WHOLE = HALF as int\nconst SHOWN = WHOLE as string\n",
+        );
+        assert_eq!(values["SIZE"], ConstValue::Int(3075));
+        assert_eq!(values["HALF"], ConstValue::Float(1537.5));
+        assert_eq!(values["BIG"], ConstValue::Bool(true));
+        assert_eq!(values["LABEL"].to_string(), "\"size is\"");
+        assert_eq!(values["POW"].to_string(), "1024.0");
+        assert_eq!(values["ROUND"], ConstValue::Int(3));
+        assert_eq!(values["MODE"].to_string(), "\"big\"");
+        assert_eq!(values["WHOLE"], ConstValue::Int(1537));
+        assert_eq!(values["SHOWN"].to_string(), "\"1537\"");
+    }
+
+    #[test]
+    fn test_folds_powers() {
+        let values = constants(
+            "const KIB = 2 ** 10\nconst TOWER = 2 ** 3 ** 2\nconst ROOT = 2.0 ** 0.5\nconst NEG = -2 ** 2\nconst INV = 2 ** -1\n",
+        );
+        assert_eq!(values["KIB"], ConstValue::Int(1024));
+        assert_eq!(values["TOWER"], ConstValue::Int(512));
+        assert_eq!(values["ROOT"], ConstValue::Float(2f64.sqrt()));
+        assert_eq!(values["NEG"], ConstValue::Int(-4));
+        // An integer raised to a negative power has no integer value
+        assert!(!values.contains_key("INV"));
+    }
+
+    #[test]
+    fn test_folds_floor_division() {
+        let values = constants(
+            "const TRUNC = -7 / 2\nconst REM = -7 % 2\nconst FLOOR = Math.floorDiv(-7, 2)\nconst MOD = Math.floorMod(-7, 2)\nconst NEG = Math.floorMod(7, -2)\nconst HALF = Math.floorDiv(-7.5, 2)\n",
+        );
+        assert_eq!(values["TRUNC"], ConstValue::Int(-3));
+        assert_eq!(values["REM"], ConstValue::Int(-1));
+        assert_eq!(values["FLOOR"], ConstValue::Int(-4));
+        assert_eq!(values["MOD"], ConstValue::Int(1));
+        assert_eq!(values["NEG"], ConstValue::Int(-1));
+        assert_eq!(values["HALF"], ConstValue::Float(-4.0));
+    }
+
+    #[test]
+    fn test_folds_bitwise_operators() {
+        let values = constants(
+            "const READ = 1 << 2
+const FLAGS = READ | 1
+const MASKED = FLAGS & ~1
+const FLIP = 6 ^ 3
+const HALF = -8 >> 1
+const CHECK = FLAGS & READ != 0
+const FAR = 1 << 64
+",
+        );
+        assert_eq!(values["READ"], ConstValue::Int(4));
+        assert_eq!(values["FLAGS"], ConstValue::Int(5));
+        assert_eq!(values["MASKED"], ConstValue::Int(4));
+        assert_eq!(values["FLIP"], ConstValue::Int(5));
+        assert_eq!(values["HALF"], ConstValue::Int(-4));
+        // `&` binds tighter than `!=`
+        assert_eq!(values["CHECK"], ConstValue::Bool(true));
+        assert!(!values.contains_key("FAR"));
+    }
+
+    #[test]
+    fn test_runtime_failures_do_not_fold() {
+        let values = constants(
+            "const ZERO = 1 / 0\nconst OVERFLOW = 9223372036854775807 + 1\nconst RANDOM = Math.random()\nconst CALL = compute(2)\nconst PARSED = \"42\" as int\n",
+        );
+        assert!(values.is_empty(), "{:?}", values);
+    }
+}
@@ -0,0 +1,341 @@
+//! Warnings for string literals whose Rust conversion is guessed.
+//!
+//! A string literal is a `&str` in Rust, and codegen turns it into a
+//! `String` only for a parameter or field declared as a string. When the
+//! parameter has no declared type, codegen still appends `.to_string()` but
+//! the parameter's own Rust type is guessed from its name, so the call often
+//! doesn't compile. Each such call is reported once per parameter (E0024),
+//! whether it calls a function, a class constructor or a method of an
+//! instance whose class is known, and so is an assignment to a field
+//! without a type.
+
+use crate::ast::*;
+use crate::error::SemanticErrorInfo;
+use crate::error_codes::E0024_UNDETERMINED_STRING_CONVERSION;
+use std::collections::{HashMap, HashSet};
+
+/// A warning for each parameter of a top-level function, constructor or
+/// method that has no declared type and is passed a string literal, and for
+/// each field without a type that is assigned one
+pub fn string_argument_warnings(program: &Program) -> Vec<SemanticErrorInfo> {
+    let mut functions = HashMap::new();
+    let mut classes = HashMap::new();
+    for item in &program.items {
+        match item {
+            TopLevel::Function(func) => {
+                functions.insert(func.name.as_str(), untyped_names(&func.params));
+            }
+            TopLevel::Class(class) => {
+                classes.insert(class.name.as_str(), class);
+            }
+            _ => {}
+        }
+    }
+    functions.retain(|_, params| params.iter().any(Option::is_some));
+    let mut finder = Finder {
+        functions,
+        classes,
+        instances: HashMap::new(),
+        current_class: None,
+        reported: HashSet::new(),
+        warnings: Vec::new(),
+    };
+    finder.visit_program(program);
+    finder.warnings
+}
+
+/// The name of a parameter codegen has to guess a type for: no declared
+/// type, no default to take one from, and not destructured
+fn untyped_name(param: &Param) -> Option<&str> {
+    if param.type_ref.is_some() || param.default.is_some() {
+        return None;
+    }
+    param.name()
+}
+
+fn untyped_names(params: &[Param]) -> Vec<Option<&str>> {
+    params.iter().map(untyped_name).collect()
+}
+
+/// The `constructor` of `class`, if it declares one; without it, the class
+/// is a data class whose constructor takes the fields
+fn constructor(class: &ClassDecl) -> Option<&MethodDecl> {
+    class.members.iter().find_map(|member| match member {
+        Member::Method(method) if method.name == "constructor" => Some(method),
+        _ => None,
+    })
+}
+
+struct Finder<'a> {
+    /// Top-level functions with an untyped parameter, by name
+    functions: HashMap<&'a str, Vec<Option<&'a str>>>,
+    /// Classes by name, for their constructors, methods and fields
+    classes: HashMap<&'a str, &'a ClassDecl>,
+    /// Class of each variable known to hold an instance, in the function
+    /// being walked
+    instances: HashMap<&'a str, &'a str>,
+    /// Class whose members are being walked, the class of `this`
+    current_class: Option<&'a str>,
+    /// (callee, parameter) and (`Class.`, field) pairs already warned about;
+    /// the trailing dot keeps a field apart from a constructor parameter of
+    /// the same name
+    reported: HashSet<(String, &'a str)>,
+    warnings: Vec<SemanticErrorInfo>,
+}
+
+impl<'a> Finder<'a> {
+    fn call(&mut self, call: &'a CallExpr) {
+        let Expr::Identifier(name) = call.callee.as_ref() else {
+            return;
+        };
+        let declared = self
+            .classes
+            .get(name.as_str())
+            .copied()
+            .and_then(constructor);
+        let params = match (self.functions.get(name.as_str()), declared) {
+            (Some(params), _) => params.clone(),
+            (None, Some(method)) => untyped_names(&method.params),
+            (None, None) => return,
+        };
+        self.string_args(name, &call.args, &params);
+    }
+
+    fn method_call(&mut self, call: &'a MethodCallExpr) {
+        let Some(class) = self.class_of(&call.object) else {
+            return;
+        };
+        let method = class.members.iter().find_map(|member| match member {
+            Member::Method(method) if method.name == call.method => Some(method),
+            _ => None,
+        });
+        if let Some(method) = method {
+            let callee = format!("{}.{}", class.name, method.name);
+            self.string_args(&callee, &call.args, &untyped_names(&method.params));
+        }
+    }
+
+    fn string_args(&mut self, callee: &str, args: &[Expr], params: &[Option<&'a str>]) {
+        for (arg, param) in args.iter().zip(params) {
+            let Some(param) = *param else { continue };
+            if !matches!(arg, Expr::Literal(Literal::String(_)))
+                || !self.reported.insert((callee.to_string(), param))
+            {
+                continue;
+            }
+            self.warnings.push(
+                SemanticErrorInfo::new(
+                    E0024_UNDETERMINED_STRING_CONVERSION,
+                    "String conversion can't be determined",
+                    &format!(
+                        "'{}' is called with a string literal for parameter '{}', which has no declared type; the literal is passed as a String while the parameter's Rust type is guessed from its name",
+                        callee, param
+                    ),
+                )
+                .with_help(&format!("Declare the parameter, e.g. '{}: string'", param)),
+            );
+        }
+    }
+
+    /// `object.field = "..."` where `field` has no type. A data class's
+    /// fields without one are strings, so only other classes are checked.
+    fn assign(&mut self, assign: &'a AssignStmt) {
+        let (Expr::Member { object, property }, Expr::Literal(Literal::String(_))) =
+            (&assign.target, &assign.value)
+        else {
+            return;
+        };
+        let Some(class) = self.class_of(object) else {
+            return;
+        };
+        let untyped = class.members.iter().any(|member| {
+            matches!(member, Member::Field(field)
+                if &field.name == property && field.type_ref.is_none())
+        });
+        if !untyped
+            || constructor(class).is_none()
+            || !self
+                .reported
+                .insert((format!("{}.", class.name), property.as_str()))
+        {
+            return;
+        }
+        self.warnings.push(
+            SemanticErrorInfo::new(
+                E0024_UNDETERMINED_STRING_CONVERSION,
+                "String conversion can't be determined",
+                &format!(
+                    "Field '{}.{}' is assigned a string literal but has no declared type; the literal is stored as a String while the field's Rust type is guessed",
+                    class.name, property
+                ),
+            )
+            .with_help(&format!("Declare the field, e.g. '{}: string'", property)),
+        );
+    }
+
+    /// The class `object` is an instance of, when it is `this` or a variable
+    /// known to hold one
+    fn class_of(&self, object: &Expr) -> Option<&'a ClassDecl> {
+        let class = match object {
+            Expr::Identifier(name) if name == "this" => self.current_class?,
+            Expr::Identifier(name) => *self.instances.get(name.as_str())?,
+            _ => return None,
+        };
+        self.classes.get(class).copied()
+    }
+
+    /// Remember that `name` holds an instance of the class `type_ref` or
+    /// `init` names
+    fn bind(&mut self, name: &'a str, type_ref: Option<&'a TypeRef>, init: Option<&'a Expr>) {
+        let class = match (type_ref, init) {
+            (Some(TypeRef::Simple(class)), _) => class,
+            (None, Some(Expr::Call(call))) => match call.callee.as_ref() {
+                Expr::Identifier(class) => class,
+                _ => return,
+            },
+            _ => return,
+        };
+        if let Some((&class, _)) = self.classes.get_key_value(class.as_str()) {
+            self.instances.insert(name, class);
+        }
+    }
+
+    fn params(&mut self, params: &'a [Param]) {
+        for param in params {
+            if let Some(name) = param.name() {
+                self.bind(name, param.type_ref.as_ref(), None);
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Finder<'a> {
+    fn visit_item(&mut self, item: &'a TopLevel) {
+        self.instances.clear();
+        self.current_class = match item {
+            TopLevel::Class(ClassDecl { name, .. }) | TopLevel::Type(TypeDecl { name, .. }) => {
+                Some(name.as_str())
+            }
+            TopLevel::ClassExtension(extension) => Some(extension.name.as_str()),
+            _ => None,
+        };
+        walk_item(self, item);
+        self.current_class = None;
+    }
+
+    fn visit_function(&mut self, func: &'a FunctionDecl) {
+        let outer = std::mem::take(&mut self.instances);
+        self.params(&func.params);
+        walk_function(self, func);
+        self.instances = outer;
+    }
+
+    fn visit_method(&mut self, method: &'a MethodDecl) {
+        let outer = std::mem::take(&mut self.instances);
+        self.params(&method.params);
+        walk_method(self, method);
+        self.instances = outer;
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                walk_stmt(self, stmt);
+                if let [binding] = decl.bindings.as_slice() {
+                    if let Some(name) = binding.name() {
+                        self.bind(name, binding.type_ref.as_ref(), Some(&decl.init));
+                    }
+                }
+            }
+            Stmt::Assign(assign) => {
+                self.assign(assign);
+                walk_stmt(self, stmt);
+            }
+            _ => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Call(call) => self.call(call),
+            Expr::MethodCall(call) => self.method_call(call),
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::tokenize, parser::parse, semantic::analyze};
+
+    fn warnings(source: &str) -> Vec<SemanticErrorInfo> {
+        let program = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+        string_argument_warnings(&program)
+    }
+
+    #[test]
+    fn test_warns_once_per_untyped_parameter() {
+        let source = r#"
+greet(who) {
+    print(who)
+}
+
+main() {
+    greet("Ana")
+    greet("Luis")
+}
+"#;
+        let warnings = warnings(source);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "E0024");
+        assert!(warnings[0].message.contains("'who'"));
+    }
+
+    #[test]
+    fn test_typed_and_defaulted_parameters_are_not_reported() {
+        let source = r#"
+label(text: string, suffix = "!") {
+    print(text + suffix)
+}
+
+main() {
+    label("hi", "?")
+}
+"#;
+        assert!(warnings(source).is_empty());
+    }
+
+    #[test]
+    fn test_constructors_methods_and_fields_are_reported() {
+        let source = r#"
+Badge {
+    label
+    constructor(label) {
+        this.label = label
+    }
+    rename(to) {
+        this.label = to
+    }
+}
+
+Tag {
+    name
+}
+
+main() {
+    let badge = Badge("new")
+    badge.rename("old")
+    badge.label = "plain"
+    let tag = Tag("data classes take strings")
+    tag.name = "still a string"
+}
+"#;
+        let messages: Vec<String> = warnings(source).into_iter().map(|w| w.message).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains("'Badge' is called") && messages[0].contains("'label'"));
+        assert!(messages[1].contains("'Badge.rename'") && messages[1].contains("'to'"));
+        assert!(messages[2].contains("Field 'Badge.label'"));
+    }
+}
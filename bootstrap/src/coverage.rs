@@ -0,0 +1,260 @@
+//! Statement coverage reports for `livac test --coverage`.
+//!
+//! With `CompilerOptions::coverage` set, codegen emits a
+//! `crate::liva_cov_hit("file.liva", line)` probe before every statement, and
+//! each test writes its hit counts to `LIVA_COVERAGE_FILE` as
+//! `file<TAB>line<TAB>hits` lines. This module merges the probe sites found in
+//! the generated code with those counts and renders lcov and HTML reports.
+
+use std::collections::BTreeMap;
+
+const PROBE: &str = "liva_cov_hit(";
+
+/// Per-file, per-line hit counts for every instrumented Liva statement
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CoverageReport {
+    files: BTreeMap<String, BTreeMap<u32, u64>>,
+}
+
+impl CoverageReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register every probe in a generated Rust file as a coverable line (0 hits)
+    pub fn add_sites(&mut self, generated_rust: &str) {
+        for (file, line) in probe_sites(generated_rust) {
+            self.files.entry(file).or_default().entry(line).or_insert(0);
+        }
+    }
+
+    /// Add the counts written by one test run (`LIVA_COVERAGE_FILE` contents)
+    pub fn add_hits(&mut self, data: &str) {
+        for entry in data.lines() {
+            let mut parts = entry.rsplitn(3, '\t');
+            let (Some(hits), Some(line), Some(file)) = (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            let (Ok(hits), Ok(line)) = (hits.parse::<u64>(), line.parse::<u32>()) else {
+                continue;
+            };
+            *self
+                .files
+                .entry(file.to_string())
+                .or_default()
+                .entry(line)
+                .or_insert(0) += hits;
+        }
+    }
+
+    /// Drop files the report should not cover (e.g. the test files themselves)
+    pub fn retain_files(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.files.retain(|file, _| keep(file));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Line counts per file: file -> (line -> hits)
+    pub fn files(&self) -> &BTreeMap<String, BTreeMap<u32, u64>> {
+        &self.files
+    }
+
+    /// (covered lines, coverable lines) across all files
+    pub fn totals(&self) -> (usize, usize) {
+        self.files.values().fold((0, 0), |(hit, total), lines| {
+            (
+                hit + lines.values().filter(|&&h| h > 0).count(),
+                total + lines.len(),
+            )
+        })
+    }
+
+    /// Render in lcov tracefile format (`lcov.info`)
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for (file, lines) in &self.files {
+            out.push_str("TN:\n");
+            out.push_str(&format!("SF:{}\n", file));
+            for (line, hits) in lines {
+                out.push_str(&format!("DA:{},{}\n", line, hits));
+            }
+            out.push_str(&format!(
+                "LH:{}\n",
+                lines.values().filter(|&&h| h > 0).count()
+            ));
+            out.push_str(&format!("LF:{}\n", lines.len()));
+            out.push_str("end_of_record\n");
+        }
+        out
+    }
+
+    /// Render a standalone HTML page; `read_source` supplies each file's text
+    pub fn to_html(&self, mut read_source: impl FnMut(&str) -> Option<String>) -> String {
+        let (hit, total) = self.totals();
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str("<title>Liva coverage</title>\n<style>\n");
+        out.push_str("body { font-family: sans-serif; margin: 2em; }\n");
+        out.push_str("table { border-collapse: collapse; }\n");
+        out.push_str("td, th { padding: 2px 10px; text-align: left; }\n");
+        out.push_str("pre { font-family: monospace; line-height: 1.3; }\n");
+        out.push_str(".hit { background: #dfd; }\n.miss { background: #fdd; }\n");
+        out.push_str(".ln { color: #888; display: inline-block; width: 4em; }\n");
+        out.push_str(".cnt { color: #555; display: inline-block; width: 5em; }\n");
+        out.push_str("</style>\n</head>\n<body>\n");
+        out.push_str(&format!(
+            "<h1>Liva coverage: {}/{} lines ({})</h1>\n",
+            hit,
+            total,
+            percent(hit, total)
+        ));
+
+        out.push_str("<table>\n<tr><th>File</th><th>Lines</th><th>Coverage</th></tr>\n");
+        for (i, (file, lines)) in self.files.iter().enumerate() {
+            let file_hit = lines.values().filter(|&&h| h > 0).count();
+            out.push_str(&format!(
+                "<tr><td><a href=\"#f{}\">{}</a></td><td>{}/{}</td><td>{}</td></tr>\n",
+                i,
+                escape_html(file),
+                file_hit,
+                lines.len(),
+                percent(file_hit, lines.len())
+            ));
+        }
+        out.push_str("</table>\n");
+
+        for (i, (file, lines)) in self.files.iter().enumerate() {
+            out.push_str(&format!(
+                "<h2 id=\"f{}\">{}</h2>\n<pre>\n",
+                i,
+                escape_html(file)
+            ));
+            let source = read_source(file).unwrap_or_default();
+            for (idx, text) in source.lines().enumerate() {
+                let line = idx as u32 + 1;
+                let (class, count) = match lines.get(&line) {
+                    Some(0) => (" class=\"miss\"", "0".to_string()),
+                    Some(hits) => (" class=\"hit\"", hits.to_string()),
+                    None => ("", String::new()),
+                };
+                out.push_str(&format!(
+                    "<div{}><span class=\"ln\">{}</span><span class=\"cnt\">{}</span>{}</div>\n",
+                    class,
+                    line,
+                    count,
+                    escape_html(text)
+                ));
+            }
+            out.push_str("</pre>\n");
+        }
+
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// Probe sites `(file, line)` in generated Rust code, in order of appearance
+pub fn probe_sites(generated_rust: &str) -> Vec<(String, u32)> {
+    let mut sites = Vec::new();
+    let mut rest = generated_rust;
+    while let Some(pos) = rest.find(PROBE) {
+        rest = &rest[pos + PROBE.len()..];
+        if let Some((site, remaining)) = parse_probe_args(rest) {
+            sites.push(site);
+            rest = remaining;
+        }
+    }
+    sites
+}
+
+/// Parse `"file", line)` as written by codegen (file is a Rust string literal)
+fn parse_probe_args(input: &str) -> Option<((String, u32), &str)> {
+    let mut chars = input.strip_prefix('"')?.char_indices();
+    let mut file = String::new();
+    let end = loop {
+        match chars.next()? {
+            (_, '\\') => file.push(chars.next()?.1),
+            (i, '"') => break i + 2,
+            (_, c) => file.push(c),
+        }
+    };
+    let rest = input[end..].strip_prefix(", ")?;
+    let close = rest.find(')')?;
+    let line = rest[..close].parse().ok()?;
+    Some(((file, line), &rest[close + 1..]))
+}
+
+fn percent(hit: usize, total: usize) -> String {
+    if total == 0 {
+        "n/a".to_string()
+    } else {
+        format!("{:.1}%", hit as f64 * 100.0 / total as f64)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENERATED: &str = r#"
+fn add(a: i32, b: i32) -> i32 {
+    crate::liva_cov_hit("src/math.liva", 2);
+    return a + b;
+}
+fn sub(a: i32, b: i32) -> i32 {
+    crate::liva_cov_hit("src/math.liva", 6);
+    return a - b;
+}
+"#;
+
+    #[test]
+    fn test_probe_sites() {
+        assert_eq!(
+            probe_sites(GENERATED),
+            vec![
+                ("src/math.liva".to_string(), 2),
+                ("src/math.liva".to_string(), 6)
+            ]
+        );
+        assert_eq!(
+            probe_sites(r#"crate::liva_cov_hit("C:\\liva\\a.liva", 3);"#),
+            vec![("C:\\liva\\a.liva".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn test_merge_hits_and_render_lcov() {
+        let mut report = CoverageReport::new();
+        report.add_sites(GENERATED);
+        report.add_hits("src/math.liva\t2\t3\n");
+        report.add_hits("src/math.liva\t2\t1\nmalformed line\n");
+
+        assert_eq!(report.totals(), (1, 2));
+        assert_eq!(
+            report.to_lcov(),
+            "TN:\nSF:src/math.liva\nDA:2,4\nDA:6,0\nLH:1\nLF:2\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn test_html_marks_hit_and_missed_lines() {
+        let mut report = CoverageReport::new();
+        report.add_sites(GENERATED);
+        report.add_hits("src/math.liva\t2\t1\n");
+
+        let html = report.to_html(|_| Some("add(a, b) {\n  return a + b\n}\n".to_string()));
+        assert!(html.contains("1/2 lines (50.0%)"));
+        assert!(html.contains("<div class=\"hit\"><span class=\"ln\">2</span>"));
+        assert!(!html.contains("class=\"miss\"><span class=\"ln\">2</span>"));
+    }
+}
@@ -0,0 +1,482 @@
+//! Debug Adapter Protocol server for `livac dap`.
+//!
+//! The adapter sits between the editor and a native debug adapter
+//! (`lldb-dap` by default, override with `LIVA_DAP_BACKEND`) and speaks DAP on
+//! both sides. It handles the Liva-specific parts and forwards everything else
+//! unchanged:
+//!
+//! - `launch` with a `.liva` program compiles it with debug line markers,
+//!   runs `cargo build`, and launches the resulting binary instead.
+//! - `setBreakpoints` on a `.liva` source is rewritten to the generated Rust
+//!   file and lines via the [`LineMap`], and the reply is mapped back.
+//! - `stackTrace` replies and `breakpoint` events report Liva files and lines.
+
+use crate::debuginfo::{LineMap, LINE_MAP_FILE};
+use crate::CompilerOptions;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Debug adapters tried in order when `LIVA_DAP_BACKEND` is not set
+const DEFAULT_BACKENDS: &[&str] = &["lldb-dap", "lldb-vscode"];
+
+/// Read one `Content-Length`-framed DAP message; `None` at end of input
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(len) = header.strip_prefix("Content-Length:") {
+            content_length = len.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write one DAP message with its `Content-Length` header
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Breakpoints of one forwarded `setBreakpoints` request
+#[derive(Debug, Clone)]
+struct PendingBreakpoints {
+    /// The `.liva` source the client asked about
+    source: Value,
+    /// Per requested breakpoint: the Liva line it resolved to, or `None` when
+    /// no statement starts on or after it (not forwarded to the backend)
+    resolved: Vec<Option<u32>>,
+}
+
+/// Rewrites DAP traffic between Liva and generated Rust locations
+#[derive(Debug, Default)]
+pub struct Translator {
+    map: LineMap,
+    pending: HashMap<i64, PendingBreakpoints>,
+}
+
+impl Translator {
+    pub fn new(map: LineMap) -> Self {
+        Self {
+            map,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn set_line_map(&mut self, map: LineMap) {
+        self.map = map;
+    }
+
+    /// Rewrite a client request before it is forwarded to the backend
+    pub fn translate_request(&mut self, message: &mut Value) {
+        if message["command"] != "setBreakpoints" {
+            return;
+        }
+        let Some(liva_path) = message["arguments"]["source"]["path"].as_str() else {
+            return;
+        };
+        if !liva_path.ends_with(".liva") {
+            return;
+        }
+        let liva_path = liva_path.to_string();
+        let source = message["arguments"]["source"].clone();
+
+        let requested = message["arguments"]["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let mut resolved = Vec::with_capacity(requested.len());
+        let mut forwarded = Vec::new();
+        let mut rust_file = None;
+        for mut bp in requested {
+            let line = bp["line"].as_u64().unwrap_or(0) as u32;
+            match self.map.liva_to_rust(&liva_path, line) {
+                Some(entry) => {
+                    resolved.push(Some(entry.liva_line));
+                    bp["line"] = json!(entry.rust_line);
+                    forwarded.push(bp);
+                    rust_file = Some(entry.rust_file.clone());
+                }
+                None => resolved.push(None),
+            }
+        }
+
+        let args = &mut message["arguments"];
+        if let Some(rust_file) = rust_file {
+            args["source"] = json!({
+                "name": file_name(&rust_file),
+                "path": rust_file,
+            });
+        }
+        args["breakpoints"] = Value::Array(forwarded);
+        if let Some(obj) = args.as_object_mut() {
+            // Deprecated parallel array; superseded by the rewritten breakpoints
+            obj.remove("lines");
+        }
+
+        if let Some(seq) = message["seq"].as_i64() {
+            self.pending
+                .insert(seq, PendingBreakpoints { source, resolved });
+        }
+    }
+
+    /// Rewrite a backend response or event before it reaches the client
+    pub fn translate_from_backend(&mut self, message: &mut Value) {
+        match (message["type"].as_str(), message["command"].as_str()) {
+            (Some("response"), Some("setBreakpoints")) => self.translate_breakpoint_reply(message),
+            (Some("response"), Some("stackTrace")) => {
+                if let Some(frames) = message["body"]["stackFrames"].as_array_mut() {
+                    for frame in frames {
+                        self.to_liva_location(frame);
+                    }
+                }
+            }
+            (Some("event"), _) if message["event"] == "breakpoint" => {
+                self.to_liva_location(&mut message["body"]["breakpoint"]);
+            }
+            _ => {}
+        }
+    }
+
+    fn translate_breakpoint_reply(&mut self, message: &mut Value) {
+        let Some(pending) = message["request_seq"]
+            .as_i64()
+            .and_then(|seq| self.pending.remove(&seq))
+        else {
+            return;
+        };
+
+        let mut from_backend = message["body"]["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter();
+        let breakpoints: Vec<Value> = pending
+            .resolved
+            .iter()
+            .map(|resolved| match resolved {
+                Some(line) => {
+                    let mut bp = from_backend.next().unwrap_or_else(|| json!({}));
+                    bp["source"] = pending.source.clone();
+                    bp["line"] = json!(line);
+                    bp
+                }
+                None => json!({
+                    "verified": false,
+                    "message": "No Liva statement at or after this line",
+                }),
+            })
+            .collect();
+        message["body"]["breakpoints"] = Value::Array(breakpoints);
+    }
+
+    /// Replace a `source` + `line` pair in generated Rust with its Liva origin
+    fn to_liva_location(&self, located: &mut Value) {
+        let (Some(path), Some(line)) =
+            (located["source"]["path"].as_str(), located["line"].as_u64())
+        else {
+            return;
+        };
+        if let Some(entry) = self.map.rust_to_liva(path, line as u32) {
+            located["source"] = json!({
+                "name": file_name(&entry.liva_file),
+                "path": entry.liva_file,
+            });
+            located["line"] = json!(entry.liva_line);
+        }
+    }
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Compile `program` with debug markers into `<dir>/.liva_debug_build`, build it
+/// with cargo, save the line map, and return the binary path and map
+pub fn build_for_debugging(program: &Path) -> Result<(PathBuf, LineMap), String> {
+    let program = program
+        .canonicalize()
+        .map_err(|e| format!("Cannot open {}: {}", program.display(), e))?;
+    let output_dir = program
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(".liva_debug_build");
+
+    let options = CompilerOptions {
+        input: program.clone(),
+        output: Some(output_dir.clone()),
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: true,
+        inline_threshold: crate::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: crate::BuildProfile::Dev,
+        backend: crate::backend::BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let result = crate::compile_file(&options).map_err(|e| e.to_string())?;
+
+    let output = Command::new("cargo")
+        .arg("build")
+        .current_dir(&output_dir)
+        .output()
+        .map_err(|e| format!("Failed to run cargo build: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo build failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let map = LineMap::from_build(
+        &output_dir,
+        result.rust_code.as_deref().unwrap_or_default(),
+        result.module_files.as_ref(),
+    );
+    if let Ok(json) = map.to_json() {
+        let _ = std::fs::write(output_dir.join(LINE_MAP_FILE), json);
+    }
+
+    let binary = output_dir
+        .join("target")
+        .join("debug")
+        .join(&result.metadata.crate_name);
+    Ok((binary, map))
+}
+
+/// Error response for `request`, in the shape DAP clients display
+fn error_response(request: &Value, message: &str) -> Value {
+    json!({
+        "seq": 0,
+        "type": "response",
+        "request_seq": request["seq"],
+        "command": request["command"],
+        "success": false,
+        "message": message,
+    })
+}
+
+/// Run the adapter on stdin/stdout until the client disconnects
+pub fn run_stdio() -> io::Result<()> {
+    let client_out = Arc::new(Mutex::new(io::stdout()));
+    let mut client_in = BufReader::new(io::stdin());
+
+    let backend_cmds: Vec<String> = match std::env::var("LIVA_DAP_BACKEND") {
+        Ok(cmd) => vec![cmd],
+        Err(_) => DEFAULT_BACKENDS.iter().map(|s| s.to_string()).collect(),
+    };
+    let backend = backend_cmds.iter().find_map(|cmd| {
+        Command::new(cmd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()
+    });
+
+    let Some(mut backend) = backend else {
+        // Without a native debugger, answer every request with an explanation
+        let message = format!(
+            "No debug backend found (tried {}). Install lldb-dap or set LIVA_DAP_BACKEND.",
+            backend_cmds.join(", ")
+        );
+        while let Some(request) = read_message(&mut client_in)? {
+            let mut out = client_out.lock().unwrap_or_else(|e| e.into_inner());
+            write_message(&mut *out, &error_response(&request, &message))?;
+            if request["command"] == "disconnect" {
+                break;
+            }
+        }
+        return Ok(());
+    };
+
+    let mut backend_in = backend.stdin.take().expect("backend stdin is piped");
+    let backend_out = backend.stdout.take().expect("backend stdout is piped");
+    let translator = Arc::new(RwLock::new(Translator::default()));
+
+    // Backend -> client
+    let pump = {
+        let translator = Arc::clone(&translator);
+        let client_out = Arc::clone(&client_out);
+        std::thread::spawn(move || -> io::Result<()> {
+            let mut reader = BufReader::new(backend_out);
+            while let Some(mut message) = read_message(&mut reader)? {
+                translator
+                    .write()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .translate_from_backend(&mut message);
+                let mut out = client_out.lock().unwrap_or_else(|e| e.into_inner());
+                write_message(&mut *out, &message)?;
+            }
+            Ok(())
+        })
+    };
+
+    // Client -> backend
+    while let Some(mut message) = read_message(&mut client_in)? {
+        let liva_program = message["arguments"]["program"]
+            .as_str()
+            .filter(|p| message["command"] == "launch" && p.ends_with(".liva"))
+            .map(PathBuf::from);
+
+        if let Some(program) = liva_program {
+            match build_for_debugging(&program) {
+                Ok((binary, map)) => {
+                    translator
+                        .write()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .set_line_map(map);
+                    let args = &mut message["arguments"];
+                    args["program"] = json!(binary.display().to_string());
+                    if args["cwd"].is_null() {
+                        if let Some(dir) = program.parent() {
+                            args["cwd"] = json!(dir.display().to_string());
+                        }
+                    }
+                }
+                Err(e) => {
+                    let mut out = client_out.lock().unwrap_or_else(|e| e.into_inner());
+                    write_message(&mut *out, &error_response(&message, &e))?;
+                    continue;
+                }
+            }
+        }
+
+        translator
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .translate_request(&mut message);
+        write_message(&mut backend_in, &message)?;
+    }
+
+    drop(backend_in);
+    let _ = backend.wait();
+    pump.join().unwrap_or(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debuginfo::line_marker;
+
+    fn translator() -> Translator {
+        let code = format!(
+            "fn main() {{\n    {}\n    let a = 1;\n    {}\n    println!(\"{{}}\", a);\n}}\n",
+            line_marker("/p/main.liva", 2),
+            line_marker("/p/main.liva", 3),
+        );
+        let mut map = LineMap::new();
+        map.add_generated("/p/build/src/main.rs", &code);
+        Translator::new(map)
+    }
+
+    #[test]
+    fn test_message_framing_round_trip() {
+        let message = json!({"seq": 1, "type": "request", "command": "initialize"});
+        let mut buf = Vec::new();
+        write_message(&mut buf, &message).unwrap();
+        assert!(buf.starts_with(b"Content-Length: "));
+
+        let mut reader = io::Cursor::new(buf);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_breakpoints_round_trip_through_generated_rust() {
+        let mut t = translator();
+        let mut request = json!({
+            "seq": 7,
+            "type": "request",
+            "command": "setBreakpoints",
+            "arguments": {
+                "source": {"name": "main.liva", "path": "/p/main.liva"},
+                "breakpoints": [{"line": 3}, {"line": 9}],
+            }
+        });
+        t.translate_request(&mut request);
+        assert_eq!(
+            request["arguments"]["source"]["path"],
+            "/p/build/src/main.rs"
+        );
+        assert_eq!(request["arguments"]["breakpoints"], json!([{"line": 5}]));
+
+        let mut response = json!({
+            "seq": 3,
+            "type": "response",
+            "request_seq": 7,
+            "command": "setBreakpoints",
+            "success": true,
+            "body": {"breakpoints": [{"id": 1, "verified": true, "line": 5}]},
+        });
+        t.translate_from_backend(&mut response);
+        let bps = response["body"]["breakpoints"].as_array().unwrap();
+        assert_eq!(bps.len(), 2);
+        assert_eq!(bps[0]["line"], 3);
+        assert_eq!(bps[0]["source"]["path"], "/p/main.liva");
+        assert_eq!(bps[1]["verified"], false);
+    }
+
+    #[test]
+    fn test_stack_frames_report_liva_lines() {
+        let mut t = translator();
+        let mut response = json!({
+            "type": "response",
+            "command": "stackTrace",
+            "body": {"stackFrames": [
+                {"id": 1, "name": "main", "line": 5, "source": {"path": "/p/build/src/main.rs"}},
+                {"id": 2, "name": "lang_start", "line": 40, "source": {"path": "/rustc/rt.rs"}},
+            ]},
+        });
+        t.translate_from_backend(&mut response);
+        let frames = &response["body"]["stackFrames"];
+        assert_eq!(frames[0]["source"]["path"], "/p/main.liva");
+        assert_eq!(frames[0]["line"], 3);
+        assert_eq!(frames[1]["source"]["path"], "/rustc/rt.rs");
+    }
+
+    #[test]
+    fn test_non_liva_requests_pass_through() {
+        let mut t = translator();
+        let original = json!({
+            "seq": 2,
+            "type": "request",
+            "command": "setBreakpoints",
+            "arguments": {"source": {"path": "/p/lib.rs"}, "breakpoints": [{"line": 1}]},
+        });
+        let mut request = original.clone();
+        t.translate_request(&mut request);
+        assert_eq!(request, original);
+    }
+}
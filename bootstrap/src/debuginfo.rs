@@ -0,0 +1,305 @@
+//! Liva ↔ Rust line mapping for debuggers.
+//!
+//! With `CompilerOptions::debug` set, codegen writes a `// @liva <line> "<file>"`
+//! marker on the line before every statement it generates. [`LineMap`] reads
+//! those markers back out of the generated files so the debug adapter
+//! (`livac dap`) can place breakpoints on `.liva` lines and report stack
+//! frames in Liva terms. `livac build --debug` saves the map as
+//! `liva-debug.json` next to the generated crate.
+//!
+//! With `CompilerOptions::source_map` codegen writes the same markers and
+//! [`LineMap::strip_generated`] takes them back out, leaving a map of the
+//! clean code. `livac build` saves it as `liva.map.json` and uses
+//! [`LineMap::translate_diagnostics`] to point rustc errors at `.liva` lines.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const MARKER_PREFIX: &str = "// @liva ";
+
+/// File name of the serialized line map written by `livac build --debug`
+pub const LINE_MAP_FILE: &str = "liva-debug.json";
+
+/// File name of the source map `livac build` writes next to the crate
+pub const SOURCE_MAP_FILE: &str = "liva.map.json";
+
+/// Marker comment placed before the Rust code of a Liva statement
+pub fn line_marker(liva_file: &str, line: u32) -> String {
+    let file = serde_json::to_string(liva_file).unwrap_or_else(|_| "\"\"".to_string());
+    format!("{}{} {}", MARKER_PREFIX, line, file)
+}
+
+/// One Liva statement and the generated Rust line where its code starts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineEntry {
+    pub liva_file: String,
+    pub liva_line: u32,
+    pub rust_file: String,
+    pub rust_line: u32,
+}
+
+/// Line table for a generated crate
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LineMap {
+    pub entries: Vec<LineEntry>,
+}
+
+impl LineMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the markers of one generated Rust file (`rust_file` is the path
+    /// the debugger will see, e.g. `<build>/src/main.rs`)
+    pub fn add_generated(&mut self, rust_file: &str, code: &str) {
+        for (idx, text) in code.lines().enumerate() {
+            let Some((liva_file, liva_line)) = parse_marker(text) else {
+                continue;
+            };
+            self.entries.push(LineEntry {
+                liva_file,
+                liva_line,
+                rust_file: rust_file.to_string(),
+                // 1-based line of the statement, right after the marker
+                rust_line: idx as u32 + 2,
+            });
+        }
+    }
+
+    /// [`add_generated`](Self::add_generated) for code whose markers should
+    /// not stay in it: returns `code` without the marker lines, and the
+    /// entries point at the lines of that returned code
+    pub fn strip_generated(&mut self, rust_file: &str, code: &str) -> String {
+        let mut stripped = String::with_capacity(code.len());
+        let mut rust_line = 1;
+        for text in code.split_inclusive('\n') {
+            match parse_marker(text) {
+                Some((liva_file, liva_line)) => self.entries.push(LineEntry {
+                    liva_file,
+                    liva_line,
+                    rust_file: rust_file.to_string(),
+                    rust_line,
+                }),
+                None => {
+                    stripped.push_str(text);
+                    rust_line += 1;
+                }
+            }
+        }
+        stripped
+    }
+
+    /// Line map for a crate generated into `output_dir` (`src/main.rs` plus any
+    /// module files, keyed by path relative to `output_dir`)
+    pub fn from_build(
+        output_dir: &Path,
+        main_rs: &str,
+        module_files: Option<&HashMap<PathBuf, String>>,
+    ) -> Self {
+        let root = output_dir
+            .canonicalize()
+            .unwrap_or_else(|_| output_dir.to_path_buf());
+        let mut map = Self::new();
+        map.add_generated(
+            &root.join("src").join("main.rs").display().to_string(),
+            main_rs,
+        );
+        for (rel_path, code) in module_files.into_iter().flatten() {
+            map.add_generated(&root.join(rel_path).display().to_string(), code);
+        }
+        map
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Where a breakpoint on `liva_file:line` should go: the first statement
+    /// starting on or after `line`. Returns the entry so callers can report the
+    /// Liva line the breakpoint was moved to.
+    pub fn liva_to_rust(&self, liva_file: &str, line: u32) -> Option<&LineEntry> {
+        self.entries
+            .iter()
+            .filter(|e| same_file(&e.liva_file, liva_file) && e.liva_line >= line)
+            .min_by_key(|e| (e.liva_line, e.rust_line))
+    }
+
+    /// The Liva statement containing generated `rust_file:line`: the closest
+    /// marker at or above it in the same file
+    pub fn rust_to_liva(&self, rust_file: &str, line: u32) -> Option<&LineEntry> {
+        self.entries
+            .iter()
+            .filter(|e| same_file(&e.rust_file, rust_file) && e.rust_line <= line)
+            .max_by_key(|e| e.rust_line)
+    }
+
+    /// rustc `output` with each ` --> src/main.rs:12:5` location of a mapped
+    /// file rewritten as ` --> main.liva:4 (src/main.rs:12:5)`: the Liva
+    /// statement the line belongs to, with the Rust location kept after it
+    pub fn translate_diagnostics(&self, output: &str) -> String {
+        let mut rust_files: Vec<&str> = self.entries.iter().map(|e| e.rust_file.as_str()).collect();
+        rust_files.sort_unstable();
+        rust_files.dedup();
+        output
+            .split_inclusive('\n')
+            .map(|line| self.translate_location(line, &rust_files))
+            .collect()
+    }
+
+    fn translate_location(&self, line: &str, rust_files: &[&str]) -> String {
+        if !line.contains("--> ") && !line.contains("::: ") {
+            return line.to_string();
+        }
+        for rust_file in rust_files {
+            let Some(start) = line.find(&format!("{}:", rust_file)) else {
+                continue;
+            };
+            let after = &line[start + rust_file.len() + 1..];
+            let digits = leading_digits(after);
+            let Some(entry) = after[..digits]
+                .parse()
+                .ok()
+                .and_then(|rust_line| self.rust_to_liva(rust_file, rust_line))
+            else {
+                continue;
+            };
+            // The `:column` after the line, if there is one
+            let column = after[digits..]
+                .strip_prefix(':')
+                .map_or(0, |rest| 1 + leading_digits(rest));
+            let end = start + rust_file.len() + 1 + digits + column;
+            return format!(
+                "{}{}:{} ({}){}",
+                &line[..start],
+                entry.liva_file,
+                entry.liva_line,
+                &line[start..end],
+                &line[end..]
+            );
+        }
+        line.to_string()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+fn leading_digits(text: &str) -> usize {
+    text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len()
+}
+
+/// The Liva file and line of a marker line
+fn parse_marker(text: &str) -> Option<(String, u32)> {
+    let rest = text.trim().strip_prefix(MARKER_PREFIX)?;
+    let (line, file) = rest.split_once(' ')?;
+    Some((serde_json::from_str(file).ok()?, line.parse().ok()?))
+}
+
+fn same_file(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    match (Path::new(a).canonicalize(), Path::new(b).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_map() -> LineMap {
+        let code = format!(
+            "fn add(a: i32, b: i32) -> i32 {{\n    {}\n    let r = a + b;\n    {}\n    return r;\n}}\n",
+            line_marker("/p/math.liva", 2),
+            line_marker("/p/math.liva", 5),
+        );
+        let mut map = LineMap::new();
+        map.add_generated("/p/build/src/main.rs", &code);
+        map
+    }
+
+    #[test]
+    fn test_markers_map_to_following_line() {
+        let map = sample_map();
+        assert_eq!(map.entries.len(), 2);
+        assert_eq!(map.entries[0].liva_line, 2);
+        assert_eq!(map.entries[0].rust_line, 3);
+        assert_eq!(map.entries[1].rust_line, 5);
+    }
+
+    #[test]
+    fn test_breakpoints_move_to_next_statement() {
+        let map = sample_map();
+        let entry = map.liva_to_rust("/p/math.liva", 3).unwrap();
+        assert_eq!((entry.liva_line, entry.rust_line), (5, 5));
+        assert!(map.liva_to_rust("/p/math.liva", 6).is_none());
+        assert!(map.liva_to_rust("/p/other.liva", 2).is_none());
+    }
+
+    #[test]
+    fn test_rust_lines_resolve_to_enclosing_statement() {
+        let map = sample_map();
+        assert_eq!(
+            map.rust_to_liva("/p/build/src/main.rs", 4)
+                .unwrap()
+                .liva_line,
+            2
+        );
+        assert!(map.rust_to_liva("/p/build/src/main.rs", 1).is_none());
+    }
+
+    #[test]
+    fn test_stripped_markers_map_to_remaining_lines() {
+        let code = format!(
+            "fn main() {{\n    {}\n    let x = 1;\n    {}\n    print(x);\n}}\n",
+            line_marker("main.liva", 2),
+            line_marker("main.liva", 3),
+        );
+        let mut map = LineMap::new();
+        let stripped = map.strip_generated("src/main.rs", &code);
+        assert_eq!(stripped, "fn main() {\n    let x = 1;\n    print(x);\n}\n");
+        let lines: Vec<_> = map
+            .entries
+            .iter()
+            .map(|e| (e.liva_line, e.rust_line))
+            .collect();
+        assert_eq!(lines, vec![(2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn test_rustc_locations_point_at_liva_lines() {
+        let mut map = LineMap::new();
+        map.strip_generated(
+            "src/main.rs",
+            &format!(
+                "fn main() {{\n    {}\n    let x: i32 = \"a\";\n}}\n",
+                line_marker("main.liva", 2)
+            ),
+        );
+        let rustc = "error[E0308]: mismatched types\n --> src/main.rs:2:18\n  |\n";
+        assert_eq!(
+            map.translate_diagnostics(rustc),
+            "error[E0308]: mismatched types\n --> main.liva:2 (src/main.rs:2:18)\n  |\n"
+        );
+        // Lines before any statement, and other files, stay as they are
+        let prelude = " --> src/main.rs:1:1\n --> src/lib.rs:2:1\n";
+        assert_eq!(map.translate_diagnostics(prelude), prelude);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let map = sample_map();
+        let json = map.to_json().unwrap();
+        assert!(json.contains("\"livaLine\": 2"));
+        assert_eq!(LineMap::from_json(&json).unwrap(), map);
+    }
+}
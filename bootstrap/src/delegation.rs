@@ -0,0 +1,310 @@
+//! Delegation.
+//!
+//! `delegate engine` in a class body forwards every public method of the
+//! field's class to it; `delegate engine { start, stop }` forwards only the
+//! listed ones. Each forwarded method becomes an ordinary method of the
+//! outer class whose body calls the field's method with the same arguments,
+//! so the rest of the compiler never sees the `delegate` line.
+//!
+//! A method the class declares itself, with the same name and parameter
+//! count, wins over a forwarded one. Two delegates forwarding the same
+//! method are an error, as is a delegate forwarding to a class that
+//! delegates back to it.
+//!
+//! Semantic analysis runs this pass over the program at hand; a
+//! multi-module build runs it across modules first (see
+//! `ModuleResolver::expand_delegates`). Expanding a class clears its
+//! `delegates`, so running the pass twice changes nothing.
+
+use crate::ast::*;
+use crate::error::{CompilerError, SemanticErrorInfo};
+use crate::error_codes::*;
+use crate::Result;
+use std::collections::HashMap;
+
+/// Add the forwarding methods of every class in `programs` that delegates
+pub fn expand(programs: &mut [&mut Program]) -> Result<()> {
+    let mut classes: HashMap<String, ClassDecl> = programs
+        .iter()
+        .flat_map(|program| &program.items)
+        .filter_map(|item| match item {
+            TopLevel::Class(class) => Some((class.name.clone(), class.clone())),
+            _ => None,
+        })
+        .collect();
+
+    let delegating: Vec<String> = classes
+        .values()
+        .filter(|class| !class.delegates.is_empty())
+        .map(|class| class.name.clone())
+        .collect();
+    if delegating.is_empty() {
+        return Ok(());
+    }
+    for name in &delegating {
+        expand_class(name, &mut classes, &mut Vec::new())?;
+    }
+
+    for program in programs.iter_mut() {
+        for item in &mut program.items {
+            if let TopLevel::Class(class) = item {
+                if !class.delegates.is_empty() {
+                    if let Some(expanded) = classes.get(&class.name) {
+                        class.members = expanded.members.clone();
+                        class.delegates.clear();
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Expand `name`, after the classes it delegates to so their forwarded
+/// methods are forwarded too. `visiting` holds the classes being expanded.
+fn expand_class(
+    name: &str,
+    classes: &mut HashMap<String, ClassDecl>,
+    visiting: &mut Vec<String>,
+) -> Result<()> {
+    let Some(class) = classes.get(name) else {
+        return Ok(());
+    };
+    if class.delegates.is_empty() {
+        return Ok(());
+    }
+    if visiting.iter().any(|other| other == name) {
+        visiting.push(name.to_string());
+        return Err(invalid_delegate(
+            &format!("Classes delegate to each other: {}", visiting.join(" -> ")),
+            "Declare the methods in one of the classes instead of delegating both ways",
+        ));
+    }
+
+    let mut targets = Vec::new();
+    for delegate in &class.delegates {
+        targets.push(target_class(class, delegate, classes)?);
+    }
+    visiting.push(name.to_string());
+    for target in &targets {
+        expand_class(target, classes, visiting)?;
+    }
+    visiting.pop();
+
+    let class = &classes[name];
+    // (name, parameter count) of each forwarded method -> field it forwards to
+    let mut forwarded: HashMap<(String, usize), &str> = HashMap::new();
+    let mut methods = Vec::new();
+    for (delegate, target) in class.delegates.iter().zip(&targets) {
+        for method in delegated_methods(delegate, &classes[target.as_str()])? {
+            if declares_method(&class.members, method) {
+                continue;
+            }
+            let key = (method.name.clone(), method.params.len());
+            if let Some(other) = forwarded.insert(key, &delegate.field) {
+                return Err(conflict(class, method, other, &delegate.field));
+            }
+            methods.push(forwarding_method(&delegate.field, method));
+        }
+    }
+
+    let class = classes.get_mut(name).expect("class was looked up above");
+    class
+        .members
+        .extend(methods.into_iter().map(Member::Method));
+    class.delegates.clear();
+    Ok(())
+}
+
+/// Name of the class of the field `delegate` forwards to
+fn target_class(
+    class: &ClassDecl,
+    delegate: &DelegateDecl,
+    classes: &HashMap<String, ClassDecl>,
+) -> Result<String> {
+    let field = class.members.iter().find_map(|member| match member {
+        Member::Field(field) if field.name == delegate.field => Some(field),
+        _ => None,
+    });
+    let Some(field) = field else {
+        return Err(invalid_delegate(
+            &format!(
+                "Class '{}' has no field '{}' to delegate to",
+                class.name, delegate.field
+            ),
+            &format!(
+                "Declare the field first, e.g. {}: SomeClass",
+                delegate.field
+            ),
+        ));
+    };
+    match &field.type_ref {
+        Some(TypeRef::Simple(name)) if !field.is_optional && classes.contains_key(name) => {
+            Ok(name.clone())
+        }
+        _ => Err(invalid_delegate(
+            &format!(
+                "Field '{}' of class '{}' isn't of a class type",
+                delegate.field, class.name
+            ),
+            "Only fields annotated with a (non-optional) class type can be delegated to",
+        )),
+    }
+}
+
+/// The methods of `target` that `delegate` forwards
+fn delegated_methods<'a>(
+    delegate: &DelegateDecl,
+    target: &'a ClassDecl,
+) -> Result<Vec<&'a MethodDecl>> {
+    let public: Vec<&MethodDecl> = target
+        .members
+        .iter()
+        .filter_map(|member| match member {
+            Member::Method(method)
+                if method.name != "constructor" && method.visibility == Visibility::Public =>
+            {
+                Some(method)
+            }
+            _ => None,
+        })
+        .collect();
+    if delegate.methods.is_empty() {
+        return Ok(public);
+    }
+
+    for name in &delegate.methods {
+        if !public.iter().any(|method| &method.name == name) {
+            return Err(invalid_delegate(
+                &format!(
+                    "Class '{}' has no public method '{}' to delegate to",
+                    target.name, name
+                ),
+                &format!(
+                    "Remove '{}' from 'delegate {} {{ ... }}'",
+                    name, delegate.field
+                ),
+            ));
+        }
+    }
+    Ok(public
+        .into_iter()
+        .filter(|method| delegate.methods.contains(&method.name))
+        .collect())
+}
+
+/// `method(params) => this.field.method(params)`, going through `or fail`
+/// when the forwarded method can fail
+fn forwarding_method(field: &str, method: &MethodDecl) -> MethodDecl {
+    let params: Vec<Param> = method
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| Param {
+            pattern: match &param.pattern {
+                BindingPattern::Identifier(name) => BindingPattern::Identifier(name.clone()),
+                _ => BindingPattern::Identifier(format!("arg{}", i)),
+            },
+            type_ref: param.type_ref.clone(),
+            default: param.default.clone(),
+        })
+        .collect();
+    let call = Expr::MethodCall(MethodCallExpr {
+        object: Box::new(Expr::Member {
+            object: Box::new(Expr::Identifier("this".to_string())),
+            property: field.to_string(),
+        }),
+        method: method.name.clone(),
+        args: params
+            .iter()
+            .filter_map(|param| param.name())
+            .map(|name| Expr::Identifier(name.to_string()))
+            .collect(),
+        adapter: ArrayAdapter::Seq,
+        adapter_options: AdapterOptions::default(),
+        comprehension: None,
+    });
+
+    let (body, expr_body) = if method.contains_fail {
+        let value = "value".to_string();
+        let stmts = vec![
+            Stmt::VarDecl(VarDecl {
+                bindings: vec![VarBinding {
+                    pattern: BindingPattern::Identifier(value.clone()),
+                    type_ref: None,
+                    span: None,
+                }],
+                init: call,
+                is_fallible: true,
+                or_fail_msg: Some(Box::new(Expr::Literal(Literal::String(String::new())))),
+                or_value: None,
+                or_fail_line: 0,
+                combinator: None,
+                span: None,
+            }),
+            Stmt::Return(ReturnStmt {
+                expr: Some(Expr::Identifier(value)),
+            }),
+        ];
+        (stmts, None)
+    } else {
+        let stmts = vec![Stmt::Return(ReturnStmt {
+            expr: Some(call.clone()),
+        })];
+        (stmts, Some(call))
+    };
+
+    MethodDecl {
+        name: method.name.clone(),
+        visibility: Visibility::Public,
+        type_params: method.type_params.clone(),
+        params,
+        return_type: method.return_type.clone(),
+        body: Some(BlockStmt {
+            stmts: body,
+            stmt_lines: Vec::new(),
+            span: None,
+        }),
+        expr_body,
+        is_async_inferred: false,
+        contains_fail: method.contains_fail,
+        is_abstract: false,
+        doc: method.doc.clone(),
+        span: None,
+    }
+}
+
+fn declares_method(members: &[Member], method: &MethodDecl) -> bool {
+    members.iter().any(|other| {
+        matches!(other, Member::Method(other)
+            if other.name == method.name && other.params.len() == method.params.len())
+    })
+}
+
+fn invalid_delegate(message: &str, help: &str) -> CompilerError {
+    CompilerError::SemanticError(Box::new(
+        SemanticErrorInfo::new(E0018_INVALID_DELEGATE, "Invalid delegate", message).with_help(help),
+    ))
+}
+
+fn conflict(class: &ClassDecl, method: &MethodDecl, first: &str, second: &str) -> CompilerError {
+    CompilerError::SemanticError(Box::new(
+        SemanticErrorInfo::new(
+            E0019_DELEGATE_CONFLICT,
+            "Conflicting delegates",
+            &format!(
+                "Class '{}' delegates '{}' with {} parameter{} to both '{}' and '{}'",
+                class.name,
+                method.name,
+                method.params.len(),
+                if method.params.len() == 1 { "" } else { "s" },
+                first,
+                second
+            ),
+        )
+        .with_help(&format!(
+            "List the methods to forward, e.g. delegate {} {{ ... }}, or declare '{}' in '{}'",
+            second, method.name, class.name
+        )),
+    ))
+}
@@ -1,5 +1,7 @@
 use crate::ast::*;
 use crate::error::Result;
+use crate::lexer::Token;
+use crate::span::Span;
 use serde::Serialize;
 use std::collections::BTreeSet;
 
@@ -26,9 +28,48 @@ pub struct DesugarContext {
     pub has_crypto: bool,                  // true if Crypto.* is used (sha2, md5, base64 crates)
     pub has_server: bool,                  // true if Server.create() is used (axum crate)
     pub has_db: bool,                      // true if DB.* is used (rusqlite crate)
+    pub has_key_sort: bool,                // true if sortBy/minBy/maxBy is used (liva_rt::SortKey)
     pub async_functions: BTreeSet<String>, // Functions that are async (BTreeSet for deterministic order)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimeDecl>, // program-level `runtime { ... }` configuration
     #[serde(skip)]
     pub source_filename: String, // Source filename for error traces
+    #[serde(skip)]
+    pub crate_name: String, // Cargo package name; empty means `liva_project`
+    #[serde(skip)]
+    pub deterministic: bool, // seeded RNG + single-threaded runtime (`livac test --deterministic`)
+    #[serde(skip)]
+    pub coverage: bool,  // per-statement hit counters (`livac test --coverage`)
+    #[serde(skip)]
+    pub debug: bool,     // Liva line markers + #[inline(never)] for the debug adapter
+    #[serde(skip)]
+    pub explain_codegen: bool, // comment codegen's guesses (`--explain-codegen`)
+    #[serde(skip)]
+    pub source_map: bool, // Liva line markers for the rustc source map, stripped again by lib.rs
+    #[serde(skip)]
+    pub inline: crate::inlining::InlinePlan, // per-module, per-function #[inline] attributes
+    #[serde(skip)]
+    pub numeric_coercion: crate::NumericCoercion, // JSON fields used as numbers (`--numeric-coercion`)
+    #[serde(skip)]
+    pub identifier_mapping: crate::IdentifierMapping, // Rust spelling of Liva names (`--identifier-mapping`)
+    #[serde(skip)]
+    pub symbols: crate::symbols::SymbolTable, // classes of every module in the build
+    #[serde(skip)]
+    pub has_mocks: bool, // true if a test stubs a builtin with `mock`
+    #[serde(skip)]
+    pub has_test_hooks: bool, // true if tests run `before`/`after` or beforeEach/afterEach hooks
+    #[serde(skip)]
+    pub has_task_scope: bool, // true if a `scope { }` block is used (liva_rt::TaskScope)
+    #[serde(skip)]
+    pub has_power: bool, // true if `**` is used (liva_rt::pow)
+    #[serde(skip)]
+    pub has_floor_division: bool, // true if Math.floorDiv/floorMod is used
+    #[serde(skip)]
+    pub has_type_of: bool, // true if typeOf() is used (liva_rt::type_of)
+    #[serde(skip)]
+    pub has_char_code: bool, // true if charCode()/fromCharCode() is used
+    #[serde(skip)]
+    pub has_frozen: bool, // true if freeze() is used (liva_rt::Frozen)
 }
 
 impl DesugarContext {
@@ -46,8 +87,28 @@ impl DesugarContext {
             has_crypto: false,
             has_server: false,
             has_db: false,
+            has_key_sort: false,
+            has_mocks: false,
+            has_test_hooks: false,
+            has_task_scope: false,
+            has_power: false,
+            has_floor_division: false,
+            has_type_of: false,
+            has_char_code: false,
+            has_frozen: false,
             async_functions: BTreeSet::new(),
+            runtime: None,
             source_filename: String::new(),
+            crate_name: String::new(),
+            deterministic: false,
+            coverage: false,
+            debug: false,
+            explain_codegen: false,
+            source_map: false,
+            inline: crate::inlining::InlinePlan::new(),
+            numeric_coercion: crate::NumericCoercion::Coerce,
+            identifier_mapping: crate::IdentifierMapping::Snake,
+            symbols: crate::symbols::SymbolTable::default(),
         }
     }
 }
@@ -66,9 +127,12 @@ pub fn desugar(program: Program) -> Result<DesugarContext> {
             });
         }
 
-        // Check for async/parallel usage and rust blocks
-        check_concurrency(&item, &mut ctx);
+        if let TopLevel::Runtime(runtime) = item {
+            ctx.runtime = Some(runtime.clone());
+        }
     }
+    // Check for async/parallel usage, rust blocks and runtime helpers
+    note_runtime_use(&program, &mut ctx);
 
     // Add tokio if async is used
     if ctx.has_async {
@@ -80,218 +144,937 @@ pub fn desugar(program: Program) -> Result<DesugarContext> {
         });
     }
 
+    ctx.symbols = program.symbols;
     Ok(ctx)
 }
 
-fn check_concurrency(item: &TopLevel, ctx: &mut DesugarContext) {
-    match item {
-        TopLevel::Function(func) => {
-            if func.is_async_inferred {
+/// Record in `ctx` what `program` needs from the generated crate: async
+/// and parallel execution, the crates behind builtin namespaces, and the
+/// optional parts of `liva_rt`
+fn note_runtime_use(program: &Program, ctx: &mut DesugarContext) {
+    RuntimeUse { ctx }.visit_program(program);
+}
+
+/// Add the optional parts of `liva_rt` that `program` calls to `ctx`. The
+/// runtime is emitted once, in main.rs, so an imported module's calls need
+/// the entry module's runtime to carry them.
+pub fn note_runtime_helpers(program: &Program, ctx: &mut DesugarContext) {
+    let mut used = DesugarContext::new();
+    note_runtime_use(program, &mut used);
+    ctx.has_key_sort |= used.has_key_sort;
+    ctx.has_mocks |= used.has_mocks;
+    ctx.has_test_hooks |= used.has_test_hooks;
+    ctx.has_task_scope |= used.has_task_scope;
+    ctx.has_power |= used.has_power;
+    ctx.has_floor_division |= used.has_floor_division;
+    ctx.has_type_of |= used.has_type_of;
+    ctx.has_char_code |= used.has_char_code;
+    ctx.has_frozen |= used.has_frozen;
+}
+
+struct RuntimeUse<'a> {
+    ctx: &'a mut DesugarContext,
+}
+
+impl Visitor<'_> for RuntimeUse<'_> {
+    fn visit_item(&mut self, item: &TopLevel) {
+        let ctx = &mut *self.ctx;
+        match item {
+            TopLevel::Function(func) if func.is_async_inferred => {
                 ctx.has_async = true;
                 ctx.async_functions.insert(func.name.clone());
             }
-            if let Some(body) = &func.body {
-                check_block_concurrency_block(body, ctx);
+            TopLevel::Class(class) => {
+                for member in &class.members {
+                    if let Member::Method(method) = member {
+                        if method.is_async_inferred {
+                            ctx.has_async = true;
+                            // Track as ClassName.methodName for method calls
+                            ctx.async_functions
+                                .insert(format!("{}.{}", class.name, method.name));
+                        }
+                    }
+                }
+            }
+            TopLevel::TestHook(_) => ctx.has_test_hooks = true,
+            _ => {}
+        }
+        walk_item(self, item);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let ctx = &mut *self.ctx;
+        match stmt {
+            Stmt::For(for_stmt) if for_stmt.policy != DataParallelPolicy::Seq => {
+                ctx.has_parallel = true;
             }
-            if let Some(expr) = &func.expr_body {
-                check_expr_concurrency(expr, ctx);
+            Stmt::Scope(_) => {
+                ctx.has_async = true;
+                ctx.has_task_scope = true;
             }
+            Stmt::Mock(_) => ctx.has_mocks = true,
+            _ => {}
         }
-        TopLevel::Class(class) => {
-            for member in &class.members {
-                if let Member::Method(method) = member {
-                    if method.is_async_inferred {
-                        ctx.has_async = true;
-                        // Track as ClassName.methodName for method calls
-                        ctx.async_functions
-                            .insert(format!("{}.{}", class.name, method.name));
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        let ctx = &mut *self.ctx;
+        match expr {
+            Expr::Call(call) => {
+                match call.exec_policy {
+                    ExecPolicy::Async | ExecPolicy::TaskAsync => ctx.has_async = true,
+                    ExecPolicy::Par | ExecPolicy::TaskPar => ctx.has_parallel = true,
+                    ExecPolicy::Normal => {}
+                }
+                if let Expr::Identifier(name) = call.callee.as_ref() {
+                    match name.as_str() {
+                        "beforeEach" | "afterEach" => ctx.has_test_hooks = true,
+                        "typeOf" => ctx.has_type_of = true,
+                        "charCode" | "fromCharCode" => ctx.has_char_code = true,
+                        "freeze" => ctx.has_frozen = true,
+                        _ => {}
                     }
-                    if let Some(body) = &method.body {
-                        check_block_concurrency_block(body, ctx);
+                }
+            }
+            Expr::MethodCall(method_call) => {
+                if let Expr::Identifier(name) = method_call.object.as_ref() {
+                    match (name.as_str(), method_call.method.as_str()) {
+                        ("Math", "random") | ("Random", _) => ctx.has_random = true,
+                        ("Math", "floorDiv" | "floorMod") => ctx.has_floor_division = true,
+                        ("Log", _) => ctx.has_logging = true,
+                        ("Config", _) => ctx.has_config = true,
+                        ("Regex", _) => ctx.has_regex = true,
+                        ("Date", _) => ctx.has_date = true,
+                        ("Crypto", _) => ctx.has_crypto = true,
+                        ("Server", _) => {
+                            ctx.has_server = true;
+                            ctx.has_async = true;
+                        }
+                        ("DB", _) => ctx.has_db = true,
+                        _ => {}
                     }
-                    if let Some(expr) = &method.expr_body {
-                        check_expr_concurrency(expr, ctx);
+                }
+                if matches!(method_call.method.as_str(), "sortBy" | "minBy" | "maxBy") {
+                    ctx.has_key_sort = true;
+                }
+                // Parallel array adapters
+                if matches!(
+                    method_call.adapter,
+                    ArrayAdapter::Par | ArrayAdapter::ParVec
+                ) {
+                    ctx.has_parallel = true;
+                }
+            }
+            Expr::Binary { op: BinOp::Pow, .. } => ctx.has_power = true,
+            Expr::RustBlock { .. } => ctx.has_rust_blocks = true,
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Name of the constant that is `true` in dev builds and `false` in release
+pub const DEBUG_CONSTANT: &str = "DEBUG";
+
+/// Replaces `DEBUG` with its value for `profile` and drops the branches it
+/// rules out, so that `if DEBUG { Log.debug(...) }` leaves no code in a
+/// release binary. Only conditions that mention `DEBUG` are folded, and a
+/// program that declares its own top-level `DEBUG` is left as written.
+pub fn fold_profile_constants(program: &mut Program, profile: crate::BuildProfile) {
+    let declares_debug = program.items.iter().any(|item| match item {
+        TopLevel::ConstDecl(decl) => decl.name == DEBUG_CONSTANT,
+        TopLevel::Function(func) => func.name == DEBUG_CONSTANT,
+        _ => false,
+    });
+    if declares_debug {
+        return;
+    }
+    let debug = profile.is_debug();
+    for item in &mut program.items {
+        match item {
+            TopLevel::Function(func) => fold_function(func, debug),
+            TopLevel::Class(class) => {
+                for member in &mut class.members {
+                    match member {
+                        Member::Method(method) => fold_method(method, debug),
+                        Member::Field(field) => {
+                            if let Some(init) = &mut field.init {
+                                fold_expr(init, debug);
+                            }
+                        }
                     }
                 }
             }
+            TopLevel::ClassExtension(ext) => {
+                for method in &mut ext.methods {
+                    fold_method(method, debug);
+                }
+            }
+            TopLevel::Test(test) => fold_block(&mut test.body, debug),
+            TopLevel::Init(init) => fold_block(&mut init.body, debug),
+            TopLevel::TestHook(hook) => fold_block(&mut hook.body, debug),
+            TopLevel::ConstDecl(decl) => fold_expr(&mut decl.init, debug),
+            TopLevel::ExprStmt(expr) => fold_expr(expr, debug),
+            _ => {}
+        }
+    }
+}
+
+fn fold_function(func: &mut FunctionDecl, debug: bool) {
+    if let Some(body) = &mut func.body {
+        fold_block(body, debug);
+    }
+    if let Some(expr) = &mut func.expr_body {
+        fold_expr(expr, debug);
+    }
+}
+
+fn fold_method(method: &mut MethodDecl, debug: bool) {
+    if let Some(body) = &mut method.body {
+        fold_block(body, debug);
+    }
+    if let Some(expr) = &mut method.expr_body {
+        fold_expr(expr, debug);
+    }
+}
+
+/// Folds the statements of `block`, keeping `stmt_lines` in step with them
+fn fold_block(block: &mut BlockStmt, debug: bool) {
+    let lines = std::mem::take(&mut block.stmt_lines);
+    let has_lines = lines.len() == block.stmts.len();
+    let mut stmts = Vec::with_capacity(block.stmts.len());
+    for (i, stmt) in std::mem::take(&mut block.stmts).into_iter().enumerate() {
+        if let Some(stmt) = fold_stmt(stmt, debug) {
+            stmts.push(stmt);
+            if has_lines {
+                block.stmt_lines.push(lines[i]);
+            }
+        }
+    }
+    block.stmts = stmts;
+}
+
+fn fold_stmts(stmts: &mut Vec<Stmt>, debug: bool) {
+    *stmts = std::mem::take(stmts)
+        .into_iter()
+        .filter_map(|stmt| fold_stmt(stmt, debug))
+        .collect();
+}
+
+/// The statement with `DEBUG` folded; `None` when it was an `if` whose
+/// branch for this profile is empty
+fn fold_stmt(mut stmt: Stmt, debug: bool) -> Option<Stmt> {
+    match &mut stmt {
+        Stmt::If(if_stmt) => {
+            let folds = mentions_debug(&if_stmt.condition);
+            fold_expr(&mut if_stmt.condition, debug);
+            let taken = match crate::consteval::evaluate(&if_stmt.condition, &Default::default()) {
+                Some(crate::consteval::ConstValue::Bool(taken)) if folds => taken,
+                _ => {
+                    fold_if_body(&mut if_stmt.then_branch, debug);
+                    fold_else_ifs(if_stmt, debug);
+                    return Some(stmt);
+                }
+            };
+            let Stmt::If(mut if_stmt) = stmt else {
+                unreachable!()
+            };
+            let branch = if taken {
+                Some(if_stmt.then_branch)
+            } else if !if_stmt.else_ifs.is_empty() {
+                // The first `else if` takes over the chain
+                let first = if_stmt.else_ifs.remove(0);
+                if_stmt.condition = first.condition;
+                if_stmt.then_branch = first.body;
+                return fold_stmt(Stmt::If(if_stmt), debug);
+            } else {
+                if_stmt.else_branch
+            };
+            return match branch? {
+                IfBody::Block(mut block) => {
+                    fold_block(&mut block, debug);
+                    Some(Stmt::Block(block))
+                }
+                IfBody::Stmt(inner) => fold_stmt(*inner, debug),
+            };
+        }
+        Stmt::VarDecl(decl) => {
+            fold_expr(&mut decl.init, debug);
+            if let Some(msg) = &mut decl.or_fail_msg {
+                fold_expr(msg, debug);
+            }
+            if let Some(value) = &mut decl.or_value {
+                fold_expr(value, debug);
+            }
         }
-        TopLevel::Test(test) => {
-            check_block_concurrency_block(&test.body, ctx);
+        Stmt::ConstDecl(decl) => fold_expr(&mut decl.init, debug),
+        Stmt::Assign(assign) => {
+            fold_expr(&mut assign.target, debug);
+            fold_expr(&mut assign.value, debug);
         }
-        TopLevel::ExprStmt(expr) => {
-            check_expr_concurrency(expr, ctx);
+        Stmt::While(while_stmt) => {
+            fold_expr(&mut while_stmt.condition, debug);
+            fold_block(&mut while_stmt.body, debug);
         }
-        _ => {}
+        Stmt::For(for_stmt) => {
+            fold_expr(&mut for_stmt.iterable, debug);
+            fold_block(&mut for_stmt.body, debug);
+        }
+        Stmt::Switch(switch) => {
+            fold_expr(&mut switch.discriminant, debug);
+            for case in &mut switch.cases {
+                fold_expr(&mut case.value, debug);
+                fold_stmts(&mut case.body, debug);
+            }
+            if let Some(default) = &mut switch.default {
+                fold_stmts(default, debug);
+            }
+        }
+        Stmt::TryCatch(try_catch) => {
+            fold_block(&mut try_catch.try_block, debug);
+            fold_block(&mut try_catch.catch_block, debug);
+        }
+        Stmt::Throw(throw) => fold_expr(&mut throw.expr, debug),
+        Stmt::Fail(fail) => fold_expr(&mut fail.expr, debug),
+        Stmt::Return(ret) => {
+            if let Some(expr) = &mut ret.expr {
+                fold_expr(expr, debug);
+            }
+        }
+        Stmt::Defer(defer) => {
+            let body = std::mem::replace(&mut *defer.body, Stmt::Break);
+            *defer.body = fold_stmt(body, debug)?;
+        }
+        Stmt::Expr(expr_stmt) => fold_expr(&mut expr_stmt.expr, debug),
+        Stmt::Block(block) => fold_block(block, debug),
+        Stmt::Scope(scope) => fold_block(&mut scope.body, debug),
+        Stmt::Mock(mock) => fold_expr(&mut mock.value, debug),
+        Stmt::Function(func) => fold_function(func, debug),
+        Stmt::Break | Stmt::Continue => {}
     }
+    Some(stmt)
 }
 
-fn check_block_concurrency(body: &IfBody, ctx: &mut DesugarContext) {
-    match body {
-        IfBody::Block(block) => {
-            for stmt in &block.stmts {
-                check_stmt_concurrency(stmt, ctx);
+/// Fold the `else if` links and final `else` of a chain whose first
+/// condition stays: a link whose condition folds to `false` is dropped, and
+/// one that folds to `true` becomes the final `else`
+fn fold_else_ifs(if_stmt: &mut IfStmt, debug: bool) {
+    let mut kept = Vec::new();
+    for mut link in std::mem::take(&mut if_stmt.else_ifs) {
+        let folds = mentions_debug(&link.condition);
+        fold_expr(&mut link.condition, debug);
+        match crate::consteval::evaluate(&link.condition, &Default::default()) {
+            Some(crate::consteval::ConstValue::Bool(false)) if folds => {}
+            Some(crate::consteval::ConstValue::Bool(true)) if folds => {
+                if_stmt.else_branch = Some(link.body);
+                break;
+            }
+            _ => {
+                fold_if_body(&mut link.body, debug);
+                kept.push(link);
             }
         }
+    }
+    if_stmt.else_ifs = kept;
+    if let Some(else_branch) = &mut if_stmt.else_branch {
+        fold_if_body(else_branch, debug);
+    }
+}
+
+fn fold_if_body(body: &mut IfBody, debug: bool) {
+    match body {
+        IfBody::Block(block) => fold_block(block, debug),
         IfBody::Stmt(stmt) => {
-            check_stmt_concurrency(stmt, ctx);
+            let inner = std::mem::replace(stmt.as_mut(), Stmt::Break);
+            **stmt = fold_stmt(inner, debug).unwrap_or_else(|| {
+                Stmt::Block(BlockStmt {
+                    stmts: Vec::new(),
+                    stmt_lines: Vec::new(),
+                    span: None,
+                })
+            });
+        }
+    }
+}
+
+/// Whether `expr` reads `DEBUG` outside any nested body
+fn mentions_debug(expr: &Expr) -> bool {
+    match expr {
+        Expr::Identifier(name) => name == DEBUG_CONSTANT,
+        Expr::Unary { operand, .. } => mentions_debug(operand),
+        Expr::Binary { left, right, .. } => mentions_debug(left) || mentions_debug(right),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => mentions_debug(condition) || mentions_debug(then_expr) || mentions_debug(else_expr),
+        _ => false,
+    }
+}
+
+/// Replaces `DEBUG` in `expr`; a ternary or `&&` / `||` whose deciding
+/// side became constant is reduced to what it evaluates
+fn fold_expr(expr: &mut Expr, debug: bool) {
+    let folds = mentions_debug(expr);
+    match expr {
+        Expr::Identifier(name) if name == DEBUG_CONSTANT => {
+            *expr = Expr::Literal(Literal::Bool(debug));
+            return;
+        }
+        Expr::Binary { left, right, .. } => {
+            fold_expr(left, debug);
+            fold_expr(right, debug);
+        }
+        Expr::Unary { operand, .. } => fold_expr(operand, debug),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => {
+            fold_expr(condition, debug);
+            fold_expr(then_expr, debug);
+            fold_expr(else_expr, debug);
+        }
+        Expr::Call(call) => {
+            fold_expr(&mut call.callee, debug);
+            call.args.iter_mut().for_each(|arg| fold_expr(arg, debug));
+        }
+        Expr::MethodCall(call) => {
+            fold_expr(&mut call.object, debug);
+            call.args.iter_mut().for_each(|arg| fold_expr(arg, debug));
+        }
+        Expr::Member { object, .. } | Expr::OptionalChain { object, .. } => {
+            fold_expr(object, debug)
+        }
+        Expr::Index { object, index } => {
+            fold_expr(object, debug);
+            fold_expr(index, debug);
+        }
+        Expr::ObjectLiteral(fields) | Expr::StructLiteral { fields, .. } => {
+            fields
+                .iter_mut()
+                .for_each(|(_, value)| fold_expr(value, debug));
         }
+        Expr::ArrayLiteral(items) | Expr::SetLiteral(items) | Expr::Tuple(items) => {
+            items.iter_mut().for_each(|item| fold_expr(item, debug));
+        }
+        Expr::MapLiteral(entries) => {
+            for (key, value) in entries {
+                fold_expr(key, debug);
+                fold_expr(value, debug);
+            }
+        }
+        Expr::Lambda(lambda) => match &mut lambda.body {
+            LambdaBody::Expr(body) => fold_expr(body, debug),
+            LambdaBody::Block(block) => fold_block(block, debug),
+        },
+        Expr::StringTemplate { parts } => {
+            for part in parts {
+                if let StringTemplatePart::Expr(e) = part {
+                    fold_expr(e, debug);
+                }
+            }
+        }
+        Expr::Fail(inner)
+        | Expr::Unwrap(inner)
+        | Expr::Try(inner)
+        | Expr::Cast { expr: inner, .. } => fold_expr(inner, debug),
+        Expr::Switch(switch) => {
+            fold_expr(&mut switch.discriminant, debug);
+            for arm in &mut switch.arms {
+                if let Some(guard) = &mut arm.guard {
+                    fold_expr(guard, debug);
+                }
+                fold_switch_body(&mut arm.body, debug);
+            }
+        }
+        Expr::If(if_expr) => {
+            fold_expr(&mut if_expr.condition, debug);
+            fold_switch_body(&mut if_expr.then_branch, debug);
+            fold_switch_body(&mut if_expr.else_branch, debug);
+        }
+        Expr::Identifier(_)
+        | Expr::MethodRef { .. }
+        | Expr::Literal(_)
+        | Expr::RustBlock { .. } => {}
+    }
+    if !folds {
+        return;
+    }
+    let reduced = match expr {
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => match condition.as_ref() {
+            Expr::Literal(Literal::Bool(true)) => Some(then_expr.as_ref().clone()),
+            Expr::Literal(Literal::Bool(false)) => Some(else_expr.as_ref().clone()),
+            _ => None,
+        },
+        Expr::Binary {
+            op: op @ (BinOp::And | BinOp::Or),
+            left,
+            right,
+        } => match (*op, left.as_ref()) {
+            (BinOp::And, Expr::Literal(Literal::Bool(true)))
+            | (BinOp::Or, Expr::Literal(Literal::Bool(false))) => Some(right.as_ref().clone()),
+            (BinOp::And, Expr::Literal(Literal::Bool(false)))
+            | (BinOp::Or, Expr::Literal(Literal::Bool(true))) => Some(left.as_ref().clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(reduced) = reduced {
+        *expr = reduced;
+    }
+}
+
+fn fold_switch_body(body: &mut SwitchBody, debug: bool) {
+    match body {
+        SwitchBody::Expr(expr) => fold_expr(expr, debug),
+        SwitchBody::Block(stmts) => fold_stmts(stmts, debug),
+    }
+}
+
+/// A rewrite of the source that happens before Rust is generated, as
+/// printed by `livac build --trace-desugar`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesugarStep {
+    /// `compound-assign`, `parse-cast`, `or-fail`, `or-value`, `or-else`,
+    /// `expect`, `map-err` or `comprehension`
+    pub rule: &'static str,
+    /// The construct as written
+    pub span: Span,
+    pub before: String,
+    pub after: String,
+}
+
+/// Every rewrite applied to `program`, in source order. `source` is the
+/// text `program` was parsed from; nodes without a span are skipped.
+pub fn trace(program: &Program, source: &str) -> Vec<DesugarStep> {
+    let mut steps = Vec::new();
+    for item in &program.items {
+        match item {
+            TopLevel::Function(func) => {
+                trace_body(&func.body, &func.expr_body, source, &mut steps);
+            }
+            TopLevel::Class(class) => {
+                for member in &class.members {
+                    if let Member::Method(method) = member {
+                        trace_body(&method.body, &method.expr_body, source, &mut steps);
+                    }
+                }
+            }
+            TopLevel::ClassExtension(extension) => {
+                for method in &extension.methods {
+                    trace_body(&method.body, &method.expr_body, source, &mut steps);
+                }
+            }
+            TopLevel::Test(test) => trace_stmts(&test.body.stmts, source, &mut steps),
+            TopLevel::Init(init) => trace_stmts(&init.body.stmts, source, &mut steps),
+            TopLevel::TestHook(hook) => trace_stmts(&hook.body.stmts, source, &mut steps),
+            TopLevel::ExprStmt(expr) => trace_expr(expr, source, &mut steps),
+            _ => {}
+        }
+    }
+    steps.sort_by_key(|step| step.span.start);
+    steps
+}
+
+fn trace_body(
+    body: &Option<BlockStmt>,
+    expr_body: &Option<Expr>,
+    source: &str,
+    steps: &mut Vec<DesugarStep>,
+) {
+    if let Some(block) = body {
+        trace_stmts(&block.stmts, source, steps);
+    }
+    if let Some(expr) = expr_body {
+        trace_expr(expr, source, steps);
+    }
+}
+
+fn trace_stmts(stmts: &[Stmt], source: &str, steps: &mut Vec<DesugarStep>) {
+    for stmt in stmts {
+        trace_stmt(stmt, source, steps);
     }
 }
 
-fn check_block_concurrency_block(block: &BlockStmt, ctx: &mut DesugarContext) {
-    for stmt in &block.stmts {
-        check_stmt_concurrency(stmt, ctx);
+fn trace_if_body(body: &IfBody, source: &str, steps: &mut Vec<DesugarStep>) {
+    match body {
+        IfBody::Block(block) => trace_stmts(&block.stmts, source, steps),
+        IfBody::Stmt(stmt) => trace_stmt(stmt, source, steps),
     }
 }
 
-fn check_stmt_concurrency(stmt: &Stmt, ctx: &mut DesugarContext) {
+fn trace_stmt(stmt: &Stmt, source: &str, steps: &mut Vec<DesugarStep>) {
     match stmt {
         Stmt::VarDecl(var) => {
-            check_expr_concurrency(&var.init, ctx);
+            trace_var_decl(var, source, steps);
+            trace_expr(&var.init, source, steps);
         }
-        Stmt::ConstDecl(const_decl) => check_expr_concurrency(&const_decl.init, ctx),
+        Stmt::ConstDecl(const_decl) => trace_expr(&const_decl.init, source, steps),
         Stmt::Assign(assign) => {
-            check_expr_concurrency(&assign.target, ctx);
-            check_expr_concurrency(&assign.value, ctx);
+            trace_compound_assign(assign, source, steps);
+            trace_expr(&assign.value, source, steps);
         }
         Stmt::If(if_stmt) => {
-            check_expr_concurrency(&if_stmt.condition, ctx);
-            check_block_concurrency(&if_stmt.then_branch, ctx);
+            for (condition, body) in if_stmt.branches() {
+                trace_expr(condition, source, steps);
+                trace_if_body(body, source, steps);
+            }
             if let Some(else_branch) = &if_stmt.else_branch {
-                check_block_concurrency(else_branch, ctx);
+                trace_if_body(else_branch, source, steps);
             }
         }
         Stmt::While(while_stmt) => {
-            check_expr_concurrency(&while_stmt.condition, ctx);
-            check_block_concurrency_block(&while_stmt.body, ctx);
+            trace_expr(&while_stmt.condition, source, steps);
+            trace_stmts(&while_stmt.body.stmts, source, steps);
         }
         Stmt::For(for_stmt) => {
-            if matches!(
-                for_stmt.policy,
-                DataParallelPolicy::Par | DataParallelPolicy::Vec | DataParallelPolicy::ParVec
-            ) {
-                ctx.has_parallel = true;
+            trace_expr(&for_stmt.iterable, source, steps);
+            trace_stmts(&for_stmt.body.stmts, source, steps);
+        }
+        Stmt::Switch(switch) => {
+            trace_expr(&switch.discriminant, source, steps);
+            for case in &switch.cases {
+                trace_stmts(&case.body, source, steps);
             }
-            check_expr_concurrency(&for_stmt.iterable, ctx);
-            check_block_concurrency_block(&for_stmt.body, ctx);
+            if let Some(default) = &switch.default {
+                trace_stmts(default, source, steps);
+            }
+        }
+        Stmt::TryCatch(try_catch) => {
+            trace_stmts(&try_catch.try_block.stmts, source, steps);
+            trace_stmts(&try_catch.catch_block.stmts, source, steps);
         }
+        Stmt::Throw(throw) => trace_expr(&throw.expr, source, steps),
+        Stmt::Fail(fail) => trace_expr(&fail.expr, source, steps),
         Stmt::Return(ret) => {
             if let Some(expr) = &ret.expr {
-                check_expr_concurrency(expr, ctx);
+                trace_expr(expr, source, steps);
             }
         }
-        Stmt::Defer(defer_stmt) => {
-            check_stmt_concurrency(&defer_stmt.body, ctx);
-        }
-        Stmt::Expr(expr_stmt) => check_expr_concurrency(&expr_stmt.expr, ctx),
-        Stmt::Block(block) => check_block_concurrency_block(block, ctx),
-        _ => {}
+        Stmt::Defer(defer) => trace_stmt(&defer.body, source, steps),
+        Stmt::Expr(expr_stmt) => trace_expr(&expr_stmt.expr, source, steps),
+        Stmt::Block(block) => trace_stmts(&block.stmts, source, steps),
+        Stmt::Scope(scope) => trace_stmts(&scope.body.stmts, source, steps),
+        Stmt::Mock(mock) => trace_expr(&mock.value, source, steps),
+        Stmt::Function(func) => trace_body(&func.body, &func.expr_body, source, steps),
+        Stmt::Break | Stmt::Continue => {}
     }
 }
 
-fn check_expr_concurrency(expr: &Expr, ctx: &mut DesugarContext) {
+/// Statements nested in expressions: lambda bodies and block arms
+fn trace_expr(expr: &Expr, source: &str, steps: &mut Vec<DesugarStep>) {
+    let branch = |body: &SwitchBody, steps: &mut Vec<DesugarStep>| match body {
+        SwitchBody::Expr(expr) => trace_expr(expr, source, steps),
+        SwitchBody::Block(stmts) => trace_stmts(stmts, source, steps),
+    };
     match expr {
+        Expr::Lambda(lambda) => match &lambda.body {
+            LambdaBody::Expr(expr) => trace_expr(expr, source, steps),
+            LambdaBody::Block(block) => trace_stmts(&block.stmts, source, steps),
+        },
         Expr::Call(call) => {
-            match call.exec_policy {
-                ExecPolicy::Async | ExecPolicy::TaskAsync => {
-                    ctx.has_async = true;
-                }
-                ExecPolicy::Par | ExecPolicy::TaskPar => {
-                    ctx.has_parallel = true;
-                }
-                ExecPolicy::Normal => {}
-            }
-
-            check_expr_concurrency(&call.callee, ctx);
+            trace_expr(&call.callee, source, steps);
             for arg in &call.args {
-                check_expr_concurrency(arg, ctx);
+                trace_expr(arg, source, steps);
             }
         }
-        Expr::MethodCall(method_call) => {
-            // Check if it's Math.random()
-            if let Expr::Identifier(name) = method_call.object.as_ref() {
-                if name == "Math" && method_call.method == "random" {
-                    ctx.has_random = true;
-                }
-                if name == "Log" {
-                    ctx.has_logging = true;
-                }
-                if name == "Config" {
-                    ctx.has_config = true;
-                }
-                if name == "Regex" {
-                    ctx.has_regex = true;
-                }
-                if name == "Date" {
-                    ctx.has_date = true;
-                }
-                if name == "Random" {
-                    ctx.has_random = true;
-                }
-                if name == "Crypto" {
-                    ctx.has_crypto = true;
-                }
-                if name == "Server" {
-                    ctx.has_server = true;
-                    ctx.has_async = true;
-                }
-                if name == "DB" {
-                    ctx.has_db = true;
-                }
-            }
-
-            // Check if it uses parallel array adapters
-            match method_call.adapter {
-                crate::ast::ArrayAdapter::Par | crate::ast::ArrayAdapter::ParVec => {
-                    ctx.has_parallel = true;
-                }
-                _ => {}
+        Expr::MethodCall(call) => {
+            trace_comprehension(call, source, steps);
+            trace_expr(&call.object, source, steps);
+            for arg in &call.args {
+                trace_expr(arg, source, steps);
             }
-
-            // Continue checking nested expressions
-            check_expr_concurrency(&method_call.object, ctx);
-            for arg in &method_call.args {
-                check_expr_concurrency(arg, ctx);
+        }
+        Expr::If(if_expr) => {
+            trace_expr(&if_expr.condition, source, steps);
+            branch(&if_expr.then_branch, steps);
+            branch(&if_expr.else_branch, steps);
+        }
+        Expr::Switch(switch) => {
+            trace_expr(&switch.discriminant, source, steps);
+            for arm in &switch.arms {
+                branch(&arm.body, steps);
             }
         }
-        Expr::Lambda(lambda) => match &lambda.body {
-            LambdaBody::Expr(expr) => check_expr_concurrency(expr, ctx),
-            LambdaBody::Block(block) => check_block_concurrency_block(block, ctx),
-        },
         Expr::Binary { left, right, .. } => {
-            check_expr_concurrency(left, ctx);
-            check_expr_concurrency(right, ctx);
+            trace_expr(left, source, steps);
+            trace_expr(right, source, steps);
         }
-        Expr::Unary { operand, .. } => check_expr_concurrency(operand, ctx),
         Expr::Ternary {
             condition,
             then_expr,
             else_expr,
         } => {
-            check_expr_concurrency(condition, ctx);
-            check_expr_concurrency(then_expr, ctx);
-            check_expr_concurrency(else_expr, ctx);
+            trace_expr(condition, source, steps);
+            trace_expr(then_expr, source, steps);
+            trace_expr(else_expr, source, steps);
         }
-        Expr::Member { object, .. } => check_expr_concurrency(object, ctx),
+        Expr::Unary { operand: inner, .. }
+        | Expr::Member { object: inner, .. }
+        | Expr::OptionalChain { object: inner, .. }
+        | Expr::Cast { expr: inner, .. }
+        | Expr::Fail(inner)
+        | Expr::Unwrap(inner)
+        | Expr::Try(inner) => trace_expr(inner, source, steps),
         Expr::Index { object, index } => {
-            check_expr_concurrency(object, ctx);
-            check_expr_concurrency(index, ctx);
+            trace_expr(object, source, steps);
+            trace_expr(index, source, steps);
         }
-        Expr::ArrayLiteral(elements) => {
-            for elem in elements {
-                check_expr_concurrency(elem, ctx);
+        Expr::ArrayLiteral(items) | Expr::SetLiteral(items) | Expr::Tuple(items) => {
+            for item in items {
+                trace_expr(item, source, steps);
             }
         }
-        Expr::ObjectLiteral(fields) => {
+        Expr::ObjectLiteral(fields) | Expr::StructLiteral { fields, .. } => {
             for (_, value) in fields {
-                check_expr_concurrency(value, ctx);
+                trace_expr(value, source, steps);
+            }
+        }
+        Expr::MapLiteral(entries) => {
+            for (key, value) in entries {
+                trace_expr(key, source, steps);
+                trace_expr(value, source, steps);
+            }
+        }
+        Expr::StringTemplate { parts } => {
+            for part in parts {
+                if let StringTemplatePart::Expr(expr) = part {
+                    trace_expr(expr, source, steps);
+                }
+            }
+        }
+        Expr::Literal(_)
+        | Expr::Identifier(_)
+        | Expr::MethodRef { .. }
+        | Expr::RustBlock { .. } => {}
+    }
+}
+
+/// `x += n` → `x = x + n`, done by the parser
+fn trace_compound_assign(assign: &AssignStmt, source: &str, steps: &mut Vec<DesugarStep>) {
+    let (Some(op), Some(span)) = (assign.op, assign.span) else {
+        return;
+    };
+    let before = span.snippet(source);
+    let Some((start, end)) = find_token(before, false, |token| {
+        matches!(
+            token,
+            Token::PlusAssign
+                | Token::MinusAssign
+                | Token::StarAssign
+                | Token::SlashAssign
+                | Token::PercentAssign
+                | Token::StarStarAssign
+                | Token::AmpAssign
+                | Token::PipeAssign
+                | Token::CaretAssign
+                | Token::ShlAssign
+                | Token::Ge
+        )
+    }) else {
+        return;
+    };
+    // `>>=` lexes as `>` and `>=`
+    let start = if op == BinOp::Shr { start - 1 } else { start };
+    let target = before[..start].trim();
+    let rhs = before[end..].trim();
+    // `x *= a + b` is `x = x * (a + b)`
+    let grouped = matches!(
+        &assign.value,
+        Expr::Binary { right, .. } if matches!(right.as_ref(), Expr::Binary { .. } | Expr::Ternary { .. })
+    );
+    let rhs = if grouped {
+        format!("({})", rhs)
+    } else {
+        rhs.to_string()
+    };
+    steps.push(DesugarStep {
+        rule: "compound-assign",
+        span,
+        before: before.to_string(),
+        after: format!("{} = {} {} {}", target, target, op, rhs),
+    });
+}
+
+/// `[x * 2 for x in items if x > 0]` → `items.filter(x => x > 0).map(x => x * 2)`,
+/// done by the parser
+fn trace_comprehension(call: &MethodCallExpr, source: &str, steps: &mut Vec<DesugarStep>) {
+    let Some(Comprehension {
+        filtered,
+        span: Some(span),
+    }) = &call.comprehension
+    else {
+        return;
+    };
+    let var = match call.args.first() {
+        Some(Expr::Lambda(lambda)) => lambda.params.first().and_then(|param| param.name()),
+        _ => None,
+    };
+    let before = span.snippet(source);
+    let inner = before
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .unwrap_or(before);
+    let (Some(var), Some((for_start, _))) = (var, find_token(inner, false, |t| *t == Token::For))
+    else {
+        return;
+    };
+    let element = inner[..for_start].trim();
+    let Some((_, in_end)) = find_token(&inner[for_start..], false, |t| *t == Token::In) else {
+        return;
+    };
+    let rest = &inner[for_start + in_end..];
+    let (iterable, condition) = match find_token(rest, false, |t| *t == Token::If) {
+        Some((if_start, if_end)) if *filtered => {
+            (rest[..if_start].trim(), Some(rest[if_end..].trim()))
+        }
+        _ => (rest.trim(), None),
+    };
+
+    let adapter = match call.adapter {
+        ArrayAdapter::Seq => "",
+        ArrayAdapter::Par => ".par()",
+        ArrayAdapter::Vec => ".vec()",
+        ArrayAdapter::ParVec => ".parvec()",
+    };
+    let mut after = iterable.to_string();
+    if let Some(condition) = condition {
+        after = format!("{}{}.filter({} => {})", after, adapter, var, condition);
+    }
+    if call.method == "map" {
+        after = format!("{}{}.map({} => {})", after, adapter, var, element);
+    }
+    steps.push(DesugarStep {
+        rule: "comprehension",
+        span: *span,
+        before: before.to_string(),
+        after,
+    });
+}
+
+/// `text as int` with an error binding → `parseInt(text)`, done by codegen;
+/// `or fail` and `or <value>` and the result combinators → a `match` on the result
+fn trace_var_decl(var: &VarDecl, source: &str, steps: &mut Vec<DesugarStep>) {
+    let Some(span) = var.span else {
+        return;
+    };
+    let text = span.snippet(source);
+    let Some((assign_start, init_start)) = find_token(text, false, |token| *token == Token::Assign)
+    else {
+        return;
+    };
+    let has_clause = var.or_fail_msg.is_some() || var.or_value.is_some();
+    let (init_end, clause) = if var.combinator.is_some() {
+        // `.orElse(v)`, `.expect(msg)`, `.mapErr(fn)`: the clause is the
+        // argument of the last call
+        match find_token(text, true, |token| *token == Token::Dot) {
+            Some((dot_start, _)) => {
+                let call = &text[dot_start..];
+                let arg = call
+                    .find('(')
+                    .and_then(|open| call[open + 1..].trim_end().strip_suffix(')'))
+                    .unwrap_or("");
+                (dot_start, arg.trim())
+            }
+            None => (text.len(), ""),
+        }
+    } else {
+        match find_token(text, true, |token| *token == Token::Or) {
+            Some((or_start, or_end)) if has_clause => (or_start, text[or_end..].trim()),
+            _ => (text.len(), ""),
+        }
+    };
+    let raw_init = &text[init_start..init_end];
+    let init = raw_init.trim();
+    let init_offset = span.start + init_start + raw_init.len() - raw_init.trim_start().len();
+    let head = text[..assign_start].trim_end();
+
+    let mut lowered = init.to_string();
+    if let Expr::Cast {
+        target: TypeRef::Simple(target),
+        ..
+    } = &var.init
+    {
+        let parser = match target.as_str() {
+            "int" => Some("parseInt"),
+            "float" => Some("parseFloat"),
+            _ => None,
+        };
+        let cast = find_token(init, true, |token| *token == Token::As);
+        if let (Some(parser), Some((as_start, _)), true) = (parser, cast, var.is_fallible) {
+            lowered = format!("{}({})", parser, init[..as_start].trim());
+            steps.push(DesugarStep {
+                rule: "parse-cast",
+                span: Span::new(init_offset, init_offset + init.len()),
+                before: init.to_string(),
+                after: lowered.clone(),
+            });
+        }
+    }
+
+    let (rule, on_error) = if let Some(combinator) = &var.combinator {
+        match combinator {
+            ResultCombinator::OrElse => ("or-else", clause.to_string()),
+            ResultCombinator::Expect => ("expect", format!("panic!(\"{{}}\", {})", clause)),
+            ResultCombinator::MapErr(_) => {
+                steps.push(DesugarStep {
+                    rule: "map-err",
+                    span,
+                    before: text.to_string(),
+                    after: format!(
+                        "{} = match {} {{ Ok(v) => (v, None), Err(e) => (default, Some(({})(e))) }}",
+                        head, lowered, clause
+                    ),
+                });
+                return;
             }
         }
-        Expr::RustBlock { .. } => {
-            ctx.has_rust_blocks = true;
+    } else if var.or_fail_msg.is_some() {
+        let message = clause.strip_prefix("fail").unwrap_or(clause).trim();
+        let message = if message.is_empty() { "\"\"" } else { message };
+        ("or-fail", format!("return Err({})", message))
+    } else if var.or_value.is_some() {
+        ("or-value", clause.to_string())
+    } else {
+        return;
+    };
+    steps.push(DesugarStep {
+        rule,
+        span,
+        before: text.to_string(),
+        after: format!(
+            "{} = match {} {{ Ok(v) => v, Err(_) => {} }}",
+            head, lowered, on_error
+        ),
+    });
+}
+
+/// Byte range of the first (or, with `last`, the last) token of `text`
+/// matching `pred` outside brackets
+fn find_token(text: &str, last: bool, pred: impl Fn(&Token) -> bool) -> Option<(usize, usize)> {
+    let tokens = crate::lexer::tokenize(text).ok()?;
+    let mut depth = 0i32;
+    let mut found = None;
+    for tok in &tokens {
+        match tok.token {
+            Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+            Token::RParen | Token::RBracket | Token::RBrace => depth -= 1,
+            ref token if depth == 0 && pred(token) => {
+                found = Some((tok.span.start, tok.span.end));
+                if !last {
+                    break;
+                }
+            }
+            _ => {}
         }
-        _ => {}
     }
+    found
 }
 
 #[cfg(test)]
@@ -331,6 +1114,122 @@ mod tests {
         assert!(ctx.has_parallel);
     }
 
+    #[test]
+    fn test_trace_desugarings() {
+        let source = "total(items: [number]): number {\n    let sum = 0\n    for n in items {\n        sum *= n + 1\n    }\n    return sum\n}\n\nport(text: string): number {\n    let value = text as int or 8080\n    let checked = parsePort(text) or fail \"bad port\"\n    return value\n}\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+
+        let steps: Vec<(&str, &str, String)> = trace(&program, source)
+            .into_iter()
+            .map(|step| (step.rule, step.span.snippet(source), step.after))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("compound-assign", "sum *= n + 1", "sum = sum * (n + 1)".to_string()),
+                (
+                    "or-value",
+                    "let value = text as int or 8080",
+                    "let value = match parseInt(text) { Ok(v) => v, Err(_) => 8080 }".to_string()
+                ),
+                ("parse-cast", "text as int", "parseInt(text)".to_string()),
+                (
+                    "or-fail",
+                    "let checked = parsePort(text) or fail \"bad port\"",
+                    "let checked = match parsePort(text) { Ok(v) => v, Err(_) => return Err(\"bad port\") }"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_comprehensions() {
+        let source = "main() {\n    let doubled = [x * 2 for x in items if x > 0]\n    let kept = [x for par x in items if x > 0]\n    let names = [u.name for u in users]\n}\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+
+        let steps: Vec<(&str, &str, String)> = trace(&program, source)
+            .into_iter()
+            .map(|step| (step.rule, step.span.snippet(source), step.after))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    "comprehension",
+                    "[x * 2 for x in items if x > 0]",
+                    "items.filter(x => x > 0).map(x => x * 2)".to_string()
+                ),
+                (
+                    "comprehension",
+                    "[x for par x in items if x > 0]",
+                    "items.par().filter(x => x > 0)".to_string()
+                ),
+                (
+                    "comprehension",
+                    "[u.name for u in users]",
+                    "users.map(u => u.name)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_result_combinators() {
+        let source = "port(text: string): number {\n    let value = parsePort(text).orElse(8080)\n    let fixed = parsePort(\"80\").expect(\"bad port\")\n    let checked, err = parsePort(text).mapErr(describe)\n    return value\n}\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+
+        let steps: Vec<(&str, String)> = trace(&program, source)
+            .into_iter()
+            .map(|step| (step.rule, step.after))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    "or-else",
+                    "let value = match parsePort(text) { Ok(v) => v, Err(_) => 8080 }".to_string()
+                ),
+                (
+                    "expect",
+                    "let fixed = match parsePort(\"80\") { Ok(v) => v, Err(_) => panic!(\"{}\", \"bad port\") }"
+                        .to_string()
+                ),
+                (
+                    "map-err",
+                    "let checked, err = match parsePort(text) { Ok(v) => (v, None), Err(e) => (default, Some((describe)(e))) }"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fold_profile_constants() {
+        let body_len = |source: &str, profile: crate::BuildProfile| {
+            let mut program = parse(tokenize(source).unwrap(), source).unwrap();
+            fold_profile_constants(&mut program, profile);
+            program
+                .items
+                .iter()
+                .find_map(|item| match item {
+                    TopLevel::Function(func) if func.name == "main" => {
+                        Some(func.body.as_ref().unwrap().stmts.len())
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let source = "main() {\n    if DEBUG && verbose() {\n        print(\"trace\")\n    }\n    print(\"done\")\n}\n";
+        assert_eq!(body_len(source, crate::BuildProfile::Release), 1);
+        assert_eq!(body_len(source, crate::BuildProfile::Dev), 2);
+
+        // A user-declared DEBUG shadows the profile constant
+        let declared = format!("const DEBUG = true\n\n{}", source);
+        assert_eq!(body_len(&declared, crate::BuildProfile::Release), 2);
+    }
+
     #[test]
     fn test_detect_task_and_par_calls() {
         let source = r#"
@@ -0,0 +1,185 @@
+//! Diagnostics that don't stop compilation.
+//!
+//! An error ends a compilation through [`CompilerError`](crate::CompilerError);
+//! everything else the compiler has to say — a warning, or a hint about
+//! a better way to write something — is a [`Diagnostic`]. Semantic analysis,
+//! the custom passes and code generation each report into a
+//! [`DiagnosticSink`], and the diagnostics come back in
+//! [`CompilationResult::diagnostics`](crate::CompilationResult::diagnostics)
+//! in the order they were reported.
+
+use crate::error::SemanticErrorInfo;
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Hint,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Lowercase name, as in the JSON output
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Hint => "hint",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A message with a severity; its code, text and source location are those
+/// of `info`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub info: SemanticErrorInfo,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, info: SemanticErrorInfo) -> Self {
+        Diagnostic { severity, info }
+    }
+
+    pub fn error(info: SemanticErrorInfo) -> Self {
+        Self::new(Severity::Error, info)
+    }
+
+    pub fn warning(info: SemanticErrorInfo) -> Self {
+        Self::new(Severity::Warning, info)
+    }
+
+    pub fn hint(info: SemanticErrorInfo) -> Self {
+        Self::new(Severity::Hint, info)
+    }
+
+    pub fn code(&self) -> &str {
+        &self.info.code
+    }
+
+    /// The JSON `--json` prints for an error, plus a `"severity"` field
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        let mut value: serde_json::Value = serde_json::from_str(&self.info.to_json()?)?;
+        if let Some(object) = value.as_object_mut() {
+            object.insert("severity".to_string(), self.severity.name().into());
+        }
+        serde_json::to_string(&value)
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}: {}",
+            self.severity, self.info.code, self.info.message
+        )?;
+        if let Some(location) = &self.info.location {
+            write!(f, " ({}:{})", location.file, location.line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the diagnostics of one compilation, in the order they're reported
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticSink {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn error(&mut self, info: SemanticErrorInfo) {
+        self.push(Diagnostic::error(info));
+    }
+
+    pub fn warning(&mut self, info: SemanticErrorInfo) {
+        self.push(Diagnostic::warning(info));
+    }
+
+    pub fn hint(&mut self, info: SemanticErrorInfo) {
+        self.push(Diagnostic::hint(info));
+    }
+
+    /// Report each of `infos` as a warning
+    pub fn warnings(&mut self, infos: impl IntoIterator<Item = SemanticErrorInfo>) {
+        self.diagnostics
+            .extend(infos.into_iter().map(Diagnostic::warning));
+    }
+
+    pub fn extend(&mut self, diagnostics: impl IntoIterator<Item = Diagnostic>) {
+        self.diagnostics.extend(diagnostics);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Everything reported so far, leaving the sink empty
+    pub fn take(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_carries_severity_and_code() {
+        let mut sink = DiagnosticSink::new();
+        sink.warning(SemanticErrorInfo::new(
+            "E0606",
+            "Unused task",
+            "never awaited",
+        ));
+        sink.hint(SemanticErrorInfo::new("E0024", "Conversion", "guessed"));
+
+        let diagnostics = sink.into_vec();
+        assert_eq!(diagnostics.len(), 2);
+        let json: serde_json::Value =
+            serde_json::from_str(&diagnostics[0].to_json().unwrap()).unwrap();
+        assert_eq!(json["severity"], "warning");
+        assert_eq!(json["code"], "E0606");
+        assert_eq!(diagnostics[1].severity, Severity::Hint);
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "hint E0024: guessed",
+            "no location, so no file:line suffix"
+        );
+    }
+}
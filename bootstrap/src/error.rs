@@ -3,6 +3,10 @@ use thiserror::Error;
 
 use crate::error_codes::ErrorCategory;
 
+/// Errors `livac` reports in one run unless `--max-errors` says otherwise,
+/// and the limit [`check_source`](crate::check_source) collects up to
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ErrorLocation {
     pub file: String,
@@ -409,25 +413,38 @@ impl From<&str> for SemanticErrorInfo {
 #[derive(Error, Debug)]
 pub enum CompilerError {
     #[error("{}", .0.format())]
-    LexerError(SemanticErrorInfo),
+    LexerError(Box<SemanticErrorInfo>),
 
     #[error("{}", .0.format())]
-    ParseError(SemanticErrorInfo),
+    ParseError(Box<SemanticErrorInfo>),
 
     #[error("{}", .0.format())]
-    SemanticError(SemanticErrorInfo),
+    SemanticError(Box<SemanticErrorInfo>),
 
     #[error("{}", .0.format())]
-    TypeError(SemanticErrorInfo),
+    TypeError(Box<SemanticErrorInfo>),
 
     #[error("{}", .0.format())]
-    CodegenError(SemanticErrorInfo),
+    CodegenError(Box<SemanticErrorInfo>),
 
     #[error("IO error: {0}")]
     IoError(String),
 
     #[error("Runtime error: {0}")]
     RuntimeError(String),
+
+    /// The caller's [`CancellationToken`](crate::cancel::CancellationToken) was cancelled
+    #[error("Compilation cancelled")]
+    Cancelled,
+
+    /// The caller's [`CancellationToken`](crate::cancel::CancellationToken) deadline passed
+    #[error("Compilation exceeded its deadline")]
+    DeadlineExceeded,
+
+    /// Several errors found in one pass, when `CompilerOptions::max_errors`
+    /// is above 1; never empty and never nested
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    Multiple(Vec<CompilerError>),
 }
 
 impl CompilerError {
@@ -435,11 +452,58 @@ impl CompilerError {
     pub fn can_serialize_json(&self) -> bool {
         !matches!(
             self,
-            CompilerError::IoError(_) | CompilerError::RuntimeError(_)
+            CompilerError::IoError(_)
+                | CompilerError::RuntimeError(_)
+                | CompilerError::Cancelled
+                | CompilerError::DeadlineExceeded
+        )
+    }
+
+    /// True when compilation stopped because of a cancellation token rather
+    /// than a problem in the source
+    pub fn is_cancellation(&self) -> bool {
+        matches!(
+            self,
+            CompilerError::Cancelled | CompilerError::DeadlineExceeded
         )
     }
 
-    /// Get the underlying SemanticErrorInfo if available
+    /// One error for all of `errors`: the error itself when there is just
+    /// one, otherwise [`CompilerError::Multiple`]
+    pub fn from_errors(errors: Vec<CompilerError>) -> CompilerError {
+        let mut flat: Vec<CompilerError> = Vec::with_capacity(errors.len());
+        for error in errors {
+            match error {
+                CompilerError::Multiple(inner) => flat.extend(inner),
+                error => flat.push(error),
+            }
+        }
+        if flat.len() == 1 {
+            flat.remove(0)
+        } else {
+            CompilerError::Multiple(flat)
+        }
+    }
+
+    /// The errors this one stands for: those of a `Multiple`, otherwise
+    /// itself
+    pub fn errors(&self) -> &[CompilerError] {
+        match self {
+            CompilerError::Multiple(errors) => errors,
+            error => std::slice::from_ref(error),
+        }
+    }
+
+    /// Split into the errors this one stands for, as [`errors`](Self::errors)
+    pub fn into_errors(self) -> Vec<CompilerError> {
+        match self {
+            CompilerError::Multiple(errors) => errors,
+            error => vec![error],
+        }
+    }
+
+    /// Get the underlying SemanticErrorInfo if available; for `Multiple`,
+    /// that of the first error
     pub fn error_info(&self) -> Option<&SemanticErrorInfo> {
         match self {
             CompilerError::LexerError(info) => Some(info),
@@ -447,6 +511,20 @@ impl CompilerError {
             CompilerError::SemanticError(info) => Some(info),
             CompilerError::TypeError(info) => Some(info),
             CompilerError::CodegenError(info) => Some(info),
+            CompilerError::Multiple(errors) => errors.first()?.error_info(),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the underlying SemanticErrorInfo if available
+    pub fn error_info_mut(&mut self) -> Option<&mut SemanticErrorInfo> {
+        match self {
+            CompilerError::LexerError(info) => Some(info),
+            CompilerError::ParseError(info) => Some(info),
+            CompilerError::SemanticError(info) => Some(info),
+            CompilerError::TypeError(info) => Some(info),
+            CompilerError::CodegenError(info) => Some(info),
+            CompilerError::Multiple(errors) => errors.first_mut()?.error_info_mut(),
             _ => None,
         }
     }
@@ -32,6 +32,23 @@ pub const E0004_CIRCULAR_TYPE_ALIAS: &str = "E0004";
 pub const E0005_INVALID_LENGTH_ACCESS: &str = "E0005";
 pub const E0006_INVALID_HTTP_CALL: &str = "E0006";
 pub const E0007_UNKNOWN_HTTP_METHOD: &str = "E0007";
+pub const E0008_IMMUTABLE_RECEIVER: &str = "E0008";
+pub const E0009_INVALID_MAIN_SIGNATURE: &str = "E0009";
+pub const E0010_SHADOWED_RUNTIME_NAME: &str = "E0010";
+pub const E0011_NAME_COLLISION: &str = "E0011";
+pub const E0012_CONFLICTING_OVERLOADS: &str = "E0012";
+pub const E0013_NO_MATCHING_OVERLOAD: &str = "E0013";
+pub const E0014_AMBIGUOUS_OVERLOAD_CALL: &str = "E0014";
+pub const E0015_ABSTRACT_INSTANTIATION: &str = "E0015";
+pub const E0016_MISSING_ABSTRACT_METHOD: &str = "E0016";
+pub const E0017_MISPLACED_ABSTRACT: &str = "E0017";
+pub const E0018_INVALID_DELEGATE: &str = "E0018";
+pub const E0019_DELEGATE_CONFLICT: &str = "E0019";
+pub const E0020_INVALID_CONTRACT: &str = "E0020";
+pub const E0021_SANDBOX_VIOLATION: &str = "E0021";
+pub const E0022_PRIVATE_ACCESS: &str = "E0022";
+pub const E0023_UNSAFE_RUST_SIGNATURE: &str = "E0023";
+pub const E0024_UNDETERMINED_STRING_CONVERSION: &str = "E0024";
 
 // ============================================================================
 // E0xxx: Destructuring Errors (E0300-E0399)
@@ -51,12 +68,15 @@ pub const E0312_PARAM_DUPLICATE_BINDING: &str = "E0312";
 
 pub const E0401_INVALID_CONCURRENT_EXEC: &str = "E0401";
 pub const E0402_UNSAFE_CONCURRENT_ACCESS: &str = "E0402";
+pub const E0403_RECURSIVE_CAPTURING_FUNCTION: &str = "E0403";
+pub const E0404_ASYNC_NESTED_FUNCTION: &str = "E0404";
 pub const E0510_NON_SEND_CAPTURE: &str = "E0510";
 pub const E0511_NON_SYNC_CAPTURE: &str = "E0511";
 pub const E0602_DUPLICATE_EXEC_MODIFIER: &str = "E0602";
 pub const E0603_NOT_AWAITABLE: &str = "E0603";
 pub const E0604_AWAIT_MULTIPLE_TIMES: &str = "E0604";
 pub const E0605_AWAIT_IN_PARALLEL_LOOP: &str = "E0605";
+pub const E0606_UNUSED_TASK: &str = "E0606";
 
 // ============================================================================
 // E0xxx: Error Handling & Loop Options (E0700-E0799)
@@ -68,6 +88,15 @@ pub const E0703_INVALID_PREFETCH_SIZE: &str = "E0703";
 pub const E0704_INVALID_THREAD_COUNT: &str = "E0704";
 pub const E0705_SIMD_WITHOUT_VEC: &str = "E0705";
 pub const E0706_INVALID_SIMD_WIDTH: &str = "E0706";
+pub const E0707_UNSUPPORTED_LOOP_OPTION: &str = "E0707";
+pub const E0708_DUPLICATE_RUNTIME_BLOCK: &str = "E0708";
+pub const E0709_UNTYPED_LAZY_VALUE: &str = "E0709";
+pub const E0710_INVALID_RESULT_COMBINATOR: &str = "E0710";
+pub const E0711_FALLIBLE_CONSTANT: &str = "E0711";
+pub const E0712_DUPLICATE_INIT_BLOCK: &str = "E0712";
+pub const E0713_ASYNC_INIT_BLOCK: &str = "E0713";
+pub const E0714_DUPLICATE_TEST_HOOK: &str = "E0714";
+pub const E0715_MOCK_OUTSIDE_TEST: &str = "E0715";
 
 // ============================================================================
 // E0xxx: Pattern Matching Exhaustiveness (E0900-E0999)
@@ -76,6 +105,7 @@ pub const E0706_INVALID_SIMD_WIDTH: &str = "E0706";
 pub const E0901_NON_EXHAUSTIVE_BOOL: &str = "E0901";
 pub const E0902_NON_EXHAUSTIVE_INT: &str = "E0902";
 pub const E0903_NON_EXHAUSTIVE_STRING: &str = "E0903";
+pub const E0905_NON_EXHAUSTIVE_UNION: &str = "E0905";
 pub const E0906_INCOMPATIBLE_OR_BINDINGS: &str = "E0906";
 
 // Class extensions (`extend ClassName { ... }`)
@@ -99,6 +129,8 @@ pub const E2001_INVALID_EXEC_MODIFIER: &str = "E2001";
 pub const E2002_DUPLICATE_EXEC_MODIFIER: &str = "E2002";
 pub const E2003_INVALID_LOOP_POLICY: &str = "E2003";
 pub const E2004_UNDEFINED_INTERFACE: &str = "E2004";
+pub const E2005_NESTING_TOO_DEEP: &str = "E2005";
+pub const E2006_UNKNOWN_MOCK_TARGET: &str = "E2006";
 
 // ============================================================================
 // E3xxx: Code Generation Errors
@@ -107,6 +139,8 @@ pub const E2004_UNDEFINED_INTERFACE: &str = "E2004";
 pub const E3000_CODEGEN_ERROR: &str = "E3000";
 pub const E3001_COMPILATION_FAILED: &str = "E3001";
 pub const E3002_BUILD_FAILED: &str = "E3002";
+pub const E3003_IMPLICIT_NUMERIC_COERCION: &str = "E3003";
+pub const E3004_INVALID_GENERATED_RUST: &str = "E3004";
 
 // ============================================================================
 // E4xxx: Module System Errors
@@ -119,6 +153,9 @@ pub const E4006_SYMBOL_NOT_FOUND: &str = "E4006";
 pub const E4007_INVALID_IMPORT_SYNTAX: &str = "E4007";
 pub const E4008_EMPTY_IMPORT_LIST: &str = "E4008";
 pub const E4009_MODULE_NOT_EXPORTED: &str = "E4009";
+pub const E4010_INVALID_MANIFEST: &str = "E4010";
+pub const E4011_DEPENDENCY_UNRESOLVED: &str = "E4011";
+pub const E4012_DEPENDENCY_CONFLICT: &str = "E4012";
 
 // ============================================================================
 // E5xxx: Type System Errors
@@ -127,6 +164,19 @@ pub const E4009_MODULE_NOT_EXPORTED: &str = "E4009";
 pub const E5001_TYPE_MISMATCH: &str = "E5001";
 pub const E5002_MISSING_TRAIT_CONSTRAINT: &str = "E5002";
 pub const E5003_TYPE_ARG_COUNT_MISMATCH: &str = "E5003";
+pub const E5004_BRANCH_TYPE_MISMATCH: &str = "E5004";
+pub const E5005_INVALID_CAST: &str = "E5005";
+pub const E5006_FLOAT_GROUP_KEY: &str = "E5006";
+pub const E5007_INVALID_TYPE_OF: &str = "E5007";
+pub const E5008_UNNARROWED_UNION: &str = "E5008";
+pub const E5009_INVALID_ARRAY_CONSTRUCTOR: &str = "E5009";
+pub const E5010_NON_BOOL_CONDITION: &str = "E5010";
+
+// Strict mode (`--strict`): inference the compiler would otherwise guess at
+pub const E5101_STRICT_UNTYPED_PARAM: &str = "E5101";
+pub const E5102_STRICT_UNTYPED_FIELD: &str = "E5102";
+pub const E5103_STRICT_UNTYPED_JSON: &str = "E5103";
+pub const E5104_STRICT_IMPLICIT_TO_STRING: &str = "E5104";
 
 // ============================================================================
 // E9xxx: Rust Interop Errors
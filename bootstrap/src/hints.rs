@@ -9,6 +9,8 @@ pub fn get_hint(error_code: &str) -> Option<&'static str> {
     match error_code {
         // Parser Errors
         E2000_PARSE_ERROR => Some("Check for missing semicolons, parentheses, or keywords"),
+        E2005_NESTING_TOO_DEEP => Some("Move inner parts into `let` bindings or helper functions so no expression or block nests that deep"),
+        E2006_UNKNOWN_MOCK_TARGET => Some("`livac builtins --json` marks the builtins a test can stub with \"mockable\": true"),
 
         // Module Errors
         E4003_INVALID_MODULE_PATH => Some("Module paths should be relative (e.g., './module') or from the standard library"),
@@ -16,12 +18,18 @@ pub fn get_hint(error_code: &str) -> Option<&'static str> {
         E4006_SYMBOL_NOT_FOUND => Some("Check the module's exports or look for typos in the symbol name"),
         E4007_INVALID_IMPORT_SYNTAX => Some("Use: import { symbol1, symbol2 } from 'module'"),
         E4008_EMPTY_IMPORT_LIST => Some("Specify at least one symbol to import, or remove the import statement"),
+        E4010_INVALID_MANIFEST => Some("Check liva.toml: [dependencies] entries look like name = { path = \"...\" } or name = { git = \"...\" }"),
+        E4011_DEPENDENCY_UNRESOLVED => Some("Check the dependency's path or git URL, then run `livac install`"),
+        E4012_DEPENDENCY_CONFLICT => Some("Every manifest must point a package name at the same source"),
 
         // Concurrency Errors
+        E0403_RECURSIVE_CAPTURING_FUNCTION => Some("Pass the captured values as parameters, or move the function to the top level"),
+        E0404_ASYNC_NESTED_FUNCTION => Some("Move the function to the top level; nested functions are always synchronous"),
         E0602_DUPLICATE_EXEC_MODIFIER => Some("Use only one execution modifier: async, par, task async, or task par"),
         E0603_NOT_AWAITABLE => Some("Only async and task async expressions can be awaited"),
         E0604_AWAIT_MULTIPLE_TIMES => Some("Each async operation can only be awaited once. Store the result in a variable if needed"),
         E0605_AWAIT_IN_PARALLEL_LOOP => Some("Parallel loops execute synchronously. Use 'for async' for asynchronous iteration"),
+        E0606_UNUSED_TASK => Some("Await the task, or drop the 'task' keyword if its result isn't needed"),
 
         // Error Handling
         E0701_FALLIBLE_WITHOUT_BINDING => Some("Use error binding: let result, err = fallibleFunc(...)"),
@@ -30,10 +38,55 @@ pub fn get_hint(error_code: &str) -> Option<&'static str> {
         E0704_INVALID_THREAD_COUNT => Some("Thread count must be a positive integer, e.g., 'threads 4'"),
         E0705_SIMD_WITHOUT_VEC => Some("SIMD width requires vectorized execution: use 'for vec' or 'for parvec'"),
         E0706_INVALID_SIMD_WIDTH => Some("SIMD width must be a positive integer (typically 4, 8, 16, or 32)"),
+        E0707_UNSUPPORTED_LOOP_OPTION => Some("Remove the option; use 'chunk' or 'threads' to tune parallel loops"),
+        E0708_DUPLICATE_RUNTIME_BLOCK => Some("Merge the settings into a single 'runtime { ... }' block"),
+        E0709_UNTYPED_LAZY_VALUE => Some("Add a type annotation: lazy CONFIG: Config = loadConfig()"),
+        E0710_INVALID_RESULT_COMBINATOR => Some("Use .mapErr(fn) on a plain call with two bindings: let value, err = call().mapErr(fn)"),
+        E0711_FALLIBLE_CONSTANT => Some("Declare it lazy to keep the error for its readers: lazy CONFIG = loadConfig()"),
+        E0712_DUPLICATE_INIT_BLOCK => Some("Merge the statements into a single 'init { ... }' block"),
+        E0713_ASYNC_INIT_BLOCK => Some("Move async calls out of 'init' and into 'main'"),
+        E0714_DUPLICATE_TEST_HOOK => Some("Merge the statements into a single 'before { ... }' or 'after { ... }' block"),
+        E0715_MOCK_OUTSIDE_TEST => Some("Move the 'mock' into the 'test' block that needs it"),
+
+        // Code Generation
+        E3003_IMPLICIT_NUMERIC_COERCION => Some("Convert the JSON field explicitly with .asFloat() or .asInt(), or build with --numeric-coercion checked"),
+        E3004_INVALID_GENERATED_RUST => Some("This is a livac bug, not a problem in your program; please report it with the snippet above and the Liva code behind it"),
+
+        // Type System
+        E5004_BRANCH_TYPE_MISMATCH => Some("Make every branch produce the same type, e.g. convert with toString(value)"),
+        E5005_INVALID_CAST => Some("Use `as int`, `as float` or `as string`; only parsing a string takes error binding"),
+        E5006_FLOAT_GROUP_KEY => Some("Group by a rounded key with `as int`, or by its text with `as string`"),
+        E5007_INVALID_TYPE_OF => Some("Compare typeOf() with a built-in type tag (int, string, array, ...), a class name or a member of the union"),
+        E5008_UNNARROWED_UNION => Some("Check the member first: switch typeOf(value) { \"int\" => ..., \"string\" => ... }"),
+        E5009_INVALID_ARRAY_CONSTRUCTOR => Some("Build arrays with Array.fill(count, value), Array.from(start..end) or (start..end).toArray()"),
+        E5010_NON_BOOL_CONDITION => Some("Liva has no truthiness; compare explicitly: count != 0, name != \"\", items.length > 0, value != null"),
+
+        // Strict Mode
+        E5101_STRICT_UNTYPED_PARAM => Some("Annotate the parameter, e.g. 'count: int'; without --strict it would be guessed from its name or default to int"),
+        E5102_STRICT_UNTYPED_FIELD => Some("Annotate the field or give it an initial value to infer the type from"),
+        E5103_STRICT_UNTYPED_JSON => Some("Parse into a declared type: let user: User, err = JSON.parse(text)"),
+        E5104_STRICT_IMPLICIT_TO_STRING => Some("Convert explicitly with toString(value), or use a template: $\"total: {value}\""),
 
         // Semantic Errors
         E0001_INTERFACE_NOT_IMPL => Some("Implement all required methods or remove the interface declaration"),
         E0002_METHOD_SIGNATURE_MISMATCH => Some("Method signature must exactly match the interface definition"),
+        E0008_IMMUTABLE_RECEIVER => Some("Constants and frozen values never change; modify a `let` binding or a clone() of the value"),
+        E0009_INVALID_MAIN_SIGNATURE => Some("Declare main() with no parameters, returning nothing or an int exit code: main(): int { ... }"),
+        E0010_SHADOWED_RUNTIME_NAME => Some("Pick a name that isn't a crate the generated Rust uses and doesn't start with `liva_`"),
+        E0011_NAME_COLLISION => Some("Liva names are converted to snake_case for Rust, so myValue and my_value are the same name there"),
+        E0012_CONFLICTING_OVERLOADS => Some("Overloads of a method are told apart by argument count alone; give each a different number of parameters"),
+        E0013_NO_MATCHING_OVERLOAD => Some("Pass as many arguments as one of the method's overloads takes"),
+        E0014_AMBIGUOUS_OVERLOAD_CALL => Some("Annotate the receiver's class, e.g. let shape: Circle = ..., so the call can pick an overload"),
+        E0015_ABSTRACT_INSTANTIATION => Some("Create one of the classes implementing the abstract class instead"),
+        E0016_MISSING_ABSTRACT_METHOD => Some("A concrete class must implement every abstract method it inherits, with the same parameter count"),
+        E0017_MISPLACED_ABSTRACT => Some("Only abstract classes declare abstract methods, and they have no constructor"),
+        E0018_INVALID_DELEGATE => Some("Delegate to a field whose type is a class, and list only its public methods"),
+        E0019_DELEGATE_CONFLICT => Some("Two delegated fields forward the same method; list the methods to forward, or declare the method in the class"),
+        E0020_INVALID_CONTRACT => Some("Only 'ensures' clauses of a function that returns a value can read 'result'"),
+        E0021_SANDBOX_VIOLATION => Some("A sandboxed build only allows the capabilities the host grants with --allow"),
+        E0022_PRIVATE_ACCESS => Some("Members whose name starts with '_' can only be used inside the methods of their class; go through a public method"),
+        E0023_UNSAFE_RUST_SIGNATURE => Some("An 'unsafe rust fn' is only checked at its signature: give every parameter a plain name and a Liva type"),
+        E0024_UNDETERMINED_STRING_CONVERSION => Some("A string literal becomes a String only for a parameter or field declared 'string'; declare its type so the literal matches it"),
 
         _ => None,
     }
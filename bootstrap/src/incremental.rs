@@ -0,0 +1,817 @@
+//! Incremental compilation, for `CompilerOptions::incremental`.
+//!
+//! Editors and `livac dev` compile the same file over and over, and most of
+//! it hasn't changed since the previous run. In incremental mode the source
+//! is split into its top-level items — a line starting in column 1 begins
+//! one, together with the comments and annotations right above it — and
+//! each item is lexed and parsed on its own. Parsed items are kept per file
+//! by their name and a hash of their text, so an edit lexes and parses only
+//! the items it touches: an unchanged item is taken from the cache and moved
+//! to where it now starts, even when the items above it grew or shrank.
+//!
+//! Semantic analysis validates each function and class against the
+//! declarations of the whole program. The analyzer keys each validation by
+//! those declarations and the items up to it, and replays the validations
+//! of the previous analysis whose keys are unchanged instead of running
+//! them. Code generation is not cached per item: generating an item reads
+//! and updates state every other item shares (the classes and variables it
+//! tracks, the union types it collects), so it runs over the whole program
+//! whenever any item changed. When the source and options are exactly those
+//! of the previous in-memory compile, its result is returned as is.
+//!
+//! [`crate::check_source_incremental`], which the language server runs on
+//! every edit, parses and analyzes the same way.
+//!
+//! The last [`MAX_FILES`] files compiled are kept; the cache is shared by
+//! every compile in the process.
+
+use crate::ast::*;
+use crate::error::Result;
+use crate::span::Span;
+use crate::{parser, CompilationResult, CompilerOptions};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Write};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+/// Files whose compiles are kept; past that, the one used longest ago is
+/// forgotten
+pub const MAX_FILES: usize = 32;
+
+#[derive(Default)]
+struct Cache {
+    files: HashMap<String, FileCache>,
+    /// Counts uses, so files can be ordered by their last
+    clock: u64,
+}
+
+/// What an earlier compile of one file left behind
+#[derive(Default)]
+struct FileCache {
+    /// Parsed items by their name and the hash of their text
+    items: HashMap<ItemKey, ParsedItem>,
+    /// Validations of functions and classes by their key
+    validations: HashMap<u64, Validation>,
+    /// Fingerprint and result of the last in-memory compile
+    result: Option<(u64, CompilationResult)>,
+    /// Value of the clock when the file was last used
+    used: u64,
+}
+
+/// Identity of a top-level item's text, wherever in the file it is
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ItemKey {
+    /// The declaration line up to its parameters or body, e.g. `main` or
+    /// `type Point`
+    name: String,
+    hash: u64,
+}
+
+impl ItemKey {
+    fn new(text: &str) -> Self {
+        ItemKey {
+            name: item_name(text).to_string(),
+            hash: hash(text),
+        }
+    }
+}
+
+/// An item as parsed where it started last time
+#[derive(Clone)]
+struct ParsedItem {
+    /// Byte offset and 1-based line the item started at
+    start: usize,
+    line: usize,
+    program: Program,
+}
+
+/// What validating one function or class added to the analyzer, replayed
+/// when the item comes up again with the same key
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Validation {
+    pub(crate) inferred_types: Vec<InferredType>,
+    pub(crate) call_graph: Vec<CallEdge>,
+    pub(crate) async_functions: Vec<String>,
+    pub(crate) fallible_functions: Vec<String>,
+    pub(crate) json_classes: Vec<String>,
+}
+
+impl Cache {
+    /// The cache of `filename`, making room for it when it is new
+    fn file(&mut self, filename: &str) -> &mut FileCache {
+        self.clock += 1;
+        if !self.files.contains_key(filename) && self.files.len() >= MAX_FILES {
+            let oldest = self
+                .files
+                .iter()
+                .min_by_key(|(_, file)| file.used)
+                .map(|(name, _)| name.clone());
+            if let Some(oldest) = oldest {
+                self.files.remove(&oldest);
+            }
+        }
+        let file = self.files.entry(filename.to_string()).or_default();
+        file.used = self.clock;
+        file
+    }
+}
+
+fn cache() -> MutexGuard<'static, Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Forget everything cached for every file
+pub fn clear() {
+    cache().files.clear();
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the `Debug` form of `value`, for values that aren't `Hash`, such
+/// as syntax trees
+pub(crate) fn debug_hash<T: Debug + ?Sized>(value: &T) -> u64 {
+    struct Writer(DefaultHasher);
+    impl Write for Writer {
+        fn write_str(&mut self, text: &str) -> fmt::Result {
+            self.0.write(text.as_bytes());
+            Ok(())
+        }
+    }
+    let mut writer = Writer(DefaultHasher::new());
+    write!(writer, "{:?}", value).expect("hashing never fails");
+    writer.0.finish()
+}
+
+/// Identity of one compile: the file, its source and every option
+pub(crate) fn fingerprint(
+    source: &str,
+    filename: &str,
+    crate_name: &str,
+    options: &CompilerOptions,
+) -> u64 {
+    hash(&(source, filename, crate_name, format!("{:?}", options)))
+}
+
+/// Result of the last in-memory compile of `filename`, if it had `fingerprint`
+pub(crate) fn cached_result(filename: &str, fingerprint: u64) -> Option<CompilationResult> {
+    let mut cache = cache();
+    if !cache.files.contains_key(filename) {
+        return None;
+    }
+    match &cache.file(filename).result {
+        Some((cached, result)) if *cached == fingerprint => Some(result.clone()),
+        _ => None,
+    }
+}
+
+pub(crate) fn store_result(filename: &str, fingerprint: u64, result: &CompilationResult) {
+    cache().file(filename).result = Some((fingerprint, result.clone()));
+}
+
+/// Validations the last analysis of `filename` made, taken out of the cache
+/// so other files are analyzed meanwhile
+pub(crate) fn take_validations(filename: &str) -> HashMap<u64, Validation> {
+    std::mem::take(&mut cache().file(filename).validations)
+}
+
+/// Keep the validations an analysis of `filename` made, for the next one
+pub(crate) fn store_validations(filename: &str, validations: HashMap<u64, Validation>) {
+    cache().file(filename).validations = validations;
+}
+
+/// Byte ranges of the top-level items of `source`, covering all of it
+pub fn split_items(source: &str) -> Vec<Range<usize>> {
+    let mut starts = vec![0];
+    let mut offset = 0;
+    // The last non-blank line is a comment or annotation in column 1, which
+    // belongs to the item below it
+    let mut attached = false;
+    let mut in_block_comment = false;
+    for line in source.split_inclusive('\n') {
+        if !in_block_comment && !line.trim().is_empty() {
+            let column_one = !matches!(line.chars().next(), Some(' ' | '\t' | '}' | ')' | ']'));
+            if column_one && !attached && offset > 0 {
+                starts.push(offset);
+            }
+            attached = column_one
+                && (line.starts_with("//") || line.starts_with("/*") || line.starts_with('@'));
+        }
+        in_block_comment = ends_in_block_comment(line, in_block_comment);
+        offset += line.len();
+    }
+    starts.push(source.len());
+    starts.windows(2).map(|pair| pair[0]..pair[1]).collect()
+}
+
+/// Whether a `/* */` comment is still open at the end of `line`; strings
+/// aren't told apart, which at worst merges two items into one
+fn ends_in_block_comment(line: &str, mut inside: bool) -> bool {
+    let mut rest = line;
+    loop {
+        let marker = if inside { "*/" } else { "/*" };
+        match rest.find(marker) {
+            Some(at) => {
+                inside = !inside;
+                rest = &rest[at + marker.len()..];
+            }
+            None => return inside,
+        }
+    }
+}
+
+/// The declaration line of an item — the first one that isn't blank, a
+/// comment or an annotation — up to its parameters, body or type
+fn item_name(text: &str) -> &str {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| {
+            !line.is_empty()
+                && !line.starts_with("//")
+                && !line.starts_with("/*")
+                && !line.starts_with('*')
+                && !line.starts_with('@')
+        })
+        .unwrap_or("");
+    let end = line.find(['(', '{', '=', '<', ':']).unwrap_or(line.len());
+    line[..end].trim_end()
+}
+
+/// Parse `source`, the contents of `filename`, reusing the items that are
+/// unchanged since the last incremental parse of the file
+///
+/// An item that doesn't parse on its own — a syntax error, or a split the
+/// column-1 rule got wrong — makes the whole file parse in one piece, so
+/// errors are the ones a normal parse reports.
+pub fn parse(filename: &str, source: &str) -> Result<Program> {
+    parse_counting(filename, source).map(|(program, _)| program)
+}
+
+/// [`parse`], also returning how many items had to be lexed and parsed
+fn parse_counting(filename: &str, source: &str) -> Result<(Program, usize)> {
+    // Taken out of the cache so other files compile meanwhile
+    let previous = std::mem::take(&mut cache().file(filename).items);
+    let items_source = parser::ItemSource::new(source);
+    let mut items = HashMap::new();
+    let mut program = Program {
+        items: Vec::new(),
+        inferred_types: Vec::new(),
+        call_graph: Vec::new(),
+        signatures: Vec::new(),
+        symbols: Default::default(),
+        comments: Vec::new(),
+    };
+    let mut parsed = 0;
+    for range in split_items(source) {
+        let key = ItemKey::new(&source[range.clone()]);
+        let line = items_source.line(range.start);
+        let part = match previous.get(&key) {
+            Some(item) => {
+                let mut part = item.program.clone();
+                Relocation {
+                    bytes: range.start as isize - item.start as isize,
+                    lines: line as i64 - item.line as i64,
+                }
+                .program(&mut part);
+                part
+            }
+            None => match items_source.parse(range.clone()) {
+                Ok(part) => {
+                    parsed += 1;
+                    part
+                }
+                Err(_) => return Ok((items_source.parse(0..source.len())?, parsed)),
+            },
+        };
+        program.items.extend(part.items.iter().cloned());
+        program.comments.extend(part.comments.iter().cloned());
+        let item = ParsedItem {
+            start: range.start,
+            line,
+            program: part,
+        };
+        items.insert(key, item);
+    }
+    cache().file(filename).items = items;
+    Ok((program, parsed))
+}
+
+/// Moves the spans and line numbers of a parsed item from where it was
+/// parsed to where it is now. The matches are exhaustive so a new syntax
+/// node with a position can't be missed.
+struct Relocation {
+    bytes: isize,
+    lines: i64,
+}
+
+impl Relocation {
+    fn program(&self, program: &mut Program) {
+        for item in &mut program.items {
+            self.item(item);
+        }
+        for comment in &mut program.comments {
+            self.span(&mut comment.span);
+        }
+    }
+
+    fn span(&self, span: &mut Span) {
+        span.start = span.start.saturating_add_signed(self.bytes);
+        span.end = span.end.saturating_add_signed(self.bytes);
+    }
+
+    fn opt_span(&self, span: &mut Option<Span>) {
+        if let Some(span) = span {
+            self.span(span);
+        }
+    }
+
+    /// Line numbers are 0 where the parser recorded none
+    fn line(&self, line: &mut u32) {
+        if *line > 0 {
+            *line = (*line as i64 + self.lines) as u32;
+        }
+    }
+
+    fn item(&self, item: &mut TopLevel) {
+        match item {
+            TopLevel::Import(_)
+            | TopLevel::UseRust(_)
+            | TopLevel::Enum(_)
+            | TopLevel::Runtime(_) => {}
+            TopLevel::Type(decl) => {
+                self.members(&mut decl.members);
+                self.opt_span(&mut decl.span);
+            }
+            TopLevel::TypeAlias(decl) => self.opt_span(&mut decl.span),
+            TopLevel::Class(decl) => {
+                self.members(&mut decl.members);
+                self.opt_span(&mut decl.span);
+            }
+            TopLevel::Function(func) => self.function(func),
+            TopLevel::Test(test) => {
+                self.block(&mut test.body);
+                self.opt_span(&mut test.span);
+            }
+            TopLevel::ConstDecl(decl) => self.const_decl(decl),
+            TopLevel::ExprStmt(expr) => self.expr(expr),
+            TopLevel::ClassExtension(extension) => {
+                for method in &mut extension.methods {
+                    self.method(method);
+                }
+                self.opt_span(&mut extension.span);
+            }
+            TopLevel::Init(init) => {
+                self.block(&mut init.body);
+                self.opt_span(&mut init.span);
+            }
+            TopLevel::TestHook(hook) => {
+                self.block(&mut hook.body);
+                self.opt_span(&mut hook.span);
+            }
+        }
+    }
+
+    fn members(&self, members: &mut [Member]) {
+        for member in members {
+            match member {
+                Member::Field(field) => {
+                    if let Some(init) = &mut field.init {
+                        self.expr(init);
+                    }
+                }
+                Member::Method(method) => self.method(method),
+            }
+        }
+    }
+
+    fn params(&self, params: &mut [Param]) {
+        for param in params {
+            if let Some(default) = &mut param.default {
+                self.expr(default);
+            }
+        }
+    }
+
+    fn method(&self, method: &mut MethodDecl) {
+        self.params(&mut method.params);
+        if let Some(body) = &mut method.body {
+            self.block(body);
+        }
+        if let Some(expr) = &mut method.expr_body {
+            self.expr(expr);
+        }
+        self.opt_span(&mut method.span);
+    }
+
+    fn function(&self, func: &mut FunctionDecl) {
+        self.params(&mut func.params);
+        if let Some(body) = &mut func.body {
+            self.block(body);
+        }
+        if let Some(expr) = &mut func.expr_body {
+            self.expr(expr);
+        }
+        for contract in func.requires.iter_mut().chain(&mut func.ensures) {
+            self.expr(&mut contract.condition);
+            self.line(&mut contract.line);
+        }
+        self.opt_span(&mut func.span);
+    }
+
+    fn const_decl(&self, decl: &mut ConstDecl) {
+        self.expr(&mut decl.init);
+        self.opt_span(&mut decl.span);
+    }
+
+    fn block(&self, block: &mut BlockStmt) {
+        self.stmts(&mut block.stmts);
+        for line in &mut block.stmt_lines {
+            self.line(line);
+        }
+        self.opt_span(&mut block.span);
+    }
+
+    fn stmts(&self, stmts: &mut [Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn if_body(&self, body: &mut IfBody) {
+        match body {
+            IfBody::Block(block) => self.block(block),
+            IfBody::Stmt(stmt) => self.stmt(stmt),
+        }
+    }
+
+    fn stmt(&self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                for binding in &mut decl.bindings {
+                    self.opt_span(&mut binding.span);
+                }
+                self.expr(&mut decl.init);
+                if let Some(msg) = &mut decl.or_fail_msg {
+                    self.expr(msg);
+                }
+                if let Some(value) = &mut decl.or_value {
+                    self.expr(value);
+                }
+                match &mut decl.combinator {
+                    Some(ResultCombinator::MapErr(expr)) => self.expr(expr),
+                    Some(ResultCombinator::OrElse | ResultCombinator::Expect) | None => {}
+                }
+                self.line(&mut decl.or_fail_line);
+                self.opt_span(&mut decl.span);
+            }
+            Stmt::ConstDecl(decl) => self.const_decl(decl),
+            Stmt::Assign(assign) => {
+                self.expr(&mut assign.target);
+                self.expr(&mut assign.value);
+                self.opt_span(&mut assign.span);
+            }
+            Stmt::If(if_stmt) => {
+                for (condition, body) in if_stmt.branches_mut() {
+                    self.expr(condition);
+                    self.if_body(body);
+                }
+                if let Some(body) = &mut if_stmt.else_branch {
+                    self.if_body(body);
+                }
+            }
+            Stmt::While(while_stmt) => {
+                self.expr(&mut while_stmt.condition);
+                self.block(&mut while_stmt.body);
+            }
+            Stmt::For(for_stmt) => {
+                self.expr(&mut for_stmt.iterable);
+                self.block(&mut for_stmt.body);
+            }
+            Stmt::Switch(switch) => {
+                self.expr(&mut switch.discriminant);
+                for case in &mut switch.cases {
+                    self.expr(&mut case.value);
+                    self.stmts(&mut case.body);
+                }
+                if let Some(default) = &mut switch.default {
+                    self.stmts(default);
+                }
+            }
+            Stmt::TryCatch(try_catch) => {
+                self.block(&mut try_catch.try_block);
+                self.block(&mut try_catch.catch_block);
+            }
+            Stmt::Throw(throw) => self.expr(&mut throw.expr),
+            Stmt::Fail(fail) => {
+                self.expr(&mut fail.expr);
+                self.line(&mut fail.line);
+            }
+            Stmt::Return(ret) => {
+                if let Some(expr) = &mut ret.expr {
+                    self.expr(expr);
+                }
+            }
+            Stmt::Defer(defer) => self.stmt(&mut defer.body),
+            Stmt::Scope(scope) => self.block(&mut scope.body),
+            Stmt::Mock(mock) => self.expr(&mut mock.value),
+            Stmt::Break | Stmt::Continue => {}
+            Stmt::Expr(expr_stmt) => self.expr(&mut expr_stmt.expr),
+            Stmt::Block(block) => self.block(block),
+            Stmt::Function(func) => self.function(func),
+        }
+    }
+
+    fn switch_body(&self, body: &mut SwitchBody) {
+        match body {
+            SwitchBody::Expr(expr) => self.expr(expr),
+            SwitchBody::Block(stmts) => self.stmts(stmts),
+        }
+    }
+
+    fn pattern(&self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Literal(_)
+            | Pattern::Wildcard
+            | Pattern::Binding(_)
+            | Pattern::Typed { .. }
+            | Pattern::EnumVariant { .. } => {}
+            Pattern::Range(range) => {
+                for bound in [&mut range.start, &mut range.end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+            Pattern::Tuple(patterns) | Pattern::Array(patterns) | Pattern::Or(patterns) => {
+                for pattern in patterns {
+                    self.pattern(pattern);
+                }
+            }
+        }
+    }
+
+    fn exprs(&self, exprs: &mut [Expr]) {
+        for expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&self, expr: &mut Expr) {
+        match expr {
+            Expr::Literal(_)
+            | Expr::Identifier(_)
+            | Expr::MethodRef { .. }
+            | Expr::RustBlock { .. } => {}
+            Expr::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => {
+                self.expr(condition);
+                self.expr(then_expr);
+                self.expr(else_expr);
+            }
+            Expr::Call(call) => {
+                self.expr(&mut call.callee);
+                self.exprs(&mut call.args);
+            }
+            Expr::Index { object, index } => {
+                self.expr(object);
+                self.expr(index);
+            }
+            Expr::ObjectLiteral(fields) | Expr::StructLiteral { fields, .. } => {
+                for (_, value) in fields {
+                    self.expr(value);
+                }
+            }
+            Expr::ArrayLiteral(elements) | Expr::SetLiteral(elements) | Expr::Tuple(elements) => {
+                self.exprs(elements)
+            }
+            Expr::MapLiteral(entries) => {
+                for (key, value) in entries {
+                    self.expr(key);
+                    self.expr(value);
+                }
+            }
+            Expr::Lambda(lambda) => {
+                for param in &mut lambda.params {
+                    self.opt_span(&mut param.span);
+                }
+                match &mut lambda.body {
+                    LambdaBody::Expr(body) => self.expr(body),
+                    LambdaBody::Block(block) => self.block(block),
+                }
+            }
+            // Interpolations are parsed from the template's own text, so
+            // their spans are relative to it and don't move
+            Expr::StringTemplate { .. } => {}
+            Expr::MethodCall(call) => {
+                self.expr(&mut call.object);
+                self.exprs(&mut call.args);
+                if let Some(comprehension) = &mut call.comprehension {
+                    self.opt_span(&mut comprehension.span);
+                }
+            }
+            Expr::Switch(switch) => {
+                self.expr(&mut switch.discriminant);
+                for arm in &mut switch.arms {
+                    self.pattern(&mut arm.pattern);
+                    if let Some(guard) = &mut arm.guard {
+                        self.expr(guard);
+                    }
+                    self.switch_body(&mut arm.body);
+                }
+            }
+            Expr::If(if_expr) => {
+                self.expr(&mut if_expr.condition);
+                self.switch_body(&mut if_expr.then_branch);
+                self.switch_body(&mut if_expr.else_branch);
+            }
+            Expr::Unary { operand: inner, .. }
+            | Expr::Member { object: inner, .. }
+            | Expr::Fail(inner)
+            | Expr::Cast { expr: inner, .. }
+            | Expr::Unwrap(inner)
+            | Expr::Try(inner)
+            | Expr::OptionalChain { object: inner, .. } => self.expr(inner),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancel::CancellationToken;
+    use crate::diagnostics::DiagnosticSink;
+    use crate::{lexer, semantic};
+
+    const SOURCE: &str = r#"import { helper } from "./helper"
+
+/// Adds one
+inc(n: number): number {
+    return n + 1
+}
+
+@inline
+double(n: number): number => n * 2
+
+main() {
+    print(inc(1), double(2))
+}
+"#;
+
+    #[test]
+    fn test_split_keeps_comments_with_items() {
+        let items: Vec<&str> = split_items(SOURCE)
+            .into_iter()
+            .map(|range| &SOURCE[range])
+            .collect();
+        assert_eq!(items.len(), 4);
+        assert!(items[1].starts_with("/// Adds one\ninc("));
+        assert!(items[2].starts_with("@inline\ndouble("));
+        assert!(items[3].starts_with("main()"));
+    }
+
+    #[test]
+    fn test_matches_full_parse() {
+        let (program, parsed) = parse_counting("test_matches_full_parse", SOURCE).unwrap();
+        let full = parser::parse(lexer::tokenize(SOURCE).unwrap(), SOURCE).unwrap();
+        assert_eq!(parsed, 4);
+        assert_eq!(program, full);
+    }
+
+    #[test]
+    fn test_reparses_only_changed_items() {
+        let file = "test_reparses_only_changed_items";
+        parse_counting(file, SOURCE).unwrap();
+        let (_, parsed) = parse_counting(file, SOURCE).unwrap();
+        assert_eq!(parsed, 0);
+
+        // Only `inc` changed; `double` and `main` move down a line and keep
+        // their parse
+        let edited = SOURCE.replace("return n + 1", "let m = n + 1\n    return m");
+        let (program, parsed) = parse_counting(file, &edited).unwrap();
+        assert_eq!(parsed, 1);
+        let full = parser::parse(lexer::tokenize(&edited).unwrap(), &edited).unwrap();
+        assert_eq!(program, full);
+    }
+
+    #[test]
+    fn test_moved_items_match_full_parse() {
+        let file = "test_moved_items_match_full_parse";
+        let source = r#"// Shapes
+type Shape = Circle | Square
+
+Circle {
+    radius: number = 1
+    area(): number => 3.14 * this.radius * this.radius
+}
+
+Square {
+    side: number
+}
+
+describe(radius: number): string {
+    /* the size */
+    let size = switch radius {
+        0..=1 => "small",
+        r if r > 10 => "huge",
+        _ => "big"
+    }
+    let label = if size == "big" { "large" } else { "tiny" }
+    let data = load() or fail "no data"
+    for i in 0..3 {
+        defer print(i)
+    }
+    return $"{size} {label} {[1, 2].map(x => x * 2)}"
+}
+
+main() {
+    print(describe(Circle(2).radius))
+}
+"#;
+        parse_counting(file, source).unwrap();
+
+        // Only the new import is parsed; everything below it moves down
+        let edited = format!("import {{ load }} from \"./data\"\n\n{}", source);
+        let (program, parsed) = parse_counting(file, &edited).unwrap();
+        assert_eq!(parsed, 1);
+        let full = parser::parse(lexer::tokenize(&edited).unwrap(), &edited).unwrap();
+        assert_eq!(program, full);
+
+        // Removing the import moves every item back up
+        let (program, parsed) = parse_counting(file, source).unwrap();
+        assert_eq!(parsed, 0);
+        let full = parser::parse(lexer::tokenize(source).unwrap(), source).unwrap();
+        assert_eq!(program, full);
+    }
+
+    #[test]
+    fn test_reanalysis_replays_unchanged_items() {
+        let file = "test_reanalysis_replays_unchanged_items";
+        let source = "Counter {\n    count: number\n    bump(): number => this.count + 1\n}\n\ninc(n: number): number {\n    return n + 1\n}\n\nmain() {\n    let c = Counter(1)\n    print(inc(c.bump()))\n}\n";
+        let analyze = |source: &str, incremental: bool| {
+            let options = CompilerOptions {
+                incremental,
+                ..CompilerOptions::default()
+            };
+            let program = parser::parse(lexer::tokenize(source).unwrap(), source).unwrap();
+            semantic::analyze_cancellable(
+                program,
+                file.to_string(),
+                source.to_string(),
+                None,
+                &options,
+                &CancellationToken::new(),
+                &mut DiagnosticSink::new(),
+            )
+            .unwrap()
+        };
+        let keys =
+            |file: &str| -> Vec<u64> { cache().file(file).validations.keys().copied().collect() };
+
+        analyze(source, true);
+        let first = keys(file);
+        assert_eq!(first.len(), 3);
+
+        // The class and `inc` are above the edit, so only `main` runs again
+        let edited = source.replace("inc(c.bump())", "c.bump() + 1");
+        let program = analyze(&edited, true);
+        let second = keys(file);
+        assert_eq!(second.iter().filter(|key| first.contains(key)).count(), 2);
+        assert_eq!(program, analyze(&edited, false));
+    }
+
+    #[test]
+    fn test_cache_forgets_the_file_used_longest_ago() {
+        let mut cache = Cache::default();
+        for n in 0..MAX_FILES {
+            cache.file(&format!("file{}", n));
+        }
+        cache.file("file0");
+        cache.file("one more");
+        assert_eq!(cache.files.len(), MAX_FILES);
+        assert!(cache.files.contains_key("file0"));
+        assert!(!cache.files.contains_key("file1"));
+    }
+
+    #[test]
+    fn test_syntax_error_reported_by_full_parse() {
+        let source = "main() {\n    let x = \n}\n";
+        let err = parse("test_syntax_error_reported_by_full_parse", source).unwrap_err();
+        let full = parser::parse(lexer::tokenize(source).unwrap(), source).unwrap_err();
+        assert_eq!(err.to_string(), full.to_string());
+    }
+}
@@ -0,0 +1,629 @@
+//! Inline hints for small Liva functions, across modules.
+//!
+//! Every Liva module becomes a Rust module in one crate, so rustc can inline
+//! across them — but it only does so eagerly for functions it considers
+//! small, and the task/format machinery codegen wraps around a Liva call
+//! often pushes tiny helpers over that limit. The inlining itself is left to
+//! rustc: before codegen runs, [`plan`] looks at every top-level function of
+//! every module and decides which ones get an `#[inline]` attribute:
+//!
+//! - `@inline` / `@noinline` on the declaration always win
+//! - otherwise a function is inlined when its body is at most
+//!   `CompilerOptions::inline_threshold` AST nodes and it is not recursive
+//!   (directly or through other functions)
+//!
+//! A threshold of 0 turns the automatic decisions off; annotations still apply.
+//!
+//! [`inline_calls`] replaces calls in the AST only for the smallest helpers:
+//! a call of a function whose expression body only combines its `int`,
+//! `number` and `bool` parameters with operators becomes that body, so no
+//! Rust call is left at all. The arguments have to be variables or literals,
+//! which makes pasting them into the body mean the same as passing them.
+//! Every other call stays a call, with the attribute as a hint.
+//!
+//! Functions are told apart by module, so two modules can each have their
+//! own `format`. A call by name lands on the module's own function, or on
+//! the one function of that name the module imports.
+
+use crate::ast::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Default body size (in AST nodes) under which functions are inlined
+pub const DEFAULT_INLINE_THRESHOLD: usize = 16;
+
+/// Attribute codegen puts on a lowered function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlineDecision {
+    /// Small, non-recursive: `#[inline]`
+    Inline,
+    /// `@inline`: `#[inline(always)]`
+    Always,
+    /// `@noinline`: `#[inline(never)]`
+    Never,
+}
+
+impl InlineDecision {
+    pub fn rust_attribute(self) -> &'static str {
+        match self {
+            InlineDecision::Inline => "#[inline]",
+            InlineDecision::Always => "#[inline(always)]",
+            InlineDecision::Never => "#[inline(never)]",
+        }
+    }
+}
+
+/// Decisions by module path, then by top-level function name
+pub type InlinePlan = BTreeMap<PathBuf, BTreeMap<String, InlineDecision>>;
+
+/// A top-level function: the index of its module and its name
+type Node = (usize, String);
+
+/// Decide the inline attribute of every top-level function in `modules`,
+/// given with their paths. Functions that should keep rustc's default get
+/// no entry.
+pub fn plan<'a>(
+    modules: impl IntoIterator<Item = (&'a Path, &'a Program)>,
+    threshold: usize,
+) -> InlinePlan {
+    let modules: Vec<(&Path, &Program)> = modules.into_iter().collect();
+    let resolver = Resolver::new(modules.iter().map(|(_, program)| *program));
+
+    let mut calls: HashMap<Node, HashSet<Node>> = HashMap::new();
+    let mut small: HashSet<Node> = HashSet::new();
+    for (module, (_, program)) in modules.iter().enumerate() {
+        for func in functions(program) {
+            let mut counter = BodyCounter::default();
+            counter.visit_function(func);
+            let node = (module, func.name.clone());
+            if !counter.opaque && counter.nodes <= threshold {
+                small.insert(node.clone());
+            }
+            let callees = counter
+                .callees
+                .iter()
+                .filter(|callee| !counter.nested.contains(*callee))
+                .filter_map(|callee| Some((resolver.resolve(module, callee)?, callee.clone())))
+                .collect();
+            calls.insert(node, callees);
+        }
+    }
+
+    let mut decisions = InlinePlan::new();
+    for (module, (path, program)) in modules.iter().enumerate() {
+        for func in functions(program) {
+            let node = (module, func.name.clone());
+            let decision = match func.inline {
+                Some(InlineHint::Always) => Some(InlineDecision::Always),
+                Some(InlineHint::Never) => Some(InlineDecision::Never),
+                None if func.name == "main" || threshold == 0 => None,
+                None => (small.contains(&node) && !is_recursive(&node, &calls))
+                    .then_some(InlineDecision::Inline),
+            };
+            if let Some(decision) = decision {
+                decisions
+                    .entry(path.to_path_buf())
+                    .or_default()
+                    .insert(func.name.clone(), decision);
+            }
+        }
+    }
+    decisions
+}
+
+/// Replace calls of the functions in `programs` whose body can stand in for
+/// the call, within and across the programs. `@noinline` keeps a function's
+/// calls, `@inline` replaces them whatever the body's size, and a threshold
+/// of 0 leaves the rest alone.
+pub fn inline_calls(programs: &mut [&mut Program], threshold: usize) {
+    let resolver = Resolver::new(programs.iter().map(|program| &**program));
+    let mut candidates = HashMap::new();
+    for (module, program) in programs.iter().enumerate() {
+        for func in functions(program) {
+            if let Some(inlinable) = Inlinable::of(func, threshold) {
+                candidates.insert((module, func.name.clone()), inlinable);
+            }
+        }
+    }
+    if candidates.is_empty() {
+        return;
+    }
+    for (module, program) in programs.iter_mut().enumerate() {
+        let mut inliner = Inliner {
+            module,
+            resolver: &resolver,
+            candidates: &candidates,
+            local: HashSet::new(),
+        };
+        inliner.visit_program(program);
+    }
+}
+
+fn functions(program: &Program) -> impl Iterator<Item = &FunctionDecl> {
+    program.items.iter().filter_map(|item| match item {
+        TopLevel::Function(func) => Some(func),
+        _ => None,
+    })
+}
+
+/// Whether `node` can reach itself through the call graph
+fn is_recursive(node: &Node, calls: &HashMap<Node, HashSet<Node>>) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack: Vec<&Node> = calls
+        .get(node)
+        .map(|c| c.iter().collect())
+        .unwrap_or_default();
+    while let Some(current) = stack.pop() {
+        if current == node {
+            return true;
+        }
+        if seen.insert(current) {
+            if let Some(next) = calls.get(current) {
+                stack.extend(next.iter());
+            }
+        }
+    }
+    false
+}
+
+/// Where a call by name lands: the module's own top-level function, or the
+/// one function of that name in another module, when the module imports it
+struct Resolver {
+    /// Top-level function names of each module
+    defined: Vec<HashSet<String>>,
+    /// Names each module imports under their own name
+    imported: Vec<HashSet<String>>,
+    /// Whether each module has an `import *` without an alias
+    wildcard: Vec<bool>,
+}
+
+impl Resolver {
+    fn new<'a>(programs: impl Iterator<Item = &'a Program>) -> Self {
+        let mut resolver = Resolver {
+            defined: Vec::new(),
+            imported: Vec::new(),
+            wildcard: Vec::new(),
+        };
+        for program in programs {
+            resolver
+                .defined
+                .push(functions(program).map(|func| func.name.clone()).collect());
+            let imports = program.items.iter().filter_map(|item| match item {
+                TopLevel::Import(import) => Some(import),
+                _ => None,
+            });
+            let mut names = HashSet::new();
+            let mut wildcard = false;
+            for import in imports {
+                wildcard |= import.is_wildcard && import.alias.is_none();
+                names.extend(
+                    import
+                        .imports
+                        .iter()
+                        .filter(|name| !import.renames.contains_key(*name))
+                        .cloned(),
+                );
+            }
+            resolver.imported.push(names);
+            resolver.wildcard.push(wildcard);
+        }
+        resolver
+    }
+
+    /// The module defining the function a call of `name` in `module` lands on
+    fn resolve(&self, module: usize, name: &str) -> Option<usize> {
+        if self.defined[module].contains(name) {
+            return Some(module);
+        }
+        if !self.wildcard[module] && !self.imported[module].contains(name) {
+            return None;
+        }
+        let mut definers = (0..self.defined.len())
+            .filter(|&other| other != module && self.defined[other].contains(name));
+        match (definers.next(), definers.next()) {
+            (Some(other), None) => Some(other),
+            _ => None,
+        }
+    }
+}
+
+/// A function whose calls can be replaced by its body: an expression body
+/// that only combines its `int` and `bool` parameters with operators, so it
+/// means the same wherever it is pasted
+struct Inlinable {
+    params: Vec<String>,
+    body: Expr,
+}
+
+impl Inlinable {
+    fn of(func: &FunctionDecl, threshold: usize) -> Option<Self> {
+        let wanted = match func.inline {
+            Some(InlineHint::Never) => false,
+            Some(InlineHint::Always) => true,
+            None => threshold > 0,
+        };
+        if !wanted
+            || func.name == "main"
+            || func.body.is_some()
+            || !func.type_params.is_empty()
+            || !func.requires.is_empty()
+            || !func.ensures.is_empty()
+            || func.unsafe_rust
+            || func.is_async_inferred
+            || func.contains_fail
+            || !func.captures.is_empty()
+            || !is_scalar(func.return_type.as_ref())
+        {
+            return None;
+        }
+        let body = func.expr_body.as_ref()?;
+        let params = func
+            .params
+            .iter()
+            .map(|param| match (param.name(), &param.default) {
+                (Some(name), None) if is_scalar(param.type_ref.as_ref()) => Some(name.to_string()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let mut counter = BodyCounter::default();
+        counter.visit_expr(body);
+        if !is_pure(body, &params) || (func.inline.is_none() && counter.nodes > threshold) {
+            return None;
+        }
+        Some(Inlinable {
+            params,
+            body: body.clone(),
+        })
+    }
+
+    /// The body with `args` in place of the parameters, when they are
+    /// variables or literals
+    fn call(&self, args: &[Expr]) -> Option<Expr> {
+        let atoms = args.iter().all(|arg| {
+            matches!(
+                arg,
+                Expr::Identifier(_) | Expr::Literal(Literal::Int(_) | Literal::Bool(_))
+            )
+        });
+        if !atoms || args.len() != self.params.len() {
+            return None;
+        }
+        let mut body = self.body.clone();
+        substitute(&mut body, &self.params, args);
+        Some(body)
+    }
+}
+
+fn is_scalar(type_ref: Option<&TypeRef>) -> bool {
+    matches!(type_ref, Some(TypeRef::Simple(name)) if matches!(name.as_str(), "int" | "number" | "bool"))
+}
+
+/// Whether `expr` is made of `params`, `int` and `bool` literals and
+/// operators that read nothing else
+fn is_pure(expr: &Expr, params: &[String]) -> bool {
+    match expr {
+        Expr::Literal(Literal::Int(_) | Literal::Bool(_)) => true,
+        Expr::Identifier(name) => params.contains(name),
+        Expr::Binary { op, left, right } => {
+            !matches!(op, BinOp::Coalesce | BinOp::Range | BinOp::RangeInclusive)
+                && is_pure(left, params)
+                && is_pure(right, params)
+        }
+        Expr::Unary { op, operand } => !matches!(op, UnOp::Await) && is_pure(operand, params),
+        Expr::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        } => [condition, then_expr, else_expr]
+            .into_iter()
+            .all(|e| is_pure(e, params)),
+        _ => false,
+    }
+}
+
+/// Put `args` in place of `params` in an expression [`is_pure`] accepted
+fn substitute(expr: &mut Expr, params: &[String], args: &[Expr]) {
+    Substitution { params, args }.visit_expr(expr);
+}
+
+struct Substitution<'a> {
+    params: &'a [String],
+    args: &'a [Expr],
+}
+
+impl VisitorMut for Substitution<'_> {
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        let param = match expr {
+            Expr::Identifier(name) => self.params.iter().position(|param| param == name),
+            _ => None,
+        };
+        match param {
+            Some(index) => *expr = self.args[index].clone(),
+            None => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+/// Counts the AST nodes of a function body and collects the free functions it calls
+#[derive(Default)]
+struct BodyCounter {
+    nodes: usize,
+    callees: HashSet<String>,
+    /// Functions declared in the body, which its calls by their names reach
+    nested: HashSet<String>,
+    /// Contains a `rust { }` block, whose cost can't be measured
+    opaque: bool,
+}
+
+impl Visitor<'_> for BodyCounter {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.nodes += 1;
+        if let Stmt::Function(func) = stmt {
+            self.nested.insert(func.name.clone());
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.nodes += 1;
+        match expr {
+            Expr::Call(call) => {
+                if let Expr::Identifier(name) = call.callee.as_ref() {
+                    self.callees.insert(name.clone());
+                }
+            }
+            Expr::MethodRef { method, .. } => {
+                self.callees.insert(method.clone());
+            }
+            Expr::RustBlock { .. } => self.opaque = true,
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+/// Replaces calls in one module's items, see [`inline_calls`]
+struct Inliner<'a> {
+    module: usize,
+    resolver: &'a Resolver,
+    candidates: &'a HashMap<Node, Inlinable>,
+    /// Names declared in the item walked so far, which a call by that name
+    /// may reach instead of a top-level function
+    local: HashSet<String>,
+}
+
+impl Inliner<'_> {
+    fn declare(&mut self, pattern: &BindingPattern) {
+        self.local.extend(pattern.names());
+    }
+
+    /// What replaces `call`, if it calls an [`Inlinable`] function
+    fn inlined(&self, call: &CallExpr) -> Option<Expr> {
+        let Expr::Identifier(name) = call.callee.as_ref() else {
+            return None;
+        };
+        if call.exec_policy != ExecPolicy::Normal
+            || !call.type_args.is_empty()
+            || self.local.contains(name)
+        {
+            return None;
+        }
+        let module = self.resolver.resolve(self.module, name)?;
+        self.candidates
+            .get(&(module, name.clone()))?
+            .call(&call.args)
+    }
+}
+
+impl VisitorMut for Inliner<'_> {
+    fn visit_item(&mut self, item: &mut TopLevel) {
+        self.local.clear();
+        walk_item_mut(self, item);
+    }
+
+    fn visit_function(&mut self, func: &mut FunctionDecl) {
+        for param in &func.params {
+            self.declare(&param.pattern);
+        }
+        walk_function_mut(self, func);
+    }
+
+    fn visit_method(&mut self, method: &mut MethodDecl) {
+        for param in &method.params {
+            self.declare(&param.pattern);
+        }
+        walk_method_mut(self, method);
+    }
+
+    fn visit_stmts(&mut self, stmts: &mut [Stmt]) {
+        // A nested function can be called before its declaration
+        for stmt in stmts.iter() {
+            if let Stmt::Function(func) = stmt {
+                self.local.insert(func.name.clone());
+            }
+        }
+        walk_stmts_mut(self, stmts);
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                let names: Vec<String> = decl
+                    .bindings
+                    .iter()
+                    .flat_map(|binding| binding.pattern.names())
+                    .collect();
+                walk_stmt_mut(self, stmt);
+                self.local.extend(names);
+            }
+            Stmt::ConstDecl(decl) => {
+                self.visit_expr(&mut decl.init);
+                self.local.insert(decl.name.clone());
+            }
+            Stmt::For(for_stmt) => {
+                self.visit_expr(&mut for_stmt.iterable);
+                self.local.insert(for_stmt.var.clone());
+                self.local.extend(for_stmt.var2.iter().cloned());
+                self.visit_block(&mut for_stmt.body);
+            }
+            Stmt::TryCatch(try_catch) => {
+                self.visit_block(&mut try_catch.try_block);
+                self.local.insert(try_catch.catch_var.clone());
+                self.visit_block(&mut try_catch.catch_block);
+            }
+            // A call whose value is dropped stays a call; its body alone
+            // would be a statement without effect
+            Stmt::Expr(ExprStmt {
+                expr: Expr::Call(call),
+            }) => call.args.iter_mut().for_each(|arg| self.visit_expr(arg)),
+            _ => walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Lambda(lambda) => {
+                for param in &lambda.params {
+                    self.declare(&param.pattern);
+                }
+            }
+            Expr::Switch(switch) => {
+                for arm in &switch.arms {
+                    self.local.extend(arm.pattern.names());
+                }
+            }
+            _ => {}
+        }
+        walk_expr_mut(self, expr);
+        if let Expr::Call(call) = expr {
+            if let Some(body) = self.inlined(call) {
+                *expr = body;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    fn parse_source(source: &str) -> Program {
+        parse(tokenize(source).unwrap(), source).unwrap()
+    }
+
+    fn plan_source(source: &str, threshold: usize) -> BTreeMap<String, InlineDecision> {
+        let program = parse_source(source);
+        plan([(Path::new(""), &program)], threshold)
+            .remove(Path::new(""))
+            .unwrap_or_default()
+    }
+
+    /// The expression `main` returns once calls are inlined
+    fn returned(program: &Program) -> &Expr {
+        functions(program)
+            .find(|func| func.name == "main")
+            .and_then(|main| main.expr_body.as_ref())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_small_functions_are_inlined() {
+        let plan = plan_source(
+            "double(x: number): number => x * 2",
+            DEFAULT_INLINE_THRESHOLD,
+        );
+        assert_eq!(plan.get("double"), Some(&InlineDecision::Inline));
+    }
+
+    #[test]
+    fn test_recursive_functions_are_not_inlined() {
+        let source = r#"
+            fact(n: number): number => n <= 1 ? 1 : n * fact(n - 1)
+            isEven(n: number): bool => n == 0 ? true : isOdd(n - 1)
+            isOdd(n: number): bool => n == 0 ? false : isEven(n - 1)
+        "#;
+        let plan = plan_source(source, DEFAULT_INLINE_THRESHOLD);
+        assert!(plan.is_empty(), "{:?}", plan);
+    }
+
+    #[test]
+    fn test_annotations_override_threshold() {
+        let source = r#"
+            @noinline
+            tiny(): number => 1
+            @inline
+            big(x: number): number => x * x * x * x * x * x * x * x * x * x * x * x
+        "#;
+        let plan = plan_source(source, 0);
+        assert_eq!(plan.get("tiny"), Some(&InlineDecision::Never));
+        assert_eq!(plan.get("big"), Some(&InlineDecision::Always));
+    }
+
+    #[test]
+    fn test_same_names_in_other_modules_and_nested_functions_are_apart() {
+        let helpers = parse_source(
+            r#"
+            format(n: number): number {
+                let a = n * 2
+                let b = a * 3
+                let c = b * 4
+                let d = c * 5
+                let e = d * 6
+                return e
+            }
+            "#,
+        );
+        let main = parse_source(
+            r#"
+            format(n: number): number => n + 1
+            walk(n: number): number {
+                walk(m: number): number => m
+                return walk(n)
+            }
+            "#,
+        );
+        let plan = plan(
+            [
+                (Path::new("helpers.liva"), &helpers),
+                (Path::new("main.liva"), &main),
+            ],
+            DEFAULT_INLINE_THRESHOLD,
+        );
+        assert_eq!(plan.get(Path::new("helpers.liva")), None);
+        let main_plan = &plan[Path::new("main.liva")];
+        assert_eq!(main_plan.get("format"), Some(&InlineDecision::Inline));
+        // `walk` calls its nested `walk`, not itself
+        assert_eq!(main_plan.get("walk"), Some(&InlineDecision::Inline));
+    }
+
+    #[test]
+    fn test_calls_of_pure_helpers_are_replaced_across_modules() {
+        let mut math =
+            parse_source("double(x: int): int => x * 2\n@noinline\ntriple(x: int): int => x * 3\n");
+        let mut main = parse_source(
+            "import { double, triple } from \"./math.liva\"\nmain(): int => double(4) + triple(4) + double(4 + 1)\n",
+        );
+        inline_calls(&mut [&mut math, &mut main], DEFAULT_INLINE_THRESHOLD);
+
+        let Expr::Binary { left, right, .. } = returned(&main) else {
+            panic!("{:?}", returned(&main));
+        };
+        // `double(4 + 1)` keeps its call: the argument isn't a variable or literal
+        assert!(matches!(right.as_ref(), Expr::Call(_)), "{:?}", right);
+        let Expr::Binary { left, right, .. } = left.as_ref() else {
+            panic!("{:?}", left);
+        };
+        assert_eq!(
+            left.as_ref(),
+            &Expr::Binary {
+                op: BinOp::Mul,
+                left: Box::new(Expr::Literal(Literal::Int(4))),
+                right: Box::new(Expr::Literal(Literal::Int(2))),
+            }
+        );
+        // `@noinline` keeps the call
+        assert!(matches!(right.as_ref(), Expr::Call(_)), "{:?}", right);
+    }
+}
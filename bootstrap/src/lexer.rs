@@ -152,18 +152,33 @@ pub enum Token {
     SlashAssign,
     #[token("%=")]
     PercentAssign,
+    #[token("**=")]
+    StarStarAssign,
+    #[token("&=")]
+    AmpAssign,
+    #[token("|=")]
+    PipeAssign,
+    #[token("^=")]
+    CaretAssign,
+    #[token("<<=")]
+    ShlAssign,
     #[token("+")]
     Plus,
     #[token("-")]
     Minus,
     #[token("*")]
     Star,
+    #[token("**")]
+    StarStar,
     #[token("/")]
     Slash,
     #[token("%")]
     Percent,
     #[token("<")]
     Lt,
+    // `>>` is two `Gt` tokens, so `Array<Array<int>>` still closes twice
+    #[token("<<")]
+    Shl,
     #[token("<=")]
     Le,
     #[token(">")]
@@ -176,8 +191,14 @@ pub enum Token {
     Ne,
     #[token("&&")]
     AndAnd,
+    #[token("&")]
+    Amp,
     #[token("|")]
     Pipe,
+    #[token("^")]
+    Caret,
+    #[token("~")]
+    Tilde,
     #[token("||")]
     OrOr,
     #[token("!")]
@@ -222,6 +243,8 @@ pub enum Token {
     QuestionDot,
     #[token("=>")]
     Arrow,
+    #[token("@")]
+    At,
 
     // Literals
     #[regex(r"[0-9][0-9_]*", |lex| lex.slice().replace('_', "").parse::<i64>().ok())]
@@ -239,21 +262,7 @@ pub enum Token {
 
     #[regex(r"'([^'\\]|\\.)+'", |lex| {
         let s = lex.slice();
-        let content = &s[1..s.len()-1];
-        // B26 fix: interpret escape sequences instead of taking first char blindly
-        if content.starts_with('\\') && content.len() >= 2 {
-            match content.chars().nth(1) {
-                Some('n') => Some('\n'),
-                Some('t') => Some('\t'),
-                Some('r') => Some('\r'),
-                Some('\\') => Some('\\'),
-                Some('\'') => Some('\''),
-                Some('0') => Some('\0'),
-                _ => content.chars().next(),
-            }
-        } else {
-            content.chars().next()
-        }
+        char_literal(&s[1..s.len()-1])
     })]
     CharLiteral(char),
 
@@ -278,6 +287,35 @@ pub enum Token {
     RustBlock(String),
 }
 
+/// The value of a char literal's content (between the quotes): exactly one
+/// character or one escape. Anything else — `'ab'`, `'\q'` — is rejected so
+/// the lexer reports it instead of silently keeping the first character.
+fn char_literal(content: &str) -> Option<char> {
+    let mut chars = content.chars();
+    let first = chars.next()?;
+    if first != '\\' {
+        return chars.next().is_none().then_some(first);
+    }
+    let value = match chars.next()? {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '\\' => '\\',
+        '\'' => '\'',
+        '"' => '"',
+        '0' => '\0',
+        'u' => {
+            let hex = chars.as_str().strip_prefix('{')?.strip_suffix('}')?;
+            if hex.is_empty() || hex.len() > 6 {
+                return None;
+            }
+            return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+        }
+        _ => return None,
+    };
+    chars.next().is_none().then_some(value)
+}
+
 #[derive(Debug, Clone)]
 pub struct TokenWithSpan {
     pub token: Token,
@@ -463,11 +501,16 @@ fn find_template_strings(source: &str) -> Vec<TemplateStringInfo> {
     templates
 }
 
-/// Information about a `rust { ... }` block found during pre-processing.
+/// Information about a `rust { ... }` block, or the body of an
+/// `unsafe rust fn`, found during pre-processing.
 #[derive(Debug)]
 struct RustBlockInfo {
-    /// Byte offset where the `rust` keyword starts
+    /// Byte offset where the `rust` keyword starts, or of the opening `{` of
+    /// an `unsafe rust fn` body
     rust_keyword_start: usize,
+    /// The block is the body of an `unsafe rust fn`, whose signature before
+    /// the `{` is tokenized as Liva
+    fn_body: bool,
     /// Byte offset right after the opening `{`
     content_start: usize,
     /// Byte offset of the closing `}` (exclusive of content)
@@ -478,7 +521,8 @@ struct RustBlockInfo {
     content: String,
 }
 
-/// Scan source for `rust { ... }` blocks (not `use rust "..."`) and extract them.
+/// Scan source for `rust { ... }` blocks (not `use rust "..."`) and the bodies
+/// of `rust fn name(...) { ... }` declarations, and extract them.
 /// Returns a list of blocks with byte ranges and raw content.
 fn find_rust_blocks(source: &str) -> Vec<RustBlockInfo> {
     let bytes = source.as_bytes();
@@ -547,6 +591,7 @@ fn find_rust_blocks(source: &str) -> Vec<RustBlockInfo> {
                         let content = source[content_start..content_end].to_string();
                         blocks.push(RustBlockInfo {
                             rust_keyword_start: rust_start,
+                            fn_body: false,
                             content_start,
                             content_end,
                             closing_brace_end,
@@ -556,6 +601,29 @@ fn find_rust_blocks(source: &str) -> Vec<RustBlockInfo> {
                         continue;
                     }
                 }
+
+                // `rust fn name(params): T { ... }`: the body starts at the
+                // first `{` after the parameter list
+                if source[j..].starts_with("fn")
+                    && bytes.get(j + 2).is_some_and(|b| b.is_ascii_whitespace())
+                {
+                    if let Some(open) = rust_fn_body_start(source, j + 2) {
+                        if let Some((content_end, closing_brace_end)) =
+                            find_balanced_brace(source, open)
+                        {
+                            blocks.push(RustBlockInfo {
+                                rust_keyword_start: open,
+                                fn_body: true,
+                                content_start: open + 1,
+                                content_end,
+                                closing_brace_end,
+                                content: source[open + 1..content_end].to_string(),
+                            });
+                            i = closing_brace_end;
+                            continue;
+                        }
+                    }
+                }
             }
         }
         i += 1;
@@ -564,6 +632,29 @@ fn find_rust_blocks(source: &str) -> Vec<RustBlockInfo> {
     blocks
 }
 
+/// Byte offset of the `{` opening the body of a `rust fn` whose name starts
+/// at or after `from`: the first one after the balanced parameter list
+fn rust_fn_body_start(source: &str, from: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut i = from + source[from..].find('(')?;
+    let mut depth = 0usize;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            b'{' | b'}' => return None,
+            _ => {}
+        }
+        i += 1;
+    }
+    Some(i + source.get(i..)?.find('{')?)
+}
+
 /// Find the matching closing `}` for the opening `{` at byte position `open_pos`.
 /// Handles Rust string literals, char literals, line comments, and block comments.
 /// Returns `Some((content_end, brace_end))` where content_end is at the `}` and
@@ -668,6 +759,155 @@ fn find_balanced_brace(source: &str, open_pos: usize) -> Option<(usize, usize)>
     None // unmatched
 }
 
+/// What kind of comment a [`Comment`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// text`
+    Line,
+    /// `/* text */`
+    Block,
+    /// `/// text` or `/** text */`, documenting the declaration after it
+    Doc,
+    /// `//! text` or `/*! text */`, documenting the whole file
+    InnerDoc,
+}
+
+/// A comment, kept as trivia next to the token stream.
+///
+/// Logos skips comments while tokenizing, so [`comments`] collects them in
+/// a separate pass; the parser attaches doc comments to declarations and
+/// keeps the full list in `Program::comments` for formatters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// The text between the comment markers, as written
+    pub text: String,
+    /// From the opening marker to the end of the line or the closing `*/`
+    pub span: Span,
+}
+
+impl Comment {
+    /// The comment text one line at a time, with the single space after
+    /// the marker and the ` * ` gutter of block comments removed
+    pub fn lines(&self) -> Vec<String> {
+        match self.kind {
+            CommentKind::Line | CommentKind::Doc | CommentKind::InnerDoc
+                if !self.text.contains('\n') =>
+            {
+                vec![strip_one_space(&self.text).trim_end().to_string()]
+            }
+            _ => {
+                let mut lines: Vec<String> = self
+                    .text
+                    .lines()
+                    .map(|line| {
+                        let line = line.trim();
+                        let line = line.strip_prefix('*').unwrap_or(line);
+                        strip_one_space(line).trim_end().to_string()
+                    })
+                    .collect();
+                while lines.first().is_some_and(|line| line.is_empty()) {
+                    lines.remove(0);
+                }
+                while lines.last().is_some_and(|line| line.is_empty()) {
+                    lines.pop();
+                }
+                lines
+            }
+        }
+    }
+}
+
+fn strip_one_space(text: &str) -> &str {
+    text.strip_prefix(' ').unwrap_or(text)
+}
+
+/// Every comment in `source`, in order. Comments inside string literals and
+/// `rust { }` blocks are not Liva comments and are left out.
+pub fn comments(source: &str) -> Vec<Comment> {
+    let bytes = source.as_bytes();
+    let skipped: Vec<(usize, usize)> = find_rust_blocks(source)
+        .iter()
+        .map(|block| (block.content_start, block.content_end))
+        .chain(
+            find_template_strings(source)
+                .iter()
+                .map(|t| (t.start, t.end)),
+        )
+        .collect();
+    let mut comments = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(&(_, end)) = skipped.iter().find(|(start, _)| *start == i) {
+            i = end;
+            continue;
+        }
+
+        if bytes[i] == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'/' {
+            let start = i;
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            let raw = &source[start..i];
+            let (kind, text) = if let Some(text) = raw.strip_prefix("//!") {
+                (CommentKind::InnerDoc, text)
+            } else if raw.starts_with("///") && !raw.starts_with("////") {
+                (CommentKind::Doc, &raw[3..])
+            } else {
+                (CommentKind::Line, &raw[2..])
+            };
+            comments.push(Comment {
+                kind,
+                text: text.trim_end_matches('\r').to_string(),
+                span: Span { start, end: i },
+            });
+            continue;
+        }
+
+        if bytes[i] == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'*' {
+            let start = i;
+            i += 2;
+            while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                i += 1;
+            }
+            let body_end = i.min(bytes.len());
+            i = (i + 2).min(bytes.len());
+            let raw = &source[start + 2..body_end];
+            let (kind, text) = if let Some(text) = raw.strip_prefix('!') {
+                (CommentKind::InnerDoc, text)
+            } else if raw.starts_with('*') && !raw.starts_with("**") {
+                (CommentKind::Doc, &raw[1..])
+            } else {
+                (CommentKind::Block, raw)
+            };
+            comments.push(Comment {
+                kind,
+                text: text.to_string(),
+                span: Span { start, end: i },
+            });
+            continue;
+        }
+
+        // Skip string literals so `"//"` isn't taken for a comment
+        if bytes[i] == b'"'
+            || (bytes[i] == b'\'' && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric()))
+        {
+            let quote = bytes[i];
+            i += 1;
+            while i < bytes.len() && bytes[i] != quote {
+                if bytes[i] == b'\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+
+    comments
+}
+
 pub fn tokenize(source: &str) -> Result<Vec<TokenWithSpan>> {
     // Phase 1a: Find rust { } blocks and extract their content
     let rust_blocks = find_rust_blocks(source);
@@ -720,17 +960,31 @@ pub fn tokenize(source: &str) -> Result<Vec<TokenWithSpan>> {
                     .unwrap_or("")
                     .to_string();
 
-                let error = SemanticErrorInfo::new(
-                    "E1000",
-                    "Invalid token",
-                    &format!("Encountered an invalid token: '{}'", display),
-                )
+                let is_char_literal =
+                    snippet.len() >= 2 && snippet.starts_with('\'') && snippet.ends_with('\'');
+                let error = if is_char_literal {
+                    SemanticErrorInfo::new(
+                        "E1000",
+                        "Invalid char literal",
+                        &format!("{} is not a single character or escape", snippet),
+                    )
+                    .with_help(
+                        "A char literal holds one character or escape ('a', '\\n', '\\u{1F600}'); \
+                         use double quotes for strings",
+                    )
+                } else {
+                    SemanticErrorInfo::new(
+                        "E1000",
+                        "Invalid token",
+                        &format!("Encountered an invalid token: '{}'", display),
+                    )
+                    .with_help("Check for unexpected characters or typos in your code")
+                }
                 .with_location("<input>", line)
                 .with_column(col)
-                .with_source_line(source_line)
-                .with_help("Check for unexpected characters or typos in your code");
+                .with_source_line(source_line);
 
-                return Err(CompilerError::LexerError(error));
+                return Err(CompilerError::LexerError(Box::new(error)));
             }
         }
     }
@@ -779,7 +1033,7 @@ pub fn tokenize(source: &str) -> Result<Vec<TokenWithSpan>> {
     let mut result_tokens = Vec::with_capacity(tokens.len());
     let mut idx = 0;
     while idx < tokens.len() {
-        if tokens[idx].token == Token::Rust {
+        if tokens[idx].token == Token::Rust || tokens[idx].token == Token::LBrace {
             // Check if this Rust keyword position matches a known block
             if let Some(block) = rust_blocks
                 .iter()
@@ -793,8 +1047,9 @@ pub fn tokenize(source: &str) -> Result<Vec<TokenWithSpan>> {
                         end: block.closing_brace_end,
                     },
                 ));
-                // Skip the Rust token + LBrace + RBrace (3 tokens)
-                idx += 3;
+                // Skip the Rust token + LBrace + RBrace (3 tokens), or
+                // LBrace + RBrace of a `rust fn` body
+                idx += if block.fn_body { 2 } else { 3 };
                 continue;
             }
         }
@@ -842,6 +1097,22 @@ mod tests {
         assert_eq!(tokens[5].token, Token::Bang);
     }
 
+    #[test]
+    fn test_char_literals() {
+        let source = r"'a' 'é' '\n' '\'' '\u{1F600}'";
+        let tokens = tokenize(source).unwrap();
+
+        assert_eq!(tokens[0].token, Token::CharLiteral('a'));
+        assert_eq!(tokens[1].token, Token::CharLiteral('é'));
+        assert_eq!(tokens[2].token, Token::CharLiteral('\n'));
+        assert_eq!(tokens[3].token, Token::CharLiteral('\''));
+        assert_eq!(tokens[4].token, Token::CharLiteral('😀'));
+
+        for invalid in ["'ab'", r"'\q'", r"'\u{D800}'", r"'\u{}'"] {
+            assert!(tokenize(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+
     #[test]
     fn test_string_template() {
         let source = r#"$"Hello {name}""#;
@@ -850,6 +1121,28 @@ mod tests {
         assert!(matches!(tokens[0].token, Token::StringTemplate(_)));
     }
 
+    #[test]
+    fn test_comments() {
+        let source = "//! Billing helpers\n\n/// Adds tax\n/// to a price\naddTax(p) => p * 2 // doubled\n\n/**\n * Block doc\n */\nlet s = \"// not a comment\"\n/* plain */\nrust { // rust comment\n}\n";
+        let found: Vec<(CommentKind, Vec<String>)> = comments(source)
+            .iter()
+            .map(|c| (c.kind, c.lines()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (CommentKind::InnerDoc, vec!["Billing helpers".to_string()]),
+                (CommentKind::Doc, vec!["Adds tax".to_string()]),
+                (CommentKind::Doc, vec!["to a price".to_string()]),
+                (CommentKind::Line, vec!["doubled".to_string()]),
+                (CommentKind::Doc, vec!["Block doc".to_string()]),
+                (CommentKind::Block, vec!["plain".to_string()]),
+            ]
+        );
+        let first = &comments(source)[0];
+        assert_eq!(first.span.snippet(source), "//! Billing helpers");
+    }
+
     // B42: find_rust_blocks must skip `rust` inside // comments
     #[test]
     fn test_find_rust_blocks_skips_line_comments() {
@@ -890,6 +1183,25 @@ let y = 1"#;
         assert!(blocks[0].content.contains("let x = 1;"));
     }
 
+    #[test]
+    fn test_unsafe_rust_fn_body_is_one_token() {
+        let source = "unsafe rust fn first(s: string): string {\n    s.chars().take(1).collect()\n}\nlet x = 1";
+        let tokens = tokenize(source).unwrap();
+        let kinds: Vec<&Token> = tokens.iter().map(|t| &t.token).collect();
+        assert_eq!(kinds[0], &Token::Ident("unsafe".to_string()));
+        assert_eq!(kinds[1], &Token::Rust);
+        assert_eq!(kinds[2], &Token::Ident("fn".to_string()));
+        let body = tokens
+            .iter()
+            .find_map(|t| match &t.token {
+                Token::RustBlock(code) => Some(code.as_str()),
+                _ => None,
+            })
+            .expect("body should be a RustBlock token");
+        assert_eq!(body.trim(), "s.chars().take(1).collect()");
+        assert!(kinds.contains(&&Token::Let));
+    }
+
     // B43: find_balanced_brace must handle lifetimes properly
     #[test]
     fn test_balanced_brace_with_lifetime() {
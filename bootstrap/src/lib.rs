@@ -24,6 +24,22 @@
 ///     output: Some("./build".into()),
 ///     verbose: true,
 ///     check_only: false,
+///     deterministic: false,
+///     coverage: false,
+///     debug: false,
+///     inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+///     strict: false,
+///     numeric_coercion: None,
+///     identifier_mapping: None,
+///     profile: livac::BuildProfile::Dev,
+///     backend: livac::backend::BackendKind::Rust,
+///     sandbox: None,
+///     self_check: false,
+///     incremental: false,
+///     explain_codegen: false,
+///     format_output: false,
+///     source_map: false,
+///     max_errors: 1,
 /// };
 ///
 /// match compile_file(&options) {
@@ -31,22 +47,55 @@
 ///     Err(e) => eprintln!("Compilation failed: {}", e),
 /// }
 /// ```
+pub mod abstract_classes;
+pub mod artifacts;
 pub mod ast;
+pub mod ast_diff;
+pub mod backend;
+pub mod builtins;
+pub mod cancel;
+pub mod captures;
 pub mod codegen;
+pub mod consteval;
+pub mod conversions;
+pub mod coverage;
+pub mod dap;
+pub mod debuginfo;
+pub mod delegation;
 pub mod desugaring;
+pub mod diagnostics;
 pub mod error;
 pub mod error_codes;
 pub mod hints;
+pub mod incremental;
+pub mod inlining;
 pub mod lexer;
 pub mod module;
+pub mod overloads;
+pub mod package;
 pub mod parser;
+pub mod pretty;
+pub mod reduce;
+pub mod runner;
+pub mod rust_ast;
+pub mod rustfmt;
+pub mod sandbox;
+pub mod schema;
+pub mod selfcheck;
 pub mod semantic;
+pub mod signatures;
 pub mod span;
 pub mod suggestions;
+pub mod symbols;
 pub mod traits;
+pub mod usage;
 
+pub use cancel::CancellationToken;
+pub use diagnostics::{Diagnostic, DiagnosticSink, Severity};
 pub use error::{CompilerError, ErrorLocation, Result, SemanticErrorInfo};
+pub use runner::{run_file, run_source, RunOptions, RunResult};
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 /// Compiler options for configuring the compilation process
@@ -63,6 +112,185 @@ pub struct CompilerOptions {
 
     /// Only check syntax, don't generate code
     pub check_only: bool,
+
+    /// Seed the random builtins and generate single-threaded runtime setup
+    /// so concurrent programs produce reproducible output
+    pub deterministic: bool,
+
+    /// Instrument every statement with a hit counter for `livac test --coverage`
+    pub coverage: bool,
+
+    /// Emit debugger line markers and keep function boundaries (`livac build --debug`)
+    pub debug: bool,
+
+    /// Body size (in AST nodes) under which non-recursive functions get
+    /// `#[inline]`; 0 leaves inlining to rustc. `@inline`/`@noinline` override it.
+    pub inline_threshold: usize,
+
+    /// Reject code whose types would otherwise be guessed (untyped parameters
+    /// and fields, untyped JSON values, implicit stringification) with
+    /// E5101–E5104. A `liva.toml` with `build.strict = true` turns it on too.
+    pub strict: bool,
+
+    /// How JSON fields used as numbers are converted; `None` takes
+    /// `build.numeric_coercion` from liva.toml, or [`NumericCoercion::Coerce`]
+    pub numeric_coercion: Option<NumericCoercion>,
+
+    /// How Liva identifiers are spelled in the generated Rust; `None` takes
+    /// `build.identifier_mapping` from liva.toml, or [`IdentifierMapping::Snake`]
+    pub identifier_mapping: Option<IdentifierMapping>,
+
+    /// Build profile; decides the value of the `DEBUG` constant, whose
+    /// branches desugaring folds away
+    pub profile: BuildProfile,
+
+    /// Code generation target
+    pub backend: backend::BackendKind,
+
+    /// Compile untrusted source: reject uses of files, the network,
+    /// processes, the environment and Rust interop the policy doesn't allow
+    /// (E0021). `None` allows everything.
+    pub sandbox: Option<sandbox::SandboxPolicy>,
+
+    /// Parse the generated Rust before writing it, so a codegen bug is
+    /// reported as E3004 at the offending line instead of as a cargo failure.
+    /// Always on when livac itself is a debug build.
+    pub self_check: bool,
+
+    /// Parse and validate only the top-level items that changed since the
+    /// last compile of the same file in this process, and return the last
+    /// result unchanged when nothing did (see [`incremental`])
+    pub incremental: bool,
+
+    /// Annotate the generated Rust with `/* livac: ... */` comments giving the
+    /// reason for each guess codegen makes (a clone, JSON bracket access, a
+    /// numeric conversion), so a bug report about wrong output can say which
+    /// heuristic fired (`livac build --explain-codegen`)
+    pub explain_codegen: bool,
+
+    /// Run the generated Rust through `rustfmt` before writing it, or only
+    /// strip trailing whitespace and extra blank lines when rustfmt isn't
+    /// available (see [`rustfmt`])
+    pub format_output: bool,
+
+    /// Record which Liva line each generated Rust statement comes from in
+    /// `CompilationResult::metadata.line_map`, without leaving markers in the
+    /// code, so rustc errors can be pointed back at the `.liva` source
+    /// (`livac build` writes it as `liva.map.json`, see [`debuginfo`])
+    pub source_map: bool,
+
+    /// Errors to collect before giving up. With 1 the first lexer, parser or
+    /// semantic error ends the compile; above that, parsing resumes at the
+    /// next statement or declaration, semantic analysis at the next
+    /// declaration and the custom passes at the next pass, and the errors
+    /// come back as [`CompilerError::Multiple`].
+    /// The default is 1; `livac` uses `--max-errors`, which defaults to
+    /// [`error::DEFAULT_MAX_ERRORS`].
+    pub max_errors: usize,
+}
+
+/// Policy for JSON fields that codegen converts to numbers on its own, such
+/// as `item.price` in `item.price * 2`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumericCoercion {
+    /// A missing or non-numeric value becomes `0.0`
+    #[default]
+    Coerce,
+    /// A missing or non-numeric value stops the program with an error naming
+    /// the field
+    Checked,
+    /// Implicit conversions are a compile error (E3003); convert explicitly
+    /// with `.asFloat()` or `.asInt()`
+    Forbid,
+}
+
+/// Rust spelling of the functions, variables, fields and methods a program
+/// declares. Type names are left as written either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdentifierMapping {
+    /// `myValue` becomes `my_value`, as Rust code is usually written
+    #[default]
+    Snake,
+    /// `myValue` stays `myValue`, so the generated code reads like the
+    /// source; Rust keywords still become raw identifiers (`r#type`)
+    Preserve,
+}
+
+/// Build profile selected with `--profile` (`--release` is short for
+/// `--profile release`)
+///
+/// `dev` builds without optimizations and with debug assertions, `release`
+/// with optimizations and without them. The Liva constant `DEBUG` is `true`
+/// only in `dev`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildProfile {
+    #[default]
+    Dev,
+    Release,
+}
+
+impl BuildProfile {
+    /// Value of the `DEBUG` constant
+    pub fn is_debug(self) -> bool {
+        self == BuildProfile::Dev
+    }
+
+    /// Cargo's directory under `target/` for binaries of this profile
+    pub fn target_dir(self) -> &'static str {
+        match self {
+            BuildProfile::Dev => "debug",
+            BuildProfile::Release => "release",
+        }
+    }
+}
+
+impl std::str::FromStr for BuildProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "dev" => Ok(BuildProfile::Dev),
+            "release" => Ok(BuildProfile::Release),
+            other => Err(format!(
+                "unknown profile '{}' (expected dev or release)",
+                other
+            )),
+        }
+    }
+}
+
+impl std::str::FromStr for NumericCoercion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "coerce" => Ok(NumericCoercion::Coerce),
+            "checked" => Ok(NumericCoercion::Checked),
+            "forbid" => Ok(NumericCoercion::Forbid),
+            other => Err(format!(
+                "unknown numeric coercion policy '{}' (expected coerce, checked or forbid)",
+                other
+            )),
+        }
+    }
+}
+
+impl std::str::FromStr for IdentifierMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "snake" => Ok(IdentifierMapping::Snake),
+            "preserve" => Ok(IdentifierMapping::Preserve),
+            other => Err(format!(
+                "unknown identifier mapping '{}' (expected snake or preserve)",
+                other
+            )),
+        }
+    }
 }
 
 impl Default for CompilerOptions {
@@ -72,6 +300,22 @@ impl Default for CompilerOptions {
             output: None,
             verbose: false,
             check_only: false,
+            deterministic: false,
+            coverage: false,
+            debug: false,
+            inline_threshold: inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
+            profile: BuildProfile::Dev,
+            backend: backend::BackendKind::Rust,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            source_map: false,
+            max_errors: 1,
         }
     }
 }
@@ -98,12 +342,86 @@ impl Default for CompilerOptions {
 ///     output: Some(PathBuf::from("./output")),
 ///     verbose: false,
 ///     check_only: false,
+///     deterministic: false,
+///     coverage: false,
+///     debug: false,
+///     inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+///     strict: false,
+///     numeric_coercion: None,
+///     identifier_mapping: None,
+///     profile: livac::BuildProfile::Dev,
+///     backend: livac::backend::BackendKind::Rust,
+///     sandbox: None,
+///     self_check: false,
+///     incremental: false,
+///     explain_codegen: false,
+///     format_output: false,
+///     source_map: false,
+///     max_errors: 1,
 /// };
 ///
 /// compile_file(&options)?;
 /// # Ok::<(), livac::CompilerError>(())
 /// ```
 pub fn compile_file(options: &CompilerOptions) -> Result<CompilationResult> {
+    compile_file_with_passes(options, &mut PassManager::new())
+}
+
+/// Compile a Liva source file, running the registered custom passes between
+/// semantic analysis and lowering
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use livac::{ast, compile_file_with_passes, CompilerOptions, CompilerPass, DiagnosticSink, PassManager};
+///
+/// struct NoTodoFunctions;
+///
+/// impl CompilerPass for NoTodoFunctions {
+///     fn name(&self) -> &str {
+///         "no-todo-functions"
+///     }
+///
+///     fn run(&mut self, program: &mut ast::Program, diagnostics: &mut DiagnosticSink) {
+///         for item in &program.items {
+///             if let ast::TopLevel::Function(f) = item {
+///                 if f.name.starts_with("todo") {
+///                     diagnostics.warning(livac::SemanticErrorInfo::new(
+///                         "W9000",
+///                         "Unfinished function",
+///                         &format!("'{}' is still a placeholder", f.name),
+///                     ));
+///                 }
+///             }
+///         }
+///     }
+/// }
+///
+/// let mut passes = PassManager::new();
+/// passes.add(NoTodoFunctions);
+/// let result = compile_file_with_passes(&CompilerOptions::default(), &mut passes)?;
+/// for diagnostic in &result.diagnostics {
+///     eprintln!("{}", diagnostic);
+/// }
+/// # Ok::<(), livac::CompilerError>(())
+/// ```
+pub fn compile_file_with_passes(
+    options: &CompilerOptions,
+    passes: &mut PassManager,
+) -> Result<CompilationResult> {
+    // Named after the file (or its project), so programs sharing an output
+    // directory don't overwrite each other's binaries
+    let crate_name = artifacts::crate_name(&options.input);
+    compile_file_as(options, &crate_name, passes, &CancellationToken::new())
+}
+
+/// [`compile_file_with_passes`] with the generated crate named `crate_name`
+fn compile_file_as(
+    options: &CompilerOptions,
+    crate_name: &str,
+    passes: &mut PassManager,
+    cancel: &CancellationToken,
+) -> Result<CompilationResult> {
     // Read source file
     let source = std::fs::read_to_string(&options.input)
         .map_err(|e| CompilerError::IoError(format!("Failed to read input file: {}", e)))?;
@@ -113,73 +431,275 @@ pub fn compile_file(options: &CompilerOptions) -> Result<CompilationResult> {
     // Quick check: does this file have imports?
     let has_imports = source.contains("import ");
 
+    // `[build]` settings in liva.toml apply to every build of the project
+    let build = package::Manifest::build_settings_for(&options.input);
+    let mut options = options.clone();
+    options.strict |= build.strict;
+    options.numeric_coercion = options.numeric_coercion.or(build.numeric_coercion);
+    options.identifier_mapping = options.identifier_mapping.or(build.identifier_mapping);
+    // and `[rust_dependencies]` pins the crates `use rust` imports
+    let rust_dependencies = package::Manifest::rust_dependencies_for(&options.input);
+
     if has_imports {
         // Multi-file compilation with module resolver
-        compile_with_modules(&options.input, options)
+        compile_with_modules(
+            &options.input,
+            crate_name,
+            &options,
+            &rust_dependencies,
+            passes,
+            cancel,
+        )
     } else {
         // Single-file compilation (legacy path)
-        compile_source_with_filename(&source, filename, options)
+        compile_source_with_filename(
+            &source,
+            filename,
+            crate_name,
+            &options,
+            &rust_dependencies,
+            passes,
+            cancel,
+        )
     }
 }
 
 fn compile_source_with_filename(
     source: &str,
     filename: &str,
+    crate_name: &str,
     options: &CompilerOptions,
+    rust_dependencies: &BTreeMap<String, package::RustDependency>,
+    passes: &mut PassManager,
+    cancel: &CancellationToken,
 ) -> Result<CompilationResult> {
-    // 1. Lexer - tokenize source
-    let tokens = lexer::tokenize(source)?;
+    // An in-memory compile of an unchanged source gives the same result;
+    // custom passes may not, so they always run
+    let fingerprint = (options.incremental && options.output.is_none() && passes.is_empty())
+        .then(|| incremental::fingerprint(source, filename, crate_name, options));
+    if let Some(result) = fingerprint.and_then(|fp| incremental::cached_result(filename, fp)) {
+        return Ok(result);
+    }
+    let finish = |result: CompilationResult| -> Result<CompilationResult> {
+        if let Some(fp) = fingerprint {
+            incremental::store_result(filename, fp, &result);
+        }
+        Ok(result)
+    };
 
-    // 2. Parser - build AST
-    let ast = parser::parse(tokens, source)?;
+    // 1-2. Lexer and parser - build AST, reusing unchanged items when incremental
+    let ast = if options.incremental {
+        incremental::parse(filename, source)?
+    } else if options.max_errors > 1 {
+        let (ast, mut errors) = parser::parse_with_recovery(lexer::tokenize(source)?, source);
+        if !errors.is_empty() {
+            errors.truncate(options.max_errors);
+            return Err(CompilerError::from_errors(errors));
+        }
+        ast
+    } else {
+        parser::parse(lexer::tokenize(source)?, source)?
+    };
+    cancel.check()?;
 
     // 3. Semantic analysis with source information
-    let analyzed_ast =
-        semantic::analyze_with_source(ast, filename.to_string(), source.to_string())?;
+    let mut diagnostics = DiagnosticSink::new();
+    let mut analyzed_ast = semantic::analyze_cancellable(
+        ast,
+        filename.to_string(),
+        source.to_string(),
+        None,
+        options,
+        cancel,
+        &mut diagnostics,
+    )?;
+
+    // 3a. Give each overload of a method its own name, and point calls at it
+    overloads::resolve(&mut analyzed_ast, &[])?;
+
+    // 3b. Custom passes registered by downstream tooling
+    passes.run_cancellable(
+        &mut analyzed_ast,
+        &mut diagnostics,
+        options.max_errors,
+        cancel,
+    )?;
+    let features = usage::FeatureUsage::collect([&analyzed_ast]);
 
     // If check-only mode, stop here
     if options.check_only {
-        return Ok(CompilationResult {
+        return finish(CompilationResult {
             rust_code: None,
             cargo_toml: None,
             output_dir: None,
             has_imports: false,
             module_files: None,
+            diagnostics: diagnostics.into_vec(),
+            metadata: artifacts::ArtifactMetadata::default(),
+            features,
         });
     }
 
     // 4. Desugaring
+    cancel.check()?;
+    desugaring::fold_profile_constants(&mut analyzed_ast, options.profile);
+    // Debug and coverage builds keep every call, so each function is seen running
+    if !options.debug && !options.coverage {
+        inlining::inline_calls(&mut [&mut analyzed_ast], options.inline_threshold);
+    }
     let mut desugar_ctx = desugaring::desugar(analyzed_ast.clone())?;
+    package::pin_rust_crates(&mut desugar_ctx.rust_crates, rust_dependencies);
     desugar_ctx.source_filename = filename.to_string();
+    desugar_ctx.crate_name = crate_name.to_string();
+    desugar_ctx.deterministic = options.deterministic;
+    desugar_ctx.coverage = options.coverage;
+    desugar_ctx.debug = options.debug;
+    desugar_ctx.explain_codegen = options.explain_codegen;
+    desugar_ctx.source_map = options.source_map;
+    desugar_ctx.numeric_coercion = options.numeric_coercion.unwrap_or_default();
+    desugar_ctx.identifier_mapping = options.identifier_mapping.unwrap_or_default();
+    if !options.debug {
+        desugar_ctx.inline = inlining::plan(
+            [(std::path::Path::new(""), &analyzed_ast)],
+            options.inline_threshold,
+        );
+    }
 
     // 5. Code generation
-    let (rust_code, cargo_toml) = codegen::generate_with_ast(&analyzed_ast, desugar_ctx)?;
+    cancel.check()?;
+    let debug = desugar_ctx.debug;
+    let backend = options.backend.backend();
+    let mut project = backend.generate_program(&analyzed_ast, desugar_ctx)?;
+    diagnostics.extend(project.diagnostics);
+    let mut rust_code = project
+        .files
+        .remove(&backend.entry_file())
+        .unwrap_or_default();
+    let cargo_toml = project.manifest;
+    if selfcheck::enabled(options.self_check) {
+        selfcheck::check(&backend.entry_file(), &rust_code)?;
+    }
+    if options.format_output {
+        rust_code = rustfmt::format(&rust_code);
+    }
+    // Markers written only for the source map come back out of the code
+    let mut line_map = None;
+    if options.source_map && !debug {
+        let mut map = debuginfo::LineMap::new();
+        let rust_file = backend.entry_file().display().to_string();
+        rust_code = map.strip_generated(&rust_file, &rust_code);
+        line_map = Some(map);
+    }
+    let mut metadata = artifacts::ArtifactMetadata::collect(
+        &analyzed_ast,
+        &[(
+            PathBuf::from(filename),
+            backend.entry_file(),
+            rust_code.as_str(),
+        )],
+        &cargo_toml,
+        debug,
+        options.profile,
+    );
+    if line_map.is_some() {
+        metadata.line_map = line_map;
+    }
 
     // 6. Write output files if output directory specified
+    cancel.check()?;
     let output_dir = if let Some(out_dir) = &options.output {
         Some(write_output_files(&rust_code, &cargo_toml, out_dir)?)
     } else {
         None
     };
 
-    Ok(CompilationResult {
+    finish(CompilationResult {
         rust_code: Some(rust_code),
         cargo_toml: Some(cargo_toml),
         output_dir,
         has_imports: false,
         module_files: None,
+        diagnostics: diagnostics.into_vec(),
+        metadata,
+        features,
     })
 }
 
+/// Public and private symbols of each module, by path
+type ModuleMap = std::collections::HashMap<
+    PathBuf,
+    (
+        std::collections::HashSet<String>,
+        std::collections::HashSet<String>,
+    ),
+>;
+
+/// Resolver for the modules `entry_point` imports, with the packages declared
+/// in the nearest liva.toml available to bare imports
+fn module_resolver(entry_point: &Path) -> Result<module::ModuleResolver> {
+    let packages = match entry_point.parent().and_then(package::Manifest::find) {
+        Some(manifest_path) => package::resolve_project(&manifest_path)?,
+        None => package::Resolution::default(),
+    };
+    module::ModuleResolver::with_packages(entry_point, packages)
+}
+
+/// Symbols semantic analysis of `entry_module` can import: every resolved
+/// module, the packages by import source and the virtual modules it names
+fn module_map(
+    resolver: &module::ModuleResolver,
+    modules: &[&module::Module],
+    entry_module: &module::Module,
+) -> ModuleMap {
+    let mut module_map = ModuleMap::new();
+    for module in modules {
+        module_map.insert(
+            module.path.clone(),
+            (
+                module.public_symbols.clone(),
+                module.private_symbols.clone(),
+            ),
+        );
+    }
+
+    // Package imports ("mathx") are looked up by their import source
+    for (source, path) in resolver.package_imports() {
+        if let Some(module) = resolver.get_module(path) {
+            module_map.insert(
+                module::package_module_path(source),
+                (
+                    module.public_symbols.clone(),
+                    module.private_symbols.clone(),
+                ),
+            );
+        }
+    }
+
+    // Add virtual modules (liva/test, etc.) to the module map
+    // Scan entry module imports for virtual module references
+    for import in &entry_module.imports {
+        if module::is_virtual_module(&import.source) {
+            if let Some(symbols) = module::virtual_module_symbols(&import.source) {
+                let vpath = module::virtual_module_path(&import.source);
+                module_map.insert(vpath, (symbols, std::collections::HashSet::new()));
+            }
+        }
+    }
+
+    module_map
+}
+
 /// Compile a multi-file Liva project using the module resolver
 fn compile_with_modules(
     entry_point: &std::path::Path,
+    crate_name: &str,
     options: &CompilerOptions,
+    rust_dependencies: &BTreeMap<String, package::RustDependency>,
+    passes: &mut PassManager,
+    cancel: &CancellationToken,
 ) -> Result<CompilationResult> {
-    use crate::module::ModuleResolver;
-
     // 1. Resolve all modules starting from entry point
-    let mut resolver = ModuleResolver::new(entry_point)?;
+    let mut resolver = module_resolver(entry_point)?;
     {
         let compilation_order = resolver.resolve_all()?;
 
@@ -192,10 +712,24 @@ fn compile_with_modules(
         }
     }
 
+    cancel.check()?;
+
     // 1b. Hoist `extend ClassName { ... }` items into their owner ClassDecl.
     //     See docs/language-reference/class-extensions.md.
     resolver.hoist_class_extensions()?;
 
+    // 1c. Copy the members abstract classes pass on into the classes
+    //     implementing them, wherever the abstract class is declared
+    resolver.inherit_abstract_members()?;
+
+    // 1d. Add the methods `delegate` lines forward, wherever the field's
+    //     class is declared
+    resolver.expand_delegates()?;
+
+    // 1e. Replace `Class.schema()` / `fields(Class)` with the field list,
+    //     wherever the class is declared
+    resolver.expand_schemas();
+
     // Re-acquire the compilation order after hoisting (modules were mutated).
     let order_paths = resolver.compilation_order()?;
     let compilation_order: Vec<&crate::module::Module> = order_paths
@@ -209,45 +743,67 @@ fn compile_with_modules(
         .iter()
         .find(|m| m.path.canonicalize().ok() == entry_point.canonicalize().ok())
         .ok_or_else(|| {
-            CompilerError::CodegenError(error::SemanticErrorInfo::new(
+            CompilerError::CodegenError(Box::new(error::SemanticErrorInfo::new(
                 "E4005",
                 "Entry point not found in resolved modules",
                 "The entry point file could not be found in the list of resolved modules.\nHint: This is likely an internal compiler error. Please report this issue.",
-            ))
+            )))
         })?;
 
     let filename = entry_point.to_str().unwrap_or("unknown");
 
     // Build module context map for semantic analysis
-    let mut module_map = std::collections::HashMap::new();
-    for module in &compilation_order {
-        module_map.insert(
-            module.path.clone(),
-            (
-                module.public_symbols.clone(),
-                module.private_symbols.clone(),
-            ),
-        );
-    }
-
-    // Add virtual modules (liva/test, etc.) to the module map
-    // Scan entry module imports for virtual module references
-    for import in &entry_module.imports {
-        if module::is_virtual_module(&import.source) {
-            if let Some(symbols) = module::virtual_module_symbols(&import.source) {
-                let vpath = module::virtual_module_path(&import.source);
-                module_map.insert(vpath, (symbols, std::collections::HashSet::new()));
-            }
-        }
-    }
+    let module_map = module_map(&resolver, &compilation_order, entry_module);
 
     // 2. Semantic analysis with module context
-    let analyzed_ast = semantic::analyze_with_modules(
+    let mut diagnostics = DiagnosticSink::new();
+    let mut analyzed_ast = semantic::analyze_cancellable(
         entry_module.ast.clone(),
         filename.to_string(),
         entry_module.source.clone(),
-        &module_map,
+        Some(&module_map),
+        options,
+        cancel,
+        &mut diagnostics,
+    )?;
+    // Imported modules skip full analysis, but not the sandbox
+    if let Some(policy) = &options.sandbox {
+        for module in compilation_order
+            .iter()
+            .filter(|module| module.path != entry_module.path)
+        {
+            semantic::check_sandbox(
+                &module.ast,
+                module.path.display().to_string(),
+                module.source.clone(),
+                policy,
+            )?;
+        }
+    }
+
+    // 2a. Give each overload of a method its own name, and point calls at it,
+    //     including calls into classes of imported modules
+    let others = |module: &module::Module| -> Vec<&ast::Program> {
+        compilation_order
+            .iter()
+            .filter(|other| other.path != module.path)
+            .map(|other| &other.ast)
+            .collect()
+    };
+    overloads::resolve(&mut analyzed_ast, &others(entry_module))?;
+
+    // 2b. Custom passes registered by downstream tooling
+    passes.run_cancellable(
+        &mut analyzed_ast,
+        &mut diagnostics,
+        options.max_errors,
+        cancel,
     )?;
+    let imported = compilation_order
+        .iter()
+        .filter(|module| module.path != entry_module.path)
+        .map(|module| &module.ast);
+    let features = usage::FeatureUsage::collect(std::iter::once(&analyzed_ast).chain(imported));
 
     // If check-only mode, stop here
     if options.check_only {
@@ -257,24 +813,138 @@ fn compile_with_modules(
             output_dir: None,
             has_imports: true,
             module_files: None,
+            diagnostics: diagnostics.into_vec(),
+            metadata: artifacts::ArtifactMetadata::default(),
+            features,
         });
     }
 
     // 3. Desugaring
+    cancel.check()?;
+    // Fold `DEBUG` after analysis, so both sides of its branches are checked
+    desugaring::fold_profile_constants(&mut analyzed_ast, options.profile);
+    let mut folded_modules: Vec<module::Module> = compilation_order
+        .iter()
+        .map(|module| {
+            let mut folded = (*module).clone();
+            desugaring::fold_profile_constants(&mut folded.ast, options.profile);
+            overloads::resolve(&mut folded.ast, &others(module))?;
+            Ok(folded)
+        })
+        .collect::<Result<_>>()?;
+    // Small helpers are pasted into their callers, also across modules; the
+    // entry module is generated from its analyzed AST
+    if !options.debug && !options.coverage {
+        let mut programs: Vec<&mut ast::Program> = folded_modules
+            .iter_mut()
+            .filter(|module| module.path != entry_module.path)
+            .map(|module| &mut module.ast)
+            .collect();
+        programs.push(&mut analyzed_ast);
+        inlining::inline_calls(&mut programs, options.inline_threshold);
+    }
+    let compilation_order: Vec<&module::Module> = folded_modules.iter().collect();
     let mut desugar_ctx = desugaring::desugar(analyzed_ast.clone())?;
+    package::pin_rust_crates(&mut desugar_ctx.rust_crates, rust_dependencies);
     desugar_ctx.source_filename = filename.to_string();
+    desugar_ctx.crate_name = crate_name.to_string();
+    desugar_ctx.deterministic = options.deterministic;
+    desugar_ctx.coverage = options.coverage;
+    desugar_ctx.debug = options.debug;
+    desugar_ctx.explain_codegen = options.explain_codegen;
+    desugar_ctx.source_map = options.source_map;
+    desugar_ctx.numeric_coercion = options.numeric_coercion.unwrap_or_default();
+    desugar_ctx.identifier_mapping = options.identifier_mapping.unwrap_or_default();
+    // Imported modules skip analysis, so their classes are collected here; the
+    // entry module's analyzed table wins over a class of the same name
+    let mut classes = symbols::SymbolTable::default();
+    for module in compilation_order
+        .iter()
+        .filter(|module| module.path != entry_module.path)
+    {
+        classes.extend(symbols::SymbolTable::collect(&module.ast));
+    }
+    classes.extend(std::mem::take(&mut desugar_ctx.symbols));
+    desugar_ctx.symbols = classes;
+    // The runtime (and its optional helpers) is emitted once, in main.rs
+    for module in &compilation_order {
+        desugaring::note_runtime_helpers(&module.ast, &mut desugar_ctx);
+    }
+    // Inlining decisions span every module so small helpers inline across them
+    if !options.debug {
+        desugar_ctx.inline = inlining::plan(
+            compilation_order.iter().map(|m| (m.path.as_path(), &m.ast)),
+            options.inline_threshold,
+        );
+    }
+
+    // Lower the analyzed entry AST (async inference, pass transforms), not the raw parse
+    let mut analyzed_entry = (*entry_module).clone();
+    analyzed_entry.ast = analyzed_ast.clone();
+    let entry_module = &analyzed_entry;
 
     // 4. Code generation - Multi-file project
-    let files = codegen::generate_multifile_project(
-        &compilation_order[..],
-        entry_module,
-        desugar_ctx.clone(),
-    )?;
+    cancel.check()?;
+    let backend = options.backend.backend();
+    let project =
+        backend.generate_modules(&compilation_order[..], entry_module, desugar_ctx.clone())?;
+    diagnostics.extend(project.diagnostics);
+    let mut files = project.files;
+    let cargo_toml = project.manifest;
+    if selfcheck::enabled(options.self_check) {
+        for (path, code) in &files {
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                selfcheck::check(path, code)?;
+            }
+        }
+    }
+    if options.format_output {
+        for (path, code) in files.iter_mut() {
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                *code = rustfmt::format(code);
+            }
+        }
+    }
+    // Markers written only for the source map come back out of the code
+    let mut line_map = None;
+    if options.source_map && !desugar_ctx.debug {
+        let mut map = debuginfo::LineMap::new();
+        for (path, code) in files.iter_mut() {
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                *code = map.strip_generated(&path.display().to_string(), code);
+            }
+        }
+        line_map = Some(map);
+    }
 
-    // Generate Cargo.toml
-    let cargo_toml = codegen::generate_cargo_toml(&desugar_ctx)?;
+    let generated: Vec<(PathBuf, PathBuf, &str)> = compilation_order
+        .iter()
+        .map(|module| {
+            let rust_file = if module.path == entry_module.path {
+                backend.entry_file()
+            } else {
+                backend.module_file(module)
+            };
+            (module.path.clone(), rust_file)
+        })
+        .filter_map(|(liva_file, rust_file)| {
+            let code = files.get(&rust_file)?;
+            Some((liva_file, rust_file, code.as_str()))
+        })
+        .collect();
+    let mut metadata = artifacts::ArtifactMetadata::collect(
+        &entry_module.ast,
+        &generated,
+        &cargo_toml,
+        desugar_ctx.debug,
+        options.profile,
+    );
+    if line_map.is_some() {
+        metadata.line_map = line_map;
+    }
 
     // 5. Write output files if output directory specified
+    cancel.check()?;
     let output_dir = if let Some(out_dir) = &options.output {
         Some(write_multifile_output(&files, &cargo_toml, out_dir)?)
     } else {
@@ -283,12 +953,12 @@ fn compile_with_modules(
 
     // For backward compatibility with single-file result
     // Extract main.rs content if available
-    let main_rs_content = files.get(&std::path::PathBuf::from("src/main.rs")).cloned();
+    let main_rs_content = files.get(&backend.entry_file()).cloned();
 
     // Extract module files (all files except main.rs)
     let module_files: std::collections::HashMap<PathBuf, String> = files
         .iter()
-        .filter(|(path, _)| *path != &std::path::PathBuf::from("src/main.rs"))
+        .filter(|(path, _)| **path != backend.entry_file())
         .map(|(path, content)| (path.clone(), content.clone()))
         .collect();
 
@@ -304,6 +974,9 @@ fn compile_with_modules(
         output_dir,
         has_imports: true,
         module_files: module_files_opt,
+        diagnostics: diagnostics.into_vec(),
+        metadata,
+        features,
     })
 }
 
@@ -319,7 +992,63 @@ fn compile_with_modules(
 /// * `Ok(CompilationResult)` - On successful compilation
 /// * `Err(CompilerError)` - On compilation failure
 pub fn compile_source(source: &str, options: &CompilerOptions) -> Result<CompilationResult> {
-    compile_source_with_filename(source, "unknown", options)
+    compile_source_with_passes(source, options, &mut PassManager::new())
+}
+
+/// Compile Liva source code from a string, running the registered custom passes
+pub fn compile_source_with_passes(
+    source: &str,
+    options: &CompilerOptions,
+    passes: &mut PassManager,
+) -> Result<CompilationResult> {
+    compile_source_with_filename(
+        source,
+        "unknown",
+        artifacts::DEFAULT_CRATE_NAME,
+        options,
+        &BTreeMap::new(),
+        passes,
+        &CancellationToken::new(),
+    )
+}
+
+/// Compile Liva source code from a string, giving up as soon as `cancel` is
+/// cancelled or its deadline passes
+///
+/// Stages, custom passes and semantic analysis (per declaration and
+/// statement) poll the token, so a superseded run stops promptly with
+/// [`CompilerError::Cancelled`] or [`CompilerError::DeadlineExceeded`].
+/// Nothing is written to `options.output` once the token has fired.
+///
+/// # Example
+///
+/// ```rust
+/// use livac::{compile_source_with, CancellationToken, CompilerError, CompilerOptions};
+///
+/// let options = CompilerOptions {
+///     check_only: true,
+///     ..CompilerOptions::default()
+/// };
+/// let token = CancellationToken::new();
+/// token.cancel();
+///
+/// let err = compile_source_with("main() { print(1) }", &options, &token).unwrap_err();
+/// assert!(matches!(err, CompilerError::Cancelled));
+/// ```
+pub fn compile_source_with(
+    source: &str,
+    options: &CompilerOptions,
+    cancel: &CancellationToken,
+) -> Result<CompilationResult> {
+    compile_source_with_filename(
+        source,
+        "unknown",
+        artifacts::DEFAULT_CRATE_NAME,
+        options,
+        &BTreeMap::new(),
+        &mut PassManager::new(),
+        cancel,
+    )
 }
 
 // Note: The implementation below was replaced by compile_source_with_filename above
@@ -333,7 +1062,8 @@ pub fn compile_source_old(source: &str, options: &CompilerOptions) -> Result<Com
     let ast = parser::parse(tokens, source)?;
 
     // 3. Semantic analysis
-    let analyzed_ast = semantic::analyze(ast)?;
+    let mut analyzed_ast = semantic::analyze(ast)?;
+    overloads::resolve(&mut analyzed_ast, &[])?;
 
     // If check-only mode, stop here
     if options.check_only {
@@ -370,6 +1100,7 @@ pub fn compile_source_old(source: &str, options: &CompilerOptions) -> Result<Com
 
 /// Result of a successful compilation
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct CompilationResult {
     /// Generated Rust code (None if check-only mode)
     pub rust_code: Option<String>,
@@ -385,6 +1116,107 @@ pub struct CompilationResult {
 
     /// Additional module files (relative path -> content)
     pub module_files: Option<std::collections::HashMap<PathBuf, String>>,
+
+    /// Warnings and hints from semantic analysis, custom passes and code
+    /// generation, in that order
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Crate name, binary path, generated files, dependencies, entry points
+    /// and source map (empty in check-only mode)
+    pub metadata: artifacts::ArtifactMetadata,
+
+    /// Language features the compiled modules use, for `livac build --report`
+    pub features: usage::FeatureUsage,
+}
+
+/// A custom analysis or transform pass run between semantic analysis and lowering
+///
+/// Passes receive the analyzed AST, may rewrite it in place, and report
+/// problems into the compilation's [`DiagnosticSink`]. An error ends the
+/// compilation after the pass that reported it, or once
+/// [`CompilerOptions::max_errors`] errors are in; warnings and hints come back
+/// in [`CompilationResult::diagnostics`].
+pub trait CompilerPass {
+    /// Short identifier used in error messages (e.g. "no-todo-functions")
+    fn name(&self) -> &str;
+
+    fn run(&mut self, program: &mut ast::Program, diagnostics: &mut DiagnosticSink);
+}
+
+/// Ordered list of custom passes applied to every compilation
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn CompilerPass>>,
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a pass; passes run in registration order
+    pub fn add<P: CompilerPass + 'static>(&mut self, pass: P) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Run every pass over `program`, stopping after the first pass that
+    /// reports an error. Warnings and hints go into `diagnostics`.
+    pub fn run(
+        &mut self,
+        program: &mut ast::Program,
+        diagnostics: &mut DiagnosticSink,
+    ) -> Result<()> {
+        self.run_cancellable(program, diagnostics, 1, &CancellationToken::new())
+    }
+
+    /// Like [`run`](Self::run), but checks `cancel` before each pass, and
+    /// with `max_errors` above 1 keeps running passes until that many errors
+    /// are reported. Every error a pass reports is kept, up to `max_errors`,
+    /// and they come back together as [`CompilerError::Multiple`].
+    pub fn run_cancellable(
+        &mut self,
+        program: &mut ast::Program,
+        diagnostics: &mut DiagnosticSink,
+        max_errors: usize,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let max_errors = max_errors.max(1);
+        let mut errors = Vec::new();
+
+        for pass in &mut self.passes {
+            cancel.check()?;
+            let mut reported = DiagnosticSink::new();
+            pass.run(program, &mut reported);
+
+            for diagnostic in reported.into_vec() {
+                if diagnostic.severity == Severity::Error {
+                    let mut info = diagnostic.info;
+                    info.message = format!("[{}] {}", pass.name(), info.message);
+                    errors.push(CompilerError::SemanticError(Box::new(info)));
+                } else {
+                    diagnostics.push(diagnostic);
+                }
+            }
+            if errors.len() >= max_errors {
+                break;
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        errors.truncate(max_errors);
+        Err(CompilerError::from_errors(errors))
+    }
 }
 
 /// Write generated code to the filesystem
@@ -479,10 +1311,355 @@ fn write_multifile_output(
 /// # Ok::<(), livac::CompilerError>(())
 /// ```
 pub fn check_syntax(source: &str) -> Result<()> {
-    let tokens = lexer::tokenize(source)?;
-    let ast = parser::parse(tokens, source)?;
-    semantic::analyze(ast)?;
-    Ok(())
+    check_source(source, "<input>").into_result()
+}
+
+/// Every diagnostic [`check_syntax`] would stop at the first of, in one pass
+///
+/// Parsing resumes after a statement or declaration that doesn't parse, and
+/// a file that parses is analyzed up to [`error::DEFAULT_MAX_ERRORS`]
+/// semantic errors. An empty list means the source is valid.
+///
+/// # Example
+///
+/// ```rust
+/// use livac::check_syntax_all;
+///
+/// let source = "first() {\n    let = 1\n}\n\nsecond() {\n    let = 2\n}\n";
+/// assert_eq!(check_syntax_all(source).len(), 2);
+/// ```
+pub fn check_syntax_all(source: &str) -> Vec<CompilerError> {
+    check_source(source, "<input>").diagnostics
+}
+
+/// Kind of a top-level declaration listed in a [`CheckReport`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Interface,
+    TypeAlias,
+    Enum,
+    Constant,
+    Extension,
+    Test,
+}
+
+/// Summary of a top-level declaration found while checking a file
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SymbolSummary {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Byte span of the declaration, when the parser records one
+    pub span: Option<span::Span>,
+}
+
+/// Result of [`check_source`]: every diagnostic plus whatever AST could be built
+#[derive(Debug)]
+pub struct CheckReport {
+    pub file_name: String,
+    /// All lexer, parser and semantic errors, in source order per phase
+    pub diagnostics: Vec<CompilerError>,
+    /// Warnings semantic analysis reported for a file without errors
+    pub warnings: Vec<Diagnostic>,
+    /// The analyzed AST when the file is valid, otherwise the partial parse
+    pub ast: Option<ast::Program>,
+    /// Top-level declarations found in `ast`
+    pub symbols: Vec<SymbolSummary>,
+}
+
+impl CheckReport {
+    /// True when no diagnostics were produced
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Collapse the report into the first diagnostic, as `check_syntax` does
+    pub fn into_result(self) -> Result<()> {
+        match self.diagnostics.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn push_diagnostic(&mut self, err: CompilerError) {
+        for mut err in err.into_errors() {
+            if let Some(location) = err.error_info_mut().and_then(|info| info.location.as_mut()) {
+                if location.file == "<input>" {
+                    location.file = self.file_name.clone();
+                }
+            }
+            self.diagnostics.push(err);
+        }
+    }
+}
+
+/// Parse and validate Liva source code, collecting every diagnostic
+///
+/// Unlike [`check_syntax`], parsing recovers at top-level declarations so
+/// that all syntax errors are reported, and the (possibly partial) AST and
+/// a summary of its symbols are returned for editors and linters. Semantic
+/// analysis only runs when the file parses cleanly.
+///
+/// # Example
+///
+/// ```rust
+/// use livac::check_source;
+///
+/// let report = check_source("sum(a: number, b: number): number => a + b", "sum.liva");
+/// assert!(report.is_ok());
+/// assert_eq!(report.symbols[0].name, "sum");
+/// ```
+pub fn check_source(source: &str, file_name: &str) -> CheckReport {
+    check_source_with(source, file_name, &CancellationToken::new())
+        .expect("a fresh cancellation token never fires")
+}
+
+/// [`check_source`] that stops early when `cancel` fires
+///
+/// Language servers start a new check on every edit and cancel the previous
+/// one; a cancelled check returns `Err(CompilerError::Cancelled)` (or
+/// `DeadlineExceeded`) instead of a partial report.
+pub fn check_source_with(
+    source: &str,
+    file_name: &str,
+    cancel: &CancellationToken,
+) -> Result<CheckReport> {
+    check_source_in(source, file_name, None, false, false, cancel)
+}
+
+/// [`check_source_with`] for a document checked again after every edit
+///
+/// Parsing and analysis reuse what the previous check of `file_name` left
+/// in the [`incremental`] cache, so only the items the edit touched are
+/// lexed, parsed and validated again. `file_name` names the cache entry:
+/// give each document its own, e.g. its path.
+pub fn check_source_incremental(
+    source: &str,
+    file_name: &str,
+    cancel: &CancellationToken,
+) -> Result<CheckReport> {
+    check_source_in(source, file_name, None, false, true, cancel)
+}
+
+/// [`check_source_with`], analyzing imports against `modules` when given
+fn check_source_in(
+    source: &str,
+    file_name: &str,
+    modules: Option<&ModuleMap>,
+    strict: bool,
+    incremental: bool,
+    cancel: &CancellationToken,
+) -> Result<CheckReport> {
+    let mut report = CheckReport {
+        file_name: file_name.to_string(),
+        diagnostics: Vec::new(),
+        warnings: Vec::new(),
+        ast: None,
+        symbols: Vec::new(),
+    };
+
+    // A file with syntax errors is parsed again with recovery, so that all
+    // of them are reported
+    let parsed = if incremental {
+        incremental::parse(file_name, source).ok()
+    } else {
+        None
+    };
+    let program = match parsed {
+        Some(program) => program,
+        None => {
+            let tokens = match lexer::tokenize(source) {
+                Ok(tokens) => tokens,
+                Err(err) => {
+                    report.push_diagnostic(err);
+                    return Ok(report);
+                }
+            };
+            cancel.check()?;
+
+            let (program, parse_errors) = parser::parse_with_recovery(tokens, source);
+            for err in parse_errors {
+                report.push_diagnostic(err);
+            }
+            program
+        }
+    };
+    cancel.check()?;
+
+    let program = if report.is_ok() {
+        // Imports resolve relative to the file, so it needs its path then;
+        // incremental analysis keeps its validations under the file's name
+        let (source_file, source_code) = match modules {
+            Some(_) => (file_name.to_string(), source.to_string()),
+            None if incremental => (file_name.to_string(), String::new()),
+            None => (String::new(), String::new()),
+        };
+        let options = CompilerOptions {
+            strict,
+            incremental,
+            max_errors: error::DEFAULT_MAX_ERRORS,
+            ..CompilerOptions::default()
+        };
+        let mut warnings = DiagnosticSink::new();
+        match semantic::analyze_cancellable(
+            program.clone(),
+            source_file,
+            source_code,
+            modules,
+            &options,
+            cancel,
+            &mut warnings,
+        ) {
+            Ok(analyzed) => {
+                report.warnings = warnings.into_vec();
+                analyzed
+            }
+            Err(err) if err.is_cancellation() => return Err(err),
+            Err(err) => {
+                report.push_diagnostic(err);
+                program
+            }
+        }
+    } else {
+        program
+    };
+
+    report.symbols = summarize_symbols(&program);
+    report.ast = Some(program);
+    Ok(report)
+}
+
+/// [`check_source`] for a file on disk, with its imports resolved
+///
+/// A file that imports others is analyzed against the modules it pulls in,
+/// as `livac check` does. When those can't be resolved (a missing file, or
+/// one that doesn't parse) the failure is reported at the file's first
+/// import, since the error's own location is in another file. Files of a
+/// project with `build.strict` in its liva.toml are checked in strict mode.
+pub fn check_file(path: &Path) -> CheckReport {
+    let file_name = path.to_string_lossy().into_owned();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            let mut report = check_source("", &file_name);
+            report.push_diagnostic(CompilerError::IoError(format!(
+                "Failed to read {}: {}",
+                file_name, err
+            )));
+            return report;
+        }
+    };
+
+    let cancel = CancellationToken::new();
+    let strict = package::Manifest::build_settings_for(path).strict;
+    if !source.contains("import ") {
+        return check_source_in(&source, &file_name, None, strict, false, &cancel)
+            .expect("a fresh cancellation token never fires");
+    }
+
+    let modules = module_resolver(path).and_then(|mut resolver| {
+        resolver.resolve_all()?;
+        resolver.hoist_class_extensions()?;
+        let order = resolver.compilation_order()?;
+        let modules: Vec<&module::Module> = order
+            .iter()
+            .filter_map(|p| resolver.get_module(p))
+            .collect();
+        let entry = modules
+            .iter()
+            .find(|m| m.path.canonicalize().ok() == path.canonicalize().ok())
+            .copied();
+        Ok(entry.map(|entry| module_map(&resolver, &modules, entry)))
+    });
+
+    match modules {
+        Ok(modules) => check_source_in(
+            &source,
+            &file_name,
+            modules.as_ref(),
+            strict,
+            false,
+            &cancel,
+        )
+        .expect("a fresh cancellation token never fires"),
+        Err(err) => {
+            let mut report = check_source(&source, &file_name);
+            if report.is_ok() {
+                let line = source
+                    .lines()
+                    .position(|l| l.trim_start().starts_with("import "))
+                    .map_or(1, |i| i + 1);
+                let info = SemanticErrorInfo::new(
+                    error_codes::E4005_MODULE_COMPILATION_FAILED,
+                    "Imported module has errors",
+                    &err.to_string(),
+                )
+                .with_location(&file_name, line);
+                report.push_diagnostic(CompilerError::SemanticError(Box::new(info)));
+            }
+            report
+        }
+    }
+}
+
+/// Checks every `.liva` file under `root`, in path order
+///
+/// Each file is checked with [`check_file`], so a file that imports a broken
+/// one is flagged too, not only the broken file itself. Hidden directories
+/// and build output (`target`, `build`, `dist`, `node_modules`) are skipped.
+pub fn check_workspace(root: &Path) -> Vec<CheckReport> {
+    let skipped = |entry: &walkdir::DirEntry| {
+        let name = entry.file_name().to_string_lossy();
+        entry.depth() > 0
+            && (name.starts_with('.')
+                || (entry.file_type().is_dir()
+                    && matches!(name.as_ref(), "target" | "build" | "dist" | "node_modules")))
+    };
+
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| !skipped(entry))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "liva"))
+        .collect();
+    files.sort();
+
+    files.iter().map(|path| check_file(path)).collect()
+}
+
+fn summarize_symbols(program: &ast::Program) -> Vec<SymbolSummary> {
+    use ast::TopLevel;
+
+    let symbol = |name: &str, kind, span| SymbolSummary {
+        name: name.to_string(),
+        kind,
+        span,
+    };
+
+    program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            TopLevel::Function(f) => Some(symbol(&f.name, SymbolKind::Function, f.span)),
+            TopLevel::Class(c) => Some(symbol(&c.name, SymbolKind::Class, c.span)),
+            TopLevel::Type(t) => Some(symbol(&t.name, SymbolKind::Interface, t.span)),
+            TopLevel::TypeAlias(t) => Some(symbol(&t.name, SymbolKind::TypeAlias, t.span)),
+            TopLevel::Enum(e) => Some(symbol(&e.name, SymbolKind::Enum, None)),
+            TopLevel::ConstDecl(c) => Some(symbol(&c.name, SymbolKind::Constant, c.span)),
+            TopLevel::ClassExtension(e) => Some(symbol(&e.name, SymbolKind::Extension, e.span)),
+            TopLevel::Test(t) => Some(symbol(&t.name, SymbolKind::Test, t.span)),
+            TopLevel::Import(_)
+            | TopLevel::UseRust(_)
+            | TopLevel::ExprStmt(_)
+            | TopLevel::Runtime(_)
+            | TopLevel::Init(_)
+            | TopLevel::TestHook(_) => None,
+        })
+        .collect()
 }
 
 /// Get version information about the compiler
@@ -514,12 +1691,140 @@ mod tests {
             output: None,
             verbose: false,
             check_only: false,
+            deterministic: false,
+            coverage: false,
+            debug: false,
+            inline_threshold: inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
+            profile: BuildProfile::Dev,
+            backend: backend::BackendKind::Rust,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            source_map: false,
+            max_errors: 1,
         };
 
         let result = compile_source(source, &options);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_strict_mode_rejects_guessed_types() {
+        let compile = |source: &str, strict: bool| {
+            let options = CompilerOptions {
+                check_only: true,
+                strict,
+                ..CompilerOptions::default()
+            };
+            compile_source(source, &options)
+                .err()
+                .and_then(|e| e.error_info().map(|info| info.code.clone()))
+        };
+
+        let cases = [
+            ("add(a, b) => a + b\n", "E5101"),
+            ("Person {\n    nickname\n}\n", "E5102"),
+            (
+                "main() {\n    let data, err = JSON.parse(\"{}\")\n}\n",
+                "E5103",
+            ),
+            (
+                "main() {\n    let n = 3\n    print(\"n = \" + n)\n}\n",
+                "E5104",
+            ),
+        ];
+        for (source, code) in cases {
+            assert_eq!(compile(source, true).as_deref(), Some(code), "{}", source);
+            assert_eq!(compile(source, false), None, "{}", source);
+        }
+
+        // Fully typed code, and constructor parameters named after typed fields
+        let typed = "Person {\n    name: string\n    constructor(name) {\n        this.name = name\n    }\n}\n\ngreet(p: Person): string => \"hi \" + p.name\n";
+        assert_eq!(compile(typed, true), None);
+    }
+
+    #[test]
+    fn test_profile_folds_debug_branches() {
+        let source = r#"
+main() {
+    if DEBUG {
+        print("debug trace")
+    } else {
+        print("release path")
+    }
+    let level = DEBUG ? "verbose" : "quiet"
+    print(level)
+}
+"#;
+        let generate = |profile: BuildProfile| {
+            let options = CompilerOptions {
+                profile,
+                ..CompilerOptions::default()
+            };
+            compile_source(source, &options).unwrap().rust_code.unwrap()
+        };
+
+        let release = generate(BuildProfile::Release);
+        assert!(!release.contains("debug trace"), "{}", release);
+        assert!(release.contains("release path"));
+        assert!(!release.contains("verbose"));
+
+        let dev = generate(BuildProfile::Dev);
+        assert!(dev.contains("debug trace"), "{}", dev);
+        assert!(!dev.contains("release path"));
+        assert!(dev.contains("verbose"));
+    }
+
+    #[test]
+    fn test_format_output_leaves_no_trailing_whitespace() {
+        let source = "main() {\n    let names = [\"a\", \"b\"]\n    for name in names {\n        print(name)\n    }\n}\n";
+        let options = CompilerOptions {
+            format_output: true,
+            ..CompilerOptions::default()
+        };
+        let rust_code = compile_source(source, &options).unwrap().rust_code.unwrap();
+        assert!(rust_code.contains("fn main"));
+        for line in rust_code.lines() {
+            assert_eq!(line, line.trim_end(), "{}", rust_code);
+        }
+        assert!(!rust_code.contains("\n\n\n"), "{}", rust_code);
+    }
+
+    #[test]
+    fn test_source_map_points_at_clean_rust_lines() {
+        let source = "main() {\n    let total = 40 + 2\n    print(total)\n}\n";
+        let options = CompilerOptions {
+            source_map: true,
+            max_errors: 1,
+            ..CompilerOptions::default()
+        };
+        let result = compile_source(source, &options).unwrap();
+        let rust_code = result.rust_code.unwrap();
+        assert!(!rust_code.contains("// @liva"), "{}", rust_code);
+
+        let map = result.metadata.line_map.unwrap();
+        let entry = map.entries.iter().find(|e| e.liva_line == 2).unwrap();
+        let rust_line = rust_code.lines().nth(entry.rust_line as usize - 1).unwrap();
+        assert!(
+            rust_line.trim_start().starts_with("let") && rust_line.contains("total"),
+            "{}",
+            rust_line
+        );
+    }
+
+    #[test]
+    fn test_build_profile_from_str() {
+        assert_eq!("dev".parse::<BuildProfile>(), Ok(BuildProfile::Dev));
+        assert_eq!("release".parse::<BuildProfile>(), Ok(BuildProfile::Release));
+        assert!("fast".parse::<BuildProfile>().is_err());
+        assert_eq!(BuildProfile::Release.target_dir(), "release");
+    }
+
     #[test]
     fn test_check_syntax_valid() {
         let source = r#"
@@ -538,6 +1843,310 @@ mod tests {
         assert!(check_syntax(source).is_err());
     }
 
+    #[test]
+    fn test_check_source_reports_all_parse_errors() {
+        let source = "first() {\n  let = 1\n}\n\nok() => 1\n\nsecond() {\n  let = 2\n}\n";
+
+        let report = check_source(source, "broken.liva");
+        assert_eq!(report.diagnostics.len(), 2);
+        let location = report.diagnostics[0]
+            .error_info()
+            .and_then(|info| info.location.as_ref())
+            .unwrap();
+        assert_eq!(location.file, "broken.liva");
+        assert!(report.symbols.iter().any(|s| s.name == "ok"));
+    }
+
+    #[test]
+    fn test_check_source_symbols_carry_spans() {
+        let source = "type Shape {\n    area(): float\n}\n\nBox {\n    w: int\n}\n\nsum(a: int, b: int): int => a + b\n";
+
+        let report = check_source(source, "symbols.liva");
+        let json = serde_json::to_value(&report.symbols).unwrap();
+        for symbol in json.as_array().unwrap() {
+            assert!(!symbol["span"].is_null(), "{}", symbol);
+        }
+        let sum = &json[2];
+        assert_eq!(sum["name"], "sum");
+        assert_eq!(sum["span"]["start"], source.find("sum").unwrap());
+    }
+
+    #[test]
+    fn test_check_syntax_all_resumes_after_bad_statements() {
+        let source = "main() {\n    let = 1\n    print(2)\n    let = 3\n}\n\nok() => 1\n";
+
+        let errors = check_syntax_all(source);
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        let lines: Vec<_> = errors
+            .iter()
+            .filter_map(|e| e.error_info()?.location.as_ref().map(|l| l.line))
+            .collect();
+        assert_eq!(lines, vec![2, 4]);
+        assert!(check_syntax_all("ok() => 1\n").is_empty());
+    }
+
+    #[test]
+    fn test_max_errors_reports_every_declaration() {
+        let source = "first() {\n    let count = 3\n    if count {\n        print(count)\n    }\n}\n\nsecond() {\n    let name = \"liva\"\n    while name {\n        print(name)\n    }\n}\n\nmain() {\n    first()\n    second()\n}\n";
+        let options = |max_errors| CompilerOptions {
+            check_only: true,
+            max_errors,
+            ..CompilerOptions::default()
+        };
+
+        let first_only = compile_source(source, &options(1)).unwrap_err();
+        assert_eq!(first_only.errors().len(), 1);
+
+        let all = compile_source(source, &options(error::DEFAULT_MAX_ERRORS)).unwrap_err();
+        let codes: Vec<_> = all
+            .errors()
+            .iter()
+            .filter_map(|e| e.error_info().map(|info| info.code.as_str()))
+            .collect();
+        assert_eq!(codes, vec!["E5010", "E5010"], "{}", all);
+
+        let capped = compile_source(source, &options(2)).unwrap_err();
+        assert_eq!(capped.errors().len(), 2);
+    }
+
+    #[test]
+    fn test_check_source_symbols() {
+        let source = r#"
+const LIMIT = 10
+
+Point {
+    x: number
+}
+
+sum(a: number, b: number): number => a + b
+"#;
+
+        let report = check_source(source, "symbols.liva");
+        assert!(report.is_ok());
+        assert!(report.ast.is_some());
+        let kinds: Vec<_> = report
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("LIMIT", SymbolKind::Constant),
+                ("Point", SymbolKind::Class),
+                ("sum", SymbolKind::Function),
+            ]
+        );
+    }
+
+    struct RenamePass;
+
+    impl CompilerPass for RenamePass {
+        fn name(&self) -> &str {
+            "rename"
+        }
+
+        fn run(&mut self, program: &mut ast::Program, diagnostics: &mut DiagnosticSink) {
+            for item in &mut program.items {
+                if let ast::TopLevel::Function(f) = item {
+                    if f.name == "helper" {
+                        f.name = "renamed_helper".to_string();
+                        diagnostics.warning(SemanticErrorInfo::new(
+                            "W9000",
+                            "Renamed",
+                            "helper was renamed",
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    struct RejectAllPass;
+
+    impl CompilerPass for RejectAllPass {
+        fn name(&self) -> &str {
+            "reject-all"
+        }
+
+        fn run(&mut self, _program: &mut ast::Program, diagnostics: &mut DiagnosticSink) {
+            diagnostics.error(SemanticErrorInfo::new(
+                "E9000",
+                "Rejected",
+                "not allowed here",
+            ));
+        }
+    }
+
+    #[test]
+    fn test_custom_pass_transforms_ast_and_reports_warnings() {
+        let source = "helper(): number => 1\n";
+        let options = CompilerOptions::default();
+        let mut passes = PassManager::new();
+        passes.add(RenamePass);
+
+        let result = compile_source_with_passes(source, &options, &mut passes).unwrap();
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].severity, Severity::Warning);
+        assert!(result.rust_code.unwrap().contains("fn renamed_helper"));
+    }
+
+    #[test]
+    fn test_semantic_warnings_come_back_as_diagnostics() {
+        let source = "liva_helper(): number => 1\n\nmain() {\n    print(liva_helper())\n}\n";
+        let options = CompilerOptions {
+            check_only: true,
+            ..CompilerOptions::default()
+        };
+        let result = compile_source(source, &options).unwrap();
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].code(), "E0010");
+        assert_eq!(result.diagnostics[0].severity, Severity::Warning);
+
+        let report = check_source(source, "helper.liva");
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_custom_pass_error_stops_compilation() {
+        let options = CompilerOptions::default();
+        let mut passes = PassManager::new();
+        passes.add(RenamePass).add(RejectAllPass);
+        assert_eq!(passes.len(), 2);
+
+        let err = compile_source_with_passes("main() {}", &options, &mut passes).unwrap_err();
+        assert!(err.to_string().contains("[reject-all] not allowed here"));
+    }
+
+    /// Reports an error for every function
+    struct NoFunctionsPass;
+
+    impl CompilerPass for NoFunctionsPass {
+        fn name(&self) -> &str {
+            "no-functions"
+        }
+
+        fn run(&mut self, program: &mut ast::Program, diagnostics: &mut DiagnosticSink) {
+            for item in &program.items {
+                if let ast::TopLevel::Function(f) = item {
+                    diagnostics.error(SemanticErrorInfo::new(
+                        "E9001",
+                        "Function",
+                        &format!("'{}' is a function", f.name),
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_pass_errors_are_collected_up_to_max_errors() {
+        let source = "one() => 1
+two() => 2
+three() => 3
+";
+        let errors = |max_errors| {
+            let options = CompilerOptions {
+                max_errors,
+                ..CompilerOptions::default()
+            };
+            let mut passes = PassManager::new();
+            passes.add(NoFunctionsPass).add(RejectAllPass);
+            compile_source_with_passes(source, &options, &mut passes)
+                .unwrap_err()
+                .into_errors()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let first = errors(1);
+        assert_eq!(first.len(), 1);
+        assert!(first[0].contains("[no-functions] 'one' is a function"));
+        assert_eq!(errors(2).len(), 2);
+
+        let all = errors(10);
+        assert_eq!(all.len(), 4, "{:?}", all);
+        assert!(all[2].contains("'three'"));
+        assert!(all[3].contains("[reject-all] not allowed here"));
+    }
+
+    /// Cancels the token it was given, as an editor would on the next keystroke
+    struct CancelPass(CancellationToken);
+
+    impl CompilerPass for CancelPass {
+        fn name(&self) -> &str {
+            "cancel"
+        }
+
+        fn run(&mut self, _program: &mut ast::Program, _diagnostics: &mut DiagnosticSink) {
+            self.0.cancel();
+        }
+    }
+
+    #[test]
+    fn test_cancelled_compilation_stops_before_later_passes_and_output() {
+        let tmp = tempdir().unwrap();
+        let options = CompilerOptions {
+            output: Some(tmp.path().join("build")),
+            ..CompilerOptions::default()
+        };
+        let token = CancellationToken::new();
+        let mut passes = PassManager::new();
+        passes.add(CancelPass(token.clone())).add(RejectAllPass);
+
+        let err = compile_source_with_filename(
+            "main() { print(1) }",
+            "cancel.liva",
+            "cancel",
+            &options,
+            &BTreeMap::new(),
+            &mut passes,
+            &token,
+        )
+        .unwrap_err();
+        assert!(matches!(err, CompilerError::Cancelled));
+        assert!(!tmp.path().join("build").exists());
+    }
+
+    #[test]
+    fn test_check_source_with_expired_deadline() {
+        let token = CancellationToken::with_deadline(std::time::Instant::now());
+        let err = check_source_with("main() {}", "late.liva", &token).unwrap_err();
+        assert!(matches!(err, CompilerError::DeadlineExceeded));
+
+        let report = check_source_with("main() {}", "ok.liva", &CancellationToken::new()).unwrap();
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn test_check_source_incremental_matches_full_check() {
+        let file = "test_check_source_incremental.liva";
+        let check = |source: &str| {
+            check_source_incremental(source, file, &CancellationToken::new()).unwrap()
+        };
+        let source =
+            "inc(n: number): number {\n    return n + 1\n}\n\nmain() {\n    print(inc(1))\n}\n";
+        let edits = [
+            source.to_string(),
+            source.replace("n + 1", "n + 2"),
+            source.replace("print(inc(1))", "let x = \n    print(inc(1))"),
+            format!("// Counting\n{}", source),
+        ];
+        for edited in &edits {
+            let incremental = check(edited);
+            let full = check_source(edited, file);
+            assert_eq!(incremental.ast, full.ast);
+            assert_eq!(incremental.symbols, full.symbols);
+            assert_eq!(
+                incremental.diagnostics.len(),
+                full.diagnostics.len(),
+                "{}",
+                edited
+            );
+        }
+    }
+
     #[test]
     fn test_version() {
         let ver = version();
@@ -552,6 +2161,22 @@ mod tests {
             output: None,
             verbose: false,
             check_only: true,
+            deterministic: false,
+            coverage: false,
+            debug: false,
+            inline_threshold: inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
+            profile: BuildProfile::Dev,
+            backend: backend::BackendKind::Rust,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            source_map: false,
+            max_errors: 1,
         };
 
         let result = compile_source(
@@ -569,6 +2194,36 @@ mod tests {
         assert!(result.output_dir.is_none());
     }
 
+    #[test]
+    fn test_liva_toml_pins_use_rust_crates() {
+        let tmp = tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join(package::MANIFEST_FILE),
+            "[package]\nname = \"app\"\n\n[rust_dependencies]\nitertools = \"0.13\"\n",
+        )
+        .unwrap();
+        let input = tmp.path().join("main.liva");
+        std::fs::write(
+            &input,
+            "use rust \"itertools\"\nuse rust \"semver\"\n\nmain() {\n    print(1)\n}\n",
+        )
+        .unwrap();
+
+        let result = compile_file(&CompilerOptions {
+            input,
+            ..CompilerOptions::default()
+        })
+        .expect("compilation should succeed");
+
+        let cargo_toml = result.cargo_toml.expect("Cargo.toml expected");
+        assert!(
+            cargo_toml.contains("itertools = \"0.13\""),
+            "{}",
+            cargo_toml
+        );
+        assert!(cargo_toml.contains("semver = \"*\""), "{}", cargo_toml);
+    }
+
     #[test]
     fn test_compile_source_writes_output_files() {
         let tmp = tempdir().unwrap();
@@ -578,6 +2233,22 @@ mod tests {
             output: Some(out_dir.clone()),
             verbose: true,
             check_only: false,
+            deterministic: false,
+            coverage: false,
+            debug: false,
+            inline_threshold: inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
+            profile: BuildProfile::Dev,
+            backend: backend::BackendKind::Rust,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            source_map: false,
+            max_errors: 1,
         };
 
         let result = compile_source(
@@ -599,6 +2270,34 @@ mod tests {
         assert!(output_dir.join("Cargo.toml").exists());
     }
 
+    #[test]
+    fn test_compile_file_names_crate_after_input() {
+        let tmp = tempdir().unwrap();
+        let input = tmp.path().join("todo-cli.liva");
+        std::fs::write(&input, "main() {\n    print(1)\n}\n").unwrap();
+        let options = CompilerOptions {
+            input,
+            profile: BuildProfile::Release,
+            ..CompilerOptions::default()
+        };
+
+        let result = compile_file(&options).expect("compilation should succeed");
+
+        assert_eq!(result.metadata.crate_name, "todo_cli");
+        assert_eq!(
+            result.metadata.binary,
+            Some(PathBuf::from("target/release/todo_cli"))
+        );
+        assert!(result
+            .cargo_toml
+            .expect("manifest expected")
+            .contains("name = \"todo_cli\""));
+
+        // Source compiled from a string keeps the default name
+        let result = compile_source("main() { print(1) }", &CompilerOptions::default()).unwrap();
+        assert_eq!(result.metadata.crate_name, artifacts::DEFAULT_CRATE_NAME);
+    }
+
     #[test]
     fn test_version_info_contains_components() {
         let info = version_info();
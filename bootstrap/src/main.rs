@@ -3,14 +3,41 @@ use colored::*;
 use std::path::PathBuf;
 use std::process::Command;
 
-use livac::{CompilerError, CompilerOptions};
+use livac::error_codes::{E3000_CODEGEN_ERROR, E3002_BUILD_FAILED};
+use livac::sandbox::{Capability, SandboxPolicy};
+use livac::{BuildProfile, CompilerError, CompilerOptions, IdentifierMapping, NumericCoercion};
+
+mod cli;
+use cli::events::{Events, MessageFormat};
+use cli::scaffold::{self, Template};
 
 const GITHUB_REPO: &str = "liva-lang/livac";
 
+/// Exit codes, one per kind of failure, so scripts can tell them apart
+const EXIT_FAILURE: i32 = 1;
+const EXIT_PARSE_ERROR: i32 = 2;
+const EXIT_SEMANTIC_ERROR: i32 = 3;
+const EXIT_CODEGEN_ERROR: i32 = 4;
+const EXIT_BUILD_ERROR: i32 = 5;
+const EXIT_RUNTIME_ERROR: i32 = 6;
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  success
+  1  other failure (I/O, missing toolchain, bad arguments)
+  2  lexer or parse error
+  3  semantic or type error
+  4  code generation error
+  5  cargo build of the generated Rust failed
+  6  the compiled program was killed by a signal (livac run)
+
+`livac run` otherwise exits with the status of the compiled program.";
+
 #[derive(Parser)]
 #[command(name = "livac")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "Liva → Rust compiler", long_about = None)]
+#[command(after_help = EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -20,60 +47,306 @@ struct Cli {
 enum Commands {
     /// Compile a Liva file to a native binary
     Build {
-        /// Input Liva file
-        input: PathBuf,
+        /// Input Liva file (default: the entry of the project's liva.toml)
+        input: Option<PathBuf>,
 
-        /// Output directory (default: ./target/liva_build)
+        /// Output directory (default: `build.output` in liva.toml, or
+        /// ./target/liva_build)
         #[arg(short, long)]
         output: Option<PathBuf>,
 
+        /// Put the generated crate in a subdirectory of the output directory
+        /// named after the program, so several programs can share one
+        #[arg(long)]
+        per_target: bool,
+
         /// Show generated Rust code
         #[arg(short, long)]
         verbose: bool,
 
-        /// Compile with optimizations (cargo build --release)
+        /// Print only errors and warnings, without the banner and progress lines
+        #[arg(short, long, conflicts_with = "verbose")]
+        quiet: bool,
+
+        /// Compile with optimizations (cargo build --release); same as
+        /// `--profile release`
         #[arg(long)]
         release: bool,
 
+        /// Build profile: dev (debug assertions, `DEBUG` is true) or release
+        /// (optimized, `DEBUG` is false and its branches are compiled out)
+        #[arg(long, value_name = "PROFILE", conflicts_with = "release")]
+        profile: Option<BuildProfile>,
+
         /// Output errors in JSON format for IDE integration
         #[arg(long)]
         json: bool,
+
+        /// json: report progress, diagnostics and artifacts as newline-delimited
+        /// JSON events on stdout
+        #[arg(long, value_enum, value_name = "FORMAT", default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+
+        /// Emit Liva line markers and write a liva-debug.json line map for debuggers
+        #[arg(long, conflicts_with = "release")]
+        debug: bool,
+
+        /// Body size (AST nodes) under which functions get #[inline]; 0 disables
+        #[arg(long, default_value_t = livac::inlining::DEFAULT_INLINE_THRESHOLD)]
+        inline_threshold: usize,
+
+        /// Reject code whose types would be guessed (untyped parameters, untyped
+        /// JSON, implicit string conversion); liva.toml: `[build] strict = true`
+        #[arg(long)]
+        strict: bool,
+
+        /// How JSON fields used as numbers are converted: coerce (missing → 0),
+        /// checked (runtime error) or forbid (compile error); default from liva.toml
+        #[arg(long, value_name = "POLICY")]
+        numeric_coercion: Option<NumericCoercion>,
+
+        /// Rust names of Liva identifiers: snake (`myValue` → `my_value`) or
+        /// preserve (kept as written); default from liva.toml
+        #[arg(long, value_name = "MAPPING")]
+        identifier_mapping: Option<IdentifierMapping>,
+
+        /// Compile untrusted source: reject file, network, process and
+        /// environment access and Rust interop unless allowed with --allow
+        #[arg(long)]
+        sandbox: bool,
+
+        /// Capabilities a --sandbox build may use: fs, net, process, env, rust
+        #[arg(
+            long,
+            value_name = "CAPABILITY",
+            value_delimiter = ',',
+            requires = "sandbox"
+        )]
+        allow: Vec<Capability>,
+
+        /// Crate a --sandbox build may bring in with `use rust`
+        #[arg(long, value_name = "CRATE", requires = "sandbox")]
+        allow_crate: Vec<String>,
+
+        /// Parse the generated Rust before writing it and report a syntax
+        /// error there as a livac bug (E3004); always on in debug builds of livac
+        #[arg(long)]
+        self_check: bool,
+
+        /// Print an intermediate form instead of building: ast-pretty (the
+        /// analyzed AST as a tree, with spans and inferred types)
+        #[arg(long, value_enum, value_name = "KIND")]
+        emit: Option<Emit>,
+
+        /// Print each rewrite desugaring applies, with the source before and after
+        #[arg(long)]
+        trace_desugar: bool,
+
+        /// Annotate the generated Rust with `/* livac: ... */` comments giving
+        /// the reason for each guess codegen makes, for bug reports
+        #[arg(long)]
+        explain_codegen: bool,
+
+        /// Run the generated Rust through rustfmt (without rustfmt, only
+        /// trailing whitespace and extra blank lines are removed)
+        #[arg(long)]
+        format_output: bool,
+
+        /// Write a JSON summary of the language features the program uses
+        /// (async, parallelism, fallible functions, classes, ...) to FILE;
+        /// the report stays local
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+
+        /// Errors to report before giving up; parsing resumes at the next
+        /// statement and analysis at the next declaration. 1 stops at the first
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = livac::error::DEFAULT_MAX_ERRORS,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        max_errors: usize,
     },
 
     /// Compile and run a Liva file
     Run {
-        /// Input Liva file
-        input: PathBuf,
+        /// Input Liva file (default: the entry of the project's liva.toml)
+        input: Option<PathBuf>,
 
-        /// Output directory (default: ./target/liva_build)
+        /// Output directory (default: `build.output` in liva.toml, or
+        /// ./target/liva_build)
         #[arg(short, long)]
         output: Option<PathBuf>,
 
+        /// Put the generated crate in a subdirectory of the output directory
+        /// named after the program, so several programs can share one
+        #[arg(long)]
+        per_target: bool,
+
         /// Show generated Rust code
         #[arg(short, long)]
         verbose: bool,
 
-        /// Compile with optimizations (cargo build --release)
+        /// Print only errors and warnings, without the banner and progress lines
+        #[arg(short, long, conflicts_with = "verbose")]
+        quiet: bool,
+
+        /// Compile with optimizations (cargo build --release); same as
+        /// `--profile release`
         #[arg(long)]
         release: bool,
 
+        /// Build profile: dev (debug assertions, `DEBUG` is true) or release
+        /// (optimized, `DEBUG` is false and its branches are compiled out)
+        #[arg(long, value_name = "PROFILE", conflicts_with = "release")]
+        profile: Option<BuildProfile>,
+
         /// Output errors in JSON format for IDE integration
         #[arg(long)]
         json: bool,
 
+        /// json: report progress, diagnostics and artifacts as newline-delimited
+        /// JSON events on stdout
+        #[arg(long, value_enum, value_name = "FORMAT", default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+
+        /// Reject code whose types would be guessed (untyped parameters, untyped
+        /// JSON, implicit string conversion); liva.toml: `[build] strict = true`
+        #[arg(long)]
+        strict: bool,
+
+        /// How JSON fields used as numbers are converted: coerce (missing → 0),
+        /// checked (runtime error) or forbid (compile error); default from liva.toml
+        #[arg(long, value_name = "POLICY")]
+        numeric_coercion: Option<NumericCoercion>,
+
+        /// Rust names of Liva identifiers: snake (`myValue` → `my_value`) or
+        /// preserve (kept as written); default from liva.toml
+        #[arg(long, value_name = "MAPPING")]
+        identifier_mapping: Option<IdentifierMapping>,
+
+        /// Compile untrusted source: reject file, network, process and
+        /// environment access and Rust interop unless allowed with --allow
+        #[arg(long)]
+        sandbox: bool,
+
+        /// Capabilities a --sandbox build may use: fs, net, process, env, rust
+        #[arg(
+            long,
+            value_name = "CAPABILITY",
+            value_delimiter = ',',
+            requires = "sandbox"
+        )]
+        allow: Vec<Capability>,
+
+        /// Crate a --sandbox build may bring in with `use rust`
+        #[arg(long, value_name = "CRATE", requires = "sandbox")]
+        allow_crate: Vec<String>,
+
+        /// Parse the generated Rust before writing it and report a syntax
+        /// error there as a livac bug (E3004); always on in debug builds of livac
+        #[arg(long)]
+        self_check: bool,
+
+        /// Run the generated Rust through rustfmt (without rustfmt, only
+        /// trailing whitespace and extra blank lines are removed)
+        #[arg(long)]
+        format_output: bool,
+
         /// Arguments to pass to the compiled program (after --)
         #[arg(last = true)]
         program_args: Vec<String>,
     },
 
+    /// Run a program and rebuild and restart it whenever a source file changes
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Dev {
+        #[command(subcommand)]
+        command: Option<DevCommand>,
+
+        /// Entry Liva file; `.liva` files under its directory are watched
+        #[arg(required = true)]
+        input: Option<PathBuf>,
+
+        /// Compile with optimizations (cargo build --release); same as
+        /// `--profile release`
+        #[arg(long)]
+        release: bool,
+
+        /// Build profile: dev (debug assertions, `DEBUG` is true) or release
+        /// (optimized, `DEBUG` is false and its branches are compiled out)
+        #[arg(long, value_name = "PROFILE", conflicts_with = "release")]
+        profile: Option<BuildProfile>,
+
+        /// Seconds a restarting program may take to finish in-flight requests
+        #[arg(long, value_name = "SECS", default_value_t = 10)]
+        drain_timeout: u64,
+
+        /// Arguments to pass to the program (after --)
+        #[arg(last = true)]
+        program_args: Vec<String>,
+    },
+
     /// Check a Liva file for errors without compiling
     Check {
-        /// Input Liva file
-        input: PathBuf,
+        /// Input Liva file, or the directory to check with --workspace
+        #[arg(required_unless_present = "workspace")]
+        input: Option<PathBuf>,
+
+        /// Check every .liva file under the input directory (default: current
+        /// directory), resolving imports between them
+        #[arg(long)]
+        workspace: bool,
 
         /// Output errors in JSON format for IDE integration
         #[arg(long)]
         json: bool,
+
+        /// json: report progress, diagnostics and artifacts as newline-delimited
+        /// JSON events on stdout
+        #[arg(
+            long,
+            value_enum,
+            value_name = "FORMAT",
+            default_value_t = MessageFormat::Human,
+            conflicts_with = "workspace"
+        )]
+        message_format: MessageFormat,
+
+        /// Reject code whose types would be guessed; for --workspace, set
+        /// `[build] strict = true` in liva.toml instead
+        #[arg(long, conflicts_with = "workspace")]
+        strict: bool,
+
+        /// Compile untrusted source: reject file, network, process and
+        /// environment access and Rust interop unless allowed with --allow
+        #[arg(long, conflicts_with = "workspace")]
+        sandbox: bool,
+
+        /// Capabilities a --sandbox build may use: fs, net, process, env, rust
+        #[arg(
+            long,
+            value_name = "CAPABILITY",
+            value_delimiter = ',',
+            requires = "sandbox"
+        )]
+        allow: Vec<Capability>,
+
+        /// Crate a --sandbox build may bring in with `use rust`
+        #[arg(long, value_name = "CRATE", requires = "sandbox")]
+        allow_crate: Vec<String>,
+
+        /// Errors to report before giving up; parsing resumes at the next
+        /// statement and analysis at the next declaration. 1 stops at the first
+        #[arg(
+            long,
+            value_name = "N",
+            conflicts_with = "workspace",
+            default_value_t = livac::error::DEFAULT_MAX_ERRORS,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
+        max_errors: usize,
     },
 
     /// Format Liva source files
@@ -89,6 +362,10 @@ enum Commands {
         #[arg(short, long)]
         verbose: bool,
 
+        /// Also sort, merge and deduplicate import statements
+        #[arg(long)]
+        organize_imports: bool,
+
         /// Output errors in JSON format for IDE integration
         #[arg(long)]
         json: bool,
@@ -106,11 +383,30 @@ enum Commands {
         /// Show detailed output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Reproducible runs: seeded randomness, single-threaded runtime and test harness
+        #[arg(long)]
+        deterministic: bool,
+
+        /// Seed for the random builtins in deterministic mode
+        #[arg(long, default_value_t = 0, requires = "deterministic")]
+        seed: u64,
+
+        /// Record statement coverage and write lcov.info + index.html reports
+        #[arg(long)]
+        coverage: bool,
+
+        /// Output directory for coverage reports
+        #[arg(long, default_value = "coverage", requires = "coverage")]
+        coverage_dir: PathBuf,
     },
 
     /// Start Language Server Protocol mode
     Lsp,
 
+    /// Start the Debug Adapter Protocol server (stdio) for step-debugging .liva files
+    Dap,
+
     /// Lint a Liva file for warnings (unused variables, unreachable code, etc.)
     Lint {
         /// Input Liva file
@@ -119,46 +415,315 @@ enum Commands {
         /// Output warnings in JSON format for IDE integration
         #[arg(long)]
         json: bool,
+
+        /// Case that names must be written in (W013): camel or snake;
+        /// default from liva.toml `[lint] naming`, otherwise unchecked
+        #[arg(long, value_name = "CONVENTION")]
+        naming: Option<livac::package::NamingConvention>,
+    },
+
+    /// Rewrite syntax removed in newer livac versions (`data`, `fire`, `__` prefix)
+    Migrate {
+        /// Input Liva file
+        input: PathBuf,
+
+        /// Show the changes as a diff without writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// List the builtin functions with their signatures
+    Builtins {
+        /// Output the table as JSON for editor extensions and doc generators
+        #[arg(long)]
+        json: bool,
     },
 
     /// Update livac to the latest version
     Update,
 
+    /// Create a new Liva project in a new directory
+    New {
+        /// Project name (also the directory name)
+        name: String,
+
+        /// Starter template
+        #[arg(short, long, value_enum, default_value_t = Template::App)]
+        template: Template,
+    },
+
+    /// Add a dependency to liva.toml and fetch it
+    Add {
+        /// Package name (the name used in imports: `from "name"`)
+        name: String,
+
+        /// Local directory of the package
+        #[arg(long, conflicts_with = "git")]
+        path: Option<String>,
+
+        /// Git repository URL of the package
+        #[arg(long)]
+        git: Option<String>,
+
+        /// Git commit to use
+        #[arg(long, requires = "git", conflicts_with_all = ["tag", "branch"])]
+        rev: Option<String>,
+
+        /// Git tag to use
+        #[arg(long, requires = "git", conflicts_with = "branch")]
+        tag: Option<String>,
+
+        /// Git branch to follow
+        #[arg(long, requires = "git")]
+        branch: Option<String>,
+    },
+
+    /// Fetch the dependencies in liva.toml and write liva.lock
+    Install,
+
     /// Initialize a new Liva project
     Init {
         /// Project name or "." for current directory
         #[arg(default_value = ".")]
         name: String,
+
+        /// Starter template
+        #[arg(short, long, value_enum, default_value_t = Template::App)]
+        template: Template,
+    },
+}
+
+/// Subcommands of `livac dev`
+#[derive(Subcommand)]
+enum DevCommand {
+    /// Compile every example program, and run those with a `.stdout` file
+    /// next to them and compare their output with it
+    RunExamples {
+        /// Directory of example programs
+        #[arg(default_value = "examples")]
+        dir: PathBuf,
+
+        /// Only compile; build and run nothing
+        #[arg(long)]
+        no_run: bool,
+
+        /// Write each program's output to its `.stdout` file instead of
+        /// comparing (create an empty one to add an example)
+        #[arg(long, conflicts_with = "no_run")]
+        bless: bool,
+
+        /// Seconds each program may run
+        #[arg(long, value_name = "SECS", default_value_t = 10)]
+        timeout: u64,
+
+        /// Only examples whose path contains this text
+        #[arg(long, value_name = "TEXT")]
+        filter: Option<String>,
+    },
+
+    /// Show the declarations and statements added, removed or changed
+    /// between two versions of a file (exit code 1 when they differ)
+    AstDiff {
+        /// The old version
+        old: PathBuf,
+
+        /// The new version
+        new: PathBuf,
+
+        /// Print the changes as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Remove declarations and statements from a file that makes the
+    /// compiler fail for as long as it still fails the same way
+    Reduce {
+        /// The file the compiler fails on
+        input: PathBuf,
+
+        /// Write the reduced program here instead of printing it
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Also keep the error message the same, not just its code or the
+        /// place the compiler panicked
+        #[arg(long)]
+        exact: bool,
     },
 }
 
 /// Internal struct passed to compile() with resolved options
+/// Intermediate output `livac build --emit` prints
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum Emit {
+    AstPretty,
+}
+
 struct CompileArgs {
     output: Option<PathBuf>,
     run: bool,
     verbose: bool,
     check: bool,
     json: bool,
-    release: bool,
+    profile: BuildProfile,
+    debug: bool,
+    inline_threshold: usize,
+    strict: bool,
+    numeric_coercion: Option<NumericCoercion>,
+    identifier_mapping: Option<IdentifierMapping>,
     program_args: Vec<String>,
+    quiet: bool,
+    events: Events,
+    per_target: bool,
+    sandbox: Option<SandboxPolicy>,
+    self_check: bool,
+    /// Reuse the items parsed by the previous compile in this process
+    incremental: bool,
+    /// Comment the guesses codegen makes in the generated Rust
+    explain_codegen: bool,
+    /// Run the generated Rust through rustfmt
+    format_output: bool,
+    report: Option<PathBuf>,
+    /// Errors to collect before giving up
+    max_errors: usize,
 }
 
-fn handle_compile_error(json: bool, e: CompilerError) -> ! {
-    if json {
-        if let Some(json_str) = e.to_json() {
-            println!("{}", json_str);
+impl CompileArgs {
+    /// Where the crate for `crate_name` goes under `output`: a subdirectory
+    /// of its own with `--per-target`, `output` itself otherwise
+    fn target_dir(&self, output: &std::path::Path, crate_name: &str) -> PathBuf {
+        if self.per_target {
+            output.join(crate_name)
+        } else {
+            output.to_path_buf()
+        }
+    }
+
+    /// Progress output goes to stderr, so with `livac run` stdout is the
+    /// program's alone; `--quiet` drops it, and JSON events replace it
+    fn progress(&self, message: std::fmt::Arguments) {
+        if !self.quiet && !self.events.enabled() {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+/// `--release` is short for `--profile release`; without either,
+/// `build.profile` in the liva.toml of `input`'s project, or `dev`
+fn select_profile(
+    release: bool,
+    profile: Option<BuildProfile>,
+    input: &std::path::Path,
+) -> BuildProfile {
+    if release {
+        BuildProfile::Release
+    } else {
+        profile
+            .or_else(|| livac::package::Manifest::build_settings_for(input).profile)
+            .unwrap_or_default()
+    }
+}
+
+/// The file `livac build` and `livac run` compile: `input`, or the entry of
+/// the project whose liva.toml is in the current directory or above
+fn project_input(input: Option<PathBuf>) -> PathBuf {
+    input
+        .or_else(|| livac::package::Manifest::entry_for(std::path::Path::new(".")))
+        .unwrap_or_else(|| {
+            eprintln!(
+                "{} No input file given and no {} found",
+                "Error:".red().bold(),
+                livac::package::MANIFEST_FILE
+            );
             std::process::exit(1);
+        })
+}
+
+/// The policy for `--sandbox`, allowing the `--allow` capabilities and
+/// `--allow-crate` crates
+fn sandbox_policy(
+    sandbox: bool,
+    allow: Vec<Capability>,
+    allow_crate: Vec<String>,
+) -> Option<SandboxPolicy> {
+    if !sandbox {
+        return None;
+    }
+    let policy = allow
+        .into_iter()
+        .fold(SandboxPolicy::new(), SandboxPolicy::allow);
+    Some(
+        allow_crate
+            .iter()
+            .fold(policy, |policy, name| policy.allow_crate(name)),
+    )
+}
+
+/// The exit code for a failed compile (see `EXIT_CODES_HELP`)
+fn exit_code(e: &CompilerError) -> i32 {
+    match e {
+        CompilerError::LexerError(_) | CompilerError::ParseError(_) => EXIT_PARSE_ERROR,
+        CompilerError::SemanticError(_) | CompilerError::TypeError(_) => EXIT_SEMANTIC_ERROR,
+        CompilerError::CodegenError(info) if info.code == E3002_BUILD_FAILED => EXIT_BUILD_ERROR,
+        CompilerError::CodegenError(_) => EXIT_CODEGEN_ERROR,
+        CompilerError::RuntimeError(_) => EXIT_RUNTIME_ERROR,
+        CompilerError::IoError(_) | CompilerError::Cancelled | CompilerError::DeadlineExceeded => {
+            EXIT_FAILURE
+        }
+        CompilerError::Multiple(errors) => errors.first().map_or(EXIT_FAILURE, exit_code),
+    }
+}
+
+/// A warning or hint on stderr, labeled by its severity
+fn print_diagnostic(diagnostic: &livac::Diagnostic) {
+    let label = match diagnostic.severity {
+        livac::Severity::Error => "Error:".red().bold(),
+        livac::Severity::Warning => "⚠ Warning".yellow().bold(),
+        livac::Severity::Hint => "💡 Hint".cyan().bold(),
+    };
+    eprintln!(
+        "{} {}: {}",
+        label,
+        diagnostic.code(),
+        diagnostic.info.message
+    );
+    if let Some(help) = &diagnostic.info.help {
+        eprintln!("   {} {}", "→".bright_black(), help);
+    }
+}
+
+fn handle_compile_error(json: bool, events: Events, e: CompilerError) -> ! {
+    if events.enabled() {
+        events.error(&e);
+        events.finished(exit_code(&e));
+    } else if json {
+        for error in e.errors() {
+            match error.to_json() {
+                Some(json_str) => println!("{}", json_str),
+                None => eprintln!(r#"{{"error": "{}"}}"#, error),
+            }
         }
-        eprintln!(r#"{{"error": "{}"}}"#, e);
     } else {
-        eprintln!("{} {}", "Error:".red().bold(), e);
+        for error in e.errors() {
+            eprintln!("{} {}", "Error:".red().bold(), error);
+        }
+        if e.errors().len() > 1 {
+            eprintln!(
+                "{}",
+                format!("{} errors found", e.errors().len()).red().bold()
+            );
+        }
     }
-    std::process::exit(1);
+    std::process::exit(exit_code(&e));
 }
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
+    // clap exits with 2 on bad arguments, which is taken by parse errors
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        std::process::exit(if e.use_stderr() { EXIT_FAILURE } else { 0 });
+    });
 
     match cli.command {
         Commands::Update => {
@@ -171,10 +736,17 @@ async fn main() {
             let code = delegate_to_liva_tools(&["lsp".to_string()]);
             std::process::exit(code);
         }
+        Commands::Dap => {
+            if let Err(e) = livac::dap::run_stdio() {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
         Commands::Fmt {
             input,
             check,
             verbose,
+            organize_imports,
             json: _json,
         } => {
             let mut args = vec!["fmt".to_string(), input.display().to_string()];
@@ -184,6 +756,9 @@ async fn main() {
             if verbose {
                 args.push("--verbose".to_string());
             }
+            if organize_imports {
+                args.push("--organize-imports".to_string());
+            }
             let code = delegate_to_liva_tools(&args);
             std::process::exit(code);
         }
@@ -191,76 +766,324 @@ async fn main() {
             input,
             filter,
             verbose,
+            deterministic,
+            seed,
+            coverage,
+            coverage_dir,
         } => {
-            let exit_code = run_tests(input.as_ref(), filter.as_deref(), verbose);
+            let seed = deterministic.then_some(seed);
+            let coverage_dir = coverage.then_some(coverage_dir.as_path());
+            let exit_code = run_tests(
+                input.as_ref(),
+                filter.as_deref(),
+                verbose,
+                seed,
+                coverage_dir,
+            );
             std::process::exit(exit_code);
         }
-        Commands::Check { input, json } => {
+        Commands::Check {
+            input,
+            workspace: true,
+            json,
+            ..
+        } => {
+            let root = input.unwrap_or_else(|| PathBuf::from("."));
+            std::process::exit(check_workspace(&root, json));
+        }
+        Commands::Check {
+            input,
+            json,
+            message_format,
+            strict,
+            sandbox,
+            allow,
+            allow_crate,
+            max_errors,
+            ..
+        } => {
+            let input = input.expect("clap requires an input without --workspace");
             let args = CompileArgs {
                 output: None,
                 run: false,
                 verbose: false,
                 check: true,
                 json,
-                release: false,
+                profile: BuildProfile::Dev,
+                debug: false,
+                inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+                strict,
+                numeric_coercion: None,
+                identifier_mapping: None,
                 program_args: vec![],
+                quiet: false,
+                events: Events::new(message_format),
+                per_target: false,
+                sandbox: sandbox_policy(sandbox, allow, allow_crate),
+                self_check: false,
+                incremental: false,
+                explain_codegen: false,
+                format_output: false,
+                report: None,
+                max_errors,
             };
             if let Err(e) = compile(&args, &input) {
-                handle_compile_error(args.json, e);
+                handle_compile_error(args.json, args.events, e);
             }
+            args.events.finished(0);
         }
         Commands::Build {
             input,
             output,
+            per_target,
             verbose,
+            quiet,
             release,
+            profile,
             json,
+            message_format,
+            debug,
+            inline_threshold,
+            strict,
+            numeric_coercion,
+            identifier_mapping,
+            sandbox,
+            allow,
+            allow_crate,
+            self_check,
+            emit,
+            trace_desugar,
+            explain_codegen,
+            format_output,
+            report,
+            max_errors,
         } => {
+            let input = project_input(input);
+            if trace_desugar {
+                if let Err(e) = print_desugar_trace(&input) {
+                    handle_compile_error(json, Events::default(), e);
+                }
+            }
+            if let Some(kind) = emit {
+                if let Err(e) = emit_program(kind, &input) {
+                    handle_compile_error(json, Events::default(), e);
+                }
+                return;
+            }
             let args = CompileArgs {
-                output,
+                output: output.or_else(|| livac::package::Manifest::output_dir_for(&input)),
                 run: false,
                 verbose,
                 check: false,
                 json,
-                release,
+                profile: select_profile(release, profile, &input),
+                debug,
+                inline_threshold,
+                strict,
+                numeric_coercion,
+                identifier_mapping,
                 program_args: vec![],
+                quiet,
+                events: Events::new(message_format),
+                per_target,
+                sandbox: sandbox_policy(sandbox, allow, allow_crate),
+                self_check,
+                incremental: false,
+                explain_codegen,
+                format_output,
+                report,
+                max_errors,
             };
             if let Err(e) = compile(&args, &input) {
-                handle_compile_error(args.json, e);
+                handle_compile_error(args.json, args.events, e);
             }
+            args.events.finished(0);
         }
         Commands::Run {
             input,
             output,
+            per_target,
             verbose,
+            quiet,
             release,
+            profile,
             json,
+            message_format,
+            strict,
+            numeric_coercion,
+            identifier_mapping,
+            sandbox,
+            allow,
+            allow_crate,
+            self_check,
+            format_output,
             program_args,
         } => {
+            let input = project_input(input);
             let args = CompileArgs {
-                output,
+                output: output.or_else(|| livac::package::Manifest::output_dir_for(&input)),
                 run: true,
                 verbose,
                 check: false,
                 json,
-                release,
+                profile: select_profile(release, profile, &input),
+                debug: false,
+                inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+                strict,
+                numeric_coercion,
+                identifier_mapping,
                 program_args,
+                quiet,
+                events: Events::new(message_format),
+                per_target,
+                sandbox: sandbox_policy(sandbox, allow, allow_crate),
+                self_check,
+                incremental: false,
+                explain_codegen: false,
+                format_output,
+                report: None,
+                max_errors: livac::error::DEFAULT_MAX_ERRORS,
             };
             if let Err(e) = compile(&args, &input) {
-                handle_compile_error(args.json, e);
+                handle_compile_error(args.json, args.events, e);
+            }
+            args.events.finished(0);
+        }
+        Commands::Dev {
+            command:
+                Some(DevCommand::RunExamples {
+                    dir,
+                    no_run,
+                    bless,
+                    timeout,
+                    filter,
+                }),
+            ..
+        } => {
+            let options = cli::examples::ExamplesOptions {
+                dir,
+                run: !no_run,
+                bless,
+                timeout: std::time::Duration::from_secs(timeout),
+                filter,
+            };
+            match cli::examples::run(&options) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("{} {}", "Error:".red().bold(), e);
+                    std::process::exit(1);
+                }
             }
         }
-        Commands::Init { name } => {
-            if let Err(e) = run_init(&name) {
+        Commands::Dev {
+            command: Some(DevCommand::AstDiff { old, new, json }),
+            ..
+        } => match cli::ast_diff::run(&old, &new, json) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(2);
+            }
+        },
+        Commands::Dev {
+            command:
+                Some(DevCommand::Reduce {
+                    input,
+                    output,
+                    exact,
+                }),
+            ..
+        } => {
+            if let Err(e) = cli::reduce::run(&input, output.as_deref(), exact) {
                 eprintln!("{} {}", "Error:".red().bold(), e);
                 std::process::exit(1);
             }
         }
-        Commands::Lint { input, json } => {
+        Commands::Dev {
+            command: None,
+            input: Some(input),
+            release,
+            profile,
+            drain_timeout,
+            program_args,
+        } => {
+            let profile = select_profile(release, profile, &input);
+            let options = cli::dev::DevOptions {
+                input,
+                profile,
+                drain_timeout: std::time::Duration::from_secs(drain_timeout),
+                program_args,
+            };
+            if let Err(e) = cli::dev::run(options) {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Dev { input: None, .. } => {
+            unreachable!("clap requires an input file unless a subcommand is given")
+        }
+        Commands::New { name, template } => {
+            if let Err(e) = scaffold::new_project(&name, template) {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Add {
+            name,
+            path,
+            git,
+            rev,
+            tag,
+            branch,
+        } => {
+            let source = cli::deps::AddSource {
+                path,
+                git,
+                rev,
+                tag,
+                branch,
+            };
+            if let Err(e) = cli::deps::add(&name, source) {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Install => {
+            if let Err(e) = cli::deps::install() {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Init { name, template } => {
+            if let Err(e) = scaffold::init_project(&name, template) {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Builtins { json } => print_builtins(json),
+        Commands::Lint {
+            input,
+            json,
+            naming,
+        } => {
             let mut args = vec!["lint".to_string(), input.display().to_string()];
             if json {
                 args.push("--json".to_string());
             }
+            if let Some(naming) = naming {
+                args.push("--naming".to_string());
+                args.push(naming.as_str().to_string());
+            }
+            let code = delegate_to_liva_tools(&args);
+            std::process::exit(code);
+        }
+        Commands::Migrate { input, dry_run } => {
+            let mut args = vec!["migrate".to_string(), input.display().to_string()];
+            if dry_run {
+                args.push("--dry-run".to_string());
+            }
             let code = delegate_to_liva_tools(&args);
             std::process::exit(code);
         }
@@ -308,429 +1131,6 @@ fn delegate_to_liva_tools(args: &[String]) -> i32 {
     }
 }
 
-/// Initialize a new Liva project with scaffolding
-fn run_init(name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    // Resolve "." to current directory
-    let (project_dir, display_name) = if name == "." {
-        let cwd = std::env::current_dir()?;
-        let dir_name = cwd
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("my-project")
-            .to_string();
-        (cwd, dir_name)
-    } else {
-        // Validate project name
-        if name.is_empty() {
-            return Err("Project name cannot be empty".into());
-        }
-        if name.contains(std::path::MAIN_SEPARATOR) || name.contains('/') || name.contains('\\') {
-            return Err("Project name cannot contain path separators".into());
-        }
-        if !name
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-        {
-            return Err(
-                "Project name can only contain letters, numbers, hyphens, and underscores".into(),
-            );
-        }
-        let dir = PathBuf::from(name);
-        if dir.exists() {
-            return Err(format!("Directory '{}' already exists", name).into());
-        }
-        (dir, name.to_string())
-    };
-
-    // Check if target files already exist (for "." mode)
-    if name == "." {
-        if project_dir.join("main.liva").exists() {
-            return Err("main.liva already exists in current directory".into());
-        }
-    }
-
-    // Create directory structure
-    std::fs::create_dir_all(project_dir.join("tests"))?;
-
-    println!(
-        "{} Creating project '{}'...",
-        "→".blue(),
-        display_name.bold()
-    );
-
-    // Write source files
-    std::fs::write(project_dir.join("main.liva"), template_main(&display_name))?;
-    std::fs::write(project_dir.join("math.liva"), template_math())?;
-    std::fs::write(project_dir.join("models.liva"), template_models())?;
-    std::fs::write(
-        project_dir.join("tests").join("main.test.liva"),
-        template_test(),
-    )?;
-    std::fs::write(project_dir.join(".gitignore"), GITIGNORE_TEMPLATE)?;
-
-    println!("{} Created project structure:", "✓".green().bold());
-    if name == "." {
-        println!("    ./ ({})", display_name);
-    } else {
-        println!("    {}/", display_name);
-    }
-    println!("    ├── main.liva");
-    println!("    ├── math.liva");
-    println!("    ├── models.liva");
-    println!("    ├── tests/");
-    println!("    │   └── main.test.liva");
-    println!("    └── .gitignore");
-    println!();
-    println!("  Get started:");
-    if name == "." {
-        println!("    {} main.liva", "livac run".cyan());
-        println!("    {} tests/main.test.liva", "livac test".cyan());
-    } else {
-        println!("    {} {}/main.liva", "livac run".cyan(), display_name);
-        println!(
-            "    {} {}/tests/main.test.liva",
-            "livac test".cyan(),
-            display_name
-        );
-    }
-
-    Ok(())
-}
-
-// ── Templates ──────────────────────────────────────────────
-
-const GITIGNORE_TEMPLATE: &str = "\
-# Liva build output
-target/
-
-# Environment files with secrets
-.env
-*.env.local
-
-# OS files
-.DS_Store
-Thumbs.db
-";
-
-fn template_main(name: &str) -> String {
-    format!(
-        r#"// {name} - Liva Language Tour
-// A multi-file showcase of the language
-// Run:  livac run main.liva
-// Test: livac test tests/main.test.liva
-
-import {{ add, square, isEven, factorial, divide, describeScore, greet }} from "./math.liva"
-import {{ Point, Pet }} from "./models.liva"
-
-const VERSION = "0.1.0"
-
-// -- Enums --
-
-enum Color {{ Red, Green, Blue }}
-
-enum Shape {{
-    Circle(radius: float),
-    Rect(width: float, height: float),
-    Dot
-}}
-
-// -- Enum helpers (switch expressions) --
-
-shapeInfo(s: Shape): string {{
-    let info = switch s {{
-        Shape.Circle(r)  => $"Circle r={{r}}",
-        Shape.Rect(w, h) => $"Rect {{w}}x{{h}}",
-        Shape.Dot        => "Dot"
-    }}
-    return info
-}}
-
-colorName(c: Color): string {{
-    let result = switch c {{
-        Color.Red   => "Red",
-        Color.Green => "Green",
-        Color.Blue  => "Blue"
-    }}
-    return result
-}}
-
-// -- Main --
-
-main() {{
-    print($"Welcome to {name}! (v{{VERSION}})")
-    print("")
-
-    // -- Variables & Types --
-    print("-- Variables & Types --")
-    let count = 0
-    let pi: float = 3.14159
-    let words: [string] = ["Liva", "compiles", "to", "Rust"]
-
-    print($"pi = {{pi}}")
-    let motto = words.join(" ")
-    print($"Motto: {{motto}}")
-    print("")
-
-    // -- Functions (imported from math.liva) --
-    print("-- Functions --")
-    print($"add(2, 3)    = {{add(2, 3)}}")
-    print($"square(7)    = {{square(7)}}")
-    print($"factorial(6) = {{factorial(6)}}")
-    print(greet("World"))
-    print(greet("Liva"))
-    print("")
-
-    // -- Error Handling --
-    print("-- Error Handling --")
-
-    // Error binding: two-variable pattern
-    let result, e1 = divide(10.0, 3.0)
-    if e1 {{
-        print($"Error: {{e1}}")
-    }} else {{
-        print($"10 / 3 = {{result}}")
-    }}
-
-    // Catching a failure
-    let bad, e2 = divide(5.0, 0.0)
-    if e2 {{
-        print($"Caught: {{e2}}")
-    }}
-    print("")
-
-    // -- Control Flow --
-    print("-- Control Flow --")
-    for i in 1..=5 {{
-        count = count + 1
-    }}
-    print($"Counted to {{count}}")
-
-    for word in words {{
-        print($"  -> {{word}}")
-    }}
-    print("")
-
-    // -- Arrays (functional pipeline) --
-    print("-- Arrays --")
-    let numbers = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
-    let evens = numbers.filter(n => isEven(n))
-    let doubled = evens.map(n => n * 2)
-    let total = doubled.reduce(0, (acc, n) => acc + n)
-
-    print($"Numbers: {{numbers}}")
-    print($"Evens:   {{evens}}")
-    print($"Doubled: {{doubled}}")
-    print($"Sum:     {{total}}")
-
-    let hasLarge = numbers.some(n => n > 8)
-    let allPos = numbers.every(n => n > 0)
-    print($"Has > 8: {{hasLarge}}")
-    print($"All > 0: {{allPos}}")
-    print("")
-
-    // -- Maps --
-    print("-- Maps --")
-    let scores = Map {{ "Alice": 95, "Bob": 82, "Carol": 91 }}
-    let aliceScore = scores.get("Alice") or 0
-    print($"Alice: {{aliceScore}}")
-    scores.set("Dave", 78)
-
-    let hasDave = scores.has("Dave")
-    print($"Has Dave: {{hasDave}}")
-
-    for student, score in scores {{
-        print($"  {{student}}: {{describeScore(score)}}")
-    }}
-    print("")
-
-    // -- Sets --
-    print("-- Sets --")
-    let tags = Set {{ "rust", "fast", "safe" }}
-    tags.add("compiled")
-    let hasRust = tags.has("rust")
-    let hasGo = tags.has("go")
-    print($"has rust: {{hasRust}}")
-    print($"has go:   {{hasGo}}")
-    print("")
-
-    // -- Data Classes (imported from models.liva) --
-    print("-- Data Classes --")
-    let p1 = Point(10, 20)
-    let p2 = Point(10, 20)
-    let p3 = Point(99, 1)
-    print($"p1:        {{p1}}")
-    print($"p1 == p2:  {{p1 == p2}}")
-    print($"p1 == p3:  {{p1 == p3}}")
-    print("")
-
-    // -- Enums & Pattern Matching --
-    print("-- Enums & Pattern Matching --")
-    print($"Color: {{colorName(Color.Red)}}")
-    print($"  {{shapeInfo(Shape.Circle(5.0))}}")
-    print($"  {{shapeInfo(Shape.Rect(4.0, 6.0))}}")
-    print($"  {{shapeInfo(Shape.Dot)}}")
-    print("")
-
-    // -- Classes & Interfaces (imported from models.liva) --
-    print("-- Classes & Interfaces --")
-    let pet = Pet("Luna", "cat")
-    print(pet.describe())
-    print("")
-
-    // -- Grade Report (switch with ranges) --
-    print("-- Grade Report --")
-    let testScores = [95, 82, 73, 45]
-    for score in testScores {{
-        print($"  Score {{score}}: {{describeScore(score)}}")
-    }}
-    print("")
-
-    // -- Math stdlib --
-    print("-- Math --")
-    print($"sqrt(16) = {{Math.sqrt(16.0)}}")
-    print($"pow(2,3) = {{Math.pow(2.0, 3.0)}}")
-    print($"PI       = {{Math.PI}}")
-    print($"abs(-5)  = {{Math.abs(-5.0)}}")
-    print("")
-
-    print("Tour complete!")
-}}
-"#,
-        name = name
-    )
-}
-
-fn template_math() -> String {
-    r#"// math.liva - Pure functions
-
-// -- Arrow functions (implicit return) --
-
-add(a: number, b: number): number => a + b
-square(n: number): number => n * n
-isEven(n: number): bool => n % 2 == 0
-greet(name: string): string => $"Hello, {name}!"
-
-// -- Block function (multi-line, recursion) --
-
-factorial(n: number): number {
-    if n <= 1 { return 1 }
-    return n * factorial(n - 1)
-}
-
-// -- Fallible function (fail keyword) --
-
-divide(a: float, b: float): float {
-    if b == 0.0 { fail "Division by zero" }
-    return a / b
-}
-
-// -- Pattern matching: switch with ranges --
-
-describeScore(score: number): string {
-    let label = switch score {
-        90..=100 => "A - Excellent",
-        80..=89  => "B - Good",
-        70..=79  => "C - Average",
-        _        => "F - Needs work"
-    }
-    return label
-}
-"#
-    .to_string()
-}
-
-fn template_models() -> String {
-    r#"// models.liva - Data classes, classes, interfaces
-
-// -- Data Class (auto constructor, Display, PartialEq) --
-
-Point { x: number; y: number }
-
-// -- Interface --
-
-Describable { describe(): string }
-
-// -- Class implementing interface --
-
-Pet : Describable {
-    name: string
-    kind: string
-    constructor(name: string, kind: string) {
-        this.name = name
-        this.kind = kind
-    }
-    describe(): string => $"{this.name} the {this.kind}"
-}
-"#
-    .to_string()
-}
-
-fn template_test() -> String {
-    r#"// Tests for the project
-// Run: livac test tests/main.test.liva
-
-import { describe, test, expect } from "liva/test"
-
-// -- Functions under test --
-
-add(a: number, b: number): number => a + b
-square(n: number): number => n * n
-isEven(n: number): bool => n % 2 == 0
-
-factorial(n: number): number {
-    if n <= 1 { return 1 }
-    return n * factorial(n - 1)
-}
-
-describeScore(score: number): string {
-    let label = switch score {
-        90..=100 => "A - Excellent",
-        80..=89  => "B - Good",
-        70..=79  => "C - Average",
-        _        => "F - Needs work"
-    }
-    return label
-}
-
-// -- Tests --
-
-describe("Math", () => {
-    test("add works", () => {
-        expect(add(2, 3)).toBe(5)
-        expect(add(-1, 1)).toBe(0)
-        expect(add(0, 0)).toBe(0)
-    })
-
-    test("square computes squares", () => {
-        expect(square(7)).toBe(49)
-        expect(square(0)).toBe(0)
-    })
-
-    test("isEven detects parity", () => {
-        expect(isEven(4)).toBeTruthy()
-        expect(isEven(3)).toBeFalsy()
-        expect(isEven(0)).toBeTruthy()
-    })
-
-    test("factorial recurses correctly", () => {
-        expect(factorial(0)).toBe(1)
-        expect(factorial(1)).toBe(1)
-        expect(factorial(6)).toBe(720)
-    })
-})
-
-describe("Score Grading", () => {
-    test("describeScore grades correctly", () => {
-        expect(describeScore(95)).toBe("A - Excellent")
-        expect(describeScore(82)).toBe("B - Good")
-        expect(describeScore(73)).toBe("C - Average")
-        expect(describeScore(50)).toBe("F - Needs work")
-    })
-})
-"#
-    .to_string()
-}
-
 /// Self-update: download the latest release from GitHub and replace the current binary
 async fn self_update() -> Result<(), Box<dyn std::error::Error>> {
     let current_version = env!("CARGO_PKG_VERSION");
@@ -986,7 +1386,53 @@ fn dirs_or_home() -> PathBuf {
         .unwrap_or_else(|_| PathBuf::from("."))
 }
 
-fn run_tests(input: Option<&PathBuf>, filter: Option<&str>, verbose: bool) -> i32 {
+/// Run `*.test.liva` files. `seed` is set in deterministic mode.
+/// Write `lcov.info` and `index.html` for `livac test --coverage` and print the total
+fn write_coverage_reports(mut coverage: livac::coverage::CoverageReport, dir: &std::path::Path) {
+    // Report on the code under test; keep test files only if nothing else was instrumented
+    let mut without_tests = coverage.clone();
+    without_tests.retain_files(|file| !file.ends_with(".test.liva"));
+    if !without_tests.is_empty() {
+        coverage = without_tests;
+    }
+
+    let (hit, total) = coverage.totals();
+    let percent = if total == 0 {
+        100.0
+    } else {
+        hit as f64 * 100.0 / total as f64
+    };
+    println!(
+        "{}",
+        format!("Cover:  {}/{} lines ({:.1}%)", hit, total, percent).bold()
+    );
+
+    let html = coverage.to_html(|file| std::fs::read_to_string(file).ok());
+    let written = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(dir.join("lcov.info"), coverage.to_lcov()))
+        .and_then(|_| std::fs::write(dir.join("index.html"), html));
+    match written {
+        Ok(()) => println!(
+            "        {} {}",
+            "→".blue(),
+            dir.join("index.html").display()
+        ),
+        Err(e) => eprintln!(
+            "{} Failed to write coverage reports to {}: {}",
+            "Error:".red().bold(),
+            dir.display(),
+            e
+        ),
+    }
+}
+
+fn run_tests(
+    input: Option<&PathBuf>,
+    filter: Option<&str>,
+    verbose: bool,
+    seed: Option<u64>,
+    coverage_dir: Option<&std::path::Path>,
+) -> i32 {
     use std::time::Instant;
     use walkdir::WalkDir;
 
@@ -1058,8 +1504,16 @@ fn run_tests(input: Option<&PathBuf>, filter: Option<&str>, verbose: bool) -> i3
         test_files.len(),
         if test_files.len() == 1 { "" } else { "s" }
     );
+    if let Some(seed) = seed {
+        println!("  {} Deterministic mode (seed {})", "→".blue(), seed);
+    }
+    if coverage_dir.is_some() {
+        println!("  {} Recording statement coverage", "→".blue());
+    }
     println!();
 
+    let mut coverage = livac::coverage::CoverageReport::new();
+
     let mut total_passed = 0;
     let mut total_failed = 0;
     let mut total_files_passed = 0;
@@ -1078,6 +1532,22 @@ fn run_tests(input: Option<&PathBuf>, filter: Option<&str>, verbose: bool) -> i3
             output: None,
             verbose: false,
             check_only: false,
+            deterministic: seed.is_some(),
+            coverage: coverage_dir.is_some(),
+            debug: false,
+            inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
+            profile: BuildProfile::Dev,
+            backend: livac::backend::BackendKind::Rust,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            source_map: false,
+            max_errors: 1,
         };
 
         let result = match livac::compile_file(&options) {
@@ -1194,9 +1664,39 @@ fn run_tests(input: Option<&PathBuf>, filter: Option<&str>, verbose: bool) -> i3
             cmd.env("LIVAC_PROJECT_ROOT", &cwd);
         }
 
-        // Add filter if specified
+        // Coverage: register every probe, then collect the counts after the run
+        let coverage_file = build_dir.join("liva_coverage.txt");
+        if coverage_dir.is_some() {
+            coverage.add_sites(&main_rs);
+            for content in result.module_files.iter().flat_map(|m| m.values()) {
+                coverage.add_sites(content);
+            }
+            let _ = std::fs::remove_file(&coverage_file);
+            let coverage_file = coverage_file.canonicalize().unwrap_or_else(|_| {
+                build_dir
+                    .canonicalize()
+                    .unwrap_or_else(|_| build_dir.clone())
+                    .join("liva_coverage.txt")
+            });
+            cmd.env("LIVA_COVERAGE_FILE", coverage_file);
+        }
+
+        // Deterministic mode: one test thread, one rayon worker, fixed seed
+        if let Some(seed) = seed {
+            cmd.env("LIVA_SEED", seed.to_string())
+                .env("LIVA_THREADS", "1")
+                .env("RAYON_NUM_THREADS", "1");
+        }
+
+        // Harness args go after `--`
+        if filter.is_some() || seed.is_some() {
+            cmd.arg("--");
+        }
         if let Some(filter) = filter {
-            cmd.arg("--").arg(filter);
+            cmd.arg(filter);
+        }
+        if seed.is_some() {
+            cmd.arg("--test-threads=1");
         }
 
         let output = match cmd.output() {
@@ -1215,6 +1715,12 @@ fn run_tests(input: Option<&PathBuf>, filter: Option<&str>, verbose: bool) -> i3
             }
         };
 
+        if coverage_dir.is_some() {
+            if let Ok(hits) = std::fs::read_to_string(&coverage_file) {
+                coverage.add_hits(&hits);
+            }
+        }
+
         let stdout = String::from_utf8_lossy(&output.stdout);
         let stderr = String::from_utf8_lossy(&output.stderr);
 
@@ -1321,6 +1827,10 @@ fn run_tests(input: Option<&PathBuf>, filter: Option<&str>, verbose: bool) -> i3
     }
     println!("{}", time_summary);
 
+    if let Some(dir) = coverage_dir {
+        write_coverage_reports(coverage, dir);
+    }
+
     if !failed_files.is_empty() {
         println!();
         println!("{}", "Failed files:".red().bold());
@@ -1352,13 +1862,19 @@ fn run_tests(input: Option<&PathBuf>, filter: Option<&str>, verbose: bool) -> i3
 
 /// Parse the output of `cargo test` to extract pass/fail counts
 fn parse_cargo_test_output(output: &str) -> (usize, usize) {
-    // Look for line like: "test result: ok. 3 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out"
-    for line in output.lines() {
-        if line.contains("test result:") {
-            let passed = extract_number(line, "passed");
-            let failed = extract_number(line, "failed");
-            return (passed, failed);
-        }
+    // Look for lines like: "test result: ok. 3 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out"
+    // There is one per test target (a `lib.liva` module also yields a lib target), so sum them
+    let results: Vec<&str> = output
+        .lines()
+        .filter(|line| line.contains("test result:"))
+        .collect();
+    if !results.is_empty() {
+        return results.iter().fold((0, 0), |(passed, failed), line| {
+            (
+                passed + extract_number(line, "passed"),
+                failed + extract_number(line, "failed"),
+            )
+        });
     }
     // Fallback: count individual test lines
     let passed = output.lines().filter(|l| l.contains("... ok")).count();
@@ -1380,6 +1896,64 @@ fn extract_number(s: &str, word: &str) -> usize {
     }
 }
 
+/// `livac check --workspace`: reports the diagnostics of every file under
+/// `root` and returns the exit code
+fn check_workspace(root: &std::path::Path, json: bool) -> i32 {
+    let reports = livac::check_workspace(root);
+    let mut failed_files = 0;
+    let mut error_count = 0;
+
+    for report in &reports {
+        if report.is_ok() {
+            continue;
+        }
+        failed_files += 1;
+        for err in &report.diagnostics {
+            error_count += 1;
+            if json {
+                // One object per line, so editors can stream them
+                let error = err
+                    .to_json()
+                    .and_then(|json_str| serde_json::from_str(&json_str).ok())
+                    .unwrap_or_else(|| serde_json::Value::String(err.to_string()));
+                println!(
+                    "{}",
+                    serde_json::json!({ "file": report.file_name, "error": error })
+                );
+            } else {
+                eprintln!("{} {}", "Error:".red().bold(), err);
+            }
+        }
+    }
+
+    if !json {
+        if failed_files == 0 {
+            println!(
+                "{} {} file{} checked",
+                "✓".green().bold(),
+                reports.len(),
+                if reports.len() == 1 { "" } else { "s" }
+            );
+        } else {
+            eprintln!(
+                "{} {} error{} in {} of {} files",
+                "✗".red().bold(),
+                error_count,
+                if error_count == 1 { "" } else { "s" },
+                failed_files,
+                reports.len()
+            );
+        }
+    }
+
+    // The first error decides, as it would for `livac check <file>`
+    reports
+        .iter()
+        .flat_map(|report| &report.diagnostics)
+        .next()
+        .map_or(0, exit_code)
+}
+
 /// Check if cargo is available in PATH
 fn check_cargo_available() -> Result<(), CompilerError> {
     match Command::new("cargo").arg("--version").output() {
@@ -1402,6 +1976,148 @@ fn check_cargo_available() -> Result<(), CompilerError> {
     }
 }
 
+/// Read and parse `input`
+fn parse_file(input: &PathBuf) -> Result<(String, livac::ast::Program), CompilerError> {
+    let source =
+        std::fs::read_to_string(input).map_err(|e| CompilerError::IoError(e.to_string()))?;
+    let program = livac::parser::parse(livac::lexer::tokenize(&source)?, &source)?;
+    Ok((source, program))
+}
+
+/// Print the desugaring rewrites of `input` as `-`/`+` pairs
+fn print_desugar_trace(input: &PathBuf) -> Result<(), CompilerError> {
+    let (source, program) = parse_file(input)?;
+    let lines = livac::span::SourceMap::new(&source);
+    for step in livac::desugaring::trace(&program, &source) {
+        let (line, column) = step.span.start_position(&lines);
+        println!(
+            "{} {} {}:{}:{}",
+            "desugar".cyan().bold(),
+            step.rule,
+            input.display(),
+            line,
+            column
+        );
+        println!("  {} {}", "-".red(), step.before);
+        println!("  {} {}", "+".green(), step.after);
+    }
+    Ok(())
+}
+
+/// Parse and analyze `input`, then print it in the `kind` form
+/// `livac builtins`: one builtin per line, or the whole table as JSON
+fn print_builtins(json: bool) {
+    if json {
+        let table = livac::builtins::to_json();
+        println!("{}", serde_json::to_string_pretty(&table).unwrap());
+        return;
+    }
+    for builtin in livac::builtins::BUILTINS {
+        let mut tags = Vec::new();
+        if builtin.fallible {
+            tags.push("fallible".to_string());
+        }
+        if builtin.is_async {
+            tags.push("async".to_string());
+        }
+        if !builtin.crates.is_empty() {
+            tags.push(format!("crates: {}", builtin.crates.join(", ")));
+        }
+        let label = builtin.signature().label();
+        if tags.is_empty() {
+            println!("{}", label);
+        } else {
+            println!("{}  {}", label, format!("[{}]", tags.join("; ")).dimmed());
+        }
+    }
+}
+
+fn emit_program(kind: Emit, input: &PathBuf) -> Result<(), CompilerError> {
+    let (source, program) = parse_file(input)?;
+    let program =
+        livac::semantic::analyze_with_source(program, input.display().to_string(), source.clone())?;
+    match kind {
+        Emit::AstPretty => print!("{}", livac::pretty::pretty_program(&program, Some(&source))),
+    }
+    Ok(())
+}
+
+/// Write `content` unless the file already holds it, keeping its mtime so
+/// cargo doesn't rebuild what a recompile left unchanged
+fn write_if_changed(path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    if std::fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+        return Ok(());
+    }
+    std::fs::write(path, content)
+}
+
+/// `cargo build` of the generated crate in `output_dir`. rustc locations in
+/// a failed build's output are pointed back at the Liva source through
+/// `line_map` when there is one.
+fn cargo_build(
+    args: &CompileArgs,
+    output_dir: &std::path::Path,
+    line_map: Option<&livac::debuginfo::LineMap>,
+) -> Result<(), CompilerError> {
+    let mut cargo_cmd = Command::new("cargo");
+    cargo_cmd.arg("build");
+    if args.events.enabled() {
+        cargo_cmd.arg("--color=never").arg("--message-format=json");
+    } else {
+        cargo_cmd.arg("--color=always");
+    }
+    if args.profile == BuildProfile::Release {
+        cargo_cmd.arg("--release");
+    }
+    let output = cargo_cmd
+        .current_dir(output_dir)
+        .output()
+        .map_err(|e| CompilerError::IoError(e.to_string()))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    args.events.cargo_output(&stdout, &stderr);
+
+    if !output.status.success() {
+        if !args.events.enabled() {
+            // Show the actual Rust compiler error
+            eprintln!("\n{}", "Rust Compilation Error:".red().bold());
+            eprintln!("{}", "=".repeat(80));
+
+            let translate = |output: &str| match line_map {
+                Some(map) => map.translate_diagnostics(output),
+                None => output.to_string(),
+            };
+
+            // Print stdout (cargo messages)
+            if !stdout.is_empty() {
+                eprint!("{}", translate(&stdout));
+            }
+
+            // Print stderr (error messages)
+            if !stderr.is_empty() {
+                eprint!("{}", translate(&stderr));
+            }
+
+            eprintln!("{}", "=".repeat(80));
+            eprintln!(
+                "\n{}",
+                "💡 Tip: This is a Rust type error in the generated code.".yellow()
+            );
+            eprintln!("   Check the Liva code for type mismatches or incompatible operations.\n");
+        }
+
+        return Err(CompilerError::CodegenError(Box::new(
+            livac::SemanticErrorInfo::new(
+                E3002_BUILD_FAILED,
+                "Build failed",
+                "cargo build rejected the generated Rust code",
+            ),
+        )));
+    }
+    Ok(())
+}
+
 fn compile(args: &CompileArgs, input: &PathBuf) -> Result<(), CompilerError> {
     let skip_cargo = std::env::var("LIVAC_SKIP_CARGO").is_ok();
 
@@ -1411,44 +2127,100 @@ fn compile(args: &CompileArgs, input: &PathBuf) -> Result<(), CompilerError> {
     }
 
     if !args.json {
-        println!(
+        args.progress(format_args!(
             "{}",
             format!("🧩 Liva Compiler v{}", env!("CARGO_PKG_VERSION"))
                 .cyan()
                 .bold()
-        );
-        println!("{} {}", "→ Compiling".green(), input.display());
+        ));
+        args.progress(format_args!(
+            "{} {}",
+            "→ Compiling".green(),
+            input.display()
+        ));
     }
 
+    // Debug builds record absolute .liva paths so debuggers can match breakpoints
+    let input = if args.debug {
+        input.canonicalize().unwrap_or_else(|_| input.clone())
+    } else {
+        input.clone()
+    };
+
+    let crate_name = livac::artifacts::crate_name(&input);
     let options = CompilerOptions {
         input: input.clone(),
-        output: args.output.clone(),
+        output: args
+            .output
+            .as_ref()
+            .map(|dir| args.target_dir(dir, &crate_name)),
         verbose: false,
         check_only: args.check,
+        deterministic: false,
+        coverage: false,
+        debug: args.debug,
+        inline_threshold: args.inline_threshold,
+        strict: args.strict,
+        numeric_coercion: args.numeric_coercion,
+        identifier_mapping: args.identifier_mapping,
+        profile: args.profile,
+        backend: livac::backend::BackendKind::Rust,
+        sandbox: args.sandbox.clone(),
+        self_check: args.self_check,
+        incremental: args.incremental,
+        explain_codegen: args.explain_codegen,
+        format_output: args.format_output,
+        source_map: true,
+        max_errors: args.max_errors,
     };
 
-    let result = livac::compile_file(&options)?;
+    let result = args
+        .events
+        .phase("compile", || livac::compile_file(&options))?;
+
+    for diagnostic in &result.diagnostics {
+        if args.events.enabled() {
+            args.events.report(diagnostic);
+        } else if args.json {
+            if let Ok(json) = diagnostic.to_json() {
+                println!("{}", json);
+            }
+        } else {
+            print_diagnostic(diagnostic);
+        }
+    }
+
+    if let Some(report) = &args.report {
+        let json = serde_json::to_string_pretty(&result.features)
+            .map_err(|e| CompilerError::IoError(e.to_string()))?;
+        std::fs::write(report, json + "\n").map_err(|e| CompilerError::IoError(e.to_string()))?;
+        args.progress(format_args!(
+            "{} {}",
+            "✓ Feature report written to".green(),
+            report.display()
+        ));
+    }
 
     if args.check {
         if !args.json {
-            println!("{}", "✓ Check passed".green().bold());
+            args.progress(format_args!("{}", "✓ Check passed".green().bold()));
         }
         return Ok(());
     }
 
     let main_rs = result.rust_code.ok_or_else(|| {
-        CompilerError::CodegenError(livac::SemanticErrorInfo::new(
+        CompilerError::CodegenError(Box::new(livac::SemanticErrorInfo::new(
             "E3001",
             "Code generation failed",
             "No Rust code generated",
-        ))
+        )))
     })?;
     let cargo_toml = result.cargo_toml.ok_or_else(|| {
-        CompilerError::CodegenError(livac::SemanticErrorInfo::new(
-            "E3002",
+        CompilerError::CodegenError(Box::new(livac::SemanticErrorInfo::new(
+            E3000_CODEGEN_ERROR,
             "Code generation failed",
             "No Cargo.toml generated",
-        ))
+        )))
     })?;
 
     // Determine output directory
@@ -1463,13 +2235,14 @@ fn compile(args: &CompileArgs, input: &PathBuf) -> Result<(), CompilerError> {
         // Default: ./target/liva_build
         PathBuf::from("./target/liva_build")
     };
+    let output_dir = args.target_dir(&output_dir, &result.metadata.crate_name);
 
     std::fs::create_dir_all(&output_dir).map_err(|e| CompilerError::IoError(e.to_string()))?;
 
     let src_dir = output_dir.join("src");
     std::fs::create_dir_all(&src_dir).map_err(|e| CompilerError::IoError(e.to_string()))?;
 
-    std::fs::write(src_dir.join("main.rs"), &main_rs)
+    write_if_changed(&src_dir.join("main.rs"), &main_rs)
         .map_err(|e| CompilerError::IoError(e.to_string()))?;
 
     // Write module files if present
@@ -1481,95 +2254,100 @@ fn compile(args: &CompileArgs, input: &PathBuf) -> Result<(), CompilerError> {
                 std::fs::create_dir_all(parent)
                     .map_err(|e| CompilerError::IoError(e.to_string()))?;
             }
-            std::fs::write(&file_path, content)
+            write_if_changed(&file_path, content)
                 .map_err(|e| CompilerError::IoError(e.to_string()))?;
         }
     }
 
-    std::fs::write(output_dir.join("Cargo.toml"), &cargo_toml)
+    write_if_changed(&output_dir.join("Cargo.toml"), &cargo_toml)
         .map_err(|e| CompilerError::IoError(e.to_string()))?;
 
+    if args.debug {
+        let map = livac::debuginfo::LineMap::from_build(
+            &output_dir,
+            &main_rs,
+            result.module_files.as_ref(),
+        );
+        let json = map
+            .to_json()
+            .map_err(|e| CompilerError::IoError(e.to_string()))?;
+        let line_map = output_dir.join(livac::debuginfo::LINE_MAP_FILE);
+        std::fs::write(&line_map, json).map_err(|e| CompilerError::IoError(e.to_string()))?;
+        args.events.artifact("line-map", &line_map);
+    }
+    if let Some(map) = &result.metadata.line_map {
+        let json = map
+            .to_json()
+            .map_err(|e| CompilerError::IoError(e.to_string()))?;
+        let source_map = output_dir.join(livac::debuginfo::SOURCE_MAP_FILE);
+        write_if_changed(&source_map, &json).map_err(|e| CompilerError::IoError(e.to_string()))?;
+        args.events.artifact("source-map", &source_map);
+    }
+    args.events.artifact("rust-crate", &output_dir);
+
     if args.verbose {
-        println!("\n{}", "Generated Rust code:".yellow().bold());
-        println!("{}", "=".repeat(60));
-        println!("{}", main_rs);
-        println!("{}", "=".repeat(60));
+        eprintln!("\n{}", "Generated Rust code:".yellow().bold());
+        eprintln!("{}", "=".repeat(60));
+        eprintln!("{}", main_rs);
+        eprintln!("{}", "=".repeat(60));
     }
 
-    println!("{} {}", "✓ Generated at".green(), output_dir.display());
+    args.progress(format_args!(
+        "{} {}",
+        "✓ Generated at".green(),
+        output_dir.display()
+    ));
+
+    let binary_path = output_dir
+        .join("target")
+        .join(args.profile.target_dir())
+        .join(&result.metadata.crate_name);
 
     // 8. Run cargo build
     if skip_cargo {
-        println!(
+        args.progress(format_args!(
             "  {} Skipping cargo build (LIVAC_SKIP_CARGO set)...",
             "→".blue()
-        );
+        ));
     } else {
-        println!(
+        args.progress(format_args!(
             "  {} Running cargo build{}...",
             "→".blue(),
-            if args.release { " --release" } else { "" }
-        );
-        let mut cargo_cmd = Command::new("cargo");
-        cargo_cmd.arg("build").arg("--color=always");
-        if args.release {
-            cargo_cmd.arg("--release");
-        }
-        let output = cargo_cmd
-            .current_dir(&output_dir)
-            .output()
-            .map_err(|e| CompilerError::IoError(e.to_string()))?;
-
-        if !output.status.success() {
-            // Show the actual Rust compiler error
-            eprintln!("\n{}", "Rust Compilation Error:".red().bold());
-            eprintln!("{}", "=".repeat(80));
-
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-
-            // Print stdout (cargo messages)
-            if !stdout.is_empty() {
-                eprint!("{}", stdout);
-            }
-
-            // Print stderr (error messages)
-            if !stderr.is_empty() {
-                eprint!("{}", stderr);
+            if args.profile == BuildProfile::Release {
+                " --release"
+            } else {
+                ""
             }
-
-            eprintln!("{}", "=".repeat(80));
-            eprintln!(
-                "\n{}",
-                "💡 Tip: This is a Rust type error in the generated code.".yellow()
-            );
-            eprintln!("   Check the Liva code for type mismatches or incompatible operations.\n");
-
-            return Err(CompilerError::CodegenError("Cargo build failed".into()));
-        }
+        ));
+        args.events.phase("cargo-build", || {
+            cargo_build(args, &output_dir, result.metadata.line_map.as_ref())
+        })?;
+        args.events.artifact("binary", &binary_path);
     }
 
-    println!("{}", "✓ Compilation successful!".green().bold());
+    args.progress(format_args!(
+        "{}",
+        "✓ Compilation successful!".green().bold()
+    ));
 
     // 9. Run if requested
     if args.run {
         if skip_cargo {
-            println!(
+            args.progress(format_args!(
                 "\n{}",
                 "Skipping program run (LIVAC_SKIP_CARGO set)"
                     .yellow()
                     .bold()
-            );
+            ));
         } else {
-            println!("\n{}", "Running program:".cyan().bold());
-            println!("{}", "=".repeat(60));
+            args.progress(format_args!("\n{}", "Running program:".cyan().bold()));
+            args.progress(format_args!("{}", "=".repeat(60)));
 
             // Run the compiled binary from the user's working directory
             // (not from the build dir, so relative paths in the program work correctly)
-            let profile = if args.release { "release" } else { "debug" };
-            let binary_path = output_dir.join("target").join(profile).join("liva_project");
-
+            // Inherit stdin so `cat data.txt | livac run prog.liva` feeds the program
             let mut cmd = Command::new(&binary_path);
+            cmd.stdin(std::process::Stdio::inherit());
 
             // Pass LIVA_VERBOSE env var when --verbose is set (enables Log.debug output)
             if args.verbose {
@@ -1581,14 +2359,23 @@ fn compile(args: &CompileArgs, input: &PathBuf) -> Result<(), CompilerError> {
                 cmd.arg(arg);
             }
 
-            let status = cmd
-                .status()
-                .map_err(|e| CompilerError::IoError(e.to_string()))?;
-
-            if !status.success() {
-                return Err(CompilerError::RuntimeError(
-                    "Program execution failed".into(),
-                ));
+            let status = args.events.phase("run", || {
+                cmd.status()
+                    .map_err(|e| CompilerError::IoError(e.to_string()))
+            })?;
+            match status.code() {
+                Some(0) => {}
+                // The program reported its own failure (`main(): int`, `fail` in
+                // main, a panic), so exit with its status rather than ours
+                Some(code) => {
+                    args.events.finished(code);
+                    std::process::exit(code);
+                }
+                None => {
+                    return Err(CompilerError::RuntimeError(
+                        "Program was terminated by a signal".into(),
+                    ));
+                }
             }
         }
     }
@@ -1642,8 +2429,23 @@ mod tests {
             verbose: false,
             check: true,
             json: false,
-            release: false,
+            profile: BuildProfile::Dev,
+            debug: false,
+            inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
             program_args: vec![],
+            quiet: false,
+            events: Events::default(),
+            per_target: false,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            report: None,
+            max_errors: livac::error::DEFAULT_MAX_ERRORS,
         };
 
         let _guard = EnvVarGuard::set("LIVAC_SKIP_CARGO", "1");
@@ -1670,8 +2472,23 @@ mod tests {
             verbose: true,
             check: false,
             json: false,
-            release: false,
+            profile: BuildProfile::Dev,
+            debug: false,
+            inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
             program_args: vec![],
+            quiet: false,
+            events: Events::default(),
+            per_target: false,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            report: None,
+            max_errors: livac::error::DEFAULT_MAX_ERRORS,
         };
 
         let _guard = EnvVarGuard::set("LIVAC_SKIP_CARGO", "1");
@@ -1692,8 +2509,23 @@ mod tests {
             verbose: false,
             check: false,
             json: false,
-            release: false,
+            profile: BuildProfile::Dev,
+            debug: false,
+            inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
             program_args: vec![],
+            quiet: false,
+            events: Events::default(),
+            per_target: false,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            report: None,
+            max_errors: livac::error::DEFAULT_MAX_ERRORS,
         };
 
         let err = compile(&args, &input).expect_err("expected IO error");
@@ -1708,4 +2540,150 @@ mod tests {
             other => panic!("unexpected error: {other:?}"),
         }
     }
+
+    #[test]
+    fn test_exit_code_per_failure_kind() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("broken.liva");
+        fs::write(&input, "main() {\n    let = 1\n}\n").unwrap();
+        let args = CompileArgs {
+            output: None,
+            run: false,
+            verbose: false,
+            check: true,
+            json: false,
+            profile: BuildProfile::Dev,
+            debug: false,
+            inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+            strict: false,
+            numeric_coercion: None,
+            identifier_mapping: None,
+            program_args: vec![],
+            quiet: false,
+            events: Events::default(),
+            per_target: false,
+            sandbox: None,
+            self_check: false,
+            incremental: false,
+            explain_codegen: false,
+            format_output: false,
+            report: None,
+            max_errors: livac::error::DEFAULT_MAX_ERRORS,
+        };
+        let err = compile(&args, &input).expect_err("expected a parse error");
+        assert_eq!(exit_code(&err), EXIT_PARSE_ERROR);
+
+        let build_failed = CompilerError::CodegenError(Box::new(livac::SemanticErrorInfo::new(
+            E3002_BUILD_FAILED,
+            "Build failed",
+            "cargo build rejected the generated Rust code",
+        )));
+        assert_eq!(exit_code(&build_failed), EXIT_BUILD_ERROR);
+        let no_code = CompilerError::CodegenError(Box::new(livac::SemanticErrorInfo::new(
+            E3000_CODEGEN_ERROR,
+            "Code generation failed",
+            "No Cargo.toml generated",
+        )));
+        assert_eq!(exit_code(&no_code), EXIT_CODEGEN_ERROR);
+        let crashed = CompilerError::RuntimeError("Program was terminated by a signal".into());
+        assert_eq!(exit_code(&crashed), EXIT_RUNTIME_ERROR);
+        let missing = CompilerError::IoError("No such file".into());
+        assert_eq!(exit_code(&missing), EXIT_FAILURE);
+        let several = CompilerError::from_errors(vec![crashed, missing]);
+        assert_eq!(exit_code(&several), EXIT_RUNTIME_ERROR);
+    }
+
+    #[test]
+    fn test_run_flags_and_program_args() {
+        let cli = Cli::try_parse_from([
+            "livac",
+            "run",
+            "prog.liva",
+            "--quiet",
+            "--",
+            "--port",
+            "8080",
+        ])
+        .unwrap();
+        let Commands::Run {
+            quiet,
+            program_args,
+            ..
+        } = cli.command
+        else {
+            panic!("expected the run command");
+        };
+        assert!(quiet);
+        assert_eq!(program_args, ["--port", "8080"]);
+
+        // --quiet and --verbose contradict each other
+        assert!(Cli::try_parse_from(["livac", "run", "prog.liva", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_message_format_flag() {
+        let cli = Cli::try_parse_from(["livac", "build", "prog.liva", "--message-format", "json"])
+            .unwrap();
+        let Commands::Build { message_format, .. } = cli.command else {
+            panic!("expected the build command");
+        };
+        assert_eq!(message_format, MessageFormat::Json);
+
+        // `check --workspace` reports through its own --json
+        let workspace = ["livac", "check", "--workspace", "--message-format", "json"];
+        assert!(Cli::try_parse_from(workspace).is_err());
+    }
+
+    #[test]
+    fn test_per_target_output_layout() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("out");
+        let _guard = EnvVarGuard::set("LIVAC_SKIP_CARGO", "1");
+        for name in ["hello.liva", "word-count.liva"] {
+            let input = dir.path().join(name);
+            fs::write(&input, "main() {\n    print(1)\n}\n").unwrap();
+            let args = CompileArgs {
+                output: Some(out.clone()),
+                run: false,
+                verbose: false,
+                check: false,
+                json: false,
+                profile: BuildProfile::Dev,
+                debug: false,
+                inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+                strict: false,
+                numeric_coercion: None,
+                identifier_mapping: None,
+                program_args: vec![],
+                quiet: true,
+                events: Events::default(),
+                per_target: true,
+                sandbox: None,
+                self_check: false,
+                incremental: false,
+                explain_codegen: false,
+                format_output: false,
+                report: None,
+                max_errors: livac::error::DEFAULT_MAX_ERRORS,
+            };
+            compile(&args, &input).unwrap();
+        }
+
+        for crate_name in ["hello", "word_count"] {
+            let manifest = fs::read_to_string(out.join(crate_name).join("Cargo.toml")).unwrap();
+            assert!(manifest.contains(&format!("name = \"{}\"", crate_name)));
+        }
+        assert!(!out.join("Cargo.toml").exists());
+    }
+
+    #[test]
+    fn test_parse_cargo_test_output_sums_all_targets() {
+        let output = "\
+running 0 tests
+test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+running 3 tests
+test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
+        assert_eq!(parse_cargo_test_output(output), (2, 1));
+    }
 }
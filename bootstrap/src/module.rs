@@ -39,6 +39,60 @@ pub fn virtual_module_path(source: &str) -> PathBuf {
     PathBuf::from(format!("<virtual:{}>", source))
 }
 
+/// Sentinel PathBuf under which a package import (`"mathx"`) is registered
+/// for semantic analysis
+pub fn package_module_path(source: &str) -> PathBuf {
+    PathBuf::from(format!("<package:{}>", source))
+}
+
+/// Standard library modules written in Liva (`lib/std/`) and embedded in the
+/// compiler: name after `std/`, source
+const STD_MODULES: &[(&str, &str)] = &[
+    (
+        "collections",
+        include_str!("../../lib/std/collections.liva"),
+    ),
+    ("validators", include_str!("../../lib/std/validators.liva")),
+];
+
+/// Name of the standard library module an import source names
+/// (`"std/collections"` → `collections`), whether or not it exists
+fn std_module_name(source: &str) -> Option<&str> {
+    let name = source.strip_prefix("std/")?;
+    Some(name.strip_suffix(".liva").unwrap_or(name))
+}
+
+/// Check if an import source refers to an embedded standard library module
+pub fn is_std_module(source: &str) -> bool {
+    std_module_name(source).is_some_and(|name| STD_MODULES.iter().any(|(n, _)| *n == name))
+}
+
+/// Names of the embedded standard library modules, as imported
+pub fn std_module_sources() -> impl Iterator<Item = String> {
+    STD_MODULES.iter().map(|(name, _)| format!("std/{}", name))
+}
+
+/// Sentinel PathBuf an embedded standard library module is loaded under
+pub fn std_module_path(source: &str) -> PathBuf {
+    PathBuf::from(format!(
+        "<std:{}>",
+        std_module_name(source).unwrap_or(source)
+    ))
+}
+
+/// Rust module an embedded standard library module is generated as, from its
+/// import source: `"std/collections"` → `std_collections`
+pub fn std_rust_module_name(source: &str) -> Option<String> {
+    std_module_name(source).map(|name| format!("std_{}", name.replace('-', "_")))
+}
+
+/// Name and source of the embedded module loaded under `path`, a
+/// [`std_module_path`]
+fn std_module_at(path: &Path) -> Option<(&'static str, &'static str)> {
+    let name = path.to_str()?.strip_prefix("<std:")?.strip_suffix('>')?;
+    STD_MODULES.iter().find(|(n, _)| *n == name).copied()
+}
+
 /// Represents a single Liva module (file)
 #[derive(Debug, Clone)]
 pub struct Module {
@@ -59,6 +113,10 @@ pub struct Module {
 
     /// Source code (for error reporting)
     pub source: String,
+
+    /// Package this file is the entry point of, for modules pulled in from
+    /// `liva.toml` dependencies
+    pub package: Option<String>,
 }
 
 impl Module {
@@ -66,16 +124,21 @@ impl Module {
     pub fn from_file(path: &Path) -> Result<Self> {
         // Read file
         let source = fs::read_to_string(path).map_err(|e| {
-            CompilerError::CodegenError(SemanticErrorInfo::new(
+            CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                 "E4004",
                 &format!("Cannot read module file: {}", path.display()),
                 &format!(
                     "{}\nHint: Check that the file exists and you have read permissions.",
                     e.to_string()
                 ),
-            ))
+            )))
         })?;
+        Self::from_source(path, source)
+    }
 
+    /// Create a module from source that is not read from `path`, such as an
+    /// embedded standard library module
+    pub fn from_source(path: &Path, source: String) -> Result<Self> {
         // Lex and parse
         let tokens = tokenize(&source)?;
         let ast = parse(tokens, &source)?;
@@ -102,6 +165,16 @@ impl Module {
                     }
                 }
                 TopLevel::Import(import_decl) => {
+                    // Named re-exports are part of this module's public API;
+                    // wildcard ones are filled in by the resolver
+                    if import_decl.is_reexport {
+                        for local in import_decl.local_names() {
+                            public_symbols.insert(local.to_string());
+                        }
+                        if let Some(alias) = &import_decl.alias {
+                            public_symbols.insert(alias.clone());
+                        }
+                    }
                     imports.push(import_decl.clone());
                 }
                 TopLevel::ConstDecl(const_decl) => {
@@ -131,9 +204,27 @@ impl Module {
             private_symbols,
             imports,
             source,
+            package: None,
         })
     }
 
+    /// Name of the generated Rust module: the package name for a package
+    /// entry file, the file stem otherwise
+    pub fn rust_module_name(&self) -> String {
+        if let Some((name, _)) = std_module_at(&self.path) {
+            return format!("std_{}", name.replace('-', "_"));
+        }
+        let name = match &self.package {
+            Some(package) => package.as_str(),
+            None => self
+                .path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("module"),
+        };
+        name.replace('-', "_")
+    }
+
     /// Get all symbol names (public + private)
     pub fn all_symbols(&self) -> HashSet<String> {
         self.public_symbols
@@ -157,19 +248,29 @@ impl DependencyGraph {
         }
     }
 
+    /// Register a module that may have no outgoing edges
+    pub fn add_node(&mut self, path: PathBuf) {
+        self.edges.entry(path).or_default();
+    }
+
     /// Add an edge from `from` module to `to` module
     pub fn add_edge(&mut self, from: PathBuf, to: PathBuf) {
-        self.edges.entry(from).or_insert_with(Vec::new).push(to);
+        self.edges.entry(to.clone()).or_default();
+        self.edges.entry(from).or_default().push(to);
     }
 
-    /// Detect if there's a cycle starting from `start_path`
-    /// Returns Some(cycle_path) if cycle detected, None otherwise
+    /// Detect if there's a cycle reachable from `start_path`
+    /// Returns Some(cycle) if a cycle is detected, None otherwise. The cycle
+    /// starts and ends with the same module (`a → b → c → a`); modules that
+    /// merely lead into it are not included.
     pub fn detect_cycle(&self, start_path: &Path) -> Option<Vec<PathBuf>> {
         let mut visited = HashSet::new();
         let mut path = Vec::new();
 
         if self.has_cycle_dfs(start_path, &mut visited, &mut path) {
-            Some(path)
+            let repeated = path.last()?;
+            let start = path.iter().position(|p| p == repeated)?;
+            Some(path.split_off(start))
         } else {
             None
         }
@@ -245,11 +346,11 @@ impl DependencyGraph {
 
         // If we haven't visited all nodes, there's a cycle
         if result.len() != in_degree.len() {
-            return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+            return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                 "E4003",
                 "Circular dependency detected",
                 "Cannot compile modules with circular dependencies.\nHint: Check your import statements to find the circular reference chain.",
-            )));
+            ))));
         }
 
         Ok(result)
@@ -266,32 +367,42 @@ pub struct ModuleResolver {
     entry_point: PathBuf,
 
     /// Root directory of the project
-    #[allow(dead_code)]
     root_dir: PathBuf,
 
     /// Dependency graph
     dependency_graph: DependencyGraph,
+
+    /// Every import between loaded modules: (importer, imported, declaration).
+    /// The dependency graph is built from these once all modules are loaded,
+    /// since deciding whether an import is type-only needs the target parsed.
+    import_edges: Vec<(PathBuf, PathBuf, ImportDecl)>,
+
+    /// Packages from `liva.toml` that bare imports resolve against
+    packages: crate::package::Resolution,
+
+    /// Bare import sources resolved through `packages`: source -> file
+    package_imports: HashMap<String, PathBuf>,
 }
 
 impl ModuleResolver {
     /// Create a new resolver for a project
     pub fn new(entry_point: &Path) -> Result<Self> {
         let entry_point = entry_point.canonicalize().map_err(|e| {
-            CompilerError::CodegenError(SemanticErrorInfo::new(
+            CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                 "E4004",
                 &format!("Cannot find entry point: {}", entry_point.display()),
                 &format!("{}\nHint: Verify the file path is correct.", e.to_string()),
-            ))
+            )))
         })?;
 
         let root_dir = entry_point
             .parent()
             .ok_or_else(|| {
-                CompilerError::CodegenError(SemanticErrorInfo::new(
+                CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E4004",
                     "Cannot determine project root directory",
                     "Entry point file has no parent directory.",
-                ))
+                )))
             })?
             .to_path_buf();
 
@@ -300,32 +411,47 @@ impl ModuleResolver {
             entry_point,
             root_dir,
             dependency_graph: DependencyGraph::new(),
+            import_edges: Vec::new(),
+            packages: crate::package::Resolution::default(),
+            package_imports: HashMap::new(),
         })
     }
 
+    /// Create a resolver whose bare imports (`"mathx"`) resolve to the given packages
+    pub fn with_packages(entry_point: &Path, packages: crate::package::Resolution) -> Result<Self> {
+        let mut resolver = Self::new(entry_point)?;
+        resolver.packages = packages;
+        Ok(resolver)
+    }
+
+    /// Bare import sources that resolved to a package file: source -> file
+    pub fn package_imports(&self) -> &HashMap<String, PathBuf> {
+        &self.package_imports
+    }
+
     /// Resolve all modules starting from entry point
     pub fn resolve_all(&mut self) -> Result<Vec<&Module>> {
         // Load entry point
         self.load_module_recursive(&self.entry_point.clone())?;
 
+        // Type-only imports may form cycles: they never need the other module
+        // initialised first, and Rust resolves mutually `use`-ing modules fine
+        for (from, to, import) in &self.import_edges {
+            if !self.is_type_only_import(import, to) {
+                self.dependency_graph.add_edge(from.clone(), to.clone());
+            }
+        }
+
         // Check for cycles
         if let Some(cycle) = self.dependency_graph.detect_cycle(&self.entry_point) {
-            let cycle_str = cycle
-                .iter()
-                .map(|p| p.display().to_string())
-                .collect::<Vec<_>>()
-                .join("\n  → ");
-
-            return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
-                "E4003",
-                "Circular dependency detected",
-                &format!("Import chain forms a cycle:\n  {}\n\nHint: Remove one of the imports in this chain to break the circular dependency.", cycle_str),
-            )));
+            return Err(self.cycle_error(&cycle));
         }
 
         // Get compilation order (topological sort)
         let order = self.dependency_graph.topological_sort()?;
 
+        self.resolve_reexports()?;
+
         // Return modules in compilation order
         let modules: Vec<&Module> = order
             .iter()
@@ -335,6 +461,126 @@ impl ModuleResolver {
         Ok(modules)
     }
 
+    /// Whether every symbol an import names is a type declaration (class,
+    /// type, alias or enum) in the target module
+    fn is_type_only_import(&self, import: &ImportDecl, target: &Path) -> bool {
+        if import.is_wildcard || import.imports.is_empty() {
+            return false;
+        }
+        let Some(module) = self.modules.get(target) else {
+            return false;
+        };
+        import.imports.iter().all(|symbol| {
+            module.ast.items.iter().any(|item| match item {
+                TopLevel::Class(class) => &class.name == symbol,
+                TopLevel::Type(type_decl) => &type_decl.name == symbol,
+                TopLevel::TypeAlias(alias) => &alias.name == symbol,
+                TopLevel::Enum(enum_decl) => &enum_decl.name == symbol,
+                _ => false,
+            })
+        })
+    }
+
+    /// E4003 diagnostic for an import cycle, listing the chain of files and
+    /// the import statement behind each step
+    fn cycle_error(&self, cycle: &[PathBuf]) -> CompilerError {
+        let chain = cycle
+            .iter()
+            .map(|p| self.display_path(p))
+            .collect::<Vec<_>>()
+            .join(" → ");
+
+        let mut steps = String::new();
+        for pair in cycle.windows(2) {
+            let import = self
+                .import_edges
+                .iter()
+                .find(|(from, to, _)| from == &pair[0] && to == &pair[1])
+                .map(|(_, _, import)| import.to_string())
+                .unwrap_or_default();
+            steps.push_str(&format!("\n  {}: {}", self.display_path(&pair[0]), import));
+        }
+
+        CompilerError::CodegenError(Box::new(
+            SemanticErrorInfo::new(
+                "E4003",
+                "Circular dependency detected",
+                &format!("Import cycle: {}\n{}", chain, steps),
+            )
+            .with_hint(
+                "Move the shared definitions into a separate module both sides import, or import only types (classes, types, enums) across the cycle — type-only imports may be circular.",
+            ),
+        ))
+    }
+
+    /// Path of a module relative to the project root, for diagnostics
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
+    /// Check `export { ... } from` targets and extend each module's public
+    /// symbols with whatever its `export * from` imports provide. Runs to a
+    /// fixed point so re-export chains (`a` re-exports `b` re-exports `c`)
+    /// resolve regardless of load order.
+    fn resolve_reexports(&mut self) -> Result<()> {
+        let mut reexports = Vec::new();
+        for (path, module) in &self.modules {
+            for import in module.imports.iter().filter(|i| i.is_reexport) {
+                let target = self.resolve_import_path(path, &import.source)?;
+                reexports.push((path.clone(), import.clone(), target));
+            }
+        }
+
+        for (path, import, target) in &reexports {
+            if import.is_wildcard {
+                continue;
+            }
+            let Some(target_module) = self.modules.get(target) else {
+                continue;
+            };
+            for symbol in &import.imports {
+                if !target_module.public_symbols.contains(symbol) {
+                    return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
+                        "E4006",
+                        "Re-exported symbol not found",
+                        &format!(
+                            "'{}' re-exports '{}' from '{}', but that module does not export it.\nHint: Only public symbols (no '_' prefix) can be re-exported.",
+                            path.display(),
+                            symbol,
+                            import.source
+                        ),
+                    ))));
+                }
+            }
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (path, import, target) in &reexports {
+                if !import.is_wildcard || import.alias.is_some() {
+                    continue;
+                }
+                let Some(provided) = self.modules.get(target).map(|m| m.public_symbols.clone())
+                else {
+                    continue;
+                };
+                if let Some(module) = self.modules.get_mut(path) {
+                    for symbol in provided {
+                        if module.public_symbols.insert(symbol) {
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Load a module and all its dependencies recursively
     fn load_module_recursive(&mut self, path: &Path) -> Result<()> {
         // Skip virtual modules (they don't have filesystem paths)
@@ -342,16 +588,25 @@ impl ModuleResolver {
             return Ok(());
         }
 
+        // Embedded standard library modules are parsed from the compiler's copy
+        if let Some((_, source)) = std_module_at(path) {
+            if self.modules.contains_key(path) {
+                return Ok(());
+            }
+            let module = Module::from_source(path, source.to_string())?;
+            return self.add_module(path.to_path_buf(), module);
+        }
+
         // Canonicalize path
         let canonical_path = path.canonicalize().map_err(|e| {
-            CompilerError::CodegenError(SemanticErrorInfo::new(
+            CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                 "E4004",
                 &format!("Cannot find module: {}", path.display()),
                 &format!(
                     "{}\nHint: Check that the import path is correct and the file exists.",
                     e.to_string()
                 ),
-            ))
+            )))
         })?;
 
         // Check if already loaded (avoid re-parsing)
@@ -360,19 +615,32 @@ impl ModuleResolver {
         }
 
         // Load the module
-        let module = Module::from_file(&canonical_path)?;
+        let mut module = Module::from_file(&canonical_path)?;
+        module.package = self
+            .packages
+            .package_for_entry(&canonical_path)
+            .map(str::to_string);
+        self.add_module(canonical_path, module)
+    }
 
+    /// Register a loaded module and load everything it imports
+    fn add_module(&mut self, canonical_path: PathBuf, module: Module) -> Result<()> {
         // Process imports
         let imports = module.imports.clone();
         self.modules.insert(canonical_path.clone(), module);
+        self.dependency_graph.add_node(canonical_path.clone());
 
         // Recursively load imported modules
         for import in imports {
             let imported_path = self.resolve_import_path(&canonical_path, &import.source)?;
+            if self.packages.import_path(&import.source).is_some() {
+                self.package_imports
+                    .insert(import.source.clone(), imported_path.clone());
+            }
 
-            // Add edge to dependency graph
-            self.dependency_graph
-                .add_edge(canonical_path.clone(), imported_path.clone());
+            // Record the edge; the dependency graph is built in resolve_all
+            self.import_edges
+                .push((canonical_path.clone(), imported_path.clone(), import));
 
             // Recursively load
             self.load_module_recursive(&imported_path)?;
@@ -388,16 +656,46 @@ impl ModuleResolver {
             return Ok(virtual_module_path(import_path));
         }
 
+        // Standard library modules embedded in the compiler ("std/collections")
+        if is_std_module(import_path) {
+            return Ok(std_module_path(import_path));
+        }
+        if std_module_name(import_path).is_some() {
+            return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
+                "E4004",
+                &format!("Module not found: '{}'", import_path),
+                &format!(
+                    "There is no standard library module '{}'.\nHint: Available modules: {}. Import a file in a std/ directory with \"./std/...\".",
+                    import_path,
+                    std_module_sources().collect::<Vec<_>>().join(", ")
+                ),
+            ))));
+        }
+
+        // Packages declared in liva.toml ("mathx", "mathx/geometry")
+        if let Some(path) = self.packages.import_path(import_path) {
+            return path.canonicalize().map_err(|_| {
+                CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
+                    "E4004",
+                    &format!("Module not found: '{}'", import_path),
+                    &format!(
+                        "File does not exist: {}\nHint: Check the path inside the package.",
+                        path.display()
+                    ),
+                )))
+            });
+        }
+
         // Get the directory of the current file
         let current_dir = current_file.parent().ok_or_else(|| {
-            CompilerError::CodegenError(SemanticErrorInfo::new(
+            CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                 "E4004",
                 "Cannot resolve import path",
                 &format!(
                     "Current file has no parent directory: {}",
                     current_file.display()
                 ),
-            ))
+            )))
         })?;
 
         // Resolve relative path
@@ -415,27 +713,27 @@ impl ModuleResolver {
             if with_ext.exists() {
                 // Canonicalize to avoid path mismatches (e.g., ../src/token.liva vs src/token.liva)
                 return with_ext.canonicalize().map_err(|e| {
-                    CompilerError::CodegenError(SemanticErrorInfo::new(
+                    CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                         "E4004",
                         &format!("Cannot canonicalize path: {}", with_ext.display()),
                         &e.to_string(),
-                    ))
+                    )))
                 });
             }
-            return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+            return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                 "E4004",
                 &format!("Module not found: '{}'", import_path),
                 &format!("File does not exist: {}\nHint: Check the import path. Relative paths should start with './' or '../'.", resolved.display()),
-            )));
+            ))));
         }
 
         // Canonicalize to avoid path mismatches in dependency graph
         resolved.canonicalize().map_err(|e| {
-            CompilerError::CodegenError(SemanticErrorInfo::new(
+            CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                 "E4004",
                 &format!("Cannot canonicalize path: {}", resolved.display()),
                 &e.to_string(),
-            ))
+            )))
         })
     }
 
@@ -459,6 +757,32 @@ impl ModuleResolver {
         self.dependency_graph.topological_sort()
     }
 
+    /// Copy the fields and methods abstract classes pass on into the classes
+    /// implementing them, across modules. See [`crate::abstract_classes`].
+    pub fn inherit_abstract_members(&mut self) -> Result<()> {
+        let bases = crate::abstract_classes::collect(self.modules.values().map(|m| &m.ast));
+        for module in self.modules.values_mut() {
+            crate::abstract_classes::inherit(&mut module.ast, &bases)?;
+        }
+        Ok(())
+    }
+
+    /// Add the forwarding methods of classes that `delegate` to a field,
+    /// across modules. See [`crate::delegation`].
+    pub fn expand_delegates(&mut self) -> Result<()> {
+        let mut programs: Vec<&mut Program> =
+            self.modules.values_mut().map(|m| &mut m.ast).collect();
+        crate::delegation::expand(&mut programs)
+    }
+
+    /// Replace the `Class.schema()` and `fields(Class)` calls, across
+    /// modules. See [`crate::schema`].
+    pub fn expand_schemas(&mut self) {
+        let mut programs: Vec<&mut Program> =
+            self.modules.values_mut().map(|m| &mut m.ast).collect();
+        crate::schema::expand(&mut programs);
+    }
+
     /// Hoist `extend ClassName { ... }` items into their owner `ClassDecl`.
     ///
     /// Walks every resolved module:
@@ -509,7 +833,7 @@ impl ModuleResolver {
         // Pass 3: apply extensions to their owner class, validating.
         for (target_name, methods, src_path) in extensions {
             let owner_path = owner_by_name.get(&target_name).ok_or_else(|| {
-                CompilerError::CodegenError(SemanticErrorInfo::new(
+                CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                     "E0911",
                     &format!(
                         "Cannot find class `{}` to extend",
@@ -521,7 +845,7 @@ impl ModuleResolver {
                          See docs/language-reference/class-extensions.md.",
                         target_name, target_name
                     ),
-                ))
+                )))
             })?.clone();
 
             // If the extension lives in a different module than the owner,
@@ -546,6 +870,8 @@ impl ModuleResolver {
                             imports: Vec::new(),
                             is_wildcard: true,
                             alias: None,
+                            renames: HashMap::new(),
+                            is_reexport: false,
                         };
                         owner.imports.insert(0, synth.clone());
                         owner.ast.items.insert(0, TopLevel::Import(synth));
@@ -568,31 +894,34 @@ impl ModuleResolver {
                 })
                 .expect("class must exist in owner module by construction");
 
-            // Collect existing method names (base + previously hoisted) for dup check.
-            let mut existing: HashSet<String> = class
+            // Collect existing methods (base + previously hoisted) for dup check.
+            // An extension may overload a method with a different parameter count.
+            let mut existing: HashSet<(String, usize)> = class
                 .members
                 .iter()
                 .filter_map(|m| match m {
-                    Member::Method(md) => Some(md.name.clone()),
+                    Member::Method(md) => Some((md.name.clone(), md.params.len())),
                     _ => None,
                 })
                 .collect();
 
             for m in methods {
-                if !existing.insert(m.name.clone()) {
-                    return Err(CompilerError::CodegenError(SemanticErrorInfo::new(
+                if !existing.insert((m.name.clone(), m.params.len())) {
+                    return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
                         "E0912",
                         &format!(
                             "Duplicate method `{}.{}` introduced by `extend`",
                             target_name, m.name
                         ),
                         &format!(
-                            "Method `{}` is already defined on `{}` (in the owner file or another `extend` block). \
-                             Rename the extension method, or remove the duplicate. \
+                            "Method `{}` with {} parameters is already defined on `{}` (in the owner file or another `extend` block). \
+                             Rename the extension method, change its parameter count, or remove the duplicate. \
                              See docs/language-reference/class-extensions.md.",
-                            m.name, target_name
+                            m.name,
+                            m.params.len(),
+                            target_name
                         ),
-                    )));
+                    ))));
                 }
                 class.members.push(Member::Method(m));
             }
@@ -633,4 +962,49 @@ mod tests {
 
         assert!(graph.detect_cycle(&PathBuf::from("a.liva")).is_some());
     }
+
+    #[test]
+    fn test_dependency_graph_cycle_excludes_lead_in() {
+        let mut graph = DependencyGraph::new();
+        graph.add_edge(PathBuf::from("main.liva"), PathBuf::from("a.liva"));
+        graph.add_edge(PathBuf::from("a.liva"), PathBuf::from("b.liva"));
+        graph.add_edge(PathBuf::from("b.liva"), PathBuf::from("a.liva"));
+
+        let cycle = graph.detect_cycle(&PathBuf::from("main.liva")).unwrap();
+        assert_eq!(
+            cycle,
+            vec![
+                PathBuf::from("a.liva"),
+                PathBuf::from("b.liva"),
+                PathBuf::from("a.liva")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_topological_sort_keeps_isolated_nodes() {
+        let mut graph = DependencyGraph::new();
+        graph.add_node(PathBuf::from("main.liva"));
+        graph.add_node(PathBuf::from("types.liva"));
+
+        assert_eq!(graph.topological_sort().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_std_modules_are_embedded() {
+        assert!(is_std_module("std/collections"));
+        assert!(is_std_module("std/collections.liva"));
+        assert!(!is_std_module("std/nope"));
+        assert!(!is_std_module("./std/collections"));
+        assert_eq!(
+            std_rust_module_name("std/collections").as_deref(),
+            Some("std_collections")
+        );
+
+        let path = std_module_path("std/collections");
+        let (_, source) = std_module_at(&path).unwrap();
+        let module = Module::from_source(&path, source.to_string()).unwrap();
+        assert!(module.public_symbols.contains("sumOf"));
+        assert_eq!(module.rust_module_name(), "std_collections");
+    }
 }
@@ -0,0 +1,520 @@
+//! Method overloading by arity.
+//!
+//! A class can declare several methods with one name, as long as no two of
+//! them accept the same number of arguments. Each overload becomes a Rust
+//! method of its own, named after its parameter count (`scale` taking two
+//! parameters is `scale_2`), and this pass renames every call to the
+//! overload that accepts its number of arguments.
+//!
+//! Picking the overload needs the receiver's class, which is worked out by
+//! name the way codegen tracks class instances: `this`, parameters and
+//! bindings annotated with a class, bindings initialized with a constructor
+//! call or a function with a declared return type, loop variables over an
+//! array of a class, and class-typed fields. A call on a receiver that can't
+//! be placed resolves when every class declaring the method agrees on the
+//! overload, and is reported as ambiguous otherwise.
+
+use crate::ast::*;
+use crate::error::{CompilerError, SemanticErrorInfo};
+use crate::error_codes::*;
+use crate::Result;
+use std::collections::HashMap;
+
+/// Rust name of the overload of `method` that takes `params` parameters
+pub fn overload_name(method: &str, params: usize) -> String {
+    format!("{}_{}", method, params)
+}
+
+/// Rename the overloaded methods declared in `program`, and every call in it
+/// to an overloaded method of a class from `program` or `imported`
+pub fn resolve(program: &mut Program, imported: &[&Program]) -> Result<()> {
+    let mut classes = Classes::default();
+    for module in imported.iter().copied().chain([&*program]) {
+        classes.collect(module)?;
+    }
+
+    let mut renamed = false;
+    let mut resolver = Resolver::new(&classes);
+    for item in &mut program.items {
+        match item {
+            TopLevel::Class(ClassDecl { name, members, .. })
+            | TopLevel::Type(TypeDecl { name, members, .. }) => {
+                for member in members {
+                    match member {
+                        Member::Field(field) => {
+                            if let Some(init) = &mut field.init {
+                                resolver.visit_expr(init);
+                            }
+                        }
+                        Member::Method(method) => {
+                            if classes.is_overloaded(name, &method.name) {
+                                method.name = overload_name(&method.name, method.params.len());
+                                renamed = true;
+                            }
+                            resolver.method(name, method);
+                        }
+                    }
+                }
+            }
+            TopLevel::ClassExtension(extension) => {
+                for method in &mut extension.methods {
+                    resolver.method(&extension.name, method);
+                }
+            }
+            _ => resolver.visit_item(item),
+        }
+        if let Some(error) = resolver.error.take() {
+            return Err(error);
+        }
+    }
+    // Keep the analyzed symbol table in step with the renamed overloads
+    if renamed && !program.symbols.is_empty() {
+        program.symbols = crate::symbols::SymbolTable::collect(program);
+    }
+    Ok(())
+}
+
+/// Arguments a method accepts: all of its parameters, or fewer when the
+/// last ones have defaults
+#[derive(Debug, Clone, Copy)]
+struct Arity {
+    required: usize,
+    params: usize,
+}
+
+impl Arity {
+    fn of(params: &[Param]) -> Self {
+        Arity {
+            required: params.iter().filter(|p| p.default.is_none()).count(),
+            params: params.len(),
+        }
+    }
+
+    fn accepts(self, args: usize) -> bool {
+        (self.required..=self.params).contains(&args)
+    }
+
+    /// Smallest argument count both accept, if any
+    fn overlap(self, other: Arity) -> Option<usize> {
+        let args = self.required.max(other.required);
+        (args <= self.params.min(other.params)).then_some(args)
+    }
+}
+
+/// What a call names in Rust
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// The method isn't overloaded; the call keeps its name
+    Plain,
+    Overload(String),
+    /// No overload accepts the arguments; the parameter counts there are
+    NoMatch(Vec<usize>),
+}
+
+/// What the pass knows about the classes it can see
+#[derive(Default)]
+struct Classes {
+    /// Overloads of each method, by class and method name
+    methods: HashMap<String, HashMap<String, Vec<Arity>>>,
+    /// Declared field types, by class and field name
+    fields: HashMap<String, HashMap<String, TypeRef>>,
+    /// Declared return types of top-level functions, `None` when undeclared
+    functions: HashMap<String, Option<TypeRef>>,
+}
+
+impl Classes {
+    fn collect(&mut self, program: &Program) -> Result<()> {
+        for item in &program.items {
+            match item {
+                TopLevel::Class(class) => {
+                    let mut methods: HashMap<String, Vec<Arity>> = HashMap::new();
+                    let mut fields = HashMap::new();
+                    for member in &class.members {
+                        match member {
+                            Member::Field(field) => {
+                                if let Some(type_ref) = &field.type_ref {
+                                    fields.insert(field.name.clone(), type_ref.clone());
+                                }
+                            }
+                            // Constructors aren't overloaded
+                            Member::Method(method) if method.name != "constructor" => {
+                                let arity = Arity::of(&method.params);
+                                let overloads = methods.entry(method.name.clone()).or_default();
+                                if let Some(args) =
+                                    overloads.iter().find_map(|other| other.overlap(arity))
+                                {
+                                    return Err(conflicting_overloads(
+                                        &class.name,
+                                        &method.name,
+                                        args,
+                                    ));
+                                }
+                                overloads.push(arity);
+                            }
+                            Member::Method(_) => {}
+                        }
+                    }
+                    self.methods.insert(class.name.clone(), methods);
+                    self.fields.insert(class.name.clone(), fields);
+                }
+                TopLevel::Function(func) => {
+                    self.functions
+                        .insert(func.name.clone(), func.return_type.clone());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn is_overloaded(&self, class: &str, method: &str) -> bool {
+        self.methods
+            .get(class)
+            .and_then(|methods| methods.get(method))
+            .is_some_and(|overloads| overloads.len() > 1)
+    }
+
+    /// What `method` called on `class` with `args` arguments names, or
+    /// `None` when the class doesn't declare the method
+    fn target(&self, class: &str, method: &str, args: usize) -> Option<Target> {
+        let overloads = self.methods.get(class)?.get(method)?;
+        if overloads.len() == 1 {
+            return Some(Target::Plain);
+        }
+        Some(match overloads.iter().find(|arity| arity.accepts(args)) {
+            Some(arity) => Target::Overload(overload_name(method, arity.params)),
+            None => Target::NoMatch(overloads.iter().map(|arity| arity.params).collect()),
+        })
+    }
+}
+
+/// The receiver of a method call, as far as the pass can tell
+enum Receiver {
+    Class(String),
+    /// A value of a type that isn't a class the pass knows, such as an array
+    /// or a string; its methods are never overloads
+    Other,
+    Unknown,
+}
+
+struct Resolver<'a> {
+    classes: &'a Classes,
+    /// Declared or inferred types of the visible bindings, innermost scope
+    /// last; `None` for a binding whose type isn't known
+    scopes: Vec<HashMap<String, Option<TypeRef>>>,
+    /// The first call the pass couldn't resolve
+    error: Option<CompilerError>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(classes: &'a Classes) -> Self {
+        Resolver {
+            classes,
+            scopes: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn method(&mut self, class: &str, method: &mut MethodDecl) {
+        self.scopes.push(HashMap::new());
+        self.declare("this".to_string(), Some(TypeRef::Simple(class.to_string())));
+        self.visit_method(method);
+        self.scopes.pop();
+    }
+
+    fn declare_params(&mut self, params: &[Param]) {
+        for param in params {
+            match param.name() {
+                Some(name) => self.declare(name.to_string(), param.type_ref.clone()),
+                None => self.declare_pattern(&param.pattern),
+            }
+        }
+    }
+
+    fn declare(&mut self, name: String, type_ref: Option<TypeRef>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, type_ref);
+        }
+    }
+
+    /// Shadow the names a destructuring pattern binds; their types aren't
+    /// tracked
+    fn declare_pattern(&mut self, pattern: &BindingPattern) {
+        for name in pattern.names() {
+            self.declare(name, None);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<TypeRef> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .flatten()
+    }
+
+    fn type_of(&self, expr: &Expr) -> Option<TypeRef> {
+        match expr {
+            Expr::Identifier(name) => self.lookup(name),
+            Expr::Call(call) => match call.callee.as_ref() {
+                Expr::Identifier(name) if self.classes.methods.contains_key(name) => {
+                    Some(TypeRef::Simple(name.clone()))
+                }
+                Expr::Identifier(name) => self.classes.functions.get(name).cloned().flatten(),
+                _ => None,
+            },
+            Expr::StructLiteral { type_name, .. } => Some(TypeRef::Simple(type_name.clone())),
+            Expr::Member { object, property } => {
+                let Receiver::Class(class) = self.receiver(object) else {
+                    return None;
+                };
+                self.classes.fields.get(&class)?.get(property).cloned()
+            }
+            Expr::Index { object, .. } => match strip_optional(self.type_of(object)?) {
+                TypeRef::Array(element) => Some(*element),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn receiver(&self, expr: &Expr) -> Receiver {
+        match expr {
+            Expr::Literal(_)
+            | Expr::StringTemplate { .. }
+            | Expr::ArrayLiteral(_)
+            | Expr::MapLiteral(_)
+            | Expr::SetLiteral(_)
+            | Expr::ObjectLiteral(_)
+            | Expr::Tuple(_) => Receiver::Other,
+            // `Set()`, `parseInt(s)` and other builtins
+            Expr::Call(call)
+                if matches!(
+                    call.callee.as_ref(),
+                    Expr::Identifier(name) if !self.classes.methods.contains_key(name)
+                        && !self.classes.functions.contains_key(name)
+                        && !self.scopes.iter().any(|scope| scope.contains_key(name))
+                ) =>
+            {
+                Receiver::Other
+            }
+            _ => match self.type_of(expr).map(strip_optional) {
+                Some(TypeRef::Simple(name)) if self.classes.methods.contains_key(&name) => {
+                    Receiver::Class(name)
+                }
+                Some(_) => Receiver::Other,
+                None => Receiver::Unknown,
+            },
+        }
+    }
+
+    /// Rust name of the overload `call` picks, when its method is overloaded
+    fn resolve_call(&self, call: &MethodCallExpr) -> Result<Option<String>> {
+        let args = call.args.len();
+        let (class, target) = match self.receiver(&call.object) {
+            Receiver::Class(class) => match self.classes.target(&class, &call.method, args) {
+                Some(target) => (class, target),
+                None => return Ok(None),
+            },
+            Receiver::Other => return Ok(None),
+            Receiver::Unknown => {
+                let mut declaring: Vec<(&String, Target)> = self
+                    .classes
+                    .methods
+                    .keys()
+                    .filter_map(|class| {
+                        Some((class, self.classes.target(class, &call.method, args)?))
+                    })
+                    .collect();
+                declaring.sort_by(|a, b| a.0.cmp(b.0));
+                match declaring.as_slice() {
+                    [] => return Ok(None),
+                    [(class, target), rest @ ..]
+                        if rest.iter().all(|(_, other)| other == target) =>
+                    {
+                        (class.to_string(), target.clone())
+                    }
+                    _ => {
+                        let classes: Vec<&str> =
+                            declaring.iter().map(|(class, _)| class.as_str()).collect();
+                        return Err(ambiguous_call(&call.method, &classes));
+                    }
+                }
+            }
+        };
+        match target {
+            Target::Plain => Ok(None),
+            Target::Overload(name) => Ok(Some(name)),
+            Target::NoMatch(params) => {
+                Err(no_matching_overload(&class, &call.method, args, &params))
+            }
+        }
+    }
+}
+
+impl VisitorMut for Resolver<'_> {
+    fn visit_function(&mut self, func: &mut FunctionDecl) {
+        // A nested function is a binding of the enclosing body
+        self.declare(func.name.clone(), None);
+        self.scopes.push(HashMap::new());
+        self.declare_params(&func.params);
+        walk_function_mut(self, func);
+        self.scopes.pop();
+    }
+
+    fn visit_method(&mut self, method: &mut MethodDecl) {
+        self.scopes.push(HashMap::new());
+        self.declare_params(&method.params);
+        walk_method_mut(self, method);
+        self.scopes.pop();
+    }
+
+    fn visit_stmts(&mut self, stmts: &mut [Stmt]) {
+        self.scopes.push(HashMap::new());
+        walk_stmts_mut(self, stmts);
+        self.scopes.pop();
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::VarDecl(_) => {
+                walk_stmt_mut(self, stmt);
+                let Stmt::VarDecl(decl) = stmt else {
+                    return;
+                };
+                let inferred = match decl.bindings.as_slice() {
+                    [_] => self.type_of(&decl.init),
+                    _ => None,
+                };
+                for binding in &decl.bindings {
+                    match binding.name() {
+                        Some(name) => self.declare(
+                            name.to_string(),
+                            binding.type_ref.clone().or_else(|| inferred.clone()),
+                        ),
+                        None => self.declare_pattern(&binding.pattern),
+                    }
+                }
+            }
+            Stmt::ConstDecl(decl) => {
+                self.visit_expr(&mut decl.init);
+                let type_ref = self.type_of(&decl.init);
+                self.declare(decl.name.clone(), type_ref);
+            }
+            Stmt::For(for_stmt) => {
+                self.visit_expr(&mut for_stmt.iterable);
+                let element = match self.type_of(&for_stmt.iterable).map(strip_optional) {
+                    Some(TypeRef::Array(element)) => Some(*element),
+                    _ => None,
+                };
+                self.scopes.push(HashMap::new());
+                match &for_stmt.var2 {
+                    Some(var2) => {
+                        self.declare(for_stmt.var.clone(), None);
+                        self.declare(var2.clone(), element);
+                    }
+                    None => self.declare(for_stmt.var.clone(), element),
+                }
+                self.visit_block(&mut for_stmt.body);
+                self.scopes.pop();
+            }
+            Stmt::TryCatch(try_catch) => {
+                self.visit_block(&mut try_catch.try_block);
+                self.scopes.push(HashMap::new());
+                self.declare(try_catch.catch_var.clone(), None);
+                self.visit_block(&mut try_catch.catch_block);
+                self.scopes.pop();
+            }
+            _ => walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        if self.error.is_some() {
+            return;
+        }
+        match expr {
+            Expr::MethodCall(_) => {
+                walk_expr_mut(self, expr);
+                let Expr::MethodCall(call) = expr else {
+                    return;
+                };
+                match self.resolve_call(call) {
+                    Ok(Some(name)) => call.method = name,
+                    Ok(None) => {}
+                    Err(error) => {
+                        self.error.get_or_insert(error);
+                    }
+                }
+            }
+            Expr::Lambda(lambda) => {
+                self.scopes.push(HashMap::new());
+                for param in &lambda.params {
+                    match param.name() {
+                        Some(name) => self.declare(name.to_string(), param.type_ref.clone()),
+                        None => self.declare_pattern(&param.pattern),
+                    }
+                }
+                walk_expr_mut(self, expr);
+                self.scopes.pop();
+            }
+            _ => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+fn strip_optional(type_ref: TypeRef) -> TypeRef {
+    match type_ref {
+        TypeRef::Optional(inner) => *inner,
+        other => other,
+    }
+}
+
+fn conflicting_overloads(class: &str, method: &str, args: usize) -> CompilerError {
+    CompilerError::SemanticError(Box::new(
+        SemanticErrorInfo::new(
+            E0012_CONFLICTING_OVERLOADS,
+            "Conflicting overloads",
+            &format!(
+                "Class '{}' declares more than one '{}' that accepts {} argument{}",
+                class,
+                method,
+                args,
+                if args == 1 { "" } else { "s" }
+            ),
+        )
+        .with_help("Give each overload a different number of parameters, or rename one of them"),
+    ))
+}
+
+fn no_matching_overload(class: &str, method: &str, args: usize, params: &[usize]) -> CompilerError {
+    let counts: Vec<String> = params.iter().map(usize::to_string).collect();
+    CompilerError::SemanticError(Box::new(SemanticErrorInfo::new(
+        E0013_NO_MATCHING_OVERLOAD,
+        "No matching overload",
+        &format!(
+            "No overload of '{}.{}' takes {} argument{}; its overloads take {}",
+            class,
+            method,
+            args,
+            if args == 1 { "" } else { "s" },
+            counts.join(", ")
+        ),
+    )))
+}
+
+fn ambiguous_call(method: &str, classes: &[&str]) -> CompilerError {
+    CompilerError::SemanticError(Box::new(
+        SemanticErrorInfo::new(
+            E0014_AMBIGUOUS_OVERLOAD_CALL,
+            "Ambiguous overloaded call",
+            &format!(
+                "Can't tell which '{}' this call means: {} declare it, and at least one overloads it",
+                method,
+                classes.join(", ")
+            ),
+        )
+        .with_help("Annotate the receiver with its class, e.g. let shape: Circle = ..."),
+    ))
+}
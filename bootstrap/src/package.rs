@@ -0,0 +1,1182 @@
+//! Package dependencies: `liva.toml`, `liva.lock` and the fetch cache.
+//!
+//! A project lists the Liva libraries it uses under `[dependencies]`:
+//!
+//! ```toml
+//! [dependencies]
+//! mathx = { path = "../mathx" }
+//! strings = { git = "https://github.com/liva-lang/strings", tag = "v0.2.0" }
+//! ```
+//!
+//! [`resolve`] walks these and each library's own `[dependencies]`, checks
+//! git sources out into the cache (`$LIVA_HOME/cache`, default
+//! `~/.liva/cache`) and returns every package's entry file. The module
+//! resolver then maps a bare import such as `import { clamp } from "mathx"` to
+//! that file. The commits picked for git sources are recorded in `liva.lock`
+//! so later builds check out the same revisions.
+
+use crate::error::{CompilerError, Result, SemanticErrorInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Project manifest file name
+pub const MANIFEST_FILE: &str = "liva.toml";
+/// Lockfile written next to the manifest
+pub const LOCK_FILE: &str = "liva.lock";
+/// Entry file of a library that doesn't set `package.entry`
+pub const DEFAULT_LIB_ENTRY: &str = "src/lib.liva";
+/// Entry file `livac build` and `livac run` use without an input file when
+/// `package.entry` isn't set
+pub const DEFAULT_APP_ENTRY: &str = "src/main.liva";
+
+/// Contents of `liva.toml`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub package: PackageInfo,
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, DependencySpec>,
+    #[serde(default)]
+    pub build: BuildSettings,
+    #[serde(default)]
+    pub lint: LintSettings,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rust_dependencies: BTreeMap<String, RustDependency>,
+}
+
+/// The `[build]` table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildSettings {
+    /// Compile every file of the project as with `--strict`
+    #[serde(default)]
+    pub strict: bool,
+    /// Default for `--numeric-coercion`: "coerce", "checked" or "forbid"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numeric_coercion: Option<crate::NumericCoercion>,
+    /// Default for `--identifier-mapping`: "snake" or "preserve"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier_mapping: Option<crate::IdentifierMapping>,
+    /// Default for `--output`, relative to the directory of `liva.toml`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Default for `--profile`: "dev" or "release"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<crate::BuildProfile>,
+}
+
+/// One `[rust_dependencies]` entry: the version, and extra features, of a
+/// crate the program imports with `use rust`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RustDependency {
+    /// `regex = "1.10"`
+    Version(String),
+    /// `reqwest = { version = "0.11", features = ["blocking"] }`
+    Detailed {
+        version: Option<String>,
+        #[serde(default)]
+        features: Vec<String>,
+    },
+}
+
+impl RustDependency {
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            RustDependency::Version(version) => Some(version),
+            RustDependency::Detailed { version, .. } => version.as_deref(),
+        }
+    }
+
+    pub fn features(&self) -> &[String] {
+        match self {
+            RustDependency::Version(_) => &[],
+            RustDependency::Detailed { features, .. } => features,
+        }
+    }
+}
+
+/// Give each crate a `use rust` declaration that has no version of its own
+/// the one `pins` lists for it, and add the features listed there. Crates
+/// `pins` lists that the program doesn't import are left out.
+pub fn pin_rust_crates(
+    crates: &mut [crate::desugaring::RustCrateDep],
+    pins: &BTreeMap<String, RustDependency>,
+) {
+    for dep in crates {
+        let Some(pin) = pins.get(&dep.name) else {
+            continue;
+        };
+        if dep.version.is_none() {
+            dep.version = pin.version().map(str::to_string);
+        }
+        for feature in pin.features() {
+            if !dep.features.contains(feature) {
+                dep.features.push(feature.clone());
+            }
+        }
+    }
+}
+
+/// The `[lint]` table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LintSettings {
+    /// Default for `livac lint --naming`: "camel" or "snake"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub naming: Option<NamingConvention>,
+}
+
+/// Case that `livac lint` expects the functions, variables, parameters,
+/// fields and methods of a program to be written in (W013). Types and enum
+/// variants are PascalCase under both, and constants may be UPPER_SNAKE_CASE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamingConvention {
+    /// `unitPrice`, `computeTotal`
+    Camel,
+    /// `unit_price`, `compute_total`
+    Snake,
+}
+
+impl NamingConvention {
+    /// The spelling used on the command line and in liva.toml
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NamingConvention::Camel => "camel",
+            NamingConvention::Snake => "snake",
+        }
+    }
+}
+
+impl std::str::FromStr for NamingConvention {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "camel" => Ok(NamingConvention::Camel),
+            "snake" => Ok(NamingConvention::Snake),
+            other => Err(format!(
+                "unknown naming convention '{}' (expected camel or snake)",
+                other
+            )),
+        }
+    }
+}
+
+/// The `[package]` table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackageInfo {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+}
+
+/// One `[dependencies]` entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DependencySpec {
+    /// `name = "0.1"`: a registry version requirement
+    Version(String),
+    /// `name = { path = "..." }` or `name = { git = "...", tag = "..." }`
+    Detailed(DependencyDetail),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DependencyDetail {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// Which commit of a git source to use
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitReference {
+    DefaultBranch,
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+/// Where a dependency comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Path(String),
+    Git {
+        url: String,
+        reference: GitReference,
+    },
+    Registry(String),
+}
+
+impl DependencySpec {
+    pub fn source(&self, name: &str) -> Result<Source> {
+        // The name becomes part of the package cache path
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(manifest_error(&format!(
+                "Dependency name '{}' may only contain letters, digits, `_` and `-`",
+                name
+            )));
+        }
+        let detail = match self {
+            DependencySpec::Version(version) => return Ok(Source::Registry(version.clone())),
+            DependencySpec::Detailed(detail) => detail,
+        };
+        match (&detail.path, &detail.git) {
+            (Some(path), None) => Ok(Source::Path(path.clone())),
+            (None, Some(url)) if url.starts_with('-') => Err(manifest_error(&format!(
+                "Dependency '{}' has git URL '{}', which would be read as a git option",
+                name, url
+            ))),
+            (None, Some(url)) => {
+                let reference = match (&detail.rev, &detail.tag, &detail.branch) {
+                    (None, None, None) => GitReference::DefaultBranch,
+                    (Some(rev), None, None) => GitReference::Rev(rev.clone()),
+                    (None, Some(tag), None) => GitReference::Tag(tag.clone()),
+                    (None, None, Some(branch)) => GitReference::Branch(branch.clone()),
+                    _ => {
+                        return Err(manifest_error(&format!(
+                            "Dependency '{}' sets more than one of `rev`, `tag` and `branch`",
+                            name
+                        )))
+                    }
+                };
+                Ok(Source::Git {
+                    url: url.clone(),
+                    reference,
+                })
+            }
+            (Some(_), Some(_)) => Err(manifest_error(&format!(
+                "Dependency '{}' sets both `path` and `git`; pick one",
+                name
+            ))),
+            (None, None) => match &detail.version {
+                Some(version) => Ok(Source::Registry(version.clone())),
+                None => Err(manifest_error(&format!(
+                    "Dependency '{}' needs a `path` or `git` source",
+                    name
+                ))),
+            },
+        }
+    }
+
+    /// Inline-table form used when `livac add` writes the manifest
+    pub fn to_toml(&self) -> String {
+        let detail = match self {
+            DependencySpec::Version(version) => return toml_string(version),
+            DependencySpec::Detailed(detail) => detail,
+        };
+        let fields = [
+            ("path", &detail.path),
+            ("git", &detail.git),
+            ("rev", &detail.rev),
+            ("tag", &detail.tag),
+            ("branch", &detail.branch),
+            ("version", &detail.version),
+        ];
+        let parts: Vec<String> = fields
+            .iter()
+            .filter_map(|(key, value)| {
+                value
+                    .as_ref()
+                    .map(|v| format!("{} = {}", key, toml_string(v)))
+            })
+            .collect();
+        format!("{{ {} }}", parts.join(", "))
+    }
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text)
+            .map_err(|e| manifest_error(&format!("Invalid {}: {}", MANIFEST_FILE, e)))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| manifest_error(&format!("Cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    /// `[build]` settings of the project `file` belongs to. A manifest that
+    /// can't be read gives the defaults; resolving dependencies reports it.
+    pub fn build_settings_for(file: &Path) -> BuildSettings {
+        file.parent()
+            .and_then(Self::find)
+            .and_then(|path| Self::load(&path).ok())
+            .map(|manifest| manifest.build)
+            .unwrap_or_default()
+    }
+
+    /// `[rust_dependencies]` of the project `file` belongs to, or none
+    pub fn rust_dependencies_for(file: &Path) -> BTreeMap<String, RustDependency> {
+        file.parent()
+            .and_then(Self::find)
+            .and_then(|path| Self::load(&path).ok())
+            .map(|manifest| manifest.rust_dependencies)
+            .unwrap_or_default()
+    }
+
+    /// `build.output` of the project `file` belongs to, resolved against the
+    /// directory of its `liva.toml`
+    pub fn output_dir_for(file: &Path) -> Option<PathBuf> {
+        let path = file.parent().and_then(Self::find)?;
+        let output = Self::load(&path).ok()?.build.output?;
+        Some(path.parent()?.join(output))
+    }
+
+    /// Entry file of the project whose `liva.toml` is in `dir` or one of its
+    /// ancestors: `package.entry`, or `src/main.liva` when it isn't set
+    pub fn entry_for(dir: &Path) -> Option<PathBuf> {
+        let path = Self::find(dir)?;
+        let manifest = Self::load(&path).ok()?;
+        let entry = manifest
+            .package
+            .entry
+            .as_deref()
+            .unwrap_or(DEFAULT_APP_ENTRY);
+        Some(path.parent()?.join(entry))
+    }
+
+    /// `[lint]` settings of the project `file` belongs to, or the defaults
+    pub fn lint_settings_for(file: &Path) -> LintSettings {
+        file.parent()
+            .and_then(Self::find)
+            .and_then(|path| Self::load(&path).ok())
+            .map(|manifest| manifest.lint)
+            .unwrap_or_default()
+    }
+
+    /// Nearest `liva.toml` in `start` or one of its ancestors
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        let start = if start.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            start
+        };
+        let start = start.canonicalize().ok()?;
+        start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|path| path.is_file())
+    }
+}
+
+/// Add or replace `name` under `[dependencies]`, keeping the rest of the file
+/// (comments, ordering) untouched
+pub fn add_dependency(manifest_text: &str, name: &str, spec: &DependencySpec) -> String {
+    let entry = format!("{} = {}", name, spec.to_toml());
+    let mut lines: Vec<String> = manifest_text.lines().map(str::to_string).collect();
+
+    let Some(header) = lines.iter().position(|l| l.trim() == "[dependencies]") else {
+        let mut text = manifest_text.trim_end().to_string();
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(&format!("[dependencies]\n{}\n", entry));
+        return text;
+    };
+
+    let section_end = lines[header + 1..]
+        .iter()
+        .position(|l| l.trim_start().starts_with('['))
+        .map_or(lines.len(), |n| header + 1 + n);
+    let existing = lines[header + 1..section_end].iter().position(|l| {
+        l.trim_start()
+            .strip_prefix(name)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    });
+    match existing {
+        Some(n) => lines[header + 1 + n] = entry,
+        None => {
+            // After the last entry, before any blank lines separating the next table
+            let insert_at = lines[header + 1..section_end]
+                .iter()
+                .rposition(|l| !l.trim().is_empty())
+                .map_or(header + 1, |n| header + 2 + n);
+            lines.insert(insert_at, entry);
+        }
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Contents of `liva.lock`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// `path+<dir>` or `git+<url>[?tag=..|?branch=..|?rev=..]#<commit>`
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+impl Lockfile {
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| manifest_error(&format!("Invalid {}: {}", LOCK_FILE, e)))
+    }
+
+    pub fn to_toml(&self) -> String {
+        let body = toml::to_string(self).unwrap_or_default();
+        format!(
+            "# This file is generated by livac. Do not edit it by hand.\n{}",
+            body
+        )
+    }
+
+    /// Commit recorded for a git source, if the lock still matches the manifest
+    fn locked_commit(&self, name: &str, source_id: &str) -> Option<&str> {
+        self.packages
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.source.rsplit_once('#'))
+            .filter(|(id, _)| *id == source_id)
+            .map(|(_, commit)| commit)
+    }
+}
+
+/// A dependency ready to be compiled as a module
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPackage {
+    pub name: String,
+    pub version: String,
+    /// Package directory (a checkout in the cache for git sources)
+    pub root: PathBuf,
+    /// Canonical path of the entry file
+    pub entry: PathBuf,
+    /// Lockfile source string
+    pub source: String,
+    pub dependencies: Vec<String>,
+}
+
+/// Every package reachable from a project's manifest, by name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resolution {
+    pub packages: BTreeMap<String, ResolvedPackage>,
+}
+
+impl Resolution {
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// File a bare import refers to: `"mathx"` is the package's entry file,
+    /// `"mathx/geometry"` is `geometry.liva` inside the package's `src/`
+    /// directory (or its root when it has no `src/`)
+    pub fn import_path(&self, source: &str) -> Option<PathBuf> {
+        if source.starts_with("./") || source.starts_with("../") || source.starts_with('/') {
+            return None;
+        }
+        let (name, rest) = match source.split_once('/') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (source, None),
+        };
+        let package = self.packages.get(name)?;
+        let Some(rest) = rest else {
+            return Some(package.entry.clone());
+        };
+        let base = package.entry.parent().unwrap_or(&package.root);
+        let path = base.join(rest);
+        if path.extension().is_none() {
+            Some(path.with_extension("liva"))
+        } else {
+            Some(path)
+        }
+    }
+
+    /// Name of the package whose entry file is `path`
+    pub fn package_for_entry(&self, path: &Path) -> Option<&str> {
+        self.packages
+            .values()
+            .find(|p| p.entry == path)
+            .map(|p| p.name.as_str())
+    }
+
+    pub fn to_lockfile(&self) -> Lockfile {
+        Lockfile {
+            version: 1,
+            packages: self
+                .packages
+                .values()
+                .map(|p| LockedPackage {
+                    name: p.name.clone(),
+                    version: p.version.clone(),
+                    source: p.source.clone(),
+                    dependencies: p.dependencies.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Resolve every dependency of the project at `project_root`, reusing the
+/// commits pinned in `lock` where the manifest still asks for the same source
+pub fn resolve(
+    project_root: &Path,
+    manifest: &Manifest,
+    lock: Option<&Lockfile>,
+) -> Result<Resolution> {
+    let project_root = project_root
+        .canonicalize()
+        .unwrap_or_else(|_| project_root.to_path_buf());
+    let mut resolution = Resolution::default();
+    let mut queue: VecDeque<(String, DependencySpec, PathBuf, String)> = manifest
+        .dependencies
+        .iter()
+        .map(|(name, spec)| {
+            (
+                name.clone(),
+                spec.clone(),
+                project_root.clone(),
+                display_name(&manifest.package.name, "the project"),
+            )
+        })
+        .collect();
+
+    while let Some((name, spec, base_dir, required_by)) = queue.pop_front() {
+        let source = spec.source(&name)?;
+        let (root, source_id, lock_source) = match &source {
+            Source::Path(path) => {
+                let root = base_dir.join(path).canonicalize().map_err(|e| {
+                    fetch_error(&format!(
+                        "Dependency '{}' (required by {}) points to '{}', which cannot be read: {}",
+                        name, required_by, path, e
+                    ))
+                })?;
+                let id = format!("path+{}", relative_path(&project_root, &root).display());
+                (root, id.clone(), id)
+            }
+            Source::Git { url, reference } => {
+                let id = git_source_id(url, reference);
+                let locked = lock.and_then(|l| l.locked_commit(&name, &id));
+                let (root, commit) = fetch_git(&name, url, reference, locked)?;
+                (root, id.clone(), format!("{}#{}", id, commit))
+            }
+            Source::Registry(version) => {
+                return Err(fetch_error(&format!(
+                    "Dependency '{}' = \"{}\" needs a package registry, which is not available yet.\nHint: Use a path or git source: {} = {{ git = \"https://...\" }}",
+                    name, version, name
+                )))
+            }
+        };
+
+        if let Some(existing) = resolution.packages.get(&name) {
+            let existing_id = existing
+                .source
+                .rsplit_once('#')
+                .map_or(existing.source.as_str(), |(id, _)| id);
+            if existing_id != source_id {
+                return Err(CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
+                    "E4012",
+                    "Conflicting dependency sources",
+                    &format!(
+                        "Package '{}' is required from two different sources:\n  {}\n  {} (required by {})\nHint: Make every manifest point '{}' at the same path or git revision.",
+                        name, existing.source, lock_source, required_by, name
+                    ),
+                ))));
+            }
+            continue;
+        }
+
+        let dep_manifest_path = root.join(MANIFEST_FILE);
+        let dep_manifest = if dep_manifest_path.is_file() {
+            Manifest::load(&dep_manifest_path)?
+        } else {
+            Manifest::default()
+        };
+        let entry_rel = dep_manifest
+            .package
+            .entry
+            .clone()
+            .unwrap_or_else(|| DEFAULT_LIB_ENTRY.to_string());
+        let entry = root.join(&entry_rel).canonicalize().map_err(|_| {
+            fetch_error(&format!(
+                "Package '{}' has no entry file '{}' (in {})\nHint: Libraries export their API from src/lib.liva, or set `entry` under [package] in their liva.toml.",
+                name,
+                entry_rel,
+                root.display()
+            ))
+        })?;
+
+        for (dep_name, dep_spec) in &dep_manifest.dependencies {
+            queue.push_back((
+                dep_name.clone(),
+                dep_spec.clone(),
+                root.clone(),
+                format!("'{}'", name),
+            ));
+        }
+        resolution.packages.insert(
+            name.clone(),
+            ResolvedPackage {
+                name,
+                version: dep_manifest.package.version.clone(),
+                root,
+                entry,
+                source: lock_source,
+                dependencies: dep_manifest.dependencies.keys().cloned().collect(),
+            },
+        );
+    }
+
+    Ok(resolution)
+}
+
+/// Resolve the dependencies of the project whose manifest is `manifest_path`,
+/// then write `liva.lock` if the result changed
+pub fn resolve_project(manifest_path: &Path) -> Result<Resolution> {
+    let manifest = Manifest::load(manifest_path)?;
+    let root = manifest_path.parent().unwrap_or(Path::new("."));
+    let lock_path = root.join(LOCK_FILE);
+    let lock = match std::fs::read_to_string(&lock_path) {
+        Ok(text) => Some(Lockfile::parse(&text)?),
+        Err(_) => None,
+    };
+
+    let resolution = resolve(root, &manifest, lock.as_ref())?;
+    let new_lock = resolution.to_lockfile();
+    if lock.as_ref() != Some(&new_lock) && !(lock.is_none() && resolution.is_empty()) {
+        std::fs::write(&lock_path, new_lock.to_toml()).map_err(|e| {
+            CompilerError::IoError(format!("Failed to write {}: {}", lock_path.display(), e))
+        })?;
+    }
+    Ok(resolution)
+}
+
+/// Where fetched packages are kept: `$LIVA_HOME/cache`, else `~/.liva/cache`
+pub fn cache_dir() -> PathBuf {
+    if let Ok(home) = std::env::var("LIVA_HOME") {
+        return PathBuf::from(home).join("cache");
+    }
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(".liva")
+        .join("cache")
+}
+
+fn git_source_id(url: &str, reference: &GitReference) -> String {
+    match reference {
+        GitReference::DefaultBranch => format!("git+{}", url),
+        GitReference::Branch(branch) => format!("git+{}?branch={}", url, branch),
+        GitReference::Tag(tag) => format!("git+{}?tag={}", url, tag),
+        GitReference::Rev(rev) => format!("git+{}?rev={}", url, rev),
+    }
+}
+
+/// Clone `url` into the cache (once) and check out the wanted commit.
+/// Returns the checkout directory and the commit hash.
+fn fetch_git(
+    name: &str,
+    url: &str,
+    reference: &GitReference,
+    locked: Option<&str>,
+) -> Result<(PathBuf, String)> {
+    let key = format!("{}-{:016x}", name, fnv1a(url.as_bytes()));
+    let db = cache_dir().join("git").join("db").join(&key);
+
+    let target = match (locked, reference) {
+        (Some(commit), _) => commit.to_string(),
+        (None, GitReference::DefaultBranch) => "origin/HEAD".to_string(),
+        (None, GitReference::Branch(branch)) => format!("origin/{}", branch),
+        (None, GitReference::Tag(tag)) => format!("refs/tags/{}", tag),
+        (None, GitReference::Rev(rev)) => rev.clone(),
+    };
+    let target_commit = format!("{}^{{commit}}", target);
+
+    if !db.exists() {
+        std::fs::create_dir_all(db.parent().unwrap_or(&db)).map_err(|e| {
+            fetch_error(&format!(
+                "Cannot create package cache {}: {}",
+                db.display(),
+                e
+            ))
+        })?;
+        git(
+            &[
+                "clone",
+                "--quiet",
+                "--no-checkout",
+                "--",
+                url,
+                &path_arg(&db),
+            ],
+            None,
+        )
+        .map_err(|e| fetch_error(&format!("Cannot clone '{}' for '{}': {}", url, name, e)))?;
+    } else if locked.is_none()
+        || git(
+            &["rev-parse", "--verify", "--quiet", &target_commit],
+            Some(&db),
+        )
+        .is_err()
+    {
+        // Unpinned references follow the remote; pinned ones only fetch when missing
+        git(
+            &["fetch", "--quiet", "--tags", "--force", "origin"],
+            Some(&db),
+        )
+        .map_err(|e| fetch_error(&format!("Cannot fetch '{}' for '{}': {}", url, name, e)))?;
+    }
+
+    let commit = git(&["rev-parse", "--verify", &target_commit], Some(&db)).map_err(|_| {
+        fetch_error(&format!(
+            "Dependency '{}': '{}' does not name a commit in {}",
+            name,
+            locked.map_or_else(|| reference_name(reference), str::to_string),
+            url
+        ))
+    })?;
+
+    let checkout = cache_dir()
+        .join("git")
+        .join("checkouts")
+        .join(&key)
+        .join(&commit[..commit.len().min(12)]);
+    if !checkout.exists() {
+        let result = git(
+            &[
+                "clone",
+                "--quiet",
+                "--shared",
+                "--no-checkout",
+                &path_arg(&db),
+                &path_arg(&checkout),
+            ],
+            None,
+        )
+        .and_then(|_| git(&["checkout", "--quiet", &commit], Some(&checkout)));
+        if let Err(e) = result {
+            let _ = std::fs::remove_dir_all(&checkout);
+            return Err(fetch_error(&format!(
+                "Cannot check out {} of '{}': {}",
+                commit, name, e
+            )));
+        }
+    }
+    Ok((checkout, commit))
+}
+
+fn reference_name(reference: &GitReference) -> String {
+    match reference {
+        GitReference::DefaultBranch => "the default branch".to_string(),
+        GitReference::Branch(b) => format!("branch '{}'", b),
+        GitReference::Tag(t) => format!("tag '{}'", t),
+        GitReference::Rev(r) => format!("rev '{}'", r),
+    }
+}
+
+/// Run git and return trimmed stdout, or stderr as the error
+fn git(args: &[&str], dir: Option<&Path>) -> std::result::Result<String, String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args).env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd
+        .output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+fn path_arg(path: &Path) -> String {
+    path.display().to_string()
+}
+
+/// `to` relative to `from` when they share a prefix (both canonical)
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to_parts: Vec<Component> = to.components().collect();
+    let common = from
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return to.to_path_buf();
+    }
+    let mut rel = PathBuf::new();
+    for _ in common..from.len() {
+        rel.push("..");
+    }
+    for part in &to_parts[common..] {
+        rel.push(part);
+    }
+    if rel.as_os_str().is_empty() {
+        rel.push(".");
+    }
+    rel
+}
+
+fn display_name(name: &str, fallback: &str) -> String {
+    if name.is_empty() {
+        fallback.to_string()
+    } else {
+        format!("'{}'", name)
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn manifest_error(message: &str) -> CompilerError {
+    CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
+        "E4010",
+        "Invalid package manifest",
+        message,
+    )))
+}
+
+fn fetch_error(message: &str) -> CompilerError {
+    CompilerError::CodegenError(Box::new(SemanticErrorInfo::new(
+        "E4011",
+        "Dependency could not be resolved",
+        message,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn library(dir: &Path, name: &str, deps: &str) {
+        write(
+            &dir.join(MANIFEST_FILE),
+            &format!(
+                "[package]\nname = \"{}\"\nversion = \"0.2.0\"\n\n[dependencies]\n{}",
+                name, deps
+            ),
+        );
+        write(
+            &dir.join(DEFAULT_LIB_ENTRY),
+            "twice(n: number): number => n * 2\n",
+        );
+    }
+
+    #[test]
+    fn test_manifest_sources() {
+        let manifest = Manifest::parse(
+            r#"
+[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+mathx = { path = "../mathx" }
+strs = { git = "https://example.com/strs", tag = "v1" }
+json = "1.0"
+"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.package.name, "app");
+        assert_eq!(
+            manifest.dependencies["mathx"].source("mathx").unwrap(),
+            Source::Path("../mathx".to_string())
+        );
+        assert_eq!(
+            manifest.dependencies["strs"].source("strs").unwrap(),
+            Source::Git {
+                url: "https://example.com/strs".to_string(),
+                reference: GitReference::Tag("v1".to_string()),
+            }
+        );
+        assert_eq!(
+            manifest.dependencies["json"].source("json").unwrap(),
+            Source::Registry("1.0".to_string())
+        );
+
+        let both = Manifest::parse("[dependencies]\nx = { path = \"a\", git = \"b\" }\n").unwrap();
+        assert!(both.dependencies["x"].source("x").is_err());
+        let option =
+            Manifest::parse("[dependencies]\nx = { git = \"--upload-pack=touch\" }\n").unwrap();
+        assert!(option.dependencies["x"].source("x").is_err());
+        let escaping =
+            Manifest::parse("[dependencies]\n\"../x\" = { git = \"https://example.com/x\" }\n")
+                .unwrap();
+        assert!(escaping.dependencies["../x"].source("../x").is_err());
+        assert!(manifest.dependencies["strs"].source("my-strs_2").is_ok());
+        assert!(Manifest::parse("[dependencies\n").is_err());
+        assert!(!manifest.build.strict);
+    }
+
+    #[test]
+    fn test_build_settings() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("src/main.liva");
+        write(&file, "main() {}\n");
+        assert_eq!(
+            Manifest::build_settings_for(&file),
+            BuildSettings::default()
+        );
+
+        write(
+            &dir.path().join(MANIFEST_FILE),
+            "[package]\nname = \"app\"\n\n[build]\nstrict = true\nnumeric_coercion = \"checked\"\nidentifier_mapping = \"preserve\"\noutput = \"dist\"\nprofile = \"release\"\n",
+        );
+        let build = Manifest::build_settings_for(&file);
+        assert!(build.strict);
+        assert_eq!(
+            build.numeric_coercion,
+            Some(crate::NumericCoercion::Checked)
+        );
+        assert_eq!(
+            build.identifier_mapping,
+            Some(crate::IdentifierMapping::Preserve)
+        );
+        assert_eq!(build.profile, Some(crate::BuildProfile::Release));
+
+        // Paths are relative to liva.toml, not to the file
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(Manifest::output_dir_for(&file), Some(root.join("dist")));
+        assert_eq!(
+            Manifest::entry_for(&root.join("src")),
+            Some(root.join(DEFAULT_APP_ENTRY))
+        );
+    }
+
+    #[test]
+    fn test_rust_dependencies_pin_use_rust_crates() {
+        let manifest = Manifest::parse(
+            "[rust_dependencies]\nregex = \"1.10\"\nreqwest = { version = \"0.12\", features = [\"blocking\"] }\nunused = \"2\"\n",
+        )
+        .unwrap();
+        let dep = |name: &str, version: Option<&str>| crate::desugaring::RustCrateDep {
+            name: name.to_string(),
+            alias: None,
+            version: version.map(str::to_string),
+            features: Vec::new(),
+        };
+        let mut crates = vec![
+            dep("regex", None),
+            dep("reqwest", Some("0.11")),
+            dep("itertools", None),
+        ];
+        pin_rust_crates(&mut crates, &manifest.rust_dependencies);
+
+        assert_eq!(crates[0].version.as_deref(), Some("1.10"));
+        // A version in `use rust` wins; the features still apply
+        assert_eq!(crates[1].version.as_deref(), Some("0.11"));
+        assert_eq!(crates[1].features, ["blocking"]);
+        assert_eq!(crates[2].version, None);
+        assert_eq!(crates.len(), 3);
+    }
+
+    #[test]
+    fn test_lint_settings() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("main.liva");
+        write(&file, "main() {}\n");
+        assert_eq!(Manifest::lint_settings_for(&file).naming, None);
+
+        write(
+            &dir.path().join(MANIFEST_FILE),
+            "[package]\nname = \"app\"\n\n[lint]\nnaming = \"snake\"\n",
+        );
+        assert_eq!(
+            Manifest::lint_settings_for(&file).naming,
+            Some(NamingConvention::Snake)
+        );
+    }
+
+    #[test]
+    fn test_add_dependency_edits_in_place() {
+        let text = "# my app\n[package]\nname = \"app\"\n\n[dependencies]\nold = { path = \"../old\" }\n\n[dev]\nx = 1\n";
+        let spec = DependencySpec::Detailed(DependencyDetail {
+            git: Some("https://example.com/strs".to_string()),
+            tag: Some("v1".to_string()),
+            ..Default::default()
+        });
+        let updated = add_dependency(text, "strs", &spec);
+        assert_eq!(
+            updated,
+            "# my app\n[package]\nname = \"app\"\n\n[dependencies]\nold = { path = \"../old\" }\nstrs = { git = \"https://example.com/strs\", tag = \"v1\" }\n\n[dev]\nx = 1\n"
+        );
+
+        let path_spec = DependencySpec::Detailed(DependencyDetail {
+            path: Some("../new".to_string()),
+            ..Default::default()
+        });
+        let replaced = add_dependency(&updated, "old", &path_spec);
+        assert!(replaced.contains("old = { path = \"../new\" }\n"));
+        assert!(!replaced.contains("../old"));
+
+        let created = add_dependency("[package]\nname = \"app\"\n", "old", &path_spec);
+        assert_eq!(
+            created,
+            "[package]\nname = \"app\"\n\n[dependencies]\nold = { path = \"../new\" }\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_transitive_path_dependencies() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        library(&root.join("strs"), "strs", "");
+        library(
+            &root.join("mathx"),
+            "mathx",
+            "strs = { path = \"../strs\" }\n",
+        );
+        write(
+            &root.join("app").join(MANIFEST_FILE),
+            "[package]\nname = \"app\"\n\n[dependencies]\nmathx = { path = \"../mathx\" }\n",
+        );
+
+        let resolution = resolve_project(&root.join("app").join(MANIFEST_FILE)).unwrap();
+        let names: Vec<&str> = resolution.packages.keys().map(String::as_str).collect();
+        assert_eq!(names, vec!["mathx", "strs"]);
+        assert_eq!(resolution.packages["strs"].source, "path+../strs");
+        assert_eq!(resolution.packages["mathx"].dependencies, vec!["strs"]);
+        assert_eq!(
+            resolution.import_path("mathx"),
+            Some(root.join("mathx").join(DEFAULT_LIB_ENTRY))
+        );
+        assert_eq!(
+            resolution.import_path("mathx/geometry"),
+            Some(root.join("mathx").join("src").join("geometry.liva"))
+        );
+        assert_eq!(resolution.import_path("./mathx"), None);
+        assert_eq!(resolution.import_path("other"), None);
+
+        let lock = fs::read_to_string(root.join("app").join(LOCK_FILE)).unwrap();
+        assert_eq!(Lockfile::parse(&lock).unwrap(), resolution.to_lockfile());
+    }
+
+    #[test]
+    fn test_resolve_reports_conflicts_and_missing_packages() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        library(&root.join("strs-a"), "strs", "");
+        library(&root.join("strs-b"), "strs", "");
+        library(
+            &root.join("mathx"),
+            "mathx",
+            "strs = { path = \"../strs-b\" }\n",
+        );
+
+        let manifest = Manifest::parse(
+            "[dependencies]\nstrs = { path = \"strs-a\" }\nmathx = { path = \"mathx\" }\n",
+        )
+        .unwrap();
+        let err = resolve(root, &manifest, None).unwrap_err();
+        assert!(err.to_string().contains("E4012"), "{}", err);
+
+        let missing = Manifest::parse("[dependencies]\nnope = { path = \"nope\" }\n").unwrap();
+        assert!(resolve(root, &missing, None)
+            .unwrap_err()
+            .to_string()
+            .contains("E4011"));
+
+        let registry = Manifest::parse("[dependencies]\njson = \"1.0\"\n").unwrap();
+        assert!(resolve(root, &registry, None)
+            .unwrap_err()
+            .to_string()
+            .contains("registry"));
+    }
+
+    #[test]
+    fn test_resolve_git_dependency_and_lock_pins_commit() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::env::set_var("LIVA_HOME", root.join("home"));
+
+        let repo = root.join("strs");
+        library(&repo, "strs", "");
+        let commit = |message: &str| {
+            git(&["add", "-A"], Some(&repo)).unwrap();
+            git(
+                &[
+                    "-c",
+                    "user.name=t",
+                    "-c",
+                    "user.email=t@t",
+                    "commit",
+                    "-qm",
+                    message,
+                ],
+                Some(&repo),
+            )
+            .unwrap();
+            git(&["rev-parse", "HEAD"], Some(&repo)).unwrap()
+        };
+        git(&["init", "-q"], Some(&repo)).unwrap();
+        let first = commit("first");
+
+        let manifest = Manifest::parse(&format!(
+            "[dependencies]\nstrs = {{ git = {} }}\n",
+            toml_string(&repo.display().to_string())
+        ))
+        .unwrap();
+        let resolution = resolve(&root, &manifest, None).unwrap();
+        let strs = &resolution.packages["strs"];
+        assert!(strs.source.ends_with(&format!("#{}", first)));
+        assert!(strs.root.starts_with(root.join("home").join("cache")));
+        assert!(strs.entry.is_file());
+
+        // The lock keeps the first commit; without it the new one is picked up
+        write(&repo.join("src").join("extra.liva"), "one(): number => 1\n");
+        let second = commit("second");
+        let lock = resolution.to_lockfile();
+        let locked = resolve(&root, &manifest, Some(&lock)).unwrap();
+        assert!(locked.packages["strs"].source.ends_with(&first));
+        let updated = resolve(&root, &manifest, None).unwrap();
+        assert!(updated.packages["strs"].source.ends_with(&second));
+        assert!(updated.import_path("strs/extra").unwrap().is_file());
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/p/app"), Path::new("/p/libs/mathx")),
+            PathBuf::from("../libs/mathx")
+        );
+        assert_eq!(
+            relative_path(Path::new("/p/app"), Path::new("/p/app/vendor/x")),
+            PathBuf::from("vendor/x")
+        );
+    }
+}
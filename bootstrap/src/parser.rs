@@ -1,23 +1,64 @@
 use crate::ast::*;
 use crate::error::{CompilerError, Result, SemanticErrorInfo};
-use crate::lexer::{tokenize, Token, TokenWithSpan};
-use crate::span::SourceMap;
+use crate::error_codes::{E2000_PARSE_ERROR, E2005_NESTING_TOO_DEEP, E2006_UNKNOWN_MOCK_TARGET};
+use crate::lexer::{tokenize, Comment, CommentKind, Token, TokenWithSpan};
+use crate::span::{SourceMap, Span};
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Deepest nesting of expressions and statements the parser accepts, with
+/// a chain of binary operators or calls counting one level per link. Later
+/// passes walk the tree recursively, so this also bounds their stack use.
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+/// Stack left when [`grow_stack`] moves to a fresh segment, and the size of
+/// that segment
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_PER_SEGMENT: usize = 4 * 1024 * 1024;
+
+/// Run `f` on a fresh stack segment if the current one is nearly used up.
+/// Unoptimized builds use far bigger frames per nesting level than release
+/// ones, so the recursive passes call this once per level.
+pub(crate) fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_PER_SEGMENT, f)
+}
 
 pub struct Parser {
     tokens: Vec<TokenWithSpan>,
     current: usize,
-    source: String,
-    source_map: SourceMap,
+    source: Rc<str>,
+    source_map: Rc<SourceMap>,
+    comments: Vec<Comment>,
+    /// Expressions and statements being parsed around the current token
+    depth: usize,
+    /// Set by [`Parser::parse_program_recovering`]: a statement that fails to
+    /// parse is recorded in `errors` and skipped instead of ending the parse
+    recovering: bool,
+    errors: Vec<CompilerError>,
 }
 
 impl Parser {
     fn new(tokens: Vec<TokenWithSpan>, source: String) -> Self {
-        let source_map = SourceMap::new(&source);
+        let source_map = Rc::new(SourceMap::new(&source));
+        let comments = crate::lexer::comments(&source);
+        Self::with_source(tokens, source.into(), source_map, comments)
+    }
+
+    fn with_source(
+        tokens: Vec<TokenWithSpan>,
+        source: Rc<str>,
+        source_map: Rc<SourceMap>,
+        comments: Vec<Comment>,
+    ) -> Self {
         Self {
             tokens,
             current: 0,
             source,
             source_map,
+            comments,
+            depth: 0,
+            recovering: false,
+            errors: Vec::new(),
         }
     }
 
@@ -70,6 +111,21 @@ impl Parser {
         }
     }
 
+    /// `>>` (with `next` = `Gt`) or `>>=` (with `next` = `Ge`): a `>` directly
+    /// followed by `next`. The lexer never joins them, because generic types
+    /// close with `>>`.
+    fn check_shift_right(&self, next: &Token) -> bool {
+        let (Some(first), Some(second)) = (
+            self.tokens.get(self.current),
+            self.tokens.get(self.current + 1),
+        ) else {
+            return false;
+        };
+        first.token == Token::Gt
+            && std::mem::discriminant(&second.token) == std::mem::discriminant(next)
+            && first.span.end == second.span.start
+    }
+
     /// Check if the current token is an identifier with a specific name
     fn check_ident(&self, name: &str) -> bool {
         matches!(self.peek(), Some(Token::Ident(s)) if s == name)
@@ -84,7 +140,7 @@ impl Parser {
         }
     }
 
-    /// Check for compound assignment operators (`+=`, `-=`, `*=`, `/=`, `%=`).
+    /// Check for compound assignment operators (`+=`, `-=`, `*=`, `/=`, `%=`, `**=`).
     /// If matched, advances the parser and returns the corresponding BinOp.
     fn match_compound_assign(&mut self) -> Option<BinOp> {
         if self.check(&Token::PlusAssign) {
@@ -102,13 +158,31 @@ impl Parser {
         } else if self.check(&Token::PercentAssign) {
             self.advance();
             Some(BinOp::Mod)
+        } else if self.check(&Token::StarStarAssign) {
+            self.advance();
+            Some(BinOp::Pow)
+        } else if self.check(&Token::AmpAssign) {
+            self.advance();
+            Some(BinOp::BitAnd)
+        } else if self.check(&Token::PipeAssign) {
+            self.advance();
+            Some(BinOp::BitOr)
+        } else if self.check(&Token::CaretAssign) {
+            self.advance();
+            Some(BinOp::BitXor)
+        } else if self.check(&Token::ShlAssign) {
+            self.advance();
+            Some(BinOp::Shl)
+        } else if self.check_shift_right(&Token::Ge) {
+            self.advance();
+            self.advance();
+            Some(BinOp::Shr)
         } else {
             None
         }
     }
 
     /// Get the span of the current token
-    #[allow(dead_code)]
     fn current_span(&self) -> Option<crate::span::Span> {
         if self.current < self.tokens.len() {
             Some(self.tokens[self.current].span)
@@ -117,6 +191,72 @@ impl Parser {
         }
     }
 
+    /// Span from the start of `start` to the end of the previous token
+    fn span_since(&self, start: Option<crate::span::Span>) -> Option<crate::span::Span> {
+        let (start, end) = (start?, self.previous_span()?);
+        Some(crate::span::Span::new(
+            start.start,
+            end.end.max(start.start),
+        ))
+    }
+
+    /// Whether a line break separates the current token from the previous one.
+    /// `(`, `[` and postfix `!` only continue an expression on the same line,
+    /// and `return` only takes a value that starts on its line.
+    fn at_line_start(&self) -> bool {
+        match (self.previous_span(), self.current_span()) {
+            (Some(previous), Some(current)) => self
+                .source
+                .get(previous.end..current.start)
+                .is_some_and(|gap| gap.contains('\n')),
+            _ => false,
+        }
+    }
+
+    /// Called when a list element isn't followed by `,`: anything but `close`
+    /// means a comma is missing, which is worth saying when the next element
+    /// sits on its own line. Another closing bracket means the list itself
+    /// was left open, which the caller reports.
+    fn expect_list_end(&self, close: &Token, what: &str) -> Result<()> {
+        let closes_other = matches!(
+            self.peek_token(0),
+            Some(Token::RParen | Token::RBracket | Token::RBrace)
+        );
+        if self.is_at_end() || self.check(close) || closes_other || !self.at_line_start() {
+            return Ok(());
+        }
+        Err(self.error_with_help(
+            format!("Expected ',' between {}", what),
+            Some("Separate list items with ',' (a trailing ',' after the last one is fine)".into()),
+        ))
+    }
+
+    /// Doc comments directly above the token at `start`, joined into one
+    /// text. Anything but whitespace between them ends the run.
+    fn doc_comment_before(&self, start: Option<crate::span::Span>) -> Option<String> {
+        let start = start?.start;
+        let mut end = start;
+        let mut lines = Vec::new();
+        for comment in self
+            .comments
+            .iter()
+            .rev()
+            .skip_while(|c| c.span.start >= start)
+        {
+            let gap = self.source.get(comment.span.end..end).unwrap_or("x");
+            if comment.kind != CommentKind::Doc || !gap.trim().is_empty() {
+                break;
+            }
+            lines.splice(0..0, comment.lines());
+            end = comment.span.start;
+        }
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+
     /// Get the span of the previous token
     fn previous_span(&self) -> Option<crate::span::Span> {
         if self.current > 0 && self.current - 1 < self.tokens.len() {
@@ -247,15 +387,24 @@ impl Parser {
     }
 
     fn error_with_help(&self, message: String, help: Option<String>) -> CompilerError {
+        self.error_with_code(E2000_PARSE_ERROR, "Parse Error", message, help)
+    }
+
+    fn error_with_code(
+        &self,
+        code: &str,
+        title: &str,
+        message: String,
+        help: Option<String>,
+    ) -> CompilerError {
         let token_index = if self.current < self.tokens.len() {
             self.current
         } else if !self.tokens.is_empty() {
             self.tokens.len() - 1
         } else {
-            return CompilerError::ParseError(
-                SemanticErrorInfo::new("E2000", "Parse Error", &message)
-                    .with_location("<input>", 1),
-            );
+            return CompilerError::ParseError(Box::new(
+                SemanticErrorInfo::new(code, title, &message).with_location("<input>", 1),
+            ));
         };
 
         let (line, col) = self.calculate_line_col(token_index);
@@ -300,7 +449,7 @@ impl Parser {
             3 // default length
         };
 
-        let mut error = SemanticErrorInfo::new("E2000", "Parse Error", &message)
+        let mut error = SemanticErrorInfo::new(code, title, &message)
             .with_location("<input>", line)
             .with_column(col)
             .with_source_line(source_line)
@@ -311,7 +460,38 @@ impl Parser {
             error = error.with_help(&help_text);
         }
 
-        CompilerError::ParseError(error)
+        CompilerError::ParseError(Box::new(error))
+    }
+
+    /// Run `parse` one nesting level deeper, failing once the code is nested
+    /// deeper than [`MAX_NESTING_DEPTH`] instead of overflowing the stack
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.check_depth(1)?;
+        self.depth += 1;
+        let result = grow_stack(|| parse(self));
+        self.depth -= 1;
+        result
+    }
+
+    /// Fail if `links` more levels would nest the code too deeply
+    fn check_depth(&self, links: usize) -> Result<()> {
+        if self.depth + links <= MAX_NESTING_DEPTH {
+            return Ok(());
+        }
+        Err(self.error_with_code(
+            E2005_NESTING_TOO_DEEP,
+            "Nesting too deep",
+            format!(
+                "Code is nested more than {} levels deep here",
+                MAX_NESTING_DEPTH
+            ),
+            Some("Split it up with intermediate `let` bindings or helper functions".into()),
+        ))
+    }
+
+    /// 1-based source line of the current token
+    fn current_line(&self) -> u32 {
+        self.calculate_line_col(self.current).0 as u32
     }
 
     fn calculate_line_col(&self, token_index: usize) -> (usize, usize) {
@@ -326,15 +506,210 @@ impl Parser {
         let mut items = Vec::new();
 
         while !self.is_at_end() {
+            if self.match_token(&Token::Semicolon) {
+                continue;
+            }
             items.push(self.parse_top_level()?);
         }
 
-        Ok(Program { items })
+        Ok(Program {
+            items,
+            inferred_types: Vec::new(),
+            call_graph: Vec::new(),
+            signatures: Vec::new(),
+            symbols: Default::default(),
+            comments: std::mem::take(&mut self.comments),
+        })
+    }
+
+    /// Parse the whole program, recovering from errors at top-level boundaries
+    /// and at statements inside blocks. Returns every item that parsed
+    /// together with all errors found, in source order.
+    pub fn parse_program_recovering(&mut self) -> (Program, Vec<CompilerError>) {
+        let mut items = Vec::new();
+        self.recovering = true;
+
+        while !self.is_at_end() {
+            if self.match_token(&Token::Semicolon) {
+                continue;
+            }
+            let start = self.current;
+            match self.parse_top_level() {
+                Ok(item) => items.push(item),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize_top_level(start);
+                }
+            }
+        }
+        self.recovering = false;
+
+        (
+            Program {
+                items,
+                inferred_types: Vec::new(),
+                call_graph: Vec::new(),
+                signatures: Vec::new(),
+                symbols: Default::default(),
+                comments: std::mem::take(&mut self.comments),
+            },
+            std::mem::take(&mut self.errors),
+        )
+    }
+
+    /// Skip tokens until the next one that begins a line at column 1, which is
+    /// where top-level declarations start in formatted Liva code. Closing
+    /// delimiters at column 1 belong to the broken declaration and are skipped.
+    fn synchronize_top_level(&mut self, start: usize) {
+        if self.current <= start {
+            self.current = start + 1;
+        }
+        while !self.is_at_end() {
+            let (_, col) = self.tokens[self.current].line_col(&self.source_map);
+            let is_closing = matches!(
+                self.tokens[self.current].token,
+                Token::RBrace | Token::RParen | Token::RBracket
+            );
+            if col == 1 && !is_closing {
+                break;
+            }
+            self.current += 1;
+        }
+    }
+
+    /// Skip the rest of a statement that failed to parse, which began at
+    /// token `start`: up to the next token that begins a line no further
+    /// indented than the statement, or the `}` closing the block, outside
+    /// any brackets opened in between
+    fn synchronize_statement(&mut self, start: usize) {
+        let start_col = self.calculate_line_col(start).1;
+        if self.current <= start {
+            self.current = start + 1;
+        }
+        let mut open = 0usize;
+        let mut previous_line = self.calculate_line_col(self.current - 1).0;
+        while !self.is_at_end() {
+            let (line, col) = self.calculate_line_col(self.current);
+            match self.tokens[self.current].token {
+                Token::LBrace | Token::LParen | Token::LBracket => open += 1,
+                Token::RBrace if open == 0 => break,
+                Token::RBrace | Token::RParen | Token::RBracket => open = open.saturating_sub(1),
+                _ if open == 0 && line > previous_line && col <= start_col => break,
+                _ => {}
+            }
+            previous_line = line;
+            self.current += 1;
+        }
     }
 
     fn parse_top_level(&mut self) -> Result<TopLevel> {
+        let start = self.current_span();
+        let mut item = self.parse_top_level_item()?;
+        let span = self.span_since(start);
+        match &mut item {
+            TopLevel::Function(func) => func.span = span,
+            TopLevel::Class(class) => class.span = span,
+            TopLevel::Type(type_decl) => type_decl.span = span,
+            TopLevel::Test(test) => test.span = span,
+            TopLevel::Init(init) => init.span = span,
+            TopLevel::TestHook(hook) => hook.span = span,
+            _ => {}
+        }
+        let doc = match &mut item {
+            TopLevel::Function(func) => Some(&mut func.doc),
+            TopLevel::Class(class) => Some(&mut class.doc),
+            TopLevel::Type(type_decl) => Some(&mut type_decl.doc),
+            TopLevel::TypeAlias(alias) => Some(&mut alias.doc),
+            TopLevel::Enum(enum_decl) => Some(&mut enum_decl.doc),
+            TopLevel::ConstDecl(const_decl) => Some(&mut const_decl.doc),
+            _ => None,
+        };
+        // An annotated function gets its doc in the outer call, above the `@`
+        if let Some(doc) = doc.filter(|doc| doc.is_none()) {
+            *doc = self.doc_comment_before(start);
+        }
+        Ok(item)
+    }
+
+    fn parse_top_level_item(&mut self) -> Result<TopLevel> {
+        // Function annotations: `@inline` / `@noinline` / `@checked`
+        if self.match_token(&Token::At) {
+            let annotation = self.parse_identifier()?;
+            let hint = match annotation.as_str() {
+                "inline" => Some(InlineHint::Always),
+                "noinline" => Some(InlineHint::Never),
+                "checked" => None,
+                other => {
+                    return Err(self.error_with_help(
+                        format!("Unknown annotation '@{}'", other),
+                        Some("Supported annotations: @inline, @noinline, @checked".to_string()),
+                    ))
+                }
+            };
+            return match self.parse_top_level()? {
+                TopLevel::Function(mut func) => {
+                    match hint {
+                        Some(_) if func.inline.is_some() => {
+                            return Err(self.error(format!(
+                                "Function '{}' has more than one inline annotation",
+                                func.name
+                            )));
+                        }
+                        Some(hint) => func.inline = Some(hint),
+                        None if func.requires.is_empty() && func.ensures.is_empty() => {
+                            return Err(self.error_with_help(
+                                format!(
+                                    "'@checked' on function '{}', which has no contracts",
+                                    func.name
+                                ),
+                                Some(
+                                    "Add a 'requires' or 'ensures' clause, or remove '@checked'"
+                                        .to_string(),
+                                ),
+                            ));
+                        }
+                        None => func.checked = true,
+                    }
+                    Ok(TopLevel::Function(func))
+                }
+                _ => Err(self.error(format!(
+                    "'@{}' can only be applied to a top-level function",
+                    annotation
+                ))),
+            };
+        }
+
         if self.match_token(&Token::Import) {
-            return self.parse_import_decl();
+            return self.parse_import_decl(false);
+        }
+
+        // `export` is contextual: only a re-export when followed by `{` or `*`
+        if self.check_ident("export")
+            && (self.peek_next_is(&Token::LBrace) || self.peek_next_is(&Token::Star))
+        {
+            self.advance(); // consume "export"
+            return self.parse_import_decl(true);
+        }
+
+        // `unsafe rust fn name(params): T { raw Rust }`
+        if self.check_ident("unsafe") && self.peek_next_is(&Token::Rust) {
+            self.advance(); // consume "unsafe"
+            self.advance(); // consume "rust"
+            if !self.check_ident("fn") {
+                return Err(self.error("Expected 'fn' after 'unsafe rust'".to_string()));
+            }
+            self.advance(); // consume "fn"
+            return Ok(TopLevel::Function(self.parse_unsafe_rust_fn()?));
+        }
+        if self.check(&Token::Rust) && self.peek_next_is(&Token::Ident("fn".to_string())) {
+            return Err(self.error_with_help(
+                "A function with a Rust body must be declared 'unsafe rust fn'".to_string(),
+                Some(
+                    "Write 'unsafe rust fn name(...)', or use a 'rust { ... }' block inside a \
+                     Liva function"
+                        .to_string(),
+                ),
+            ));
         }
 
         if self.match_token(&Token::Use) {
@@ -404,6 +779,7 @@ impl Parser {
                     name,
                     type_params,
                     target_type,
+                    doc: None,
                     span,
                 }));
             } else {
@@ -415,6 +791,8 @@ impl Parser {
                     name,
                     type_params,
                     members,
+                    doc: None,
+                    span: None,
                 }));
             }
         }
@@ -490,11 +868,20 @@ impl Parser {
             self.expect(Token::LBrace)?;
             let body = self.parse_block_stmt()?;
             self.expect(Token::RBrace)?;
-            return Ok(TopLevel::Test(TestDecl { name, body }));
+            return Ok(TopLevel::Test(TestDecl {
+                name,
+                body,
+                span: None,
+            }));
         }
 
-        // Top-level const declaration
-        if self.match_token(&Token::Const) {
+        // Top-level const declaration, or `lazy NAME = expr` evaluated on first use
+        let is_lazy =
+            self.check_ident("lazy") && matches!(self.peek_token(1), Some(Token::Ident(_)));
+        if is_lazy {
+            self.advance(); // consume "lazy"
+        }
+        if is_lazy || self.match_token(&Token::Const) {
             let name = self.parse_identifier()?;
             let span = self.previous_span();
             let type_ref = if self.match_token(&Token::Colon) {
@@ -509,10 +896,47 @@ impl Parser {
                 name,
                 type_ref,
                 init: value,
+                is_lazy,
+                doc: None,
                 span,
             }));
         }
 
+        // Program-level runtime configuration: runtime { threads: 8 }
+        if self.check_ident("runtime") && self.peek_next_is(&Token::LBrace) {
+            self.advance(); // consume "runtime"
+            return self.parse_runtime_decl();
+        }
+
+        // Module initialization: init { ... }
+        if self.check_ident("init") && self.peek_next_is(&Token::LBrace) {
+            self.advance(); // consume "init"
+            self.expect(Token::LBrace)?;
+            let body = self.parse_block_stmt()?;
+            self.expect(Token::RBrace)?;
+            return Ok(TopLevel::Init(InitDecl { body, span: None }));
+        }
+
+        // Test setup and teardown: before { ... } / after { ... }
+        let hook_kind = if self.check_ident("before") {
+            Some(TestHookKind::Before)
+        } else if self.check_ident("after") {
+            Some(TestHookKind::After)
+        } else {
+            None
+        };
+        if let Some(kind) = hook_kind.filter(|_| self.peek_next_is(&Token::LBrace)) {
+            self.advance(); // consume "before" / "after"
+            self.expect(Token::LBrace)?;
+            let body = self.parse_block_stmt()?;
+            self.expect(Token::RBrace)?;
+            return Ok(TopLevel::TestHook(TestHookDecl {
+                kind,
+                body,
+                span: None,
+            }));
+        }
+
         // Check if we have any tokens left to parse
         if self.is_at_end() {
             return Err(self.error("Unexpected end of file".into()));
@@ -550,6 +974,13 @@ impl Parser {
                 )));
             }
         }
+        // Abstract class: abstract Name { ... }
+        let is_abstract =
+            self.check_ident("abstract") && matches!(self.peek_token(1), Some(Token::Ident(_)));
+        if is_abstract {
+            self.advance(); // consume "abstract"
+        }
+
         // Data class detection: no longer uses a `data` keyword.
         // Classes with fields but no explicit constructor are automatically
         // treated as data classes (auto-derive constructor, PartialEq, Display).
@@ -578,7 +1009,7 @@ impl Parser {
 
         if self.match_token(&Token::LBrace) {
             // It's a class
-            let members = self.parse_members()?;
+            let (members, delegates) = self.parse_class_body()?;
             self.expect(Token::RBrace)?;
             return Ok(TopLevel::Class(ClassDecl {
                 name,
@@ -587,12 +1018,35 @@ impl Parser {
                 members,
                 needs_serde: false, // Will be set by semantic analyzer if used with JSON.parse
                 is_data: false,     // Auto-detected in codegen based on structure
+                is_abstract,
+                delegates,
+                doc: None,
+                span: None,
             }));
         }
 
+        if is_abstract {
+            return Err(self.error_with_help(
+                format!("Function '{}' can't be abstract", name),
+                Some("Only classes and their methods can be abstract".to_string()),
+            ));
+        }
+
         // Otherwise it's a function
         // type_params already parsed above
+        Ok(TopLevel::Function(
+            self.parse_function_decl(name, type_params)?,
+        ))
+    }
 
+    /// Parse the rest of a function declaration after its name and type
+    /// parameters: `(params)`, an optional return type, and a `=> expr` or
+    /// block body
+    fn parse_function_decl(
+        &mut self,
+        name: String,
+        type_params: Vec<TypeParameter>,
+    ) -> Result<FunctionDecl> {
         self.expect(Token::LParen)?;
         let params = self.parse_params()?;
         self.expect(Token::RParen)?;
@@ -602,6 +1056,7 @@ impl Parser {
         } else {
             None
         };
+        let (requires, ensures) = self.parse_contracts()?;
 
         if self.check(&Token::Arrow) || self.check(&Token::Assign) {
             self.advance();
@@ -609,7 +1064,7 @@ impl Parser {
             let body = self.parse_expression()?;
             let body_opt = Some(body);
             let contains_fail = self.function_body_contains_fail(&None, &body_opt);
-            return Ok(TopLevel::Function(FunctionDecl {
+            return Ok(FunctionDecl {
                 name,
                 type_params,
                 params,
@@ -618,7 +1073,15 @@ impl Parser {
                 expr_body: body_opt,
                 is_async_inferred: false,
                 contains_fail,
-            }));
+                inline: None,
+                requires,
+                ensures,
+                checked: false,
+                unsafe_rust: false,
+                captures: Vec::new(),
+                doc: None,
+                span: None,
+            });
         }
 
         // Block function
@@ -626,8 +1089,8 @@ impl Parser {
         let body = self.parse_block_stmt()?;
         self.expect(Token::RBrace)?;
 
-        Ok(TopLevel::Function(FunctionDecl {
-            name: name.clone(),
+        Ok(FunctionDecl {
+            name,
             type_params,
             params,
             return_type,
@@ -635,18 +1098,146 @@ impl Parser {
             expr_body: None,
             is_async_inferred: false,
             contains_fail: self.function_body_contains_fail(&Some(body), &None),
-        }))
+            inline: None,
+            requires,
+            ensures,
+            checked: false,
+            unsafe_rust: false,
+            captures: Vec::new(),
+            doc: None,
+            span: None,
+        })
+    }
+
+    /// Parse the rest of `unsafe rust fn` after `fn`: the name, typed
+    /// parameters, an optional return type, and the body the lexer kept as
+    /// raw Rust
+    fn parse_unsafe_rust_fn(&mut self) -> Result<FunctionDecl> {
+        let name = self.parse_identifier()?;
+        self.expect(Token::LParen)?;
+        let params = self.parse_params()?;
+        self.expect(Token::RParen)?;
+
+        let return_type = if self.match_token(&Token::Colon) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let code = match self.peek() {
+            Some(Token::RustBlock(code)) => code.clone(),
+            _ => {
+                return Err(self.error(format!(
+                    "Expected the Rust body of 'unsafe rust fn {}'",
+                    name
+                )))
+            }
+        };
+        self.advance();
+        // A `T!` body is a Rust `Result`, whose errors callers bind like `fail`s
+        let contains_fail = matches!(return_type, Some(TypeRef::Fallible(_)));
+
+        Ok(FunctionDecl {
+            name,
+            type_params: Vec::new(),
+            params,
+            return_type,
+            body: None,
+            expr_body: Some(Expr::RustBlock { code }),
+            is_async_inferred: false,
+            contains_fail,
+            inline: None,
+            requires: Vec::new(),
+            ensures: Vec::new(),
+            checked: false,
+            unsafe_rust: true,
+            captures: Vec::new(),
+            doc: None,
+            span: None,
+        })
+    }
+
+    /// `requires cond` / `ensures cond` clauses between a function's
+    /// signature and its body, in any order. Neither word is reserved.
+    fn parse_contracts(&mut self) -> Result<(Vec<Contract>, Vec<Contract>)> {
+        let mut requires = Vec::new();
+        let mut ensures = Vec::new();
+        loop {
+            let clauses = if self.check_ident("requires") {
+                &mut requires
+            } else if self.check_ident("ensures") {
+                &mut ensures
+            } else {
+                break;
+            };
+            self.advance();
+            let line = self.current_line();
+            let start = self.current_span();
+            // `=>` after the condition starts the body, not a lambda
+            let condition = self.parse_expression_no_lambda()?;
+            let source = self
+                .span_since(start)
+                .map(|span| span.snippet(&self.source).to_string())
+                .unwrap_or_default();
+            clauses.push(Contract {
+                condition,
+                source,
+                line,
+            });
+        }
+        Ok((requires, ensures))
+    }
+
+    /// Whether the statement at the current token declares a nested function:
+    /// `name(...)` followed by a return type, `=>`, or a `{` on the same line.
+    /// A call statement never continues with any of those.
+    fn is_nested_function_start(&self) -> bool {
+        if !matches!(self.peek(), Some(Token::Ident(_))) || !self.peek_next_is(&Token::LParen) {
+            return false;
+        }
+        let mut depth = 0usize;
+        let mut close = self.current + 1;
+        while let Some(tok) = self.tokens.get(close) {
+            match tok.token {
+                Token::LParen => depth += 1,
+                Token::RParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            close += 1;
+        }
+        let (Some(close), Some(next)) = (self.tokens.get(close), self.tokens.get(close + 1)) else {
+            return false;
+        };
+        match &next.token {
+            Token::Colon | Token::Arrow => true,
+            Token::Ident(word) if word == "requires" || word == "ensures" => true,
+            Token::LBrace => {
+                close.line_col(&self.source_map).0 == next.line_col(&self.source_map).0
+            }
+            _ => false,
+        }
     }
 
     /// Parse import declaration
     /// Supports:
     /// - Named imports: `import { add, multiply } from "./math.liva"`
-    /// - Wildcard imports: `import * as math from "./math.liva"`
-    fn parse_import_decl(&mut self) -> Result<TopLevel> {
-        // Check for wildcard import: import * as alias from "path"
+    /// - Aliased imports: `import { parse as parseCfg } from "./config"`
+    /// - Wildcard imports: `import * as math from "./math.liva"` or `import * from "./math.liva"`
+    ///
+    /// With `is_reexport` the same forms are parsed after `export`
+    /// (`export { parse } from "./config"`, `export * from "./config"`).
+    fn parse_import_decl(&mut self, is_reexport: bool) -> Result<TopLevel> {
+        // Wildcard import: import * [as alias] from "path"
         if self.match_token(&Token::Star) {
-            self.expect(Token::As)?;
-            let alias = self.parse_identifier()?;
+            let alias = if self.match_token(&Token::As) {
+                Some(self.parse_identifier()?)
+            } else {
+                None
+            };
             self.expect(Token::From)?;
             let source = self.parse_string_literal()?;
 
@@ -654,26 +1245,31 @@ impl Parser {
                 imports: vec![],
                 source,
                 is_wildcard: true,
-                alias: Some(alias),
+                alias,
+                renames: std::collections::HashMap::new(),
+                is_reexport,
             }));
         }
 
-        // Named imports: import { name1, name2, ... } from "path"
+        // Named imports: import { name1, name2 as alias2, ... } from "path"
         self.expect(Token::LBrace)?;
 
         let mut imports = Vec::new();
+        let mut renames = std::collections::HashMap::new();
 
-        // Parse first import
-        if !self.check(&Token::RBrace) {
-            imports.push(self.parse_identifier()?);
-
-            // Parse remaining imports
-            while self.match_token(&Token::Comma) {
-                // Allow trailing comma
-                if self.check(&Token::RBrace) {
-                    break;
+        while !self.check(&Token::RBrace) {
+            let name = self.parse_identifier()?;
+            if self.match_token(&Token::As) {
+                let local = self.parse_identifier()?;
+                if local != name {
+                    renames.insert(name.clone(), local);
                 }
-                imports.push(self.parse_identifier()?);
+            }
+            imports.push(name);
+
+            // Allow trailing comma
+            if !self.match_token(&Token::Comma) {
+                break;
             }
         }
 
@@ -686,6 +1282,8 @@ impl Parser {
             source,
             is_wildcard: false,
             alias: None,
+            renames,
+            is_reexport,
         }))
     }
 
@@ -715,19 +1313,21 @@ impl Parser {
     fn stmt_contains_fail(&self, stmt: &Stmt) -> bool {
         match stmt {
             Stmt::Fail(_) => true,
-            Stmt::VarDecl(var) => var.or_fail_msg.is_some() || self.expr_contains_fail(&var.init),
+            // `.expect()` fills `or_fail_msg` too, but panics instead of failing
+            Stmt::VarDecl(var) => {
+                (var.or_fail_msg.is_some() && var.combinator != Some(ResultCombinator::Expect))
+                    || self.expr_contains_fail(&var.init)
+            }
             Stmt::Assign(assign) => self.expr_contains_fail(&assign.value),
             Stmt::Return(ret) => ret
                 .expr
                 .as_ref()
                 .map_or(false, |e| self.expr_contains_fail(e)),
             Stmt::If(if_stmt) => {
-                self.expr_contains_fail(&if_stmt.condition)
-                    || self.if_body_contains_fail(&if_stmt.then_branch)
-                    || if_stmt
-                        .else_branch
-                        .as_ref()
-                        .map_or(false, |b| self.if_body_contains_fail(b))
+                if_stmt
+                    .branches()
+                    .any(|(condition, _)| self.expr_contains_fail(condition))
+                    || if_stmt.bodies().any(|b| self.if_body_contains_fail(b))
             }
             Stmt::While(while_stmt) => {
                 self.expr_contains_fail(&while_stmt.condition)
@@ -751,6 +1351,7 @@ impl Parser {
             }
             Stmt::Throw(throw) => self.expr_contains_fail(&throw.expr),
             Stmt::Expr(expr_stmt) => self.expr_contains_fail(&expr_stmt.expr),
+            Stmt::Scope(scope) => self.block_contains_fail(&scope.body),
             _ => false,
         }
     }
@@ -839,7 +1440,7 @@ impl Parser {
                 TypeParameter::with_constraints(param_name, constraints)
             });
 
-            if !self.match_token(&Token::Comma) {
+            if !self.match_token(&Token::Comma) || self.check(&Token::Gt) {
                 break;
             }
         }
@@ -903,11 +1504,26 @@ impl Parser {
             name,
             type_params,
             variants,
+            doc: None,
         }))
     }
 
+    /// Members of an interface or class extension, which can't delegate
     fn parse_members(&mut self) -> Result<Vec<Member>> {
+        let (members, delegates) = self.parse_class_body()?;
+        if let Some(delegate) = delegates.first() {
+            return Err(self.error(format!(
+                "'delegate {}' is only allowed in a class declaration",
+                delegate.field
+            )));
+        }
+        Ok(members)
+    }
+
+    /// Members of a class, and its `delegate` lines
+    fn parse_class_body(&mut self) -> Result<(Vec<Member>, Vec<DelegateDecl>)> {
         let mut members = Vec::new();
+        let mut delegates = Vec::new();
 
         while !self.is_at_end() && !self.check(&Token::RBrace) {
             if let Some(token) = self.peek() {
@@ -918,11 +1534,33 @@ impl Parser {
                     )));
                 }
             }
+            // `delegate field` or `delegate field { a, b }`; contextual, like `abstract`
+            if self.check_ident("delegate") && matches!(self.peek_token(1), Some(Token::Ident(_))) {
+                self.advance(); // consume "delegate"
+                delegates.push(self.parse_delegate()?);
+                continue;
+            }
+            let start = self.current_span();
+            let doc = self.doc_comment_before(start);
+            // `abstract` is contextual: only a modifier when a name follows
+            let is_abstract =
+                self.check_ident("abstract") && matches!(self.peek_token(1), Some(Token::Ident(_)));
+            if is_abstract {
+                self.advance(); // consume "abstract"
+            }
             let name = self.parse_identifier()?;
             let visibility = Visibility::from_name(&name);
 
+            let is_method = self.peek() == Some(&Token::Lt) || self.peek() == Some(&Token::LParen);
+            if is_abstract && !is_method {
+                return Err(self.error_with_help(
+                    format!("Field '{}' can't be abstract", name),
+                    Some("Only methods can be abstract: abstract area(): number".to_string()),
+                ));
+            }
+
             // Check if it's a method (has parentheses or type parameters)
-            if self.peek() == Some(&Token::Lt) || self.peek() == Some(&Token::LParen) {
+            if is_method {
                 let type_params = if self.check(&Token::Lt) {
                     // Parse type parameters first
                     self.advance(); // consume '<'
@@ -941,6 +1579,21 @@ impl Parser {
                     None
                 };
 
+                if is_abstract
+                    && (self.check(&Token::Arrow)
+                        || self.check(&Token::Assign)
+                        || self.check(&Token::LBrace))
+                {
+                    return Err(self.error_with_help(
+                        format!("Abstract method '{}' can't have a body", name),
+                        Some(
+                            "Declare only the signature, and implement it in each class \
+                             implementing the abstract class"
+                                .to_string(),
+                        ),
+                    ));
+                }
+
                 let expr_body = if self.check(&Token::Arrow) || self.check(&Token::Assign) {
                     self.advance();
                     Some(self.parse_expression()?)
@@ -960,10 +1613,15 @@ impl Parser {
                             stmts: vec![Stmt::Return(ReturnStmt {
                                 expr: Some(body.clone()),
                             })],
+                            stmt_lines: Vec::new(),
+                            span: None,
                         }),
                         expr_body: Some(body.clone()),
                         is_async_inferred: false,
                         contains_fail: self.function_body_contains_fail(&None, &Some(body)),
+                        is_abstract: false,
+                        doc,
+                        span: self.span_since(start),
                     }));
 
                     // Consume optional semicolon for one-liner methods
@@ -983,12 +1641,15 @@ impl Parser {
                         expr_body: None,
                         is_async_inferred: false,
                         contains_fail: self.function_body_contains_fail(&Some(body), &None),
+                        is_abstract: false,
+                        doc,
+                        span: self.span_since(start),
                     }));
 
                     // Consume optional semicolon for block methods
                     self.match_token(&Token::Semicolon);
                 } else {
-                    // Interface method signature (no body)
+                    // Interface or abstract method signature (no body)
                     members.push(Member::Method(MethodDecl {
                         name,
                         visibility,
@@ -999,6 +1660,9 @@ impl Parser {
                         expr_body: None,
                         is_async_inferred: false,
                         contains_fail: false,
+                        is_abstract,
+                        doc,
+                        span: self.span_since(start),
                     }));
 
                     // Consume optional semicolon for interface method signatures
@@ -1027,6 +1691,7 @@ impl Parser {
                     type_ref,
                     init,
                     is_optional,
+                    doc,
                 }));
 
                 // Consume optional semicolon
@@ -1034,7 +1699,34 @@ impl Parser {
             }
         }
 
-        Ok(members)
+        Ok((members, delegates))
+    }
+
+    /// The rest of `delegate field` or `delegate field { a, b }`
+    fn parse_delegate(&mut self) -> Result<DelegateDecl> {
+        let field = self.parse_identifier()?;
+        let mut methods = Vec::new();
+        if self.match_token(&Token::LBrace) {
+            while !self.check(&Token::RBrace) {
+                methods.push(self.parse_identifier()?);
+                if !self.match_token(&Token::Comma) {
+                    self.expect_list_end(&Token::RBrace, "delegated methods")?;
+                    break;
+                }
+            }
+            self.expect(Token::RBrace)?;
+            if methods.is_empty() {
+                return Err(self.error_with_help(
+                    format!("'delegate {}' lists no methods", field),
+                    Some(format!(
+                        "Drop the braces to forward every method: delegate {}",
+                        field
+                    )),
+                ));
+            }
+        }
+        self.match_token(&Token::Semicolon);
+        Ok(DelegateDecl { field, methods })
     }
 
     fn parse_params(&mut self) -> Result<Vec<Param>> {
@@ -1068,14 +1760,22 @@ impl Parser {
             });
 
             if !self.match_token(&Token::Comma) {
+                self.expect_list_end(&Token::RParen, "parameters")?;
                 break;
             }
+            if self.check(&Token::RParen) {
+                break; // Trailing comma
+            }
         }
 
         Ok(params)
     }
 
     fn parse_type(&mut self) -> Result<TypeRef> {
+        self.nested(Self::parse_type_union)
+    }
+
+    fn parse_type_union(&mut self) -> Result<TypeRef> {
         // Parse the base type (which could be tuple, array, or simple type)
         let base_type = self.parse_base_type()?;
 
@@ -1243,7 +1943,7 @@ impl Parser {
 
             loop {
                 args.push(self.parse_type()?);
-                if !self.match_token(&Token::Comma) {
+                if !self.match_token(&Token::Comma) || self.check(&Token::Gt) {
                     break;
                 }
             }
@@ -1282,13 +1982,42 @@ impl Parser {
     }
 
     fn parse_block_stmt(&mut self) -> Result<BlockStmt> {
+        // The caller consumed `{` and consumes the `}` we stop at
+        let open = self.previous_span();
         let mut stmts = Vec::new();
+        let mut stmt_lines = Vec::new();
 
         while !self.is_at_end() && !self.check(&Token::RBrace) {
-            stmts.push(self.parse_statement()?);
+            // `;` may end any statement; one with nothing before it is skipped
+            if self.match_token(&Token::Semicolon) {
+                continue;
+            }
+            let start = self.current;
+            match self.parse_statement() {
+                Ok(stmt) => {
+                    stmt_lines.push(self.calculate_line_col(start).0 as u32);
+                    stmts.push(stmt);
+                }
+                Err(err) if self.recovering && !err.is_cancellation() => {
+                    self.errors.push(err);
+                    self.synchronize_statement(start);
+                }
+                Err(err) => return Err(err),
+            }
         }
 
-        Ok(BlockStmt { stmts })
+        let span = match (open, self.current_span()) {
+            (Some(open), Some(close)) if self.check(&Token::RBrace) => {
+                Some(crate::span::Span::new(open.start, close.end))
+            }
+            _ => None,
+        };
+
+        Ok(BlockStmt {
+            stmts,
+            stmt_lines,
+            span,
+        })
     }
 
     fn parse_let_bindings(&mut self) -> Result<Vec<VarBinding>> {
@@ -1480,6 +2209,7 @@ impl Parser {
             let value = if self.is_at_end()
                 || self.check(&Token::Semicolon)
                 || self.check(&Token::RBrace)
+                || self.at_line_start()
             {
                 None
             } else {
@@ -1510,6 +2240,7 @@ impl Parser {
             }))
         } else {
             // Parse assignment statement: target = value  or  target += value
+            let start = self.current_span();
             let target = self.parse_expression()?;
             if self.match_token(&Token::Assign) {
                 let value = self.parse_expression()?;
@@ -1517,6 +2248,7 @@ impl Parser {
                     target,
                     value,
                     op: None,
+                    span: self.span_since(start),
                 }))
             } else if let Some(bin_op) = self.match_compound_assign() {
                 let rhs = self.parse_expression()?;
@@ -1529,6 +2261,7 @@ impl Parser {
                     target,
                     value,
                     op: Some(bin_op),
+                    span: self.span_since(start),
                 }))
             } else {
                 // Expression statement
@@ -1551,6 +2284,7 @@ impl Parser {
             Ok(Stmt::Block(block))
         } else {
             // B108 fix: Parse as expression, then check for assignment/compound-assignment
+            let start = self.current_span();
             let expr = self.parse_expression()?;
             if self.match_token(&Token::Assign) {
                 let value = self.parse_expression()?;
@@ -1558,6 +2292,7 @@ impl Parser {
                     target: expr,
                     value,
                     op: None,
+                    span: self.span_since(start),
                 }))
             } else if let Some(bin_op) = self.match_compound_assign() {
                 let rhs = self.parse_expression()?;
@@ -1570,6 +2305,7 @@ impl Parser {
                     target: expr,
                     value,
                     op: Some(bin_op),
+                    span: self.span_since(start),
                 }))
             } else {
                 Ok(Stmt::Expr(ExprStmt { expr }))
@@ -1578,7 +2314,12 @@ impl Parser {
     }
 
     fn parse_statement(&mut self) -> Result<Stmt> {
+        self.nested(Self::parse_statement_kind)
+    }
+
+    fn parse_statement_kind(&mut self) -> Result<Stmt> {
         if self.match_token(&Token::Let) {
+            let start = self.previous_span();
             let bindings = self.parse_let_bindings()?;
             self.expect(Token::Assign)?;
             let init = self.parse_expression()?;
@@ -1624,14 +2365,18 @@ impl Parser {
             // Post-process: if init is `Call/MethodCall or <value>` (parsed as BinaryOp),
             // split it into init=call, or_value=value. This is the `or <value>` syntax
             // for providing a default when a fallible function fails.
-            // e.g., `let port = parsePort("abc") or 3000`
+            // e.g., `let port = parsePort("abc") or 3000`, `let n = text as int or 0`
             let init = if or_fail_msg.is_none() {
                 match init {
                     Expr::Binary {
                         op: BinOp::Or,
                         left,
                         right,
-                    } if matches!(*left, Expr::Call(_) | Expr::MethodCall(_)) => {
+                    } if matches!(
+                        *left,
+                        Expr::Call(_) | Expr::MethodCall(_) | Expr::Cast { .. }
+                    ) =>
+                    {
                         or_value = Some(right);
                         *left
                     }
@@ -1641,6 +2386,36 @@ impl Parser {
                 init
             };
 
+            // Result combinators: `.orElse(v)` and `.expect("msg")` on a fallible
+            // call fill the same fields as `or v` / `or fail "msg"`, and
+            // `.mapErr(fn)` rewrites the error of an error binding.
+            // e.g., `let port = parsePort(text).orElse(3000)`
+            let mut combinator = None;
+            let init = if or_fail_msg.is_none() && or_value.is_none() {
+                let (init, split) = Self::split_result_combinator(init);
+                if let Some((method, arg)) = split {
+                    let arg = Box::new(arg);
+                    combinator = Some(match method.as_str() {
+                        "orElse" => {
+                            or_value = Some(arg);
+                            ResultCombinator::OrElse
+                        }
+                        "expect" => {
+                            or_fail_msg = Some(arg);
+                            or_fail_line = start
+                                .map(|s| s.start_position(&self.source_map).0 as u32)
+                                .unwrap_or(0);
+                            ResultCombinator::Expect
+                        }
+                        _ => ResultCombinator::MapErr(arg),
+                    });
+                }
+                init
+            } else {
+                init
+            };
+
+            let span = self.span_since(start);
             self.match_token(&Token::Semicolon); // Optional semicolon
 
             let is_fallible = bindings.len() > 1 || or_fail_msg.is_some() || or_value.is_some();
@@ -1652,6 +2427,8 @@ impl Parser {
                 or_fail_msg,
                 or_value,
                 or_fail_line,
+                combinator,
+                span,
             }));
         }
 
@@ -1670,6 +2447,8 @@ impl Parser {
                 name,
                 type_ref,
                 init: value,
+                is_lazy: false,
+                doc: None,
                 span,
             }));
         }
@@ -1678,6 +2457,7 @@ impl Parser {
             let value = if self.is_at_end()
                 || self.check(&Token::Semicolon)
                 || self.check(&Token::RBrace)
+                || self.at_line_start()
             {
                 None
             } else {
@@ -1718,7 +2498,22 @@ impl Parser {
             }));
         }
 
-        if self.match_token(&Token::Try) {
+        // `scope { ... }` — `scope` is only a keyword in front of a block
+        if self.check_ident("scope") && self.peek_next_is(&Token::LBrace) {
+            self.advance(); // consume `scope`
+            self.advance(); // consume `{`
+            let body = self.parse_block_stmt()?;
+            self.expect(Token::RBrace)?;
+            return Ok(Stmt::Scope(ScopeStmt { body }));
+        }
+
+        // `mock HTTP.get returns ...` — `mock` is only a keyword in front of a name
+        if self.check_ident("mock") && matches!(self.peek_token(1), Some(Token::Ident(_))) {
+            self.advance(); // consume `mock`
+            return self.parse_mock();
+        }
+
+        if self.match_token(&Token::Try) {
             self.expect(Token::LBrace)?;
             let try_block = self.parse_block_stmt()?;
             self.expect(Token::RBrace)?;
@@ -1737,54 +2532,27 @@ impl Parser {
         }
 
         if self.match_token(&Token::If) {
-            // Parse condition - paréntesis opcionales
-            // Use parse_expression_no_lambda to avoid `ident =>` being parsed as a lambda
-            // (same as while/for). The `=>` here means one-liner body, not lambda arrow.
-            let condition = if self.match_token(&Token::LParen) {
-                let cond = self.parse_expression()?;
-                self.expect(Token::RParen)?;
-                cond
-            } else {
-                self.parse_expression_no_lambda()?
-            };
-
-            // Check if it's a simple statement (like if cond fail "msg") or => one-liner
-            let then_branch = if self.check(&Token::LBrace) {
-                self.expect(Token::LBrace)?;
-                let block = self.parse_block_stmt()?;
-                self.expect(Token::RBrace)?;
-                IfBody::Block(block)
-            } else {
-                // Consume optional => for one-liner syntax: if cond => expr
-                self.match_token(&Token::Arrow);
-                let stmt = self.parse_simple_statement()?;
-                IfBody::Stmt(Box::new(stmt))
-            };
-
-            let else_branch = if self.match_token(&Token::Else) {
-                if self.check(&Token::If) {
-                    // This is an else-if, parse it recursively as a nested if statement
-                    let else_if_stmt = self.parse_statement()?;
-                    Some(IfBody::Stmt(Box::new(else_if_stmt)))
-                } else if self.check(&Token::LBrace) {
-                    // This is a regular else block
-                    self.expect(Token::LBrace)?;
-                    let block = self.parse_block_stmt()?;
-                    self.expect(Token::RBrace)?;
-                    Some(IfBody::Block(block))
+            let condition = self.parse_if_condition()?;
+            let then_branch = self.parse_if_body()?;
+
+            // `else if` links are collected in a loop, keeping the chain flat
+            let mut else_ifs = Vec::new();
+            let mut else_branch = None;
+            while self.match_token(&Token::Else) {
+                if self.match_token(&Token::If) {
+                    let condition = self.parse_if_condition()?;
+                    let body = self.parse_if_body()?;
+                    else_ifs.push(ElseIf { condition, body });
                 } else {
-                    // Consume optional => for one-liner else: else => expr
-                    self.match_token(&Token::Arrow);
-                    let stmt = self.parse_simple_statement()?;
-                    Some(IfBody::Stmt(Box::new(stmt)))
+                    else_branch = Some(self.parse_if_body()?);
+                    break;
                 }
-            } else {
-                None
-            };
+            }
 
             return Ok(Stmt::If(IfStmt {
                 condition,
                 then_branch,
+                else_ifs,
                 else_branch,
             }));
         }
@@ -1793,8 +2561,13 @@ impl Parser {
             let condition = self.parse_expression_no_lambda()?;
             let body = if self.match_token(&Token::Arrow) {
                 // One-liner: while cond => stmt
+                let line = self.current_line();
                 let stmt = self.parse_simple_statement()?;
-                BlockStmt { stmts: vec![stmt] }
+                BlockStmt {
+                    stmts: vec![stmt],
+                    stmt_lines: vec![line],
+                    span: None,
+                }
             } else {
                 self.expect(Token::LBrace)?;
                 let body = self.parse_block_stmt()?;
@@ -1942,13 +2715,20 @@ impl Parser {
                 policy = DataParallelPolicy::ParVec;
             }
 
+            // `for key, value in map`, or `for (i, item) in items.enumerate()`
+            let parenthesized = self.match_token(&Token::LParen);
             let var = self.parse_identifier()?;
-            // Support `for key, value in map` destructuring
             let var2 = if self.match_token(&Token::Comma) {
                 Some(self.parse_identifier()?)
             } else {
                 None
             };
+            if parenthesized {
+                if var2.is_none() {
+                    return Err(self.error("Expected `,` and a second loop variable".to_string()));
+                }
+                self.expect(Token::RParen)?;
+            }
             self.expect(Token::In)?;
             let iterable = self.parse_expression_no_lambda()?;
 
@@ -1960,8 +2740,13 @@ impl Parser {
 
             let body = if self.match_token(&Token::Arrow) {
                 // One-liner: for x in items => stmt
+                let line = self.current_line();
                 let stmt = self.parse_simple_statement()?;
-                BlockStmt { stmts: vec![stmt] }
+                BlockStmt {
+                    stmts: vec![stmt],
+                    stmt_lines: vec![line],
+                    span: None,
+                }
             } else {
                 self.expect(Token::LBrace)?;
                 let body = self.parse_block_stmt()?;
@@ -1978,18 +2763,29 @@ impl Parser {
             return Ok(Stmt::For(stmt));
         }
 
+        if self.is_nested_function_start() {
+            let start = self.current_span();
+            let name = self.parse_identifier()?;
+            let mut func = self.parse_function_decl(name, Vec::new())?;
+            func.span = self.span_since(start);
+            return Ok(Stmt::Function(func));
+        }
+
         // Expression statement
+        let start = self.current_span();
         let expr = self.parse_expression()?;
         if self.match_token(&Token::Assign) {
             if !is_valid_assignment_target(&expr) {
                 return Err(self.error("Invalid assignment target".into()));
             }
             let value = self.parse_expression()?;
+            let span = self.span_since(start);
             self.match_token(&Token::Semicolon);
             return Ok(Stmt::Assign(AssignStmt {
                 target: expr,
                 value,
                 op: None,
+                span,
             }));
         }
         if let Some(bin_op) = self.match_compound_assign() {
@@ -2002,11 +2798,13 @@ impl Parser {
                 left: Box::new(expr.clone()),
                 right: Box::new(rhs),
             };
+            let span = self.span_since(start);
             self.match_token(&Token::Semicolon);
             return Ok(Stmt::Assign(AssignStmt {
                 target: expr,
                 value,
                 op: Some(bin_op),
+                span,
             }));
         }
 
@@ -2046,10 +2844,12 @@ impl Parser {
             params
         } else {
             // Single parameter without parentheses: x => ... or {x, y} => ...
+            let start = self.current_span();
             let pattern = self.parse_param_pattern()?;
             vec![LambdaParam {
                 pattern,
                 type_ref: None,
+                span: self.span_since(start),
             }]
         };
 
@@ -2080,16 +2880,22 @@ impl Parser {
 
         loop {
             // Parse pattern (can be identifier, object destructuring, or array destructuring)
+            let start = self.current_span();
             let pattern = self.parse_param_pattern()?;
+            let span = self.span_since(start);
             let type_ref = if self.match_token(&Token::Colon) {
                 Some(self.parse_type()?)
             } else {
                 None
             };
 
-            params.push(LambdaParam { pattern, type_ref });
+            params.push(LambdaParam {
+                pattern,
+                type_ref,
+                span,
+            });
 
-            if !self.match_token(&Token::Comma) {
+            if !self.match_token(&Token::Comma) || self.check(&Token::RParen) {
                 break;
             }
         }
@@ -2112,7 +2918,7 @@ impl Parser {
         let expr = self.parse_coalesce()?;
 
         if self.match_token(&Token::DotDotEq) {
-            let right = self.parse_assignment()?;
+            let right = self.nested(Self::parse_assignment)?;
             return Ok(Expr::Binary {
                 op: BinOp::RangeInclusive,
                 left: Box::new(expr),
@@ -2121,7 +2927,7 @@ impl Parser {
         }
 
         if self.match_token(&Token::DotDot) {
-            let right = self.parse_assignment()?;
+            let right = self.nested(Self::parse_assignment)?;
             return Ok(Expr::Binary {
                 op: BinOp::Range,
                 left: Box::new(expr),
@@ -2133,7 +2939,7 @@ impl Parser {
             // Ternary operator: condition ? true_expr : false_expr
             let then_expr = self.parse_expression()?;
             self.expect(Token::Colon)?;
-            let else_expr = self.parse_assignment()?; // Right associative
+            let else_expr = self.nested(Self::parse_assignment)?; // Right associative
             return Ok(Expr::Ternary {
                 condition: Box::new(expr),
                 then_expr: Box::new(then_expr),
@@ -2149,7 +2955,7 @@ impl Parser {
     fn parse_coalesce(&mut self) -> Result<Expr> {
         let expr = self.parse_or()?;
         if self.match_token(&Token::QuestionQuestion) {
-            let right = self.parse_coalesce()?;
+            let right = self.nested(Self::parse_coalesce)?;
             return Ok(Expr::Binary {
                 op: BinOp::Coalesce,
                 left: Box::new(expr),
@@ -2172,6 +2978,7 @@ impl Parser {
                 left: Box::new(expr),
                 right: Box::new(right),
             };
+            self.check_depth(chain_length(&expr))?;
         }
 
         Ok(expr)
@@ -2187,6 +2994,7 @@ impl Parser {
                 left: Box::new(expr),
                 right: Box::new(right),
             };
+            self.check_depth(chain_length(&expr))?;
         }
 
         Ok(expr)
@@ -2207,15 +3015,17 @@ impl Parser {
                 left: Box::new(expr),
                 right: Box::new(right),
             };
+            self.check_depth(chain_length(&expr))?;
         }
 
         Ok(expr)
     }
 
     fn parse_comparison(&mut self) -> Result<Expr> {
-        let mut expr = self.parse_term()?;
+        let mut expr = self.parse_bit_or()?;
 
-        while self.match_token(&Token::Gt)
+        // `x >>= 1` is left for the compound assignment
+        while (!self.check_shift_right(&Token::Ge) && self.match_token(&Token::Gt))
             || self.match_token(&Token::Ge)
             || self.match_token(&Token::Lt)
             || self.match_token(&Token::Le)
@@ -2227,12 +3037,89 @@ impl Parser {
                 Some(&Token::Le) => BinOp::Le,
                 _ => unreachable!(),
             };
+            let right = self.parse_bit_or()?;
+            expr = Expr::Binary {
+                op,
+                left: Box::new(expr),
+                right: Box::new(right),
+            };
+            self.check_depth(chain_length(&expr))?;
+        }
+
+        Ok(expr)
+    }
+
+    // Bitwise operators bind as in Rust: tighter than comparisons, so
+    // `flags & MASK == 0` is `(flags & MASK) == 0`; `|` loosest, then `^`,
+    // `&`, and the shifts just below `+`/`-`.
+    fn parse_bit_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_bit_xor()?;
+
+        while self.match_token(&Token::Pipe) {
+            let right = self.parse_bit_xor()?;
+            expr = Expr::Binary {
+                op: BinOp::BitOr,
+                left: Box::new(expr),
+                right: Box::new(right),
+            };
+            self.check_depth(chain_length(&expr))?;
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_bit_xor(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_bit_and()?;
+
+        while self.match_token(&Token::Caret) {
+            let right = self.parse_bit_and()?;
+            expr = Expr::Binary {
+                op: BinOp::BitXor,
+                left: Box::new(expr),
+                right: Box::new(right),
+            };
+            self.check_depth(chain_length(&expr))?;
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_bit_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_shift()?;
+
+        while self.match_token(&Token::Amp) {
+            let right = self.parse_shift()?;
+            expr = Expr::Binary {
+                op: BinOp::BitAnd,
+                left: Box::new(expr),
+                right: Box::new(right),
+            };
+            self.check_depth(chain_length(&expr))?;
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_shift(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_term()?;
+
+        loop {
+            let op = if self.match_token(&Token::Shl) {
+                BinOp::Shl
+            } else if self.check_shift_right(&Token::Gt) {
+                self.advance();
+                self.advance();
+                BinOp::Shr
+            } else {
+                break;
+            };
             let right = self.parse_term()?;
             expr = Expr::Binary {
                 op,
                 left: Box::new(expr),
                 right: Box::new(right),
             };
+            self.check_depth(chain_length(&expr))?;
         }
 
         Ok(expr)
@@ -2253,13 +3140,14 @@ impl Parser {
                 left: Box::new(expr),
                 right: Box::new(right),
             };
+            self.check_depth(chain_length(&expr))?;
         }
 
         Ok(expr)
     }
 
     fn parse_factor(&mut self) -> Result<Expr> {
-        let mut expr = self.parse_unary()?;
+        let mut expr = self.parse_cast()?;
 
         while self.match_token(&Token::Star)
             || self.match_token(&Token::Slash)
@@ -2271,18 +3159,37 @@ impl Parser {
                 Some(&Token::Percent) => BinOp::Mod,
                 _ => unreachable!(),
             };
-            let right = self.parse_unary()?;
+            let right = self.parse_cast()?;
             expr = Expr::Binary {
                 op,
                 left: Box::new(expr),
                 right: Box::new(right),
             };
+            self.check_depth(chain_length(&expr))?;
         }
 
         Ok(expr)
     }
 
+    /// `value as int`: binds tighter than `*`, looser than unary operators
+    fn parse_cast(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.match_token(&Token::As) {
+            let target = self.parse_base_type()?;
+            expr = Expr::Cast {
+                expr: Box::new(expr),
+                target,
+            };
+            self.check_depth(chain_length(&expr))?;
+        }
+        Ok(expr)
+    }
+
     fn parse_unary(&mut self) -> Result<Expr> {
+        self.nested(Self::parse_prefixed)
+    }
+
+    fn parse_prefixed(&mut self) -> Result<Expr> {
         if self.match_token(&Token::Bang) || self.match_token(&Token::Not) {
             let right = self.parse_unary()?;
             return Ok(Expr::Unary {
@@ -2299,6 +3206,14 @@ impl Parser {
             });
         }
 
+        if self.match_token(&Token::Tilde) {
+            let right = self.parse_unary()?;
+            return Ok(Expr::Unary {
+                op: UnOp::BitNot,
+                operand: Box::new(right),
+            });
+        }
+
         if self.match_token(&Token::Await) {
             let operand = self.parse_unary()?;
             return Ok(Expr::Unary {
@@ -2326,7 +3241,71 @@ impl Parser {
             return self.parse_exec_call(policy, "task");
         }
 
-        self.parse_call()
+        self.parse_power()
+    }
+
+    /// `base ** exponent`: binds tighter than unary operators on its left,
+    /// so `-2 ** 2` is `-(2 ** 2)`, and groups to the right, so `2 ** 3 ** 2`
+    /// is `2 ** (3 ** 2)`. The exponent may itself be negated: `2.0 ** -1`.
+    fn parse_power(&mut self) -> Result<Expr> {
+        let base = self.parse_call()?;
+        if !self.match_token(&Token::StarStar) {
+            return Ok(base);
+        }
+        let exponent = self.parse_unary()?;
+        Ok(Expr::Binary {
+            op: BinOp::Pow,
+            left: Box::new(base),
+            right: Box::new(exponent),
+        })
+    }
+
+    /// Splits a trailing `.orElse(v)` / `.expect(msg)` / `.mapErr(fn)` off a
+    /// `let` initializer. After `async`/`par`/`task` the call wraps the whole
+    /// chain, so its execution policy moves to the call that is left.
+    fn split_result_combinator(init: Expr) -> (Expr, Option<(String, Expr)>) {
+        fn is_combinator(mc: &MethodCallExpr) -> bool {
+            mc.args.len() == 1
+                && matches!(mc.method.as_str(), "orElse" | "expect" | "mapErr")
+                && matches!(
+                    *mc.object,
+                    Expr::Call(_) | Expr::MethodCall(_) | Expr::Cast { .. }
+                )
+        }
+
+        match init {
+            Expr::MethodCall(mc) if is_combinator(&mc) => {
+                let MethodCallExpr {
+                    object,
+                    method,
+                    mut args,
+                    ..
+                } = mc;
+                let arg = args.remove(0);
+                (*object, Some((method, arg)))
+            }
+            Expr::Call(call)
+                if call.exec_policy != ExecPolicy::Normal
+                    && call.args.is_empty()
+                    && matches!(call.callee.as_ref(), Expr::MethodCall(mc) if is_combinator(mc)) =>
+            {
+                let (object, split) = Self::split_result_combinator(*call.callee);
+                let object = match object {
+                    Expr::Call(mut inner) => {
+                        inner.exec_policy = call.exec_policy;
+                        Expr::Call(inner)
+                    }
+                    other => Expr::Call(CallExpr {
+                        callee: Box::new(other),
+                        args: Vec::new(),
+                        exec_policy: call.exec_policy,
+                        type_args: Vec::new(),
+                    }),
+                };
+                (object, split)
+            }
+            other => (other, None),
+        }
     }
 
     fn parse_exec_call(&mut self, policy: ExecPolicy, modifier: &str) -> Result<Expr> {
@@ -2436,6 +3415,69 @@ impl Parser {
         Ok(options)
     }
 
+    /// Condition of an `if` or `else if`; parentheses are optional
+    fn parse_if_condition(&mut self) -> Result<Expr> {
+        // Use parse_expression_no_lambda to avoid `ident =>` being parsed as a lambda
+        // (same as while/for). The `=>` here means one-liner body, not lambda arrow.
+        if self.match_token(&Token::LParen) {
+            let cond = self.parse_expression()?;
+            self.expect(Token::RParen)?;
+            Ok(cond)
+        } else {
+            self.parse_expression_no_lambda()
+        }
+    }
+
+    /// `{ ... }` body of an `if` branch, or a one-liner like `if cond fail "msg"`
+    /// or `if cond => expr`
+    fn parse_if_body(&mut self) -> Result<IfBody> {
+        if self.check(&Token::LBrace) {
+            self.expect(Token::LBrace)?;
+            let block = self.parse_block_stmt()?;
+            self.expect(Token::RBrace)?;
+            Ok(IfBody::Block(block))
+        } else {
+            // Consume optional => for one-liner syntax: if cond => expr
+            self.match_token(&Token::Arrow);
+            let stmt = self.parse_simple_statement()?;
+            Ok(IfBody::Stmt(Box::new(stmt)))
+        }
+    }
+
+    /// Parse the body of `runtime { key: value, ... }` (after the `runtime` keyword)
+    fn parse_runtime_decl(&mut self) -> Result<TopLevel> {
+        self.expect(Token::LBrace)?;
+        let mut decl = RuntimeDecl::default();
+
+        while !self.check(&Token::RBrace) && !self.is_at_end() {
+            // `threads` is also a loop-option keyword, so it lexes as its own token
+            let key = if self.match_token(&Token::Threads) {
+                "threads".to_string()
+            } else {
+                self.parse_identifier()?
+            };
+            self.expect(Token::Colon)?;
+            let value = self.parse_option_int(&key)?;
+            match key.as_str() {
+                "threads" => decl.threads = Some(value),
+                "asyncThreads" => decl.async_threads = Some(value),
+                "parThreads" => decl.par_threads = Some(value),
+                _ => {
+                    return Err(self.error_with_help(
+                        format!("Unknown runtime option '{}'", key),
+                        Some("Supported options: threads, asyncThreads, parThreads".into()),
+                    ))
+                }
+            }
+            if !self.match_token(&Token::Comma) {
+                self.match_token(&Token::Semicolon);
+            }
+        }
+
+        self.expect(Token::RBrace)?;
+        Ok(TopLevel::Runtime(decl))
+    }
+
     fn parse_option_int(&mut self, option_name: &str) -> Result<i64> {
         match self.peek() {
             Some(Token::IntLiteral(value)) => {
@@ -2494,6 +3536,7 @@ impl Parser {
                     | Token::Null
                     | Token::If
                     | Token::Switch
+                    | Token::Fail
                     | Token::Plus
                     | Token::Minus
                     | Token::Not
@@ -2508,6 +3551,7 @@ impl Parser {
         let mut expr = self.parse_primary()?;
 
         loop {
+            self.check_depth(chain_length(&expr))?;
             // Check for type arguments like sum<float>
             if self.check(&Token::Lt) && self.is_type_argument_list() {
                 let type_args = self.parse_type_arguments()?;
@@ -2520,7 +3564,7 @@ impl Parser {
                 } else {
                     return Err(self.error("Expected '(' after type arguments".to_string()));
                 }
-            } else if self.match_token(&Token::LParen) {
+            } else if !self.at_line_start() && self.match_token(&Token::LParen) {
                 expr = self.finish_call(expr)?;
             } else if self.check(&Token::LBrace) {
                 // Check if this is a Map literal or struct literal
@@ -2615,6 +3659,7 @@ impl Parser {
                             args,
                             adapter: ArrayAdapter::Seq,
                             adapter_options: AdapterOptions::default(),
+                            comprehension: None,
                         });
                         continue;
                     };
@@ -2631,6 +3676,7 @@ impl Parser {
                         args,
                         adapter,
                         adapter_options: options,
+                        comprehension: None,
                     });
                 } else {
                     // Regular member access (not a method call)
@@ -2639,7 +3685,7 @@ impl Parser {
                         property: name,
                     };
                 }
-            } else if self.match_token(&Token::LBracket) {
+            } else if !self.at_line_start() && self.match_token(&Token::LBracket) {
                 let index = self.parse_expression()?;
                 self.expect(Token::RBracket)?;
                 expr = Expr::Index {
@@ -2657,7 +3703,7 @@ impl Parser {
                 } else {
                     return Err(self.error("Expected identifier before '::'".to_string()));
                 }
-            } else if self.match_token(&Token::Bang) {
+            } else if !self.at_line_start() && self.match_token(&Token::Bang) {
                 // Postfix unwrap: expr! → Unwrap(expr)
                 expr = Expr::Unwrap(Box::new(expr));
             } else if self.check(&Token::Question) && Self::question_is_try(self.peek_token(1)) {
@@ -2689,8 +3735,19 @@ impl Parser {
                 break;
             }
 
-            // Parse option name
-            let option_name = self.parse_identifier()?;
+            // The option names are also loop-option keywords, so they lex as
+            // their own tokens
+            let option_name = if self.match_token(&Token::Threads) {
+                "threads".to_string()
+            } else if self.match_token(&Token::Chunk) {
+                "chunk".to_string()
+            } else if self.match_token(&Token::SimdWidth) {
+                "simdWidth".to_string()
+            } else if self.match_token(&Token::Ordered) {
+                "ordered".to_string()
+            } else {
+                self.parse_identifier()?
+            };
             self.expect(Token::Colon)?;
 
             match option_name.as_str() {
@@ -2749,8 +3806,12 @@ impl Parser {
             loop {
                 args.push(self.parse_expression()?);
                 if !self.match_token(&Token::Comma) {
+                    self.expect_list_end(&Token::RParen, "arguments")?;
                     break;
                 }
+                if self.check(&Token::RParen) {
+                    break; // Trailing comma
+                }
             }
         }
 
@@ -2927,6 +3988,10 @@ impl Parser {
             return self.parse_switch_expr();
         }
 
+        if self.match_token(&Token::If) {
+            return self.parse_if_expr();
+        }
+
         if self.match_token(&Token::LParen) {
             // Handle empty tuple: ()
             if self.match_token(&Token::RParen) {
@@ -2993,14 +4058,23 @@ impl Parser {
     }
 
     fn parse_array_literal(&mut self) -> Result<Expr> {
+        let start = self.previous_span();
         let mut elements = Vec::new();
 
         if !self.check(&Token::RBracket) {
             loop {
-                elements.push(self.parse_expression()?);
+                let element = self.parse_expression()?;
+                if elements.is_empty() && self.check(&Token::For) {
+                    return self.parse_comprehension(element, start);
+                }
+                elements.push(element);
                 if !self.match_token(&Token::Comma) {
+                    self.expect_list_end(&Token::RBracket, "array elements")?;
                     break;
                 }
+                if self.check(&Token::RBracket) {
+                    break; // Trailing comma
+                }
             }
         }
 
@@ -3008,6 +4082,71 @@ impl Parser {
         Ok(Expr::ArrayLiteral(elements))
     }
 
+    /// The rest of `[element for x in items if condition]`, rewritten to
+    /// `items.filter(x => condition).map(x => element)`. A policy after
+    /// `for` (`for par x in items`) becomes the adapter of both calls.
+    fn parse_comprehension(
+        &mut self,
+        element: Expr,
+        start: Option<crate::span::Span>,
+    ) -> Result<Expr> {
+        self.expect(Token::For)?;
+        let adapter = if self.match_token(&Token::Par) {
+            ArrayAdapter::Par
+        } else if self.match_token(&Token::Vec) {
+            ArrayAdapter::Vec
+        } else if self.match_token(&Token::ParVec) {
+            ArrayAdapter::ParVec
+        } else {
+            self.match_token(&Token::Seq);
+            ArrayAdapter::Seq
+        };
+        let var = self.parse_identifier()?;
+        self.expect(Token::In)?;
+        let iterable = self.parse_expression_no_lambda()?;
+        let condition = if self.match_token(&Token::If) {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        self.expect(Token::RBracket)?;
+
+        let call = |object: Expr, method: &str, body: Expr| MethodCallExpr {
+            object: Box::new(object),
+            method: method.to_string(),
+            args: vec![Expr::Lambda(LambdaExpr {
+                is_move: false,
+                params: vec![LambdaParam {
+                    pattern: BindingPattern::Identifier(var.clone()),
+                    type_ref: None,
+                    span: None,
+                }],
+                return_type: None,
+                body: LambdaBody::Expr(Box::new(body)),
+                captures: Vec::new(),
+            })],
+            adapter,
+            adapter_options: AdapterOptions::default(),
+            comprehension: None,
+        };
+        let filtered = condition.is_some();
+        let identity = matches!(&element, Expr::Identifier(name) if *name == var);
+        let mut outer = match condition {
+            // `[x for x in items if x > 0]` needs no `map`
+            Some(condition) if identity => call(iterable, "filter", condition),
+            Some(condition) => {
+                let filter = call(iterable, "filter", condition);
+                call(Expr::MethodCall(filter), "map", element)
+            }
+            None => call(iterable, "map", element),
+        };
+        outer.comprehension = Some(Comprehension {
+            filtered,
+            span: self.span_since(start),
+        });
+        Ok(Expr::MethodCall(outer))
+    }
+
     /// Parse switch expression: switch x { 1 => "one", 2 => "two", _ => "other" }
     fn parse_switch_expr(&mut self) -> Result<Expr> {
         let discriminant = Box::new(self.parse_expression()?);
@@ -3062,6 +4201,43 @@ impl Parser {
         Ok(Expr::Switch(SwitchExpr { discriminant, arms }))
     }
 
+    /// Parse an `if` in value position: `if cond { a } else { b }`
+    fn parse_if_expr(&mut self) -> Result<Expr> {
+        let condition = Box::new(self.parse_expression_no_lambda()?);
+        let then_branch = self.parse_if_expr_branch()?;
+
+        if !self.match_token(&Token::Else) {
+            return Err(self.error("`if` used as a value needs an `else` branch".into()));
+        }
+        let else_branch = if self.match_token(&Token::If) {
+            SwitchBody::Expr(Box::new(self.parse_if_expr()?))
+        } else {
+            self.parse_if_expr_branch()?
+        };
+
+        Ok(Expr::If(IfExpr {
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    /// `{ ... }` branch of an `if` expression; a lone expression becomes
+    /// `SwitchBody::Expr`
+    fn parse_if_expr_branch(&mut self) -> Result<SwitchBody> {
+        self.expect(Token::LBrace)?;
+        let mut stmts = Vec::new();
+        while !self.is_at_end() && !self.check(&Token::RBrace) {
+            stmts.push(self.parse_statement()?);
+        }
+        self.expect(Token::RBrace)?;
+
+        if let [Stmt::Expr(expr_stmt)] = stmts.as_slice() {
+            return Ok(SwitchBody::Expr(Box::new(expr_stmt.expr.clone())));
+        }
+        Ok(SwitchBody::Block(stmts))
+    }
+
     /// Parse a pattern for pattern matching
     fn parse_pattern(&mut self) -> Result<Pattern> {
         self.parse_or_pattern()
@@ -3228,6 +4404,7 @@ impl Parser {
             fields.push((key, value));
 
             if !self.match_token(&Token::Comma) {
+                self.expect_list_end(&Token::RBrace, "fields")?;
                 break;
             }
         }
@@ -3253,6 +4430,7 @@ impl Parser {
             entries.push((key, value));
 
             if !self.match_token(&Token::Comma) {
+                self.expect_list_end(&Token::RBrace, "map entries")?;
                 break;
             }
         }
@@ -3270,6 +4448,7 @@ impl Parser {
             entries.push(value);
 
             if !self.match_token(&Token::Comma) {
+                self.expect_list_end(&Token::RBrace, "set entries")?;
                 break;
             }
         }
@@ -3277,6 +4456,41 @@ impl Parser {
         Ok(entries)
     }
 
+    /// The rest of `mock Builtin.name returns value`, after `mock`
+    fn parse_mock(&mut self) -> Result<Stmt> {
+        let target_at = self.current;
+        let mut target = self.parse_identifier()?;
+        if self.match_token(&Token::Dot) {
+            target = format!("{}.{}", target, self.parse_identifier()?);
+        }
+        let Some(builtin) = crate::builtins::lookup(&target).filter(|b| b.mockable) else {
+            self.current = target_at;
+            let mockable: Vec<_> = crate::builtins::BUILTINS
+                .iter()
+                .filter(|b| b.mockable)
+                .map(|b| b.name)
+                .collect();
+            return Err(self.error_with_code(
+                E2006_UNKNOWN_MOCK_TARGET,
+                "Unknown mock target",
+                format!("'{}' is not a builtin tests can stub", target),
+                Some(format!(
+                    "Builtins that can be mocked: {}",
+                    mockable.join(", ")
+                )),
+            ));
+        };
+        if !self.check_ident("returns") {
+            return Err(self.error(format!("Expected 'returns' after 'mock {}'", target)));
+        }
+        self.advance(); // consume `returns`
+        let value = self.parse_expression()?;
+        Ok(Stmt::Mock(MockStmt {
+            target: builtin.name.to_string(),
+            value,
+        }))
+    }
+
     fn parse_identifier(&mut self) -> Result<String> {
         match self.advance() {
             Some(Token::Ident(s)) => Ok(s.clone()),
@@ -3319,6 +4533,7 @@ impl Parser {
             Some(Token::Not) => Ok("not".to_string()),
             Some(Token::Move) => Ok("move".to_string()),
             Some(Token::Test) => Ok("test".to_string()),
+            Some(Token::From) => Ok("from".to_string()), // Array.from(range)
             _ => Err(self.error("Expected method name".into())),
         }
     }
@@ -3351,6 +4566,84 @@ pub fn parse(tokens: Vec<TokenWithSpan>, source: &str) -> Result<Program> {
     parser.parse_program()
 }
 
+/// A source whose top-level items are lexed and parsed one range at a time,
+/// so parsing one item never lexes or scans the rest of the source
+pub struct ItemSource {
+    source: Rc<str>,
+    source_map: Rc<SourceMap>,
+}
+
+impl ItemSource {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source_map: Rc::new(SourceMap::new(source)),
+            source: source.into(),
+        }
+    }
+
+    /// 1-based line of the byte at `offset`
+    pub fn line(&self, offset: usize) -> usize {
+        self.source_map.line_col(offset).0
+    }
+
+    /// Lex and parse the items in `range`, which starts and ends between two
+    /// items. Spans and line numbers are those of the whole source.
+    pub fn parse(&self, range: Range<usize>) -> Result<Program> {
+        let start = range.start;
+        let text = &self.source[range];
+        let shift = |span: Span| Span::new(span.start + start, span.end + start);
+        let tokens = tokenize(text)?
+            .into_iter()
+            .map(|token| TokenWithSpan::new(token.token, shift(token.span)))
+            .collect();
+        let comments = crate::lexer::comments(text)
+            .into_iter()
+            .map(|comment| Comment {
+                span: shift(comment.span),
+                ..comment
+            })
+            .collect();
+        let mut parser = Parser::with_source(
+            tokens,
+            self.source.clone(),
+            self.source_map.clone(),
+            comments,
+        );
+        parser.parse_program()
+    }
+}
+
+/// Like [`parse`], but keeps going after a top-level error so callers can
+/// report every diagnostic along with the partial AST.
+pub fn parse_with_recovery(
+    tokens: Vec<TokenWithSpan>,
+    source: &str,
+) -> (Program, Vec<CompilerError>) {
+    let mut parser = Parser::new(tokens, source.to_string());
+    parser.parse_program_recovering()
+}
+
+/// Nodes on the left spine of `expr`: the operators of `a + b + c` or the
+/// calls and accesses of `a.b().c[0]`, which the parser builds in a loop
+/// rather than by recursion
+fn chain_length(expr: &Expr) -> usize {
+    let mut length = 0;
+    let mut node = expr;
+    loop {
+        node = match node {
+            Expr::Binary { left, .. } => left,
+            Expr::Call(call) => &call.callee,
+            Expr::MethodCall(call) => &call.object,
+            Expr::Member { object, .. }
+            | Expr::OptionalChain { object, .. }
+            | Expr::Index { object, .. } => object,
+            Expr::Cast { expr, .. } | Expr::Unwrap(expr) | Expr::Try(expr) => expr,
+            _ => return length,
+        };
+        length += 1;
+    }
+}
+
 fn is_valid_assignment_target(expr: &Expr) -> bool {
     matches!(
         expr,
@@ -3433,7 +4726,7 @@ fn parse_string_template_parts(raw: &str) -> Result<Vec<StringTemplatePart>> {
                     }
                 }
                 if depth != 0 {
-                    return Err(CompilerError::ParseError(
+                    return Err(CompilerError::ParseError(Box::new(
                         SemanticErrorInfo::new(
                             "E2001",
                             "Unclosed interpolation",
@@ -3443,11 +4736,11 @@ fn parse_string_template_parts(raw: &str) -> Result<Vec<StringTemplatePart>> {
                         .with_help(
                             "Make sure all '{' characters in interpolations have matching '}'",
                         ),
-                    ));
+                    )));
                 }
                 let expr_src_trimmed = expr_src.trim();
                 if expr_src_trimmed.is_empty() {
-                    return Err(CompilerError::ParseError(
+                    return Err(CompilerError::ParseError(Box::new(
                         SemanticErrorInfo::new(
                             "E2002",
                             "Empty interpolation",
@@ -3457,7 +4750,7 @@ fn parse_string_template_parts(raw: &str) -> Result<Vec<StringTemplatePart>> {
                         .with_help(
                             "Add an expression inside the interpolation or remove the empty braces",
                         ),
-                    ));
+                    )));
                 }
                 // First, try to normalize single quotes to double quotes for string literals
                 // This allows $"Status: {age >= 18 ? 'adult' : 'minor'}" to work
@@ -3483,7 +4776,7 @@ fn parse_string_template_parts(raw: &str) -> Result<Vec<StringTemplatePart>> {
                     chars.next();
                     buffer.push('}');
                 } else {
-                    return Err(CompilerError::ParseError(
+                    return Err(CompilerError::ParseError(Box::new(
                         SemanticErrorInfo::new(
                             "E2003",
                             "Unmatched closing brace",
@@ -3493,7 +4786,7 @@ fn parse_string_template_parts(raw: &str) -> Result<Vec<StringTemplatePart>> {
                         .with_help(
                             "Use '}}' to escape a literal '}' character in a string template",
                         ),
-                    ));
+                    )));
                 }
             }
             _ => buffer.push(ch),
@@ -3580,6 +4873,146 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_trailing_commas() {
+        let source = r#"
+Pair<A, B,> {
+    first: A
+    second: B
+}
+
+add(a: number, b: number,): number => a + b
+
+main() {
+    let xs = [1, 2, 3,]
+    let total = add(
+        xs[0],
+        xs[1],
+    )
+    let p = Pair { first: 1, second: "x", }
+    let m = Map { "a": 1, }
+    let s = Set { 1, 2, }
+    let pick = (a, b,) => a
+    let pairs: Map<string, number,> = Map {}
+    print(total, p, m, s, pick(1, 2), pairs)
+}
+"#;
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let TopLevel::Function(add) = &program.items[1] else {
+            panic!("Expected function");
+        };
+        assert_eq!(add.params.len(), 2);
+        let TopLevel::Function(main) = &program.items[2] else {
+            panic!("Expected function");
+        };
+        let Stmt::VarDecl(decl) = &main.body.as_ref().unwrap().stmts[0] else {
+            panic!("Expected let");
+        };
+        assert!(matches!(&decl.init, Expr::ArrayLiteral(items) if items.len() == 3));
+    }
+
+    #[test]
+    fn test_optional_semicolons() {
+        let source = "const LIMIT = 3;\n;\nmain() {\n    let a = 1; let b = 2;\n    ;\n    if a < b { return; }\n    for i in 0..LIMIT { continue; }\n    print(a + b);\n};\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        assert_eq!(program.items.len(), 2);
+        let TopLevel::Function(main) = &program.items[1] else {
+            panic!("Expected function");
+        };
+        assert_eq!(main.body.as_ref().unwrap().stmts.len(), 5);
+    }
+
+    #[test]
+    fn test_line_breaks_end_expressions() {
+        let body = |source: &str| {
+            let program = parse(tokenize(source).unwrap(), source).unwrap();
+            let TopLevel::Function(main) = &program.items[0] else {
+                panic!("Expected function");
+            };
+            main.body.clone().unwrap().stmts
+        };
+
+        // A `(` or `[` on the next line starts a new statement
+        let stmts =
+            body("main() {\n    let f = compute\n    (1, 2)\n    let xs = f\n    [3].length\n}\n");
+        assert_eq!(stmts.len(), 4);
+        assert!(matches!(&stmts[1], Stmt::Expr(e) if matches!(e.expr, Expr::Tuple(_))));
+
+        // ...but a `.` still continues a chain
+        let stmts =
+            body("main() {\n    let n = [1, 2]\n        .map(x => x * 2)\n        .length\n}\n");
+        assert_eq!(stmts.len(), 1);
+
+        // `return` takes a value only from its own line
+        let stmts = body("main() {\n    return\n    print(1)\n}\n");
+        assert!(matches!(&stmts[0], Stmt::Return(r) if r.expr.is_none()));
+        assert_eq!(stmts.len(), 2);
+    }
+
+    #[test]
+    fn test_missing_comma_between_lines() {
+        let source = "main() {\n    let xs = [\n        1\n        2\n    ]\n}\n";
+        let err = parse(tokenize(source).unwrap(), source).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Expected ',' between array elements"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_doc_comments_attach_to_declarations() {
+        let source = r#"
+/// A registered user
+User {
+    /// Shown on the profile page
+    name: string
+
+    // not a doc comment
+    greet() => print(this.name)
+}
+
+/// Retries before giving up
+/// on a request
+@inline
+retries() => 3
+
+/// Detached by the comment below
+// plain
+main() {}
+"#;
+        let tokens = tokenize(source).unwrap();
+        let program = parse(tokens, source).unwrap();
+
+        let TopLevel::Class(user) = &program.items[0] else {
+            panic!("Expected class");
+        };
+        assert_eq!(user.doc.as_deref(), Some("A registered user"));
+        let docs: Vec<Option<&str>> = user
+            .members
+            .iter()
+            .map(|member| match member {
+                Member::Field(field) => field.doc.as_deref(),
+                Member::Method(method) => method.doc.as_deref(),
+            })
+            .collect();
+        assert_eq!(docs, vec![Some("Shown on the profile page"), None]);
+
+        let TopLevel::Function(retries) = &program.items[1] else {
+            panic!("Expected function");
+        };
+        assert_eq!(
+            retries.doc.as_deref(),
+            Some("Retries before giving up\non a request")
+        );
+        let TopLevel::Function(main) = &program.items[2] else {
+            panic!("Expected function");
+        };
+        assert_eq!(main.doc, None);
+        assert_eq!(program.comments.len(), 7);
+    }
+
     #[test]
     fn test_parse_async_call() {
         let source = r#"
@@ -3593,6 +5026,39 @@ mod tests {
         assert_eq!(program.items.len(), 1);
     }
 
+    #[test]
+    fn test_parse_aliased_imports_and_reexports() {
+        let source = r#"
+            import { parse as parseCfg, Config } from "./config"
+            import * from "./math"
+            export { render } from "./html"
+            export * from "./util"
+        "#;
+        let tokens = tokenize(source).unwrap();
+        let program = parse(tokens, source).unwrap();
+
+        assert_eq!(program.items.len(), 4);
+        match &program.items[0] {
+            TopLevel::Import(import) => {
+                assert_eq!(import.imports, vec!["parse", "Config"]);
+                assert_eq!(import.local_names(), vec!["parseCfg", "Config"]);
+                assert!(!import.is_reexport);
+            }
+            _ => panic!("Expected import"),
+        }
+        match &program.items[1] {
+            TopLevel::Import(import) => assert!(import.is_wildcard && import.alias.is_none()),
+            _ => panic!("Expected import"),
+        }
+        match &program.items[3] {
+            TopLevel::Import(import) => {
+                assert!(import.is_wildcard && import.is_reexport);
+                assert_eq!(import.to_string(), "export * from \"./util\"");
+            }
+            _ => panic!("Expected re-export"),
+        }
+    }
+
     #[test]
     fn test_parse_string_template_with_complex_expression() {
         let parts = super::parse_string_template_parts("First user: {users[0].name}\\n").unwrap();
@@ -3606,4 +5072,98 @@ mod tests {
             other => panic!("expected trailing newline text, got {:?}", other),
         }
     }
+
+    #[test]
+    fn test_parse_if_expression() {
+        let source = r#"
+            grade(score: number): string {
+                let grade = if score > 90 { "A" } else if score > 80 { "B" } else {
+                    let low = score < 50
+                    low ? "F" : "C"
+                }
+                return grade
+            }
+        "#;
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let TopLevel::Function(func) = &program.items[0] else {
+            panic!("Expected function");
+        };
+        let Some(Stmt::VarDecl(var)) = func.body.as_ref().map(|b| &b.stmts[0]) else {
+            panic!("Expected let");
+        };
+        let Expr::If(if_expr) = &var.init else {
+            panic!("Expected if expression, got {:?}", var.init);
+        };
+        assert!(matches!(if_expr.then_branch, SwitchBody::Expr(_)));
+        match &if_expr.else_branch {
+            SwitchBody::Expr(nested) => match nested.as_ref() {
+                Expr::If(inner) => assert!(
+                    matches!(&inner.else_branch, SwitchBody::Block(stmts) if stmts.len() == 2)
+                ),
+                other => panic!("Expected else-if, got {:?}", other),
+            },
+            other => panic!("Expected else-if, got {:?}", other),
+        }
+
+        let source = "main() {\n    let x = if true { 1 }\n}\n";
+        assert!(parse(tokenize(source).unwrap(), source).is_err());
+    }
+
+    #[test]
+    fn test_parse_cast_precedence() {
+        let source = "const X = -n as float * 2\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let TopLevel::ConstDecl(decl) = &program.items[0] else {
+            panic!("Expected const");
+        };
+        let Expr::Binary {
+            op: BinOp::Mul,
+            left,
+            ..
+        } = &decl.init
+        else {
+            panic!("Expected multiplication, got {:?}", decl.init);
+        };
+        match left.as_ref() {
+            Expr::Cast { expr, target } => {
+                assert!(matches!(expr.as_ref(), Expr::Unary { op: UnOp::Neg, .. }));
+                assert_eq!(target, &TypeRef::Simple("float".to_string()));
+            }
+            other => panic!("Expected cast, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_lazy_value() {
+        let source = "lazy CONFIG: Config = loadConfig()\nconst LIMIT = 3\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let TopLevel::ConstDecl(lazy) = &program.items[0] else {
+            panic!("Expected lazy value");
+        };
+        assert!(lazy.is_lazy);
+        assert_eq!(lazy.name, "CONFIG");
+        assert_eq!(lazy.type_ref, Some(TypeRef::Simple("Config".to_string())));
+        let TopLevel::ConstDecl(limit) = &program.items[1] else {
+            panic!("Expected const");
+        };
+        assert!(!limit.is_lazy);
+    }
+
+    #[test]
+    fn test_parse_parenthesized_loop_variables() {
+        let source =
+            "main() {\n    for (i, item) in items.enumerate() {\n        print(i)\n    }\n}\n";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let TopLevel::Function(func) = &program.items[0] else {
+            panic!("Expected function");
+        };
+        let Some(Stmt::For(for_stmt)) = func.body.as_ref().and_then(|b| b.stmts.first()) else {
+            panic!("Expected for loop");
+        };
+        assert_eq!(for_stmt.var, "i");
+        assert_eq!(for_stmt.var2.as_deref(), Some("item"));
+
+        let missing = "main() {\n    for (i) in items {\n    }\n}\n";
+        assert!(parse(tokenize(missing).unwrap(), missing).is_err());
+    }
 }
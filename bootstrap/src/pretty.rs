@@ -0,0 +1,803 @@
+//! Tree view of a `Program` for debugging, printed by
+//! `livac build --emit ast-pretty`.
+//!
+//! Every node gets one line, indented under its parent, with its source
+//! position where the parser recorded one and the type semantic analysis
+//! inferred for unannotated bindings:
+//!
+//! ```text
+//! Function main() @1:1
+//!   Block @1:8
+//!     Let
+//!       Binding total: number (inferred) @2:9
+//!       init
+//!         Binary +
+//!           Literal 1
+//!           Literal 2
+//! ```
+//!
+//! The tree is walked with an explicit stack rather than recursion, so a
+//! pathologically deep expression prints instead of overflowing the stack.
+//! Past `MAX_INDENT` levels the indentation stops growing and the line is
+//! prefixed with its depth instead.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::*;
+use crate::span::{SourceMap, Span};
+
+/// Nesting level after which lines stop being indented further
+pub const MAX_INDENT: usize = 64;
+
+/// Prints `program` as a tree; with `source`, spans show as `line:column`
+/// instead of byte ranges
+pub fn pretty_program(program: &Program, source: Option<&str>) -> String {
+    let mut out = String::new();
+    Printer::new(program, source)
+        .write(&mut out, program.items.iter().map(Node::Item).collect())
+        .expect("writing to a String cannot fail");
+    out
+}
+
+/// Prints a single expression as a tree
+pub fn pretty_expr(expr: &Expr) -> String {
+    let mut out = String::new();
+    Printer::default()
+        .write(&mut out, vec![Node::Expr(expr)])
+        .expect("writing to a String cannot fail");
+    out
+}
+
+/// Prints a single top-level item as a tree, without source positions, so a
+/// declaration prints the same wherever it sits in the file
+pub fn pretty_item(item: &TopLevel) -> String {
+    positionless(Node::Item(item))
+}
+
+/// Prints a single class member as a tree, without source positions
+pub fn pretty_member(member: &Member) -> String {
+    positionless(Node::Member(member))
+}
+
+/// Prints a single statement as a tree, without source positions
+pub fn pretty_stmt(stmt: &Stmt) -> String {
+    positionless(Node::Stmt(stmt))
+}
+
+fn positionless(node: Node<'_>) -> String {
+    let mut out = String::new();
+    Printer {
+        positions: false,
+        ..Printer::default()
+    }
+    .write(&mut out, vec![node])
+    .expect("writing to a String cannot fail");
+    out
+}
+
+/// Same tree as `pretty_program`, with spans as byte ranges
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Printer::new(self, None).write(f, self.items.iter().map(Node::Item).collect())
+    }
+}
+
+enum Node<'a> {
+    Item(&'a TopLevel),
+    Function(&'a FunctionDecl),
+    Member(&'a Member),
+    Stmt(&'a Stmt),
+    Expr(&'a Expr),
+    /// A line of its own, grouping the nodes under it
+    Label(String, Vec<Node<'a>>),
+}
+
+struct Printer<'a> {
+    lines: Option<SourceMap>,
+    inferred: HashMap<(usize, usize), &'a TypeRef>,
+    /// Show where nodes are in the source
+    positions: bool,
+}
+
+impl Default for Printer<'_> {
+    fn default() -> Self {
+        Self {
+            lines: None,
+            inferred: HashMap::new(),
+            positions: true,
+        }
+    }
+}
+
+impl<'a> Printer<'a> {
+    fn new(program: &'a Program, source: Option<&str>) -> Self {
+        Self {
+            lines: source.map(SourceMap::new),
+            inferred: program
+                .inferred_types
+                .iter()
+                .map(|inferred| ((inferred.span.start, inferred.span.end), &inferred.type_ref))
+                .collect(),
+            positions: true,
+        }
+    }
+
+    fn write(&self, out: &mut impl fmt::Write, roots: Vec<Node<'a>>) -> fmt::Result {
+        let mut stack: Vec<(usize, Node<'a>)> = roots.into_iter().rev().map(|n| (0, n)).collect();
+        while let Some((depth, node)) = stack.pop() {
+            let (line, children) = self.expand(node);
+            if depth > MAX_INDENT {
+                write!(out, "{:indent$}[{}] ", "", depth, indent = MAX_INDENT * 2)?;
+            } else {
+                write!(out, "{:indent$}", "", indent = depth * 2)?;
+            }
+            writeln!(out, "{}", line)?;
+            stack.extend(children.into_iter().rev().map(|child| (depth + 1, child)));
+        }
+        Ok(())
+    }
+
+    /// The line for `node` and the nodes nested under it
+    fn expand(&self, node: Node<'a>) -> (String, Vec<Node<'a>>) {
+        match node {
+            Node::Item(item) => self.expand_item(item),
+            Node::Function(func) => (
+                format!(
+                    "Function {}{}({}){}{}{}",
+                    func.name,
+                    type_params(&func.type_params),
+                    params(&func.params),
+                    annotation(&func.return_type),
+                    if func.unsafe_rust { " unsafe rust" } else { "" },
+                    self.at(func.span)
+                ),
+                self.body(&func.params, &func.body, &func.expr_body),
+            ),
+            Node::Member(Member::Field(field)) => (
+                format!(
+                    "Field {}{}{}",
+                    field.name,
+                    if field.is_optional { "?" } else { "" },
+                    annotation(&field.type_ref)
+                ),
+                field.init.iter().map(Node::Expr).collect(),
+            ),
+            Node::Member(Member::Method(method)) => self.method(method),
+            Node::Stmt(stmt) => self.expand_stmt(stmt),
+            Node::Expr(expr) => self.expand_expr(expr),
+            Node::Label(line, children) => (line, children),
+        }
+    }
+
+    fn expand_item(&self, item: &'a TopLevel) -> (String, Vec<Node<'a>>) {
+        match item {
+            TopLevel::Import(import) => (import.to_string(), Vec::new()),
+            TopLevel::UseRust(decl) => (format!("UseRust {:?}", decl.crate_name), Vec::new()),
+            TopLevel::Type(decl) => (
+                format!(
+                    "Interface {}{}{}",
+                    decl.name,
+                    type_params(&decl.type_params),
+                    self.at(decl.span)
+                ),
+                decl.members.iter().map(Node::Member).collect(),
+            ),
+            TopLevel::TypeAlias(decl) => (
+                format!(
+                    "TypeAlias {}{} = {}{}",
+                    decl.name,
+                    type_params(&decl.type_params),
+                    decl.target_type,
+                    self.at(decl.span)
+                ),
+                Vec::new(),
+            ),
+            TopLevel::Class(decl) => {
+                let implements = if decl.implements.is_empty() {
+                    String::new()
+                } else {
+                    format!(" : {}", decl.implements.join(", "))
+                };
+                (
+                    format!(
+                        "Class {}{}{}{}",
+                        decl.name,
+                        type_params(&decl.type_params),
+                        implements,
+                        self.at(decl.span)
+                    ),
+                    decl.members.iter().map(Node::Member).collect(),
+                )
+            }
+            TopLevel::Enum(decl) => (
+                format!("Enum {}{}", decl.name, type_params(&decl.type_params)),
+                decl.variants
+                    .iter()
+                    .map(|variant| {
+                        let fields: Vec<String> = variant
+                            .fields
+                            .iter()
+                            .map(|field| format!("{}: {}", field.name, field.type_ref))
+                            .collect();
+                        let line = if fields.is_empty() {
+                            format!("Variant {}", variant.name)
+                        } else {
+                            format!("Variant {}({})", variant.name, fields.join(", "))
+                        };
+                        Node::Label(line, Vec::new())
+                    })
+                    .collect(),
+            ),
+            TopLevel::Function(func) => self.expand(Node::Function(func)),
+            TopLevel::Test(test) => (
+                format!("Test {:?}{}", test.name, self.at(test.span)),
+                vec![self.block(&test.body)],
+            ),
+            TopLevel::ConstDecl(decl) => self.const_decl(decl),
+            TopLevel::ExprStmt(expr) => ("Expr".to_string(), vec![Node::Expr(expr)]),
+            TopLevel::ClassExtension(decl) => (
+                format!("Extend {}{}", decl.name, self.at(decl.span)),
+                decl.methods
+                    .iter()
+                    .map(|method| {
+                        let (line, children) = self.method(method);
+                        Node::Label(line, children)
+                    })
+                    .collect(),
+            ),
+            TopLevel::Runtime(decl) => {
+                let settings: Vec<String> = [
+                    ("threads", decl.threads),
+                    ("asyncThreads", decl.async_threads),
+                    ("parThreads", decl.par_threads),
+                ]
+                .iter()
+                .filter_map(|(key, value)| value.map(|v| format!("{}: {}", key, v)))
+                .collect();
+                (format!("Runtime {{ {} }}", settings.join(", ")), Vec::new())
+            }
+            TopLevel::Init(init) => (
+                format!("Init{}", self.at(init.span)),
+                vec![self.block(&init.body)],
+            ),
+            TopLevel::TestHook(hook) => (
+                format!("TestHook {}{}", hook.kind.keyword(), self.at(hook.span)),
+                vec![self.block(&hook.body)],
+            ),
+        }
+    }
+
+    fn expand_stmt(&self, stmt: &'a Stmt) -> (String, Vec<Node<'a>>) {
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                let mut children: Vec<Node<'a>> = decl
+                    .bindings
+                    .iter()
+                    .map(|binding| {
+                        let line = format!(
+                            "Binding {}{}{}",
+                            binding_pattern(&binding.pattern),
+                            self.binding_type(&binding.type_ref, binding.span),
+                            self.at(binding.span)
+                        );
+                        Node::Label(line, Vec::new())
+                    })
+                    .collect();
+                children.push(label("init", vec![Node::Expr(&decl.init)]));
+                if let Some(message) = &decl.or_fail_msg {
+                    children.push(label("or fail", vec![Node::Expr(message)]));
+                }
+                if let Some(value) = &decl.or_value {
+                    children.push(label("or", vec![Node::Expr(value)]));
+                }
+                if let Some(ResultCombinator::MapErr(map)) = &decl.combinator {
+                    children.push(label("mapErr", vec![Node::Expr(map)]));
+                }
+                let line = if decl.is_fallible {
+                    "Let (fallible)"
+                } else {
+                    "Let"
+                };
+                (line.to_string(), children)
+            }
+            Stmt::ConstDecl(decl) => self.const_decl(decl),
+            Stmt::Assign(assign) => (
+                match assign.op {
+                    Some(op) => format!("Assign {}=", op),
+                    None => "Assign".to_string(),
+                },
+                vec![
+                    label("target", vec![Node::Expr(&assign.target)]),
+                    label("value", vec![Node::Expr(&assign.value)]),
+                ],
+            ),
+            Stmt::If(stmt) => {
+                let mut children = vec![
+                    label("condition", vec![Node::Expr(&stmt.condition)]),
+                    label("then", vec![self.if_body(&stmt.then_branch)]),
+                ];
+                for link in &stmt.else_ifs {
+                    children.push(label(
+                        "else if",
+                        vec![
+                            label("condition", vec![Node::Expr(&link.condition)]),
+                            label("then", vec![self.if_body(&link.body)]),
+                        ],
+                    ));
+                }
+                if let Some(else_branch) = &stmt.else_branch {
+                    children.push(label("else", vec![self.if_body(else_branch)]));
+                }
+                ("If".to_string(), children)
+            }
+            Stmt::While(stmt) => (
+                "While".to_string(),
+                vec![
+                    label("condition", vec![Node::Expr(&stmt.condition)]),
+                    self.block(&stmt.body),
+                ],
+            ),
+            Stmt::For(stmt) => {
+                let vars = match &stmt.var2 {
+                    Some(second) => format!("{}, {}", stmt.var, second),
+                    None => stmt.var.clone(),
+                };
+                let policy = match stmt.policy {
+                    DataParallelPolicy::Seq => "",
+                    DataParallelPolicy::Par => " (par)",
+                    DataParallelPolicy::Vec => " (vec)",
+                    DataParallelPolicy::ParVec => " (parvec)",
+                };
+                (
+                    format!("For {}{}", vars, policy),
+                    vec![
+                        label("in", vec![Node::Expr(&stmt.iterable)]),
+                        self.block(&stmt.body),
+                    ],
+                )
+            }
+            Stmt::Switch(stmt) => {
+                let mut children = vec![label("subject", vec![Node::Expr(&stmt.discriminant)])];
+                for case in &stmt.cases {
+                    let mut body = vec![Node::Expr(&case.value)];
+                    body.extend(case.body.iter().map(Node::Stmt));
+                    children.push(label("case", body));
+                }
+                if let Some(default) = &stmt.default {
+                    children.push(label("default", default.iter().map(Node::Stmt).collect()));
+                }
+                ("Switch".to_string(), children)
+            }
+            Stmt::TryCatch(stmt) => (
+                "Try".to_string(),
+                vec![
+                    self.block(&stmt.try_block),
+                    Node::Label(
+                        format!("catch {}", stmt.catch_var),
+                        vec![self.block(&stmt.catch_block)],
+                    ),
+                ],
+            ),
+            Stmt::Throw(stmt) => ("Throw".to_string(), vec![Node::Expr(&stmt.expr)]),
+            Stmt::Fail(stmt) => ("Fail".to_string(), vec![Node::Expr(&stmt.expr)]),
+            Stmt::Return(stmt) => (
+                "Return".to_string(),
+                stmt.expr.iter().map(Node::Expr).collect(),
+            ),
+            Stmt::Defer(stmt) => ("Defer".to_string(), vec![Node::Stmt(&stmt.body)]),
+            Stmt::Scope(stmt) => ("Scope".to_string(), vec![self.block(&stmt.body)]),
+            Stmt::Mock(stmt) => (
+                format!("Mock {}", stmt.target),
+                vec![Node::Expr(&stmt.value)],
+            ),
+            Stmt::Break => ("Break".to_string(), Vec::new()),
+            Stmt::Continue => ("Continue".to_string(), Vec::new()),
+            Stmt::Expr(stmt) => ("Expr".to_string(), vec![Node::Expr(&stmt.expr)]),
+            Stmt::Block(block) => self.expand(self.block(block)),
+            Stmt::Function(func) => self.expand(Node::Function(func)),
+        }
+    }
+
+    fn expand_expr(&self, expr: &'a Expr) -> (String, Vec<Node<'a>>) {
+        let exprs = |exprs: &'a [Expr]| exprs.iter().map(Node::Expr).collect::<Vec<_>>();
+        let fields = |fields: &'a [(String, Expr)]| {
+            fields
+                .iter()
+                .map(|(key, value)| Node::Label(format!("{}:", key), vec![Node::Expr(value)]))
+                .collect::<Vec<_>>()
+        };
+        match expr {
+            Expr::Literal(lit) => (format!("Literal {}", literal(lit)), Vec::new()),
+            Expr::Identifier(name) => (format!("Identifier {}", name), Vec::new()),
+            Expr::Binary { op, left, right } => (
+                format!("Binary {}", op),
+                vec![Node::Expr(left), Node::Expr(right)],
+            ),
+            Expr::Unary { op, operand } => (format!("Unary {}", op), vec![Node::Expr(operand)]),
+            Expr::Ternary {
+                condition,
+                then_expr,
+                else_expr,
+            } => (
+                "Ternary".to_string(),
+                vec![
+                    label("condition", vec![Node::Expr(condition)]),
+                    label("then", vec![Node::Expr(then_expr)]),
+                    label("else", vec![Node::Expr(else_expr)]),
+                ],
+            ),
+            Expr::Call(call) => {
+                let policy = match call.exec_policy {
+                    ExecPolicy::Normal => "",
+                    ExecPolicy::Async => " (async)",
+                    ExecPolicy::Par => " (par)",
+                    ExecPolicy::TaskAsync => " (task async)",
+                    ExecPolicy::TaskPar => " (task par)",
+                };
+                let type_args = if call.type_args.is_empty() {
+                    String::new()
+                } else {
+                    let args: Vec<String> = call.type_args.iter().map(|t| t.to_string()).collect();
+                    format!("<{}>", args.join(", "))
+                };
+                let mut children = vec![label("callee", vec![Node::Expr(&call.callee)])];
+                if !call.args.is_empty() {
+                    children.push(label("args", exprs(&call.args)));
+                }
+                (format!("Call{}{}", type_args, policy), children)
+            }
+            Expr::Member { object, property } => {
+                (format!("Member .{}", property), vec![Node::Expr(object)])
+            }
+            Expr::Index { object, index } => (
+                "Index".to_string(),
+                vec![Node::Expr(object), Node::Expr(index)],
+            ),
+            Expr::ObjectLiteral(entries) => ("Object".to_string(), fields(entries)),
+            Expr::StructLiteral {
+                type_name,
+                fields: entries,
+            } => (format!("Struct {}", type_name), fields(entries)),
+            Expr::ArrayLiteral(items) => ("Array".to_string(), exprs(items)),
+            Expr::MapLiteral(entries) => (
+                "Map".to_string(),
+                entries
+                    .iter()
+                    .map(|(key, value)| label("entry", vec![Node::Expr(key), Node::Expr(value)]))
+                    .collect(),
+            ),
+            Expr::SetLiteral(items) => ("Set".to_string(), exprs(items)),
+            Expr::Tuple(items) => ("Tuple".to_string(), exprs(items)),
+            Expr::Lambda(lambda) => {
+                let params: Vec<String> = lambda
+                    .params
+                    .iter()
+                    .map(|param| {
+                        format!(
+                            "{}{}",
+                            binding_pattern(&param.pattern),
+                            self.binding_type(&param.type_ref, param.span)
+                        )
+                    })
+                    .collect();
+                let body = match &lambda.body {
+                    LambdaBody::Expr(body) => Node::Expr(body),
+                    LambdaBody::Block(block) => self.block(block),
+                };
+                (
+                    format!(
+                        "Lambda{} ({}){}",
+                        if lambda.is_move { " move" } else { "" },
+                        params.join(", "),
+                        annotation(&lambda.return_type)
+                    ),
+                    vec![body],
+                )
+            }
+            Expr::StringTemplate { parts } => (
+                "Template".to_string(),
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        StringTemplatePart::Text(text) => {
+                            Node::Label(format!("Text {:?}", text), Vec::new())
+                        }
+                        StringTemplatePart::Expr(expr) => Node::Expr(expr),
+                    })
+                    .collect(),
+            ),
+            Expr::Fail(expr) => ("Fail".to_string(), vec![Node::Expr(expr)]),
+            Expr::MethodCall(call) => {
+                let adapter = match call.adapter {
+                    ArrayAdapter::Seq => "",
+                    ArrayAdapter::Par => " (par)",
+                    ArrayAdapter::Vec => " (vec)",
+                    ArrayAdapter::ParVec => " (parvec)",
+                };
+                let mut children = vec![Node::Expr(&call.object)];
+                if !call.args.is_empty() {
+                    children.push(label("args", exprs(&call.args)));
+                }
+                (format!("MethodCall .{}{}", call.method, adapter), children)
+            }
+            Expr::Switch(switch) => {
+                let mut children = vec![label("subject", vec![Node::Expr(&switch.discriminant)])];
+                for arm in &switch.arms {
+                    let mut body = Vec::new();
+                    if let Some(guard) = &arm.guard {
+                        body.push(label("if", vec![Node::Expr(guard)]));
+                    }
+                    body.push(switch_body(&arm.body));
+                    children.push(Node::Label(format!("Arm {}", arm.pattern), body));
+                }
+                ("Switch".to_string(), children)
+            }
+            Expr::If(expr) => (
+                "If".to_string(),
+                vec![
+                    label("condition", vec![Node::Expr(&expr.condition)]),
+                    label("then", vec![switch_body(&expr.then_branch)]),
+                    label("else", vec![switch_body(&expr.else_branch)]),
+                ],
+            ),
+            Expr::Cast { expr, target } => (format!("Cast as {}", target), vec![Node::Expr(expr)]),
+            Expr::MethodRef { object, method } => {
+                (format!("MethodRef {}::{}", object, method), Vec::new())
+            }
+            Expr::Unwrap(expr) => ("Unwrap !".to_string(), vec![Node::Expr(expr)]),
+            Expr::Try(expr) => ("Try ?".to_string(), vec![Node::Expr(expr)]),
+            Expr::OptionalChain { object, property } => (
+                format!("OptionalChain ?.{}", property),
+                vec![Node::Expr(object)],
+            ),
+            Expr::RustBlock { code } => (
+                format!("RustBlock ({} lines)", code.lines().count()),
+                Vec::new(),
+            ),
+        }
+    }
+
+    fn method(&self, method: &'a MethodDecl) -> (String, Vec<Node<'a>>) {
+        (
+            format!(
+                "Method {}{}({}){}{}",
+                method.name,
+                type_params(&method.type_params),
+                params(&method.params),
+                annotation(&method.return_type),
+                self.at(method.span)
+            ),
+            self.body(&method.params, &method.body, &method.expr_body),
+        )
+    }
+
+    fn const_decl(&self, decl: &'a ConstDecl) -> (String, Vec<Node<'a>>) {
+        (
+            format!(
+                "{} {}{}{}",
+                if decl.is_lazy { "Lazy" } else { "Const" },
+                decl.name,
+                annotation(&decl.type_ref),
+                self.at(decl.span)
+            ),
+            vec![Node::Expr(&decl.init)],
+        )
+    }
+
+    /// Parameter defaults, then the block or expression body
+    fn body(
+        &self,
+        params: &'a [Param],
+        body: &'a Option<BlockStmt>,
+        expr_body: &'a Option<Expr>,
+    ) -> Vec<Node<'a>> {
+        let mut children: Vec<Node<'a>> = params
+            .iter()
+            .filter_map(|param| {
+                let default = param.default.as_ref()?;
+                let name = binding_pattern(&param.pattern);
+                Some(Node::Label(
+                    format!("default {}", name),
+                    vec![Node::Expr(default)],
+                ))
+            })
+            .collect();
+        if let Some(block) = body {
+            children.push(self.block(block));
+        }
+        if let Some(expr) = expr_body {
+            children.push(label("=>", vec![Node::Expr(expr)]));
+        }
+        children
+    }
+
+    fn block(&self, block: &'a BlockStmt) -> Node<'a> {
+        Node::Label(
+            format!("Block{}", self.at(block.span)),
+            block.stmts.iter().map(Node::Stmt).collect(),
+        )
+    }
+
+    fn if_body(&self, body: &'a IfBody) -> Node<'a> {
+        match body {
+            IfBody::Block(block) => self.block(block),
+            IfBody::Stmt(stmt) => Node::Stmt(stmt),
+        }
+    }
+
+    /// `: T` for an annotated binding, `: T (inferred)` for one analysis
+    /// typed, nothing otherwise
+    fn binding_type(&self, declared: &Option<TypeRef>, span: Option<Span>) -> String {
+        if let Some(type_ref) = declared {
+            return format!(": {}", type_ref);
+        }
+        span.and_then(|span| self.inferred.get(&(span.start, span.end)))
+            .map(|type_ref| format!(": {} (inferred)", type_ref))
+            .unwrap_or_default()
+    }
+
+    /// ` @line:column`, or ` @start..end` without the source
+    fn at(&self, span: Option<Span>) -> String {
+        if !self.positions {
+            return String::new();
+        }
+        match (span, &self.lines) {
+            (Some(span), Some(lines)) => {
+                let (line, column) = span.start_position(lines);
+                format!(" @{}:{}", line, column)
+            }
+            (Some(span), None) => format!(" @{}..{}", span.start, span.end),
+            (None, _) => String::new(),
+        }
+    }
+}
+
+fn label<'a>(text: &str, children: Vec<Node<'a>>) -> Node<'a> {
+    Node::Label(text.to_string(), children)
+}
+
+fn switch_body(body: &SwitchBody) -> Node<'_> {
+    match body {
+        SwitchBody::Expr(expr) => Node::Expr(expr),
+        SwitchBody::Block(stmts) => label("Block", stmts.iter().map(Node::Stmt).collect()),
+    }
+}
+
+fn literal(lit: &Literal) -> String {
+    match lit {
+        Literal::Int(value) => value.to_string(),
+        Literal::Float(value) => format!("{:?}", value),
+        Literal::String(value) => format!("{:?}", value),
+        Literal::Char(value) => format!("{:?}", value),
+        Literal::Bool(value) => value.to_string(),
+        Literal::Null => "null".to_string(),
+    }
+}
+
+fn binding_pattern(pattern: &BindingPattern) -> String {
+    match pattern {
+        BindingPattern::Identifier(name) => name.clone(),
+        BindingPattern::Object(object) => {
+            let fields: Vec<String> = object
+                .fields
+                .iter()
+                .map(|field| {
+                    if field.key == field.binding {
+                        field.key.clone()
+                    } else {
+                        format!("{}: {}", field.key, field.binding)
+                    }
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        BindingPattern::Array(array) => {
+            let mut elements: Vec<String> = array
+                .elements
+                .iter()
+                .map(|element| element.clone().unwrap_or_else(|| "_".to_string()))
+                .collect();
+            if let Some(rest) = &array.rest {
+                elements.push(format!("...{}", rest));
+            }
+            format!("[{}]", elements.join(", "))
+        }
+        BindingPattern::Tuple(tuple) => format!("({})", tuple.elements.join(", ")),
+    }
+}
+
+fn params(params: &[Param]) -> String {
+    params
+        .iter()
+        .map(|param| {
+            let name = binding_pattern(&param.pattern);
+            match &param.type_ref {
+                Some(type_ref) => format!("{}: {}", name, type_ref),
+                None => name,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn type_params(params: &[TypeParameter]) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+    format!("<{}>", params.join(", "))
+}
+
+/// `: T`, or nothing without a type
+fn annotation(type_ref: &Option<TypeRef>) -> String {
+    type_ref
+        .as_ref()
+        .map(|t| format!(": {}", t))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyzed(source: &str) -> Program {
+        let program =
+            crate::parser::parse(crate::lexer::tokenize(source).unwrap(), source).unwrap();
+        crate::semantic::analyze_with_source(program, "test.liva".into(), source.into()).unwrap()
+    }
+
+    #[test]
+    fn test_tree_with_spans_and_inferred_types() {
+        let source = "main() {\n    let items = [1, 2]\n    print(items)\n}\n";
+        let program = analyzed(source);
+        let tree = pretty_program(&program, Some(source));
+        let expected = "\
+Function main() @1:1
+  Block @1:8
+    Let
+      Binding items: [number] (inferred) @2:9
+      init
+        Array
+          Literal 1
+          Literal 2
+    Expr
+      Call
+        callee
+          Identifier print
+        args
+          Identifier items
+";
+        assert_eq!(tree, expected);
+
+        // Without the source, spans are byte ranges
+        assert!(
+            program.to_string().contains("Function main() @0.."),
+            "{}",
+            program
+        );
+    }
+
+    #[test]
+    fn test_deep_expression_does_not_overflow() {
+        let depth = 100_000;
+        let mut expr = Expr::Literal(Literal::Int(1));
+        for _ in 0..depth {
+            expr = Expr::Unary {
+                op: UnOp::Neg,
+                operand: Box::new(expr),
+            };
+        }
+
+        let tree = pretty_expr(&expr);
+        assert_eq!(tree.lines().count(), depth + 1);
+        let last = tree.lines().last().unwrap();
+        assert_eq!(
+            last,
+            format!("{:w$}[{}] Literal 1", "", depth, w = MAX_INDENT * 2)
+        );
+
+        // Dropping the chain recurses as well
+        std::mem::forget(expr);
+    }
+}
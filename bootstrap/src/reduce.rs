@@ -0,0 +1,324 @@
+//! Shrinking a program that crashes the compiler, for `livac dev reduce`.
+//!
+//! [`failure`] compiles a source in memory, the way `livac build` does with
+//! `--self-check`, and says how it failed: the error code, or where the
+//! compiler panicked. [`reduce`] then removes lines from the source for as
+//! long as a caller-supplied test still passes, usually "fails the same
+//! way", and returns what is left.
+//!
+//! What gets removed is a chunk of lines: a line on its own, or a line that
+//! opens brackets together with everything up to the line that closes them.
+//! A whole declaration is one chunk, each statement in its body another, so
+//! the biggest chunks go first and whole functions and classes disappear
+//! before their statements are tried one at a time. Rounds repeat until one
+//! removes nothing.
+
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+use std::sync::Once;
+
+use crate::error::CompilerError;
+use crate::lexer::Token;
+use crate::span::SourceMap;
+use crate::CompilerOptions;
+
+/// How a compilation went wrong
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// What stays the same while reducing: `error E3004`, or
+    /// `panic at src/codegen.rs:120:9`
+    pub signature: String,
+    /// The error or panic message
+    pub message: String,
+}
+
+impl Failure {
+    fn from_error(error: &CompilerError) -> Self {
+        match error.error_info() {
+            Some(info) if !info.code.is_empty() => Failure {
+                signature: format!("error {}", info.code),
+                message: info.message.clone(),
+            },
+            Some(info) => Failure {
+                signature: format!("error: {}", info.message),
+                message: info.message.clone(),
+            },
+            None => Failure {
+                signature: format!("error: {}", error),
+                message: error.to_string(),
+            },
+        }
+    }
+
+    /// Whether `other` is the same failure; with `exact`, the message has to
+    /// match too
+    pub fn matches(&self, other: &Failure, exact: bool) -> bool {
+        self.signature == other.signature && (!exact || self.message == other.message)
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.signature, self.message)
+    }
+}
+
+thread_local! {
+    /// Set while [`failure`] compiles on this thread
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+    /// Where the last panic captured on this thread happened
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Install, once per process, a panic hook that records where a panic inside
+/// [`failure`] happened instead of printing it. Panics anywhere else, or on
+/// other threads, still go to the hook that was installed before.
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !CAPTURING.with(Cell::get) {
+                return previous(info);
+            }
+            let location = info.location().map(|location| {
+                format!(
+                    "{}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                )
+            });
+            PANIC_LOCATION.with(|last| *last.borrow_mut() = location);
+        }));
+    });
+}
+
+/// Compile `source` without writing anything; `None` when it compiles
+///
+/// A panic is caught and reported as a failure. Its location is only known
+/// after [`install_panic_hook`]; without it, the panic is also printed.
+pub fn failure(source: &str) -> Option<Failure> {
+    let options = CompilerOptions {
+        self_check: true,
+        ..CompilerOptions::default()
+    };
+    CAPTURING.with(|capturing| capturing.set(true));
+    let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        crate::compile_source(source, &options)
+    }));
+    CAPTURING.with(|capturing| capturing.set(false));
+
+    match outcome {
+        Ok(Ok(_)) => None,
+        Ok(Err(error)) => Some(Failure::from_error(&error)),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panic".to_string());
+            let location = PANIC_LOCATION
+                .with(|last| last.borrow_mut().take())
+                .unwrap_or_else(|| "unknown location".to_string());
+            Some(Failure {
+                signature: format!("panic at {}", location),
+                message,
+            })
+        }
+    }
+}
+
+/// What [`reduce`] left of a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reduction {
+    pub source: String,
+    /// Lines of the original source that were removed
+    pub removed_lines: usize,
+    /// How many candidates were tested
+    pub attempts: usize,
+}
+
+/// Remove chunks of `source` while `still_fails` holds for what remains
+///
+/// `still_fails` is never asked about `source` itself; the caller checks
+/// that first.
+pub fn reduce(source: &str, mut still_fails: impl FnMut(&str) -> bool) -> Reduction {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut alive = vec![true; lines.len()];
+    let mut attempts = 0;
+
+    let mut try_removing = |alive: &mut Vec<bool>, remove: &[usize]| -> bool {
+        if remove.iter().all(|&line| !alive[line]) {
+            return false;
+        }
+        let mut trial = alive.clone();
+        for &line in remove {
+            trial[line] = false;
+        }
+        attempts += 1;
+        if still_fails(&render(&lines, &trial)) {
+            *alive = trial;
+            true
+        } else {
+            false
+        }
+    };
+
+    loop {
+        // Chunks are found in what is left, then mapped back to line numbers
+        // of the original, so removing one leaves the others valid
+        let kept: Vec<usize> = (0..lines.len()).filter(|&line| alive[line]).collect();
+        let mut candidates: Vec<Vec<usize>> = chunks(&render(&lines, &alive))
+            .into_iter()
+            .map(|range| kept[range].to_vec())
+            .collect();
+        candidates.sort_by_key(|chunk| std::cmp::Reverse(chunk.len()));
+
+        let mut removed_any = false;
+        for chunk in candidates {
+            removed_any |= try_removing(&mut alive, &chunk);
+        }
+        if !removed_any {
+            break;
+        }
+    }
+
+    // Blank and comment-only lines are never a chunk; drop them all at once
+    let text = render(&lines, &alive);
+    let kept: Vec<usize> = (0..lines.len()).filter(|&line| alive[line]).collect();
+    let filler: Vec<usize> = untokenized_lines(&text)
+        .into_iter()
+        .map(|line| kept[line])
+        .collect();
+    if !filler.is_empty() {
+        try_removing(&mut alive, &filler);
+    }
+
+    Reduction {
+        source: render(&lines, &alive),
+        removed_lines: alive.iter().filter(|alive| !**alive).count(),
+        attempts,
+    }
+}
+
+fn render(lines: &[&str], alive: &[bool]) -> String {
+    let mut out = String::new();
+    for (line, _) in lines.iter().zip(alive).filter(|(_, alive)| **alive) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// The first token of each line, if it has one, and by how much the line
+/// changes the bracket depth; `None` when `source` doesn't lex
+fn first_tokens(source: &str) -> Option<Vec<(Option<Token>, i32)>> {
+    let tokens = crate::lexer::tokenize(source).ok()?;
+    let map = SourceMap::new(source);
+    let mut lines: Vec<(Option<Token>, i32)> = vec![(None, 0); source.lines().count()];
+    for token in tokens {
+        let Some((first, depth_change)) = lines.get_mut(map.line_col(token.span.start).0 - 1)
+        else {
+            continue;
+        };
+        match token.token {
+            Token::LParen | Token::LBrace | Token::LBracket => *depth_change += 1,
+            Token::RParen | Token::RBrace | Token::RBracket => *depth_change -= 1,
+            _ => {}
+        }
+        first.get_or_insert(token.token);
+    }
+    Some(lines)
+}
+
+/// Line ranges of `source` that can go on their own: each line, and for a
+/// line that opens brackets, everything up to the line closing them. A line
+/// starting with a closing bracket belongs to the chunk that opened it.
+fn chunks(source: &str) -> Vec<Range<usize>> {
+    let Some(lines) = first_tokens(source) else {
+        // Without tokens there are no brackets to match; try line by line
+        return source
+            .lines()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(line, _)| line..line + 1)
+            .collect();
+    };
+
+    let mut chunks = Vec::new();
+    let mut depth = 0;
+    for (start, (first, change)) in lines.iter().enumerate() {
+        let base = depth;
+        depth += change;
+        let opens_line = match first {
+            Some(Token::RParen | Token::RBrace | Token::RBracket) | None => false,
+            Some(_) => true,
+        };
+        if !opens_line {
+            continue;
+        }
+        let mut end = start;
+        let mut inner = depth;
+        while inner > base && end + 1 < lines.len() {
+            end += 1;
+            inner += lines[end].1;
+        }
+        chunks.push(start..end + 1);
+    }
+    chunks
+}
+
+/// Lines of `source` without a token: blank, or only a comment
+fn untokenized_lines(source: &str) -> Vec<usize> {
+    match first_tokens(source) {
+        Some(lines) => lines
+            .iter()
+            .enumerate()
+            .filter(|(_, (first, _))| first.is_none())
+            .map(|(line, _)| line)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce_keeps_only_what_the_test_needs() {
+        let source = "\
+helper(x) {
+  return x + 1
+}
+
+// entry point
+main() {
+  let a = helper(1)
+  let b = [
+    1,
+    2,
+  ]
+  print(a)
+  crash(b)
+}
+";
+        let reduction = reduce(source, |candidate| {
+            candidate.contains("crash(b)") && crate::check_syntax(candidate).is_ok()
+        });
+        assert_eq!(reduction.source, "main() {\n  crash(b)\n}\n");
+        assert_eq!(reduction.removed_lines, 11);
+        assert!(reduction.attempts > 0);
+    }
+
+    #[test]
+    fn test_failure_signature_is_the_error_code() {
+        assert_eq!(failure("main() { print(1) }"), None);
+
+        let first = failure("main() { let x = }").expect("a parse error");
+        let second = failure("main() {\n  print(2)\n  let y = \n}").expect("a parse error");
+        assert!(first.signature.starts_with("error E"), "{}", first);
+        assert!(first.matches(&second, false));
+    }
+}
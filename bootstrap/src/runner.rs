@@ -0,0 +1,461 @@
+//! Compile, build and run a program in one call, with its output captured.
+//!
+//! [`run_source`] is what a web playground needs from the compiler: source in,
+//! stdout, stderr and exit status out, without shelling out to `livac`. The
+//! program is compiled under a [`SandboxPolicy`] (no capabilities by default),
+//! built with cargo and killed when it outlives [`RunOptions::timeout`].
+//!
+//! Every call builds its own crate, but all of them share one cargo target
+//! directory under [`RunOptions::work_dir`], so the runtime's dependencies
+//! are compiled once and later runs only build the program itself.
+
+use crate::diagnostics::Diagnostic;
+use crate::error::{CompilerError, Result, SemanticErrorInfo};
+use crate::error_codes::E3002_BUILD_FAILED;
+use crate::sandbox::SandboxPolicy;
+use crate::{BuildProfile, CancellationToken, CompilationResult, CompilerOptions, PassManager};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often a running process is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long to wait for output still buffered in a pipe once the process is gone
+const OUTPUT_GRACE: Duration = Duration::from_millis(500);
+
+/// Source of unique crate names for concurrent runs in one process
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+/// Limits and input for [`run_source`]
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Written to the program's stdin, which is then closed
+    pub stdin: String,
+
+    /// Command-line arguments of the program
+    pub args: Vec<String>,
+
+    /// Wall-clock limit of the program; it is killed once this passes
+    pub timeout: Duration,
+
+    /// Wall-clock limit of compiling plus `cargo build`
+    pub build_timeout: Duration,
+
+    /// Bytes kept of stdout and of stderr each; the rest is dropped
+    pub max_output: usize,
+
+    /// Address space the program may use, in bytes. Enforced with
+    /// `ulimit -v` on Unix and ignored elsewhere.
+    pub memory_limit: Option<u64>,
+
+    /// Capabilities the program may use; `None` compiles without a sandbox
+    pub sandbox: Option<SandboxPolicy>,
+
+    /// Profile of the cargo build
+    pub profile: BuildProfile,
+
+    /// Holds the generated crates and the shared cargo target directory.
+    /// Keep it between calls; deleting it only costs a rebuild.
+    pub work_dir: PathBuf,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            stdin: String::new(),
+            args: Vec::new(),
+            timeout: Duration::from_secs(10),
+            build_timeout: Duration::from_secs(300),
+            max_output: 1024 * 1024,
+            memory_limit: Some(512 * 1024 * 1024),
+            sandbox: Some(SandboxPolicy::new()),
+            profile: BuildProfile::Dev,
+            work_dir: std::env::temp_dir().join("liva_run"),
+        }
+    }
+}
+
+/// What the program did, from [`run_source`]
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct RunResult {
+    pub stdout: String,
+    pub stderr: String,
+
+    /// `None` when the program was killed, by the timeout or a signal
+    pub exit_code: Option<i32>,
+
+    /// The program ran longer than [`RunOptions::timeout`]
+    pub timed_out: bool,
+
+    /// stdout or stderr went over [`RunOptions::max_output`] and was cut
+    pub truncated: bool,
+
+    /// How long the program ran, not counting the build
+    pub duration: Duration,
+
+    /// Compiler warnings and hints
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl RunResult {
+    /// The program exited on its own with status 0
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+/// Compile `source`, build it and run it with the limits in `options`
+///
+/// Compile errors (including sandbox violations, E0021) and build failures
+/// (E3002) are returned as errors; a build that outlives
+/// [`RunOptions::build_timeout`] gives [`CompilerError::DeadlineExceeded`].
+/// Once the program starts, everything it does — failing, panicking or
+/// timing out — is reported in the [`RunResult`].
+///
+/// ```rust,no_run
+/// use livac::{run_source, RunOptions};
+/// use std::time::Duration;
+///
+/// let options = RunOptions {
+///     timeout: Duration::from_secs(2),
+///     ..RunOptions::default()
+/// };
+/// let result = run_source("main() {\n    print(\"hello\")\n}\n", &options).unwrap();
+/// assert_eq!(result.stdout, "hello\n");
+/// ```
+pub fn run_source(source: &str, options: &RunOptions) -> Result<RunResult> {
+    run(
+        options,
+        Path::new("main.liva"),
+        |compile_options, crate_name, cancel| {
+            crate::compile_source_with_filename(
+                source,
+                "main.liva",
+                crate_name,
+                compile_options,
+                &std::collections::BTreeMap::new(),
+                &mut PassManager::new(),
+                cancel,
+            )
+        },
+    )
+}
+
+/// [`run_source`] for a file on disk, compiled together with the modules it
+/// imports
+pub fn run_file(input: &Path, options: &RunOptions) -> Result<RunResult> {
+    run(options, input, |compile_options, crate_name, cancel| {
+        crate::compile_file_as(compile_options, crate_name, &mut PassManager::new(), cancel)
+    })
+}
+
+/// Compile `input` with `compile` into a crate of its own, build and run it,
+/// then delete the crate and its build artifacts
+fn run(
+    options: &RunOptions,
+    input: &Path,
+    compile: impl FnOnce(&CompilerOptions, &str, &CancellationToken) -> Result<CompilationResult>,
+) -> Result<RunResult> {
+    let crate_name = format!(
+        "liva_run_{}_{}",
+        std::process::id(),
+        NEXT_RUN.fetch_add(1, Ordering::Relaxed)
+    );
+    let crate_dir = options.work_dir.join(&crate_name);
+    let target_dir = options.work_dir.join("target");
+
+    let deadline = Instant::now() + options.build_timeout;
+    let compile_options = CompilerOptions {
+        input: input.to_path_buf(),
+        output: Some(crate_dir.clone()),
+        profile: options.profile,
+        sandbox: options.sandbox.clone(),
+        ..CompilerOptions::default()
+    };
+    let result = compile(
+        &compile_options,
+        &crate_name,
+        &CancellationToken::with_deadline(deadline),
+    )
+    .and_then(|compiled| {
+        build_and_run(
+            options,
+            compiled.diagnostics,
+            deadline,
+            &crate_name,
+            &crate_dir,
+            &target_dir,
+        )
+    });
+
+    let _ = std::fs::remove_dir_all(&crate_dir);
+    remove_build_artifacts(&target_dir.join(options.profile.target_dir()), &crate_name);
+    result
+}
+
+/// Build the crate compiled into `crate_dir` and run its binary
+fn build_and_run(
+    options: &RunOptions,
+    diagnostics: Vec<Diagnostic>,
+    deadline: Instant,
+    crate_name: &str,
+    crate_dir: &Path,
+    target_dir: &Path,
+) -> Result<RunResult> {
+    // Build in the shared target directory, so dependencies are built once
+    let mut cargo = Command::new("cargo");
+    cargo
+        .arg("build")
+        .arg("--quiet")
+        .arg("--color=never")
+        .env("CARGO_TARGET_DIR", target_dir)
+        .current_dir(crate_dir);
+    if options.profile == BuildProfile::Release {
+        cargo.arg("--release");
+    }
+    let build = run_captured(
+        &mut cargo,
+        b"",
+        deadline.saturating_duration_since(Instant::now()),
+        options.max_output,
+    )
+    .map_err(|e| CompilerError::IoError(format!("Failed to run cargo: {}", e)))?;
+    if build.timed_out {
+        return Err(CompilerError::DeadlineExceeded);
+    }
+    if !build.status.is_some_and(|status| status.success()) {
+        return Err(CompilerError::CodegenError(Box::new(
+            SemanticErrorInfo::new(
+                E3002_BUILD_FAILED,
+                "Build failed",
+                &format!(
+                    "cargo build rejected the generated Rust code:\n{}",
+                    String::from_utf8_lossy(&build.stderr)
+                ),
+            ),
+        )));
+    }
+
+    // Run the binary from its crate directory
+    let binary = target_dir
+        .join(options.profile.target_dir())
+        .join(crate_name);
+    let mut program = program_command(&binary, options);
+    program.current_dir(crate_dir);
+    let started = Instant::now();
+    let run = run_captured(
+        &mut program,
+        options.stdin.as_bytes(),
+        options.timeout,
+        options.max_output,
+    )
+    .map_err(|e| CompilerError::IoError(format!("Failed to run {}: {}", binary.display(), e)))?;
+
+    Ok(RunResult {
+        stdout: String::from_utf8_lossy(&run.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&run.stderr).into_owned(),
+        exit_code: run.status.and_then(|status| status.code()),
+        timed_out: run.timed_out,
+        truncated: run.truncated,
+        duration: started.elapsed(),
+        diagnostics,
+    })
+}
+
+/// The command that starts `binary`, under the memory limit where the
+/// platform has one
+fn program_command(binary: &Path, options: &RunOptions) -> Command {
+    #[cfg(unix)]
+    if let Some(limit) = options.memory_limit {
+        // `exec` keeps the process id, so the timeout kills the program itself
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg("ulimit -v \"$0\" && exec \"$@\"")
+            .arg((limit / 1024).max(1).to_string())
+            .arg(binary)
+            .args(&options.args);
+        return command;
+    }
+    let mut command = Command::new(binary);
+    command.args(&options.args);
+    command
+}
+
+/// Delete the files cargo left for `crate_name` in a profile directory
+fn remove_build_artifacts(profile_dir: &Path, crate_name: &str) {
+    let _ = std::fs::remove_file(profile_dir.join(crate_name));
+    let prefix = format!("{}-", crate_name);
+    for dir in ["deps", ".fingerprint", "incremental"] {
+        let Ok(entries) = std::fs::read_dir(profile_dir.join(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+                continue;
+            }
+            let path = entry.path();
+            let _ = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+        }
+    }
+}
+
+/// Output and status of a process run by [`run_captured`]
+struct Captured {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    /// `None` when the process was killed at the timeout
+    status: Option<ExitStatus>,
+    timed_out: bool,
+    truncated: bool,
+}
+
+/// Run `command` with `stdin` as its input until it exits or `timeout`
+/// passes, keeping at most `max_output` bytes of each output stream
+fn run_captured(
+    command: &mut Command,
+    stdin: &[u8],
+    timeout: Duration,
+    max_output: usize,
+) -> std::io::Result<Captured> {
+    let deadline = Instant::now() + timeout;
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin from a thread so a program that doesn't read it can't block us
+    if let Some(mut pipe) = child.stdin.take() {
+        let input = stdin.to_vec();
+        std::thread::spawn(move || {
+            let _ = pipe.write_all(&input);
+        });
+    }
+    let stdout = Capture::start(child.stdout.take(), max_output);
+    let stderr = Capture::start(child.stderr.take(), max_output);
+
+    let (status, timed_out) = loop {
+        if let Some(status) = child.try_wait()? {
+            break (Some(status), false);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            break (None, true);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let (stdout, stdout_cut) = stdout.finish();
+    let (stderr, stderr_cut) = stderr.finish();
+    Ok(Captured {
+        stdout,
+        stderr,
+        status,
+        timed_out,
+        truncated: stdout_cut || stderr_cut,
+    })
+}
+
+/// One output stream, read on its own thread
+struct Capture {
+    buffer: Arc<Mutex<(Vec<u8>, bool)>>,
+    done: mpsc::Receiver<()>,
+}
+
+impl Capture {
+    fn start(stream: Option<impl Read + Send + 'static>, max_output: usize) -> Self {
+        let buffer = Arc::new(Mutex::new((Vec::new(), false)));
+        let (done_tx, done) = mpsc::channel::<()>();
+        if let Some(mut stream) = stream {
+            let buffer = Arc::clone(&buffer);
+            std::thread::spawn(move || {
+                // Keep draining past the limit so the writer never blocks on a full pipe
+                let mut chunk = [0u8; 8192];
+                while let Ok(read) = stream.read(&mut chunk) {
+                    if read == 0 {
+                        break;
+                    }
+                    let mut buffer = buffer.lock().unwrap();
+                    let (kept, cut) = &mut *buffer;
+                    let room = max_output.saturating_sub(kept.len());
+                    kept.extend_from_slice(&chunk[..read.min(room)]);
+                    *cut |= read > room;
+                }
+                drop(done_tx);
+            });
+        }
+        Capture { buffer, done }
+    }
+
+    /// The bytes read so far and whether any were dropped. Waits briefly for
+    /// the stream to close, which a process that left children behind may
+    /// never do.
+    fn finish(self) -> (Vec<u8>, bool) {
+        let _ = self.done.recv_timeout(OUTPUT_GRACE);
+        let mut buffer = self.buffer.lock().unwrap();
+        (std::mem::take(&mut buffer.0), buffer.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(work_dir: &Path) -> RunOptions {
+        RunOptions {
+            work_dir: work_dir.to_path_buf(),
+            ..RunOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_compile_errors_stop_before_building() {
+        let dir = tempfile::tempdir().unwrap();
+        let reads =
+            "main() {\n    let text, err = File.read(\"/etc/passwd\")\n    print(text)\n}\n";
+        let err = run_source(reads, &options(dir.path())).unwrap_err();
+        assert_eq!(err.error_info().unwrap().code, "E0021");
+
+        assert!(run_source("main() {", &options(dir.path())).is_err());
+        // Nothing is left behind for failed runs
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_captured_pipes_and_truncates() {
+        let mut echo = Command::new("sh");
+        echo.arg("-c").arg("cat; echo oops >&2; exit 3");
+        let run = run_captured(&mut echo, b"hello", Duration::from_secs(10), 3).unwrap();
+        assert_eq!(run.stdout, b"hel");
+        assert_eq!(run.stderr, b"oop");
+        assert!(run.truncated);
+        assert!(!run.timed_out);
+        assert_eq!(run.status.and_then(|status| status.code()), Some(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_captured_kills_at_timeout() {
+        let mut sleep = Command::new("sh");
+        sleep.arg("-c").arg("echo started; exec sleep 10");
+        let started = Instant::now();
+        let run = run_captured(&mut sleep, b"", Duration::from_millis(200), 1024).unwrap();
+        assert!(run.timed_out);
+        assert!(run.status.is_none());
+        assert_eq!(run.stdout, b"started\n");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
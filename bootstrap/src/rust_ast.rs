@@ -0,0 +1,641 @@
+//! Typed Rust syntax for code generation.
+//!
+//! Codegen has always built Rust source by appending strings to
+//! `CodeGenerator::output`, so every emitter has to get parentheses,
+//! indentation and escaping right by hand. The nodes here carry the
+//! structure instead: an emitter builds an `Expr` and the printer decides
+//! where parentheses go from Rust's precedence table, indents nested blocks
+//! and escapes string literals.
+//!
+//! Emitters are moving over one construct at a time. Code that the string
+//! generator produced enters a tree as `Expr::Raw` together with the
+//! precedence of its outermost operator, so a node can sit above text that
+//! was generated the old way and still be parenthesized correctly.
+
+use std::fmt::Write;
+
+/// How tightly an expression binds; a variant binds tighter than every
+/// variant before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Prec {
+    /// `if`, blocks, `return`: always parenthesized as an operand
+    Lowest,
+    Range,
+    Or,
+    And,
+    Compare,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Sum,
+    Product,
+    Cast,
+    Prefix,
+    /// Calls, method calls, fields, indexing, `?` and `.await`
+    Postfix,
+    /// Paths, literals, macros and anything already parenthesized
+    Atom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Range,
+    RangeInclusive,
+}
+
+impl BinOp {
+    pub fn token(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::Range => "..",
+            BinOp::RangeInclusive => "..=",
+        }
+    }
+
+    pub fn prec(self) -> Prec {
+        match self {
+            BinOp::Mul | BinOp::Div | BinOp::Rem => Prec::Product,
+            BinOp::Add | BinOp::Sub => Prec::Sum,
+            BinOp::Shl | BinOp::Shr => Prec::Shift,
+            BinOp::BitAnd => Prec::BitAnd,
+            BinOp::BitXor => Prec::BitXor,
+            BinOp::BitOr => Prec::BitOr,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => Prec::Compare,
+            BinOp::And => Prec::And,
+            BinOp::Or => Prec::Or,
+            BinOp::Range | BinOp::RangeInclusive => Prec::Range,
+        }
+    }
+
+    /// Comparisons and ranges can't be chained: `a < b == c` is an error
+    fn chains(self) -> bool {
+        !matches!(self.prec(), Prec::Compare | Prec::Range)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOp {
+    Neg,
+    Not,
+    Ref,
+    Deref,
+}
+
+impl UnOp {
+    pub fn token(self) -> &'static str {
+        match self {
+            UnOp::Neg => "-",
+            UnOp::Not => "!",
+            UnOp::Ref => "&",
+            UnOp::Deref => "*",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lit {
+    Int(i64),
+    Bool(bool),
+    Char(char),
+    /// Printed escaped, so the value is the string itself
+    Str(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Path(String),
+    Lit(Lit),
+    Unary(UnOp, Box<Expr>),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+    /// `expr as Type`
+    Cast(Box<Expr>, String),
+    Call(Box<Expr>, Vec<Expr>),
+    MethodCall(Box<Expr>, String, Vec<Expr>),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Try(Box<Expr>),
+    Await(Box<Expr>),
+    /// `name!(args)`
+    Macro(String, Vec<Expr>),
+    /// `vec![elements]`
+    Vec(Vec<Expr>),
+    /// `[elements]`
+    Array(Vec<Expr>),
+    Tuple(Vec<Expr>),
+    If(Box<Expr>, Block, Option<Block>),
+    Block(Block),
+    Return(Option<Box<Expr>>),
+    Break,
+    Continue,
+    /// Source produced by the string generator, and how tightly it binds
+    Raw(String, Prec),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Let {
+        mutable: bool,
+        pattern: String,
+        ty: Option<String>,
+        init: Option<Expr>,
+    },
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub tail: Option<Box<Expr>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Fn {
+        name: String,
+        params: Vec<(String, String)>,
+        ret: Option<String>,
+        body: Block,
+    },
+    Const {
+        name: String,
+        ty: String,
+        init: Expr,
+    },
+}
+
+impl Expr {
+    pub fn path(path: impl Into<String>) -> Expr {
+        Expr::Path(path.into())
+    }
+
+    pub fn call(callee: impl Into<String>, args: Vec<Expr>) -> Expr {
+        Expr::Call(Box::new(Expr::path(callee)), args)
+    }
+
+    pub fn method(self, name: impl Into<String>, args: Vec<Expr>) -> Expr {
+        Expr::MethodCall(Box::new(self), name.into(), args)
+    }
+
+    pub fn unary(op: UnOp, operand: Expr) -> Expr {
+        Expr::Unary(op, Box::new(operand))
+    }
+
+    pub fn binary(left: Expr, op: BinOp, right: Expr) -> Expr {
+        Expr::Binary(Box::new(left), op, Box::new(right))
+    }
+
+    pub fn ret(value: Expr) -> Expr {
+        Expr::Return(Some(Box::new(value)))
+    }
+
+    pub fn prec(&self) -> Prec {
+        match self {
+            Expr::Path(_) | Expr::Macro(..) | Expr::Vec(_) | Expr::Array(_) | Expr::Tuple(_) => {
+                Prec::Atom
+            }
+            Expr::Lit(Lit::Int(n)) if *n < 0 => Prec::Prefix,
+            Expr::Lit(_) => Prec::Atom,
+            Expr::Unary(..) => Prec::Prefix,
+            Expr::Binary(_, op, _) => op.prec(),
+            Expr::Cast(..) => Prec::Cast,
+            Expr::Call(..)
+            | Expr::MethodCall(..)
+            | Expr::Field(..)
+            | Expr::Index(..)
+            | Expr::Try(_)
+            | Expr::Await(_) => Prec::Postfix,
+            Expr::If(..) | Expr::Block(_) | Expr::Return(_) | Expr::Break | Expr::Continue => {
+                Prec::Lowest
+            }
+            Expr::Raw(_, prec) => *prec,
+        }
+    }
+
+    /// The expression as Rust source; blocks that span lines are indented
+    /// as if the expression started at nesting depth `indent`
+    pub fn print(&self, indent: usize) -> String {
+        let mut printer = Printer {
+            out: String::new(),
+            indent,
+        };
+        printer.expr(self);
+        printer.out
+    }
+}
+
+impl Block {
+    /// A block that only evaluates `tail`
+    pub fn of(tail: Expr) -> Block {
+        Block {
+            stmts: Vec::new(),
+            tail: Some(Box::new(tail)),
+        }
+    }
+}
+
+impl Stmt {
+    pub fn print(&self, indent: usize) -> String {
+        let mut printer = Printer {
+            out: String::new(),
+            indent,
+        };
+        printer.stmt(self);
+        printer.out
+    }
+}
+
+impl Item {
+    pub fn print(&self, indent: usize) -> String {
+        let mut printer = Printer {
+            out: String::new(),
+            indent,
+        };
+        printer.item(self);
+        printer.out
+    }
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+}
+
+impl Printer {
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    /// `expr`, parenthesized unless it binds at least as tightly as `min`
+    fn operand(&mut self, expr: &Expr, min: Prec) {
+        if expr.prec() < min {
+            self.out.push('(');
+            self.expr(expr);
+            self.out.push(')');
+        } else {
+            self.expr(expr);
+        }
+    }
+
+    fn list(&mut self, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Path(path) => self.out.push_str(path),
+            Expr::Lit(Lit::Int(n)) => write!(self.out, "{}", n).unwrap(),
+            Expr::Lit(Lit::Bool(b)) => write!(self.out, "{}", b).unwrap(),
+            Expr::Lit(Lit::Char(c)) => write!(self.out, "{:?}", c).unwrap(),
+            Expr::Lit(Lit::Str(s)) => write!(self.out, "{:?}", s).unwrap(),
+            Expr::Unary(op, operand) => {
+                self.out.push_str(op.token());
+                self.operand(operand, Prec::Prefix);
+            }
+            Expr::Binary(left, op, right) => {
+                let prec = op.prec();
+                // `a as T < b` reads as the start of generic arguments
+                let left_is_cast = left.prec() == Prec::Cast;
+                if (left.prec() == prec && !op.chains())
+                    || (left_is_cast && matches!(op, BinOp::Lt | BinOp::Shl))
+                {
+                    self.out.push('(');
+                    self.expr(left);
+                    self.out.push(')');
+                } else {
+                    self.operand(left, prec);
+                }
+                write!(self.out, " {} ", op.token()).unwrap();
+                if right.prec() == prec {
+                    self.out.push('(');
+                    self.expr(right);
+                    self.out.push(')');
+                } else {
+                    self.operand(right, prec);
+                }
+            }
+            Expr::Cast(operand, ty) => {
+                self.operand(operand, Prec::Cast);
+                write!(self.out, " as {}", ty).unwrap();
+            }
+            Expr::Call(callee, args) => {
+                self.operand(callee, Prec::Postfix);
+                self.out.push('(');
+                self.list(args);
+                self.out.push(')');
+            }
+            Expr::MethodCall(receiver, name, args) => {
+                self.operand(receiver, Prec::Postfix);
+                write!(self.out, ".{}(", name).unwrap();
+                self.list(args);
+                self.out.push(')');
+            }
+            Expr::Field(object, name) => {
+                self.operand(object, Prec::Postfix);
+                write!(self.out, ".{}", name).unwrap();
+            }
+            Expr::Index(object, index) => {
+                self.operand(object, Prec::Postfix);
+                self.out.push('[');
+                self.expr(index);
+                self.out.push(']');
+            }
+            Expr::Try(operand) => {
+                self.operand(operand, Prec::Postfix);
+                self.out.push('?');
+            }
+            Expr::Await(operand) => {
+                self.operand(operand, Prec::Postfix);
+                self.out.push_str(".await");
+            }
+            Expr::Macro(name, args) => {
+                write!(self.out, "{}!(", name).unwrap();
+                self.list(args);
+                self.out.push(')');
+            }
+            Expr::Vec(elements) => {
+                self.out.push_str("vec![");
+                self.list(elements);
+                self.out.push(']');
+            }
+            Expr::Array(elements) => {
+                self.out.push('[');
+                self.list(elements);
+                self.out.push(']');
+            }
+            Expr::Tuple(elements) => {
+                self.out.push('(');
+                self.list(elements);
+                // `(x,)` is a tuple, `(x)` just parentheses
+                if elements.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            Expr::If(condition, then_block, else_block) => {
+                self.out.push_str("if ");
+                self.expr(condition);
+                self.out.push(' ');
+                self.block(then_block);
+                if let Some(else_block) = else_block {
+                    self.out.push_str(" else ");
+                    self.block(else_block);
+                }
+            }
+            Expr::Block(block) => self.block(block),
+            Expr::Return(None) => self.out.push_str("return"),
+            Expr::Return(Some(value)) => {
+                self.out.push_str("return ");
+                self.expr(value);
+            }
+            Expr::Break => self.out.push_str("break"),
+            Expr::Continue => self.out.push_str("continue"),
+            Expr::Raw(code, _) => self.out.push_str(code),
+        }
+    }
+
+    /// A block with only a tail expression stays on one line
+    fn block(&mut self, block: &Block) {
+        match (&block.stmts[..], &block.tail) {
+            ([], None) => self.out.push_str("{}"),
+            ([], Some(tail)) => {
+                self.out.push_str("{ ");
+                self.expr(tail);
+                self.out.push_str(" }");
+            }
+            (stmts, tail) => self.lines(stmts, tail),
+        }
+    }
+
+    /// `{`, one line per statement and the tail, then `}` on its own line
+    fn lines(&mut self, stmts: &[Stmt], tail: &Option<Box<Expr>>) {
+        self.out.push('{');
+        self.indent += 1;
+        for stmt in stmts {
+            self.newline();
+            self.stmt(stmt);
+        }
+        if let Some(tail) = tail {
+            self.newline();
+            self.expr(tail);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let {
+                mutable,
+                pattern,
+                ty,
+                init,
+            } => {
+                self.out
+                    .push_str(if *mutable { "let mut " } else { "let " });
+                self.out.push_str(pattern);
+                if let Some(ty) = ty {
+                    write!(self.out, ": {}", ty).unwrap();
+                }
+                if let Some(init) = init {
+                    self.out.push_str(" = ");
+                    self.expr(init);
+                }
+                self.out.push(';');
+            }
+            // Block-like expressions end a statement on their own
+            Stmt::Expr(expr @ (Expr::If(..) | Expr::Block(_))) => self.expr(expr),
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+                self.out.push(';');
+            }
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::Fn {
+                name,
+                params,
+                ret,
+                body,
+            } => {
+                write!(self.out, "fn {}(", name).unwrap();
+                for (i, (param, ty)) in params.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    write!(self.out, "{}: {}", param, ty).unwrap();
+                }
+                self.out.push(')');
+                if let Some(ret) = ret {
+                    write!(self.out, " -> {}", ret).unwrap();
+                }
+                self.out.push(' ');
+                // Function bodies never collapse onto the signature line
+                if body.stmts.is_empty() && body.tail.is_none() {
+                    self.out.push_str("{}");
+                } else {
+                    self.lines(&body.stmts, &body.tail);
+                }
+            }
+            Item::Const { name, ty, init } => {
+                write!(self.out, "const {}: {} = ", name, ty).unwrap();
+                self.expr(init);
+                self.out.push(';');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> Expr {
+        Expr::path(name)
+    }
+
+    #[test]
+    fn test_parenthesizes_by_precedence() {
+        let sum = Expr::binary(var("a"), BinOp::Add, var("b"));
+        let product = Expr::binary(sum.clone(), BinOp::Mul, var("c"));
+        assert_eq!(product.print(0), "(a + b) * c");
+
+        // Left-associative: only the right operand of an equal operator needs them
+        let left = Expr::binary(sum.clone(), BinOp::Sub, var("c"));
+        assert_eq!(left.print(0), "a + b - c");
+        let right = Expr::binary(var("c"), BinOp::Sub, sum.clone());
+        assert_eq!(right.print(0), "c - (a + b)");
+
+        assert_eq!(Expr::unary(UnOp::Neg, sum).print(0), "-(a + b)");
+        assert_eq!(
+            Expr::Lit(Lit::Int(-5)).method("abs", vec![]).print(0),
+            "(-5).abs()"
+        );
+    }
+
+    #[test]
+    fn test_comparisons_and_casts_do_not_chain() {
+        let less = Expr::binary(var("a"), BinOp::Lt, var("b"));
+        let chained = Expr::binary(less, BinOp::Eq, Expr::Lit(Lit::Bool(true)));
+        assert_eq!(chained.print(0), "(a < b) == true");
+
+        let cast = Expr::Cast(Box::new(var("n")), "usize".to_string());
+        assert_eq!(
+            Expr::binary(cast, BinOp::Lt, var("len")).print(0),
+            "(n as usize) < len"
+        );
+    }
+
+    #[test]
+    fn test_raw_code_keeps_its_precedence() {
+        let generated = Expr::Raw("x.len() + 1".to_string(), Prec::Sum);
+        let product = Expr::binary(generated, BinOp::Mul, var("y"));
+        assert_eq!(product.print(0), "(x.len() + 1) * y");
+    }
+
+    #[test]
+    fn test_prints_collections() {
+        let one = Expr::Tuple(vec![var("a")]);
+        assert_eq!(one.print(0), "(a,)");
+        let pairs = Expr::Array(vec![Expr::Tuple(vec![var("k"), var("v")])]);
+        assert_eq!(
+            Expr::Call(Box::new(var("HashMap::from")), vec![pairs]).print(0),
+            "HashMap::from([(k, v)])"
+        );
+        let elements = Expr::Vec(vec![Expr::Lit(Lit::Int(1)), var("n")]);
+        assert_eq!(elements.method("len", vec![]).print(0), "vec![1, n].len()");
+    }
+
+    #[test]
+    fn test_postfix_operators_parenthesize_their_operand() {
+        let sum = Expr::Raw("a + b".to_string(), Prec::Sum);
+        assert_eq!(Expr::Try(Box::new(sum.clone())).print(0), "(a + b)?");
+        assert_eq!(sum.method("unwrap", vec![]).print(0), "(a + b).unwrap()");
+
+        let stmt = Stmt::Expr(Expr::ret(Expr::call("Err", vec![var("e")])));
+        assert_eq!(stmt.print(0), "return Err(e);");
+        assert_eq!(Stmt::Expr(Expr::Break).print(0), "break;");
+    }
+
+    #[test]
+    fn test_prints_blocks_and_items() {
+        let ternary = Expr::If(
+            Box::new(var("ok")),
+            Block::of(Expr::Lit(Lit::Str("yes \"sir\"".to_string()))),
+            Some(Block::of(Expr::Lit(Lit::Str("no".to_string())))),
+        );
+        assert_eq!(ternary.print(0), r#"if ok { "yes \"sir\"" } else { "no" }"#);
+
+        let item = Item::Fn {
+            name: "twice".to_string(),
+            params: vec![("n".to_string(), "i32".to_string())],
+            ret: Some("i32".to_string()),
+            body: Block {
+                stmts: vec![Stmt::Let {
+                    mutable: false,
+                    pattern: "m".to_string(),
+                    ty: None,
+                    init: Some(Expr::binary(var("n"), BinOp::Mul, Expr::Lit(Lit::Int(2)))),
+                }],
+                tail: Some(Box::new(var("m"))),
+            },
+        };
+        assert_eq!(
+            item.print(0),
+            "fn twice(n: i32) -> i32 {\n    let m = n * 2;\n    m\n}"
+        );
+
+        let item = Item::Const {
+            name: "LIMIT".to_string(),
+            ty: "i32".to_string(),
+            init: Expr::Lit(Lit::Int(10)),
+        };
+        assert_eq!(item.print(0), "const LIMIT: i32 = 10;");
+    }
+}
@@ -0,0 +1,179 @@
+//! Formatting of the generated Rust.
+//!
+//! Codegen builds Rust text piece by piece, so its indentation drifts and
+//! lines keep trailing spaces, which is what `--verbose` shows and what a
+//! debugger steps through. With `CompilerOptions::format_output` every
+//! generated file goes through `rustfmt` before it is written. When rustfmt
+//! isn't installed or rejects the file, [`tidy`] still strips trailing
+//! whitespace and runs of blank lines, so the option never fails a build.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// `code` as rustfmt prints it, or tidied when rustfmt can't be run
+pub fn format(code: &str) -> String {
+    run_rustfmt(code).unwrap_or_else(|| tidy(code))
+}
+
+fn run_rustfmt(code: &str) -> Option<String> {
+    let mut child = Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    // Written from another thread so a large file can't fill both pipes
+    let mut stdin = child.stdin.take()?;
+    let input = code.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().ok()?;
+    writer.join().ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// `code` without trailing whitespace and with at most one blank line in a
+/// row. Lines that continue a string literal are kept as they are, since
+/// their whitespace is part of the string.
+pub fn tidy(code: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut scanner = Scanner::default();
+    let mut blank_lines = 0;
+    for line in code.lines() {
+        let in_literal = scanner.in_string();
+        scanner.scan(line);
+        if in_literal || scanner.in_string() {
+            out.push_str(line);
+            out.push('\n');
+            blank_lines = 0;
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_lines += 1;
+            if blank_lines > 1 {
+                continue;
+            }
+        } else {
+            blank_lines = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Whether the end of the text seen so far is inside a string literal
+#[derive(Default)]
+struct Scanner {
+    /// Inside a string: how many `#`s close it, and whether it is raw (a
+    /// plain string honours escapes)
+    string: Option<(usize, bool)>,
+    block_comments: usize,
+}
+
+impl Scanner {
+    fn in_string(&self) -> bool {
+        self.string.is_some()
+    }
+
+    fn scan(&mut self, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if let Some((hashes, raw)) = self.string {
+                if !raw && c == '\\' {
+                    i += 2;
+                    continue;
+                }
+                if c == '"' && chars[i + 1..].iter().take_while(|&&h| h == '#').count() >= hashes {
+                    self.string = None;
+                    i += 1 + hashes;
+                    continue;
+                }
+                i += 1;
+                continue;
+            }
+            if self.block_comments > 0 {
+                if c == '*' && chars.get(i + 1) == Some(&'/') {
+                    self.block_comments -= 1;
+                    i += 2;
+                } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+                    self.block_comments += 1;
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+            match c {
+                '/' if chars.get(i + 1) == Some(&'/') => return,
+                '/' if chars.get(i + 1) == Some(&'*') => {
+                    self.block_comments += 1;
+                    i += 2;
+                }
+                '"' => {
+                    self.string = Some((0, false));
+                    i += 1;
+                }
+                // `r"..."`, `r#"..."#`, `br"..."`, not part of an identifier
+                'r' if i == 0 || !is_ident_char(chars[i - 1]) || chars[i - 1] == 'b' => {
+                    let hashes = chars[i + 1..].iter().take_while(|&&h| h == '#').count();
+                    if chars.get(i + 1 + hashes) == Some(&'"') {
+                        self.string = Some((hashes, true));
+                        i += 2 + hashes;
+                    } else {
+                        i += 1;
+                    }
+                }
+                // A char literal such as '"' or '\'', not a lifetime
+                '\'' => {
+                    let rest = &chars[i + 1..];
+                    i += match rest {
+                        ['\\', _, ..] => {
+                            1 + rest
+                                .iter()
+                                .skip(2)
+                                .position(|&c| c == '\'')
+                                .map_or(2, |p| p + 3)
+                        }
+                        [_, '\'', ..] => 3,
+                        _ => 1,
+                    };
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tidy_strips_trailing_whitespace_and_blank_runs() {
+        let code = "fn main() {   \n    let x = 1;  \n\n\n\n    print(x);\n}\n";
+        assert_eq!(
+            tidy(code),
+            "fn main() {\n    let x = 1;\n\n    print(x);\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_tidy_keeps_string_literal_lines() {
+        let code = "let s = \"one  \ntwo  \";  \nlet r = r#\"a \" b  \n\"#;\nlet c = '\"';  \n";
+        assert_eq!(
+            tidy(code),
+            "let s = \"one  \ntwo  \";  \nlet r = r#\"a \" b  \n\"#;\nlet c = '\"';\n"
+        );
+    }
+}
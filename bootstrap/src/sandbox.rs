@@ -0,0 +1,365 @@
+//! Sandboxed compilation (`--sandbox`) for source the host doesn't trust.
+//!
+//! Services that compile user-submitted snippets (playgrounds, course
+//! platforms) can't let a program read the host's files, open connections or
+//! start processes. A [`SandboxPolicy`] lists the capabilities a program may
+//! use; semantic analysis rejects anything else with E0021:
+//!
+//! | Capability | Grants |
+//! |------------|--------|
+//! | `fs` | `File.*`, `Dir.*`, `DB.*`, `Path.exists`, `Config.load`, `CSV.read*`/`CSV.write*`, `import` of other files |
+//! | `net` | `HTTP.*`, `Server.*` |
+//! | `process` | `Process.*`, `Sys.args`, `Sys.exit` |
+//! | `env` | `Env.*`, `Sys.env` |
+//! | `rust` | `use rust` of any crate, `rust { ... }` blocks and `unsafe rust fn` |
+//!
+//! Crates named with [`SandboxPolicy::allow_crate`] may be brought in with
+//! `use rust` even without `rust`.
+//!
+//! The check goes by name: `File.read` is a filesystem call wherever it
+//! appears. Codegen also resolves these namespaces by name, so a program
+//! can't declare or import its own `File` to get past the check: naming a
+//! top-level declaration or import after a namespace needs the
+//! capabilities the namespace does.
+
+use crate::ast::*;
+use crate::span::Span;
+use std::collections::BTreeSet;
+
+/// Something a sandboxed program may be allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Capability {
+    Fs,
+    Net,
+    Process,
+    Env,
+    Rust,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Capability::Fs,
+        Capability::Net,
+        Capability::Process,
+        Capability::Env,
+        Capability::Rust,
+    ];
+
+    /// Name used by `--allow` and in diagnostics
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Fs => "fs",
+            Capability::Net => "net",
+            Capability::Process => "process",
+            Capability::Env => "env",
+            Capability::Rust => "rust",
+        }
+    }
+
+    /// Capability the builtin `namespace.function` needs, if any
+    pub fn of_builtin(namespace: &str, function: &str) -> Option<Capability> {
+        match (namespace, function) {
+            ("File" | "Dir" | "DB", _)
+            | ("Path", "exists")
+            | ("Config", "load")
+            | ("CSV", "read" | "write" | "readTable" | "writeTable") => Some(Capability::Fs),
+            ("HTTP" | "Server", _) => Some(Capability::Net),
+            ("Env", _) | ("Sys", "env") => Some(Capability::Env),
+            ("Process" | "Sys", _) => Some(Capability::Process),
+            _ => None,
+        }
+    }
+
+    /// Capabilities some function of the builtin `namespace` needs
+    pub fn of_namespace(namespace: &str) -> &'static [Capability] {
+        match namespace {
+            "File" | "Dir" | "DB" | "Path" | "Config" | "CSV" => &[Capability::Fs],
+            "HTTP" | "Server" => &[Capability::Net],
+            "Env" => &[Capability::Env],
+            "Process" => &[Capability::Process],
+            "Sys" => &[Capability::Process, Capability::Env],
+            _ => &[],
+        }
+    }
+}
+
+impl std::fmt::Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Capability {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Capability::ALL
+            .into_iter()
+            .find(|capability| capability.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown capability '{}' (expected fs, net, process, env or rust)",
+                    s
+                )
+            })
+    }
+}
+
+/// Capabilities a sandboxed program may use; the default allows none
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    allowed: BTreeSet<Capability>,
+    crates: BTreeSet<String>,
+}
+
+impl SandboxPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.allowed.insert(capability);
+        self
+    }
+
+    /// Let `use rust` bring in `crate_name` without allowing every crate
+    pub fn allow_crate(mut self, crate_name: &str) -> Self {
+        self.crates.insert(crate_name.to_string());
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.allowed.contains(&capability)
+    }
+
+    fn allows_crate(&self, crate_name: &str) -> bool {
+        self.allows(Capability::Rust) || self.crates.contains(crate_name)
+    }
+}
+
+/// Use of a capability the policy doesn't allow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub capability: Capability,
+    /// What the program does: "'File.read'", "`use rust \"libc\"`"
+    pub what: String,
+    /// Declaration the use is in, when it has a span
+    pub span: Option<Span>,
+}
+
+impl Violation {
+    pub fn message(&self) -> String {
+        format!(
+            "{} needs the '{}' capability, which this sandbox does not allow",
+            self.what, self.capability
+        )
+    }
+
+    pub fn help(&self) -> String {
+        match self.capability {
+            Capability::Rust => "Sandboxed programs can only use the crates the host allows \
+                                 (--allow-crate), and no inline Rust"
+                .to_string(),
+            capability => format!(
+                "Remove it, or let the host allow it with `--allow {}`",
+                capability
+            ),
+        }
+    }
+}
+
+/// The first use in `program` of a capability `policy` doesn't allow
+pub fn check(program: &Program, policy: &SandboxPolicy) -> Option<Violation> {
+    let mut checker = Checker {
+        policy,
+        span: None,
+        violation: None,
+    };
+    for (name, span) in declared_names(program) {
+        for &capability in Capability::of_namespace(name) {
+            checker.span = span;
+            checker.require(
+                capability,
+                format!("Declaring `{}`, the name of a builtin namespace,", name),
+            );
+        }
+    }
+    for item in &program.items {
+        checker.visit_item(item);
+        if checker.violation.is_some() {
+            break;
+        }
+    }
+    checker.violation
+}
+
+/// Top-level names the program declares or imports, with the span of the
+/// declaration when it has one
+fn declared_names(program: &Program) -> Vec<(&str, Option<Span>)> {
+    let mut names = Vec::new();
+    for item in &program.items {
+        match item {
+            TopLevel::Class(decl) => names.push((decl.name.as_str(), decl.span)),
+            TopLevel::Type(decl) => names.push((decl.name.as_str(), decl.span)),
+            TopLevel::TypeAlias(decl) => names.push((decl.name.as_str(), decl.span)),
+            TopLevel::Enum(decl) => names.push((decl.name.as_str(), None)),
+            TopLevel::ConstDecl(decl) => names.push((decl.name.as_str(), decl.span)),
+            TopLevel::Import(import) => {
+                names.extend(import.local_names().into_iter().map(|name| (name, None)));
+                names.extend(import.alias.as_deref().map(|alias| (alias, None)));
+            }
+            // Functions are called by name, never as a namespace
+            TopLevel::Function(_)
+            | TopLevel::UseRust(_)
+            | TopLevel::Test(_)
+            | TopLevel::ExprStmt(_)
+            | TopLevel::ClassExtension(_)
+            | TopLevel::Runtime(_)
+            | TopLevel::Init(_)
+            | TopLevel::TestHook(_) => {}
+        }
+    }
+    names
+}
+
+struct Checker<'a> {
+    policy: &'a SandboxPolicy,
+    /// Span of the innermost declaration being walked
+    span: Option<Span>,
+    violation: Option<Violation>,
+}
+
+impl Checker<'_> {
+    fn require(&mut self, capability: Capability, what: String) {
+        if self.violation.is_none() && !self.policy.allows(capability) {
+            self.violation = Some(Violation {
+                capability,
+                what,
+                span: self.span,
+            });
+        }
+    }
+
+    fn builtin(&mut self, namespace: &str, function: &str) {
+        if let Some(capability) = Capability::of_builtin(namespace, function) {
+            self.require(capability, format!("'{}.{}'", namespace, function));
+        }
+    }
+}
+
+impl Visitor<'_> for Checker<'_> {
+    fn visit_item(&mut self, item: &TopLevel) {
+        match item {
+            TopLevel::Import(import)
+                if !crate::module::is_virtual_module(&import.source)
+                    && !crate::module::is_std_module(&import.source) =>
+            {
+                self.require(Capability::Fs, format!("`import` of \"{}\"", import.source));
+            }
+            TopLevel::UseRust(decl)
+                if self.violation.is_none() && !self.policy.allows_crate(&decl.crate_name) =>
+            {
+                self.violation = Some(Violation {
+                    capability: Capability::Rust,
+                    what: format!("`use rust \"{}\"`", decl.crate_name),
+                    span: None,
+                });
+            }
+            TopLevel::Test(test) => self.span = test.span,
+            TopLevel::Init(init) => self.span = init.span,
+            TopLevel::TestHook(hook) => self.span = hook.span,
+            TopLevel::ConstDecl(decl) => self.span = decl.span,
+            _ => {}
+        }
+        walk_item(self, item);
+    }
+
+    fn visit_function(&mut self, func: &FunctionDecl) {
+        let span = func.span.or(self.span);
+        let outer = std::mem::replace(&mut self.span, span);
+        if func.unsafe_rust {
+            self.require(Capability::Rust, format!("`unsafe rust fn {}`", func.name));
+        }
+        walk_function(self, func);
+        self.span = outer;
+    }
+
+    fn visit_method(&mut self, method: &MethodDecl) {
+        self.span = method.span;
+        walk_method(self, method);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if self.violation.is_some() {
+            return;
+        }
+        match expr {
+            Expr::RustBlock { .. } => {
+                self.require(Capability::Rust, "A `rust { ... }` block".to_string())
+            }
+            Expr::MethodRef { object, method } => self.builtin(object, method),
+            Expr::Call(call) => {
+                if let Expr::Member { object, property } = call.callee.as_ref() {
+                    if let Expr::Identifier(namespace) = object.as_ref() {
+                        self.builtin(namespace, property);
+                    }
+                }
+            }
+            Expr::MethodCall(call) => {
+                if let Expr::Identifier(namespace) = call.object.as_ref() {
+                    self.builtin(namespace, &call.method);
+                }
+            }
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capability_names_round_trip() {
+        for capability in Capability::ALL {
+            assert_eq!(capability.name().parse::<Capability>(), Ok(capability));
+        }
+        assert!("disk".parse::<Capability>().is_err());
+    }
+
+    #[test]
+    fn test_builtin_capabilities() {
+        assert_eq!(Capability::of_builtin("File", "read"), Some(Capability::Fs));
+        assert_eq!(Capability::of_builtin("Sys", "env"), Some(Capability::Env));
+        assert_eq!(
+            Capability::of_builtin("Sys", "exit"),
+            Some(Capability::Process)
+        );
+        assert_eq!(
+            Capability::of_builtin("Server", "create"),
+            Some(Capability::Net)
+        );
+        assert_eq!(Capability::of_builtin("Path", "basename"), None);
+        assert_eq!(Capability::of_builtin("CSV", "parse"), None);
+        assert_eq!(Capability::of_builtin("Math", "sqrt"), None);
+    }
+
+    #[test]
+    fn test_namespace_capabilities_cover_builtins() {
+        let calls = [
+            ("File", "read"),
+            ("Path", "exists"),
+            ("CSV", "write"),
+            ("HTTP", "get"),
+            ("Sys", "env"),
+            ("Sys", "exit"),
+            ("Process", "run"),
+        ];
+        for (namespace, function) in calls {
+            let capability = Capability::of_builtin(namespace, function).unwrap();
+            assert!(Capability::of_namespace(namespace).contains(&capability));
+        }
+        assert!(Capability::of_namespace("Math").is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+//! Class schemas.
+//!
+//! `User.schema()` and `fields(User)` describe the fields of a class: an
+//! array with one JSON object per field, in declaration order, holding its
+//! `name`, its `type` as written in the declaration, whether it is
+//! `optional` (`email?: string`) and whether it is `private` (`_token`).
+//! The description comes from the class declaration, so this pass replaces
+//! each call with the array literal and the rest of the compiler sees an
+//! ordinary array of objects.
+//!
+//! A field declared without a type gets the type of its initial value when
+//! that is a literal, and `any` otherwise. Fields a class gets from the
+//! abstract class it implements come first, as they do in the generated
+//! struct. A class that declares its own `schema` method, and a module that
+//! declares or imports its own `fields` function, keep calling those.
+//!
+//! Semantic analysis runs this pass over the program at hand; a
+//! multi-module build runs it across modules first (see
+//! `ModuleResolver::expand_schemas`), so a class imported from another
+//! module can be described too. Running the pass twice changes nothing.
+
+use crate::ast::*;
+use std::collections::HashMap;
+
+/// The schema of a class, and whether it declares a `schema` method itself
+struct ClassSchema {
+    fields: Expr,
+    own_schema: bool,
+}
+
+/// Replace the `Class.schema()` and `fields(Class)` calls in `programs`
+pub fn expand(programs: &mut [&mut Program]) {
+    let classes: HashMap<String, ClassSchema> = programs
+        .iter()
+        .flat_map(|program| &program.items)
+        .filter_map(|item| match item {
+            TopLevel::Class(class) => Some((class.name.clone(), class_schema(class))),
+            _ => None,
+        })
+        .collect();
+    if classes.is_empty() {
+        return;
+    }
+
+    for program in programs.iter_mut() {
+        let mut expander = Expander {
+            classes: &classes,
+            own_fields: declares_fields(program),
+        };
+        expander.visit_program(program);
+    }
+}
+
+fn class_schema(class: &ClassDecl) -> ClassSchema {
+    let mut fields = Vec::new();
+    let mut own_schema = false;
+    for member in &class.members {
+        match member {
+            Member::Field(field) => fields.push(field_schema(field)),
+            Member::Method(method) => own_schema |= method.name == "schema",
+        }
+    }
+    ClassSchema {
+        fields: Expr::ArrayLiteral(fields),
+        own_schema,
+    }
+}
+
+/// `{ name: "email", type: "string", optional: true, private: false }`
+fn field_schema(field: &FieldDecl) -> Expr {
+    let type_name = match (&field.type_ref, &field.init) {
+        (Some(type_ref), _) => type_ref.to_string(),
+        (None, Some(Expr::Literal(literal))) => literal_type(literal).to_string(),
+        (None, _) => "any".to_string(),
+    };
+    let string = |text: String| Expr::Literal(Literal::String(text));
+    let boolean = |value: bool| Expr::Literal(Literal::Bool(value));
+    Expr::ObjectLiteral(vec![
+        ("name".to_string(), string(field.name.clone())),
+        ("type".to_string(), string(type_name)),
+        ("optional".to_string(), boolean(field.is_optional)),
+        ("private".to_string(), boolean(field.name.starts_with('_'))),
+    ])
+}
+
+fn literal_type(literal: &Literal) -> &'static str {
+    match literal {
+        Literal::Int(_) => "int",
+        Literal::Float(_) => "float",
+        Literal::String(_) => "string",
+        Literal::Char(_) => "char",
+        Literal::Bool(_) => "bool",
+        Literal::Null => "any",
+    }
+}
+
+/// Whether `program` declares or imports a function named `fields`
+fn declares_fields(program: &Program) -> bool {
+    program.items.iter().any(|item| match item {
+        TopLevel::Function(func) => func.name == "fields",
+        TopLevel::Import(import) => import.local_names().contains(&"fields"),
+        _ => false,
+    })
+}
+
+struct Expander<'a> {
+    classes: &'a HashMap<String, ClassSchema>,
+    /// `fields` names a function of the program rather than the builtin
+    own_fields: bool,
+}
+
+impl Expander<'_> {
+    /// The array a call stands for, if it describes a class
+    fn schema_of(&self, expr: &Expr) -> Option<Expr> {
+        let class = match expr {
+            Expr::MethodCall(call) if call.method == "schema" && call.args.is_empty() => {
+                match call.object.as_ref() {
+                    Expr::Identifier(name) => {
+                        self.classes.get(name).filter(|class| !class.own_schema)?
+                    }
+                    _ => return None,
+                }
+            }
+            Expr::Call(call) if !self.own_fields => {
+                match (call.callee.as_ref(), call.args.as_slice()) {
+                    (Expr::Identifier(callee), [Expr::Identifier(name)]) if callee == "fields" => {
+                        self.classes.get(name)?
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(class.fields.clone())
+    }
+}
+
+impl VisitorMut for Expander<'_> {
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        match self.schema_of(expr) {
+            Some(schema) => *expr = schema,
+            None => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    /// The initializer of the first `let` in `main`, after expansion
+    fn expanded(source: &str) -> Expr {
+        let mut program = parse(tokenize(source).unwrap(), source).unwrap();
+        expand(&mut [&mut program]);
+        let main = program.items.iter().find_map(|item| match item {
+            TopLevel::Function(func) if func.name == "main" => func.body.as_ref(),
+            _ => None,
+        });
+        match main.map(|body| &body.stmts[0]) {
+            Some(Stmt::VarDecl(decl)) => decl.init.clone(),
+            other => panic!("expected a let, found {:?}", other),
+        }
+    }
+
+    /// `(name, type)` of each field an expanded schema lists
+    fn field_types(schema: &Expr) -> Vec<(String, String)> {
+        let Expr::ArrayLiteral(fields) = schema else {
+            panic!("expected an array, found {:?}", schema);
+        };
+        let text =
+            |fields: &[(String, Expr)], key: &str| match fields.iter().find(|(k, _)| k == key) {
+                Some((_, Expr::Literal(Literal::String(value)))) => value.clone(),
+                other => panic!("expected a string for '{}', found {:?}", key, other),
+            };
+        fields
+            .iter()
+            .map(|field| match field {
+                Expr::ObjectLiteral(entries) => (text(entries, "name"), text(entries, "type")),
+                other => panic!("expected an object, found {:?}", other),
+            })
+            .collect()
+    }
+
+    const USER: &str = r#"
+        User {
+            id: int
+            email?: string
+            tags: [string]
+            _token = "secret"
+            greet() => print(this.id)
+        }
+    "#;
+
+    #[test]
+    fn test_schema_lists_fields_in_order() {
+        let source = format!("{}\nmain() {{\n    let s = User.schema()\n}}", USER);
+        let schema = expanded(&source);
+        assert_eq!(
+            field_types(&schema),
+            vec![
+                ("id".to_string(), "int".to_string()),
+                ("email".to_string(), "string".to_string()),
+                ("tags".to_string(), "[string]".to_string()),
+                ("_token".to_string(), "string".to_string()),
+            ]
+        );
+        let Expr::ArrayLiteral(fields) = &schema else {
+            unreachable!()
+        };
+        let flag = |index: usize, key: &str| match &fields[index] {
+            Expr::ObjectLiteral(entries) => entries
+                .iter()
+                .any(|(k, v)| k == key && *v == Expr::Literal(Literal::Bool(true))),
+            _ => false,
+        };
+        assert!(flag(1, "optional") && !flag(0, "optional"));
+        assert!(flag(3, "private") && !flag(2, "private"));
+    }
+
+    #[test]
+    fn test_fields_matches_schema() {
+        let schema = expanded(&format!(
+            "{}\nmain() {{\n    let s = User.schema()\n}}",
+            USER
+        ));
+        let fields = expanded(&format!(
+            "{}\nmain() {{\n    let s = fields(User)\n}}",
+            USER
+        ));
+        assert_eq!(schema, fields);
+    }
+
+    #[test]
+    fn test_user_definitions_win() {
+        let own_method = r#"
+            Point {
+                x: int
+                schema() => "point"
+            }
+            main() {
+                let s = Point.schema()
+            }
+        "#;
+        assert!(matches!(expanded(own_method), Expr::MethodCall(_)));
+
+        let own_function = format!(
+            "{}\nfields(value: string): int => 0\nmain() {{\n    let s = fields(User)\n}}",
+            USER
+        );
+        assert!(matches!(expanded(&own_function), Expr::Call(_)));
+
+        let not_a_class = "main() {\n    let s = fields(user)\n}";
+        assert!(matches!(expanded(not_a_class), Expr::Call(_)));
+    }
+}
@@ -0,0 +1,83 @@
+//! Syntax check of the Rust that codegen produced.
+//!
+//! A codegen bug that emits malformed Rust otherwise shows up only when cargo
+//! builds the crate, as a rustc error in a file the user never wrote. With
+//! `--self-check`, and in every debug build of livac, each generated file is
+//! parsed with `syn` before it is written; the first syntax error becomes an
+//! E3004 internal error quoting the generated lines around it.
+
+use crate::error::{CompilerError, Result, SemanticErrorInfo};
+use crate::error_codes::E3004_INVALID_GENERATED_RUST;
+use std::path::Path;
+
+/// Whether a build checks its output, given `--self-check`
+pub fn enabled(requested: bool) -> bool {
+    requested || cfg!(debug_assertions)
+}
+
+/// Parse `code`, the generated `file`, and report the first syntax error in it
+pub fn check(file: &Path, code: &str) -> Result<()> {
+    let error = match syn::parse_file(code) {
+        Ok(_) => return Ok(()),
+        Err(error) => error,
+    };
+    let (start, end) = (error.span().start(), error.span().end());
+    let lines: Vec<&str> = code.lines().collect();
+    let line = start.line.clamp(1, lines.len().max(1));
+    let index = line - 1;
+    let owned =
+        |range: &[&str]| -> Vec<String> { range.iter().map(|line| line.to_string()).collect() };
+
+    let mut info = SemanticErrorInfo::new(
+        E3004_INVALID_GENERATED_RUST,
+        "Internal compiler error: generated Rust doesn't parse",
+        &format!(
+            "livac generated invalid Rust in {}: {}",
+            file.display(),
+            error
+        ),
+    )
+    .with_location(&file.display().to_string(), line)
+    .with_column(start.column + 1)
+    .with_context(
+        owned(&lines[index.saturating_sub(2)..index.min(lines.len())]),
+        owned(&lines[(index + 1).min(lines.len())..(index + 3).min(lines.len())]),
+    );
+    if let Some(source_line) = lines.get(index) {
+        info = info.with_source_line(source_line.to_string());
+    }
+    if end.line == start.line && end.column > start.column {
+        info = info.with_length(end.column - start.column);
+    }
+    Err(CompilerError::CodegenError(Box::new(info)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_rust_passes() {
+        let code = "fn main() {\n    let x: i32 = 1;\n    println!(\"{}\", x);\n}\n";
+        assert!(check(Path::new("src/main.rs"), code).is_ok());
+    }
+
+    #[test]
+    fn test_syntax_error_points_at_generated_line() {
+        let code = "fn main() {\n    let x = 1;\n    let y = x +;\n    println!(\"{}\", y);\n}\n";
+        let Err(CompilerError::CodegenError(info)) = check(Path::new("src/main.rs"), code) else {
+            panic!("expected a codegen error");
+        };
+        assert_eq!(info.code, "E3004");
+        let location = info.location.unwrap();
+        assert_eq!(location.line, 3);
+        assert_eq!(location.source_line.as_deref(), Some("    let y = x +;"));
+        assert_eq!(
+            location.context_before,
+            Some(vec![
+                "fn main() {".to_string(),
+                "    let x = 1;".to_string()
+            ])
+        );
+    }
+}
@@ -1,14 +1,40 @@
 use crate::ast::*;
+use crate::cancel::CancellationToken;
+use crate::diagnostics::DiagnosticSink;
 use crate::error::{CompilerError, ErrorLocation, Result, SemanticErrorInfo};
+use crate::error_codes::{
+    E0008_IMMUTABLE_RECEIVER, E0009_INVALID_MAIN_SIGNATURE, E0010_SHADOWED_RUNTIME_NAME,
+    E0011_NAME_COLLISION, E0015_ABSTRACT_INSTANTIATION, E0020_INVALID_CONTRACT,
+    E0021_SANDBOX_VIOLATION, E0022_PRIVATE_ACCESS, E0023_UNSAFE_RUST_SIGNATURE,
+    E0403_RECURSIVE_CAPTURING_FUNCTION, E0404_ASYNC_NESTED_FUNCTION, E0510_NON_SEND_CAPTURE,
+    E0511_NON_SYNC_CAPTURE, E0701_FALLIBLE_WITHOUT_BINDING, E0709_UNTYPED_LAZY_VALUE,
+    E0710_INVALID_RESULT_COMBINATOR, E0711_FALLIBLE_CONSTANT, E0712_DUPLICATE_INIT_BLOCK,
+    E0713_ASYNC_INIT_BLOCK, E0714_DUPLICATE_TEST_HOOK, E0715_MOCK_OUTSIDE_TEST,
+    E0905_NON_EXHAUSTIVE_UNION, E5001_TYPE_MISMATCH, E5004_BRANCH_TYPE_MISMATCH,
+    E5005_INVALID_CAST, E5006_FLOAT_GROUP_KEY, E5007_INVALID_TYPE_OF, E5008_UNNARROWED_UNION,
+    E5009_INVALID_ARRAY_CONSTRUCTOR, E5010_NON_BOOL_CONDITION, E5101_STRICT_UNTYPED_PARAM,
+    E5102_STRICT_UNTYPED_FIELD, E5103_STRICT_UNTYPED_JSON, E5104_STRICT_IMPLICIT_TO_STRING,
+};
+use crate::incremental::Validation;
+use crate::parser::grow_stack;
 use crate::suggestions;
 use crate::traits::TraitRegistry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Public and private symbols of each module a file can import, by path
+pub type ModuleSymbols = HashMap<std::path::PathBuf, (HashSet<String>, HashSet<String>)>;
 
 pub struct SemanticAnalyzer {
     // Track which functions are async
     async_functions: HashSet<String>,
     // Track which functions are fallible (contain fail)
     fallible_functions: HashSet<String>,
+    // `lazy` values whose initializer can fail; reading one needs error
+    // binding, like calling a fallible function
+    fallible_lazies: HashSet<String>,
+    // `const`s whose initializer can't be evaluated at compile time; they
+    // are initialized on first read, like `lazy` values
+    runtime_constants: HashSet<String>,
     // Track defined types
     types: HashMap<String, TypeInfo>,
     // Track function signatures (arity, optional type information)
@@ -18,6 +44,12 @@ pub struct SemanticAnalyzer {
     // Current scope for variables
     current_scope: Vec<HashMap<String, Option<TypeRef>>>,
     awaitable_scopes: Vec<HashMap<String, AwaitableInfo>>,
+    // Bindings whose value can't be modified, per scope; the outermost holds
+    // top-level constants
+    immutable_scopes: Vec<HashMap<String, Immutable>>,
+    // Types narrowed by a check such as `x != null` or `typeOf(x) == "int"`,
+    // per scope; they apply to bindings declared in the same or an outer scope
+    narrowed_scopes: Vec<HashMap<String, Option<TypeRef>>>,
     // Source file name for error reporting
     source_file: String,
     // Source code for line tracking
@@ -25,11 +57,20 @@ pub struct SemanticAnalyzer {
     // Source map for precise line/column tracking
     source_map: Option<crate::span::SourceMap>,
     // Imported symbols: map from module path to (public_symbols, private_symbols)
-    imported_modules: HashMap<std::path::PathBuf, (HashSet<String>, HashSet<String>)>,
+    imported_modules: ModuleSymbols,
     // Imported symbol names in current module (for collision detection)
     imported_symbols: HashSet<String>,
+    /// Names brought in by `import * from`, which local definitions and
+    /// explicit imports shadow instead of colliding with
+    glob_imported_symbols: HashSet<String>,
     // Track if we're currently in an error binding context (allows fallible calls)
     in_error_binding: bool,
+    /// `scope { }` blocks around the current statement; their tasks hand
+    /// errors to the scope, so fallible fire-and-forget calls are allowed
+    task_scope_depth: usize,
+    /// Set for the `async`/`par` call of a statement inside a scope, and
+    /// cleared as soon as that call is validated
+    scoped_fire: bool,
     // Track type parameters in current scope (for generics)
     type_parameters: Vec<HashSet<String>>,
     // Track type parameter constraints (T -> [Add, Sub, ...])
@@ -42,10 +83,58 @@ pub struct SemanticAnalyzer {
     type_aliases: HashMap<String, (Vec<TypeParameter>, TypeRef)>,
     // Enum variants: map from enum name to list of variant names
     enum_variants: HashMap<String, Vec<String>>,
+    // Classes declared `abstract`, which can't be instantiated
+    abstract_classes: HashSet<String>,
     // True when validating an `Expr::Switch` that appears directly as a
     // statement. In that case, codegen auto-injects `_ => {}` so we skip
     // exhaustiveness checks (Cycle 33).
     in_stmt_switch: bool,
+    // Polled before every top-level item and statement so editors can abort
+    // analysis of large files
+    cancel: CancellationToken,
+    // Types of unannotated bindings and lambda parameters, handed to editors
+    // through `Program::inferred_types`
+    inferred_types: Vec<InferredType>,
+    // Function or `Class.method` whose body is being validated, and the
+    // calls seen so far, handed to editors through `Program::call_graph`
+    current_callable: Option<String>,
+    call_graph: Vec<CallEdge>,
+    // Class whose method is being validated; only it may use its `_` members
+    current_class: Option<String>,
+    // `--strict`: reject code whose types codegen would have to guess
+    strict: bool,
+    // `--sandbox`: capabilities the program may use
+    sandbox: Option<crate::sandbox::SandboxPolicy>,
+    // A binding whose Rust name equals that of a different visible binding
+    // (`my_value` after `myValue`), reported once the declaration is done
+    name_collision: Option<(String, String)>,
+    // Errors of declarations validated so far, and how many to collect
+    // before giving up; with 1 the first error stops the analysis
+    errors: Vec<CompilerError>,
+    max_errors: usize,
+    // Incremental analysis: the validations of the previous analysis of the
+    // file, by key, and those of this one (see `crate::incremental`)
+    reusable: Option<HashMap<u64, Validation>>,
+    validations: HashMap<u64, Validation>,
+}
+
+/// Names in the sets validating an item can add to, as of the last item
+/// recorded; the sets only grow
+#[derive(Default)]
+struct Seen {
+    async_functions: HashSet<String>,
+    fallible_functions: HashSet<String>,
+    json_classes: HashSet<String>,
+}
+
+/// Names in `set` that aren't in `seen` yet, adding them to it
+fn added(set: &HashSet<String>, seen: &mut HashSet<String>) -> Vec<String> {
+    if set.len() == seen.len() {
+        return Vec::new();
+    }
+    let added: Vec<String> = set.difference(seen).cloned().collect();
+    seen.extend(added.iter().cloned());
+    added
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +143,11 @@ struct TypeInfo {
     name: String,
     fields: HashMap<String, (Visibility, TypeRef)>,
     methods: HashMap<String, (Visibility, bool)>, // (visibility, is_async)
+    // `_` fields, typed or not, and `_` methods
+    private: HashSet<String>,
+    // A class that is neither abstract nor generic, so calling it makes a
+    // value of exactly this type
+    constructible: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -65,6 +159,39 @@ struct FunctionSignature {
     defaults: Vec<bool>,
 }
 
+/// How an expression uses a member, for E0022's suggestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemberUse {
+    Read,
+    Write,
+    Call,
+}
+
+/// Why a binding's value can't be modified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Immutable {
+    /// `const NAME = ...`
+    Constant,
+    /// `let name = freeze(...)`
+    Frozen,
+}
+
+impl Immutable {
+    fn describe(self) -> &'static str {
+        match self {
+            Immutable::Constant => "constant",
+            Immutable::Frozen => "frozen value",
+        }
+    }
+
+    fn help(self) -> &'static str {
+        match self {
+            Immutable::Constant => "Bind the value with `let` first, e.g. let items = [...]",
+            Immutable::Frozen => "Modify a copy instead: let copy = clone(value)",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum AwaitableKind {
     Async,
@@ -99,19 +226,39 @@ impl SemanticAnalyzer {
             external_modules: HashSet::new(),
             current_scope: vec![HashMap::new()],
             awaitable_scopes: vec![HashMap::new()],
+            immutable_scopes: vec![HashMap::new()],
+            narrowed_scopes: vec![HashMap::new()],
             source_file,
             source_code,
             source_map,
             imported_modules: HashMap::new(),
             imported_symbols: HashSet::new(),
+            glob_imported_symbols: HashSet::new(),
             in_error_binding: false,
+            task_scope_depth: 0,
+            scoped_fire: false,
+            fallible_lazies: HashSet::new(),
+            runtime_constants: HashSet::new(),
             type_parameters: vec![HashSet::new()],
             type_constraints: vec![HashMap::new()],
             trait_registry: TraitRegistry::new(),
             json_classes: HashSet::new(),
             type_aliases: HashMap::new(),
             enum_variants: HashMap::new(),
+            abstract_classes: HashSet::new(),
             in_stmt_switch: false,
+            cancel: CancellationToken::new(),
+            inferred_types: Vec::new(),
+            current_callable: None,
+            call_graph: Vec::new(),
+            current_class: None,
+            strict: false,
+            sandbox: None,
+            name_collision: None,
+            errors: Vec::new(),
+            max_errors: 1,
+            reusable: None,
+            validations: HashMap::new(),
         }
     }
 
@@ -152,6 +299,17 @@ impl SemanticAnalyzer {
         if !self.imported_modules.is_empty() {
             self.validate_imports(&program)?;
         }
+        if let Some(policy) = &self.sandbox {
+            self.check_sandbox(&program, policy)?;
+        }
+
+        // Classes implementing an abstract class get the members it passes on
+        let bases = crate::abstract_classes::collect([&program]);
+        crate::abstract_classes::inherit(&mut program, &bases)?;
+        // ...and the methods its `delegate` lines forward
+        crate::delegation::expand(&mut [&mut program])?;
+        // `Class.schema()` and `fields(Class)` become the list of its fields
+        crate::schema::expand(&mut [&mut program]);
 
         // First pass: collect type definitions and function signatures
         self.collect_definitions(&program)?;
@@ -161,6 +319,7 @@ impl SemanticAnalyzer {
         while changed {
             changed = false;
             for item in &mut program.items {
+                self.cancel.check()?;
                 if self.infer_async(item)? {
                     changed = true;
                 }
@@ -169,25 +328,266 @@ impl SemanticAnalyzer {
 
         // Detect fallible functions (those containing 'fail')
         self.detect_fallible_functions(&program);
+        self.resolve_lazy_values(&mut program)?;
+
+        // Record which enclosing locals each nested function uses
+        crate::captures::resolve(&mut program);
+
+        let runtime_blocks = program
+            .items
+            .iter()
+            .filter(|item| matches!(item, TopLevel::Runtime(_)))
+            .count();
+        if runtime_blocks > 1 {
+            return Err(CompilerError::SemanticError(Box::new(
+                "E0708: Only one `runtime { ... }` block is allowed per program.".into(),
+            )));
+        }
+
+        let second_init = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                TopLevel::Init(init) => Some(init),
+                _ => None,
+            })
+            .nth(1);
+        if let Some(init) = second_init {
+            return Err(CompilerError::SemanticError(Box::new(
+                self.error_with_span(
+                    E0712_DUPLICATE_INIT_BLOCK,
+                    "Duplicate init block",
+                    "Only one `init { ... }` block is allowed per module",
+                    init.span,
+                ),
+            )));
+        }
+        for kind in [TestHookKind::Before, TestHookKind::After] {
+            let second_hook = program
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    TopLevel::TestHook(hook) if hook.kind == kind => Some(hook),
+                    _ => None,
+                })
+                .nth(1);
+            if let Some(hook) = second_hook {
+                return Err(CompilerError::SemanticError(Box::new(
+                    self.error_with_span(
+                        E0714_DUPLICATE_TEST_HOOK,
+                        "Duplicate test hook",
+                        &format!(
+                            "Only one `{} {{ ... }}` block is allowed per file",
+                            kind.keyword()
+                        ),
+                        hook.span,
+                    ),
+                )));
+            }
+        }
+
+        self.check_function_name_collisions(&program)?;
 
-        // Third pass: type checking and validation
+        // Third pass: type checking and validation, going on to the next
+        // declaration after an error when collecting several. In incremental
+        // mode each item's key covers the declarations and every item above
+        // it, which is all that validating it reads.
+        let mut key = self
+            .reusable
+            .is_some()
+            .then(|| self.declarations_fingerprint());
+        let mut seen = match key {
+            Some(_) => Seen {
+                async_functions: self.async_functions.clone(),
+                fallible_functions: self.fallible_functions.clone(),
+                json_classes: self.json_classes.clone(),
+            },
+            None => Seen::default(),
+        };
         for item in &program.items {
-            self.validate_item(item)?;
+            self.cancel.check()?;
+            // Imports are checked by `validate_imports`; validating one does nothing
+            if !matches!(item, TopLevel::Import(_)) {
+                key = key.map(|above| crate::incremental::debug_hash(&(above, item)));
+            }
+            let result = match (key, item) {
+                (Some(key), TopLevel::Function(_) | TopLevel::Class(_)) => {
+                    self.validate_item_reusing(item, key, &mut seen)
+                }
+                _ => self.validate_item(item),
+            };
+            match result {
+                Ok(()) => {}
+                Err(err) if err.is_cancellation() || self.max_errors <= 1 => return Err(err),
+                Err(err) => {
+                    key = None;
+                    self.errors.push(err);
+                    if self.errors.len() >= self.max_errors {
+                        break;
+                    }
+                    self.reset_after_error();
+                }
+            }
+        }
+        if !self.errors.is_empty() {
+            return Err(CompilerError::from_errors(std::mem::take(&mut self.errors)));
         }
 
         // Fourth pass: Mark classes that need serde (Phase 2: JSON Typed Parsing)
         self.mark_json_classes(&mut program);
 
+        program.inferred_types = std::mem::take(&mut self.inferred_types);
+        program.call_graph = std::mem::take(&mut self.call_graph);
+        program.signatures = crate::signatures::collect(&program);
+        program.symbols = crate::symbols::SymbolTable::collect(&program);
+
         Ok(program)
     }
 
+    /// Hash of everything validating an item reads besides the item, as it
+    /// stands before the first item is validated
+    fn declarations_fingerprint(&self) -> u64 {
+        fn set(set: &HashSet<String>) -> BTreeSet<&String> {
+            set.iter().collect()
+        }
+        fn map<V>(map: &HashMap<String, V>) -> BTreeMap<&String, &V> {
+            map.iter().collect()
+        }
+        let types: BTreeMap<_, _> = self
+            .types
+            .iter()
+            .map(|(name, info)| {
+                let members = (map(&info.fields), map(&info.methods), set(&info.private));
+                (name, (members, info.constructible))
+            })
+            .collect();
+        let modules: BTreeMap<_, _> = self
+            .imported_modules
+            .iter()
+            .map(|(path, (public, private))| (path, (set(public), set(private))))
+            .collect();
+        let scopes = (
+            self.current_scope.iter().map(map).collect::<Vec<_>>(),
+            self.awaitable_scopes.iter().map(map).collect::<Vec<_>>(),
+            self.immutable_scopes.iter().map(map).collect::<Vec<_>>(),
+            self.narrowed_scopes.iter().map(map).collect::<Vec<_>>(),
+            self.type_parameters.iter().map(set).collect::<Vec<_>>(),
+            self.type_constraints.iter().map(map).collect::<Vec<_>>(),
+        );
+        let names = [
+            &self.async_functions,
+            &self.fallible_functions,
+            &self.fallible_lazies,
+            &self.runtime_constants,
+            &self.external_modules,
+            &self.imported_symbols,
+            &self.glob_imported_symbols,
+            &self.abstract_classes,
+        ]
+        .map(set);
+        crate::incremental::debug_hash(&(
+            types,
+            map(&self.functions),
+            modules,
+            scopes,
+            names,
+            map(&self.type_aliases),
+            map(&self.enum_variants),
+            self.strict,
+            &self.sandbox,
+        ))
+    }
+
+    /// [`Self::validate_item`], or a replay of what it did when the previous
+    /// analysis validated an item with the same `key`
+    fn validate_item_reusing(&mut self, item: &TopLevel, key: u64, seen: &mut Seen) -> Result<()> {
+        let previous = self
+            .reusable
+            .as_mut()
+            .and_then(|reusable| reusable.remove(&key));
+        let validation = match previous {
+            Some(validation) => {
+                self.inferred_types
+                    .extend(validation.inferred_types.iter().cloned());
+                self.call_graph
+                    .extend(validation.call_graph.iter().cloned());
+                self.async_functions
+                    .extend(validation.async_functions.iter().cloned());
+                self.fallible_functions
+                    .extend(validation.fallible_functions.iter().cloned());
+                self.json_classes
+                    .extend(validation.json_classes.iter().cloned());
+                seen.async_functions
+                    .extend(validation.async_functions.iter().cloned());
+                seen.fallible_functions
+                    .extend(validation.fallible_functions.iter().cloned());
+                seen.json_classes
+                    .extend(validation.json_classes.iter().cloned());
+                validation
+            }
+            None => {
+                let inferred = self.inferred_types.len();
+                let calls = self.call_graph.len();
+                self.validate_item(item)?;
+                Validation {
+                    inferred_types: self.inferred_types[inferred..].to_vec(),
+                    call_graph: self.call_graph[calls..].to_vec(),
+                    async_functions: added(&self.async_functions, &mut seen.async_functions),
+                    fallible_functions: added(
+                        &self.fallible_functions,
+                        &mut seen.fallible_functions,
+                    ),
+                    json_classes: added(&self.json_classes, &mut seen.json_classes),
+                }
+            }
+        };
+        self.validations.insert(key, validation);
+        Ok(())
+    }
+
+    /// Reject the first use of a capability `policy` doesn't allow
+    fn check_sandbox(
+        &self,
+        program: &Program,
+        policy: &crate::sandbox::SandboxPolicy,
+    ) -> Result<()> {
+        match crate::sandbox::check(program, policy) {
+            Some(violation) => Err(CompilerError::SemanticError(Box::new(
+                self.error_with_span(
+                    E0021_SANDBOX_VIOLATION,
+                    "Capability not allowed",
+                    &violation.message(),
+                    violation.span,
+                )
+                .with_help(&violation.help()),
+            ))),
+            None => Ok(()),
+        }
+    }
+
     /// Validate all import statements in the program
     fn validate_imports(&mut self, program: &Program) -> Result<()> {
         use crate::ast::TopLevel;
 
+        // Top-level names declared in this file, so imports can be checked
+        // for collisions before the definitions themselves are collected
+        let local_definitions: HashSet<String> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                TopLevel::Function(func) => Some(func.name.clone()),
+                TopLevel::Class(class) => Some(class.name.clone()),
+                TopLevel::Type(type_decl) => Some(type_decl.name.clone()),
+                TopLevel::TypeAlias(alias) => Some(alias.name.clone()),
+                TopLevel::Enum(enum_decl) => Some(enum_decl.name.clone()),
+                TopLevel::ConstDecl(const_decl) => Some(const_decl.name.clone()),
+                _ => None,
+            })
+            .collect();
+
         for item in &program.items {
             if let TopLevel::Import(import) = item {
-                self.validate_import(import)?;
+                self.validate_import(import, &local_definitions)?;
             }
         }
 
@@ -195,7 +595,11 @@ impl SemanticAnalyzer {
     }
 
     /// Validate a single import declaration
-    fn validate_import(&mut self, import: &crate::ast::ImportDecl) -> Result<()> {
+    fn validate_import(
+        &mut self,
+        import: &crate::ast::ImportDecl,
+        local_definitions: &HashSet<String>,
+    ) -> Result<()> {
         use std::path::Path;
 
         // Virtual modules (liva/test, etc.) — use sentinel path lookup
@@ -204,38 +608,42 @@ impl SemanticAnalyzer {
             let module_info = self.imported_modules.get(&vpath);
 
             let (public_symbols, _private_symbols) = module_info.ok_or_else(|| {
-                CompilerError::SemanticError(SemanticErrorInfo::new(
+                CompilerError::SemanticError(Box::new(SemanticErrorInfo::new(
                     "E4004",
                     "Unknown virtual module",
                     &format!(
                         "Module '{}' is not a known built-in module.\nAvailable modules: liva/test",
                         import.source
                     ),
-                ))
+                )))
             })?;
 
             // Validate named imports against virtual module's symbols
             if !import.is_wildcard {
                 for symbol in &import.imports {
                     if !public_symbols.contains(symbol) {
-                        return Err(CompilerError::SemanticError(SemanticErrorInfo::new(
-                            "E4006",
-                            "Symbol not found in module",
-                            &format!(
-                                "'{}' is not exported by '{}'.\nAvailable symbols: {}",
-                                symbol,
-                                import.source,
-                                public_symbols
-                                    .iter()
-                                    .cloned()
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
+                        return Err(CompilerError::SemanticError(Box::new(
+                            SemanticErrorInfo::new(
+                                "E4006",
+                                "Symbol not found in module",
+                                &format!(
+                                    "'{}' is not exported by '{}'.\nAvailable symbols: {}",
+                                    symbol,
+                                    import.source,
+                                    public_symbols
+                                        .iter()
+                                        .cloned()
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ),
                             ),
                         )));
                     }
-                    self.imported_symbols.insert(symbol.clone());
+                    let local = import.local_name(symbol);
+                    self.check_import_collision(local, &import.source, local_definitions)?;
+                    self.imported_symbols.insert(local.to_string());
                     self.functions.insert(
-                        symbol.clone(),
+                        local.to_string(),
                         FunctionSignature {
                             params: vec![],
                             return_type: None,
@@ -248,6 +656,17 @@ impl SemanticAnalyzer {
             return Ok(());
         }
 
+        // Packages from liva.toml are registered under their import source,
+        // embedded standard library modules under their sentinel path
+        let package_info = self
+            .imported_modules
+            .get(&crate::module::package_module_path(&import.source))
+            .or_else(|| {
+                self.imported_modules
+                    .get(&crate::module::std_module_path(&import.source))
+            })
+            .cloned();
+
         // Resolve the import path relative to the current file
         let current_file = Path::new(&self.source_file);
         let current_dir = current_file.parent().unwrap_or_else(|| Path::new("."));
@@ -269,9 +688,13 @@ impl SemanticAnalyzer {
         let canonical_path = import_path.canonicalize().ok();
 
         // Try to find the module by matching against all known modules
-        let module_info = canonical_path
+        let module_info = package_info
             .as_ref()
-            .and_then(|p| self.imported_modules.get(p))
+            .or_else(|| {
+                canonical_path
+                    .as_ref()
+                    .and_then(|p| self.imported_modules.get(p))
+            })
             .or_else(|| {
                 // Fallback: try to find by comparing file names
                 self.imported_modules
@@ -283,20 +706,41 @@ impl SemanticAnalyzer {
         // Check if we have information about this module
         let (public_symbols, private_symbols) = module_info
             .ok_or_else(|| {
-                CompilerError::SemanticError(SemanticErrorInfo::new(
+                CompilerError::SemanticError(Box::new(SemanticErrorInfo::new(
                     "E4004",
                     "Cannot find module",
                     &format!("Module not found: {}\nHint: Make sure the module file exists in the same directory or provide the correct relative path.", import.source),
-                ))
+                )))
             })?;
 
         if import.is_wildcard {
-            // Wildcard import: import * as name
             if let Some(alias) = &import.alias {
+                // Wildcard import: import * as name
                 // All public symbols are available via alias.symbol
                 // We'll handle this in expression validation
                 // For now, just record that we have this namespace
+                self.check_import_collision(alias, &import.source, local_definitions)?;
                 self.imported_symbols.insert(alias.clone());
+            } else {
+                // Wildcard import: import * from "path" — every public
+                // symbol lands in this module's namespace unless a local
+                // definition or explicit import already binds the name
+                for symbol in public_symbols {
+                    if local_definitions.contains(symbol) || self.imported_symbols.contains(symbol)
+                    {
+                        continue;
+                    }
+                    self.glob_imported_symbols.insert(symbol.clone());
+                    self.functions.insert(
+                        symbol.clone(),
+                        FunctionSignature {
+                            params: vec![],
+                            return_type: None,
+                            is_async: false,
+                            defaults: vec![],
+                        },
+                    );
+                }
             }
         } else {
             // Named imports: validate each symbol
@@ -327,12 +771,12 @@ impl SemanticAnalyzer {
                         );
                     }
 
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
 
                 // Check if symbol is private (starts with _)
                 if private_symbols.contains(symbol) {
-                    return Err(CompilerError::SemanticError(
+                    return Err(CompilerError::SemanticError(Box::new(
                         SemanticErrorInfo::new(
                             "E4007",
                             "Cannot import private symbol",
@@ -341,47 +785,20 @@ impl SemanticAnalyzer {
                                 symbol, import.source
                             ),
                         )
-                    ));
-                }
-
-                // Check for name collision with existing symbols
-                if self.functions.contains_key(symbol) || self.types.contains_key(symbol) {
-                    return Err(CompilerError::SemanticError(
-                        SemanticErrorInfo::new(
-                            "E4008",
-                            "Import conflicts with local definition",
-                            &format!(
-                                "Cannot import '{}': a {} with this name is already defined in this module.\nHint: Use an alias for the import: 'import {{ {} as new_name }} from \"{}\"'",
-                                symbol,
-                                if self.functions.contains_key(symbol) { "function" } else { "type" },
-                                symbol,
-                                import.source
-                            ),
-                        )
-                    ));
+                    )));
                 }
 
-                // Check for collision with another import
-                if self.imported_symbols.contains(symbol) {
-                    return Err(CompilerError::SemanticError(
-                        SemanticErrorInfo::new(
-                            "E4009",
-                            "Import conflicts with another import",
-                            &format!(
-                                "Symbol '{}' is imported multiple times.\nHint: Use aliases to distinguish between them: 'import {{ {} as name1 }} from \"module1\"' and 'import {{ {} as name2 }} from \"module2\"'",
-                                symbol, symbol, symbol
-                            ),
-                        )
-                    ));
-                }
+                // Check for collision with local definitions and other imports
+                let local = import.local_name(symbol);
+                self.check_import_collision(local, &import.source, local_definitions)?;
 
                 // Record this symbol as imported
-                self.imported_symbols.insert(symbol.clone());
+                self.imported_symbols.insert(local.to_string());
 
                 // Add to function registry so it can be called
                 // (We don't know the signature, so we'll be permissive)
                 self.functions.insert(
-                    symbol.clone(),
+                    local.to_string(),
                     FunctionSignature {
                         params: vec![],    // Unknown params
                         return_type: None, // Unknown return type
@@ -395,6 +812,43 @@ impl SemanticAnalyzer {
         Ok(())
     }
 
+    /// Reject an import whose local name is already bound in this module,
+    /// either by a top-level definition or by an earlier import
+    fn check_import_collision(
+        &self,
+        local: &str,
+        source: &str,
+        local_definitions: &HashSet<String>,
+    ) -> Result<()> {
+        if local_definitions.contains(local) || self.types.contains_key(local) {
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    "E4008",
+                    "Import conflicts with local definition",
+                    &format!(
+                        "Cannot import '{}': a definition with this name already exists in this module.\nHint: Use an alias for the import: 'import {{ {} as new_name }} from \"{}\"'",
+                        local, local, source
+                    ),
+                )
+            )));
+        }
+
+        if self.imported_symbols.contains(local) {
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    "E4009",
+                    "Import conflicts with another import",
+                    &format!(
+                        "Symbol '{}' is imported multiple times.\nHint: Use aliases to distinguish between them: 'import {{ {} as name1 }} from \"module1\"' and 'import {{ {} as name2 }} from \"module2\"'",
+                        local, local, local
+                    ),
+                )
+            )));
+        }
+
+        Ok(())
+    }
+
     fn collect_definitions(&mut self, program: &Program) -> Result<()> {
         for item in &program.items {
             match item {
@@ -434,7 +888,7 @@ impl SemanticAnalyzer {
                                 && !user_version
                                     .starts_with(internal_ver.split('.').next().unwrap_or(""))
                             {
-                                return Err(CompilerError::SemanticError(
+                                return Err(CompilerError::SemanticError(Box::new(
                                     SemanticErrorInfo::new(
                                         "E9002",
                                         "Internal crate version override",
@@ -452,14 +906,18 @@ impl SemanticAnalyzer {
                                     .with_hint(&format!(
                                         "Internal crates: tokio, serde, serde_json, reqwest, rayon, rand"
                                     )),
-                                ));
+                                )));
                             }
                         }
                     }
                 }
                 TopLevel::Class(class) => {
+                    if class.is_abstract {
+                        self.abstract_classes.insert(class.name.clone());
+                    }
                     let mut fields = HashMap::new();
                     let mut methods = HashMap::new();
+                    let private = private_members(&class.members);
 
                     for member in &class.members {
                         match member {
@@ -486,6 +944,8 @@ impl SemanticAnalyzer {
                             name: class.name.clone(),
                             fields,
                             methods,
+                            private,
+                            constructible: !class.is_abstract && class.type_params.is_empty(),
                         },
                     );
                 }
@@ -518,11 +978,15 @@ impl SemanticAnalyzer {
                             name: type_decl.name.clone(),
                             fields,
                             methods,
+                            private: private_members(&type_decl.members),
+                            constructible: false,
                         },
                     );
                 }
-                TopLevel::ConstDecl(_) => {
-                    // Top-level constants are simple values, no special registration needed
+                TopLevel::ConstDecl(const_decl) => {
+                    // Top-level constants are simple values; only their
+                    // immutability is tracked
+                    self.immutable_scopes[0].insert(const_decl.name.clone(), Immutable::Constant);
                 }
                 TopLevel::Enum(enum_decl) => {
                     // Register enum as a type (with no fields/methods for now)
@@ -532,6 +996,8 @@ impl SemanticAnalyzer {
                             name: enum_decl.name.clone(),
                             fields: HashMap::new(),
                             methods: HashMap::new(),
+                            private: HashSet::new(),
+                            constructible: false,
                         },
                     );
                     // Register enum variant names for exhaustiveness checking
@@ -669,13 +1135,10 @@ impl SemanticAnalyzer {
                 self.expr_contains_async(&assign.target) || self.expr_contains_async(&assign.value)
             }
             Stmt::If(if_stmt) => {
-                self.expr_contains_async(&if_stmt.condition)
-                    || self.contains_async_call(&if_stmt.then_branch)
-                    || if_stmt
-                        .else_branch
-                        .as_ref()
-                        .map(|b| self.contains_async_call(b))
-                        .unwrap_or(false)
+                if_stmt
+                    .branches()
+                    .any(|(condition, _)| self.expr_contains_async(condition))
+                    || if_stmt.bodies().any(|b| self.contains_async_call(b))
             }
             Stmt::While(while_stmt) => {
                 self.expr_contains_async(&while_stmt.condition)
@@ -699,6 +1162,9 @@ impl SemanticAnalyzer {
             Stmt::Throw(throw_stmt) => self.expr_contains_async(&throw_stmt.expr),
             Stmt::Fail(fail_stmt) => self.expr_contains_async(&fail_stmt.expr),
             Stmt::Defer(defer_stmt) => self.stmt_contains_async(&defer_stmt.body),
+            // Leaving a scope awaits its tasks
+            Stmt::Scope(_) => true,
+            Stmt::Mock(mock) => self.expr_contains_async(&mock.value),
             Stmt::Break | Stmt::Continue => false,
             Stmt::Return(ret) => ret
                 .expr
@@ -707,6 +1173,8 @@ impl SemanticAnalyzer {
                 .unwrap_or(false),
             Stmt::Expr(expr_stmt) => self.expr_contains_async(&expr_stmt.expr),
             Stmt::Block(block) => self.contains_async_call_stmt(block),
+            // Declaring a nested function runs none of its body
+            Stmt::Function(_) => false,
         }
     }
 
@@ -788,9 +1256,20 @@ impl SemanticAnalyzer {
             }),
             // B24 fix: check rust { } blocks for .await
             Expr::RustBlock { code } => code.contains(".await"),
-            Expr::Unwrap(inner) => self.expr_contains_async(inner),
+            Expr::Unwrap(inner) | Expr::Cast { expr: inner, .. } => self.expr_contains_async(inner),
             Expr::Try(inner) => self.expr_contains_async(inner),
             Expr::OptionalChain { object, .. } => self.expr_contains_async(object),
+            Expr::If(if_expr) => {
+                self.expr_contains_async(&if_expr.condition)
+                    || [&if_expr.then_branch, &if_expr.else_branch]
+                        .into_iter()
+                        .any(|branch| match branch {
+                            SwitchBody::Expr(expr) => self.expr_contains_async(expr),
+                            SwitchBody::Block(stmts) => {
+                                stmts.iter().any(|s| self.stmt_contains_async(s))
+                            }
+                        })
+            }
             _ => false,
         }
     }
@@ -803,13 +1282,114 @@ impl SemanticAnalyzer {
     fn detect_fallible_functions(&mut self, program: &Program) {
         for item in &program.items {
             if let TopLevel::Function(func) = item {
-                if self.function_contains_fail(&func.body, &func.expr_body) {
+                // An `unsafe rust fn` returning `T!` fails through its Rust `Result`
+                if self.function_contains_fail(&func.body, &func.expr_body)
+                    || (func.unsafe_rust && func.contains_fail)
+                {
                     self.fallible_functions.insert(func.name.clone());
                 }
             }
         }
     }
 
+    /// Marks `lazy` values with a failing initializer and fills in the type
+    /// of unannotated ones, which codegen needs for the static holding them.
+    ///
+    /// A `const` is evaluated at compile time when its initializer is a
+    /// constant expression (see [`crate::consteval`]); any other `const`
+    /// becomes a `lazy` value, so a Rust `const` never gets an initializer
+    /// rustc can't evaluate. Such a `const` must not fail (E0711).
+    fn resolve_lazy_values(&mut self, program: &mut Program) -> Result<()> {
+        // `DEBUG` is folded by desugaring, so either value will do here
+        let mut constants = HashMap::from([(
+            crate::desugaring::DEBUG_CONSTANT.to_string(),
+            crate::consteval::ConstValue::Bool(true),
+        )]);
+        for item in &mut program.items {
+            let TopLevel::ConstDecl(decl) = item else {
+                continue;
+            };
+            if !decl.is_lazy {
+                if let Some(value) = crate::consteval::evaluate(&decl.init, &constants) {
+                    constants.insert(decl.name.clone(), value);
+                    continue;
+                }
+                decl.is_lazy = true;
+                self.runtime_constants.insert(decl.name.clone());
+            }
+            let fails = match &decl.init {
+                Expr::Call(call) => matches!(
+                    call.callee.as_ref(),
+                    Expr::Identifier(name) if self.fallible_functions.contains(name)
+                ),
+                Expr::Identifier(name) => self.fallible_lazies.contains(name),
+                _ => false,
+            };
+            if fails && self.runtime_constants.contains(&decl.name) {
+                return Err(CompilerError::SemanticError(Box::new(
+                    self.error_with_span(
+                        E0711_FALLIBLE_CONSTANT,
+                        "Constant can fail",
+                        &format!(
+                            "The initializer of constant '{}' can fail, and reading a constant takes no error binding",
+                            decl.name
+                        ),
+                        decl.span,
+                    )
+                    .with_help(&format!(
+                        "Declare it `lazy {} = ...` and bind the error where it's read",
+                        decl.name
+                    )),
+                )));
+            }
+            if fails {
+                self.fallible_lazies.insert(decl.name.clone());
+            }
+            if let Some(type_ref) = &decl.type_ref {
+                self.current_scope[0].insert(decl.name.clone(), Some(type_ref.clone()));
+                continue;
+            }
+            let inferred = self
+                .infer_expr_type(&decl.init)
+                .or_else(|| match &decl.init {
+                    Expr::Call(call) => match call.callee.as_ref() {
+                        Expr::Identifier(name) => self.functions.get(name)?.return_type.clone(),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+            match inferred {
+                Some(type_ref) => {
+                    self.current_scope[0].insert(decl.name.clone(), Some(type_ref.clone()));
+                    decl.type_ref = Some(type_ref);
+                }
+                None => {
+                    let (title, kind, keyword) = if self.runtime_constants.contains(&decl.name) {
+                        ("Constant needs a type", "constant", "const")
+                    } else {
+                        ("Lazy value needs a type", "lazy value", "lazy")
+                    };
+                    return Err(CompilerError::SemanticError(Box::new(
+                        self.error_with_span(
+                            E0709_UNTYPED_LAZY_VALUE,
+                            title,
+                            &format!(
+                                "The type of {} '{}' can't be inferred from its initializer",
+                                kind, decl.name
+                            ),
+                            decl.span,
+                        )
+                        .with_help(&format!(
+                            "Annotate it: {} {}: Type = ...",
+                            keyword, decl.name
+                        )),
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Check if a function contains any 'fail' statements
     fn function_contains_fail(&self, body: &Option<BlockStmt>, expr_body: &Option<Expr>) -> bool {
         if let Some(block) = body {
@@ -831,13 +1411,7 @@ impl SemanticAnalyzer {
     fn stmt_contains_fail(&self, stmt: &Stmt) -> bool {
         match stmt {
             Stmt::Fail(_) => true,
-            Stmt::If(if_stmt) => {
-                self.if_body_contains_fail(&if_stmt.then_branch)
-                    || if_stmt
-                        .else_branch
-                        .as_ref()
-                        .map_or(false, |eb| self.if_body_contains_fail(eb))
-            }
+            Stmt::If(if_stmt) => if_stmt.bodies().any(|b| self.if_body_contains_fail(b)),
             Stmt::While(while_stmt) => self.stmt_list_contains_fail(&while_stmt.body.stmts),
             Stmt::For(for_stmt) => self.stmt_list_contains_fail(&for_stmt.body.stmts),
             Stmt::Return(ret) => ret
@@ -846,8 +1420,8 @@ impl SemanticAnalyzer {
                 .map_or(false, |e| self.expr_contains_fail(e)),
             Stmt::Expr(expr_stmt) => self.expr_contains_fail(&expr_stmt.expr),
             Stmt::VarDecl(var) => {
-                // `or fail` makes the containing function fallible
-                if var.or_fail_msg.is_some() {
+                // `or fail` makes the containing function fallible; `.expect()` panics instead
+                if var.or_fail_msg.is_some() && var.combinator != Some(ResultCombinator::Expect) {
                     return true;
                 }
                 // Error binding (`let x, err = ...`) and `or <default>` consume the error
@@ -858,6 +1432,7 @@ impl SemanticAnalyzer {
                 self.expr_contains_fail(&var.init)
             }
             Stmt::Defer(defer_stmt) => self.stmt_contains_fail(&defer_stmt.body),
+            Stmt::Scope(scope) => self.stmt_list_contains_fail(&scope.body.stmts),
             _ => false,
         }
     }
@@ -916,6 +1491,17 @@ impl SemanticAnalyzer {
                     || self.expr_contains_fail(then_expr)
                     || self.expr_contains_fail(else_expr)
             }
+            Expr::If(if_expr) => {
+                self.expr_contains_fail(&if_expr.condition)
+                    || [&if_expr.then_branch, &if_expr.else_branch]
+                        .into_iter()
+                        .any(|branch| match branch {
+                            SwitchBody::Expr(expr) => self.expr_contains_fail(expr),
+                            SwitchBody::Block(stmts) => {
+                                stmts.iter().any(|s| self.stmt_contains_fail(s))
+                            }
+                        })
+            }
             Expr::Fail(_) => true,
             _ => false,
         }
@@ -1045,16 +1631,81 @@ impl SemanticAnalyzer {
 
     fn validate_item(&mut self, item: &TopLevel) -> Result<()> {
         match item {
-            TopLevel::Function(func) => self.validate_function(func),
-            TopLevel::Class(class) => self.validate_class(class),
+            TopLevel::Function(func) => {
+                if func.name == "main" {
+                    self.validate_main_signature(func)?;
+                }
+                self.current_callable = Some(func.name.clone());
+                let result = self.validate_function(func);
+                self.current_callable = None;
+                result
+            }
+            TopLevel::Class(class) => {
+                let result = self.validate_class(class);
+                self.current_callable = None;
+                result
+            }
             TopLevel::Type(type_decl) => self.validate_type_decl(type_decl),
             TopLevel::TypeAlias(alias) => self.validate_type_alias(alias),
             TopLevel::Enum(_) => Ok(()), // Enums are validated at parse time
+            TopLevel::Runtime(runtime) => self.validate_runtime_decl(runtime),
+            TopLevel::Init(init) => self.validate_init(init),
+            TopLevel::ConstDecl(decl) if decl.is_lazy => {
+                // A failure in the initializer is kept in the value, so
+                // fallible calls need no binding here
+                let previous_error_binding = std::mem::replace(&mut self.in_error_binding, true);
+                self.enter_scope();
+                let result = self.validate_expr(&decl.init);
+                self.exit_scope()?;
+                self.in_error_binding = previous_error_binding;
+                result
+            }
             _ => Ok(()),
         }
     }
 
+    /// `init` runs before `main` starts the async runtime, so it must stay
+    /// synchronous (E0713)
+    fn validate_init(&mut self, init: &InitDecl) -> Result<()> {
+        if self.contains_async_call_stmt(&init.body) {
+            return Err(CompilerError::SemanticError(Box::new(
+                self.error_with_span(
+                    E0713_ASYNC_INIT_BLOCK,
+                    "Async call in init block",
+                    "An `init` block runs before the async runtime starts and can't call async functions",
+                    init.span,
+                )
+                .with_help("Make the call at the start of `main` instead"),
+            )));
+        }
+        self.validate_block_stmt(&init.body)
+    }
+
+    fn validate_runtime_decl(&self, runtime: &RuntimeDecl) -> Result<()> {
+        let options = [
+            ("threads", runtime.threads),
+            ("asyncThreads", runtime.async_threads),
+            ("parThreads", runtime.par_threads),
+        ];
+        for (name, value) in options {
+            if matches!(value, Some(count) if count <= 0) {
+                return Err(CompilerError::SemanticError(Box::new(
+                    format!(
+                        "E0704: `{}` runtime option must be a positive integer.",
+                        name
+                    )
+                    .into(),
+                )));
+            }
+        }
+        Ok(())
+    }
+
     fn validate_function(&mut self, func: &FunctionDecl) -> Result<()> {
+        if func.unsafe_rust {
+            self.check_unsafe_rust_boundary(func)?;
+        }
+
         // Enter type parameter scope and register type parameters with constraints
         self.enter_type_param_scope();
 
@@ -1068,7 +1719,7 @@ impl SemanticAnalyzer {
                             suggestions::find_multiple_suggestions(&constraint, &suggestions, 3, 3);
 
                         self.exit_type_param_scope();
-                        return Err(CompilerError::SemanticError(
+                        return Err(CompilerError::SemanticError(Box::new(
                             format!(
                                 "E5001: Unknown trait constraint '{}'. {}Available traits: {}",
                                 constraint,
@@ -1080,7 +1731,7 @@ impl SemanticAnalyzer {
                                 suggestions.join(", ")
                             )
                             .into(),
-                        ));
+                        )));
                     }
 
                     // Expand aliases to underlying traits
@@ -1107,6 +1758,12 @@ impl SemanticAnalyzer {
                 self.validate_type_ref(type_ref, &type_params)?;
             }
         }
+        if self.strict {
+            if let Err(err) = self.check_strict_params(&func.params, None, &func.name, func.span) {
+                self.exit_type_param_scope();
+                return Err(err);
+            }
+        }
 
         // Check return type
         if let Some(return_type) = &func.return_type {
@@ -1130,12 +1787,103 @@ impl SemanticAnalyzer {
         if let Some(expr) = &func.expr_body {
             self.validate_expr(expr)?;
         }
+        self.validate_contracts(func)?;
 
         self.exit_scope()?;
         self.exit_type_param_scope();
         Ok(())
     }
 
+    /// E0023: the signature is all that's checked of an `unsafe rust fn`,
+    /// so every parameter needs a plain name and a declared type
+    fn check_unsafe_rust_boundary(&self, func: &FunctionDecl) -> Result<()> {
+        let problem = if func.name == "main" {
+            Some("'main' can't have a Rust body".to_string())
+        } else {
+            func.params.iter().find_map(|param| match param.name() {
+                None => Some("a destructured parameter can't cross into Rust".to_string()),
+                Some(name) if param.type_ref.is_none() => {
+                    Some(format!("parameter '{}' has no declared type", name))
+                }
+                Some(_) => None,
+            })
+        };
+        let Some(problem) = problem else {
+            return Ok(());
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            self.error_with_span(
+                E0023_UNSAFE_RUST_SIGNATURE,
+                "Unchecked unsafe Rust boundary",
+                &format!("In 'unsafe rust fn {}', {}", func.name, problem),
+                func.span,
+            )
+            .with_help(
+                "Declare it as 'unsafe rust fn name(a: number, b: string): T { ... }'; \
+                 callers are checked against these types",
+            ),
+        )))
+    }
+
+    /// `requires` clauses see the parameters; `ensures` clauses also see
+    /// `result`, the returned value
+    fn validate_contracts(&mut self, func: &FunctionDecl) -> Result<()> {
+        for contract in &func.requires {
+            self.validate_contract("requires", contract)?;
+        }
+        if func.ensures.is_empty() {
+            return Ok(());
+        }
+        self.enter_scope();
+        if func.return_type.is_some() || func.expr_body.is_some() {
+            self.declare_symbol("result", func.return_type.clone());
+        }
+        for contract in &func.ensures {
+            self.validate_contract("ensures", contract)?;
+        }
+        self.exit_scope()
+    }
+
+    fn validate_contract(&mut self, keyword: &str, contract: &Contract) -> Result<()> {
+        if self.lookup_symbol("result").is_none()
+            && crate::captures::used_names(&contract.condition).contains("result")
+        {
+            let message = if keyword == "requires" {
+                "'result' is the returned value, which a 'requires' clause runs before"
+            } else {
+                "'result' is the returned value, but this function returns nothing"
+            };
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E0020_INVALID_CONTRACT,
+                    "Invalid contract",
+                    &format!("{} in '{} {}'", message, keyword, contract.source),
+                )
+                .with_help("Check the returned value in an 'ensures' clause"),
+            )));
+        }
+        self.validate_expr(&contract.condition)?;
+        let Some(condition_type) = self.infer_expr_type(&contract.condition) else {
+            return Ok(());
+        };
+        let tag = condition_type.type_of_name();
+        let known = TYPE_OF_TAGS.contains(&tag.as_str()) || self.types.contains_key(&tag);
+        if !known || tag == "bool" {
+            return Ok(());
+        }
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E5001_TYPE_MISMATCH,
+                &format!("'{}' needs a condition", keyword),
+                &format!(
+                    "'{} {}' is a value of type {}, not a bool",
+                    keyword, contract.source, condition_type
+                ),
+            )
+            .with_help("Write the clause as a comparison, e.g. 'requires n > 0'"),
+        )))
+    }
+
     fn validate_class(&mut self, class: &ClassDecl) -> Result<()> {
         // Enter type parameter scope and register class type parameters with constraints
         self.enter_type_param_scope();
@@ -1150,7 +1898,7 @@ impl SemanticAnalyzer {
                             suggestions::find_multiple_suggestions(&constraint, &suggestions, 3, 3);
 
                         self.exit_type_param_scope();
-                        return Err(CompilerError::SemanticError(
+                        return Err(CompilerError::SemanticError(Box::new(
                             format!(
                                 "E5001: Unknown trait constraint '{}'. {}Available traits: {}",
                                 constraint,
@@ -1162,7 +1910,7 @@ impl SemanticAnalyzer {
                                 suggestions.join(", ")
                             )
                             .into(),
-                        ));
+                        )));
                     }
 
                     // Expand aliases to underlying traits
@@ -1189,6 +1937,20 @@ impl SemanticAnalyzer {
                 Member::Field(field) => {
                     if let Some(type_ref) = &field.type_ref {
                         self.validate_type_ref(type_ref, &type_params)?;
+                    } else if self.strict && field.init.is_none() {
+                        let error = self
+                            .error_with_span(
+                                E5102_STRICT_UNTYPED_FIELD,
+                                "Untyped field",
+                                &format!(
+                                    "Field '{}' of '{}' has no type and no initial value; strict mode does not guess field types from their names",
+                                    field.name, class.name
+                                ),
+                                class.span,
+                            )
+                            .with_hint(&format!("Annotate it, e.g. '{}: string'", field.name));
+                        self.exit_type_param_scope();
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
                 Member::Method(method) => {
@@ -1210,7 +1972,7 @@ impl SemanticAnalyzer {
                     ).with_suggestion(&format!("Use composition instead: add a field of type '{}' to your class", iface_name));
 
                     self.exit_type_param_scope();
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
                 // It's a valid interface - could add validation that all methods are implemented
             } else {
@@ -1229,7 +1991,7 @@ impl SemanticAnalyzer {
                 }
 
                 self.exit_type_param_scope();
-                return Err(CompilerError::SemanticError(error));
+                return Err(CompilerError::SemanticError(Box::new(error)));
             }
         }
 
@@ -1273,13 +2035,13 @@ impl SemanticAnalyzer {
         // 4. Check for circular reference (alias can't reference itself directly)
         if self.type_ref_contains_name(&alias.target_type, &alias.name) {
             self.exit_type_param_scope();
-            return Err(CompilerError::SemanticError(
+            return Err(CompilerError::SemanticError(Box::new(
                 format!(
                     "E0004: Circular type alias: '{}' references itself",
                     alias.name
                 )
                 .into(),
-            ));
+            )));
         }
 
         // 5. Register the alias for later expansion
@@ -1407,6 +2169,9 @@ impl SemanticAnalyzer {
         owner: &str,
         class_type_params: &HashSet<String>,
     ) -> Result<()> {
+        self.current_callable = Some(format!("{}.{}", owner, method.name));
+        let enclosing_class = self.current_class.replace(owner.to_string());
+
         // Register method's own type parameters with constraints
         // Note: Class type parameters are already in scope from validate_class
         for param in &method.type_params {
@@ -1418,7 +2183,7 @@ impl SemanticAnalyzer {
                         let similar =
                             suggestions::find_multiple_suggestions(&constraint, &suggestions, 3, 3);
 
-                        return Err(CompilerError::SemanticError(
+                        return Err(CompilerError::SemanticError(Box::new(
                             format!(
                                 "E5001: Unknown trait constraint '{}'. {}Available traits: {}",
                                 constraint,
@@ -1430,7 +2195,7 @@ impl SemanticAnalyzer {
                                 suggestions.join(", ")
                             )
                             .into(),
-                        ));
+                        )));
                     }
 
                     // Expand aliases to underlying traits
@@ -1459,6 +2224,9 @@ impl SemanticAnalyzer {
                 self.validate_type_ref(type_ref, &all_type_params)?;
             }
         }
+        if self.strict {
+            self.check_strict_params(&method.params, Some(owner), &method.name, method.span)?;
+        }
 
         if let Some(return_type) = &method.return_type {
             self.validate_type_ref(return_type, &all_type_params)?;
@@ -1484,6 +2252,7 @@ impl SemanticAnalyzer {
         }
 
         self.exit_scope()?;
+        self.current_class = enclosing_class;
         Ok(())
     }
 
@@ -1503,22 +2272,80 @@ impl SemanticAnalyzer {
         Ok(())
     }
 
-    fn validate_block_stmt(&mut self, block: &BlockStmt) -> Result<()> {
-        self.enter_scope();
-        for stmt in &block.stmts {
-            self.validate_stmt(stmt)?;
+    /// Validates `body` with the types `narrowing` refines
+    /// The `else if` links and final `else` of `if_stmt`, each seeing every
+    /// earlier condition as false
+    fn validate_else_ifs(&mut self, if_stmt: &IfStmt) -> Result<()> {
+        for link in &if_stmt.else_ifs {
+            self.validate_expr(&link.condition)?;
+            self.check_condition(&link.condition, false)?;
+            let narrowing = self.condition_narrowing(&link.condition, true);
+            self.validate_narrowed_block(&link.body, narrowing)?;
+            let else_narrowing = self.condition_narrowing(&link.condition, false);
+            self.narrow(else_narrowing);
+        }
+        match &if_stmt.else_branch {
+            Some(else_branch) => self.validate_block(else_branch),
+            None => Ok(()),
         }
-        self.exit_scope()?;
-        Ok(())
     }
 
-    fn validate_stmt(&mut self, stmt: &Stmt) -> Result<()> {
-        let empty: HashSet<String> = HashSet::new();
+    fn validate_narrowed_block(
+        &mut self,
+        body: &IfBody,
+        narrowing: Vec<(String, TypeRef)>,
+    ) -> Result<()> {
+        if narrowing.is_empty() {
+            return self.validate_block(body);
+        }
+        self.enter_scope();
+        self.narrow(narrowing);
+        let result = self.validate_block(body);
+        self.exit_scope()?;
+        result
+    }
+
+    /// Validates `expr` with the types `narrowing` refines
+    fn validate_narrowed_expr(
+        &mut self,
+        expr: &Expr,
+        narrowing: Vec<(String, TypeRef)>,
+    ) -> Result<()> {
+        if narrowing.is_empty() {
+            return self.validate_expr(expr);
+        }
+        self.enter_scope();
+        self.narrow(narrowing);
+        let result = self.validate_expr(expr);
+        self.exit_scope()?;
+        result
+    }
+
+    fn validate_block_stmt(&mut self, block: &BlockStmt) -> Result<()> {
+        self.enter_scope();
+        for stmt in &block.stmts {
+            self.validate_stmt(stmt)?;
+        }
+        self.exit_scope()?;
+        Ok(())
+    }
+
+    fn validate_stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        grow_stack(|| self.validate_stmt_kind(stmt))
+    }
+
+    fn validate_stmt_kind(&mut self, stmt: &Stmt) -> Result<()> {
+        self.cancel.check()?;
+        let empty: HashSet<String> = HashSet::new();
 
         match stmt {
             Stmt::VarDecl(var) => {
                 // Validate the init expression (will check fallibility in validate_call_expr)
                 // Note: is_fallible=true means error binding pattern is used, so fallible calls are allowed
+                if let Some(ResultCombinator::MapErr(_)) = &var.combinator {
+                    self.validate_map_err(var)?;
+                }
+
                 let previous_error_binding = self.in_error_binding;
                 if var.is_fallible {
                     self.in_error_binding = true;
@@ -1537,9 +2364,18 @@ impl SemanticAnalyzer {
                             self.validate_json_parse_type_hint(type_hint)?;
                         }
                     }
+                } else if self.strict {
+                    self.check_strict_json_binding(var)?;
                 }
 
-                self.validate_expr(&var.init)?;
+                match &var.init {
+                    // `let n, err = text as int` parses the string
+                    Expr::Cast { expr, target } if var.is_fallible => {
+                        self.validate_expr(expr)?;
+                        self.validate_parse_cast(expr, target)?;
+                    }
+                    init => self.validate_expr(init)?,
+                }
                 self.in_error_binding = previous_error_binding;
 
                 for binding in &var.bindings {
@@ -1558,6 +2394,19 @@ impl SemanticAnalyzer {
                             .or_else(|| self.infer_expr_type(&var.init))
                     };
 
+                    if binding.type_ref.is_none() && !var.is_fallible {
+                        let hint = declared_type
+                            .clone()
+                            .or_else(|| self.call_return_type(&var.init));
+                        if let (Some(name), Some(span), Some(type_ref)) =
+                            (binding.name(), binding.span, hint)
+                        {
+                            if name != "_" {
+                                self.record_inferred_type(span, type_ref);
+                            }
+                        }
+                    }
+
                     // Validate and declare the binding pattern (supports destructuring)
                     self.validate_and_declare_pattern(
                         &binding.pattern,
@@ -1567,6 +2416,24 @@ impl SemanticAnalyzer {
                         binding.span,
                     )?;
                 }
+
+                // `freeze(value)` and copies of a frozen binding share the value
+                let frozen = match &var.init {
+                    Expr::Call(call) => {
+                        matches!(call.callee.as_ref(), Expr::Identifier(name) if name == "freeze")
+                    }
+                    Expr::Identifier(source) => {
+                        self.immutable_binding(source) == Some(Immutable::Frozen)
+                    }
+                    _ => false,
+                };
+                if let ([binding], true) = (var.bindings.as_slice(), frozen) {
+                    if let (Some(name), Some(immutables)) =
+                        (binding.name(), self.immutable_scopes.last_mut())
+                    {
+                        immutables.insert(name.to_string(), Immutable::Frozen);
+                    }
+                }
             }
             Stmt::ConstDecl(const_decl) => {
                 if let Some(type_ref) = &const_decl.type_ref {
@@ -1586,34 +2453,62 @@ impl SemanticAnalyzer {
                     )
                     .with_help(&format!("Consider using a different name or removing the previous declaration of '{}'", const_decl.name));
 
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
+                }
+                if let Some(immutables) = self.immutable_scopes.last_mut() {
+                    immutables.insert(const_decl.name.clone(), Immutable::Constant);
                 }
                 self.update_awaitable_from_expr(&const_decl.name, &const_decl.init)?;
             }
             Stmt::Assign(assign) => {
                 self.validate_assignment_target(&assign.target)?;
+                self.check_immutable_assignment(&assign.target)?;
                 self.validate_expr(&assign.value)?;
                 self.handle_assignment(&assign.target, &assign.value)?;
+                self.widen_on_assignment(&assign.target, &assign.value);
             }
             Stmt::If(if_stmt) => {
                 self.validate_expr(&if_stmt.condition)?;
-                self.validate_block(&if_stmt.then_branch)?;
-                if let Some(else_branch) = &if_stmt.else_branch {
-                    self.validate_block(else_branch)?;
+                self.check_condition(&if_stmt.condition, false)?;
+                let then_narrowing = self.condition_narrowing(&if_stmt.condition, true);
+                self.validate_narrowed_block(&if_stmt.then_branch, then_narrowing)?;
+
+                let else_narrowing = self.condition_narrowing(&if_stmt.condition, false);
+                if !if_stmt.else_ifs.is_empty() {
+                    self.enter_scope();
+                    self.narrow(else_narrowing);
+                    let result = self.validate_else_ifs(if_stmt);
+                    self.exit_scope()?;
+                    result?;
+                } else if let Some(else_branch) = &if_stmt.else_branch {
+                    self.validate_narrowed_block(else_branch, else_narrowing)?;
+                } else if if_stmt.then_branch.always_exits() {
+                    // `if x == null { return }` leaves `x` non-null below
+                    self.narrow(else_narrowing);
                 }
             }
             Stmt::While(while_stmt) => {
                 self.validate_expr(&while_stmt.condition)?;
+                self.check_condition(&while_stmt.condition, false)?;
                 self.validate_block_stmt(&while_stmt.body)?;
             }
             Stmt::For(for_stmt) => {
                 self.validate_expr(&for_stmt.iterable)?;
+                let (var_type, var2_type) = self.loop_var_types(for_stmt);
                 self.enter_scope();
-                if self.declare_symbol(&for_stmt.var, None) {
+                let mut duplicate = None;
+                if self.declare_symbol(&for_stmt.var, var_type) {
+                    duplicate = Some(&for_stmt.var);
+                } else if let Some(var2) = &for_stmt.var2 {
+                    if self.declare_symbol(var2, var2_type) {
+                        duplicate = Some(var2);
+                    }
+                }
+                if let Some(name) = duplicate {
                     self.exit_scope()?;
-                    return Err(CompilerError::SemanticError(
-                        format!("Loop variable '{}' already defined", for_stmt.var).into(),
-                    ));
+                    return Err(CompilerError::SemanticError(Box::new(
+                        format!("Loop variable '{}' already defined", name).into(),
+                    )));
                 }
                 self.validate_block_stmt(&for_stmt.body)?;
                 let validation = self.validate_for_loop(for_stmt);
@@ -1668,6 +2563,11 @@ impl SemanticAnalyzer {
                 if matches!(&expr_stmt.expr, Expr::Switch(_)) {
                     self.in_stmt_switch = true;
                 }
+                self.scoped_fire = self.task_scope_depth > 0
+                    && matches!(
+                        &expr_stmt.expr,
+                        Expr::Call(call) if matches!(call.exec_policy, ExecPolicy::Async | ExecPolicy::Par)
+                    );
                 let result = self.validate_expr(&expr_stmt.expr);
                 self.in_stmt_switch = prev;
                 result?;
@@ -1680,24 +2580,124 @@ impl SemanticAnalyzer {
                         call.exec_policy,
                         ExecPolicy::TaskAsync | ExecPolicy::TaskPar
                     ) {
-                        return Err(CompilerError::SemanticError(
+                        return Err(CompilerError::SemanticError(Box::new(
                             "W0601: task call result is never awaited.".into(),
-                        ));
+                        )));
                     }
                 }
             }
             Stmt::Block(block) => {
                 self.validate_block_stmt(block)?;
             }
+            Stmt::Scope(scope) => {
+                self.task_scope_depth += 1;
+                let result = self.validate_block_stmt(&scope.body);
+                self.task_scope_depth -= 1;
+                result?;
+            }
+            // Test bodies aren't validated, so any `mock` seen here is outside a test
+            Stmt::Mock(mock) => {
+                return Err(CompilerError::SemanticError(Box::new(
+                    SemanticErrorInfo::new(
+                        E0715_MOCK_OUTSIDE_TEST,
+                        "Mock outside a test",
+                        &format!("`mock {}` only works inside a `test` block", mock.target),
+                    )
+                    .with_help("Stubs are cleared before each test and never apply to `main`"),
+                )));
+            }
+            Stmt::Function(func) => {
+                // A nested function's tasks don't belong to the caller's scope
+                let task_scope_depth = std::mem::take(&mut self.task_scope_depth);
+                let result = self.validate_nested_function(func);
+                self.task_scope_depth = task_scope_depth;
+                result?;
+            }
         }
 
         Ok(())
     }
 
+    /// A function declared inside a function body. It becomes a local of the
+    /// enclosing scope, so calls after the declaration (and recursive ones)
+    /// resolve, and its body is checked with the enclosing locals in scope.
+    fn validate_nested_function(&mut self, func: &FunctionDecl) -> Result<()> {
+        let is_async = if let Some(body) = &func.body {
+            self.contains_async_call_stmt(body)
+        } else if let Some(expr) = &func.expr_body {
+            self.expr_contains_async(expr)
+        } else {
+            false
+        };
+        if is_async {
+            return Err(CompilerError::SemanticError(Box::new(
+                self.error_with_span(
+                    E0404_ASYNC_NESTED_FUNCTION,
+                    "Async nested function",
+                    &format!(
+                        "Nested function '{}' calls async code, which only top-level functions can do",
+                        func.name
+                    ),
+                    func.span,
+                )
+                .with_hint("Move the function to the top level"),
+            )));
+        }
+
+        if !func.captures.is_empty() && crate::captures::is_recursive(func) {
+            return Err(CompilerError::SemanticError(Box::new(
+                self.error_with_span(
+                    E0403_RECURSIVE_CAPTURING_FUNCTION,
+                    "Recursive nested function captures locals",
+                    &format!(
+                        "Nested function '{}' calls itself and uses {} of the enclosing function, so it can't be lowered to a Rust closure",
+                        func.name,
+                        func.captures
+                            .iter()
+                            .map(|name| format!("'{}'", name))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    func.span,
+                )
+                .with_hint("Pass the captured values as parameters, or move the function to the top level"),
+            )));
+        }
+
+        self.declare_symbol(&func.name, None);
+        if func.contains_fail {
+            self.fallible_functions.insert(func.name.clone());
+        }
+        self.validate_function(func)
+    }
+
     fn validate_expr(&mut self, expr: &Expr) -> Result<()> {
+        grow_stack(|| self.validate_expr_kind(expr))
+    }
+
+    fn validate_expr_kind(&mut self, expr: &Expr) -> Result<()> {
         match expr {
             Expr::Literal(_) => Ok(()),
             Expr::Identifier(name) => {
+                if !self.in_error_binding
+                    && self.fallible_lazies.contains(name)
+                    && self.find_symbol_scope(name) == Some(0)
+                {
+                    return Err(CompilerError::SemanticError(Box::new(
+                        SemanticErrorInfo::new(
+                            E0701_FALLIBLE_WITHOUT_BINDING,
+                            "Fallible lazy value must be read with error binding",
+                            &format!(
+                                "The initializer of lazy value '{}' can fail, so reading it can fail too",
+                                name
+                            ),
+                        )
+                        .with_help(&format!(
+                            "Change to: let value, err = {0}, or: let value = {0} or fail",
+                            name
+                        )),
+                    )));
+                }
                 // When a task variable is used (referenced by name), mark it as awaited
                 // because the codegen will auto-await it before first use.
                 // Use try-mark (silent) — don't error if already awaited or not an awaitable.
@@ -1707,7 +2707,40 @@ impl SemanticAnalyzer {
             Expr::Fail(expr) => self.validate_expr(expr),
             Expr::Binary { left, right, op } => {
                 self.validate_expr(left)?;
-                self.validate_expr(right)?;
+                // The right side of `&&`/`||` only runs after the left one's check
+                let narrowing = match op {
+                    BinOp::And => self.condition_narrowing(left, true),
+                    BinOp::Or => self.condition_narrowing(left, false),
+                    _ => Vec::new(),
+                };
+                self.validate_narrowed_expr(right, narrowing)?;
+
+                // `||` doubles as `value or default`, so only `&&` is checked
+                if *op == BinOp::And {
+                    self.check_condition(left, false)?;
+                    self.check_condition(right, false)?;
+                }
+
+                if matches!(op, BinOp::Eq | BinOp::Ne) {
+                    self.check_type_of_comparison(left, right)?;
+                }
+
+                if self.strict && *op == BinOp::Add {
+                    self.check_strict_concat(left, right)?;
+                }
+
+                self.check_char_operands(op, left, right)?;
+
+                if *op == BinOp::Pow {
+                    self.check_power_operands(left, right)?;
+                }
+
+                if matches!(
+                    op,
+                    BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr
+                ) {
+                    self.check_bitwise_operands(&op.to_string(), &[left, right])?;
+                }
 
                 // Check constraints for binary operators on generic types
                 self.validate_binary_op_constraints(left, right, op)
@@ -1719,6 +2752,12 @@ impl SemanticAnalyzer {
                     self.validate_await_expr(operand)
                 } else {
                     self.validate_expr(operand)?;
+                    if *op == UnOp::BitNot {
+                        self.check_bitwise_operands("~", &[operand])?;
+                    }
+                    if *op == UnOp::Not {
+                        self.check_condition(operand, true)?;
+                    }
                     // Check constraints for unary operators on generic types
                     self.validate_unary_op_constraints(operand, op)
                 }
@@ -1729,17 +2768,39 @@ impl SemanticAnalyzer {
                 else_expr,
             } => {
                 self.validate_expr(condition)?;
-                self.validate_expr(then_expr)?;
-                self.validate_expr(else_expr)
+                self.check_condition(condition, false)?;
+                let narrowing = self.condition_narrowing(condition, true);
+                self.validate_narrowed_expr(then_expr, narrowing)?;
+                let narrowing = self.condition_narrowing(condition, false);
+                self.validate_narrowed_expr(else_expr, narrowing)
+            }
+            Expr::If(if_expr) => {
+                self.validate_expr(&if_expr.condition)?;
+                self.check_condition(&if_expr.condition, false)?;
+                for branch in [&if_expr.then_branch, &if_expr.else_branch] {
+                    self.enter_scope();
+                    match branch {
+                        SwitchBody::Expr(expr) => self.validate_expr(expr)?,
+                        SwitchBody::Block(stmts) => {
+                            for stmt in stmts {
+                                self.validate_stmt(stmt)?;
+                            }
+                        }
+                    }
+                    self.exit_scope()?;
+                }
+                self.check_branch_types("if", &[&if_expr.then_branch, &if_expr.else_branch])
             }
             Expr::Call(call) => self.validate_call_expr(call),
             Expr::Member { object, property } => {
                 self.validate_expr(object)?;
+                self.check_union_access(object, &format!(".{}", property))?;
+                self.check_private_access(object, property, MemberUse::Read)?;
                 if property == "length" && !self.expr_supports_length(object) {
-                    return Err(CompilerError::SemanticError(
+                    return Err(CompilerError::SemanticError(Box::new(
                         "E0005: `.length` is only available on strings, bytes, and arrays. Consider `.count()` for iterables."
                             .into(),
-                    ));
+                    )));
                 }
                 Ok(())
             }
@@ -1798,10 +2859,34 @@ impl SemanticAnalyzer {
                 }
                 Ok(())
             }
-            Expr::Lambda(lambda) => self.validate_lambda(lambda),
+            Expr::Lambda(lambda) => {
+                // Nor do a lambda's, which may run after the scope ends
+                let task_scope_depth = std::mem::take(&mut self.task_scope_depth);
+                let result = self.validate_lambda(lambda);
+                self.task_scope_depth = task_scope_depth;
+                result
+            }
             Expr::MethodCall(method_call) => {
                 // Validate the object expression
                 self.validate_expr(&method_call.object)?;
+                // Like the loop option, `.parvec({simdWidth: 4})` has nothing
+                // to lower to yet
+                if method_call.adapter_options.simd_width.is_some() {
+                    return Err(CompilerError::SemanticError(Box::new(
+                        "E0707: `simdWidth` adapter option is not supported yet. Use `chunk` or `threads` instead."
+                            .into(),
+                    )));
+                }
+                // Unions print through Display; anything else needs narrowing
+                if method_call.method != "toString" {
+                    let access = format!(".{}()", method_call.method);
+                    self.check_union_access(&method_call.object, &access)?;
+                }
+                self.check_private_access(
+                    &method_call.object,
+                    &method_call.method,
+                    MemberUse::Call,
+                )?;
 
                 // Check if this is response.json() - mark as fallible
                 if method_call.method == "json" {
@@ -1810,6 +2895,56 @@ impl SemanticAnalyzer {
                     // We don't need to store it anywhere, the compiler will handle it
                 }
 
+                if let Some(TypeRef::Simple(receiver)) = self.infer_expr_type(&method_call.object) {
+                    let declares_method = self
+                        .types
+                        .get(&receiver)
+                        .is_some_and(|info| info.methods.contains_key(&method_call.method));
+                    if declares_method {
+                        self.record_call(format!("{}.{}", receiver, method_call.method));
+                    }
+                }
+
+                self.check_mutation_receiver(method_call)?;
+                if method_call.is_array_constructor() {
+                    self.check_array_constructor(method_call)?;
+                }
+
+                // Element-wise array methods: the callback's first parameter is
+                // the element type
+                if matches!(
+                    method_call.method.as_str(),
+                    "map"
+                        | "filter"
+                        | "forEach"
+                        | "find"
+                        | "findIndex"
+                        | "some"
+                        | "every"
+                        | "sortBy"
+                        | "minBy"
+                        | "maxBy"
+                        | "groupBy"
+                ) {
+                    if let (Some(TypeRef::Array(element)), Some(Expr::Lambda(lambda))) = (
+                        self.infer_expr_type(&method_call.object)
+                            .map(Self::strip_optional),
+                        method_call.args.first(),
+                    ) {
+                        if method_call.method == "groupBy" {
+                            self.check_group_key(lambda, &element)?;
+                        }
+                        if let Some(LambdaParam {
+                            type_ref: None,
+                            span: Some(span),
+                            ..
+                        }) = lambda.params.first()
+                        {
+                            self.record_inferred_type(*span, *element);
+                        }
+                    }
+                }
+
                 // Validate method arguments
                 for arg in &method_call.args {
                     self.validate_expr(arg)?;
@@ -1828,26 +2963,27 @@ impl SemanticAnalyzer {
 
                 // Validate all arms
                 for arm in &switch_expr.arms {
-                    // Validate guard if present
-                    if let Some(guard) = &arm.guard {
-                        self.validate_expr(guard)?;
+                    // `switch typeOf(x)` narrows `x` to the case's type
+                    let narrowing =
+                        self.switch_arm_narrowing(&switch_expr.discriminant, &arm.pattern)?;
+                    let narrowed = !narrowing.is_empty();
+                    if narrowed {
+                        self.enter_scope();
+                        self.narrow(narrowing);
                     }
-
-                    // Validate body
-                    match &arm.body {
-                        SwitchBody::Expr(expr) => self.validate_expr(expr)?,
-                        SwitchBody::Block(stmts) => {
-                            for stmt in stmts {
-                                self.validate_stmt(stmt)?;
-                            }
-                        }
+                    let result = self.validate_switch_arm(arm);
+                    if narrowed {
+                        self.exit_scope()?;
                     }
+                    result?;
                 }
 
-                // Check exhaustiveness — skip in statement position (codegen
-                // synthesizes a `_ => {}` arm).
+                // Check exhaustiveness and arm types — skip in statement
+                // position (codegen synthesizes a `_ => {}` arm).
                 if !is_stmt_switch {
                     self.check_switch_exhaustiveness(switch_expr)?;
+                    let arms: Vec<&SwitchBody> = switch_expr.arms.iter().map(|a| &a.body).collect();
+                    self.check_branch_types("switch", &arms)?;
                 }
 
                 Ok(())
@@ -1859,25 +2995,25 @@ impl SemanticAnalyzer {
                 if is_class {
                     // Check if the class/type exists
                     if !self.types.contains_key(object) {
-                        return Err(CompilerError::SemanticError(
+                        return Err(CompilerError::SemanticError(Box::new(
                             format!(
                                 "E0301: Type '{}' not found in '{}::{}'",
                                 object, object, method
                             )
                             .into(),
-                        ));
+                        )));
                     }
                     // Check if method exists on the type (skip 'new' which is constructor)
                     if method != "new" {
                         if let Some(type_info) = self.types.get(object) {
                             if !type_info.methods.contains_key(method) {
-                                return Err(CompilerError::SemanticError(
+                                return Err(CompilerError::SemanticError(Box::new(
                                     format!(
                                         "E0302: Method '{}' not found on type '{}'",
                                         method, object
                                     )
                                     .into(),
-                                ));
+                                )));
                             }
                         }
                     }
@@ -1889,6 +3025,10 @@ impl SemanticAnalyzer {
                 Ok(())
             }
             Expr::Unwrap(inner) => self.validate_expr(inner),
+            Expr::Cast { expr, target } => {
+                self.validate_expr(expr)?;
+                self.validate_cast(expr, target)
+            }
             Expr::Try(inner) => {
                 // `expr?` propagates the error to the caller, so the inner
                 // fallible call is allowed without a separate error binding.
@@ -1903,15 +3043,38 @@ impl SemanticAnalyzer {
     }
 
     fn validate_call_expr(&mut self, call: &CallExpr) -> Result<()> {
+        let scoped_fire = std::mem::take(&mut self.scoped_fire);
         if let Some((first, second)) = Self::extract_modifier_chain(&call.callee) {
-            return Err(CompilerError::SemanticError(
+            return Err(CompilerError::SemanticError(Box::new(
                 format!(
                     "E0602: duplicate execution modifiers '{}' and '{}' on the same call",
                     first, second
                 )
                 .into(),
-            ));
+            )));
+        }
+
+        if let Expr::Identifier(name) = call.callee.as_ref() {
+            if self.functions.contains_key(name) {
+                self.record_call(name.clone());
+            }
+            if self.abstract_classes.contains(name) && self.lookup_symbol(name).is_none() {
+                return Err(CompilerError::SemanticError(Box::new(
+                    SemanticErrorInfo::new(
+                        E0015_ABSTRACT_INSTANTIATION,
+                        "Cannot instantiate abstract class",
+                        &format!("'{}' is abstract, so it can't be instantiated", name),
+                    )
+                    .with_help(&format!(
+                        "Create an instance of a class implementing '{}' instead",
+                        name
+                    )),
+                )));
+            }
         }
+        self.check_type_of_call(call)?;
+        self.check_char_conversion_call(call)?;
+        self.check_union_arguments(call)?;
 
         // Detect and mark HTTP.* calls as async and fallible
         if let Expr::Member {
@@ -1934,7 +3097,7 @@ impl SemanticAnalyzer {
                                     // GET and DELETE: require 1 argument (url)
                                     if call.args.len() != 1 {
                                         let line = 0; // Placeholder for now
-                                        return Err(CompilerError::SemanticError(
+                                        return Err(CompilerError::SemanticError(Box::new(
                                             SemanticErrorInfo {
                                                 location: Some(ErrorLocation {
                                                     file: self.source_file.clone(),
@@ -1958,14 +3121,14 @@ impl SemanticAnalyzer {
                                                 doc_link: None,
                                                 category: None,
                                             }
-                                        ));
+                                        )));
                                     }
                                 }
                                 "post" | "put" => {
                                     // POST and PUT: require 2 arguments (url, body)
                                     if call.args.len() != 2 {
                                         let line = 0; // Placeholder for now
-                                        return Err(CompilerError::SemanticError(
+                                        return Err(CompilerError::SemanticError(Box::new(
                                             SemanticErrorInfo {
                                                 location: Some(ErrorLocation {
                                                     file: self.source_file.clone(),
@@ -1989,7 +3152,7 @@ impl SemanticAnalyzer {
                                                 doc_link: None,
                                                 category: None,
                                             }
-                                        ));
+                                        )));
                                     }
                                 }
                                 _ => {}
@@ -1998,7 +3161,7 @@ impl SemanticAnalyzer {
                         _ => {
                             // Unknown HTTP method
                             let line = 0; // Placeholder for now
-                            return Err(CompilerError::SemanticError(
+                            return Err(CompilerError::SemanticError(Box::new(
                                 SemanticErrorInfo {
                                     location: Some(ErrorLocation {
                                         file: self.source_file.clone(),
@@ -2022,7 +3185,7 @@ impl SemanticAnalyzer {
                                     doc_link: None,
                                     category: None,
                                 }
-                            ));
+                            )));
                         }
                     }
                 }
@@ -2033,10 +3196,12 @@ impl SemanticAnalyzer {
         // This validation applies to ALL call expressions, including those nested in other expressions
         // Exception: if we're in an error binding context (let result, err = ...), allow fallible calls
         // Exception: task async/par calls defer error handling to the await point
-        let is_task_call = matches!(
-            call.exec_policy,
-            ExecPolicy::TaskAsync | ExecPolicy::TaskPar
-        );
+        // Exception: async/par statements inside a `scope` hand their error to the scope
+        let is_task_call = scoped_fire
+            || matches!(
+                call.exec_policy,
+                ExecPolicy::TaskAsync | ExecPolicy::TaskPar
+            );
         if !self.in_error_binding && !is_task_call {
             let func_name = match &*call.callee {
                 Expr::Identifier(name) => Some(name.clone()),
@@ -2086,7 +3251,7 @@ impl SemanticAnalyzer {
                         category: None,
                     };
 
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
             }
         }
@@ -2145,22 +3310,22 @@ impl SemanticAnalyzer {
                 }
 
                 match call.exec_policy {
-                    ExecPolicy::Par => Err(CompilerError::SemanticError(
+                    ExecPolicy::Par => Err(CompilerError::SemanticError(Box::new(
                         "E0603: `par` calls complete eagerly and cannot be awaited.".into(),
-                    )),
+                    ))),
                     ExecPolicy::Normal => Ok(()),
-                    _ => Err(CompilerError::SemanticError(
+                    _ => Err(CompilerError::SemanticError(Box::new(
                         "E0603: expression is not awaitable.".into(),
-                    )),
+                    ))),
                 }
             }
             Expr::Unary {
                 op: UnOp::Await, ..
-            } => Err(CompilerError::SemanticError(
+            } => Err(CompilerError::SemanticError(Box::new(
                 "E0604: expression awaited more than once.".into(),
-            )),
+            ))),
             Expr::Literal(_) | Expr::StringTemplate { .. } => Err(CompilerError::SemanticError(
-                "E0603: cannot await a literal value.".into(),
+                Box::new("E0603: cannot await a literal value.".into()),
             )),
             _ => Ok(()),
         }
@@ -2191,9 +3356,9 @@ impl SemanticAnalyzer {
         match callee {
             Expr::Identifier(name) => {
                 if name == "len" {
-                    return Err(CompilerError::SemanticError(
+                    return Err(CompilerError::SemanticError(Box::new(
                         "W0700: `len(expr)` is deprecated. Use `expr.length` instead.".into(),
-                    ));
+                    )));
                 }
                 if self.lookup_symbol(name).is_none() {
                     self.validate_known_function(name, args.len())?;
@@ -2217,30 +3382,6 @@ impl SemanticAnalyzer {
             self.validate_type_ref(ret_type, &empty)?;
         }
 
-        // E0510: Check for non-Send captures in move lambdas used in parallel contexts
-        if lambda.is_move && !lambda.captures.is_empty() {
-            // For now, we'll emit a warning for any captures in move lambdas
-            // In a full implementation, we'd check if the captured variables are Send
-            // This is a placeholder implementation
-            for capture in &lambda.captures {
-                // Check if this lambda is used in a parallel context
-                // For now, we'll emit a warning for any move lambda with captures
-                // TODO: Implement proper Send trait checking
-                println!("Warning: E0510: Move lambda captures '{}' which may not be Send-safe for parallel execution", capture);
-            }
-        }
-
-        // E0511: Check for non-Sync captures in lambdas used in parallel contexts
-        if !lambda.captures.is_empty() {
-            // For now, we'll emit a warning for any captures in lambdas that might be used in parallel contexts
-            // In a full implementation, we'd check if the captured variables are Sync
-            // This is a placeholder implementation
-            for capture in &lambda.captures {
-                // TODO: Implement proper Sync trait checking and context detection
-                println!("Warning: E0511: Lambda captures '{}' which may not be Sync-safe for parallel execution", capture);
-            }
-        }
-
         self.enter_scope();
 
         for param in &lambda.params {
@@ -2254,9 +3395,9 @@ impl SemanticAnalyzer {
                 BindingPattern::Identifier(name) => {
                     if self.declare_symbol(name, param.type_ref.clone()) {
                         self.exit_scope()?;
-                        return Err(CompilerError::SemanticError(
+                        return Err(CompilerError::SemanticError(Box::new(
                             format!("Parameter '{}' defined multiple times", name).into(),
-                        ));
+                        )));
                     }
                 }
                 BindingPattern::Object(obj_pattern) => {
@@ -2264,10 +3405,10 @@ impl SemanticAnalyzer {
                     for field in &obj_pattern.fields {
                         if self.declare_symbol(&field.binding, None) {
                             self.exit_scope()?;
-                            return Err(CompilerError::SemanticError(
+                            return Err(CompilerError::SemanticError(Box::new(
                                 format!("Binding '{}' defined multiple times", field.binding)
                                     .into(),
-                            ));
+                            )));
                         }
                     }
                 }
@@ -2276,9 +3417,9 @@ impl SemanticAnalyzer {
                     for element in arr_pattern.elements.iter().flatten() {
                         if self.declare_symbol(element, None) {
                             self.exit_scope()?;
-                            return Err(CompilerError::SemanticError(
+                            return Err(CompilerError::SemanticError(Box::new(
                                 format!("Binding '{}' defined multiple times", element).into(),
-                            ));
+                            )));
                         }
                     }
 
@@ -2286,9 +3427,9 @@ impl SemanticAnalyzer {
                     if let Some(rest_name) = &arr_pattern.rest {
                         if self.declare_symbol(rest_name, None) {
                             self.exit_scope()?;
-                            return Err(CompilerError::SemanticError(
+                            return Err(CompilerError::SemanticError(Box::new(
                                 format!("Binding '{}' defined multiple times", rest_name).into(),
-                            ));
+                            )));
                         }
                     }
                 }
@@ -2297,9 +3438,9 @@ impl SemanticAnalyzer {
                     for element in &tuple_pattern.elements {
                         if self.declare_symbol(element, None) {
                             self.exit_scope()?;
-                            return Err(CompilerError::SemanticError(
+                            return Err(CompilerError::SemanticError(Box::new(
                                 format!("Binding '{}' defined multiple times", element).into(),
-                            ));
+                            )));
                         }
                     }
                 }
@@ -2326,9 +3467,9 @@ impl SemanticAnalyzer {
             DataParallelPolicy::Par | DataParallelPolicy::ParVec
         ) && Self::block_contains_await_stmt(&for_stmt.body)
         {
-            return Err(CompilerError::SemanticError(
+            return Err(CompilerError::SemanticError(Box::new(
                 "E0605: `await` is not allowed inside `for par` or `for parvec` loops.".into(),
-            ));
+            )));
         }
 
         Ok(())
@@ -2341,46 +3482,64 @@ impl SemanticAnalyzer {
     ) -> Result<()> {
         if let Some(chunk) = options.chunk {
             if chunk <= 0 {
-                return Err(CompilerError::SemanticError(
+                return Err(CompilerError::SemanticError(Box::new(
                     "E0702: `chunk` option must be a positive integer.".into(),
-                ));
+                )));
             }
         }
 
         if let Some(prefetch) = options.prefetch {
             if prefetch <= 0 {
-                return Err(CompilerError::SemanticError(
+                return Err(CompilerError::SemanticError(Box::new(
                     "E0703: `prefetch` option must be a positive integer.".into(),
-                ));
+                )));
             }
         }
 
         if let Some(thread_option) = &options.threads {
             if let ThreadOption::Count(count) = thread_option {
                 if *count <= 0 {
-                    return Err(CompilerError::SemanticError(
+                    return Err(CompilerError::SemanticError(Box::new(
                         "E0704: `threads` option must be a positive integer when specified.".into(),
-                    ));
+                    )));
                 }
             }
         }
 
         if let Some(simd) = &options.simd_width {
             if !matches!(policy, DataParallelPolicy::Vec | DataParallelPolicy::ParVec) {
-                return Err(CompilerError::SemanticError(
+                return Err(CompilerError::SemanticError(Box::new(
                     "E0705: `simdWidth` option requires `for vec` or `for parvec` policy.".into(),
-                ));
+                )));
             }
 
             if let SimdWidthOption::Width(width) = simd {
                 if *width <= 0 {
-                    return Err(CompilerError::SemanticError(
+                    return Err(CompilerError::SemanticError(Box::new(
                         "E0706: `simdWidth` value must be a positive integer.".into(),
-                    ));
+                    )));
                 }
             }
         }
 
+        // The runtime has no equivalent for these yet; reject them here rather
+        // than silently ignoring them in the generated program.
+        let unsupported = [
+            ("prefetch", options.prefetch.is_some()),
+            ("reduction", options.reduction.is_some()),
+            ("schedule", options.schedule.is_some()),
+            ("simdWidth", options.simd_width.is_some()),
+        ];
+        if let Some((name, _)) = unsupported.iter().find(|(_, present)| *present) {
+            return Err(CompilerError::SemanticError(Box::new(
+                format!(
+                    "E0707: `{}` loop option is not supported yet. Use `chunk` or `threads` instead.",
+                    name
+                )
+                .into(),
+            )));
+        }
+
         Ok(())
     }
 
@@ -2410,12 +3569,10 @@ impl SemanticAnalyzer {
                     || Self::expr_contains_await(&assign.value)
             }
             Stmt::If(if_stmt) => {
-                Self::expr_contains_await(&if_stmt.condition)
-                    || Self::block_contains_await(&if_stmt.then_branch)
-                    || if_stmt
-                        .else_branch
-                        .as_ref()
-                        .map_or(false, |block| Self::block_contains_await(block))
+                if_stmt
+                    .branches()
+                    .any(|(condition, _)| Self::expr_contains_await(condition))
+                    || if_stmt.bodies().any(Self::block_contains_await)
             }
             Stmt::While(while_stmt) => {
                 Self::expr_contains_await(&while_stmt.condition)
@@ -2448,6 +3605,8 @@ impl SemanticAnalyzer {
             Stmt::Throw(throw_stmt) => Self::expr_contains_await(&throw_stmt.expr),
             Stmt::Fail(fail_stmt) => Self::expr_contains_await(&fail_stmt.expr),
             Stmt::Defer(defer_stmt) => Self::stmt_contains_await(&defer_stmt.body),
+            Stmt::Scope(_) => true,
+            Stmt::Mock(mock) => Self::expr_contains_await(&mock.value),
             Stmt::Break | Stmt::Continue => false,
             Stmt::Return(ret) => ret
                 .expr
@@ -2455,6 +3614,7 @@ impl SemanticAnalyzer {
                 .map_or(false, |expr| Self::expr_contains_await(expr)),
             Stmt::Expr(expr_stmt) => Self::expr_contains_await(&expr_stmt.expr),
             Stmt::Block(block) => Self::block_contains_await_stmt(block),
+            Stmt::Function(_) => false,
         }
     }
 
@@ -2529,8 +3689,19 @@ impl SemanticAnalyzer {
                             }
                     })
             }
+            Expr::If(if_expr) => {
+                Self::expr_contains_await(&if_expr.condition)
+                    || [&if_expr.then_branch, &if_expr.else_branch]
+                        .into_iter()
+                        .any(|branch| match branch {
+                            SwitchBody::Expr(expr) => Self::expr_contains_await(expr),
+                            SwitchBody::Block(stmts) => stmts.iter().any(Self::stmt_contains_await),
+                        })
+            }
             Expr::RustBlock { .. } => false,
-            Expr::Unwrap(inner) => Self::expr_contains_await(inner),
+            Expr::Unwrap(inner) | Expr::Cast { expr: inner, .. } => {
+                Self::expr_contains_await(inner)
+            }
             Expr::Try(inner) => Self::expr_contains_await(inner),
             Expr::OptionalChain { object, .. } => Self::expr_contains_await(object),
         }
@@ -2543,7 +3714,10 @@ impl SemanticAnalyzer {
             // Skip validation for imported functions (they have empty params)
             // This is indicated by params being empty AND not being in async/fallible sets
             // (local functions with no params would still be in those sets)
-            if total == 0 && self.imported_symbols.contains(name) {
+            if total == 0
+                && (self.imported_symbols.contains(name)
+                    || self.glob_imported_symbols.contains(name))
+            {
                 // Imported function - skip arity validation
                 return Ok(());
             }
@@ -2556,13 +3730,13 @@ impl SemanticAnalyzer {
             let required = total.saturating_sub(optional);
 
             if arity < required || arity > total {
-                return Err(CompilerError::SemanticError(
+                return Err(CompilerError::SemanticError(Box::new(
                     format!(
                         "Function '{}' expects between {} and {} arguments but {} were provided",
                         name, required, total, arity
                     )
                     .into(),
-                ));
+                )));
             }
         }
 
@@ -2587,31 +3761,51 @@ impl SemanticAnalyzer {
                         error = error.with_suggestion(&format!("Did you mean '{}'?", suggested));
                     }
 
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
             }
-            Expr::Member { object, .. } => {
+            Expr::Member { object, property } => {
                 self.validate_expr(object)?;
+                self.check_private_access(object, property, MemberUse::Write)?;
             }
             Expr::Index { object, index } => {
                 self.validate_expr(object)?;
                 self.validate_expr(index)?;
             }
             _ => {
-                return Err(CompilerError::SemanticError(
+                return Err(CompilerError::SemanticError(Box::new(
                     "Invalid assignment target".into(),
-                ));
+                )));
             }
         }
         Ok(())
     }
 
+    /// Drop what a declaration whose validation failed midway left behind,
+    /// so the next one is checked from a clean slate
+    fn reset_after_error(&mut self) {
+        self.current_scope.truncate(1);
+        self.awaitable_scopes.truncate(1);
+        self.immutable_scopes.truncate(1);
+        self.narrowed_scopes.truncate(1);
+        self.type_parameters.truncate(1);
+        self.type_constraints.truncate(1);
+        self.in_error_binding = false;
+        self.in_stmt_switch = false;
+        self.current_callable = None;
+        self.current_class = None;
+        self.name_collision = None;
+    }
+
     fn enter_scope(&mut self) {
         self.current_scope.push(HashMap::new());
         self.awaitable_scopes.push(HashMap::new());
+        self.immutable_scopes.push(HashMap::new());
+        self.narrowed_scopes.push(HashMap::new());
     }
 
     fn exit_scope(&mut self) -> Result<()> {
+        self.check_name_collision(None)?;
         let awaitables = self.awaitable_scopes.pop().unwrap_or_default();
         let mut unawaited_task: Option<String> = None;
 
@@ -2623,11 +3817,13 @@ impl SemanticAnalyzer {
         }
 
         self.current_scope.pop();
+        self.immutable_scopes.pop();
+        self.narrowed_scopes.pop();
 
         if let Some(name) = unawaited_task {
-            return Err(CompilerError::SemanticError(
+            return Err(CompilerError::SemanticError(Box::new(
                 format!("W0601: task handle '{}' is never awaited.", name).into(),
-            ));
+            )));
         }
 
         Ok(())
@@ -2697,6 +3893,23 @@ impl SemanticAnalyzer {
     }
 
     fn declare_symbol(&mut self, name: &str, ty: Option<TypeRef>) -> bool {
+        if let Some(immutables) = self.immutable_scopes.last_mut() {
+            immutables.remove(name);
+        }
+        if let Some(narrowed) = self.narrowed_scopes.last_mut() {
+            narrowed.remove(name);
+        }
+        if self.name_collision.is_none() && name != "_" {
+            let rust_name = Self::rust_binding_name(name);
+            let existing = self.current_scope.iter().rev().find_map(|scope| {
+                scope
+                    .keys()
+                    .find(|other| *other != name && Self::rust_binding_name(other) == rust_name)
+            });
+            if let Some(existing) = existing {
+                self.name_collision = Some((name.to_string(), existing.clone()));
+            }
+        }
         if let Some(scope) = self.current_scope.last_mut() {
             let existed = scope.contains_key(name);
             scope.insert(name.to_string(), ty);
@@ -2706,93 +3919,642 @@ impl SemanticAnalyzer {
         }
     }
 
-    fn lookup_symbol(&self, name: &str) -> Option<&Option<TypeRef>> {
-        for scope in self.current_scope.iter().rev() {
-            if let Some(entry) = scope.get(name) {
-                return Some(entry);
-            }
+    /// Name codegen gives a binding; all-caps names are constants and keep theirs
+    fn rust_binding_name(name: &str) -> String {
+        if name.chars().all(|c| c.is_uppercase() || c == '_') {
+            name.to_string()
+        } else {
+            crate::codegen::rust_identifier(name)
         }
-        None
     }
 
-    fn find_symbol_scope(&self, name: &str) -> Option<usize> {
-        for index in (0..self.current_scope.len()).rev() {
-            if self.current_scope[index].contains_key(name) {
-                return Some(index);
+    /// Top-level functions are snake_cased too, so `loadUser` and `load_user`
+    /// would be two definitions of one Rust function
+    fn check_function_name_collisions(&mut self, program: &Program) -> Result<()> {
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for item in &program.items {
+            let TopLevel::Function(func) = item else {
+                continue;
+            };
+            match seen.get(&crate::codegen::rust_identifier(&func.name)) {
+                Some(existing) if *existing != func.name => {
+                    self.name_collision = Some((func.name.clone(), existing.to_string()));
+                    return self.check_name_collision(func.span);
+                }
+                _ => {
+                    seen.insert(crate::codegen::rust_identifier(&func.name), &func.name);
+                }
             }
         }
-        None
-    }
-
-    /// Get all variable names currently in scope (for suggestions)
-    fn get_all_variables(&self) -> Vec<String> {
-        let mut vars = Vec::new();
-        for scope in &self.current_scope {
-            vars.extend(scope.keys().cloned());
-        }
-        vars
-    }
-
-    /// Get all function names currently defined (for suggestions)
-    #[allow(dead_code)]
-    fn get_all_functions(&self) -> Vec<String> {
-        self.functions.keys().cloned().collect()
+        Ok(())
     }
 
-    /// Get all type names currently defined (for suggestions)
-    fn get_all_types(&self) -> Vec<String> {
-        self.types.keys().cloned().collect()
+    /// E0011 for a collision recorded by `declare_symbol`
+    fn check_name_collision(&mut self, span: Option<crate::span::Span>) -> Result<()> {
+        let Some((name, existing)) = self.name_collision.take() else {
+            return Ok(());
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            self.error_with_span(
+                E0011_NAME_COLLISION,
+                "Names collide in Rust",
+                &format!(
+                    "'{}' and '{}' are different names in Liva, but both become `{}` in the generated Rust",
+                    name,
+                    existing,
+                    Self::rust_binding_name(&name)
+                ),
+                span,
+            )
+            .with_help(&format!(
+                "Rename '{}' so it differs from '{}' in more than case and underscores",
+                name, existing
+            )),
+        )))
     }
 
-    fn set_awaitable(&mut self, name: &str, info: AwaitableInfo) {
-        if let Some(index) = self.find_symbol_scope(name) {
-            if let Some(scope) = self.awaitable_scopes.get_mut(index) {
-                scope.insert(name.to_string(), info);
+    fn lookup_symbol(&self, name: &str) -> Option<&Option<TypeRef>> {
+        for (scope, narrowed) in self.current_scope.iter().zip(&self.narrowed_scopes).rev() {
+            if let Some(entry) = narrowed.get(name).or_else(|| scope.get(name)) {
+                return Some(entry);
             }
         }
+        None
     }
 
-    fn clear_awaitable(&mut self, name: &str) {
-        if let Some(index) = self.find_symbol_scope(name) {
-            if let Some(scope) = self.awaitable_scopes.get_mut(index) {
-                scope.remove(name);
+    /// Refines the types of bindings for the rest of the current scope
+    fn narrow(&mut self, narrowings: Vec<(String, TypeRef)>) {
+        if let Some(narrowed) = self.narrowed_scopes.last_mut() {
+            for (name, type_ref) in narrowings {
+                narrowed.insert(name, Some(type_ref));
             }
         }
     }
 
-    fn move_awaitable(&mut self, source: &str, target: &str) -> bool {
-        if let Some(index) = self.find_symbol_scope(source) {
-            if let Some(info) = self.awaitable_scopes[index].remove(source) {
-                self.set_awaitable(target, info);
-                return true;
+    /// Bindings `condition` refines when it evaluates to `when_true`:
+    /// `x != null` makes an optional `x` non-null, `typeOf(x) == "int"`
+    /// picks a member of a union. `&&`, `||` and `!` combine checks.
+    fn condition_narrowing(&self, condition: &Expr, when_true: bool) -> Vec<(String, TypeRef)> {
+        match condition {
+            Expr::Binary {
+                op: BinOp::And,
+                left,
+                right,
+            } if when_true => {
+                let mut narrowings = self.condition_narrowing(left, true);
+                narrowings.extend(self.condition_narrowing(right, true));
+                narrowings
+            }
+            Expr::Binary {
+                op: BinOp::Or,
+                left,
+                right,
+            } if !when_true => {
+                let mut narrowings = self.condition_narrowing(left, false);
+                narrowings.extend(self.condition_narrowing(right, false));
+                narrowings
+            }
+            Expr::Unary {
+                op: UnOp::Not,
+                operand,
+            } => self.condition_narrowing(operand, !when_true),
+            Expr::Binary {
+                op: op @ (BinOp::Eq | BinOp::Ne),
+                left,
+                right,
+            } => {
+                // Which way the check must go for the narrowed branch
+                let equal = (*op == BinOp::Eq) == when_true;
+                let (subject, compared) = match (left.as_ref(), right.as_ref()) {
+                    (Expr::Literal(Literal::Null), other) => (other, None),
+                    (other, Expr::Literal(Literal::Null)) => (other, None),
+                    (Expr::Literal(Literal::String(name)), other)
+                    | (other, Expr::Literal(Literal::String(name))) => (other, Some(name)),
+                    _ => return Vec::new(),
+                };
+                match (subject, compared) {
+                    // `x != null`
+                    (Expr::Identifier(name), None) if !equal => {
+                        match self.lookup_symbol(name).cloned().flatten() {
+                            Some(TypeRef::Optional(inner)) => vec![(name.clone(), *inner)],
+                            _ => Vec::new(),
+                        }
+                    }
+                    // `typeOf(x) == "int"`
+                    (Expr::Call(call), Some(type_name)) => {
+                        let Some(name) = Self::type_of_subject(call) else {
+                            return Vec::new();
+                        };
+                        let Some(members) = self.union_members(name) else {
+                            return Vec::new();
+                        };
+                        let (matching, rest): (Vec<TypeRef>, Vec<TypeRef>) = members
+                            .into_iter()
+                            .partition(|member| member.type_of_name() == *type_name);
+                        let remaining = if equal { matching } else { rest };
+                        match remaining.len() {
+                            0 => Vec::new(),
+                            1 => vec![(name.to_string(), remaining[0].clone())],
+                            _ => vec![(name.to_string(), TypeRef::Union(remaining))],
+                        }
+                    }
+                    _ => Vec::new(),
+                }
             }
+            _ => Vec::new(),
         }
-        false
     }
 
-    fn classify_call_awaitable(&self, call: &CallExpr) -> Option<AwaitableKind> {
-        match call.exec_policy {
-            ExecPolicy::Async => Some(AwaitableKind::Async),
-            ExecPolicy::TaskAsync | ExecPolicy::TaskPar => Some(AwaitableKind::Task),
-            ExecPolicy::Normal => {
-                if let Expr::Identifier(name) = call.callee.as_ref() {
-                    if self.async_functions.contains(name) {
-                        Some(AwaitableKind::Async)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
+    /// The identifier inspected by a `typeOf(x)` call
+    fn type_of_subject(call: &CallExpr) -> Option<&str> {
+        match (call.callee.as_ref(), call.args.as_slice()) {
+            (Expr::Identifier(callee), [Expr::Identifier(name)]) if callee == "typeOf" => {
+                Some(name)
             }
             _ => None,
         }
     }
 
-    fn classify_awaitable_expr(&self, expr: &Expr) -> Option<AwaitableKind> {
-        if let Expr::Call(call) = expr {
-            self.classify_call_awaitable(call)
-        } else {
+    /// Member types of a binding whose type is a union, directly or through
+    /// a type alias
+    fn union_members(&self, name: &str) -> Option<Vec<TypeRef>> {
+        self.union_type_members(self.lookup_symbol(name)?.as_ref()?)
+    }
+
+    /// Member types of a union type, written out or through a type alias
+    fn union_type_members(&self, type_ref: &TypeRef) -> Option<Vec<TypeRef>> {
+        match type_ref {
+            TypeRef::Union(members) => Some(members.clone()),
+            TypeRef::Simple(alias) => match self.type_aliases.get(alias) {
+                Some((_, TypeRef::Union(members))) => Some(members.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Members and methods of a union value are only available once a check
+    /// has narrowed it to one member
+    fn check_union_access(&self, object: &Expr, access: &str) -> Result<()> {
+        let Expr::Identifier(name) = object else {
+            return Ok(());
+        };
+        let Some(members) = self.union_members(name) else {
+            return Ok(());
+        };
+        let union = TypeRef::Union(members);
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E5008_UNNARROWED_UNION,
+                "Union value used before narrowing",
+                &format!(
+                    "'{}' is {}, so `{}` isn't known to exist until its type is checked",
+                    name, union, access
+                ),
+            )
+            .with_help(&format!(
+                "Narrow it first: switch typeOf({}) {{ ... }} or if typeOf({}) == \"...\" {{ ... }}",
+                name, name
+            )),
+        )))
+    }
+
+    /// E0022: a `_` field or method of a class used outside the methods of
+    /// that class. Classes implementing an abstract class own what it passes
+    /// on, so they may use its `_` members through `this`.
+    fn check_private_access(&self, object: &Expr, member: &str, usage: MemberUse) -> Result<()> {
+        let bare = member.trim_start_matches('_');
+        if bare.len() == member.len() || bare.is_empty() {
+            return Ok(());
+        }
+        let class = match self.infer_expr_type(object).map(Self::strip_optional) {
+            Some(TypeRef::Simple(name)) | Some(TypeRef::Generic { base: name, .. }) => name,
+            _ => return Ok(()),
+        };
+        let Some(info) = self.types.get(&class) else {
+            return Ok(());
+        };
+        if !info.private.contains(member) || self.current_class.as_deref() == Some(class.as_str()) {
+            return Ok(());
+        }
+
+        let is_method = info.methods.contains_key(member);
+        let mut chars = bare.chars();
+        let capitalized: String = chars
+            .next()
+            .into_iter()
+            .flat_map(char::to_uppercase)
+            .chain(chars)
+            .collect();
+        let public_method = |name: &str| {
+            info.methods
+                .get(name)
+                .is_some_and(|(visibility, _)| *visibility == Visibility::Public)
+        };
+        let (kind, help) = if is_method {
+            let help = if public_method(bare) {
+                format!("Call the public method '{}()' instead", bare)
+            } else {
+                format!(
+                    "Call it from a public method of '{}', or rename it to '{}' to make it public",
+                    class, bare
+                )
+            };
+            ("Method", help)
+        } else if usage == MemberUse::Write {
+            let setter = format!("set{}", capitalized);
+            let help = if public_method(&setter) {
+                format!("Use the public setter '{}(value)' instead", setter)
+            } else {
+                format!(
+                    "Add a public setter to '{}', e.g. {}(value) {{ this.{} = value }}",
+                    class, setter, member
+                )
+            };
+            ("Field", help)
+        } else {
+            let getter = format!("get{}", capitalized);
+            let help = if let Some(name) = [getter.as_str(), bare]
+                .into_iter()
+                .find(|name| public_method(name))
+            {
+                format!("Use the public getter '{}()' instead", name)
+            } else {
+                format!(
+                    "Add a public getter to '{}', e.g. {}() => this.{}",
+                    class, getter, member
+                )
+            };
+            ("Field", help)
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E0022_PRIVATE_ACCESS,
+                "Private member access",
+                &format!(
+                    "{} '{}' is private to '{}'; only methods of '{}' can use it",
+                    kind, member, class, class
+                ),
+            )
+            .with_help(&help),
+        )))
+    }
+
+    /// An argument for a union-typed parameter has to be one of its members
+    fn check_union_arguments(&self, call: &CallExpr) -> Result<()> {
+        let Expr::Identifier(callee) = call.callee.as_ref() else {
+            return Ok(());
+        };
+        let Some(signature) = self.functions.get(callee) else {
+            return Ok(());
+        };
+        for (arg, param) in call.args.iter().zip(&signature.params) {
+            let Some(members) = param.as_ref().and_then(|p| self.union_type_members(p)) else {
+                continue;
+            };
+            let Some(arg_type) = self.infer_expr_type(arg) else {
+                continue;
+            };
+            let tag = arg_type.type_of_name();
+            let known = TYPE_OF_TAGS.contains(&tag.as_str()) || self.types.contains_key(&tag);
+            if !known
+                || self.union_type_members(&arg_type).is_some()
+                || members.iter().any(|member| member.type_of_name() == tag)
+            {
+                continue;
+            }
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5001_TYPE_MISMATCH,
+                    "Argument isn't a member of the parameter's union",
+                    &format!(
+                        "'{}' takes {}, but got a value of type {}",
+                        callee,
+                        TypeRef::Union(members),
+                        arg_type
+                    ),
+                )
+                .with_help("Pass a value of one of the union's member types"),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Members of the union a `switch typeOf(x)` inspects
+    fn type_of_switch_members(&self, switch_expr: &SwitchExpr) -> Option<Vec<TypeRef>> {
+        let Expr::Call(call) = switch_expr.discriminant.as_ref() else {
+            return None;
+        };
+        if !self.is_type_of_call(call) {
+            return None;
+        }
+        self.union_members(Self::type_of_subject(call)?)
+    }
+
+    fn validate_switch_arm(&mut self, arm: &SwitchArm) -> Result<()> {
+        // Validate guard if present
+        if let Some(guard) = &arm.guard {
+            self.validate_expr(guard)?;
+            self.check_condition(guard, false)?;
+        }
+
+        // Validate body
+        match &arm.body {
+            SwitchBody::Expr(expr) => self.validate_expr(expr),
+            SwitchBody::Block(stmts) => {
+                for stmt in stmts {
+                    self.validate_stmt(stmt)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// `typeOf()` takes exactly one value
+    fn check_type_of_call(&self, call: &CallExpr) -> Result<()> {
+        if !self.is_type_of_call(call) || call.args.len() == 1 {
+            return Ok(());
+        }
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E5007_INVALID_TYPE_OF,
+                "typeOf() takes one value",
+                &format!("typeOf() was called with {} arguments", call.args.len()),
+            )
+            .with_help("Pass the value to inspect, e.g. typeOf(value)"),
+        )))
+    }
+
+    /// `charCode(c)` takes a char, or a one-character string as `chars()` and
+    /// `charAt` hand out; `fromCharCode(n)` takes an int code
+    fn check_char_conversion_call(&self, call: &CallExpr) -> Result<()> {
+        let Expr::Identifier(name) = call.callee.as_ref() else {
+            return Ok(());
+        };
+        if !matches!(name.as_str(), "charCode" | "fromCharCode")
+            || self.functions.contains_key(name)
+            || self.lookup_symbol(name).is_some()
+        {
+            return Ok(());
+        }
+        let [arg] = call.args.as_slice() else {
+            return Err(CompilerError::SemanticError(Box::new(
+                format!(
+                    "Function '{}' expects 1 argument but {} were provided",
+                    name,
+                    call.args.len()
+                )
+                .into(),
+            )));
+        };
+        let Some(arg_type) = self.infer_expr_type(arg) else {
+            return Ok(());
+        };
+        let tag = arg_type.type_of_name();
+        let known = TYPE_OF_TAGS.contains(&tag.as_str()) || self.types.contains_key(&tag);
+        let (expected, wanted, help) = if name == "charCode" {
+            (
+                "string",
+                "a char",
+                "Pass a char such as 'A', or an item of text.chars()",
+            )
+        } else {
+            (
+                "int",
+                "an int code",
+                "Pass a code point, e.g. fromCharCode(65) for 'A'",
+            )
+        };
+        if !known || tag == expected {
+            return Ok(());
+        }
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E5001_TYPE_MISMATCH,
+                &format!("{}() needs {}", name, wanted),
+                &format!(
+                    "{}() takes {}, not a value of type {}",
+                    name, wanted, arg_type
+                ),
+            )
+            .with_help(help),
+        )))
+    }
+
+    /// A call of the `typeOf` builtin, not of a user function by that name
+    fn is_type_of_call(&self, call: &CallExpr) -> bool {
+        matches!(call.callee.as_ref(), Expr::Identifier(callee) if callee == "typeOf")
+            && !self.functions.contains_key("typeOf")
+    }
+
+    /// `typeOf(x) == "name"` with a name `typeOf(x)` never returns: not a
+    /// member of `x`'s union, or neither a built-in type tag nor a type
+    fn check_type_of_comparison(&self, left: &Expr, right: &Expr) -> Result<()> {
+        let (call, type_name) = match (left, right) {
+            (Expr::Call(call), Expr::Literal(Literal::String(name)))
+            | (Expr::Literal(Literal::String(name)), Expr::Call(call)) => (call, name),
+            _ => return Ok(()),
+        };
+        if !self.is_type_of_call(call) {
+            return Ok(());
+        }
+        self.check_type_of_name(call, type_name)
+    }
+
+    /// `name` is something `typeOf()` of the call's argument can return
+    fn check_type_of_name(&self, call: &CallExpr, type_name: &str) -> Result<()> {
+        let subject = Self::type_of_subject(call);
+        if let Some(members) = subject.and_then(|name| self.union_members(name)) {
+            let names: Vec<String> = members.iter().map(|member| member.type_of_name()).collect();
+            if names.iter().any(|name| name == type_name) {
+                return Ok(());
+            }
+            let name = subject.unwrap_or_default();
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5007_INVALID_TYPE_OF,
+                    "typeOf() compared with a type the union doesn't have",
+                    &format!(
+                        "'{}' is {}, so typeOf({}) is never \"{}\"",
+                        name,
+                        TypeRef::Union(members.clone()),
+                        name,
+                        type_name
+                    ),
+                )
+                .with_help(&format!("Compare with one of: {}", names.join(", "))),
+            )));
+        }
+        if TYPE_OF_TAGS.contains(&type_name)
+            || self.types.contains_key(type_name)
+            || self.type_aliases.contains_key(type_name)
+        {
+            return Ok(());
+        }
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E5007_INVALID_TYPE_OF,
+                "typeOf() compared with an unknown type",
+                &format!("typeOf() never returns \"{}\"", type_name),
+            )
+            .with_help(&format!(
+                "Compare with a class name or one of: {}",
+                TYPE_OF_TAGS.join(", ")
+            )),
+        )))
+    }
+
+    /// Bindings a `switch typeOf(x)` case refines: `case "int"` makes `x`
+    /// an int in its arm
+    fn switch_arm_narrowing(
+        &self,
+        discriminant: &Expr,
+        pattern: &Pattern,
+    ) -> Result<Vec<(String, TypeRef)>> {
+        let Expr::Call(call) = discriminant else {
+            return Ok(Vec::new());
+        };
+        if !self.is_type_of_call(call) {
+            return Ok(Vec::new());
+        }
+        let tags: Vec<&String> = match pattern {
+            Pattern::Literal(Literal::String(tag)) => vec![tag],
+            Pattern::Or(patterns) => patterns
+                .iter()
+                .filter_map(|p| match p {
+                    Pattern::Literal(Literal::String(tag)) => Some(tag),
+                    _ => None,
+                })
+                .collect(),
+            _ => return Ok(Vec::new()),
+        };
+        for tag in &tags {
+            self.check_type_of_name(call, tag)?;
+        }
+        let (Some(name), [tag]) = (Self::type_of_subject(call), tags.as_slice()) else {
+            return Ok(Vec::new());
+        };
+        let member = self
+            .union_members(name)
+            .and_then(|members| members.into_iter().find(|m| &m.type_of_name() == *tag));
+        Ok(member
+            .map(|m| vec![(name.to_string(), m)])
+            .unwrap_or_default())
+    }
+
+    /// Drops the narrowing of `name` when it's assigned a value that may not
+    /// have the narrowed type
+    fn widen_on_assignment(&mut self, target: &Expr, value: &Expr) {
+        let Expr::Identifier(name) = target else {
+            return;
+        };
+        let value_type = self.infer_expr_type(value).map(|ty| ty.type_of_name());
+        for narrowed in &mut self.narrowed_scopes {
+            let keeps = matches!(
+                (narrowed.get(name), &value_type),
+                (Some(Some(ty)), Some(value_type)) if ty.type_of_name() == *value_type
+            );
+            if !keeps {
+                narrowed.remove(name);
+            }
+        }
+    }
+
+    /// Why `name` can't be modified, if it can't: a local `const` or frozen
+    /// binding, or a top-level constant no local shadows
+    fn immutable_binding(&self, name: &str) -> Option<Immutable> {
+        let scope = self.find_symbol_scope(name).unwrap_or(0);
+        self.immutable_scopes.get(scope)?.get(name).copied()
+    }
+
+    /// The immutable binding at the root of `expr`: `cfg`, `cfg.items` or
+    /// `cfg.items[0]`
+    fn immutable_root<'a>(&self, expr: &'a Expr) -> Option<(&'a str, Immutable)> {
+        match expr {
+            Expr::Identifier(name) => Some((name, self.immutable_binding(name)?)),
+            Expr::Member { object, .. } | Expr::Index { object, .. } => self.immutable_root(object),
+            _ => None,
+        }
+    }
+
+    fn find_symbol_scope(&self, name: &str) -> Option<usize> {
+        for index in (0..self.current_scope.len()).rev() {
+            if self.current_scope[index].contains_key(name) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Get all variable names currently in scope (for suggestions)
+    fn get_all_variables(&self) -> Vec<String> {
+        let mut vars = Vec::new();
+        for scope in &self.current_scope {
+            vars.extend(scope.keys().cloned());
+        }
+        vars
+    }
+
+    /// Get all function names currently defined (for suggestions)
+    #[allow(dead_code)]
+    fn get_all_functions(&self) -> Vec<String> {
+        self.functions.keys().cloned().collect()
+    }
+
+    /// Get all type names currently defined (for suggestions)
+    fn get_all_types(&self) -> Vec<String> {
+        self.types.keys().cloned().collect()
+    }
+
+    fn set_awaitable(&mut self, name: &str, info: AwaitableInfo) {
+        if let Some(index) = self.find_symbol_scope(name) {
+            if let Some(scope) = self.awaitable_scopes.get_mut(index) {
+                scope.insert(name.to_string(), info);
+            }
+        }
+    }
+
+    fn clear_awaitable(&mut self, name: &str) {
+        if let Some(index) = self.find_symbol_scope(name) {
+            if let Some(scope) = self.awaitable_scopes.get_mut(index) {
+                scope.remove(name);
+            }
+        }
+    }
+
+    fn move_awaitable(&mut self, source: &str, target: &str) -> bool {
+        if let Some(index) = self.find_symbol_scope(source) {
+            if let Some(info) = self.awaitable_scopes[index].remove(source) {
+                self.set_awaitable(target, info);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn classify_call_awaitable(&self, call: &CallExpr) -> Option<AwaitableKind> {
+        match call.exec_policy {
+            ExecPolicy::Async => Some(AwaitableKind::Async),
+            ExecPolicy::TaskAsync | ExecPolicy::TaskPar => Some(AwaitableKind::Task),
+            ExecPolicy::Normal => {
+                if let Expr::Identifier(name) = call.callee.as_ref() {
+                    if self.async_functions.contains(name) {
+                        Some(AwaitableKind::Async)
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn classify_awaitable_expr(&self, expr: &Expr) -> Option<AwaitableKind> {
+        if let Expr::Call(call) = expr {
+            self.classify_call_awaitable(call)
+        } else {
             None
         }
     }
@@ -2826,18 +4588,18 @@ impl SemanticAnalyzer {
                     info.state = AwaitState::Awaited;
                     return Ok(());
                 }
-                return Err(CompilerError::SemanticError(
+                return Err(CompilerError::SemanticError(Box::new(
                     format!("E0604: handle '{}' awaited more than once.", name).into(),
-                ));
+                )));
             }
-            return Err(CompilerError::SemanticError(
+            return Err(CompilerError::SemanticError(Box::new(
                 format!("E0603: expression '{}' is not awaitable.", name).into(),
-            ));
+            )));
         }
 
-        Err(CompilerError::SemanticError(
+        Err(CompilerError::SemanticError(Box::new(
             format!("E0603: expression '{}' is not awaitable.", name).into(),
-        ))
+        )))
     }
 
     /// Silently mark a task variable as used/awaited if it's a pending task.
@@ -2915,48 +4677,574 @@ impl SemanticAnalyzer {
                 }
                 Some(TypeRef::Tuple(types))
             }
-            Expr::Identifier(name) => self.lookup_symbol(name).cloned().flatten(),
-            Expr::Member { object, property } => {
-                if property == "length" {
-                    return Some(TypeRef::Simple("number".into()));
-                }
+            Expr::Identifier(name) => match self.lookup_symbol(name) {
+                Some(ty) => ty.clone(),
+                None if name == crate::desugaring::DEBUG_CONSTANT => {
+                    Some(TypeRef::Simple("bool".into()))
+                }
+                None => None,
+            },
+            Expr::Member { object, property } => {
+                if property == "length" {
+                    return Some(TypeRef::Simple("number".into()));
+                }
+
+                let base_type = self.infer_expr_type(object)?;
+                let base_type = Self::strip_optional(base_type);
+
+                // Handle tuple member access (.0, .1, .2, etc.)
+                if let TypeRef::Tuple(types) = &base_type {
+                    if let Ok(index) = property.parse::<usize>() {
+                        if index < types.len() {
+                            return Some(types[index].clone());
+                        }
+                    }
+                    // Invalid tuple index - will error later
+                    return None;
+                }
+
+                // Handle struct/class member access
+                if let TypeRef::Simple(type_name) = base_type {
+                    if let Some(info) = self.types.get(&type_name) {
+                        if let Some((_, field_ty)) = info.fields.get(property) {
+                            return Some(field_ty.clone());
+                        }
+                    }
+                }
+                None
+            }
+            Expr::Index { object, .. } => {
+                let base = self.infer_expr_type(object)?;
+                if let TypeRef::Array(inner) = Self::strip_optional(base) {
+                    Some(*inner)
+                } else {
+                    None
+                }
+            }
+            Expr::Cast { target, .. } => Some(target.clone()),
+            Expr::Binary {
+                op: BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr,
+                left,
+                ..
+            } => self.infer_expr_type(left),
+            Expr::Unary {
+                op: UnOp::BitNot,
+                operand,
+            } => self.infer_expr_type(operand),
+            // A float on either side makes the power a float
+            Expr::Binary {
+                op: BinOp::Pow,
+                left,
+                right,
+            } => {
+                let (left, right) = (self.infer_expr_type(left)?, self.infer_expr_type(right)?);
+                if right.type_of_name() == "float" {
+                    Some(right)
+                } else {
+                    Some(left)
+                }
+            }
+            Expr::If(if_expr) => self
+                .unify_branch_types(&[&if_expr.then_branch, &if_expr.else_branch])
+                .ok()
+                .flatten(),
+            Expr::Switch(switch_expr) => {
+                let arms: Vec<&SwitchBody> = switch_expr.arms.iter().map(|a| &a.body).collect();
+                self.unify_branch_types(&arms).ok().flatten()
+            }
+            Expr::MethodCall(method_call) if method_call.is_array_constructor() => {
+                let element = match (method_call.method.as_str(), method_call.args.as_slice()) {
+                    ("fill", [_, value]) => self.infer_expr_type(value)?,
+                    ("from", [range]) if range.is_range() => TypeRef::Simple("number".into()),
+                    ("toArray", []) => TypeRef::Simple("number".into()),
+                    _ => return None,
+                };
+                Some(TypeRef::Array(Box::new(element)))
+            }
+            // Liva hands out the characters of a string as one-character strings
+            Expr::MethodCall(method_call)
+                if matches!(
+                    (method_call.method.as_str(), method_call.args.len()),
+                    ("chars", 0) | ("charAt", 1)
+                ) && matches!(
+                    self.infer_expr_type(&method_call.object).map(Self::strip_optional),
+                    Some(TypeRef::Simple(name)) if name == "string"
+                ) =>
+            {
+                let character = TypeRef::Simple("string".into());
+                if method_call.method == "chars" {
+                    Some(TypeRef::Array(Box::new(character)))
+                } else {
+                    Some(character)
+                }
+            }
+            Expr::MethodCall(method_call) => {
+                let element = self.array_element_type(&method_call.object)?;
+                match (method_call.method.as_str(), method_call.args.as_slice()) {
+                    ("enumerate", []) => Some(TypeRef::Array(Box::new(TypeRef::Tuple(vec![
+                        TypeRef::Simple("number".into()),
+                        element,
+                    ])))),
+                    ("zip", [other]) => {
+                        let other = self.array_element_type(other)?;
+                        Some(TypeRef::Array(Box::new(TypeRef::Tuple(vec![
+                            element, other,
+                        ]))))
+                    }
+                    ("windows" | "chunks", [_]) => {
+                        Some(TypeRef::Array(Box::new(TypeRef::Array(Box::new(element)))))
+                    }
+                    ("pop", []) | ("removeAt", [_]) => Some(element),
+                    _ => None,
+                }
+            }
+            Expr::Call(call) => match (call.callee.as_ref(), call.args.as_slice()) {
+                (Expr::Identifier(name), [a, b]) if name == "zip" => {
+                    let (a, b) = (self.array_element_type(a)?, self.array_element_type(b)?);
+                    Some(TypeRef::Array(Box::new(TypeRef::Tuple(vec![a, b]))))
+                }
+                (Expr::Identifier(name), [value]) if name == "clone" || name == "freeze" => {
+                    self.infer_expr_type(value)
+                }
+                (Expr::Identifier(name), [_]) if name == "typeOf" => {
+                    Some(TypeRef::Simple("string".into()))
+                }
+                (Expr::Identifier(name), [_]) if name == "charCode" => {
+                    Some(TypeRef::Simple("number".into()))
+                }
+                (Expr::Identifier(name), [_]) if name == "fromCharCode" => {
+                    Some(TypeRef::Simple("char".into()))
+                }
+                // `Person(...)` makes a Person
+                (Expr::Identifier(name), _)
+                    if self.lookup_symbol(name).is_none()
+                        && !self.functions.contains_key(name)
+                        && self.types.get(name).is_some_and(|info| info.constructible) =>
+                {
+                    Some(TypeRef::Simple(name.clone()))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn array_element_type(&self, expr: &Expr) -> Option<TypeRef> {
+        match Self::strip_optional(self.infer_expr_type(expr)?) {
+            TypeRef::Array(element) => Some(*element),
+            _ => None,
+        }
+    }
+
+    /// Types of the loop variables of `for var in iterable` / `for var, var2
+    /// in iterable`: an array's elements, an array's index and element, the
+    /// halves of a pair from `enumerate()`/`zip()`, or a map's keys and values
+    fn loop_var_types(&self, for_stmt: &ForStmt) -> (Option<TypeRef>, Option<TypeRef>) {
+        let Some(iterable) = self
+            .infer_expr_type(&for_stmt.iterable)
+            .map(Self::strip_optional)
+        else {
+            return (None, None);
+        };
+        match (iterable, for_stmt.var2.is_some()) {
+            // `[]` has no element type to go on
+            (TypeRef::Array(element), _) if element.to_string() == "unknown" => (None, None),
+            (TypeRef::Array(element), true) => match *element {
+                TypeRef::Tuple(mut pair) if pair.len() == 2 => {
+                    let second = pair.pop();
+                    (pair.pop(), second)
+                }
+                element => (Some(TypeRef::Simple("number".into())), Some(element)),
+            },
+            (TypeRef::Array(element), false) => (Some(*element), None),
+            (TypeRef::Map(key, value), true) => (Some(*key), Some(*value)),
+            _ => (None, None),
+        }
+    }
+
+    /// `push`, `pop`, `insert`, `removeAt`, `clear` and the map and set
+    /// updates change their receiver in place, so it has to be a mutable
+    /// binding. A `const` compiles to a Rust constant and an array literal to
+    /// a temporary, so the change would be silently lost; a frozen value
+    /// would panic.
+    fn check_mutation_receiver(&self, call: &MethodCallExpr) -> Result<()> {
+        if !matches!(
+            call.method.as_str(),
+            "push" | "pop" | "insert" | "removeAt" | "clear" | "set" | "add" | "delete"
+        ) {
+            return Ok(());
+        }
+        let (receiver, help) = match call.object.as_ref() {
+            Expr::ArrayLiteral(_) => ("an array literal".to_string(), Immutable::Constant.help()),
+            object => match self.immutable_root(object) {
+                Some((name, kind)) => (format!("{} '{}'", kind.describe(), name), kind.help()),
+                None => return Ok(()),
+            },
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E0008_IMMUTABLE_RECEIVER,
+                "Immutable receiver",
+                &format!(
+                    "`{}` modifies its receiver, but is called on {}",
+                    call.method, receiver
+                ),
+            )
+            .with_help(help),
+        )))
+    }
+
+    /// `main` becomes Rust's `fn main`, which takes no arguments and can only
+    /// return an exit code; a fallible `main` exits with status 1 on failure
+    fn validate_main_signature(&self, func: &FunctionDecl) -> Result<()> {
+        let returns = match &func.return_type {
+            Some(TypeRef::Fallible(inner)) => Some(inner.as_ref()),
+            other => other.as_ref(),
+        };
+        let valid_return = match returns {
+            None => true,
+            Some(TypeRef::Simple(name)) => matches!(
+                name.as_str(),
+                "void"
+                    | "int"
+                    | "number"
+                    | "i8"
+                    | "i16"
+                    | "i32"
+                    | "i64"
+                    | "u8"
+                    | "u16"
+                    | "u32"
+                    | "u64"
+                    | "isize"
+                    | "usize"
+            ),
+            Some(_) => false,
+        };
+        let detail = if !func.params.is_empty() {
+            "main() can't take parameters; read command-line arguments with Sys.args()".to_string()
+        } else if !valid_return {
+            format!(
+                "main() returns '{}', but can only return nothing or an int exit code",
+                returns.map(|ty| ty.to_string()).unwrap_or_default()
+            )
+        } else {
+            return Ok(());
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            self.error_with_span(
+                E0009_INVALID_MAIN_SIGNATURE,
+                "Invalid main signature",
+                &detail,
+                func.span,
+            ),
+        )))
+    }
+
+    /// Reassigning a constant, and field and element assignments through a
+    /// constant or frozen binding. A frozen binding itself can be reassigned.
+    fn check_immutable_assignment(&self, target: &Expr) -> Result<()> {
+        let Some((name, kind)) = self.immutable_root(target) else {
+            return Ok(());
+        };
+        let message = match (target, kind) {
+            (Expr::Identifier(_), Immutable::Frozen) => return Ok(()),
+            (Expr::Identifier(_), Immutable::Constant) => {
+                format!("Cannot reassign constant '{}'", name)
+            }
+            _ => format!("Cannot modify {} '{}'", kind.describe(), name),
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(E0008_IMMUTABLE_RECEIVER, "Immutable receiver", &message)
+                .with_help(kind.help()),
+        )))
+    }
+
+    /// `groupBy` keys go into a hash map, which needs exact equality; NaN
+    /// isn't equal to itself, so a `float` key is rejected
+    fn check_group_key(&mut self, lambda: &LambdaExpr, element: &TypeRef) -> Result<()> {
+        let (Some(name), LambdaBody::Expr(key)) =
+            (lambda.params.first().and_then(|p| p.name()), &lambda.body)
+        else {
+            return Ok(());
+        };
+        self.enter_scope();
+        self.declare_symbol(name, Some(element.clone()));
+        let key_type = self.infer_expr_type(key);
+        self.exit_scope()?;
+
+        if matches!(&key_type, Some(TypeRef::Simple(t)) if t == "float") {
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5006_FLOAT_GROUP_KEY,
+                    "Float group key",
+                    "`groupBy` can't group by a `float` key",
+                )
+                .with_help(&format!(
+                    "Round the key: groupBy({0} => ... as int), or use its text: groupBy({0} => ... as string)",
+                    name
+                )),
+            )));
+        }
+        Ok(())
+    }
+
+    /// `Array.fill(count, value)` needs a numeric count and `Array.from` a
+    /// range; `range.toArray()` always works
+    fn check_array_constructor(&self, call: &MethodCallExpr) -> Result<()> {
+        let (usage, problem) = match (call.method.as_str(), call.args.as_slice()) {
+            ("toArray", _) => return Ok(()),
+            ("fill", [count, _]) => {
+                let count_type = self.infer_expr_type(count).map(Self::strip_optional);
+                match count_type {
+                    Some(TypeRef::Simple(name)) if !matches!(name.as_str(), "int" | "number") => (
+                        "Array.fill(count, value)",
+                        format!("The count of `Array.fill` must be an int, not `{}`", name),
+                    ),
+                    _ => return Ok(()),
+                }
+            }
+            ("fill", args) => (
+                "Array.fill(count, value)",
+                format!(
+                    "`Array.fill` takes a count and a value, not {} arguments",
+                    args.len()
+                ),
+            ),
+            ("from", [range]) if range.is_range() => return Ok(()),
+            ("from", _) => (
+                "Array.from(start..end)",
+                "`Array.from` takes a range, such as `0..n`".to_string(),
+            ),
+            _ => return Ok(()),
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E5009_INVALID_ARRAY_CONSTRUCTOR,
+                "Invalid array constructor",
+                &problem,
+            )
+            .with_help(&format!("Call it as {}", usage)),
+        )))
+    }
+
+    /// `.mapErr(fn)` rewrites the `err` of an error binding, so it needs one,
+    /// and can't apply to a task whose error only arrives when awaited
+    fn validate_map_err(&self, var: &VarDecl) -> Result<()> {
+        if var.bindings.len() != 2 {
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E0710_INVALID_RESULT_COMBINATOR,
+                    "mapErr needs an error binding",
+                    "`.mapErr(fn)` rewrites the error, so the error must be bound to a variable",
+                )
+                .with_help("Change to: let value, err = load(path).mapErr(e => $\"loading: {e}\")"),
+            )));
+        }
+        if let Expr::Call(call) = &var.init {
+            if call.exec_policy != ExecPolicy::Normal {
+                return Err(CompilerError::SemanticError(Box::new(
+                    SemanticErrorInfo::new(
+                        E0710_INVALID_RESULT_COMBINATOR,
+                        "mapErr can't be applied to a task",
+                        "The error of an async or parallel call only exists once the task is awaited",
+                    )
+                    .with_help("Bind the task's error first, then rewrite it where you check it"),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks an `as` conversion outside error binding: the target must be
+    /// `int`, `float` or `string`, and the source can't be a string to parse
+    fn validate_cast(&self, expr: &Expr, target: &TypeRef) -> Result<()> {
+        let target_name = Self::cast_target(target)?;
+        let source = self.infer_expr_type(expr).map(Self::strip_optional);
+        if matches!(&source, Some(TypeRef::Simple(name)) if name == "char")
+            && target_name == "float"
+        {
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5005_INVALID_CAST,
+                    "Char can't become a float",
+                    "A char converts to its code with `as int` or charCode(), not to a float",
+                )
+                .with_help("Convert the code instead: charCode(c) as float"),
+            )));
+        }
+        let parses = matches!(&source, Some(TypeRef::Simple(name)) if name == "string");
+        if parses && target_name != "string" {
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E0701_FALLIBLE_WITHOUT_BINDING,
+                    "String conversion must be called with error binding",
+                    &format!(
+                        "Converting a string with `as {}` fails when the text isn't a number",
+                        target_name
+                    ),
+                )
+                .with_help(&format!(
+                    "Change to: let value, err = text as {0}, or give a default: let value = text as {0} or 0",
+                    target_name
+                )),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks `let value, err = source as int`, which parses a string
+    fn validate_parse_cast(&self, expr: &Expr, target: &TypeRef) -> Result<()> {
+        let target_name = Self::cast_target(target)?;
+        let source = self.infer_expr_type(expr).map(Self::strip_optional);
+        let non_string = match &source {
+            Some(TypeRef::Simple(name)) => name != "string",
+            Some(_) => true,
+            None => false,
+        };
+        if target_name == "string" || non_string {
+            let source_name = source.map_or_else(|| "value".to_string(), |t| format!("`{}`", t));
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5005_INVALID_CAST,
+                    "Conversion can't fail",
+                    &format!(
+                        "Converting a {} with `as {}` always succeeds, so it takes no error binding",
+                        source_name, target_name
+                    ),
+                )
+                .with_help(&format!("Change to: let value = source as {}", target_name)),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Name of a supported `as` target
+    fn cast_target(target: &TypeRef) -> Result<&str> {
+        match target {
+            TypeRef::Simple(name) if matches!(name.as_str(), "int" | "float" | "string") => {
+                Ok(name)
+            }
+            _ => Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5005_INVALID_CAST,
+                    "Unsupported conversion",
+                    &format!("Cannot convert a value with `as {}`", target),
+                )
+                .with_hint("Convert with `as int`, `as float` or `as string`"),
+            ))),
+        }
+    }
 
-                let base_type = self.infer_expr_type(object)?;
-                let base_type = Self::strip_optional(base_type);
+    /// Value type of an `if`/`switch` branch: the expression, or a block's
+    /// trailing expression or `return`
+    fn branch_type(&self, branch: &SwitchBody) -> Option<TypeRef> {
+        match branch {
+            SwitchBody::Expr(expr) => self.infer_expr_type(expr),
+            SwitchBody::Block(stmts) => match stmts.last()? {
+                Stmt::Expr(expr_stmt) => self.infer_expr_type(&expr_stmt.expr),
+                Stmt::Return(ret) => self.infer_expr_type(ret.expr.as_ref()?),
+                _ => None,
+            },
+        }
+    }
 
-                // Handle tuple member access (.0, .1, .2, etc.)
-                if let TypeRef::Tuple(types) = &base_type {
-                    if let Ok(index) = property.parse::<usize>() {
-                        if index < types.len() {
-                            return Some(types[index].clone());
-                        }
-                    }
-                    // Invalid tuple index - will error later
-                    return None;
+    /// Common type of the branches of an `if`/`switch` used as a value
+    ///
+    /// `Ok(None)` when some branch can't be inferred; `Err` carries the first
+    /// two primitive types that can't be unified. `int` and `float` unify to
+    /// `float`.
+    fn unify_branch_types(
+        &self,
+        branches: &[&SwitchBody],
+    ) -> std::result::Result<Option<TypeRef>, (TypeRef, TypeRef)> {
+        let mut unified: Option<TypeRef> = None;
+        let mut complete = true;
+        for branch in branches {
+            let Some(ty) = self.branch_type(branch) else {
+                complete = false;
+                continue;
+            };
+            let Some(prev) = unified.take() else {
+                unified = Some(ty);
+                continue;
+            };
+            let family = |t: &TypeRef| match t {
+                TypeRef::Simple(name) => match name.as_str() {
+                    "number" | "int" | "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32"
+                    | "u64" | "usize" => Some("int"),
+                    "float" | "f32" | "f64" => Some("float"),
+                    "string" | "String" => Some("string"),
+                    "bool" => Some("bool"),
+                    "char" => Some("char"),
+                    _ => None,
+                },
+                _ => None,
+            };
+            unified = Some(match (family(&prev), family(&ty)) {
+                _ if prev == ty => prev,
+                (Some(a), Some(b)) if a == b => prev,
+                (Some("int"), Some("float")) => ty,
+                (Some("float"), Some("int")) => prev,
+                (Some(_), Some(_)) => return Err((prev, ty)),
+                _ => {
+                    complete = false;
+                    prev
                 }
+            });
+        }
+        Ok(unified.filter(|_| complete))
+    }
 
-                // Handle struct/class member access
-                if let TypeRef::Simple(type_name) = base_type {
-                    if let Some(info) = self.types.get(&type_name) {
-                        if let Some((_, field_ty)) = info.fields.get(property) {
-                            return Some(field_ty.clone());
-                        }
-                    }
-                }
-                None
-            }
-            Expr::Index { object, .. } => {
-                let base = self.infer_expr_type(object)?;
-                if let TypeRef::Array(inner) = Self::strip_optional(base) {
-                    Some(*inner)
-                } else {
-                    None
-                }
-            }
+    /// E5004 when the branches of an `if`/`switch` value disagree on its type
+    fn check_branch_types(&self, kind: &str, branches: &[&SwitchBody]) -> Result<()> {
+        match self.unify_branch_types(branches) {
+            Err((first, second)) => Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5004_BRANCH_TYPE_MISMATCH,
+                    "Mismatched branch types",
+                    &format!(
+                        "`{}` branches produce different types: `{}` and `{}`",
+                        kind, first, second
+                    ),
+                )
+                .with_hint(
+                    "Make every branch produce the same type, e.g. convert with toString(value)",
+                ),
+            ))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    /// Declared return type of a call to a known top-level function
+    fn call_return_type(&self, expr: &Expr) -> Option<TypeRef> {
+        match expr {
+            Expr::Call(call) => match call.callee.as_ref() {
+                Expr::Identifier(name) => self.functions.get(name)?.return_type.clone(),
+                _ => None,
+            },
             _ => None,
         }
     }
 
+    /// Remember that the callable being validated calls `callee`
+    fn record_call(&mut self, callee: String) {
+        let Some(caller) = self.current_callable.clone() else {
+            return;
+        };
+        let edge = CallEdge { caller, callee };
+        if !self.call_graph.contains(&edge) {
+            self.call_graph.push(edge);
+        }
+    }
+
+    /// Remember a type for editor hints, skipping ones inference gave up on
+    fn record_inferred_type(&mut self, span: crate::span::Span, type_ref: TypeRef) {
+        if !type_ref.to_string().contains("unknown") {
+            self.inferred_types.push(InferredType { span, type_ref });
+        }
+    }
+
     fn strip_optional(ty: TypeRef) -> TypeRef {
         match ty {
             TypeRef::Optional(inner) => Self::strip_optional(*inner),
@@ -3032,8 +5320,9 @@ impl SemanticAnalyzer {
                         span
                     )
                     .with_help(&format!("Consider using a different name or removing the previous declaration of '{}'", name));
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
+                self.check_name_collision(span)?;
 
                 if is_fallible {
                     self.clear_awaitable(name);
@@ -3085,7 +5374,7 @@ impl SemanticAnalyzer {
                                             .collect::<Vec<_>>()
                                             .join(", ")
                                     ));
-                                return Err(CompilerError::SemanticError(error));
+                                return Err(CompilerError::SemanticError(Box::new(error)));
                             }
                         }
                     }
@@ -3109,7 +5398,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help("Each binding in a destructuring pattern must be unique");
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
 
@@ -3133,7 +5422,7 @@ impl SemanticAnalyzer {
                             span
                         )
                         .with_help(&format!("Consider using a different name or removing the previous declaration of '{}'", field.binding));
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
 
                     if !is_fallible {
@@ -3161,7 +5450,7 @@ impl SemanticAnalyzer {
                             span,
                         )
                         .with_help("Array destructuring can only be used with array types");
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
 
                 // Check for duplicate bindings
@@ -3185,7 +5474,7 @@ impl SemanticAnalyzer {
                                 .with_help(
                                     "Each binding in a destructuring pattern must be unique",
                                 );
-                            return Err(CompilerError::SemanticError(error));
+                            return Err(CompilerError::SemanticError(Box::new(error)));
                         }
                     }
                 }
@@ -3200,7 +5489,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help("Each binding in a destructuring pattern must be unique");
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
 
@@ -3228,7 +5517,7 @@ impl SemanticAnalyzer {
                                 span
                             )
                             .with_help(&format!("Consider using a different name or removing the previous declaration of '{}'", name));
-                            return Err(CompilerError::SemanticError(error));
+                            return Err(CompilerError::SemanticError(Box::new(error)));
                         }
 
                         if !is_fallible {
@@ -3248,7 +5537,7 @@ impl SemanticAnalyzer {
                             span
                         )
                         .with_help(&format!("Consider using a different name or removing the previous declaration of '{}'", rest));
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
 
                     if !is_fallible {
@@ -3272,7 +5561,7 @@ impl SemanticAnalyzer {
                             span,
                         )
                         .with_help("Tuple destructuring can only be used with tuple types");
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
 
                 // Check for duplicate bindings
@@ -3293,7 +5582,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help("Each binding in a destructuring pattern must be unique");
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
 
@@ -3315,7 +5604,7 @@ impl SemanticAnalyzer {
                             span
                         )
                         .with_help(&format!("Consider using a different name or removing the previous declaration of '{}'", name));
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
 
                     if !is_fallible {
@@ -3347,8 +5636,9 @@ impl SemanticAnalyzer {
                             span,
                         )
                         .with_help(&format!("Each parameter must have a unique name"));
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
+                self.check_name_collision(span)?;
             }
             BindingPattern::Object(obj_pattern) => {
                 // Validate field existence if type is known
@@ -3378,7 +5668,7 @@ impl SemanticAnalyzer {
                                             .collect::<Vec<_>>()
                                             .join(", ")
                                     ));
-                                return Err(CompilerError::SemanticError(error));
+                                return Err(CompilerError::SemanticError(Box::new(error)));
                             }
                         }
                     }
@@ -3402,7 +5692,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help("Each binding in a destructuring pattern must be unique");
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
 
@@ -3426,7 +5716,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help(&format!("Consider using a different name"));
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
             }
@@ -3458,7 +5748,7 @@ impl SemanticAnalyzer {
                                 .with_help(
                                     "Each binding in a destructuring pattern must be unique",
                                 );
-                            return Err(CompilerError::SemanticError(error));
+                            return Err(CompilerError::SemanticError(Box::new(error)));
                         }
                     }
                 }
@@ -3472,7 +5762,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help("Each binding in a destructuring pattern must be unique");
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
 
@@ -3488,7 +5778,7 @@ impl SemanticAnalyzer {
                                     span,
                                 )
                                 .with_help(&format!("Consider using a different name"));
-                            return Err(CompilerError::SemanticError(error));
+                            return Err(CompilerError::SemanticError(Box::new(error)));
                         }
                     }
                 }
@@ -3505,7 +5795,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help(&format!("Consider using a different name"));
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
             }
@@ -3528,7 +5818,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help("Each binding in a destructuring pattern must be unique");
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
 
@@ -3545,7 +5835,7 @@ impl SemanticAnalyzer {
                                 span,
                             )
                             .with_help(&format!("Consider using a different name"));
-                        return Err(CompilerError::SemanticError(error));
+                        return Err(CompilerError::SemanticError(Box::new(error)));
                     }
                 }
             }
@@ -3554,6 +5844,299 @@ impl SemanticAnalyzer {
     }
 
     /// Validate that a binary operator can be used with the given operands
+    /// Strict mode: every parameter needs a type, except one named after a
+    /// typed field of `owner` (directly or through a `setX`/`getX` method),
+    /// which takes that field's type
+    fn check_strict_params(
+        &self,
+        params: &[Param],
+        owner: Option<&str>,
+        callable: &str,
+        span: Option<crate::span::Span>,
+    ) -> Result<()> {
+        let field_type_known = |name: &str| {
+            let Some(fields) = owner.and_then(|owner| self.types.get(owner)) else {
+                return false;
+            };
+            let accessor_field = callable
+                .strip_prefix("set")
+                .or_else(|| callable.strip_prefix("get"))
+                .filter(|rest| !rest.is_empty())
+                .map(|rest| rest[..1].to_lowercase() + &rest[1..]);
+            let field = accessor_field.as_deref().unwrap_or(name);
+            fields.fields.contains_key(field) || fields.fields.contains_key(&format!("_{}", field))
+        };
+
+        for param in params {
+            if param.type_ref.is_some() || param.name().is_some_and(field_type_known) {
+                continue;
+            }
+            let name = param.name().unwrap_or("(destructured)");
+            let error = self
+                .error_with_span(
+                    E5101_STRICT_UNTYPED_PARAM,
+                    "Untyped parameter",
+                    &format!(
+                        "Parameter '{}' of '{}' has no type annotation; strict mode does not guess parameter types",
+                        name, callable
+                    ),
+                    span,
+                )
+                .with_hint(&format!("Annotate it, e.g. '{}: int'", name));
+            return Err(CompilerError::SemanticError(Box::new(error)));
+        }
+        Ok(())
+    }
+
+    /// Strict mode: `JSON.parse` and `.json()` results need a declared type,
+    /// instead of becoming a dynamic value coerced at each use
+    fn check_strict_json_binding(&self, var: &VarDecl) -> Result<()> {
+        let Expr::MethodCall(call) = &var.init else {
+            return Ok(());
+        };
+        let is_json_parse = call.method == "parse"
+            && matches!(call.object.as_ref(), Expr::Identifier(id) if id == "JSON");
+        if !is_json_parse && call.method != "json" {
+            return Ok(());
+        }
+
+        let binding = var.bindings.first();
+        let name = binding.and_then(|b| b.name()).unwrap_or("_");
+        let error = self
+            .error_with_span(
+                E5103_STRICT_UNTYPED_JSON,
+                "Untyped JSON value",
+                &format!(
+                    "'{}' holds parsed JSON without a declared type; strict mode does not coerce JSON values implicitly",
+                    name
+                ),
+                binding.and_then(|b| b.span),
+            )
+            .with_hint(&format!(
+                "Declare the expected type: let {}: MyType, err = ...",
+                name
+            ));
+        Err(CompilerError::SemanticError(Box::new(error)))
+    }
+
+    /// Conditions are bools; a number, string, collection or optional isn't
+    /// truthy, so the error spells out the comparison it most likely meant.
+    /// `negated` is set for the operand of `!`, which flips that comparison.
+    fn check_condition(&self, condition: &Expr, negated: bool) -> Result<()> {
+        let Some(condition_type) = self.infer_expr_type(condition) else {
+            return Ok(());
+        };
+        let is = if negated { "==" } else { "!=" };
+        let length = if negated {
+            ".length == 0"
+        } else {
+            ".length > 0"
+        };
+        let comparison = match &condition_type {
+            TypeRef::Optional(_) => Some(format!(" {} null", is)),
+            TypeRef::Array(_) | TypeRef::Map(..) | TypeRef::Set(_) => Some(length.to_string()),
+            TypeRef::Simple(name) if name == "char" => None,
+            TypeRef::Simple(_) => match condition_type.type_of_name().as_str() {
+                "int" | "float" => Some(format!(" {} 0", is)),
+                "string" => Some(format!(" {} \"\"", is)),
+                "array" => Some(length.to_string()),
+                tag if self.types.contains_key(tag) => None,
+                // bool, and anything this pass can't pin down
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        let error = SemanticErrorInfo::new(
+            E5010_NON_BOOL_CONDITION,
+            "Condition is not a bool",
+            &format!(
+                "A condition must be a bool, but this one is a value of type {}",
+                condition_type
+            ),
+        );
+        let error = match (comparison, Self::condition_subject(condition)) {
+            (Some(comparison), Some(subject)) => {
+                error.with_suggestion(&format!("Did you mean '{}{}'?", subject, comparison))
+            }
+            (Some(comparison), None) => error.with_help(&format!(
+                "Compare the value explicitly, e.g. value{}",
+                comparison
+            )),
+            (None, _) => error.with_help("Compare the value explicitly to get a bool"),
+        };
+        Err(CompilerError::SemanticError(Box::new(error)))
+    }
+
+    /// Source text of a condition simple enough to quote in a fix-it: a name
+    /// or a chain of fields on one
+    fn condition_subject(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Identifier(name) => Some(name.clone()),
+            Expr::Member { object, property } => {
+                Some(format!("{}.{}", Self::condition_subject(object)?, property))
+            }
+            _ => None,
+        }
+    }
+
+    /// `**` raises a number to a number
+    fn check_power_operands(&self, left: &Expr, right: &Expr) -> Result<()> {
+        for operand in [left, right] {
+            let Some(operand_type) = self.infer_expr_type(operand) else {
+                continue;
+            };
+            let tag = operand_type.type_of_name();
+            let known = TYPE_OF_TAGS.contains(&tag.as_str()) || self.types.contains_key(&tag);
+            if !known || matches!(tag.as_str(), "int" | "float") {
+                continue;
+            }
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5001_TYPE_MISMATCH,
+                    "'**' needs numbers",
+                    &format!(
+                        "'**' raises a number to a number, but got a value of type {}",
+                        operand_type
+                    ),
+                )
+                .with_help("Use int or float operands, e.g. 2 ** 10 or x ** 0.5"),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Bitwise operators work on integers only; `&`/`|` on bools would be
+    /// `&&`/`||` without the short circuit
+    fn check_bitwise_operands(&self, symbol: &str, operands: &[&Expr]) -> Result<()> {
+        for operand in operands {
+            let Some(operand_type) = self.infer_expr_type(operand) else {
+                continue;
+            };
+            let tag = operand_type.type_of_name();
+            let known = TYPE_OF_TAGS.contains(&tag.as_str()) || self.types.contains_key(&tag);
+            if !known || tag == "int" {
+                continue;
+            }
+            let help = if tag == "bool" && (symbol == "&" || symbol == "|") {
+                format!("Use '{}{}' to combine conditions", symbol, symbol)
+            } else {
+                "Bitwise operators take int operands; convert with 'as int' if needed".to_string()
+            };
+            return Err(CompilerError::SemanticError(Box::new(
+                SemanticErrorInfo::new(
+                    E5001_TYPE_MISMATCH,
+                    &format!("'{}' needs integers", symbol),
+                    &format!(
+                        "'{}' works on the bits of an int, but got a value of type {}",
+                        symbol, operand_type
+                    ),
+                )
+                .with_help(&help),
+            )));
+        }
+        Ok(())
+    }
+
+    /// A char compares with chars, and a char literal also with a string
+    /// (`charAt` and `chars()` hand out one-character strings); a char value
+    /// doesn't compare with strings or numbers, and arithmetic goes through
+    /// its code
+    fn check_char_operands(&self, op: &BinOp, left: &Expr, right: &Expr) -> Result<()> {
+        let comparison = matches!(
+            op,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge
+        );
+        let arithmetic = matches!(
+            op,
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod
+        );
+        if !comparison && !arithmetic {
+            return Ok(());
+        }
+        let (Some(left_ty), Some(right_ty)) = (
+            self.infer_expr_type(left).map(Self::strip_optional),
+            self.infer_expr_type(right).map(Self::strip_optional),
+        ) else {
+            return Ok(());
+        };
+        let is_char = |ty: &TypeRef| matches!(ty, TypeRef::Simple(name) if name == "char");
+        let is_string = |ty: &TypeRef| matches!(ty, TypeRef::Simple(name) if name == "string" || name == "String");
+        let (char_side, other) = match (is_char(&left_ty), is_char(&right_ty)) {
+            (false, false) => return Ok(()),
+            (true, true) if comparison => return Ok(()),
+            (true, _) => (left, right_ty),
+            (false, true) => (right, left_ty),
+        };
+
+        let (title, help) = if comparison {
+            let is_literal = matches!(char_side, Expr::Literal(Literal::Char(_)));
+            match other.type_of_name().as_str() {
+                "string" if is_literal => return Ok(()),
+                "string" => (
+                    "Char compared with a string",
+                    "Convert the char with `c as string`, or compare with a char literal like 'a'",
+                ),
+                "int" | "float" => (
+                    "Char compared with a number",
+                    "Compare character codes instead, e.g. charCode(c) >= 65",
+                ),
+                _ => return Ok(()),
+            }
+        } else if *op == BinOp::Add && is_string(&other) {
+            // `+` with a string joins text
+            return Ok(());
+        } else {
+            (
+                "Arithmetic on a char",
+                "Go through the character code, e.g. fromCharCode(charCode(c) + 1)",
+            )
+        };
+        Err(CompilerError::SemanticError(Box::new(
+            SemanticErrorInfo::new(
+                E5001_TYPE_MISMATCH,
+                title,
+                &format!(
+                    "'{}' can't combine a char with a value of type {}",
+                    op, other
+                ),
+            )
+            .with_help(help),
+        )))
+    }
+
+    /// Strict mode: `+` joins strings only with strings; anything else would
+    /// be converted with `Display` behind the scenes
+    fn check_strict_concat(&self, left: &Expr, right: &Expr) -> Result<()> {
+        let is_string = |ty: &TypeRef| matches!(ty, TypeRef::Simple(name) if name == "string" || name == "String");
+        let (Some(left_ty), Some(right_ty)) =
+            (self.infer_expr_type(left), self.infer_expr_type(right))
+        else {
+            return Ok(());
+        };
+        let other = match (is_string(&left_ty), is_string(&right_ty)) {
+            (true, false) => right_ty,
+            (false, true) => left_ty,
+            _ => return Ok(()),
+        };
+
+        let location = self
+            .current_callable
+            .as_ref()
+            .map(|callable| format!(" in '{}'", callable))
+            .unwrap_or_default();
+        let error = SemanticErrorInfo::new(
+            E5104_STRICT_IMPLICIT_TO_STRING,
+            "Implicit string conversion",
+            &format!(
+                "'+' joins a string with a value of type '{}'{}; strict mode does not stringify values implicitly",
+                other, location
+            ),
+        )
+        .with_hint("Convert explicitly with toString(value), or use a string template $\"...{value}\"");
+        Err(CompilerError::SemanticError(Box::new(error)))
+    }
+
     fn validate_binary_op_constraints(&self, left: &Expr, right: &Expr, op: &BinOp) -> Result<()> {
         // Get the operator string for trait lookup
         let op_str = match op {
@@ -3596,7 +6179,7 @@ impl SemanticAnalyzer {
                 ));
                 error = error
                     .with_suggestion(&format!("Add constraint: <{}: {}>", type_param, trait_name));
-                return Err(CompilerError::SemanticError(error));
+                return Err(CompilerError::SemanticError(Box::new(error)));
             }
         }
 
@@ -3617,7 +6200,7 @@ impl SemanticAnalyzer {
                 ));
                 error = error
                     .with_suggestion(&format!("Add constraint: <{}: {}>", type_param, trait_name));
-                return Err(CompilerError::SemanticError(error));
+                return Err(CompilerError::SemanticError(Box::new(error)));
             }
         }
 
@@ -3658,7 +6241,7 @@ impl SemanticAnalyzer {
                 ));
                 error = error
                     .with_suggestion(&format!("Add constraint: <{}: {}>", type_param, trait_name));
-                return Err(CompilerError::SemanticError(error));
+                return Err(CompilerError::SemanticError(Box::new(error)));
             }
         }
 
@@ -3734,14 +6317,16 @@ impl SemanticAnalyzer {
                 if let Some((alias_type_params, target_type)) = self.type_aliases.get(base) {
                     // Validate that the number of arguments matches the alias parameters
                     if args.len() != alias_type_params.len() {
-                        return Err(CompilerError::SemanticError(SemanticErrorInfo::new(
-                            "E5003",
-                            "Type argument count mismatch",
-                            &format!(
-                                "Type alias `{}` expects {} type argument(s), but {} provided",
-                                base,
-                                alias_type_params.len(),
-                                args.len()
+                        return Err(CompilerError::SemanticError(Box::new(
+                            SemanticErrorInfo::new(
+                                "E5003",
+                                "Type argument count mismatch",
+                                &format!(
+                                    "Type alias `{}` expects {} type argument(s), but {} provided",
+                                    base,
+                                    alias_type_params.len(),
+                                    args.len()
+                                ),
                             ),
                         )));
                     }
@@ -3863,7 +6448,7 @@ impl SemanticAnalyzer {
                 error.example = Some("// ✅ Good:\n1 | 2 | 3 => \"small\"\n\n// ✅ Good with bindings:\nx | y => x  // Both bind one variable\n\n// ❌ Bad:\nSome(x) | None => x  // Inconsistent bindings".to_string());
                 error.doc_link = Some("https://github.com/liva-lang/livac/blob/main/docs/language-reference/pattern-matching.md#or-patterns".to_string());
 
-                return Err(CompilerError::SemanticError(error));
+                return Err(CompilerError::SemanticError(Box::new(error)));
             }
         }
 
@@ -3924,6 +6509,11 @@ impl SemanticAnalyzer {
             return Ok(()); // Exhaustive with wildcard/binding
         }
 
+        // `switch typeOf(x)` on a union: every member needs a case
+        if let Some(members) = self.type_of_switch_members(switch_expr) {
+            return self.check_union_exhaustiveness(switch_expr, &members);
+        }
+
         // Try to infer discriminant type from literal patterns
         let discriminant_type = self.infer_switch_discriminant_type(switch_expr);
 
@@ -3973,7 +6563,7 @@ impl SemanticAnalyzer {
                     ));
                     error.doc_link = Some("https://github.com/liva-lang/livac/blob/main/docs/language-reference/pattern-matching.md#exhaustiveness".to_string());
 
-                    return Err(CompilerError::SemanticError(error));
+                    return Err(CompilerError::SemanticError(Box::new(error)));
                 }
 
                 Ok(())
@@ -4128,7 +6718,7 @@ impl SemanticAnalyzer {
             error.example = Some("switch num {\n    0 => \"zero\",\n    1 => \"one\",\n    _ => \"other\"  // Required\n}".to_string());
             error.doc_link = Some("https://github.com/liva-lang/livac/blob/main/docs/language-reference/pattern-matching.md#exhaustiveness".to_string());
 
-            return Err(CompilerError::SemanticError(error));
+            return Err(CompilerError::SemanticError(Box::new(error)));
         }
 
         // For ranges or large sets, we always require a wildcard (already checked at start)
@@ -4144,7 +6734,7 @@ impl SemanticAnalyzer {
             error.example = Some("switch num {\n    0..=10 => \"small\",\n    11..=100 => \"medium\",\n    _ => \"large\"  // Required\n}".to_string());
             error.doc_link = Some("https://github.com/liva-lang/livac/blob/main/docs/language-reference/pattern-matching.md#exhaustiveness".to_string());
 
-            return Err(CompilerError::SemanticError(error));
+            return Err(CompilerError::SemanticError(Box::new(error)));
         }
 
         Ok(())
@@ -4200,7 +6790,7 @@ impl SemanticAnalyzer {
         ));
         error.doc_link = Some("https://github.com/liva-lang/livac/blob/main/docs/language-reference/pattern-matching.md#exhaustiveness".to_string());
 
-        Err(CompilerError::SemanticError(error))
+        Err(CompilerError::SemanticError(Box::new(error)))
     }
 
     /// Collect enum variant names from a pattern (recursively for Or-patterns)
@@ -4218,6 +6808,64 @@ impl SemanticAnalyzer {
         }
     }
 
+    fn check_union_exhaustiveness(
+        &self,
+        switch_expr: &SwitchExpr,
+        members: &[TypeRef],
+    ) -> Result<()> {
+        let mut covered: HashSet<&str> = HashSet::new();
+        for arm in &switch_expr.arms {
+            match &arm.pattern {
+                Pattern::Literal(Literal::String(tag)) => {
+                    covered.insert(tag);
+                }
+                Pattern::Or(patterns) => {
+                    for pattern in patterns {
+                        if let Pattern::Literal(Literal::String(tag)) = pattern {
+                            covered.insert(tag);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let missing: Vec<String> = members
+            .iter()
+            .map(|member| member.type_of_name())
+            .filter(|name| !covered.contains(name.as_str()))
+            .collect();
+        if missing.is_empty() {
+            return Ok(()); // Exhaustive — every member has a case, no `_` needed
+        }
+
+        let missing_list = missing
+            .iter()
+            .map(|name| format!("\"{}\"", name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut error = SemanticErrorInfo::new(
+            E0905_NON_EXHAUSTIVE_UNION,
+            "Non-exhaustive Union Pattern Matching",
+            &format!(
+                "switch on typeOf() of {} is not exhaustive — missing member(s): {}",
+                TypeRef::Union(members.to_vec()),
+                missing_list
+            ),
+        );
+
+        error.category = Some("Pattern Matching".to_string());
+        error.hint = Some(
+            "Add a case for each missing member or use wildcard `_` to catch the rest".to_string(),
+        );
+        error.example = Some(format!(
+            "switch typeOf(value) {{\n    {} => ...,\n    _ => ...  // or cover all members\n}}",
+            missing_list
+        ));
+        error.doc_link = Some("https://github.com/liva-lang/livac/blob/main/docs/language-reference/union-types.md#type-narrowing".to_string());
+
+        Err(CompilerError::SemanticError(Box::new(error)))
+    }
+
     fn check_string_exhaustiveness(&self, _switch_expr: &SwitchExpr) -> Result<()> {
         // Strings are infinite, so we always require a wildcard or binding pattern
         // This is already checked by has_catch_all at the start of check_switch_exhaustiveness
@@ -4234,7 +6882,7 @@ impl SemanticAnalyzer {
         error.example = Some("switch text {\n    \"active\" => 1,\n    \"inactive\" => 2,\n    _ => 0  // Required\n}".to_string());
         error.doc_link = Some("https://github.com/liva-lang/livac/blob/main/docs/language-reference/pattern-matching.md#exhaustiveness".to_string());
 
-        return Err(CompilerError::SemanticError(error));
+        return Err(CompilerError::SemanticError(Box::new(error)));
     }
 
     /// Validate that a type hint for JSON.parse is serializable (Phase 1: JSON Typed Parsing)
@@ -4250,13 +6898,13 @@ impl SemanticAnalyzer {
                 if !valid_primitives.contains(&name.as_str()) {
                     // Check if it's a defined class
                     if !self.types.contains_key(name) {
-                        return Err(CompilerError::SemanticError(
+                        return Err(CompilerError::SemanticError(Box::new(
                             format!(
                                 "Type '{}' is not defined or not serializable for JSON parsing",
                                 name
                             )
                             .into(),
-                        ));
+                        )));
                     }
                     // Phase 2: Mark this class as needing serde derive
                     self.json_classes.insert(name.clone());
@@ -4274,9 +6922,9 @@ impl SemanticAnalyzer {
             TypeRef::Generic { base, args } => {
                 // Validate base type
                 if !self.types.contains_key(base) {
-                    return Err(CompilerError::SemanticError(
+                    return Err(CompilerError::SemanticError(Box::new(
                         format!("Generic type '{}' is not defined", base).into(),
-                    ));
+                    )));
                 }
                 // Validate all type arguments
                 for arg in args {
@@ -4306,14 +6954,14 @@ impl SemanticAnalyzer {
             // Sets are serializable if the element type is
             TypeRef::Set(inner) => self.validate_json_parse_type_hint(inner),
             // Function types cannot be serialized to/from JSON.
-            TypeRef::Fn(_, _) => Err(CompilerError::SemanticError(
+            TypeRef::Fn(_, _) => Err(CompilerError::SemanticError(Box::new(
                 SemanticErrorInfo::new(
                     "E0904",
                     "Invalid JSON.parse type",
                     "Function types cannot be parsed from JSON",
                 )
                 .with_help("Use a serializable type (primitive, struct, array, map, or set)"),
-            )),
+            ))),
         }
     }
 
@@ -4416,6 +7064,19 @@ impl SemanticAnalyzer {
     }
 }
 
+/// Names of the `_` fields and methods among `members`
+fn private_members(members: &[Member]) -> HashSet<String> {
+    members
+        .iter()
+        .map(|member| match member {
+            Member::Field(field) => (&field.name, field.visibility),
+            Member::Method(method) => (&method.name, method.visibility),
+        })
+        .filter(|(_, visibility)| *visibility == Visibility::Private)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
 pub fn analyze(program: Program) -> Result<Program> {
     let mut analyzer = SemanticAnalyzer::new(String::new(), String::new());
     analyzer.analyze_program(program)
@@ -4435,9 +7096,168 @@ pub fn analyze_with_modules(
     program: Program,
     source_file: String,
     source_code: String,
-    modules: &HashMap<std::path::PathBuf, (HashSet<String>, HashSet<String>)>,
+    modules: &ModuleSymbols,
+) -> Result<Program> {
+    analyze_cancellable(
+        program,
+        source_file,
+        source_code,
+        Some(modules),
+        &crate::CompilerOptions::default(),
+        &CancellationToken::new(),
+        &mut DiagnosticSink::new(),
+    )
+}
+
+/// Check a module the entry module imports against the sandbox policy; only
+/// the entry module goes through full analysis
+pub(crate) fn check_sandbox(
+    program: &Program,
+    source_file: String,
+    source_code: String,
+    policy: &crate::sandbox::SandboxPolicy,
+) -> Result<()> {
+    SemanticAnalyzer::new(source_file, source_code).check_sandbox(program, policy)
+}
+
+/// Analyze a program with the `strict`, `sandbox` and `max_errors` settings
+/// of `options`, stopping with `CompilerError::Cancelled` (or
+/// `DeadlineExceeded`) as soon as `cancel` fires
+///
+/// With `max_errors` above 1, every declaration is validated even after one
+/// fails, and the errors come back together (see
+/// [`CompilerError::from_errors`]). Warnings about a program that passes go
+/// into `diagnostics`.
+pub(crate) fn analyze_cancellable(
+    program: Program,
+    source_file: String,
+    source_code: String,
+    modules: Option<&ModuleSymbols>,
+    options: &crate::CompilerOptions,
+    cancel: &CancellationToken,
+    diagnostics: &mut DiagnosticSink,
 ) -> Result<Program> {
+    let incremental = options.incremental.then(|| source_file.clone());
     let mut analyzer = SemanticAnalyzer::new(source_file, source_code);
-    analyzer.imported_modules = modules.clone();
-    analyzer.analyze_program(program)
+    if let Some(modules) = modules {
+        analyzer.imported_modules = modules.clone();
+    }
+    analyzer.strict = options.strict;
+    analyzer.sandbox = options.sandbox.clone();
+    analyzer.max_errors = options.max_errors;
+    analyzer.cancel = cancel.clone();
+    if let Some(filename) = &incremental {
+        analyzer.reusable = Some(crate::incremental::take_validations(filename));
+    }
+    let program = analyzer.analyze_program(program);
+    if let Some(filename) = &incremental {
+        let validations = std::mem::take(&mut analyzer.validations);
+        crate::incremental::store_validations(filename, validations);
+    }
+    let program = program?;
+    diagnostics.warnings(runtime_name_warnings(&program));
+    diagnostics.warnings(crate::conversions::string_argument_warnings(&program));
+    diagnostics.warnings(lambda_capture_warnings(&program));
+    Ok(program)
+}
+
+/// Warnings for lambdas that capture variables: a capture may not be
+/// `Sync`-safe if the lambda runs in parallel (E0511), nor `Send`-safe if the
+/// lambda is `move` (E0510). Neither is checked against the captured types yet.
+pub fn lambda_capture_warnings(program: &Program) -> Vec<SemanticErrorInfo> {
+    struct Captures(Vec<SemanticErrorInfo>);
+
+    impl<'ast> Visitor<'ast> for Captures {
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            if let Expr::Lambda(lambda) = expr {
+                for capture in &lambda.captures {
+                    if lambda.is_move {
+                        self.0.push(SemanticErrorInfo::new(
+                            E0510_NON_SEND_CAPTURE,
+                            "Non-Send capture in parallel lambda",
+                            &format!(
+                                "Move lambda captures '{}' which may not be Send-safe for parallel execution",
+                                capture
+                            ),
+                        ));
+                    }
+                    self.0.push(SemanticErrorInfo::new(
+                        E0511_NON_SYNC_CAPTURE,
+                        "Non-Sync capture in parallel lambda",
+                        &format!(
+                            "Lambda captures '{}' which may not be Sync-safe for parallel execution",
+                            capture
+                        ),
+                    ));
+                }
+            }
+            walk_expr(self, expr)
+        }
+    }
+
+    let mut captures = Captures(Vec::new());
+    captures.visit_program(program);
+    captures.0
+}
+
+/// Modules the generated Rust refers to by name: the runtime and the crates
+/// codegen may depend on
+pub const RUNTIME_MODULES: &[&str] = &[
+    "liva_rt",
+    "std",
+    "core",
+    "alloc",
+    "tokio",
+    "rayon",
+    "serde",
+    "serde_json",
+    "reqwest",
+    "chrono",
+    "regex",
+    "rand",
+    "uuid",
+    "axum",
+    "rusqlite",
+    "sha2",
+    "md5",
+    "base64",
+];
+
+/// Warnings for top-level names that clash with the generated code: a name
+/// equal to a runtime module shadows it, and a name starting with `liva_` is
+/// renamed with a `_` suffix.
+pub fn runtime_name_warnings(program: &Program) -> Vec<SemanticErrorInfo> {
+    let names = program.items.iter().filter_map(|item| match item {
+        TopLevel::Function(func) => Some(("function", &func.name)),
+        TopLevel::Class(class) => Some(("class", &class.name)),
+        TopLevel::Enum(decl) => Some(("enum", &decl.name)),
+        TopLevel::TypeAlias(alias) => Some(("type", &alias.name)),
+        TopLevel::ConstDecl(decl) => Some(("constant", &decl.name)),
+        _ => None,
+    });
+    names
+        .filter_map(|(kind, name)| {
+            let message = if RUNTIME_MODULES.contains(&name.as_str()) {
+                format!(
+                    "{} '{}' has the name of the `{}` module the generated Rust uses, which it shadows",
+                    kind, name, name
+                )
+            } else if name.starts_with("liva_") && !name.ends_with('_') {
+                format!(
+                    "{} '{}' is renamed to '{}_' in the generated Rust, since `liva_` names are reserved for the runtime",
+                    kind, name, name
+                )
+            } else {
+                return None;
+            };
+            Some(
+                SemanticErrorInfo::new(
+                    E0010_SHADOWED_RUNTIME_NAME,
+                    "Name clashes with the runtime",
+                    &message,
+                )
+                .with_help("Rename the declaration"),
+            )
+        })
+        .collect()
 }
@@ -0,0 +1,165 @@
+//! Parameter lists of callables, for editor signature help.
+//!
+//! Semantic analysis records a [`Signature`] for every function and class
+//! method a module declares in `Program::signatures`; [`builtin`] covers the
+//! functions the compiler itself provides (`print`, `parseInt`, `Math.*`).
+
+use crate::ast::{
+    Expr, FunctionDecl, Literal, Member, MethodDecl, Param, Program, TopLevel, TypeRef, UnOp,
+};
+
+/// Name, parameters and return type of something callable
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    /// `name` for functions, `Class.name` for methods, `Math.pow` for builtins
+    pub name: String,
+    pub params: Vec<SignatureParam>,
+    pub return_type: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureParam {
+    pub name: String,
+    pub type_ref: Option<String>,
+    /// Source form of the default value, when the parameter has one
+    pub default: Option<String>,
+}
+
+impl SignatureParam {
+    /// `name: type = default`, leaving out what isn't known
+    pub fn label(&self) -> String {
+        let mut label = self.name.clone();
+        if let Some(type_ref) = &self.type_ref {
+            label.push_str(": ");
+            label.push_str(type_ref);
+        }
+        if let Some(default) = &self.default {
+            label.push_str(" = ");
+            label.push_str(default);
+        }
+        label
+    }
+}
+
+impl Signature {
+    /// `name(param, ...): return`, as written in Liva
+    pub fn label(&self) -> String {
+        let params: Vec<String> = self.params.iter().map(SignatureParam::label).collect();
+        match &self.return_type {
+            Some(ret) => format!("{}({}): {}", self.name, params.join(", "), ret),
+            None => format!("{}({})", self.name, params.join(", ")),
+        }
+    }
+
+    /// Arguments a call must pass: the parameters without defaults
+    pub fn required_params(&self) -> usize {
+        self.params.iter().filter(|p| p.default.is_none()).count()
+    }
+
+    pub(crate) fn from_function(func: &FunctionDecl) -> Self {
+        Self {
+            name: func.name.clone(),
+            params: func.params.iter().map(signature_param).collect(),
+            return_type: func.return_type.as_ref().map(TypeRef::to_string),
+        }
+    }
+
+    pub(crate) fn from_method(owner: &str, method: &MethodDecl) -> Self {
+        Self {
+            name: format!("{}.{}", owner, method.name),
+            params: method.params.iter().map(signature_param).collect(),
+            return_type: method.return_type.as_ref().map(TypeRef::to_string),
+        }
+    }
+}
+
+/// Signatures of every function, class method and class extension method
+/// in `program`, in declaration order
+pub fn collect(program: &Program) -> Vec<Signature> {
+    let mut signatures = Vec::new();
+    for item in &program.items {
+        match item {
+            TopLevel::Function(func) => signatures.push(Signature::from_function(func)),
+            TopLevel::Class(class) => {
+                for member in &class.members {
+                    if let Member::Method(method) = member {
+                        signatures.push(Signature::from_method(&class.name, method));
+                    }
+                }
+            }
+            TopLevel::ClassExtension(extension) => {
+                for method in &extension.methods {
+                    signatures.push(Signature::from_method(&extension.name, method));
+                }
+            }
+            _ => {}
+        }
+    }
+    signatures
+}
+
+fn signature_param(param: &Param) -> SignatureParam {
+    SignatureParam {
+        // Destructured parameters have no single name
+        name: param.name().unwrap_or("{…}").to_string(),
+        type_ref: param.type_ref.as_ref().map(TypeRef::to_string),
+        default: param.default.as_ref().map(default_text),
+    }
+}
+
+/// Short source form of a default value; anything beyond a literal or a
+/// name is elided
+fn default_text(expr: &Expr) -> String {
+    match expr {
+        Expr::Literal(Literal::Int(n)) => n.to_string(),
+        Expr::Literal(Literal::Float(n)) => format!("{:?}", n),
+        Expr::Literal(Literal::String(s)) => format!("\"{}\"", s),
+        Expr::Literal(Literal::Char(c)) => format!("'{}'", c),
+        Expr::Literal(Literal::Bool(b)) => b.to_string(),
+        Expr::Literal(Literal::Null) => "null".to_string(),
+        Expr::Identifier(name) => name.clone(),
+        Expr::Unary {
+            op: UnOp::Neg,
+            operand,
+        } => format!("-{}", default_text(operand)),
+        Expr::ArrayLiteral(items) if items.is_empty() => "[]".to_string(),
+        _ => "…".to_string(),
+    }
+}
+
+/// Signature of a compiler-provided function, such as `parseInt` or `Math.pow`
+pub fn builtin(name: &str) -> Option<Signature> {
+    crate::builtins::lookup(name).map(|builtin| builtin.signature())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::TopLevel;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_function_signature_label_includes_defaults() {
+        let source =
+            "greet(name: string, greeting: string = \"Hello\", times = 1): string => greeting";
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let TopLevel::Function(func) = &program.items[0] else {
+            panic!("expected a function");
+        };
+
+        let signature = Signature::from_function(func);
+        assert_eq!(
+            signature.label(),
+            "greet(name: string, greeting: string = \"Hello\", times = 1): string"
+        );
+        assert_eq!(signature.required_params(), 1);
+    }
+
+    #[test]
+    fn test_builtin_lookup() {
+        let pow = builtin("Math.pow").unwrap();
+        assert_eq!(pow.label(), "Math.pow(base: float, exp: float): float");
+        assert!(builtin("Math.nope").is_none());
+    }
+}
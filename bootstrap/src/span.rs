@@ -6,7 +6,7 @@ use std::ops::Range;
 /// returned by `logos::Lexer::span()`.  Helper methods provide convenient access
 /// to derived information such as line/column positions without duplicating the
 /// conversion logic across the compiler pipeline.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -0,0 +1,232 @@
+//! Classes a build declares, as one table every stage queries.
+//!
+//! Semantic analysis records a [`SymbolTable`] in `Program::symbols`;
+//! desugaring carries it in `DesugarContext::symbols`, with the classes of
+//! imported modules added, and codegen reads class fields and method return
+//! types from it instead of walking class declarations again.
+
+use crate::ast::{ClassDecl, Member, Program, TopLevel, TypeRef};
+use std::collections::BTreeMap;
+
+/// Classes by name; a later module's class replaces one of the same name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolTable {
+    classes: BTreeMap<String, ClassSymbol>,
+}
+
+/// Fields and methods of one class, in declaration order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassSymbol {
+    pub name: String,
+    /// Names of the class's type parameters
+    pub type_params: Vec<String>,
+    pub fields: Vec<FieldSymbol>,
+    pub methods: Vec<MethodSymbol>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldSymbol {
+    pub name: String,
+    pub type_ref: Option<TypeRef>,
+    /// `name?: T` or `name: T?`
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSymbol {
+    pub name: String,
+    /// Declared type of each parameter; a type parameter of the class or
+    /// the method counts as undeclared, since the caller decides it
+    pub params: Vec<Option<TypeRef>>,
+    pub return_type: Option<TypeRef>,
+    /// The method contains a `fail`
+    pub fallible: bool,
+}
+
+impl SymbolTable {
+    /// Classes declared in `program`
+    pub fn collect(program: &Program) -> Self {
+        let mut table = SymbolTable::default();
+        for item in &program.items {
+            if let TopLevel::Class(class) = item {
+                table.add_class(class);
+            }
+        }
+        table
+    }
+
+    /// Add the classes of `other`, such as an imported module's
+    pub fn extend(&mut self, other: SymbolTable) {
+        self.classes.extend(other.classes);
+    }
+
+    pub fn add_class(&mut self, class: &ClassDecl) {
+        let mut symbol = ClassSymbol {
+            name: class.name.clone(),
+            type_params: class.type_params.iter().map(|tp| tp.name.clone()).collect(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+        };
+        for member in &class.members {
+            match member {
+                Member::Field(field) => symbol.fields.push(FieldSymbol {
+                    name: field.name.clone(),
+                    type_ref: field.type_ref.clone(),
+                    optional: field.is_optional
+                        || matches!(field.type_ref, Some(TypeRef::Optional(_))),
+                }),
+                Member::Method(method) => symbol.methods.push(MethodSymbol {
+                    name: method.name.clone(),
+                    params: method
+                        .params
+                        .iter()
+                        .map(|param| {
+                            param.type_ref.clone().filter(|ty| {
+                                !matches!(ty, TypeRef::Simple(name)
+                                    if symbol.type_params.contains(name)
+                                        || method.type_params.iter().any(|tp| &tp.name == name))
+                            })
+                        })
+                        .collect(),
+                    return_type: method.return_type.clone(),
+                    fallible: method.contains_fail,
+                }),
+            }
+        }
+        self.classes.insert(class.name.clone(), symbol);
+    }
+
+    pub fn class(&self, name: &str) -> Option<&ClassSymbol> {
+        self.classes.get(name)
+    }
+
+    pub fn is_class(&self, name: &str) -> bool {
+        self.classes.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Every class, by name
+    pub fn classes(&self) -> impl Iterator<Item = &ClassSymbol> {
+        self.classes.values()
+    }
+}
+
+impl ClassSymbol {
+    pub fn field(&self, name: &str) -> Option<&FieldSymbol> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn method(&self, name: &str) -> Option<&MethodSymbol> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+
+    /// Whether each field is optional, in constructor argument order
+    pub fn constructor_optionals(&self) -> Vec<bool> {
+        self.fields.iter().map(|field| field.optional).collect()
+    }
+
+    /// A class without a `constructor` is a data class: its constructor
+    /// takes the fields
+    pub fn is_data(&self) -> bool {
+        self.method("constructor").is_none()
+    }
+
+    /// Declared type of each constructor argument: the parameters of
+    /// `constructor`, or the fields of a data class
+    pub fn constructor_params(&self) -> Vec<Option<TypeRef>> {
+        match self.method("constructor") {
+            Some(constructor) => constructor.params.clone(),
+            None => self
+                .fields
+                .iter()
+                .map(|field| self.field_type(&field.name))
+                .collect(),
+        }
+    }
+
+    /// Type of `field` as far as it is declared: `None` for a type parameter
+    /// of the class, or no type outside a data class, where it is a string
+    pub fn field_type(&self, field: &str) -> Option<TypeRef> {
+        match &self.field(field)?.type_ref {
+            Some(TypeRef::Simple(name)) if self.type_params.contains(name) => None,
+            Some(ty) => Some(ty.clone()),
+            None if self.is_data() => Some(TypeRef::Simple("string".to_string())),
+            None => None,
+        }
+    }
+}
+
+impl FieldSymbol {
+    /// Element type name of a `[T]` field whose `T` is a named type
+    pub fn array_element(&self) -> Option<&str> {
+        match &self.type_ref {
+            Some(TypeRef::Array(element)) => match element.as_ref() {
+                TypeRef::Simple(name) => Some(name.as_str()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl MethodSymbol {
+    pub fn returns_string(&self) -> bool {
+        matches!(&self.return_type, Some(TypeRef::Simple(name)) if name == "string")
+    }
+
+    /// Element type name of a method returning `[T]`, empty when `T` isn't a
+    /// named type
+    pub fn array_element(&self) -> Option<String> {
+        match &self.return_type {
+            Some(TypeRef::Array(element)) => Some(match element.as_ref() {
+                TypeRef::Simple(name) => name.clone(),
+                _ => String::new(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::tokenize, parser::parse, semantic::analyze};
+
+    #[test]
+    fn test_analysis_records_classes() {
+        let source = r#"
+Todo {
+    title: string
+    tags: [string]
+    due?: string
+    constructor(title: string, tags: [string]) {
+        this.title = title
+        this.tags = tags
+    }
+    label(): string {
+        return this.title
+    }
+    check() {
+        if this.title == "" fail "untitled"
+    }
+}
+
+main() {
+    print(Todo("a", []).label())
+}
+"#;
+        let program = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+        let todo = program.symbols.class("Todo").unwrap();
+        assert_eq!(todo.constructor_optionals(), vec![false, false, true]);
+        assert_eq!(todo.field("tags").unwrap().array_element(), Some("string"));
+        assert!(todo.method("label").unwrap().returns_string());
+        assert!(todo.method("check").unwrap().fallible);
+        let string = Some(TypeRef::Simple("string".to_string()));
+        assert_eq!(todo.constructor_params()[0], string);
+        assert_eq!(todo.field_type("due"), string);
+        assert!(!program.symbols.is_class("main"));
+    }
+}
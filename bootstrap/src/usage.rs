@@ -0,0 +1,205 @@
+//! Language feature usage of one build, for `livac build --report`.
+//!
+//! The report counts what a program uses — async and parallel execution,
+//! fallible functions, classes, JSON literals and so on — by walking the
+//! analyzed AST, so async inference has already run on the entry module.
+//! It is written to a local file named by the user and sent nowhere; it is
+//! meant for auditing a codebase before adopting or upgrading a feature.
+
+use crate::ast::*;
+use serde::Serialize;
+
+/// How often each language feature appears in the compiled modules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct FeatureUsage {
+    /// Liva files compiled, the entry module included
+    pub modules: usize,
+    /// Functions and methods, nested functions included
+    pub functions: usize,
+    /// Functions and methods async inference marked async
+    pub async_functions: usize,
+    /// Functions and methods that can `fail`
+    pub fallible_functions: usize,
+    pub classes: usize,
+    pub types: usize,
+    pub enums: usize,
+    pub tests: usize,
+    /// `async f()` and `task async f()` calls
+    pub async_calls: usize,
+    /// `par f()` and `task par f()` calls
+    pub par_calls: usize,
+    /// `for par` / `for vec` / `for parvec` loops
+    pub parallel_loops: usize,
+    /// `.par()` / `.vec()` / `.parvec()` array adapters
+    pub par_adapters: usize,
+    /// `let value, err = ...` bindings
+    pub error_bindings: usize,
+    /// `{ key: value }` literals
+    pub json_literals: usize,
+    pub lambdas: usize,
+    pub string_templates: usize,
+    /// `switch` statements and expressions
+    pub switches: usize,
+    /// `use rust` declarations and `rust { ... }` blocks, `unsafe rust fn`
+    /// bodies included
+    pub rust_interop: usize,
+    /// Signatures of the `unsafe rust fn` declarations, whose bodies the
+    /// compiler doesn't check: the unsafe surface of the program
+    pub unsafe_rust: Vec<String>,
+}
+
+impl FeatureUsage {
+    /// Usage across `programs`, one per compiled module
+    pub fn collect<'a>(programs: impl IntoIterator<Item = &'a Program>) -> Self {
+        let mut counter = Counter(FeatureUsage::default());
+        for program in programs {
+            counter.0.modules += 1;
+            counter.visit_program(program);
+        }
+        counter.0
+    }
+
+    fn callable(&mut self, is_async: bool, contains_fail: bool, return_type: &Option<TypeRef>) {
+        self.functions += 1;
+        if is_async {
+            self.async_functions += 1;
+        }
+        if contains_fail || matches!(return_type, Some(TypeRef::Fallible(_))) {
+            self.fallible_functions += 1;
+        }
+    }
+}
+
+struct Counter(FeatureUsage);
+
+impl Visitor<'_> for Counter {
+    fn visit_item(&mut self, item: &TopLevel) {
+        match item {
+            TopLevel::UseRust(_) => self.0.rust_interop += 1,
+            TopLevel::Class(_) => self.0.classes += 1,
+            TopLevel::Type(_) => self.0.types += 1,
+            TopLevel::Enum(_) => self.0.enums += 1,
+            TopLevel::Test(_) => self.0.tests += 1,
+            _ => {}
+        }
+        walk_item(self, item);
+    }
+
+    fn visit_function(&mut self, func: &FunctionDecl) {
+        if func.unsafe_rust {
+            self.0
+                .unsafe_rust
+                .push(crate::signatures::Signature::from_function(func).label());
+        }
+        self.0.callable(
+            func.is_async_inferred,
+            func.contains_fail,
+            &func.return_type,
+        );
+        walk_function(self, func);
+    }
+
+    fn visit_method(&mut self, method: &MethodDecl) {
+        self.0.callable(
+            method.is_async_inferred,
+            method.contains_fail,
+            &method.return_type,
+        );
+        walk_method(self, method);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl(decl) if decl.is_fallible => self.0.error_bindings += 1,
+            Stmt::For(for_stmt) if for_stmt.policy != DataParallelPolicy::Seq => {
+                self.0.parallel_loops += 1
+            }
+            Stmt::Switch(_) => self.0.switches += 1,
+            _ => {}
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::RustBlock { .. } => self.0.rust_interop += 1,
+            Expr::Call(call) => match call.exec_policy {
+                ExecPolicy::Async | ExecPolicy::TaskAsync => self.0.async_calls += 1,
+                ExecPolicy::Par | ExecPolicy::TaskPar => self.0.par_calls += 1,
+                ExecPolicy::Normal => {}
+            },
+            Expr::MethodCall(call) if call.adapter != ArrayAdapter::Seq => self.0.par_adapters += 1,
+            Expr::ObjectLiteral(_) => self.0.json_literals += 1,
+            Expr::Lambda(_) => self.0.lambdas += 1,
+            Expr::StringTemplate { .. } => self.0.string_templates += 1,
+            Expr::Switch(_) => self.0.switches += 1,
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::tokenize, parser::parse, semantic::analyze};
+
+    fn usage(source: &str) -> FeatureUsage {
+        let program = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+        FeatureUsage::collect([&program])
+    }
+
+    #[test]
+    fn test_counts_features() {
+        let source = r#"
+Point {
+    x: number
+    constructor(x: number) { this.x = x }
+}
+
+divide(a: number, b: number): number {
+    if b == 0 fail "division by zero"
+    return a / b
+}
+
+main() {
+    let q, err = divide(4, 2)
+    let config = { port: 8080 }
+    let doubled = [1, 2, 3].par().map(n => n * 2)
+    for par n in [1, 2] {
+        print($"{n}")
+    }
+    print(q, err, config, doubled, Point(1))
+}
+"#;
+        let usage = usage(source);
+        assert_eq!(usage.modules, 1);
+        assert_eq!(usage.classes, 1);
+        assert_eq!(usage.fallible_functions, 1);
+        assert_eq!(usage.error_bindings, 1);
+        assert_eq!(usage.json_literals, 1);
+        assert_eq!(usage.par_adapters, 1);
+        assert_eq!(usage.parallel_loops, 1);
+        assert_eq!(usage.lambdas, 1);
+        assert_eq!(usage.string_templates, 1);
+        assert_eq!(usage.async_calls, 0);
+    }
+
+    #[test]
+    fn test_lists_unsafe_rust() {
+        let source = r#"
+unsafe rust fn checksum(data: string): number {
+    data.bytes().map(|b| b as i32).sum()
+}
+
+main() {
+    print(checksum("liva"))
+}
+"#;
+        let usage = usage(source);
+        assert_eq!(usage.unsafe_rust, vec!["checksum(data: string): number"]);
+        assert_eq!(usage.rust_interop, 1);
+    }
+}
@@ -0,0 +1,102 @@
+//! Conformance tests every code generation backend must pass.
+
+use livac::backend::BackendKind;
+use livac::module::Module;
+use livac::{compile_file, compile_source, CompilerOptions};
+use tempfile::TempDir;
+
+const PROGRAM: &str = r#"
+Point {
+    x: number
+    y: number
+}
+
+add(a: number, b: number): number => a + b
+
+main() {
+    let p = Point(1, 2)
+    print(add(p.x, p.y))
+}
+"#;
+
+fn options(backend: BackendKind) -> CompilerOptions {
+    CompilerOptions {
+        backend,
+        ..CompilerOptions::default()
+    }
+}
+
+#[test]
+fn test_backend_names_round_trip() {
+    for &kind in BackendKind::ALL {
+        let name = kind.backend().name();
+        assert_eq!(name.parse::<BackendKind>(), Ok(kind));
+    }
+    assert!("cobol".parse::<BackendKind>().is_err());
+}
+
+#[test]
+fn test_single_file_output() {
+    for &kind in BackendKind::ALL {
+        let backend = kind.backend();
+        let result = compile_source(PROGRAM, &options(kind)).unwrap();
+        let entry = result.rust_code.unwrap();
+        assert!(!entry.is_empty(), "{}: empty entry file", backend.name());
+        assert!(!result.cargo_toml.unwrap().is_empty());
+        assert!(
+            result
+                .metadata
+                .files
+                .iter()
+                .any(|file| file.path == backend.entry_file()),
+            "{}: entry file missing from metadata",
+            backend.name()
+        );
+    }
+}
+
+#[test]
+fn test_output_is_deterministic() {
+    for &kind in BackendKind::ALL {
+        let first = compile_source(PROGRAM, &options(kind)).unwrap();
+        let second = compile_source(PROGRAM, &options(kind)).unwrap();
+        assert_eq!(first.rust_code, second.rust_code, "{:?}", kind);
+        assert_eq!(first.cargo_toml, second.cargo_toml, "{:?}", kind);
+    }
+}
+
+#[test]
+fn test_every_module_gets_a_file() {
+    let dir = TempDir::new().unwrap();
+    let main = dir.path().join("main.liva");
+    let math = dir.path().join("math.liva");
+    std::fs::write(
+        &main,
+        "import { square } from \"./math.liva\"\n\nmain() {\n    print(square(3))\n}\n",
+    )
+    .unwrap();
+    std::fs::write(&math, "square(n: number): number => n * n\n").unwrap();
+
+    for &kind in BackendKind::ALL {
+        let backend = kind.backend();
+        let result = compile_file(&CompilerOptions {
+            input: main.clone(),
+            ..options(kind)
+        })
+        .unwrap();
+        assert!(result.has_imports);
+        assert!(!result.rust_code.unwrap().is_empty());
+
+        let module_file = backend.module_file(&Module::from_file(&math).unwrap());
+        let module_files = result.module_files.unwrap_or_default();
+        assert!(
+            module_files
+                .get(&module_file)
+                .is_some_and(|code| !code.is_empty()),
+            "{}: no {} in {:?}",
+            backend.name(),
+            module_file.display(),
+            module_files.keys().collect::<Vec<_>>()
+        );
+    }
+}
@@ -70,6 +70,69 @@ fn check_emits_json_when_requested() {
     );
 }
 
+#[test]
+fn check_workspace_reports_every_broken_file() {
+    let (dir, _) = write_temp("main() {\n    print(\"ok\")\n}\n", "ok.liva");
+    std::fs::write(dir.path().join("bad.liva"), "main() {\n    let x =\n").unwrap();
+    std::fs::write(dir.path().join("worse.liva"), "f( {\n").unwrap();
+
+    let out = Command::new(livac_bin())
+        .args([
+            "check",
+            "--workspace",
+            "--json",
+            dir.path().to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("bad.liva"), "stdout: {}", stdout);
+    assert!(stdout.contains("worse.liva"), "stdout: {}", stdout);
+    assert!(!stdout.contains("ok.liva"), "stdout: {}", stdout);
+}
+
+#[test]
+fn build_emit_ast_pretty_prints_tree() {
+    let (_dir, path) = write_temp(
+        "main() {\n    let n = 2 ** 3\n    print(n)\n}\n",
+        "tree.liva",
+    );
+    let out = Command::new(livac_bin())
+        .args(["build", "--emit", "ast-pretty", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.starts_with("Function main() @1:1\n"), "{}", stdout);
+    assert!(
+        stdout.contains("      Binding n: number (inferred) @2:9\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("        Binary **\n"), "{}", stdout);
+}
+
+#[test]
+fn builtins_json_lists_builtins() {
+    let out = Command::new(livac_bin())
+        .args(["builtins", "--json"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let table: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let builtins = table["builtins"].as_array().unwrap();
+    let read = builtins.iter().find(|b| b["name"] == "File.read").unwrap();
+    assert_eq!(read["fallible"], true);
+    assert_eq!(read["params"][0]["name"], "path");
+    let get = builtins.iter().find(|b| b["name"] == "HTTP.get").unwrap();
+    assert_eq!(get["async"], true);
+}
+
 #[test]
 fn fmt_check_passes_on_clean_source() {
     let clean = "main() {\n    print(\"ok\")\n}\n";
@@ -178,3 +241,36 @@ fn unknown_subcommand_errors() {
         .unwrap();
     assert!(!out.status.success());
 }
+
+#[test]
+fn dev_run_examples_compiles_the_golden_examples() {
+    let examples = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let out = Command::new(livac_bin())
+        .args([
+            "dev",
+            "run-examples",
+            "--no-run",
+            examples.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "stdout: {}", stdout);
+    assert!(stdout.contains("hello.liva"), "stdout: {}", stdout);
+}
+
+#[test]
+fn dev_run_examples_fails_on_a_broken_sample() {
+    let (dir, _path) = write_temp("main() {\n    let x =\n}\n", "broken.liva");
+    let out = Command::new(livac_bin())
+        .args([
+            "dev",
+            "run-examples",
+            "--no-run",
+            dir.path().to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stdout).contains("FAIL"));
+}
@@ -2901,6 +2901,97 @@ main() {
     assert_snapshot!("feature_parvec_filter", rust_code);
 }
 
+#[test]
+fn test_comprehension_generates_filter_map_chain() {
+    let program = |init: &str| {
+        format!("main() {{\n    let numbers = [1, 2, 3, 4, 5]\n    let result = {init}\n    print(result)\n}}\n")
+    };
+    for (comprehension, chain) in [
+        (
+            "[x * 2 for x in numbers if x > 2]",
+            "numbers.filter(x => x > 2).map(x => x * 2)",
+        ),
+        (
+            "[x for x in numbers if x > 2]",
+            "numbers.filter(x => x > 2)",
+        ),
+        ("[x + 1 for x in numbers]", "numbers.map(x => x + 1)"),
+        (
+            "[x * 2 for par x in numbers]",
+            "numbers.par().map(x => x * 2)",
+        ),
+    ] {
+        assert_eq!(
+            compile_and_generate(&program(comprehension)),
+            compile_and_generate(&program(chain)),
+            "{}",
+            comprehension
+        );
+    }
+}
+
+#[test]
+fn test_power_operator_picks_pow_method() {
+    let source = r#"
+main() {
+    let x = 1.5
+    let n = 3
+    let a = x ** 2
+    let b = x ** 0.5
+    let c = 2 ** 10
+    let d = (n as float) ** 3
+    let e = 2 ** 0.5
+    let f = (x + 0.5) ** -1
+    let g = n ** 2
+    print($"{a} {b} {c} {d} {e} {f} {g}")
+}
+"#;
+    let rust = compile_and_generate(source);
+    for expected in [
+        "let a = x.powi(2);",
+        "let b = x.powf(0.5_f64);",
+        "let c = i32::pow(2, 10);",
+        "let d = ((n) as f64).powi(3);",
+        "let e = (2 as f64).powf(0.5_f64);",
+        "let f = (x + 0.5_f64).powi(-1);",
+        // `n` isn't known to be an integer here, so rustc picks the method
+        "let g = liva_rt::pow(n, 2);",
+    ] {
+        assert!(rust.contains(expected), "missing {}\n{}", expected, rust);
+    }
+}
+
+#[test]
+fn test_bitwise_operators_and_bits_functions() {
+    let source = r#"
+main() {
+    let flags = 5
+    let mask = 1 << 2
+    let on = flags & mask != 0
+    let both = flags & (mask | 8)
+    let inv = ~(flags ^ mask)
+    let half = flags >> 1
+    flags |= 8
+    let ones = Bits.countOnes(flags)
+    let turned = Bits.rotateLeft(flags, 3)
+    print($"{on} {both} {inv} {half} {ones} {turned}")
+}
+"#;
+    let rust = compile_and_generate(source);
+    for expected in [
+        "let mask = 1 << 2;",
+        "let on = flags & mask != 0;",
+        "let both = flags & (mask | 8);",
+        "let inv = !(flags ^ mask);",
+        "let half = flags >> 1;",
+        "flags = flags | 8;",
+        "let ones = i32::count_ones(flags) as i32;",
+        "let turned = i32::rotate_left(flags, (3) as u32);",
+    ] {
+        assert!(rust.contains(expected), "missing {}\n{}", expected, rust);
+    }
+}
+
 #[test]
 fn test_dir_list_and_isdir() {
     let source = r#"
@@ -4099,6 +4190,32 @@ main() {
     assert_snapshot!("v14_math_clamp_sign_log", rust_code);
 }
 
+#[test]
+fn test_math_floor_div_and_mod() {
+    let source = r#"
+main() {
+    let n = -7
+    let q = Math.floorDiv(n, 2)
+    let r = Math.floorMod(n, 2)
+    let t = n % 2
+    print(q, r, t)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("let q = liva_rt::floor_div(n, 2);"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("let r = liva_rt::floor_mod(n, 2);"),
+        "{}",
+        rust_code
+    );
+    // `%` stays Rust's truncating remainder
+    assert!(rust_code.contains("let t = n % 2;"), "{}", rust_code);
+}
+
 // ============================================================
 // v1.4 — Stdlib P0: Array methods
 // ============================================================
@@ -4382,6 +4499,111 @@ main() {
     assert_snapshot!("v15_rust_block_nested_braces", rust_code);
 }
 
+#[test]
+fn test_unsafe_rust_fn_keeps_body_verbatim() {
+    let source = r#"
+unsafe rust fn checksum(data: string): number {
+    let mut sum: i32 = 0;
+    for b in data.bytes() { sum = sum.wrapping_add(b as i32); }
+    sum
+}
+
+main() {
+    print(checksum("liva"))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("fn checksum(data: String) -> i32 {"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("for b in data.bytes() { sum = sum.wrapping_add(b as i32); }"));
+}
+
+#[test]
+fn test_unsafe_rust_fn_bridges_result() {
+    let source = r#"
+unsafe rust fn parsePort(text: string): number! {
+    text.trim().parse::<i32>()
+}
+
+unsafe rust fn firstWord(text: string): string? {
+    text.split_whitespace().next().map(|word| word.to_string())
+}
+
+main() {
+    let port, err = parsePort("8080")
+    print(port, err, firstWord("hello world"))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("fn parse_port(text: String) -> Result<i32, liva_rt::Error> {"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("liva_rt::from_rust_result({"));
+    assert!(rust_code.contains("fn first_word(text: String) -> Option<String> {"));
+}
+
+#[test]
+fn test_operands_parenthesized_by_rust_precedence() {
+    // Los paréntesis de la fuente se conservan donde Rust los necesita
+    let source = r#"
+calc(a: bool, b: bool, n: number) {
+    let both = not (a and b)
+    let neg = -(n - 3)
+    let diff = n - (n - 1)
+    let mask = ~(n & 3)
+    print(both, neg, diff, mask)
+}
+
+main() {
+    calc(true, false, 10)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("!(a && b)"), "{}", rust_code);
+    assert!(rust_code.contains("-(n - 3)"));
+    assert!(rust_code.contains("n - (n - 1)"));
+    assert!(rust_code.contains("!(n & 3)"));
+}
+
+#[test]
+fn test_postfix_unwrap_parenthesizes_its_operand() {
+    let source = r#"
+find(items: [number], n: number): number? {
+    for item in items {
+        if item == n {
+            return item
+        }
+    }
+    return null
+}
+
+main() {
+    let items = [1, 2, 3]
+    let found = (find(items, 2) ?? find(items, 3))!
+    let first = (items.length > 2 ? find(items, 1) : null)!
+    print(found, first, (1, "one"))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains(
+            "(find(items.clone(), 2)).unwrap_or_else(|| find(items.clone(), 3)).unwrap()"
+        ),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains(
+        "(if (items.len() as i32) > 2 { find(items.clone(), 1) } else { None }).unwrap()"
+    ));
+    assert!(rust_code.contains("return None;"));
+    assert!(rust_code.contains("(1, \"one\".to_string())"));
+}
+
 #[test]
 fn test_v15_use_rust_version_features_cargo() {
     let source = r#"
@@ -5266,6 +5488,69 @@ main() {
     assert_snapshot!("type_as_field_name", rust_code);
 }
 
+#[test]
+fn test_identifiers_colliding_with_rust_and_runtime() {
+    let source = r#"
+liva_rt(n: number): number => n + 1
+
+main() {
+    let fn = 1
+    let crate = 2
+    let super = liva_rt(fn)
+    print(crate + super)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("let r#fn = 1") && rust_code.contains("let crate_ = 2"),
+        "keywords must be escaped, with a suffix where r# isn't allowed: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("fn liva_rt_(n: i32)") && rust_code.contains("= liva_rt_("),
+        "a user `liva_` name must not clash with the runtime: {}",
+        rust_code
+    );
+    assert!(rust_code.contains("mod liva_rt {"));
+}
+
+#[test]
+fn test_doc_comments_carried_to_rust() {
+    let source = r#"
+//! Order totals
+
+/// A line on an invoice
+Item {
+    /// Price in cents
+    price: number
+}
+
+/// Sum of the item prices
+total(items: [Item]): number {
+    // running sum, not documentation
+    let sum = 0
+    for item in items {
+        sum = sum + item.price
+    }
+    return sum
+}
+
+main() {
+    print(total([Item { price: 3 }]))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.starts_with("//! Order totals\n#![allow("),
+        "file docs must open the crate: {}",
+        rust_code
+    );
+    assert!(rust_code.contains("/// A line on an invoice\n#[derive("));
+    assert!(rust_code.contains("    /// Price in cents\n    pub price: "));
+    assert!(rust_code.contains("/// Sum of the item prices\nfn total("));
+    assert!(!rust_code.contains("running sum"));
+}
+
 #[test]
 fn test_enum_field_default_derive() {
     // B14: Class with enum field should compile — enum must derive Default
@@ -5543,20 +5828,21 @@ main() {
 "#;
     let rust_code = compile_and_generate(source);
     let main_code = rust_code.split("fn main()").last().unwrap_or("");
-    // Each escape should generate its correct Rust char literal
+    // Each escape should keep its meaning; `ch` is a one-character string,
+    // so the char literals are compared as string literals
     assert!(
-        main_code.contains("'\\n'"),
-        "Should contain newline char literal: {}",
+        main_code.contains("ch.as_str() == \"\\n\""),
+        "Should contain newline literal: {}",
         main_code
     );
     assert!(
-        main_code.contains("'\\t'"),
-        "Should contain tab char literal: {}",
+        main_code.contains("ch.as_str() == \"\\t\""),
+        "Should contain tab literal: {}",
         main_code
     );
     assert!(
-        main_code.contains("'\\\\'"),
-        "Should contain backslash char literal: {}",
+        main_code.contains("ch.as_str() == \"\\\\\""),
+        "Should contain backslash literal: {}",
         main_code
     );
     assert_snapshot!("char_escape_sequences", rust_code);
@@ -6572,6 +6858,27 @@ main() {
     assert_snapshot!("process_functions", rust_code);
 }
 
+#[test]
+fn test_process_args() {
+    let source = r#"
+main() {
+    let args = Process.args()
+    let port = args.length > 1 && args[0] == "--port" ? args[1] : "8080"
+    print(port)
+}
+"#;
+
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("let args = std::env::args().skip(1).collect::<Vec<String>>();"),
+        "{}",
+        rust_code
+    );
+    // Indexed like Sys.args(): a plain Vec<String>
+    assert!(rust_code.contains("args[("), "{}", rust_code);
+    assert!(rust_code.contains(") as usize].clone()"), "{}", rust_code);
+}
+
 // ==================== HTTP Server Tests ====================
 
 #[test]
@@ -6602,6 +6909,11 @@ main() {
         "Should contain TcpListener: {}",
         rust_code
     );
+    assert!(
+        rust_code.contains("with_graceful_shutdown(liva_shutdown_signal())"),
+        "Should drain connections on shutdown: {}",
+        rust_code
+    );
     assert!(
         rust_code.contains("#[tokio::main]"),
         "Should contain tokio::main: {}",
@@ -6927,6 +7239,183 @@ main() {
     );
 }
 
+// ============================================================
+// Structured concurrency (scope) tests
+// ============================================================
+
+#[test]
+fn test_scope_owns_tasks() {
+    let source = r#"
+save(n: number) {
+    if n < 0 {
+        fail "negative"
+    }
+    print(n)
+}
+
+audit(msg: string) {
+    print(msg)
+}
+
+compute(n: number): number => n * 2
+
+main() {
+    let scope = 3
+    scope {
+        async save(scope)
+        async audit("saved")
+        let total = par compute(2)
+        print(total)
+    }
+    print("done")
+}
+"#;
+
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("let mut __scope_0 = liva_rt::TaskScope::new();"),
+        "Should open a task scope: {}",
+        rust_code
+    );
+    // A fallible task's result goes to the scope; others are discarded
+    assert!(
+        rust_code.contains("__scope_0.fire_async(async move { save(scope) })"),
+        "Should keep the fallible task's result: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("__scope_0.fire_async(async move { audit(\"saved\".to_string()); })"),
+        "Should fire audit in the scope: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("__scope_0.spawn_parallel("),
+        "Should spawn the par task in the scope: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("if let Err(e) = __scope_0.join().await { panic!(\"{}\", e); }"),
+        "Should join the scope outside fallible functions: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("#[tokio::main]"),
+        "A scope needs the async runtime: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_scope_in_fallible_function() {
+    let source = r#"
+save(n: number) {
+    print(n)
+}
+
+saveAll(n: number) {
+    scope {
+        if n < 0 {
+            fail "negative"
+        }
+        async save(n)
+        scope {
+            async save(n + 1)
+        }
+        let log = (x: number) => {
+            async save(x)
+        }
+        log(n)
+    }
+}
+
+main() {
+    let _, err = saveAll(1)
+    print(err)
+}
+"#;
+
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("if let Err(e) = __scope_0.join().await { return Err(e); }"),
+        "Should return the scope's error: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("__scope_1.fire_async(async move { save((n + 1)); })")
+            || rust_code.contains("__scope_1.fire_async(async move { save(n + 1); })"),
+        "Nested scope should own its task: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_rt::fire_async("),
+        "A lambda's task shouldn't join the enclosing scope: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_scope_formatter() {
+    let source = "main() {\n    scope {\n        async work()\n    }\n}\n";
+    let options = liva_tools::formatter::FormatOptions::default();
+    let formatted = liva_tools::formatter::format_source(source, &options).unwrap();
+    assert!(
+        formatted.contains("    scope {\n        async work()\n    }"),
+        "{}",
+        formatted
+    );
+}
+
+#[test]
+fn test_result_combinators() {
+    let source = r#"
+parsePort(text: string): number {
+  if text == "" {
+    fail "empty port"
+  }
+  return 8080
+}
+
+main() {
+  let port = parsePort("").orElse(3000)
+  let fixed = parsePort("80").expect("port is hardcoded")
+  let value, err = parsePort("x").mapErr(e => $"reading port: {e}")
+  let content, readErr = File.read("app.conf").mapErr(e => $"config: {e}")
+  print(port, fixed, value, err, content, readErr)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("{ Ok(v) => v, Err(_) => 3000 };"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("Err(e) => panic!(\"{}\", liva_rt::Error::chain(\"port is hardcoded\""),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("let err = err.map(|e| liva_rt::Error::chain(("),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("let read_err = if read_err.is_empty() { read_err } else { ("),
+        "{}",
+        rust_code
+    );
+    // `.expect()` panics, so main doesn't become fallible
+    assert!(rust_code.contains("fn main() {"), "{}", rust_code);
+}
+
+#[test]
+fn test_result_combinator_formatter() {
+    let source = "main() {\n    let port = parsePort(text).orElse(3000)\n    let value, err = load(path).mapErr(describe)\n}\n";
+    let options = liva_tools::formatter::FormatOptions::default();
+    let formatted = liva_tools::formatter::format_source(source, &options).unwrap();
+    assert_eq!(formatted, source);
+}
+
 // ===================== Compound Assignment Tests (C4) =====================
 
 #[test]
@@ -7521,3 +8010,1752 @@ main() {
         rust_code
     );
 }
+
+#[test]
+fn test_runtime_block_configures_thread_pools() {
+    let source = r#"
+runtime { threads: 2, asyncThreads: 3 }
+
+work(n: number): number => n * 2
+
+main() {
+    let doubled = [1, 2, 3].par().map(n => n * 2)
+    print(doubled)
+    let r = async work(21)
+    print(r)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("std::env::var(\"LIVA_THREADS\")"),
+        "runtime block must honour the LIVA_THREADS override: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_thread_count(Some(2))") && rust_code.contains("build_global()"),
+        "parThreads must fall back to threads for the rayon pool: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_thread_count(Some(3))")
+            && rust_code.contains("block_on(liva_main())"),
+        "asyncThreads must size the tokio runtime: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("async fn liva_main()") && !rust_code.contains("#[tokio::main]"),
+        "main body must move into liva_main behind the wrapper: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_deterministic_runtime_block_uses_one_thread() {
+    let source = r#"
+runtime { threads: 2, asyncThreads: 3 }
+
+work(n: number): number => n * 2
+
+main() {
+    let doubled = [1, 2, 3].par().map(n => n * 2)
+    print(doubled)
+    let r = async work(21)
+    print(r)
+}
+"#;
+    let tokens = livac::lexer::tokenize(source).unwrap();
+    let program = livac::parser::parse(tokens, source).unwrap();
+    let analyzed = livac::semantic::analyze(program).unwrap();
+    let mut ctx = livac::desugaring::desugar(analyzed.clone()).unwrap();
+    ctx.deterministic = true;
+    let (rust_code, _) = generate_with_ast(&analyzed, ctx).unwrap();
+
+    assert!(
+        rust_code.contains("rayon::ThreadPoolBuilder::new().num_threads(1).build_global()")
+            && rust_code.contains("tokio::runtime::Builder::new_current_thread()"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        !rust_code.contains("liva_thread_count"),
+        "LIVA_THREADS must not override deterministic mode: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_no_runtime_block_keeps_tokio_main() {
+    let source = r#"
+work(n: number): number => n * 2
+
+main() {
+    let r = async work(21)
+    print(r)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("#[tokio::main]"));
+    assert!(!rust_code.contains("liva_thread_count"));
+}
+
+#[test]
+fn test_runtime_block_without_pools_omits_thread_count_helper() {
+    let source = r#"
+runtime { threads: 2 }
+
+main() {
+    print("no pools to size")
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        !rust_code.contains("liva_thread_count"),
+        "a sync main without par() never calls the helper: {}",
+        rust_code
+    );
+    assert!(rust_code.contains("fn liva_main()"), "{}", rust_code);
+}
+
+#[test]
+fn test_init_block_runs_before_main() {
+    let source = r#"
+init {
+    print("loading")
+}
+
+main() {
+    print("running")
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    let init = rust_code.find("fn liva_init() {").expect(&rust_code);
+    let main = rust_code.find("fn main() {").expect(&rust_code);
+    let call = rust_code.find("liva_init();").expect(&rust_code);
+    let body = rust_code.find("\"running\"").expect(&rust_code);
+    assert!(
+        main < call && call < body,
+        "main must call liva_init before its own body: {}",
+        rust_code
+    );
+    assert!(
+        rust_code[init..main].contains("\"loading\""),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_before_and_after_hooks_wrap_every_test() {
+    let source = r#"
+before {
+    print("setup")
+}
+
+after {
+    print("teardown")
+}
+
+test "first" {
+    let a = 1
+}
+
+test "second" {
+    let b = 2
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("fn before_each() {"), "{}", rust_code);
+    assert!(rust_code.contains("fn after_each() {"), "{}", rust_code);
+    assert!(
+        rust_code.contains("static LIVA_TEST_HOOKS: std::sync::Mutex<()>"),
+        "{}",
+        rust_code
+    );
+    // The lock is held before `before` runs, so hooked tests run one at a time
+    for name in ["fn test_first()", "fn test_second()"] {
+        let test = rust_code.find(name).expect(&rust_code);
+        let body = &rust_code[test..];
+        let lock = body
+            .find("crate::LIVA_TEST_HOOKS.lock()")
+            .expect(&rust_code);
+        let setup = body.find("before_each();").expect(&rust_code);
+        let teardown = body.find("after_each();").expect(&rust_code);
+        assert!(lock < setup && setup < teardown, "{}", rust_code);
+    }
+}
+
+#[test]
+fn test_before_and_after_hooks_run_around_describe_tests() {
+    let source = r#"
+import { describe, test, beforeEach } from "liva/test"
+
+before {
+    print("setup")
+}
+
+describe("group", () => {
+    beforeEach(() => {
+        print("group setup")
+    })
+
+    test("inner", () => {
+        let a = 1
+    })
+})
+"#;
+    let rust_code = compile_and_generate(source);
+    let test = rust_code.find("fn test_inner()").expect(&rust_code);
+    let body = &rust_code[test..];
+    let file_setup = body.find("before_each();").expect(&rust_code);
+    let group_setup = body.find("before_each_1();").expect(&rust_code);
+    assert!(file_setup < group_setup, "{}", rust_code);
+    assert!(
+        body.contains("crate::LIVA_TEST_HOOKS.lock()"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_tests_without_hooks_have_no_setup_calls() {
+    let rust_code = compile_and_generate("test \"plain\" {\n    let a = 1\n}\n");
+    assert!(!rust_code.contains("before_each"));
+    assert!(!rust_code.contains("LIVA_TEST_HOOKS"));
+}
+
+#[test]
+fn test_mock_stubs_builtins_in_tests() {
+    let source = r#"
+fetchUsers(url: string): string {
+    let resp, err = HTTP.get(url)
+    if err {
+        return ""
+    }
+    return resp.body
+}
+
+askName(): string {
+    return readLine()
+}
+
+test "users come from the stub" {
+    mock HTTP.get returns "[]"
+    mock readLine returns "ana"
+    let name = askName()
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("liva_rt::mock(\"HTTP.get\", liva_rt::mock_http_response("),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_rt::mock(\"readLine\", (\"ana\").to_string());"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_rt::mock_or(\"HTTP.get\", liva_rt::liva_http_get("),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_rt::mocked::<String>(\"readLine\")"),
+        "{}",
+        rust_code
+    );
+    // Cada test empieza sin stubs
+    let test = rust_code
+        .find("fn test_users_come_from_the_stub()")
+        .expect(&rust_code);
+    let body = &rust_code[test..];
+    let clear = body.find("liva_rt::clear_mocks();").expect(&rust_code);
+    assert!(
+        clear < body.find("liva_rt::mock(").unwrap(),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_builtins_are_called_directly_without_mocks() {
+    let source =
+        "askName(): string {\n    return readLine()\n}\n\nmain() {\n    print(askName())\n}\n";
+    let rust_code = compile_and_generate(source);
+    assert!(!rust_code.contains("liva_rt::mocked"), "{}", rust_code);
+    assert!(
+        !rust_code.contains("liva_rt::clear_mocks()"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_else_if_chain_generates_flat_rust() {
+    let source = r#"
+grade(score: int): string {
+    if score > 90 {
+        return "A"
+    } else if score > 80 {
+        return "B"
+    } else if score > 70 {
+        return "C"
+    } else {
+        return "F"
+    }
+}
+
+main() {
+    print(grade(85))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains(
+            "    } else if score > 80 {\n        return \"B\".to_string();\n    } else if score > 70 {"
+        ),
+        "{}",
+        rust_code
+    );
+    assert!(
+        !rust_code.contains("} else {\n        if "),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_fallible_main_exits_with_error() {
+    let source = r#"
+main() {
+    let path = Sys.env("CONFIG")
+    if path == "" fail "config missing"
+    print(path)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("fn main() -> std::process::ExitCode {")
+            && rust_code.contains("fn liva_main() -> Result<(), liva_rt::Error>"),
+        "fallible main must run behind an ExitCode wrapper: {}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("Ok(()) => std::process::ExitCode::SUCCESS,")
+            && rust_code.contains("Err(e) => liva_rt::main_failed(&e),"),
+        "a failure must print the error and exit non-zero: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_main_returns_exit_code() {
+    let source = r#"
+main(): int {
+    print("checking")
+    return 3
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("fn liva_main() -> i32"), "{}", rust_code);
+    assert!(
+        rust_code.contains("let result = liva_main();")
+            && rust_code
+                .contains("std::process::ExitCode::from(u8::try_from(result).unwrap_or(u8::MAX))"),
+        "{}",
+        rust_code
+    );
+
+    let fallible_async = r#"
+work(n: number): number => n * 2
+
+main(): int {
+    let r = async work(21)
+    if r > 40 fail "too big"
+    return 0
+}
+"#;
+    let rust_code = compile_and_generate(fallible_async);
+    assert!(
+        rust_code.contains("#[tokio::main]\nasync fn main() -> std::process::ExitCode {")
+            && rust_code.contains("let result = liva_main().await;")
+            && rust_code.contains(
+                "Ok(code) => std::process::ExitCode::from(u8::try_from(code).unwrap_or(u8::MAX)),"
+            ),
+        "{}",
+        rust_code
+    );
+
+    // Without fail or a return type, main stays as written
+    let plain = "main() {\n    print(\"hi\")\n}\n";
+    assert!(!compile_and_generate(plain).contains("liva_main"));
+}
+
+#[test]
+fn test_deterministic_mode_seeds_random_builtins() {
+    let source = r#"
+main() {
+    let a = Random.nextInt(1, 1000)
+    let b = Math.random()
+    let id = Random.uuid()
+    let s = Random.shuffle([1, 2, 3])
+    print($"{a} {b} {id} {s}")
+}
+"#;
+    let tokens = livac::lexer::tokenize(source).unwrap();
+    let program = livac::parser::parse(tokens, source).unwrap();
+    let analyzed = livac::semantic::analyze(program).unwrap();
+    let mut ctx = livac::desugaring::desugar(analyzed.clone()).unwrap();
+    ctx.deterministic = true;
+    let (rust_code, _) = generate_with_ast(&analyzed, ctx).unwrap();
+
+    assert!(
+        rust_code.contains("fn liva_rng() -> rand::rngs::StdRng")
+            && rust_code.contains("std::env::var(\"LIVA_SEED\")"),
+        "deterministic mode must emit the seeded RNG helper: {}",
+        rust_code
+    );
+    assert!(
+        !rust_code.contains("thread_rng()") && !rust_code.contains("Uuid::new_v4()"),
+        "no random builtin may bypass the seeded RNG: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_deterministic_mode_reseeds_the_rng_for_each_test() {
+    let source = r#"
+roll(): number => Random.nextInt(1, 6)
+
+test "first roll" {
+    let a = roll()
+}
+
+test "second roll" {
+    let b = roll()
+}
+"#;
+    let tokens = livac::lexer::tokenize(source).unwrap();
+    let program = livac::parser::parse(tokens, source).unwrap();
+    let analyzed = livac::semantic::analyze(program).unwrap();
+    let mut ctx = livac::desugaring::desugar(analyzed.clone()).unwrap();
+    ctx.deterministic = true;
+    let (rust_code, _) = generate_with_ast(&analyzed, ctx).unwrap();
+
+    assert!(rust_code.contains("fn liva_reseed()"), "{}", rust_code);
+    assert_eq!(
+        rust_code.matches("crate::liva_reseed();").count(),
+        2,
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_coverage_mode_instruments_statements() {
+    let source = r#"
+classify(n: number): string {
+    if n > 0 {
+        return "pos"
+    }
+    return "neg"
+}
+
+test "classifies" {
+    let c = classify(1)
+}
+"#;
+    let tokens = livac::lexer::tokenize(source).unwrap();
+    let program = livac::parser::parse(tokens, source).unwrap();
+    let analyzed = livac::semantic::analyze(program).unwrap();
+    let mut ctx = livac::desugaring::desugar(analyzed.clone()).unwrap();
+    ctx.coverage = true;
+    ctx.source_filename = "cls.liva".to_string();
+    let (rust_code, _) = generate_with_ast(&analyzed, ctx).unwrap();
+
+    let sites = livac::coverage::probe_sites(&rust_code);
+    let lines: Vec<u32> = sites.iter().map(|(_, line)| *line).collect();
+    assert_eq!(lines, vec![3, 4, 6, 10], "probes: {}", rust_code);
+    assert!(sites.iter().all(|(file, _)| file == "cls.liva"));
+    assert!(
+        rust_code.contains("let _liva_cov = crate::LivaCoverageGuard;")
+            && rust_code.contains("LIVA_COVERAGE_FILE"),
+        "tests must flush coverage counts: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_no_coverage_probes_by_default() {
+    let rust_code = compile_and_generate("main() {\n    print(1)\n}\n");
+    assert!(!rust_code.contains("liva_cov_hit"));
+}
+
+#[test]
+fn test_debug_mode_emits_line_markers_and_frames() {
+    let source = r#"
+square(n: number): number {
+    let r = n * n
+    return r
+}
+
+main() {
+    print(square(4))
+}
+"#;
+    let tokens = livac::lexer::tokenize(source).unwrap();
+    let program = livac::parser::parse(tokens, source).unwrap();
+    let analyzed = livac::semantic::analyze(program).unwrap();
+    let mut ctx = livac::desugaring::desugar(analyzed.clone()).unwrap();
+    ctx.debug = true;
+    ctx.source_filename = "/p/sq.liva".to_string();
+    let (rust_code, _) = generate_with_ast(&analyzed, ctx).unwrap();
+
+    assert!(
+        rust_code.contains("#[inline(never)]\nfn square("),
+        "Liva functions must keep their own frame: {}",
+        rust_code
+    );
+    let mut map = livac::debuginfo::LineMap::new();
+    map.add_generated("/p/build/src/main.rs", &rust_code);
+    let liva_lines: Vec<u32> = map.entries.iter().map(|e| e.liva_line).collect();
+    assert_eq!(liva_lines, vec![3, 4, 8]);
+
+    let rust_lines: Vec<&str> = rust_code.lines().collect();
+    let entry = map.liva_to_rust("/p/sq.liva", 3).unwrap();
+    assert!(rust_lines[entry.rust_line as usize - 1].contains("let r"));
+}
+
+#[test]
+fn test_string_literal_expression_body_returns_owned_string() {
+    let rust_code = compile_and_generate(
+        r#"
+status(): string => "ok"
+
+Service {
+    constructor() {}
+    name(): string => "api"
+}
+
+main() {
+    print(status())
+}
+"#,
+    );
+    assert!(
+        rust_code.contains("fn status() -> String {\n    \"ok\".to_string()\n}"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("\"api\".to_string()"), "{}", rust_code);
+}
+
+#[test]
+fn test_inline_attributes_follow_plan_and_annotations() {
+    let source = r#"
+double(n: number): number => n * 2
+
+fact(n: number): number => n <= 1 ? 1 : n * fact(n - 1)
+
+@noinline
+triple(n: number): number => n * 3
+
+main() {
+    print(double(fact(triple(2))))
+}
+"#;
+    let tokens = livac::lexer::tokenize(source).unwrap();
+    let program = livac::parser::parse(tokens, source).unwrap();
+    let analyzed = livac::semantic::analyze(program).unwrap();
+    let mut ctx = livac::desugaring::desugar(analyzed.clone()).unwrap();
+    ctx.inline = livac::inlining::plan(
+        [(std::path::Path::new(""), &analyzed)],
+        livac::inlining::DEFAULT_INLINE_THRESHOLD,
+    );
+    let (rust_code, _) = generate_with_ast(&analyzed, ctx).unwrap();
+
+    assert!(rust_code.contains("#[inline]\nfn double("), "{}", rust_code);
+    assert!(
+        rust_code.contains("#[inline(never)]\nfn triple("),
+        "{}",
+        rust_code
+    );
+    assert!(!rust_code.contains("#[inline]\nfn fact("), "{}", rust_code);
+    assert!(
+        !rust_code.contains("#[inline]\n#[tokio::main]"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_numeric_coercion_policy() {
+    use livac::NumericCoercion;
+
+    let implicit = "firstTotal(items) {\n  return items[0].price * 2\n}\n";
+    let explicit = "firstTotal(items) {\n  return items[0].price.asFloat() * 2\n}\n";
+    let generate = |source: &str, policy: NumericCoercion| {
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let analyzed_program = analyze(program).unwrap();
+        let mut ctx = livac::desugaring::desugar(analyzed_program.clone()).unwrap();
+        ctx.numeric_coercion = policy;
+        generate_with_ast(&analyzed_program, ctx)
+    };
+
+    let coerced = generate(implicit, NumericCoercion::Coerce).unwrap().0;
+    assert!(coerced.contains("[\"price\"].as_f64().unwrap_or(0.0)"));
+
+    let checked = generate(implicit, NumericCoercion::Checked).unwrap().0;
+    assert!(checked.contains("[\"price\"].as_f64().expect("));
+    assert!(!checked.contains("[\"price\"].as_f64().unwrap_or(0.0)"));
+
+    let err = generate(implicit, NumericCoercion::Forbid).unwrap_err();
+    assert_eq!(
+        err.error_info().map(|info| info.code.as_str()),
+        Some("E3003")
+    );
+    // An explicit conversion is fine under every policy
+    let converted = generate(explicit, NumericCoercion::Forbid).unwrap().0;
+    assert!(!converted.contains("[\"price\"].as_f64().unwrap_or(0.0)"));
+}
+
+#[test]
+fn test_explain_codegen_comments_heuristics() {
+    // Cada suposición de codegen queda explicada en un comentario
+    let source = "firstTotal(items) {\n  return items[0].price * 2\n}\n";
+    let generate = |explain: bool| {
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let analyzed_program = analyze(program).unwrap();
+        let mut ctx = livac::desugaring::desugar(analyzed_program.clone()).unwrap();
+        ctx.explain_codegen = explain;
+        generate_with_ast(&analyzed_program, ctx).unwrap().0
+    };
+
+    let explained = generate(true);
+    assert!(
+        explained.contains(
+            "/* livac: `price` is named like a number, so the JSON value is converted */"
+        ),
+        "{}",
+        explained
+    );
+    assert!(explained.contains("so `.price` uses JSON bracket notation */"));
+    assert!(explained.contains("[\"price\"].as_f64().unwrap_or(0.0)"));
+
+    // Sin la opción, el código generado no cambia
+    let plain = generate(false);
+    assert!(!plain.contains("/* livac:"));
+}
+
+#[test]
+fn test_identifier_mapping() {
+    use livac::IdentifierMapping;
+
+    let source = "computeTotal(unitPrice: int, itemCount: int): int {\n  let subTotal = unitPrice * itemCount\n  return subTotal\n}\n\nmain() {\n  let loop = computeTotal(2, 3)\n  print(loop)\n}\n";
+    let generate = |mapping: IdentifierMapping| {
+        let program = parse(tokenize(source).unwrap(), source).unwrap();
+        let analyzed_program = analyze(program).unwrap();
+        let mut ctx = livac::desugaring::desugar(analyzed_program.clone()).unwrap();
+        ctx.identifier_mapping = mapping;
+        generate_with_ast(&analyzed_program, ctx).unwrap().0
+    };
+
+    let snake = generate(IdentifierMapping::Snake);
+    assert!(
+        snake.contains("fn compute_total(unit_price: i32, item_count: i32)"),
+        "{}",
+        snake
+    );
+    assert!(snake.contains("let sub_total"), "{}", snake);
+    assert!(!snake.contains("non_snake_case"), "{}", snake);
+
+    let preserved = generate(IdentifierMapping::Preserve);
+    assert!(
+        preserved.contains("fn computeTotal(unitPrice: i32, itemCount: i32)"),
+        "{}",
+        preserved
+    );
+    assert!(preserved.contains("let subTotal"), "{}", preserved);
+    assert!(preserved.contains("non_snake_case"), "{}", preserved);
+    // Keywords are escaped either way
+    assert!(preserved.contains("r#loop"), "{}", preserved);
+}
+
+#[test]
+fn test_nested_functions() {
+    let source = r#"
+total(items: [int], rate: float): float {
+  let bonus = 2
+  square(n: int): int => n * n
+  scaled(n: int): float {
+    return (square(n) + bonus) * rate
+  }
+  return scaled(items[0])
+}
+"#;
+
+    let rust_code = compile_and_generate(source);
+    // No captures: a nested fn; captures `bonus` and `rate`: a closure
+    assert!(
+        rust_code.contains("    fn square(n: i32) -> i32 {"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("    let scaled = |n: i32| -> f64 {"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("    };"), "{}", rust_code);
+}
+
+#[test]
+fn test_if_and_switch_as_values() {
+    let source = r#"
+main() {
+  let score = 85
+  let grade = if score > 90 { "A" } else if score > 80 { "B" } else { "C" }
+  let ratio = if score > 50 { 1 } else { 0.5 }
+  let label = switch score {
+    100 => "perfect",
+    _ => {
+      let missing = 100 - score
+      $"{missing} to go"
+    }
+  }
+  print(grade, ratio, label)
+}
+"#;
+
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains(
+            "let grade = if score > 90 {\n        \"A\".to_string()\n    } else if score > 80 {"
+        ),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("    } else {\n        \"C\".to_string()\n    };"),
+        "{}",
+        rust_code
+    );
+    // int and float branches unify to float
+    assert!(
+        rust_code.contains("        1.0\n    } else {\n        0.5_f64\n    };"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("let label = match score {"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_as_conversions() {
+    let source = r#"
+main() {
+  let ratio = 7.9
+  let whole = ratio as int
+  let half = whole as float / 2.0
+  let label = whole as string
+  let text = "42"
+  let parsed, err = text as int
+  let price = "3.5" as float or 0.0
+  print(whole, half, label, parsed, err, price)
+}
+"#;
+
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("let whole = ((ratio) as i32);"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("((whole) as f64)"), "{}", rust_code);
+    assert!(
+        rust_code.contains("let label = format!(\"{}\", whole);"),
+        "{}",
+        rust_code
+    );
+    // Parsing a string goes through the parseInt/parseFloat paths
+    assert!(
+        rust_code.contains(".parse::<i32>() { Ok(v) => (v, None)"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains(".parse::<f64>() { Ok(v) => v, Err(_) => "),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_template_display_debug_selection() {
+    // Strings, numbers and call results use Display; only aggregates use Debug
+    let source = r#"
+User {
+  name: string
+  tags: [string]
+
+  constructor(name: string, tags: [string]) {
+    this.name = name
+    this.tags = tags
+  }
+
+  getName(): string {
+    return this.name
+  }
+}
+
+main() {
+  let user = User("Ada", ["admin"])
+  let scores = [90, 85]
+  let best = true
+  print($"{user.getName()} scored {scores[0]}")
+  print($"{best ? "top" : "rest"} of {scores.length}")
+  print($"all: {scores}, tags: {user.tags}, pair: {[1, 2]}")
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    let main_code = rust_code.split("fn main()").last().unwrap_or("");
+    assert!(main_code.contains("\"{} scored {}\""), "{}", main_code);
+    assert!(main_code.contains("\"{} of {}\""), "{}", main_code);
+    assert!(
+        main_code.contains("\"all: {:?}, tags: {:?}, pair: {:?}\""),
+        "{}",
+        main_code
+    );
+}
+
+#[test]
+fn test_lambda_block_early_returns() {
+    // `return` inside a block lambda exits the lambda, even in a fallible function
+    let source = r#"
+grade(scores: [number]): [string] {
+  if scores.length == 0 {
+    fail "no scores"
+  }
+  let grades = scores.map(score => {
+    if score < 0 {
+      return "invalid"
+    }
+    for cutoff in [90, 50] {
+      if score >= cutoff {
+        return "pass"
+      }
+    }
+    if score > 40 {
+      return "close"
+    } else {
+      return "fail"
+    }
+  })
+  return grades
+}
+
+main() {
+  let grades, err = grade([95, 45, -1])
+  print(grades)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    let lambda = rust_code
+        .split("fn grade(")
+        .nth(1)
+        .and_then(|grade| grade.split("map(|").nth(1))
+        .and_then(|rest| rest.split("}).collect").next())
+        .unwrap_or("");
+    assert!(
+        lambda.contains("return \"invalid\".to_string();"),
+        "{}",
+        lambda
+    );
+    assert!(
+        lambda.contains("return \"pass\".to_string();"),
+        "{}",
+        lambda
+    );
+    assert!(!lambda.contains("Ok("), "{}", lambda);
+    // The trailing if/else is the lambda's value
+    assert!(lambda.contains("\"close\".to_string()\n"), "{}", lambda);
+    assert!(!lambda.contains("return \"fail\""), "{}", lambda);
+}
+
+#[test]
+fn test_v20_array_min_by_max_by() {
+    let source = r#"
+Reading {
+    sensor: string
+    value: float
+}
+
+main() {
+    let readings = [Reading("a", 2.5), Reading("b", 0.5), Reading("c", 2.5)]
+    let lowest = readings.minBy(r => r.value)
+    if lowest != null {
+        print(lowest.sensor)
+    }
+    let highest = readings.maxBy(r => r.value) or fail "no readings"
+    let sorted = readings.sortBy(r => r.value)
+    print(highest.sensor, sorted)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    // minBy/maxBy yield Option<Reading>: narrowed by `!= null`, unwrapped by `or fail`
+    assert!(!rust_code.contains(".cloned().unwrap()"), "{}", rust_code);
+    assert!(
+        rust_code.contains("if let Some(lowest) = lowest {"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("lowest.sensor"), "{}", rust_code);
+    assert!(rust_code.contains("highest.sensor"), "{}", rust_code);
+    assert!(
+        !rust_code.contains(".get_field(\"sensor\")"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("pub trait SortKey"), "{}", rust_code);
+    assert!(
+        rust_code
+            .contains(".iter().min_by(|__a, __b| { let r = (*__a).clone(); let __ka = r.value;"),
+        "{}",
+        rust_code
+    );
+    // Ties keep the first element, so maxBy scans from the end
+    assert!(rust_code.contains(".iter().rev().max_by("), "{}", rust_code);
+    // Float keys compare with a total order instead of partial_cmp
+    assert!(
+        rust_code.contains("liva_rt::key_cmp(&__ka, &__kb) }).cloned()"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_rt::key_cmp(&__ka, &__kb) }); __v }"),
+        "{}",
+        rust_code
+    );
+    assert!(!rust_code.contains("__ka.partial_cmp"), "{}", rust_code);
+}
+
+#[test]
+fn test_runtime_without_key_sorts_omits_sort_key() {
+    let source = r#"
+main() {
+    let nums = [3, 1, 2]
+    print(nums.sort())
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("mod liva_rt {"), "{}", rust_code);
+    assert!(!rust_code.contains("SortKey"), "{}", rust_code);
+}
+
+#[test]
+fn test_runtime_carries_only_the_helpers_a_program_calls() {
+    let rust_code = compile_and_generate("main() {\n    print(\"hello\")\n}\n");
+    for helper in [
+        "pub struct TaskScope",
+        "pub trait Power",
+        "pub trait FloorDivision",
+        "pub trait TypeOf",
+        "pub trait CharCode",
+        "pub struct Frozen",
+        "LIVA_MOCKS",
+    ] {
+        assert!(!rust_code.contains(helper), "{}\n{}", helper, rust_code);
+    }
+
+    let source = r#"
+main() {
+    let base = 7
+    let items = freeze([1, 2])
+    print(base ** 2, Math.floorDiv(base, 2), typeOf(base), charCode("A"), items)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    for helper in [
+        "pub trait Power",
+        "pub trait FloorDivision",
+        "pub trait TypeOf",
+        "pub trait CharCode",
+        "pub struct Frozen",
+    ] {
+        assert!(rust_code.contains(helper), "{}\n{}", helper, rust_code);
+    }
+    assert!(!rust_code.contains("pub struct TaskScope"), "{}", rust_code);
+    // The helpers stay inside `mod liva_rt`
+    let mod_end = rust_code.find("\n}\n").unwrap();
+    assert!(
+        rust_code.find("pub struct Frozen").unwrap() < mod_end,
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_enumerate_zip_windows() {
+    let source = r#"
+main() {
+  let names = ["Ada", "Grace"]
+  let years = [1815, 1906]
+  for i, name in names.enumerate() {
+    print($"{i}: {name}")
+  }
+  for (name, year) in zip(names, years) {
+    print(name, year)
+  }
+  for window in years.windows(2) {
+    print(window)
+  }
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains(
+            "for (i, name) in names.iter().cloned().enumerate().map(|(i, x)| (i as i32, x)).collect::<Vec<_>>() {"
+        ),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("for (name, year) in names.iter().zip(years.iter())"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("years.windows((2) as usize).map(|w| w.to_vec())"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("println!(\"{:?}\", window);"),
+        "a window is an array: {}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_array_mutation_methods() {
+    let source = r#"
+main() {
+  let names = ["Ada"]
+  names.push("Grace")
+  names.insert(0, "Alan")
+  let removed = names.removeAt(1)
+  let last = names.pop()
+  print(removed, last)
+  names.clear()
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("let mut names = vec!["), "{}", rust_code);
+    assert!(
+        rust_code.contains("names.push(\"Grace\".to_string())"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("names.insert((0) as usize, \"Alan\".to_string())"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("names.remove((1) as usize)"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("names.pop().expect(\"pop from empty array\")"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("names.clear()"), "{}", rust_code);
+}
+
+#[test]
+fn test_clone_builtin() {
+    let source = r#"
+Point {
+  x: int
+  y: int
+}
+
+main() {
+  let points = [Point(1, 2)]
+  let copy = clone(points)
+  let first = clone(copy[0])
+  print($"{clone(points)}", first.x)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("let copy = points.clone();"),
+        "{}",
+        rust_code
+    );
+    assert!(!rust_code.contains(".clone().clone()"), "{}", rust_code);
+    assert!(
+        rust_code.contains("format!(\"{:?}\", points.clone())"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_freeze_builtin() {
+    let source = r#"
+Config {
+  port: int
+}
+
+show(config: Config) {
+  print(config.port)
+}
+
+main() {
+  let config = freeze(Config(80))
+  let shared = config
+  show(shared)
+  let copy = clone(config)
+  print(copy.port)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("let config = liva_rt::freeze(Config::new(80));"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("show(shared.thaw())"), "{}", rust_code);
+    assert!(
+        rust_code.contains("let copy = config.thaw();"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("pub struct Frozen<T>"), "{}", rust_code);
+}
+
+#[test]
+fn test_lazy_values() {
+    let source = r#"
+loadPort(): number {
+  fail "no port"
+}
+
+lazy GREETING = "hello"
+lazy PORT = loadPort()
+
+main() {
+  print(GREETING)
+  let port, err = PORT
+  print(port, err)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("fn GREETING() -> String {"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("fn PORT() -> Result<i32, liva_rt::Error> {"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("static VALUE: std::sync::OnceLock<Result<i32, liva_rt::Error>>"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("VALUE.get_or_init(|| load_port()).clone()"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("match PORT() { Ok(v) => (v, None)"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_constants_fold_or_initialize_lazily() {
+    let source = r#"
+loadPort(): number => 8080
+
+const KB = 1024
+const SIZE = KB * 4
+const RATIO: float = 2 * 3
+const LABEL = $"size {SIZE}"
+const PORT = loadPort()
+
+main() {
+  print(SIZE, RATIO, LABEL, PORT)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("const KB: i32 = 1024;"), "{}", rust_code);
+    assert!(
+        rust_code.contains("const SIZE: i32 = 4096;"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("const RATIO: f64 = 6_f64;"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("const LABEL: &str = \"size 4096\";"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("fn PORT() -> i32 {"), "{}", rust_code);
+    assert!(!rust_code.contains("const PORT"), "{}", rust_code);
+}
+
+#[test]
+fn test_array_constructors() {
+    let source = r#"
+main() {
+  let zeros = Array.fill(1000, 0)
+  let dash = "-"
+  let rule = Array.fill(3, dash)
+  let digits = Array.from(0..10)
+  let dice = (1..=6).toArray()
+  print(zeros.length, rule, dash, digits, dice)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("vec![0; std::cmp::max(1000, 0) as usize]"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("vec![dash.clone(); std::cmp::max(3, 0) as usize]"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("(0 .. 10).collect::<Vec<_>>()"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("(1 ..= 6).collect::<Vec<_>>()"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("zeros.len()"), "{}", rust_code);
+}
+
+#[test]
+fn test_flow_type_narrowing() {
+    let source = r#"
+classify(value: int | string): string {
+  if typeOf(value) == "string" {
+    return value
+  }
+  return "a number"
+}
+
+greet(name: string?) {
+  if name == null {
+    return
+  }
+  print(name.length)
+}
+
+main() {
+  let value: int | string = "hi"
+  print(typeOf(value))
+  print(classify(value))
+  greet("Ada")
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("if let Union_i32_String::Str(value) = value.clone() {"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains(
+            "(match &value { Union_i32_String::Int(_) => \"int\", Union_i32_String::Str(_) => \"string\", }).to_string()"
+        ),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("let Some(name) = name else {"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("name.len()"), "{}", rust_code);
+}
+
+#[test]
+fn test_type_of_builtin() {
+    let source = r#"
+Point {
+  x: int
+}
+
+classify(value: int | string): string {
+  return switch typeOf(value) {
+    "string" => value.toUpperCase(),
+    "int" => $"n{value}",
+    _ => "?"
+  }
+}
+
+main() {
+  let p = Point(1)
+  let data, _err = JSON.parse("[1, 2]")
+  print(typeOf(p), typeOf(42), typeOf(data))
+  print(classify("hi"))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("match value.clone() {"), "{}", rust_code);
+    assert!(
+        rust_code.contains("Union_i32_String::Str(value) =>"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("Union_i32_String::Int(value) =>"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("String::from(\"Point\")"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("liva_rt::type_of(&42)"), "{}", rust_code);
+    assert!(
+        rust_code.contains("liva_rt::type_of(&data)"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_union_parameters_and_returns() {
+    let source = r##"
+type Id = int | string
+
+main() {
+  print(show(parse("a")), show(7), show("b"))
+}
+
+parse(text: string): int | string {
+  if text == "" {
+    return 0
+  }
+  return text
+}
+
+show(id: Id): string {
+  return switch typeOf(id) {
+    "int" => $"#{id}",
+    "string" => id.toUpperCase()
+  }
+}
+"##;
+    let rust_code = compile_and_generate(source);
+    // The union enum lands after the crate attributes, so the file is valid Rust
+    livac::selfcheck::check(std::path::Path::new("main.rs"), &rust_code)
+        .unwrap_or_else(|e| panic!("{}\n{}", e, rust_code));
+    let allows = rust_code.find("#![allow(").expect(&rust_code);
+    let union = rust_code.find("enum Union_i32_String {").expect(&rust_code);
+    assert!(allows < union, "{}", rust_code);
+    assert!(rust_code.contains("-> Union_i32_String {"), "{}", rust_code);
+    // Calls ahead of the definitions still see the union signatures
+    assert!(
+        rust_code.contains("show(Union_i32_String::Str(\"b\".to_string()))"),
+        "{}",
+        rust_code
+    );
+}
+
+#[test]
+fn test_method_overloads_by_arity() {
+    let compile = |source: &str| livac::compile_source(source, &livac::CompilerOptions::default());
+    let source = r#"
+Shape {
+    w: number
+    h: number
+
+    constructor(w: number, h: number) {
+        this.w = w
+        this.h = h
+    }
+
+    scale(f: number) => Shape(this.w * f, this.h * f)
+    scale(fx: number, fy: number) => Shape(this.w * fx, this.h * fy)
+}
+
+main() {
+    let s = Shape(1, 2)
+    let a = s.scale(2)
+    let b = s.scale(2, 3).scale(4)
+    print(a.w, b.h)
+}
+"#;
+    let rust_code = compile(source).unwrap().rust_code.unwrap();
+    assert!(rust_code.contains("fn scale_1("), "{}", rust_code);
+    assert!(rust_code.contains("fn scale_2("), "{}", rust_code);
+    assert!(rust_code.contains("s.scale_1("), "{}", rust_code);
+    assert!(rust_code.contains("s.scale_2("), "{}", rust_code);
+    assert!(rust_code.contains(").scale_1("), "{}", rust_code);
+    assert!(!rust_code.contains(".scale("), "{}", rust_code);
+
+    let code = |source: &str| {
+        compile(source)
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let same_arity = "Box {\n    v: number\n    get(i: number) => this.v + i\n    get(j: string) => this.v\n}\n\nmain() {\n    print(Box(1).get(2))\n}\n";
+    assert_eq!(code(same_arity).as_deref(), Some("E0012"));
+
+    let no_match = "Box {\n    v: number\n    get() => this.v\n    get(i: number) => this.v + i\n}\n\nmain() {\n    let b = Box(1)\n    print(b.get(1, 2))\n}\n";
+    assert_eq!(code(no_match).as_deref(), Some("E0013"));
+
+    // An untyped receiver is fine as long as every class agrees on the overload
+    let ambiguous = "A {\n    get() => 1\n    get(i: number) => i\n}\n\nB {\n    get(i: number) => i\n}\n\nshow(x) {\n    print(x.get(1))\n}\n\nmain() {\n    show(A())\n}\n";
+    assert_eq!(code(ambiguous).as_deref(), Some("E0014"));
+}
+
+#[test]
+fn test_abstract_class_generates_trait_and_struct() {
+    let source = r#"
+abstract Shape {
+    name: string
+    abstract area(): number
+    describe(): string => $"{this.name}: {this.area()}"
+}
+
+Square : Shape {
+    side: number
+    area() => this.side * this.side
+}
+
+main() {
+    let s = Square("sq", 2)
+    print(s.describe())
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("pub trait Shape {"), "{}", rust_code);
+    assert!(
+        rust_code.contains("fn base(&self) -> ShapeBase;"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("fn area(&self) -> i32;"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("pub struct ShapeBase {"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("impl Shape for Square {"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("Square::area(self)"), "{}", rust_code);
+    assert!(
+        rust_code.contains("name: self.name.clone(),"),
+        "{}",
+        rust_code
+    );
+    // The inherited field and method land in the implementing class
+    assert!(rust_code.contains("pub struct Square {"), "{}", rust_code);
+    assert!(
+        rust_code.contains("fn describe(&self) -> String"),
+        "{}",
+        rust_code
+    );
+    assert!(!rust_code.contains("Shape::new"), "{}", rust_code);
+}
+
+#[test]
+fn test_delegate_generates_forwarding_methods() {
+    let source = r#"
+Engine {
+    power: number
+    start() => print("vroom")
+    boost(by: number): number => this.power + by
+}
+
+Car {
+    engine: Engine
+    delegate engine
+}
+
+main() {
+    let car = Car(Engine(100))
+    car.start()
+    print(car.boost(10))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("pub struct Car {"), "{}", rust_code);
+    assert!(rust_code.contains("self.engine.start()"), "{}", rust_code);
+    assert!(
+        rust_code.contains("fn boost(&self, by: i32) -> i32"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("self.engine.boost(by)"), "{}", rust_code);
+}
+
+#[test]
+fn test_class_schema_generates_field_list() {
+    let source = r#"
+User {
+    id: int
+    email?: string
+}
+
+main() {
+    let schema = User.schema()
+    let same = fields(User)
+    print(schema.length + same.length)
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(rust_code.contains("serde_json::json!"), "{}", rust_code);
+    assert!(rust_code.contains("\"name\": \"email\""), "{}", rust_code);
+    assert!(rust_code.contains("\"type\": \"string\""), "{}", rust_code);
+    assert!(rust_code.contains("\"optional\": true"), "{}", rust_code);
+    assert!(!rust_code.contains("schema()"), "{}", rust_code);
+    assert!(!rust_code.contains("fields(User"), "{}", rust_code);
+}
+
+#[test]
+fn test_char_conversions_and_comparisons() {
+    let source = r#"
+main() {
+    let word = "Liva"
+    let next = fromCharCode(charCode('A') + 1)
+    for ch in word.chars() {
+        if ch >= 'a' {
+            print(charCode(ch))
+        }
+    }
+    if word.charAt(0) == 'L' && next == 'B' {
+        print(next)
+    }
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("liva_rt::from_char_code(liva_rt::char_code(&'A') + 1)"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("liva_rt::char_code(&ch)"),
+        "{}",
+        rust_code
+    );
+    // Characters of a string are one-character strings; chars stay chars
+    assert!(rust_code.contains("ch.as_str() >= \"a\""), "{}", rust_code);
+    assert!(rust_code.contains(".as_str() == \"L\""), "{}", rust_code);
+    assert!(rust_code.contains("next == 'B'"), "{}", rust_code);
+}
+
+#[test]
+fn test_nesting_up_to_the_parser_limit_generates() {
+    // Whatever the parser accepts, the later passes must handle on the 8 MiB
+    // stack of the compiler's main thread
+    let levels = livac::parser::MAX_NESTING_DEPTH - 8;
+    let source = format!(
+        "sum(n: int): int {{\n    let x = {}n{}\n    let y = n{}\n    return x + y\n}}\n\n\
+         main() => print(sum(1))\n",
+        "(n + ".repeat(levels),
+        ")".repeat(levels),
+        " + n".repeat(levels)
+    );
+    let rust_code = std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || compile_and_generate(&source))
+        .unwrap()
+        .join()
+        .expect("compiling deeply nested code overflowed the stack");
+    assert!(rust_code.contains("fn sum("), "{}", rust_code);
+}
+
+#[test]
+fn test_function_contracts() {
+    let source = r#"
+divide(a: int, b: int): int
+    requires b != 0
+    ensures result * b <= a
+{
+    return a / b
+}
+
+@checked
+root(n: int): int requires n >= 0 => n / 2
+
+main() {
+    print(divide(7, 2) + root(9))
+}
+"#;
+    let rust_code = compile_and_generate(source);
+
+    // Debug-only unless `@checked`, with the clause and its line in the message
+    assert!(rust_code.contains("debug_assert!("), "{}", rust_code);
+    assert!(
+        rust_code.contains("\"divide requires b != 0 (line 3)\");"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("\"root requires n >= 0 (line 10)\");"),
+        "{}",
+        rust_code
+    );
+    let root_check = rust_code
+        .lines()
+        .find(|line| line.contains("root requires"))
+        .unwrap();
+    assert!(
+        root_check.trim_start().starts_with("assert!("),
+        "{}",
+        rust_code
+    );
+
+    // `ensures` runs the body first and checks the value it produced
+    assert!(
+        rust_code.contains("let __liva_result = (|| -> i32 {"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("#[cfg(debug_assertions)]\n    let __liva_old_b = b.clone();"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("let result = __liva_result.clone();"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("\"divide ensures result * b <= a (line 4)\");"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("    __liva_result\n}"), "{}", rust_code);
+}
+
+#[test]
+fn test_string_literal_args_follow_parameter_types() {
+    // Un literal solo se convierte en String cuando el parámetro es string
+    let source = r#"
+shout(text: string) {
+    print(text)
+}
+
+initial(letter: char) {
+    print(letter)
+}
+
+main() {
+    shout("hey")
+    initial("a")
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("shout(\"hey\".to_string())"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("initial(\"a\")"), "{}", rust_code);
+}
+
+#[test]
+fn test_unused_task_is_reported_as_a_warning() {
+    // Sin análisis semántico, que ya rechaza el handle sin await
+    let source = r#"
+fetchData(): number {
+    return 1
+}
+
+main() {
+    let t = task async fetchData()
+}
+"#;
+    let program = parse(tokenize(source).unwrap(), source).unwrap();
+    let ctx = livac::desugaring::desugar(program.clone()).unwrap();
+    let mut diagnostics = livac::DiagnosticSink::new();
+    livac::codegen::generate_with_diagnostics(&program, ctx, &mut diagnostics).unwrap();
+
+    let diagnostics = diagnostics.into_vec();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, livac::Severity::Warning);
+    assert_eq!(diagnostics[0].code(), "E0606");
+    assert!(diagnostics[0].info.message.contains("'t'"));
+}
+
+#[test]
+fn test_string_literals_follow_class_member_types() {
+    // Constructores, métodos y campos de clases conocidas usan el tipo declarado
+    let source = r#"
+Stamp {
+    mark: char
+    label: string
+    constructor(mark: char, label: string) {
+        this.mark = mark
+        this.label = label
+    }
+    relabel(label: string, mark: char) {
+        this.label = label
+        this.mark = mark
+    }
+}
+
+main() {
+    let stamp = Stamp("x", "first")
+    stamp.relabel("second", "y")
+    stamp.mark = "z"
+    stamp.label = "third"
+}
+"#;
+    let rust_code = compile_and_generate(source);
+    assert!(
+        rust_code.contains("Stamp::new(\"x\", \"first\".to_string())"),
+        "{}",
+        rust_code
+    );
+    assert!(
+        rust_code.contains("relabel(\"second\".to_string(), \"y\")"),
+        "{}",
+        rust_code
+    );
+    assert!(rust_code.contains("mark = \"z\";"), "{}", rust_code);
+    assert!(
+        rust_code.contains("label = \"third\".to_string();"),
+        "{}",
+        rust_code
+    );
+}
@@ -1,4 +1,5 @@
-use livac::{compile_file, CompilerOptions};
+use livac::backend::BackendKind;
+use livac::{compile_file, BuildProfile, CompilerOptions};
 use std::path::PathBuf;
 use tempfile::TempDir;
 
@@ -13,6 +14,22 @@ fn test_http_get() {
         output: Some(temp_dir.path().to_path_buf()),
         verbose: false,
         check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
 
     let result = compile_file(&options);
@@ -33,6 +50,22 @@ fn test_http_post() {
         output: Some(temp_dir.path().to_path_buf()),
         verbose: false,
         check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
 
     let result = compile_file(&options);
@@ -53,6 +86,22 @@ fn test_http_put() {
         output: Some(temp_dir.path().to_path_buf()),
         verbose: false,
         check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
 
     let result = compile_file(&options);
@@ -73,6 +122,22 @@ fn test_http_delete() {
         output: Some(temp_dir.path().to_path_buf()),
         verbose: false,
         check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
 
     let result = compile_file(&options);
@@ -93,6 +158,22 @@ fn test_http_errors() {
         output: Some(temp_dir.path().to_path_buf()),
         verbose: false,
         check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
 
     let result = compile_file(&options);
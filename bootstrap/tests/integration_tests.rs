@@ -1,8 +1,16 @@
-use livac::{compile_file, CompilerOptions};
+use livac::backend::BackendKind;
+use livac::{compile_file, BuildProfile, CompilerOptions};
 use std::path::PathBuf;
 use std::process::Command;
 use tempfile::TempDir;
 
+/// Builds that invoke cargo are slow, so they only run with LIVA_RUN_CARGO_CHECK=1
+fn cargo_check_enabled() -> bool {
+    std::env::var("LIVA_RUN_CARGO_CHECK")
+        .map(|value| value == "1")
+        .unwrap_or(false)
+}
+
 /// Test helper para proyectos de integración
 fn test_integration_project(project_name: &str) {
     let project_path = PathBuf::from(format!("tests/integration/{}", project_name));
@@ -17,6 +25,22 @@ fn test_integration_project(project_name: &str) {
         output: Some(output_dir.clone()),
         verbose: false,
         check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
 
     // Compilar el proyecto
@@ -77,10 +101,7 @@ fn test_integration_project(project_name: &str) {
     );
 
     // Ejecutar cargo check para asegurar que el proyecto compila (en modo offline si es necesario)
-    if std::env::var("LIVA_RUN_CARGO_CHECK")
-        .map(|value| value == "1")
-        .unwrap_or(false)
-    {
+    if cargo_check_enabled() {
         let check_output = Command::new("cargo")
             .arg("check")
             .arg("--offline")
@@ -178,6 +199,606 @@ fn test_extensionless_import_integration() {
     test_integration_project("proj_extensionless_import");
 }
 
+#[test]
+fn test_imported_project_main_is_async_for_server() {
+    // `app.listen` awaits, so async inference must reach the entry module of a multi-file build
+    let temp_dir = TempDir::new().unwrap();
+    let options = CompilerOptions {
+        input: PathBuf::from("tests/integration/proj_server_import/main.liva"),
+        output: Some(temp_dir.path().to_path_buf()),
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let rust_code = compile_file(&options).unwrap().rust_code.unwrap();
+    assert!(rust_code.contains("async fn main()"), "{}", rust_code);
+}
+
+#[test]
+fn test_path_dependency_integration() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let write = |path: &str, contents: &str| {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+    write(
+        "math-x/liva.toml",
+        "[package]\nname = \"math-x\"\nversion = \"0.3.0\"\n",
+    );
+    write(
+        "math-x/src/lib.liva",
+        "clamp(value: number, lo: number, hi: number): number {\n    if value < lo { return lo }\n    if value > hi { return hi }\n    return value\n}\n",
+    );
+    write(
+        "app/liva.toml",
+        "[package]\nname = \"app\"\n\n[dependencies]\nmath-x = { path = \"../math-x\" }\n",
+    );
+    write(
+        "app/src/main.liva",
+        "import { clamp } from \"math-x\"\n\nmain() {\n    print(clamp(42, 0, 10))\n}\n",
+    );
+
+    let options = CompilerOptions {
+        input: root.join("app/src/main.liva"),
+        output: Some(root.join("out")),
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let result = compile_file(&options).expect("project with a path dependency compiles");
+
+    let main_rs = result.rust_code.unwrap();
+    assert!(main_rs.contains("mod math_x;"), "{}", main_rs);
+    assert!(main_rs.contains("use crate::math_x::"), "{}", main_rs);
+    let module_files = result.module_files.unwrap();
+    assert!(module_files.contains_key(&PathBuf::from("src/math_x.rs")));
+
+    let lock = std::fs::read_to_string(root.join("app/liva.lock")).unwrap();
+    assert!(lock.contains("name = \"math-x\""), "{}", lock);
+    assert!(lock.contains("source = \"path+../math-x\""), "{}", lock);
+}
+
+#[test]
+fn test_std_module_import_integration() {
+    let tmp = TempDir::new().unwrap();
+    let input = tmp.path().join("main.liva");
+    std::fs::write(
+        &input,
+        "import { sumOf, distinct } from \"std/collections\"\n\nmain() {\n    print(sumOf([1, 2, 3]))\n    print(distinct([\"a\", \"b\", \"a\"]))\n}\n",
+    )
+    .unwrap();
+
+    let options = CompilerOptions {
+        input: input.clone(),
+        output: Some(tmp.path().join("out")),
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let result = compile_file(&options).expect("std module import compiles");
+
+    // El módulo embebido se genera junto al código del usuario
+    let main_rs = result.rust_code.unwrap();
+    assert!(main_rs.contains("mod std_collections;"), "{}", main_rs);
+    assert!(
+        main_rs.contains("use crate::std_collections::"),
+        "{}",
+        main_rs
+    );
+    let module_files = result.module_files.unwrap();
+    assert!(module_files.contains_key(&PathBuf::from("src/std_collections.rs")));
+
+    // Un módulo std desconocido es un error E4004
+    std::fs::write(&input, "import { nope } from \"std/nope\"\n\nmain() {}\n").unwrap();
+    let Err(livac::CompilerError::CodegenError(info)) = compile_file(&options) else {
+        panic!("expected an unknown std module error");
+    };
+    assert_eq!(info.code, "E4004");
+    assert!(info.message.contains("std/collections"), "{}", info.message);
+}
+
+#[test]
+fn test_incremental_compile_matches_full_compile() {
+    let tmp = TempDir::new().unwrap();
+    let input = tmp.path().join("main.liva");
+    let source =
+        "inc(n: number): number {\n    return n + 1\n}\n\nmain() {\n    print(inc(1))\n}\n";
+    std::fs::write(&input, source).unwrap();
+
+    let options = CompilerOptions {
+        input: input.clone(),
+        output: None,
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: true,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let full = CompilerOptions {
+        incremental: false,
+        ..options.clone()
+    };
+    let first = compile_file(&options).unwrap();
+    assert_eq!(first.rust_code, compile_file(&full).unwrap().rust_code);
+    assert_eq!(first.rust_code, compile_file(&options).unwrap().rust_code);
+
+    // Solo cambia `main`; el resultado es el de una compilación completa
+    std::fs::write(&input, source.replace("inc(1)", "inc(41)")).unwrap();
+    let edited = compile_file(&options).unwrap().rust_code.unwrap();
+    assert!(edited.contains("41"), "{}", edited);
+    assert_eq!(Some(edited), compile_file(&full).unwrap().rust_code);
+}
+
+#[test]
+fn test_check_workspace_flags_importers_of_broken_modules() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let write = |path: &str, contents: &str| {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+    write(
+        "shapes.liva",
+        "area(w: number, h: number): number => w * h\n",
+    );
+    write(
+        "main.liva",
+        "import { area } from \"./shapes.liva\"\n\nmain() {\n    print(area(2, 3))\n}\n",
+    );
+    write("util/broken.liva", "helper( {\n");
+    write(
+        "util/uses_broken.liva",
+        "// helpers\nimport { helper } from \"./broken.liva\"\n\nmain() {\n    helper()\n}\n",
+    );
+    write("target/generated.liva", "not liva at all {\n");
+
+    let reports = livac::check_workspace(root);
+    let checked: Vec<(String, bool)> = reports
+        .iter()
+        .map(|report| {
+            let relative = std::path::Path::new(&report.file_name)
+                .strip_prefix(root)
+                .unwrap();
+            (
+                relative.to_string_lossy().replace('\\', "/"),
+                report.is_ok(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        checked,
+        vec![
+            ("main.liva".to_string(), true),
+            ("shapes.liva".to_string(), true),
+            ("util/broken.liva".to_string(), false),
+            ("util/uses_broken.liva".to_string(), false),
+        ]
+    );
+
+    let import_error = reports[3].diagnostics[0].error_info().unwrap();
+    assert_eq!(import_error.code, "E4005");
+    assert_eq!(import_error.location.as_ref().unwrap().line, 2);
+}
+
+#[test]
+fn test_import_aliases_and_reexports_integration() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let write = |path: &str, contents: &str| {
+        std::fs::write(root.join(path), contents).unwrap();
+    };
+    write("config.liva", "parse(n: number): number => n * 2\n");
+    write("math.liva", "add(a: number, b: number): number => a + b\n");
+    write(
+        "api.liva",
+        "export { parse as parseConfig } from \"./config\"\nexport * from \"./math\"\n",
+    );
+    write(
+        "main.liva",
+        "import { parseConfig as parseCfg, add } from \"./api\"\n\nparse(x: number): number => x\n\nmain() {\n    print(add(parseCfg(3), parse(1)))\n}\n",
+    );
+
+    let options = CompilerOptions {
+        input: root.join("main.liva"),
+        output: Some(root.join("out")),
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let result = compile_file(&options).expect("aliased imports and re-exports compile");
+
+    let main_rs = result.rust_code.unwrap();
+    assert!(
+        main_rs.contains("use crate::api::{parse_config as parse_cfg, add};"),
+        "{}",
+        main_rs
+    );
+    let module_files = result.module_files.unwrap();
+    let api_rs = &module_files[&PathBuf::from("src/api.rs")];
+    assert!(
+        api_rs.contains("pub use crate::config::parse as parse_config;"),
+        "{}",
+        api_rs
+    );
+    assert!(api_rs.contains("pub use crate::math::*;"), "{}", api_rs);
+}
+
+#[test]
+fn test_inlining_across_modules_integration() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let write = |path: &str, contents: &str| {
+        std::fs::write(root.join(path), contents).unwrap();
+    };
+    write(
+        "math.liva",
+        "double(x: int): int => x * 2\n\n@noinline\ntriple(x: int): int => x * 3\n\nscale(x: number): number {\n    let y = x * 2\n    return y + 1\n}\n",
+    );
+    write(
+        "main.liva",
+        "import { double, triple, scale } from \"./math\"\n\nmain() {\n    let n = 4\n    print(double(n), triple(n), scale(n))\n}\n",
+    );
+
+    let options = CompilerOptions {
+        input: root.join("main.liva"),
+        output: Some(root.join("out")),
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let result = compile_file(&options).expect("imported helpers compile");
+
+    let main_rs = result.rust_code.unwrap();
+    let module_files = result.module_files.unwrap();
+    let math_rs = &module_files[&PathBuf::from("src/math.rs")];
+    // `double`'s body replaces its call; the others stay calls, with hints
+    assert!(
+        main_rs.contains("n * 2, triple(n), scale(n)"),
+        "{}",
+        main_rs
+    );
+    assert!(math_rs.contains("#[inline]\npub fn double("), "{}", math_rs);
+    assert!(
+        math_rs.contains("#[inline(never)]\npub fn triple("),
+        "{}",
+        math_rs
+    );
+    assert!(math_rs.contains("#[inline]\npub fn scale("), "{}", math_rs);
+}
+
+#[test]
+fn test_init_blocks_run_in_dependency_order() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let write = |path: &str, contents: &str| {
+        std::fs::write(root.join(path), contents).unwrap();
+    };
+    write(
+        "config.liva",
+        "init {\n    print(\"config\")\n}\n\nport(): number => 8080\n",
+    );
+    write(
+        "server.liva",
+        "import { port } from \"./config\"\n\ninit {\n    print(\"server\")\n}\n\nstart(): number => port()\n",
+    );
+    write(
+        "main.liva",
+        "import { start } from \"./server\"\n\ninit {\n    print(\"main\")\n}\n\nmain() {\n    print(start())\n}\n",
+    );
+
+    let options = CompilerOptions {
+        input: root.join("main.liva"),
+        output: Some(root.join("out")),
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let result = compile_file(&options).expect("modules with init blocks compile");
+
+    let main_rs = result.rust_code.unwrap();
+    let calls = [
+        "config::liva_init();",
+        "server::liva_init();",
+        "    liva_init();",
+        "println!(\"{}\", start());",
+    ];
+    let positions: Vec<usize> = calls
+        .iter()
+        .map(|call| {
+            main_rs
+                .find(call)
+                .unwrap_or_else(|| panic!("{}: {}", call, main_rs))
+        })
+        .collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", main_rs);
+
+    let module_files = result.module_files.unwrap();
+    let config_rs = &module_files[&PathBuf::from("src/config.rs")];
+    assert!(config_rs.contains("pub fn liva_init() {"), "{}", config_rs);
+}
+
+#[test]
+fn test_multifile_compilation_reports_artifact_metadata() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    std::fs::write(
+        root.join("math.liva"),
+        "add(a: number, b: number): number => a + b\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("main.liva"),
+        "import { add } from \"./math\"\n\nmain() {\n    print(add(1, 2))\n}\n",
+    )
+    .unwrap();
+
+    let options = CompilerOptions {
+        input: root.join("main.liva"),
+        output: None,
+        verbose: false,
+        check_only: false,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let metadata = compile_file(&options).unwrap().metadata;
+
+    let paths: Vec<PathBuf> = metadata.files.iter().map(|f| f.path.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("src/main.rs"),
+            PathBuf::from("src/math.rs"),
+            PathBuf::from("Cargo.toml")
+        ]
+    );
+    let math = metadata
+        .source_map
+        .iter()
+        .find(|m| m.rust_file.as_path() == std::path::Path::new("src/math.rs"))
+        .unwrap();
+    assert!(math.liva_file.ends_with("math.liva"));
+    assert!(metadata.dependencies.iter().any(|d| d.name == "tokio"));
+    assert_eq!(metadata.entry_points.len(), 1);
+    assert_eq!(metadata.entry_points[0].name, "main");
+}
+
+#[test]
+fn test_import_alias_collision_is_rejected() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    std::fs::write(
+        root.join("config.liva"),
+        "parse(n: number): number => n * 2\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("main.liva"),
+        "import { parse as load } from \"./config\"\n\nload(x: number): number => x\n\nmain() {\n    print(load(1))\n}\n",
+    )
+    .unwrap();
+
+    let options = CompilerOptions {
+        input: root.join("main.liva"),
+        output: Some(root.join("out")),
+        verbose: false,
+        check_only: true,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    };
+    let err = compile_file(&options).expect_err("alias colliding with a local function");
+    assert!(err.to_string().contains("E4008"), "{}", err);
+}
+
+fn check_project(root: &std::path::Path) -> livac::Result<livac::CompilationResult> {
+    compile_file(&CompilerOptions {
+        input: root.join("main.liva"),
+        output: Some(root.join("out")),
+        verbose: false,
+        check_only: true,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
+    })
+}
+
+#[test]
+fn test_import_cycle_reports_full_chain() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let write = |path: &str, contents: &str| {
+        std::fs::write(root.join(path), contents).unwrap();
+    };
+    write(
+        "main.liva",
+        "import { a } from \"./a\"\n\nmain() {\n    print(a())\n}\n",
+    );
+    write(
+        "a.liva",
+        "import { b } from \"./b\"\n\na(): number => b()\n",
+    );
+    write("b.liva", "import { a } from \"./a\"\n\nb(): number => 1\n");
+
+    let err = check_project(root).expect_err("a.liva and b.liva import each other");
+    let message = err.to_string();
+    assert!(message.contains("E4003"), "{}", message);
+    assert!(message.contains("a.liva → b.liva → a.liva"), "{}", message);
+    assert!(
+        message.contains("b.liva: import { a } from \"./a\""),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_type_only_import_cycle_is_allowed() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+    let write = |path: &str, contents: &str| {
+        std::fs::write(root.join(path), contents).unwrap();
+    };
+    write(
+        "main.liva",
+        "import { Canvas } from \"./canvas\"\n\nmain() {\n    print(\"ok\")\n}\n",
+    );
+    write(
+        "canvas.liva",
+        "import { Shape } from \"./shapes\"\n\nCanvas {\n    shapes: [Shape]\n}\n",
+    );
+    write(
+        "shapes.liva",
+        "import { Canvas } from \"./canvas\"\n\nShape {\n    name: string\n    owner: Canvas?\n}\n",
+    );
+
+    check_project(root).expect("cycles through type-only imports resolve");
+}
+
 // ---------------------------------------------------------------------------
 // `extend ClassName { ... }` — class extensions across files.
 // See docs/language-reference/class-extensions.md.
@@ -197,6 +818,22 @@ fn expect_compile_error(main_liva: &str, expected_marker: &str) {
         output: Some(temp_dir.path().to_path_buf()),
         verbose: false,
         check_only: true,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
     match compile_file(&options) {
         Ok(_) => panic!(
@@ -257,6 +894,22 @@ fn test_compile_check_only() {
         output: None,
         verbose: false,
         check_only: true,
+        deterministic: false,
+        coverage: false,
+        debug: false,
+        inline_threshold: livac::inlining::DEFAULT_INLINE_THRESHOLD,
+        strict: false,
+        numeric_coercion: None,
+        identifier_mapping: None,
+        profile: BuildProfile::Dev,
+        backend: BackendKind::Rust,
+        sandbox: None,
+        self_check: false,
+        incremental: false,
+        explain_codegen: false,
+        format_output: false,
+        source_map: false,
+        max_errors: 1,
     };
 
     let result = compile_file(&options);
@@ -281,7 +934,7 @@ fn test_init_creates_multi_file_project() {
     let project_name = "test-project";
 
     let output = Command::new(livac_binary())
-        .args(["init", project_name])
+        .args(["init", project_name, "--template", "tour"])
         .current_dir(tmp.path())
         .output()
         .expect("Failed to execute livac init");
@@ -294,16 +947,20 @@ fn test_init_creates_multi_file_project() {
 
     let project_dir = tmp.path().join(project_name);
     assert!(
-        project_dir.join("main.liva").exists(),
-        "main.liva not created"
+        project_dir.join("liva.toml").exists(),
+        "liva.toml not created"
+    );
+    assert!(
+        project_dir.join("src/main.liva").exists(),
+        "src/main.liva not created"
     );
     assert!(
-        project_dir.join("math.liva").exists(),
-        "math.liva not created"
+        project_dir.join("src/math.liva").exists(),
+        "src/math.liva not created"
     );
     assert!(
-        project_dir.join("models.liva").exists(),
-        "models.liva not created"
+        project_dir.join("src/models.liva").exists(),
+        "src/models.liva not created"
     );
     assert!(
         project_dir.join("tests/main.test.liva").exists(),
@@ -315,7 +972,7 @@ fn test_init_creates_multi_file_project() {
     );
 
     // Verify main.liva content
-    let main_content = std::fs::read_to_string(project_dir.join("main.liva")).unwrap();
+    let main_content = std::fs::read_to_string(project_dir.join("src/main.liva")).unwrap();
     assert!(
         main_content.contains("main()"),
         "main.liva should contain main()"
@@ -330,7 +987,7 @@ fn test_init_creates_multi_file_project() {
     );
 
     // Verify math.liva content
-    let math_content = std::fs::read_to_string(project_dir.join("math.liva")).unwrap();
+    let math_content = std::fs::read_to_string(project_dir.join("src/math.liva")).unwrap();
     assert!(
         math_content.contains("add("),
         "math.liva should contain add function"
@@ -341,7 +998,7 @@ fn test_init_creates_multi_file_project() {
     );
 
     // Verify models.liva content
-    let models_content = std::fs::read_to_string(project_dir.join("models.liva")).unwrap();
+    let models_content = std::fs::read_to_string(project_dir.join("src/models.liva")).unwrap();
     assert!(
         models_content.contains("Point"),
         "models.liva should contain Point data class"
@@ -354,7 +1011,7 @@ fn test_init_creates_multi_file_project() {
     // Verify test file content
     let test_content = std::fs::read_to_string(project_dir.join("tests/main.test.liva")).unwrap();
     assert!(
-        test_content.contains("test "),
+        test_content.contains("test("),
         "test file should contain test blocks"
     );
 }
@@ -417,19 +1074,19 @@ fn test_init_dot_current_dir() {
     );
 
     assert!(
-        project_dir.join("main.liva").exists(),
-        "main.liva not created"
+        project_dir.join("liva.toml").exists(),
+        "liva.toml not created"
     );
     assert!(
-        project_dir.join("math.liva").exists(),
-        "math.liva not created"
+        project_dir.join("src/main.liva").exists(),
+        "src/main.liva not created"
     );
     assert!(
-        project_dir.join("models.liva").exists(),
-        "models.liva not created"
+        project_dir.join("src/greeting.liva").exists(),
+        "src/greeting.liva not created"
     );
     assert!(
-        project_dir.join("tests/main.test.liva").exists(),
+        project_dir.join("tests/greeting.test.liva").exists(),
         "test file not created"
     );
     assert!(
@@ -438,9 +1095,9 @@ fn test_init_dot_current_dir() {
     );
 
     // Verify project name is derived from directory name
-    let main_content = std::fs::read_to_string(project_dir.join("main.liva")).unwrap();
+    let manifest = std::fs::read_to_string(project_dir.join("liva.toml")).unwrap();
     assert!(
-        main_content.contains("my-cool-app"),
+        manifest.contains("name = \"my-cool-app\""),
         "Should use directory name as project name"
     );
 }
@@ -450,7 +1107,8 @@ fn test_init_dot_already_has_main() {
     let tmp = TempDir::new().unwrap();
     let project_dir = tmp.path().join("existing-proj");
     std::fs::create_dir(&project_dir).unwrap();
-    std::fs::write(project_dir.join("main.liva"), "main() { }").unwrap();
+    std::fs::create_dir(project_dir.join("src")).unwrap();
+    std::fs::write(project_dir.join("src/main.liva"), "main() { }").unwrap();
 
     let output = Command::new(livac_binary())
         .args(["init", "."])
@@ -487,7 +1145,190 @@ fn test_init_no_args_defaults_to_dot() {
         String::from_utf8_lossy(&output.stderr)
     );
     assert!(
-        project_dir.join("main.liva").exists(),
-        "main.liva not created"
+        project_dir.join("src/main.liva").exists(),
+        "src/main.liva not created"
+    );
+}
+
+#[test]
+fn test_new_library_template() {
+    let tmp = TempDir::new().unwrap();
+
+    let output = Command::new(livac_binary())
+        .args(["new", "my-lib", "--template", "library"])
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute livac new");
+
+    assert!(
+        output.status.success(),
+        "livac new failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let project_dir = tmp.path().join("my-lib");
+    let manifest = std::fs::read_to_string(project_dir.join("liva.toml")).unwrap();
+    assert!(manifest.contains("entry = \"src/lib.liva\""));
+    assert!(project_dir.join("src/lib.liva").exists());
+    assert!(!project_dir.join("src/main.liva").exists());
+    assert!(project_dir.join("tests/lib.test.liva").exists());
+}
+
+#[test]
+fn test_new_requires_fresh_directory() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::create_dir(tmp.path().join("taken")).unwrap();
+
+    let output = Command::new(livac_binary())
+        .args(["new", "taken"])
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute livac new");
+
+    assert!(
+        !output.status.success(),
+        "Should fail when directory exists"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+}
+
+// ── livac run tests ─────────────────────────────────────────
+
+/// Build and run `source` with `livac run`, or `None` when cargo builds are disabled
+fn run_program(name: &str, source: &str) -> Option<std::process::Output> {
+    if !cargo_check_enabled() {
+        eprintln!(
+            "Skipping livac run for '{}' (set LIVA_RUN_CARGO_CHECK=1 to enable)",
+            name
+        );
+        return None;
+    }
+    let tmp = TempDir::new().unwrap();
+    let file = format!("{}.liva", name);
+    std::fs::write(tmp.path().join(&file), source).unwrap();
+    let output = Command::new(livac_binary())
+        .args(["run", "--quiet", &file])
+        .current_dir(tmp.path())
+        .output()
+        .expect("Failed to execute livac run");
+    Some(output)
+}
+
+#[test]
+fn test_run_passes_main_exit_code_through() {
+    let Some(output) = run_program("exit_code", "main(): int {\n    return 3\n}\n") else {
+        return;
+    };
+    assert_eq!(
+        output.status.code(),
+        Some(3),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Runtime error"));
+}
+
+#[test]
+fn test_run_prints_windows_and_chunks() {
+    let source = r#"
+main() {
+  let years = [1815, 1906, 1912]
+  for window in years.windows(2) {
+    print(window)
+  }
+  for part in years.chunks(2) {
+    print(part.length)
+    print(part)
+  }
+}
+"#;
+    let Some(output) = run_program("windows", source) else {
+        return;
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        stdout,
+        "[1815, 1906]\n[1906, 1912]\n2\n[1815, 1906]\n1\n[1912]\n"
+    );
+}
+
+#[test]
+fn test_run_min_by_max_by_on_empty_and_non_empty_arrays() {
+    let source = r#"
+Item {
+  name: string
+  price: float
+  constructor(name: string, price: float) {
+    this.name = name
+    this.price = price
+  }
+}
+
+main() {
+  let items = [Item("tea", 3.0), Item("bread", 1.5), Item("jam", 3.0)]
+  let empty: [Item] = []
+  let cheapest = items.minBy(i => i.price)
+  if cheapest != null {
+    print(cheapest.name)
+  }
+  let missing = empty.maxBy(i => i.price)
+  if missing == null {
+    print("no items")
+  }
+  let priciest = items.maxBy(i => i.price) or fail "no items"
+  print(priciest.name)
+  let lowest = [4, 2, 9].minBy(n => n)
+  print(lowest ?? 0)
+}
+"#;
+    let Some(output) = run_program("min_by_max_by", source) else {
+        return;
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(stdout, "bread\nno items\ntea\n2\n");
+}
+
+#[test]
+fn test_run_union_parameters_and_returns() {
+    let source = r##"
+type Id = int | string
+
+main() {
+  print(show(parse("")))
+  print(show(parse("a")))
+  print(show(7))
+}
+
+parse(text: string): int | string {
+  if text == "" {
+    return 0
+  }
+  return text
+}
+
+show(id: Id): string {
+  return switch typeOf(id) {
+    "int" => $"#{id}",
+    "string" => id.toUpperCase()
+  }
+}
+"##;
+    let Some(output) = run_program("unions", source) else {
+        return;
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
     );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "#0\nA\n#7\n");
 }
@@ -99,6 +99,11 @@ fn test_unclosed_char() {
     test_lexer_err("unclosed_char");
 }
 
+#[test]
+fn test_char_literal_too_long() {
+    test_lexer_err("char_literal_too_long");
+}
+
 #[test]
 fn test_unclosed_comment() {
     test_lexer_err("unclosed_comment");
@@ -175,3 +175,328 @@ fn test_oneliner_arrow() {
 fn test_method_references() {
     test_parser_ok("method_references");
 }
+
+#[test]
+fn test_else_if_chain_is_flat() {
+    use livac::ast::{IfBody, Stmt, TopLevel};
+
+    let source = (1..=200)
+        .map(|n| format!("if n == {} {{\n        return {}\n    }}", n, n))
+        .collect::<Vec<_>>()
+        .join(" else ");
+    let source = format!(
+        "classify(n: int): int {{\n    {} else {{\n        return 0\n    }}\n}}\n",
+        source
+    );
+    let tokens = tokenize(&source).unwrap();
+    let ast = parse(tokens, &source).unwrap();
+
+    let TopLevel::Function(func) = &ast.items[0] else {
+        panic!("expected a function");
+    };
+    let Stmt::If(if_stmt) = &func.body.as_ref().unwrap().stmts[0] else {
+        panic!("expected an if statement");
+    };
+    assert_eq!(if_stmt.else_ifs.len(), 199);
+    assert!(if_stmt.bodies().all(
+        |body| matches!(body, IfBody::Block(block) if !matches!(block.stmts[0], Stmt::If(_)))
+    ));
+    assert!(matches!(if_stmt.else_branch, Some(IfBody::Block(_))));
+}
+
+#[test]
+fn test_abstract_modifier() {
+    use livac::ast::{Member, TopLevel};
+
+    let source = "abstract Shape {\n    name: string\n    abstract area(): number\n    describe() => this.name\n}\n\nabstract(n: int) => n\n";
+    let ast = parse(tokenize(source).unwrap(), source).unwrap();
+    let TopLevel::Class(class) = &ast.items[0] else {
+        panic!("expected a class");
+    };
+    assert!(class.is_abstract);
+    let abstract_methods: Vec<(&str, bool)> = class
+        .members
+        .iter()
+        .filter_map(|member| match member {
+            Member::Method(method) => Some((method.name.as_str(), method.is_abstract)),
+            Member::Field(_) => None,
+        })
+        .collect();
+    assert_eq!(abstract_methods, vec![("area", true), ("describe", false)]);
+    // Without a name after it, `abstract` is an ordinary identifier
+    assert!(matches!(&ast.items[1], TopLevel::Function(func) if func.name == "abstract"));
+
+    for invalid in [
+        "abstract Shape {\n    abstract name: string\n}\n",
+        "abstract Shape {\n    abstract area(): number => 1\n}\n",
+        "abstract area(): number => 1\n",
+    ] {
+        let result = parse(tokenize(invalid).unwrap(), invalid);
+        assert!(result.is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_comprehension() {
+    use livac::ast::{ArrayAdapter, Expr, Stmt, TopLevel};
+
+    let source =
+        "main() {\n    let a = [x * 2 for par x in items if x > 0]\n    let b = [x * 2, 3]\n}\n";
+    let ast = parse(tokenize(source).unwrap(), source).unwrap();
+    let TopLevel::Function(main) = &ast.items[0] else {
+        panic!("expected a function");
+    };
+    let inits: Vec<&Expr> = main
+        .body
+        .as_ref()
+        .unwrap()
+        .stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::VarDecl(var) => Some(&var.init),
+            _ => None,
+        })
+        .collect();
+    let Expr::MethodCall(map) = inits[0] else {
+        panic!("expected a map call, got {:?}", inits[0]);
+    };
+    assert_eq!(map.method, "map");
+    assert_eq!(map.adapter, ArrayAdapter::Par);
+    assert!(map.comprehension.as_ref().is_some_and(|c| c.filtered));
+    let Expr::MethodCall(filter) = map.object.as_ref() else {
+        panic!("expected a filter call");
+    };
+    assert_eq!(filter.method, "filter");
+    assert!(filter.comprehension.is_none());
+    assert!(matches!(filter.object.as_ref(), Expr::Identifier(name) if name == "items"));
+    // Only a `for` after the first element makes a comprehension
+    assert!(matches!(inits[1], Expr::ArrayLiteral(items) if items.len() == 2));
+
+    let invalid = "main() {\n    let a = [1, x for x in items]\n}\n";
+    assert!(parse(tokenize(invalid).unwrap(), invalid).is_err());
+}
+
+#[test]
+fn test_power_operator() {
+    use livac::ast::{BinOp, Expr, Stmt, TopLevel, UnOp};
+
+    let source = "main() {\n    let a = 2 ** 3 ** 2\n    let b = -2 ** 2\n    let c = x * y ** 2\n    n **= 2\n}\n";
+    let ast = parse(tokenize(source).unwrap(), source).unwrap();
+    let TopLevel::Function(main) = &ast.items[0] else {
+        panic!("expected a function");
+    };
+    let stmts = &main.body.as_ref().unwrap().stmts;
+    let init = |i: usize| match &stmts[i] {
+        Stmt::VarDecl(var) => &var.init,
+        other => panic!("expected a let, got {:?}", other),
+    };
+    let is_pow = |expr: &Expr| matches!(expr, Expr::Binary { op: BinOp::Pow, .. });
+
+    // Right-associative: 2 ** (3 ** 2)
+    let Expr::Binary { op, left, right } = init(0) else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(*op, BinOp::Pow);
+    assert!(!is_pow(left) && is_pow(right));
+    // Tighter than unary minus: -(2 ** 2)
+    assert!(matches!(init(1), Expr::Unary { op: UnOp::Neg, operand } if is_pow(operand)));
+    // Tighter than `*`: x * (y ** 2)
+    assert!(matches!(init(2), Expr::Binary { op: BinOp::Mul, right, .. } if is_pow(right)));
+    let Stmt::Assign(assign) = &stmts[3] else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(assign.op, Some(BinOp::Pow));
+    assert!(is_pow(&assign.value));
+}
+
+#[test]
+fn test_bitwise_operators() {
+    use livac::ast::{BinOp, Expr, Stmt, TopLevel, UnOp};
+
+    let source = "main() {\n    let a = flags & MASK == 0\n    let b = w | x ^ y & z\n    let c = 1 << n + 1\n    let d = x >> 2 > 0\n    let e: Array<Array<int>> = []\n    let f = ~x\n    n >>= 1\n    n |= 4\n}\n";
+    let ast = parse(tokenize(source).unwrap(), source).unwrap();
+    let TopLevel::Function(main) = &ast.items[0] else {
+        panic!("expected a function");
+    };
+    let stmts = &main.body.as_ref().unwrap().stmts;
+    let init = |i: usize| match &stmts[i] {
+        Stmt::VarDecl(var) => &var.init,
+        other => panic!("expected a let, got {:?}", other),
+    };
+    let op_of = |expr: &Expr| match expr {
+        Expr::Binary { op, .. } => Some(*op),
+        _ => None,
+    };
+    let operand_ops = |expr: &Expr| match expr {
+        Expr::Binary { left, right, .. } => (op_of(left), op_of(right)),
+        other => panic!("expected a binary expression, got {:?}", other),
+    };
+
+    // Tighter than comparisons: (flags & MASK) == 0
+    assert_eq!(op_of(init(0)), Some(BinOp::Eq));
+    assert_eq!(operand_ops(init(0)).0, Some(BinOp::BitAnd));
+    // `|` loosest, then `^`, then `&`: w | (x ^ (y & z))
+    assert_eq!(op_of(init(1)), Some(BinOp::BitOr));
+    let Expr::Binary { right: xor, .. } = init(1) else {
+        unreachable!()
+    };
+    assert_eq!(op_of(xor), Some(BinOp::BitXor));
+    assert_eq!(operand_ops(xor).1, Some(BinOp::BitAnd));
+    // Looser than `+`: 1 << (n + 1)
+    assert_eq!(op_of(init(2)), Some(BinOp::Shl));
+    assert_eq!(operand_ops(init(2)).1, Some(BinOp::Add));
+    // `>>` is two `>`s: (x >> 2) > 0
+    assert_eq!(op_of(init(3)), Some(BinOp::Gt));
+    assert_eq!(operand_ops(init(3)).0, Some(BinOp::Shr));
+    assert!(matches!(init(5), Expr::Unary { op, .. } if *op == UnOp::BitNot));
+
+    for (i, expected) in [(6, BinOp::Shr), (7, BinOp::BitOr)] {
+        let Stmt::Assign(assign) = &stmts[i] else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(assign.op, Some(expected));
+    }
+}
+
+#[test]
+fn test_delegate_lines() {
+    use livac::ast::{DelegateDecl, TopLevel};
+
+    let source = "Car {\n    engine: Engine\n    radio: Radio\n    delegate engine\n    delegate radio { on, off }\n    delegate(n: int) => n\n}\n";
+    let ast = parse(tokenize(source).unwrap(), source).unwrap();
+    let TopLevel::Class(class) = &ast.items[0] else {
+        panic!("expected a class");
+    };
+    let delegate = |field: &str, methods: &[&str]| DelegateDecl {
+        field: field.to_string(),
+        methods: methods.iter().map(|m| m.to_string()).collect(),
+    };
+    assert_eq!(
+        class.delegates,
+        vec![delegate("engine", &[]), delegate("radio", &["on", "off"])]
+    );
+    // Without a name after it, `delegate` is an ordinary method name
+    assert_eq!(class.members.len(), 3);
+
+    for invalid in [
+        "Car {\n    engine: Engine\n    delegate engine {}\n}\n",
+        "type Drivable {\n    delegate engine\n}\n",
+        "extend Car {\n    delegate engine\n}\n",
+    ] {
+        let result = parse(tokenize(invalid).unwrap(), invalid);
+        assert!(result.is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_ternary_branch_can_fail() {
+    use livac::ast::{Expr, TopLevel};
+
+    let source = "divide(a: number, b: number) => b == 0 ? fail \"Division by zero\" : a / b\n";
+    let ast = parse(tokenize(source).unwrap(), source).unwrap();
+    let TopLevel::Function(divide) = &ast.items[0] else {
+        panic!("expected a function");
+    };
+    let Some(Expr::Ternary { then_expr, .. }) = &divide.expr_body else {
+        panic!("expected a ternary body");
+    };
+    assert!(matches!(then_expr.as_ref(), Expr::Fail(_)));
+}
+
+#[test]
+fn test_function_contracts() {
+    use livac::ast::{Stmt, TopLevel};
+
+    let source = "@checked\ndivide(a: int, b: int): int\n    requires b != 0\n    ensures result * b <= a\n    requires a >= 0\n{\n    return a / b\n}\n\nabs(n: int): int ensures result >= 0 => n < 0 ? -n : n\n\nmain() {\n    half(n: int) requires n > 0 {\n        print(n / 2)\n    }\n    half(4)\n}\n";
+    let ast = parse(tokenize(source).unwrap(), source).unwrap();
+    let TopLevel::Function(divide) = &ast.items[0] else {
+        panic!("expected a function");
+    };
+    assert!(divide.checked);
+    let sources = |contracts: &[livac::ast::Contract]| {
+        contracts
+            .iter()
+            .map(|contract| (contract.source.clone(), contract.line))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        sources(&divide.requires),
+        [("b != 0".to_string(), 3), ("a >= 0".to_string(), 5)]
+    );
+    assert_eq!(
+        sources(&divide.ensures),
+        [("result * b <= a".to_string(), 4)]
+    );
+
+    let TopLevel::Function(abs) = &ast.items[1] else {
+        panic!("expected a function");
+    };
+    assert!(!abs.checked && abs.expr_body.is_some());
+    assert_eq!(sources(&abs.ensures), [("result >= 0".to_string(), 10)]);
+
+    let TopLevel::Function(main) = &ast.items[2] else {
+        panic!("expected a function");
+    };
+    let Stmt::Function(half) = &main.body.as_ref().unwrap().stmts[0] else {
+        panic!("expected a nested function");
+    };
+    assert_eq!(sources(&half.requires), [("n > 0".to_string(), 13)]);
+
+    // `@checked` needs something to check
+    let unchecked = "@checked\nf(n: int): int => n\n";
+    assert!(parse(tokenize(unchecked).unwrap(), unchecked).is_err());
+}
+
+/// Parse `source` on a thread with the 8 MiB stack the compiler's main
+/// thread gets, returning the error code if it fails
+fn parse_code_on_main_stack(source: String) -> Option<String> {
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || match parse(tokenize(&source).unwrap(), &source) {
+            Ok(_) => None,
+            Err(livac::CompilerError::ParseError(info)) => Some(info.code),
+            Err(other) => panic!("unexpected error: {}", other),
+        })
+        .unwrap()
+        .join()
+        .expect("parser overflowed its stack")
+}
+
+#[test]
+fn test_deep_nesting_is_rejected() {
+    let main = |body: String| format!("main() {{\n    let x = {}\n}}\n", body);
+    let levels = 10_000;
+    let pathological = [
+        main(format!("{}1{}", "(".repeat(levels), ")".repeat(levels))),
+        main(format!("{}0", "x ? 1 : ".repeat(levels))),
+        main(format!("{}x", "!".repeat(levels))),
+        main(format!("{}1", "2 ** ".repeat(levels))),
+        main(format!("1{}", " + 1".repeat(levels))),
+        main(format!("x{}", ".next()".repeat(levels))),
+        main(format!("[{}]", "[".repeat(levels) + &"]".repeat(levels))),
+        format!(
+            "main() {{\n{}print(1)\n{}}}\n",
+            "if true {\n".repeat(levels),
+            "}\n".repeat(levels)
+        ),
+    ];
+    for source in pathological {
+        let head: String = source.chars().take(40).collect();
+        assert_eq!(
+            parse_code_on_main_stack(source).as_deref(),
+            Some("E2005"),
+            "{}",
+            head
+        );
+    }
+
+    // Nesting well below the limit still parses
+    let levels = 100;
+    let nested = [
+        main(format!("{}1{}", "(1 + ".repeat(levels), ")".repeat(levels))),
+        main(format!("1{}", " + 1".repeat(levels))),
+    ];
+    for source in nested {
+        assert_eq!(parse_code_on_main_stack(source), None);
+    }
+}
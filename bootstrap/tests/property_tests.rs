@@ -98,3 +98,46 @@ proptest! {
         }
     }
 }
+
+/// Mezcla aleatoria de construcciones anidadas: `(`, `[`, operadores
+/// prefijos, ternarios y cadenas de `+`
+fn nested_expression() -> impl Strategy<Value = String> {
+    let openers = prop::sample::select(vec!["(", "[", "-", "!", "x ? 1 : ", "1 + ", "f("]);
+    prop::collection::vec(openers, 0..1000).prop_map(|openers| {
+        let closers: String = openers
+            .iter()
+            .rev()
+            .map(|opener| match *opener {
+                "(" | "f(" => ")",
+                "[" => "]",
+                _ => "",
+            })
+            .collect();
+        format!(
+            "main() {{\n    let y = {}1{}\n}}\n",
+            openers.concat(),
+            closers
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_parser_deep_nesting(input in nested_expression()) {
+        // El parser rechaza el anidamiento excesivo con E2005 en lugar de
+        // desbordar la pila (8 MiB, como el hilo principal del compilador)
+        let result = std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || match parse(tokenize(&input).unwrap(), &input) {
+                Ok(_) => None,
+                Err(livac::CompilerError::ParseError(info)) => Some(info.code),
+                Err(_) => Some(String::new()),
+            })
+            .unwrap()
+            .join();
+        prop_assert!(result.is_ok(), "parser overflowed its stack");
+        if let Some(code) = result.unwrap() {
+            prop_assert_eq!(code, "E2005");
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use insta::assert_snapshot;
+use livac::ast::TopLevel;
 use livac::{lexer::tokenize, parser::parse, semantic::analyze};
 use std::fs;
 
@@ -123,6 +124,16 @@ fn test_for_par_chunk_invalid() {
     test_semantics_err("for_par_chunk_invalid");
 }
 
+#[test]
+fn test_for_par_schedule_unsupported() {
+    test_semantics_err("for_par_schedule");
+}
+
+#[test]
+fn test_runtime_duplicate_block() {
+    test_semantics_err("runtime_duplicate");
+}
+
 #[test]
 fn test_internal_crate_override() {
     test_semantics_err("internal_crate_override");
@@ -132,3 +143,966 @@ fn test_internal_crate_override() {
 fn test_enum_exhaustive_error() {
     test_semantics_err("enum_exhaustive");
 }
+
+#[test]
+fn test_inferred_types_for_unannotated_bindings() {
+    let source = "double(x: number): number => x * 2\n\nmain() {\n    let items = [1, 2, 3]\n    let twice = double(2)\n    let typed: string = \"a\"\n    let doubled = items.map(n => n * 2)\n}\n";
+    let analyzed = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+
+    let hints: Vec<(&str, String)> = analyzed
+        .inferred_types
+        .iter()
+        .map(|t| (t.span.snippet(source), t.type_ref.to_string()))
+        .collect();
+    assert_eq!(
+        hints,
+        vec![
+            ("items", "[number]".to_string()),
+            ("twice", "number".to_string()),
+            ("n", "number".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_call_graph_records_functions_and_methods() {
+    let source = "square(x: number): number => x * x\n\nCounter {\n    count: number\n\n    bump() {\n        this.count = square(this.count)\n    }\n\n    twice() {\n        this.bump()\n        this.bump()\n    }\n}\n\nmain() {\n    let c: Counter = Counter(1)\n    c.twice()\n    print(square(3))\n}\n";
+    let analyzed = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+
+    let edges: Vec<(&str, &str)> = analyzed
+        .call_graph
+        .iter()
+        .map(|edge| (edge.caller.as_str(), edge.callee.as_str()))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            ("Counter.bump", "square"),
+            ("Counter.twice", "Counter.bump"),
+            ("main", "Counter.twice"),
+            ("main", "square"),
+        ]
+    );
+}
+
+#[test]
+fn test_signatures_cover_functions_and_methods() {
+    let source = "greet(name: string, punctuation = \"!\") => print(name + punctuation)\n\nCounter {\n    count: number\n\n    bump(by: number = 1): number {\n        this.count = this.count + by\n        return this.count\n    }\n}\n";
+    let analyzed = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+
+    let labels: Vec<String> = analyzed.signatures.iter().map(|s| s.label()).collect();
+    assert_eq!(
+        labels,
+        vec![
+            "greet(name: string, punctuation = \"!\")",
+            "Counter.bump(by: number = 1): number",
+        ]
+    );
+}
+
+#[test]
+fn test_nested_function_errors() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    // Recursion is fine without captures, since that lowers to a nested `fn`
+    let recursive =
+        "main() {\n    fact(n: int): int => n <= 1 ? 1 : n * fact(n - 1)\n    print(fact(5))\n}\n";
+    assert_eq!(code(recursive), None);
+
+    let capturing = "main() {\n    let step = 2\n    countdown(n: int) {\n        if n > 0 {\n            countdown(n - step)\n        }\n    }\n    countdown(10)\n}\n";
+    assert_eq!(code(capturing).as_deref(), Some("E0403"));
+
+    let async_body = "load(): string {\n    return \"data\"\n}\n\nmain() {\n    fetch(): string {\n        let data = async load()\n        return data\n    }\n    print(fetch())\n}\n";
+    assert_eq!(code(async_body).as_deref(), Some("E0404"));
+}
+
+#[test]
+fn test_branch_type_unification() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let unified = "main() {\n    let n = 3\n    let half = if n > 2 { n } else { 0.5 }\n    let name = switch n {\n        1 => \"one\",\n        _ => $\"{n}\"\n    }\n    print(half, name)\n}\n";
+    assert_eq!(code(unified), None);
+
+    let mismatched_if =
+        "main() {\n    let n = 3\n    let x = if n > 2 { \"big\" } else { n }\n    print(x)\n}\n";
+    assert_eq!(code(mismatched_if).as_deref(), Some("E5004"));
+
+    let mismatched_switch = "main() {\n    let n = 3\n    let x = switch n {\n        1 => true,\n        _ => \"other\"\n    }\n    print(x)\n}\n";
+    assert_eq!(code(mismatched_switch).as_deref(), Some("E5004"));
+}
+
+#[test]
+fn test_as_conversion_errors() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let valid = "main() {\n    let text = \"42\"\n    let n, err = text as int\n    let f = text as float or 0.0\n    let s = 3.5 as int as string\n    print(n, err, f, s)\n}\n";
+    assert_eq!(code(valid), None);
+
+    // Parsing a string can fail, so it needs error binding or a default
+    let unbound = "main() {\n    let text = \"42\"\n    let n = text as int\n    print(n)\n}\n";
+    assert_eq!(code(unbound).as_deref(), Some("E0701"));
+
+    let cannot_fail = "main() {\n    let n, err = 3.5 as int\n    print(n, err)\n}\n";
+    assert_eq!(code(cannot_fail).as_deref(), Some("E5005"));
+
+    let unsupported = "main() {\n    let b = 1 as bool\n    print(b)\n}\n";
+    assert_eq!(code(unsupported).as_deref(), Some("E5005"));
+}
+
+#[test]
+fn test_group_by_float_key() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let by_float = "main() {\n    let prices: [float] = [1.5, 2.5]\n    let groups = prices.groupBy(p => p)\n    print(groups)\n}\n";
+    assert_eq!(code(by_float).as_deref(), Some("E5006"));
+
+    let by_int = "main() {\n    let prices: [float] = [1.5, 2.5]\n    let groups = prices.groupBy(p => p as int)\n    print(groups)\n}\n";
+    assert_eq!(code(by_int), None);
+}
+
+#[test]
+fn test_pair_loop_variables_are_typed() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    // `i` is the int index and `name` the string element
+    let mixed = "main() {\n    let names = [\"Ada\", \"Grace\"]\n    for i, name in names.enumerate() {\n        let label = if i > 0 { name } else { 0 }\n        print(label)\n    }\n}\n";
+    assert_eq!(code(mixed).as_deref(), Some("E5004"));
+
+    let zipped = "main() {\n    let names = [\"Ada\"]\n    let years = [1815]\n    for (name, year) in zip(names, years) {\n        let label = if year > 1900 { name } else { \"early\" }\n        print(label)\n    }\n}\n";
+    assert_eq!(code(zipped), None);
+}
+
+#[test]
+fn test_array_mutation_needs_mutable_binding() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let local = "main() {\n    let items = [1, 2]\n    items.push(3)\n    items.insert(0, 0)\n    let first = items.removeAt(0)\n    print(first, items.pop())\n    items.clear()\n}\n";
+    assert_eq!(code(local), None);
+
+    let local_const = "main() {\n    const ITEMS = [1, 2]\n    ITEMS.push(3)\n}\n";
+    assert_eq!(code(local_const).as_deref(), Some("E0008"));
+
+    let top_level = "const LIMITS = [1, 2]\n\nmain() {\n    LIMITS.clear()\n}\n";
+    assert_eq!(code(top_level).as_deref(), Some("E0008"));
+
+    // A local `let` shadows the constant
+    let shadowed = "const LIMITS = [1, 2]\n\nmain() {\n    let LIMITS = [3]\n    LIMITS.pop()\n}\n";
+    assert_eq!(code(shadowed), None);
+
+    let literal = "main() {\n    [1, 2].push(3)\n}\n";
+    assert_eq!(code(literal).as_deref(), Some("E0008"));
+}
+
+#[test]
+fn test_runtime_name_warnings() {
+    let warnings = |source: &str| {
+        let program = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+        livac::semantic::runtime_name_warnings(&program)
+            .into_iter()
+            .map(|w| (w.code, w.message))
+            .collect::<Vec<_>>()
+    };
+
+    let clashing = "tokio() => 1\n\nliva_log(msg: string) {\n    print(msg)\n}\n\nmain() {\n    liva_log(\"hi\")\n}\n";
+    let found = warnings(clashing);
+    assert_eq!(found.len(), 2, "{:?}", found);
+    assert!(found.iter().all(|(code, _)| code == "E0010"));
+    assert!(found[0].1.contains("`tokio` module"), "{:?}", found);
+    assert!(found[1].1.contains("renamed to 'liva_log_'"), "{:?}", found);
+
+    assert!(warnings("logger(msg: string) => print(msg)\n").is_empty());
+}
+
+#[test]
+fn test_lambda_capture_warnings() {
+    struct Capture;
+
+    impl livac::ast::VisitorMut for Capture {
+        fn visit_expr(&mut self, expr: &mut livac::ast::Expr) {
+            if let livac::ast::Expr::Lambda(lambda) = expr {
+                lambda.captures = vec!["total".to_string()];
+            }
+            livac::ast::walk_expr_mut(self, expr)
+        }
+    }
+
+    let source = "main() {\n    let total = 0\n    let add = move (x) => total + x\n    let show = () => print(total)\n}\n";
+    let mut program = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+    assert!(livac::semantic::lambda_capture_warnings(&program).is_empty());
+
+    livac::ast::VisitorMut::visit_program(&mut Capture, &mut program);
+    let codes = livac::semantic::lambda_capture_warnings(&program)
+        .into_iter()
+        .map(|w| w.code)
+        .collect::<Vec<_>>();
+    assert_eq!(codes, ["E0510", "E0511", "E0511"]);
+}
+
+#[test]
+fn test_main_signature() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    assert_eq!(code("main(): int {\n    return 1\n}\n"), None);
+    assert_eq!(code("main() {\n    fail \"config missing\"\n}\n"), None);
+    assert_eq!(code("main(): void {\n    print(1)\n}\n"), None);
+    assert_eq!(
+        code("main(): string {\n    return \"done\"\n}\n").as_deref(),
+        Some("E0009")
+    );
+    assert_eq!(
+        code("main(args: [string]) {\n    print(args)\n}\n").as_deref(),
+        Some("E0009")
+    );
+}
+
+#[test]
+fn test_snake_case_name_collisions() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let locals =
+        "main() {\n    let myValue = 1\n    let my_value = 2\n    print(myValue, my_value)\n}\n";
+    assert_eq!(code(locals).as_deref(), Some("E0011"));
+    let param = "load(userId: number) {\n    let user_id = userId + 1\n    print(user_id)\n}\n\nmain() {\n    load(1)\n}\n";
+    assert_eq!(code(param).as_deref(), Some("E0011"));
+    let functions =
+        "loadUser() => 1\n\nload_user() => 2\n\nmain() {\n    print(loadUser(), load_user())\n}\n";
+    assert_eq!(code(functions).as_deref(), Some("E0011"));
+
+    let separate = "first() {\n    let myValue = 1\n    print(myValue)\n}\n\nsecond() {\n    let my_value = 2\n    print(my_value)\n}\n\nmain() {\n    first()\n    second()\n}\n";
+    assert_eq!(code(separate), None);
+    let constant =
+        "const MAX_SIZE = 10\n\nmain() {\n    let maxSize = MAX_SIZE\n    print(maxSize)\n}\n";
+    assert_eq!(code(constant), None);
+}
+
+#[test]
+fn test_constants_outside_compile_time_become_lazy() {
+    let analyze_source = |source: &str| analyze(parse(tokenize(source).unwrap(), source).unwrap());
+    let code = |source: &str| {
+        analyze_source(source)
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let source = "port(): number => 8080\n\nconst BASE = 40\nconst ANSWER = BASE + 2\nconst MODE = DEBUG ? \"dev\" : \"release\"\nconst PORT = port()\nconst NAMES: [string] = [\"a\", \"b\"]\n";
+    let program = analyze_source(source).unwrap();
+    let lazy: Vec<(&str, bool)> = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            TopLevel::ConstDecl(decl) => Some((decl.name.as_str(), decl.is_lazy)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        lazy,
+        vec![
+            ("BASE", false),
+            ("ANSWER", false),
+            ("MODE", false),
+            ("PORT", true),
+            ("NAMES", true),
+        ]
+    );
+
+    let fallible = "load(): number {\n    fail \"no value\"\n}\n\nconst VALUE = load()\n";
+    assert_eq!(code(fallible).as_deref(), Some("E0711"));
+    let untyped = "load() => 1\n\nconst VALUE = load()\n";
+    assert_eq!(code(untyped).as_deref(), Some("E0709"));
+}
+
+#[test]
+fn test_array_constructor_arguments() {
+    let code = |body: &str| {
+        let source = format!("main() {{\n    {}\n}}\n", body);
+        analyze(parse(tokenize(&source).unwrap(), &source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    assert_eq!(code("let a = Array.fill(10, \"x\")"), None);
+    assert_eq!(code("let n = 4\n    let a = Array.fill(n * 2, 0.5)"), None);
+    assert_eq!(code("let a = Array.from(0..10)"), None);
+    assert_eq!(code("let a = (1..=3).toArray()"), None);
+
+    assert_eq!(code("let a = Array.fill(10)").as_deref(), Some("E5009"));
+    assert_eq!(code("let a = Array.fill(2.5, 0)").as_deref(), Some("E5009"));
+    assert_eq!(
+        code("let a = Array.fill(\"3\", 0)").as_deref(),
+        Some("E5009")
+    );
+    assert_eq!(code("let a = Array.from([1, 2])").as_deref(), Some("E5009"));
+}
+
+#[test]
+fn test_frozen_values_reject_mutation() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let reads = "main() {\n    let limits = freeze([1, 2])\n    let copy = clone(limits)\n    copy.push(3)\n    print(limits.length, copy)\n}\n";
+    assert_eq!(code(reads), None);
+
+    let push = "main() {\n    let limits = freeze([1, 2])\n    limits.push(3)\n}\n";
+    assert_eq!(code(push).as_deref(), Some("E0008"));
+
+    // Copies of the binding are frozen too
+    let alias =
+        "main() {\n    let limits = freeze([1, 2])\n    let same = limits\n    same[0] = 5\n}\n";
+    assert_eq!(code(alias).as_deref(), Some("E0008"));
+
+    let field = "Config {\n    port: int\n}\n\nmain() {\n    let config = freeze(Config(80))\n    config.port = 8080\n}\n";
+    assert_eq!(code(field).as_deref(), Some("E0008"));
+
+    let reassign_frozen =
+        "main() {\n    let limits = freeze([1])\n    limits = freeze([2])\n    print(limits)\n}\n";
+    assert_eq!(code(reassign_frozen), None);
+
+    let reassign_const = "main() {\n    const LIMIT = 1\n    LIMIT = 2\n}\n";
+    assert_eq!(code(reassign_const).as_deref(), Some("E0008"));
+}
+
+#[test]
+fn test_lazy_values() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let load = "loadPort(): number {\n    fail \"no port\"\n}\n\n";
+
+    let bound = format!(
+        "{}lazy PORT = loadPort()\n\nmain() {{\n    let port, err = PORT\n    print(port, err)\n}}\n",
+        load
+    );
+    assert_eq!(code(&bound), None);
+
+    let or_fail = format!(
+        "{}lazy PORT = loadPort()\n\nrun(): number {{\n    let port = PORT or fail\n    return port\n}}\n",
+        load
+    );
+    assert_eq!(code(&or_fail), None);
+
+    let unbound = format!(
+        "{}lazy PORT = loadPort()\n\nmain() {{\n    print(PORT)\n}}\n",
+        load
+    );
+    assert_eq!(code(&unbound).as_deref(), Some("E0701"));
+
+    let untyped = "lazy HOME = Sys.env(\"HOME\")\n\nmain() {\n    print(HOME)\n}\n";
+    assert_eq!(code(untyped).as_deref(), Some("E0709"));
+
+    let clear = "lazy NAMES: [string] = [\"a\"]\n\nmain() {\n    NAMES.clear()\n}\n";
+    assert_eq!(code(clear).as_deref(), Some("E0008"));
+}
+
+#[test]
+fn test_result_combinators() {
+    let code = |body: &str| {
+        let source = format!(
+            "load(path: string): string {{\n    fail \"missing\"\n}}\n\nmain() {{\n{}}}\n",
+            body
+        );
+        analyze(parse(tokenize(&source).unwrap(), &source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    assert_eq!(
+        code("    let text = load(\"a\").orElse(\"\")\n    print(text)\n"),
+        None
+    );
+    assert_eq!(
+        code("    let text = load(\"a\").expect(\"bundled\")\n    print(text)\n"),
+        None
+    );
+    assert_eq!(
+        code("    let text, err = load(\"a\").mapErr(e => $\"a: {e}\")\n    print(text, err)\n"),
+        None
+    );
+    assert_eq!(
+        code("    let text = load(\"a\").mapErr(e => $\"a: {e}\")\n    print(text)\n").as_deref(),
+        Some("E0710")
+    );
+    assert_eq!(
+        code("    let text, err = async load(\"a\").mapErr(e => e)\n    print(text, err)\n")
+            .as_deref(),
+        Some("E0710")
+    );
+}
+
+#[test]
+fn test_flow_type_narrowing() {
+    let error = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .map(|e| e.to_string())
+    };
+    let with_value = |body: &str| {
+        format!(
+            "main() {{\n    let value: int | string = \"hi\"\n{}}}\n",
+            body
+        )
+    };
+
+    // `.length` needs the string member
+    let unchecked = with_value("    print(value.length)\n");
+    assert!(error(&unchecked).is_some_and(|e| e.contains("E5008")));
+    let checked =
+        with_value("    if typeOf(value) == \"string\" {\n        print(value.length)\n    }\n");
+    assert_eq!(error(&checked), None);
+    let else_branch = with_value(
+        "    if typeOf(value) == \"int\" {\n        print(value)\n    } else {\n        print(value.length)\n    }\n",
+    );
+    assert_eq!(error(&else_branch), None);
+    let guard = with_value(
+        "    if typeOf(value) != \"string\" {\n        return\n    }\n    print(value.length)\n",
+    );
+    assert_eq!(error(&guard), None);
+    let and = with_value(
+        "    if typeOf(value) == \"string\" && value.length > 1 {\n        print(value)\n    }\n",
+    );
+    assert_eq!(error(&and), None);
+
+    // Assigning another member ends the narrowing
+    let reassigned = with_value(
+        "    if typeOf(value) == \"string\" {\n        value = 5\n        print(value.length)\n    }\n",
+    );
+    assert!(error(&reassigned).is_some_and(|e| e.contains("E5008")));
+
+    let missing_member =
+        with_value("    if typeOf(value) == \"bool\" {\n        print(value)\n    }\n");
+    assert!(error(&missing_member).is_some_and(|e| e.contains("E5007")));
+}
+
+#[test]
+fn test_type_of_builtin() {
+    let error = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .map(|e| e.to_string())
+    };
+
+    // Any value has a type tag
+    let tags = "Point {\n    x: int\n}\n\nmain() {\n    let count = 5\n    let p = Point(1)\n    let kind: string = typeOf(count)\n    if typeOf(p) == \"Point\" || typeOf([1]) == \"array\" {\n        print(kind)\n    }\n}\n";
+    assert_eq!(error(tags), None);
+    let unknown = "main() {\n    let count = 5\n    print(typeOf(count) == \"integer\")\n}\n";
+    assert!(error(unknown).is_some_and(|e| e.contains("E5007")));
+    let arity = "main() {\n    print(typeOf(1, 2))\n}\n";
+    assert!(error(arity).is_some_and(|e| e.contains("E5007")));
+
+    // Switch cases narrow a union like `if` checks do
+    let switch = "describe(value: int | string): string {\n    return switch typeOf(value) {\n        \"string\" => value.toUpperCase(),\n        \"int\" => $\"n{value}\",\n        _ => \"?\"\n    }\n}\n";
+    assert_eq!(error(switch), None);
+    let bad_case = switch.replace("\"int\" =>", "\"bool\" =>");
+    assert!(error(&bad_case).is_some_and(|e| e.contains("E5007")));
+}
+
+#[test]
+fn test_null_checks_narrow_optionals() {
+    let source = "greet(name: string?) {\n    if name == null {\n        return\n    }\n    let known = name\n    print(known)\n}\n";
+    let analyzed = analyze(parse(tokenize(source).unwrap(), source).unwrap()).unwrap();
+    let hints: Vec<(&str, String)> = analyzed
+        .inferred_types
+        .iter()
+        .map(|t| (t.span.snippet(source), t.type_ref.to_string()))
+        .collect();
+    assert_eq!(hints, vec![("known", "string".to_string())]);
+}
+
+#[test]
+fn test_union_parameters_and_returns() {
+    let error = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .map(|e| e.to_string())
+    };
+    let program = |body: &str| {
+        format!(
+            "type Id = int | string\n\nparse(text: string): int | string {{\n    if text == \"\" {{\n        return 0\n    }}\n    return text\n}}\n\nshow(id: Id): string {{\n{}}}\n\nmain() {{\n    print(show(parse(\"a\")), show(7))\n}}\n",
+            body
+        )
+    };
+
+    let exhaustive = program("    return switch typeOf(id) {\n        \"int\" => $\"#{id}\",\n        \"string\" => id.toUpperCase()\n    }\n");
+    assert_eq!(error(&exhaustive), None);
+    let missing = program("    return switch typeOf(id) {\n        \"int\" => $\"#{id}\"\n    }\n");
+    assert!(error(&missing).is_some_and(|e| e.contains("E0905") && e.contains("\"string\"")));
+    let unnarrowed = program("    return id.toUpperCase()\n");
+    assert!(error(&unnarrowed).is_some_and(|e| e.contains("E5008")));
+    let displayed = program("    return id.toString()\n");
+    assert_eq!(error(&displayed), None);
+
+    let wrong_arg = program("    return $\"{id}\"\n").replace("show(7)", "show(true)");
+    assert!(error(&wrong_arg).is_some_and(|e| e.contains("E5001")));
+}
+
+#[test]
+fn test_init_blocks() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let single = "init {\n    print(\"ready\")\n}\n\nmain() {\n    print(1)\n}\n";
+    assert_eq!(code(single), None);
+
+    let twice = "init {\n    print(1)\n}\n\ninit {\n    print(2)\n}\n\nmain() {}\n";
+    assert_eq!(code(twice).as_deref(), Some("E0712"));
+
+    let awaits = "work(n: number): number => n * 2\n\ninit {\n    let r = async work(1)\n    print(r)\n}\n\nmain() {}\n";
+    assert_eq!(code(awaits).as_deref(), Some("E0713"));
+}
+
+#[test]
+fn test_duplicate_test_hooks() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+
+    let both = "before {\n    print(1)\n}\n\nafter {\n    print(2)\n}\n\nmain() {}\n";
+    assert_eq!(code(both), None);
+
+    let twice = "after {\n    print(1)\n}\n\nafter {\n    print(2)\n}\n\nmain() {}\n";
+    assert_eq!(code(twice).as_deref(), Some("E0714"));
+}
+
+#[test]
+fn test_mock_targets_and_placement() {
+    let code = |source: &str| match parse(tokenize(source).unwrap(), source) {
+        Ok(program) => analyze(program)
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone())),
+        Err(e) => e.error_info().map(|info| info.code.clone()),
+    };
+
+    let in_test = "test \"stubbed\" {\n    mock Date.now returns Date.new(2024, 1, 1)\n    mock prompt returns \"yes\"\n}\n";
+    assert_eq!(code(in_test), None);
+
+    let unknown = "test \"stubbed\" {\n    mock File.read returns \"\"\n}\n";
+    assert_eq!(code(unknown).as_deref(), Some("E2006"));
+
+    let in_main = "main() {\n    mock readLine returns \"ana\"\n}\n";
+    assert_eq!(code(in_main).as_deref(), Some("E0715"));
+}
+
+#[test]
+fn test_simd_width_is_rejected_until_supported() {
+    let error = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .map(|e| e.to_string())
+            .unwrap_or_default()
+    };
+
+    let loop_option = "main() {\n    let lanes = [1, 2]\n    for vec lane in lanes with simdWidth 4 {\n        print(lane)\n    }\n}\n";
+    assert!(
+        error(loop_option).contains("E0707"),
+        "{}",
+        error(loop_option)
+    );
+    assert!(error(loop_option).contains("simdWidth"));
+
+    let adapter = "main() {\n    let lanes = [1, 2]\n    let doubled = lanes.parvec({simdWidth: 4}).map(x => x * 2)\n    print(doubled)\n}\n";
+    assert!(error(adapter).contains("E0707"), "{}", error(adapter));
+
+    // `seq` still gets the more specific E0705
+    let seq = "main() {\n    let lanes = [1, 2]\n    for seq lane in lanes with simdWidth 4 {\n        print(lane)\n    }\n}\n";
+    assert!(error(seq).contains("E0705"), "{}", error(seq));
+}
+
+#[test]
+fn test_abstract_class_errors() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+    let shape = "abstract Shape {\n    name: string\n    abstract area(): number\n    describe() => $\"{this.name}: {this.area()}\"\n}\n\n";
+
+    let complete = format!("{shape}Square : Shape {{\n    side: number\n    area() => this.side * this.side\n}}\n\nmain() {{\n    let s = Square(\"sq\", 2)\n    print(s.describe())\n}}\n");
+    assert_eq!(code(&complete), None);
+
+    let instantiated = format!("{shape}main() {{\n    let s = Shape(\"sq\")\n    print(s)\n}}\n");
+    assert_eq!(code(&instantiated).as_deref(), Some("E0015"));
+
+    // Implementing `area` with another parameter count doesn't count
+    let missing = format!("{shape}Square : Shape {{\n    side: number\n    area(scale: number) => this.side * scale\n}}\n");
+    assert_eq!(code(&missing).as_deref(), Some("E0016"));
+
+    // An abstract class may leave them to the classes implementing it
+    let partial = format!("{shape}abstract Polygon : Shape {{\n    sides: number\n}}\n\nSquare : Polygon {{\n    side: number\n    area() => this.side * this.side\n}}\n");
+    assert_eq!(code(&partial), None);
+
+    let concrete_abstract_method = "Square {\n    side: number\n    abstract area(): number\n}\n";
+    assert_eq!(code(concrete_abstract_method).as_deref(), Some("E0017"));
+
+    let abstract_constructor = "abstract Shape {\n    name: string\n    constructor(name: string) {\n        this.name = name\n    }\n}\n";
+    assert_eq!(code(abstract_constructor).as_deref(), Some("E0017"));
+}
+
+#[test]
+fn test_delegate_errors() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+    let classes = "Engine {\n    power: number\n    start() => print(\"vroom\")\n    stop() => print(\"off\")\n}\n\nRadio {\n    station: string\n    start() => print(this.station)\n    on() => print(\"on\")\n}\n\n";
+
+    let forwarding = format!("{classes}Car {{\n    engine: Engine\n    delegate engine\n}}\n\nmain() {{\n    let car = Car(Engine(100))\n    car.start()\n    car.stop()\n}}\n");
+    assert_eq!(code(&forwarding), None);
+
+    // The class's own `start` wins, so both delegates can forward the rest
+    let own = format!("{classes}Car {{\n    engine: Engine\n    radio: Radio\n    delegate engine\n    delegate radio\n    start() => this.engine.start()\n}}\n");
+    assert_eq!(code(&own), None);
+
+    let conflict = format!("{classes}Car {{\n    engine: Engine\n    radio: Radio\n    delegate engine\n    delegate radio\n}}\n");
+    assert_eq!(code(&conflict).as_deref(), Some("E0019"));
+
+    let listed = format!("{classes}Car {{\n    engine: Engine\n    radio: Radio\n    delegate engine\n    delegate radio {{ on }}\n}}\n");
+    assert_eq!(code(&listed), None);
+
+    for invalid in [
+        format!("{classes}Car {{\n    engine: Engine\n    delegate motor\n}}\n"),
+        format!("{classes}Car {{\n    speed: number\n    delegate speed\n}}\n"),
+        format!("{classes}Car {{\n    engine: Engine\n    delegate engine {{ honk }}\n}}\n"),
+    ] {
+        assert_eq!(code(&invalid).as_deref(), Some("E0018"), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_private_member_access() {
+    let error = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().cloned())
+    };
+    let account = "BankAccount {\n    _balance: number\n    constructor(balance: number) {\n        this._balance = balance\n    }\n    getBalance() => this._balance\n    _log(msg: string) {\n        print(msg)\n    }\n    transfer(to: BankAccount, amount: number) {\n        this._log(\"transfer\")\n        to._balance = to._balance + amount\n        this._balance = this._balance - amount\n    }\n}\n\n";
+
+    // Methods may use the `_` members of any value of their own class
+    let inside = format!("{account}main() {{\n    let acc = BankAccount(100)\n    acc.transfer(BankAccount(0), 5)\n    print(acc.getBalance())\n}}\n");
+    assert_eq!(error(&inside).map(|info| info.message), None);
+
+    let read = error(&format!(
+        "{account}main() {{\n    let acc = BankAccount(100)\n    print(acc._balance)\n}}\n"
+    ))
+    .unwrap();
+    assert_eq!(read.code, "E0022");
+    assert_eq!(
+        read.message,
+        "Field '_balance' is private to 'BankAccount'; only methods of 'BankAccount' can use it"
+    );
+    assert_eq!(
+        read.help.as_deref(),
+        Some("Use the public getter 'getBalance()' instead")
+    );
+
+    let write = error(&format!(
+        "{account}main() {{\n    let acc = BankAccount(100)\n    acc._balance = 0\n}}\n"
+    ))
+    .unwrap();
+    assert_eq!(write.code, "E0022");
+    assert!(write.help.unwrap().contains("setBalance(value)"));
+
+    let call = error(&format!(
+        "{account}audit(acc: BankAccount) {{\n    acc._log(\"audit\")\n}}\n"
+    ))
+    .unwrap();
+    assert_eq!(call.code, "E0022");
+    assert!(call.message.starts_with("Method '_log'"));
+
+    // What an abstract class passes on is the implementing class's own
+    let shape = "abstract Shape {\n    _scale: number\n    abstract area(): number\n    _scaled(value: number): number => value * this._scale\n}\n\nSquare : Shape {\n    side: number\n    area() => this._scaled(this.side * this.side)\n}\n\n";
+    let inherited =
+        format!("{shape}main() {{\n    let sq = Square(2, 3)\n    print(sq.area())\n}}\n");
+    assert_eq!(error(&inherited).map(|info| info.message), None);
+
+    let outside =
+        format!("{shape}main() {{\n    let sq = Square(2, 3)\n    print(sq._scale)\n}}\n");
+    assert_eq!(
+        error(&outside).map(|info| info.code).as_deref(),
+        Some("E0022")
+    );
+    let through_base = format!("{shape}Circle : Shape {{\n    radius: number\n    area() => this.radius * this.radius * 3\n    sameScale(other: Shape): bool => other._scale == this._scale\n}}\n");
+    assert_eq!(
+        error(&through_base).map(|info| info.code).as_deref(),
+        Some("E0022")
+    );
+}
+
+#[test]
+fn test_power_operands() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+    let numbers = "main() {\n    let n = 3\n    let x = 1.5\n    let a = n ** 2 + x ** 0.5\n    let b = 2 ** -1\n    print(a, b)\n}\n";
+    assert_eq!(code(numbers), None);
+
+    for invalid in [
+        "main() {\n    let s = \"ab\"\n    print(s ** 2)\n}\n",
+        "main() {\n    let flags = [true]\n    print(2 ** flags)\n}\n",
+    ] {
+        assert_eq!(code(invalid).as_deref(), Some("E5001"), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_bitwise_operands() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+    let ints = "main() {\n    let n = 6\n    let m = n & 3 | n << 1 ^ ~n >> 2\n    n &= 1\n    print(m)\n}\n";
+    assert_eq!(code(ints), None);
+
+    for invalid in [
+        "main() {\n    let x = 1.5\n    print(x & 1)\n}\n",
+        "main() {\n    let ok = true\n    print(ok | false)\n}\n",
+        "main() {\n    let s = \"ab\"\n    print(~s)\n}\n",
+        "main() {\n    let n = 1\n    print(n << 0.5)\n}\n",
+    ] {
+        assert_eq!(code(invalid).as_deref(), Some("E5001"), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_char_operands() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().map(|info| info.code.clone()))
+    };
+    let chars = "main() {\n    let c = 'a'\n    let d = fromCharCode(charCode(c) + 1)\n    let text = \"abc\"\n    for ch in text.chars() {\n        if ch == 'b' {\n            print(charCode(ch))\n        }\n    }\n    print(c < d, text.charAt(0) == 'a', c as int, \"x\" + c)\n}\n";
+    assert_eq!(code(chars), None);
+
+    for invalid in [
+        "main() {\n    let c = 'a'\n    print(c == \"a\")\n}\n",
+        "main() {\n    let c = 'a'\n    print(c > 64)\n}\n",
+        "main() {\n    let c = 'a'\n    print(c + 1)\n}\n",
+        "main() {\n    print(charCode(65))\n}\n",
+        "main() {\n    print(fromCharCode(\"A\"))\n}\n",
+    ] {
+        assert_eq!(code(invalid).as_deref(), Some("E5001"), "{}", invalid);
+    }
+    let to_float = "main() {\n    let c = 'a'\n    print(c as float)\n}\n";
+    assert_eq!(code(to_float).as_deref(), Some("E5005"));
+}
+
+#[test]
+fn test_non_bool_conditions() {
+    let error = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .and_then(|e| e.error_info().cloned())
+    };
+    let valid = "main() {\n    let count = 3\n    let name = \"liva\"\n    let maybe: string? = null\n    let n, err = parseInt(\"4\")\n    if err {\n        print(\"bad\")\n    }\n    if count != 0 && name != \"\" && maybe == null {\n        print(!false)\n    }\n    while count > 5 {\n        print(n)\n    }\n    print(name or \"anon\")\n}\n";
+    assert!(error(valid).is_none(), "{:?}", error(valid));
+
+    // Sin truthiness: el error propone la comparación explícita
+    for (invalid, suggestion) in [
+        ("main() {\n    let count = 3\n    if count {\n        print(count)\n    }\n}\n", "count != 0"),
+        ("main() {\n    let name = \"liva\"\n    while name {\n        print(name)\n    }\n}\n", "name != \"\""),
+        ("main() {\n    let n = 2.5\n    print(!n)\n}\n", "n == 0"),
+        ("main() {\n    let items = [1, 2]\n    if items {\n        print(items)\n    }\n}\n", "items.length > 0"),
+        ("main() {\n    let maybe: int? = null\n    if maybe {\n        print(maybe)\n    }\n}\n", "maybe != null"),
+        ("main() {\n    let ok = true\n    let n = 1\n    print(ok && n)\n}\n", "n != 0"),
+    ] {
+        let info = error(invalid).expect(invalid);
+        assert_eq!(info.code, "E5010", "{}", invalid);
+        assert_eq!(info.suggestion, Some(format!("Did you mean '{}'?", suggestion)));
+    }
+}
+
+#[test]
+fn test_function_contracts() {
+    let code = |source: &str| {
+        analyze(parse(tokenize(source).unwrap(), source).unwrap())
+            .err()
+            .map(|e| e.error_info().map(|info| info.code.clone()))
+    };
+    let valid = "divide(a: int, b: int): int\n    requires b != 0\n    ensures result * b <= a\n{\n    return a / b\n}\n\nmain() {\n    print(divide(7, 2))\n}\n";
+    assert_eq!(code(valid), None);
+
+    // `result` is only in scope for `ensures`
+    let early = "f(n: int): int requires result > 0 => n\n\nmain() {\n    print(f(1))\n}\n";
+    assert_eq!(code(early), Some(Some("E0020".to_string())));
+    // ...and only when the function returns something
+    let void = "log(n: int) ensures result > 0 {\n    print(n)\n}\n\nmain() {\n    log(1)\n}\n";
+    assert_eq!(code(void), Some(Some("E0020".to_string())));
+
+    let not_bool = "f(n: int): int requires n => n\n\nmain() {\n    print(f(1))\n}\n";
+    assert_eq!(code(not_bool), Some(Some("E5001".to_string())));
+}
+
+#[test]
+fn test_sandbox_capabilities() {
+    use livac::sandbox::{Capability, SandboxPolicy};
+    use livac::{compile_source, CompilerOptions};
+
+    let code = |source: &str, policy: SandboxPolicy| {
+        let options = CompilerOptions {
+            check_only: true,
+            sandbox: Some(policy),
+            ..CompilerOptions::default()
+        };
+        compile_source(source, &options)
+            .err()
+            .map(|e| e.error_info().map(|info| info.message.clone()))
+    };
+    let pure = "main() {\n    let xs = [1, 2, 3]\n    print(xs.map(x => x * 2))\n}\n";
+    assert_eq!(code(pure, SandboxPolicy::new()), None);
+
+    let reads = "load(): string {\n    let text, err = File.read(\"/etc/passwd\")\n    return text\n}\n\nmain() {\n    print(load())\n}\n";
+    let err = code(reads, SandboxPolicy::new()).unwrap().unwrap();
+    assert!(
+        err.contains("'File.read' needs the 'fs' capability"),
+        "{}",
+        err
+    );
+    assert_eq!(
+        code(reads, SandboxPolicy::new().allow(Capability::Fs)),
+        None
+    );
+
+    // Codegen resolves `File.read` to the builtin whatever `File` the
+    // program declares, so declaring one needs the capability too
+    let shadowed = "File {\n    name: string\n}\n\nmain() {\n    let r, err = File.read(\"/etc/hostname\")\n    print(r)\n}\n";
+    let err = code(shadowed, SandboxPolicy::new()).unwrap().unwrap();
+    assert!(err.contains("Declaring `File`"), "{}", err);
+    let unused = "File {\n    name: string\n}\n\nmain() {\n    print(File(\"a\").name)\n}\n";
+    assert!(code(unused, SandboxPolicy::new()).is_some());
+    assert_eq!(
+        code(shadowed, SandboxPolicy::new().allow(Capability::Fs)),
+        None
+    );
+    let imported =
+        "import { Process } from \"liva/test\"\n\nmain() {\n    print(Process.pid())\n}\n";
+    assert!(code(imported, SandboxPolicy::new())
+        .unwrap()
+        .unwrap()
+        .contains("'process'"));
+
+    let fetches = "main() {\n    let res, err = async HTTP.get(\"https://example.com\")\n}\n";
+    let err = code(fetches, SandboxPolicy::new().allow(Capability::Fs));
+    assert!(err.unwrap().unwrap().contains("'net'"));
+
+    // Calls inside lambdas count too
+    let spawns = "main() {\n    let f = () => Process.pid()\n}\n";
+    assert!(code(spawns, SandboxPolicy::new())
+        .unwrap()
+        .unwrap()
+        .contains("'process'"));
+
+    let crates = "use rust \"regex\"\n\nmain() {\n    print(1)\n}\n";
+    assert!(code(crates, SandboxPolicy::new()).is_some());
+    assert_eq!(
+        code(crates, SandboxPolicy::new().allow_crate("regex")),
+        None
+    );
+    let inline = "main() {\n    let n = rust { 1 + 1 }\n}\n";
+    assert!(code(inline, SandboxPolicy::new().allow_crate("regex")).is_some());
+    let unsafe_fn = "unsafe rust fn twice(n: number): number {\n    n * 2\n}\n\nmain() {\n    print(twice(2))\n}\n";
+    let err = code(unsafe_fn, SandboxPolicy::new()).unwrap().unwrap();
+    assert!(err.contains("`unsafe rust fn twice`"), "{}", err);
+    assert_eq!(
+        code(unsafe_fn, SandboxPolicy::new().allow(Capability::Rust)),
+        None
+    );
+}
+
+#[test]
+fn test_unsafe_rust_fn_boundary() {
+    let code = |source: &str| {
+        parse(tokenize(source).unwrap(), source)
+            .and_then(analyze)
+            .err()
+            .map(|e| e.error_info().map(|info| info.code.clone()))
+    };
+    let typed = "unsafe rust fn twice(n: number): number {\n    n * 2\n}\n\nmain() {\n    print(twice(2))\n}\n";
+    assert_eq!(code(typed), None);
+
+    // Solo se comprueba la firma: cada parámetro necesita su tipo
+    let untyped =
+        "unsafe rust fn twice(n): number {\n    n * 2\n}\n\nmain() {\n    print(twice(2))\n}\n";
+    assert_eq!(code(untyped), Some(Some("E0023".to_string())));
+
+    let main = "unsafe rust fn main() {\n    println!(\"{}\", \"hi\");\n}\n";
+    assert_eq!(code(main), Some(Some("E0023".to_string())));
+
+    // Un `T!` devuelve un `Result` de Rust: hay que recoger el error
+    let fallible = "unsafe rust fn parsePort(text: string): number! {\n    text.parse::<i32>()\n}\n\nmain() {\n    let port = parsePort(\"80\")\n    print(port)\n}\n";
+    assert_eq!(code(fallible), Some(Some("E0701".to_string())));
+    let bound = fallible.replace("let port =", "let port, err =");
+    assert_eq!(code(&bound), None);
+
+    // Sin `unsafe` es un error de sintaxis
+    let bare = "rust fn twice(n: number): number {\n    n * 2\n}\n";
+    assert!(parse(tokenize(bare).unwrap(), bare).is_err());
+}
+
+#[test]
+fn test_scope_takes_fallible_task_errors() {
+    let analyze_source = |source: &str| analyze(parse(tokenize(source).unwrap(), source).unwrap());
+    let save = "save(n: number) {\n    if n < 0 {\n        fail \"negative\"\n    }\n}\n\n";
+
+    // The scope fails with a fired task's error, so no binding is needed
+    let fired = format!(
+        "{}main() {{\n    scope {{\n        async save(1)\n    }}\n}}\n",
+        save
+    );
+    assert!(analyze_source(&fired).is_ok());
+
+    // Outside a scope, or when the task is bound to a value, it still is
+    for body in [
+        "    async save(1)\n",
+        "    scope {\n        let pending = async save(1)\n    }\n",
+        "    scope {\n        let log = () => {\n            async save(1)\n        }\n        log()\n    }\n",
+    ] {
+        let source = format!("{}main() {{\n{}}}\n", save, body);
+        let error = analyze_source(&source).unwrap_err();
+        assert!(error.to_string().contains("E0701"), "{}", error);
+    }
+}
@@ -75,7 +75,7 @@ impl Formatter {
 
     /// Get the current indentation string
     fn indent(&self) -> String {
-        " ".repeat(self.options.indent_size * self.indent_level)
+        indentation(&self.options, self.indent_level)
     }
 
     /// Current column position at the current indent level
@@ -148,6 +148,9 @@ impl Formatter {
                 // skip explicit emission here.
                 // TODO: emit `extend Name { ... }` verbatim when round-tripping is required.
             }
+            TopLevel::Runtime(decl) => self.format_runtime(decl),
+            TopLevel::Init(decl) => self.format_init(decl),
+            TopLevel::TestHook(decl) => self.format_test_hook(decl),
         }
     }
 
@@ -156,39 +159,48 @@ impl Formatter {
     // ======================================================================
 
     fn format_import(&mut self, decl: &ImportDecl) {
+        let keyword = if decl.is_reexport { "export" } else { "import" };
         if decl.is_wildcard {
             if let Some(alias) = &decl.alias {
-                self.write_line(&format!("import * as {} from \"{}\"", alias, decl.source));
+                self.write_line(&format!(
+                    "{} * as {} from \"{}\"",
+                    keyword, alias, decl.source
+                ));
             } else {
-                self.write_line(&format!("import * from \"{}\"", decl.source));
+                self.write_line(&format!("{} * from \"{}\"", keyword, decl.source));
             }
-        } else if decl.imports.len() == 1 {
-            self.write_line(&format!(
-                "import {{ {} }} from \"{}\"",
-                decl.imports[0], decl.source
-            ));
+            return;
+        }
+
+        let symbols: Vec<String> = decl
+            .imports
+            .iter()
+            .map(|symbol| match decl.renames.get(symbol) {
+                Some(local) => format!("{} as {}", symbol, local),
+                None => symbol.clone(),
+            })
+            .collect();
+        let single_line = format!(
+            "{} {{ {} }} from \"{}\"",
+            keyword,
+            symbols.join(", "),
+            decl.source
+        );
+        if symbols.len() == 1 || !self.would_exceed_width(&single_line) {
+            self.write_line(&single_line);
         } else {
-            let single_line = format!(
-                "import {{ {} }} from \"{}\"",
-                decl.imports.join(", "),
-                decl.source
-            );
-            if !self.would_exceed_width(&single_line) {
-                self.write_line(&single_line);
-            } else {
-                // Multi-line imports
-                self.write_line("import {");
-                self.indent_level += 1;
-                for (i, import) in decl.imports.iter().enumerate() {
-                    if i + 1 < decl.imports.len() {
-                        self.write_line(&format!("{},", import));
-                    } else {
-                        self.write_line(import);
-                    }
+            // Multi-line imports
+            self.write_line(&format!("{} {{", keyword));
+            self.indent_level += 1;
+            for (i, symbol) in symbols.iter().enumerate() {
+                if i + 1 < symbols.len() {
+                    self.write_line(&format!("{},", symbol));
+                } else {
+                    self.write_line(symbol);
                 }
-                self.indent_level -= 1;
-                self.write_line(&format!("}} from \"{}\"", decl.source));
             }
+            self.indent_level -= 1;
+            self.write_line(&format!("}} from \"{}\"", decl.source));
         }
     }
 
@@ -207,6 +219,23 @@ impl Formatter {
         self.write_line(&parts.join(" "));
     }
 
+    fn format_runtime(&mut self, decl: &RuntimeDecl) {
+        let options = [
+            ("threads", decl.threads),
+            ("asyncThreads", decl.async_threads),
+            ("parThreads", decl.par_threads),
+        ];
+        let parts: Vec<String> = options
+            .iter()
+            .filter_map(|(name, value)| value.map(|v| format!("{}: {}", name, v)))
+            .collect();
+        if parts.is_empty() {
+            self.write_line("runtime {}");
+        } else {
+            self.write_line(&format!("runtime {{ {} }}", parts.join(", ")));
+        }
+    }
+
     // ======================================================================
     // Type declarations
     // ======================================================================
@@ -215,7 +244,7 @@ impl Formatter {
         let type_params = self.format_type_params(&decl.type_params);
         self.write_line(&format!("{}{} {{", decl.name, type_params));
         self.indent_level += 1;
-        self.format_members(&decl.members);
+        self.format_members(&decl.members, &[]);
         self.indent_level -= 1;
         self.write_line("}");
     }
@@ -260,19 +289,32 @@ impl Formatter {
             format!(" : {}", decl.implements.join(", "))
         };
 
+        let modifier = if decl.is_abstract { "abstract " } else { "" };
+
         // No `data` keyword — data classes are auto-detected from structure
-        self.write_line(&format!("{}{}{} {{", decl.name, type_params, implements));
+        self.write_line(&format!(
+            "{}{}{}{} {{",
+            modifier, decl.name, type_params, implements
+        ));
         self.indent_level += 1;
-        self.format_members(&decl.members);
+        self.format_members(&decl.members, &decl.delegates);
         self.indent_level -= 1;
         self.write_line("}");
     }
 
-    fn format_members(&mut self, members: &[Member]) {
+    /// Members in source order, with the `delegate` lines between the
+    /// fields and the methods
+    fn format_members(&mut self, members: &[Member], delegates: &[DelegateDecl]) {
         let mut last_kind: Option<&str> = None;
         let mut first = true;
+        let mut delegates_written = delegates.is_empty();
 
         for member in members {
+            if !delegates_written && matches!(member, Member::Method(_)) {
+                self.format_delegates(delegates);
+                delegates_written = true;
+                first = false;
+            }
             match member {
                 Member::Field(field) => {
                     // Add blank line when transitioning from methods back to fields
@@ -292,6 +334,23 @@ impl Formatter {
             }
             first = false;
         }
+        if !delegates_written {
+            self.format_delegates(delegates);
+        }
+    }
+
+    fn format_delegates(&mut self, delegates: &[DelegateDecl]) {
+        for delegate in delegates {
+            if delegate.methods.is_empty() {
+                self.write_line(&format!("delegate {}", delegate.field));
+            } else {
+                self.write_line(&format!(
+                    "delegate {} {{ {} }}",
+                    delegate.field,
+                    delegate.methods.join(", ")
+                ));
+            }
+        }
     }
 
     fn format_field(&mut self, field: &FieldDecl) {
@@ -388,11 +447,12 @@ impl Formatter {
                 self.write_line("}");
             }
         } else {
-            // Interface method (no body)
+            // Interface or abstract method (no body)
+            let modifier = if method.is_abstract { "abstract " } else { "" };
             let params_str = self.format_params_simple(&method.params);
             self.write_line(&format!(
-                "{}{}({}){}",
-                method.name, type_params, params_str, ret_type
+                "{}{}{}({}){}",
+                modifier, method.name, type_params, params_str, ret_type
             ));
         }
     }
@@ -441,6 +501,14 @@ impl Formatter {
             .map(|t| format!(": {}", self.format_type_ref(t)))
             .unwrap_or_default();
 
+        if decl.checked {
+            self.write_line("@checked");
+        }
+        if !decl.requires.is_empty() || !decl.ensures.is_empty() {
+            self.format_function_with_contracts(decl, &type_params, &ret_type);
+            return;
+        }
+
         // Try to simplify { return expr } to => expr
         if let Some(block) = &decl.body {
             if let Some(expr) = self.try_extract_single_return(block) {
@@ -510,6 +578,42 @@ impl Formatter {
         }
     }
 
+    /// Contracts go one per line, indented under the signature; a block body
+    /// then opens on its own line
+    fn format_function_with_contracts(
+        &mut self,
+        decl: &FunctionDecl,
+        type_params: &str,
+        ret_type: &str,
+    ) {
+        let params_str = self.format_params_simple(&decl.params);
+        self.write_line(&format!(
+            "{}{}({}){}",
+            decl.name, type_params, params_str, ret_type
+        ));
+        self.indent_level += 1;
+        for contract in &decl.requires {
+            let condition = self.format_expr(&contract.condition);
+            self.write_line(&format!("requires {}", condition));
+        }
+        for contract in &decl.ensures {
+            let condition = self.format_expr(&contract.condition);
+            self.write_line(&format!("ensures {}", condition));
+        }
+        if let Some(expr) = &decl.expr_body {
+            let body = self.format_expr(expr);
+            self.write_line(&format!("=> {}", body));
+            self.indent_level -= 1;
+        } else if let Some(block) = &decl.body {
+            self.indent_level -= 1;
+            self.write_line("{");
+            self.indent_level += 1;
+            self.format_block(block);
+            self.indent_level -= 1;
+            self.write_line("}");
+        }
+    }
+
     fn format_test(&mut self, decl: &TestDecl) {
         self.write_line(&format!("test \"{}\" {{", decl.name));
         self.indent_level += 1;
@@ -518,6 +622,22 @@ impl Formatter {
         self.write_line("}");
     }
 
+    fn format_init(&mut self, decl: &InitDecl) {
+        self.write_line("init {");
+        self.indent_level += 1;
+        self.format_block(&decl.body);
+        self.indent_level -= 1;
+        self.write_line("}");
+    }
+
+    fn format_test_hook(&mut self, decl: &TestHookDecl) {
+        self.write_line(&format!("{} {{", decl.kind.keyword()));
+        self.indent_level += 1;
+        self.format_block(&decl.body);
+        self.indent_level -= 1;
+        self.write_line("}");
+    }
+
     // ======================================================================
     // Simplification helpers
     // ======================================================================
@@ -626,6 +746,10 @@ impl Formatter {
                 let expr = self.format_expr(&fail.expr);
                 self.write_line(&format!("fail {}", expr));
             }
+            Stmt::Mock(mock) => {
+                let value = self.format_expr(&mock.value);
+                self.write_line(&format!("mock {} returns {}", mock.target, value));
+            }
             Stmt::Return(ret) => {
                 if let Some(expr) = &ret.expr {
                     let e = self.format_expr(expr);
@@ -663,6 +787,7 @@ impl Formatter {
                     }
                 }
             }
+            Stmt::Function(decl) => self.format_function(decl),
             Stmt::Expr(expr_stmt) => {
                 let e = self.format_expr(&expr_stmt.expr);
                 // If the expression has embedded newlines (multiline call), handle it
@@ -679,6 +804,13 @@ impl Formatter {
                 self.indent_level -= 1;
                 self.write_line("}");
             }
+            Stmt::Scope(scope) => {
+                self.write_line("scope {");
+                self.indent_level += 1;
+                self.format_block(&scope.body);
+                self.indent_level -= 1;
+                self.write_line("}");
+            }
         }
     }
 
@@ -698,10 +830,18 @@ impl Formatter {
 
     fn format_var_decl(&mut self, decl: &VarDecl) {
         let init = self.format_expr(&decl.init);
-        let or_fail_suffix = if let Some(msg) = &decl.or_fail_msg {
-            format!(" or fail {}", self.format_expr(msg))
-        } else {
-            String::new()
+        let or_fail_suffix = match (&decl.combinator, &decl.or_fail_msg, &decl.or_value) {
+            (Some(ResultCombinator::OrElse), _, Some(value)) => {
+                format!(".orElse({})", self.format_expr(value))
+            }
+            (Some(ResultCombinator::Expect), Some(msg), _) => {
+                format!(".expect({})", self.format_expr(msg))
+            }
+            (Some(ResultCombinator::MapErr(map)), _, _) => {
+                format!(".mapErr({})", self.format_expr(map))
+            }
+            (_, Some(msg), _) => format!(" or fail {}", self.format_expr(msg)),
+            _ => String::new(),
         };
         if decl.bindings.len() == 1 {
             let binding = &decl.bindings[0];
@@ -767,7 +907,7 @@ impl Formatter {
                     }
                 })
                 .collect();
-            let line = format!("let {} = {}", patterns.join(", "), init);
+            let line = format!("let {} = {}{}", patterns.join(", "), init, or_fail_suffix);
             if init.contains('\n') {
                 let init_lines: Vec<&str> = init.lines().collect();
                 self.write_line(&format!("let {} = {}", patterns.join(", "), init_lines[0]));
@@ -775,6 +915,11 @@ impl Formatter {
                     self.output.push_str(il);
                     self.output.push('\n');
                 }
+                if !or_fail_suffix.is_empty() {
+                    self.output.pop();
+                    self.output.push_str(&or_fail_suffix);
+                    self.output.push('\n');
+                }
             } else {
                 self.write_line(&line);
             }
@@ -788,7 +933,8 @@ impl Formatter {
             .as_ref()
             .map(|t| format!(": {}", self.format_type_ref(t)))
             .unwrap_or_default();
-        self.write_line(&format!("const {}{} = {}", decl.name, type_ann, init));
+        let keyword = if decl.is_lazy { "lazy" } else { "const" };
+        self.write_line(&format!("{} {}{} = {}", keyword, decl.name, type_ann, init));
     }
 
     fn format_assign(&mut self, assign: &AssignStmt) {
@@ -820,68 +966,28 @@ impl Formatter {
 
     fn format_if(&mut self, if_stmt: &IfStmt) {
         let cond = self.format_condition(&if_stmt.condition);
-        match &if_stmt.then_branch {
-            IfBody::Block(block) => {
-                self.write_line(&format!("if {} {{", cond));
-                self.indent_level += 1;
-                self.format_block(block);
-                self.indent_level -= 1;
-            }
-            IfBody::Stmt(stmt) => {
-                self.write_line(&format!("if {} {{", cond));
-                self.indent_level += 1;
-                self.format_stmt(stmt);
-                self.indent_level -= 1;
-            }
+        self.write_line(&format!("if {} {{", cond));
+        self.format_if_body(&if_stmt.then_branch);
+        for link in &if_stmt.else_ifs {
+            let cond = self.format_condition(&link.condition);
+            self.write_line(&format!("}} else if {} {{", cond));
+            self.format_if_body(&link.body);
         }
-
         if let Some(else_branch) = &if_stmt.else_branch {
-            self.format_else_branch(else_branch);
-        } else {
-            self.write_line("}");
+            self.write_line("} else {");
+            self.format_if_body(else_branch);
         }
+        self.write_line("}");
     }
 
-    fn format_else_branch(&mut self, branch: &IfBody) {
-        match branch {
-            IfBody::Block(block) => {
-                self.write_line("} else {");
-                self.indent_level += 1;
-                self.format_block(block);
-                self.indent_level -= 1;
-                self.write_line("}");
-            }
-            IfBody::Stmt(stmt) => {
-                if let Stmt::If(inner_if) = stmt.as_ref() {
-                    let cond = self.format_condition(&inner_if.condition);
-                    match &inner_if.then_branch {
-                        IfBody::Block(block) => {
-                            self.write_line(&format!("}} else if {} {{", cond));
-                            self.indent_level += 1;
-                            self.format_block(block);
-                            self.indent_level -= 1;
-                        }
-                        IfBody::Stmt(s) => {
-                            self.write_line(&format!("}} else if {} {{", cond));
-                            self.indent_level += 1;
-                            self.format_stmt(s);
-                            self.indent_level -= 1;
-                        }
-                    }
-                    if let Some(inner_else) = &inner_if.else_branch {
-                        self.format_else_branch(inner_else);
-                    } else {
-                        self.write_line("}");
-                    }
-                } else {
-                    self.write_line("} else {");
-                    self.indent_level += 1;
-                    self.format_stmt(stmt);
-                    self.indent_level -= 1;
-                    self.write_line("}");
-                }
-            }
+    /// Body of one branch of an `if` chain, indented under its header line
+    fn format_if_body(&mut self, body: &IfBody) {
+        self.indent_level += 1;
+        match body {
+            IfBody::Block(block) => self.format_block(block),
+            IfBody::Stmt(stmt) => self.format_stmt(stmt),
         }
+        self.indent_level -= 1;
     }
 
     fn format_while(&mut self, while_stmt: &WhileStmt) {
@@ -1027,6 +1133,7 @@ impl Formatter {
                             format!("!{}", operand_str)
                         }
                     }
+                    UnOp::BitNot => format!("~{}", operand_str),
                     UnOp::Await => format!("await {}", operand_str),
                 }
             }
@@ -1067,6 +1174,7 @@ impl Formatter {
             }
             Expr::MethodCall(mc) => self.format_method_call(mc),
             Expr::Switch(switch_expr) => self.format_switch_expr(switch_expr),
+            Expr::If(if_expr) => self.format_if_expr(if_expr),
             Expr::MapLiteral(entries) => self.format_map_literal(entries),
             Expr::SetLiteral(elements) => self.format_set_literal(elements),
             Expr::MethodRef { object, method } => {
@@ -1081,6 +1189,15 @@ impl Formatter {
             Expr::Try(inner) => {
                 format!("{}?", self.format_expr(inner))
             }
+            Expr::Cast { expr, target } => {
+                let inner = self.format_expr(expr);
+                let target = self.format_type_ref(target);
+                if matches!(expr.as_ref(), Expr::Binary { .. } | Expr::Ternary { .. }) {
+                    format!("({}) as {}", inner, target)
+                } else {
+                    format!("{} as {}", inner, target)
+                }
+            }
             Expr::OptionalChain { object, property } => {
                 format!("{}?.{}", self.format_expr(object), property)
             }
@@ -1172,6 +1289,11 @@ impl Formatter {
     }
 
     fn format_method_call(&mut self, mc: &MethodCallExpr) -> String {
+        if let Some(comprehension) = &mc.comprehension {
+            if let Some(formatted) = self.format_comprehension(mc, comprehension) {
+                return formatted;
+            }
+        }
         let obj = self.format_expr(&mc.object);
         let args: Vec<String> = mc.args.iter().map(|a| self.format_expr(a)).collect();
 
@@ -1222,6 +1344,49 @@ impl Formatter {
         single_line
     }
 
+    /// `[element for x in items if condition]`, from the `filter` / `map`
+    /// calls the parser rewrote it to
+    fn format_comprehension(
+        &mut self,
+        mc: &MethodCallExpr,
+        comprehension: &Comprehension,
+    ) -> Option<String> {
+        fn lambda_parts(call: &MethodCallExpr) -> Option<(&str, &Expr)> {
+            let Some(Expr::Lambda(lambda)) = call.args.first() else {
+                return None;
+            };
+            let var = lambda.params.first()?.name()?;
+            match &lambda.body {
+                LambdaBody::Expr(body) => Some((var, body)),
+                LambdaBody::Block(_) => None,
+            }
+        }
+
+        let (var, body) = lambda_parts(mc)?;
+        let (element, condition, iterable) = match (mc.method.as_str(), &*mc.object) {
+            ("filter", _) => (var.to_string(), Some(body), &*mc.object),
+            ("map", Expr::MethodCall(filter)) if comprehension.filtered => {
+                let (_, condition) = lambda_parts(filter)?;
+                (self.format_expr(body), Some(condition), &*filter.object)
+            }
+            _ => (self.format_expr(body), None, &*mc.object),
+        };
+        let policy = match mc.adapter {
+            ArrayAdapter::Seq => "",
+            ArrayAdapter::Par => "par ",
+            ArrayAdapter::Vec => "vec ",
+            ArrayAdapter::ParVec => "parvec ",
+        };
+        let iterable = self.format_expr(iterable);
+        let condition = condition
+            .map(|condition| format!(" if {}", self.format_expr(condition)))
+            .unwrap_or_default();
+        Some(format!(
+            "[{} for {}{} in {}{}]",
+            element, policy, var, iterable, condition
+        ))
+    }
+
     fn format_map_literal(&mut self, entries: &[(Expr, Expr)]) -> String {
         if entries.is_empty() {
             return "Map {}".to_string();
@@ -1464,6 +1629,26 @@ impl Formatter {
         result
     }
 
+    fn format_if_expr(&mut self, if_expr: &IfExpr) -> String {
+        let condition = self.format_expr(&if_expr.condition);
+        let then_branch = self.format_if_expr_branch(&if_expr.then_branch);
+        let else_branch = match &if_expr.else_branch {
+            SwitchBody::Expr(e) if matches!(e.as_ref(), Expr::If(_)) => self.format_expr(e),
+            branch => self.format_if_expr_branch(branch),
+        };
+        format!("if {} {} else {}", condition, then_branch, else_branch)
+    }
+
+    fn format_if_expr_branch(&mut self, branch: &SwitchBody) -> String {
+        match branch {
+            SwitchBody::Expr(e) => format!("{{ {} }}", self.format_expr(e)),
+            SwitchBody::Block(stmts) => {
+                let inner: Vec<String> = stmts.iter().map(|s| self.format_stmt_inline(s)).collect();
+                format!("{{ {} }}", inner.join("; "))
+            }
+        }
+    }
+
     fn format_pattern(&mut self, pattern: &Pattern) -> String {
         match pattern {
             Pattern::Literal(lit) => self.format_literal(lit),
@@ -2020,6 +2205,166 @@ pub fn format_source(source: &str, options: &FormatOptions) -> Result<String> {
     Ok(result)
 }
 
+/// Format Liva source code with its imports organized.
+///
+/// All import and re-export statements move into one block where the first
+/// one was. Named imports from the same module are merged, their symbols
+/// sorted and deduplicated; repeated wildcard imports collapse into one.
+/// Plain imports come before re-exports, each group sorted by module path.
+pub fn organize_imports(source: &str, options: &FormatOptions) -> Result<String> {
+    let tokens = lexer::tokenize(source)?;
+    let mut ast = parser::parse(tokens, source)?;
+    organize_program_imports(&mut ast);
+
+    let mut formatter = Formatter::new(options.clone());
+    formatter.format_program(&ast);
+    Ok(reinsert_comments(source, &formatter.output, options))
+}
+
+fn organize_program_imports(program: &mut Program) {
+    let Some(first) = program
+        .items
+        .iter()
+        .position(|item| matches!(item, TopLevel::Import(_)))
+    else {
+        return;
+    };
+
+    let mut imports: Vec<ImportDecl> = Vec::new();
+    let mut rest = Vec::new();
+    for item in std::mem::take(&mut program.items) {
+        match item {
+            TopLevel::Import(decl) => merge_import(&mut imports, decl),
+            other => rest.push(other),
+        }
+    }
+
+    for decl in &mut imports {
+        decl.imports.sort();
+    }
+    imports.sort_by(|a, b| {
+        (a.is_reexport, &a.source, !a.is_wildcard, &a.alias).cmp(&(
+            b.is_reexport,
+            &b.source,
+            !b.is_wildcard,
+            &b.alias,
+        ))
+    });
+
+    // Every item before the first import is a non-import, so `first` is
+    // still the right position in `rest`
+    rest.splice(first..first, imports.into_iter().map(TopLevel::Import));
+    program.items = rest;
+}
+
+/// Add `decl` to `imports`, folding it into an earlier named import of the
+/// same module unless that would rebind one of its symbols
+fn merge_import(imports: &mut Vec<ImportDecl>, decl: ImportDecl) {
+    if decl.is_wildcard {
+        if !imports.contains(&decl) {
+            imports.push(decl);
+        }
+        return;
+    }
+
+    let target = imports.iter_mut().find(|existing| {
+        !existing.is_wildcard
+            && existing.source == decl.source
+            && existing.is_reexport == decl.is_reexport
+            && decl.imports.iter().all(|symbol| {
+                !existing.imports.contains(symbol)
+                    || existing.local_name(symbol) == decl.local_name(symbol)
+            })
+    });
+    let Some(existing) = target else {
+        imports.push(decl);
+        return;
+    };
+
+    for symbol in decl.imports {
+        if existing.imports.contains(&symbol) {
+            continue;
+        }
+        if let Some(local) = decl.renames.get(&symbol) {
+            existing.renames.insert(symbol.clone(), local.clone());
+        }
+        existing.imports.push(symbol);
+    }
+}
+
+/// Indentation string for `level` nesting levels
+pub fn indentation(options: &FormatOptions, level: usize) -> String {
+    " ".repeat(options.indent_size * level)
+}
+
+/// Indentation the formatter would give each line of `source`, worked out
+/// from bracket nesting alone so it also applies to code that doesn't parse,
+/// as while typing. A line is indented one level deeper than the line that
+/// opened its innermost bracket; a line starting with a closing bracket lines
+/// up with that opener. Several brackets opened on one line (`f(x => {`) only
+/// add one level.
+///
+/// `None` for lines that start inside a multi-line string, whose leading
+/// whitespace is part of the string.
+pub fn line_indents(source: &str, options: &FormatOptions) -> Vec<Option<String>> {
+    let mut indents = Vec::new();
+    // Level of the line that opened each unclosed bracket
+    let mut openers: Vec<usize> = Vec::new();
+    let mut in_string = false;
+    let mut level = 0;
+
+    for line in source.split('\n') {
+        if in_string {
+            indents.push(None);
+        } else {
+            let trimmed = line.trim_start();
+            level = if trimmed.starts_with(['}', ')', ']']) {
+                openers.last().copied().unwrap_or(0)
+            } else {
+                openers.last().map_or(0, |opener| opener + 1)
+            };
+            indents.push(Some(indentation(options, level)));
+        }
+
+        let mut chars = line.chars().peekable();
+        let mut escaped = false;
+        while let Some(c) = chars.next() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '\'' => {
+                    // Char literal: skip to its closing quote
+                    let mut escaped = false;
+                    for c in chars.by_ref() {
+                        match c {
+                            _ if escaped => escaped = false,
+                            '\\' => escaped = true,
+                            '\'' => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '/' if chars.peek() == Some(&'/') => break,
+                '{' | '(' | '[' => openers.push(level),
+                '}' | ')' | ']' => {
+                    openers.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    indents
+}
+
 /// Check if the source code is already formatted correctly.
 pub fn check_format(source: &str, options: &FormatOptions) -> Result<bool> {
     let formatted = format_source(source, options)?;
@@ -2083,6 +2428,77 @@ mod tests {
         assert!(output.contains("    age: number"));
     }
 
+    #[test]
+    fn test_format_abstract_class() {
+        let input = "abstract Shape{name:string\nabstract area():number\n}";
+        let output = fmt(input);
+        assert!(output.contains("abstract Shape {"));
+        assert!(output.contains("    abstract area(): number"));
+    }
+
+    #[test]
+    fn test_format_comprehension() {
+        let input = "main(){let a=[x*2 for x in items if x>0]\nlet b=[x for par x in items if x>0]\nlet c=[u.name for u in users]}";
+        let output = fmt(input);
+        for expected in [
+            "let a = [x * 2 for x in items if x > 0]",
+            "let b = [x for par x in items if x > 0]",
+            "let c = [u.name for u in users]",
+        ] {
+            assert!(output.contains(expected), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_format_power() {
+        let input = "main(){let a=2**3**2\nlet b=-x**2\nn**=2}";
+        let output = fmt(input);
+        for expected in ["let a = 2 ** 3 ** 2", "let b = -x ** 2", "n **= 2"] {
+            assert!(output.contains(expected), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_format_bitwise() {
+        let input = "main(){let m=a&~b|c^d\nlet s=x<<2>>1\nflags|=1\nn>>=3}";
+        let output = fmt(input);
+        for expected in [
+            "let m = a & ~b | c ^ d",
+            "let s = x << 2 >> 1",
+            "flags |= 1",
+            "n >>= 3",
+        ] {
+            assert!(output.contains(expected), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_format_contracts() {
+        let input = "@checked\nsqrt(x:float):float requires x>=0 ensures result>=0 {return Math.sqrt(x)}\nabs(n:int):int ensures result>=0=>n<0?-n:n";
+        let output = fmt(input);
+        assert!(
+            output.contains("@checked\nsqrt(x: float): float\n    requires x >= 0\n    ensures result >= 0\n{\n    return Math.sqrt(x)\n}"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("abs(n: int): int\n    ensures result >= 0\n    => n < 0 ? -n : n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_format_delegate() {
+        let input = "Car{engine:Engine\nradio:Radio\ndelegate engine\ndelegate radio{on,off}\nhonk()=>print(\"beep\")\n}";
+        let output = fmt(input);
+        assert!(
+            output.contains("    radio: Radio\n    delegate engine\n    delegate radio { on, off }\n\n    honk()"),
+            "{}",
+            output
+        );
+    }
+
     #[test]
     fn test_format_if_else() {
         let input = "main(){if x > 0{print(\"pos\")} else{print(\"neg\")}}";
@@ -2105,6 +2521,18 @@ mod tests {
         assert_eq!(output, "add(a, b) => a + b\n");
     }
 
+    #[test]
+    fn test_format_lazy_value() {
+        let input = "lazy  CONFIG:Config=loadConfig()\nconst LIMIT=3";
+        let output = fmt(input);
+        assert!(
+            output.contains("lazy CONFIG: Config = loadConfig()\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("const LIMIT = 3\n"), "{}", output);
+    }
+
     #[test]
     fn test_format_for_loop() {
         let input = "main(){for i in 0..10{print(i)}}";
@@ -2306,6 +2734,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_format_import_keeps_aliases_and_reexports() {
+        let input = "import{parse as parseCfg}from\"./config\"\nexport{add}from\"./math\"\nexport*from\"./ops\"";
+        let output = fmt(input);
+        assert!(output.contains("import { parse as parseCfg } from \"./config\""));
+        assert!(output.contains("export { add } from \"./math\""));
+        assert!(output.contains("export * from \"./ops\""));
+    }
+
+    #[test]
+    fn test_organize_imports_merges_sorts_and_dedupes() {
+        let input = "import { sub } from \"./ops\"\nimport { mul, add } from \"./math\"\nexport { sub } from \"./ops\"\nimport { add, div } from \"./math\"\nimport * from \"./util\"\nimport * from \"./util\"\n\nmain() {\n    print(add(1, 2))\n}\n";
+        let output = organize_imports(input, &FormatOptions::default()).unwrap();
+        assert_eq!(
+            output,
+            "import { add, div, mul } from \"./math\"\nimport { sub } from \"./ops\"\nimport * from \"./util\"\nexport { sub } from \"./ops\"\n\nmain() {\n    print(add(1, 2))\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_organize_imports_keeps_conflicting_aliases_apart() {
+        let input =
+            "import { parse } from \"./config\"\nimport { parse as parseCfg } from \"./config\"\n";
+        let output = organize_imports(input, &FormatOptions::default()).unwrap();
+        assert_eq!(
+            output,
+            "import { parse } from \"./config\"\nimport { parse as parseCfg } from \"./config\"\n"
+        );
+    }
+
     #[test]
     fn test_format_groups_consecutive_imports() {
         let input = "import { add } from \"./math.liva\"\nimport { sub } from \"./ops.liva\"\n\nmain() {\n    print(\"hi\")\n}";
@@ -2357,4 +2815,24 @@ mod tests {
             output
         );
     }
+
+    #[test]
+    fn test_line_indents_follow_bracket_nesting() {
+        let source = "main() {\nitems.forEach(x => {\nprint(x)\n})\nlet s = \"a {\nb\"\n}\n";
+        let indents = line_indents(source, &FormatOptions::default());
+        let indents: Vec<Option<&str>> = indents.iter().map(|i| i.as_deref()).collect();
+        assert_eq!(
+            indents,
+            vec![
+                Some(""),
+                Some("    "),
+                Some("        "),
+                Some("    "),
+                Some("    "),
+                None,
+                Some(""),
+                Some(""),
+            ]
+        );
+    }
 }
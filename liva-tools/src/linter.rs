@@ -2,7 +2,7 @@ use colored::Colorize;
 /// Linter module for Liva
 ///
 /// Runs static analysis on the parsed AST to detect code smells and warnings.
-/// Warnings use W-codes (W001-W008) and are non-blocking — compilation proceeds.
+/// Warnings use W-codes (W001-W013) and are non-blocking — compilation proceeds.
 ///
 /// ## Warning Codes
 ///
@@ -14,7 +14,14 @@ use colored::Colorize;
 /// - **W006**: Empty block (if / else / while / for body)
 /// - **W007**: Function parameter declared but never used
 /// - **W008**: Unnecessary `else` after a diverging branch (`return`/`throw`/`fail`/`break`/`continue`)
+/// - **W009**: Array, object or class instance copied with `let b = a`, then one side modified
+/// - **W010**: `else if` chain comparing one value against literals, which reads better as a `switch`
+/// - **W011**: `x // 2` after code, which is Python floor division but a comment in Liva
+/// - **W012**: `%` with a negated operand, whose result differs from Python's floor modulo
+/// - **W013**: Name not in the configured case (`--naming` / `[lint] naming`; off by default)
 use livac::ast::*;
+use livac::lexer::{CommentKind, Token};
+use livac::package::NamingConvention;
 use livac::span::SourceMap;
 use std::collections::{HashMap, HashSet};
 
@@ -85,6 +92,13 @@ impl LintWarning {
     }
 }
 
+/// Settings of the checks that only run when asked for
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    /// W013: the case value names must be written in; `None` skips the check
+    pub naming: Option<NamingConvention>,
+}
+
 /// Linter context that accumulates warnings while walking the AST.
 pub struct Linter {
     warnings: Vec<LintWarning>,
@@ -92,6 +106,7 @@ pub struct Linter {
     source_code: String,
     #[allow(dead_code)]
     source_map: Option<SourceMap>,
+    config: LintConfig,
 }
 
 /// Tracks a variable declaration for unused-variable analysis.
@@ -118,9 +133,16 @@ impl Linter {
             source_file,
             source_code,
             source_map,
+            config: LintConfig::default(),
         }
     }
 
+    /// Run the optional checks `config` turns on as well
+    pub fn with_config(mut self, config: LintConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Run all lint checks on the program and return warnings.
     pub fn lint(&mut self, program: &Program) -> Vec<LintWarning> {
         self.check_unused_imports(program);
@@ -131,6 +153,11 @@ impl Linter {
         self.check_empty_blocks(program);
         self.check_unused_parameters(program);
         self.check_redundant_else(program);
+        self.check_implicit_copies(program);
+        self.check_switch_chains(program);
+        self.check_comment_division(program);
+        self.check_negative_modulo();
+        self.check_naming(program);
         self.warnings.clone()
     }
 
@@ -365,17 +392,16 @@ impl Linter {
                     self.collect_var_decls_block(&for_stmt.body, vars, last_line);
                 }
                 Stmt::If(if_stmt) => {
-                    if let IfBody::Block(b) = &if_stmt.then_branch {
-                        self.collect_var_decls_block(b, vars, last_line);
-                    }
-                    if let Some(IfBody::Block(b)) = &if_stmt.else_branch {
-                        self.collect_var_decls_block(b, vars, last_line);
+                    for body in if_stmt.bodies() {
+                        if let IfBody::Block(b) = body {
+                            self.collect_var_decls_block(b, vars, last_line);
+                        }
                     }
                 }
                 Stmt::While(w) => {
                     self.collect_var_decls_block(&w.body, vars, last_line);
                 }
-                Stmt::Block(b) => {
+                Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
                     self.collect_var_decls_block(b, vars, last_line);
                 }
                 Stmt::TryCatch(tc) => {
@@ -483,6 +509,9 @@ impl Linter {
                 if let Some(or_val) = &decl.or_value {
                     self.collect_var_usages_expr(or_val, used);
                 }
+                if let Some(ResultCombinator::MapErr(map)) = &decl.combinator {
+                    self.collect_var_usages_expr(map, used);
+                }
             }
             Stmt::ConstDecl(decl) => {
                 self.collect_var_usages_expr(&decl.init, used);
@@ -492,13 +521,11 @@ impl Linter {
                 self.collect_var_usages_expr(&assign.value, used);
             }
             Stmt::If(if_stmt) => {
-                self.collect_var_usages_expr(&if_stmt.condition, used);
-                match &if_stmt.then_branch {
-                    IfBody::Block(b) => self.collect_var_usages_block(b, used),
-                    IfBody::Stmt(s) => self.collect_var_usages_stmt(s, used),
+                for (condition, _) in if_stmt.branches() {
+                    self.collect_var_usages_expr(condition, used);
                 }
-                if let Some(else_branch) = &if_stmt.else_branch {
-                    match else_branch {
+                for body in if_stmt.bodies() {
+                    match body {
                         IfBody::Block(b) => self.collect_var_usages_block(b, used),
                         IfBody::Stmt(s) => self.collect_var_usages_stmt(s, used),
                     }
@@ -547,12 +574,23 @@ impl Linter {
             Stmt::Expr(expr_stmt) => {
                 self.collect_var_usages_expr(&expr_stmt.expr, used);
             }
-            Stmt::Block(b) => {
+            Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
                 self.collect_var_usages_block(b, used);
             }
             Stmt::Defer(defer_stmt) => {
                 self.collect_var_usages_stmt(&defer_stmt.body, used);
             }
+            Stmt::Mock(mock) => {
+                self.collect_var_usages_expr(&mock.value, used);
+            }
+            Stmt::Function(func) => {
+                if let Some(body) = &func.body {
+                    self.collect_var_usages_block(body, used);
+                }
+                if let Some(expr) = &func.expr_body {
+                    self.collect_var_usages_expr(expr, used);
+                }
+            }
             Stmt::Break | Stmt::Continue => {}
         }
     }
@@ -658,10 +696,23 @@ impl Linter {
                     }
                 }
             }
+            Expr::If(if_expr) => {
+                self.collect_var_usages_expr(&if_expr.condition, used);
+                for branch in [&if_expr.then_branch, &if_expr.else_branch] {
+                    match branch {
+                        SwitchBody::Expr(e) => self.collect_var_usages_expr(e, used),
+                        SwitchBody::Block(stmts) => {
+                            for s in stmts {
+                                self.collect_var_usages_stmt(s, used);
+                            }
+                        }
+                    }
+                }
+            }
             Expr::MethodRef { object, .. } => {
                 used.insert(object.clone());
             }
-            Expr::Unwrap(inner) => {
+            Expr::Unwrap(inner) | Expr::Cast { expr: inner, .. } => {
                 self.collect_var_usages_expr(inner, used);
             }
             Expr::Try(inner) => {
@@ -891,11 +942,10 @@ impl Linter {
                 }
                 // Recurse into sub-blocks
                 Stmt::If(if_stmt) => {
-                    if let IfBody::Block(b) = &if_stmt.then_branch {
-                        self.check_unreachable_block(b, start_line);
-                    }
-                    if let Some(IfBody::Block(b)) = &if_stmt.else_branch {
-                        self.check_unreachable_block(b, start_line);
+                    for body in if_stmt.bodies() {
+                        if let IfBody::Block(b) = body {
+                            self.check_unreachable_block(b, start_line);
+                        }
                     }
                 }
                 Stmt::While(w) => {
@@ -908,7 +958,7 @@ impl Linter {
                     self.check_unreachable_block(&tc.try_block, start_line);
                     self.check_unreachable_block(&tc.catch_block, start_line);
                 }
-                Stmt::Block(b) => {
+                Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
                     self.check_unreachable_block(b, start_line);
                 }
                 Stmt::Defer(defer_stmt) => {
@@ -917,6 +967,11 @@ impl Linter {
                         self.check_unreachable_block(b, start_line);
                     }
                 }
+                Stmt::Function(func) => {
+                    if let Some(body) = &func.body {
+                        self.check_unreachable_block(body, start_line);
+                    }
+                }
                 _ => {}
             }
         }
@@ -1021,9 +1076,11 @@ impl Linter {
     fn check_always_tf_stmt(&mut self, stmt: &Stmt, start_line: usize) {
         match stmt {
             Stmt::If(if_stmt) => {
-                self.check_always_tf_expr(&if_stmt.condition, start_line);
-                if let IfBody::Block(b) = &if_stmt.then_branch {
-                    self.check_always_tf_block(b, start_line);
+                for (condition, body) in if_stmt.branches() {
+                    self.check_always_tf_expr(condition, start_line);
+                    if let IfBody::Block(b) = body {
+                        self.check_always_tf_block(b, start_line);
+                    }
                 }
                 if let Some(IfBody::Block(b)) = &if_stmt.else_branch {
                     self.check_always_tf_block(b, start_line);
@@ -1047,7 +1104,7 @@ impl Linter {
                     self.check_always_tf_expr(expr, start_line);
                 }
             }
-            Stmt::Block(b) => {
+            Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
                 self.check_always_tf_block(b, start_line);
             }
             Stmt::TryCatch(tc) => {
@@ -1057,6 +1114,11 @@ impl Linter {
             Stmt::Defer(defer_stmt) => {
                 self.check_always_tf_stmt(&defer_stmt.body, start_line);
             }
+            Stmt::Function(func) => {
+                if let Some(body) = &func.body {
+                    self.check_always_tf_block(body, start_line);
+                }
+            }
             _ => {}
         }
     }
@@ -1157,6 +1219,14 @@ impl Linter {
                 self.check_always_tf_expr(then_expr, start_line);
                 self.check_always_tf_expr(else_expr, start_line);
             }
+            Expr::If(if_expr) => {
+                self.check_always_tf_expr(&if_expr.condition, start_line);
+                for branch in [&if_expr.then_branch, &if_expr.else_branch] {
+                    if let SwitchBody::Expr(e) = branch {
+                        self.check_always_tf_expr(e, start_line);
+                    }
+                }
+            }
             Expr::Call(call) => {
                 for arg in &call.args {
                     self.check_always_tf_expr(arg, start_line);
@@ -1276,11 +1346,8 @@ impl Linter {
                 }
             }
             Stmt::If(if_stmt) => {
-                if let IfBody::Block(b) = &if_stmt.then_branch {
-                    self.shadow_walk_block(b, scopes, *last_line);
-                }
-                if let Some(else_branch) = &if_stmt.else_branch {
-                    if let IfBody::Block(b) = else_branch {
+                for body in if_stmt.bodies() {
+                    if let IfBody::Block(b) = body {
                         self.shadow_walk_block(b, scopes, *last_line);
                     }
                 }
@@ -1304,7 +1371,7 @@ impl Linter {
                 self.shadow_walk_block(&for_stmt.body, scopes, *last_line);
                 scopes.pop();
             }
-            Stmt::Block(b) => {
+            Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
                 self.shadow_walk_block(b, scopes, *last_line);
             }
             Stmt::TryCatch(tc) => {
@@ -1414,6 +1481,17 @@ impl Linter {
                         self.empty_walk_block(b, *last_line);
                     }
                 }
+                for link in &if_stmt.else_ifs {
+                    if let IfBody::Block(b) = &link.body {
+                        if b.stmts.is_empty() {
+                            let line = self.find_line_containing("else if", *last_line);
+                            *last_line = line;
+                            self.warn_empty("else if", line);
+                        } else {
+                            self.empty_walk_block(b, *last_line);
+                        }
+                    }
+                }
                 if let Some(IfBody::Block(b)) = &if_stmt.else_branch {
                     if b.stmts.is_empty() {
                         let line = self.find_line_containing("else", *last_line);
@@ -1445,7 +1523,7 @@ impl Linter {
                     self.empty_walk_block(&for_stmt.body, *last_line);
                 }
             }
-            Stmt::Block(b) => {
+            Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
                 if !b.stmts.is_empty() {
                     self.empty_walk_block(b, *last_line);
                 }
@@ -1482,7 +1560,20 @@ impl Linter {
         for item in &program.items {
             match item {
                 TopLevel::Function(f) => {
-                    self.check_params(&f.name, &f.params, f.body.as_ref(), f.expr_body.as_ref());
+                    // A parameter only a contract reads is still used
+                    let contracts: Vec<&Expr> = f
+                        .requires
+                        .iter()
+                        .chain(&f.ensures)
+                        .map(|contract| &contract.condition)
+                        .collect();
+                    self.check_params(
+                        &f.name,
+                        &f.params,
+                        f.body.as_ref(),
+                        f.expr_body.as_ref(),
+                        &contracts,
+                    );
                 }
                 TopLevel::Class(class) => {
                     for member in &class.members {
@@ -1494,6 +1585,7 @@ impl Linter {
                                 &method.params,
                                 method.body.as_ref(),
                                 method.expr_body.as_ref(),
+                                &[],
                             );
                         }
                     }
@@ -1509,6 +1601,7 @@ impl Linter {
         params: &[Param],
         body: Option<&BlockStmt>,
         expr_body: Option<&Expr>,
+        contracts: &[&Expr],
     ) {
         if params.is_empty() {
             return;
@@ -1517,7 +1610,7 @@ impl Linter {
         if let Some(b) = body {
             self.collect_var_usages_block(b, &mut used);
         }
-        if let Some(e) = expr_body {
+        for e in expr_body.into_iter().chain(contracts.iter().copied()) {
             self.collect_var_usages_expr(e, &mut used);
         }
 
@@ -1590,29 +1683,31 @@ impl Linter {
         match stmt {
             Stmt::If(if_stmt) => {
                 // Recurse first so nested if/else are also checked.
-                Self::walk_if_body(self, &if_stmt.then_branch);
-                if let Some(else_branch) = &if_stmt.else_branch {
-                    Self::walk_if_body(self, else_branch);
-
-                    // The diverging-then check: if the then-branch is a block
-                    // that ends with a diverging statement, the else is
-                    // redundant (the else body can be dedented one level).
-                    if let IfBody::Block(b) = &if_stmt.then_branch {
-                        if block_diverges(b) {
-                            let line = self.find_line_containing("else", 0);
-                            self.warn_redundant_else(line);
-                        }
-                    } else if let IfBody::Stmt(s) = &if_stmt.then_branch {
-                        if stmt_diverges(s) {
-                            let line = self.find_line_containing("else", 0);
-                            self.warn_redundant_else(line);
-                        }
+                for body in if_stmt.bodies() {
+                    Self::walk_if_body(self, body);
+                }
+
+                // The diverging-then check: if a branch ends with a diverging
+                // statement, the `else` after it is redundant (the rest of the
+                // chain can be dedented one level).
+                let branches: Vec<&IfBody> = if_stmt.branches().map(|(_, body)| body).collect();
+                let followed = branches.len() - 1 + usize::from(if_stmt.else_branch.is_some());
+                for body in &branches[..followed] {
+                    let diverges = match body {
+                        IfBody::Block(b) => block_diverges(b),
+                        IfBody::Stmt(s) => stmt_diverges(s),
+                    };
+                    if diverges {
+                        let line = self.find_line_containing("else", 0);
+                        self.warn_redundant_else(line);
                     }
                 }
             }
             Stmt::While(w) => self.redundant_else_walk_block(&w.body),
             Stmt::For(f) => self.redundant_else_walk_block(&f.body),
-            Stmt::Block(b) => self.redundant_else_walk_block(b),
+            Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
+                self.redundant_else_walk_block(b)
+            }
             Stmt::TryCatch(tc) => {
                 self.redundant_else_walk_block(&tc.try_block);
                 self.redundant_else_walk_block(&tc.catch_block);
@@ -1647,6 +1742,520 @@ impl Linter {
     }
 }
 
+// ───────────────────────────────────────────────────────────
+// W009: Copied aggregate modified
+// ───────────────────────────────────────────────────────────
+
+/// `let copy = source` between two aggregate bindings, as seen by W009
+struct AggregateCopy {
+    copy: String,
+    source: String,
+    line: usize,
+}
+
+/// Aggregate bindings and the copies made between them in one body
+#[derive(Default)]
+struct CopyTracker {
+    aggregates: HashSet<String>,
+    copies: Vec<AggregateCopy>,
+    /// Indexes into `copies` already warned about
+    reported: HashSet<usize>,
+}
+
+impl Linter {
+    /// `let b = a` copies an array, object or class instance; it doesn't alias
+    /// it. Code written for reference semantics changes one and expects the
+    /// other to follow.
+    fn check_implicit_copies(&mut self, program: &Program) {
+        for item in &program.items {
+            match item {
+                TopLevel::Function(f) => {
+                    if let Some(body) = &f.body {
+                        self.implicit_copies_in(&f.params, body);
+                    }
+                }
+                TopLevel::Class(class) => {
+                    for member in &class.members {
+                        if let Member::Method(m) = member {
+                            if let Some(body) = &m.body {
+                                self.implicit_copies_in(&m.params, body);
+                            }
+                        }
+                    }
+                }
+                TopLevel::Test(t) => self.implicit_copies_in(&[], &t.body),
+                _ => {}
+            }
+        }
+    }
+
+    fn implicit_copies_in(&mut self, params: &[Param], body: &BlockStmt) {
+        let mut tracker = CopyTracker::default();
+        for param in params {
+            if let (Some(name), Some(type_ref)) = (param.name(), &param.type_ref) {
+                if is_aggregate_type(type_ref) {
+                    tracker.aggregates.insert(name.to_string());
+                }
+            }
+        }
+        self.implicit_copies_block(body, &mut tracker);
+    }
+
+    fn implicit_copies_block(&mut self, block: &BlockStmt, tracker: &mut CopyTracker) {
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            let line = block.stmt_lines.get(i).map(|&l| l as usize);
+            self.implicit_copies_stmt(stmt, line, tracker);
+        }
+    }
+
+    fn implicit_copies_stmt(
+        &mut self,
+        stmt: &Stmt,
+        line: Option<usize>,
+        tracker: &mut CopyTracker,
+    ) {
+        match stmt {
+            Stmt::VarDecl(var) => {
+                self.implicit_copies_expr(&var.init, tracker);
+                let [binding] = var.bindings.as_slice() else {
+                    return;
+                };
+                let Some(name) = binding.name() else { return };
+                let is_aggregate = match &var.init {
+                    Expr::Identifier(source) if tracker.aggregates.contains(source) => {
+                        let line = line.unwrap_or_else(|| self.estimate_var_line(name, 0));
+                        tracker.copies.push(AggregateCopy {
+                            copy: name.to_string(),
+                            source: source.clone(),
+                            line,
+                        });
+                        true
+                    }
+                    init => {
+                        is_aggregate_init(init)
+                            || binding.type_ref.as_ref().is_some_and(is_aggregate_type)
+                    }
+                };
+                if is_aggregate {
+                    tracker.aggregates.insert(name.to_string());
+                } else {
+                    tracker.aggregates.remove(name);
+                }
+            }
+            Stmt::Assign(assign) => {
+                self.implicit_copies_expr(&assign.value, tracker);
+                match &assign.target {
+                    Expr::Member { object, .. } | Expr::Index { object, .. } => {
+                        if let Some(name) = base_name(object) {
+                            self.report_copy_mutation(name, tracker);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Stmt::Expr(expr_stmt) => self.implicit_copies_expr(&expr_stmt.expr, tracker),
+            Stmt::Return(ret) => {
+                if let Some(expr) = &ret.expr {
+                    self.implicit_copies_expr(expr, tracker);
+                }
+            }
+            Stmt::If(if_stmt) => {
+                for body in if_stmt.bodies() {
+                    match body {
+                        IfBody::Block(b) => self.implicit_copies_block(b, tracker),
+                        IfBody::Stmt(s) => self.implicit_copies_stmt(s, None, tracker),
+                    }
+                }
+            }
+            Stmt::While(w) => self.implicit_copies_block(&w.body, tracker),
+            Stmt::For(f) => self.implicit_copies_block(&f.body, tracker),
+            Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
+                self.implicit_copies_block(b, tracker)
+            }
+            Stmt::TryCatch(tc) => {
+                self.implicit_copies_block(&tc.try_block, tracker);
+                self.implicit_copies_block(&tc.catch_block, tracker);
+            }
+            _ => {}
+        }
+    }
+
+    /// Mutating method calls, including those inside callbacks like `forEach`
+    fn implicit_copies_expr(&mut self, expr: &Expr, tracker: &mut CopyTracker) {
+        match expr {
+            Expr::MethodCall(mc) => {
+                if is_mutating_method(&mc.method) {
+                    if let Some(name) = base_name(&mc.object) {
+                        self.report_copy_mutation(name, tracker);
+                    }
+                }
+                self.implicit_copies_expr(&mc.object, tracker);
+                for arg in &mc.args {
+                    self.implicit_copies_expr(arg, tracker);
+                }
+            }
+            Expr::Call(call) => {
+                for arg in &call.args {
+                    self.implicit_copies_expr(arg, tracker);
+                }
+            }
+            Expr::Lambda(lambda) => match &lambda.body {
+                LambdaBody::Expr(body) => self.implicit_copies_expr(body, tracker),
+                LambdaBody::Block(block) => self.implicit_copies_block(block, tracker),
+            },
+            _ => {}
+        }
+    }
+
+    /// Warns once per copy when `name` is either side of one
+    fn report_copy_mutation(&mut self, name: &str, tracker: &mut CopyTracker) {
+        for (i, copy) in tracker.copies.iter().enumerate() {
+            if (copy.copy != name && copy.source != name) || !tracker.reported.insert(i) {
+                continue;
+            }
+            let message = if copy.copy == name {
+                format!(
+                    "'{}' is a copy of '{}'; modifying it leaves '{}' unchanged",
+                    copy.copy, copy.source, copy.source
+                )
+            } else {
+                format!(
+                    "'{}' is modified after '{}' was copied from it; '{}' keeps the old value",
+                    copy.source, copy.copy, copy.copy
+                )
+            };
+            self.warnings.push(LintWarning {
+                code: "W009".to_string(),
+                title: "Copied value modified".to_string(),
+                message,
+                file: self.source_file.clone(),
+                line: copy.line,
+                column: None,
+                source_line: self.source_line_at(copy.line),
+                help: Some(format!(
+                    "Make the copy explicit with `let {} = clone({})`, or modify '{}' directly",
+                    copy.copy, copy.source, copy.source
+                )),
+            });
+        }
+    }
+}
+
+// ───────────────────────────────────────────────────────────
+// W010: `else if` chain that could be a switch
+// ───────────────────────────────────────────────────────────
+
+/// Branches an `else if` chain needs before W010 suggests a `switch`
+const SWITCH_CHAIN_MIN_BRANCHES: usize = 3;
+
+impl Linter {
+    fn check_switch_chains(&mut self, program: &Program) {
+        let mut last_line = 0;
+        for item in &program.items {
+            match item {
+                TopLevel::Function(f) => {
+                    if let Some(body) = &f.body {
+                        self.switch_chain_walk_block(body, &mut last_line);
+                    }
+                }
+                TopLevel::Class(class) => {
+                    for member in &class.members {
+                        if let Member::Method(m) = member {
+                            if let Some(body) = &m.body {
+                                self.switch_chain_walk_block(body, &mut last_line);
+                            }
+                        }
+                    }
+                }
+                TopLevel::Test(t) => self.switch_chain_walk_block(&t.body, &mut last_line),
+                _ => {}
+            }
+        }
+    }
+
+    fn switch_chain_walk_block(&mut self, block: &BlockStmt, last_line: &mut usize) {
+        for stmt in &block.stmts {
+            self.switch_chain_walk_stmt(stmt, last_line);
+        }
+    }
+
+    fn switch_chain_walk_stmt(&mut self, stmt: &Stmt, last_line: &mut usize) {
+        match stmt {
+            Stmt::If(if_stmt) => {
+                let line = self.find_if_line(*last_line);
+                *last_line = line;
+                if let Some(subject) = switch_chain_subject(if_stmt) {
+                    self.warn_switch_chain(&subject, if_stmt.else_ifs.len() + 1, line);
+                }
+                for body in if_stmt.bodies() {
+                    match body {
+                        IfBody::Block(b) => self.switch_chain_walk_block(b, last_line),
+                        IfBody::Stmt(s) => self.switch_chain_walk_stmt(s, last_line),
+                    }
+                }
+            }
+            Stmt::While(w) => self.switch_chain_walk_block(&w.body, last_line),
+            Stmt::For(f) => self.switch_chain_walk_block(&f.body, last_line),
+            Stmt::Block(b) | Stmt::Scope(ScopeStmt { body: b }) => {
+                self.switch_chain_walk_block(b, last_line)
+            }
+            Stmt::TryCatch(tc) => {
+                self.switch_chain_walk_block(&tc.try_block, last_line);
+                self.switch_chain_walk_block(&tc.catch_block, last_line);
+            }
+            _ => {}
+        }
+    }
+
+    /// Line of the next statement starting with `if`, skipping `else if` lines
+    fn find_if_line(&self, after_line: usize) -> usize {
+        self.source_code
+            .lines()
+            .enumerate()
+            .skip(after_line)
+            .find(|(_, line)| line.trim_start().starts_with("if "))
+            .map_or(after_line + 1, |(idx, _)| idx + 1)
+    }
+
+    fn warn_switch_chain(&mut self, subject: &str, branches: usize, line: usize) {
+        self.warnings.push(LintWarning {
+            code: "W010".to_string(),
+            title: "If chain could be a switch".to_string(),
+            message: format!(
+                "All {} branches of this `if` chain compare '{}' against a literal",
+                branches, subject
+            ),
+            file: self.source_file.clone(),
+            line,
+            column: None,
+            source_line: self.source_line_at(line),
+            help: Some(format!(
+                "Use `switch {} {{ ... }}` with one arm per value and `_` for the final `else`",
+                subject
+            )),
+        });
+    }
+}
+
+// ───────────────────────────────────────────────────────────
+// W011 / W012: Python division habits
+// ───────────────────────────────────────────────────────────
+
+impl Linter {
+    /// W011: `total // 2` is floor division in Python, but in Liva `//`
+    /// starts a comment and the line goes on with just `total`. A comment
+    /// set apart by more than one space (`2 ** 10   // 1024`) is taken to
+    /// be a note on the result.
+    fn check_comment_division(&mut self, program: &Program) {
+        for comment in &program.comments {
+            let divisor = comment.text.trim();
+            if comment.kind != CommentKind::Line || !is_number(divisor) {
+                continue;
+            }
+            let start = comment.span.start;
+            let before = self.source_code[..start]
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default();
+            let code = before.trim_end();
+            let padding = comment.text.len() - comment.text.trim_start().len();
+            if before.len() - code.len() > 1 || padding > 1 {
+                continue;
+            }
+            if !code.ends_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | ')' | ']')) {
+                continue;
+            }
+            let line = self.line_at_offset(start);
+            self.warnings.push(LintWarning {
+                code: "W011".to_string(),
+                title: "Floor division written as a comment".to_string(),
+                message: format!(
+                    "`//` starts a comment in Liva, so `// {}` is ignored rather than dividing",
+                    divisor
+                ),
+                file: self.source_file.clone(),
+                line,
+                column: None,
+                source_line: self.source_line_at(line),
+                help: Some(format!(
+                    "Use Math.floorDiv(value, {}) to divide rounding down",
+                    divisor
+                )),
+            });
+        }
+    }
+
+    /// W012: `%` keeps the sign of the dividend, as in Rust; Python's takes
+    /// the sign of the divisor. Only shows when an operand is negated
+    /// right there: `-n % 3` or `n % -3`.
+    fn check_negative_modulo(&mut self) {
+        let Ok(tokens) = livac::lexer::tokenize(&self.source_code) else {
+            return;
+        };
+        for (i, percent) in tokens.iter().enumerate() {
+            if percent.token != Token::Percent {
+                continue;
+            }
+            let negative_divisor = tokens.get(i + 1).is_some_and(|t| t.token == Token::Minus);
+            let negative_dividend = i >= 2
+                && tokens[i - 2].token == Token::Minus
+                && matches!(
+                    tokens[i - 1].token,
+                    Token::Ident(_) | Token::IntLiteral(_) | Token::FloatLiteral(_)
+                )
+                && (i == 2 || starts_operand(&tokens[i - 3].token));
+            if !negative_divisor && !negative_dividend {
+                continue;
+            }
+            let line = self.line_at_offset(percent.span.start);
+            self.warnings.push(LintWarning {
+                code: "W012".to_string(),
+                title: "Remainder of a negative number".to_string(),
+                message: "`%` keeps the sign of the left operand: -7 % 3 is -1, not 2 as in Python"
+                    .to_string(),
+                file: self.source_file.clone(),
+                line,
+                column: None,
+                source_line: self.source_line_at(line),
+                help: Some(
+                    "Use Math.floorMod(a, b) for a remainder with the sign of the right operand"
+                        .to_string(),
+                ),
+            });
+        }
+    }
+
+    /// 1-based line of a byte offset into the source
+    fn line_at_offset(&self, offset: usize) -> usize {
+        self.source_code[..offset].matches('\n').count() + 1
+    }
+}
+
+/// A number literal, as the whole text of a comment
+fn is_number(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_digit()) && text.replace('_', "").parse::<f64>().is_ok()
+}
+
+/// Tokens after which a `-` negates the operand that follows instead of
+/// subtracting it
+fn starts_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Assign
+            | Token::PlusAssign
+            | Token::MinusAssign
+            | Token::StarAssign
+            | Token::SlashAssign
+            | Token::PercentAssign
+            | Token::StarStarAssign
+            | Token::AmpAssign
+            | Token::PipeAssign
+            | Token::CaretAssign
+            | Token::ShlAssign
+            | Token::LParen
+            | Token::LBracket
+            | Token::Comma
+            | Token::Colon
+            | Token::Question
+            | Token::Arrow
+            | Token::Return
+    )
+}
+
+/// The expression every condition of a long `else if` chain compares with
+/// `==` against a literal, rendered as source; conditions may join several
+/// such comparisons with `||`
+fn switch_chain_subject(if_stmt: &IfStmt) -> Option<String> {
+    if if_stmt.else_ifs.len() + 1 < SWITCH_CHAIN_MIN_BRANCHES {
+        return None;
+    }
+    let mut subject = None;
+    for (condition, _) in if_stmt.branches() {
+        if !compares_subject(condition, &mut subject) {
+            return None;
+        }
+    }
+    subject.and_then(subject_source)
+}
+
+/// Whether `condition` is `subject == literal` (either way round) or an `||`
+/// of those; the first subject seen is stored in `subject`
+fn compares_subject<'a>(condition: &'a Expr, subject: &mut Option<&'a Expr>) -> bool {
+    let Expr::Binary { op, left, right } = condition else {
+        return false;
+    };
+    match op {
+        BinOp::Or => compares_subject(left, subject) && compares_subject(right, subject),
+        BinOp::Eq => {
+            let operand = match (left.as_ref(), right.as_ref()) {
+                (operand, Expr::Literal(_)) | (Expr::Literal(_), operand) => operand,
+                _ => return false,
+            };
+            match subject {
+                Some(seen) => *seen == operand,
+                None => {
+                    *subject = Some(operand);
+                    true
+                }
+            }
+        }
+        _ => false,
+    }
+}
+
+/// `name` or `a.b.c`; other subjects aren't worth a switch suggestion
+fn subject_source(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Identifier(name) => Some(name.clone()),
+        Expr::Member { object, property } => {
+            subject_source(object).map(|object| format!("{}.{}", object, property))
+        }
+        _ => None,
+    }
+}
+
+/// Arrays, maps, sets and class instances; class names start uppercase
+fn is_aggregate_type(type_ref: &TypeRef) -> bool {
+    match type_ref {
+        TypeRef::Array(_) | TypeRef::Map(_, _) | TypeRef::Set(_) => true,
+        TypeRef::Simple(name) => name.starts_with(|c: char| c.is_ascii_uppercase()),
+        TypeRef::Optional(inner) => is_aggregate_type(inner),
+        _ => false,
+    }
+}
+
+fn is_aggregate_init(init: &Expr) -> bool {
+    match init {
+        Expr::ArrayLiteral(_)
+        | Expr::ObjectLiteral(_)
+        | Expr::StructLiteral { .. }
+        | Expr::MapLiteral(_)
+        | Expr::SetLiteral(_) => true,
+        // Constructor calls: `Point(1, 2)`
+        Expr::Call(call) => matches!(
+            call.callee.as_ref(),
+            Expr::Identifier(name) if name.starts_with(|c: char| c.is_ascii_uppercase())
+        ),
+        _ => false,
+    }
+}
+
+fn is_mutating_method(method: &str) -> bool {
+    matches!(
+        method,
+        "push" | "pop" | "insert" | "removeAt" | "clear" | "set" | "add" | "delete"
+    )
+}
+
+/// The variable at the root of `a`, `a.b` or `a[i].b`
+fn base_name(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Identifier(name) => Some(name),
+        Expr::Member { object, .. } | Expr::Index { object, .. } => base_name(object),
+        _ => None,
+    }
+}
+
 /// Returns true if a block's execution always leaves the enclosing function /
 /// loop (i.e. its last statement is `return`, `throw`, `fail`, `break`, or
 /// `continue`). Used by W008 to detect a redundant `else`.
@@ -1690,13 +2299,288 @@ fn collect_pattern_names(pattern: &BindingPattern) -> Vec<String> {
     out
 }
 
+// ───────────────────────────────────────────────────────────
+// W013: Naming convention
+// ───────────────────────────────────────────────────────────
+
+/// What a name must look like
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NameRule {
+    /// Classes, types, enums and enum variants: PascalCase
+    Type,
+    /// Functions, variables, parameters, fields and methods: the convention
+    Value,
+    /// Constants: the convention, or UPPER_SNAKE_CASE
+    Constant,
+}
+
+impl Linter {
+    /// W013: names that don't follow the configured convention. The
+    /// `_`/`__` visibility prefix doesn't count as part of the name.
+    fn check_naming(&mut self, program: &Program) {
+        let Some(convention) = self.config.naming else {
+            return;
+        };
+        // (what, name, rule, line)
+        let mut names: Vec<(&'static str, String, NameRule, usize)> = Vec::new();
+        for item in &program.items {
+            match item {
+                TopLevel::Function(f) => {
+                    names.push((
+                        "Function",
+                        f.name.clone(),
+                        NameRule::Value,
+                        self.word_line(&f.name),
+                    ));
+                    self.collect_callable_names(&f.name, &f.params, f.body.as_ref(), &mut names);
+                }
+                TopLevel::Class(class) => {
+                    names.push((
+                        "Class",
+                        class.name.clone(),
+                        NameRule::Type,
+                        self.word_line(&class.name),
+                    ));
+                    for member in &class.members {
+                        match member {
+                            Member::Field(field) => names.push((
+                                "Field",
+                                field.name.clone(),
+                                NameRule::Value,
+                                self.word_line(&field.name),
+                            )),
+                            Member::Method(method) => {
+                                if method.name != "constructor" {
+                                    names.push((
+                                        "Method",
+                                        method.name.clone(),
+                                        NameRule::Value,
+                                        self.word_line(&method.name),
+                                    ));
+                                }
+                                self.collect_callable_names(
+                                    &method.name,
+                                    &method.params,
+                                    method.body.as_ref(),
+                                    &mut names,
+                                );
+                            }
+                        }
+                    }
+                }
+                TopLevel::Type(decl) => {
+                    names.push((
+                        "Type",
+                        decl.name.clone(),
+                        NameRule::Type,
+                        self.word_line(&decl.name),
+                    ));
+                }
+                TopLevel::TypeAlias(alias) => {
+                    names.push((
+                        "Type",
+                        alias.name.clone(),
+                        NameRule::Type,
+                        self.word_line(&alias.name),
+                    ));
+                }
+                TopLevel::Enum(decl) => {
+                    names.push((
+                        "Enum",
+                        decl.name.clone(),
+                        NameRule::Type,
+                        self.word_line(&decl.name),
+                    ));
+                    for variant in &decl.variants {
+                        names.push((
+                            "Enum variant",
+                            variant.name.clone(),
+                            NameRule::Type,
+                            self.word_line(&variant.name),
+                        ));
+                    }
+                }
+                TopLevel::ConstDecl(decl) => {
+                    names.push((
+                        "Constant",
+                        decl.name.clone(),
+                        NameRule::Constant,
+                        self.word_line(&decl.name),
+                    ));
+                }
+                TopLevel::Test(test) => {
+                    self.collect_callable_names("", &[], Some(&test.body), &mut names);
+                }
+                _ => {}
+            }
+        }
+
+        for (what, name, rule, line) in names {
+            let Some(expected) = expected_name(&name, rule, convention) else {
+                continue;
+            };
+            let case = match (rule, convention) {
+                (NameRule::Type, _) => "PascalCase",
+                (_, NamingConvention::Camel) => "camelCase",
+                (_, NamingConvention::Snake) => "snake_case",
+            };
+            self.warnings.push(LintWarning {
+                code: "W013".to_string(),
+                title: "Name doesn't follow the naming convention".to_string(),
+                message: format!("{} '{}' is not {}", what, name, case),
+                file: self.source_file.clone(),
+                line,
+                column: None,
+                source_line: self.source_line_at(line),
+                help: Some(format!("Rename it to {}", expected)),
+            });
+        }
+    }
+
+    /// Parameters and local variables of a function, method or test
+    fn collect_callable_names(
+        &self,
+        callable: &str,
+        params: &[Param],
+        body: Option<&BlockStmt>,
+        names: &mut Vec<(&'static str, String, NameRule, usize)>,
+    ) {
+        let callable_line = if callable.is_empty() {
+            0
+        } else {
+            self.word_line(callable)
+        };
+        for param in params {
+            if let Some(name) = param.name() {
+                names.push((
+                    "Parameter",
+                    name.to_string(),
+                    NameRule::Value,
+                    callable_line.max(1),
+                ));
+            }
+        }
+        if let Some(body) = body {
+            let mut vars: HashMap<String, VarInfo> = HashMap::new();
+            self.collect_var_decls_block(body, &mut vars, callable_line);
+            let mut vars: Vec<VarInfo> = vars.into_values().collect();
+            vars.sort_by(|a, b| (a.line, &a.name).cmp(&(b.line, &b.name)));
+            for var in vars {
+                names.push(("Variable", var.name, NameRule::Value, var.line));
+            }
+        }
+    }
+
+    /// 1-based line of the first whole-word occurrence of `name`
+    fn word_line(&self, name: &str) -> usize {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        for (idx, line) in self.source_code.lines().enumerate() {
+            for (start, _) in line.match_indices(name) {
+                let before = line[..start].chars().next_back();
+                let after = line[start + name.len()..].chars().next();
+                if !before.is_some_and(is_word) && !after.is_some_and(is_word) {
+                    return idx + 1;
+                }
+            }
+        }
+        1
+    }
+}
+
+/// `name` rewritten to follow `rule` under `convention`, or `None` when it
+/// already does
+fn expected_name(name: &str, rule: NameRule, convention: NamingConvention) -> Option<String> {
+    let bare = name.trim_start_matches('_');
+    let prefix = &name[..name.len() - bare.len()];
+    if bare.is_empty() || !bare.starts_with(|c: char| c.is_alphabetic()) {
+        return None;
+    }
+    let has_upper = bare.chars().any(|c| c.is_uppercase());
+    let has_lower = bare.chars().any(|c| c.is_lowercase());
+    let follows = match (rule, convention) {
+        (NameRule::Type, _) => bare.starts_with(|c: char| c.is_uppercase()) && !bare.contains('_'),
+        (NameRule::Constant, _) if !has_lower => true,
+        (_, NamingConvention::Camel) => {
+            bare.starts_with(|c: char| c.is_lowercase()) && !bare.contains('_')
+        }
+        (_, NamingConvention::Snake) => !has_upper,
+    };
+    if follows {
+        return None;
+    }
+    let words = name_words(bare);
+    let renamed = match (rule, convention) {
+        (NameRule::Type, _) => words.iter().map(|w| capitalize(w)).collect(),
+        (_, NamingConvention::Camel) => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+            .collect(),
+        (_, NamingConvention::Snake) => words.join("_"),
+    };
+    Some(format!("{}{}", prefix, renamed))
+}
+
+/// The lowercase words of a name in any case: `parseJSONBody` and
+/// `parse_json_body` both give `parse`, `json`, `body`
+fn name_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|j| chars[j]);
+        let next = chars.get(i + 1);
+        // A capital starts a word after a lowercase letter or digit, and
+        // ends an acronym when a lowercase letter follows it
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_ascii_digit()
+                    || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 // ──────────────────────────────────────────────────────────────────
 // Public API
 // ──────────────────────────────────────────────────────────────────
 
 /// Run the linter on a parsed program and return warnings.
 pub fn lint(program: &Program, source_file: &str, source_code: &str) -> Vec<LintWarning> {
-    let mut linter = Linter::new(source_file.to_string(), source_code.to_string());
+    lint_with_config(program, source_file, source_code, &LintConfig::default())
+}
+
+/// [`lint`] with the optional checks `config` turns on
+pub fn lint_with_config(
+    program: &Program,
+    source_file: &str,
+    source_code: &str,
+    config: &LintConfig,
+) -> Vec<LintWarning> {
+    let mut linter =
+        Linter::new(source_file.to_string(), source_code.to_string()).with_config(config.clone());
     linter.lint(program)
 }
 
@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tower_lsp::lsp_types::*;
+
+use livac::ast::{Program, TopLevel};
+
+use super::document::LineIndex;
+
+/// Runs the file's `main()` with `livac run`; arguments: `[path]`
+pub const RUN_COMMAND: &str = "liva.run";
+/// Runs one test block with `livac test --filter`; arguments: `[path, filter]`
+pub const RUN_TEST_COMMAND: &str = "liva.runTest";
+/// Starts a debug session through `livac dap`; arguments: `[path]`. This one
+/// is for the editor extension to handle, the server can't start sessions.
+pub const DEBUG_COMMAND: &str = "liva.debug";
+
+/// "▶ Run" and "Debug" over `main()`, and "▶ Run test" over each `test` block
+pub fn code_lenses(program: &Program, text: &str, uri: &Url) -> Vec<CodeLens> {
+    let Ok(path) = uri.to_file_path() else {
+        return Vec::new();
+    };
+    let path = Value::String(path.to_string_lossy().into_owned());
+    let lines = LineIndex::new(text);
+    let mut lenses = Vec::new();
+
+    for item in &program.items {
+        match item {
+            TopLevel::Function(func) if func.name == "main" => {
+                let Some(span) = func.span else { continue };
+                let range = header_range(&lines, span.start);
+                lenses.push(lens(range, "▶ Run", RUN_COMMAND, vec![path.clone()]));
+                lenses.push(lens(range, "Debug", DEBUG_COMMAND, vec![path.clone()]));
+            }
+            TopLevel::Test(test) => {
+                let Some(span) = test.span else { continue };
+                let filter = livac::codegen::test_function_name(&test.name);
+                lenses.push(lens(
+                    header_range(&lines, span.start),
+                    "▶ Run test",
+                    RUN_TEST_COMMAND,
+                    vec![path.clone(), Value::String(filter)],
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    lenses
+}
+
+/// Command line of `livac` for an `executeCommand` request, or `None` for
+/// commands the server doesn't run itself
+pub fn livac_args(command: &str, arguments: &[Value]) -> Option<Vec<String>> {
+    let path = arguments.first()?.as_str()?.to_string();
+    match command {
+        RUN_COMMAND => Some(vec!["run".to_string(), path]),
+        RUN_TEST_COMMAND => {
+            let filter = arguments.get(1)?.as_str()?.to_string();
+            Some(vec![
+                "test".to_string(),
+                path,
+                "--filter".to_string(),
+                filter,
+            ])
+        }
+        _ => None,
+    }
+}
+
+/// The `livac` binary: `LIVAC_BIN`, then one installed next to this
+/// binary, then `livac` on PATH
+pub fn livac_binary() -> PathBuf {
+    if let Ok(path) = std::env::var("LIVAC_BIN") {
+        return PathBuf::from(path);
+    }
+    let name = if cfg!(windows) { "livac.exe" } else { "livac" };
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(name))
+        .filter(|sibling| sibling.exists())
+        .unwrap_or_else(|| PathBuf::from("livac"))
+}
+
+/// Directory to run a command for `path` in: the file's own
+pub fn working_dir(path: &str) -> &Path {
+    Path::new(path).parent().unwrap_or_else(|| Path::new("."))
+}
+
+fn header_range(lines: &LineIndex, offset: usize) -> Range {
+    let start = lines.position(offset);
+    Range { start, end: start }
+}
+
+fn lens(range: Range, title: &str, command: &str, arguments: Vec<Value>) -> CodeLens {
+    CodeLens {
+        range,
+        command: Some(Command {
+            title: title.to_string(),
+            command: command.to_string(),
+            arguments: Some(arguments),
+        }),
+        data: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lenses_over_main_and_tests() {
+        let source = "helper() => 1\n\nmain() {\n    print(helper())\n}\n\ntest \"Helper returns one\" {\n    assert(helper() == 1)\n}\n";
+        let program =
+            livac::parser::parse(livac::lexer::tokenize(source).unwrap(), source).unwrap();
+        let uri = Url::from_file_path(std::env::temp_dir().join("app.liva")).unwrap();
+
+        let lenses: Vec<(u32, String, Vec<Value>)> = code_lenses(&program, source, &uri)
+            .into_iter()
+            .map(|lens| {
+                let command = lens.command.unwrap();
+                (
+                    lens.range.start.line,
+                    command.title,
+                    command.arguments.unwrap(),
+                )
+            })
+            .collect();
+        let titles: Vec<(u32, &str)> = lenses.iter().map(|(l, t, _)| (*l, t.as_str())).collect();
+        assert_eq!(titles, vec![(2, "▶ Run"), (2, "Debug"), (6, "▶ Run test")]);
+
+        let args = livac_args(RUN_TEST_COMMAND, &lenses[2].2).unwrap();
+        assert_eq!(args[0], "test");
+        assert!(args[1].ends_with("app.liva"));
+        assert_eq!(&args[2..], ["--filter", "test_helper_returns_one"]);
+        assert!(livac_args(DEBUG_COMMAND, &lenses[1].2).is_none());
+    }
+}
@@ -0,0 +1,258 @@
+use tower_lsp::lsp_types::*;
+
+use livac::ast::{Expr, Literal, Program, TopLevel, UnOp};
+use livac::consteval::{self, ConstValue};
+
+use super::document::LineIndex;
+
+/// A constant expression in a document, with its computed value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstantExpr {
+    pub range: Range,
+    /// The expression as written
+    pub text: String,
+    pub value: ConstValue,
+}
+
+impl ConstantExpr {
+    /// Hover contents: the expression and what it evaluates to
+    pub fn hover(&self) -> Hover {
+        Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!(
+                    "```liva\n{}\n```\n\n= `{}` ({})",
+                    self.text,
+                    self.value,
+                    self.value.type_name()
+                ),
+            }),
+            range: Some(self.range),
+        }
+    }
+}
+
+/// The constant expression selected by `range`, or around its start when the
+/// range is empty
+///
+/// Without a selection, the expression is the operand of the `=`, `return`,
+/// `(` or `,` the cursor follows on its line, up to the next `,`, `)` or `{`.
+/// Identifiers resolve to the top-level `const`s of `program`. Plain literals
+/// and expressions that can't be folded give `None`.
+pub fn constant_at(program: Option<&Program>, text: &str, range: Range) -> Option<ConstantExpr> {
+    let lines = LineIndex::new(text);
+    let (start, end) = if range.start == range.end {
+        expression_around(text, lines.offset(range.start))?
+    } else if range.start.line == range.end.line {
+        (lines.offset(range.start), lines.offset(range.end))
+    } else {
+        return None;
+    };
+
+    let raw = &text[start..end];
+    let expression = raw.trim();
+    let start = start + raw.len() - raw.trim_start().len();
+    let expr = parse_expression(expression)?;
+    if is_trivial(&expr) {
+        return None;
+    }
+
+    let constants = program
+        .map(consteval::program_constants)
+        .unwrap_or_default();
+    let value = consteval::evaluate(&expr, &constants)?;
+    Some(ConstantExpr {
+        range: Range {
+            start: lines.position(start),
+            end: lines.position(start + expression.len()),
+        },
+        text: expression.to_string(),
+        value,
+    })
+}
+
+/// Code action replacing the constant expression with its value
+pub fn replace_action(uri: &Url, constant: &ConstantExpr) -> CodeAction {
+    let edit = TextEdit {
+        range: constant.range,
+        new_text: constant.value.to_string(),
+    };
+    let mut changes = std::collections::HashMap::new();
+    changes.insert(uri.clone(), vec![edit]);
+    CodeAction {
+        title: format!("Replace with computed value `{}`", constant.value),
+        kind: Some(CodeActionKind::REFACTOR_REWRITE),
+        edit: Some(WorkspaceEdit {
+            changes: Some(changes),
+            document_changes: None,
+            change_annotations: None,
+        }),
+        is_preferred: Some(false),
+        ..Default::default()
+    }
+}
+
+/// Byte range of the expression operand around `offset`, within its line
+fn expression_around(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    let line = &text[line_start..line_end];
+    let cursor = offset - line_start;
+    let bytes = line.as_bytes();
+
+    // Operand starts, one per open bracket, reset by separators
+    let mut starts = vec![0];
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line[..cursor].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        let next = bytes.get(i + 1).copied();
+        let prev = i.checked_sub(1).map(|p| bytes[p]);
+        match c {
+            '"' => in_string = true,
+            '/' if next == Some(b'/') => return None,
+            '(' | '[' | '{' => starts.push(i + 1),
+            ')' | ']' | '}' if starts.len() > 1 => {
+                starts.pop();
+            }
+            ',' | ';' => *starts.last_mut()? = i + 1,
+            // `=` and `=>` start an operand; `==`, `<=`, `>=` and `!=` don't
+            '=' if !matches!(next, Some(b'='))
+                && !matches!(prev, Some(b'=' | b'<' | b'>' | b'!')) =>
+            {
+                let skip = if next == Some(b'>') { 2 } else { 1 };
+                *starts.last_mut()? = i + skip;
+            }
+            _ => {}
+        }
+    }
+
+    let mut end = line.len();
+    let mut depth = 0usize;
+    for (i, c) in line[cursor..].char_indices() {
+        let at = cursor + i;
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '/' if bytes.get(at + 1) == Some(&b'/') => {
+                end = at;
+                break;
+            }
+            '(' | '[' => depth += 1,
+            ')' | ']' if depth > 0 => depth -= 1,
+            ')' | ']' | '{' | '}' | ',' | ';' if depth == 0 => {
+                end = at;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let mut start = *starts.last()?;
+    // Keywords in front of the operand aren't part of it
+    let operand = &line[start..end];
+    let lead = operand.len() - operand.trim_start().len();
+    for keyword in ["return ", "if ", "while "] {
+        if operand[lead..].starts_with(keyword) {
+            start += lead + keyword.len();
+        }
+    }
+    (start < end).then_some((line_start + start, line_start + end))
+}
+
+/// Parses `source` as a single expression
+fn parse_expression(source: &str) -> Option<Expr> {
+    let wrapped = format!("const __liva_eval = {}\n", source);
+    let tokens = livac::lexer::tokenize(&wrapped).ok()?;
+    let program = livac::parser::parse(tokens, &wrapped).ok()?;
+    match program.items.as_slice() {
+        [TopLevel::ConstDecl(decl)] => Some(decl.init.clone()),
+        _ => None,
+    }
+}
+
+/// Expressions whose value is already spelled out
+fn is_trivial(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(_) | Expr::Identifier(_) => true,
+        Expr::Unary {
+            op: UnOp::Neg,
+            operand,
+        } => matches!(
+            operand.as_ref(),
+            Expr::Literal(Literal::Int(_) | Literal::Float(_))
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "const KB = 1024\n\nmain() {\n    let size = KB * 3 + 1\n    print(2 * 5, \"a, b\")\n    if size > 2 * KB {\n        return -1\n    }\n}\n";
+
+    fn program() -> Program {
+        livac::parser::parse(livac::lexer::tokenize(SOURCE).unwrap(), SOURCE).unwrap()
+    }
+
+    fn at(line: u32, character: u32) -> Option<(String, String)> {
+        let position = Position { line, character };
+        let range = Range {
+            start: position,
+            end: position,
+        };
+        constant_at(Some(&program()), SOURCE, range).map(|c| (c.text, c.value.to_string()))
+    }
+
+    #[test]
+    fn test_evaluates_expression_around_cursor() {
+        assert_eq!(
+            at(3, 22),
+            Some(("KB * 3 + 1".to_string(), "3073".to_string()))
+        );
+        assert_eq!(at(4, 13), Some(("2 * 5".to_string(), "10".to_string())));
+        // `size` is a variable, `-1` and `"a, b"` are already literals
+        assert_eq!(at(5, 18), None);
+        assert_eq!(at(6, 16), None);
+        assert_eq!(at(4, 19), None);
+    }
+
+    #[test]
+    fn test_selection_and_replace_action() {
+        let range = Range {
+            start: Position {
+                line: 5,
+                character: 14,
+            },
+            end: Position {
+                line: 5,
+                character: 20,
+            },
+        };
+        let constant = constant_at(Some(&program()), SOURCE, range).unwrap();
+        assert_eq!(constant.value, ConstValue::Int(2048));
+
+        let uri = Url::parse("file:///sizes.liva").unwrap();
+        let action = replace_action(&uri, &constant);
+        let edits = &action.edit.unwrap().changes.unwrap()[&uri];
+        assert_eq!(edits[0].range, range);
+        assert_eq!(edits[0].new_text, "2048");
+    }
+}
@@ -5,6 +5,21 @@ use super::imports::ImportInfo;
 use super::symbols::SymbolTable;
 use livac::ast::Program;
 
+/// Result of the last analysis of a document that finished without errors
+pub struct AnalysisSnapshot {
+    /// Document version the snapshot was built from
+    pub version: i32,
+
+    /// Semantically analyzed AST
+    pub ast: Program,
+
+    /// Symbol table extracted from `ast`
+    pub symbols: SymbolTable,
+
+    /// Import declarations extracted from `ast`
+    pub imports: Vec<ImportInfo>,
+}
+
 /// State of an open document
 pub struct DocumentState {
     /// Full text content
@@ -13,14 +28,12 @@ pub struct DocumentState {
     /// Document version (increments on change)
     pub version: i32,
 
-    /// Parsed AST (cached)
+    /// AST of the current text; only partial while it has parse errors
     pub ast: Option<Program>,
 
-    /// Symbol table (extracted from AST)
-    pub symbols: Option<SymbolTable>,
-
-    /// Import declarations (extracted from AST)
-    pub imports: Vec<ImportInfo>,
+    /// Last successful analysis, kept while the buffer has errors so hover,
+    /// completion and navigation keep working mid-edit
+    pub analysis: Option<AnalysisSnapshot>,
 
     /// Current diagnostics
     pub diagnostics: Vec<Diagnostic>,
@@ -36,13 +49,32 @@ impl DocumentState {
             text,
             version,
             ast: None,
-            symbols: None,
-            imports: Vec::new(),
+            analysis: None,
             diagnostics: Vec::new(),
             last_parsed: Instant::now(),
         }
     }
 
+    /// Symbol table of the last successful analysis
+    pub fn symbols(&self) -> Option<&SymbolTable> {
+        self.analysis.as_ref().map(|analysis| &analysis.symbols)
+    }
+
+    /// Imports of the last successful analysis
+    pub fn imports(&self) -> &[ImportInfo] {
+        self.analysis
+            .as_ref()
+            .map(|analysis| analysis.imports.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// True when the cached analysis was built from an older version of the text
+    pub fn is_analysis_stale(&self) -> bool {
+        self.analysis
+            .as_ref()
+            .is_some_and(|analysis| analysis.version != self.version)
+    }
+
     /// Gets word at cursor position
     pub fn word_at_position(&self, position: Position) -> Option<String> {
         let line_idx = position.line as usize;
@@ -77,3 +109,61 @@ impl DocumentState {
         }
     }
 }
+
+/// Converts between byte offsets and LSP positions (UTF-16 columns)
+pub(crate) struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    pub(crate) fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character: usize = self.text[self.line_starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        Position {
+            line: line as u32,
+            character: character as u32,
+        }
+    }
+
+    /// Byte offset of `position`, clamped to its line
+    pub(crate) fn offset(&self, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let line = self.line(position.line as usize);
+        let mut units = 0;
+        for (offset, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return line_start + offset;
+            }
+            units += c.len_utf16();
+        }
+        line_start + line.len()
+    }
+
+    /// Text of line `line`, without its line break
+    pub(crate) fn line(&self, line: usize) -> &'a str {
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        self.text[start..end].trim_end_matches('\r')
+    }
+
+    pub(crate) fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line]
+    }
+}
@@ -0,0 +1,529 @@
+use tower_lsp::lsp_types::*;
+
+use livac::ast::{ClassDecl, Member, Program, TopLevel};
+
+use super::workspace::{locate_class_header, locate_method};
+
+/// What one file contributes to the call and type hierarchies
+///
+/// Built from the analyzed AST: the call edges come from
+/// `Program::call_graph`, positions from scanning the source, since functions
+/// and classes carry no spans yet.
+#[derive(Debug, Clone, Default)]
+pub struct FileHierarchy {
+    pub callables: Vec<Callable>,
+    pub calls: Vec<Call>,
+    pub types: Vec<TypeNode>,
+}
+
+/// A top-level function or class method
+#[derive(Debug, Clone)]
+pub struct Callable {
+    /// `name` for functions, `Class.name` for methods
+    pub qualified: String,
+    pub kind: SymbolKind,
+    /// From the declaration to the line before the next declaration
+    pub range: Range,
+    pub selection_range: Range,
+}
+
+/// `caller` calls `callee` at `ranges`, both qualified like [`Callable`]
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub caller: String,
+    pub callee: String,
+    pub ranges: Vec<Range>,
+}
+
+/// A class or interface and the interfaces it implements
+#[derive(Debug, Clone)]
+pub struct TypeNode {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub implements: Vec<String>,
+    pub selection_range: Range,
+}
+
+impl Callable {
+    fn short_name(&self) -> &str {
+        short_name(&self.qualified)
+    }
+
+    fn item(&self, uri: &Url) -> CallHierarchyItem {
+        CallHierarchyItem {
+            name: self.short_name().to_string(),
+            kind: self.kind,
+            tags: None,
+            detail: self
+                .qualified
+                .rsplit_once('.')
+                .map(|(owner, _)| owner.to_string()),
+            uri: uri.clone(),
+            range: self.range,
+            selection_range: self.selection_range,
+            data: Some(serde_json::Value::String(self.qualified.clone())),
+        }
+    }
+}
+
+impl TypeNode {
+    fn item(&self, uri: &Url) -> TypeHierarchyItem {
+        TypeHierarchyItem {
+            name: self.name.clone(),
+            kind: self.kind,
+            tags: None,
+            detail: (!self.implements.is_empty()).then(|| self.implements.join(", ")),
+            uri: uri.clone(),
+            range: self.selection_range,
+            selection_range: self.selection_range,
+            data: None,
+        }
+    }
+}
+
+impl FileHierarchy {
+    pub fn from_ast(program: &Program, source: &str) -> Self {
+        let mut hierarchy = Self::default();
+
+        for item in &program.items {
+            match item {
+                TopLevel::Function(func) => {
+                    if let Some(selection) = locate_function(&func.name, source) {
+                        hierarchy.push_callable(func.name.clone(), SymbolKind::FUNCTION, selection);
+                    }
+                }
+                TopLevel::Class(class) => {
+                    let Some(selection) = locate_class_header(&class.name, source) else {
+                        continue;
+                    };
+                    hierarchy.types.push(TypeNode {
+                        name: class.name.clone(),
+                        kind: if is_interface(class) {
+                            SymbolKind::INTERFACE
+                        } else {
+                            SymbolKind::CLASS
+                        },
+                        implements: class.implements.clone(),
+                        selection_range: selection,
+                    });
+                    for member in &class.members {
+                        let Member::Method(method) = member else {
+                            continue;
+                        };
+                        if method.body.is_none() && method.expr_body.is_none() {
+                            continue;
+                        }
+                        if let Some(selection) = locate_method(&class.name, &method.name, source) {
+                            hierarchy.push_callable(
+                                format!("{}.{}", class.name, method.name),
+                                SymbolKind::METHOD,
+                                selection,
+                            );
+                        }
+                    }
+                }
+                TopLevel::Type(type_decl) => {
+                    if let Some(selection) = locate_type_decl(&type_decl.name, source) {
+                        hierarchy.types.push(TypeNode {
+                            name: type_decl.name.clone(),
+                            kind: SymbolKind::INTERFACE,
+                            implements: Vec::new(),
+                            selection_range: selection,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        hierarchy.close_ranges(source);
+
+        let lines: Vec<&str> = source.lines().collect();
+        for edge in &program.call_graph {
+            let Some(caller) = hierarchy.callable(&edge.caller) else {
+                continue;
+            };
+            let ranges = call_ranges(&lines, caller, &edge.callee);
+            hierarchy.calls.push(Call {
+                caller: edge.caller.clone(),
+                callee: edge.callee.clone(),
+                ranges,
+            });
+        }
+
+        hierarchy
+    }
+
+    fn push_callable(&mut self, qualified: String, kind: SymbolKind, selection: Range) {
+        self.callables.push(Callable {
+            qualified,
+            kind,
+            range: selection,
+            selection_range: selection,
+        });
+    }
+
+    /// Extend every callable's range to the line before the next declaration
+    fn close_ranges(&mut self, source: &str) {
+        let mut starts: Vec<u32> = self
+            .callables
+            .iter()
+            .map(|c| c.selection_range.start.line)
+            .chain(self.types.iter().map(|t| t.selection_range.start.line))
+            .collect();
+        starts.sort_unstable();
+        starts.dedup();
+
+        let lines: Vec<&str> = source.lines().collect();
+        for callable in &mut self.callables {
+            let start = callable.selection_range.start.line;
+            let next = starts
+                .iter()
+                .copied()
+                .find(|&line| line > start)
+                .unwrap_or(lines.len() as u32);
+            // Trailing blank lines belong to nobody
+            let mut last = next.saturating_sub(1).max(start);
+            while last > start && lines[last as usize].trim().is_empty() {
+                last -= 1;
+            }
+            callable.range = Range {
+                start: Position {
+                    line: start,
+                    character: callable.selection_range.start.character,
+                },
+                end: Position {
+                    line: last,
+                    character: lines.get(last as usize).map_or(0, |l| l.len()) as u32,
+                },
+            };
+        }
+    }
+
+    fn callable(&self, qualified: &str) -> Option<&Callable> {
+        self.callables.iter().find(|c| c.qualified == qualified)
+    }
+}
+
+/// Call hierarchy entries for the name under the cursor: declarations in the
+/// current file when there are any, otherwise anywhere in the workspace
+pub fn prepare_call_hierarchy(
+    files: &[(Url, FileHierarchy)],
+    uri: &Url,
+    word: &str,
+) -> Vec<CallHierarchyItem> {
+    let mut found: Vec<CallHierarchyItem> = files
+        .iter()
+        .flat_map(|(file_uri, file)| {
+            file.callables
+                .iter()
+                .filter(|c| c.short_name() == word)
+                .map(move |c| c.item(file_uri))
+        })
+        .collect();
+    if found.iter().any(|item| item.uri == *uri) {
+        found.retain(|item| item.uri == *uri);
+    }
+    found
+}
+
+/// Every function or method, in any file, that calls `item`
+pub fn incoming_calls(
+    files: &[(Url, FileHierarchy)],
+    item: &CallHierarchyItem,
+) -> Vec<CallHierarchyIncomingCall> {
+    let target = qualified_name(item);
+    let mut incoming = Vec::new();
+
+    for (uri, file) in files {
+        for call in file.calls.iter().filter(|call| call.callee == target) {
+            if let Some(caller) = file.callable(&call.caller) {
+                incoming.push(CallHierarchyIncomingCall {
+                    from: caller.item(uri),
+                    from_ranges: call.ranges.clone(),
+                });
+            }
+        }
+    }
+
+    incoming
+}
+
+/// Every function or method `item` calls, resolved in its own file first
+pub fn outgoing_calls(
+    files: &[(Url, FileHierarchy)],
+    item: &CallHierarchyItem,
+) -> Vec<CallHierarchyOutgoingCall> {
+    let source = qualified_name(item);
+    let Some((_, file)) = files.iter().find(|(uri, _)| *uri == item.uri) else {
+        return Vec::new();
+    };
+
+    file.calls
+        .iter()
+        .filter(|call| call.caller == source)
+        .filter_map(|call| {
+            let to = file
+                .callable(&call.callee)
+                .map(|callee| callee.item(&item.uri))
+                .or_else(|| {
+                    files.iter().find_map(|(uri, other)| {
+                        other.callable(&call.callee).map(|callee| callee.item(uri))
+                    })
+                })?;
+            Some(CallHierarchyOutgoingCall {
+                to,
+                from_ranges: call.ranges.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Type hierarchy entries for the class or interface named `word`, preferring
+/// the current file
+pub fn prepare_type_hierarchy(
+    files: &[(Url, FileHierarchy)],
+    uri: &Url,
+    word: &str,
+) -> Vec<TypeHierarchyItem> {
+    let mut found: Vec<TypeHierarchyItem> = files
+        .iter()
+        .flat_map(|(file_uri, file)| {
+            file.types
+                .iter()
+                .filter(|t| t.name == word)
+                .map(move |t| t.item(file_uri))
+        })
+        .collect();
+    if found.iter().any(|item| item.uri == *uri) {
+        found.retain(|item| item.uri == *uri);
+    }
+    found
+}
+
+/// The interfaces `item` implements
+pub fn supertypes(
+    files: &[(Url, FileHierarchy)],
+    item: &TypeHierarchyItem,
+) -> Vec<TypeHierarchyItem> {
+    let Some(node) = files
+        .iter()
+        .filter(|(uri, _)| *uri == item.uri)
+        .flat_map(|(_, file)| file.types.iter())
+        .find(|t| t.name == item.name)
+    else {
+        return Vec::new();
+    };
+
+    node.implements
+        .iter()
+        .filter_map(|parent| {
+            files.iter().find_map(|(uri, file)| {
+                file.types
+                    .iter()
+                    .find(|t| t.name == *parent)
+                    .map(|t| t.item(uri))
+            })
+        })
+        .collect()
+}
+
+/// The classes that implement `item`
+pub fn subtypes(
+    files: &[(Url, FileHierarchy)],
+    item: &TypeHierarchyItem,
+) -> Vec<TypeHierarchyItem> {
+    files
+        .iter()
+        .flat_map(|(uri, file)| {
+            file.types
+                .iter()
+                .filter(|t| t.implements.contains(&item.name))
+                .map(move |t| t.item(uri))
+        })
+        .collect()
+}
+
+fn qualified_name(item: &CallHierarchyItem) -> String {
+    item.data
+        .as_ref()
+        .and_then(|data| data.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| item.name.clone())
+}
+
+fn short_name(qualified: &str) -> &str {
+    qualified.rsplit('.').next().unwrap_or(qualified)
+}
+
+/// An interface is a class with neither fields nor method bodies
+fn is_interface(class: &ClassDecl) -> bool {
+    !class.members.is_empty()
+        && class.members.iter().all(|member| match member {
+            Member::Method(method) => method.body.is_none() && method.expr_body.is_none(),
+            Member::Field(_) => false,
+        })
+}
+
+/// Where `callee` is called inside `caller`'s range. Method callees match
+/// `.name(`; function callees match `name(` not preceded by `.`.
+fn call_ranges(lines: &[&str], caller: &Callable, callee: &str) -> Vec<Range> {
+    let is_method = callee.contains('.');
+    let name = short_name(callee);
+    let needle = format!("{}(", name);
+    let mut ranges = Vec::new();
+
+    for line_idx in caller.range.start.line..=caller.range.end.line {
+        let Some(line) = lines.get(line_idx as usize) else {
+            break;
+        };
+        let code = line.split("//").next().unwrap_or("");
+        let mut from = 0;
+        while let Some(pos) = code[from..].find(&needle) {
+            let at = from + pos;
+            from = at + needle.len();
+            let before = code[..at].chars().next_back();
+            let matches = if is_method {
+                before == Some('.')
+            } else {
+                !before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.')
+            };
+            // The declaration of a recursive function is not a call
+            let is_declaration = line_idx == caller.selection_range.start.line
+                && at as u32 == caller.selection_range.start.character;
+            if matches && !is_declaration {
+                ranges.push(Range {
+                    start: Position {
+                        line: line_idx,
+                        character: at as u32,
+                    },
+                    end: Position {
+                        line: line_idx,
+                        character: (at + name.len()) as u32,
+                    },
+                });
+            }
+        }
+    }
+
+    ranges
+}
+
+/// Range of the name of a top-level function declaration: `name(` or
+/// `name<` at the start of a line
+fn locate_function(name: &str, source: &str) -> Option<Range> {
+    source.lines().enumerate().find_map(|(line_idx, line)| {
+        let rest = line.strip_prefix(name)?;
+        matches!(rest.chars().next(), Some('(') | Some('<')).then(|| Range {
+            start: Position {
+                line: line_idx as u32,
+                character: 0,
+            },
+            end: Position {
+                line: line_idx as u32,
+                character: name.len() as u32,
+            },
+        })
+    })
+}
+
+/// Range of the name in a `type Name { ... }` interface declaration
+fn locate_type_decl(name: &str, source: &str) -> Option<Range> {
+    let header = format!("type {}", name);
+    source.lines().enumerate().find_map(|(line_idx, line)| {
+        let col = line.find(&header)?;
+        let next = line[col + header.len()..].chars().next();
+        matches!(next, Some(' ') | Some('{') | Some('<')).then(|| Range {
+            start: Position {
+                line: line_idx as u32,
+                character: (col + 5) as u32,
+            },
+            end: Position {
+                line: line_idx as u32,
+                character: (col + header.len()) as u32,
+            },
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Type nodes don't need analysis, and analyzing a file on its own would
+    /// reject interfaces it imports
+    fn index(files: &[(&str, &str)], analyze: bool) -> Vec<(Url, FileHierarchy)> {
+        files
+            .iter()
+            .map(|(name, source)| {
+                let tokens = livac::lexer::tokenize(source).unwrap();
+                let mut program = livac::parser::parse(tokens, source).unwrap();
+                if analyze {
+                    program = livac::semantic::analyze(program).unwrap();
+                }
+                let uri = Url::parse(&format!("file:///tmp/{}", name)).unwrap();
+                (uri, FileHierarchy::from_ast(&program, source))
+            })
+            .collect()
+    }
+
+    const SOURCE: &str = "square(x: number): number => x * x\n\nCounter {\n    count: number\n\n    bump() {\n        this.count = square(this.count)\n    }\n}\n\nmain() {\n    let c: Counter = Counter(1)\n    c.bump()\n    print(square(3))\n}\n";
+
+    #[test]
+    fn test_incoming_and_outgoing_calls() {
+        let files = index(&[("main.liva", SOURCE)], true);
+        let uri = &files[0].0;
+
+        let square = prepare_call_hierarchy(&files, uri, "square");
+        assert_eq!(square.len(), 1);
+        let callers: Vec<(String, Vec<u32>)> = incoming_calls(&files, &square[0])
+            .into_iter()
+            .map(|call| {
+                (
+                    call.from.name,
+                    call.from_ranges.iter().map(|r| r.start.line).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            callers,
+            vec![
+                ("bump".to_string(), vec![6]),
+                ("main".to_string(), vec![13])
+            ]
+        );
+
+        let main = prepare_call_hierarchy(&files, uri, "main");
+        let callees: Vec<(String, Option<String>)> = outgoing_calls(&files, &main[0])
+            .into_iter()
+            .map(|call| (call.to.name, call.to.detail))
+            .collect();
+        assert_eq!(
+            callees,
+            vec![
+                ("bump".to_string(), Some("Counter".to_string())),
+                ("square".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_hierarchy_across_files() {
+        let shapes = "Shape {\n    area(): number\n}\n";
+        let circle = "import { Shape } from \"./shapes.liva\"\n\nCircle : Shape {\n    r: number\n    area(): number => this.r * this.r\n}\n";
+        let files = index(&[("shapes.liva", shapes), ("circle.liva", circle)], false);
+
+        let shape = prepare_type_hierarchy(&files, &files[0].0, "Shape");
+        assert_eq!(shape[0].kind, SymbolKind::INTERFACE);
+        let subs: Vec<String> = subtypes(&files, &shape[0])
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(subs, vec!["Circle"]);
+
+        let circle = prepare_type_hierarchy(&files, &files[1].0, "Circle");
+        let supers = supertypes(&files, &circle[0]);
+        assert_eq!(supers.len(), 1);
+        assert_eq!(supers[0].uri, files[0].0);
+    }
+}
@@ -132,6 +132,32 @@ impl ImportResolver {
         None
     }
 
+    /// Path to write in an import statement in `from` to reach `to`,
+    /// relative to `from`'s directory: `"./math.liva"`, `"../lib/util.liva"`
+    pub fn relative_import_path(&self, from: &Url, to: &Url) -> Option<String> {
+        let from_path = from.to_file_path().ok()?;
+        let to_path = to.to_file_path().ok()?;
+        let from_dir: Vec<_> = from_path.parent()?.components().collect();
+        let to_parts: Vec<_> = to_path.components().collect();
+
+        let common = from_dir
+            .iter()
+            .zip(&to_parts)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut parts: Vec<String> = vec!["..".to_string(); from_dir.len() - common];
+        if parts.is_empty() {
+            parts.push(".".to_string());
+        }
+        parts.extend(
+            to_parts[common..]
+                .iter()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+        );
+        Some(parts.join("/"))
+    }
+
     /// Check if a symbol is imported in a list of imports
     pub fn is_symbol_imported(&self, symbol_name: &str, imports: &[ImportInfo]) -> bool {
         for import in imports {
@@ -156,6 +182,36 @@ impl ImportResolver {
     }
 }
 
+/// Line right after the last top-level import or re-export statement, where
+/// a new import goes; `None` when the file has no imports
+pub fn import_insertion_line(text: &str) -> Option<u32> {
+    let mut end = None;
+    let mut in_braces = false;
+
+    for (idx, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if in_braces {
+            if trimmed.contains('}') {
+                in_braces = false;
+                end = Some(idx as u32 + 1);
+            }
+            continue;
+        }
+        let is_import = line == trimmed
+            && (trimmed.starts_with("import ")
+                || trimmed.starts_with("import{")
+                || trimmed.starts_with("export {")
+                || trimmed.starts_with("export{")
+                || trimmed.starts_with("export *"));
+        if is_import {
+            in_braces = trimmed.contains('{') && !trimmed.contains('}');
+            end = Some(idx as u32 + 1);
+        }
+    }
+
+    end
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,4 +257,31 @@ mod tests {
         let resolved_uri = resolved.unwrap();
         assert_eq!(resolved_uri.path(), "/workspace/math.liva");
     }
+
+    #[test]
+    #[cfg(unix)] // file:///workspace/... URIs require Unix-style paths
+    fn test_relative_import_path() {
+        let resolver = ImportResolver::new(vec![]);
+        let main = Url::parse("file:///workspace/src/main.liva").unwrap();
+
+        let sibling = Url::parse("file:///workspace/src/math.liva").unwrap();
+        assert_eq!(
+            resolver.relative_import_path(&main, &sibling).as_deref(),
+            Some("./math.liva")
+        );
+
+        let nested = Url::parse("file:///workspace/lib/util/strings.liva").unwrap();
+        assert_eq!(
+            resolver.relative_import_path(&main, &nested).as_deref(),
+            Some("../lib/util/strings.liva")
+        );
+    }
+
+    #[test]
+    fn test_import_insertion_line() {
+        assert_eq!(import_insertion_line("main() {}\n"), None);
+
+        let text = "// header\nimport { a } from \"./a\"\nimport {\n    b,\n    c,\n} from \"./b\"\n\nmain() {}\n";
+        assert_eq!(import_insertion_line(text), Some(6));
+    }
 }
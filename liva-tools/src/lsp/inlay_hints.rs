@@ -0,0 +1,223 @@
+use tower_lsp::lsp_types::*;
+
+use livac::ast::{FunctionDecl, Program, TopLevel};
+
+use super::document::LineIndex;
+
+/// Computes the inlay hints of a document that fall inside `range`
+///
+/// - `: T` after unannotated `let` bindings and lambda parameters, from the
+///   types semantic analysis recorded in `Program::inferred_types`
+/// - `name:` before the arguments of calls to top-level functions declared
+///   in the same file, unless the argument already spells the name
+///
+/// `program` must be the analyzed AST of exactly `text`; byte spans are
+/// converted to positions against it.
+pub fn compute_inlay_hints(program: &Program, text: &str, range: Range) -> Vec<InlayHint> {
+    let lines = LineIndex::new(text);
+    let mut hints = Vec::new();
+
+    for inferred in &program.inferred_types {
+        hints.push(InlayHint {
+            position: lines.position(inferred.span.end),
+            label: InlayHintLabel::String(format!(": {}", inferred.type_ref)),
+            kind: Some(InlayHintKind::TYPE),
+            text_edits: None,
+            tooltip: None,
+            padding_left: Some(false),
+            padding_right: Some(false),
+            data: None,
+        });
+    }
+
+    for item in &program.items {
+        if let TopLevel::Function(func) = item {
+            parameter_hints(func, text, &lines, &mut hints);
+        }
+    }
+
+    hints.retain(|hint| in_range(hint.position, range));
+    hints.sort_by_key(|hint| (hint.position.line, hint.position.character));
+    hints
+}
+
+fn parameter_hints(func: &FunctionDecl, text: &str, lines: &LineIndex, hints: &mut Vec<InlayHint>) {
+    let names: Vec<Option<&str>> = func.params.iter().map(|p| p.name()).collect();
+    if names.iter().all(Option::is_none) {
+        return;
+    }
+
+    for open_paren in call_sites(&func.name, text) {
+        for (arg, name) in call_arguments(text, open_paren).into_iter().zip(&names) {
+            let Some(name) = name else { continue };
+            let arg_text = &text[arg.0..arg.1];
+            if arg_text == *name || arg_text.ends_with(&format!(".{}", name)) {
+                continue;
+            }
+            hints.push(InlayHint {
+                position: lines.position(arg.0),
+                label: InlayHintLabel::String(format!("{}:", name)),
+                kind: Some(InlayHintKind::PARAMETER),
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(false),
+                padding_right: Some(true),
+                data: None,
+            });
+        }
+    }
+}
+
+/// Byte offsets of the `(` of every call to `name`. Occurrences at the start
+/// of a line are top-level declarations, and `.name(` is a method call.
+fn call_sites(name: &str, text: &str) -> Vec<usize> {
+    let needle = format!("{}(", name);
+    let mut sites = Vec::new();
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let code = line.split("//").next().unwrap_or("");
+        let mut from = 0;
+        while let Some(pos) = code[from..].find(&needle) {
+            let at = from + pos;
+            from = at + needle.len();
+            let before = code[..at].chars().next_back();
+            let is_call = at > 0
+                && !before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.')
+                && !inside_string(&code[..at]);
+            if is_call {
+                sites.push(line_start + at + name.len());
+            }
+        }
+        line_start += line.len();
+    }
+
+    sites
+}
+
+/// Whether `prefix` ends inside a string literal
+fn inside_string(prefix: &str) -> bool {
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in prefix.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ => {}
+        }
+    }
+    in_string
+}
+
+/// Trimmed byte ranges of the arguments of the call whose `(` is at `open_paren`
+fn call_arguments(text: &str, open_paren: usize) -> Vec<(usize, usize)> {
+    let mut args = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = open_paren + 1;
+
+    for (offset, c) in text[open_paren + 1..].char_indices() {
+        let at = open_paren + 1 + offset;
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth > 0 => depth -= 1,
+            ',' | ')' if depth == 0 => {
+                push_trimmed(text, start, at, &mut args);
+                if c == ')' {
+                    return args;
+                }
+                start = at + 1;
+            }
+            _ => {}
+        }
+    }
+
+    // Unterminated call: the buffer is mid-edit, show nothing
+    Vec::new()
+}
+
+fn push_trimmed(text: &str, start: usize, end: usize, args: &mut Vec<(usize, usize)>) {
+    let raw = &text[start..end];
+    let trimmed = raw.trim();
+    if !trimmed.is_empty() {
+        let lead = raw.len() - raw.trim_start().len();
+        args.push((start + lead, start + lead + trimmed.len()));
+    }
+}
+
+fn in_range(position: Position, range: Range) -> bool {
+    (position.line, position.character) >= (range.start.line, range.start.character)
+        && (position.line, position.character) <= (range.end.line, range.end.character)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hints(source: &str) -> Vec<(u32, u32, String)> {
+        let tokens = livac::lexer::tokenize(source).unwrap();
+        let program =
+            livac::semantic::analyze(livac::parser::parse(tokens, source).unwrap()).unwrap();
+        let whole = Range {
+            start: Position {
+                line: 0,
+                character: 0,
+            },
+            end: Position {
+                line: u32::MAX,
+                character: 0,
+            },
+        };
+        compute_inlay_hints(&program, source, whole)
+            .into_iter()
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => {
+                    (hint.position.line, hint.position.character, label)
+                }
+                InlayHintLabel::LabelParts(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_type_and_parameter_hints() {
+        let source = "area(width: number, height: number): number => width * height\n\nmain() {\n    let height = 3\n    let size = area(2, height)\n    print(\"area(1, 2)\")\n}\n";
+        assert_eq!(
+            hints(source),
+            vec![
+                (3, 14, ": number".to_string()),
+                (4, 12, ": number".to_string()),
+                (4, 20, "width:".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lambda_parameter_hint() {
+        let source =
+            "main() {\n    let names = [\"a\", \"b\"]\n    names.forEach(name => print(name))\n}\n";
+        let labels: Vec<String> = hints(source)
+            .into_iter()
+            .map(|(_, _, label)| label)
+            .collect();
+        assert_eq!(labels, vec![": [string]", ": string"]);
+    }
+
+    #[test]
+    fn test_unterminated_call_has_no_arguments() {
+        assert!(call_arguments("f(1, 2", 1).is_empty());
+        assert_eq!(call_arguments("f( 1 , g(2, 3))", 1), vec![(3, 4), (7, 14)]);
+    }
+}
@@ -1,6 +1,10 @@
+pub mod code_lens;
+pub mod const_eval;
 pub mod diagnostics;
 pub mod document;
+pub mod hierarchy;
 pub mod imports;
+pub mod inlay_hints;
 /// Language Server Protocol implementation for Liva
 ///
 /// This module provides LSP support for IDE integration.
@@ -12,7 +16,9 @@ pub mod imports;
 /// - `symbols.rs`: Symbol table and indexing
 /// - `diagnostics.rs`: Error to diagnostic conversion
 pub mod server;
+pub mod signature_help;
 pub mod symbols;
+pub mod syntax_ranges;
 pub mod workspace;
 
 pub use document::DocumentState;
@@ -4,13 +4,20 @@ use tower_lsp::lsp_types::request::{GotoImplementationParams, GotoImplementation
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
+use super::code_lens;
+use super::const_eval;
 use super::diagnostics::{error_to_diagnostic, warning_to_diagnostic};
-use super::document::DocumentState;
-use super::imports::ImportResolver;
+use super::document::{AnalysisSnapshot, DocumentState, LineIndex};
+use super::hierarchy;
+use super::imports::{import_insertion_line, ImportResolver};
+use super::inlay_hints::compute_inlay_hints;
+use super::signature_help::{call_context, signature_help};
 use super::symbols::SymbolTable;
+use super::syntax_ranges;
 use super::workspace::{WorkspaceIndex, WorkspaceManager};
 use crate::linter;
-use livac::{lexer, parser, semantic};
+use livac::signatures::Signature;
+use livac::{lexer, parser, semantic, CancellationToken};
 
 /// Main Language Server for Liva
 pub struct LivaLanguageServer {
@@ -20,6 +27,10 @@ pub struct LivaLanguageServer {
     /// Open documents indexed by URI
     documents: DashMap<Url, DocumentState>,
 
+    /// Token of the analysis currently running for each document; a newer
+    /// edit cancels it
+    analyses: DashMap<Url, CancellationToken>,
+
     /// Workspace file manager
     workspace: std::sync::Arc<tokio::sync::RwLock<WorkspaceManager>>,
 
@@ -28,6 +39,10 @@ pub struct LivaLanguageServer {
 
     /// Import resolver
     import_resolver: std::sync::Arc<tokio::sync::RwLock<ImportResolver>>,
+
+    /// Whether files that aren't open get diagnostics too, enabled with the
+    /// `workspaceDiagnostics` initialization option
+    workspace_diagnostics: std::sync::atomic::AtomicBool,
 }
 
 impl LivaLanguageServer {
@@ -36,83 +51,177 @@ impl LivaLanguageServer {
         Self {
             client,
             documents: DashMap::new(),
+            analyses: DashMap::new(),
             workspace: std::sync::Arc::new(tokio::sync::RwLock::new(WorkspaceManager::new(vec![]))),
             workspace_index: std::sync::Arc::new(WorkspaceIndex::default()),
             import_resolver: std::sync::Arc::new(tokio::sync::RwLock::new(ImportResolver::new(
                 vec![],
             ))),
+            workspace_diagnostics: std::sync::atomic::AtomicBool::new(false),
         }
     }
 
     /// Parses a document and updates its state
-    async fn parse_document(&self, uri: &Url) {
+    ///
+    /// Returns false when the analysis was superseded by a newer edit, in
+    /// which case the document state is left for the newer run to update.
+    async fn parse_document(&self, uri: &Url) -> bool {
+        let (text, version) = match self.documents.get(uri) {
+            Some(doc) => (doc.text.clone(), doc.version),
+            None => return false,
+        };
+
+        // Also names the document's entry in the incremental cache, so it is
+        // the whole path: files in different folders may share a name
+        let filename = uri
+            .to_file_path()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| uri.to_string());
+
+        // Abort the previous analysis of this document, it is out of date
+        let token = CancellationToken::new();
+        if let Some(previous) = self.analyses.insert(uri.clone(), token.clone()) {
+            previous.cancel();
+        }
+
+        // Lex, parse (with recovery) and analyze in one pass, off the async
+        // runtime, reusing the items the edit left unchanged
+        let report = tokio::task::spawn_blocking(move || {
+            livac::check_source_incremental(&text, &filename, &token)
+        })
+        .await;
+        let report = match report {
+            Ok(Ok(report)) => report,
+            // Cancelled, or the analysis panicked: nothing to publish
+            _ => return false,
+        };
+
         let mut doc = match self.documents.get_mut(uri) {
-            Some(doc) => doc,
-            None => return,
+            Some(doc) if doc.version == version => doc,
+            _ => return false,
         };
+        let filename = report.file_name.clone();
 
-        // Tokenize
-        let tokens = match lexer::tokenize(&doc.text) {
-            Ok(tokens) => tokens,
-            Err(e) => {
-                // Store lexer error as diagnostic
-                if let Some(diag) = error_to_diagnostic(&e) {
-                    doc.diagnostics = vec![diag];
+        let is_valid = report.is_ok();
+        let diagnostics: Vec<Diagnostic> = report
+            .diagnostics
+            .iter()
+            .filter_map(error_to_diagnostic)
+            .collect();
+
+        match report.ast {
+            Some(analyzed_ast) if is_valid => {
+                // Build symbol table from AST (pass source text for span conversion)
+                let symbols = SymbolTable::from_ast(&analyzed_ast, &doc.text);
+
+                // Extract imports from AST
+                let import_resolver = self.import_resolver.read().await;
+                let imports = import_resolver.extract_imports(&analyzed_ast, uri);
+                drop(import_resolver);
+
+                // Index file in workspace index
+                self.workspace_index
+                    .index_file(uri.clone(), &analyzed_ast, &doc.text);
+
+                // Run linter and surface its warnings as LSP diagnostics.
+                let lint_diags: Vec<Diagnostic> = linter::lint(&analyzed_ast, &filename, &doc.text)
+                    .iter()
+                    .map(warning_to_diagnostic)
+                    .collect();
+
+                // Record what this file imports, so edits there re-analyze it
+                self.workspace_index.set_dependencies(
+                    uri,
+                    imports
+                        .iter()
+                        .filter_map(|import| import.resolved_uri.clone()),
+                );
+
+                doc.ast = Some(analyzed_ast.clone());
+                doc.analysis = Some(AnalysisSnapshot {
+                    version,
+                    ast: analyzed_ast,
+                    symbols,
+                    imports,
+                });
+                doc.diagnostics = lint_diags;
+            }
+            partial_ast => {
+                // Keep the partial AST so navigation still works on the valid parts;
+                // `doc.analysis` keeps serving hover and completion meanwhile
+                if partial_ast.is_some() {
+                    doc.ast = partial_ast;
                 }
-                return;
+                doc.diagnostics = diagnostics;
             }
-        };
+        }
+        true
+    }
 
-        // Parse
-        match parser::parse(tokens, &doc.text) {
-            Ok(ast) => {
-                // Run semantic analysis
-                match semantic::analyze(ast.clone()) {
-                    Ok(analyzed_ast) => {
-                        // Build symbol table from AST (pass source text for span conversion)
-                        let symbols = SymbolTable::from_ast(&analyzed_ast, &doc.text);
-
-                        // Extract imports from AST
-                        let import_resolver = self.import_resolver.read().await;
-                        let imports = import_resolver.extract_imports(&analyzed_ast, uri);
-                        drop(import_resolver);
-
-                        // Index file in workspace index
-                        self.workspace_index
-                            .index_file(uri.clone(), &analyzed_ast, &doc.text);
-
-                        // Run linter and surface its warnings as LSP diagnostics.
-                        let filename = uri
-                            .to_file_path()
-                            .ok()
-                            .and_then(|p| p.file_name().map(|s| s.to_string_lossy().into_owned()))
-                            .unwrap_or_else(|| "<unknown>".to_string());
-                        let lint_diags: Vec<Diagnostic> = linter::lint(&analyzed_ast, &filename, &doc.text)
-                            .iter()
-                            .map(warning_to_diagnostic)
-                            .collect();
-
-                        doc.ast = Some(analyzed_ast);
-                        doc.symbols = Some(symbols);
-                        doc.imports = imports;
-                        doc.diagnostics = lint_diags;
-                    }
-                    Err(e) => {
-                        // Store semantic error as diagnostic
-                        doc.ast = Some(ast);
-                        if let Some(diag) = error_to_diagnostic(&e) {
-                            doc.diagnostics = vec![diag];
-                        }
-                    }
+    /// Re-analyzes the open documents that import `uri`, directly or
+    /// transitively, so their cached analysis reflects its new exports.
+    /// With workspace diagnostics on, closed importers are re-checked too.
+    async fn reanalyze_dependents(&self, uri: &Url) {
+        for dependent in self.workspace_index.dependents_of(uri) {
+            if !self.documents.contains_key(&dependent) {
+                if self.workspace_diagnostics_enabled() {
+                    self.check_closed_file(&dependent).await;
                 }
+                continue;
             }
-            Err(e) => {
-                // Store parse error as diagnostic
-                if let Some(diag) = error_to_diagnostic(&e) {
-                    doc.diagnostics = vec![diag];
-                }
+            if self.parse_document(&dependent).await {
+                self.publish_diagnostics(&dependent).await;
+            }
+        }
+    }
+
+    fn workspace_diagnostics_enabled(&self) -> bool {
+        self.workspace_diagnostics
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Checks a file that isn't open, as saved on disk and with its imports
+    /// resolved, then indexes it and publishes its diagnostics
+    async fn check_closed_file(&self, uri: &Url) {
+        let path = match uri.to_file_path() {
+            Ok(path) => path,
+            Err(_) => return,
+        };
+        let (report, source) = match tokio::task::spawn_blocking(move || {
+            let source = std::fs::read_to_string(&path).unwrap_or_default();
+            (livac::check_file(&path), source)
+        })
+        .await
+        {
+            Ok(checked) => checked,
+            Err(_) => return,
+        };
+
+        if let Some(ast) = &report.ast {
+            let imports = self.import_resolver.read().await.extract_imports(ast, uri);
+            self.workspace_index.set_dependencies(
+                uri,
+                imports
+                    .iter()
+                    .filter_map(|import| import.resolved_uri.clone()),
+            );
+            if report.is_ok() {
+                self.workspace_index.index_file(uri.clone(), ast, &source);
             }
         }
+
+        // The file was opened meanwhile: its own analysis publishes
+        if self.documents.contains_key(uri) {
+            return;
+        }
+        let diagnostics = report
+            .diagnostics
+            .iter()
+            .filter_map(error_to_diagnostic)
+            .collect();
+        self.client
+            .publish_diagnostics(uri.clone(), diagnostics, None)
+            .await;
     }
 
     /// Publishes diagnostics for a document
@@ -131,6 +240,15 @@ impl LivaLanguageServer {
 #[tower_lsp::async_trait]
 impl LanguageServer for LivaLanguageServer {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let workspace_diagnostics = params
+            .initialization_options
+            .as_ref()
+            .and_then(|options| options.get("workspaceDiagnostics"))
+            .and_then(|enabled| enabled.as_bool())
+            .unwrap_or(false);
+        self.workspace_diagnostics
+            .store(workspace_diagnostics, std::sync::atomic::Ordering::Relaxed);
+
         // Initialize workspace with root URIs
         if let Some(workspace_folders) = params.workspace_folders {
             let root_uris: Vec<Url> = workspace_folders
@@ -180,11 +298,41 @@ impl LanguageServer for LivaLanguageServer {
                     work_done_progress_options: Default::default(),
                 }),
                 folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        code_lens::RUN_COMMAND.to_string(),
+                        code_lens::RUN_TEST_COMMAND.to_string(),
+                    ],
+                    work_done_progress_options: Default::default(),
+                }),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: None,
+                    work_done_progress_options: Default::default(),
+                }),
                 document_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: "}".to_string(),
+                    more_trigger_character: Some(vec!["\n".to_string()]),
+                }),
                 document_symbol_provider: Some(OneOf::Left(true)),
                 workspace_symbol_provider: Some(OneOf::Left(true)),
-                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::REFACTOR_REWRITE,
+                            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                        ]),
+                        ..Default::default()
+                    },
+                )),
                 workspace: Some(WorkspaceServerCapabilities {
                     workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                         supported: Some(true),
@@ -206,10 +354,48 @@ impl LanguageServer for LivaLanguageServer {
             .log_message(MessageType::INFO, "Liva Language Server initialized")
             .await;
 
+        // lsp-types 0.94 has no `typeHierarchyProvider` server capability, so
+        // type hierarchy support is announced through dynamic registration
+        let type_hierarchy = Registration {
+            id: "liva-type-hierarchy".to_string(),
+            method: "textDocument/prepareTypeHierarchy".to_string(),
+            register_options: Some(serde_json::json!({
+                "documentSelector": [{ "language": "liva" }],
+            })),
+        };
+        if let Err(err) = self.client.register_capability(vec![type_hierarchy]).await {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Type hierarchy not registered: {}", err),
+                )
+                .await;
+        }
+
         // Index all workspace files
         let workspace = self.workspace.read().await;
         let files = workspace.list_liva_files();
         let file_count = files.len();
+        drop(workspace);
+
+        // Check every file with imports resolved, so problems in files that
+        // aren't open show up right away; this indexes them as well
+        if self.workspace_diagnostics_enabled() {
+            let mut files = files;
+            files.sort();
+            for file_uri in &files {
+                if !self.documents.contains_key(file_uri) {
+                    self.check_closed_file(file_uri).await;
+                }
+            }
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!("Checked {} workspace files", file_count),
+                )
+                .await;
+            return;
+        }
 
         for file_uri in files {
             if let Ok(path) = file_uri.to_file_path() {
@@ -262,8 +448,9 @@ impl LanguageServer for LivaLanguageServer {
             .insert(uri.clone(), DocumentState::new(text, version));
 
         // Parse and publish diagnostics
-        self.parse_document(&uri).await;
-        self.publish_diagnostics(&uri).await;
+        if self.parse_document(&uri).await {
+            self.publish_diagnostics(&uri).await;
+        }
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -278,9 +465,11 @@ impl LanguageServer for LivaLanguageServer {
             doc.version = params.text_document.version;
         }
 
-        // Parse and publish diagnostics
-        self.parse_document(&uri).await;
-        self.publish_diagnostics(&uri).await;
+        // Parse and publish diagnostics, unless a newer edit superseded this run
+        if self.parse_document(&uri).await {
+            self.publish_diagnostics(&uri).await;
+            self.reanalyze_dependents(&uri).await;
+        }
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
@@ -293,8 +482,10 @@ impl LanguageServer for LivaLanguageServer {
 
         // Optionally re-parse on save
         let uri = &params.text_document.uri;
-        self.parse_document(uri).await;
-        self.publish_diagnostics(uri).await;
+        if self.parse_document(uri).await {
+            self.publish_diagnostics(uri).await;
+            self.reanalyze_dependents(uri).await;
+        }
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
@@ -305,10 +496,17 @@ impl LanguageServer for LivaLanguageServer {
             )
             .await;
 
-        // Remove document from cache
+        // Remove document from cache and stop any analysis still running
         self.documents.remove(&params.text_document.uri);
+        if let Some((_, token)) = self.analyses.remove(&params.text_document.uri) {
+            token.cancel();
+        }
 
-        // Clear diagnostics
+        // Back to the saved file's diagnostics, or none
+        if self.workspace_diagnostics_enabled() {
+            self.check_closed_file(&params.text_document.uri).await;
+            return;
+        }
         self.client
             .publish_diagnostics(params.text_document.uri, Vec::new(), None)
             .await;
@@ -360,6 +558,11 @@ impl LanguageServer for LivaLanguageServer {
             ("parseInt", "parseInt(str: string) -> (int, string)"),
             ("parseFloat", "parseFloat(str: string) -> (float, string)"),
             ("toString", "toString(value) -> string"),
+            ("clone", "clone(value: T) -> T"),
+            ("freeze", "freeze(value: T) -> T"),
+            ("typeOf", "typeOf(value) -> string"),
+            ("charCode", "charCode(c: char) -> int"),
+            ("fromCharCode", "fromCharCode(code: int) -> char"),
         ];
 
         for (name, signature) in builtins {
@@ -373,7 +576,7 @@ impl LanguageServer for LivaLanguageServer {
         }
 
         // Local file symbols (priority 3 - local symbols)
-        if let Some(symbols) = &doc.symbols {
+        if let Some(symbols) = doc.symbols() {
             for symbol in symbols.all() {
                 if items.iter().any(|item| item.label == symbol.name) {
                     continue;
@@ -402,7 +605,7 @@ impl LanguageServer for LivaLanguageServer {
 
         // Imported symbols (priority 4 - from explicit imports)
         let import_resolver = self.import_resolver.read().await;
-        for import_info in &doc.imports {
+        for import_info in doc.imports() {
             if let Some(import_uri) = &import_info.resolved_uri {
                 // Get all symbols from imported file
                 if let Some(imported_symbols) = self.workspace_index.get_file_symbols(import_uri) {
@@ -520,7 +723,7 @@ impl LanguageServer for LivaLanguageServer {
         };
 
         // 1. Try current file first (fast path)
-        if let Some(symbols) = &doc.symbols {
+        if let Some(symbols) = doc.symbols() {
             if let Some(symbol_list) = symbols.lookup(&word) {
                 // Return the first symbol's location (TODO: handle overloads)
                 if let Some(symbol) = symbol_list.first() {
@@ -535,7 +738,7 @@ impl LanguageServer for LivaLanguageServer {
 
         // 2. Check if symbol is imported and resolve to imported file
         let import_resolver = self.import_resolver.read().await;
-        let import_source = import_resolver.get_import_source(&word, &doc.imports);
+        let import_source = import_resolver.get_import_source(&word, doc.imports());
         drop(import_resolver);
 
         if let Some(import_uri) = import_source {
@@ -660,7 +863,7 @@ impl LanguageServer for LivaLanguageServer {
         let mut all_locations = Vec::new();
 
         // 1. Search in current file
-        if let Some(symbols) = &doc.symbols {
+        if let Some(symbols) = doc.symbols() {
             if symbols.lookup(&word).is_some() {
                 let ranges = symbols.find_references(&word, &doc.text);
 
@@ -686,7 +889,7 @@ impl LanguageServer for LivaLanguageServer {
             // Check if file is open in editor
             if let Some(open_doc) = self.documents.get(&file_uri) {
                 // Search in open document
-                if let Some(symbols) = &open_doc.symbols {
+                if let Some(symbols) = open_doc.symbols() {
                     if symbols.lookup(&word).is_some() {
                         let ranges = symbols.find_references(&word, &open_doc.text);
                         for range in ranges {
@@ -782,8 +985,7 @@ impl LanguageServer for LivaLanguageServer {
         // to scope highlights to the current document only \u2014 that's the
         // contract of `textDocument/documentHighlight`.
         let highlights: Vec<DocumentHighlight> = doc
-            .symbols
-            .as_ref()
+            .symbols()
             .map(|s| s.find_references(&word, &doc.text))
             .unwrap_or_default()
             .into_iter()
@@ -812,10 +1014,16 @@ impl LanguageServer for LivaLanguageServer {
             None => return Ok(None),
         };
 
-        // Build a chain of expanding ranges per requested position:
-        //   word \u2192 line \u2192 whole document. This is a lightweight
-        // syntax-agnostic fallback; a proper implementation would walk
-        // the AST and emit token/expression/block/function nesting.
+        if let Some(analysis) = doc.analysis.as_ref().filter(|_| !doc.is_analysis_stale()) {
+            return Ok(Some(syntax_ranges::selection_ranges(
+                &analysis.ast,
+                &doc.text,
+                &params.positions,
+            )));
+        }
+
+        // Without an up-to-date AST, build a chain of expanding ranges per
+        // requested position: word \u2192 line \u2192 whole document.
         let mut out = Vec::with_capacity(params.positions.len());
         let lines: Vec<&str> = doc.text.lines().collect();
         let total_lines = lines.len() as u32;
@@ -857,6 +1065,78 @@ impl LanguageServer for LivaLanguageServer {
         Ok(Some(out))
     }
 
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let word = match self
+            .documents
+            .get(uri)
+            .and_then(|doc| doc.word_at_position(position))
+        {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+
+        let items =
+            hierarchy::prepare_call_hierarchy(&self.workspace_index.hierarchies(), uri, &word);
+        Ok((!items.is_empty()).then_some(items))
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        let calls = hierarchy::incoming_calls(&self.workspace_index.hierarchies(), &params.item);
+        Ok(Some(calls))
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        let calls = hierarchy::outgoing_calls(&self.workspace_index.hierarchies(), &params.item);
+        Ok(Some(calls))
+    }
+
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let word = match self
+            .documents
+            .get(uri)
+            .and_then(|doc| doc.word_at_position(position))
+        {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+
+        let items =
+            hierarchy::prepare_type_hierarchy(&self.workspace_index.hierarchies(), uri, &word);
+        Ok((!items.is_empty()).then_some(items))
+    }
+
+    async fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let items = hierarchy::supertypes(&self.workspace_index.hierarchies(), &params.item);
+        Ok(Some(items))
+    }
+
+    async fn subtypes(
+        &self,
+        params: TypeHierarchySubtypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let items = hierarchy::subtypes(&self.workspace_index.hierarchies(), &params.item);
+        Ok(Some(items))
+    }
+
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
         let uri = &params.text_document_position.text_document.uri;
         let position = params.text_document_position.position;
@@ -908,8 +1188,7 @@ impl LanguageServer for LivaLanguageServer {
 
         // Current document edits (always).
         let current_ranges = doc
-            .symbols
-            .as_ref()
+            .symbols()
             .map(|s| s.find_references(&word, &doc.text))
             .unwrap_or_default();
         if !current_ranges.is_empty() {
@@ -934,8 +1213,7 @@ impl LanguageServer for LivaLanguageServer {
 
                 let ranges = if let Some(open_doc) = self.documents.get(&file_uri) {
                     open_doc
-                        .symbols
-                        .as_ref()
+                        .symbols()
                         .map(|s| s.find_references(&word, &open_doc.text))
                         .unwrap_or_default()
                 } else if let Ok(path) = file_uri.to_file_path() {
@@ -1154,6 +1432,118 @@ impl LanguageServer for LivaLanguageServer {
             }
         }
 
+        let wants = |kind: &CodeActionKind| match &params.context.only {
+            Some(only) => only.iter().any(|k| kind.as_str().starts_with(k.as_str())),
+            None => true,
+        };
+
+        // Auto-import: the symbol under the cursor is not defined or imported
+        // here, but another workspace file declares it
+        if wants(&CodeActionKind::QUICKFIX) {
+            if let Some(word) = doc.word_at_position(params.range.start) {
+                let defined_here = doc
+                    .symbols()
+                    .is_some_and(|symbols| symbols.lookup(&word).is_some());
+                let import_resolver = self.import_resolver.read().await;
+                let imported = import_resolver.is_symbol_imported(&word, doc.imports());
+
+                if !defined_here && !imported && !word.starts_with('_') {
+                    let mut sources: Vec<Url> = self
+                        .workspace_index
+                        .lookup_global(&word)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|(symbol_uri, _)| symbol_uri)
+                        .filter(|symbol_uri| symbol_uri != uri)
+                        .collect();
+                    sources.sort();
+                    sources.dedup();
+
+                    let insert_line = import_insertion_line(&doc.text);
+                    for source_uri in sources {
+                        let Some(path) = import_resolver.relative_import_path(uri, &source_uri)
+                        else {
+                            continue;
+                        };
+                        let statement = format!("import {{ {} }} from \"{}\"\n", word, path);
+                        let (line, new_text) = match insert_line {
+                            Some(line) => (line, statement),
+                            // First import: keep it apart from the code below
+                            None => (0, format!("{}\n", statement)),
+                        };
+                        let edit = TextEdit {
+                            range: Range {
+                                start: Position { line, character: 0 },
+                                end: Position { line, character: 0 },
+                            },
+                            new_text,
+                        };
+                        let mut changes = std::collections::HashMap::new();
+                        changes.insert(uri.clone(), vec![edit]);
+                        actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                            title: format!("Import `{}` from \"{}\"", word, path),
+                            kind: Some(CodeActionKind::QUICKFIX),
+                            edit: Some(WorkspaceEdit {
+                                changes: Some(changes),
+                                document_changes: None,
+                                change_annotations: None,
+                            }),
+                            is_preferred: Some(false),
+                            ..Default::default()
+                        }));
+                    }
+                }
+            }
+        }
+
+        // Fold the constant expression under the cursor or the selection
+        if wants(&CodeActionKind::REFACTOR_REWRITE) {
+            if let Some(constant) =
+                const_eval::constant_at(doc.ast.as_ref(), &doc.text, params.range)
+            {
+                actions.push(CodeActionOrCommand::CodeAction(const_eval::replace_action(
+                    uri, &constant,
+                )));
+            }
+        }
+
+        // Organize imports: sort, merge and deduplicate through the formatter
+        if wants(&CodeActionKind::SOURCE_ORGANIZE_IMPORTS)
+            && import_insertion_line(&doc.text).is_some()
+        {
+            let options = crate::formatter::FormatOptions::default();
+            if let Ok(organized) = crate::formatter::organize_imports(&doc.text, &options) {
+                if organized != doc.text {
+                    let line_count = doc.text.lines().count() as u32;
+                    let edit = TextEdit {
+                        range: Range {
+                            start: Position {
+                                line: 0,
+                                character: 0,
+                            },
+                            end: Position {
+                                line: line_count + 1,
+                                character: 0,
+                            },
+                        },
+                        new_text: organized,
+                    };
+                    let mut changes = std::collections::HashMap::new();
+                    changes.insert(uri.clone(), vec![edit]);
+                    actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                        title: "Organize imports".to_string(),
+                        kind: Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS),
+                        edit: Some(WorkspaceEdit {
+                            changes: Some(changes),
+                            document_changes: None,
+                            change_annotations: None,
+                        }),
+                        ..Default::default()
+                    }));
+                }
+            }
+        }
+
         if actions.is_empty() {
             Ok(None)
         } else {
@@ -1170,7 +1560,20 @@ impl LanguageServer for LivaLanguageServer {
             Some(doc) => doc,
             None => return Ok(None),
         };
-        let ranges = compute_folding_ranges(&doc.text);
+        let ranges = match doc.analysis.as_ref().filter(|_| !doc.is_analysis_stale()) {
+            // Declarations, blocks and strings from the AST; imports and
+            // comment runs are not part of it
+            Some(analysis) => {
+                let mut ranges = syntax_ranges::folding_ranges(&analysis.ast, &doc.text);
+                ranges.extend(
+                    compute_folding_ranges(&doc.text)
+                        .into_iter()
+                        .filter(|r| r.kind != Some(FoldingRangeKind::Region)),
+                );
+                ranges
+            }
+            None => compute_folding_ranges(&doc.text),
+        };
         if ranges.is_empty() {
             Ok(None)
         } else {
@@ -1178,7 +1581,97 @@ impl LanguageServer for LivaLanguageServer {
         }
     }
 
-    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = &params.text_document.uri;
+        let doc = match self.documents.get(uri) {
+            Some(doc) => doc,
+            None => return Ok(None),
+        };
+        // Spans of an older analysis no longer line up with the text
+        let analysis = match &doc.analysis {
+            Some(analysis) if !doc.is_analysis_stale() => analysis,
+            _ => return Ok(None),
+        };
+        let hints = compute_inlay_hints(&analysis.ast, &doc.text, params.range);
+        if hints.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(hints))
+        }
+    }
+
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let uri = &params.text_document.uri;
+        let doc = match self.documents.get(uri) {
+            Some(doc) => doc,
+            None => return Ok(None),
+        };
+        // Spans of the partial parse match the current text
+        let ast = match &doc.ast {
+            Some(ast) => ast,
+            None => return Ok(None),
+        };
+        Ok(Some(code_lens::code_lenses(ast, &doc.text, uri)))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        let args = match code_lens::livac_args(&params.command, &params.arguments) {
+            Some(args) => args,
+            None => {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                    "Unknown command or arguments: {}",
+                    params.command
+                )))
+            }
+        };
+
+        // Programs can run for a while: report back when done instead of
+        // holding the request open
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let label = format!("livac {}", args.join(" "));
+            let output = tokio::process::Command::new(code_lens::livac_binary())
+                .args(&args)
+                .current_dir(code_lens::working_dir(&args[1]))
+                .output()
+                .await;
+            match output {
+                Ok(output) => {
+                    let text = format!(
+                        "{}{}",
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&output.stderr)
+                    );
+                    client
+                        .log_message(MessageType::INFO, format!("$ {}\n{}", label, text))
+                        .await;
+                    let (kind, outcome) = if output.status.success() {
+                        (MessageType::INFO, "finished")
+                    } else {
+                        (MessageType::ERROR, "failed")
+                    };
+                    client
+                        .show_message(
+                            kind,
+                            format!("`{}` {}, see the output panel", label, outcome),
+                        )
+                        .await;
+                }
+                Err(err) => {
+                    client
+                        .show_message(MessageType::ERROR, format!("Could not run livac: {}", err))
+                        .await;
+                }
+            }
+        });
+
+        Ok(None)
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
         let uri = &params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
         let doc = match self.documents.get(uri) {
@@ -1186,29 +1679,81 @@ impl LanguageServer for LivaLanguageServer {
             None => return Ok(None),
         };
 
-        // Get the word at the cursor position
-        let word = match doc.word_at_position(position) {
-            Some(w) => w,
+        let offset = LineIndex::new(&doc.text).offset(position);
+        let call = match call_context(&doc.text, offset) {
+            Some(call) => call,
             None => return Ok(None),
         };
 
-        // Look up the symbol in the symbol table
-        if let Some(symbols) = &doc.symbols {
-            if let Some(symbol_list) = symbols.lookup(&word) {
-                if let Some(symbol) = symbol_list.first() {
+        // Signatures don't hold spans, so a stale analysis is still useful
+        let local: &[Signature] = doc
+            .analysis
+            .as_ref()
+            .map_or(&[], |analysis| analysis.ast.signatures.as_slice());
+        let signature = local
+            .iter()
+            .find(|s| s.name == call.callee)
+            .cloned()
+            .or_else(|| livac::signatures::builtin(&call.callee))
+            .or_else(|| self.workspace_index.lookup_signature(&call.callee))
+            .or_else(|| {
+                // `counter.bump(` or `this.bump(`: the receiver's type isn't
+                // known here, so settle for a method only one class declares
+                let suffix = format!(".{}", call.method()?);
+                let mut candidates = local.iter().filter(|s| s.name.ends_with(&suffix));
+                match (candidates.next(), candidates.next()) {
+                    (Some(only), None) => Some(only.clone()),
+                    _ => None,
+                }
+            });
+
+        Ok(signature.map(|signature| signature_help(&signature, call.active_parameter)))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let doc = match self.documents.get(uri) {
+            Some(doc) => doc,
+            None => return Ok(None),
+        };
+
+        // Get the word at the cursor position
+        let word = doc.word_at_position(position);
+
+        if let Some(word) = &word {
+            // Look up the symbol in the symbol table
+            if let Some(symbols) = doc.symbols() {
+                if let Some(symbol_list) = symbols.lookup(word) {
+                    if let Some(symbol) = symbol_list.first() {
+                        return Ok(Some(symbol_hover(symbol)));
+                    }
+                }
+            }
+
+            // Fall back to the workspace index — surface hover info for symbols
+            // defined in other files (cross-file functions, classes, enums...).
+            if let Some(entries) = self.workspace_index.lookup_global(word) {
+                if let Some((_, symbol)) = entries.first() {
                     return Ok(Some(symbol_hover(symbol)));
                 }
             }
         }
 
-        // Fall back to the workspace index — surface hover info for symbols
-        // defined in other files (cross-file functions, classes, enums...).
-        if let Some(entries) = self.workspace_index.lookup_global(&word) {
-            if let Some((_, symbol)) = entries.first() {
-                return Ok(Some(symbol_hover(symbol)));
-            }
+        // Numbers and operators of a constant expression show its value
+        let cursor = Range {
+            start: position,
+            end: position,
+        };
+        if let Some(constant) = const_eval::constant_at(doc.ast.as_ref(), &doc.text, cursor) {
+            return Ok(Some(constant.hover()));
         }
 
+        let word = match word {
+            Some(w) => w,
+            None => return Ok(None),
+        };
+
         // Check for built-in keywords/types
         let builtin_info = match word.as_str() {
             "int" => Some("```liva\ntype int\n```\n\nSigned 32-bit integer type"),
@@ -1299,6 +1844,29 @@ impl LanguageServer for LivaLanguageServer {
         }
     }
 
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let doc = match self.documents.get(uri) {
+            Some(doc) => doc,
+            None => return Ok(None),
+        };
+
+        let options = crate::formatter::FormatOptions {
+            indent_size: params.options.tab_size as usize,
+            ..Default::default()
+        };
+        let edits = compute_on_type_edits(
+            &doc.text,
+            params.text_document_position.position,
+            &params.ch,
+            &options,
+        );
+        Ok((!edits.is_empty()).then_some(edits))
+    }
+
     /// Document symbols — populates VS Code's Outline view and breadcrumbs
     /// with all top-level functions, classes, type aliases, and methods.
     async fn document_symbol(
@@ -1458,6 +2026,59 @@ fn find_word_ranges_in_source(source: &str, word: &str) -> Vec<tower_lsp::lsp_ty
     ranges
 }
 
+/// Re-indentation after typing `}` (every line of the block it closes) or a
+/// newline (the new line), using the formatter's bracket-based indentation
+/// so it works on code that doesn't parse yet. Blank lines other than the
+/// cursor's are left alone.
+fn compute_on_type_edits(
+    text: &str,
+    position: Position,
+    ch: &str,
+    options: &crate::formatter::FormatOptions,
+) -> Vec<TextEdit> {
+    let indents = crate::formatter::line_indents(text, options);
+    let lines: Vec<&str> = text.split('\n').collect();
+    let cursor_line = position.line as usize;
+    let Some(Some(current)) = indents.get(cursor_line) else {
+        return Vec::new();
+    };
+
+    let first_line = match ch {
+        // The block starts after the closest earlier line at the `}`'s level,
+        // which is the line that opened it
+        "}" => (0..cursor_line)
+            .rev()
+            .find(|&l| indents[l].as_ref() == Some(current) && !lines[l].trim().is_empty())
+            .map_or(0, |l| l + 1),
+        "\n" => cursor_line,
+        _ => return Vec::new(),
+    };
+
+    (first_line..=cursor_line)
+        .filter_map(|l| {
+            let expected = indents[l].as_ref()?;
+            let line = lines[l].trim_end_matches('\r');
+            if line.trim().is_empty() && l != cursor_line {
+                return None;
+            }
+            let leading = line.len() - line.trim_start().len();
+            (line[..leading] != **expected).then(|| TextEdit {
+                range: Range {
+                    start: Position {
+                        line: l as u32,
+                        character: 0,
+                    },
+                    end: Position {
+                        line: l as u32,
+                        character: leading as u32,
+                    },
+                },
+                new_text: expected.clone(),
+            })
+        })
+        .collect()
+}
+
 /// Compute folding ranges for `text` by matching `{`/`}` pairs and collapsing
 /// consecutive `import` lines and `//` line-comment blocks.
 ///
@@ -1626,6 +2247,51 @@ mod folding_tests {
     }
 }
 
+#[cfg(test)]
+mod on_type_tests {
+    use super::compute_on_type_edits;
+    use crate::formatter::FormatOptions;
+    use tower_lsp::lsp_types::Position;
+
+    fn edited_lines(src: &str, line: u32, ch: &str) -> Vec<(u32, String)> {
+        let position = Position { line, character: 0 };
+        compute_on_type_edits(src, position, ch, &FormatOptions::default())
+            .into_iter()
+            .map(|edit| (edit.range.start.line, edit.new_text))
+            .collect()
+    }
+
+    #[test]
+    fn closing_brace_reindents_block() {
+        let src = "main() {\n    if ok {\n  print(1)\n      print(2)\n        }\n}\n";
+        assert_eq!(
+            edited_lines(src, 4, "}"),
+            vec![
+                (2, "        ".to_string()),
+                (3, "        ".to_string()),
+                (4, "    ".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_indents_new_line() {
+        let src = "main() {\n    items.forEach(x => {\n\n    })\n}\n";
+        assert_eq!(
+            edited_lines(src, 2, "\n"),
+            vec![(2, "        ".to_string())]
+        );
+        // Already right: nothing to do
+        assert!(edited_lines("main() {\n    \n}\n", 1, "\n").is_empty());
+    }
+
+    #[test]
+    fn leaves_multiline_strings_alone() {
+        let src = "main() {\n    let s = \"a\n  b\"\n}\n";
+        assert!(edited_lines(src, 2, "\n").is_empty());
+    }
+}
+
 #[cfg(test)]
 mod rename_helpers_tests {
     use super::find_word_ranges_in_source;
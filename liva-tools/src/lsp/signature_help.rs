@@ -0,0 +1,155 @@
+use tower_lsp::lsp_types::*;
+
+use livac::signatures::Signature;
+
+/// The call the cursor is inside of
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallContext {
+    /// Callee as written: `area`, `Math.pow`, `counter.bump`
+    pub callee: String,
+    /// Index of the argument the cursor is in
+    pub active_parameter: u32,
+}
+
+impl CallContext {
+    /// Method name of a qualified callee (`bump` in `counter.bump`)
+    pub fn method(&self) -> Option<&str> {
+        self.callee.rsplit_once('.').map(|(_, method)| method)
+    }
+}
+
+/// Finds the innermost unclosed call around `offset`, skipping nested
+/// brackets and string literals
+pub fn call_context(text: &str, offset: usize) -> Option<CallContext> {
+    let prefix = &text[..offset];
+    // Open brackets: `(` with the commas seen inside it, or `[`/`{`
+    let mut open: Vec<(char, usize, u32)> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut in_comment = false;
+
+    for (at, c) in prefix.char_indices() {
+        if in_comment {
+            in_comment = c != '\n';
+            continue;
+        }
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '/' if prefix[at + 1..].starts_with('/') => in_comment = true,
+            '(' | '[' | '{' => open.push((c, at, 0)),
+            ')' | ']' | '}' => {
+                open.pop();
+            }
+            ',' => {
+                if let Some((_, _, commas)) = open.last_mut() {
+                    *commas += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    if in_string || in_comment {
+        return None;
+    }
+
+    let (_, open_paren, commas) = *open.last().filter(|(c, _, _)| *c == '(')?;
+    let callee = callee_before(&text[..open_paren])?;
+    Some(CallContext {
+        callee,
+        active_parameter: commas,
+    })
+}
+
+/// The dotted name ending right before a `(`, such as `Math.pow`
+fn callee_before(prefix: &str) -> Option<String> {
+    let trimmed = prefix.trim_end();
+    let start = trimmed
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| c.is_alphanumeric() || c == '_' || c == '.')
+        .last()
+        .map(|(i, _)| i)?;
+    let callee = trimmed[start..].trim_start_matches('.');
+    let first = callee.chars().next()?;
+    let keyword = matches!(
+        callee,
+        "if" | "while" | "for" | "switch" | "return" | "await" | "fail"
+    );
+    (!keyword && (first.is_alphabetic() || first == '_')).then(|| callee.to_string())
+}
+
+/// The LSP form of `signature`, with `active_parameter` highlighted
+pub fn signature_help(signature: &Signature, active_parameter: u32) -> SignatureHelp {
+    let parameters = signature
+        .params
+        .iter()
+        .map(|param| ParameterInformation {
+            label: ParameterLabel::Simple(param.label()),
+            documentation: None,
+        })
+        .collect();
+
+    // Past the last parameter: nothing to highlight
+    let active = (active_parameter < signature.params.len() as u32).then_some(active_parameter);
+    SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label: signature.label(),
+            documentation: None,
+            parameters: Some(parameters),
+            active_parameter: active,
+        }],
+        active_signature: Some(0),
+        active_parameter: active,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context_at_end(text: &str) -> Option<(String, u32)> {
+        call_context(text, text.len()).map(|c| (c.callee, c.active_parameter))
+    }
+
+    #[test]
+    fn test_call_context_counts_top_level_commas() {
+        assert_eq!(
+            context_at_end("let x = Math.pow(2, "),
+            Some(("Math.pow".to_string(), 1))
+        );
+        assert_eq!(
+            context_at_end("area([1, 2], f(3, 4), \"a, b\", "),
+            Some(("area".to_string(), 3))
+        );
+        assert_eq!(context_at_end("area(f(3, "), Some(("f".to_string(), 1)));
+        assert_eq!(context_at_end("area(1, 2)"), None);
+        assert_eq!(
+            context_at_end("print(\"(\""),
+            Some(("print".to_string(), 0))
+        );
+        assert_eq!(context_at_end("let t = (1, "), None);
+        assert_eq!(context_at_end("if (a, "), None);
+    }
+
+    #[test]
+    fn test_signature_help_highlights_active_parameter() {
+        let signature = livac::signatures::builtin("Math.pow").unwrap();
+        let help = signature_help(&signature, 1);
+        assert_eq!(help.active_parameter, Some(1));
+        assert_eq!(
+            help.signatures[0].label,
+            "Math.pow(base: float, exp: float): float"
+        );
+
+        assert_eq!(signature_help(&signature, 2).active_parameter, None);
+    }
+}
@@ -81,7 +81,10 @@ impl SymbolTable {
             | TopLevel::Test(_)
             | TopLevel::ExprStmt(_)
             | TopLevel::Enum(_)
-            | TopLevel::ClassExtension(_) => {
+            | TopLevel::ClassExtension(_)
+            | TopLevel::Runtime(_)
+            | TopLevel::Init(_)
+            | TopLevel::TestHook(_) => {
                 // Skip for now
             }
         }
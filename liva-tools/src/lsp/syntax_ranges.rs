@@ -0,0 +1,387 @@
+use tower_lsp::lsp_types::*;
+
+use livac::ast::*;
+use livac::lexer::{tokenize, Token};
+use livac::span::Span;
+
+use super::document::LineIndex;
+
+/// Folding ranges for every multi-line declaration, block and string literal
+///
+/// Import and comment folding is text-based and lives in the server; this
+/// only covers what the AST knows about.
+pub fn folding_ranges(program: &Program, text: &str) -> Vec<FoldingRange> {
+    let nodes = SyntaxNodes::collect(program, text);
+    let mut ranges: Vec<FoldingRange> = Vec::new();
+
+    for span in nodes.regions.iter().chain(&nodes.strings) {
+        let start = nodes.lines.position(span.start);
+        let end = nodes.lines.position(span.end.saturating_sub(1));
+        if end.line <= start.line {
+            continue;
+        }
+        // A function and its body block usually cover the same lines
+        if ranges
+            .iter()
+            .any(|r| r.start_line == start.line && r.end_line == end.line)
+        {
+            continue;
+        }
+        ranges.push(FoldingRange {
+            start_line: start.line,
+            start_character: Some(start.character),
+            end_line: end.line,
+            end_character: Some(end.character),
+            kind: Some(FoldingRangeKind::Region),
+            collapsed_text: None,
+        });
+    }
+
+    ranges.sort_by_key(|r| (r.start_line, r.end_line));
+    ranges
+}
+
+/// For each position, the chain of enclosing syntax nodes: word → statement →
+/// block → enclosing statement → … → declaration → document
+pub fn selection_ranges(
+    program: &Program,
+    text: &str,
+    positions: &[Position],
+) -> Vec<SelectionRange> {
+    let nodes = SyntaxNodes::collect(program, text);
+    let document = Range {
+        start: Position {
+            line: 0,
+            character: 0,
+        },
+        end: nodes.lines.position(text.len()),
+    };
+
+    positions
+        .iter()
+        .map(|&position| {
+            let offset = nodes.lines.offset(position);
+            let mut enclosing: Vec<Span> = nodes
+                .regions
+                .iter()
+                .chain(&nodes.statements)
+                .chain(&nodes.strings)
+                .copied()
+                .chain(word_span(text, offset))
+                .filter(|span| span.start <= offset && offset <= span.end)
+                .collect();
+            enclosing.sort_by_key(|span| std::cmp::Reverse(span.end - span.start));
+
+            let mut chain = SelectionRange {
+                range: document,
+                parent: None,
+            };
+            let mut outer = Span::new(0, text.len());
+            for span in enclosing {
+                // Statement extents are line-based, so only keep properly nested nodes
+                let nested = outer.start <= span.start && span.end <= outer.end && span != outer;
+                if !nested {
+                    continue;
+                }
+                outer = span;
+                chain = SelectionRange {
+                    range: Range {
+                        start: nodes.lines.position(span.start),
+                        end: nodes.lines.position(span.end),
+                    },
+                    parent: Some(Box::new(chain)),
+                };
+            }
+            chain
+        })
+        .collect()
+}
+
+/// Identifier around `offset`, if any
+fn word_span(text: &str, offset: usize) -> Option<Span> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word(c))
+        .last()
+        .map_or(offset, |(i, _)| i);
+    let end = text[offset..]
+        .char_indices()
+        .find(|&(_, c)| !is_word(c))
+        .map_or(text.len(), |(i, _)| offset + i);
+    (end > start).then(|| Span::new(start, end))
+}
+
+/// Byte spans of the syntax nodes editors care about
+struct SyntaxNodes<'t> {
+    lines: LineIndex<'t>,
+    /// Declarations and `{ }` blocks
+    regions: Vec<Span>,
+    /// Statements directly inside multi-line blocks, from their first to their
+    /// last non-blank line
+    statements: Vec<Span>,
+    /// String literals and templates
+    strings: Vec<Span>,
+}
+
+impl<'t> SyntaxNodes<'t> {
+    fn collect(program: &Program, text: &'t str) -> Self {
+        let mut nodes = Self {
+            lines: LineIndex::new(text),
+            regions: Vec::new(),
+            statements: Vec::new(),
+            strings: Vec::new(),
+        };
+
+        for item in &program.items {
+            match item {
+                TopLevel::Function(func) => {
+                    nodes.regions.extend(func.span);
+                    nodes.body(func.body.as_ref(), func.expr_body.as_ref());
+                }
+                TopLevel::Class(class) => {
+                    nodes.regions.extend(class.span);
+                    for member in &class.members {
+                        if let Member::Method(method) = member {
+                            nodes.method(method);
+                        }
+                    }
+                }
+                TopLevel::Type(type_decl) => nodes.regions.extend(type_decl.span),
+                TopLevel::ClassExtension(extension) => {
+                    nodes.regions.extend(extension.span);
+                    extension.methods.iter().for_each(|m| nodes.method(m));
+                }
+                TopLevel::Test(test) => nodes.block(&test.body),
+                TopLevel::Init(init) => nodes.block(&init.body),
+                TopLevel::TestHook(hook) => nodes.block(&hook.body),
+                _ => {}
+            }
+        }
+
+        if let Ok(tokens) = tokenize(text) {
+            nodes.strings = tokens
+                .iter()
+                .filter(|t| matches!(t.token, Token::StringLiteral(_) | Token::StringTemplate(_)))
+                .map(|t| t.span)
+                .collect();
+        }
+
+        nodes
+    }
+
+    fn method(&mut self, method: &MethodDecl) {
+        self.regions.extend(method.span);
+        // One-liner methods also get a synthesized block, without a span
+        match &method.expr_body {
+            Some(expr) => self.expr(expr),
+            None => self.body(method.body.as_ref(), None),
+        }
+    }
+
+    fn body(&mut self, block: Option<&BlockStmt>, expr: Option<&Expr>) {
+        if let Some(block) = block {
+            self.block(block);
+        }
+        if let Some(expr) = expr {
+            self.expr(expr);
+        }
+    }
+
+    fn block(&mut self, block: &BlockStmt) {
+        if let Some(span) = block.span {
+            self.regions.push(span);
+            self.statement_extents(block, span);
+        }
+        block.stmts.iter().for_each(|stmt| self.stmt(stmt));
+    }
+
+    /// Statements have no spans, only the line they start on: each one runs
+    /// to the line before the next statement or the closing brace
+    fn statement_extents(&mut self, block: &BlockStmt, span: Span) {
+        let open_line = self.lines.position(span.start).line as usize;
+        let close_line = self.lines.position(span.end.saturating_sub(1)).line as usize;
+
+        for (i, &line) in block.stmt_lines.iter().enumerate() {
+            let first = (line as usize).saturating_sub(1);
+            if first <= open_line || first >= close_line {
+                continue;
+            }
+            let next = block
+                .stmt_lines
+                .get(i + 1)
+                .map_or(close_line, |&l| (l as usize).saturating_sub(1));
+            let mut last = next.saturating_sub(1).max(first);
+            while last > first && self.lines.line(last).trim().is_empty() {
+                last -= 1;
+            }
+
+            let first_text = self.lines.line(first);
+            let last_text = self.lines.line(last);
+            let start =
+                self.lines.line_start(first) + first_text.len() - first_text.trim_start().len();
+            let end = self.lines.line_start(last) + last_text.trim_end().len();
+            if end > start {
+                self.statements.push(Span::new(start, end));
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl(decl) => self.expr(&decl.init),
+            Stmt::ConstDecl(decl) => self.expr(&decl.init),
+            Stmt::Assign(assign) => self.expr(&assign.value),
+            Stmt::If(if_stmt) => {
+                for (condition, body) in if_stmt.branches() {
+                    self.expr(condition);
+                    self.if_body(body);
+                }
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.if_body(else_branch);
+                }
+            }
+            Stmt::While(while_stmt) => self.block(&while_stmt.body),
+            Stmt::For(for_stmt) => {
+                self.expr(&for_stmt.iterable);
+                self.block(&for_stmt.body);
+            }
+            Stmt::Switch(switch) => {
+                for case in &switch.cases {
+                    case.body.iter().for_each(|s| self.stmt(s));
+                }
+                if let Some(default) = &switch.default {
+                    default.iter().for_each(|s| self.stmt(s));
+                }
+            }
+            Stmt::TryCatch(try_catch) => {
+                self.block(&try_catch.try_block);
+                self.block(&try_catch.catch_block);
+            }
+            Stmt::Return(ret) => {
+                if let Some(expr) = &ret.expr {
+                    self.expr(expr);
+                }
+            }
+            Stmt::Defer(defer) => self.stmt(&defer.body),
+            Stmt::Expr(expr_stmt) => self.expr(&expr_stmt.expr),
+            Stmt::Block(block) => self.block(block),
+            Stmt::Scope(scope) => self.block(&scope.body),
+            Stmt::Mock(mock) => self.expr(&mock.value),
+            Stmt::Function(func) => {
+                self.regions.extend(func.span);
+                self.body(func.body.as_ref(), func.expr_body.as_ref());
+            }
+            Stmt::Throw(_) | Stmt::Fail(_) | Stmt::Break | Stmt::Continue => {}
+        }
+    }
+
+    fn if_body(&mut self, body: &IfBody) {
+        match body {
+            IfBody::Block(block) => self.block(block),
+            IfBody::Stmt(stmt) => self.stmt(stmt),
+        }
+    }
+
+    /// Only lambdas, switch arms and `if` expressions hold blocks inside expressions
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Lambda(lambda) => match &lambda.body {
+                LambdaBody::Expr(body) => self.expr(body),
+                LambdaBody::Block(block) => self.block(block),
+            },
+            Expr::Call(call) => call.args.iter().for_each(|a| self.expr(a)),
+            Expr::MethodCall(call) => {
+                self.expr(&call.object);
+                call.args.iter().for_each(|a| self.expr(a));
+            }
+            Expr::Switch(switch) => {
+                for arm in &switch.arms {
+                    match &arm.body {
+                        SwitchBody::Expr(e) => self.expr(e),
+                        SwitchBody::Block(stmts) => stmts.iter().for_each(|s| self.stmt(s)),
+                    }
+                }
+            }
+            Expr::If(if_expr) => {
+                for branch in [&if_expr.then_branch, &if_expr.else_branch] {
+                    match branch {
+                        SwitchBody::Expr(e) => self.expr(e),
+                        SwitchBody::Block(stmts) => stmts.iter().for_each(|s| self.stmt(s)),
+                    }
+                }
+            }
+            Expr::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Ternary {
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                self.expr(then_expr);
+                self.expr(else_expr);
+            }
+            Expr::ArrayLiteral(items) | Expr::Tuple(items) => {
+                items.iter().for_each(|i| self.expr(i));
+            }
+            Expr::ObjectLiteral(fields) | Expr::StructLiteral { fields, .. } => {
+                fields.iter().for_each(|(_, v)| self.expr(v));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "Counter {\n    count: number\n\n    bump(by: number) {\n        if by > 0 {\n            this.count = this.count + by\n        }\n    }\n}\n\nmain() {\n    let banner = \"multi\nline\"\n    print(banner)\n}\n";
+
+    fn program() -> Program {
+        livac::parser::parse(tokenize(SOURCE).unwrap(), SOURCE).unwrap()
+    }
+
+    #[test]
+    fn test_folds_declarations_blocks_and_strings() {
+        let lines: Vec<(u32, u32)> = folding_ranges(&program(), SOURCE)
+            .iter()
+            .map(|r| (r.start_line, r.end_line))
+            .collect();
+        assert_eq!(lines, vec![(0, 8), (3, 7), (4, 6), (10, 14), (11, 12)]);
+    }
+
+    #[test]
+    fn test_selection_expands_by_syntax_node() {
+        let position = Position {
+            line: 5,
+            character: 31,
+        };
+        let mut ranges = Vec::new();
+        let mut current = Some(Box::new(
+            selection_ranges(&program(), SOURCE, &[position]).remove(0),
+        ));
+        while let Some(selection) = current {
+            let r = selection.range;
+            ranges.push((r.start.line, r.start.character, r.end.line, r.end.character));
+            current = selection.parent;
+        }
+
+        assert_eq!(
+            ranges,
+            vec![
+                (5, 30, 5, 35), // count
+                (5, 12, 5, 40), // this.count = this.count + by
+                (4, 18, 6, 9),  // { ... } of the if
+                (4, 8, 6, 9),   // if by > 0 { ... }
+                (3, 21, 7, 5),  // method body
+                (3, 4, 7, 5),   // bump(...) { ... }
+                (0, 0, 8, 1),   // Counter { ... }
+                (0, 0, 15, 0),  // document
+            ]
+        );
+    }
+}
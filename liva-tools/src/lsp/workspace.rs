@@ -1,8 +1,11 @@
 use dashmap::DashMap;
 use livac::ast::{Member, Program, TopLevel};
+use livac::signatures::{self, Signature};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use tower_lsp::lsp_types::*;
 
+use super::hierarchy::FileHierarchy;
 use super::symbols::{Symbol, SymbolTable};
 
 /// Metadata about a file in the workspace
@@ -175,6 +178,18 @@ pub struct WorkspaceIndex {
     /// Used by `goto_implementation` to jump from an interface or method
     /// declaration to all concrete implementors in the workspace.
     implementations: DashMap<String, Vec<(Url, Symbol)>>,
+
+    /// URI -> files it imports, from its last successful analysis.
+    /// Walked backwards by `dependents_of` to find what an edit invalidates.
+    dependencies: DashMap<Url, HashSet<Url>>,
+
+    /// URI -> functions, methods, calls and types declared in that file.
+    /// Backs the call and type hierarchy requests.
+    hierarchies: DashMap<Url, FileHierarchy>,
+
+    /// URI -> parameter lists of the functions and methods declared in that
+    /// file. Backs signature help for calls into other files.
+    signatures: DashMap<Url, Vec<Signature>>,
 }
 
 impl WorkspaceIndex {
@@ -184,7 +199,42 @@ impl WorkspaceIndex {
             symbols: DashMap::new(),
             file_symbols: DashMap::new(),
             implementations: DashMap::new(),
+            dependencies: DashMap::new(),
+            hierarchies: DashMap::new(),
+            signatures: DashMap::new(),
+        }
+    }
+
+    /// Records the files `uri` imports, replacing what was recorded before
+    pub fn set_dependencies(&self, uri: &Url, imports: impl IntoIterator<Item = Url>) {
+        self.dependencies
+            .insert(uri.clone(), imports.into_iter().collect());
+    }
+
+    /// Files that import `uri`, directly or through other files, in the order
+    /// they were reached. `uri` itself is never included, even in a cycle.
+    pub fn dependents_of(&self, uri: &Url) -> Vec<Url> {
+        let mut dependents = Vec::new();
+        let mut seen: HashSet<Url> = HashSet::from([uri.clone()]);
+        let mut frontier = vec![uri.clone()];
+
+        while let Some(current) = frontier.pop() {
+            let mut importers: Vec<Url> = self
+                .dependencies
+                .iter()
+                .filter(|entry| entry.value().contains(&current))
+                .map(|entry| entry.key().clone())
+                .collect();
+            importers.sort();
+            for importer in importers {
+                if seen.insert(importer.clone()) {
+                    dependents.push(importer.clone());
+                    frontier.push(importer);
+                }
+            }
         }
+
+        dependents
     }
 
     /// Indexes a file and adds its symbols to the global index.
@@ -223,6 +273,10 @@ impl WorkspaceIndex {
         // on a method name also resolves). The class name lookups go through
         // the regular symbol table.
         self.index_implementations(&uri, ast, source);
+
+        self.hierarchies
+            .insert(uri.clone(), FileHierarchy::from_ast(ast, source));
+        self.signatures.insert(uri, signatures::collect(ast));
     }
 
     fn index_implementations(&self, uri: &Url, ast: &Program, source: &str) {
@@ -309,6 +363,9 @@ impl WorkspaceIndex {
 
     /// Removes a file from the index
     pub fn remove_file(&self, uri: &Url) {
+        self.hierarchies.remove(uri);
+        self.signatures.remove(uri);
+
         // Remove the file's symbol table
         if let Some((_, table)) = self.file_symbols.remove(uri) {
             // Remove all symbols from this file from the global index
@@ -365,6 +422,28 @@ impl WorkspaceIndex {
         self.file_symbols.contains_key(uri)
     }
 
+    /// Snapshot of every file's hierarchy data, ordered by URI
+    pub fn hierarchies(&self) -> Vec<(Url, FileHierarchy)> {
+        let mut files: Vec<(Url, FileHierarchy)> = self
+            .hierarchies
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        files
+    }
+
+    /// Signature of the function or `Class.method` called `name`, from the
+    /// first file (by URI) that declares one
+    pub fn lookup_signature(&self, name: &str) -> Option<Signature> {
+        let mut files: Vec<Url> = self.signatures.iter().map(|e| e.key().clone()).collect();
+        files.sort();
+        files.iter().find_map(|uri| {
+            let file = self.signatures.get(uri)?;
+            file.iter().find(|s| s.name == name).cloned()
+        })
+    }
+
     /// Lists all indexed file URIs
     pub fn indexed_files(&self) -> Vec<Url> {
         self.file_symbols
@@ -383,7 +462,7 @@ impl Default for WorkspaceIndex {
 /// Find the LSP range for a class header by scanning the source for the
 /// class name followed by `:` or `{`. Returns `None` if the class header
 /// cannot be located (in that case the caller falls back to a default range).
-fn locate_class_header(name: &str, source: &str) -> Option<Range> {
+pub(crate) fn locate_class_header(name: &str, source: &str) -> Option<Range> {
     for (line_idx, line) in source.lines().enumerate() {
         let trimmed = line.trim_start();
         if !trimmed.starts_with(name) {
@@ -415,7 +494,7 @@ fn locate_class_header(name: &str, source: &str) -> Option<Range> {
 /// Best-effort: scans the source after the class header for the first
 /// `methodName(` occurrence and returns its range. Returns `None` if not
 /// found. Does not attempt to handle nested classes (Liva has none).
-fn locate_method(class_name: &str, method_name: &str, source: &str) -> Option<Range> {
+pub(crate) fn locate_method(class_name: &str, method_name: &str, source: &str) -> Option<Range> {
     let mut in_class = false;
     let needle = format!("{}(", method_name);
     for (line_idx, line) in source.lines().enumerate() {
@@ -508,4 +587,26 @@ mod workspace_index_tests {
             "duplicate workspace-symbol entries after reindex"
         );
     }
+
+    #[test]
+    fn test_dependents_of_follows_imports_transitively() {
+        let index = WorkspaceIndex::new();
+        let uri = |name: &str| Url::parse(&format!("file:///tmp/{}.liva", name)).unwrap();
+
+        // main -> api -> math, and math -> api closes a cycle
+        index.set_dependencies(&uri("main"), [uri("api")]);
+        index.set_dependencies(&uri("api"), [uri("math")]);
+        index.set_dependencies(&uri("math"), [uri("api")]);
+        index.set_dependencies(&uri("other"), []);
+
+        assert_eq!(
+            index.dependents_of(&uri("math")),
+            vec![uri("api"), uri("main")]
+        );
+        assert!(index.dependents_of(&uri("main")).is_empty());
+
+        // Dropping the import from main cuts it off
+        index.set_dependencies(&uri("main"), []);
+        assert_eq!(index.dependents_of(&uri("math")), vec![uri("api")]);
+    }
 }
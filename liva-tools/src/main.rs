@@ -1,8 +1,9 @@
 //! `liva-tools` — CLI dispatcher for Liva developer tooling.
 //!
 //! Subcommands:
-//!   - `fmt <file> [--check] [--verbose]`
-//!   - `lint <file> [--json]`
+//!   - `fmt <file> [--check] [--verbose] [--organize-imports]`
+//!   - `lint <file> [--json] [--naming camel|snake]`
+//!   - `migrate <file> [--dry-run]`
 //!   - `lsp`
 //!
 //! Called as a subprocess by the `livac` binary so the (eventually frozen)
@@ -13,7 +14,8 @@ use colored::Colorize;
 use std::path::PathBuf;
 use std::process::ExitCode;
 
-use liva_tools::{formatter, linter, lsp};
+use liva_tools::{formatter, linter, lsp, migrate};
+use livac::package::{Manifest, NamingConvention};
 
 #[derive(Parser)]
 #[command(name = "liva-tools", version, about = "Liva developer tools")]
@@ -31,12 +33,25 @@ enum Commands {
         check: bool,
         #[arg(short, long)]
         verbose: bool,
+        /// Also sort, merge and deduplicate import statements
+        #[arg(long)]
+        organize_imports: bool,
     },
     /// Lint a Liva source file
     Lint {
         input: PathBuf,
         #[arg(long)]
         json: bool,
+        /// Case that names must be written in: camel or snake (W013)
+        #[arg(long)]
+        naming: Option<NamingConvention>,
+    },
+    /// Rewrite syntax removed in newer livac versions
+    Migrate {
+        input: PathBuf,
+        /// Show the changes without writing the file
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Run the Liva language server (stdio)
     Lsp,
@@ -49,14 +64,26 @@ fn main() -> ExitCode {
             input,
             check,
             verbose,
-        } => match run_format(&input, check, verbose) {
+            organize_imports,
+        } => match run_format(&input, check, verbose, organize_imports) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                ExitCode::from(1)
+            }
+        },
+        Commands::Lint {
+            input,
+            json,
+            naming,
+        } => ExitCode::from(run_lint(&input, json, naming) as u8),
+        Commands::Migrate { input, dry_run } => match run_migrate(&input, dry_run) {
             Ok(()) => ExitCode::SUCCESS,
             Err(e) => {
                 eprintln!("{} {}", "Error:".red().bold(), e);
                 ExitCode::from(1)
             }
         },
-        Commands::Lint { input, json } => ExitCode::from(run_lint(&input, json) as u8),
         Commands::Lsp => match run_lsp_server() {
             Ok(()) => ExitCode::SUCCESS,
             Err(e) => {
@@ -67,7 +94,7 @@ fn main() -> ExitCode {
     }
 }
 
-fn run_lint(input: &PathBuf, json: bool) -> i32 {
+fn run_lint(input: &PathBuf, json: bool, naming: Option<NamingConvention>) -> i32 {
     let source = match std::fs::read_to_string(input) {
         Ok(s) => s,
         Err(e) => {
@@ -106,7 +133,10 @@ fn run_lint(input: &PathBuf, json: bool) -> i32 {
         }
     };
 
-    let warnings = linter::lint(&ast, filename, &source);
+    let config = linter::LintConfig {
+        naming: naming.or_else(|| Manifest::lint_settings_for(input).naming),
+    };
+    let warnings = linter::lint_with_config(&ast, filename, &source, &config);
 
     if json {
         println!("{}", linter::format_warnings_json(&warnings));
@@ -123,16 +153,23 @@ fn run_format(
     input: &PathBuf,
     check_only: bool,
     verbose: bool,
+    organize_imports: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let options = formatter::FormatOptions::default();
     let source = std::fs::read_to_string(input)?;
+    let format = |source: &str| {
+        if organize_imports {
+            formatter::organize_imports(source, &options)
+        } else {
+            formatter::format_source(source, &options)
+        }
+    };
 
     if check_only {
-        let is_formatted = formatter::check_format(&source, &options)?;
-        if is_formatted {
+        let formatted = format(&source)?;
+        if formatted == source {
             println!("{} {}", "✓".green(), input.display());
         } else {
-            let formatted = formatter::format_source(&source, &options)?;
             println!("{} {} (needs formatting)", "✗".red(), input.display());
             if verbose {
                 println!();
@@ -143,7 +180,7 @@ fn run_format(
             std::process::exit(1);
         }
     } else {
-        let formatted = formatter::format_source(&source, &options)?;
+        let formatted = format(&source)?;
         if formatted == source {
             println!("{} {} (already formatted)", "✓".green(), input.display());
         } else {
@@ -155,6 +192,49 @@ fn run_format(
     Ok(())
 }
 
+fn run_migrate(input: &PathBuf, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let source = std::fs::read_to_string(input)?;
+    let migration = migrate::migrate(&source, &formatter::FormatOptions::default())?;
+
+    if migration.changes.is_empty() {
+        println!("{} {} (nothing to migrate)", "✓".green(), input.display());
+        return Ok(());
+    }
+    for change in &migration.changes {
+        let marker = if change.applied {
+            "✓".green()
+        } else {
+            "!".yellow()
+        };
+        println!(
+            "  {} L{} [{}] {}",
+            marker,
+            change.line,
+            change.rule.name(),
+            change.description
+        );
+    }
+
+    if !migration.is_changed() {
+        println!("{} {} (nothing to migrate)", "✓".green(), input.display());
+    } else if dry_run {
+        println!();
+        for diff in simple_diff(&source, &migration.source) {
+            println!("{}", diff);
+        }
+        println!(
+            "{} {} (dry run, not written)",
+            "~".yellow(),
+            input.display()
+        );
+    } else {
+        std::fs::write(input, &migration.source)?;
+        println!("{} {} (migrated)", "✓".green().bold(), input.display());
+    }
+
+    Ok(())
+}
+
 fn run_lsp_server() -> Result<(), Box<dyn std::error::Error>> {
     let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(async {
@@ -0,0 +1,232 @@
+/// Migration of Liva sources written for older livac versions
+///
+/// Rewrites syntax that has since been removed from the language, then hands
+/// the result to the formatter so the file comes out in canonical style.
+///
+/// ## Rules
+///
+/// - **data-keyword** (v1.3): `data Point { ... }` → `Point { ... }`; classes
+///   without a constructor are data classes automatically
+/// - **fire-keyword** (v1.3): `fire async f()` → `async f()`; an `async` or
+///   `par` call used as a statement is already fire-and-forget
+/// - **double-underscore** (v0.6): `__ssn` → `_ssn`; the `_` prefix alone
+///   means private. Skipped when the new name is already taken.
+///
+/// The current parser rejects the first two, so they are rewritten on the
+/// token stream before parsing. Renames touch only identifier tokens, which
+/// leaves comments, strings and `rust { }` blocks alone.
+use livac::error::Result;
+use livac::lexer::{self, Token, TokenWithSpan};
+use std::collections::HashSet;
+
+use crate::formatter::{self, FormatOptions};
+
+/// A legacy syntax rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    DataKeyword,
+    FireKeyword,
+    DoubleUnderscore,
+}
+
+impl Rule {
+    /// Rule name as shown in reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::DataKeyword => "data-keyword",
+            Rule::FireKeyword => "fire-keyword",
+            Rule::DoubleUnderscore => "double-underscore",
+        }
+    }
+}
+
+/// One rewrite found in the original source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub rule: Rule,
+    /// 1-based line in the original source
+    pub line: usize,
+    pub description: String,
+    /// False when the rewrite was found but not applied
+    pub applied: bool,
+}
+
+/// Result of migrating one source file
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// The migrated source; the original, untouched, when nothing applied
+    pub source: String,
+    pub changes: Vec<Change>,
+}
+
+impl Migration {
+    /// Whether any rewrite was applied
+    pub fn is_changed(&self) -> bool {
+        self.changes.iter().any(|change| change.applied)
+    }
+}
+
+/// Migrates `source` to the current syntax and formats it
+pub fn migrate(source: &str, options: &FormatOptions) -> Result<Migration> {
+    let tokens = lexer::tokenize(source)?;
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut changes = Vec::new();
+    let line_of = |offset: usize| source[..offset].matches('\n').count() + 1;
+
+    let names: HashSet<&str> = tokens
+        .iter()
+        .filter_map(|t| match &t.token {
+            Token::Ident(name) | Token::PrivateIdent(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut renamed: HashSet<&str> = HashSet::new();
+
+    for (i, tok) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1);
+        match &tok.token {
+            Token::Ident(word) if word == "data" && is_data_class_header(source, &tokens, i) => {
+                let class = ident(&tokens[i + 1]).unwrap_or_default();
+                edits.push((tok.span.start, removal_end(tok, next), String::new()));
+                changes.push(Change {
+                    rule: Rule::DataKeyword,
+                    line: line_of(tok.span.start),
+                    description: format!("removed `data` before class `{}`", class),
+                    applied: true,
+                });
+            }
+            Token::Ident(word)
+                if word == "fire"
+                    && matches!(next.map(|t| &t.token), Some(Token::Async | Token::Par)) =>
+            {
+                edits.push((tok.span.start, removal_end(tok, next), String::new()));
+                changes.push(Change {
+                    rule: Rule::FireKeyword,
+                    line: line_of(tok.span.start),
+                    description: "removed `fire`".to_string(),
+                    applied: true,
+                });
+            }
+            Token::PrivateIdent(name) if name.starts_with("__") && name.len() > 2 => {
+                let new_name = &name[1..];
+                let taken = names.contains(new_name);
+                if !taken {
+                    edits.push((tok.span.start, tok.span.end, new_name.to_string()));
+                }
+                // Report each name once, at its first occurrence
+                if renamed.insert(name.as_str()) {
+                    changes.push(Change {
+                        rule: Rule::DoubleUnderscore,
+                        line: line_of(tok.span.start),
+                        description: if taken {
+                            format!("kept `{}`: `{}` is already used", name, new_name)
+                        } else {
+                            format!("renamed `{}` to `{}`", name, new_name)
+                        },
+                        applied: !taken,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if edits.is_empty() {
+        return Ok(Migration {
+            source: source.to_string(),
+            changes,
+        });
+    }
+
+    let mut rewritten = source.to_string();
+    for (start, end, text) in edits.into_iter().rev() {
+        rewritten.replace_range(start..end, &text);
+    }
+    Ok(Migration {
+        source: formatter::format_source(&rewritten, options)?,
+        changes,
+    })
+}
+
+/// `data` starting a line, followed by a class name and its `{`, `:` or `<`
+fn is_data_class_header(source: &str, tokens: &[TokenWithSpan], i: usize) -> bool {
+    let line_start = source[..tokens[i].span.start]
+        .rfind('\n')
+        .map_or(0, |n| n + 1);
+    let starts_line = source[line_start..tokens[i].span.start].trim().is_empty();
+    starts_line
+        && tokens.get(i + 1).and_then(ident).is_some()
+        && matches!(
+            tokens.get(i + 2).map(|t| &t.token),
+            Some(Token::LBrace | Token::Colon | Token::Lt)
+        )
+}
+
+/// End of a removed keyword, taking the whitespace after it along
+fn removal_end(tok: &TokenWithSpan, next: Option<&TokenWithSpan>) -> usize {
+    next.map_or(tok.span.end, |t| t.span.start)
+}
+
+fn ident(tok: &TokenWithSpan) -> Option<&str> {
+    match &tok.token {
+        Token::Ident(name) => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Migration {
+        migrate(source, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_rewrites_legacy_syntax() {
+        let source = "// A point\ndata Point {\n    x: number\n    y: number\n}\n\nPerson {\n    name: string\n    __ssn: string\n\n    masked() => this.__ssn\n}\n\nmain() {\n    let data = 1\n    fire async print(\"data\")\n}\n";
+        let migration = run(source);
+
+        let rules: Vec<(Rule, usize)> =
+            migration.changes.iter().map(|c| (c.rule, c.line)).collect();
+        assert_eq!(
+            rules,
+            vec![
+                (Rule::DataKeyword, 2),
+                (Rule::DoubleUnderscore, 9),
+                (Rule::FireKeyword, 16),
+            ]
+        );
+        assert!(migration.is_changed());
+        assert!(
+            migration.source.contains("// A point\nPoint {"),
+            "{}",
+            migration.source
+        );
+        assert!(migration.source.contains("_ssn: string"));
+        assert!(migration.source.contains("this._ssn"));
+        assert!(!migration.source.contains("__ssn"));
+        assert!(!migration.source.contains("fire"));
+        // `data` as a variable name and inside strings stays
+        assert!(migration.source.contains("let data = 1"));
+        assert!(migration.source.contains("print(\"data\")"));
+    }
+
+    #[test]
+    fn test_clashing_rename_is_skipped() {
+        let source = "Account {\n    _id: number\n    __id: number\n}\n";
+        let migration = run(source);
+        assert_eq!(migration.changes.len(), 1);
+        assert!(!migration.changes[0].applied);
+        assert!(!migration.is_changed());
+        assert_eq!(migration.source, source);
+    }
+
+    #[test]
+    fn test_current_source_is_untouched() {
+        let source = "main() {\n  print(\"hi\")\n}\n";
+        let migration = run(source);
+        assert!(migration.changes.is_empty());
+        assert_eq!(migration.source, source);
+    }
+}
@@ -8,6 +8,19 @@ fn lint_source(source: &str) -> Vec<linter::LintWarning> {
     linter::lint(&ast, "test.liva", source)
 }
 
+/// Helper: like `lint_source`, with the naming check (W013) on
+fn lint_naming(source: &str, naming: livac::package::NamingConvention) -> Vec<linter::LintWarning> {
+    let tokens = livac::lexer::tokenize(source).expect("tokenize failed");
+    let ast = livac::parser::parse(tokens, source).expect("parse failed");
+    let config = linter::LintConfig {
+        naming: Some(naming),
+    };
+    linter::lint_with_config(&ast, "test.liva", source, &config)
+        .into_iter()
+        .filter(|w| w.code == "W013")
+        .collect()
+}
+
 /// Helper: get warning codes from a source
 fn lint_codes(source: &str) -> Vec<String> {
     lint_source(source).iter().map(|w| w.code.clone()).collect()
@@ -646,3 +659,262 @@ scan(items: [int]): int {
     assert!(codes.iter().any(|c| c == "W008"));
 }
 
+// ─── W009: Copied value modified ────────────────────────────────
+
+#[test]
+fn w009_modified_copy() {
+    let warnings = lint_source(
+        r#"
+main() {
+    let items = [1, 2]
+    let more = items
+    more.push(3)
+    print(items, more)
+}
+"#,
+    );
+    let w009: Vec<_> = warnings.iter().filter(|w| w.code == "W009").collect();
+    assert_eq!(w009.len(), 1);
+    assert_eq!(w009[0].line, 4);
+    assert!(w009[0].message.contains("'more' is a copy of 'items'"));
+    assert!(w009[0].help.as_deref().unwrap().contains("clone(items)"));
+}
+
+#[test]
+fn w009_source_modified_after_copy() {
+    let codes = lint_codes(
+        r#"
+shift(origin: Point) {
+    let start = origin
+    origin.x = 5
+    print(start, origin)
+}
+"#,
+    );
+    assert!(codes.iter().any(|c| c == "W009"));
+}
+
+#[test]
+fn w009_no_warning_for_explicit_clone_or_scalars() {
+    let codes = lint_codes(
+        r#"
+main() {
+    let items = [1, 2]
+    let more = clone(items)
+    more.push(3)
+    let n = 1
+    let m = n
+    print(items, more, m)
+}
+"#,
+    );
+    assert!(!codes.iter().any(|c| c == "W009"));
+}
+
+// ─── W010: If chain could be a switch ───────────────────────────
+
+#[test]
+fn w010_chain_on_one_subject() {
+    let warnings = lint_source(
+        r#"
+describe(status: string): string {
+    if status == "active" {
+        return "Active"
+    } else if status == "paused" || status == "held" {
+        return "Paused"
+    } else if status == "closed" {
+        return "Closed"
+    }
+    return "Unknown"
+}
+"#,
+    );
+    let w010: Vec<_> = warnings.iter().filter(|w| w.code == "W010").collect();
+    assert_eq!(w010.len(), 1);
+    assert_eq!(w010[0].line, 3);
+    assert!(w010[0].message.contains("'status'"));
+    assert!(w010[0].help.as_deref().unwrap().contains("switch status"));
+}
+
+#[test]
+fn w010_no_warning_for_short_or_mixed_chains() {
+    let codes = lint_codes(
+        r#"
+classify(n: int, m: int): string {
+    if n == 1 {
+        return "one"
+    } else if n == 2 {
+        return "two"
+    }
+    if n == 1 {
+        return "one"
+    } else if m == 2 {
+        return "two"
+    } else if n == 3 {
+        return "three"
+    }
+    if n < 0 {
+        return "negative"
+    } else if n == 0 {
+        return "zero"
+    } else if n == 1 {
+        return "one"
+    }
+    return "many"
+}
+"#,
+    );
+    assert!(!codes.iter().any(|c| c == "W010"));
+}
+
+// ─── W011: Floor division written as a comment ──────────────────
+
+#[test]
+fn w011_python_floor_division() {
+    let warnings = lint_source(
+        r#"
+half(total: int): int {
+    let result = total // 2
+    return result
+}
+"#,
+    );
+    let w011: Vec<_> = warnings.iter().filter(|w| w.code == "W011").collect();
+    assert_eq!(w011.len(), 1);
+    assert_eq!(w011[0].line, 3);
+    let help = w011[0].help.as_deref().unwrap();
+    assert!(help.contains("Math.floorDiv(value, 2)"));
+}
+
+#[test]
+fn w011_no_warning_for_ordinary_comments() {
+    let codes = lint_codes(
+        r#"
+// 2
+main() {
+    let timeout = 30 // seconds
+    let retries = 3 // 3 is plenty
+    let kib = 2 ** 10   // 1024
+    print(timeout, retries, kib)
+}
+"#,
+    );
+    assert!(!codes.iter().any(|c| c == "W011"));
+}
+
+// ─── W012: Remainder of a negative number ───────────────────────
+
+#[test]
+fn w012_negated_operand() {
+    let warnings = lint_source(
+        r#"
+main(n: int) {
+    let a = -n % 3
+    let b = n % -3
+    let c = n - 7 % 3
+    print(a, b, c)
+}
+"#,
+    );
+    let lines: Vec<usize> = warnings
+        .iter()
+        .filter(|w| w.code == "W012")
+        .map(|w| w.line)
+        .collect();
+    assert_eq!(lines, vec![3, 4]);
+}
+
+// ─── W013: Naming convention ────────────────────────────────────
+
+const MIXED_NAMES: &str = r#"
+const MAX_SIZE = 10
+const defaultName = "x"
+
+Shopping_cart {
+    constructor() {}
+    itemCount: number
+    add_item(unitPrice: number) {
+        let sub_total = unitPrice * 2
+        print(sub_total)
+    }
+}
+
+enum Color { Red, dark_blue }
+
+computeTotal(item_count: number) {
+    let _unitPrice = 3
+    return item_count * _unitPrice
+}
+"#;
+
+#[test]
+fn w013_camel_case() {
+    let warnings = lint_naming(MIXED_NAMES, livac::package::NamingConvention::Camel);
+    let found: Vec<(&str, usize)> = warnings
+        .iter()
+        .map(|w| (w.message.as_str(), w.line))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("Class 'Shopping_cart' is not PascalCase", 5),
+            ("Method 'add_item' is not camelCase", 8),
+            ("Variable 'sub_total' is not camelCase", 9),
+            ("Enum variant 'dark_blue' is not PascalCase", 14),
+            ("Parameter 'item_count' is not camelCase", 16),
+        ]
+    );
+    assert_eq!(
+        warnings[0].help.as_deref(),
+        Some("Rename it to ShoppingCart")
+    );
+    assert_eq!(warnings[1].help.as_deref(), Some("Rename it to addItem"));
+}
+
+#[test]
+fn w013_snake_case() {
+    let warnings = lint_naming(MIXED_NAMES, livac::package::NamingConvention::Snake);
+    let found: Vec<(&str, Option<&str>)> = warnings
+        .iter()
+        .map(|w| (w.message.as_str(), w.help.as_deref()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                "Constant 'defaultName' is not snake_case",
+                Some("Rename it to default_name")
+            ),
+            (
+                "Class 'Shopping_cart' is not PascalCase",
+                Some("Rename it to ShoppingCart")
+            ),
+            (
+                "Field 'itemCount' is not snake_case",
+                Some("Rename it to item_count")
+            ),
+            (
+                "Parameter 'unitPrice' is not snake_case",
+                Some("Rename it to unit_price")
+            ),
+            (
+                "Enum variant 'dark_blue' is not PascalCase",
+                Some("Rename it to DarkBlue")
+            ),
+            (
+                "Function 'computeTotal' is not snake_case",
+                Some("Rename it to compute_total")
+            ),
+            (
+                "Variable '_unitPrice' is not snake_case",
+                Some("Rename it to _unit_price")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn w013_off_by_default() {
+    let codes = lint_codes(MIXED_NAMES);
+    assert!(!codes.iter().any(|c| c == "W013"));
+}